@@ -330,7 +330,7 @@ impl cosmic::Application for MirrorApp {
 
             let _ = tx_clone.send(Message::Connected).await;
 
-            let decoder = match VideoDecoder::new() {
+            let mut decoder = match VideoDecoder::new() {
                 Ok(d) => d,
                 Err(e) => {
                     let _ = tx_clone
@@ -4,6 +4,7 @@
 //! Handles method calls, signal subscription, and error recovery.
 
 use anyhow::{Context, Result};
+use cosmic_ext_connect_protocol::CapabilitySet;
 #[allow(dead_code)]
 use futures::stream::StreamExt;
 use std::collections::HashMap;
@@ -42,6 +43,8 @@ pub struct DeviceInfo {
     pub incoming_capabilities: Vec<String>,
     /// Supported outgoing plugin capabilities
     pub outgoing_capabilities: Vec<String>,
+    /// Recent packet activity: "active", "idle" or "stale"
+    pub activity: String,
 }
 
 /// Battery status from DBus
@@ -66,6 +69,100 @@ pub struct ScreenShareStats {
     pub avg_fps: u64,
 }
 
+/// Per-device byte counters from DBus
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, zbus::zvariant::Type)]
+pub struct TrafficStats {
+    /// Protocol packet bytes sent
+    pub control_sent: u64,
+    /// Protocol packet bytes received
+    pub control_received: u64,
+    /// File transfer bytes sent
+    pub payload_sent: u64,
+    /// File transfer bytes received
+    pub payload_received: u64,
+}
+
+/// How a device is connected, from DBus
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, zbus::zvariant::Type)]
+pub struct ConnectionInfo {
+    /// Protocol version the device announced
+    pub protocol_version: u32,
+    /// Transport of the open connection, empty when not connected
+    pub transport: String,
+    /// Measured round-trip time in milliseconds, -1 if not measured
+    pub rtt_ms: i64,
+    /// SHA-256 fingerprint of the device certificate, empty if unknown
+    pub certificate_fingerprint: String,
+}
+
+/// Everything the details view shows about one device
+///
+/// Assembled from several daemon calls by [`DbusClient::device_details`].
+/// Anything the daemon could not provide is `None`.
+#[derive(Debug, Clone)]
+pub struct DeviceDetails {
+    /// Basic device information
+    pub info: DeviceInfo,
+    /// Protocol version the device announced
+    pub protocol_version: Option<u32>,
+    /// Transport of the open connection
+    pub transport: Option<String>,
+    /// Measured round-trip time
+    pub rtt: Option<std::time::Duration>,
+    /// Battery level and charging state
+    pub battery: Option<BatteryStatus>,
+    /// Capabilities negotiated with the device
+    pub capabilities: Option<CapabilitySet>,
+    /// SHA-256 fingerprint of the device certificate
+    pub certificate_fingerprint: Option<String>,
+    /// Bytes exchanged during the current connection
+    pub traffic: TrafficStats,
+}
+
+impl DeviceDetails {
+    /// Combine the answers of the individual daemon calls
+    pub fn assemble(
+        info: DeviceInfo,
+        connection: Option<ConnectionInfo>,
+        capabilities: Option<CapabilitySet>,
+        battery: Option<BatteryStatus>,
+        traffic: Option<TrafficStats>,
+    ) -> Self {
+        let connection = connection.unwrap_or_default();
+        Self {
+            info,
+            protocol_version: Some(connection.protocol_version).filter(|&v| v > 0),
+            transport: Some(connection.transport).filter(|t| !t.is_empty()),
+            rtt: u64::try_from(connection.rtt_ms)
+                .ok()
+                .map(std::time::Duration::from_millis),
+            battery,
+            capabilities,
+            certificate_fingerprint: Some(connection.certificate_fingerprint)
+                .filter(|f| !f.is_empty()),
+            traffic: traffic.unwrap_or_default(),
+        }
+    }
+
+    /// Connection state: "connected", "paired", "reachable", or "unknown"
+    pub fn state(&self) -> &'static str {
+        if self.info.is_connected {
+            "connected"
+        } else if self.info.is_paired {
+            "paired"
+        } else if self.info.is_reachable {
+            "reachable"
+        } else {
+            "unknown"
+        }
+    }
+
+    /// Last seen timestamp (UNIX timestamp)
+    pub fn last_seen(&self) -> i64 {
+        self.info.last_seen
+    }
+}
+
 /// Notification preference for a device
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -196,6 +293,17 @@ pub struct RunCommand {
     pub command: String,
 }
 
+/// Error recorded for a device by the daemon
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DeviceErrorEntry {
+    /// When it happened (Unix milliseconds)
+    pub timestamp: i64,
+    /// Where it came from: connection, transfer, pairing or plugin
+    pub category: String,
+    /// What went wrong
+    pub message: String,
+}
+
 /// Player state
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PlayerState {
@@ -278,8 +386,12 @@ pub enum DaemonEvent {
     DeviceRemoved { device_id: String },
     /// Device state changed
     DeviceStateChanged { device_id: String, state: String },
+    /// Device capabilities changed
+    DeviceCapabilitiesChanged { device_id: String },
     /// Pairing request received
     PairingRequest { device_id: String },
+    /// PIN to show for our pairing request
+    PairingPin { device_id: String, pin: String },
     /// Pairing status changed
     PairingStatusChanged {
         #[allow(dead_code)]
@@ -327,6 +439,13 @@ pub enum DaemonEvent {
         success: bool,
         error: String,
     },
+    /// A device wants to send a file and awaits an accept or decline
+    IncomingTransferPrompt {
+        prompt_id: String,
+        device_id: String,
+        filename: String,
+        size: u64,
+    },
     /// Screen share requested by remote device (they want to share THEIR screen with us)
     ScreenShareRequested { device_id: String },
     /// Screen share outgoing request (remote wants US to share our screen with them)
@@ -415,9 +534,27 @@ trait CConnect {
     /// Get information about a specific device
     async fn get_device(&self, device_id: &str) -> zbus::fdo::Result<DeviceInfo>;
 
+    /// Get the capabilities negotiated with a device (sendable, receivable)
+    async fn get_device_capabilities(
+        &self,
+        device_id: &str,
+    ) -> zbus::fdo::Result<(Vec<String>, Vec<String>)>;
+
     /// Request pairing with a device
     async fn pair_device(&self, device_id: &str) -> zbus::fdo::Result<()>;
 
+    /// Request pairing with a device, confirmed by PIN; returns the PIN
+    async fn pair_device_with_pin(&self, device_id: &str) -> zbus::fdo::Result<String>;
+
+    /// Accept a pairing request with the PIN shown on the other device
+    async fn accept_pairing_with_pin(&self, device_id: &str, pin: &str) -> zbus::fdo::Result<()>;
+
+    /// Get this device's pairing invite URI
+    async fn get_pairing_uri(&self) -> zbus::fdo::Result<String>;
+
+    /// Pair with the device behind a scanned invite; returns its ID
+    async fn pair_with_invite(&self, uri: &str) -> zbus::fdo::Result<String>;
+
     /// Unpair a device
     async fn unpair_device(&self, device_id: &str) -> zbus::fdo::Result<()>;
 
@@ -427,6 +564,18 @@ trait CConnect {
     /// Connect to a device at a specific address
     async fn connect_to_address(&self, address: &str) -> zbus::fdo::Result<()>;
 
+    /// Reconnect to a device immediately, skipping its reconnect backoff
+    async fn reconnect_now(&self, device_id: &str) -> zbus::fdo::Result<()>;
+
+    /// Connect to devices in the background ahead of use
+    async fn prewarm(&self, device_ids: &[&str]) -> zbus::fdo::Result<()>;
+
+    /// Ask a connected device to re-send its capabilities
+    async fn refresh_capabilities(&self, device_id: &str) -> zbus::fdo::Result<()>;
+
+    /// Get the recent errors of a device (JSON array, newest first)
+    async fn get_error_history(&self, device_id: &str) -> zbus::fdo::Result<String>;
+
     /// Get device connection state
     async fn get_device_state(&self, device_id: &str) -> zbus::fdo::Result<String>;
 
@@ -454,12 +603,31 @@ trait CConnect {
     /// Request screenshot from device
     async fn take_screenshot(&self, device_id: &str) -> zbus::fdo::Result<()>;
 
+    /// Send a screenshot of this desktop to a device
+    async fn send_desktop_screenshot(&self, device_id: &str) -> zbus::fdo::Result<()>;
+
     /// Share a file with a device
     async fn share_file(&self, device_id: &str, path: &str) -> zbus::fdo::Result<()>;
 
+    /// Share several files with a device, returning the batch ID
+    async fn share_files(&self, device_id: &str, paths: &[&str]) -> zbus::fdo::Result<String>;
+
+    /// Share one file with several devices at once, returning the fan-out ID
+    async fn share_file_to_devices(
+        &self,
+        device_ids: &[&str],
+        path: &str,
+    ) -> zbus::fdo::Result<String>;
+
     /// Cancel an active file transfer
     async fn cancel_transfer(&self, transfer_id: &str) -> zbus::fdo::Result<()>;
 
+    /// Receive a file the daemon asked about
+    async fn accept_incoming_transfer(&self, prompt_id: &str) -> zbus::fdo::Result<()>;
+
+    /// Refuse a file the daemon asked about
+    async fn decline_incoming_transfer(&self, prompt_id: &str) -> zbus::fdo::Result<()>;
+
     /// Share text or URL with a device
     async fn share_text(&self, device_id: &str, text: &str) -> zbus::fdo::Result<()>;
 
@@ -480,6 +648,12 @@ trait CConnect {
     /// Get screen share statistics from a device
     async fn get_screen_share_stats(&self, device_id: &str) -> zbus::fdo::Result<ScreenShareStats>;
 
+    /// Get bytes exchanged with a device during the current connection
+    async fn get_traffic_stats(&self, device_id: &str) -> zbus::fdo::Result<TrafficStats>;
+
+    /// Get how a device is connected
+    async fn get_connection_info(&self, device_id: &str) -> zbus::fdo::Result<ConnectionInfo>;
+
     /// Request battery update from a device
     async fn request_battery_update(&self, device_id: &str) -> zbus::fdo::Result<()>;
 
@@ -589,10 +763,22 @@ trait CConnect {
     #[zbus(signal)]
     fn device_state_changed(device_id: &str, state: &str) -> zbus::fdo::Result<()>;
 
+    /// Signal: Device capabilities changed
+    #[zbus(signal)]
+    fn device_capabilities_changed(
+        device_id: &str,
+        incoming_capabilities: Vec<String>,
+        outgoing_capabilities: Vec<String>,
+    ) -> zbus::fdo::Result<()>;
+
     /// Signal: Pairing request received
     #[zbus(signal)]
     fn pairing_request(device_id: &str) -> zbus::fdo::Result<()>;
 
+    /// Signal: PIN generated for our pairing request
+    #[zbus(signal)]
+    fn pairing_pin(device_id: &str, pin: &str) -> zbus::fdo::Result<()>;
+
     /// Signal: Pairing status changed
     #[zbus(signal)]
     fn pairing_status_changed(device_id: &str, status: &str) -> zbus::fdo::Result<()>;
@@ -639,6 +825,14 @@ trait CConnect {
     /// Get run commands (returns JSON string map of id -> Command)
     async fn get_run_commands(&self, device_id: String) -> zbus::fdo::Result<String>;
 
+    /// Schedule a run command on a remote device, returning its first run
+    async fn schedule_command(
+        &self,
+        device_id: &str,
+        command_id: &str,
+        schedule: &str,
+    ) -> zbus::fdo::Result<String>;
+
     /// Start screen share
     async fn start_screen_share(&self, device_id: &str, port: u16) -> zbus::fdo::Result<()>;
 
@@ -717,6 +911,15 @@ trait CConnect {
         error: &str,
     ) -> zbus::fdo::Result<()>;
 
+    /// Signal: A device wants to send a file
+    #[zbus(signal)]
+    fn incoming_transfer_prompt(
+        prompt_id: &str,
+        device_id: &str,
+        filename: &str,
+        size: u64,
+    ) -> zbus::fdo::Result<()>;
+
     /// Signal: Screen share requested
     #[zbus(signal)]
     fn screen_share_requested(device_id: &str) -> zbus::fdo::Result<()>;
@@ -931,6 +1134,26 @@ impl DbusClient {
             }
         });
 
+        let event_tx = self.event_tx.clone();
+        let mut capabilities_changed_stream =
+            self.proxy.receive_device_capabilities_changed().await?;
+        tokio::spawn(async move {
+            while let Some(signal) = capabilities_changed_stream.next().await {
+                if let Ok(args) = signal.args() {
+                    let device_id = args.device_id().to_string();
+                    if event_tx
+                        .send(DaemonEvent::DeviceCapabilitiesChanged { device_id })
+                        .is_err()
+                    {
+                        tracing::warn!(
+                            "Event channel closed, stopping DeviceCapabilitiesChanged signal listener"
+                        );
+                        break;
+                    }
+                }
+            }
+        });
+
         let event_tx = self.event_tx.clone();
         let mut pairing_request_stream = self.proxy.receive_pairing_request().await?;
         tokio::spawn(async move {
@@ -945,6 +1168,24 @@ impl DbusClient {
             }
         });
 
+        let event_tx = self.event_tx.clone();
+        let mut pairing_pin_stream = self.proxy.receive_pairing_pin().await?;
+        tokio::spawn(async move {
+            while let Some(signal) = pairing_pin_stream.next().await {
+                if let Ok(args) = signal.args() {
+                    let device_id = args.device_id().to_string();
+                    let pin = args.pin().to_string();
+                    if event_tx
+                        .send(DaemonEvent::PairingPin { device_id, pin })
+                        .is_err()
+                    {
+                        tracing::warn!("Event channel closed, stopping PairingPin signal listener");
+                        break;
+                    }
+                }
+            }
+        });
+
         let event_tx = self.event_tx.clone();
         let mut pairing_status_changed_stream = self.proxy.receive_pairing_status_changed().await?;
         tokio::spawn(async move {
@@ -1040,6 +1281,29 @@ impl DbusClient {
             }
         });
 
+        let event_tx = self.event_tx.clone();
+        let mut transfer_prompt_stream = self.proxy.receive_incoming_transfer_prompt().await?;
+        tokio::spawn(async move {
+            while let Some(signal) = transfer_prompt_stream.next().await {
+                if let Ok(args) = signal.args() {
+                    if event_tx
+                        .send(DaemonEvent::IncomingTransferPrompt {
+                            prompt_id: args.prompt_id().to_string(),
+                            device_id: args.device_id().to_string(),
+                            filename: args.filename().to_string(),
+                            size: *args.size(),
+                        })
+                        .is_err()
+                    {
+                        tracing::warn!(
+                            "Event channel closed, stopping IncomingTransferPrompt signal listener"
+                        );
+                        break;
+                    }
+                }
+            }
+        });
+
         let event_tx = self.event_tx.clone();
         let mut screen_share_stream = self.proxy.receive_screen_share_requested().await?;
         tokio::spawn(async move {
@@ -1300,6 +1564,42 @@ impl DbusClient {
             .context("Failed to get device info")
     }
 
+    /// Get the capabilities the daemon negotiated with a device
+    ///
+    /// Only actions in this set actually work; the device's own advertisement
+    /// may include plugins disabled on this desktop.
+    pub async fn device_capabilities(&self, device_id: &str) -> Result<CapabilitySet> {
+        debug!("Getting negotiated capabilities for {}", device_id);
+        let (sendable, receivable) = self
+            .proxy
+            .get_device_capabilities(device_id)
+            .await
+            .context("Failed to get device capabilities")?;
+        Ok(CapabilitySet::from_parts(sendable, receivable))
+    }
+
+    /// Gather everything the details view shows about a device
+    ///
+    /// Only the basic device information is required; battery, capabilities,
+    /// connection info and traffic counters are left empty if the daemon
+    /// cannot provide them (for example a device without a battery).
+    #[allow(dead_code)]
+    pub async fn device_details(&self, device_id: &str) -> Result<DeviceDetails> {
+        debug!("Getting device details for {}", device_id);
+        let info = self.get_device(device_id).await?;
+        let connection = self.proxy.get_connection_info(device_id).await.ok();
+        let capabilities = self.device_capabilities(device_id).await.ok();
+        let battery = self.proxy.get_battery_status(device_id).await.ok();
+        let traffic = self.proxy.get_traffic_stats(device_id).await.ok();
+        Ok(DeviceDetails::assemble(
+            info,
+            connection,
+            capabilities,
+            battery,
+            traffic,
+        ))
+    }
+
     /// Request pairing with a device
     pub async fn pair_device(&self, device_id: &str) -> Result<()> {
         info!("Requesting pairing with device {}", device_id);
@@ -1309,6 +1609,23 @@ impl DbusClient {
             .context("Failed to pair device")
     }
 
+    /// Get this device's pairing invite URI, to show as a QR code
+    pub async fn get_pairing_uri(&self) -> Result<String> {
+        self.proxy
+            .get_pairing_uri()
+            .await
+            .context("Failed to get pairing invite")
+    }
+
+    /// Pair with the device behind a scanned invite, returning its ID
+    pub async fn pair_with_invite(&self, uri: &str) -> Result<String> {
+        info!("Pairing with invite");
+        self.proxy
+            .pair_with_invite(uri)
+            .await
+            .context("Failed to pair with invite")
+    }
+
     /// Unpair a device
     pub async fn unpair_device(&self, device_id: &str) -> Result<()> {
         info!("Unpairing device {}", device_id);
@@ -1338,6 +1655,55 @@ impl DbusClient {
             .context("Failed to connect to address")
     }
 
+    /// Reconnect to a device immediately, skipping its reconnect backoff
+    #[allow(dead_code)]
+    pub async fn reconnect_now(&self, device_id: &str) -> Result<()> {
+        info!("Reconnecting to device {} now", device_id);
+        self.proxy
+            .reconnect_now(device_id)
+            .await
+            .context("Failed to reconnect to device")
+    }
+
+    /// Hint the daemon to connect to devices that are likely to be used soon
+    pub async fn prewarm(&self, device_ids: &[&str]) -> Result<()> {
+        debug!("Prewarming connections to {} devices", device_ids.len());
+        self.proxy
+            .prewarm(device_ids)
+            .await
+            .context("Failed to prewarm connections")
+    }
+
+    /// Ask a connected device to re-send its capabilities
+    ///
+    /// The new set arrives as a `DeviceCapabilitiesChanged` event if it
+    /// differs from the one the daemon has stored.
+    #[allow(dead_code)]
+    pub async fn refresh_capabilities(&self, device_id: &str) -> Result<()> {
+        info!("Refreshing capabilities of device {}", device_id);
+        self.proxy
+            .refresh_capabilities(device_id)
+            .await
+            .context("Failed to refresh device capabilities")
+    }
+
+    /// Get the recent errors of a device, newest first
+    #[allow(dead_code)]
+    pub async fn error_history(&self, device_id: &str) -> Result<Vec<DeviceErrorEntry>> {
+        let json = self
+            .proxy
+            .get_error_history(device_id)
+            .await
+            .context("Failed to get error history")?;
+        serde_json::from_str(&json).context("Failed to parse error history")
+    }
+
+    /// Newest error of a device, for a "last error" line
+    #[allow(dead_code)]
+    pub async fn last_error(&self, device_id: &str) -> Result<Option<DeviceErrorEntry>> {
+        Ok(self.error_history(device_id).await?.into_iter().next())
+    }
+
     /// Get device connection state
     #[allow(dead_code)]
     pub async fn get_device_state(&self, device_id: &str) -> Result<String> {
@@ -1420,6 +1786,15 @@ impl DbusClient {
             .context("Failed to take screenshot")
     }
 
+    /// Send a screenshot of this desktop to a device
+    pub async fn send_desktop_screenshot(&self, device_id: &str) -> Result<()> {
+        info!("Sending desktop screenshot to device {}", device_id);
+        self.proxy
+            .send_desktop_screenshot(device_id)
+            .await
+            .context("Failed to send desktop screenshot")
+    }
+
     /// Share a file with a device
     pub async fn share_file(&self, device_id: &str, path: &str) -> Result<()> {
         info!("Sharing file {} with device {}", path, device_id);
@@ -1429,6 +1804,29 @@ impl DbusClient {
             .context("Failed to share file")
     }
 
+    /// Share several files with a device as one batch
+    pub async fn share_files(&self, device_id: &str, paths: &[&str]) -> Result<String> {
+        info!("Sharing {} files with device {}", paths.len(), device_id);
+        self.proxy
+            .share_files(device_id, paths)
+            .await
+            .context("Failed to share files")
+    }
+
+    /// Share one file with several devices at once
+    ///
+    /// The daemon reads the file once and streams it to every device; each
+    /// device reports its own transfer progress. Returns the fan-out ID of the
+    /// `FanOutComplete` signal summarizing the result.
+    #[allow(dead_code)]
+    pub async fn share_file_multi(&self, path: &str, device_ids: &[&str]) -> Result<String> {
+        info!("Sharing {} with {} devices", path, device_ids.len());
+        self.proxy
+            .share_file_to_devices(device_ids, path)
+            .await
+            .context("Failed to share file with devices")
+    }
+
     /// Cancel an active file transfer
     pub async fn cancel_transfer(&self, transfer_id: &str) -> Result<()> {
         info!("Cancelling transfer {}", transfer_id);
@@ -1438,6 +1836,24 @@ impl DbusClient {
             .context("Failed to cancel transfer")
     }
 
+    /// Receive a file the daemon asked about
+    pub async fn accept_incoming_transfer(&self, prompt_id: &str) -> Result<()> {
+        info!("Accepting incoming transfer {}", prompt_id);
+        self.proxy
+            .accept_incoming_transfer(prompt_id)
+            .await
+            .context("Failed to accept incoming transfer")
+    }
+
+    /// Refuse a file the daemon asked about
+    pub async fn decline_incoming_transfer(&self, prompt_id: &str) -> Result<()> {
+        info!("Declining incoming transfer {}", prompt_id);
+        self.proxy
+            .decline_incoming_transfer(prompt_id)
+            .await
+            .context("Failed to decline incoming transfer")
+    }
+
     /// Share text with a device
     pub async fn share_text(&self, device_id: &str, text: &str) -> Result<()> {
         info!("Sharing text with device {}: {}", device_id, text);
@@ -1909,6 +2325,26 @@ impl DbusClient {
         serde_json::from_str(&json).context("Failed to parse run commands JSON")
     }
 
+    /// Schedule a run command on a remote device
+    ///
+    /// `schedule` is `once YYYY-MM-DD HH:MM`, `daily HH:MM` or `every <n>m|h|d`.
+    /// Returns the first run time.
+    pub async fn schedule_command(
+        &self,
+        device_id: &str,
+        command_id: &str,
+        schedule: &str,
+    ) -> Result<String> {
+        info!(
+            "Scheduling command {} on device {}: {}",
+            command_id, device_id, schedule
+        );
+        self.proxy
+            .schedule_command(device_id, command_id, schedule)
+            .await
+            .context("Failed to schedule command")
+    }
+
     /// Open a URL on a connected Android device (App Continuity)
     ///
     /// # Arguments
@@ -2141,3 +2577,95 @@ impl ReconnectingClient {
         self.event_rx.try_recv()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn phone() -> DeviceInfo {
+        DeviceInfo {
+            id: "phone1".to_string(),
+            name: "Pixel".to_string(),
+            device_type: "phone".to_string(),
+            is_paired: true,
+            is_reachable: true,
+            is_connected: true,
+            has_pairing_request: false,
+            last_seen: 1_700_000_000,
+            incoming_capabilities: vec!["cconnect.ping".to_string()],
+            outgoing_capabilities: vec!["cconnect.ping".to_string()],
+            activity: "active".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_details_reflect_daemon_state() {
+        let details = DeviceDetails::assemble(
+            phone(),
+            Some(ConnectionInfo {
+                protocol_version: 8,
+                transport: "tcp".to_string(),
+                rtt_ms: 12,
+                certificate_fingerprint: "ab:cd".to_string(),
+            }),
+            Some(CapabilitySet::from_parts(
+                vec!["cconnect.ping".to_string()],
+                vec!["cconnect.battery".to_string()],
+            )),
+            Some(BatteryStatus {
+                level: 80,
+                is_charging: true,
+            }),
+            Some(TrafficStats {
+                control_sent: 10,
+                control_received: 20,
+                payload_sent: 30,
+                payload_received: 40,
+            }),
+        );
+
+        assert_eq!(details.state(), "connected");
+        assert_eq!(details.last_seen(), 1_700_000_000);
+        assert_eq!(details.protocol_version, Some(8));
+        assert_eq!(details.transport.as_deref(), Some("tcp"));
+        assert_eq!(details.rtt, Some(Duration::from_millis(12)));
+        assert_eq!(details.certificate_fingerprint.as_deref(), Some("ab:cd"));
+        assert_eq!(details.battery.as_ref().map(|b| b.level), Some(80));
+        assert!(details
+            .capabilities
+            .as_ref()
+            .is_some_and(|c| c.can_receive("cconnect.battery")));
+        assert_eq!(details.traffic.payload_received, 40);
+    }
+
+    #[test]
+    fn test_details_tolerate_missing_fields() {
+        let mut info = phone();
+        info.is_connected = false;
+        let details = DeviceDetails::assemble(
+            info,
+            Some(ConnectionInfo {
+                protocol_version: 8,
+                transport: String::new(),
+                rtt_ms: -1,
+                certificate_fingerprint: String::new(),
+            }),
+            None,
+            None,
+            None,
+        );
+
+        assert_eq!(details.state(), "paired");
+        assert_eq!(details.protocol_version, Some(8));
+        assert_eq!(details.transport, None);
+        assert_eq!(details.rtt, None);
+        assert_eq!(details.certificate_fingerprint, None);
+        assert!(details.battery.is_none());
+        assert!(details.capabilities.is_none());
+        assert_eq!(details.traffic.payload_sent, 0);
+
+        let bare = DeviceDetails::assemble(phone(), None, None, None, None);
+        assert_eq!(bare.protocol_version, None);
+    }
+}
@@ -10,8 +10,8 @@ use std::collections::HashMap;
 use messages::{Message, NotificationType, OperationType};
 use state::{
     ActiveScreenShare, AppNotification, CameraStats, ConversationSummary, DeviceState, FocusTarget,
-    HistoryEvent, ReceivedFile, SmsMessageDisplay, SystemInfo, TransferState, ViewMode,
-    MAX_DISPLAYED_HISTORY_ITEMS, MAX_RECEIVED_FILES_HISTORY,
+    HistoryEvent, ReceivedFile, SmsMessageDisplay, SystemInfo, TransferPromptState, TransferState,
+    ViewMode, MAX_DISPLAYED_HISTORY_ITEMS, MAX_RECEIVED_FILES_HISTORY,
 };
 
 use cosmic::{
@@ -28,7 +28,8 @@ use cosmic::{
 };
 
 use cosmic_ext_connect_protocol::{
-    ConnectionState, Device, DeviceInfo as ProtocolDeviceInfo, DeviceType, PairingStatus,
+    CapabilitySet, ConnectionState, Device, DeviceInfo as ProtocolDeviceInfo, DeviceType,
+    PairingStatus,
 };
 
 use dbus_client::DbusClient;
@@ -382,6 +383,7 @@ struct CConnectApplet {
     mpris_album_art: HashMap<String, cosmic::iced::widget::image::Handle>,
     // File transfers
     active_transfers: HashMap<String, TransferState>,
+    transfer_prompts: HashMap<String, TransferPromptState>,
     received_files_history: Vec<ReceivedFile>,
     // Renaming state
     renaming_device: Option<String>,
@@ -451,6 +453,10 @@ struct CConnectApplet {
     // App Continuity (Open plugin) state
     open_url_dialog_device: Option<String>, // device_id showing open URL dialog
     open_url_input: String,                 // URL input field
+    // Pairing invite state
+    pairing_invite_open: bool, // Whether the pairing invite dialog is shown
+    pairing_invite_uri: Option<String>, // Our invite, once fetched
+    pairing_invite_input: String, // Scanned invite input field
     // SMS dialog state
     sms_dialog_device: Option<String>, // device_id showing SMS dialog
     sms_phone_number_input: String,    // Phone number input field
@@ -537,6 +543,30 @@ async fn fetch_battery_statuses(
     statuses
 }
 
+/// Fetches negotiated capabilities for a list of device IDs
+async fn fetch_device_capabilities(device_ids: Vec<String>) -> HashMap<String, CapabilitySet> {
+    let mut capabilities = HashMap::new();
+    let Ok((client, _)) = DbusClient::connect().await else {
+        return capabilities;
+    };
+    for device_id in device_ids {
+        match client.device_capabilities(&device_id).await {
+            Ok(set) => {
+                capabilities.insert(device_id, set);
+            }
+            Err(e) => tracing::debug!("No capabilities for {}: {}", device_id, e),
+        }
+    }
+    capabilities
+}
+
+/// Creates a task that fetches negotiated capabilities for the given devices
+fn fetch_device_capabilities_task(device_ids: Vec<String>) -> Task<Message> {
+    Task::perform(fetch_device_capabilities(device_ids), |capabilities| {
+        cosmic::Action::App(Message::DeviceCapabilitiesUpdated(capabilities))
+    })
+}
+
 /// Fetches list of available MPRIS media players
 async fn fetch_mpris_players() -> Vec<String> {
     let Ok((client, _)) = DbusClient::connect().await else {
@@ -556,6 +586,17 @@ async fn fetch_mpris_players() -> Vec<String> {
     }
 }
 
+/// Ask the daemon to connect to paired devices before the user acts
+async fn prewarm_devices(device_ids: Vec<String>) {
+    let Ok((client, _)) = DbusClient::connect().await else {
+        return;
+    };
+    let ids: Vec<&str> = device_ids.iter().map(String::as_str).collect();
+    if let Err(e) = client.prewarm(&ids).await {
+        tracing::debug!("Failed to prewarm connections: {}", e);
+    }
+}
+
 /// Opens a file picker dialog and returns device_id with selected file paths
 async fn open_file_picker(device_id: String, multiple: bool) -> Option<(String, Vec<String>)> {
     use ashpd::desktop::file_chooser::OpenFileRequest;
@@ -700,6 +741,8 @@ fn convert_device_info(info: &dbus_client::DeviceInfo) -> DeviceState {
 
     let mut protocol_info = ProtocolDeviceInfo::new(&info.name, device_type, 1814);
     protocol_info.device_id = info.id.clone();
+    protocol_info.incoming_capabilities = info.incoming_capabilities.clone();
+    protocol_info.outgoing_capabilities = info.outgoing_capabilities.clone();
 
     let device = Device {
         info: protocol_info,
@@ -716,12 +759,15 @@ fn convert_device_info(info: &dbus_client::DeviceInfo) -> DeviceState {
         port: None,
         certificate_fingerprint: None,
         certificate_data: None,
+        last_activity: None,
     };
 
     DeviceState {
         device,
         battery_level: None,
         is_charging: false,
+        is_active: info.activity == "active",
+        capabilities: None,
     }
 }
 
@@ -767,6 +813,7 @@ impl cosmic::Application for CConnectApplet {
             mpris_states: std::collections::HashMap::new(),
             mpris_album_art: HashMap::new(),
             active_transfers: std::collections::HashMap::new(),
+            transfer_prompts: HashMap::new(),
             received_files_history: Vec::new(),
             renaming_device: None,
             nickname_input: String::new(),
@@ -811,6 +858,9 @@ impl cosmic::Application for CConnectApplet {
             settings_window: None,
             open_url_dialog_device: None,
             open_url_input: String::new(),
+            pairing_invite_open: false,
+            pairing_invite_uri: None,
+            pairing_invite_input: String::new(),
             sms_dialog_device: None,
             sms_phone_number_input: String::new(),
             sms_message_input: String::new(),
@@ -846,12 +896,24 @@ impl cosmic::Application for CConnectApplet {
             }
             Message::PopupOpened => {
                 tracing::info!("Popup opened, fetching devices and MPRIS players");
-                Task::batch(vec![
+                let cold_devices: Vec<String> = self
+                    .devices
+                    .iter()
+                    .filter(|d| d.device.is_paired() && !d.device.is_connected())
+                    .map(|d| d.device.info.device_id.clone())
+                    .collect();
+                let mut tasks = vec![
                     fetch_devices_task(),
                     Task::perform(fetch_mpris_players(), |players| {
                         cosmic::Action::App(Message::MprisPlayersUpdated(players))
                     }),
-                ])
+                ];
+                if !cold_devices.is_empty() {
+                    tasks.push(Task::perform(prewarm_devices(cold_devices), |_| {
+                        cosmic::Action::None
+                    }));
+                }
+                Task::batch(tasks)
             }
             Message::SetViewMode(mode) => {
                 self.view_mode = mode;
@@ -867,7 +929,17 @@ impl cosmic::Application for CConnectApplet {
                 tracing::info!("Device list updated: {} devices", devices.len());
                 self.scanning = false;
 
+                // Keep known capabilities until the refetch below replaces them
+                let mut known_capabilities: HashMap<String, CapabilitySet> = self
+                    .devices
+                    .drain(..)
+                    .filter_map(|d| Some((d.device.info.device_id, d.capabilities?)))
+                    .collect();
                 self.devices = devices.values().map(convert_device_info).collect();
+                for device_state in &mut self.devices {
+                    device_state.capabilities =
+                        known_capabilities.remove(&device_state.device.info.device_id);
+                }
 
                 let connected_ids: Vec<String> = self
                     .devices
@@ -885,9 +957,12 @@ impl cosmic::Application for CConnectApplet {
                     connected_ids.len()
                 );
                 self.loading_battery = true;
-                Task::perform(fetch_battery_statuses(connected_ids), |statuses| {
-                    cosmic::Action::App(Message::BatteryStatusesUpdated(statuses))
-                })
+                Task::batch(vec![
+                    fetch_device_capabilities_task(connected_ids.clone()),
+                    Task::perform(fetch_battery_statuses(connected_ids), |statuses| {
+                        cosmic::Action::App(Message::BatteryStatusesUpdated(statuses))
+                    }),
+                ])
             }
             Message::BatteryStatusesUpdated(statuses) => {
                 self.loading_battery = false;
@@ -902,6 +977,20 @@ impl cosmic::Application for CConnectApplet {
 
                 Task::none()
             }
+            Message::DeviceCapabilitiesUpdated(capabilities) => {
+                for device_state in &mut self.devices {
+                    if let Some(set) = capabilities.get(&device_state.device.info.device_id) {
+                        device_state.capabilities = Some(set.clone());
+                    }
+                }
+                Task::none()
+            }
+            Message::DeviceCapabilitiesChanged(device_id) => {
+                tracing::debug!("Capabilities changed for {}, refreshing", device_id);
+                // The device list carries the advertised capabilities and
+                // refetches the negotiated ones
+                fetch_devices_task()
+            }
             Message::PairDevice(device_id) => {
                 let id = device_id.clone();
                 Task::batch(vec![
@@ -1105,13 +1194,13 @@ impl cosmic::Application for CConnectApplet {
             }
             Message::ShareUrl(device_id) => {
                 tracing::info!("Share URL to device: {}", device_id);
-                match get_clipboard_text() {
-                    Some(text)
-                        if text.starts_with("http://")
-                            || text.starts_with("https://")
-                            || text.starts_with("www.") =>
-                    {
+                let url = get_clipboard_text().map(|text| {
+                    cosmic_ext_connect_protocol::plugins::share::ShareUrl::parse(&text)
+                });
+                match url {
+                    Some(Ok(url)) => {
                         let id = device_id.clone();
+                        let text = url.into_string();
                         Task::batch(vec![
                             Task::done(cosmic::Action::App(Message::OperationStarted(
                                 device_id.clone(),
@@ -1124,8 +1213,8 @@ impl cosmic::Application for CConnectApplet {
                             ),
                         ])
                     }
-                    Some(_) => {
-                        tracing::warn!("Clipboard text is not a valid URL");
+                    Some(Err(e)) => {
+                        tracing::warn!("Clipboard text is not a valid URL: {}", e);
                         Task::none()
                     }
                     None => {
@@ -1156,6 +1245,17 @@ impl cosmic::Application for CConnectApplet {
             Message::OpenOnPhone(device_id, url) => {
                 tracing::info!("Opening URL on phone: {} -> {}", url, device_id);
 
+                // Reject malformed input before closing the dialog so it can be corrected
+                if let Err(e) = cosmic_ext_connect_protocol::plugins::share::OpenTarget::parse(&url)
+                {
+                    tracing::warn!("Invalid URL for open on phone: {}", e);
+                    return Task::done(cosmic::Action::App(Message::ShowNotification(
+                        e.to_string(),
+                        NotificationType::Error,
+                        None,
+                    )));
+                }
+
                 // Clear dialog
                 self.open_url_dialog_device = None;
                 self.open_url_input.clear();
@@ -1196,6 +1296,114 @@ impl cosmic::Application for CConnectApplet {
                 self.open_url_input.clear();
                 Task::none()
             }
+            Message::ShowPairingInvite => {
+                self.pairing_invite_open = true;
+                self.pairing_invite_uri = None;
+                // Pre-fill with a scanned invite from the clipboard
+                if let Some(text) = get_clipboard_text() {
+                    if text.trim().starts_with(
+                        cosmic_ext_connect_protocol::pairing::invite::PAIRING_URI_PREFIX,
+                    ) {
+                        self.pairing_invite_input = text.trim().to_string();
+                    }
+                }
+                Task::perform(
+                    async move {
+                        let (client, _) = DbusClient::connect()
+                            .await
+                            .map_err(|e| format!("Failed to connect to daemon: {}", e))?;
+                        client
+                            .get_pairing_uri()
+                            .await
+                            .map_err(|e| format!("Failed to get pairing invite: {}", e))
+                    },
+                    |result| {
+                        cosmic::Action::App(match result {
+                            Ok(uri) => Message::PairingInviteLoaded(uri),
+                            Err(err) => {
+                                tracing::error!("{}", err);
+                                Message::ShowNotification(err, NotificationType::Error, None)
+                            }
+                        })
+                    },
+                )
+            }
+            Message::PairingInviteLoaded(uri) => {
+                if self.pairing_invite_open {
+                    self.pairing_invite_uri = Some(uri);
+                }
+                Task::none()
+            }
+            Message::CopyPairingInvite => {
+                let Some(uri) = self.pairing_invite_uri.clone() else {
+                    return Task::none();
+                };
+                let copied = arboard::Clipboard::new().and_then(|mut c| c.set_text(uri));
+                let (message, kind) = match copied {
+                    Ok(()) => (
+                        "Pairing invite copied".to_string(),
+                        NotificationType::Success,
+                    ),
+                    Err(e) => (
+                        format!("Failed to copy pairing invite: {}", e),
+                        NotificationType::Error,
+                    ),
+                };
+                Task::done(cosmic::Action::App(Message::ShowNotification(
+                    message, kind, None,
+                )))
+            }
+            Message::PairingInviteInput(input) => {
+                self.pairing_invite_input = input;
+                Task::none()
+            }
+            Message::PairWithInvite(uri) => {
+                // Reject malformed input before closing the dialog so it can be corrected
+                if let Err(e) = cosmic_ext_connect_protocol::pairing::parse_pairing_uri(&uri) {
+                    tracing::warn!("Invalid pairing invite: {}", e);
+                    return Task::done(cosmic::Action::App(Message::ShowNotification(
+                        e.to_string(),
+                        NotificationType::Error,
+                        None,
+                    )));
+                }
+
+                self.pairing_invite_open = false;
+                self.pairing_invite_input.clear();
+
+                Task::perform(
+                    async move {
+                        let (client, _) = DbusClient::connect()
+                            .await
+                            .map_err(|e| format!("Failed to connect to daemon: {}", e))?;
+                        client
+                            .pair_with_invite(&uri)
+                            .await
+                            .map_err(|e| format!("Failed to pair with invite: {}", e))
+                    },
+                    |result| {
+                        cosmic::Action::App(match result {
+                            Ok(device_id) => {
+                                tracing::info!("Invited pairing request sent to {}", device_id);
+                                Message::ShowNotification(
+                                    "Pairing request sent".to_string(),
+                                    NotificationType::Success,
+                                    None,
+                                )
+                            }
+                            Err(err) => {
+                                tracing::error!("{}", err);
+                                Message::ShowNotification(err, NotificationType::Error, None)
+                            }
+                        })
+                    },
+                )
+            }
+            Message::CancelPairingInvite => {
+                self.pairing_invite_open = false;
+                self.pairing_invite_input.clear();
+                Task::none()
+            }
             Message::MuteCall(device_id) => {
                 tracing::info!("Muting call on device: {}", device_id);
                 Task::perform(
@@ -2600,6 +2808,9 @@ impl cosmic::Application for CConnectApplet {
             }
             // File Transfer events
             Message::TransferProgress(tid, device_id, filename, cur, tot, dir) => {
+                // A prompt answered from the desktop notification is done
+                self.transfer_prompts
+                    .retain(|_, p| p.device_id != device_id || p.filename != filename);
                 let now = std::time::Instant::now();
                 let entry = self.active_transfers.entry(tid.clone());
                 entry
@@ -2640,6 +2851,50 @@ impl cosmic::Application for CConnectApplet {
                 }
                 Task::none()
             }
+            Message::IncomingTransferPrompt(prompt_id, device_id, filename, size) => {
+                tracing::info!("{} wants to send '{}'", device_id, filename);
+                // Prompts the daemon has since timed out were declined there;
+                // answering them only fails quietly
+                self.transfer_prompts.insert(
+                    prompt_id,
+                    TransferPromptState {
+                        device_id,
+                        filename,
+                        size,
+                    },
+                );
+                Task::none()
+            }
+            Message::AcceptIncomingTransfer(prompt_id) => {
+                self.transfer_prompts.remove(&prompt_id);
+                if let Some(ref client) = self.dbus_client {
+                    let client = client.clone();
+                    let future = async move {
+                        if let Err(e) = client.accept_incoming_transfer(&prompt_id).await {
+                            tracing::warn!("Failed to accept incoming transfer: {}", e);
+                        }
+                    };
+                    return Task::perform(future, |_| {
+                        cosmic::Action::App(Message::Tick(std::time::Instant::now()))
+                    });
+                }
+                Task::none()
+            }
+            Message::DeclineIncomingTransfer(prompt_id) => {
+                self.transfer_prompts.remove(&prompt_id);
+                if let Some(ref client) = self.dbus_client {
+                    let client = client.clone();
+                    let future = async move {
+                        if let Err(e) = client.decline_incoming_transfer(&prompt_id).await {
+                            tracing::warn!("Failed to decline incoming transfer: {}", e);
+                        }
+                    };
+                    return Task::perform(future, |_| {
+                        cosmic::Action::App(Message::Tick(std::time::Instant::now()))
+                    });
+                }
+                Task::none()
+            }
 
             Message::ShowFileSyncSettings(device_id) => {
                 self.file_sync_settings_device = Some(device_id.clone());
@@ -2921,9 +3176,21 @@ impl cosmic::Application for CConnectApplet {
                                 success,
                                 error,
                             )),
+                            dbus_client::DaemonEvent::DeviceCapabilitiesChanged { device_id } => {
+                                Some(Message::DeviceCapabilitiesChanged(device_id))
+                            }
+                            dbus_client::DaemonEvent::IncomingTransferPrompt {
+                                prompt_id,
+                                device_id,
+                                filename,
+                                size,
+                            } => Some(Message::IncomingTransferPrompt(
+                                prompt_id, device_id, filename, size,
+                            )),
                             e @ dbus_client::DaemonEvent::DeviceAdded { .. }
                             | e @ dbus_client::DaemonEvent::DeviceRemoved { .. }
                             | e @ dbus_client::DaemonEvent::PairingRequest { .. }
+                            | e @ dbus_client::DaemonEvent::PairingPin { .. }
                             | e @ dbus_client::DaemonEvent::PairingStatusChanged { .. }
                             | e @ dbus_client::DaemonEvent::DeviceStateChanged { .. }
                             | e @ dbus_client::DaemonEvent::IncomingCall { .. }
@@ -3209,6 +3476,14 @@ impl CConnectApplet {
                     details: format!("Device {} wants to pair", device_id),
                 });
             }
+            dbus_client::DaemonEvent::PairingPin { device_id, pin } => {
+                self.history.push(HistoryEvent {
+                    timestamp,
+                    event_type: "Pairing PIN".to_string(),
+                    device_name: "Unknown".to_string(),
+                    details: format!("Enter {} on device {} to pair", pin, device_id),
+                });
+            }
             dbus_client::DaemonEvent::PairingStatusChanged {
                 device_id: _,
                 status,
@@ -3533,6 +3808,10 @@ impl CConnectApplet {
                 self.open_url_dialog_device = None;
                 self.open_url_input.clear();
                 return Task::none();
+            } else if self.pairing_invite_open {
+                self.pairing_invite_open = false;
+                self.pairing_invite_input.clear();
+                return Task::none();
             } else if self.sms_dialog_device.is_some() {
                 self.sms_dialog_device = None;
                 self.sms_phone_number_input.clear();
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 
 use cosmic::iced::{keyboard, window};
+use cosmic_ext_connect_protocol::CapabilitySet;
 
 use crate::{
     dbus_client,
@@ -60,6 +61,8 @@ pub enum Message {
     // Daemon responses
     DeviceListUpdated(HashMap<String, dbus_client::DeviceInfo>),
     BatteryStatusesUpdated(HashMap<String, dbus_client::BatteryStatus>),
+    DeviceCapabilitiesUpdated(HashMap<String, CapabilitySet>),
+    DeviceCapabilitiesChanged(String), // device_id
     // MPRIS control
     MprisPlayersUpdated(Vec<String>),
     MprisPlayerSelected(String),
@@ -215,6 +218,9 @@ pub enum Message {
         String,
     ), // id, device, file, cur, tot, dir
     TransferComplete(String, String, String, bool, String), // id, device, file, success, error
+    IncomingTransferPrompt(String, String, String, u64),    // prompt_id, device, file, size
+    AcceptIncomingTransfer(String),                         // prompt_id
+    DeclineIncomingTransfer(String),                        // prompt_id
     // File Sync
     LoadSyncFolders(String),
     SyncFoldersLoaded(String, Vec<dbus_client::SyncFolderInfo>),
@@ -235,6 +241,13 @@ pub enum Message {
     OpenUrlInput(String),        // url input text
     OpenOnPhone(String, String), // device_id, url
     CancelOpenUrlDialog,
+    // Pairing invites
+    ShowPairingInvite,
+    PairingInviteLoaded(String), // our invite URI
+    CopyPairingInvite,
+    PairingInviteInput(String), // scanned invite URI text
+    PairWithInvite(String),     // scanned invite URI
+    CancelPairingInvite,
     // Telephony and SMS
     MuteCall(String),      // device_id
     ShowSmsDialog(String), // device_id
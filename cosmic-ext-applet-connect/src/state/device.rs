@@ -1,4 +1,4 @@
-use cosmic_ext_connect_protocol::Device;
+use cosmic_ext_connect_protocol::{CapabilitySet, Device};
 
 /// Device state with battery information
 #[derive(Debug, Clone)]
@@ -6,6 +6,10 @@ pub struct DeviceState {
     pub device: Device,
     pub battery_level: Option<u8>,
     pub is_charging: bool,
+    /// The device exchanged packets recently
+    pub is_active: bool,
+    /// Capabilities negotiated with the device, once the daemon reported them
+    pub capabilities: Option<CapabilitySet>,
 }
 
 /// Application notification for the UI
@@ -10,7 +10,10 @@ pub use conversations::{ConversationSummary, SmsMessageDisplay};
 pub use device::{AppNotification, DeviceState, FocusTarget, HistoryEvent, ViewMode};
 pub use screen_share::ActiveScreenShare;
 pub use system::SystemInfo;
-pub use transfer::{ReceivedFile, TransferState, MAX_DISPLAYED_HISTORY_ITEMS, MAX_RECEIVED_FILES_HISTORY};
+pub use transfer::{
+    ReceivedFile, TransferPromptState, TransferState, MAX_DISPLAYED_HISTORY_ITEMS,
+    MAX_RECEIVED_FILES_HISTORY,
+};
 
 // Re-export NotificationType from messages module for device module
 pub use crate::messages::NotificationType;
@@ -12,6 +12,14 @@ pub struct TransferState {
     pub last_bytes: u64,
 }
 
+/// An incoming file waiting for the user to accept or decline
+#[derive(Debug, Clone)]
+pub struct TransferPromptState {
+    pub device_id: String,
+    pub filename: String,
+    pub size: u64,
+}
+
 /// A recently received file for history tracking
 #[derive(Debug, Clone)]
 pub struct ReceivedFile {
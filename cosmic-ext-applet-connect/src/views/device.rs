@@ -8,7 +8,9 @@ use cosmic::{
     Element,
 };
 
-use cosmic_ext_connect_protocol::{ConnectionState, Device, DeviceType, PairingStatus};
+use cosmic_ext_connect_protocol::{
+    CapabilitySet, ConnectionState, Device, DeviceType, PairingStatus,
+};
 
 use crate::{
     horizontal_space, messages::OperationType, space_xxs, space_xxs_f32,
@@ -181,6 +183,18 @@ impl CConnectApplet {
         .spacing(space_xxs())
         .align_y(cosmic::iced::Alignment::Center);
 
+        if device.is_connected() && device_state.is_active {
+            metadata_row = metadata_row.push(
+                text("•")
+                    .size(ICON_XS)
+                    .class(theme::Text::Color(theme_muted_color())),
+            );
+            metadata_row = metadata_row.push(
+                cosmic::widget::text::caption("Active now")
+                    .class(theme::Text::Color(theme_muted_color())),
+            );
+        }
+
         // Add battery if available
         if let Some(level) = device_state.battery_level {
             metadata_row = metadata_row.push(
@@ -245,7 +259,8 @@ impl CConnectApplet {
         );
 
         // Build actions
-        let actions_row = self.build_device_actions(device, device_id);
+        let actions_row =
+            self.build_device_actions(device, device_id, device_state.capabilities.as_ref());
 
         // Main device row layout
         let mut content = column![
@@ -385,9 +400,16 @@ impl CConnectApplet {
         &self,
         device: &'a Device,
         device_id: &str,
+        capabilities: Option<&CapabilitySet>,
     ) -> cosmic::iced::widget::Row<'a, Message, cosmic::Theme> {
         let mut actions = row![].spacing(space_xxs());
 
+        // Actions the daemon did not negotiate with the device are greyed out;
+        // until it reports them, whatever the device advertises is offered
+        let can_send = |packet_type: &str| capabilities.map_or(true, |c| c.can_send(packet_type));
+        let can_receive =
+            |packet_type: &str| capabilities.map_or(true, |c| c.can_receive(packet_type));
+
         // Quick actions for connected & paired devices
         if device.is_connected() && device.is_paired() {
             let is_pinging = self
@@ -402,26 +424,30 @@ impl CConnectApplet {
 
             if device.has_incoming_capability("cconnect.share") {
                 actions = actions
-                    .push(action_button_with_tooltip(
+                    .push(gated_action_button(
+                        can_send("cconnect.share"),
                         "document-send-symbolic",
                         "Send file",
                         Message::SendFile(device_id.to_string()),
                     ))
-                    .push(action_button_with_tooltip_loading(
+                    .push(gated_action_button_loading(
+                        can_send("cconnect.share"),
                         "insert-text-symbolic",
                         "Share clipboard text",
                         Message::ShareText(device_id.to_string()),
                         self.pending_operations
                             .contains(&(device_id.to_string(), OperationType::ShareText)),
                     ))
-                    .push(action_button_with_tooltip_loading(
+                    .push(gated_action_button_loading(
+                        can_send("cconnect.share"),
                         "send-to-symbolic",
                         "Share URL",
                         Message::ShareUrl(device_id.to_string()),
                         self.pending_operations
                             .contains(&(device_id.to_string(), OperationType::ShareUrl)),
                     ))
-                    .push(action_button_with_tooltip(
+                    .push(gated_action_button(
+                        can_send("cconnect.share"),
                         "smartphone-symbolic",
                         "Open on Phone (App Continuity)",
                         Message::ShowOpenUrlDialog(device_id.to_string()),
@@ -433,7 +459,8 @@ impl CConnectApplet {
                 let is_ringing = self
                     .pending_operations
                     .contains(&(device_id.to_string(), OperationType::FindPhone));
-                actions = actions.push(action_button_with_tooltip_loading(
+                actions = actions.push(gated_action_button_loading(
+                    can_send("cconnect.findmyphone.request"),
                     "find-location-symbolic",
                     "Ring device",
                     Message::FindPhone(device_id.to_string()),
@@ -443,7 +470,8 @@ impl CConnectApplet {
 
             // Lock device button
             if device.has_incoming_capability("cconnect.lock.request") {
-                actions = actions.push(action_button_with_tooltip(
+                actions = actions.push(gated_action_button(
+                    can_send("cconnect.lock.request"),
                     "system-lock-screen-symbolic",
                     "Lock device",
                     Message::LockDevice(device_id.to_string()),
@@ -452,7 +480,8 @@ impl CConnectApplet {
 
             // Power control button (shutdown)
             if device.has_incoming_capability("cconnect.power.request") {
-                actions = actions.push(action_button_with_tooltip(
+                actions = actions.push(gated_action_button(
+                    can_send("cconnect.power.request"),
                     "system-shutdown-symbolic",
                     "Shutdown device",
                     Message::PowerAction(device_id.to_string(), "shutdown".to_string()),
@@ -461,7 +490,8 @@ impl CConnectApplet {
 
             // Wake-on-LAN button (for offline devices)
             if device.has_incoming_capability("cconnect.wol.request") {
-                actions = actions.push(action_button_with_tooltip(
+                actions = actions.push(gated_action_button(
+                    can_send("cconnect.wol.request"),
                     "network-wired-symbolic",
                     "Wake device",
                     Message::WakeDevice(device_id.to_string()),
@@ -470,7 +500,8 @@ impl CConnectApplet {
 
             // System Volume button
             if device.has_incoming_capability("cconnect.systemvolume.request") {
-                actions = actions.push(action_button_with_tooltip(
+                actions = actions.push(gated_action_button(
+                    can_send("cconnect.systemvolume.request"),
                     "multimedia-volume-control-symbolic",
                     "Control volume",
                     Message::SetDeviceVolume(device_id.to_string(), 0.5),
@@ -479,7 +510,8 @@ impl CConnectApplet {
 
             // System Monitor button
             if device.has_incoming_capability("cconnect.systemmonitor.request") {
-                actions = actions.push(action_button_with_tooltip(
+                actions = actions.push(gated_action_button(
+                    can_send("cconnect.systemmonitor.request"),
                     "utilities-system-monitor-symbolic",
                     "Get system info",
                     Message::RequestSystemInfo(device_id.to_string()),
@@ -494,7 +526,8 @@ impl CConnectApplet {
                     DeviceType::Desktop | DeviceType::Laptop
                 )
             {
-                actions = actions.push(action_button_with_tooltip(
+                actions = actions.push(gated_action_button(
+                    can_send("cconnect.screenshot.request"),
                     "camera-photo-symbolic",
                     "Take screenshot",
                     Message::TakeScreenshot(device_id.to_string()),
@@ -506,7 +539,8 @@ impl CConnectApplet {
                 let is_muting = self
                     .pending_operations
                     .contains(&(device_id.to_string(), OperationType::MuteCall));
-                actions = actions.push(action_button_with_tooltip_loading(
+                actions = actions.push(gated_action_button_loading(
+                    can_send("cconnect.telephony"),
                     "audio-volume-muted-symbolic",
                     "Mute incoming call",
                     Message::MuteCall(device_id.to_string()),
@@ -516,7 +550,8 @@ impl CConnectApplet {
 
             // SMS button
             if device.has_incoming_capability("cconnect.sms.messages") {
-                actions = actions.push(action_button_with_tooltip(
+                actions = actions.push(gated_action_button(
+                    can_send("cconnect.sms.messages"),
                     "mail-message-new-symbolic",
                     "Send SMS",
                     Message::ShowSmsDialog(device_id.to_string()),
@@ -536,12 +571,13 @@ impl CConnectApplet {
                 } else {
                     "Start audio streaming"
                 };
-                actions = actions.push(
-                    button::icon(icon::from_name(audio_icon).size(ICON_S))
-                        .on_press(Message::ToggleAudioStream(device_id.to_string()))
-                        .padding(space_xxxs())
-                        .tooltip(audio_tooltip),
-                );
+                let toggle = button::icon(icon::from_name(audio_icon).size(ICON_S));
+                let toggle = if can_send("cconnect.audiostream") {
+                    toggle.on_press(Message::ToggleAudioStream(device_id.to_string()))
+                } else {
+                    toggle
+                };
+                actions = actions.push(toggle.padding(space_xxxs()).tooltip(audio_tooltip));
             }
 
             // Presenter mode toggle button
@@ -557,12 +593,13 @@ impl CConnectApplet {
                 } else {
                     "Start presenter mode"
                 };
-                actions = actions.push(
-                    button::icon(icon::from_name(presenter_icon).size(ICON_S))
-                        .on_press(Message::TogglePresenterMode(device_id.to_string()))
-                        .padding(space_xxxs())
-                        .tooltip(presenter_tooltip),
-                );
+                let toggle = button::icon(icon::from_name(presenter_icon).size(ICON_S));
+                let toggle = if can_send("cconnect.presenter") {
+                    toggle.on_press(Message::TogglePresenterMode(device_id.to_string()))
+                } else {
+                    toggle
+                };
+                actions = actions.push(toggle.padding(space_xxxs()).tooltip(presenter_tooltip));
             }
             // Battery refresh button
             let is_refreshing_battery = self
@@ -577,7 +614,8 @@ impl CConnectApplet {
 
             // Screen Mirroring button
             if device.has_outgoing_capability("cconnect.screenshare") {
-                actions = actions.push(action_button_with_tooltip(
+                actions = actions.push(gated_action_button(
+                    can_receive("cconnect.screenshare"),
                     "video-display-symbolic",
                     "Mirror Screen",
                     Message::LaunchScreenMirror(device_id.to_string()),
@@ -586,7 +624,8 @@ impl CConnectApplet {
 
             // Remote Desktop button
             if device.has_incoming_capability("cconnect.remotedesktop.request") {
-                actions = actions.push(action_button_with_tooltip(
+                actions = actions.push(gated_action_button(
+                    can_send("cconnect.remotedesktop.request"),
                     "preferences-desktop-remote-desktop-symbolic",
                     "Remote Desktop",
                     Message::ShowRemoteDesktopSettings(device_id.to_string()),
@@ -611,12 +650,13 @@ impl CConnectApplet {
                 } else {
                     Message::StartExtendedDisplay(device_id.to_string())
                 };
-                actions = actions.push(
-                    button::icon(icon::from_name(ext_icon).size(ICON_S))
-                        .on_press(ext_msg)
-                        .padding(space_xxxs())
-                        .tooltip(ext_tooltip),
-                );
+                let toggle = button::icon(icon::from_name(ext_icon).size(ICON_S));
+                let toggle = if can_send("cconnect.extendeddisplay") {
+                    toggle.on_press(ext_msg)
+                } else {
+                    toggle
+                };
+                actions = actions.push(toggle.padding(space_xxxs()).tooltip(ext_tooltip));
             }
 
             // Camera streaming toggle button
@@ -635,17 +675,19 @@ impl CConnectApplet {
                 } else {
                     "Start camera streaming"
                 };
-                actions = actions.push(
-                    button::icon(icon::from_name(camera_icon).size(ICON_S))
-                        .on_press(Message::ToggleCameraStreaming(device_id.to_string()))
-                        .padding(space_xxxs())
-                        .tooltip(camera_tooltip),
-                );
+                let toggle = button::icon(icon::from_name(camera_icon).size(ICON_S));
+                let toggle = if can_send("cconnect.camera") {
+                    toggle.on_press(Message::ToggleCameraStreaming(device_id.to_string()))
+                } else {
+                    toggle
+                };
+                actions = actions.push(toggle.padding(space_xxxs()).tooltip(camera_tooltip));
             }
 
             // Run Commands button
             if device.has_incoming_capability("cconnect.runcommand") {
-                actions = actions.push(action_button_with_tooltip(
+                actions = actions.push(gated_action_button(
+                    can_send("cconnect.runcommand"),
                     "utilities-terminal-symbolic",
                     "Run Commands",
                     Message::ShowRunCommandSettings(device_id.to_string()),
@@ -962,6 +1004,45 @@ pub(crate) fn action_button_with_tooltip_loading(
     }
 }
 
+/// Creates a small icon button that is greyed out when the device lacks the action
+pub(crate) fn gated_action_button(
+    supported: bool,
+    icon_name: &str,
+    tooltip_text: &'static str,
+    message: Message,
+) -> Element<'static, Message> {
+    if supported {
+        action_button_with_tooltip(icon_name, tooltip_text, message)
+    } else {
+        unsupported_action_button(icon_name)
+    }
+}
+
+/// Like [`gated_action_button`], with a loading state
+pub(crate) fn gated_action_button_loading(
+    supported: bool,
+    icon_name: &str,
+    tooltip_text: &'static str,
+    message: Message,
+    is_loading: bool,
+) -> Element<'static, Message> {
+    if supported {
+        action_button_with_tooltip_loading(icon_name, tooltip_text, message, is_loading)
+    } else {
+        unsupported_action_button(icon_name)
+    }
+}
+
+/// Creates a disabled icon button for an action the device does not support
+fn unsupported_action_button(icon_name: &str) -> Element<'static, Message> {
+    cosmic::widget::tooltip(
+        button::icon(icon::from_name(icon_name).size(ICON_S)).padding(space_xxs()),
+        "Not supported by this device",
+        cosmic::widget::tooltip::Position::Bottom,
+    )
+    .into()
+}
+
 /// Returns the icon name for a device type
 pub(crate) fn device_type_icon(device_type: DeviceType) -> &'static str {
     match device_type {
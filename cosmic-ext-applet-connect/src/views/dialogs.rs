@@ -66,6 +66,75 @@ impl CConnectApplet {
             .into()
     }
 
+    /// Pairing invite dialog: show ours, or pair with a scanned one
+    pub fn pairing_invite_view(&self) -> Element<'_, Message> {
+        use cosmic::iced::widget::row;
+
+        let own_invite: Element<'_, Message> = match &self.pairing_invite_uri {
+            Some(uri) => row![
+                text::caption(uri.as_str()).width(Length::Fill),
+                cosmic::widget::tooltip(
+                    button::icon(icon::from_name("edit-copy-symbolic").size(ICON_S))
+                        .on_press(Message::CopyPairingInvite)
+                        .padding(space_xxs()),
+                    "Copy invite",
+                    cosmic::widget::tooltip::Position::Bottom,
+                )
+            ]
+            .spacing(space_xxs())
+            .align_y(Alignment::Center)
+            .into(),
+            None => text::caption("Loading invite...").into(),
+        };
+
+        let content = column![
+            row![
+                text::title3("Pairing Invite").width(Length::Fill),
+                cosmic::widget::tooltip(
+                    button::icon(icon::from_name("window-close-symbolic").size(ICON_S))
+                        .on_press(Message::CancelPairingInvite)
+                        .padding(space_xxs()),
+                    "Close",
+                    cosmic::widget::tooltip::Position::Bottom,
+                )
+            ]
+            .align_y(Alignment::Center),
+            divider::horizontal::default(),
+            text::caption("Scan this invite on the other device to pair without a key"),
+            own_invite,
+            divider::horizontal::default(),
+            text::caption("Or pair with an invite scanned from the other device"),
+            text_input("cconnect://pair/...", &self.pairing_invite_input)
+                .on_input(Message::PairingInviteInput)
+                .on_submit({
+                    let uri = self.pairing_invite_input.clone();
+                    move |_| Message::PairWithInvite(uri.clone())
+                }),
+            row![
+                button::text("Cancel")
+                    .on_press(Message::CancelPairingInvite)
+                    .width(Length::Fill),
+                if !self.pairing_invite_input.is_empty() {
+                    button::text("Pair")
+                        .on_press(Message::PairWithInvite(self.pairing_invite_input.clone()))
+                        .class(cosmic::theme::Button::Suggested)
+                        .width(Length::Fill)
+                } else {
+                    button::text("Pair")
+                        .class(cosmic::theme::Button::Suggested)
+                        .width(Length::Fill)
+                },
+            ]
+            .spacing(space_xxs()),
+        ]
+        .spacing(space_xs());
+
+        container(content)
+            .class(cosmic::theme::Container::Card)
+            .padding(space_xs())
+            .into()
+    }
+
     /// SMS dialog view for sending SMS messages via device
     pub fn sms_dialog_view(&self, device_id: &str) -> Element<'_, Message> {
         use cosmic::iced::widget::row;
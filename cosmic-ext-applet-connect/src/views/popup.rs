@@ -359,6 +359,9 @@ impl CConnectApplet {
         if let Some(device_id) = &self.sms_dialog_device {
             return self.sms_dialog_view(device_id);
         }
+        if self.pairing_invite_open {
+            return self.pairing_invite_view();
+        }
 
         // Settings overrides
         if let Some(device_id) = &self.remotedesktop_settings_device {
@@ -439,6 +442,13 @@ impl CConnectApplet {
                     "Refresh devices (Ctrl+R)",
                     cosmic::widget::tooltip::Position::Bottom,
                 ),
+                cosmic::widget::tooltip(
+                    button::icon(icon::from_name("contact-new-symbolic").size(ICON_S))
+                        .on_press(Message::ShowPairingInvite)
+                        .padding(space_xxxs()),
+                    "Pairing invite",
+                    cosmic::widget::tooltip::Position::Bottom,
+                ),
                 cosmic::widget::tooltip(
                     button::icon(icon::from_name("help-about-symbolic").size(ICON_S))
                         .on_press(Message::ToggleKeyboardShortcutsHelp)
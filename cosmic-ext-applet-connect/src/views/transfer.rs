@@ -18,7 +18,42 @@ impl CConnectApplet {
     pub fn transfer_queue_view(&self) -> Element<'_, Message> {
         let mut transfers_list = column![].spacing(space_xxs());
 
-        if self.active_transfers.is_empty() {
+        for (prompt_id, prompt) in &self.transfer_prompts {
+            let device_name = self
+                .devices
+                .iter()
+                .find(|d| d.device.id() == prompt.device_id)
+                .map(|d| d.device.name().to_string())
+                .unwrap_or_else(|| prompt.device_id.clone());
+
+            let prompt_row = row![
+                icon::from_name(Self::file_type_icon(&prompt.filename)).size(ICON_M),
+                column![
+                    cosmic::widget::text::body(&prompt.filename),
+                    cosmic::widget::text::caption(format!(
+                        "From {} · {}",
+                        device_name,
+                        Self::format_file_size(prompt.size)
+                    )),
+                ]
+                .spacing(space_xxxs())
+                .width(Length::Fill),
+                button::standard("Decline")
+                    .on_press(Message::DeclineIncomingTransfer(prompt_id.clone())),
+                button::suggested("Accept")
+                    .on_press(Message::AcceptIncomingTransfer(prompt_id.clone())),
+            ]
+            .spacing(space_xxs())
+            .align_y(cosmic::iced::Alignment::Center);
+
+            transfers_list = transfers_list.push(
+                container(prompt_row)
+                    .padding(space_xxs())
+                    .class(cosmic::theme::Container::Card),
+            );
+        }
+
+        if self.active_transfers.is_empty() && self.transfer_prompts.is_empty() {
             transfers_list = transfers_list.push(
                 container(
                     column![
@@ -0,0 +1,247 @@
+//! Clipboard Images
+//!
+//! Prepares images copied to the desktop clipboard for sending to devices.
+//! The CConnect clipboard packet only carries text, so images are sent as a
+//! shared file.
+//!
+//! ## Size Policy
+//!
+//! [`ClipboardImageConfig`] sets the maximum dimensions and the format:
+//! - Images within the limits are sent as lossless PNG with their pixels
+//!   untouched
+//! - Larger images are scaled down to fit, keeping the aspect ratio, and
+//!   encoded as PNG or JPEG with the configured quality
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let prepared = prepare_image(rgba, &config.plugins.clipboard_images)?;
+//! tokio::fs::write(dir.join(prepared.filename()), &prepared.data).await?;
+//! ```
+
+use crate::config::{ClipboardImageConfig, ClipboardImageFormat};
+use anyhow::{Context, Result};
+use cosmic_ext_connect_protocol::ConnectionManager;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// An encoded clipboard image ready to send
+#[derive(Debug, Clone)]
+pub struct PreparedImage {
+    /// Encoded file contents
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// Encoding of `data`
+    pub format: ClipboardImageFormat,
+    /// Whether the image was scaled down
+    pub downscaled: bool,
+}
+
+impl PreparedImage {
+    /// File name to send the image under
+    pub fn filename(&self) -> String {
+        let extension = match self.format {
+            ClipboardImageFormat::Png => "png",
+            ClipboardImageFormat::Jpeg => "jpg",
+        };
+        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        format!("clipboard_{}.{}", timestamp, extension)
+    }
+}
+
+/// Apply the size policy to a clipboard image and encode it
+pub fn prepare_image(image: RgbaImage, config: &ClipboardImageConfig) -> Result<PreparedImage> {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return Err(anyhow::anyhow!("Clipboard image is empty"));
+    }
+
+    let max_width = config.max_width.max(1);
+    let max_height = config.max_height.max(1);
+    if width <= max_width && height <= max_height {
+        debug!("Clipboard image within limits: {}x{}", width, height);
+        return Ok(PreparedImage {
+            data: encode(
+                DynamicImage::ImageRgba8(image),
+                ClipboardImageFormat::Png,
+                0,
+            )?,
+            width,
+            height,
+            format: ClipboardImageFormat::Png,
+            downscaled: false,
+        });
+    }
+
+    // Fits within the bounds, keeping the aspect ratio
+    let resized = DynamicImage::ImageRgba8(image).resize(
+        max_width,
+        max_height,
+        image::imageops::FilterType::Lanczos3,
+    );
+    debug!(
+        "Downscaled clipboard image from {}x{} to {}x{}",
+        width,
+        height,
+        resized.width(),
+        resized.height()
+    );
+
+    Ok(PreparedImage {
+        width: resized.width(),
+        height: resized.height(),
+        data: encode(resized, config.format, config.jpeg_quality)?,
+        format: config.format,
+        downscaled: true,
+    })
+}
+
+fn encode(image: DynamicImage, format: ClipboardImageFormat, jpeg_quality: u8) -> Result<Vec<u8>> {
+    let mut buffer = Cursor::new(Vec::new());
+    match format {
+        ClipboardImageFormat::Png => image
+            .write_to(&mut buffer, ImageFormat::Png)
+            .context("Failed to encode clipboard image as PNG")?,
+        ClipboardImageFormat::Jpeg => {
+            // JPEG has no alpha channel
+            JpegEncoder::new_with_quality(&mut buffer, jpeg_quality.clamp(1, 100))
+                .encode_image(&DynamicImage::ImageRgb8(image.to_rgb8()))
+                .context("Failed to encode clipboard image as JPEG")?
+        }
+    }
+    Ok(buffer.into_inner())
+}
+
+/// Prepare a clipboard image and share it with each of `device_ids`
+pub async fn send_clipboard_image(
+    image: RgbaImage,
+    config: ClipboardImageConfig,
+    device_ids: Vec<String>,
+    connection_manager: Arc<RwLock<ConnectionManager>>,
+) -> Result<()> {
+    let prepared = tokio::task::spawn_blocking(move || prepare_image(image, &config))
+        .await
+        .context("Clipboard image task failed")??;
+
+    let dir = std::env::temp_dir().join("cosmic-ext-connect-clipboard");
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .context("Failed to create clipboard image directory")?;
+    let path = dir.join(prepared.filename());
+    tokio::fs::write(&path, &prepared.data)
+        .await
+        .context("Failed to write clipboard image")?;
+
+    info!(
+        "Sending clipboard image {}x{} ({} bytes{}) to {} device(s)",
+        prepared.width,
+        prepared.height,
+        prepared.data.len(),
+        if prepared.downscaled {
+            ", downscaled"
+        } else {
+            ""
+        },
+        device_ids.len()
+    );
+    for device_id in &device_ids {
+        if let Err(e) = share_file(&connection_manager, device_id, &path).await {
+            warn!("Failed to send clipboard image to {}: {}", device_id, e);
+        }
+    }
+
+    let _ = tokio::fs::remove_file(&path).await;
+    Ok(())
+}
+
+/// Offer a file to a device through the share plugin
+async fn share_file(
+    connection_manager: &Arc<RwLock<ConnectionManager>>,
+    device_id: &str,
+    path: &Path,
+) -> Result<()> {
+    use cosmic_ext_connect_protocol::plugins::share::SharePlugin;
+    use cosmic_ext_connect_protocol::{FileTransferInfo, TlsPayloadServer};
+
+    let file_info = FileTransferInfo::from_path(path).await?;
+    let (tls_config, traffic_counter) = {
+        let conn_mgr = connection_manager.read().await;
+        (
+            conn_mgr.tls_config(),
+            conn_mgr.traffic_counter(device_id).await,
+        )
+    };
+    let server = TlsPayloadServer::new(tls_config)
+        .await?
+        .with_traffic_counter(traffic_counter);
+
+    let packet = SharePlugin::new().create_file_packet(file_info.into(), server.port());
+    connection_manager
+        .read()
+        .await
+        .send_packet(device_id, &packet)
+        .await?;
+
+    server.send_file(path).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(format: ClipboardImageFormat) -> ClipboardImageConfig {
+        ClipboardImageConfig {
+            enabled: true,
+            max_width: 100,
+            max_height: 50,
+            format,
+            jpeg_quality: 80,
+        }
+    }
+
+    fn gradient(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            image::Rgba([(x % 256) as u8, (y % 256) as u8, 128, 255])
+        })
+    }
+
+    #[test]
+    fn test_oversized_image_is_downscaled() {
+        let prepared =
+            prepare_image(gradient(400, 100), &config(ClipboardImageFormat::Jpeg)).unwrap();
+
+        assert!(prepared.downscaled);
+        assert!(prepared.width <= 100 && prepared.height <= 50);
+        // Aspect ratio kept: 4:1
+        assert_eq!((prepared.width, prepared.height), (100, 25));
+        assert_eq!(prepared.format, ClipboardImageFormat::Jpeg);
+        assert!(prepared.filename().ends_with(".jpg"));
+
+        let decoded =
+            image::load_from_memory_with_format(&prepared.data, ImageFormat::Jpeg).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (100, 25));
+    }
+
+    #[test]
+    fn test_small_image_is_sent_untouched() {
+        let original = gradient(80, 40);
+        let prepared =
+            prepare_image(original.clone(), &config(ClipboardImageFormat::Jpeg)).unwrap();
+
+        assert!(!prepared.downscaled);
+        assert_eq!((prepared.width, prepared.height), (80, 40));
+        // Lossless even though downscaled images would be JPEG
+        assert_eq!(prepared.format, ClipboardImageFormat::Png);
+        let decoded = image::load_from_memory_with_format(&prepared.data, ImageFormat::Png)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(decoded, original);
+    }
+}
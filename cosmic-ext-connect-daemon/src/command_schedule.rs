@@ -0,0 +1,198 @@
+//! Scheduled Remote Commands
+//!
+//! Keeps the [`CommandScheduler`] on disk and sends due commands to their
+//! devices. The schedule is checked every [`CHECK_INTERVAL`]; commands for
+//! devices that are not connected wait until they are.
+
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use cosmic_ext_connect_protocol::plugins::runcommand_schedule::{
+    execute_request_packet, CommandScheduler, Schedule, ScheduledCommand,
+};
+use cosmic_ext_connect_protocol::{ConnectionManager, DeviceManager};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// How often due commands are looked for
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Persistent command schedule
+pub struct ScheduledCommands {
+    scheduler: RwLock<CommandScheduler>,
+    path: PathBuf,
+}
+
+impl ScheduledCommands {
+    /// Load the schedule stored at `path`, starting empty if there is none
+    pub fn load(path: PathBuf) -> Self {
+        let scheduler = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable command schedule {:?}: {}", path, e);
+                CommandScheduler::new()
+            }),
+            Err(_) => CommandScheduler::new(),
+        };
+        Self {
+            scheduler: RwLock::new(scheduler),
+            path,
+        }
+    }
+
+    /// Schedule a device's command, returning its first run
+    pub async fn schedule(
+        &self,
+        device_id: &str,
+        command_id: &str,
+        schedule: Schedule,
+        now: NaiveDateTime,
+    ) -> Result<NaiveDateTime> {
+        let mut scheduler = self.scheduler.write().await;
+        let next_run = scheduler.schedule(device_id, command_id, schedule, now)?;
+        self.save(&scheduler).await?;
+        Ok(next_run)
+    }
+
+    /// Remove a command's schedule, returning whether it had one
+    pub async fn cancel(&self, device_id: &str, command_id: &str) -> Result<bool> {
+        let mut scheduler = self.scheduler.write().await;
+        let removed = scheduler.cancel(device_id, command_id);
+        if removed {
+            self.save(&scheduler).await?;
+        }
+        Ok(removed)
+    }
+
+    /// A device's scheduled commands, soonest first
+    pub async fn commands_for(&self, device_id: &str) -> Vec<ScheduledCommand> {
+        self.scheduler
+            .read()
+            .await
+            .commands_for(device_id)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    async fn save(&self, scheduler: &CommandScheduler) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .context("Failed to create command schedule directory")?;
+        }
+        let contents =
+            serde_json::to_string_pretty(scheduler).context("Failed to serialize schedule")?;
+        tokio::fs::write(&self.path, contents)
+            .await
+            .context("Failed to write command schedule")?;
+        Ok(())
+    }
+
+    /// Send every command due at `now`, returning how many were sent
+    pub async fn run_due(
+        &self,
+        now: NaiveDateTime,
+        device_manager: &Arc<RwLock<DeviceManager>>,
+        connection_manager: &Arc<RwLock<ConnectionManager>>,
+    ) -> usize {
+        let connected: HashSet<String> = device_manager
+            .read()
+            .await
+            .connected_devices()
+            .map(|device| device.id().to_string())
+            .collect();
+
+        let mut scheduler = self.scheduler.write().await;
+        let due = scheduler.due(now, |id| connected.contains(id));
+        if due.is_empty() {
+            return 0;
+        }
+
+        let mut sent = 0;
+        for (device_id, command_id) in due {
+            let packet = execute_request_packet(&command_id);
+            match connection_manager
+                .read()
+                .await
+                .send_packet(&device_id, &packet)
+                .await
+            {
+                Ok(()) => {
+                    info!("Ran scheduled command {} on {}", command_id, device_id);
+                    scheduler.fired(&device_id, &command_id, now);
+                    sent += 1;
+                }
+                // Left due, so it is retried on the next check
+                Err(e) => warn!(
+                    "Failed to send scheduled command {} to {}: {}",
+                    command_id, device_id, e
+                ),
+            }
+        }
+        if sent > 0 {
+            if let Err(e) = self.save(&scheduler).await {
+                warn!("Failed to save command schedule: {}", e);
+            }
+        }
+        sent
+    }
+
+    /// Check for due commands every [`CHECK_INTERVAL`]
+    pub fn spawn_runner(
+        self: Arc<Self>,
+        device_manager: Arc<RwLock<DeviceManager>>,
+        connection_manager: Arc<RwLock<ConnectionManager>>,
+    ) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                let now = chrono::Local::now().naive_local();
+                let sent = self
+                    .run_due(now, &device_manager, &connection_manager)
+                    .await;
+                if sent > 0 {
+                    debug!("Sent {} scheduled command(s)", sent);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_schedule_is_persisted() {
+        let dir =
+            std::env::temp_dir().join(format!("cconnect-schedule-test-{}", std::process::id()));
+        let path = dir.join("scheduled_commands.json");
+        let _ = std::fs::remove_file(&path);
+        let now = chrono::NaiveDate::from_ymd_opt(2026, 10, 16)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+
+        let commands = ScheduledCommands::load(path.clone());
+        commands
+            .schedule("phone", "mute", "daily 22:00".parse().unwrap(), now)
+            .await
+            .unwrap();
+
+        let reloaded = ScheduledCommands::load(path.clone());
+        let scheduled = reloaded.commands_for("phone").await;
+        assert_eq!(scheduled.len(), 1);
+        assert_eq!(scheduled[0].command_id, "mute");
+
+        assert!(reloaded.cancel("phone", "mute").await.unwrap());
+        assert!(ScheduledCommands::load(path)
+            .commands_for("phone")
+            .await
+            .is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
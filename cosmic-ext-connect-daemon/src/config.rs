@@ -3,8 +3,12 @@
 //! Configuration management for the CConnect daemon.
 
 use anyhow::{Context, Result};
-use cosmic_ext_connect_protocol::TransportPreference;
+use cosmic_ext_connect_protocol::plugins::safe_mode;
+use cosmic_ext_connect_protocol::plugins::share_hooks::CompletionHook;
+use cosmic_ext_connect_protocol::plugins::share_users::UserDirectories;
+use cosmic_ext_connect_protocol::{CConnectConfig, ReceiveTrust, TransportPreference};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use std::time::Duration;
@@ -31,6 +35,14 @@ pub struct Config {
 
     /// Storage paths
     pub paths: PathConfig,
+
+    /// Protocol tuning (timeouts, resource limits, streaming)
+    ///
+    /// Listen port, discovery timing and certificate directory are still taken
+    /// from the `network` and `paths` sections; the rest of `discovery`
+    /// applies, and `stream` configures the extended display server.
+    #[serde(default = "default_protocol_config")]
+    pub protocol: CConnectConfig,
 }
 
 /// Device configuration
@@ -69,6 +81,14 @@ pub struct NetworkConfig {
     /// Device timeout in seconds (how long before a device is considered offline)
     #[serde(default = "default_device_timeout")]
     pub device_timeout: u64,
+
+    /// Also discover devices over mDNS/DNS-SD, for networks that block broadcasts
+    #[serde(default = "default_false")]
+    pub enable_mdns: bool,
+
+    /// Also announce and listen over IPv6 multicast, for IPv6-only segments
+    #[serde(default = "default_true")]
+    pub enable_ipv6: bool,
 }
 
 /// Transport configuration
@@ -103,6 +123,15 @@ pub struct TransportConfig {
     /// Bluetooth device filtering (empty = no filter, accepts all)
     #[serde(default)]
     pub bluetooth_device_filter: Vec<String>,
+
+    /// Seconds between checks for a better transport to move connected
+    /// devices to (0 = only when a transport connects)
+    #[serde(default = "default_migration_interval")]
+    pub migration_interval_secs: u64,
+
+    /// Keep the previous transport open as a fallback after switching
+    #[serde(default = "default_true")]
+    pub keep_fallback: bool,
 }
 
 /// Transport preference configuration (serialization wrapper)
@@ -204,10 +233,81 @@ pub struct PluginConfig {
     #[serde(default = "default_true")]
     pub enable_share: bool,
 
+    /// Copy text received via the share plugin to the desktop clipboard
+    ///
+    /// A notification with a preview is shown either way.
+    #[serde(default = "default_true")]
+    pub share_text_to_clipboard: bool,
+
+    /// Maximum size in bytes of files accepted via the share plugin
+    ///
+    /// Larger files are refused before anything is written to disk. Devices
+    /// can override this in their own settings. `None` means unlimited.
+    #[serde(default)]
+    pub share_max_incoming_file_size: Option<u64>,
+
+    /// Actions run after a file has been received (open it, run a command)
+    ///
+    /// Each hook can be limited to certain file extensions and devices.
+    #[serde(default)]
+    pub share_completion_hooks: Vec<CompletionHook>,
+
+    /// Write metadata sent along with a file (caption, tags) to a
+    /// `<file>.meta.json` sidecar next to it
+    #[serde(default)]
+    pub share_metadata_sidecars: bool,
+
+    /// Hash shared files with SHA-256 so the receiver can detect corruption
+    ///
+    /// Files we send carry their digest, and received files that don't match
+    /// the sender's digest are discarded. Turn off to skip the extra hashing.
+    #[serde(default = "default_true")]
+    pub share_verify_checksums: bool,
+
+    /// Parallel connections a shared file may be split over (1 = single stream)
+    ///
+    /// Helps on high-latency links. Only used with devices that advertise
+    /// support, and such transfers cannot be resumed part-way.
+    #[serde(default = "default_share_payload_streams")]
+    pub share_payload_streams: usize,
+
+    /// Whether incoming files are received, asked about or refused
+    ///
+    /// With `prompt`, a notification offers to accept or decline each file.
+    /// Files the sender staged for confirmation are always asked about,
+    /// unless the device's own setting trusts it. Devices can override this
+    /// in their own settings.
+    #[serde(default = "default_share_receive_trust")]
+    pub share_receive_trust: ReceiveTrust,
+
+    /// Seconds to wait for an answer to a receive prompt before declining
+    #[serde(default = "default_share_prompt_timeout")]
+    pub share_prompt_timeout_secs: u64,
+
+    /// User that files shared from this machine are sent as (None = untagged)
+    #[serde(default)]
+    pub share_send_as: Option<String>,
+
+    /// Directory received files are saved into, by the user they are tagged with
+    ///
+    /// Untagged files and users without an entry go to `~/Downloads`.
+    #[serde(default)]
+    pub share_user_directories: HashMap<String, PathBuf>,
+
     /// Enable clipboard plugin
     #[serde(default = "default_true")]
     pub enable_clipboard: bool,
 
+    /// How images copied to the clipboard are sent to devices
+    #[serde(default)]
+    pub clipboard_images: ClipboardImageConfig,
+
+    /// Longest clipboard text sent inside a packet, in bytes
+    ///
+    /// Longer text is sent as a payload.
+    #[serde(default = "default_clipboard_max_inline_length")]
+    pub clipboard_max_inline_length: usize,
+
     /// Enable MPRIS plugin
     #[serde(default = "default_true")]
     pub enable_mpris: bool,
@@ -314,6 +414,52 @@ pub struct PluginConfig {
     /// Enable ExtendedDisplay plugin (wireless extended display to Android tablet)
     #[serde(default = "default_true")]
     pub enable_extendeddisplay: bool,
+
+    /// Refuse plugins that run commands or inject input (runcommand, macro,
+    /// remote input, mouse/keyboard share, remote desktop, presenter, lock,
+    /// power), whatever the `enable_*` and per-device settings say
+    ///
+    /// Also switched on by setting `COSMIC_CONNECT_SAFE_MODE=1`.
+    #[serde(default)]
+    pub safe_mode: bool,
+}
+
+/// Clipboard image configuration
+///
+/// Images within the maximum dimensions are sent unchanged as PNG. Larger
+/// images are scaled down to fit and encoded in `format`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardImageConfig {
+    /// Send images copied to the clipboard to connected devices
+    #[serde(default = "default_false")]
+    pub enabled: bool,
+
+    /// Maximum width in pixels
+    #[serde(default = "default_clipboard_image_max_dimension")]
+    pub max_width: u32,
+
+    /// Maximum height in pixels
+    #[serde(default = "default_clipboard_image_max_dimension")]
+    pub max_height: u32,
+
+    /// Format of downscaled images
+    #[serde(default)]
+    pub format: ClipboardImageFormat,
+
+    /// JPEG quality (1-100), used when `format` is JPEG
+    #[serde(default = "default_clipboard_image_jpeg_quality")]
+    pub jpeg_quality: u8,
+}
+
+/// Encoding of downscaled clipboard images
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardImageFormat {
+    /// Lossless PNG
+    #[default]
+    Png,
+    /// Lossy JPEG, smaller for photos and screenshots
+    Jpeg,
 }
 
 /// Storage paths configuration
@@ -357,6 +503,22 @@ fn default_bluetooth_timeout() -> u64 {
     15
 }
 
+fn default_migration_interval() -> u64 {
+    15
+}
+
+fn default_clipboard_image_max_dimension() -> u32 {
+    1920
+}
+
+fn default_clipboard_image_jpeg_quality() -> u8 {
+    85
+}
+
+fn default_clipboard_max_inline_length() -> usize {
+    cosmic_ext_connect_protocol::plugins::clipboard::DEFAULT_MAX_INLINE_LENGTH
+}
+
 fn default_true() -> bool {
     true
 }
@@ -369,6 +531,18 @@ fn default_max_body_length() -> usize {
     2000
 }
 
+fn default_share_receive_trust() -> ReceiveTrust {
+    ReceiveTrust::AutoAccept
+}
+
+fn default_share_prompt_timeout() -> u64 {
+    60
+}
+
+fn default_share_payload_streams() -> usize {
+    1
+}
+
 impl Default for NetworkConfig {
     fn default() -> Self {
         Self {
@@ -377,6 +551,8 @@ impl Default for NetworkConfig {
             transfer_port_end: default_transfer_port_end(),
             discovery_interval: default_discovery_interval(),
             device_timeout: default_device_timeout(),
+            enable_mdns: false,
+            enable_ipv6: true,
         }
     }
 }
@@ -394,6 +570,18 @@ impl Default for NotificationListenerConfig {
     }
 }
 
+impl Default for ClipboardImageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_width: default_clipboard_image_max_dimension(),
+            max_height: default_clipboard_image_max_dimension(),
+            format: ClipboardImageFormat::default(),
+            jpeg_quality: default_clipboard_image_jpeg_quality(),
+        }
+    }
+}
+
 impl Default for TransportConfig {
     fn default() -> Self {
         Self {
@@ -411,6 +599,10 @@ impl Default for TransportConfig {
             auto_fallback: true,
             // No device filter by default (accept all)
             bluetooth_device_filter: Vec::new(),
+            // Look for a better transport every 15 seconds
+            migration_interval_secs: default_migration_interval(),
+            // Keep Bluetooth around for when WiFi drops
+            keep_fallback: true,
         }
     }
 }
@@ -426,6 +618,12 @@ impl TransportConfig {
         Duration::from_secs(self.bluetooth_timeout_secs)
     }
 
+    /// Get the transport migration interval, None if periodic checks are off
+    pub fn migration_interval(&self) -> Option<Duration> {
+        (self.migration_interval_secs > 0)
+            .then(|| Duration::from_secs(self.migration_interval_secs))
+    }
+
     /// Check if a Bluetooth device address should be accepted
     #[allow(dead_code)]
     pub fn should_accept_bluetooth_device(&self, address: &str) -> bool {
@@ -448,7 +646,19 @@ impl Default for PluginConfig {
             enable_battery: true,
             enable_notification: true,
             enable_share: true,
+            share_text_to_clipboard: true,
+            share_max_incoming_file_size: None,
+            share_completion_hooks: Vec::new(),
+            share_metadata_sidecars: false,
+            share_verify_checksums: true,
+            share_payload_streams: default_share_payload_streams(),
+            share_receive_trust: default_share_receive_trust(),
+            share_prompt_timeout_secs: default_share_prompt_timeout(),
+            share_send_as: None,
+            share_user_directories: HashMap::new(),
             enable_clipboard: true,
+            clipboard_images: ClipboardImageConfig::default(),
+            clipboard_max_inline_length: default_clipboard_max_inline_length(),
             enable_mpris: true,
             enable_runcommand: true,
             enable_remoteinput: true,
@@ -475,10 +685,94 @@ impl Default for PluginConfig {
             enable_systemvolume: true,
             enable_connectivityreport: true,
             enable_extendeddisplay: true,
+            safe_mode: false,
         }
     }
 }
 
+impl PluginConfig {
+    /// Get the receive prompt timeout as Duration
+    pub fn share_prompt_timeout(&self) -> Duration {
+        Duration::from_secs(self.share_prompt_timeout_secs)
+    }
+
+    /// Whether the plugin registered under `plugin` is enabled
+    ///
+    /// Names are plugin factory names; unknown names are disabled.
+    pub fn is_enabled(&self, plugin: &str) -> bool {
+        match plugin {
+            "ping" => self.enable_ping,
+            "battery" => self.enable_battery,
+            "notification" => self.enable_notification,
+            "share" => self.enable_share,
+            "clipboard" => self.enable_clipboard,
+            "mpris" => self.enable_mpris,
+            "runcommand" => self.enable_runcommand,
+            "remoteinput" => self.enable_remoteinput,
+            "findmyphone" => self.enable_findmyphone,
+            "lock" => self.enable_lock,
+            "telephony" => self.enable_telephony,
+            "presenter" => self.enable_presenter,
+            "contacts" => self.enable_contacts,
+            "systemmonitor" => self.enable_systemmonitor,
+            "wol" => self.enable_wol,
+            "screenshot" => self.enable_screenshot,
+            "remotedesktop" => self.enable_remotedesktop,
+            "power" => self.enable_power,
+            "clipboardhistory" => self.enable_clipboardhistory,
+            "macro" => self.enable_macro,
+            "chat" => self.enable_chat,
+            "audiostream" => self.enable_audiostream,
+            "filesync" => self.enable_filesync,
+            "screenshare" => self.enable_screenshare,
+            "mousekeyboardshare" => self.enable_mousekeyboardshare,
+            "networkshare" => self.enable_networkshare,
+            "camera" => self.enable_camera,
+            "systemvolume" => self.enable_systemvolume,
+            "connectivity_report" => self.enable_connectivityreport,
+            "extendeddisplay" => self.enable_extendeddisplay,
+            _ => false,
+        }
+    }
+
+    /// Whether safe mode is on, by config or by environment
+    pub fn safe_mode_enabled(&self) -> bool {
+        self.safe_mode || safe_mode::requested_by_env()
+    }
+
+    /// Routing of received files to per-user directories
+    pub fn share_user_directories(&self) -> UserDirectories {
+        self.share_user_directories
+            .iter()
+            .fold(UserDirectories::default(), |dirs, (user, dir)| {
+                dirs.with_user(user.clone(), dir.clone())
+            })
+    }
+
+    /// Completion hooks to install; none in safe mode, which runs no commands
+    pub fn share_completion_hooks(&self) -> Vec<CompletionHook> {
+        if self.safe_mode_enabled() {
+            Vec::new()
+        } else {
+            self.share_completion_hooks.clone()
+        }
+    }
+}
+
+/// Protocol defaults used by the daemon
+///
+/// Keeps the daemon's historical 30 second keep-alive and 18080 extended
+/// display signaling port rather than the library defaults.
+fn default_protocol_config() -> CConnectConfig {
+    let mut config = CConnectConfig::default();
+    config.connection.keep_alive_interval = Duration::from_secs(30);
+    #[cfg(feature = "extendeddisplay")]
+    {
+        config.stream.signaling_port = 18080;
+    }
+    config
+}
+
 impl Default for Config {
     fn default() -> Self {
         let config_dir = dirs::config_dir()
@@ -514,6 +808,7 @@ impl Default for Config {
                 data_dir,
                 cert_dir,
             },
+            protocol: default_protocol_config(),
         }
     }
 }
@@ -533,6 +828,10 @@ impl Config {
                 fs::read_to_string(&config_path).context("Failed to read config file")?;
             let config: Config =
                 toml::from_str(&contents).context("Failed to parse config file")?;
+            config
+                .protocol
+                .validate()
+                .context("Invalid protocol settings in config file")?;
             Ok(config)
         } else {
             // Create default config
@@ -637,6 +936,33 @@ mod tests {
         assert_eq!(config.network.transfer_port_start, 1739);
         assert!(config.plugins.enable_ping);
         assert!(config.plugins.enable_battery);
+        assert_eq!(config.plugins.share_payload_streams, 1);
+    }
+
+    #[test]
+    fn test_plugin_enabled_by_name() {
+        let mut plugins = PluginConfig::default();
+        assert!(plugins.is_enabled("connectivity_report"));
+        plugins.enable_connectivityreport = false;
+        assert!(!plugins.is_enabled("connectivity_report"));
+        assert!(!plugins.is_enabled("unknown"));
+    }
+
+    #[test]
+    fn test_completion_hooks_skipped_in_safe_mode() {
+        use cosmic_ext_connect_protocol::plugins::share_hooks::CompletionAction;
+
+        let mut plugins = PluginConfig::default();
+        plugins.share_completion_hooks = vec![CompletionHook::new(CompletionAction::Run {
+            program: "notify-send".to_string(),
+            args: Vec::new(),
+        })];
+        if !safe_mode::requested_by_env() {
+            assert_eq!(plugins.share_completion_hooks().len(), 1);
+        }
+
+        plugins.safe_mode = true;
+        assert!(plugins.share_completion_hooks().is_empty());
     }
 
     #[test]
@@ -647,6 +973,23 @@ mod tests {
         assert_eq!(parsed.network.discovery_port, config.network.discovery_port);
     }
 
+    #[test]
+    fn test_protocol_section_round_trip_and_validation() {
+        let mut config = Config::default();
+        config.protocol.validate().unwrap();
+        assert_eq!(
+            config.protocol.connection.keep_alive_interval,
+            Duration::from_secs(30)
+        );
+
+        config.protocol.pairing.timeout = Duration::ZERO;
+        let toml_str = toml::to_string(&config).unwrap();
+        let parsed: Config = toml::from_str(&toml_str).unwrap();
+
+        let err = parsed.protocol.validate().unwrap_err();
+        assert!(err.to_string().contains("pairing.timeout"));
+    }
+
     #[test]
     fn test_transport_config_defaults() {
         let transport = TransportConfig::default();
@@ -54,6 +54,7 @@ pub struct NotificationBuilder {
     timeout: i32,
     actions: Vec<(String, String)>,
     hints: HashMap<String, zbus::zvariant::Value<'static>>,
+    replaces_id: u32,
 }
 
 impl NotificationBuilder {
@@ -68,6 +69,7 @@ impl NotificationBuilder {
             timeout: 5000, // 5 seconds default
             actions: Vec::new(),
             hints: HashMap::new(),
+            replaces_id: 0,
         }
     }
 
@@ -181,6 +183,12 @@ impl NotificationBuilder {
         self
     }
 
+    /// Replace a previously sent notification instead of adding a new one
+    pub fn replaces(mut self, notification_id: u32) -> Self {
+        self.replaces_id = notification_id;
+        self
+    }
+
     /// Set a custom hint
     #[allow(dead_code)]
     pub fn hint(mut self, key: impl Into<String>, value: zbus::zvariant::Value<'static>) -> Self {
@@ -211,7 +219,7 @@ impl NotificationBuilder {
 
         NotificationParams {
             app_name: self.app_name,
-            replaces_id: 0,
+            replaces_id: self.replaces_id,
             icon: self.icon,
             summary: self.summary,
             body: self.body,
@@ -347,6 +355,9 @@ impl CosmicNotifier {
     /// Send a notification forwarded from a device
     ///
     /// If `rich_body` is provided, it will be sanitized and used instead of plain text.
+    /// `icon` is an icon name or the path of the device's icon payload.
+    /// `replaces_id` is the ID returned for an earlier version of the notification.
+    #[allow(clippy::too_many_arguments)]
     pub async fn notify_from_device(
         &self,
         device_name: &str,
@@ -354,11 +365,14 @@ impl CosmicNotifier {
         title: &str,
         text: &str,
         rich_body: Option<&str>,
+        icon: &str,
+        replaces_id: Option<u32>,
     ) -> Result<u32> {
         let summary = format!("{} ({})", title, device_name);
 
         let mut builder = NotificationBuilder::new(summary)
-            .icon("phone-symbolic")
+            .icon(icon)
+            .replaces(replaces_id.unwrap_or(0))
             .timeout(10000);
 
         // Use rich body if available, otherwise plain text
@@ -396,6 +410,8 @@ impl CosmicNotifier {
         rich_body: Option<&str>,
         image_bytes: Option<(Vec<u8>, i32, i32)>,
         links: Vec<String>,
+        icon: &str,
+        replaces_id: Option<u32>,
     ) -> Result<u32> {
         let summary = format!("{} ({})", title, device_name);
         let body_text = if !app_name.is_empty() {
@@ -405,7 +421,8 @@ impl CosmicNotifier {
         };
 
         let mut builder = NotificationBuilder::new(summary)
-            .icon("phone-symbolic")
+            .icon(icon)
+            .replaces(replaces_id.unwrap_or(0))
             .timeout(10000);
 
         // Use rich body if available, otherwise plain text
@@ -433,6 +450,9 @@ impl CosmicNotifier {
     /// Send a messaging notification with potentially actionable web URL
     ///
     /// If `rich_body` is provided, it will be sanitized and used instead of plain text.
+    /// `icon` is the path of the app's icon payload, if the device sent one.
+    /// `replaces_id` is the ID returned for an earlier version of the notification.
+    #[allow(clippy::too_many_arguments)]
     pub async fn notify_messaging(
         &self,
         device_name: &str,
@@ -441,6 +461,8 @@ impl CosmicNotifier {
         message: &str,
         rich_body: Option<&str>,
         web_url: Option<&str>,
+        icon: Option<&str>,
+        replaces_id: Option<u32>,
     ) -> Result<u32> {
         let summary = format!("{} ({})", sender, device_name);
 
@@ -454,7 +476,8 @@ impl CosmicNotifier {
 
         let mut builder = NotificationBuilder::new(summary)
             .body(body)
-            .icon("mail-message-new-symbolic")
+            .icon(icon.unwrap_or("mail-message-new-symbolic"))
+            .replaces(replaces_id.unwrap_or(0))
             .timeout(15000); // Messaging notifications stay longer
 
         if let Some(url) = web_url {
@@ -480,6 +503,50 @@ impl CosmicNotifier {
         .await
     }
 
+    /// Send a notification offering to migrate a reinstalled device's settings
+    pub async fn notify_identity_migration(&self, device_name: &str) -> Result<u32> {
+        self.send(
+            NotificationBuilder::new("Known Device Paired Again")
+                .body(format!(
+                    "{} looks like a device you paired before. Keep its previous settings?",
+                    device_name
+                ))
+                .icon("phone-symbolic")
+                .urgency(Urgency::Normal)
+                .timeout(0)
+                .action("migrate", "Keep Settings")
+                .action("keep_separate", "Start Fresh"),
+        )
+        .await
+    }
+
+    /// Ask whether to receive an incoming file
+    ///
+    /// The notification closes once `timeout` passes; the transfer is then
+    /// declined.
+    pub async fn notify_transfer_prompt(
+        &self,
+        device_name: &str,
+        filename: &str,
+        size: u64,
+        timeout: std::time::Duration,
+    ) -> Result<u32> {
+        self.send(
+            NotificationBuilder::new(format!("File from {}", device_name))
+                .body(format!(
+                    "{} ({})",
+                    filename,
+                    crate::diagnostics::format_bytes(size)
+                ))
+                .icon("document-save-symbolic")
+                .urgency(Urgency::Normal)
+                .timeout(timeout.as_millis().min(i32::MAX as u128) as i32)
+                .action("accept_transfer", "Accept")
+                .action("decline_transfer", "Decline"),
+        )
+        .await
+    }
+
     /// Send a file received notification
     pub async fn notify_file_received(
         &self,
@@ -498,6 +565,63 @@ impl CosmicNotifier {
         .await
     }
 
+    /// Send a notification for a file refused for exceeding the size limit
+    pub async fn notify_file_rejected(
+        &self,
+        device_name: &str,
+        filename: &str,
+        size: u64,
+        limit: u64,
+    ) -> Result<u32> {
+        self.send(
+            NotificationBuilder::new(format!("File from {} rejected", device_name))
+                .body(format!(
+                    "{} ({} bytes) is larger than the {} byte limit",
+                    filename, size, limit
+                ))
+                .icon("dialog-warning-symbolic")
+                .urgency(Urgency::Normal)
+                .timeout(7000),
+        )
+        .await
+    }
+
+    /// Send a notification for text shared from a device
+    pub async fn notify_text_shared(
+        &self,
+        device_name: &str,
+        preview: &str,
+        copied_to_clipboard: bool,
+    ) -> Result<u32> {
+        let summary = if copied_to_clipboard {
+            format!("Text from {} copied to clipboard", device_name)
+        } else {
+            format!("Text from {}", device_name)
+        };
+
+        self.send(
+            NotificationBuilder::new(summary)
+                .body(preview)
+                .icon("edit-paste-symbolic")
+                .timeout(10000),
+        )
+        .await
+    }
+
+    /// Send a notification for a URL shared from a device
+    ///
+    /// Offers an "Open" action that launches the URL in the default browser.
+    pub async fn notify_url_shared(&self, device_name: &str, url: &str) -> Result<u32> {
+        self.send(
+            NotificationBuilder::new(format!("Link from {}", device_name))
+                .body(url)
+                .icon("web-browser-symbolic")
+                .timeout(10000)
+                .action(format!("open_url:{}", url), "Open"),
+        )
+        .await
+    }
+
     /// Send a battery low warning from a device
     pub async fn notify_battery_low(&self, device_name: &str, level: u8) -> Result<u32> {
         self.send(
@@ -547,7 +671,6 @@ impl CosmicNotifier {
     }
 
     /// Send a pairing error notification
-    #[allow(dead_code)]
     pub async fn notify_pairing_error(&self, device_name: &str, error: &str) -> Result<u32> {
         self.send(
             NotificationBuilder::new("Pairing Failed")
@@ -6,11 +6,18 @@
 //! Provides IPC between the background daemon and COSMIC panel applet.
 //! Exposes device management, pairing, and plugin actions via DBus.
 
+use crate::error_history::{ErrorCategory, ErrorHistory};
+use crate::event_feed::{DeviceEventFeed, DeviceEventKind, FEED_CAPACITY};
 use anyhow::{Context, Result};
+use cosmic_ext_connect_protocol::payload::RESUMABLE_FIELD;
+use cosmic_ext_connect_protocol::payload_crypto::PAYLOAD_ENCRYPTION_FIELD;
 use cosmic_ext_connect_protocol::plugins::filesync::{
     ConflictStrategy as FilesyncConflictStrategy, FileSyncPlugin, SyncFolder as FilesyncFolder,
 };
-use cosmic_ext_connect_protocol::{ConnectionManager, Device, DeviceManager, PluginManager};
+use cosmic_ext_connect_protocol::{
+    ConnectionManager, Device, DeviceManager, PayloadCipher, PayloadEncryption, PayloadKey,
+    PendingTransferPrompts, PluginManager, ResourceManager, TransferInfo, TransferPath,
+};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -103,6 +110,8 @@ pub struct DeviceInfo {
     pub incoming_capabilities: Vec<String>,
     /// Supported outgoing plugin capabilities
     pub outgoing_capabilities: Vec<String>,
+    /// Recent packet activity: "active", "idle" or "stale"
+    pub activity: String,
 }
 
 impl From<&Device> for DeviceInfo {
@@ -118,6 +127,10 @@ impl From<&Device> for DeviceInfo {
             last_seen: device.last_seen as i64,
             incoming_capabilities: device.info.incoming_capabilities.clone(),
             outgoing_capabilities: device.info.outgoing_capabilities.clone(),
+            activity: device
+                .activity_level(std::time::Instant::now())
+                .as_str()
+                .to_string(),
         }
     }
 }
@@ -144,6 +157,43 @@ pub struct ScreenShareStats {
     pub avg_fps: u64,
 }
 
+/// Per-device byte counters for DBus serialization
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, zbus::zvariant::Type)]
+pub struct TrafficStats {
+    /// Protocol packet bytes sent
+    pub control_sent: u64,
+    /// Protocol packet bytes received
+    pub control_received: u64,
+    /// File transfer bytes sent
+    pub payload_sent: u64,
+    /// File transfer bytes received
+    pub payload_received: u64,
+}
+
+impl From<cosmic_ext_connect_protocol::TrafficStats> for TrafficStats {
+    fn from(stats: cosmic_ext_connect_protocol::TrafficStats) -> Self {
+        Self {
+            control_sent: stats.control_sent,
+            control_received: stats.control_received,
+            payload_sent: stats.payload_sent,
+            payload_received: stats.payload_received,
+        }
+    }
+}
+
+/// How a device is connected, for DBus serialization
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, zbus::zvariant::Type)]
+pub struct ConnectionInfo {
+    /// Protocol version the device announced
+    pub protocol_version: u32,
+    /// Transport of the open connection ("tcp"), empty when not connected
+    pub transport: String,
+    /// Measured round-trip time in milliseconds, -1 if not measured
+    pub rtt_ms: i64,
+    /// SHA-256 fingerprint of the device certificate, empty if unknown
+    pub certificate_fingerprint: String,
+}
+
 /// Contact information for DBus serialization
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, zbus::zvariant::Type)]
 pub struct ContactInfo {
@@ -186,6 +236,30 @@ pub struct DaemonMetrics {
     pub bandwidth_bps: f64,
 }
 
+/// Per-device outcome of a batch pairing for DBus serialization
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, zbus::zvariant::Type)]
+pub struct BatchPairEntry {
+    /// ID of the device
+    pub device_id: String,
+    /// Name of the device
+    pub device_name: String,
+    /// Outcome ("paired", "declined", "timed_out", "skipped" or "failed")
+    pub outcome: String,
+    /// Outcome with its reason, for display
+    pub detail: String,
+}
+
+impl From<cosmic_ext_connect_protocol::BatchPairResult> for BatchPairEntry {
+    fn from(result: cosmic_ext_connect_protocol::BatchPairResult) -> Self {
+        Self {
+            device_id: result.device_id,
+            device_name: result.device_name,
+            outcome: result.outcome.as_str().to_string(),
+            detail: result.outcome.to_string(),
+        }
+    }
+}
+
 /// Sync Folder configuration for DBus serialization
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, zbus::zvariant::Type)]
 pub struct SyncFolderInfo {
@@ -228,6 +302,16 @@ pub struct CConnectInterface {
     config: Arc<RwLock<crate::config::Config>>,
     /// Transfer manager for tracking and cancelling file transfers
     transfer_manager: Arc<TransferManager>,
+    /// Commands scheduled to run on devices
+    scheduled_commands: Arc<crate::command_schedule::ScheduledCommands>,
+    /// Sequenced device events for external integrations
+    event_feed: Arc<DeviceEventFeed>,
+    /// Incoming transfers waiting for the user to accept or decline
+    transfer_prompts: PendingTransferPrompts,
+    /// Recent errors per device
+    error_history: Arc<ErrorHistory>,
+    /// Per-device transfer queues
+    resource_manager: Arc<ResourceManager>,
     /// Tokio runtime handle for spawning async tasks from zbus executor
     tokio_handle: Handle,
 }
@@ -246,6 +330,11 @@ impl CConnectInterface {
         dbus_connection: Connection,
         metrics: Option<Arc<RwLock<crate::diagnostics::Metrics>>>,
         config: Arc<RwLock<crate::config::Config>>,
+        scheduled_commands: Arc<crate::command_schedule::ScheduledCommands>,
+        event_feed: Arc<DeviceEventFeed>,
+        transfer_prompts: PendingTransferPrompts,
+        error_history: Arc<ErrorHistory>,
+        resource_manager: Arc<ResourceManager>,
         tokio_handle: Handle,
     ) -> Self {
         Self {
@@ -260,10 +349,74 @@ impl CConnectInterface {
             metrics,
             config,
             transfer_manager: Arc::new(TransferManager::new()),
+            scheduled_commands,
+            event_feed,
+            transfer_prompts,
+            error_history,
+            resource_manager,
             tokio_handle,
         }
     }
 
+    /// Identity and address to send a pairing request to
+    async fn pairing_target(
+        &self,
+        device_id: &str,
+    ) -> Result<cosmic_ext_connect_protocol::pairing::BatchPairCandidate, zbus::fdo::Error> {
+        let device_manager = self.device_manager.read().await;
+        let device = device_manager
+            .get_device(device_id)
+            .ok_or_else(|| zbus::fdo::Error::Failed(format!("Device not found: {}", device_id)))?;
+
+        if device.is_paired() {
+            return Err(zbus::fdo::Error::Failed(format!(
+                "Device {} is already paired",
+                device_id
+            )));
+        }
+
+        let ip: std::net::IpAddr = device
+            .host
+            .as_deref()
+            .unwrap_or("0.0.0.0")
+            .parse()
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Invalid remote address: {}", e)))?;
+        Ok(cosmic_ext_connect_protocol::pairing::BatchPairCandidate {
+            device_info: device.info.clone(),
+            remote_addr: std::net::SocketAddr::new(ip, device.port.unwrap_or(1816)),
+        })
+    }
+
+    /// Apply a device's new plugin state to packet dispatch and announce it
+    async fn apply_plugin_state(&self, device_id: &str, plugin_name: &str, enabled: bool) {
+        let plugins = self.plugin_manager.read().await;
+        if let Err(e) = plugins.set_device_enabled(device_id, plugin_name, enabled) {
+            debug!("Not switching plugin for {}: {}", device_id, e);
+        }
+        drop(plugins);
+        self.emit_plugin_state_changed(device_id, plugin_name, enabled)
+            .await;
+    }
+
+    /// Advertise the capabilities of the plugins currently enabled
+    ///
+    /// Connected devices are sent the new identity right away; see
+    /// [`ConnectionManager::update_capabilities`].
+    async fn advertise_capabilities(&self) {
+        let (incoming, outgoing) = {
+            let manager = self.plugin_manager.read().await;
+            (
+                manager.get_all_incoming_capabilities(),
+                manager.get_all_outgoing_capabilities(),
+            )
+        };
+        self.connection_manager
+            .write()
+            .await
+            .update_capabilities(incoming, outgoing)
+            .await;
+    }
+
     /// Emit a device plugin state changed signal
     async fn emit_plugin_state_changed(&self, device_id: &str, plugin_name: &str, enabled: bool) {
         let object_server = self.dbus_connection.object_server();
@@ -319,8 +472,9 @@ async fn attempt_manual_connection(
     for device in dev_mgr.devices() {
         if let (Some(host), Some(port)) = (&device.host, device.port) {
             // Parse the host to an IP address and compare with the target socket address
-            if let Ok(ip) = host.parse::<std::net::IpAddr>() {
-                let device_addr = std::net::SocketAddr::new(ip, port);
+            if let Some(device_addr) =
+                cosmic_ext_connect_protocol::transport::tcp::parse_host_addr(host, port)
+            {
                 if device_addr == addr {
                     return Ok(device.id().to_string());
                 }
@@ -331,6 +485,403 @@ async fn attempt_manual_connection(
     Err(anyhow::anyhow!("Device did not respond with identity"))
 }
 
+/// Carry a previous identity's configuration over to a reinstalled device
+///
+/// Completes a migration offered when the device paired: trust in the old
+/// identity is revoked, its state moves to `device_id` and the new device is
+/// disconnected so its plugins start again with the migrated settings.
+pub async fn migrate_device_identity(
+    device_manager: &Arc<RwLock<DeviceManager>>,
+    pairing_service: Option<&Arc<RwLock<cosmic_ext_connect_protocol::pairing::PairingService>>>,
+    connection_manager: &Arc<RwLock<ConnectionManager>>,
+    device_id: &str,
+) -> Result<cosmic_ext_connect_protocol::DeviceMigrated> {
+    let previous_device_id = device_manager
+        .read()
+        .await
+        .migration_offer(device_id)
+        .map(|offer| offer.previous_device_id.clone())
+        .with_context(|| format!("No identity migration offered for {}", device_id))?;
+
+    if let Some(pairing_service) = pairing_service {
+        let pairing_service = pairing_service.read().await;
+        if pairing_service.is_paired(&previous_device_id).await {
+            pairing_service
+                .revoke(&previous_device_id)
+                .await
+                .context("Failed to revoke previous pairing")?;
+        }
+    }
+
+    let migrated = device_manager
+        .write()
+        .await
+        .accept_migration(device_id)
+        .await?;
+
+    if let Err(e) = crate::desktop_icons::remove_desktop_icon(&previous_device_id) {
+        debug!("No desktop icon removed for {}: {}", previous_device_id, e);
+    }
+    if let Err(e) = connection_manager.read().await.disconnect(device_id).await {
+        debug!("Disconnect of migrated device {} failed: {}", device_id, e);
+    }
+
+    info!(
+        "Migrated {} from {} to {} (stores: {})",
+        migrated.device_name,
+        migrated.previous_device_id,
+        migrated.device_id,
+        migrated.migrated_stores.join(", ")
+    );
+    Ok(migrated)
+}
+
+/// Payload key agreed with `device_id` at pairing, if any
+pub async fn payload_key(
+    pairing_service: Option<&Arc<RwLock<cosmic_ext_connect_protocol::pairing::PairingService>>>,
+    device_id: &str,
+) -> Option<PayloadKey> {
+    let service = pairing_service?.read().await;
+    service.payload_key(device_id).await.unwrap_or_else(|e| {
+        warn!("Failed to load payload key for {}: {}", device_id, e);
+        None
+    })
+}
+
+/// How files offered to a device are sent
+#[derive(Debug, Clone, Default)]
+struct ShareSendOptions {
+    /// When payloads are encrypted end to end
+    encryption: PayloadEncryption,
+    /// Key agreed with the device at pairing
+    payload_key: Option<PayloadKey>,
+    /// Whether the device may continue an interrupted transfer part-way
+    resumable: bool,
+    /// Parallel payload streams agreed with the device
+    streams: usize,
+}
+
+impl ShareSendOptions {
+    /// Options for sending to `device_id`, from the daemon settings
+    async fn load(
+        pairing_service: Option<&Arc<RwLock<cosmic_ext_connect_protocol::pairing::PairingService>>>,
+        config: &Arc<RwLock<crate::config::Config>>,
+        connection_manager: &Arc<RwLock<ConnectionManager>>,
+        device_id: &str,
+    ) -> Self {
+        let payload_key = payload_key(pairing_service, device_id).await;
+        let (encryption, requested_streams) = {
+            let config = config.read().await;
+            (
+                config.protocol.encryption.policy,
+                config.plugins.share_payload_streams,
+            )
+        };
+        let streams = connection_manager
+            .read()
+            .await
+            .payload_streams(device_id, requested_streams)
+            .await;
+        Self {
+            encryption,
+            // Only devices paired with key agreement run this implementation;
+            // others never send the resume offset and would stall the sender
+            resumable: payload_key.is_some(),
+            payload_key,
+            streams,
+        }
+    }
+
+    /// Don't offer resume or parallel streams, which only `send_file` honours
+    fn for_fan_out(mut self) -> Self {
+        self.resumable = false;
+        self.streams = 1;
+        self
+    }
+
+    /// Set up `server` to send the file offered in `packet`
+    ///
+    /// With parallel streams agreed, unencrypted files large enough are split
+    /// (see [`StreamLayout`](cosmic_ext_connect_protocol::StreamLayout)).
+    /// Otherwise devices that support it are offered resume (see
+    /// [`RESUMABLE_FIELD`](cosmic_ext_connect_protocol::payload::RESUMABLE_FIELD)).
+    ///
+    /// # Errors
+    ///
+    /// Fails if the encryption policy requires a payload key the device
+    /// doesn't have.
+    fn apply(
+        &self,
+        server: cosmic_ext_connect_protocol::TlsPayloadServer,
+        packet: &mut cosmic_ext_connect_protocol::Packet,
+    ) -> cosmic_ext_connect_protocol::Result<cosmic_ext_connect_protocol::TlsPayloadServer> {
+        // There is no relay yet; every transfer goes straight to the device
+        let encrypt = self
+            .encryption
+            .should_encrypt(TransferPath::Direct, self.payload_key.is_some())?;
+        // Encrypted files are sent over a single stream
+        let server = server.with_parallel_streams(if encrypt { 1 } else { self.streams });
+        let size = packet.payload_size.map_or(0, |size| size.max(0) as u64);
+        // A split file can't be resumed part-way
+        let server = match server.stream_layout(size) {
+            Some(layout) => {
+                *packet = layout.add_to_packet(packet.clone());
+                server
+            }
+            None if self.resumable => {
+                packet.body[RESUMABLE_FIELD] = serde_json::json!(true);
+                server.allow_resume()
+            }
+            None => server,
+        };
+        match self.payload_key.as_ref().filter(|_| encrypt) {
+            Some(key) => {
+                let cipher = PayloadCipher::generate(key)?;
+                packet.body[PAYLOAD_ENCRYPTION_FIELD] = cipher.describe();
+                Ok(server.with_encryption(cipher))
+            }
+            None => Ok(server),
+        }
+    }
+}
+
+/// Sends each file of a batch through its own TLS payload server
+struct TlsBatchSender {
+    device_id: String,
+    connection_manager: Arc<RwLock<ConnectionManager>>,
+    resource_manager: Arc<ResourceManager>,
+    options: ShareSendOptions,
+}
+
+#[async_trait::async_trait]
+impl cosmic_ext_connect_protocol::plugins::share_batch::BatchFileSender for TlsBatchSender {
+    async fn announce(
+        &self,
+        info: cosmic_ext_connect_protocol::plugins::share::MultiFileInfo,
+    ) -> cosmic_ext_connect_protocol::Result<()> {
+        use cosmic_ext_connect_protocol::plugins::share::SharePlugin;
+
+        let packet = SharePlugin::new().create_multifile_update_packet(info);
+        let conn_mgr = self.connection_manager.read().await;
+        conn_mgr.send_packet(&self.device_id, &packet).await
+    }
+
+    async fn send_file(
+        &self,
+        file: &cosmic_ext_connect_protocol::FileTransferInfo,
+    ) -> cosmic_ext_connect_protocol::Result<u64> {
+        self.send_file_with_progress(file, Arc::new(|_| {})).await
+    }
+
+    async fn send_file_with_progress(
+        &self,
+        file: &cosmic_ext_connect_protocol::FileTransferInfo,
+        progress: cosmic_ext_connect_protocol::plugins::share_batch::FileProgress,
+    ) -> cosmic_ext_connect_protocol::Result<u64> {
+        use cosmic_ext_connect_protocol::plugins::share::SharePlugin;
+        use cosmic_ext_connect_protocol::{ProgressThrottle, TlsPayloadServer};
+
+        let _slot = self
+            .resource_manager
+            .acquire_payload_slot(&self.device_id)
+            .await;
+        let (tls_config, traffic_counter) = {
+            let conn_mgr = self.connection_manager.read().await;
+            (
+                conn_mgr.tls_config(),
+                conn_mgr.traffic_counter(&self.device_id).await,
+            )
+        };
+        let server = TlsPayloadServer::new(tls_config)
+            .await?
+            .with_traffic_counter(traffic_counter)
+            .with_progress(ProgressThrottle::default().wrap(Box::new(move |done, _| {
+                progress(done);
+                true
+            })));
+
+        let mut packet = SharePlugin::new().create_file_packet(file.clone().into(), server.port());
+        let server = self.options.apply(server, &mut packet)?;
+        self.connection_manager
+            .read()
+            .await
+            .send_packet(&self.device_id, &packet)
+            .await?;
+
+        send_file_within_budget(
+            &self.connection_manager,
+            &self.resource_manager,
+            &self.device_id,
+            &file.filename,
+            server,
+            file.path.clone(),
+            file.size,
+        )
+        .await?;
+        Ok(file.size)
+    }
+}
+
+/// Run `send` as a transfer that depends on the device's connection
+///
+/// The transfer is stopped, and fails, when the device disconnects, rather
+/// than outliving the connection (see
+/// [`ConnectionManager::spawn_dependent`]).
+async fn send_while_connected<F>(
+    connection_manager: &Arc<RwLock<ConnectionManager>>,
+    device_id: &str,
+    name: &str,
+    send: F,
+) -> cosmic_ext_connect_protocol::Result<()>
+where
+    F: std::future::Future<Output = cosmic_ext_connect_protocol::Result<()>> + Send + 'static,
+{
+    use cosmic_ext_connect_protocol::{DependentTask, ProtocolError};
+
+    let (result_tx, result_rx) = tokio::sync::oneshot::channel();
+    connection_manager
+        .read()
+        .await
+        .spawn_dependent(
+            device_id,
+            DependentTask::Transfer,
+            name,
+            |mut signal| async move {
+                let result = tokio::select! {
+                    result = send => result,
+                    _ = signal.cancelled() => Err(ProtocolError::Transport(
+                        "Device disconnected during transfer".to_string(),
+                    )),
+                };
+                let _ = result_tx.send(result);
+            },
+        )
+        .await?;
+
+    // The sender is dropped if the transfer was aborted
+    result_rx.await.unwrap_or_else(|_| {
+        Err(ProtocolError::Transport(
+            "Transfer aborted on disconnect".to_string(),
+        ))
+    })
+}
+
+/// Send `path` through `server` within its share of the upload budget
+///
+/// The upload is registered with `resource_manager` while it runs, so the
+/// configured `max_bytes_per_sec` is split across concurrent sends and
+/// changes to it apply mid-transfer. Like [`send_while_connected`], the send
+/// fails when the device disconnects.
+async fn send_file_within_budget(
+    connection_manager: &Arc<RwLock<ConnectionManager>>,
+    resource_manager: &ResourceManager,
+    device_id: &str,
+    name: &str,
+    server: cosmic_ext_connect_protocol::TlsPayloadServer,
+    path: String,
+    size: u64,
+) -> cosmic_ext_connect_protocol::Result<()> {
+    let upload_id = format!("{}:{}", device_id, server.port());
+    let bandwidth = resource_manager
+        .register_upload(TransferInfo::new(
+            upload_id.clone(),
+            device_id.to_string(),
+            size,
+        ))
+        .await;
+    let send = server.with_bandwidth_limit(bandwidth).send_file(path);
+    let result = send_while_connected(connection_manager, device_id, name, send).await;
+    resource_manager.unregister_transfer(&upload_id).await;
+    result
+}
+
+/// Write a new file readable only by us, failing if it already exists
+async fn write_private_file(path: &std::path::Path, contents: &[u8]) -> std::io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .await?;
+    file.write_all(contents).await?;
+    file.flush().await
+}
+
+/// Relay per-file batch progress as D-Bus signals until the batch ends
+async fn forward_batch_events(
+    dbus_conn: Connection,
+    device_id: String,
+    mut events: tokio::sync::mpsc::UnboundedReceiver<
+        cosmic_ext_connect_protocol::plugins::share_batch::BatchEvent,
+    >,
+) {
+    use cosmic_ext_connect_protocol::plugins::share_batch::BatchEvent;
+
+    let Ok(iface_ref) = dbus_conn
+        .object_server()
+        .interface::<_, CConnectInterface>(OBJECT_PATH)
+        .await
+    else {
+        return;
+    };
+    while let Some(event) = events.recv().await {
+        let result = match event {
+            BatchEvent::Preparing {
+                batch_id,
+                files_found,
+            } => {
+                CConnectInterface::batch_preparing(
+                    iface_ref.signal_emitter(),
+                    &batch_id,
+                    &device_id,
+                    files_found as u32,
+                )
+                .await
+            }
+            BatchEvent::FileStarted {
+                batch_id,
+                index,
+                total_files,
+                path,
+            } => {
+                let filename = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                CConnectInterface::batch_file_started(
+                    iface_ref.signal_emitter(),
+                    &batch_id,
+                    &device_id,
+                    &filename,
+                    index as u32,
+                    total_files as u32,
+                )
+                .await
+            }
+            BatchEvent::Progress {
+                batch_id,
+                bytes_sent,
+                total_bytes,
+            } => {
+                CConnectInterface::batch_progress(
+                    iface_ref.signal_emitter(),
+                    &batch_id,
+                    &device_id,
+                    bytes_sent,
+                    total_bytes,
+                )
+                .await
+            }
+            BatchEvent::FileFinished { .. } | BatchEvent::Completed(_) => Ok(()),
+        };
+        if let Err(e) = result {
+            debug!("Failed to emit batch progress signal: {}", e);
+        }
+    }
+}
+
 /// Parse vCard data to extract contact information
 fn parse_vcard(vcard_data: &str) -> (String, Vec<String>, Vec<String>) {
     let mut name = String::new();
@@ -413,6 +964,40 @@ impl CConnectInterface {
         Ok(info)
     }
 
+    /// Get the capabilities negotiated with a device
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID to query
+    ///
+    /// # Returns
+    /// Packet types this desktop can send to the device and packet types it
+    /// handles from the device, each sorted
+    async fn get_device_capabilities(
+        &self,
+        device_id: String,
+    ) -> Result<(Vec<String>, Vec<String>), zbus::fdo::Error> {
+        debug!("DBus: GetDeviceCapabilities called for {}", device_id);
+
+        let (local_incoming, local_outgoing) = {
+            let manager = self.plugin_manager.read().await;
+            (
+                manager.get_all_incoming_capabilities(),
+                manager.get_all_outgoing_capabilities(),
+            )
+        };
+
+        let device_manager = self.device_manager.read().await;
+        let device = device_manager
+            .get_device(&device_id)
+            .ok_or_else(|| zbus::fdo::Error::Failed(format!("Device not found: {}", device_id)))?;
+        let capabilities = device.negotiated_capabilities(&local_incoming, &local_outgoing);
+
+        Ok((
+            capabilities.sendable().map(str::to_string).collect(),
+            capabilities.receivable().map(str::to_string).collect(),
+        ))
+    }
+
     /// Request pairing with a device
     ///
     /// # Arguments
@@ -430,30 +1015,7 @@ impl CConnectInterface {
             .ok_or_else(|| zbus::fdo::Error::Failed("Pairing service not initialized".to_string()))?
             .clone();
 
-        // Get device info from device manager
-        let device_manager = self.device_manager.read().await;
-        let device = device_manager
-            .get_device(&device_id)
-            .ok_or_else(|| zbus::fdo::Error::Failed(format!("Device not found: {}", device_id)))?;
-
-        // Check if already paired
-        if device.is_paired() {
-            return Err(zbus::fdo::Error::Failed(format!(
-                "Device {} is already paired",
-                device_id
-            )));
-        }
-
-        let device_info = device.info.clone();
-        let remote_addr: std::net::SocketAddr = format!(
-            "{}:{}",
-            device.host.as_deref().unwrap_or("0.0.0.0"),
-            device.port.unwrap_or(1816)
-        )
-        .parse()
-        .map_err(|e| zbus::fdo::Error::Failed(format!("Invalid remote address: {}", e)))?;
-
-        drop(device_manager);
+        let target = self.pairing_target(&device_id).await?;
 
         // Spawn the pairing request on the Tokio runtime
         // This is needed because zbus uses its own executor that isn't Tokio
@@ -463,7 +1025,7 @@ impl CConnectInterface {
             .spawn(async move {
                 let pairing_service = pairing_service.read().await;
                 pairing_service
-                    .request_pairing(device_info, remote_addr)
+                    .request_pairing(target.device_info, target.remote_addr)
                     .await
             })
             .await
@@ -474,6 +1036,176 @@ impl CConnectInterface {
         Ok(())
     }
 
+    /// Request pairing with a device, confirmed by PIN
+    ///
+    /// The user enters the returned PIN on the other device. It is also sent
+    /// with the PairingPin signal and is only valid for this request.
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID to pair with
+    ///
+    /// # Returns
+    /// The PIN to show the user
+    async fn pair_device_with_pin(&self, device_id: String) -> Result<String, zbus::fdo::Error> {
+        info!("DBus: PairDeviceWithPin called for {}", device_id);
+
+        let pairing_service = self
+            .pairing_service
+            .as_ref()
+            .ok_or_else(|| zbus::fdo::Error::Failed("Pairing service not initialized".to_string()))?
+            .clone();
+        let target = self.pairing_target(&device_id).await?;
+
+        let pin = self
+            .tokio_handle
+            .spawn(async move {
+                let pairing_service = pairing_service.read().await;
+                pairing_service
+                    .request_pairing_with_pin(target.device_info, target.remote_addr)
+                    .await
+            })
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Tokio task failed: {}", e)))?
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Failed to request pairing: {}", e)))?;
+
+        info!("PIN pairing request sent to device {}", device_id);
+        Ok(pin)
+    }
+
+    /// Get this device's pairing invite, to show as a QR code
+    ///
+    /// The invite names this device, the addresses it listens on and its
+    /// certificate, so a device that scans it can pair without comparing
+    /// fingerprints.
+    ///
+    /// # Returns
+    /// A `cconnect://pair/` URI
+    async fn get_pairing_uri(&self) -> Result<String, zbus::fdo::Error> {
+        let pairing_service = self.pairing_service.as_ref().ok_or_else(|| {
+            zbus::fdo::Error::Failed("Pairing service not initialized".to_string())
+        })?;
+
+        let mut device_info = self.connection_manager.read().await.device_info().clone();
+        device_info.addresses =
+            cosmic_ext_connect_protocol::pairing::invite_addresses(device_info.tcp_port)
+                .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
+        if device_info.addresses.is_empty() {
+            return Err(zbus::fdo::Error::Failed(
+                "No network address to put in a pairing invite".to_string(),
+            ));
+        }
+
+        let pairing_service = pairing_service.read().await;
+        Ok(cosmic_ext_connect_protocol::pairing::pairing_uri(
+            &device_info,
+            pairing_service.certificate(),
+        ))
+    }
+
+    /// Pair with the device behind a scanned pairing invite
+    ///
+    /// No confirmation is needed: the device is paired if it presents the
+    /// certificate named in the invite. The invite is dropped if the device
+    /// does not answer within the pairing timeout.
+    ///
+    /// # Arguments
+    /// * `uri` - The scanned `cconnect://pair/` URI
+    ///
+    /// # Returns
+    /// The ID of the invited device
+    async fn pair_with_invite(&self, uri: String) -> Result<String, zbus::fdo::Error> {
+        let invite = cosmic_ext_connect_protocol::pairing::parse_pairing_uri(&uri)
+            .map_err(|e| zbus::fdo::Error::InvalidArgs(e.to_string()))?;
+        let device_id = invite.device_info.device_id.clone();
+        info!("DBus: PairWithInvite called for {}", device_id);
+
+        let pairing_service = self
+            .pairing_service
+            .as_ref()
+            .ok_or_else(|| zbus::fdo::Error::Failed("Pairing service not initialized".to_string()))?
+            .clone();
+
+        self.tokio_handle
+            .spawn(async move {
+                let pairing_service = pairing_service.read().await;
+                pairing_service.pair_with_invite(invite).await
+            })
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Tokio task failed: {}", e)))?
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Failed to request pairing: {}", e)))?;
+
+        info!("Invited pairing request sent to device {}", device_id);
+        Ok(device_id)
+    }
+
+    /// Pair with all discovered devices on the local network at once
+    ///
+    /// Only unpaired devices on a directly attached subnet are asked. Each
+    /// request still needs the usual confirmation on the other device.
+    /// Returns once every device has answered or timed out.
+    ///
+    /// # Arguments
+    /// * `name_filter` - Only pair devices whose name contains this (empty for all)
+    ///
+    /// # Returns
+    /// Outcome per device
+    async fn batch_pair_devices(
+        &self,
+        name_filter: String,
+    ) -> Result<Vec<BatchPairEntry>, zbus::fdo::Error> {
+        info!("DBus: BatchPairDevices called (filter: {:?})", name_filter);
+
+        let pairing_service = self
+            .pairing_service
+            .as_ref()
+            .ok_or_else(|| zbus::fdo::Error::Failed("Pairing service not initialized".to_string()))?
+            .clone();
+
+        let candidates: Vec<_> = {
+            let device_manager = self.device_manager.read().await;
+            device_manager
+                .devices()
+                .filter(|device| !device.is_paired() && device.is_reachable())
+                .filter_map(|device| {
+                    let remote_addr = format!(
+                        "{}:{}",
+                        device.host.as_deref()?,
+                        device.port.unwrap_or(1816)
+                    )
+                    .parse()
+                    .ok()?;
+                    Some(cosmic_ext_connect_protocol::pairing::BatchPairCandidate {
+                        device_info: device.info.clone(),
+                        remote_addr,
+                    })
+                })
+                .collect()
+        };
+
+        let mut filter = cosmic_ext_connect_protocol::BatchPairFilter::default();
+        if !name_filter.is_empty() {
+            filter = filter.with_name(name_filter);
+        }
+
+        // Pairing waits on the Tokio runtime, not the zbus executor
+        let results = self
+            .tokio_handle
+            .spawn(async move {
+                let pairing_service = pairing_service.read().await;
+                cosmic_ext_connect_protocol::pairing::batch_pair(
+                    &*pairing_service,
+                    candidates,
+                    &filter,
+                    &cosmic_ext_connect_protocol::connection::SubnetGuard::system(),
+                )
+                .await
+            })
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Tokio task failed: {}", e)))?;
+
+        Ok(results.into_iter().map(BatchPairEntry::from).collect())
+    }
+
     /// Unpair a device
     ///
     /// # Arguments
@@ -516,11 +1248,33 @@ impl CConnectInterface {
         Ok(())
     }
 
-    /// Forget (dismiss) a device, removing it from the registry
+    /// Drop a paired device's pinned certificate
+    ///
+    /// For a device known to have been reinstalled: its next connection pins
+    /// the new certificate instead of being refused. The device stays paired.
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID whose certificate to unpin
+    ///
+    /// # Returns
+    /// Success or error message
+    async fn reset_device_trust(&self, device_id: String) -> Result<(), zbus::fdo::Error> {
+        info!("DBus: ResetDeviceTrust called for {}", device_id);
+
+        let mut device_manager = self.device_manager.write().await;
+        device_manager
+            .reset_trust(&device_id)
+            .and_then(|()| device_manager.save_registry())
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Failed to reset trust: {}", e)))?;
+        Ok(())
+    }
+
+    /// Forget (dismiss) a device, wiping all state associated with it
     ///
-    /// If the device is paired, it will be unpaired first. The device is then
-    /// removed from the device manager and the registry is saved to disk.
-    /// A DeviceRemoved signal is emitted so all clients update their UI.
+    /// Unlike unpairing, the device's pinned certificate is revoked and its
+    /// per-device configuration, plugin data and transfer history are deleted
+    /// before it is removed from the registry. A single DeviceRemoved signal is
+    /// emitted once everything is gone.
     ///
     /// # Arguments
     /// * `device_id` - The device ID to forget
@@ -530,34 +1284,49 @@ impl CConnectInterface {
     async fn forget_device(&self, device_id: String) -> Result<(), zbus::fdo::Error> {
         info!("DBus: ForgetDevice called for {}", device_id);
 
-        let mut device_manager = self.device_manager.write().await;
-
-        // Check if device exists
-        let device = device_manager
+        let is_paired = self
+            .device_manager
+            .read()
+            .await
             .get_device(&device_id)
-            .ok_or_else(|| zbus::fdo::Error::Failed(format!("Device not found: {}", device_id)))?;
-
-        // If device is paired, unpair first
-        if device.is_paired() {
-            drop(device_manager);
+            .ok_or_else(|| zbus::fdo::Error::Failed(format!("Device not found: {}", device_id)))?
+            .is_paired();
 
+        // Revoke trust first so the device cannot reconnect mid-wipe
+        if is_paired {
             if let Some(pairing_service) = &self.pairing_service {
-                let pairing_service = pairing_service.read().await;
-                pairing_service.unpair(&device_id).await.map_err(|e| {
-                    zbus::fdo::Error::Failed(format!("Failed to unpair device: {}", e))
-                })?;
+                pairing_service
+                    .read()
+                    .await
+                    .revoke(&device_id)
+                    .await
+                    .map_err(|e| {
+                        zbus::fdo::Error::Failed(format!("Failed to revoke pairing: {}", e))
+                    })?;
             }
-
-            device_manager = self.device_manager.write().await;
         }
 
-        // Remove the device
-        device_manager.remove_device(&device_id);
-        device_manager.save_registry().map_err(|e| {
-            zbus::fdo::Error::Failed(format!("Failed to save registry: {}", e))
-        })?;
+        // Drop the live connection and plugin state (in-memory share history etc.)
+        if let Err(e) = self.connection_manager.read().await.disconnect(&device_id).await {
+            debug!("Disconnect of forgotten device {} failed: {}", device_id, e);
+        }
+        if let Err(e) = self
+            .plugin_manager
+            .write()
+            .await
+            .cleanup_device_plugins(&device_id)
+            .await
+        {
+            warn!("Failed to stop plugins for forgotten device {}: {}", device_id, e);
+        }
 
-        drop(device_manager);
+        let forgotten = self
+            .device_manager
+            .write()
+            .await
+            .forget_device(&device_id)
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Failed to forget device: {}", e)))?;
 
         // Emit DeviceRemoved signal
         let object_server = self.dbus_connection.object_server();
@@ -572,10 +1341,111 @@ impl CConnectInterface {
             }
         }
 
-        info!("Device {} forgotten successfully", device_id);
+        info!(
+            "Device {} forgotten (purged: {})",
+            device_id,
+            forgotten.purged_stores.join(", ")
+        );
+        Ok(())
+    }
+
+    /// Move a previous identity's configuration to a reinstalled device
+    ///
+    /// Answers an IdentityMigrationOffered signal. Nickname, plugin overrides,
+    /// sync folders and other per-device settings of the previous identity
+    /// move to the new device, and the previous identity is forgotten.
+    ///
+    /// # Arguments
+    /// * `device_id` - The newly paired device
+    ///
+    /// # Returns
+    /// Success or error message
+    async fn accept_identity_migration(&self, device_id: String) -> Result<(), zbus::fdo::Error> {
+        info!("DBus: AcceptIdentityMigration called for {}", device_id);
+
+        let migrated = migrate_device_identity(
+            &self.device_manager,
+            self.pairing_service.as_ref(),
+            &self.connection_manager,
+            &device_id,
+        )
+        .await
+        .map_err(|e| zbus::fdo::Error::Failed(format!("Failed to migrate device: {:#}", e)))?;
+
+        let object_server = self.dbus_connection.object_server();
+        if let Ok(iface_ref) = object_server
+            .interface::<_, CConnectInterface>(OBJECT_PATH)
+            .await
+        {
+            if let Err(e) =
+                Self::device_removed(iface_ref.signal_emitter(), &migrated.previous_device_id).await
+            {
+                warn!("Failed to emit DeviceRemoved signal: {}", e);
+            }
+        }
         Ok(())
     }
 
+    /// Keep a reinstalled device separate from its previous identity
+    ///
+    /// # Arguments
+    /// * `device_id` - The newly paired device
+    ///
+    /// # Returns
+    /// Success or error message
+    async fn decline_identity_migration(&self, device_id: String) -> Result<(), zbus::fdo::Error> {
+        info!("DBus: DeclineIdentityMigration called for {}", device_id);
+
+        self.device_manager
+            .write()
+            .await
+            .decline_migration(&device_id)
+            .map(|_| ())
+            .ok_or_else(|| {
+                zbus::fdo::Error::Failed(format!("No identity migration offered for {}", device_id))
+            })
+    }
+
+    /// Receive a file the user was asked about
+    ///
+    /// # Arguments
+    /// * `prompt_id` - The prompt from the IncomingTransferPrompt signal
+    ///
+    /// # Returns
+    /// Success or error message
+    async fn accept_incoming_transfer(&self, prompt_id: String) -> Result<(), zbus::fdo::Error> {
+        info!("DBus: AcceptIncomingTransfer called for {}", prompt_id);
+
+        if self.transfer_prompts.accept(&prompt_id) {
+            Ok(())
+        } else {
+            Err(zbus::fdo::Error::Failed(format!(
+                "No incoming transfer waiting for prompt {}",
+                prompt_id
+            )))
+        }
+    }
+
+    /// Refuse a file the user was asked about
+    ///
+    /// # Arguments
+    /// * `prompt_id` - The prompt from the IncomingTransferPrompt signal
+    ///
+    /// # Returns
+    /// Success or error message
+    async fn decline_incoming_transfer(&self, prompt_id: String) -> Result<(), zbus::fdo::Error> {
+        info!("DBus: DeclineIncomingTransfer called for {}", prompt_id);
+
+        if self.transfer_prompts.decline(&prompt_id) {
+            Ok(())
+        } else {
+            Err(zbus::fdo::Error::Failed(format!(
+                "No incoming transfer waiting for prompt {}",
+                prompt_id
+            )))
+        }
+    }
+
     /// Accept a pairing request from a device
     ///
     /// # Arguments
@@ -602,6 +1472,37 @@ impl CConnectInterface {
         Ok(())
     }
 
+    /// Accept a pairing request with the PIN shown on the other device
+    ///
+    /// Only for requests announced with the PairingPinRequired signal.
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID to accept pairing from
+    /// * `pin` - The PIN the user entered
+    ///
+    /// # Returns
+    /// Success or error message
+    async fn accept_pairing_with_pin(
+        &self,
+        device_id: String,
+        pin: String,
+    ) -> Result<(), zbus::fdo::Error> {
+        info!("DBus: AcceptPairingWithPin called for {}", device_id);
+
+        let pairing_service = self.pairing_service.as_ref().ok_or_else(|| {
+            zbus::fdo::Error::Failed("Pairing service not initialized".to_string())
+        })?;
+
+        let pairing_service = pairing_service.read().await;
+        pairing_service
+            .accept_pairing_with_pin(&device_id, &pin)
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Failed to accept pairing: {}", e)))?;
+
+        info!("PIN sent for pairing with device {}", device_id);
+        Ok(())
+    }
+
     /// Reject a pairing request from a device
     ///
     /// # Arguments
@@ -697,7 +1598,69 @@ impl CConnectInterface {
             }
         });
 
-        Ok(())
+        Ok(())
+    }
+
+    /// Reconnect to a device immediately, skipping its reconnect backoff
+    ///
+    /// # Arguments
+    /// * `device_id` - The device to reconnect
+    ///
+    /// # Returns
+    /// * Success once connected; the backoff is kept if the attempt fails
+    async fn reconnect_now(&self, device_id: String) -> Result<(), zbus::fdo::Error> {
+        info!("DBus: ReconnectNow called for {}", device_id);
+
+        // Connecting may take a while; don't block other callers meanwhile
+        let connection_manager = self.connection_manager.read().await.clone();
+        connection_manager
+            .reconnect_now(&device_id)
+            .await
+            .map_err(|e| {
+                zbus::fdo::Error::Failed(format!("Failed to reconnect to {}: {}", device_id, e))
+            })
+    }
+
+    /// Connect to devices in the background so the next action is quick
+    ///
+    /// A hint sent by the applet when its popup opens. Devices that are
+    /// connected, unpaired or backing off are skipped, and nothing happens
+    /// while prewarming is disabled or the system is on battery.
+    ///
+    /// # Arguments
+    /// * `device_ids` - The devices likely to be used soon
+    async fn prewarm(&self, device_ids: Vec<String>) {
+        debug!("DBus: Prewarm called for {} devices", device_ids.len());
+
+        let connection_manager = self.connection_manager.clone();
+        self.tokio_handle.spawn(async move {
+            let attempted = connection_manager.read().await.prewarm(&device_ids).await;
+            debug!("Prewarm attempted {} connections", attempted.len());
+        });
+    }
+
+    /// Ask a connected device to re-send its capabilities
+    ///
+    /// # Arguments
+    /// * `device_id` - The device to re-sync with
+    ///
+    /// # Returns
+    /// * Success once the request is queued; `DeviceCapabilitiesChanged` is
+    ///   emitted when the answer differs from the stored capabilities
+    async fn refresh_capabilities(&self, device_id: String) -> Result<(), zbus::fdo::Error> {
+        info!("DBus: RefreshCapabilities called for {}", device_id);
+
+        self.connection_manager
+            .read()
+            .await
+            .refresh_capabilities(&device_id)
+            .await
+            .map_err(|e| {
+                zbus::fdo::Error::Failed(format!(
+                    "Failed to refresh capabilities of {}: {}",
+                    device_id, e
+                ))
+            })
     }
 
     /// Get device connection state
@@ -1253,6 +2216,115 @@ impl CConnectInterface {
         Ok(())
     }
 
+    /// Send a screenshot of this desktop to a device
+    ///
+    /// Captures the whole desktop through the Screenshot portal, encodes it as
+    /// PNG and sends it as a `cconnect.screenshot.data` payload.
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID to send to
+    async fn send_desktop_screenshot(&self, device_id: String) -> Result<(), zbus::fdo::Error> {
+        use crate::desktop_screenshot::{capture_png, PortalCaptureSource};
+
+        info!("DBus: SendDesktopScreenshot called for {}", device_id);
+
+        let device_manager = self.device_manager.read().await;
+        let device = device_manager
+            .get_device(&device_id)
+            .ok_or_else(|| zbus::fdo::Error::Failed(format!("Device not found: {}", device_id)))?;
+
+        if !device.is_connected() {
+            return Err(zbus::fdo::Error::Failed("Device not connected".to_string()));
+        }
+
+        drop(device_manager);
+
+        // Capture before returning so a denied or failed capture reaches the caller
+        let screenshot = self
+            .tokio_handle
+            .spawn(async move { capture_png(&PortalCaptureSource).await })
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Screenshot task failed: {}", e)))?
+            .map_err(|e| {
+                zbus::fdo::Error::Failed(format!("Failed to capture screenshot: {:#}", e))
+            })?;
+
+        let conn_manager = self.connection_manager.clone();
+        let device_id_clone = device_id.clone();
+        self.tokio_handle.spawn(async move {
+            use cosmic_ext_connect_protocol::plugins::screenshot::ScreenshotPlugin;
+            use cosmic_ext_connect_protocol::TlsPayloadServer;
+
+            // Random name, created 0700 and removed when dropped
+            let base = dirs::runtime_dir().unwrap_or_else(std::env::temp_dir);
+            let temp_dir = match tempfile::Builder::new()
+                .prefix("cconnect-screenshot-")
+                .tempdir_in(base)
+            {
+                Ok(dir) => dir,
+                Err(e) => {
+                    warn!("Failed to create screenshot directory: {}", e);
+                    return;
+                }
+            };
+            let path = temp_dir.path().join(&screenshot.filename);
+            if let Err(e) = write_private_file(&path, &screenshot.png).await {
+                warn!("Failed to write screenshot: {}", e);
+                return;
+            }
+
+            let (tls_config, traffic_counter) = {
+                let conn_mgr = conn_manager.read().await;
+                (
+                    conn_mgr.tls_config(),
+                    conn_mgr.traffic_counter(&device_id_clone).await,
+                )
+            };
+
+            let server = match TlsPayloadServer::new(tls_config).await {
+                Ok(s) => s.with_traffic_counter(traffic_counter),
+                Err(e) => {
+                    warn!("Failed to create TLS payload server: {}", e);
+                    return;
+                }
+            };
+
+            let packet = ScreenshotPlugin::create_screenshot_response(
+                &screenshot.filename,
+                screenshot.width,
+                screenshot.height,
+                screenshot.png.len() as u64,
+                server.port(),
+            );
+            let sent = conn_manager
+                .read()
+                .await
+                .send_packet(&device_id_clone, &packet)
+                .await;
+
+            match sent {
+                Ok(()) => match send_while_connected(
+                    &conn_manager,
+                    &device_id_clone,
+                    &screenshot.filename,
+                    server.send_file(path.clone()),
+                )
+                .await
+                {
+                    Ok(()) => info!("Desktop screenshot sent to {}", device_id_clone),
+                    Err(e) => warn!("Desktop screenshot transfer failed: {}", e),
+                },
+                Err(e) => warn!("Failed to send screenshot packet: {}", e),
+            }
+
+            if let Err(e) = temp_dir.close() {
+                debug!("Failed to clean up screenshot directory: {}", e);
+            }
+        });
+
+        Ok(())
+    }
+
     /// Share a file with a device
     ///
     /// # Arguments
@@ -1304,7 +2376,19 @@ impl CConnectInterface {
         let dbus_conn = self.dbus_connection.clone();
         let transfer_manager = self.transfer_manager.clone();
         let conn_manager = self.connection_manager.clone();
+        let resource_manager = self.resource_manager.clone();
         let tokio_handle = self.tokio_handle.clone();
+        let event_feed = self.event_feed.clone();
+        let error_history = self.error_history.clone();
+        let send_as = self.config.read().await.plugins.share_send_as.clone();
+        let verify_checksums = self.config.read().await.plugins.share_verify_checksums;
+        let send_options = ShareSendOptions::load(
+            self.pairing_service.as_ref(),
+            &self.config,
+            &self.connection_manager,
+            &device_id,
+        )
+        .await;
 
         // Spawn the entire file transfer operation on tokio runtime
         // This ensures all tokio operations have access to the runtime
@@ -1315,27 +2399,51 @@ impl CConnectInterface {
 
             // Extract file metadata (inside tokio runtime)
             let file_info = match FileTransferInfo::from_path(&file_path).await {
-                Ok(info) => info,
+                Ok(info) => match send_as {
+                    Some(user) => info.with_user(user),
+                    None => info,
+                },
                 Err(e) => {
                     warn!("Failed to read file metadata: {}", e);
                     return;
                 }
             };
 
+            // Let the receiver verify what arrives
+            let file_info = if verify_checksums {
+                match file_info.clone().with_checksum().await {
+                    Ok(info) => info,
+                    Err(e) => {
+                        warn!("Failed to hash '{}', sending unverified: {}", file_path, e);
+                        file_info
+                    }
+                }
+            } else {
+                file_info
+            };
+
             info!(
                 "DBus: Sharing file '{}' ({} bytes) to {}",
                 file_info.filename, file_info.size, device_id_clone
             );
 
-            // Get TLS config from connection manager
-            let tls_config = {
+            // Wait for the device's other transfers; held until this one ends
+            let _slot = resource_manager
+                .acquire_payload_slot(&device_id_clone)
+                .await;
+
+            // Get TLS config and the device's byte counter from connection manager
+            let (tls_config, traffic_counter) = {
                 let conn_mgr = conn_manager.read().await;
-                conn_mgr.tls_config()
+                (
+                    conn_mgr.tls_config(),
+                    conn_mgr.traffic_counter(&device_id_clone).await,
+                )
             };
 
             // Create TLS payload server on available port (inside tokio runtime)
             let server = match TlsPayloadServer::new(tls_config).await {
-                Ok(s) => s,
+                Ok(s) => s.with_traffic_counter(traffic_counter),
                 Err(e) => {
                     warn!("Failed to create TLS payload server: {}", e);
                     return;
@@ -1348,7 +2456,14 @@ impl CConnectInterface {
             // Create share packet with file info and payload transfer port
             let share_info: FileShareInfo = file_info.clone().into();
             let plugin = SharePlugin::new();
-            let packet = plugin.create_file_packet(share_info, port);
+            let mut packet = plugin.create_file_packet(share_info, port);
+            let server = match send_options.apply(server, &mut packet) {
+                Ok(server) => server,
+                Err(e) => {
+                    warn!("Not sharing '{}': {}", file_path, e);
+                    return;
+                }
+            };
 
             // Send packet via ConnectionManager
             let conn_mgr = conn_manager.read().await;
@@ -1364,6 +2479,16 @@ impl CConnectInterface {
             );
 
             let filename = file_info.filename.clone();
+            publish_device_event(
+                &dbus_conn,
+                &event_feed,
+                &device_id_clone,
+                DeviceEventKind::TransferStarted {
+                    filename: filename.clone(),
+                    incoming: false,
+                },
+            )
+            .await;
 
             // Create progress callback that emits DBus signals
             let conn = dbus_conn.clone();
@@ -1410,9 +2535,20 @@ impl CConnectInterface {
                     true // Continue transfer
                 });
 
-            // Attach progress callback and start transfer
+            // Attach progress callback (coalesced to limit signal churn) and start transfer
+            let progress_callback =
+                cosmic_ext_connect_protocol::ProgressThrottle::default().wrap(progress_callback);
             let server_with_progress = server.with_progress(progress_callback);
-            let result = server_with_progress.send_file(&file_path).await;
+            let result = send_file_within_budget(
+                &conn_manager,
+                &resource_manager,
+                &device_id_clone,
+                &filename,
+                server_with_progress,
+                file_path.clone(),
+                file_info.size,
+            )
+            .await;
 
             // Determine completion status
             let (success, error_msg) = if cancel_flag.load(Ordering::SeqCst) {
@@ -1428,6 +2564,17 @@ impl CConnectInterface {
                 )
             };
 
+            // A cancellation is the user's choice, not an error
+            if !success && !cancel_flag.load(Ordering::SeqCst) {
+                error_history
+                    .record(
+                        &device_id_clone,
+                        ErrorCategory::Transfer,
+                        &format!("Sending '{}' failed: {}", filename, error_msg),
+                    )
+                    .await;
+            }
+
             // Emit completion signal
             if let Ok(object_server) = dbus_conn
                 .object_server()
@@ -1445,6 +2592,18 @@ impl CConnectInterface {
                 .await;
             }
 
+            publish_device_event(
+                &dbus_conn,
+                &event_feed,
+                &device_id_clone,
+                DeviceEventKind::TransferFinished {
+                    filename: filename.clone(),
+                    incoming: false,
+                    success,
+                },
+            )
+            .await;
+
             // Remove transfer from manager
             transfer_manager.remove_transfer(&transfer_id_clone).await;
 
@@ -1461,11 +2620,398 @@ impl CConnectInterface {
             }
         });
 
-        info!(
-            "DBus: File sharing initiated for {} (transfer_id: {})",
-            device_id, transfer_id
-        );
-        Ok(())
+        info!(
+            "DBus: File sharing initiated for {} (transfer_id: {})",
+            device_id, transfer_id
+        );
+        Ok(())
+    }
+
+    /// Share several files with a device as one batch
+    ///
+    /// Files that cannot be sent are skipped and the rest are still sent.
+    /// A directory contributes every file below it; while directories are
+    /// searched `BatchPreparing` is emitted, and `CancelTransfer` with the
+    /// batch ID stops the batch before anything is sent. `BatchFileStarted`
+    /// and `BatchProgress` follow the transfer; when every file has been
+    /// tried, `BatchComplete` reports the result of each one.
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID to share with
+    /// * `paths` - Absolute paths of the files or directories
+    ///
+    /// # Returns
+    /// The batch ID used in the `BatchComplete` signal
+    async fn share_files(
+        &self,
+        device_id: String,
+        paths: Vec<String>,
+    ) -> Result<String, zbus::fdo::Error> {
+        info!(
+            "DBus: ShareFiles called for {} with {} files",
+            device_id,
+            paths.len()
+        );
+
+        if paths.is_empty() {
+            return Err(zbus::fdo::Error::InvalidArgs("No files to share".to_string()));
+        }
+
+        let device_manager = self.device_manager.read().await;
+        let device = device_manager
+            .get_device(&device_id)
+            .ok_or_else(|| zbus::fdo::Error::Failed(format!("Device not found: {}", device_id)))?;
+
+        if !device.is_connected() {
+            return Err(zbus::fdo::Error::Failed("Device not connected".to_string()));
+        }
+
+        drop(device_manager);
+
+        let timestamp_millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_else(|_| std::time::Duration::from_secs(0))
+            .as_millis();
+        let batch_id = format!("{}_batch_{}", device_id, timestamp_millis);
+
+        let sender = TlsBatchSender {
+            device_id: device_id.clone(),
+            connection_manager: self.connection_manager.clone(),
+            resource_manager: self.resource_manager.clone(),
+            options: ShareSendOptions::load(
+                self.pairing_service.as_ref(),
+                &self.config,
+                &self.connection_manager,
+                &device_id,
+            )
+            .await,
+        };
+        let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+        let dbus_conn = self.dbus_connection.clone();
+        let batch_id_clone = batch_id.clone();
+        let transfer_manager = self.transfer_manager.clone();
+        let cancel_flag = transfer_manager.register_transfer(batch_id.clone()).await;
+
+        // Spawn on tokio runtime - the zbus executor doesn't have one
+        self.tokio_handle.spawn(async move {
+            use cosmic_ext_connect_protocol::plugins::share_batch::{
+                prepare_directory, send_batch,
+            };
+            use cosmic_ext_connect_protocol::ProtocolError;
+
+            let (events_tx, events_rx) = tokio::sync::mpsc::unbounded_channel();
+            let forwarder = tokio::spawn(forward_batch_events(
+                dbus_conn.clone(),
+                sender.device_id.clone(),
+                events_rx,
+            ));
+
+            let mut files = Vec::with_capacity(paths.len());
+            for path in paths {
+                if path.is_dir() {
+                    match prepare_directory(&batch_id_clone, &path, Some(&events_tx), &cancel_flag)
+                        .await
+                    {
+                        Ok(found) => files.extend(found),
+                        Err(ProtocolError::Cancelled(_)) => break,
+                        Err(e) => {
+                            warn!("Cannot share directory {}: {}", path.display(), e);
+                            files.push(path);
+                        }
+                    }
+                } else {
+                    files.push(path);
+                }
+            }
+
+            if cancel_flag.load(Ordering::SeqCst) {
+                info!("Batch {} cancelled while preparing", batch_id_clone);
+                drop(events_tx);
+                let _ = forwarder.await;
+                transfer_manager.remove_transfer(&batch_id_clone).await;
+                return;
+            }
+
+            let report = send_batch(&batch_id_clone, &files, &sender, Some(&events_tx)).await;
+            drop(events_tx);
+            let _ = forwarder.await;
+            transfer_manager.remove_transfer(&batch_id_clone).await;
+            let results = serde_json::to_string(&report.results).unwrap_or_default();
+
+            if let Ok(object_server) = dbus_conn
+                .object_server()
+                .interface::<_, CConnectInterface>(OBJECT_PATH)
+                .await
+            {
+                let _ = CConnectInterface::batch_complete(
+                    object_server.signal_emitter(),
+                    &batch_id_clone,
+                    &sender.device_id,
+                    report.sent() as u32,
+                    report.failed() as u32,
+                    &results,
+                )
+                .await;
+            }
+        });
+
+        Ok(batch_id)
+    }
+
+    /// Share one file with several devices at once
+    ///
+    /// The file is read once and streamed to every device concurrently.
+    /// Each device gets its own `TransferProgress` and `TransferComplete`
+    /// signals, with transfer ID `<fan_out_id>_<device_id>`; `FanOutComplete`
+    /// then summarizes the result for all of them.
+    ///
+    /// # Arguments
+    /// * `device_ids` - The devices to share with
+    /// * `path` - Absolute path of the file
+    ///
+    /// # Returns
+    /// The fan-out ID used in the `FanOutComplete` signal
+    async fn share_file_to_devices(
+        &self,
+        device_ids: Vec<String>,
+        path: String,
+    ) -> Result<String, zbus::fdo::Error> {
+        info!(
+            "DBus: ShareFileToDevices called for {} devices with path '{}'",
+            device_ids.len(),
+            path
+        );
+
+        if device_ids.is_empty() {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "No devices to share with".to_string(),
+            ));
+        }
+        if !std::path::Path::new(&path).is_file() {
+            return Err(zbus::fdo::Error::Failed(format!(
+                "File not found: {}",
+                path
+            )));
+        }
+        {
+            let device_manager = self.device_manager.read().await;
+            for device_id in &device_ids {
+                let device = device_manager.get_device(device_id).ok_or_else(|| {
+                    zbus::fdo::Error::Failed(format!("Device not found: {}", device_id))
+                })?;
+                if !device.is_connected() {
+                    return Err(zbus::fdo::Error::Failed(format!(
+                        "Device not connected: {}",
+                        device_id
+                    )));
+                }
+            }
+        }
+
+        let timestamp_millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_else(|_| std::time::Duration::from_secs(0))
+            .as_millis();
+        let fan_out_id = format!("fanout_{}", timestamp_millis);
+
+        let fan_out_id_clone = fan_out_id.clone();
+        let dbus_conn = self.dbus_connection.clone();
+        let conn_manager = self.connection_manager.clone();
+        let transfer_manager = self.transfer_manager.clone();
+        let error_history = self.error_history.clone();
+        let tokio_handle = self.tokio_handle.clone();
+        let pairing_service = self.pairing_service.clone();
+        let config = self.config.clone();
+        let resource_manager = self.resource_manager.clone();
+
+        // Spawn on tokio runtime - the zbus executor doesn't have one
+        self.tokio_handle.spawn(async move {
+            use cosmic_ext_connect_protocol::plugins::share::SharePlugin;
+            use cosmic_ext_connect_protocol::{FileTransferInfo, ProgressThrottle, TlsPayloadServer};
+
+            // Wait for every device's other transfers, always in the same
+            // order so that two fan-outs never wait on each other
+            let mut slot_order = device_ids.clone();
+            slot_order.sort();
+            slot_order.dedup();
+            let mut slots = Vec::with_capacity(slot_order.len());
+            for device_id in &slot_order {
+                slots.push(resource_manager.acquire_payload_slot(device_id).await);
+            }
+
+            let file_info = match FileTransferInfo::from_path(&path).await {
+                Ok(info) => info,
+                Err(e) => {
+                    warn!("Failed to read file metadata: {}", e);
+                    return;
+                }
+            };
+            let filename = file_info.filename.clone();
+            let transfer_id = |device_id: &str| format!("{}_{}", fan_out_id_clone, device_id);
+
+            let mut servers = Vec::with_capacity(device_ids.len());
+            let mut not_started = Vec::new();
+            for device_id in device_ids {
+                let (tls_config, traffic_counter) = {
+                    let conn_mgr = conn_manager.read().await;
+                    (
+                        conn_mgr.tls_config(),
+                        conn_mgr.traffic_counter(&device_id).await,
+                    )
+                };
+                let server = match TlsPayloadServer::new(tls_config).await {
+                    Ok(server) => server,
+                    Err(e) => {
+                        not_started.push((device_id, e));
+                        continue;
+                    }
+                };
+
+                let tid = transfer_id(&device_id);
+                let cancel_flag = transfer_manager.register_transfer(tid.clone()).await;
+                let conn = dbus_conn.clone();
+                let did = device_id.clone();
+                let fname = filename.clone();
+                let handle = tokio_handle.clone();
+                let progress = Box::new(move |bytes_transferred: u64, total_bytes: u64| {
+                    if cancel_flag.load(Ordering::SeqCst) {
+                        info!("Transfer {} cancelled by user", tid);
+                        return false;
+                    }
+                    let (conn, tid, did, fname) =
+                        (conn.clone(), tid.clone(), did.clone(), fname.clone());
+                    handle.spawn(async move {
+                        if let Ok(object_server) = conn
+                            .object_server()
+                            .interface::<_, CConnectInterface>(OBJECT_PATH)
+                            .await
+                        {
+                            let _ = CConnectInterface::transfer_progress(
+                                object_server.signal_emitter(),
+                                &tid,
+                                &did,
+                                &fname,
+                                bytes_transferred,
+                                total_bytes,
+                                "sending",
+                            )
+                            .await;
+                        }
+                    });
+                    true
+                });
+                let server = server
+                    .with_traffic_counter(traffic_counter)
+                    .with_progress(ProgressThrottle::default().wrap(progress));
+
+                let mut packet =
+                    SharePlugin::new().create_file_packet(file_info.clone().into(), server.port());
+                // send_file_multi neither resumes nor splits files
+                let options = ShareSendOptions::load(
+                    pairing_service.as_ref(),
+                    &config,
+                    &conn_manager,
+                    &device_id,
+                )
+                .await
+                .for_fan_out();
+                let server = match options.apply(server, &mut packet) {
+                    Ok(server) => server,
+                    Err(e) => {
+                        not_started.push((device_id, e));
+                        continue;
+                    }
+                };
+                let sent = conn_manager
+                    .read()
+                    .await
+                    .send_packet(&device_id, &packet)
+                    .await;
+                match sent {
+                    Ok(()) => servers.push((device_id, server)),
+                    Err(e) => not_started.push((device_id, e)),
+                }
+            }
+
+            let mut summary = match TlsPayloadServer::send_file_multi(&path, servers).await {
+                Ok(summary) => summary,
+                Err(e) => {
+                    warn!("Fan-out of '{}' failed: {}", path, e);
+                    return;
+                }
+            };
+            summary.failed.extend(not_started);
+            drop(slots);
+
+            let Ok(object_server) = dbus_conn
+                .object_server()
+                .interface::<_, CConnectInterface>(OBJECT_PATH)
+                .await
+            else {
+                return;
+            };
+            let emitter = object_server.signal_emitter();
+            let mut results = Vec::new();
+            for device_id in &summary.delivered {
+                let tid = transfer_id(device_id);
+                transfer_manager.remove_transfer(&tid).await;
+                let _ = CConnectInterface::transfer_complete(
+                    emitter,
+                    &tid,
+                    device_id,
+                    &filename,
+                    true,
+                    "",
+                )
+                .await;
+                results.push(serde_json::json!({"deviceId": device_id, "status": "sent"}));
+            }
+            for (device_id, error) in &summary.failed {
+                let tid = transfer_id(device_id);
+                transfer_manager.remove_transfer(&tid).await;
+                let message = error.to_string();
+                error_history
+                    .record(
+                        device_id,
+                        ErrorCategory::Transfer,
+                        &format!("Sending '{}' failed: {}", filename, message),
+                    )
+                    .await;
+                let _ = CConnectInterface::transfer_complete(
+                    emitter,
+                    &tid,
+                    device_id,
+                    &filename,
+                    false,
+                    &message,
+                )
+                .await;
+                results.push(serde_json::json!({
+                    "deviceId": device_id,
+                    "status": "failed",
+                    "error": message,
+                }));
+            }
+
+            info!(
+                "Fan-out {} of '{}': {} delivered, {} failed",
+                fan_out_id_clone,
+                filename,
+                summary.delivered.len(),
+                summary.failed.len()
+            );
+            let _ = CConnectInterface::fan_out_complete(
+                emitter,
+                &fan_out_id_clone,
+                &filename,
+                summary.delivered.len() as u32,
+                summary.failed.len() as u32,
+                &serde_json::Value::Array(results).to_string(),
+            )
+            .await;
+        });
+
+        Ok(fan_out_id)
     }
 
     /// Share text or URL with a device
@@ -1512,9 +3058,18 @@ impl CConnectInterface {
     /// # Arguments
     /// * `device_id` - The device ID to share with
     /// * `url` - URL to share (will be opened in default browser on receiving device)
+    ///
+    /// Bare domains are normalized to `https://`; text that is not a URL is
+    /// rejected with `InvalidArgs`.
     async fn share_url(&self, device_id: String, url: String) -> Result<(), zbus::fdo::Error> {
         info!("DBus: ShareUrl called for {} with URL '{}'", device_id, url);
 
+        let url =
+            cosmic_ext_connect_protocol::plugins::share::ShareUrl::parse(&url).map_err(|e| {
+                warn!("Rejected share URL: {}", e);
+                zbus::fdo::Error::InvalidArgs(e.to_string())
+            })?;
+
         let device_manager = self.device_manager.read().await;
         let device = device_manager
             .get_device(&device_id)
@@ -1530,7 +3085,7 @@ impl CConnectInterface {
         use cosmic_ext_connect_protocol::Packet;
         use serde_json::json;
 
-        let packet = Packet::new("cconnect.share.request", json!({ "url": url }));
+        let packet = Packet::new("cconnect.share.request", json!({ "url": url.as_str() }));
 
         // Send packet via ConnectionManager
         let conn_manager = self.connection_manager.read().await;
@@ -1846,6 +3401,87 @@ impl CConnectInterface {
         })
     }
 
+    /// Get bytes exchanged with a device during the current connection
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID to query
+    ///
+    /// # Returns
+    /// Control and payload byte counters (all zero if nothing was exchanged yet)
+    async fn get_traffic_stats(&self, device_id: String) -> TrafficStats {
+        debug!("DBus: GetTrafficStats called for {}", device_id);
+
+        self.connection_manager
+            .read()
+            .await
+            .traffic_stats(&device_id)
+            .await
+            .map(TrafficStats::from)
+            .unwrap_or_default()
+    }
+
+    /// Get how a device is connected
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID to query
+    ///
+    /// # Returns
+    /// Protocol version, transport, round-trip time and certificate
+    /// fingerprint, or error if device not found
+    async fn get_connection_info(
+        &self,
+        device_id: String,
+    ) -> Result<ConnectionInfo, zbus::fdo::Error> {
+        debug!("DBus: GetConnectionInfo called for {}", device_id);
+
+        let (protocol_version, certificate_fingerprint) = {
+            let device_manager = self.device_manager.read().await;
+            let device = device_manager.get_device(&device_id).ok_or_else(|| {
+                zbus::fdo::Error::Failed(format!("Device not found: {}", device_id))
+            })?;
+            (
+                device.info.protocol_version,
+                device.certificate_fingerprint.clone().unwrap_or_default(),
+            )
+        };
+
+        // The daemon's connections are all TLS over TCP; Bluetooth links are
+        // handled by the transport manager and not visible here
+        let transport = if self
+            .connection_manager
+            .read()
+            .await
+            .has_connection(&device_id)
+            .await
+        {
+            "tcp".to_string()
+        } else {
+            String::new()
+        };
+
+        Ok(ConnectionInfo {
+            protocol_version,
+            transport,
+            // Keepalives are fire-and-forget, so there is nothing to time yet
+            rtt_ms: -1,
+            certificate_fingerprint,
+        })
+    }
+
+    /// Reset the byte counters for a device
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID to reset
+    async fn reset_traffic_stats(&self, device_id: String) {
+        info!("DBus: ResetTrafficStats called for {}", device_id);
+
+        self.connection_manager
+            .read()
+            .await
+            .reset_traffic_stats(&device_id)
+            .await;
+    }
+
     /// Request battery update from device
     ///
     /// Sends a battery request packet to the device to get fresh battery status.
@@ -2154,7 +3790,7 @@ impl CConnectInterface {
             device_id
         );
 
-        self.emit_plugin_state_changed(&device_id, &plugin_name, enabled)
+        self.apply_plugin_state(&device_id, &plugin_name, enabled)
             .await;
 
         Ok(())
@@ -2200,7 +3836,7 @@ impl CConnectInterface {
             if enabled { "enabled" } else { "disabled" }
         );
 
-        self.emit_plugin_state_changed(&device_id, &plugin_name, enabled)
+        self.apply_plugin_state(&device_id, &plugin_name, enabled)
             .await;
 
         Ok(())
@@ -2288,7 +3924,7 @@ impl CConnectInterface {
                 }
             };
 
-            self.emit_plugin_state_changed(&device_id, &plugin_name, enabled)
+            self.apply_plugin_state(&device_id, &plugin_name, enabled)
                 .await;
         }
 
@@ -2599,6 +4235,115 @@ impl CConnectInterface {
         Ok(())
     }
 
+    /// Schedule a run command on a remote device
+    ///
+    /// The command is sent when it comes due, or once the device connects if
+    /// it is offline at that time.
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID to execute the command on
+    /// * `command_id` - The command key/ID to execute
+    /// * `schedule` - `once YYYY-MM-DD HH:MM`, `daily HH:MM` or `every <n>m|h|d`
+    ///
+    /// # Returns
+    /// The first run time, as `YYYY-MM-DD HH:MM` local time
+    async fn schedule_command(
+        &self,
+        device_id: String,
+        command_id: String,
+        schedule: String,
+    ) -> Result<String, zbus::fdo::Error> {
+        info!(
+            "DBus: ScheduleCommand called for {} - Key: {}, Schedule: {}",
+            device_id, command_id, schedule
+        );
+
+        let schedule = schedule
+            .parse()
+            .map_err(|e| zbus::fdo::Error::InvalidArgs(format!("{}", e)))?;
+        let now = chrono::Local::now().naive_local();
+        let next_run = self
+            .scheduled_commands
+            .schedule(&device_id, &command_id, schedule, now)
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Failed to schedule command: {}", e)))?;
+
+        Ok(next_run.format("%Y-%m-%d %H:%M").to_string())
+    }
+
+    /// Remove the schedule of a run command
+    ///
+    /// # Returns
+    /// Whether the command was scheduled
+    async fn unschedule_command(
+        &self,
+        device_id: String,
+        command_id: String,
+    ) -> Result<bool, zbus::fdo::Error> {
+        info!(
+            "DBus: UnscheduleCommand called for {} - Key: {}",
+            device_id, command_id
+        );
+
+        self.scheduled_commands
+            .cancel(&device_id, &command_id)
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Failed to remove schedule: {}", e)))
+    }
+
+    /// Get the scheduled run commands of a device
+    ///
+    /// # Returns
+    /// JSON array of `{command_id, schedule, next_run}`, soonest first
+    async fn get_scheduled_commands(&self, device_id: String) -> Result<String, zbus::fdo::Error> {
+        debug!("DBus: GetScheduledCommands called for {}", device_id);
+
+        let commands = self.scheduled_commands.commands_for(&device_id).await;
+        serde_json::to_string(&commands)
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Failed to serialize schedule: {}", e)))
+    }
+
+    /// Get device events recorded after a sequence number
+    ///
+    /// Consumers follow the `DeviceEvent` signal and call this after
+    /// (re)connecting with the last sequence they saw (0 for everything
+    /// retained). If the first returned sequence is more than one past
+    /// `last_seen`, older events have already left the backlog.
+    ///
+    /// # Returns
+    /// JSON array of `{seq, timestamp, device_id, type, ...}`, oldest first
+    async fn get_device_events(&self, last_seen: u64) -> Result<String, zbus::fdo::Error> {
+        debug!("DBus: GetDeviceEvents called after {}", last_seen);
+
+        let events = self.event_feed.since(last_seen).await;
+        serde_json::to_string(&events)
+            .map_err(|e| zbus::fdo::Error::Failed(format!("Failed to serialize events: {}", e)))
+    }
+
+    /// Get the recent errors of a device
+    ///
+    /// Connection, transfer, pairing and plugin errors are kept per device
+    /// for the current daemon session, up to a fixed number each.
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID
+    ///
+    /// # Returns
+    /// JSON array of `{timestamp, category, message}`, newest first
+    async fn get_error_history(&self, device_id: String) -> Result<String, zbus::fdo::Error> {
+        debug!("DBus: GetErrorHistory called for {}", device_id);
+
+        let errors = self.error_history.recent(&device_id).await;
+        serde_json::to_string(&errors).map_err(|e| {
+            zbus::fdo::Error::Failed(format!("Failed to serialize error history: {}", e))
+        })
+    }
+
+    /// Sequence number of the newest device event (0 if none)
+    async fn get_latest_event_sequence(&self) -> u64 {
+        self.event_feed.latest_seq().await
+    }
+
     /// Start screen share session
     ///
     /// Configures the ScreenShare plugin with the local port to receive the stream.
@@ -2789,6 +4534,56 @@ impl CConnectInterface {
         }
     }
 
+    /// Start extended display streaming of a single window to a device
+    ///
+    /// Like `StartExtendedDisplay`, but the portal dialog offers application
+    /// windows instead of whole outputs.
+    async fn start_extended_display_window(
+        &self,
+        device_id: String,
+    ) -> Result<(), zbus::fdo::Error> {
+        info!("DBus: StartExtendedDisplayWindow called for {}", device_id);
+
+        let mut plugin_manager = self.plugin_manager.write().await;
+
+        if let Some(plugin) =
+            plugin_manager.get_device_plugin_mut(&device_id, "extendeddisplay")
+        {
+            use cosmic_ext_connect_protocol::plugins::extendeddisplay::{
+                CaptureSource, ExtendedDisplayPlugin,
+            };
+
+            if let Some(ed_plugin) =
+                plugin.as_any_mut().downcast_mut::<ExtendedDisplayPlugin>()
+            {
+                ed_plugin
+                    .start_session_with_source(
+                        &device_id,
+                        "h264,touch",
+                        None,
+                        CaptureSource::Window,
+                    )
+                    .await
+                    .map_err(|e| {
+                        zbus::fdo::Error::Failed(format!(
+                            "Failed to start extended display: {}",
+                            e
+                        ))
+                    })?;
+                info!("Extended display of a window started for device {}", device_id);
+                Ok(())
+            } else {
+                Err(zbus::fdo::Error::Failed(
+                    "Plugin is not ExtendedDisplayPlugin".to_string(),
+                ))
+            }
+        } else {
+            Err(zbus::fdo::Error::Failed(
+                "ExtendedDisplay plugin not found".to_string(),
+            ))
+        }
+    }
+
     /// Stop extended display streaming to a device
     async fn stop_extended_display(
         &self,
@@ -3248,7 +5043,9 @@ impl CConnectInterface {
     /// Set global plugin enabled state
     ///
     /// Enable or disable a plugin globally. This affects all devices unless
-    /// overridden per-device.
+    /// overridden per-device. A loaded plugin is switched right away and
+    /// connected devices are told about the changed capabilities; enabling a
+    /// plugin that was not loaded at startup takes a restart.
     ///
     /// # Arguments
     /// * `plugin` - Plugin name
@@ -3291,11 +5088,26 @@ impl CConnectInterface {
             .save()
             .map_err(|e| zbus::fdo::Error::Failed(format!("Failed to save config: {}", e)))?;
 
+        drop(config);
+
         info!(
             "DBus: Plugin {} {} globally",
             plugin,
             if enabled { "enabled" } else { "disabled" }
         );
+
+        let switched = self
+            .plugin_manager
+            .read()
+            .await
+            .set_enabled(&plugin, enabled);
+        match switched {
+            Ok(()) => self.advertise_capabilities().await,
+            Err(_) => info!(
+                "DBus: Plugin {} is not loaded, change takes effect after restart",
+                plugin
+            ),
+        }
         Ok(())
     }
 
@@ -3783,76 +5595,201 @@ impl CConnectInterface {
                 zbus::fdo::Error::Failed("Failed to downcast to CameraPlugin".to_string())
             })?;
 
-        let is_streaming = camera.is_streaming().await;
-        if let Some(session) = camera.get_session().await {
-            let status = serde_json::json!({
-                "streaming": is_streaming,
-                "cameraId": session.camera_id,
-                "resolution": session.resolution,
-                "fps": session.fps,
-                "quality": session.quality.as_str(),
-                "frameNumber": session.frame_number,
-                "startedAt": session.started_at,
-            });
-            serde_json::to_string(&status).map_err(|e| {
-                zbus::fdo::Error::Failed(format!("Failed to serialize camera status: {}", e))
-            })
-        } else {
-            // Return stopped status if no active session
-            Ok(r#"{"streaming":false}"#.to_string())
-        }
-    }
+        let is_streaming = camera.is_streaming().await;
+        if let Some(session) = camera.get_session().await {
+            let status = serde_json::json!({
+                "streaming": is_streaming,
+                "cameraId": session.camera_id,
+                "resolution": session.resolution,
+                "fps": session.fps,
+                "quality": session.quality.as_str(),
+                "frameNumber": session.frame_number,
+                "startedAt": session.started_at,
+            });
+            serde_json::to_string(&status).map_err(|e| {
+                zbus::fdo::Error::Failed(format!("Failed to serialize camera status: {}", e))
+            })
+        } else {
+            // Return stopped status if no active session
+            Ok(r#"{"streaming":false}"#.to_string())
+        }
+    }
+
+    /// Signal: Device was added (discovered)
+    ///
+    /// Emitted when a new device is discovered on the network.
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID
+    /// * `device_info` - Device information
+    #[zbus(signal)]
+    async fn device_added(
+        signal_emitter: &SignalEmitter<'_>,
+        device_id: &str,
+        device_info: DeviceInfo,
+    ) -> zbus::Result<()>;
+
+    /// Signal: Device was removed (disappeared)
+    ///
+    /// Emitted when a device is no longer reachable on the network.
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID
+    #[zbus(signal)]
+    async fn device_removed(
+        signal_emitter: &SignalEmitter<'_>,
+        device_id: &str,
+    ) -> zbus::Result<()>;
+
+    /// Signal: Device state changed
+    ///
+    /// Emitted when a device's connection state changes (connected, paired, etc).
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID
+    /// * `state` - New state: "connected", "paired", "reachable", or "unknown"
+    #[zbus(signal)]
+    async fn device_state_changed(
+        signal_emitter: &SignalEmitter<'_>,
+        device_id: &str,
+        state: &str,
+    ) -> zbus::Result<()>;
+
+    /// Signal: Device capabilities changed
+    ///
+    /// Emitted when a connected device re-advertises its capabilities, e.g.
+    /// after a plugin was enabled on it, so clients can update their actions.
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID
+    /// * `incoming_capabilities` - Packet types the device now accepts
+    /// * `outgoing_capabilities` - Packet types the device now sends
+    #[zbus(signal)]
+    async fn device_capabilities_changed(
+        signal_emitter: &SignalEmitter<'_>,
+        device_id: &str,
+        incoming_capabilities: &[String],
+        outgoing_capabilities: &[String],
+    ) -> zbus::Result<()>;
+
+    /// Signal: A device event was recorded in the event feed
+    ///
+    /// # Arguments
+    /// * `seq` - Sequence number of the event
+    /// * `event` - The event as JSON (see `GetDeviceEvents`)
+    #[zbus(signal)]
+    async fn device_event(
+        signal_emitter: &SignalEmitter<'_>,
+        seq: u64,
+        event: &str,
+    ) -> zbus::Result<()>;
+
+    /// Signal: Traffic counters updated
+    ///
+    /// Emitted periodically while a device is connected and once with the
+    /// session totals when it disconnects.
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID
+    /// * `stats` - Cumulative byte counters for the session
+    /// * `session_ended` - Whether the connection has closed
+    #[zbus(signal)]
+    async fn traffic_stats_changed(
+        signal_emitter: &SignalEmitter<'_>,
+        device_id: &str,
+        stats: TrafficStats,
+        session_ended: bool,
+    ) -> zbus::Result<()>;
+
+    /// Signal: Pairing request received
+    ///
+    /// Emitted when a device requests to pair with us.
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID requesting pairing
+    #[zbus(signal)]
+    async fn pairing_request(
+        signal_emitter: &SignalEmitter<'_>,
+        device_id: &str,
+    ) -> zbus::Result<()>;
+
+    /// Signal: PIN generated for our pairing request
+    ///
+    /// Show it to the user, who enters it on the other device.
+    ///
+    /// # Arguments
+    /// * `device_id` - The device we asked to pair with
+    /// * `pin` - The PIN to display
+    #[zbus(signal)]
+    async fn pairing_pin(
+        signal_emitter: &SignalEmitter<'_>,
+        device_id: &str,
+        pin: &str,
+    ) -> zbus::Result<()>;
 
-    /// Signal: Device was added (discovered)
+    /// Signal: A pairing request must be confirmed with a PIN
     ///
-    /// Emitted when a new device is discovered on the network.
+    /// Answer with AcceptPairingWithPin using the PIN shown on the other
+    /// device, or RejectPairing.
     ///
     /// # Arguments
-    /// * `device_id` - The device ID
-    /// * `device_info` - Device information
+    /// * `device_id` - The device requesting pairing
     #[zbus(signal)]
-    async fn device_added(
+    async fn pairing_pin_required(
         signal_emitter: &SignalEmitter<'_>,
         device_id: &str,
-        device_info: DeviceInfo,
     ) -> zbus::Result<()>;
 
-    /// Signal: Device was removed (disappeared)
+    /// Signal: A newly paired device looks like a reinstall of a known one
     ///
-    /// Emitted when a device is no longer reachable on the network.
+    /// Answer with AcceptIdentityMigration or DeclineIdentityMigration.
     ///
     /// # Arguments
-    /// * `device_id` - The device ID
+    /// * `device_id` - The newly paired device
+    /// * `previous_device_id` - The known device it may replace
+    /// * `device_name` - Name both identities share
     #[zbus(signal)]
-    async fn device_removed(
+    async fn identity_migration_offered(
         signal_emitter: &SignalEmitter<'_>,
         device_id: &str,
+        previous_device_id: &str,
+        device_name: &str,
     ) -> zbus::Result<()>;
 
-    /// Signal: Device state changed
+    /// Signal: A second device presented a known device ID
     ///
-    /// Emitted when a device's connection state changes (connected, paired, etc).
+    /// The newcomer has a different certificate, e.g. a phone restored from
+    /// another phone's backup, and is kept as a separate device.
     ///
     /// # Arguments
-    /// * `device_id` - The device ID
-    /// * `state` - New state: "connected", "paired", "reachable", or "unknown"
+    /// * `device_id` - The ID both devices advertise
+    /// * `alias_id` - The ID the newcomer is listed under
+    /// * `device_name` - Name shown for the newcomer
     #[zbus(signal)]
-    async fn device_state_changed(
+    async fn device_id_conflict(
         signal_emitter: &SignalEmitter<'_>,
         device_id: &str,
-        state: &str,
+        alias_id: &str,
+        device_name: &str,
     ) -> zbus::Result<()>;
 
-    /// Signal: Pairing request received
+    /// Signal: A device wants to send a file and awaits the user's answer
     ///
-    /// Emitted when a device requests to pair with us.
+    /// Answer with AcceptIncomingTransfer or DeclineIncomingTransfer; without
+    /// an answer the transfer is declined once the prompt times out.
     ///
     /// # Arguments
-    /// * `device_id` - The device ID requesting pairing
+    /// * `prompt_id` - ID to answer the prompt with
+    /// * `device_id` - The sending device
+    /// * `filename` - Name the file would be saved under
+    /// * `size` - File size in bytes
     #[zbus(signal)]
-    async fn pairing_request(
+    async fn incoming_transfer_prompt(
         signal_emitter: &SignalEmitter<'_>,
+        prompt_id: &str,
         device_id: &str,
+        filename: &str,
+        size: u64,
     ) -> zbus::Result<()>;
 
     /// Signal: Pairing status changed
@@ -3861,7 +5798,7 @@ impl CConnectInterface {
     ///
     /// # Arguments
     /// * `device_id` - The device ID
-    /// * `status` - Status: "paired", "rejected", or "failed"
+    /// * `status` - Status: "paired", "rejected", "expired", or "failed"
     /// Signal: Messaging notification received
     ///
     /// Emitted when a messaging app notification arrives.
@@ -3881,6 +5818,21 @@ impl CConnectInterface {
         status: &str,
     ) -> zbus::Result<()>;
 
+    /// Signal: Pairing rejected
+    ///
+    /// Emitted alongside the "rejected" pairing status, saying why pairing
+    /// failed.
+    ///
+    /// # Arguments
+    /// * `device_id` - The device ID
+    /// * `reason` - Why pairing failed, e.g. "declined" or "wrong PIN"
+    #[zbus(signal)]
+    async fn pairing_rejected(
+        signal_emitter: &SignalEmitter<'_>,
+        device_id: &str,
+        reason: &str,
+    ) -> zbus::Result<()>;
+
     /// Signal: Plugin event
     ///
     /// Emitted when a plugin receives data or has something to notify about.
@@ -3956,6 +5908,98 @@ impl CConnectInterface {
         error_message: &str,
     ) -> zbus::Result<()>;
 
+    /// Signal: A `ShareFiles` batch is searching directories for files
+    ///
+    /// Nothing has been sent yet; the total is not known until the search
+    /// ends and `BatchFileStarted` follows.
+    ///
+    /// # Arguments
+    /// * `batch_id` - ID returned by `ShareFiles`
+    /// * `device_id` - The device ID
+    /// * `files_found` - Files found so far
+    #[zbus(signal)]
+    async fn batch_preparing(
+        signal_emitter: &SignalEmitter<'_>,
+        batch_id: &str,
+        device_id: &str,
+        files_found: u32,
+    ) -> zbus::Result<()>;
+
+    /// Signal: A file of a `ShareFiles` batch started transferring
+    ///
+    /// # Arguments
+    /// * `batch_id` - ID returned by `ShareFiles`
+    /// * `device_id` - The device ID
+    /// * `filename` - Name of the file
+    /// * `index` - Zero-based position of the file in the batch
+    /// * `total_files` - Number of files in the batch
+    #[zbus(signal)]
+    async fn batch_file_started(
+        signal_emitter: &SignalEmitter<'_>,
+        batch_id: &str,
+        device_id: &str,
+        filename: &str,
+        index: u32,
+        total_files: u32,
+    ) -> zbus::Result<()>;
+
+    /// Signal: Bytes sent so far across a `ShareFiles` batch
+    ///
+    /// # Arguments
+    /// * `batch_id` - ID returned by `ShareFiles`
+    /// * `device_id` - The device ID
+    /// * `bytes_sent` - Bytes sent so far
+    /// * `total_bytes` - Combined size of the batch's readable files
+    #[zbus(signal)]
+    async fn batch_progress(
+        signal_emitter: &SignalEmitter<'_>,
+        batch_id: &str,
+        device_id: &str,
+        bytes_sent: u64,
+        total_bytes: u64,
+    ) -> zbus::Result<()>;
+
+    /// Signal: Multi-file batch finished
+    ///
+    /// Emitted once every file of a `ShareFiles` batch has been tried.
+    ///
+    /// # Arguments
+    /// * `batch_id` - ID returned by `ShareFiles`
+    /// * `device_id` - The device ID
+    /// * `sent` - Number of files sent
+    /// * `failed` - Number of files that failed
+    /// * `results` - JSON array with `path`, `status` and `bytes` or `error` per file
+    #[zbus(signal)]
+    async fn batch_complete(
+        signal_emitter: &SignalEmitter<'_>,
+        batch_id: &str,
+        device_id: &str,
+        sent: u32,
+        failed: u32,
+        results: &str,
+    ) -> zbus::Result<()>;
+
+    /// Signal: File shared with several devices finished
+    ///
+    /// Emitted once every device of a `ShareFileToDevices` call has either
+    /// received the file or failed.
+    ///
+    /// # Arguments
+    /// * `fan_out_id` - ID returned by `ShareFileToDevices`
+    /// * `filename` - Name of the file
+    /// * `delivered` - Number of devices that received the file
+    /// * `failed` - Number of devices that did not
+    /// * `results` - JSON array with `deviceId`, `status` and `error` per device
+    #[zbus(signal)]
+    async fn fan_out_complete(
+        signal_emitter: &SignalEmitter<'_>,
+        fan_out_id: &str,
+        filename: &str,
+        delivered: u32,
+        failed: u32,
+        results: &str,
+    ) -> zbus::Result<()>;
+
     /// Signal: Screen share requested
     ///
     /// Emitted when a remote device requests to share its screen with us (incoming).
@@ -4106,10 +6150,43 @@ impl CConnectInterface {
     ) -> zbus::Result<()>;
 }
 
+/// Append an event to the feed and emit the DeviceEvent signal for it
+async fn publish_device_event(
+    connection: &Connection,
+    feed: &DeviceEventFeed,
+    device_id: &str,
+    kind: DeviceEventKind,
+) {
+    let event = feed.record(device_id, kind).await;
+    let json = match serde_json::to_string(&event) {
+        Ok(json) => json,
+        Err(e) => {
+            warn!("Failed to serialize device event: {}", e);
+            return;
+        }
+    };
+    match connection
+        .object_server()
+        .interface::<_, CConnectInterface>(OBJECT_PATH)
+        .await
+    {
+        Ok(iface_ref) => {
+            if let Err(e) =
+                CConnectInterface::device_event(iface_ref.signal_emitter(), event.seq, &json).await
+            {
+                warn!("Failed to emit DeviceEvent signal: {}", e);
+            }
+        }
+        Err(e) => warn!("Failed to get interface for DeviceEvent signal: {}", e),
+    }
+}
+
 /// DBus server for the daemon
 pub struct DbusServer {
     /// DBus connection
     connection: Connection,
+    /// Sequenced device events for external integrations
+    event_feed: Arc<DeviceEventFeed>,
 }
 
 impl DbusServer {
@@ -4137,6 +6214,9 @@ impl DbusServer {
         pending_pairing_requests: Arc<RwLock<std::collections::HashMap<String, bool>>>,
         metrics: Option<Arc<RwLock<crate::diagnostics::Metrics>>>,
         config: Arc<RwLock<crate::config::Config>>,
+        transfer_prompts: PendingTransferPrompts,
+        error_history: Arc<ErrorHistory>,
+        resource_manager: Arc<ResourceManager>,
     ) -> Result<Self> {
         info!("Starting DBus server on {}", SERVICE_NAME);
 
@@ -4146,9 +6226,28 @@ impl DbusServer {
             .await
             .context("Failed to build DBus connection")?;
 
-        // Clone device_manager and connection_manager for the Open interface before moving to CConnectInterface
+        // Clone what the Open interface needs before moving to CConnectInterface
         let device_manager_for_open = device_manager.clone();
         let connection_manager_for_open = connection_manager.clone();
+        let pairing_service_for_open = pairing_service.clone();
+        let config_for_open = config.clone();
+        let resource_manager_for_open = resource_manager.clone();
+
+        let schedule_path = config
+            .read()
+            .await
+            .paths
+            .data_dir
+            .join("scheduled_commands.json");
+        let scheduled_commands = Arc::new(crate::command_schedule::ScheduledCommands::load(
+            schedule_path,
+        ));
+        scheduled_commands
+            .clone()
+            .spawn_runner(device_manager.clone(), connection_manager.clone());
+
+        let feed_path = config.read().await.paths.data_dir.join("event_feed.json");
+        let event_feed = Arc::new(DeviceEventFeed::load(feed_path, FEED_CAPACITY));
 
         // Create interface with connection reference
         // We pass the current Tokio handle so that zbus handlers can spawn tasks on the tokio runtime
@@ -4163,6 +6262,11 @@ impl DbusServer {
             connection.clone(),
             metrics,
             config,
+            scheduled_commands,
+            event_feed.clone(),
+            transfer_prompts,
+            error_history,
+            resource_manager,
             Handle::current(),
         );
 
@@ -4174,8 +6278,13 @@ impl DbusServer {
             .context("Failed to serve interface")?;
 
         // Register the Open interface on a separate path
-        let open_interface =
-            OpenInterface::new(device_manager_for_open, connection_manager_for_open);
+        let open_interface = OpenInterface::new(
+            device_manager_for_open,
+            connection_manager_for_open,
+            pairing_service_for_open,
+            config_for_open,
+            resource_manager_for_open,
+        );
         connection
             .object_server()
             .at("/io/github/olafkfreund/CosmicExtConnect/Open", open_interface)
@@ -4192,7 +6301,10 @@ impl DbusServer {
 
         info!("DBus server started successfully");
 
-        Ok(Self { connection })
+        Ok(Self {
+            connection,
+            event_feed,
+        })
     }
 
     /// Get the DBus connection
@@ -4256,6 +6368,59 @@ impl DbusServer {
         Ok(())
     }
 
+    /// Emit a device_capabilities_changed signal
+    pub async fn emit_device_capabilities_changed(
+        &self,
+        device_id: &str,
+        incoming_capabilities: &[String],
+        outgoing_capabilities: &[String],
+    ) -> Result<()> {
+        let object_server = self.connection.object_server();
+        let iface_ref = object_server
+            .interface::<_, CConnectInterface>(OBJECT_PATH)
+            .await?;
+
+        CConnectInterface::device_capabilities_changed(
+            iface_ref.signal_emitter(),
+            device_id,
+            incoming_capabilities,
+            outgoing_capabilities,
+        )
+        .await?;
+
+        debug!("Emitted DeviceCapabilitiesChanged signal for {}", device_id);
+        Ok(())
+    }
+
+    /// Record a device event in the event feed and signal it
+    pub async fn record_device_event(&self, device_id: &str, kind: DeviceEventKind) {
+        publish_device_event(&self.connection, &self.event_feed, device_id, kind).await;
+    }
+
+    /// Emit a traffic_stats_changed signal
+    pub async fn emit_traffic_stats_changed(
+        &self,
+        device_id: &str,
+        stats: TrafficStats,
+        session_ended: bool,
+    ) -> Result<()> {
+        let object_server = self.connection.object_server();
+        let iface_ref = object_server
+            .interface::<_, CConnectInterface>(OBJECT_PATH)
+            .await?;
+
+        CConnectInterface::traffic_stats_changed(
+            iface_ref.signal_emitter(),
+            device_id,
+            stats,
+            session_ended,
+        )
+        .await?;
+
+        debug!("Emitted TrafficStatsChanged signal for {}", device_id);
+        Ok(())
+    }
+
     /// Emit a pairing_request signal
     pub async fn emit_pairing_request(&self, device_id: &str) -> Result<()> {
         let object_server = self.connection.object_server();
@@ -4269,6 +6434,111 @@ impl DbusServer {
         Ok(())
     }
 
+    /// Emit a pairing_pin signal
+    pub async fn emit_pairing_pin(&self, device_id: &str, pin: &str) -> Result<()> {
+        let object_server = self.connection.object_server();
+        let iface_ref = object_server
+            .interface::<_, CConnectInterface>(OBJECT_PATH)
+            .await?;
+
+        CConnectInterface::pairing_pin(iface_ref.signal_emitter(), device_id, pin).await?;
+
+        debug!("Emitted PairingPin signal for {}", device_id);
+        Ok(())
+    }
+
+    /// Emit a pairing_pin_required signal
+    pub async fn emit_pairing_pin_required(&self, device_id: &str) -> Result<()> {
+        let object_server = self.connection.object_server();
+        let iface_ref = object_server
+            .interface::<_, CConnectInterface>(OBJECT_PATH)
+            .await?;
+
+        CConnectInterface::pairing_pin_required(iface_ref.signal_emitter(), device_id).await?;
+
+        debug!("Emitted PairingPinRequired signal for {}", device_id);
+        Ok(())
+    }
+
+    /// Emit an incoming_transfer_prompt signal
+    pub async fn emit_incoming_transfer_prompt(
+        &self,
+        prompt_id: &str,
+        device_id: &str,
+        filename: &str,
+        size: u64,
+    ) -> Result<()> {
+        let object_server = self.connection.object_server();
+        let iface_ref = object_server
+            .interface::<_, CConnectInterface>(OBJECT_PATH)
+            .await?;
+
+        CConnectInterface::incoming_transfer_prompt(
+            iface_ref.signal_emitter(),
+            prompt_id,
+            device_id,
+            filename,
+            size,
+        )
+        .await?;
+
+        debug!(
+            "Emitted IncomingTransferPrompt signal for '{}' from {}",
+            filename, device_id
+        );
+        Ok(())
+    }
+
+    /// Emit an identity_migration_offered signal
+    pub async fn emit_identity_migration_offered(
+        &self,
+        offer: &cosmic_ext_connect_protocol::MigrationOffer,
+    ) -> Result<()> {
+        let object_server = self.connection.object_server();
+        let iface_ref = object_server
+            .interface::<_, CConnectInterface>(OBJECT_PATH)
+            .await?;
+
+        CConnectInterface::identity_migration_offered(
+            iface_ref.signal_emitter(),
+            &offer.device_id,
+            &offer.previous_device_id,
+            &offer.device_name,
+        )
+        .await?;
+
+        debug!(
+            "Emitted IdentityMigrationOffered signal for {}",
+            offer.device_id
+        );
+        Ok(())
+    }
+
+    /// Emit a device_id_conflict signal
+    pub async fn emit_device_id_conflict(
+        &self,
+        conflict: &cosmic_ext_connect_protocol::DeviceIdConflict,
+    ) -> Result<()> {
+        let object_server = self.connection.object_server();
+        let iface_ref = object_server
+            .interface::<_, CConnectInterface>(OBJECT_PATH)
+            .await?;
+
+        CConnectInterface::device_id_conflict(
+            iface_ref.signal_emitter(),
+            &conflict.device_id,
+            &conflict.alias_id,
+            &conflict.device_name,
+        )
+        .await?;
+
+        debug!(
+            "Emitted DeviceIdConflict signal for {} as {}",
+            conflict.device_id, conflict.alias_id
+        );
+        Ok(())
+    }
+
     /// Emit a messaging_notification signal
     pub async fn emit_messaging_notification(
         &self,
@@ -4312,6 +6582,22 @@ impl DbusServer {
         Ok(())
     }
 
+    /// Emit a pairing_rejected signal
+    pub async fn emit_pairing_rejected(&self, device_id: &str, reason: &str) -> Result<()> {
+        let object_server = self.connection.object_server();
+        let iface_ref = object_server
+            .interface::<_, CConnectInterface>(OBJECT_PATH)
+            .await?;
+
+        CConnectInterface::pairing_rejected(iface_ref.signal_emitter(), device_id, reason).await?;
+
+        debug!(
+            "Emitted PairingRejected signal for {} ({})",
+            device_id, reason
+        );
+        Ok(())
+    }
+
     /// Emit a plugin_event signal
     #[allow(dead_code)]
     pub async fn emit_plugin_event(&self, device_id: &str, plugin: &str, data: &str) -> Result<()> {
@@ -4652,6 +6938,12 @@ pub struct OpenInterface {
     device_manager: Arc<RwLock<DeviceManager>>,
     /// Connection manager for sending packets
     connection_manager: Arc<RwLock<ConnectionManager>>,
+    /// Pairing service, for payload keys
+    pairing_service: Option<Arc<RwLock<cosmic_ext_connect_protocol::pairing::PairingService>>>,
+    /// Daemon configuration
+    config: Arc<RwLock<crate::config::Config>>,
+    /// Per-device transfer queues
+    resource_manager: Arc<ResourceManager>,
 }
 
 impl OpenInterface {
@@ -4659,10 +6951,16 @@ impl OpenInterface {
     pub fn new(
         device_manager: Arc<RwLock<DeviceManager>>,
         connection_manager: Arc<RwLock<ConnectionManager>>,
+        pairing_service: Option<Arc<RwLock<cosmic_ext_connect_protocol::pairing::PairingService>>>,
+        config: Arc<RwLock<crate::config::Config>>,
+        resource_manager: Arc<ResourceManager>,
     ) -> Self {
         Self {
             device_manager,
             connection_manager,
+            pairing_service,
+            config,
+            resource_manager,
         }
     }
 
@@ -4712,13 +7010,10 @@ impl OpenInterface {
     }
 
     /// Create an "open URL" packet using the share plugin format
-    fn create_open_url_packet(url: String) -> cosmic_ext_connect_protocol::Packet {
-        cosmic_ext_connect_protocol::Packet::new(
-            "cconnect.share.request",
-            serde_json::json!({
-                "url": url
-            }),
-        )
+    fn create_open_url_packet(
+        target: &cosmic_ext_connect_protocol::plugins::share::OpenTarget,
+    ) -> cosmic_ext_connect_protocol::Packet {
+        cosmic_ext_connect_protocol::plugins::share::SharePlugin::new().create_open_packet(target)
     }
 }
 
@@ -4728,7 +7023,8 @@ impl OpenInterface {
     ///
     /// # Arguments
     ///
-    /// * `url` - The URL to open (must have an allowed scheme)
+    /// * `url` - The URL to open (must have an allowed scheme). Bare domains are
+    ///   opened as `https://`; the packet carries a `urlType` hint.
     ///
     /// # Returns
     ///
@@ -4737,16 +7033,23 @@ impl OpenInterface {
     /// # Errors
     ///
     /// Returns error if:
-    /// - URL scheme is not allowed
+    /// - URL is malformed or its scheme is not allowed
     /// - No suitable device is available
     /// - Device is not connected
     /// - Packet sending fails
     async fn open_on_phone(&self, url: String) -> zbus::fdo::Result<String> {
         info!("Open on phone request for URL: {}", url);
 
+        // Classify and normalize the input before sending
+        let target =
+            cosmic_ext_connect_protocol::plugins::share::OpenTarget::parse(&url).map_err(|e| {
+                error!("Rejected open request: {}", e);
+                zbus::fdo::Error::InvalidArgs(e.to_string())
+            })?;
+
         // Validate URL scheme
-        if !Self::is_scheme_allowed(&url) {
-            error!("URL scheme not allowed: {}", url);
+        if !Self::is_scheme_allowed(&target.url) {
+            error!("URL scheme not allowed: {}", target.url);
             return Err(zbus::fdo::Error::Failed(
                 "URL scheme not allowed. Allowed schemes: http, https, ftp, ftps, mailto, tel, sms, geo, file".to_string()
             ));
@@ -4757,7 +7060,7 @@ impl OpenInterface {
         debug!("Opening URL on device: {}", device.name());
 
         // Create and send packet
-        let packet = Self::create_open_url_packet(url.clone());
+        let packet = Self::create_open_url_packet(&target);
         let request_id = packet.id.to_string();
 
         self.connection_manager
@@ -4823,6 +7126,14 @@ impl OpenInterface {
         let device_id_clone = device.id().to_string();
         let device_name = device.name().to_string();
         let conn_manager = self.connection_manager.clone();
+        let resource_manager = self.resource_manager.clone();
+        let send_options = ShareSendOptions::load(
+            self.pairing_service.as_ref(),
+            &self.config,
+            &self.connection_manager,
+            device.id(),
+        )
+        .await;
 
         tokio::spawn(async move {
             use cosmic_ext_connect_protocol::plugins::share::{FileShareInfo, SharePlugin};
@@ -4842,15 +7153,23 @@ impl OpenInterface {
                 file_info.filename, file_info.size, device_name
             );
 
-            // Get TLS config from connection manager
-            let tls_config = {
+            // Wait for the device's other transfers
+            let _slot = resource_manager
+                .acquire_payload_slot(&device_id_clone)
+                .await;
+
+            // Get TLS config and the device's byte counter from connection manager
+            let (tls_config, traffic_counter) = {
                 let conn_mgr = conn_manager.read().await;
-                conn_mgr.tls_config()
+                (
+                    conn_mgr.tls_config(),
+                    conn_mgr.traffic_counter(&device_id_clone).await,
+                )
             };
 
             // Create TLS payload server
             let server = match TlsPayloadServer::new(tls_config).await {
-                Ok(s) => s,
+                Ok(s) => s.with_traffic_counter(traffic_counter),
                 Err(e) => {
                     error!("Failed to create TLS payload server: {}", e);
                     return;
@@ -4868,9 +7187,19 @@ impl OpenInterface {
                 creation_time: file_info.creation_time,
                 last_modified: file_info.last_modified,
                 open: true, // Auto-open after transfer
+                metadata: file_info.metadata.clone(),
+                user: file_info.user.clone(),
+                checksum: file_info.checksum.clone(),
             };
 
-            let packet = share_plugin.create_file_packet(share_info, port);
+            let mut packet = share_plugin.create_file_packet(share_info, port);
+            let server = match send_options.apply(server, &mut packet) {
+                Ok(server) => server,
+                Err(e) => {
+                    error!("Not opening '{}': {}", file_path_clone, e);
+                    return;
+                }
+            };
 
             // Send packet via connection manager
             let conn_mgr = conn_manager.read().await;
@@ -4878,11 +7207,22 @@ impl OpenInterface {
                 error!("Failed to send file open packet to {}: {}", device_name, e);
                 return;
             }
+            drop(conn_mgr);
 
             debug!("Sent file open packet to {}", device_name);
 
             // Send the file payload
-            match server.send_file(&file_path_clone).await {
+            let sent = send_file_within_budget(
+                &conn_manager,
+                &resource_manager,
+                &device_id_clone,
+                &file_info.filename,
+                server,
+                file_path_clone.clone(),
+                file_info.size,
+            )
+            .await;
+            match sent {
                 Ok(_) => {
                     info!(
                         "Successfully transferred file '{}' to {} for opening",
@@ -4956,8 +7296,11 @@ mod open_interface_tests {
 
     #[test]
     fn test_create_open_url_packet() {
-        let packet = OpenInterface::create_open_url_packet("https://example.com".to_string());
+        let target =
+            cosmic_ext_connect_protocol::plugins::share::OpenTarget::parse("example.com").unwrap();
+        let packet = OpenInterface::create_open_url_packet(&target);
         assert_eq!(packet.packet_type, "cconnect.share.request");
         assert_eq!(packet.body["url"], "https://example.com");
+        assert_eq!(packet.body["urlType"], "web");
     }
 }
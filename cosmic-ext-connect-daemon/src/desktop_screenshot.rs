@@ -0,0 +1,143 @@
+//! Desktop Screenshot
+//!
+//! Captures this desktop and encodes it as PNG so it can be sent to a device.
+//! This is the reverse of the screenshot plugin, which captures the screen
+//! when a device asks for it.
+//!
+//! The Screenshot portal captures every output as one image and does not
+//! report the output layout, so the whole desktop is always sent. The
+//! portal's own copy of the screenshot is deleted once it has been read.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let screenshot = capture_png(&PortalCaptureSource).await?;
+//! tokio::fs::write(&path, &screenshot.png).await?;
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use std::io::Cursor;
+use tracing::{debug, warn};
+
+/// Something that can capture the desktop
+#[async_trait]
+pub trait DesktopCaptureSource: Send + Sync {
+    /// Capture the whole desktop
+    async fn capture(&self) -> Result<RgbaImage>;
+}
+
+/// Captures through the xdg-desktop-portal Screenshot interface
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PortalCaptureSource;
+
+#[async_trait]
+impl DesktopCaptureSource for PortalCaptureSource {
+    async fn capture(&self) -> Result<RgbaImage> {
+        use ashpd::desktop::screenshot::Screenshot;
+
+        let response = Screenshot::request()
+            .interactive(false)
+            .modal(false)
+            .send()
+            .await
+            .context("Failed to request screenshot from portal")?
+            .response()
+            .context("Screenshot portal request was denied")?;
+        let path = response
+            .uri()
+            .to_file_path()
+            .map_err(|_| anyhow!("Screenshot portal returned a non-file URI"))?;
+        debug!("Portal screenshot saved to {}", path.display());
+
+        let image = tokio::task::spawn_blocking(move || {
+            let image = image::open(&path);
+            // The portal leaves its copy behind, often in ~/Pictures
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!(
+                    "Failed to remove portal screenshot {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+            image
+        })
+        .await
+        .context("Screenshot decoding task failed")?
+        .context("Failed to read portal screenshot")?;
+        Ok(image.to_rgba8())
+    }
+}
+
+/// A PNG screenshot ready to send
+#[derive(Debug, Clone)]
+pub struct EncodedScreenshot {
+    /// Suggested file name
+    pub filename: String,
+    pub width: u32,
+    pub height: u32,
+    /// PNG file contents
+    pub png: Vec<u8>,
+}
+
+/// Capture the desktop from `source` and encode it as PNG
+pub async fn capture_png(source: &dyn DesktopCaptureSource) -> Result<EncodedScreenshot> {
+    let image = source.capture().await?;
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return Err(anyhow!("Captured screenshot is empty"));
+    }
+
+    let png = tokio::task::spawn_blocking(move || {
+        let mut buffer = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(image)
+            .write_to(&mut buffer, ImageFormat::Png)
+            .map(|_| buffer.into_inner())
+    })
+    .await
+    .context("Screenshot encoding task failed")?
+    .context("Failed to encode screenshot as PNG")?;
+
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    Ok(EncodedScreenshot {
+        filename: format!("desktop_{}.png", timestamp),
+        width,
+        height,
+        png,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 60x30 desktop, red on the left and blue on the right
+    struct MockCaptureSource;
+
+    #[async_trait]
+    impl DesktopCaptureSource for MockCaptureSource {
+        async fn capture(&self) -> Result<RgbaImage> {
+            Ok(RgbaImage::from_fn(60, 30, |x, _| {
+                if x < 40 {
+                    image::Rgba([255, 0, 0, 255])
+                } else {
+                    image::Rgba([0, 0, 255, 255])
+                }
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_desktop_png() {
+        let screenshot = capture_png(&MockCaptureSource).await.unwrap();
+
+        assert_eq!((screenshot.width, screenshot.height), (60, 30));
+        assert!(screenshot.filename.ends_with(".png"));
+        let decoded = image::load_from_memory_with_format(&screenshot.png, ImageFormat::Png)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(decoded.dimensions(), (60, 30));
+        assert_eq!(decoded.get_pixel(59, 0), &image::Rgba([0, 0, 255, 255]));
+    }
+}
@@ -4,10 +4,18 @@
 //! including per-device plugin enable/disable settings.
 
 use anyhow::{Context, Result};
+use async_trait::async_trait;
+use cosmic_ext_connect_protocol::plugins::safe_mode;
+use cosmic_ext_connect_protocol::{
+    DeviceStateStore, FileSizeLimits, PluginManager, ProtocolError, ReceiveTrust,
+    ReceiveTrustLevels,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
 /// Notification preference for a device
@@ -64,6 +72,23 @@ pub struct DeviceConfig {
     #[serde(default)]
     pub mac_address: Option<String>,
 
+    /// Allow this device to lock the desktop remotely
+    #[serde(default = "default_true")]
+    pub allow_remote_lock: bool,
+
+    /// Allow this device to turn the displays off when locking
+    #[serde(default)]
+    pub allow_remote_screen_off: bool,
+
+    /// Maximum size in bytes of files this device may send (None = use global config)
+    #[serde(default)]
+    pub max_incoming_file_size: Option<u64>,
+
+    /// Whether files from this device are received, asked about or refused
+    /// (None = use global config)
+    #[serde(default)]
+    pub receive_trust: Option<ReceiveTrust>,
+
     /// RemoteDesktop plugin-specific settings
     #[serde(default)]
     pub remotedesktop_settings: Option<RemoteDesktopSettings>,
@@ -174,6 +199,10 @@ impl DeviceConfig {
             show_notifications: true,
             notification_preference: NotificationPreference::default(),
             mac_address: None,
+            allow_remote_lock: true,
+            allow_remote_screen_off: false,
+            max_incoming_file_size: None,
+            receive_trust: None,
             remotedesktop_settings: None,
         }
     }
@@ -181,11 +210,16 @@ impl DeviceConfig {
     /// Check if a specific plugin is enabled for this device
     ///
     /// Returns the device-specific setting if set, otherwise falls back to global config.
+    /// Plugins refused by safe mode are always disabled.
     pub fn is_plugin_enabled(
         &self,
         plugin_name: &str,
         global_config: &crate::config::PluginConfig,
     ) -> bool {
+        if global_config.safe_mode_enabled() && safe_mode::is_blocked_plugin(plugin_name) {
+            return false;
+        }
+
         match plugin_name {
             "ping" => self
                 .plugins
@@ -243,6 +277,24 @@ impl DeviceConfig {
         }
     }
 
+    /// Plugins with a device-specific setting, and whether it enables them
+    pub fn plugin_overrides(&self) -> Vec<(&'static str, bool)> {
+        [
+            ("ping", self.plugins.enable_ping),
+            ("battery", self.plugins.enable_battery),
+            ("notification", self.plugins.enable_notification),
+            ("share", self.plugins.enable_share),
+            ("clipboard", self.plugins.enable_clipboard),
+            ("mpris", self.plugins.enable_mpris),
+            ("remotedesktop", self.plugins.enable_remotedesktop),
+            ("findmyphone", self.plugins.enable_findmyphone),
+            ("lock", self.plugins.enable_lock),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.map(|enabled| (name, enabled)))
+        .collect()
+    }
+
     /// Clear device-specific plugin override (use global config)
     pub fn clear_plugin_override(&mut self, plugin_name: &str) {
         match plugin_name {
@@ -328,6 +380,16 @@ impl DeviceConfig {
     pub fn set_notification_preference(&mut self, preference: NotificationPreference) {
         self.notification_preference = preference;
     }
+
+    /// Get the effective incoming file size limit for this device
+    ///
+    /// Returns the device-specific limit if set, otherwise the global one.
+    pub fn get_max_incoming_file_size(
+        &self,
+        global_config: &crate::config::PluginConfig,
+    ) -> Option<u64> {
+        self.max_incoming_file_size.or(global_config.share_max_incoming_file_size)
+    }
 }
 
 /// Device configuration registry
@@ -401,11 +463,76 @@ impl DeviceConfigRegistry {
     }
 
     /// Remove device configuration
-    #[allow(dead_code)]
     pub fn remove(&mut self, device_id: &str) -> Option<DeviceConfig> {
         self.configs.remove(device_id)
     }
 
+    /// Move a device's configuration to a new device ID
+    ///
+    /// Replaces any configuration already held for `to`. Returns whether
+    /// `from` had a configuration to move.
+    pub fn migrate(&mut self, from: &str, to: &str) -> bool {
+        let Some(mut config) = self.configs.remove(from) else {
+            return false;
+        };
+        config.device_id = to.to_string();
+        self.configs.insert(to.to_string(), config);
+        true
+    }
+
+    /// Incoming file size limits for the share plugin
+    ///
+    /// The global limit is the default; devices with their own limit override it.
+    pub fn file_size_limits(&self, global_config: &crate::config::PluginConfig) -> FileSizeLimits {
+        self.configs
+            .values()
+            .filter_map(|config| {
+                config
+                    .max_incoming_file_size
+                    .map(|limit| (config.device_id.clone(), limit))
+            })
+            .fold(
+                FileSizeLimits::new(global_config.share_max_incoming_file_size),
+                |limits, (device_id, limit)| limits.with_device_limit(device_id, Some(limit)),
+            )
+    }
+
+    /// Receive trust levels for the share plugin
+    ///
+    /// The global level is the default; devices with their own level override it.
+    pub fn receive_trust_levels(
+        &self,
+        global_config: &crate::config::PluginConfig,
+    ) -> ReceiveTrustLevels {
+        self.configs
+            .values()
+            .filter_map(|config| {
+                config
+                    .receive_trust
+                    .map(|trust| (config.device_id.clone(), trust))
+            })
+            .fold(
+                ReceiveTrustLevels::new(global_config.share_receive_trust),
+                |levels, (device_id, trust)| levels.with_device_trust(device_id, trust),
+            )
+    }
+
+    /// Apply a device's saved plugin overrides to packet dispatch
+    ///
+    /// Called when the device's plugins are initialized, so a plugin switched
+    /// off for it stays off across restarts. Overrides for plugins that are
+    /// not loaded are skipped.
+    pub fn apply_plugin_overrides(&self, device_id: &str, plugins: &PluginManager) {
+        let Some(config) = self.configs.get(device_id) else {
+            return;
+        };
+        for (plugin, enabled) in config.plugin_overrides() {
+            if let Err(e) = plugins.set_device_enabled(device_id, plugin, enabled) {
+                debug!("Skipping plugin override for {}: {}", device_id, e);
+            }
+        }
+    }
+
     /// Get all device IDs with custom configurations
     #[allow(dead_code)]
     pub fn device_ids(&self) -> Vec<String> {
@@ -431,6 +558,53 @@ impl DeviceConfigRegistry {
     }
 }
 
+/// Purges a forgotten device's configuration from the shared registry
+///
+/// Covers everything stored in [`DeviceConfig`]: nickname, plugin overrides,
+/// remote desktop settings, Wake-on-LAN address and notification preferences.
+/// The same settings move with a device whose identity is migrated.
+pub struct DeviceConfigStore {
+    registry: Arc<RwLock<DeviceConfigRegistry>>,
+}
+
+impl DeviceConfigStore {
+    /// Create a store backed by the daemon's registry
+    pub fn new(registry: Arc<RwLock<DeviceConfigRegistry>>) -> Self {
+        Self { registry }
+    }
+}
+
+#[async_trait]
+impl DeviceStateStore for DeviceConfigStore {
+    fn name(&self) -> &str {
+        "device configuration"
+    }
+
+    async fn purge_device(&self, device_id: &str) -> cosmic_ext_connect_protocol::Result<()> {
+        let mut registry = self.registry.write().await;
+        if registry.remove(device_id).is_some() {
+            registry
+                .save()
+                .map_err(|e| ProtocolError::Configuration(format!("{:#}", e)))?;
+        }
+        Ok(())
+    }
+
+    async fn migrate_device(
+        &self,
+        from: &str,
+        to: &str,
+    ) -> cosmic_ext_connect_protocol::Result<()> {
+        let mut registry = self.registry.write().await;
+        if registry.migrate(from, to) {
+            registry
+                .save()
+                .map_err(|e| ProtocolError::Configuration(format!("{:#}", e)))?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -461,6 +635,75 @@ mod tests {
         assert!(config.is_plugin_enabled("ping", &global_config));
     }
 
+    #[test]
+    fn test_file_size_limit_override() {
+        let mut registry = DeviceConfigRegistry::new(&std::env::temp_dir().join("cconnect-test"));
+        let global_config = crate::config::PluginConfig {
+            share_max_incoming_file_size: Some(1024),
+            ..Default::default()
+        };
+        registry.get_or_create("phone");
+        registry.get_or_create("laptop").max_incoming_file_size = Some(4096);
+
+        let limits = registry.file_size_limits(&global_config);
+        assert_eq!(limits.limit_for("phone"), Some(1024));
+        assert_eq!(limits.limit_for("laptop"), Some(4096));
+        assert_eq!(
+            registry
+                .get("laptop")
+                .unwrap()
+                .get_max_incoming_file_size(&global_config),
+            Some(4096)
+        );
+    }
+
+    #[test]
+    fn test_safe_mode_overrides_device_settings() {
+        let global_config = crate::config::PluginConfig {
+            safe_mode: true,
+            ..Default::default()
+        };
+        let mut config = DeviceConfig::new("phone".to_string());
+        config.set_plugin_enabled("lock", true);
+        config.set_plugin_enabled("share", true);
+
+        assert!(!config.is_plugin_enabled("lock", &global_config));
+        assert!(!config.is_plugin_enabled("remotedesktop", &global_config));
+        assert!(config.is_plugin_enabled("share", &global_config));
+        assert!(config.is_plugin_enabled("clipboard", &global_config));
+    }
+
+    #[test]
+    fn test_receive_trust_override() {
+        let mut registry = DeviceConfigRegistry::new(&std::env::temp_dir().join("cconnect-test"));
+        let global_config = crate::config::PluginConfig {
+            share_receive_trust: ReceiveTrust::Prompt,
+            ..Default::default()
+        };
+        registry.get_or_create("phone");
+        registry.get_or_create("laptop").receive_trust = Some(ReceiveTrust::AutoAccept);
+
+        let levels = registry.receive_trust_levels(&global_config);
+        assert_eq!(levels.trust_for("phone"), ReceiveTrust::Prompt);
+        assert_eq!(levels.trust_for("laptop"), ReceiveTrust::AutoAccept);
+    }
+
+    #[test]
+    fn test_plugin_overrides_listed() {
+        let mut config = DeviceConfig::new("test-device".to_string());
+        assert!(config.plugin_overrides().is_empty());
+
+        config.set_plugin_enabled("clipboard", false);
+        config.set_plugin_enabled("ping", true);
+        assert_eq!(
+            config.plugin_overrides(),
+            vec![("ping", true), ("clipboard", false)]
+        );
+
+        config.clear_plugin_override("clipboard");
+        assert_eq!(config.plugin_overrides(), vec![("ping", true)]);
+    }
+
     #[test]
     fn test_device_config_serialization() {
         let mut config = DeviceConfig::new("test-device".to_string());
@@ -502,4 +745,59 @@ mod tests {
         // Cleanup
         fs::remove_dir_all(&temp_dir).ok();
     }
+
+    #[tokio::test]
+    async fn test_config_store_purges_device() {
+        let temp_dir = std::env::temp_dir().join("cconnect-test-forget");
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let mut registry = DeviceConfigRegistry::new(&temp_dir);
+        registry.get_or_create("device-1").nickname = Some("Phone".to_string());
+        registry.get_or_create("device-2").nickname = Some("Tablet".to_string());
+        registry.save().unwrap();
+
+        let registry = Arc::new(RwLock::new(registry));
+        let store = DeviceConfigStore::new(registry.clone());
+        store.purge_device("device-1").await.unwrap();
+        // Purging a device without configuration is a no-op
+        store.purge_device("unknown").await.unwrap();
+
+        assert!(!registry.read().await.has_config("device-1"));
+        let mut reloaded = DeviceConfigRegistry::new(&temp_dir);
+        reloaded.load().unwrap();
+        assert!(!reloaded.has_config("device-1"));
+        assert!(reloaded.has_config("device-2"));
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_config_store_migrates_device() {
+        let temp_dir = std::env::temp_dir().join("cconnect-test-migrate");
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let mut registry = DeviceConfigRegistry::new(&temp_dir);
+        let old = registry.get_or_create("old-phone");
+        old.nickname = Some("Work Phone".to_string());
+        old.set_plugin_enabled("clipboard", false);
+        // Defaults created for the reinstall before the user accepted
+        registry.get_or_create("new-phone");
+
+        let registry = Arc::new(RwLock::new(registry));
+        let store = DeviceConfigStore::new(registry.clone());
+        store
+            .migrate_device("old-phone", "new-phone")
+            .await
+            .unwrap();
+
+        let mut reloaded = DeviceConfigRegistry::new(&temp_dir);
+        reloaded.load().unwrap();
+        assert!(!reloaded.has_config("old-phone"));
+        let migrated = reloaded.get("new-phone").unwrap();
+        assert_eq!(migrated.device_id, "new-phone");
+        assert_eq!(migrated.nickname, Some("Work Phone".to_string()));
+        assert_eq!(migrated.plugins.enable_clipboard, Some(false));
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
 }
@@ -338,8 +338,7 @@ impl Metrics {
 }
 
 /// Format bytes in human-readable form
-#[allow(dead_code)]
-fn format_bytes(bytes: u64) -> String {
+pub(crate) fn format_bytes(bytes: u64) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
     let mut value = bytes as f64;
     let mut unit_index = 0;
@@ -10,12 +10,14 @@ use tokio::sync::RwLock;
 use tracing::{debug, error, warn};
 
 use crate::cosmic_notifications::CosmicNotifier;
+use crate::error_history::{ErrorCategory, ErrorHistory};
 
 /// Central error handler that manages error notifications and recovery
 #[derive(Clone)]
 #[allow(dead_code)]
 pub struct ErrorHandler {
     notifier: Arc<RwLock<Option<CosmicNotifier>>>,
+    history: Arc<ErrorHistory>,
 }
 
 impl ErrorHandler {
@@ -24,9 +26,15 @@ impl ErrorHandler {
     pub fn new() -> Self {
         Self {
             notifier: Arc::new(RwLock::new(None)),
+            history: Arc::new(ErrorHistory::default()),
         }
     }
 
+    /// Recent errors per device, as recorded by this handler
+    pub fn history(&self) -> Arc<ErrorHistory> {
+        self.history.clone()
+    }
+
     /// Initialize the error handler with a notifier
     ///
     /// This should be called during daemon startup.
@@ -43,6 +51,7 @@ impl ErrorHandler {
     /// This method examines the error type and:
     /// - Logs the error at appropriate level (error, warn, debug)
     /// - Shows user notification if the error requires user attention
+    /// - Records the error in the device's history when a device is given
     /// - Returns whether the error is recoverable for retry logic
     ///
     /// # Examples
@@ -78,6 +87,16 @@ impl ErrorHandler {
             error!("Critical error {}: {}", context, error);
         }
 
+        if let Some(device_id) = device_id {
+            self.history
+                .record(
+                    device_id,
+                    ErrorCategory::from_context(context),
+                    &error.user_message(),
+                )
+                .await;
+        }
+
         // Show user notification if error requires user attention
         if error.requires_user_action() {
             if let Err(e) = self.notify_error(error, device_id).await {
@@ -242,6 +261,13 @@ impl ErrorHandler {
             "Plugin {} error for device {}: {}",
             plugin_name, device_id, error
         );
+        self.history
+            .record(
+                device_id,
+                ErrorCategory::Plugin,
+                &format!("{}: {}", plugin_name, error.user_message()),
+            )
+            .await;
 
         if notify_user {
             let notifier_guard = self.notifier.read().await;
@@ -279,6 +305,25 @@ mod tests {
         assert!(!handler.notifications_enabled().await);
     }
 
+    #[tokio::test]
+    async fn test_handled_errors_are_recorded() {
+        let handler = ErrorHandler::new();
+        handler
+            .handle_error(&ProtocolError::NotPaired, "pairing", Some("phone"))
+            .await;
+        handler
+            .handle_error(
+                &ProtocolError::Timeout("no answer".to_string()),
+                "connection",
+                None,
+            )
+            .await;
+
+        let recent = handler.history().recent("phone").await;
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].category, ErrorCategory::Pairing);
+    }
+
     #[test]
     fn test_error_classification() {
         // Recoverable errors
@@ -0,0 +1,238 @@
+//! Per-Device Error History
+//!
+//! Errors are logged as they happen, but by the time a user asks why a phone
+//! keeps dropping off the log has scrolled past. The daemon keeps the newest
+//! [`ERROR_HISTORY_CAPACITY`] errors of each device in memory, tagged with
+//! where they came from, so the applet can show what went wrong last.
+//!
+//! The history is not persisted; it describes the current daemon session.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use tokio::sync::RwLock;
+
+/// Number of errors retained per device
+pub const ERROR_HISTORY_CAPACITY: usize = 20;
+
+/// Part of the daemon an error came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// Connecting to or talking with the device
+    Connection,
+    /// Sending or receiving a file
+    Transfer,
+    /// Pairing or unpairing
+    Pairing,
+    /// Handling a plugin packet
+    Plugin,
+}
+
+impl ErrorCategory {
+    /// Short name of the category
+    #[allow(dead_code)]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Connection => "connection",
+            Self::Transfer => "transfer",
+            Self::Pairing => "pairing",
+            Self::Plugin => "plugin",
+        }
+    }
+
+    /// Category of an error reported with the given handler context
+    pub fn from_context(context: &str) -> Self {
+        let context = context.to_lowercase();
+        if context.contains("pair") {
+            Self::Pairing
+        } else if context.contains("transfer") {
+            Self::Transfer
+        } else if context.contains("plugin") {
+            Self::Plugin
+        } else {
+            Self::Connection
+        }
+    }
+}
+
+/// One recorded error
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceError {
+    /// When it happened (Unix milliseconds)
+    pub timestamp: i64,
+    /// Where it came from
+    pub category: ErrorCategory,
+    /// What went wrong
+    pub message: String,
+}
+
+/// Bounded list of one device's errors, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct ErrorRing {
+    errors: VecDeque<DeviceError>,
+}
+
+impl ErrorRing {
+    /// Append an error, dropping the oldest beyond `capacity`
+    pub fn push(&mut self, error: DeviceError, capacity: usize) {
+        self.errors.push_back(error);
+        while self.errors.len() > capacity {
+            self.errors.pop_front();
+        }
+    }
+
+    /// Retained errors, newest first
+    pub fn newest_first(&self) -> Vec<DeviceError> {
+        self.errors.iter().rev().cloned().collect()
+    }
+
+    /// Number of retained errors
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Whether no error is retained
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Recent errors of every device, shared by the daemon and the D-Bus interface
+pub struct ErrorHistory {
+    rings: RwLock<HashMap<String, ErrorRing>>,
+    capacity: usize,
+}
+
+impl ErrorHistory {
+    /// Keep up to `capacity` errors per device
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            rings: RwLock::new(HashMap::new()),
+            capacity,
+        }
+    }
+
+    /// Record an error for `device_id`
+    pub async fn record(&self, device_id: &str, category: ErrorCategory, message: &str) {
+        let error = DeviceError {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            category,
+            message: message.to_string(),
+        };
+        self.rings
+            .write()
+            .await
+            .entry(device_id.to_string())
+            .or_default()
+            .push(error, self.capacity);
+    }
+
+    /// Retained errors of `device_id`, newest first
+    pub async fn recent(&self, device_id: &str) -> Vec<DeviceError> {
+        self.rings
+            .read()
+            .await
+            .get(device_id)
+            .map(ErrorRing::newest_first)
+            .unwrap_or_default()
+    }
+
+    /// Newest error of `device_id`, if any
+    #[allow(dead_code)]
+    pub async fn latest(&self, device_id: &str) -> Option<DeviceError> {
+        self.recent(device_id).await.into_iter().next()
+    }
+
+    /// Forget the errors of `device_id`
+    #[allow(dead_code)]
+    pub async fn clear(&self, device_id: &str) {
+        self.rings.write().await.remove(device_id);
+    }
+}
+
+impl Default for ErrorHistory {
+    fn default() -> Self {
+        Self::new(ERROR_HISTORY_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(errors: &[DeviceError]) -> Vec<&str> {
+        errors.iter().map(|e| e.message.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_recorded_in_order() {
+        let history = ErrorHistory::default();
+        history
+            .record("phone", ErrorCategory::Connection, "Connection reset")
+            .await;
+        history
+            .record("phone", ErrorCategory::Transfer, "Disk full")
+            .await;
+        history
+            .record("tablet", ErrorCategory::Pairing, "Pairing timed out")
+            .await;
+
+        let recent = history.recent("phone").await;
+        assert_eq!(messages(&recent), vec!["Disk full", "Connection reset"]);
+        assert_eq!(recent[0].category, ErrorCategory::Transfer);
+        assert!(recent[0].timestamp >= recent[1].timestamp);
+        assert_eq!(
+            history.latest("tablet").await.map(|e| e.category),
+            Some(ErrorCategory::Pairing)
+        );
+        assert!(history.recent("laptop").await.is_empty());
+
+        history.clear("phone").await;
+        assert!(history.recent("phone").await.is_empty());
+    }
+
+    #[test]
+    fn test_ring_is_bounded() {
+        let mut ring = ErrorRing::default();
+        for i in 0..5 {
+            ring.push(
+                DeviceError {
+                    timestamp: i,
+                    category: ErrorCategory::Connection,
+                    message: format!("error {}", i),
+                },
+                3,
+            );
+        }
+
+        assert_eq!(ring.len(), 3);
+        assert_eq!(
+            messages(&ring.newest_first()),
+            vec!["error 4", "error 3", "error 2"]
+        );
+    }
+
+    #[test]
+    fn test_category_from_context() {
+        assert_eq!(
+            ErrorCategory::from_context("pairing"),
+            ErrorCategory::Pairing
+        );
+        assert_eq!(
+            ErrorCategory::from_context("file transfer"),
+            ErrorCategory::Transfer
+        );
+        assert_eq!(
+            ErrorCategory::from_context("plugin_packet"),
+            ErrorCategory::Plugin
+        );
+        assert_eq!(
+            ErrorCategory::from_context("connection"),
+            ErrorCategory::Connection
+        );
+
+        let json = serde_json::to_value(ErrorCategory::Transfer).unwrap();
+        assert_eq!(json, "transfer");
+    }
+}
@@ -0,0 +1,324 @@
+//! Device Event Feed
+//!
+//! A sequenced log of device events (connections, battery, transfers,
+//! pairing) for external integrations such as home-automation scripts. Every
+//! event gets the next sequence number and is published live through the
+//! `DeviceEvent` D-Bus signal. A consumer that was away asks for everything
+//! after the last sequence it saw and continues from there.
+//!
+//! Only the newest [`FEED_CAPACITY`] events are retained. The log is written
+//! to disk so sequence numbers keep increasing across daemon restarts; a
+//! consumer whose first returned sequence is more than one past its last seen
+//! one has missed events that were dropped from the backlog.
+
+use anyhow::{Context, Result};
+use cosmic_ext_connect_protocol::Packet;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use tokio::sync::RwLock;
+use tracing::warn;
+
+/// Number of events retained for catching up
+pub const FEED_CAPACITY: usize = 500;
+
+/// What happened to a device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DeviceEventKind {
+    /// The device connected
+    Connected,
+    /// The device disconnected
+    Disconnected {
+        /// Reason given by the connection manager
+        reason: Option<String>,
+    },
+    /// The device reported its battery state
+    Battery {
+        /// Charge in percent
+        level: i64,
+        /// Whether it is charging
+        charging: bool,
+        /// Whether the device flagged the level as low
+        low: bool,
+    },
+    /// A file transfer started
+    TransferStarted {
+        /// File name
+        filename: String,
+        /// Whether the file is coming from the device
+        incoming: bool,
+    },
+    /// A file transfer ended
+    TransferFinished {
+        /// File name
+        filename: String,
+        /// Whether the file came from the device
+        incoming: bool,
+        /// Whether the file arrived completely
+        success: bool,
+    },
+    /// The device was paired
+    Paired,
+    /// A pairing request was rejected
+    PairingRejected {
+        /// Reason given, if any
+        reason: Option<String>,
+    },
+    /// The device was unpaired
+    Unpaired,
+}
+
+impl DeviceEventKind {
+    /// The feed event a received packet represents, if any
+    ///
+    /// Covers battery reports and incoming file offers; connection and
+    /// pairing events come from their own event streams.
+    pub fn from_packet(packet: &Packet) -> Option<Self> {
+        match packet.packet_type.as_str() {
+            "cconnect.battery" => Some(Self::Battery {
+                level: packet.body.get("currentCharge")?.as_i64()?,
+                charging: packet
+                    .body
+                    .get("isCharging")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false),
+                low: packet.body.get("thresholdEvent").and_then(|v| v.as_i64()) == Some(1),
+            }),
+            "cconnect.share.request" if packet.payload_size.is_some() => {
+                Some(Self::TransferStarted {
+                    filename: packet.body.get("filename")?.as_str()?.to_string(),
+                    incoming: true,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// One entry of the feed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedEvent {
+    /// Position in the feed, starting at 1
+    pub seq: u64,
+    /// When it happened (Unix milliseconds)
+    pub timestamp: i64,
+    /// Device the event is about
+    pub device_id: String,
+    /// What happened
+    #[serde(flatten)]
+    pub kind: DeviceEventKind,
+}
+
+/// Bounded, sequenced backlog of events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventLog {
+    next_seq: u64,
+    events: VecDeque<FeedEvent>,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self {
+            next_seq: 1,
+            events: VecDeque::new(),
+        }
+    }
+}
+
+impl EventLog {
+    /// Append an event, dropping the oldest beyond `capacity`
+    pub fn push(
+        &mut self,
+        device_id: &str,
+        kind: DeviceEventKind,
+        timestamp: i64,
+        capacity: usize,
+    ) -> FeedEvent {
+        let event = FeedEvent {
+            seq: self.next_seq,
+            timestamp,
+            device_id: device_id.to_string(),
+            kind,
+        };
+        self.next_seq += 1;
+        self.events.push_back(event.clone());
+        while self.events.len() > capacity {
+            self.events.pop_front();
+        }
+        event
+    }
+
+    /// Retained events with a sequence after `last_seen`, oldest first
+    pub fn since(&self, last_seen: u64) -> Vec<FeedEvent> {
+        self.events
+            .iter()
+            .filter(|event| event.seq > last_seen)
+            .cloned()
+            .collect()
+    }
+
+    /// Sequence of the newest event (0 if there has been none)
+    pub fn latest_seq(&self) -> u64 {
+        self.next_seq - 1
+    }
+}
+
+/// Persistent event feed shared by the daemon and the D-Bus interface
+pub struct DeviceEventFeed {
+    log: RwLock<EventLog>,
+    path: PathBuf,
+    capacity: usize,
+}
+
+impl DeviceEventFeed {
+    /// Load the feed stored at `path`, starting empty if there is none
+    pub fn load(path: PathBuf, capacity: usize) -> Self {
+        let log = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable event feed {:?}: {}", path, e);
+                EventLog::default()
+            }),
+            Err(_) => EventLog::default(),
+        };
+        Self {
+            log: RwLock::new(log),
+            path,
+            capacity,
+        }
+    }
+
+    /// Append an event and persist the feed
+    pub async fn record(&self, device_id: &str, kind: DeviceEventKind) -> FeedEvent {
+        let mut log = self.log.write().await;
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        let event = log.push(device_id, kind, timestamp, self.capacity);
+        if let Err(e) = self.save(&log).await {
+            warn!("Failed to save event feed: {}", e);
+        }
+        event
+    }
+
+    /// Retained events after `last_seen`, oldest first
+    pub async fn since(&self, last_seen: u64) -> Vec<FeedEvent> {
+        self.log.read().await.since(last_seen)
+    }
+
+    /// Sequence of the newest event
+    pub async fn latest_seq(&self) -> u64 {
+        self.log.read().await.latest_seq()
+    }
+
+    async fn save(&self, log: &EventLog) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .context("Failed to create event feed directory")?;
+        }
+        let contents = serde_json::to_string(log).context("Failed to serialize event feed")?;
+        tokio::fs::write(&self.path, contents)
+            .await
+            .context("Failed to write event feed")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn battery(level: i64) -> DeviceEventKind {
+        DeviceEventKind::Battery {
+            level,
+            charging: false,
+            low: level < 15,
+        }
+    }
+
+    #[test]
+    fn test_catch_up_returns_only_newer_events() {
+        let mut log = EventLog::default();
+        log.push("phone", DeviceEventKind::Connected, 1, 10);
+        log.push("phone", battery(80), 2, 10);
+        log.push("phone", battery(10), 3, 10);
+        log.push(
+            "phone",
+            DeviceEventKind::Disconnected { reason: None },
+            4,
+            10,
+        );
+
+        let missed = log.since(2);
+        assert_eq!(missed.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!(missed[0].kind, battery(10));
+        assert!(log.since(log.latest_seq()).is_empty());
+    }
+
+    #[test]
+    fn test_backlog_drops_oldest() {
+        let mut log = EventLog::default();
+        for level in 0..5 {
+            log.push("phone", battery(level), level, 3);
+        }
+
+        let retained = log.since(0);
+        assert_eq!(
+            retained.iter().map(|e| e.seq).collect::<Vec<_>>(),
+            vec![3, 4, 5]
+        );
+        assert_eq!(log.latest_seq(), 5);
+    }
+
+    #[test]
+    fn test_kind_from_packet() {
+        let packet = Packet::new(
+            "cconnect.battery",
+            serde_json::json!({"currentCharge": 12, "isCharging": false, "thresholdEvent": 1}),
+        );
+        assert_eq!(
+            DeviceEventKind::from_packet(&packet),
+            Some(DeviceEventKind::Battery {
+                level: 12,
+                charging: false,
+                low: true
+            })
+        );
+
+        let mut offer = Packet::new(
+            "cconnect.share.request",
+            serde_json::json!({"filename": "photo.jpg"}),
+        );
+        assert_eq!(DeviceEventKind::from_packet(&offer), None);
+        offer.payload_size = Some(1024);
+        assert_eq!(
+            DeviceEventKind::from_packet(&offer),
+            Some(DeviceEventKind::TransferStarted {
+                filename: "photo.jpg".to_string(),
+                incoming: true
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sequence_survives_reload() {
+        let path = std::env::temp_dir().join(format!(
+            "cconnect-event-feed-test-{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let feed = DeviceEventFeed::load(path.clone(), FEED_CAPACITY);
+        feed.record("phone", DeviceEventKind::Paired).await;
+        feed.record("phone", DeviceEventKind::Connected).await;
+
+        let reloaded = DeviceEventFeed::load(path.clone(), FEED_CAPACITY);
+        assert_eq!(reloaded.latest_seq().await, 2);
+        let event = reloaded.record("phone", DeviceEventKind::Unpaired).await;
+        assert_eq!(event.seq, 3);
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "unpaired");
+        assert_eq!(json["device_id"], "phone");
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,10 +1,15 @@
+mod clipboard_image;
+mod command_schedule;
 mod config;
 mod cosmic_notifications;
 mod dbus;
 mod desktop_icons;
+mod desktop_screenshot;
 mod device_config;
 mod diagnostics;
 mod error_handler;
+mod error_history;
+mod event_feed;
 mod mpris_manager;
 mod notification_image;
 mod notification_listener;
@@ -16,7 +21,7 @@ use cosmic_ext_connect_protocol::{
     discovery::{
         default_additional_broadcast_addrs, DiscoveryConfig, DiscoveryEvent, DiscoveryService,
     },
-    pairing::{PairingConfig, PairingEvent, PairingService, PairingStatus},
+    pairing::{PairingConfig, PairingEvent, PairingService, PairingStatus, RejectReason},
     plugins::{
         audiostream::AudioStreamPluginFactory,
         battery::BatteryPluginFactory,
@@ -32,7 +37,7 @@ use cosmic_ext_connect_protocol::{
         mousekeyboardshare::MouseKeyboardSharePluginFactory,
         mpris::MprisPluginFactory,
         networkshare::NetworkSharePluginFactory,
-        notification::NotificationPluginFactory,
+        notification::{NotificationIcon, NotificationPluginFactory, NotificationPosted},
         ping::PingPluginFactory,
         power::PowerPluginFactory,
         presenter::PresenterPluginFactory,
@@ -46,10 +51,12 @@ use cosmic_ext_connect_protocol::{
         systemvolume::SystemVolumePluginFactory,
         telephony::TelephonyPluginFactory,
         wol::WolPluginFactory,
-        PluginManager,
+        PluginFactory, PluginManager, PluginManifest, PluginManifestEntry,
     },
-    CertificateInfo, DeviceInfo, DeviceManager, DeviceType, Packet, TransportManager,
-    TransportManagerConfig, TransportManagerEvent,
+    Cadence, CertificateInfo, DeviceFileStore, DeviceInfo, DeviceManager, DeviceType, Packet,
+    PendingTransferPrompts, PowerAwareCadence, ReceiveTrustLevels, RecoveryManager,
+    ResourceManager, TransferGate, TransferPrompt, TransportManager, TransportManagerConfig,
+    TransportManagerEvent, UPowerStateProvider,
 };
 use dbus::DbusServer;
 use diagnostics::{BuildInfo, Cli, DiagnosticCommand, Metrics};
@@ -63,6 +70,9 @@ use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
 /// Placeholder address for Bluetooth connections that lack a real SocketAddr
 const BT_PLACEHOLDER_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+
+/// Device notifications whose desktop notification is remembered for updates
+const MAX_SHOWN_NOTIFICATIONS: usize = 256;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
@@ -141,12 +151,27 @@ struct Daemon {
     /// Packet receiver for plugins (wrapped in Mutex to allow extraction)
     packet_receiver: Arc<tokio::sync::Mutex<Option<Receiver<(String, Packet)>>>>,
 
-    /// Track connection attempts for exponential backoff (device_id -> (last_attempt, failure_count))
-    connection_attempts: Arc<RwLock<std::collections::HashMap<String, (std::time::Instant, u32)>>>,
-
     /// Receiver for captured notifications from the notification listener
     notification_receiver:
         Arc<tokio::sync::Mutex<Option<tokio::sync::mpsc::UnboundedReceiver<CapturedNotification>>>>,
+
+    /// Gate deciding whether incoming files are received
+    transfer_gate: TransferGate,
+
+    /// Receive prompts from the gate (wrapped in Mutex to allow extraction)
+    transfer_prompt_receiver: Arc<tokio::sync::Mutex<Option<Receiver<TransferPrompt>>>>,
+
+    /// Receive prompts waiting for the user's answer
+    transfer_prompts: PendingTransferPrompts,
+
+    /// Per-device transfer queues and limits
+    resource_manager: Arc<ResourceManager>,
+
+    /// Transfer history with checkpoints of received files
+    recovery_manager: Arc<RecoveryManager>,
+
+    /// Battery-aware discovery and keepalive cadence (None if disabled)
+    power_cadence: Option<Arc<PowerAwareCadence>>,
 }
 
 impl Daemon {
@@ -208,6 +233,16 @@ impl Daemon {
         let (packet_sender, packet_receiver) = channel(100);
         let packet_receiver = Arc::new(tokio::sync::Mutex::new(Some(packet_receiver)));
 
+        // Create the receive gate; per-device levels are applied when the
+        // share plugin is set up
+        let (transfer_gate, transfer_prompt_receiver) = TransferGate::new(
+            ReceiveTrustLevels::new(config.plugins.share_receive_trust),
+            config.plugins.share_prompt_timeout(),
+        );
+
+        // Files sent to and received from a device queue behind each other
+        let resource_manager = Arc::new(ResourceManager::new(config.protocol.resources.clone()));
+
         // Create device manager
         let device_manager = Arc::new(RwLock::new(
             DeviceManager::new(config.device_registry_path())
@@ -222,6 +257,30 @@ impl Daemon {
             .context("Failed to load device configurations")?;
         let device_config_registry = Arc::new(RwLock::new(device_config_registry));
 
+        // Checkpoints of files received by the share plugin
+        let recovery_manager = Arc::new(RecoveryManager::new(&config.paths.data_dir));
+        recovery_manager
+            .init()
+            .await
+            .context("Failed to load transfer history")?;
+
+        // State wiped when a device is forgotten
+        {
+            // Pinned certificates are revoked through the pairing service
+            let mut manager = device_manager.write().await;
+            manager.register_state_store(recovery_manager.clone());
+            if let Some(home) = dirs::home_dir() {
+                // Plugins keep per-device data (run commands, sync folders) here
+                manager.register_state_store(Arc::new(DeviceFileStore::directory(
+                    "plugin data",
+                    home.join(".config").join("cconnect"),
+                )));
+            }
+            manager.register_state_store(Arc::new(device_config::DeviceConfigStore::new(
+                device_config_registry.clone(),
+            )));
+        }
+
         // Create TLS configuration for payload transfers
         let tls_config = Arc::new(
             cosmic_ext_connect_protocol::TlsConfig::new(&certificate)
@@ -233,17 +292,34 @@ impl Daemon {
             listen_addr: format!("[::]:{}", config.network.discovery_port)
                 .parse()
                 .context("Invalid listen address")?,
-            keep_alive_interval: Duration::from_secs(30),
-            connection_timeout: Duration::from_secs(60),
+            ..config.protocol.connection.clone()
         };
 
+        // Slow discovery and keepalives down while on battery
+        let power_cadence = config.protocol.power.enabled.then(|| {
+            info!("Power-aware cadence enabled");
+            Arc::new(PowerAwareCadence::new(
+                Arc::new(UPowerStateProvider::new()),
+                Cadence {
+                    broadcast_interval: Duration::from_secs(config.network.discovery_interval),
+                    keep_alive_interval: connection_config.keep_alive_interval,
+                },
+                config.protocol.power.clone(),
+            ))
+        });
+
         // Create connection manager (not started yet)
-        let connection_manager = Arc::new(RwLock::new(ConnectionManager::new(
+        let mut connection_manager = ConnectionManager::new(
             certificate.clone(),
             device_info.clone(),
             device_manager.clone(),
             connection_config,
-        )?));
+        )?;
+        if let Some(cadence) = &power_cadence {
+            connection_manager.follow_keep_alive(cadence.watch_keep_alive_interval());
+            connection_manager.follow_power_source(cadence.watch_power_source());
+        }
+        let connection_manager = Arc::new(RwLock::new(connection_manager));
 
         // Create transport manager if Bluetooth is enabled
         let transport_manager = if config.transport.enable_bluetooth {
@@ -258,6 +334,8 @@ impl Daemon {
                 bluetooth_timeout: config.transport.bluetooth_timeout(),
                 auto_fallback: config.transport.auto_fallback,
                 bluetooth_device_filter: config.transport.bluetooth_device_filter.clone(),
+                migration_interval: config.transport.migration_interval(),
+                keep_fallback: config.transport.keep_fallback,
             };
 
             match TransportManager::new(connection_manager.clone(), transport_config) {
@@ -331,8 +409,15 @@ impl Daemon {
             dump_packets: false,
             packet_sender,
             packet_receiver,
-            connection_attempts: Arc::new(RwLock::new(std::collections::HashMap::new())),
             notification_receiver: Arc::new(tokio::sync::Mutex::new(None)),
+            transfer_gate,
+            transfer_prompt_receiver: Arc::new(tokio::sync::Mutex::new(Some(
+                transfer_prompt_receiver,
+            ))),
+            transfer_prompts: PendingTransferPrompts::new(),
+            resource_manager,
+            recovery_manager,
+            power_cadence,
         })
     }
 
@@ -365,226 +450,70 @@ impl Daemon {
         }
     }
 
+    /// Every plugin the daemon can run, enabled or not
+    fn plugin_manifest(config: &Config) -> PluginManifest {
+        let factories: Vec<Arc<dyn PluginFactory>> = vec![
+            Arc::new(PingPluginFactory),
+            Arc::new(BatteryPluginFactory),
+            Arc::new(NotificationPluginFactory),
+            Arc::new(SharePluginFactory),
+            Arc::new(ClipboardPluginFactory),
+            Arc::new(MprisPluginFactory),
+            Arc::new(RunCommandPluginFactory),
+            Arc::new(RemoteInputPluginFactory),
+            Arc::new(FindMyPhonePluginFactory),
+            Arc::new(LockPluginFactory),
+            Arc::new(TelephonyPluginFactory),
+            Arc::new(PresenterPluginFactory),
+            Arc::new(ContactsPluginFactory),
+            Arc::new(SystemMonitorPluginFactory),
+            Arc::new(WolPluginFactory),
+            Arc::new(ScreenshotPluginFactory),
+            Arc::new(RemoteDesktopPluginFactory),
+            Arc::new(PowerPluginFactory),
+            Arc::new(ClipboardHistoryPluginFactory),
+            Arc::new(MacroPluginFactory),
+            Arc::new(ChatPluginFactory),
+            Arc::new(AudioStreamPluginFactory),
+            Arc::new(FileSyncPluginFactory),
+            Arc::new(ScreenSharePluginFactory::with_restore_session(
+                config.plugins.screenshare_restore_session,
+            )),
+            Arc::new(MouseKeyboardSharePluginFactory),
+            Arc::new(NetworkSharePluginFactory),
+            Arc::new(SystemVolumePluginFactory),
+            Arc::new(ConnectivityReportPluginFactory),
+            Arc::new(CameraPluginFactory),
+            #[cfg(feature = "extendeddisplay")]
+            Arc::new(ExtendedDisplayPluginFactory::with_stream_config(
+                config.protocol.stream.clone(),
+            )),
+        ];
+
+        factories
+            .into_iter()
+            .fold(PluginManifest::new(), |manifest, factory| {
+                manifest.with_entry(PluginManifestEntry::from_factory(factory))
+            })
+    }
+
     /// Initialize plugin factories
     async fn initialize_plugins(&self) -> Result<()> {
         let mut manager = self.plugin_manager.write().await;
         let config = self.config.read().await;
 
-        info!("Registering plugin factories...");
-
-        // Register enabled plugin factories
-        if config.plugins.enable_ping {
-            info!("Registering ping plugin factory");
-            manager
-                .register_factory(Arc::new(PingPluginFactory))
-                .context("Failed to register ping plugin factory")?;
-        }
-
-        if config.plugins.enable_battery {
-            info!("Registering battery plugin factory");
-            manager
-                .register_factory(Arc::new(BatteryPluginFactory))
-                .context("Failed to register battery plugin factory")?;
-        }
-
-        if config.plugins.enable_notification {
-            info!("Registering notification plugin factory");
-            manager
-                .register_factory(Arc::new(NotificationPluginFactory))
-                .context("Failed to register notification plugin factory")?;
-        }
-
-        if config.plugins.enable_share {
-            info!("Registering share plugin factory");
-            manager
-                .register_factory(Arc::new(SharePluginFactory))
-                .context("Failed to register share plugin factory")?;
-        }
-
-        if config.plugins.enable_clipboard {
-            info!("Registering clipboard plugin factory");
-            manager
-                .register_factory(Arc::new(ClipboardPluginFactory))
-                .context("Failed to register clipboard plugin factory")?;
-        }
+        manager.set_safe_mode(config.plugins.safe_mode_enabled());
 
-        if config.plugins.enable_mpris {
-            info!("Registering MPRIS plugin factory");
-            manager
-                .register_factory(Arc::new(MprisPluginFactory))
-                .context("Failed to register MPRIS plugin factory")?;
-        }
-
-        if config.plugins.enable_runcommand {
-            info!("Registering RunCommand plugin factory");
-            manager
-                .register_factory(Arc::new(RunCommandPluginFactory))
-                .context("Failed to register RunCommand plugin factory")?;
-        }
-
-        if config.plugins.enable_remoteinput {
-            info!("Registering Remote Input plugin factory");
-            manager
-                .register_factory(Arc::new(RemoteInputPluginFactory))
-                .context("Failed to register Remote Input plugin factory")?;
-        }
-
-        if config.plugins.enable_findmyphone {
-            info!("Registering Find My Phone plugin factory");
-            manager
-                .register_factory(Arc::new(FindMyPhonePluginFactory))
-                .context("Failed to register Find My Phone plugin factory")?;
-        }
-
-        if config.plugins.enable_lock {
-            info!("Registering Lock plugin factory");
-            manager
-                .register_factory(Arc::new(LockPluginFactory))
-                .context("Failed to register Lock plugin factory")?;
-        }
-
-        if config.plugins.enable_telephony {
-            info!("Registering Telephony/SMS plugin factory");
-            manager
-                .register_factory(Arc::new(TelephonyPluginFactory))
-                .context("Failed to register Telephony plugin factory")?;
-        }
-
-        if config.plugins.enable_presenter {
-            info!("Registering Presenter plugin factory");
-            manager
-                .register_factory(Arc::new(PresenterPluginFactory))
-                .context("Failed to register Presenter plugin factory")?;
-        }
-
-        if config.plugins.enable_contacts {
-            info!("Registering Contacts plugin factory");
-            manager
-                .register_factory(Arc::new(ContactsPluginFactory))
-                .context("Failed to register Contacts plugin factory")?;
-        }
-
-        if config.plugins.enable_systemmonitor {
-            info!("Registering SystemMonitor plugin factory");
-            manager
-                .register_factory(Arc::new(SystemMonitorPluginFactory))
-                .context("Failed to register SystemMonitor plugin factory")?;
-        }
-
-        if config.plugins.enable_wol {
-            info!("Registering Wake-on-LAN plugin factory");
-            manager
-                .register_factory(Arc::new(WolPluginFactory))
-                .context("Failed to register WOL plugin factory")?;
-        }
-
-        if config.plugins.enable_screenshot {
-            info!("Registering Screenshot plugin factory");
-            manager
-                .register_factory(Arc::new(ScreenshotPluginFactory))
-                .context("Failed to register Screenshot plugin factory")?;
-        }
-
-        if config.plugins.enable_remotedesktop {
-            info!("Registering RemoteDesktop plugin factory");
-            manager
-                .register_factory(Arc::new(RemoteDesktopPluginFactory))
-                .context("Failed to register RemoteDesktop plugin factory")?;
-        }
-
-        if config.plugins.enable_power {
-            info!("Registering Power plugin factory");
-            manager
-                .register_factory(Arc::new(PowerPluginFactory))
-                .context("Failed to register Power plugin factory")?;
-        }
-
-        if config.plugins.enable_clipboardhistory {
-            info!("Registering ClipboardHistory plugin factory");
-            manager
-                .register_factory(Arc::new(ClipboardHistoryPluginFactory))
-                .context("Failed to register ClipboardHistory plugin factory")?;
-        }
-
-        if config.plugins.enable_macro {
-            info!("Registering Macro plugin factory");
-            manager
-                .register_factory(Arc::new(MacroPluginFactory))
-                .context("Failed to register Macro plugin factory")?;
-        }
-
-        if config.plugins.enable_chat {
-            info!("Registering Chat plugin factory");
-            manager
-                .register_factory(Arc::new(ChatPluginFactory))
-                .context("Failed to register Chat plugin factory")?;
-        }
-
-        if config.plugins.enable_audiostream {
-            info!("Registering AudioStream plugin factory");
-            manager
-                .register_factory(Arc::new(AudioStreamPluginFactory))
-                .context("Failed to register AudioStream plugin factory")?;
-        }
-
-        if config.plugins.enable_filesync {
-            info!("Registering FileSync plugin factory");
-            manager
-                .register_factory(Arc::new(FileSyncPluginFactory))
-                .context("Failed to register FileSync plugin factory")?;
-        }
-
-        if config.plugins.enable_screenshare {
-            info!("Registering ScreenShare plugin factory");
-            manager
-                .register_factory(Arc::new(ScreenSharePluginFactory::with_restore_session(
-                    config.plugins.screenshare_restore_session,
-                )))
-                .context("Failed to register ScreenShare plugin factory")?;
-        }
-
-        if config.plugins.enable_mousekeyboardshare {
-            info!("Registering MouseKeyboardShare plugin factory");
-            manager
-                .register_factory(Arc::new(MouseKeyboardSharePluginFactory))
-                .context("Failed to register MouseKeyboardShare plugin factory")?;
-        }
-
-        if config.plugins.enable_networkshare {
-            info!("Registering NetworkShare plugin factory");
-            manager
-                .register_factory(Arc::new(NetworkSharePluginFactory))
-                .context("Failed to register NetworkShare plugin factory")?;
-        }
-
-        if config.plugins.enable_systemvolume {
-            info!("Registering SystemVolume plugin factory");
-            manager
-                .register_factory(Arc::new(SystemVolumePluginFactory))
-                .context("Failed to register SystemVolume plugin factory")?;
-        }
-
-        if config.plugins.enable_connectivityreport {
-            info!("Registering ConnectivityReport plugin factory");
-            manager
-                .register_factory(Arc::new(ConnectivityReportPluginFactory))
-                .context("Failed to register ConnectivityReport plugin factory")?;
-        }
-
-        if config.plugins.enable_camera {
-            info!("Registering Camera plugin factory");
-            manager
-                .register_factory(Arc::new(CameraPluginFactory))
-                .context("Failed to register Camera plugin factory")?;
-        }
+        info!("Registering plugin factories...");
 
-        #[cfg(feature = "extendeddisplay")]
-        if config.plugins.enable_extendeddisplay {
-            info!("Registering ExtendedDisplay plugin factory");
-            manager
-                .register_factory(Arc::new(ExtendedDisplayPluginFactory::new()))
-                .context("Failed to register ExtendedDisplay plugin factory")?;
-        }
+        // Register the enabled plugins from the manifest; their capabilities
+        // are advertised in the identity sent by discovery and on handshake
+        let registered = manager
+            .register_manifest(&Self::plugin_manifest(&config), |entry| {
+                config.plugins.is_enabled(&entry.id)
+            })
+            .context("Failed to register plugin factories")?;
+        info!("Registered plugin factories: {}", registered.join(", "));
 
         info!(
             "All plugin factories registered ({} total)",
@@ -622,6 +551,9 @@ impl Daemon {
             device_timeout: Duration::from_secs(config.network.device_timeout),
             enable_timeout_check: true,
             additional_broadcast_addrs: default_additional_broadcast_addrs(),
+            enable_mdns: config.network.enable_mdns,
+            enable_ipv6: config.network.enable_ipv6,
+            ..config.protocol.discovery.clone()
         };
         drop(config);
 
@@ -629,6 +561,11 @@ impl Daemon {
         let mut discovery_service = DiscoveryService::new(device_info, discovery_config)
             .context("Failed to create discovery service")?;
 
+        if let Some(cadence) = &self.power_cadence {
+            discovery_service.follow_broadcast_interval(cadence.watch_broadcast_interval());
+            cadence.clone().spawn();
+        }
+
         // Subscribe to discovery events
         let mut event_rx = discovery_service.subscribe().await;
 
@@ -651,7 +588,6 @@ impl Daemon {
         let dbus_server = self.dbus_server.clone();
         let error_handler = self.error_handler.clone();
         let connection_manager = self.connection_manager.clone();
-        let connection_attempts = self.connection_attempts.clone();
         tokio::spawn(async move {
             while let Some(event) = event_rx.recv().await {
                 if let Err(e) = Self::handle_discovery_event(
@@ -660,7 +596,6 @@ impl Daemon {
                     &dbus_server,
                     &error_handler,
                     &connection_manager,
-                    &connection_attempts,
                 )
                 .await
                 {
@@ -682,7 +617,8 @@ impl Daemon {
         // Create pairing service with certificate directory from config
         let pairing_config = PairingConfig {
             cert_dir: config.paths.cert_dir.clone(),
-            timeout: Duration::from_secs(30),
+            timeout: config.protocol.pairing.timeout,
+            timeout_override: config.protocol.pairing.timeout_override,
         };
 
         let pairing_service =
@@ -720,12 +656,20 @@ impl Daemon {
         let pending_pairing_requests = self.pending_pairing_requests.clone();
         let error_handler = self.error_handler.clone();
         let plugin_manager = self.plugin_manager.clone();
+        let connection_mgr = self.connection_manager.clone();
         let packet_sender = self.packet_sender.clone();
         let tls_config = self.tls_config.clone();
+        let config = self.config.clone();
+        let device_config_registry = self.device_config_registry.clone();
+        let transport_manager = self.transport_manager.clone();
+        let transfer_gate = self.transfer_gate.clone();
+        let resource_manager = self.resource_manager.clone();
+        let recovery_manager = self.recovery_manager.clone();
         tokio::spawn(async move {
             while let Some(event) = event_rx.recv().await {
                 if let Err(e) = Self::handle_pairing_event(
                     event,
+                    &pairing_service,
                     &device_manager,
                     &dbus_server,
                     &cosmic_notifier,
@@ -733,8 +677,15 @@ impl Daemon {
                     &pending_pairing_requests,
                     &error_handler,
                     &plugin_manager,
+                    &connection_mgr,
                     &packet_sender,
                     &tls_config,
+                    &config,
+                    &device_config_registry,
+                    &transport_manager,
+                    &transfer_gate,
+                    &resource_manager,
+                    &recovery_manager,
                 )
                 .await
                 {
@@ -753,6 +704,7 @@ impl Daemon {
     #[allow(clippy::too_many_arguments)]
     async fn handle_pairing_event(
         event: PairingEvent,
+        pairing_service: &Arc<RwLock<PairingService>>,
         device_manager: &Arc<RwLock<DeviceManager>>,
         dbus_server: &Option<Arc<DbusServer>>,
         cosmic_notifier: &Option<Arc<cosmic_notifications::CosmicNotifier>>,
@@ -760,8 +712,15 @@ impl Daemon {
         pending_pairing_requests: &Arc<RwLock<std::collections::HashMap<String, bool>>>,
         error_handler: &ErrorHandler,
         plugin_manager: &Arc<RwLock<PluginManager>>,
+        connection_mgr: &Arc<RwLock<ConnectionManager>>,
         packet_sender: &Sender<(String, Packet)>,
         tls_config: &Arc<cosmic_ext_connect_protocol::TlsConfig>,
+        config: &Arc<RwLock<Config>>,
+        device_config_registry: &Arc<RwLock<device_config::DeviceConfigRegistry>>,
+        transport_manager: &Option<Arc<TransportManager>>,
+        transfer_gate: &TransferGate,
+        resource_manager: &Arc<ResourceManager>,
+        recovery_manager: &Arc<RecoveryManager>,
     ) -> Result<()> {
         match event {
             PairingEvent::RequestSent {
@@ -783,6 +742,13 @@ impl Daemon {
                     device_name, device_id, their_fingerprint
                 );
                 info!("User should verify fingerprints match on both devices");
+                Self::check_device_id_conflict(
+                    device_manager,
+                    dbus_server,
+                    &device_id,
+                    &their_fingerprint,
+                )
+                .await;
 
                 // Track pending pairing request
                 pending_pairing_requests
@@ -819,6 +785,29 @@ impl Daemon {
                     warn!("COSMIC notifier not available for pairing request");
                 }
             }
+            PairingEvent::PinGenerated { device_id, pin } => {
+                // Only the UI gets the PIN; it must not end up in the logs
+                info!("Pairing PIN generated for device {}", device_id);
+                if let Some(dbus) = dbus_server {
+                    if let Err(e) = dbus.emit_pairing_pin(&device_id, &pin).await {
+                        warn!("Failed to emit PairingPin signal: {}", e);
+                    }
+                }
+            }
+            PairingEvent::PinRequired {
+                device_id,
+                device_name,
+            } => {
+                info!(
+                    "Pairing request from {} ({}) must be confirmed with a PIN",
+                    device_name, device_id
+                );
+                if let Some(dbus) = dbus_server {
+                    if let Err(e) = dbus.emit_pairing_pin_required(&device_id).await {
+                        warn!("Failed to emit PairingPinRequired signal: {}", e);
+                    }
+                }
+            }
             PairingEvent::PairingAccepted {
                 device_id,
                 device_name,
@@ -827,11 +816,20 @@ impl Daemon {
                 info!("Pairing accepted with {} ({})", device_name, device_id);
                 Self::clear_pending_pairing_request(pending_pairing_requests, &device_id).await;
 
+                // A copy presenting a known ID is paired under its own alias
+                let paired_id = Self::check_device_id_conflict(
+                    device_manager,
+                    dbus_server,
+                    &device_id,
+                    &certificate_fingerprint,
+                )
+                .await;
+
                 // Mark device as paired and save to disk
                 {
                     let mut manager = device_manager.write().await;
                     if let Err(e) = manager
-                        .mark_paired(&device_id, certificate_fingerprint.clone())
+                        .mark_paired(&paired_id, certificate_fingerprint.clone())
                         .and_then(|()| manager.save_registry())
                     {
                         error!("Failed to persist pairing for device {}: {}", device_id, e);
@@ -843,6 +841,29 @@ impl Daemon {
                     }
                 }
 
+                // A reinstalled app pairs under a new ID; offer to keep its settings
+                let migration_offer = device_manager.write().await.offer_migration(&device_id);
+                if let Some(offer) = migration_offer {
+                    if let Some(dbus) = dbus_server {
+                        if let Err(e) = dbus.emit_identity_migration_offered(&offer).await {
+                            warn!("Failed to emit IdentityMigrationOffered signal: {}", e);
+                        }
+                    }
+                    if let Some(notifier) = cosmic_notifier {
+                        match notifier.notify_identity_migration(&offer.device_name).await {
+                            Ok(notification_id) => {
+                                pairing_notifications
+                                    .write()
+                                    .await
+                                    .insert(notification_id, device_id.clone());
+                            }
+                            Err(e) => {
+                                warn!("Failed to send identity migration notification: {}", e);
+                            }
+                        }
+                    }
+                }
+
                 // Initialize plugins for newly paired device
                 // This handles the case where device connected first, then paired later
                 {
@@ -859,6 +880,10 @@ impl Daemon {
                             );
                         } else {
                             info!("Initialized plugins for device {} after pairing", device_id);
+                            device_config_registry
+                                .read()
+                                .await
+                                .apply_plugin_overrides(&device_id, &plug_manager);
 
                             // Set TLS config on SharePlugin for secure file transfers
                             if let Some(plugin) =
@@ -869,12 +894,106 @@ impl Daemon {
                                     plugin.as_any_mut().downcast_mut::<SharePlugin>()
                                 {
                                     share_plugin.set_tls_config(tls_config.clone());
+                                    share_plugin.set_size_limits(
+                                        device_config_registry
+                                            .read()
+                                            .await
+                                            .file_size_limits(&config.read().await.plugins),
+                                    );
+                                    share_plugin.set_completion_hooks(
+                                        config.read().await.plugins.share_completion_hooks(),
+                                    );
+                                    share_plugin.set_metadata_sidecars(
+                                        config.read().await.plugins.share_metadata_sidecars,
+                                    );
+                                    share_plugin.set_verify_checksums(
+                                        config.read().await.plugins.share_verify_checksums,
+                                    );
+                                    share_plugin.set_user_directories(
+                                        config.read().await.plugins.share_user_directories(),
+                                    );
+                                    share_plugin.set_payload_key(
+                                        dbus::payload_key(Some(pairing_service), &device_id).await,
+                                    );
+                                    let mut gate = transfer_gate.clone();
+                                    gate.set_levels(
+                                        device_config_registry
+                                            .read()
+                                            .await
+                                            .receive_trust_levels(&config.read().await.plugins),
+                                    );
+                                    share_plugin.set_transfer_gate(gate);
+                                    share_plugin.set_resource_manager(resource_manager.clone());
+                                    share_plugin.set_recovery_manager(recovery_manager.clone());
+                                    share_plugin.set_traffic_counter(
+                                        connection_mgr
+                                            .read()
+                                            .await
+                                            .traffic_counter(&device_id)
+                                            .await,
+                                    );
                                     debug!(
                                         "Set TLS config on SharePlugin for device {}",
                                         device_id
                                     );
                                 }
                             }
+
+                            // Send oversized clipboard content as payloads, over TLS
+                            if let Some(plugin) =
+                                plug_manager.get_device_plugin_mut(&device_id, "clipboard")
+                            {
+                                use cosmic_ext_connect_protocol::plugins::clipboard::ClipboardPlugin;
+                                if let Some(clipboard_plugin) =
+                                    plugin.as_any_mut().downcast_mut::<ClipboardPlugin>()
+                                {
+                                    clipboard_plugin.set_tls_config(tls_config.clone());
+                                    clipboard_plugin.set_traffic_counter(
+                                        connection_mgr
+                                            .read()
+                                            .await
+                                            .traffic_counter(&device_id)
+                                            .await,
+                                    );
+                                    clipboard_plugin.set_max_inline_length(
+                                        config.read().await.plugins.clipboard_max_inline_length,
+                                    );
+                                }
+                            }
+
+                            // Download notification icons into the user cache and
+                            // display what the plugin posts
+                            if let Some(plugin) =
+                                plug_manager.get_device_plugin_mut(&device_id, "notification")
+                            {
+                                use cosmic_ext_connect_protocol::plugins::notification::{
+                                    NotificationIconCache, NotificationPlugin,
+                                };
+                                if let Some(notification_plugin) =
+                                    plugin.as_any_mut().downcast_mut::<NotificationPlugin>()
+                                {
+                                    notification_plugin.set_tls_config(tls_config.clone());
+                                    if let Some(cache_dir) = dirs::cache_dir() {
+                                        notification_plugin.set_icon_cache(
+                                            NotificationIconCache::new(
+                                                cache_dir
+                                                    .join("cconnect")
+                                                    .join("notification-icons"),
+                                            ),
+                                        );
+                                    }
+                                    if let Some(notifier) = cosmic_notifier {
+                                        tokio::spawn(Self::display_posted_notifications(
+                                            device_id.clone(),
+                                            notification_plugin.subscribe(),
+                                            device_manager.clone(),
+                                            device_config_registry.clone(),
+                                            notifier.clone(),
+                                            dbus_server.clone(),
+                                        ));
+                                    }
+                                }
+                            }
                         }
                     } else {
                         warn!("Device {} not found in manager after pairing", device_id);
@@ -900,15 +1019,44 @@ impl Daemon {
                     if let Err(e) = dbus.emit_pairing_status_changed(&device_id, "paired").await {
                         warn!("Failed to emit PairingStatusChanged signal: {}", e);
                     }
+                    dbus.record_device_event(&device_id, event_feed::DeviceEventKind::Paired)
+                        .await;
+                }
+
+                // A device paired over Bluetooth moves to WiFi if it announced an address
+                if let Some(transport_manager) = transport_manager {
+                    let transport_manager = transport_manager.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = transport_manager.upgrade_to_wifi(&device_id).await {
+                            warn!(
+                                "Failed to move {} to WiFi, staying on Bluetooth: {}",
+                                device_id, e
+                            );
+                        }
+                    });
                 }
             }
             PairingEvent::PairingRejected { device_id, reason } => {
-                info!(
-                    "Pairing rejected with device {} (reason: {:?})",
-                    device_id, reason
-                );
+                info!("Pairing rejected with device {} ({})", device_id, reason);
                 Self::clear_pending_pairing_request(pending_pairing_requests, &device_id).await;
 
+                let device_name = {
+                    let mut manager = device_manager.write().await;
+                    let device = manager.get_device(&device_id);
+                    let device_name = device.map(|device| device.info.device_name.clone());
+                    if device.is_some_and(|device| !device.is_paired()) {
+                        if let Err(e) = manager
+                            .update_pairing_status(&device_id, PairingStatus::Rejected { reason })
+                        {
+                            warn!(
+                                "Failed to update device {} pairing status: {}",
+                                device_id, e
+                            );
+                        }
+                    }
+                    device_name.unwrap_or_else(|| device_id.clone())
+                };
+
                 if let Some(dbus) = dbus_server {
                     if let Err(e) = dbus
                         .emit_pairing_status_changed(&device_id, "rejected")
@@ -916,6 +1064,31 @@ impl Daemon {
                     {
                         warn!("Failed to emit PairingStatusChanged signal: {}", e);
                     }
+                    if let Err(e) = dbus
+                        .emit_pairing_rejected(&device_id, &reason.to_string())
+                        .await
+                    {
+                        warn!("Failed to emit PairingRejected signal: {}", e);
+                    }
+                    dbus.record_device_event(
+                        &device_id,
+                        event_feed::DeviceEventKind::PairingRejected {
+                            reason: Some(reason.to_string()),
+                        },
+                    )
+                    .await;
+                }
+
+                // Explain failures the user did not choose
+                if reason != RejectReason::UserDeclined {
+                    if let Some(notifier) = cosmic_notifier {
+                        if let Err(e) = notifier
+                            .notify_pairing_error(&device_name, &reason.to_string())
+                            .await
+                        {
+                            warn!("Failed to send pairing failure notification: {}", e);
+                        }
+                    }
                 }
             }
             PairingEvent::StatusChanged { device_id, status } => {
@@ -923,6 +1096,11 @@ impl Daemon {
             }
             PairingEvent::DeviceUnpaired { device_id } => {
                 info!("Device unpaired: {}", device_id);
+                resource_manager.forget_device(&device_id).await;
+                if let Some(dbus) = dbus_server {
+                    dbus.record_device_event(&device_id, event_feed::DeviceEventKind::Unpaired)
+                        .await;
+                }
                 let mut manager = device_manager.write().await;
                 if let Err(e) = manager.update_pairing_status(&device_id, PairingStatus::Unpaired) {
                     warn!(
@@ -944,13 +1122,22 @@ impl Daemon {
                 }
             }
             PairingEvent::PairingTimeout { device_id } => {
+                // Reported to the user with the rejection that follows
                 warn!("Pairing request timed out for device {}", device_id);
-                let error = cosmic_ext_connect_protocol::ProtocolError::Timeout(
-                    "Pairing request timed out".to_string(),
-                );
-                error_handler
-                    .handle_error(&error, "pairing", Some(&device_id))
-                    .await;
+            }
+            PairingEvent::RequestExpired { device_id } => {
+                info!("Pairing request from {} expired unanswered", device_id);
+                Self::clear_pending_pairing_request(pending_pairing_requests, &device_id).await;
+
+                // Let the applet dismiss its accept/reject prompt
+                if let Some(dbus) = dbus_server {
+                    if let Err(e) = dbus
+                        .emit_pairing_status_changed(&device_id, "expired")
+                        .await
+                    {
+                        warn!("Failed to emit PairingStatusChanged signal: {}", e);
+                    }
+                }
             }
             PairingEvent::Error { device_id, message } => {
                 error!("Pairing error for device {:?}: {}", device_id, message);
@@ -978,6 +1165,29 @@ impl Daemon {
         }
     }
 
+    /// Keep a second device presenting a known ID apart from the first
+    ///
+    /// Returns the ID the device presenting `fingerprint` is known by.
+    async fn check_device_id_conflict(
+        device_manager: &Arc<RwLock<DeviceManager>>,
+        dbus_server: &Option<Arc<DbusServer>>,
+        device_id: &str,
+        fingerprint: &str,
+    ) -> String {
+        let mut manager = device_manager.write().await;
+        if let Some(conflict) = manager.detect_id_conflict(device_id, fingerprint) {
+            if let Err(e) = manager.save_registry() {
+                warn!("Failed to save device registry: {}", e);
+            }
+            if let Some(dbus) = dbus_server {
+                if let Err(e) = dbus.emit_device_id_conflict(&conflict).await {
+                    warn!("Failed to emit DeviceIdConflict signal: {}", e);
+                }
+            }
+        }
+        manager.resolve_device_id(device_id, fingerprint)
+    }
+
     /// Start connection manager
     async fn start_connections(&mut self) -> Result<()> {
         info!("Starting connection manager...");
@@ -1011,6 +1221,9 @@ impl Daemon {
             let config = self.config.clone();
             let error_handler = Some(self.error_handler.clone());
             let tls_config = self.tls_config.clone();
+            let transfer_gate = self.transfer_gate.clone();
+            let resource_manager = self.resource_manager.clone();
+            let recovery_manager = self.recovery_manager.clone();
             tokio::spawn(async move {
                 while let Some(event) = event_rx.recv().await {
                     // Convert TransportManagerEvent to ConnectionEvent
@@ -1056,6 +1269,15 @@ impl Daemon {
                                 remote_addr: BT_PLACEHOLDER_ADDR,
                             }
                         }
+                        TransportManagerEvent::TransportSwitched {
+                            device_id,
+                            from,
+                            to,
+                        } => {
+                            // Same session on another link, plugins keep running
+                            info!("Device {} moved from {:?} to {:?}", device_id, from, to);
+                            continue;
+                        }
                         TransportManagerEvent::Started { transport_type } => {
                             info!("Transport {:?} started", transport_type);
                             continue;
@@ -1085,6 +1307,9 @@ impl Daemon {
                         &config,
                         &error_handler,
                         &tls_config,
+                        &transfer_gate,
+                        &resource_manager,
+                        &recovery_manager,
                     )
                     .await
                     {
@@ -1127,6 +1352,9 @@ impl Daemon {
             let config = self.config.clone();
             let error_handler = Some(self.error_handler.clone());
             let tls_config = self.tls_config.clone();
+            let transfer_gate = self.transfer_gate.clone();
+            let resource_manager = self.resource_manager.clone();
+            let recovery_manager = self.recovery_manager.clone();
             tokio::spawn(async move {
                 while let Some(event) = event_rx.recv().await {
                     if let Err(e) = Self::handle_connection_event(
@@ -1144,6 +1372,9 @@ impl Daemon {
                         &config,
                         &error_handler,
                         &tls_config,
+                        &transfer_gate,
+                        &resource_manager,
+                        &recovery_manager,
                     )
                     .await
                     {
@@ -1173,6 +1404,9 @@ impl Daemon {
             self.pending_pairing_requests.clone(),
             self.metrics.clone(),
             self.config.clone(),
+            self.transfer_prompts.clone(),
+            self.error_handler.history(),
+            self.resource_manager.clone(),
         )
         .await
         .context("Failed to start DBus server")?;
@@ -1184,6 +1418,109 @@ impl Daemon {
         Ok(())
     }
 
+    /// Ask the user about incoming files the receive gate holds back
+    ///
+    /// Each prompt is announced over DBus and as a notification with
+    /// Accept/Decline actions. Dismissing the notification leaves the prompt
+    /// unanswered, so the gate declines it once the prompt timeout passes.
+    async fn start_transfer_prompts(&self) -> Result<()> {
+        let Some(mut prompt_rx) = self.transfer_prompt_receiver.lock().await.take() else {
+            return Ok(());
+        };
+
+        let prompt_timeout = self.config.read().await.plugins.share_prompt_timeout();
+        let transfer_prompts = self.transfer_prompts.clone();
+        let device_manager = self.device_manager.clone();
+        let dbus_server = self.dbus_server.clone();
+        let cosmic_notifier = self.cosmic_notifier.clone();
+        // Notification IDs to the prompts they ask about
+        let prompt_notifications: Arc<RwLock<std::collections::HashMap<u32, String>>> =
+            Arc::new(RwLock::new(std::collections::HashMap::new()));
+
+        if let Some(notifier) = &self.cosmic_notifier {
+            let notifier = notifier.clone();
+            let transfer_prompts = transfer_prompts.clone();
+            let prompt_notifications = prompt_notifications.clone();
+            tokio::spawn(async move {
+                use futures::StreamExt;
+
+                let mut action_stream = match notifier.subscribe_actions().await {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!("Failed to subscribe to transfer prompt actions: {}", e);
+                        return;
+                    }
+                };
+                while let Some((notification_id, action_key)) = action_stream.next().await {
+                    let Some(prompt_id) =
+                        prompt_notifications.write().await.remove(&notification_id)
+                    else {
+                        continue;
+                    };
+                    let answered = match action_key.as_str() {
+                        "accept_transfer" => transfer_prompts.accept(&prompt_id),
+                        "decline_transfer" => transfer_prompts.decline(&prompt_id),
+                        _ => {
+                            // Dismissed: the prompt times out to a decline
+                            debug!("Transfer prompt {} dismissed", prompt_id);
+                            continue;
+                        }
+                    };
+                    if !answered {
+                        debug!("Transfer prompt {} already expired", prompt_id);
+                    }
+                }
+            });
+        }
+
+        tokio::spawn(async move {
+            while let Some(prompt) = prompt_rx.recv().await {
+                let device_id = prompt.device_id.clone();
+                let filename = prompt.filename.clone();
+                let size = prompt.size;
+                let device_name = device_manager
+                    .read()
+                    .await
+                    .get_device(&device_id)
+                    .map(|device| device.name().to_string())
+                    .unwrap_or_else(|| device_id.clone());
+                let prompt_id = transfer_prompts.insert(prompt);
+                info!(
+                    "Asking whether to receive '{}' from {}",
+                    filename, device_name
+                );
+
+                if let Some(dbus) = &dbus_server {
+                    if let Err(e) = dbus
+                        .emit_incoming_transfer_prompt(&prompt_id, &device_id, &filename, size)
+                        .await
+                    {
+                        warn!("Failed to emit IncomingTransferPrompt signal: {}", e);
+                    }
+                }
+
+                if let Some(notifier) = &cosmic_notifier {
+                    match notifier
+                        .notify_transfer_prompt(&device_name, &filename, size, prompt_timeout)
+                        .await
+                    {
+                        Ok(notification_id) => {
+                            let mut notifications = prompt_notifications.write().await;
+                            // Forget notifications whose prompts were answered elsewhere
+                            notifications
+                                .retain(|_, prompt_id| transfer_prompts.contains(prompt_id));
+                            notifications.insert(notification_id, prompt_id);
+                        }
+                        Err(e) => warn!("Failed to show transfer prompt: {}", e),
+                    }
+                }
+            }
+            info!("Transfer prompt handler stopped");
+        });
+
+        Ok(())
+    }
+
     /// Start MPRIS player monitoring
     async fn start_mpris_monitoring(&self) -> Result<()> {
         let Some(mpris_manager) = &self.mpris_manager else {
@@ -1222,6 +1559,7 @@ impl Daemon {
 
         info!("Starting clipboard monitor...");
 
+        let image_config = config.plugins.clipboard_images.clone();
         let device_manager = self.device_manager.clone();
         let plugin_manager = self.plugin_manager.clone();
         let connection_manager = self.connection_manager.clone();
@@ -1241,6 +1579,7 @@ impl Daemon {
             };
 
             let mut last_content = String::new();
+            let mut last_image_hash = None;
             let poll_interval = Duration::from_millis(500);
 
             info!(
@@ -1254,7 +1593,72 @@ impl Daemon {
                 // Read current clipboard content
                 let current_content = match clipboard.get_text() {
                     Ok(text) => text,
-                    Err(_) => continue, // Clipboard might be empty or contain non-text
+                    Err(_) => {
+                        // Clipboard might be empty or contain an image
+                        if !image_config.enabled {
+                            continue;
+                        }
+                        let Ok(copied) = clipboard.get_image() else {
+                            continue;
+                        };
+
+                        let hash = {
+                            use std::hash::{Hash, Hasher};
+                            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                            (copied.width, copied.height).hash(&mut hasher);
+                            copied.bytes.hash(&mut hasher);
+                            hasher.finish()
+                        };
+                        if last_image_hash == Some(hash) {
+                            continue;
+                        }
+                        last_image_hash = Some(hash);
+
+                        let Some(rgba) = image::RgbaImage::from_raw(
+                            copied.width as u32,
+                            copied.height as u32,
+                            copied.bytes.into_owned(),
+                        ) else {
+                            warn!("Clipboard image has an unexpected pixel layout");
+                            continue;
+                        };
+
+                        // Devices that sync the clipboard
+                        let connected_devices: Vec<String> = device_manager
+                            .read()
+                            .await
+                            .devices()
+                            .filter(|d| d.is_connected())
+                            .map(|d| d.id().to_string())
+                            .collect();
+                        let plug_manager = plugin_manager.read().await;
+                        let device_ids: Vec<String> = connected_devices
+                            .into_iter()
+                            .filter(|id| {
+                                plug_manager.get_device_plugin(id, "clipboard").is_some()
+                            })
+                            .collect();
+                        drop(plug_manager);
+                        if device_ids.is_empty() {
+                            continue;
+                        }
+
+                        let image_config = image_config.clone();
+                        let connection_manager = connection_manager.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = clipboard_image::send_clipboard_image(
+                                rgba,
+                                image_config,
+                                device_ids,
+                                connection_manager,
+                            )
+                            .await
+                            {
+                                warn!("Failed to send clipboard image: {}", e);
+                            }
+                        });
+                        continue;
+                    }
                 };
 
                 // Check if clipboard changed
@@ -1278,14 +1682,36 @@ impl Daemon {
                                 plug_manager.get_device_plugin(device_id, "clipboard")
                             {
                                 // Downcast to ClipboardPlugin
-                                use cosmic_ext_connect_protocol::plugins::clipboard::ClipboardPlugin;
+                                use cosmic_ext_connect_protocol::plugins::clipboard::{
+                                    ClipboardContent, ClipboardPlugin, ClipboardTransfer,
+                                };
                                 if let Some(clipboard_plugin) =
                                     plugin.as_any().downcast_ref::<ClipboardPlugin>()
                                 {
-                                    // Create clipboard packet
-                                    let packet = clipboard_plugin
-                                        .create_clipboard_packet(current_content.clone())
-                                        .await;
+                                    // Create clipboard packet, serving long text as a payload
+                                    let packet = match clipboard_plugin
+                                        .prepare_content(ClipboardContent::text(
+                                            current_content.clone(),
+                                        ))
+                                        .await
+                                    {
+                                        ClipboardTransfer::Inline(packet) => packet,
+                                        ClipboardTransfer::Payload { packet, content } => {
+                                            match clipboard_plugin
+                                                .serve_payload(packet, content)
+                                                .await
+                                            {
+                                                Ok(packet) => packet,
+                                                Err(e) => {
+                                                    warn!(
+                                                        "Failed to serve clipboard payload for {}: {}",
+                                                        device_id, e
+                                                    );
+                                                    continue;
+                                                }
+                                            }
+                                        }
+                                    };
 
                                     // Send packet via connection manager
                                     let conn_manager = connection_manager.read().await;
@@ -1323,7 +1749,8 @@ impl Daemon {
             let notifier_clone = notifier.clone();
             let pairing_service = self.pairing_service.clone();
             let pairing_notifications = self.pairing_notifications.clone();
-            let _device_manager = self.device_manager.clone();
+            let device_manager = self.device_manager.clone();
+            let connection_manager = self.connection_manager.clone();
 
             tokio::spawn(async move {
                 use futures::StreamExt;
@@ -1368,6 +1795,29 @@ impl Daemon {
                                                 error!("Failed to reject pairing: {}", e);
                                             }
                                         }
+                                        "migrate" => {
+                                            drop(pairing);
+                                            if let Err(e) = dbus::migrate_device_identity(
+                                                &device_manager,
+                                                Some(pairing_svc),
+                                                &connection_manager,
+                                                &device_id,
+                                            )
+                                            .await
+                                            {
+                                                error!("Failed to migrate device: {:#}", e);
+                                            }
+                                        }
+                                        "keep_separate" => {
+                                            info!(
+                                                "Keeping {} separate from its previous identity",
+                                                device_id
+                                            );
+                                            device_manager
+                                                .write()
+                                                .await
+                                                .decline_migration(&device_id);
+                                        }
                                         _ => {
                                             if action_key.starts_with("open_web:") {
                                                 let url = action_key
@@ -1405,6 +1855,16 @@ impl Daemon {
                                 // Remove notification from tracking
                                 let mut notifications = pairing_notifications.write().await;
                                 notifications.remove(&notification_id);
+                            } else if let Some(url) = action_key.strip_prefix("open_url:") {
+                                info!("Opening shared URL from notification: {}", url);
+                                let url = url.to_string();
+                                tokio::spawn(async move {
+                                    if let Err(e) =
+                                        tokio::process::Command::new("xdg-open").arg(url).spawn()
+                                    {
+                                        error!("Failed to open shared URL: {}", e);
+                                    }
+                                });
                             }
                         }
                     }
@@ -1615,14 +2075,228 @@ impl Daemon {
                     Err(e) => {
                         warn!("Failed to convert image from path to PNG: {}", e);
                     }
-                },
-                Err(e) => {
-                    trace!("Failed to load image from path {}: {}", image_path, e);
+                },
+                Err(e) => {
+                    trace!("Failed to load image from path {}: {}", image_path, e);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Display the notifications a device's notification plugin posts
+    ///
+    /// Runs until the plugin is dropped. Notifications arrive here after the
+    /// plugin's per-app rate limit, with their icon payload resolved. An
+    /// update to a notification replaces the one already on the desktop.
+    async fn display_posted_notifications(
+        device_id: String,
+        mut posted: tokio::sync::broadcast::Receiver<NotificationPosted>,
+        device_manager: Arc<RwLock<DeviceManager>>,
+        device_config_registry: Arc<RwLock<device_config::DeviceConfigRegistry>>,
+        notifier: Arc<cosmic_notifications::CosmicNotifier>,
+        dbus_server: Option<Arc<DbusServer>>,
+    ) {
+        use tokio::sync::broadcast::error::RecvError;
+
+        // Device notification ID -> desktop notification ID
+        let mut shown: std::collections::HashMap<String, u32> = std::collections::HashMap::new();
+        loop {
+            let posted = match posted.recv().await {
+                Ok(posted) => posted,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Skipped {} notifications from {}", skipped, device_id);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            // Preexisting notifications are listed, not shown again
+            if posted.notification.is_silent() {
+                continue;
+            }
+
+            let device_name = device_manager
+                .read()
+                .await
+                .get_device(&device_id)
+                .map(|d| d.name().to_string())
+                .unwrap_or_else(|| device_id.clone());
+            let notification_pref = device_config_registry
+                .read()
+                .await
+                .get(&device_id)
+                .map(|config| config.get_notification_preference())
+                .unwrap_or(device_config::NotificationPreference::All);
+
+            let id = posted.notification.id.clone();
+            let replaces_id = shown.get(&id).copied();
+            if let Some(desktop_id) = Self::show_posted_notification(
+                &posted,
+                &device_name,
+                notification_pref,
+                replaces_id,
+                &notifier,
+                &dbus_server,
+            )
+            .await
+            {
+                if replaces_id.is_none() && shown.len() >= MAX_SHOWN_NOTIFICATIONS {
+                    shown.clear();
+                }
+                shown.insert(id, desktop_id);
+            }
+        }
+    }
+
+    /// Show a posted notification if the device's preference allows it
+    ///
+    /// Returns the ID of the desktop notification, if one was shown.
+    async fn show_posted_notification(
+        posted: &NotificationPosted,
+        device_name: &str,
+        notification_pref: device_config::NotificationPreference,
+        replaces_id: Option<u32>,
+        notifier: &cosmic_notifications::CosmicNotifier,
+        dbus_server: &Option<Arc<DbusServer>>,
+    ) -> Option<u32> {
+        let notification = &posted.notification;
+        let app_name = notification.app_name.as_str();
+        let title = notification.title.as_str();
+        let text = notification.text.as_str();
+        let icon = posted.icon.as_icon_name();
+
+        // Apply notification filtering based on preference
+        let should_show = match notification_pref {
+            device_config::NotificationPreference::All => true,
+            device_config::NotificationPreference::Important => {
+                // Important includes messaging apps, calls, alarms
+                notification.is_messaging_app
+                    || app_name.to_lowercase().contains("phone")
+                    || app_name.to_lowercase().contains("call")
+                    || app_name.to_lowercase().contains("alarm")
+                    || app_name.to_lowercase().contains("clock")
+            }
+            device_config::NotificationPreference::None => false,
+        };
+        if !should_show {
+            debug!(
+                "Notification from {} filtered based on preference {:?}",
+                device_name, notification_pref
+            );
+            return None;
+        }
+
+        if notification.is_messaging_app {
+            let result = notifier
+                .notify_messaging(
+                    device_name,
+                    app_name,
+                    title,
+                    text,
+                    notification.rich_body.as_deref(),
+                    notification.web_url.as_deref(),
+                    match &posted.icon {
+                        NotificationIcon::Cached(_) => Some(icon.as_str()),
+                        NotificationIcon::Default => None,
+                    },
+                    replaces_id,
+                )
+                .await;
+            if let Err(e) = &result {
+                warn!("Failed to send messaging notification: {}", e);
+            }
+
+            // Emit D-Bus signal for cosmic-messages
+            if let Some(dbus) = dbus_server {
+                let conv_id = notification.conversation_id.as_deref().unwrap_or("");
+                if let Err(e) = dbus
+                    .emit_messaging_notification(app_name, title, text, conv_id)
+                    .await
+                {
+                    warn!("Failed to emit messaging D-Bus signal: {}", e);
+                }
+            }
+            return result.ok();
+        }
+
+        // Issue #180: Check multiple image sources from Android
+        // Priority order:
+        // 1. imageData - Main notification image/large icon
+        // 2. senderAvatar - For messaging notifications
+        // 3. appIcon - Fallback to app icon
+        debug!(
+            "Notification image sources: imageData={}, senderAvatar={}, appIcon={}",
+            notification.image_data.is_some(),
+            notification.sender_avatar.is_some(),
+            notification.app_icon.is_some()
+        );
+
+        // Helper closure to decode a base64 image field
+        let try_decode_image_field =
+            |field: &str, base64_data: Option<&String>| -> Option<(Vec<u8>, i32, i32)> {
+                let bytes = match general_purpose::STANDARD.decode(base64_data?) {
+                    Ok(b) => b,
+                    Err(e) => {
+                        debug!("Failed to decode base64 for {}: {}", field, e);
+                        return None;
+                    }
+                };
+
+                // Load as image to get dimensions
+                match image::load_from_memory(&bytes) {
+                    Ok(img) => {
+                        let width = img.width() as i32;
+                        let height = img.height() as i32;
+                        // Convert to RGBA8 and get raw bytes
+                        let rgba = img.to_rgba8();
+                        debug!("Decoded {} image: {}x{}", field, width, height);
+                        Some((rgba.into_raw(), width, height))
+                    }
+                    Err(e) => {
+                        debug!("Failed to decode image for {}: {}", field, e);
+                        None
+                    }
                 }
-            }
+            };
+
+        // Extract best available image (Issue #180)
+        let image_bytes = try_decode_image_field("imageData", notification.image_data.as_ref())
+            .or_else(|| try_decode_image_field("senderAvatar", notification.sender_avatar.as_ref()))
+            .or_else(|| try_decode_image_field("appIcon", notification.app_icon.as_ref()));
+
+        if let Some((_, w, h)) = &image_bytes {
+            debug!("Using notification image: {}x{}", w, h);
         }
 
-        None
+        // Send notification with or without image
+        let result = if image_bytes.is_some() {
+            notifier
+                .notify_rich_from_device(
+                    &notification.id,
+                    device_name,
+                    app_name,
+                    title,
+                    text,
+                    None, // rich_body
+                    image_bytes,
+                    Vec::new(), // links
+                    &icon,
+                    replaces_id,
+                )
+                .await
+        } else {
+            notifier
+                .notify_from_device(device_name, app_name, title, text, None, &icon, replaces_id)
+                .await
+        };
+        match result {
+            Ok(desktop_id) => Some(desktop_id),
+            Err(e) => {
+                warn!("Failed to send device notification: {}", e);
+                None
+            }
+        }
     }
 
     /// Handle a connection event
@@ -1642,6 +2316,9 @@ impl Daemon {
         config: &Arc<RwLock<Config>>,
         error_handler: &Option<Arc<ErrorHandler>>,
         tls_config: &Arc<cosmic_ext_connect_protocol::TlsConfig>,
+        transfer_gate: &TransferGate,
+        resource_manager: &Arc<ResourceManager>,
+        recovery_manager: &Arc<RecoveryManager>,
     ) -> Result<()> {
         match event {
             ConnectionEvent::Connected {
@@ -1675,6 +2352,10 @@ impl Daemon {
                                 );
                             } else {
                                 info!("Initialized plugins for device {}", device_id);
+                                device_config_registry
+                                    .read()
+                                    .await
+                                    .apply_plugin_overrides(&device_id, &plug_manager);
 
                                 // Set TLS config on SharePlugin for secure file transfers
                                 if let Some(plugin) =
@@ -1685,6 +2366,45 @@ impl Daemon {
                                         plugin.as_any_mut().downcast_mut::<SharePlugin>()
                                     {
                                         share_plugin.set_tls_config(tls_config.clone());
+                                        share_plugin.set_size_limits(
+                                            device_config_registry
+                                                .read()
+                                                .await
+                                                .file_size_limits(&config.read().await.plugins),
+                                        );
+                                        share_plugin.set_completion_hooks(
+                                            config.read().await.plugins.share_completion_hooks(),
+                                        );
+                                        share_plugin.set_metadata_sidecars(
+                                            config.read().await.plugins.share_metadata_sidecars,
+                                        );
+                                        share_plugin.set_verify_checksums(
+                                            config.read().await.plugins.share_verify_checksums,
+                                        );
+                                        share_plugin.set_user_directories(
+                                            config.read().await.plugins.share_user_directories(),
+                                        );
+                                        share_plugin.set_payload_key(
+                                            dbus::payload_key(pairing_service.as_ref(), &device_id)
+                                                .await,
+                                        );
+                                        let mut gate = transfer_gate.clone();
+                                        gate.set_levels(
+                                            device_config_registry
+                                                .read()
+                                                .await
+                                                .receive_trust_levels(&config.read().await.plugins),
+                                        );
+                                        share_plugin.set_transfer_gate(gate);
+                                        share_plugin.set_resource_manager(resource_manager.clone());
+                                        share_plugin.set_recovery_manager(recovery_manager.clone());
+                                        share_plugin.set_traffic_counter(
+                                            connection_mgr
+                                                .read()
+                                                .await
+                                                .traffic_counter(&device_id)
+                                                .await,
+                                        );
                                         debug!(
                                             "Set TLS config on SharePlugin for device {}",
                                             device_id
@@ -1692,7 +2412,63 @@ impl Daemon {
                                     }
                                 }
 
-                                // Load MAC address from config and set it on WOL plugin
+                                // Send oversized clipboard content as payloads, over TLS
+                                if let Some(plugin) =
+                                    plug_manager.get_device_plugin_mut(&device_id, "clipboard")
+                                {
+                                    use cosmic_ext_connect_protocol::plugins::clipboard::ClipboardPlugin;
+                                    if let Some(clipboard_plugin) =
+                                        plugin.as_any_mut().downcast_mut::<ClipboardPlugin>()
+                                    {
+                                        clipboard_plugin.set_tls_config(tls_config.clone());
+                                        clipboard_plugin.set_traffic_counter(
+                                            connection_mgr
+                                                .read()
+                                                .await
+                                                .traffic_counter(&device_id)
+                                                .await,
+                                        );
+                                        clipboard_plugin.set_max_inline_length(
+                                            config.read().await.plugins.clipboard_max_inline_length,
+                                        );
+                                    }
+                                }
+
+                                // Download notification icons into the user cache and
+                                // display what the plugin posts
+                                if let Some(plugin) =
+                                    plug_manager.get_device_plugin_mut(&device_id, "notification")
+                                {
+                                    use cosmic_ext_connect_protocol::plugins::notification::{
+                                        NotificationIconCache, NotificationPlugin,
+                                    };
+                                    if let Some(notification_plugin) =
+                                        plugin.as_any_mut().downcast_mut::<NotificationPlugin>()
+                                    {
+                                        notification_plugin.set_tls_config(tls_config.clone());
+                                        if let Some(cache_dir) = dirs::cache_dir() {
+                                            notification_plugin.set_icon_cache(
+                                                NotificationIconCache::new(
+                                                    cache_dir
+                                                        .join("cconnect")
+                                                        .join("notification-icons"),
+                                                ),
+                                            );
+                                        }
+                                        if let Some(notifier) = cosmic_notifier {
+                                            tokio::spawn(Self::display_posted_notifications(
+                                                device_id.clone(),
+                                                notification_plugin.subscribe(),
+                                                device_manager.clone(),
+                                                device_config_registry.clone(),
+                                                notifier.clone(),
+                                                dbus_server.clone(),
+                                            ));
+                                        }
+                                    }
+                                }
+
+                                // Apply saved WOL MAC address and remote lock permissions
                                 let config_registry = device_config_registry.read().await;
                                 if let Some(device_config) = config_registry.get(&device_id) {
                                     if let Some(mac_address) = device_config.get_mac_address() {
@@ -1711,6 +2487,22 @@ impl Daemon {
                                             }
                                         }
                                     }
+
+                                    use cosmic_ext_connect_protocol::plugins::lock::LockPlugin;
+                                    if let Some(lock_plugin) =
+                                        plug_manager.get_device_plugin_mut(&device_id, "lock")
+                                    {
+                                        if let Some(lock) =
+                                            lock_plugin.as_any_mut().downcast_mut::<LockPlugin>()
+                                        {
+                                            lock.set_remote_lock_allowed(
+                                                device_config.allow_remote_lock,
+                                            );
+                                            lock.set_screen_off_allowed(
+                                                device_config.allow_remote_screen_off,
+                                            );
+                                        }
+                                    }
                                 }
 
                                 // Initialize Contacts plugin database and signals
@@ -1773,6 +2565,25 @@ impl Daemon {
                     }
                 }
 
+                // Devices running an exempt plugin (e.g. clipboard sync) are never
+                // disconnected for being idle
+                let exempt_plugins = connection_mgr.read().await.idle_exempt_plugins().to_vec();
+                let active_exempt: Vec<String> = {
+                    let plug_manager = plugin_manager.read().await;
+                    exempt_plugins
+                        .into_iter()
+                        .filter(|name| plug_manager.get_device_plugin(&device_id, name).is_some())
+                        .collect()
+                };
+                if !active_exempt.is_empty() {
+                    let conn_manager = connection_mgr.read().await;
+                    for plugin in &active_exempt {
+                        conn_manager
+                            .set_plugin_active(&device_id, plugin, true)
+                            .await;
+                    }
+                }
+
                 // Emit DBus signal for device state changed
                 if let Some(dbus) = dbus_server {
                     if let Err(e) = dbus
@@ -1781,6 +2592,8 @@ impl Daemon {
                     {
                         warn!("Failed to emit DeviceStateChanged signal: {}", e);
                     }
+                    dbus.record_device_event(&device_id, event_feed::DeviceEventKind::Connected)
+                        .await;
                 }
 
                 // Show COSMIC notification for device connection
@@ -1813,6 +2626,7 @@ impl Daemon {
 
                 // Cleanup per-device plugins ONLY if not a socket replacement
                 if !reconnect {
+                    resource_manager.forget_device(&device_id).await;
                     let mut plug_manager = plugin_manager.write().await;
                     if let Err(e) = plug_manager.cleanup_device_plugins(&device_id).await {
                         error!("Failed to cleanup plugins for device {}: {}", device_id, e);
@@ -1831,6 +2645,13 @@ impl Daemon {
                     if let Err(e) = dbus.emit_device_state_changed(&device_id, "disconnected").await {
                         warn!("Failed to emit DeviceStateChanged signal: {}", e);
                     }
+                    if !reconnect {
+                        dbus.record_device_event(
+                            &device_id,
+                            event_feed::DeviceEventKind::Disconnected { reason },
+                        )
+                        .await;
+                    }
                 }
 
                 // Show COSMIC notification for device disconnection
@@ -1883,6 +2704,20 @@ impl Daemon {
                         );
                         return Ok(());
                     }
+                    "cconnect.connect.request" => {
+                        // A device we cannot reach asked us to connect to it,
+                        // typically over Bluetooth; connecting may take a while
+                        let connection_mgr = connection_mgr.read().await.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = connection_mgr
+                                .handle_connect_request(&device_id, &packet)
+                                .await
+                            {
+                                warn!("Connect request from {} failed: {}", device_id, e);
+                            }
+                        });
+                        return Ok(());
+                    }
                     "cconnect.pair" => {
                         info!(
                             "Received pairing packet from {} at {}",
@@ -1994,7 +2829,7 @@ impl Daemon {
                                 let remote_ip = remote_addr.ip();
                                 let tls_config_clone = tls_config.clone();
 
-                                tokio::spawn(async move {
+                                let receive = async move {
                                     // Connect to payload port on Android device
                                     let payload_addr =
                                         std::net::SocketAddr::new(remote_ip, port as u16);
@@ -2073,6 +2908,33 @@ impl Daemon {
                                             );
                                         }
                                     }
+                                };
+
+                                // Stopped with the connection, like the camera plugin it feeds
+                                let frame_device_id = device_id.clone();
+                                let connection_mgr = connection_mgr.clone();
+                                tokio::spawn(async move {
+                                    let registered = connection_mgr
+                                        .read()
+                                        .await
+                                        .spawn_dependent(
+                                            &frame_device_id,
+                                            cosmic_ext_connect_protocol::DependentTask::PluginDispatch,
+                                            "camera",
+                                            |mut signal| async move {
+                                                tokio::select! {
+                                                    _ = receive => {}
+                                                    _ = signal.cancelled() => {}
+                                                }
+                                            },
+                                        )
+                                        .await;
+                                    if let Err(e) = registered {
+                                        debug!(
+                                            "Dropping camera frame from {}: {}",
+                                            frame_device_id, e
+                                        );
+                                    }
                                 });
                             } else {
                                 warn!("Camera frame payloadTransferInfo port is not a number");
@@ -2118,15 +2980,11 @@ impl Daemon {
                     drop(plug_manager);
                     drop(dev_manager);
 
-                    // Check device notification preference
-                    let notification_pref = {
-                        let config_registry = device_config_registry.read().await;
-                        if let Some(config) = config_registry.get(&device_id) {
-                            config.get_notification_preference()
-                        } else {
-                            device_config::NotificationPreference::All
+                    if let Some(dbus) = dbus_server {
+                        if let Some(kind) = event_feed::DeviceEventKind::from_packet(&packet) {
+                            dbus.record_device_event(&device_id, kind).await;
                         }
-                    };
+                    }
 
                     // Send COSMIC notifications for specific packet types
                     if let Some(notifier) = &cosmic_notifier {
@@ -2153,223 +3011,6 @@ impl Daemon {
                                     debug!("Received keepalive ping from {} - suppressing notification", device_name);
                                 }
                             }
-                            "cconnect.notification" => {
-                                // Check if it's a cancel notification
-                                let is_cancel = packet
-                                    .body
-                                    .get("isCancel")
-                                    .and_then(|v| v.as_bool())
-                                    .unwrap_or(false);
-
-                                if !is_cancel {
-                                    // Check if notification is silent (preexisting)
-                                    let is_silent = packet
-                                        .body
-                                        .get("silent")
-                                        .and_then(|v| v.as_str())
-                                        .map(|s| s == "true")
-                                        .unwrap_or(false);
-
-                                    // Only show COSMIC notification for new notifications
-                                    if !is_silent {
-                                        let app_name = packet
-                                            .body
-                                            .get("appName")
-                                            .and_then(|v| v.as_str())
-                                            .unwrap_or("");
-                                        let title = packet
-                                            .body
-                                            .get("title")
-                                            .and_then(|v| v.as_str())
-                                            .unwrap_or("Notification");
-
-                                        // Extract body text - prefer richBody over plain text
-                                        let rich_body =
-                                            packet.body.get("richBody").and_then(|v| v.as_str());
-                                        let text = packet
-                                            .body
-                                            .get("text")
-                                            .and_then(|v| v.as_str())
-                                            .unwrap_or("");
-
-                                        // Check if it's a messaging app
-                                        let is_messaging = packet
-                                            .body
-                                            .get("isMessagingApp")
-                                            .and_then(|v| v.as_bool())
-                                            .unwrap_or(false);
-
-                                        // Apply notification filtering based on preference
-                                        let should_show = match notification_pref {
-                                            device_config::NotificationPreference::All => true,
-                                            device_config::NotificationPreference::Important => {
-                                                // Important includes messaging apps, calls, alarms
-                                                is_messaging
-                                                    || app_name.to_lowercase().contains("phone")
-                                                    || app_name.to_lowercase().contains("call")
-                                                    || app_name.to_lowercase().contains("alarm")
-                                                    || app_name.to_lowercase().contains("clock")
-                                            }
-                                            device_config::NotificationPreference::None => false,
-                                        };
-
-                                        if should_show && is_messaging {
-                                            let web_url =
-                                                packet.body.get("webUrl").and_then(|v| v.as_str());
-
-                                            if let Err(e) = notifier
-                                                .notify_messaging(
-                                                    &device_name,
-                                                    app_name,
-                                                    title,
-                                                    text,
-                                                    rich_body,
-                                                    web_url,
-                                                )
-                                                .await
-                                            {
-                                                warn!(
-                                                    "Failed to send messaging notification: {}",
-                                                    e
-                                                );
-                                            }
-
-                                            // Emit D-Bus signal for cosmic-messages
-                                            if let Some(dbus) = &dbus_server {
-                                                let conv_id = packet
-                                                    .body
-                                                    .get("conversationId")
-                                                    .and_then(|v| v.as_str())
-                                                    .unwrap_or("");
-                                                if let Err(e) = dbus
-                                                    .emit_messaging_notification(
-                                                        app_name, title, text, conv_id,
-                                                    )
-                                                    .await
-                                                {
-                                                    warn!(
-                                                        "Failed to emit messaging D-Bus signal: {}",
-                                                        e
-                                                    );
-                                                }
-                                            }
-                                        } else if should_show {
-                                            // Extract notification ID for rich notifications
-                                            let notification_id = packet
-                                                .body
-                                                .get("id")
-                                                .and_then(|v| v.as_str())
-                                                .unwrap_or("");
-
-                                            // Issue #180: Check multiple image sources from Android
-                                            // Priority order:
-                                            // 1. imageData - Main notification image/large icon
-                                            // 2. senderAvatar - For messaging notifications
-                                            // 3. appIcon - Fallback to app icon
-                                            debug!(
-                                                "Notification image sources: imageData={}, senderAvatar={}, appIcon={}",
-                                                packet.body.get("imageData").is_some(),
-                                                packet.body.get("senderAvatar").is_some(),
-                                                packet.body.get("appIcon").is_some()
-                                            );
-
-                                            // Helper closure to decode base64 image field
-                                            let try_decode_image_field =
-                                                |field: &str| -> Option<(Vec<u8>, i32, i32)> {
-                                                    let base64_data =
-                                                        packet.body.get(field)?.as_str()?;
-
-                                                    // Decode base64 to bytes
-                                                    let bytes = match general_purpose::STANDARD
-                                                        .decode(base64_data)
-                                                    {
-                                                        Ok(b) => b,
-                                                        Err(e) => {
-                                                            debug!("Failed to decode base64 for {}: {}", field, e);
-                                                            return None;
-                                                        }
-                                                    };
-
-                                                    // Load as image to get dimensions
-                                                    match image::load_from_memory(&bytes) {
-                                                        Ok(img) => {
-                                                            let width = img.width() as i32;
-                                                            let height = img.height() as i32;
-                                                            // Convert to RGBA8 and get raw bytes
-                                                            let rgba = img.to_rgba8();
-                                                            debug!(
-                                                                "Decoded {} image: {}x{}",
-                                                                field, width, height
-                                                            );
-                                                            Some((rgba.into_raw(), width, height))
-                                                        }
-                                                        Err(e) => {
-                                                            debug!(
-                                                                "Failed to decode image for {}: {}",
-                                                                field, e
-                                                            );
-                                                            None
-                                                        }
-                                                    }
-                                                };
-
-                                            // Extract best available image (Issue #180)
-                                            let image_bytes = try_decode_image_field("imageData")
-                                                .or_else(|| try_decode_image_field("senderAvatar"))
-                                                .or_else(|| try_decode_image_field("appIcon"));
-
-                                            if let Some((_, w, h)) = &image_bytes {
-                                                debug!("Using notification image: {}x{}", w, h);
-                                            }
-
-                                            // Send notification with or without image
-                                            if image_bytes.is_some() {
-                                                // Use rich notification with image
-                                                if let Err(e) = notifier
-                                                    .notify_rich_from_device(
-                                                        notification_id,
-                                                        &device_name,
-                                                        app_name,
-                                                        title,
-                                                        text,
-                                                        None, // rich_body
-                                                        image_bytes,
-                                                        Vec::new(), // links
-                                                    )
-                                                    .await
-                                                {
-                                                    warn!(
-                                                        "Failed to send rich notification: {}",
-                                                        e
-                                                    );
-                                                }
-                                            } else {
-                                                // Use simple notification without image
-                                                if let Err(e) = notifier
-                                                    .notify_from_device(
-                                                        &device_name,
-                                                        app_name,
-                                                        title,
-                                                        text,
-                                                        None, // rich_body
-                                                    )
-                                                    .await
-                                                {
-                                                    warn!(
-                                                        "Failed to send device notification: {}",
-                                                        e
-                                                    );
-                                                }
-                                            }
-                                        } else {
-                                            debug!(
-                                                "Notification from {} filtered based on preference {:?}",
-                                                device_name, notification_pref
-                                            );
-                                        }
-                                    }
-                                }
-                            }
                             "cconnect.share.request" => {
                                 // Handle different share types: file, URL, or text
                                 if let Some(filename) =
@@ -2387,7 +3028,36 @@ impl Daemon {
                                         .join("Downloads");
                                         let file_path = downloads_dir.join(filename);
 
-                                        if let Err(e) = notifier
+                                        // The share plugin refuses files over the limit
+                                        let size_limit = {
+                                            let config = config.read().await;
+                                            let config_registry =
+                                                device_config_registry.read().await;
+                                            match config_registry.get(&device_id) {
+                                                Some(device_config) => device_config
+                                                    .get_max_incoming_file_size(&config.plugins),
+                                                None => config.plugins.share_max_incoming_file_size,
+                                            }
+                                        };
+
+                                        if let Some(limit) =
+                                            size_limit.filter(|&limit| file_size as u64 > limit)
+                                        {
+                                            if let Err(e) = notifier
+                                                .notify_file_rejected(
+                                                    &device_name,
+                                                    filename,
+                                                    file_size as u64,
+                                                    limit,
+                                                )
+                                                .await
+                                            {
+                                                warn!(
+                                                    "Failed to send file rejected notification: {}",
+                                                    e
+                                                );
+                                            }
+                                        } else if let Err(e) = notifier
                                             .notify_file_received(
                                                 &device_name,
                                                 filename,
@@ -2409,59 +3079,73 @@ impl Daemon {
                                 } else if let Some(url) =
                                     packet.body.get("url").and_then(|v| v.as_str())
                                 {
-                                    // URL share - open in default browser
+                                    // URL share - notify with an "Open" action
                                     info!("Received URL share from {}: {}", device_name, url);
 
-                                    // Spawn xdg-open to open URL in default browser
-                                    let url_clone = url.to_string();
-                                    let device_name_clone = device_name.clone();
-                                    tokio::spawn(async move {
-                                        match tokio::process::Command::new("xdg-open")
-                                            .arg(&url_clone)
-                                            .spawn()
-                                        {
-                                            Ok(_) => {
-                                                info!(
-                                                    "Opened URL from {} in default browser: {}",
-                                                    device_name_clone, url_clone
-                                                );
-                                            }
-                                            Err(e) => {
-                                                warn!(
-                                                    "Failed to open URL from {}: {}",
-                                                    device_name_clone, e
-                                                );
-                                            }
-                                        }
-                                    });
+                                    if let Err(e) = notifier.notify_url_shared(&device_name, url).await
+                                    {
+                                        warn!("Failed to send URL share notification: {}", e);
+                                    }
                                 } else if let Some(text) =
                                     packet.body.get("text").and_then(|v| v.as_str())
                                 {
-                                    // Text share - copy to clipboard
+                                    // Text share - notify with a preview, optionally copy to clipboard
                                     info!(
                                         "Received text share from {} ({} chars)",
                                         device_name,
                                         text.len()
                                     );
 
-                                    use arboard::Clipboard;
-                                    match Clipboard::new() {
-                                        Ok(mut clipboard) => {
-                                            if let Err(e) = clipboard.set_text(text) {
-                                                warn!(
-                                                    "Failed to copy shared text to clipboard: {}",
-                                                    e
-                                                );
-                                            } else {
-                                                info!("Copied shared text from {} to clipboard ({} chars)",
-                                                    device_name, text.len());
+                                    use cosmic_ext_connect_protocol::plugins::share::{
+                                        ShareContent, ShareReceiveOptions,
+                                    };
+
+                                    let options = ShareReceiveOptions {
+                                        text_to_clipboard: config
+                                            .read()
+                                            .await
+                                            .plugins
+                                            .share_text_to_clipboard,
+                                        ..Default::default()
+                                    };
+
+                                    if let Some(action) =
+                                        options.plan(&ShareContent::Text(text.to_string()))
+                                    {
+                                        let mut copied = false;
+                                        if let Some(clipboard_text) = &action.clipboard {
+                                            use arboard::Clipboard;
+                                            match Clipboard::new() {
+                                                Ok(mut clipboard) => {
+                                                    if let Err(e) = clipboard.set_text(clipboard_text) {
+                                                        warn!(
+                                                            "Failed to copy shared text to clipboard: {}",
+                                                            e
+                                                        );
+                                                    } else {
+                                                        copied = true;
+                                                        info!("Copied shared text from {} to clipboard ({} chars)",
+                                                            device_name, clipboard_text.len());
+                                                    }
+                                                }
+                                                Err(e) => {
+                                                    warn!(
+                                                        "Failed to initialize clipboard for text share: {}",
+                                                        e
+                                                    );
+                                                }
                                             }
                                         }
-                                        Err(e) => {
-                                            warn!(
-                                                "Failed to initialize clipboard for text share: {}",
-                                                e
-                                            );
+
+                                        if let Err(e) = notifier
+                                            .notify_text_shared(
+                                                &device_name,
+                                                &action.notification_body,
+                                                copied,
+                                            )
+                                            .await
+                                        {
+                                            warn!("Failed to send text share notification: {}", e);
                                         }
                                     }
                                 }
@@ -2559,6 +3243,30 @@ impl Daemon {
                     warn!("Received packet from unknown device: {}", device_id);
                 }
             }
+            ConnectionEvent::ConnectionRejected {
+                device_id,
+                remote_addr,
+                reason,
+            } => {
+                warn!(
+                    "Refused connection {} {:?} at {}: {}",
+                    if device_id.is_some() { "to" } else { "from" },
+                    device_id,
+                    remote_addr,
+                    reason
+                );
+            }
+            ConnectionEvent::CertificateMismatch {
+                device_id,
+                remote_addr,
+                expected_fingerprint,
+                presented_fingerprint,
+            } => {
+                warn!(
+                    "Refused {} at {}: presented certificate {} instead of pinned {}",
+                    device_id, remote_addr, presented_fingerprint, expected_fingerprint
+                );
+            }
             ConnectionEvent::ConnectionError { device_id, message } => {
                 error!("Connection error for device {:?}: {}", device_id, message);
                 if let Some(handler) = error_handler {
@@ -2568,6 +3276,89 @@ impl Daemon {
                         .await;
                 }
             }
+            ConnectionEvent::TrafficUpdated {
+                device_id,
+                stats,
+                session_ended,
+            } => {
+                if session_ended {
+                    info!(
+                        "Session with {} ended: {} bytes sent, {} bytes received",
+                        device_id,
+                        stats.total_sent(),
+                        stats.total_received()
+                    );
+                }
+                if let Some(dbus) = dbus_server {
+                    if let Err(e) = dbus
+                        .emit_traffic_stats_changed(&device_id, stats.into(), session_ended)
+                        .await
+                    {
+                        warn!("Failed to emit TrafficStatsChanged signal: {}", e);
+                    }
+                }
+            }
+            ConnectionEvent::CapabilitiesChanged {
+                device_id,
+                incoming_capabilities,
+                outgoing_capabilities,
+            } => {
+                info!(
+                    "Device {} capabilities changed ({} in, {} out)",
+                    device_id,
+                    incoming_capabilities.len(),
+                    outgoing_capabilities.len()
+                );
+                if let Some(dbus) = dbus_server {
+                    if let Err(e) = dbus
+                        .emit_device_capabilities_changed(
+                            &device_id,
+                            &incoming_capabilities,
+                            &outgoing_capabilities,
+                        )
+                        .await
+                    {
+                        warn!("Failed to emit DeviceCapabilitiesChanged signal: {}", e);
+                    }
+                }
+            }
+            ConnectionEvent::TaskCancelled {
+                device_id,
+                task,
+                name,
+                outcome,
+            } => {
+                debug!(
+                    "{:?} task '{}' for {} ended on disconnect: {:?}",
+                    task, name, device_id, outcome
+                );
+            }
+            ConnectionEvent::Timeout { device_id, silence } => {
+                // The Disconnected event that follows triggers recovery
+                warn!(
+                    "Device {} stopped answering keepalives ({:?} silent)",
+                    device_id, silence
+                );
+            }
+            ConnectionEvent::LinkFlapped {
+                device_id,
+                downtime,
+            } => {
+                info!(
+                    "Device {} dropped briefly ({:?}) and reconnected",
+                    device_id, downtime
+                );
+            }
+            ConnectionEvent::StateChanged {
+                device_id,
+                from,
+                to,
+            } => {
+                debug!(
+                    "Device {} connection state {:?} -> {:?}",
+                    device_id, from, to
+                );
+            }
             ConnectionEvent::ManagerStarted { port } => {
                 info!("Connection manager started on port {}", port);
             }
@@ -2803,9 +3594,6 @@ impl Daemon {
         dbus_server: &Option<Arc<DbusServer>>,
         _error_handler: &ErrorHandler,
         connection_manager: &Arc<RwLock<ConnectionManager>>,
-        connection_attempts: &Arc<
-            RwLock<std::collections::HashMap<String, (std::time::Instant, u32)>>,
-        >,
     ) -> Result<()> {
         match event {
             DiscoveryEvent::DeviceDiscovered {
@@ -2840,39 +3628,43 @@ impl Daemon {
                 };
 
                 if should_connect {
-                    // Check backoff
-                    let mut attempts = connection_attempts.write().await;
-                    let now = std::time::Instant::now();
-                    let (last_attempt, count) = attempts
-                        .entry(device_id.clone())
-                        .or_insert((now - Duration::from_secs(3600), 0));
-
-                    // Exponential backoff: 2^count seconds (cap at 60s)
-                    let backoff = Duration::from_secs(2u64.pow((*count).min(6)));
+                    // Check backoff (exponential, capped at 60s)
+                    let attempt = connection_manager
+                        .read()
+                        .await
+                        .claim_reconnect_attempt(&device_id)
+                        .await;
 
-                    if now.duration_since(*last_attempt) >= backoff {
+                    if let Some(attempt) = attempt {
                         info!(
                             "Auto-connecting to trusted device {} (attempt {})",
-                            device_id, count
+                            device_id, attempt
                         );
-                        *last_attempt = now;
-                        *count += 1;
 
-                        // Try to connect
-                        let _mgr = connection_manager.read().await;
                         // Need to extract SocketAddr from TransportAddress if it's TCP
                         // DiscoveryService usually returns TransportAddress::Tcp for UDP discovery results
                         if let cosmic_ext_connect_protocol::transport::TransportAddress::Tcp(addr) =
                             transport_address
                         {
                             let device_id_clone = device_id.clone();
-                            let socket_addr = *addr;
+                            // Every address the device was seen at, best route first
+                            let addresses = if info.addresses.is_empty() {
+                                vec![*addr]
+                            } else {
+                                info.addresses.clone()
+                            };
 
                             let mgr_arc = connection_manager.clone();
                             tokio::spawn(async move {
                                 let mgr = mgr_arc.read().await;
-                                if let Err(e) = mgr.connect(&device_id_clone, socket_addr).await {
-                                    warn!("Failed to auto-connect to {}: {}", device_id_clone, e);
+                                for socket_addr in addresses {
+                                    match mgr.connect(&device_id_clone, socket_addr).await {
+                                        Ok(()) => break,
+                                        Err(e) => warn!(
+                                            "Failed to auto-connect to {} at {}: {}",
+                                            device_id_clone, socket_addr, e
+                                        ),
+                                    }
                                 }
                             });
                         }
@@ -2882,8 +3674,11 @@ impl Daemon {
                     let manager = device_manager.read().await;
                     if let Some(device) = manager.get_device(&device_id) {
                         if device.is_connected() {
-                            let mut attempts = connection_attempts.write().await;
-                            attempts.remove(&device_id);
+                            connection_manager
+                                .read()
+                                .await
+                                .reset_reconnect_backoff(&device_id)
+                                .await;
                         }
                     }
                 }
@@ -3299,6 +4094,13 @@ async fn handle_internal_packet(dbus: &dbus::DbusServer, device_id: &str, packet
             }
             true
         }
+        "cconnect.internal.lock.remote_action" => {
+            let data = packet.body.to_string();
+            if let Err(e) = dbus.emit_plugin_event(device_id, "lock", &data).await {
+                error!("Failed to emit lock plugin event: {}", e);
+            }
+            true
+        }
         _ => false, // Not an internal packet
     }
 }
@@ -3558,6 +4360,12 @@ async fn main() -> Result<()> {
         .await
         .context("Failed to start DBus server")?;
 
+    // Start asking about incoming files (after DBus so prompts are announced)
+    daemon
+        .start_transfer_prompts()
+        .await
+        .context("Failed to start transfer prompts")?;
+
     // Start discovery
     daemon
         .start_discovery()
@@ -24,6 +24,7 @@ fn create_mock_device() -> Device {
         port: Some(1716),
         certificate_fingerprint: None,
         certificate_data: None,
+        last_activity: None,
     }
 }
 
@@ -365,6 +366,9 @@ async fn test_share_plugin_file() -> Result<()> {
         creation_time: None,
         last_modified: None,
         open: false,
+        metadata: Default::default(),
+        user: None,
+        checksum: None,
     };
     let packet = plugin.create_file_packet(file_info, 1739);
     assert_eq!(packet.packet_type, "cconnect.share.request");
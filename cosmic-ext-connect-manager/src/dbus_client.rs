@@ -42,6 +42,8 @@ pub struct DeviceInfo {
     pub incoming_capabilities: Vec<String>,
     /// Supported outgoing plugin capabilities
     pub outgoing_capabilities: Vec<String>,
+    /// Recent packet activity: "active", "idle" or "stale"
+    pub activity: String,
 }
 
 /// Battery status from DBus
@@ -407,6 +409,9 @@ trait CConnect {
     /// Connect to a device at a specific address
     async fn connect_to_address(&self, address: &str) -> zbus::fdo::Result<()>;
 
+    /// Reconnect to a device immediately, skipping its reconnect backoff
+    async fn reconnect_now(&self, device_id: &str) -> zbus::fdo::Result<()>;
+
     /// Get device connection state
     async fn get_device_state(&self, device_id: &str) -> zbus::fdo::Result<String>;
 
@@ -419,6 +424,9 @@ trait CConnect {
     /// Share a file with a device
     async fn share_file(&self, device_id: &str, path: &str) -> zbus::fdo::Result<()>;
 
+    /// Share several files with a device, returning the batch ID
+    async fn share_files(&self, device_id: &str, paths: &[&str]) -> zbus::fdo::Result<String>;
+
     /// Cancel an active file transfer
     async fn cancel_transfer(&self, transfer_id: &str) -> zbus::fdo::Result<()>;
 
@@ -615,6 +623,14 @@ trait CConnect {
     /// Get run commands (returns JSON string map of id -> Command)
     async fn get_run_commands(&self, device_id: String) -> zbus::fdo::Result<String>;
 
+    /// Schedule a run command on a remote device, returning its first run
+    async fn schedule_command(
+        &self,
+        device_id: &str,
+        command_id: &str,
+        schedule: &str,
+    ) -> zbus::fdo::Result<String>;
+
     /// Start screen share
     async fn start_screen_share(&self, device_id: &str, port: u16) -> zbus::fdo::Result<()>;
 
@@ -686,6 +702,9 @@ trait CConnect {
     /// Take screenshot
     async fn take_screenshot(&self, device_id: &str) -> zbus::fdo::Result<()>;
 
+    /// Send a screenshot of this desktop to a device
+    async fn send_desktop_screenshot(&self, device_id: &str) -> zbus::fdo::Result<()>;
+
     /// Signal: File transfer complete
     #[zbus(signal)]
     fn transfer_complete(
@@ -1069,6 +1088,16 @@ impl DbusClient {
             .context("Failed to connect to address")
     }
 
+    /// Reconnect to a device immediately, skipping its reconnect backoff
+    #[allow(dead_code)]
+    pub async fn reconnect_now(&self, device_id: &str) -> Result<()> {
+        info!("Reconnecting to device {} now", device_id);
+        self.proxy
+            .reconnect_now(device_id)
+            .await
+            .context("Failed to reconnect to device")
+    }
+
     /// Get device connection state
     #[allow(dead_code)]
     pub async fn get_device_state(&self, device_id: &str) -> Result<String> {
@@ -1106,6 +1135,15 @@ impl DbusClient {
             .context("Failed to share file")
     }
 
+    /// Share several files with a device as one batch
+    pub async fn share_files(&self, device_id: &str, paths: &[&str]) -> Result<String> {
+        info!("Sharing {} files with device {}", paths.len(), device_id);
+        self.proxy
+            .share_files(device_id, paths)
+            .await
+            .context("Failed to share files")
+    }
+
     /// Cancel an active file transfer
     pub async fn cancel_transfer(&self, transfer_id: &str) -> Result<()> {
         info!("Cancelling transfer {}", transfer_id);
@@ -1696,6 +1734,15 @@ impl DbusClient {
             .context("Failed to take screenshot")
     }
 
+    /// Send a screenshot of this desktop to a device
+    pub async fn send_desktop_screenshot(&self, device_id: &str) -> Result<()> {
+        info!("Sending desktop screenshot to device {}", device_id);
+        self.proxy
+            .send_desktop_screenshot(device_id)
+            .await
+            .context("Failed to send desktop screenshot")
+    }
+
     /// Get run commands
     pub async fn get_run_commands(&self, device_id: String) -> Result<HashMap<String, RunCommand>> {
         let json = self
@@ -1707,6 +1754,26 @@ impl DbusClient {
         serde_json::from_str(&json).context("Failed to parse run commands JSON")
     }
 
+    /// Schedule a run command on a remote device
+    ///
+    /// `schedule` is `once YYYY-MM-DD HH:MM`, `daily HH:MM` or `every <n>m|h|d`.
+    /// Returns the first run time.
+    pub async fn schedule_command(
+        &self,
+        device_id: &str,
+        command_id: &str,
+        schedule: &str,
+    ) -> Result<String> {
+        info!(
+            "Scheduling command {} on device {}: {}",
+            command_id, device_id, schedule
+        );
+        self.proxy
+            .schedule_command(device_id, command_id, schedule)
+            .await
+            .context("Failed to schedule command")
+    }
+
     /// Open a URL on a connected Android device (App Continuity)
     ///
     /// # Arguments
@@ -0,0 +1,167 @@
+//! Outgoing Bandwidth Limits
+//!
+//! Large transfers over a metered or slow uplink can starve everything else
+//! on the link. A [`BandwidthLimit`] is a token bucket that payload senders
+//! draw from before each write, so the bytes sent per second stay under the
+//! configured ceiling.
+//!
+//! The bucket holds at most [`MAX_BURST`] worth of tokens. A transfer that
+//! sat idle, for example while waiting for the receiver to confirm, therefore
+//! cannot send a large burst when it resumes.
+//!
+//! Cloning a limit yields a handle to the same bucket, so the ceiling can be
+//! changed while a transfer is in flight; the next write uses the new rate.
+//! [`ResourceManager`](crate::ResourceManager) uses this to split one global
+//! budget across all active transfers.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Longest burst the bucket allows, as time at the configured rate
+pub const MAX_BURST: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+struct Bucket {
+    /// Ceiling in bytes per second (None = unlimited)
+    rate: Option<u64>,
+    /// Bytes that may be sent right away; negative while writes are ahead
+    tokens: f64,
+    /// When `tokens` was last topped up
+    refilled: Instant,
+}
+
+impl Bucket {
+    /// Add the tokens earned since the last refill at the current rate
+    fn refill(&mut self, now: Instant) {
+        if let Some(rate) = self.rate {
+            let earned = now.duration_since(self.refilled).as_secs_f64() * rate as f64;
+            let burst = rate as f64 * MAX_BURST.as_secs_f64();
+            self.tokens = (self.tokens + earned).min(burst);
+        }
+        self.refilled = now;
+    }
+}
+
+/// Shared token bucket limiting outgoing payload bytes per second
+///
+/// Cloning yields a handle to the same bucket.
+#[derive(Debug, Clone)]
+pub struct BandwidthLimit {
+    inner: Arc<Mutex<Bucket>>,
+}
+
+impl Default for BandwidthLimit {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl BandwidthLimit {
+    /// Create a limit of `max_bytes_per_sec` (None = unlimited)
+    pub fn new(max_bytes_per_sec: Option<u64>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Bucket {
+                rate: max_bytes_per_sec.filter(|&rate| rate > 0),
+                tokens: 0.0,
+                refilled: Instant::now(),
+            })),
+        }
+    }
+
+    /// Create a limit that never waits
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Current ceiling in bytes per second
+    pub fn max_bytes_per_sec(&self) -> Option<u64> {
+        self.lock().rate
+    }
+
+    /// Change the ceiling, taking effect from the next write
+    ///
+    /// Zero is treated as unlimited.
+    pub fn set_max_bytes_per_sec(&self, max_bytes_per_sec: Option<u64>) {
+        let mut bucket = self.lock();
+        bucket.refill(Instant::now());
+        bucket.rate = max_bytes_per_sec.filter(|&rate| rate > 0);
+        if bucket.rate.is_none() {
+            bucket.tokens = 0.0;
+        }
+    }
+
+    /// Wait until `bytes` may be sent under the current ceiling
+    pub async fn acquire(&self, bytes: usize) {
+        let wait = {
+            let mut bucket = self.lock();
+            let Some(rate) = bucket.rate else {
+                return;
+            };
+            bucket.refill(Instant::now());
+            bucket.tokens -= bytes as f64;
+            if bucket.tokens >= 0.0 {
+                return;
+            }
+            Duration::from_secs_f64(-bucket.tokens / rate as f64)
+        };
+        tokio::time::sleep(wait).await;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Bucket> {
+        // The bucket holds plain numbers, so a poisoned lock is still usable
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn time_to_send(limit: &BandwidthLimit, bytes: usize, chunk: usize) -> Duration {
+        let started = std::time::Instant::now();
+        for _ in 0..bytes / chunk {
+            limit.acquire(chunk).await;
+        }
+        started.elapsed()
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_never_waits() {
+        let limit = BandwidthLimit::unlimited();
+        assert!(
+            time_to_send(&limit, 10 * 1024 * 1024, 64 * 1024).await < Duration::from_millis(50)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_limit_paces_writes() {
+        // 100 KB at 200 KB/s takes about half a second
+        let limit = BandwidthLimit::new(Some(200_000));
+        let elapsed = time_to_send(&limit, 100_000, 10_000).await;
+        assert!(elapsed >= Duration::from_millis(400), "{elapsed:?}");
+    }
+
+    #[tokio::test]
+    async fn test_idle_time_does_not_allow_a_burst() {
+        let limit = BandwidthLimit::new(Some(200_000));
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        // Only MAX_BURST (20 KB) was saved up, not the 60 KB the pause was worth
+        let elapsed = time_to_send(&limit, 60_000, 10_000).await;
+        assert!(elapsed >= Duration::from_millis(150), "{elapsed:?}");
+    }
+
+    #[tokio::test]
+    async fn test_limit_changes_while_sending() {
+        let limit = BandwidthLimit::new(Some(1_000));
+        let handle = limit.clone();
+        handle.set_max_bytes_per_sec(None);
+        assert_eq!(limit.max_bytes_per_sec(), None);
+        assert!(time_to_send(&limit, 1_000_000, 10_000).await < Duration::from_millis(50));
+
+        handle.set_max_bytes_per_sec(Some(100_000));
+        assert_eq!(limit.max_bytes_per_sec(), Some(100_000));
+        let elapsed = time_to_send(&limit, 30_000, 10_000).await;
+        assert!(elapsed >= Duration::from_millis(200), "{elapsed:?}");
+    }
+}
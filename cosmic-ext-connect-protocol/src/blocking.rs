@@ -0,0 +1,366 @@
+//! Blocking Facade
+//!
+//! Synchronous wrappers over the async protocol APIs for scripts, simple
+//! command-line tools and integration tests that do not want to manage a
+//! tokio runtime or go through the daemon's D-Bus interface.
+//!
+//! Enabled with the `blocking` feature. [`BlockingClient`] owns a
+//! current-thread runtime and drives each call to completion before
+//! returning. Do not call it from inside an async context; the runtime
+//! cannot be nested.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use cosmic_ext_connect_protocol::blocking::BlockingClient;
+//! use cosmic_ext_connect_protocol::{DeviceManager, TcpTransportFactory, TransportAddress};
+//! use std::sync::Arc;
+//!
+//! let devices = DeviceManager::new("/tmp/devices.json")?;
+//! let mut client = BlockingClient::new(Arc::new(TcpTransportFactory::default()), devices)?;
+//!
+//! client.connect("phone-id", TransportAddress::Tcp("192.168.1.20:1716".parse()?))?;
+//! client.ping("phone-id", Some("hello"))?;
+//! client.send_file("phone-id", "/tmp/report.pdf")?;
+//! ```
+
+use crate::plugins::ping::PingPlugin;
+use crate::plugins::share::SharePlugin;
+use crate::{
+    Device, DeviceManager, FileTransferInfo, PayloadServer, ProtocolError, Result, TlsConfig,
+    TlsPayloadServer, Transport, TransportAddress, TransportFactory,
+};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use tracing::info;
+
+/// Blocking client for one-off protocol actions
+pub struct BlockingClient {
+    runtime: Runtime,
+    factory: Arc<dyn TransportFactory>,
+    devices: DeviceManager,
+    connections: HashMap<String, Box<dyn Transport>>,
+    tls_config: Option<Arc<TlsConfig>>,
+}
+
+impl BlockingClient {
+    /// Create a client connecting through `factory`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the internal runtime cannot be started.
+    pub fn new(factory: Arc<dyn TransportFactory>, devices: DeviceManager) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| ProtocolError::from_io_error(e, "starting blocking runtime"))?;
+
+        Ok(Self {
+            runtime,
+            factory,
+            devices,
+            connections: HashMap::new(),
+            tls_config: None,
+        })
+    }
+
+    /// Serve file payloads over TLS (required by Android peers)
+    pub fn with_tls_config(mut self, config: Arc<TlsConfig>) -> Self {
+        self.tls_config = Some(config);
+        self
+    }
+
+    /// Known devices
+    pub fn list_devices(&self) -> Vec<Device> {
+        let mut devices: Vec<Device> = self.devices.devices().cloned().collect();
+        devices.sort_by(|a, b| a.name().cmp(b.name()));
+        devices
+    }
+
+    /// Whether a device is connected through this client
+    pub fn is_connected(&self, device_id: &str) -> bool {
+        self.connections
+            .get(device_id)
+            .is_some_and(|transport| transport.is_connected())
+    }
+
+    /// Connect to a device, replacing any existing connection
+    ///
+    /// # Errors
+    ///
+    /// Returns the transport error if the connection fails.
+    pub fn connect(&mut self, device_id: &str, address: TransportAddress) -> Result<()> {
+        let transport = self.runtime.block_on(self.factory.connect(address))?;
+        if let Some(old) = self.connections.insert(device_id.to_string(), transport) {
+            let _ = self.runtime.block_on(old.close());
+        }
+        info!("Connected to {}", device_id);
+        Ok(())
+    }
+
+    /// Close the connection to a device
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transport does not close cleanly.
+    pub fn disconnect(&mut self, device_id: &str) -> Result<()> {
+        match self.connections.remove(device_id) {
+            Some(transport) => self.runtime.block_on(transport.close()),
+            None => Ok(()),
+        }
+    }
+
+    /// Send a ping, optionally with a message
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidState`] if [`connect`](Self::connect) was
+    /// not called for the device, or the transport error.
+    pub fn ping(&mut self, device_id: &str, message: Option<&str>) -> Result<()> {
+        let packet = PingPlugin::new().create_ping(message.map(str::to_string));
+        let transport = self.transport(device_id)?;
+        self.runtime.block_on(transport.send_packet(&packet))
+    }
+
+    /// Send a file and wait until the device has downloaded it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, the device is not
+    /// connected, or the transfer fails.
+    pub fn send_file(
+        &mut self,
+        device_id: &str,
+        path: impl AsRef<Path>,
+    ) -> Result<FileTransferInfo> {
+        let path = path.as_ref();
+        let tls_config = self.tls_config.clone();
+        let transport = self
+            .connections
+            .get_mut(device_id)
+            .ok_or_else(|| not_connected(device_id))?;
+
+        self.runtime.block_on(async move {
+            let info = FileTransferInfo::from_path(path).await?;
+            let share = SharePlugin::new();
+
+            match tls_config {
+                Some(config) => {
+                    let server = TlsPayloadServer::new(config).await?;
+                    let packet = share.create_file_packet(info.clone().into(), server.port());
+                    transport.send_packet(&packet).await?;
+                    server.send_file(path).await?;
+                }
+                None => {
+                    let server = PayloadServer::new().await?;
+                    let packet = share.create_file_packet(info.clone().into(), server.port());
+                    transport.send_packet(&packet).await?;
+                    server.send_file(path).await?;
+                }
+            }
+
+            info!("Sent {} ({} bytes)", info.filename, info.size);
+            Ok(info)
+        })
+    }
+
+    fn transport(&mut self, device_id: &str) -> Result<&mut Box<dyn Transport>> {
+        self.connections
+            .get_mut(device_id)
+            .ok_or_else(|| not_connected(device_id))
+    }
+}
+
+fn not_connected(device_id: &str) -> ProtocolError {
+    ProtocolError::InvalidState(format!("{} is not connected", device_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        DeviceInfo, DeviceType, LatencyCategory, Packet, PayloadClient, TransportCapabilities,
+        TransportType,
+    };
+    use std::io::Write;
+    use std::sync::Mutex;
+    use tempfile::{NamedTempFile, TempDir};
+    use tokio::sync::mpsc;
+
+    /// One end of an in-memory connection
+    #[derive(Debug)]
+    struct MemoryTransport {
+        tx: mpsc::UnboundedSender<Packet>,
+        rx: mpsc::UnboundedReceiver<Packet>,
+    }
+
+    fn memory_pair() -> (MemoryTransport, MemoryTransport) {
+        let (a_tx, a_rx) = mpsc::unbounded_channel();
+        let (b_tx, b_rx) = mpsc::unbounded_channel();
+        (
+            MemoryTransport { tx: a_tx, rx: b_rx },
+            MemoryTransport { tx: b_tx, rx: a_rx },
+        )
+    }
+
+    #[async_trait::async_trait]
+    impl Transport for MemoryTransport {
+        fn capabilities(&self) -> TransportCapabilities {
+            TransportCapabilities {
+                max_packet_size: usize::MAX,
+                reliable: true,
+                connection_oriented: true,
+                latency: LatencyCategory::Low,
+                multiplexed: false,
+                msgpack: false,
+                payload_streams: 1,
+            }
+        }
+
+        fn remote_address(&self) -> TransportAddress {
+            TransportAddress::Tcp("127.0.0.1:1716".parse().unwrap())
+        }
+
+        async fn send_packet(&mut self, packet: &Packet) -> Result<()> {
+            self.tx
+                .send(packet.clone())
+                .map_err(|_| ProtocolError::Transport("memory peer closed".to_string()))
+        }
+
+        async fn receive_packet(&mut self) -> Result<Packet> {
+            self.rx
+                .recv()
+                .await
+                .ok_or_else(|| ProtocolError::Transport("memory peer closed".to_string()))
+        }
+
+        async fn close(self: Box<Self>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Hands out the client end of a prepared in-memory connection
+    #[derive(Debug)]
+    struct MemoryFactory(Mutex<Option<MemoryTransport>>);
+
+    #[async_trait::async_trait]
+    impl TransportFactory for MemoryFactory {
+        async fn connect(&self, _address: TransportAddress) -> Result<Box<dyn Transport>> {
+            let transport = self.0.lock().unwrap().take();
+            transport
+                .map(|t| Box::new(t) as Box<dyn Transport>)
+                .ok_or_else(|| ProtocolError::Transport("memory peer closed".to_string()))
+        }
+
+        fn transport_type(&self) -> TransportType {
+            TransportType::Tcp
+        }
+    }
+
+    /// Peer that downloads every shared file, reporting packets and contents
+    fn spawn_peer(
+        mut transport: MemoryTransport,
+    ) -> std::thread::JoinHandle<Vec<(Packet, Option<Vec<u8>>)>> {
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async move {
+                let download_dir = TempDir::new().unwrap();
+                let mut received = Vec::new();
+                while let Ok(packet) = transport.receive_packet().await {
+                    let mut contents = None;
+                    if let (Some(size), Some(info)) =
+                        (packet.payload_size, &packet.payload_transfer_info)
+                    {
+                        let port = info["port"].as_u64().unwrap() as u16;
+                        let target = download_dir.path().join(received.len().to_string());
+                        PayloadClient::new("127.0.0.1", port)
+                            .await
+                            .unwrap()
+                            .receive_file(&target, size as u64)
+                            .await
+                            .unwrap();
+                        contents = Some(std::fs::read(&target).unwrap());
+                    }
+                    received.push((packet, contents));
+                }
+                received
+            })
+        })
+    }
+
+    fn client_with_peer() -> (
+        BlockingClient,
+        std::thread::JoinHandle<Vec<(Packet, Option<Vec<u8>>)>>,
+        TempDir,
+    ) {
+        let (client_end, peer_end) = memory_pair();
+        let registry_dir = TempDir::new().unwrap();
+        let mut devices = DeviceManager::new(registry_dir.path().join("devices.json")).unwrap();
+        let mut info = DeviceInfo::new("Peer", DeviceType::Phone, 1716);
+        info.device_id = "peer".to_string();
+        devices.add_device(Device::from_discovery(info));
+
+        let factory = Arc::new(MemoryFactory(Mutex::new(Some(client_end))));
+        let client = BlockingClient::new(factory, devices).unwrap();
+        (client, spawn_peer(peer_end), registry_dir)
+    }
+
+    #[test]
+    fn test_send_file_and_ping_through_facade() {
+        let (mut client, peer, _registry) = client_with_peer();
+        assert_eq!(client.list_devices().len(), 1);
+        assert_eq!(client.list_devices()[0].id(), "peer");
+
+        let contents = b"blocking facade payload".repeat(100);
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&contents).unwrap();
+
+        assert!(client.ping("peer", None).is_err(), "not connected yet");
+        client
+            .connect(
+                "peer",
+                TransportAddress::Tcp("127.0.0.1:1716".parse().unwrap()),
+            )
+            .unwrap();
+        assert!(client.is_connected("peer"));
+
+        client.ping("peer", Some("hi")).unwrap();
+        let sent = client.send_file("peer", file.path()).unwrap();
+        assert_eq!(sent.size, contents.len() as u64);
+
+        // Dropping the client ends the connection so the peer returns
+        drop(client);
+        let received = peer.join().unwrap();
+        assert_eq!(received.len(), 2);
+
+        let (ping, _) = &received[0];
+        assert_eq!(ping.packet_type, "cconnect.ping");
+        assert_eq!(ping.body["message"], "hi");
+
+        // Same packet the async share path builds, and the same bytes
+        let (share, downloaded) = &received[1];
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let info = runtime
+            .block_on(FileTransferInfo::from_path(file.path()))
+            .unwrap();
+        let port = share.payload_transfer_info.as_ref().unwrap()["port"]
+            .as_u64()
+            .unwrap() as u16;
+        let expected = SharePlugin::new().create_file_packet(info.into(), port);
+        assert_eq!(share.packet_type, expected.packet_type);
+        assert_eq!(share.body, expected.body);
+        assert_eq!(share.payload_size, expected.payload_size);
+        assert_eq!(downloaded.as_deref(), Some(contents.as_slice()));
+    }
+
+    #[test]
+    fn test_send_file_requires_connection() {
+        let (mut client, _peer, _registry) = client_with_peer();
+        let file = NamedTempFile::new().unwrap();
+
+        let err = client.send_file("peer", file.path()).unwrap_err();
+        assert!(matches!(err, ProtocolError::InvalidState(_)));
+    }
+}
@@ -8,7 +8,7 @@
 use crate::{
     transport::{BluetoothConnection, BluetoothListener, BluetoothProfileService, Transport},
     transport_manager::TransportManagerEvent,
-    Packet, ProtocolError, Result,
+    Packet, PacketEncoding, ProtocolError, Result,
 };
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -346,6 +346,12 @@ impl BluetoothConnectionManager {
                         match result {
                             Ok(packet) => {
                                 debug!("Received packet '{}' from {} via Bluetooth", packet.packet_type, device_id);
+                                if packet.is_type("cconnect.identity") {
+                                    // Switch to a compact encoding if the peer decodes it
+                                    let peer = PacketEncoding::advertised_by(&packet);
+                                    let encoding = connection.capabilities().negotiate_encoding(&peer);
+                                    connection.set_encoding(encoding);
+                                }
                                 let _ = event_tx.send(TransportManagerEvent::PacketReceived {
                                     device_id: device_id.clone(),
                                     packet,
@@ -444,6 +450,35 @@ impl BluetoothConnectionManager {
         Ok(())
     }
 
+    /// Disconnect once the packets already queued for a device are sent
+    ///
+    /// Unlike [`disconnect`](Self::disconnect), the connection task gets up
+    /// to `grace` to write out its queue and close the socket itself. It is
+    /// only aborted if it does not finish in time.
+    pub async fn flush_and_disconnect(&self, device_id: &str, grace: Duration) -> Result<()> {
+        let Some(mut active_conn) = self.connections.write().await.remove(device_id) else {
+            return Ok(());
+        };
+
+        // Queued behind every pending packet, so those are written first
+        let _ = active_conn
+            .command_tx
+            .send(BluetoothConnectionCommand::Close);
+        if tokio::time::timeout(grace, &mut active_conn.task)
+            .await
+            .is_err()
+        {
+            warn!(
+                "Bluetooth connection to {} did not drain within {:?}, aborting",
+                device_id, grace
+            );
+            active_conn.task.abort();
+        }
+
+        info!("Disconnected from device {} (Bluetooth)", device_id);
+        Ok(())
+    }
+
     /// Check if there's an active connection to a device
     pub async fn has_connection(&self, device_id: &str) -> bool {
         let connections = self.connections.read().await;
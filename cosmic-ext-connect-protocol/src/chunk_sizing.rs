@@ -0,0 +1,306 @@
+//! Adaptive Payload Chunk Size
+//!
+//! A single chunk size does not suit every link. On a fast LAN small writes
+//! already saturate the wire, while on Bluetooth or a relay every write pays a
+//! round of latency and larger chunks amortize it. A [`ChunkProbe`] times the
+//! first writes of a transfer, doubles the chunk size while throughput keeps
+//! improving noticeably, and settles on the best size once it stops.
+//!
+//! ## Probe Flow
+//!
+//! 1. Start at [`ChunkProbeConfig::initial`]
+//! 2. Time [`ChunkProbeConfig::samples_per_step`] writes at the current size
+//! 3. If throughput beat the best so far by [`ChunkProbeConfig::min_gain`],
+//!    double the size (up to [`ChunkProbeConfig::max`]) and repeat
+//! 4. Otherwise settle on the best size for the rest of the transfer
+//!
+//! A [`ChunkSizePolicy`] can skip probing for transports whose best size is
+//! already known.
+
+use crate::transport::TransportType;
+use crate::{ProtocolError, Result};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::debug;
+
+/// Smallest chunk the probe tries by default (16 KB)
+pub const DEFAULT_MIN_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Largest chunk the probe tries by default (1 MB)
+pub const DEFAULT_MAX_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Chunk size used when probing is off (64 KB)
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Bounds and thresholds of a chunk size probe
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChunkProbeConfig {
+    /// Smallest chunk size
+    pub min: usize,
+    /// Largest chunk size
+    pub max: usize,
+    /// Size the probe starts at, clamped to `min..=max`
+    pub initial: usize,
+    /// Writes timed at each size
+    pub samples_per_step: u32,
+    /// Relative throughput gain needed to keep growing (0.1 = 10%)
+    pub min_gain: f64,
+}
+
+impl Default for ChunkProbeConfig {
+    fn default() -> Self {
+        Self {
+            min: DEFAULT_MIN_CHUNK_SIZE,
+            max: DEFAULT_MAX_CHUNK_SIZE,
+            initial: DEFAULT_MIN_CHUNK_SIZE,
+            samples_per_step: 2,
+            min_gain: 0.1,
+        }
+    }
+}
+
+impl ChunkProbeConfig {
+    /// Check that the bounds make sense
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::Configuration`] for empty or inverted bounds.
+    pub fn validate(&self) -> Result<()> {
+        if self.min == 0 || self.min > self.max {
+            return Err(ProtocolError::Configuration(format!(
+                "chunk size bounds {}..={} are invalid",
+                self.min, self.max
+            )));
+        }
+        if self.samples_per_step == 0 {
+            return Err(ProtocolError::Configuration(
+                "chunk probe needs at least one sample per step".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Picks the chunk size for one transfer
+#[derive(Debug, Clone)]
+pub struct ChunkProbe {
+    config: ChunkProbeConfig,
+    current: usize,
+    /// Best size measured so far with its throughput in bytes per second
+    best: Option<(usize, f64)>,
+    step_bytes: u64,
+    step_elapsed: Duration,
+    step_samples: u32,
+    settled: bool,
+}
+
+impl ChunkProbe {
+    /// Probe within `config`'s bounds
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::Configuration`] if the bounds are invalid.
+    pub fn new(config: ChunkProbeConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            current: config.initial.clamp(config.min, config.max),
+            config,
+            best: None,
+            step_bytes: 0,
+            step_elapsed: Duration::ZERO,
+            step_samples: 0,
+            settled: false,
+        })
+    }
+
+    /// Always use `size`, without probing
+    pub fn fixed(size: usize) -> Self {
+        let size = size.max(1);
+        Self {
+            config: ChunkProbeConfig {
+                min: size,
+                max: size,
+                initial: size,
+                ..ChunkProbeConfig::default()
+            },
+            current: size,
+            best: None,
+            step_bytes: 0,
+            step_elapsed: Duration::ZERO,
+            step_samples: 0,
+            settled: true,
+        }
+    }
+
+    /// Size of the next chunk
+    pub fn chunk_size(&self) -> usize {
+        self.current
+    }
+
+    /// Largest chunk this probe may ask for, to size buffers
+    pub fn max_chunk_size(&self) -> usize {
+        self.config.max
+    }
+
+    /// Whether the probe has stopped adjusting
+    pub fn is_settled(&self) -> bool {
+        self.settled
+    }
+
+    /// Record that writing `bytes` took `elapsed`
+    pub fn record(&mut self, bytes: usize, elapsed: Duration) {
+        if self.settled {
+            return;
+        }
+        self.step_bytes += bytes as u64;
+        self.step_elapsed += elapsed;
+        self.step_samples += 1;
+        if self.step_samples < self.config.samples_per_step {
+            return;
+        }
+
+        // A step too fast to time can only mean the link is not the bottleneck
+        let throughput = self.step_bytes as f64 / self.step_elapsed.as_secs_f64().max(1e-9);
+        self.step_bytes = 0;
+        self.step_elapsed = Duration::ZERO;
+        self.step_samples = 0;
+
+        let improved = match self.best {
+            Some((_, best)) => throughput > best * (1.0 + self.config.min_gain),
+            None => true,
+        };
+        if improved {
+            self.best = Some((self.current, throughput));
+            if self.current < self.config.max {
+                self.current = (self.current * 2).min(self.config.max);
+                return;
+            }
+        }
+
+        if let Some((size, _)) = self.best {
+            self.current = size;
+        }
+        self.settled = true;
+        debug!(
+            "Settled on {} byte chunks ({:.0} bytes/s)",
+            self.current,
+            self.best.map_or(throughput, |(_, t)| t)
+        );
+    }
+}
+
+impl Default for ChunkProbe {
+    fn default() -> Self {
+        Self::fixed(DEFAULT_CHUNK_SIZE)
+    }
+}
+
+/// How chunk sizes are chosen per transport
+///
+/// Transports with a preconfigured size use it as is; all others are probed.
+#[derive(Debug, Clone, Default)]
+pub struct ChunkSizePolicy {
+    probe: ChunkProbeConfig,
+    fixed: HashMap<TransportType, usize>,
+}
+
+impl ChunkSizePolicy {
+    /// Probe every transport within `probe`'s bounds
+    pub fn new(probe: ChunkProbeConfig) -> Self {
+        Self {
+            probe,
+            fixed: HashMap::new(),
+        }
+    }
+
+    /// Use `size` for `transport` instead of probing
+    pub fn with_transport_size(mut self, transport: TransportType, size: usize) -> Self {
+        self.fixed.insert(transport, size);
+        self
+    }
+
+    /// Preconfigured size for `transport`, if any
+    pub fn transport_size(&self, transport: TransportType) -> Option<usize> {
+        self.fixed.get(&transport).copied()
+    }
+
+    /// Chunk sizing for a transfer over `transport`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::Configuration`] if the probe bounds are invalid.
+    pub fn for_transport(&self, transport: TransportType) -> Result<ChunkProbe> {
+        match self.transport_size(transport) {
+            Some(size) => Ok(ChunkProbe::fixed(size)),
+            None => ChunkProbe::new(self.probe),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Time a write of `bytes` takes on a link with fixed per-write latency
+    fn link_time(bytes: usize, latency: Duration, bytes_per_sec: f64) -> Duration {
+        latency + Duration::from_secs_f64(bytes as f64 / bytes_per_sec)
+    }
+
+    fn probe_link(latency: Duration) -> ChunkProbe {
+        let mut probe = ChunkProbe::new(ChunkProbeConfig::default()).unwrap();
+        for _ in 0..64 {
+            if probe.is_settled() {
+                break;
+            }
+            let size = probe.chunk_size();
+            probe.record(size, link_time(size, latency, 10_000_000.0));
+        }
+        assert!(probe.is_settled());
+        probe
+    }
+
+    #[test]
+    fn test_high_latency_link_grows_chunks() {
+        let lan = probe_link(Duration::from_micros(100));
+        let bluetooth = probe_link(Duration::from_millis(50));
+
+        assert!(bluetooth.chunk_size() > lan.chunk_size());
+        for probe in [&lan, &bluetooth] {
+            assert!(probe.chunk_size() >= DEFAULT_MIN_CHUNK_SIZE);
+            assert!(probe.chunk_size() <= DEFAULT_MAX_CHUNK_SIZE);
+        }
+        assert_eq!(bluetooth.chunk_size(), DEFAULT_MAX_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn test_settled_probe_ignores_samples() {
+        let mut probe = ChunkProbe::fixed(32 * 1024);
+        assert!(probe.is_settled());
+        probe.record(32 * 1024, Duration::from_secs(1));
+        assert_eq!(probe.chunk_size(), 32 * 1024);
+    }
+
+    #[test]
+    fn test_policy_prefers_preconfigured_size() {
+        let policy =
+            ChunkSizePolicy::default().with_transport_size(TransportType::Bluetooth, 4 * 1024);
+
+        let bluetooth = policy.for_transport(TransportType::Bluetooth).unwrap();
+        assert!(bluetooth.is_settled());
+        assert_eq!(bluetooth.chunk_size(), 4 * 1024);
+
+        let tcp = policy.for_transport(TransportType::Tcp).unwrap();
+        assert!(!tcp.is_settled());
+        assert_eq!(tcp.chunk_size(), DEFAULT_MIN_CHUNK_SIZE);
+
+        let inverted = ChunkProbeConfig {
+            min: 2048,
+            max: 1024,
+            ..ChunkProbeConfig::default()
+        };
+        assert!(matches!(
+            ChunkSizePolicy::new(inverted).for_transport(TransportType::Tcp),
+            Err(ProtocolError::Configuration(_))
+        ));
+    }
+}
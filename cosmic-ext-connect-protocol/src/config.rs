@@ -0,0 +1,495 @@
+//! Unified Protocol Configuration
+//!
+//! Aggregates the per-subsystem configuration structs (connection, discovery,
+//! pairing, resources, power profile, payload encryption and, with the
+//! `extendeddisplay` feature, display streaming)
+//! into a single [`CConnectConfig`] that can be loaded from one file and
+//! validated up front.
+//!
+//! Every section uses `#[serde(default)]`, so a config file only needs to list
+//! the values it wants to override. Durations are written as whole seconds.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let config = CConnectConfig::from_json_str(r#"{ "pairing": { "timeout": 45 } }"#)?;
+//! config.validate()?;
+//! ```
+
+use crate::connection::ConnectionConfig;
+use crate::discovery::DiscoveryConfig;
+use crate::pairing::PairingConfig;
+use crate::payload_crypto::PayloadEncryptionConfig;
+use crate::power_profile::PowerProfileConfig;
+use crate::resource_manager::ResourceConfig;
+use crate::{ProtocolError, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+/// Lowest port accepted for listening sockets (privileged ports are rejected)
+pub const MIN_LISTEN_PORT: u16 = 1024;
+
+/// Upper bound for any configured timeout or interval
+pub const MAX_CONFIG_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Serde helper storing a [`Duration`] as whole seconds
+pub mod duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(value.as_secs())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_secs)
+    }
+}
+
+/// Serde helper storing an optional [`Duration`] as whole seconds
+pub mod optional_duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(duration) => serializer.serialize_some(&duration.as_secs()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_secs))
+    }
+}
+
+/// Top-level protocol configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CConnectConfig {
+    /// TLS connection manager settings
+    pub connection: ConnectionConfig,
+    /// UDP discovery settings
+    pub discovery: DiscoveryConfig,
+    /// Pairing settings
+    pub pairing: PairingConfig,
+    /// Connection and transfer limits
+    pub resources: ResourceConfig,
+    /// Reduced discovery and keepalive cadence on battery
+    pub power: PowerProfileConfig,
+    /// End-to-end payload encryption
+    pub encryption: PayloadEncryptionConfig,
+    /// Display streaming server settings
+    #[cfg(feature = "extendeddisplay")]
+    pub stream: cosmic_ext_display_stream::StreamConfig,
+}
+
+impl CConnectConfig {
+    /// Parse a configuration from JSON
+    pub fn from_json_str(contents: &str) -> Result<Self> {
+        Ok(serde_json::from_str(contents)?)
+    }
+
+    /// Load and validate a JSON configuration file
+    pub fn load_json(path: impl AsRef<Path>) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let config = Self::from_json_str(&contents)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check every section for out-of-range values
+    ///
+    /// Returns the first problem found as a [`ProtocolError::Configuration`]
+    /// naming the offending field.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_connection()?;
+        self.validate_discovery()?;
+        self.validate_pairing()?;
+        self.validate_resources()?;
+        self.validate_power()?;
+        #[cfg(feature = "extendeddisplay")]
+        self.validate_stream()?;
+        Ok(())
+    }
+
+    fn validate_connection(&self) -> Result<()> {
+        let c = &self.connection;
+        check_port("connection.listen_addr", c.listen_addr.port())?;
+        check_duration("connection.keep_alive_interval", c.keep_alive_interval)?;
+        check_duration("connection.connection_timeout", c.connection_timeout)?;
+        check_duration("connection.keepalive_timeout", c.keepalive_timeout)?;
+        if c.connection_timeout <= c.keep_alive_interval {
+            return Err(invalid(format!(
+                "connection.connection_timeout ({}s) must be greater than connection.keep_alive_interval ({}s)",
+                c.connection_timeout.as_secs(),
+                c.keep_alive_interval.as_secs()
+            )));
+        }
+        if c.keepalive_timeout <= c.keep_alive_interval {
+            return Err(invalid(format!(
+                "connection.keepalive_timeout ({}s) must be greater than connection.keep_alive_interval ({}s)",
+                c.keepalive_timeout.as_secs(),
+                c.keep_alive_interval.as_secs()
+            )));
+        }
+        if let Some(idle_timeout) = c.idle_timeout {
+            check_duration("connection.idle_timeout", idle_timeout)?;
+            if idle_timeout <= c.keep_alive_interval {
+                return Err(invalid(format!(
+                    "connection.idle_timeout ({}s) must be greater than connection.keep_alive_interval ({}s)",
+                    idle_timeout.as_secs(),
+                    c.keep_alive_interval.as_secs()
+                )));
+            }
+        }
+        let s = &c.socket_options;
+        if s.keepalive {
+            check_duration("connection.socket_options.keepalive_idle", s.keepalive_idle)?;
+            check_duration(
+                "connection.socket_options.keepalive_interval",
+                s.keepalive_interval,
+            )?;
+            check_nonzero(
+                "connection.socket_options.keepalive_probes",
+                s.keepalive_probes,
+            )?;
+        }
+        if c.packet_tap.enabled {
+            check_nonzero(
+                "connection.packet_tap.max_file_size",
+                c.packet_tap.max_file_size,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn validate_discovery(&self) -> Result<()> {
+        let d = &self.discovery;
+        check_duration("discovery.broadcast_interval", d.broadcast_interval)?;
+        check_duration("discovery.device_timeout", d.device_timeout)?;
+        if d.enable_timeout_check && d.device_timeout <= d.broadcast_interval {
+            return Err(invalid(format!(
+                "discovery.device_timeout ({}s) must be greater than discovery.broadcast_interval ({}s)",
+                d.device_timeout.as_secs(),
+                d.broadcast_interval.as_secs()
+            )));
+        }
+        Ok(())
+    }
+
+    fn validate_pairing(&self) -> Result<()> {
+        check_duration("pairing.timeout", self.pairing.timeout)?;
+        if let Some(timeout) = self.pairing.timeout_override {
+            check_duration("pairing.timeout_override", timeout)?;
+        }
+        Ok(())
+    }
+
+    fn validate_resources(&self) -> Result<()> {
+        let r = &self.resources;
+        check_nonzero(
+            "resources.max_connections_per_device",
+            r.max_connections_per_device,
+        )?;
+        check_nonzero("resources.max_total_connections", r.max_total_connections)?;
+        check_nonzero(
+            "resources.max_concurrent_transfers",
+            r.max_concurrent_transfers,
+        )?;
+        check_nonzero(
+            "resources.max_transfers_per_device",
+            r.max_transfers_per_device,
+        )?;
+        check_nonzero("resources.max_packet_queue_size", r.max_packet_queue_size)?;
+        check_nonzero("resources.max_transfer_size", r.max_transfer_size)?;
+        check_nonzero(
+            "resources.memory_pressure_threshold",
+            r.memory_pressure_threshold,
+        )?;
+        check_at_most(
+            "resources.max_connections_per_device",
+            r.max_connections_per_device,
+            "resources.max_total_connections",
+            r.max_total_connections,
+        )?;
+        check_at_most(
+            "resources.max_transfers_per_device",
+            r.max_transfers_per_device,
+            "resources.max_concurrent_transfers",
+            r.max_concurrent_transfers,
+        )?;
+        check_at_most(
+            "resources.max_transfer_size",
+            r.max_transfer_size,
+            "resources.max_total_transfer_size",
+            r.max_total_transfer_size,
+        )
+    }
+
+    fn validate_power(&self) -> Result<()> {
+        let p = &self.power;
+        if !p.enabled {
+            return Ok(());
+        }
+        check_duration(
+            "power.battery_broadcast_interval",
+            p.battery_broadcast_interval,
+        )?;
+        check_duration(
+            "power.battery_keep_alive_interval",
+            p.battery_keep_alive_interval,
+        )?;
+        check_duration("power.poll_interval", p.poll_interval)?;
+        let d = &self.discovery;
+        if d.enable_timeout_check && d.device_timeout <= p.battery_broadcast_interval {
+            return Err(invalid(format!(
+                "discovery.device_timeout ({}s) must be greater than power.battery_broadcast_interval ({}s)",
+                d.device_timeout.as_secs(),
+                p.battery_broadcast_interval.as_secs()
+            )));
+        }
+        let c = &self.connection;
+        let timeouts = [
+            ("connection.connection_timeout", c.connection_timeout),
+            ("connection.keepalive_timeout", c.keepalive_timeout),
+        ]
+        .into_iter()
+        .chain(c.idle_timeout.map(|t| ("connection.idle_timeout", t)));
+        for (field, timeout) in timeouts {
+            if timeout <= p.battery_keep_alive_interval {
+                return Err(invalid(format!(
+                    "{} ({}s) must be greater than power.battery_keep_alive_interval ({}s)",
+                    field,
+                    timeout.as_secs(),
+                    p.battery_keep_alive_interval.as_secs()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "extendeddisplay")]
+    fn validate_stream(&self) -> Result<()> {
+        let s = &self.stream;
+        check_port("stream.signaling_port", s.signaling_port)?;
+        check_nonzero("stream.max_clients", s.max_clients)?;
+        if !(1..=240).contains(&s.framerate) {
+            return Err(invalid(format!(
+                "stream.framerate must be between 1 and 240 fps, got {}",
+                s.framerate
+            )));
+        }
+        if let Some(timeout) = s.heartbeat_timeout {
+            check_duration("stream.heartbeat_timeout", timeout)?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid(message: String) -> ProtocolError {
+    ProtocolError::Configuration(message)
+}
+
+fn check_port(field: &str, port: u16) -> Result<()> {
+    if port < MIN_LISTEN_PORT {
+        return Err(invalid(format!(
+            "{} port must be between {} and 65535, got {}",
+            field, MIN_LISTEN_PORT, port
+        )));
+    }
+    Ok(())
+}
+
+fn check_duration(field: &str, value: Duration) -> Result<()> {
+    if value.is_zero() {
+        return Err(invalid(format!("{} must be greater than 0 seconds", field)));
+    }
+    if value > MAX_CONFIG_DURATION {
+        return Err(invalid(format!(
+            "{} must be at most {}s, got {}s",
+            field,
+            MAX_CONFIG_DURATION.as_secs(),
+            value.as_secs()
+        )));
+    }
+    Ok(())
+}
+
+fn check_nonzero<T: PartialEq + Default>(field: &str, value: T) -> Result<()> {
+    if value == T::default() {
+        return Err(invalid(format!("{} must be greater than 0", field)));
+    }
+    Ok(())
+}
+
+fn check_at_most<T: PartialOrd + std::fmt::Display>(
+    field: &str,
+    value: T,
+    limit_field: &str,
+    limit: T,
+) -> Result<()> {
+    if value > limit {
+        return Err(invalid(format!(
+            "{} ({}) must not exceed {} ({})",
+            field, value, limit_field, limit
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_message(config: &CConnectConfig) -> String {
+        match config.validate() {
+            Err(ProtocolError::Configuration(msg)) => msg,
+            other => panic!("expected configuration error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_default_is_valid() {
+        CConnectConfig::default().validate().unwrap();
+    }
+
+    #[test]
+    fn test_default_round_trip() {
+        let config = CConnectConfig::default();
+        let json = serde_json::to_string(&config).unwrap();
+        let parsed = CConnectConfig::from_json_str(&json).unwrap();
+
+        assert_eq!(parsed.connection.listen_addr, config.connection.listen_addr);
+        assert_eq!(
+            parsed.connection.keep_alive_interval,
+            config.connection.keep_alive_interval
+        );
+        assert_eq!(
+            parsed.discovery.device_timeout,
+            config.discovery.device_timeout
+        );
+        assert_eq!(parsed.pairing.cert_dir, config.pairing.cert_dir);
+        assert_eq!(
+            parsed.resources.max_transfer_size,
+            config.resources.max_transfer_size
+        );
+        parsed.validate().unwrap();
+    }
+
+    #[test]
+    fn test_partial_file_uses_defaults() {
+        let parsed = CConnectConfig::from_json_str(r#"{ "pairing": { "timeout": 45 } }"#).unwrap();
+
+        assert_eq!(parsed.pairing.timeout, Duration::from_secs(45));
+        assert_eq!(
+            parsed.connection.connection_timeout,
+            ConnectionConfig::default().connection_timeout
+        );
+    }
+
+    #[test]
+    fn test_privileged_port_rejected() {
+        let mut config = CConnectConfig::default();
+        config.connection.listen_addr = "0.0.0.0:80".parse().unwrap();
+
+        let msg = error_message(&config);
+        assert!(msg.contains("connection.listen_addr"));
+        assert!(msg.contains("got 80"));
+    }
+
+    #[test]
+    fn test_zero_timeout_rejected() {
+        let mut config = CConnectConfig::default();
+        config.pairing.timeout = Duration::ZERO;
+
+        assert_eq!(
+            error_message(&config),
+            "pairing.timeout must be greater than 0 seconds"
+        );
+    }
+
+    #[test]
+    fn test_idle_timeout_round_trip() {
+        let parsed =
+            CConnectConfig::from_json_str(r#"{ "connection": { "idle_timeout": 600 } }"#).unwrap();
+        assert_eq!(
+            parsed.connection.idle_timeout,
+            Some(Duration::from_secs(600))
+        );
+        parsed.validate().unwrap();
+
+        let json = serde_json::to_value(CConnectConfig::default()).unwrap();
+        assert!(json["connection"]["idle_timeout"].is_null());
+    }
+
+    #[test]
+    fn test_timeout_ordering_rejected() {
+        let mut config = CConnectConfig::default();
+        config.discovery.device_timeout = config.discovery.broadcast_interval;
+
+        assert!(error_message(&config).contains("discovery.device_timeout"));
+    }
+
+    #[test]
+    fn test_keepalive_fields() {
+        let parsed = CConnectConfig::from_json_str(
+            r#"{ "connection": { "keepalive_interval": 20, "keepalive_timeout": 90 } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.connection.keep_alive_interval,
+            Duration::from_secs(20)
+        );
+        assert_eq!(parsed.connection.keepalive_timeout, Duration::from_secs(90));
+        parsed.validate().unwrap();
+
+        let mut config = parsed;
+        config.connection.keepalive_timeout = config.connection.keep_alive_interval;
+        assert!(error_message(&config).contains("connection.keepalive_timeout"));
+    }
+
+    #[test]
+    fn test_battery_keep_alive_must_beat_timeout() {
+        let mut config = CConnectConfig::default();
+        config.power.enabled = true;
+        config.validate().unwrap();
+
+        config.power.battery_keep_alive_interval = config.connection.connection_timeout;
+        let msg = error_message(&config);
+        assert!(msg.contains("connection.connection_timeout"));
+        assert!(msg.contains("power.battery_keep_alive_interval"));
+
+        // Only checked while the profile is in use
+        config.power.enabled = false;
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_size_limits_rejected() {
+        let mut config = CConnectConfig::default();
+        config.resources.max_transfer_size = config.resources.max_total_transfer_size + 1;
+
+        let msg = error_message(&config);
+        assert!(msg.contains("resources.max_transfer_size"));
+        assert!(msg.contains("resources.max_total_transfer_size"));
+    }
+
+    #[test]
+    fn test_load_json_validates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cconnect.json");
+        std::fs::write(&path, r#"{ "resources": { "max_total_connections": 0 } }"#).unwrap();
+
+        assert!(matches!(
+            CConnectConfig::load_json(&path),
+            Err(ProtocolError::Configuration(_))
+        ));
+    }
+}
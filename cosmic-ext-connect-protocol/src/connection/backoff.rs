@@ -0,0 +1,141 @@
+//! Reconnect Backoff
+//!
+//! Per-device exponential backoff for automatic reconnects. Every scheduled
+//! attempt doubles the wait before the next one (2^n seconds, capped), and a
+//! successful connection clears the device's state. A manual
+//! "reconnect now" skips the wait without touching the attempt count, so a
+//! failed manual attempt leaves the schedule exactly as it was.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Longest wait between scheduled reconnect attempts
+pub const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Backoff state for one device
+#[derive(Debug, Clone, Copy)]
+struct DeviceBackoff {
+    last_attempt: Instant,
+    failures: u32,
+}
+
+/// Reconnect backoff for all devices
+#[derive(Debug, Clone)]
+pub struct ReconnectBackoff {
+    max_delay: Duration,
+    devices: HashMap<String, DeviceBackoff>,
+}
+
+impl Default for ReconnectBackoff {
+    fn default() -> Self {
+        Self::new(MAX_RECONNECT_BACKOFF)
+    }
+}
+
+impl ReconnectBackoff {
+    /// Create an empty backoff table with the given delay cap
+    pub fn new(max_delay: Duration) -> Self {
+        Self {
+            max_delay,
+            devices: HashMap::new(),
+        }
+    }
+
+    /// Attempts made since the device last connected successfully
+    pub fn failures(&self, device_id: &str) -> u32 {
+        self.devices
+            .get(device_id)
+            .map_or(0, |state| state.failures)
+    }
+
+    /// Current wait between scheduled attempts for a device
+    pub fn delay(&self, device_id: &str) -> Duration {
+        let failures = self.failures(device_id);
+        Duration::from_secs(2u64.pow(failures.min(6))).min(self.max_delay)
+    }
+
+    /// When the next scheduled attempt may start (`None` = immediately)
+    pub fn next_attempt_at(&self, device_id: &str) -> Option<Instant> {
+        self.devices
+            .get(device_id)
+            .map(|state| state.last_attempt + self.delay(device_id))
+    }
+
+    /// Whether a scheduled attempt may start at `now`
+    pub fn is_due(&self, device_id: &str, now: Instant) -> bool {
+        match self.next_attempt_at(device_id) {
+            Some(next_attempt) => now >= next_attempt,
+            None => true,
+        }
+    }
+
+    /// Record the start of a scheduled attempt, returning its attempt number
+    pub fn record_attempt(&mut self, device_id: &str, now: Instant) -> u32 {
+        let state = self
+            .devices
+            .entry(device_id.to_string())
+            .or_insert(DeviceBackoff {
+                last_attempt: now,
+                failures: 0,
+            });
+        state.last_attempt = now;
+        state.failures += 1;
+        state.failures
+    }
+
+    /// Clear a device's backoff after it connected
+    pub fn record_success(&mut self, device_id: &str) {
+        self.devices.remove(device_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_until_capped() {
+        let mut backoff = ReconnectBackoff::new(Duration::from_secs(10));
+        let start = Instant::now();
+        assert!(backoff.is_due("phone", start));
+
+        assert_eq!(backoff.record_attempt("phone", start), 1);
+        assert_eq!(backoff.delay("phone"), Duration::from_secs(2));
+        assert!(!backoff.is_due("phone", start + Duration::from_secs(1)));
+        assert!(backoff.is_due("phone", start + Duration::from_secs(2)));
+
+        backoff.record_attempt("phone", start);
+        assert_eq!(backoff.delay("phone"), Duration::from_secs(4));
+        for _ in 0..5 {
+            backoff.record_attempt("phone", start);
+        }
+        assert_eq!(backoff.delay("phone"), Duration::from_secs(10));
+
+        backoff.record_success("phone");
+        assert_eq!(backoff.failures("phone"), 0);
+        assert!(backoff.next_attempt_at("phone").is_none());
+    }
+
+    #[test]
+    fn test_success_after_failures_restarts_from_base_delay() {
+        let mut backoff = ReconnectBackoff::default();
+        let start = Instant::now();
+        for _ in 0..4 {
+            backoff.record_attempt("phone", start);
+        }
+        assert_eq!(backoff.delay("phone"), Duration::from_secs(16));
+
+        backoff.record_success("phone");
+        assert_eq!(backoff.delay("phone"), Duration::from_secs(1));
+        assert!(backoff.is_due("phone", start));
+
+        // Later failures count from one again instead of resuming at four
+        let later = start + Duration::from_secs(30);
+        assert_eq!(backoff.record_attempt("phone", later), 1);
+        assert_eq!(backoff.delay("phone"), Duration::from_secs(2));
+        assert_eq!(
+            backoff.next_attempt_at("phone"),
+            Some(later + Duration::from_secs(2))
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Request/Response Correlation
+//!
+//! Plugins that ask a device for something (system info, a screenshot, the
+//! current volume) need to match the answer to the question. A request sent
+//! through [`ConnectionManager::send_request`](super::ConnectionManager::send_request)
+//! gets a [`REQUEST_ID_FIELD`] in its body; the device answers with any
+//! packet carrying the same number in [`REPLY_TO_FIELD`] (see [`reply_to`]).
+//! The connection task hands that packet to the waiting request instead of
+//! emitting it as a regular [`PacketReceived`](super::ConnectionEvent::PacketReceived).
+//!
+//! ## Protocol
+//!
+//! ```json
+//! { "type": "cconnect.systeminfo.request", "body": { "requestId": 7 } }
+//! { "type": "cconnect.systeminfo", "body": { "replyTo": 7, "hostname": "pixel" } }
+//! ```
+//!
+//! Replies are only matched against requests sent to the same device, so a
+//! device cannot answer for another one.
+
+use crate::Packet;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+use tracing::debug;
+
+/// Request body field carrying the correlation ID
+pub const REQUEST_ID_FIELD: &str = "requestId";
+
+/// Reply body field naming the request it answers
+pub const REPLY_TO_FIELD: &str = "replyTo";
+
+/// Correlation ID of a request packet, if it has one
+pub fn request_id(packet: &Packet) -> Option<u64> {
+    packet.body.get(REQUEST_ID_FIELD)?.as_u64()
+}
+
+/// Build the reply to `request`, carrying its correlation ID if it has one
+pub fn reply_to(
+    request: &Packet,
+    packet_type: impl Into<String>,
+    body: serde_json::Value,
+) -> Packet {
+    let reply = Packet::new(packet_type, body);
+    match request_id(request) {
+        Some(id) => reply.with_body_field(REPLY_TO_FIELD, id),
+        None => reply,
+    }
+}
+
+/// Requests waiting for their reply, keyed by device and correlation ID
+#[derive(Debug)]
+pub struct PendingRequests {
+    next_id: AtomicU64,
+    waiting: Mutex<HashMap<(String, u64), oneshot::Sender<Packet>>>,
+}
+
+impl Default for PendingRequests {
+    fn default() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            waiting: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl PendingRequests {
+    /// Tag `packet` with a fresh correlation ID and wait for its reply
+    ///
+    /// Returns the ID and the receiver the reply will be delivered to.
+    pub fn register(
+        &self,
+        device_id: &str,
+        packet: &mut Packet,
+    ) -> (u64, oneshot::Receiver<Packet>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        if let Some(body) = packet.body.as_object_mut() {
+            body.insert(REQUEST_ID_FIELD.to_string(), id.into());
+        } else {
+            packet.body = serde_json::json!({ REQUEST_ID_FIELD: id });
+        }
+        let (tx, rx) = oneshot::channel();
+        self.waiting
+            .lock()
+            .unwrap()
+            .insert((device_id.to_string(), id), tx);
+        (id, rx)
+    }
+
+    /// Hand `packet` to the request it answers
+    ///
+    /// Returns `false`, leaving the packet to regular dispatch, if it is not
+    /// a reply to a pending request from `device_id`.
+    pub fn resolve(&self, device_id: &str, packet: &Packet) -> bool {
+        let Some(id) = packet.body.get(REPLY_TO_FIELD).and_then(|v| v.as_u64()) else {
+            return false;
+        };
+        let waiter = self
+            .waiting
+            .lock()
+            .unwrap()
+            .remove(&(device_id.to_string(), id));
+        match waiter {
+            Some(tx) => {
+                // The requester may have given up in the meantime
+                let _ = tx.send(packet.clone());
+                true
+            }
+            None => {
+                debug!("Reply {} from {} matches no pending request", id, device_id);
+                false
+            }
+        }
+    }
+
+    /// Stop waiting for the reply to request `id`
+    pub fn cancel(&self, device_id: &str, id: u64) {
+        self.waiting
+            .lock()
+            .unwrap()
+            .remove(&(device_id.to_string(), id));
+    }
+
+    /// Number of requests waiting for a reply
+    pub fn len(&self) -> usize {
+        self.waiting.lock().unwrap().len()
+    }
+
+    /// Whether no request is waiting
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reply_resolves_matching_request() {
+        let pending = PendingRequests::default();
+        let mut request = Packet::new("cconnect.systeminfo.request", json!({}));
+        let (id, mut rx) = pending.register("phone", &mut request);
+        assert_eq!(request_id(&request), Some(id));
+
+        let reply = reply_to(
+            &request,
+            "cconnect.systeminfo",
+            json!({"hostname": "pixel"}),
+        );
+        assert!(pending.resolve("phone", &reply));
+        assert_eq!(rx.try_recv().unwrap().body["hostname"], "pixel");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_unrelated_reply_is_not_matched() {
+        let pending = PendingRequests::default();
+        let mut request = Packet::new("cconnect.systeminfo.request", json!({}));
+        let (id, mut rx) = pending.register("phone", &mut request);
+
+        let other = Packet::new("cconnect.systeminfo", json!({REPLY_TO_FIELD: id + 1}));
+        let plain = Packet::new("cconnect.battery", json!({"currentCharge": 50}));
+        let reply = reply_to(&request, "cconnect.systeminfo", json!({}));
+        assert!(!pending.resolve("phone", &other));
+        assert!(!pending.resolve("phone", &plain));
+        // Another device cannot answer for the phone
+        assert!(!pending.resolve("tablet", &reply));
+        assert!(rx.try_recv().is_err());
+
+        pending.cancel("phone", id);
+        assert!(!pending.resolve("phone", &reply));
+    }
+}
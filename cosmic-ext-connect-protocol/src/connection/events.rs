@@ -2,8 +2,12 @@
 //!
 //! Events emitted by the connection manager for device connectivity changes.
 
+use super::state::LinkState;
+use super::teardown::{CancelOutcome, DependentTask};
+use super::traffic::TrafficStats;
 use crate::Packet;
 use std::net::SocketAddr;
+use std::time::Duration;
 
 /// Connection event types
 #[derive(Debug, Clone)]
@@ -26,6 +30,31 @@ pub enum ConnectionEvent {
         reconnect: bool,
     },
 
+    /// A connected device stopped answering and its connection was closed
+    ///
+    /// Sent when nothing arrived from a peer that answers keepalives for the
+    /// connection timeout. The matching [`Disconnected`](Self::Disconnected)
+    /// event follows once the connection is torn down.
+    Timeout {
+        /// Device ID
+        device_id: String,
+        /// How long the device had been silent
+        silence: Duration,
+    },
+
+    /// A device dropped and reconnected within the quiet reconnect window
+    ///
+    /// Replaces the [`Disconnected`](Self::Disconnected) and
+    /// [`Connected`](Self::Connected) pair so consumers can treat the drop as
+    /// a transient quality dip rather than a lost device. Only emitted when
+    /// the manager is configured with a quiet reconnect window.
+    LinkFlapped {
+        /// Device ID
+        device_id: String,
+        /// How long the device was gone
+        downtime: Duration,
+    },
+
     /// A packet has been received from a device
     PacketReceived {
         /// Device ID that sent the packet
@@ -36,6 +65,65 @@ pub enum ConnectionEvent {
         remote_addr: SocketAddr,
     },
 
+    /// A connected device re-advertised its capabilities
+    ///
+    /// Sent when an identity packet received mid-session changes what the
+    /// device supports, e.g. after a plugin was enabled on the phone. The
+    /// device's capabilities have already been updated; the connection stays
+    /// up.
+    CapabilitiesChanged {
+        /// Device ID
+        device_id: String,
+        /// Packet types the device now accepts
+        incoming_capabilities: Vec<String>,
+        /// Packet types the device now sends
+        outgoing_capabilities: Vec<String>,
+    },
+
+    /// A task tied to a connection was stopped because the connection closed
+    ///
+    /// Sent during teardown, before the transport is closed and before the
+    /// matching [`Disconnected`](Self::Disconnected) event.
+    TaskCancelled {
+        /// Device ID
+        device_id: String,
+        /// Kind of task
+        task: DependentTask,
+        /// Name the task was registered with
+        name: String,
+        /// How the task ended
+        outcome: CancelOutcome,
+    },
+
+    /// A peer outside the local subnets was refused in link-local only mode
+    ///
+    /// Sent for outgoing attempts as well as accepted connections, before
+    /// any identity is exchanged on the socket.
+    ConnectionRejected {
+        /// Device ID (known for outgoing attempts only)
+        device_id: Option<String>,
+        /// Address of the refused peer
+        remote_addr: SocketAddr,
+        /// Why the peer was refused
+        reason: String,
+    },
+
+    /// A paired device presented a certificate other than the pinned one, or none
+    ///
+    /// The connection was refused before the device was marked connected.
+    /// If the device was reinstalled, [`Device::reset_trust`](crate::Device::reset_trust)
+    /// lets the next connection pin its new certificate.
+    CertificateMismatch {
+        /// Device ID the peer claimed
+        device_id: String,
+        /// Address of the refused peer
+        remote_addr: SocketAddr,
+        /// Fingerprint pinned for the device
+        expected_fingerprint: String,
+        /// Fingerprint the peer presented (empty if it presented none)
+        presented_fingerprint: String,
+    },
+
     /// An error occurred with a connection
     ConnectionError {
         /// Device ID (if known)
@@ -44,6 +132,29 @@ pub enum ConnectionEvent {
         message: String,
     },
 
+    /// Byte counters for a device changed
+    ///
+    /// Emitted periodically while connected and once more when the connection
+    /// closes (with `session_ended` set), after which the counters reset.
+    TrafficUpdated {
+        /// Device ID
+        device_id: String,
+        /// Cumulative bytes for this session
+        stats: TrafficStats,
+        /// Whether these are the final totals of a closed connection
+        session_ended: bool,
+    },
+
+    /// A device's link moved between states of the connection state machine
+    StateChanged {
+        /// Device ID
+        device_id: String,
+        /// State before the transition
+        from: LinkState,
+        /// State after the transition
+        to: LinkState,
+    },
+
     /// Connection manager started
     ManagerStarted {
         /// Local port listening on
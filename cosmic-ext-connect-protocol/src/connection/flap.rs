@@ -0,0 +1,233 @@
+//! Quiet Reconnects
+//!
+//! Flaky links drop and come back within moments. Reporting each drop as a
+//! disconnect makes consumers tear down plugins and redraw the UI only to set
+//! everything up again a second later. With a quiet reconnect window, a
+//! disconnect is held back; if the device reconnects before the window ends
+//! both events are replaced by a single [`ConnectionEvent::LinkFlapped`],
+//! otherwise the disconnect is delivered late but unchanged.
+
+use super::events::ConnectionEvent;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+/// Holds disconnects back until they outlast the quiet reconnect window
+#[derive(Debug)]
+pub(crate) struct FlapDebouncer {
+    window: Duration,
+    /// Withheld disconnects by device, with the time they happened
+    held: HashMap<String, (Instant, ConnectionEvent)>,
+}
+
+impl FlapDebouncer {
+    /// Create a debouncer that hides drops shorter than `window`
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            held: HashMap::new(),
+        }
+    }
+
+    /// Take an event seen at `now`, returning the events to deliver
+    pub(crate) fn push(&mut self, event: ConnectionEvent, now: Instant) -> Vec<ConnectionEvent> {
+        let mut ready = self.expire(now);
+        match event {
+            ConnectionEvent::Disconnected {
+                ref device_id,
+                reconnect: false,
+                ..
+            } => {
+                self.held.insert(device_id.clone(), (now, event));
+            }
+            ConnectionEvent::Connected { ref device_id, .. } => match self.held.remove(device_id) {
+                Some((dropped_at, _)) => {
+                    let downtime = now.saturating_duration_since(dropped_at);
+                    debug!(
+                        "{} reconnected after {:?}, not reporting",
+                        device_id, downtime
+                    );
+                    ready.push(ConnectionEvent::LinkFlapped {
+                        device_id: device_id.clone(),
+                        downtime,
+                    });
+                }
+                None => ready.push(event),
+            },
+            other => ready.push(other),
+        }
+        ready
+    }
+
+    /// When the oldest withheld disconnect is due
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.held
+            .values()
+            .map(|(dropped_at, _)| *dropped_at + self.window)
+            .min()
+    }
+
+    /// Release disconnects that outlasted the window as of `now`
+    pub(crate) fn expire(&mut self, now: Instant) -> Vec<ConnectionEvent> {
+        let window = self.window;
+        let mut due: Vec<_> = self
+            .held
+            .iter()
+            .filter(|(_, (dropped_at, _))| now.saturating_duration_since(*dropped_at) >= window)
+            .map(|(device_id, (dropped_at, _))| (*dropped_at, device_id.clone()))
+            .collect();
+        due.sort();
+        due.into_iter()
+            .filter_map(|(_, device_id)| self.held.remove(&device_id))
+            .map(|(_, event)| event)
+            .collect()
+    }
+
+    /// Release everything still withheld
+    pub(crate) fn flush(&mut self) -> Vec<ConnectionEvent> {
+        let mut held: Vec<_> = self.held.drain().map(|(_, held)| held).collect();
+        held.sort_by_key(|(dropped_at, _)| *dropped_at);
+        held.into_iter().map(|(_, event)| event).collect()
+    }
+}
+
+/// Forward `events`, hiding reconnects that happen within `window`
+pub(crate) fn debounce_flaps(
+    mut events: mpsc::UnboundedReceiver<ConnectionEvent>,
+    window: Duration,
+) -> mpsc::UnboundedReceiver<ConnectionEvent> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut debouncer = FlapDebouncer::new(window);
+        loop {
+            let deadline = debouncer.next_deadline();
+            let ready = tokio::select! {
+                event = events.recv() => match event {
+                    Some(event) => debouncer.push(event, Instant::now()),
+                    None => {
+                        for event in debouncer.flush() {
+                            let _ = tx.send(event);
+                        }
+                        break;
+                    }
+                },
+                _ = sleep_until(deadline), if deadline.is_some() => {
+                    debouncer.expire(Instant::now())
+                }
+            };
+            for event in ready {
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    rx
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    if let Some(deadline) = deadline {
+        tokio::time::sleep_until(tokio::time::Instant::from_std(deadline)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connected(device_id: &str) -> ConnectionEvent {
+        ConnectionEvent::Connected {
+            device_id: device_id.to_string(),
+            remote_addr: "192.168.1.20:1816".parse().unwrap(),
+        }
+    }
+
+    fn disconnected(device_id: &str) -> ConnectionEvent {
+        ConnectionEvent::Disconnected {
+            device_id: device_id.to_string(),
+            reason: Some("Connection reset".to_string()),
+            reconnect: false,
+        }
+    }
+
+    #[test]
+    fn test_short_flap_reported_as_dip() {
+        let start = Instant::now();
+        let mut debouncer = FlapDebouncer::new(Duration::from_secs(5));
+
+        assert!(debouncer.push(disconnected("phone"), start).is_empty());
+        let ready = debouncer.push(connected("phone"), start + Duration::from_secs(2));
+
+        assert!(matches!(
+            ready.as_slice(),
+            [ConnectionEvent::LinkFlapped { device_id, downtime }]
+                if device_id == "phone" && *downtime == Duration::from_secs(2)
+        ));
+        assert!(debouncer.next_deadline().is_none());
+    }
+
+    #[test]
+    fn test_long_outage_still_disconnects() {
+        let start = Instant::now();
+        let mut debouncer = FlapDebouncer::new(Duration::from_secs(5));
+
+        assert!(debouncer.push(disconnected("phone"), start).is_empty());
+        assert_eq!(
+            debouncer.next_deadline(),
+            Some(start + Duration::from_secs(5))
+        );
+        assert!(debouncer.expire(start + Duration::from_secs(4)).is_empty());
+
+        let ready = debouncer.expire(start + Duration::from_secs(5));
+        assert!(matches!(
+            ready.as_slice(),
+            [ConnectionEvent::Disconnected { device_id, .. }] if device_id == "phone"
+        ));
+
+        // The later reconnect is an ordinary connect
+        let ready = debouncer.push(connected("phone"), start + Duration::from_secs(8));
+        assert!(matches!(
+            ready.as_slice(),
+            [ConnectionEvent::Connected { .. }]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_debounced_stream() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut events = debounce_flaps(rx, Duration::from_millis(100));
+
+        // A sub-window flap surfaces as a dip only
+        tx.send(disconnected("phone")).unwrap();
+        tx.send(connected("phone")).unwrap();
+        assert!(matches!(
+            events.recv().await,
+            Some(ConnectionEvent::LinkFlapped { .. })
+        ));
+
+        // A longer outage is delivered once the window has passed
+        tx.send(disconnected("phone")).unwrap();
+        let started = Instant::now();
+        assert!(matches!(
+            events.recv().await,
+            Some(ConnectionEvent::Disconnected { .. })
+        ));
+        assert!(started.elapsed() >= Duration::from_millis(100));
+
+        // Socket replacements are never held
+        tx.send(ConnectionEvent::Disconnected {
+            device_id: "phone".to_string(),
+            reason: None,
+            reconnect: true,
+        })
+        .unwrap();
+        assert!(matches!(
+            events.recv().await,
+            Some(ConnectionEvent::Disconnected {
+                reconnect: true,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,195 @@
+//! Idle Connection Tracking
+//!
+//! Keeps a per-device record of the last packet exchanged, running transfers
+//! and plugins that need the connection kept open, so the connection manager
+//! can drop connections nobody is using. Discovery and auto-connect bring the
+//! device back when it is needed again.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Activity state for one connected device
+#[derive(Debug)]
+struct DeviceActivity {
+    /// When a packet was last sent or received (keepalives excluded)
+    last_activity: Instant,
+    /// Number of payload transfers in progress
+    active_transfers: usize,
+    /// Exempt plugins currently running for this device
+    active_plugins: HashSet<String>,
+}
+
+impl DeviceActivity {
+    fn new() -> Self {
+        Self {
+            last_activity: Instant::now(),
+            active_transfers: 0,
+            active_plugins: HashSet::new(),
+        }
+    }
+}
+
+/// Tracks connection activity to decide which devices are idle
+#[derive(Debug)]
+pub(crate) struct IdleTracker {
+    /// Plugins whose activity keeps a connection alive
+    exempt_plugins: HashSet<String>,
+    /// Per-device activity
+    devices: HashMap<String, DeviceActivity>,
+}
+
+impl IdleTracker {
+    /// Create a tracker; `exempt_plugins` names plugins that prevent idle disconnect
+    pub(crate) fn new(exempt_plugins: &[String]) -> Self {
+        Self {
+            exempt_plugins: exempt_plugins.iter().cloned().collect(),
+            devices: HashMap::new(),
+        }
+    }
+
+    /// Record traffic for a device
+    pub(crate) fn touch(&mut self, device_id: &str) {
+        self.entry(device_id).last_activity = Instant::now();
+    }
+
+    /// Record the start of a payload transfer
+    pub(crate) fn transfer_started(&mut self, device_id: &str) {
+        let activity = self.entry(device_id);
+        activity.active_transfers += 1;
+        activity.last_activity = Instant::now();
+    }
+
+    /// Record the end of a payload transfer
+    pub(crate) fn transfer_finished(&mut self, device_id: &str) {
+        let activity = self.entry(device_id);
+        activity.active_transfers = activity.active_transfers.saturating_sub(1);
+        activity.last_activity = Instant::now();
+    }
+
+    /// Mark a plugin as running (or stopped) for a device
+    ///
+    /// Plugins not in the exempt list are ignored.
+    pub(crate) fn set_plugin_active(&mut self, device_id: &str, plugin: &str, active: bool) {
+        if !self.exempt_plugins.contains(plugin) {
+            return;
+        }
+        let activity = self.entry(device_id);
+        if active {
+            activity.active_plugins.insert(plugin.to_string());
+        } else {
+            activity.active_plugins.remove(plugin);
+        }
+    }
+
+    /// Forget a device after it disconnected
+    pub(crate) fn remove(&mut self, device_id: &str) {
+        self.devices.remove(device_id);
+    }
+
+    /// Whether a device has a payload transfer in progress
+    pub(crate) fn has_transfers(&self, device_id: &str) -> bool {
+        self.devices
+            .get(device_id)
+            .is_some_and(|a| a.active_transfers > 0)
+    }
+
+    /// Whether a device must be kept connected regardless of idleness
+    pub(crate) fn is_exempt(&self, device_id: &str) -> bool {
+        self.devices
+            .get(device_id)
+            .is_some_and(|a| a.active_transfers > 0 || !a.active_plugins.is_empty())
+    }
+
+    /// Devices with no activity for at least `timeout` that are not exempt
+    pub(crate) fn idle_devices(&self, timeout: Duration) -> Vec<String> {
+        let now = Instant::now();
+        self.devices
+            .iter()
+            .filter(|(id, activity)| {
+                now.duration_since(activity.last_activity) >= timeout && !self.is_exempt(id)
+            })
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    fn entry(&mut self, device_id: &str) -> &mut DeviceActivity {
+        self.devices
+            .entry(device_id.to_string())
+            .or_insert_with(DeviceActivity::new)
+    }
+}
+
+/// Whether a packet is a silent keepalive ping (does not count as activity)
+pub(crate) fn is_keepalive(packet: &crate::Packet) -> bool {
+    packet.is_type("cconnect.ping")
+        && packet
+            .body
+            .get("keepalive")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const TIMEOUT: Duration = Duration::from_millis(20);
+
+    fn tracker() -> IdleTracker {
+        IdleTracker::new(&["clipboard".to_string()])
+    }
+
+    #[test]
+    fn test_recent_activity_not_idle() {
+        let mut tracker = tracker();
+        tracker.touch("device1");
+
+        assert!(tracker.idle_devices(Duration::from_secs(60)).is_empty());
+    }
+
+    #[test]
+    fn test_inactive_device_is_idle() {
+        let mut tracker = tracker();
+        tracker.touch("device1");
+        std::thread::sleep(TIMEOUT * 2);
+
+        assert_eq!(tracker.idle_devices(TIMEOUT), vec!["device1".to_string()]);
+    }
+
+    #[test]
+    fn test_exempt_plugin_keeps_device() {
+        let mut tracker = tracker();
+        tracker.set_plugin_active("device1", "clipboard", true);
+        tracker.set_plugin_active("device2", "battery", true);
+        std::thread::sleep(TIMEOUT * 2);
+
+        assert!(tracker.is_exempt("device1"));
+        assert!(!tracker.is_exempt("device2"));
+        assert_eq!(tracker.idle_devices(TIMEOUT), vec!["device2".to_string()]);
+
+        tracker.set_plugin_active("device1", "clipboard", false);
+        assert!(!tracker.is_exempt("device1"));
+    }
+
+    #[test]
+    fn test_active_transfer_keeps_device() {
+        let mut tracker = tracker();
+        tracker.transfer_started("device1");
+        std::thread::sleep(TIMEOUT * 2);
+        assert!(tracker.idle_devices(TIMEOUT).is_empty());
+
+        tracker.transfer_finished("device1");
+        std::thread::sleep(TIMEOUT * 2);
+        assert_eq!(tracker.idle_devices(TIMEOUT), vec!["device1".to_string()]);
+    }
+
+    #[test]
+    fn test_keepalive_detection() {
+        let keepalive = crate::Packet::new("kdeconnect.ping", json!({ "keepalive": true }));
+        let ping = crate::Packet::new("cconnect.ping", json!({}));
+
+        assert!(is_keepalive(&keepalive));
+        assert!(!is_keepalive(&ping));
+    }
+}
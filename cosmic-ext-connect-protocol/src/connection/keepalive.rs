@@ -0,0 +1,113 @@
+//! Dead-Peer Detection
+//!
+//! A peer that vanished without closing its socket (suspended laptop, phone
+//! out of range, Wi-Fi dropped mid-transfer) leaves a connection that looks
+//! open until the OS gives up on it, which can take many minutes and never
+//! happens at all on some Bluetooth stacks. The heartbeat therefore sends a
+//! keepalive ping as a regular packet, and peers answer it with a keepalive
+//! reply. Any packet received counts as a sign of life; a connection that
+//! stays silent for [`ConnectionConfig::keepalive_timeout`] is timed out.
+//!
+//! Older clients, including the Android app, do not answer keepalives, but
+//! they still send identity refreshes, battery updates and their own pings,
+//! so the timeout is armed from any inbound traffic and kept well above the
+//! keepalive interval.
+//!
+//! [`ConnectionConfig::keepalive_timeout`]: super::ConnectionConfig::keepalive_timeout
+
+use super::idle::is_keepalive;
+use crate::Packet;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Silent ping sent by the heartbeat
+pub(crate) fn keepalive_ping() -> Packet {
+    Packet::new("cconnect.ping", serde_json::json!({ "keepalive": true }))
+}
+
+/// Answer to a peer's keepalive ping
+pub(crate) fn keepalive_reply() -> Packet {
+    Packet::new(
+        "cconnect.ping",
+        serde_json::json!({ "keepalive": true, "reply": true }),
+    )
+}
+
+/// Whether a packet is a keepalive ping that expects a reply
+pub(crate) fn wants_reply(packet: &Packet) -> bool {
+    is_keepalive(packet) && !is_reply(packet)
+}
+
+/// Whether a keepalive ping answers one of ours
+fn is_reply(packet: &Packet) -> bool {
+    packet
+        .body
+        .get("reply")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Tracks when a connection last heard from its peer
+#[derive(Debug)]
+pub(crate) struct PeerLiveness {
+    /// Silence after which the peer is considered gone
+    timeout: Duration,
+    /// When the last packet arrived
+    last_received: Instant,
+}
+
+impl PeerLiveness {
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            last_received: Instant::now(),
+        }
+    }
+
+    /// Record a packet from the peer
+    pub(crate) fn received(&mut self) {
+        self.last_received = Instant::now();
+    }
+
+    /// When the peer will be considered gone if nothing arrives before
+    pub(crate) fn deadline(&self) -> Instant {
+        self.last_received + self.timeout
+    }
+
+    /// How long the peer has been silent
+    pub(crate) fn silence(&self) -> Duration {
+        self.last_received.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const TIMEOUT: Duration = Duration::from_millis(30);
+
+    #[test]
+    fn test_only_pings_get_replies() {
+        assert!(wants_reply(&keepalive_ping()));
+        assert!(!wants_reply(&keepalive_reply()));
+        assert!(is_keepalive(&keepalive_reply()));
+        assert!(!wants_reply(&Packet::new("cconnect.ping", json!({}))));
+    }
+
+    #[tokio::test]
+    async fn test_silent_peer_times_out_without_answering_keepalives() {
+        // A peer that never replies to keepalives, like the Android app
+        let mut liveness = PeerLiveness::new(TIMEOUT);
+        liveness.received();
+        let deadline = liveness.deadline();
+        tokio::time::timeout(TIMEOUT * 3, tokio::time::sleep_until(deadline))
+            .await
+            .unwrap();
+        assert!(liveness.silence() >= TIMEOUT);
+
+        // Any traffic pushes the deadline back
+        liveness.received();
+        assert!(liveness.deadline() > deadline);
+    }
+}
@@ -16,18 +16,145 @@
 //! 3. A disconnected event is emitted for the old connection
 //! 4. A connected event is emitted for the new connection
 //! 5. No rejection is sent to the client, preventing cascade failures
+//!
+//! ## Idle Disconnect
+//!
+//! When [`ConnectionConfig::idle_timeout`] is set, connections that carried no
+//! packets (keepalives excluded) for that long are closed to save battery and
+//! radio time on the remote device. Devices with a transfer in progress or an
+//! exempt plugin running (see [`ConnectionConfig::idle_exempt_plugins`]) are
+//! kept. Discovery and auto-connect re-establish the connection on demand.
+//!
+//! ## Dead-Peer Detection
+//!
+//! The heartbeat sends a keepalive ping every
+//! [`ConnectionConfig::keep_alive_interval`]. A connection that receives
+//! nothing at all for [`ConnectionConfig::keepalive_timeout`] emits
+//! [`ConnectionEvent::Timeout`] and is closed as lost, so recovery reconnects
+//! it. Keepalives we receive are answered with a reply.
+//!
+//! ## Reconnect Backoff
+//!
+//! Automatic reconnects go through [`ConnectionManager::claim_reconnect_attempt`],
+//! which spaces attempts per device with exponential backoff (see
+//! [`ReconnectBackoff`]). [`ConnectionManager::reconnect_now`] makes a single
+//! immediate attempt for a user who does not want to wait. Any successful
+//! connection clears the backoff, whoever started it: a scheduled reconnect, a
+//! user action, or the device connecting to us. A connection only counts once
+//! the device has identified itself and passed its certificate pin; failures,
+//! including refused peers, leave the backoff in place.
+//!
+//! ## Quiet Reconnect
+//!
+//! With [`ConnectionConfig::quiet_reconnect_window`] set, subscribers do not
+//! see a device that drops and comes back within the window disconnect at
+//! all; they get a single [`ConnectionEvent::LinkFlapped`] instead. A
+//! disconnect that outlasts the window is delivered as usual once the window
+//! has passed.
+//!
+//! ## Connection State
+//!
+//! Each device's link is tracked by a [`ConnectionStateMachine`]. Outgoing
+//! attempts must be allowed by it, so a second [`ConnectionManager::connect`]
+//! while one is already in flight fails instead of opening a duplicate
+//! socket. Connections that drop without being closed on purpose are marked
+//! lost, and the next attempt for them counts as a reconnect.
+//!
+//! ## Teardown Order
+//!
+//! A closing connection stops everything that depends on it before the
+//! transport is closed: first the keepalive heartbeat, then the device's
+//! transfers and plugin dispatch tasks registered through
+//! [`ConnectionManager::spawn_dependent`]. A [`ConnectionEvent::TaskCancelled`]
+//! is emitted for each, then the device is marked disconnected, and the
+//! socket is closed last. On socket replacement only the old heartbeat stops;
+//! the device's other tasks carry over to the new connection.
+//!
+//! ## Link-Local Only
+//!
+//! With [`ConnectionConfig::link_local_only`] set, peers must be on a subnet
+//! a local interface is attached to. Outgoing attempts to other addresses
+//! fail before a socket is opened, and accepted connections from them are
+//! closed right away; both emit [`ConnectionEvent::ConnectionRejected`]. See
+//! [`super::subnet`].
+//!
+//! ## Certificate Pinning
+//!
+//! Once the peer has identified itself, the fingerprint of the certificate it
+//! presented is checked against the one pinned for the device when it paired
+//! (see [`DeviceManager::check_certificate`]). A paired device presenting a
+//! different certificate, or none at all, is refused with a
+//! [`ConnectionEvent::CertificateMismatch`] instead of being trusted.
+//!
+//! ## Connect Requests
+//!
+//! Behind some NATs only one side can open a connection. A device we cannot
+//! reach, but which can reach us over another link such as Bluetooth, can
+//! send a [`CONNECT_REQUEST`] with an address of its own that we can reach.
+//! [`ConnectionManager::handle_connect_request`] connects there, but only for
+//! a paired device and only to an address allowed by the same checks as any
+//! outgoing connection. Loopback and link-local addresses are only accepted
+//! where the device was last seen, so a paired device cannot point us at
+//! services on this machine or its link.
+//!
+//! ## Prewarming
+//!
+//! [`ConnectionManager::prewarm`] connects to paired devices ahead of use,
+//! e.g. when the applet opens, so the first action does not wait for a
+//! handshake. It is a hint: devices already connected, without a known
+//! address or still in reconnect backoff are skipped, and nothing is done
+//! while disabled via [`ConnectionConfig::prewarm`] or, unless
+//! [`ConnectionConfig::prewarm_on_battery`] is set, while on battery (see
+//! [`ConnectionManager::follow_power_source`]). Prewarmed connections are
+//! subject to idle disconnect like any other.
+//!
+//! ## Requests
+//!
+//! [`ConnectionManager::send_request`] sends a packet tagged with a fresh
+//! correlation ID and waits for the device's reply, failing with
+//! [`ProtocolError::Timeout`] after [`ConnectionConfig::request_timeout`].
+//! Replies go to the waiting request instead of being emitted as
+//! [`ConnectionEvent::PacketReceived`]. See [`super::correlation`].
+//!
+//! ## Body Compression
+//!
+//! With [`ConnectionConfig::compression`] set, packet bodies sent to a peer
+//! whose identity lists a common algorithm are compressed (see
+//! [`CompressionMode`]); other peers keep getting plain bodies. Compressed
+//! bodies received are inflated before anything else sees the packet, so
+//! plugins, requests and the packet tap always deal in plain packets, while
+//! the traffic counters record what actually crossed the wire.
+//!
+//! ## Packet Tap
+//!
+//! Builds with the `packet_tap` feature can log every packet sent and received
+//! by the connection tasks, with sensitive bodies redacted, by enabling
+//! [`ConnectionConfig::packet_tap`]. See [`super::packet_tap`].
 
+use super::backoff::ReconnectBackoff;
+use super::correlation::PendingRequests;
 use super::events::ConnectionEvent;
+use super::idle::{is_keepalive, IdleTracker};
+use super::keepalive::{keepalive_ping, keepalive_reply, wants_reply, PeerLiveness};
+use super::packet_tap::{PacketTap, PacketTapConfig, TapDirection};
+use super::state::{ConnectionStateMachine, LinkState};
+use super::subnet::{LocalSubnet, SubnetGuard};
+use super::teardown::{DependentTask, DependentTasks, TeardownSignal};
+use super::traffic::{packet_wire_size, TrafficCounter, TrafficStats};
+use crate::power_profile::{interval_changed, PowerSource};
+use crate::transport::tcp::TCP_CAPABILITIES;
+use crate::transport::TcpSocketOptions;
 use crate::{
-    CertificateInfo, Device, DeviceInfo, DeviceManager, Packet, ProtocolError, Result, TlsConfig,
-    TlsConnection, TlsDeviceInfo, TlsServer,
+    CertificateCheck, CertificateInfo, CompressionMode, Device, DeviceInfo, DeviceManager, Packet,
+    ProtocolError, Result, TlsConfig, TlsConnection, TlsDeviceInfo, TlsServer,
 };
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::{mpsc, RwLock};
-use tokio::task::JoinHandle;
+use tokio::sync::{mpsc, oneshot, watch, RwLock};
+use tokio::task::{AbortHandle, JoinHandle};
 use tracing::{debug, error, info, warn};
 
 /// Keep-alive interval (send ping every 10 seconds to maintain connection)
@@ -36,11 +163,36 @@ const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
 /// Connection timeout (consider disconnected after 60 seconds of no activity)
 const CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
 
+/// Consider the peer gone after 2 minutes without traffic
+const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(120);
+
 /// Minimum delay between connection attempts from the same device
 /// Issue #52: This is now used for logging warnings, not rejection
 /// Socket replacement prevents connection storms while maintaining stability
 const MIN_CONNECTION_DELAY: Duration = Duration::from_millis(1000);
 
+/// Upper bound on how often idle connections are checked
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long a dependent task may take to stop before it is aborted
+const TEARDOWN_GRACE: Duration = Duration::from_secs(2);
+
+/// How long a disconnected connection may take to tear down before its task
+/// is aborted
+const CLOSE_GRACE: Duration = Duration::from_secs(5);
+
+/// How long a request waits for its reply by default
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Packet asking a peer to re-send its identity
+///
+/// The peer answers with a regular `cconnect.identity` packet, which goes
+/// through the same capability update as an unsolicited re-advertisement.
+pub const IDENTITY_REQUEST: &str = "cconnect.identity.request";
+
+/// Packet asking us to connect to the sender at the address in its body
+pub const CONNECT_REQUEST: &str = "cconnect.connect.request";
+
 /// Commands that can be sent to a connection task
 enum ConnectionCommand {
     /// Send a packet
@@ -49,6 +201,10 @@ enum ConnectionCommand {
     Close,
     /// Close due to socket replacement (do not trigger plugin cleanup)
     CloseForReconnect,
+    /// Close because the connection has been idle too long
+    CloseIdle,
+    /// Send a keepalive ping (from the heartbeat task)
+    Keepalive,
 }
 
 /// Active connection to a device
@@ -56,7 +212,7 @@ struct ActiveConnection {
     /// Channel to send commands to the connection task
     command_tx: mpsc::UnboundedSender<ConnectionCommand>,
     /// Task handling this connection
-    task: JoinHandle<()>,
+    task: AbortHandle,
     /// Device ID
     #[allow(dead_code)]
     device_id: String,
@@ -64,15 +220,71 @@ struct ActiveConnection {
     remote_addr: SocketAddr,
 }
 
+/// Manager state shared with each connection task
+#[derive(Clone)]
+struct HandlerContext {
+    device_info: Arc<crate::DeviceInfo>,
+    event_tx: mpsc::UnboundedSender<ConnectionEvent>,
+    connections: Arc<RwLock<HashMap<String, ActiveConnection>>>,
+    device_manager: Arc<RwLock<DeviceManager>>,
+    last_connection_time: Arc<RwLock<HashMap<String, Instant>>>,
+    idle_tracker: Arc<RwLock<IdleTracker>>,
+    traffic: Arc<RwLock<HashMap<String, TrafficCounter>>>,
+    link_states: Arc<RwLock<ConnectionStateMachine>>,
+    backoff: Arc<RwLock<ReconnectBackoff>>,
+    dependents: Arc<RwLock<HashMap<String, DependentTasks>>>,
+    keep_alive_interval: Duration,
+    keep_alive_watch: Option<watch::Receiver<Duration>>,
+    keepalive_timeout: Duration,
+    compression: CompressionMode,
+    packet_tap: Arc<PacketTap>,
+    pending_requests: Arc<PendingRequests>,
+}
+
 /// Connection manager configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ConnectionConfig {
     /// Local address to bind TLS server to
     pub listen_addr: SocketAddr,
     /// Keep-alive interval
+    #[serde(alias = "keepalive_interval", with = "crate::config::duration_secs")]
     pub keep_alive_interval: Duration,
     /// Connection timeout
+    #[serde(with = "crate::config::duration_secs")]
     pub connection_timeout: Duration,
+    /// Close connections whose peer has sent nothing for this long
+    #[serde(with = "crate::config::duration_secs")]
+    pub keepalive_timeout: Duration,
+    /// Disconnect devices after this long without traffic (None = never)
+    #[serde(with = "crate::config::optional_duration_secs")]
+    pub idle_timeout: Option<Duration>,
+    /// Plugins whose activity exempts a device from idle disconnect
+    ///
+    /// Empty by default: a plugin that is loaded for every connected device,
+    /// such as clipboard sync, would keep every device connected.
+    pub idle_exempt_plugins: Vec<String>,
+    /// Report reconnects within this long of a drop as a link flap instead of
+    /// a disconnect (None = report every disconnect)
+    #[serde(with = "crate::config::optional_duration_secs")]
+    pub quiet_reconnect_window: Option<Duration>,
+    /// Raw packet logging (only effective with the `packet_tap` feature)
+    pub packet_tap: PacketTapConfig,
+    /// Options for plain TCP connections ([`crate::TcpTransportFactory`]);
+    /// TLS sockets are opened by cosmic-ext-connect-core with its own settings
+    pub socket_options: TcpSocketOptions,
+    /// Refuse peers that are not on a local subnet (no routers, no VPNs)
+    pub link_local_only: bool,
+    /// How long [`ConnectionManager::send_request`] waits for a reply
+    #[serde(with = "crate::config::duration_secs")]
+    pub request_timeout: Duration,
+    /// Honour [`ConnectionManager::prewarm`] hints
+    pub prewarm: bool,
+    /// Also prewarm while running on battery
+    pub prewarm_on_battery: bool,
+    /// Preferred compression for packet bodies, used with peers that
+    /// support it
+    pub compression: CompressionMode,
 }
 
 impl Default for ConnectionConfig {
@@ -81,11 +293,27 @@ impl Default for ConnectionConfig {
             listen_addr: "0.0.0.0:1814".parse().unwrap(),
             keep_alive_interval: KEEP_ALIVE_INTERVAL,
             connection_timeout: CONNECTION_TIMEOUT,
+            keepalive_timeout: KEEPALIVE_TIMEOUT,
+            idle_timeout: None,
+            idle_exempt_plugins: Vec::new(),
+            quiet_reconnect_window: None,
+            packet_tap: PacketTapConfig::default(),
+            socket_options: TcpSocketOptions::default(),
+            link_local_only: false,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            prewarm: true,
+            prewarm_on_battery: false,
+            compression: CompressionMode::Off,
         }
     }
 }
 
 /// Connection manager for handling multiple TLS connections
+///
+/// Clones share connections, devices and events with the original, so a
+/// clone can be taken out of a lock before a long `connect`. Settings changed
+/// on one are not seen by the other.
+#[derive(Clone)]
 pub struct ConnectionManager {
     /// Our device certificate
     certificate: Arc<CertificateInfo>,
@@ -116,6 +344,39 @@ pub struct ConnectionManager {
 
     /// Last connection time per device (for rate limiting to prevent connection storms)
     last_connection_time: Arc<RwLock<HashMap<String, Instant>>>,
+
+    /// Per-device activity for idle disconnect
+    idle_tracker: Arc<RwLock<IdleTracker>>,
+
+    /// Idle disconnect task handle
+    idle_task: Arc<RwLock<Option<JoinHandle<()>>>>,
+
+    /// Per-device byte counters for the current session
+    traffic: Arc<RwLock<HashMap<String, TrafficCounter>>>,
+
+    /// Per-device reconnect backoff
+    backoff: Arc<RwLock<ReconnectBackoff>>,
+
+    /// Per-device connection state
+    link_states: Arc<RwLock<ConnectionStateMachine>>,
+
+    /// Per-device tasks stopped when the device disconnects
+    dependents: Arc<RwLock<HashMap<String, DependentTasks>>>,
+
+    /// Raw packet log
+    packet_tap: Arc<PacketTap>,
+
+    /// Keepalive interval updates, e.g. from the power profile
+    keep_alive_watch: Option<watch::Receiver<Duration>>,
+
+    /// Power source, for skipping prewarming on battery
+    power_watch: Option<watch::Receiver<PowerSource>>,
+
+    /// Local subnets checked in link-local only mode
+    subnet_guard: Arc<SubnetGuard>,
+
+    /// Requests waiting for a reply
+    pending_requests: Arc<PendingRequests>,
 }
 
 /// Helper to convert discovery::DeviceInfo to TlsDeviceInfo
@@ -131,6 +392,17 @@ fn device_info_to_tls(info: &crate::DeviceInfo) -> TlsDeviceInfo {
     }
 }
 
+/// Whether `ip` only reaches this machine or its directly attached link
+fn is_host_scoped(ip: std::net::IpAddr) -> bool {
+    match ip {
+        std::net::IpAddr::V4(v4) => v4.is_loopback() || v4.is_link_local(),
+        std::net::IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_host_scoped(v4.into()),
+            None => v6.is_loopback() || v6.segments()[0] & 0xffc0 == 0xfe80,
+        },
+    }
+}
+
 impl ConnectionManager {
     /// Create a new connection manager
     pub fn new(
@@ -143,6 +415,9 @@ impl ConnectionManager {
 
         // Create TLS configuration from certificate (rustls-based)
         let tls_config = TlsConfig::new(&certificate)?;
+        let idle_tracker = IdleTracker::new(&config.idle_exempt_plugins);
+        let link_states = ConnectionStateMachine::with_events(event_tx.clone());
+        let packet_tap = PacketTap::new(&config.packet_tap);
 
         Ok(Self {
             certificate: Arc::new(certificate),
@@ -155,14 +430,85 @@ impl ConnectionManager {
             config,
             server_task: Arc::new(RwLock::new(None)),
             last_connection_time: Arc::new(RwLock::new(HashMap::new())),
+            idle_tracker: Arc::new(RwLock::new(idle_tracker)),
+            idle_task: Arc::new(RwLock::new(None)),
+            traffic: Arc::new(RwLock::new(HashMap::new())),
+            backoff: Arc::new(RwLock::new(ReconnectBackoff::default())),
+            link_states: Arc::new(RwLock::new(link_states)),
+            dependents: Arc::new(RwLock::new(HashMap::new())),
+            packet_tap: Arc::new(packet_tap),
+            keep_alive_watch: None,
+            power_watch: None,
+            subnet_guard: Arc::new(SubnetGuard::system()),
+            pending_requests: Arc::new(PendingRequests::default()),
         })
     }
 
+    /// Take the keepalive interval from `interval` instead of the config
+    ///
+    /// Heartbeats of current and future connections switch to each new
+    /// interval as it is published. Dropping the sender keeps the last one.
+    pub fn follow_keep_alive(&mut self, interval: watch::Receiver<Duration>) {
+        self.keep_alive_watch = Some(interval);
+    }
+
+    /// Take the power source from `source` for prewarming decisions
+    ///
+    /// Without a source the system is assumed to be on AC.
+    pub fn follow_power_source(&mut self, source: watch::Receiver<PowerSource>) {
+        self.power_watch = Some(source);
+    }
+
+    /// Check link-local only mode against `subnets` instead of the interfaces'
+    ///
+    /// Only takes effect for connections made after the manager is started.
+    pub fn set_local_subnets(&mut self, subnets: Vec<LocalSubnet>) {
+        self.subnet_guard = Arc::new(SubnetGuard::fixed(subnets));
+    }
+
+    /// Local device information sent in handshakes
+    pub fn device_info(&self) -> &crate::DeviceInfo {
+        &self.device_info
+    }
+
     /// Update local device information (e.g., capabilities)
     pub fn update_device_info(&mut self, device_info: crate::DeviceInfo) {
         self.device_info = Arc::new(device_info);
     }
 
+    /// Replace the advertised capabilities and tell connected devices
+    ///
+    /// Outgoing handshakes from now on carry the new capabilities, and every
+    /// connected device is sent our updated identity, which it treats as a
+    /// capability update, so it stops sending packet types we no longer
+    /// handle. The TLS server keeps the identity it was started with for
+    /// incoming handshakes. Returns how many devices were told.
+    pub async fn update_capabilities(
+        &mut self,
+        incoming: Vec<String>,
+        outgoing: Vec<String>,
+    ) -> usize {
+        let mut device_info = (*self.device_info).clone();
+        device_info.incoming_capabilities = incoming;
+        device_info.outgoing_capabilities = outgoing;
+        self.device_info = Arc::new(device_info);
+
+        let identity = self.device_info.to_identity_packet();
+        let connections = self.connections.read().await;
+        let mut told = 0;
+        for (device_id, connection) in connections.iter() {
+            match connection
+                .command_tx
+                .send(ConnectionCommand::SendPacket(identity.clone()))
+            {
+                Ok(()) => told += 1,
+                Err(_) => debug!("Connection to {} closed, not sending identity", device_id),
+            }
+        }
+        info!("Advertised updated capabilities to {} devices", told);
+        told
+    }
+
     /// Get the TLS configuration for payload transfers
     pub fn tls_config(&self) -> Arc<TlsConfig> {
         Arc::clone(&self.tls_config)
@@ -183,7 +529,10 @@ impl ConnectionManager {
             }
         });
 
-        rx
+        match self.config.quiet_reconnect_window {
+            Some(window) => super::flap::debounce_flaps(rx, window),
+            None => rx,
+        }
     }
 
     /// Start the connection manager and TLS server
@@ -206,11 +555,10 @@ impl ConnectionManager {
             .send(ConnectionEvent::ManagerStarted { port: local_port });
 
         // Spawn server accept task
-        let connections = self.connections.clone();
+        let handler = self.handler_context();
         let event_tx = self.event_tx.clone();
-        let device_manager = self.device_manager.clone();
-        let device_info = self.device_info.clone();
-        let last_connection_time = self.last_connection_time.clone();
+        let link_local_only = self.config.link_local_only;
+        let subnet_guard = self.subnet_guard.clone();
 
         let server_task = tokio::spawn(async move {
             let mut consecutive_errors = 0u32;
@@ -223,6 +571,19 @@ impl ConnectionManager {
                         consecutive_errors = 0;
 
                         let remote_addr = connection.remote_addr();
+                        if Self::check_peer_subnet(
+                            link_local_only,
+                            &subnet_guard,
+                            &event_tx,
+                            None,
+                            remote_addr,
+                        )
+                        .is_err()
+                        {
+                            // Dropping the connection closes the socket
+                            drop(connection);
+                            continue;
+                        }
                         let device_name = core_identity
                             .get_body_field::<String>("deviceName")
                             .unwrap_or_else(|| "Unknown".to_string());
@@ -237,14 +598,11 @@ impl ConnectionManager {
                         // Spawn connection handler
                         // Note: remote_identity already contains the post-TLS identity packet
                         Self::spawn_connection_handler(
+                            handler.clone(),
                             connection,
                             remote_addr,
-                            device_info.clone(),
-                            event_tx.clone(),
-                            connections.clone(),
-                            device_manager.clone(),
                             Some(remote_identity), // Pass the already-received identity
-                            last_connection_time.clone(),
+                            None,
                         );
                     }
                     Err(e) => {
@@ -291,12 +649,21 @@ impl ConnectionManager {
         *server_task_lock = Some(server_task);
         drop(server_task_lock);
 
+        if let Some(idle_timeout) = self.config.idle_timeout {
+            self.start_idle_monitor(idle_timeout).await;
+        }
+
         info!("Connection manager started on port {}", local_port);
 
         Ok(local_port)
     }
 
     /// Connect to a remote device
+    ///
+    /// Finding the device already connected clears its reconnect backoff, as
+    /// does the new connection once the device has identified itself. A
+    /// failure, or a peer refused by its certificate pin, leaves the backoff
+    /// as it was.
     pub async fn connect(&self, device_id: &str, addr: SocketAddr) -> Result<()> {
         info!("Connecting to device {} at {}", device_id, addr);
 
@@ -304,31 +671,39 @@ impl ConnectionManager {
         let connections = self.connections.read().await;
         if connections.contains_key(device_id) {
             info!("Already connected to device {}", device_id);
+            drop(connections);
+            self.reset_reconnect_backoff(device_id).await;
             return Ok(());
         }
         drop(connections);
 
+        self.check_peer_subnet_for(device_id, addr)?;
+
         // Connect with TLS (rustls with TOFU)
         // Note: cosmic-ext-connect-core TLS uses TOFU - no pre-verification needed
         // Create identity packet to send before TLS handshake (KDE Connect protocol v8)
         let identity_packet = self.device_info.to_identity_packet();
         let identity_bytes = identity_packet.to_bytes()?;
+        self.link_states.write().await.begin_connect(device_id)?;
         let mut connection =
-            TlsConnection::connect(addr, &self.tls_config, &identity_bytes).await?;
+            match TlsConnection::connect(addr, &self.tls_config, &identity_bytes).await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    self.link_states.write().await.connect_failed(device_id);
+                    return Err(e.into());
+                }
+            };
 
         connection.set_device_id(device_id.to_string());
 
         // Spawn connection handler
         // Note: For outgoing connections, we don't have pre-exchanged identity yet
         Self::spawn_connection_handler(
+            self.handler_context(),
             connection,
             addr,
-            self.device_info.clone(),
-            self.event_tx.clone(),
-            self.connections.clone(),
-            self.device_manager.clone(),
             None, // Will perform identity exchange in handler
-            self.last_connection_time.clone(),
+            Some(device_id.to_string()),
         );
 
         info!("Connected to device {} at {}", device_id, addr);
@@ -354,28 +729,34 @@ impl ConnectionManager {
         }
         drop(connections);
 
+        self.check_peer_subnet_for(device_id, addr)?;
+
         // Connect with TLS (rustls with TOFU)
         // Note: peer_cert is ignored - cosmic-ext-connect-core uses TOFU model
         // Certificate verification happens at application layer via SHA256 fingerprint
         // Create identity packet to send before TLS handshake (KDE Connect protocol v8)
         let identity_packet = self.device_info.to_identity_packet();
         let identity_bytes = identity_packet.to_bytes()?;
+        self.link_states.write().await.begin_connect(device_id)?;
         let mut connection =
-            TlsConnection::connect(addr, &self.tls_config, &identity_bytes).await?;
+            match TlsConnection::connect(addr, &self.tls_config, &identity_bytes).await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    self.link_states.write().await.connect_failed(device_id);
+                    return Err(e.into());
+                }
+            };
 
         connection.set_device_id(device_id.to_string());
 
         // Spawn connection handler
         // Note: For outgoing connections, we don't have pre-exchanged identity yet
         Self::spawn_connection_handler(
+            self.handler_context(),
             connection,
             addr,
-            self.device_info.clone(),
-            self.event_tx.clone(),
-            self.connections.clone(),
-            self.device_manager.clone(),
             None, // Will perform identity exchange in handler
-            self.last_connection_time.clone(),
+            Some(device_id.to_string()),
         );
 
         info!(
@@ -386,6 +767,126 @@ impl ConnectionManager {
         Ok(())
     }
 
+    /// Packet asking a peer to connect to us at `addr`
+    pub fn connect_request(addr: SocketAddr) -> Packet {
+        Packet::new(
+            CONNECT_REQUEST,
+            serde_json::json!({
+                "host": addr.ip().to_string(),
+                "port": addr.port(),
+            }),
+        )
+    }
+
+    /// Connect to a device at the address it asked us to use
+    ///
+    /// `packet` is a [`CONNECT_REQUEST`] received from `device_id` over some
+    /// other link. Only paired devices may ask, and the address must be a
+    /// unicast one that passes the link-local check. Loopback and link-local
+    /// addresses must also be the host the device was last seen at. Refused
+    /// requests emit [`ConnectionEvent::ConnectionRejected`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidPacket`] for a malformed request,
+    /// [`ProtocolError::PermissionDenied`] for a refused one, or the
+    /// connection error.
+    pub async fn handle_connect_request(&self, device_id: &str, packet: &Packet) -> Result<()> {
+        let host = packet.body.get("host").and_then(|v| v.as_str());
+        let port = packet
+            .body
+            .get("port")
+            .and_then(|v| v.as_u64())
+            .and_then(|p| u16::try_from(p).ok())
+            .filter(|p| *p != 0);
+        let addr = match (host.and_then(|h| h.parse::<std::net::IpAddr>().ok()), port) {
+            (Some(ip), Some(port)) => SocketAddr::new(ip, port),
+            _ => {
+                return Err(ProtocolError::InvalidPacket(format!(
+                    "Connect request from {} has no valid address",
+                    device_id
+                )))
+            }
+        };
+
+        let (paired, last_seen_at) = {
+            let device_manager = self.device_manager.read().await;
+            let last_seen_at = device_manager
+                .get_device(device_id)
+                .and_then(|device| device.host.as_deref())
+                .and_then(|host| crate::transport::tcp::parse_host_addr(host, addr.port()))
+                .map(|known| known.ip());
+            (device_manager.is_paired(device_id), last_seen_at)
+        };
+        let refusal = if !paired {
+            Some(format!("{} is not paired", device_id))
+        } else if addr.ip().is_unspecified()
+            || addr.ip().is_multicast()
+            || matches!(addr.ip(), std::net::IpAddr::V4(ip) if ip.is_broadcast())
+        {
+            Some(format!("{} is not a unicast address", addr.ip()))
+        } else if is_host_scoped(addr.ip()) && last_seen_at != Some(addr.ip()) {
+            Some(format!(
+                "{} is a loopback or link-local address {} was not seen at",
+                addr.ip(),
+                device_id
+            ))
+        } else {
+            None
+        };
+        if let Some(reason) = refusal {
+            warn!("Refusing connect request from {}: {}", device_id, reason);
+            let _ = self.event_tx.send(ConnectionEvent::ConnectionRejected {
+                device_id: Some(device_id.to_string()),
+                remote_addr: addr,
+                reason: reason.clone(),
+            });
+            return Err(ProtocolError::PermissionDenied(reason));
+        }
+
+        info!("Device {} asked us to connect to {}", device_id, addr);
+        self.connect(device_id, addr).await
+    }
+
+    /// Refuse an outgoing connection to `addr` outside the local subnets
+    fn check_peer_subnet_for(&self, device_id: &str, addr: SocketAddr) -> Result<()> {
+        Self::check_peer_subnet(
+            self.config.link_local_only,
+            &self.subnet_guard,
+            &self.event_tx,
+            Some(device_id),
+            addr,
+        )
+    }
+
+    /// Refuse a peer outside the local subnets in link-local only mode
+    ///
+    /// Emits [`ConnectionEvent::ConnectionRejected`] for a refused peer.
+    fn check_peer_subnet(
+        link_local_only: bool,
+        guard: &SubnetGuard,
+        event_tx: &mpsc::UnboundedSender<ConnectionEvent>,
+        device_id: Option<&str>,
+        remote_addr: SocketAddr,
+    ) -> Result<()> {
+        if !link_local_only || guard.permits(remote_addr.ip()) {
+            return Ok(());
+        }
+        let reason = format!("{} is not on a local subnet", remote_addr.ip());
+        warn!(
+            "Refusing {} ({}): {}",
+            device_id.unwrap_or("incoming connection"),
+            remote_addr,
+            reason
+        );
+        let _ = event_tx.send(ConnectionEvent::ConnectionRejected {
+            device_id: device_id.map(str::to_string),
+            remote_addr,
+            reason: reason.clone(),
+        });
+        Err(ProtocolError::PermissionDenied(reason))
+    }
+
     /// Send a packet to a device
     pub async fn send_packet(&self, device_id: &str, packet: &Packet) -> Result<()> {
         debug!(
@@ -412,17 +913,95 @@ impl ConnectionManager {
         Ok(())
     }
 
+    /// Send a request and wait for the device's reply
+    ///
+    /// Waits up to [`ConnectionConfig::request_timeout`]. See
+    /// [`send_request_with_timeout`](Self::send_request_with_timeout).
+    pub async fn send_request(&self, device_id: &str, packet: Packet) -> Result<Packet> {
+        self.send_request_with_timeout(device_id, packet, self.config.request_timeout)
+            .await
+    }
+
+    /// Send a request and wait up to `timeout` for the device's reply
+    ///
+    /// The packet is tagged with a fresh correlation ID; the reply is the
+    /// first packet from the device that names it (see
+    /// [`super::correlation::reply_to`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::DeviceNotFound`] if the device is not
+    /// connected and [`ProtocolError::Timeout`] if no reply arrives in time.
+    pub async fn send_request_with_timeout(
+        &self,
+        device_id: &str,
+        mut packet: Packet,
+        timeout: Duration,
+    ) -> Result<Packet> {
+        let (id, reply) = self.pending_requests.register(device_id, &mut packet);
+        if let Err(e) = self.send_packet(device_id, &packet).await {
+            self.pending_requests.cancel(device_id, id);
+            return Err(e);
+        }
+
+        match tokio::time::timeout(timeout, reply).await {
+            Ok(Ok(reply)) => Ok(reply),
+            Ok(Err(_)) => Err(ProtocolError::Timeout(format!(
+                "Request {} to {} was dropped",
+                id, device_id
+            ))),
+            Err(_) => {
+                self.pending_requests.cancel(device_id, id);
+                Err(ProtocolError::Timeout(format!(
+                    "No reply to '{}' from {} within {:?}",
+                    packet.packet_type, device_id, timeout
+                )))
+            }
+        }
+    }
+
+    /// Ask a connected device to re-send its identity
+    ///
+    /// Capabilities are updated when the answer arrives, emitting
+    /// [`ConnectionEvent::CapabilitiesChanged`] if they differ from the stored
+    /// ones. Use this when a device seems to have changed its plugins without
+    /// re-advertising them.
+    pub async fn refresh_capabilities(&self, device_id: &str) -> Result<()> {
+        info!(
+            "Requesting identity from {} to refresh capabilities",
+            device_id
+        );
+        self.send_packet(
+            device_id,
+            &Packet::new(IDENTITY_REQUEST, serde_json::json!({})),
+        )
+        .await
+    }
+
     /// Disconnect from a device
+    ///
+    /// The connection task stops the device's dependent tasks and then closes
+    /// the socket, as described in the module documentation. A task that has
+    /// not finished [`CLOSE_GRACE`] later is aborted.
     pub async fn disconnect(&self, device_id: &str) -> Result<()> {
         info!("Disconnecting from device {}", device_id);
 
         let mut connections = self.connections.write().await;
         if let Some(active_conn) = connections.remove(device_id) {
-            // Send close command
+            // Send close command; the task tears itself down in order
             let _ = active_conn.command_tx.send(ConnectionCommand::Close);
 
-            // Abort task
-            active_conn.task.abort();
+            // Abort the task if it is stuck and never gets to the command
+            let task = active_conn.task;
+            tokio::spawn(async move {
+                tokio::time::sleep(CLOSE_GRACE).await;
+                task.abort();
+            });
+
+            self.link_states
+                .write()
+                .await
+                .record_closed(device_id, false);
 
             info!("Disconnected from device {}", device_id);
         }
@@ -430,12 +1009,336 @@ impl ConnectionManager {
         Ok(())
     }
 
+    /// Current connection state of a device
+    pub async fn link_state(&self, device_id: &str) -> LinkState {
+        self.link_states.read().await.state(device_id)
+    }
+
+    /// Start a scheduled reconnect attempt if the device's backoff allows it
+    ///
+    /// Returns the attempt number, or `None` while the device is still
+    /// backing off. The caller is expected to follow up with [`connect`](Self::connect).
+    pub async fn claim_reconnect_attempt(&self, device_id: &str) -> Option<u32> {
+        let mut backoff = self.backoff.write().await;
+        let now = Instant::now();
+        if !backoff.is_due(device_id, now) {
+            return None;
+        }
+        Some(backoff.record_attempt(device_id, now))
+    }
+
+    /// Clear a device's reconnect backoff
+    pub async fn reset_reconnect_backoff(&self, device_id: &str) {
+        self.backoff.write().await.record_success(device_id);
+    }
+
+    /// Snapshot of the reconnect backoff for all devices
+    pub async fn reconnect_backoff(&self) -> ReconnectBackoff {
+        self.backoff.read().await.clone()
+    }
+
+    /// Address a device was last seen at
+    async fn last_known_addr(&self, device_id: &str) -> Result<SocketAddr> {
+        let device_manager = self.device_manager.read().await;
+        let device = device_manager
+            .get_device(device_id)
+            .ok_or_else(|| ProtocolError::DeviceNotFound(device_id.to_string()))?;
+        match (&device.host, device.port) {
+            (Some(host), Some(port)) => crate::transport::tcp::parse_host_addr(host, port)
+                .ok_or_else(|| {
+                    ProtocolError::InvalidState(format!(
+                        "invalid address for {}: {}",
+                        device_id, host
+                    ))
+                }),
+            _ => Err(ProtocolError::InvalidState(format!(
+                "no known address for {}",
+                device_id
+            ))),
+        }
+    }
+
+    /// Whether the power source says we are running on battery
+    fn on_battery(&self) -> bool {
+        self.power_watch
+            .as_ref()
+            .is_some_and(|source| *source.borrow() == PowerSource::Battery)
+    }
+
+    /// Connect to paired devices ahead of use
+    ///
+    /// Attempts run concurrently and go through the reconnect backoff like
+    /// automatic reconnects. Devices already connected, unpaired, without a
+    /// known address or not yet due are skipped. Returns the devices a
+    /// connection was attempted to.
+    pub async fn prewarm(&self, device_ids: &[String]) -> Vec<String> {
+        if !self.config.prewarm {
+            debug!("Prewarming disabled, ignoring hint");
+            return Vec::new();
+        }
+        if self.on_battery() && !self.config.prewarm_on_battery {
+            debug!("On battery, not prewarming connections");
+            return Vec::new();
+        }
+
+        let mut targets = Vec::new();
+        for device_id in device_ids {
+            if self.has_connection(device_id).await {
+                continue;
+            }
+            let paired = self
+                .device_manager
+                .read()
+                .await
+                .get_device(device_id)
+                .is_some_and(|device| device.is_paired());
+            if !paired {
+                continue;
+            }
+            let addr = match self.last_known_addr(device_id).await {
+                Ok(addr) => addr,
+                Err(e) => {
+                    debug!("Not prewarming {}: {}", device_id, e);
+                    continue;
+                }
+            };
+            if self.claim_reconnect_attempt(device_id).await.is_none() {
+                debug!("Not prewarming {}: reconnect backoff", device_id);
+                continue;
+            }
+            targets.push((device_id.clone(), addr));
+        }
+
+        let attempts = targets.iter().map(|(device_id, addr)| async move {
+            if let Err(e) = self.connect(device_id, *addr).await {
+                debug!("Prewarming {} failed: {}", device_id, e);
+            }
+        });
+        futures::future::join_all(attempts).await;
+
+        info!("Prewarmed connections to {} devices", targets.len());
+        targets
+            .into_iter()
+            .map(|(device_id, _)| device_id)
+            .collect()
+    }
+
+    /// Reconnect to a device immediately, ignoring its backoff delay
+    ///
+    /// Makes one attempt at the device's last known address. An established
+    /// connection clears the backoff; a failure leaves the attempt count and
+    /// the next scheduled attempt unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::DeviceNotFound`] for an unknown device,
+    /// [`ProtocolError::InvalidState`] if no address is known, or the
+    /// connection error.
+    pub async fn reconnect_now(&self, device_id: &str) -> Result<()> {
+        if self.has_connection(device_id).await {
+            self.reset_reconnect_backoff(device_id).await;
+            return Ok(());
+        }
+
+        let addr = self.last_known_addr(device_id).await?;
+
+        info!("Reconnecting to device {} now, skipping backoff", device_id);
+        self.connect(device_id, addr).await.map_err(|e| {
+            warn!("Immediate reconnect to {} failed: {}", device_id, e);
+            e
+        })
+    }
+
+    /// Parallel payload streams to use for a file sent to a device
+    ///
+    /// `requested` is capped by what the device advertised in its identity
+    /// and what payload transfers over TCP support. Unknown devices get a
+    /// single stream.
+    pub async fn payload_streams(&self, device_id: &str, requested: usize) -> usize {
+        let peer_streams = self
+            .device_manager
+            .read()
+            .await
+            .get_device(device_id)
+            .map_or(1, |device| device.info.payload_streams);
+        TCP_CAPABILITIES.negotiate_payload_streams(requested, peer_streams)
+    }
+
     /// Check if there's an active connection to a device
     pub async fn has_connection(&self, device_id: &str) -> bool {
         let connections = self.connections.read().await;
         connections.contains_key(device_id)
     }
 
+    /// Plugins that keep a connection alive while active
+    pub fn idle_exempt_plugins(&self) -> &[String] {
+        &self.config.idle_exempt_plugins
+    }
+
+    /// Mark a plugin as running or stopped for a device
+    ///
+    /// While an exempt plugin is active the device is never idle-disconnected.
+    /// Plugins not listed in [`ConnectionConfig::idle_exempt_plugins`] are ignored.
+    pub async fn set_plugin_active(&self, device_id: &str, plugin: &str, active: bool) {
+        self.idle_tracker
+            .write()
+            .await
+            .set_plugin_active(device_id, plugin, active);
+    }
+
+    /// Run a task that is stopped when the device disconnects
+    ///
+    /// `task` receives a [`TeardownSignal`] and should return promptly once it
+    /// fires; tasks still running [`TEARDOWN_GRACE`] later are aborted. Use
+    /// this for transfers and plugin work that talk to the device, so they end
+    /// before its connection is closed rather than failing against it.
+    pub async fn spawn_dependent<F, Fut>(
+        &self,
+        device_id: &str,
+        kind: DependentTask,
+        name: impl Into<String>,
+        task: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(TeardownSignal) -> Fut,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        if !self.has_connection(device_id).await {
+            return Err(ProtocolError::DeviceNotFound(format!(
+                "Not connected to device {}",
+                device_id
+            )));
+        }
+        self.dependents
+            .write()
+            .await
+            .entry(device_id.to_string())
+            .or_default()
+            .spawn(kind, name, task);
+        Ok(())
+    }
+
+    /// Record the start of a payload transfer with a device
+    pub async fn transfer_started(&self, device_id: &str) {
+        self.idle_tracker.write().await.transfer_started(device_id);
+    }
+
+    /// Record the end of a payload transfer with a device
+    pub async fn transfer_finished(&self, device_id: &str) {
+        self.idle_tracker.write().await.transfer_finished(device_id);
+    }
+
+    /// Whether a payload transfer with a device is in progress
+    pub async fn has_active_transfers(&self, device_id: &str) -> bool {
+        self.idle_tracker.read().await.has_transfers(device_id)
+    }
+
+    /// Byte counter for a device, created if it does not exist yet
+    ///
+    /// Pass the handle to payload transfers (e.g.
+    /// [`PayloadServer::with_traffic_counter`](crate::PayloadServer::with_traffic_counter))
+    /// so their bytes are included in the device totals.
+    pub async fn traffic_counter(&self, device_id: &str) -> TrafficCounter {
+        Self::counter_for(&self.traffic, device_id).await
+    }
+
+    /// Bytes exchanged with a device during the current connection
+    pub async fn traffic_stats(&self, device_id: &str) -> Option<TrafficStats> {
+        self.traffic
+            .read()
+            .await
+            .get(device_id)
+            .map(TrafficCounter::snapshot)
+    }
+
+    /// Bytes exchanged with every device that has counters
+    pub async fn all_traffic_stats(&self) -> HashMap<String, TrafficStats> {
+        self.traffic
+            .read()
+            .await
+            .iter()
+            .map(|(id, counter)| (id.clone(), counter.snapshot()))
+            .collect()
+    }
+
+    /// Zero the byte counters for a device, returning the previous totals
+    pub async fn reset_traffic_stats(&self, device_id: &str) -> Option<TrafficStats> {
+        self.traffic
+            .read()
+            .await
+            .get(device_id)
+            .map(TrafficCounter::reset)
+    }
+
+    async fn counter_for(
+        traffic: &Arc<RwLock<HashMap<String, TrafficCounter>>>,
+        device_id: &str,
+    ) -> TrafficCounter {
+        if let Some(counter) = traffic.read().await.get(device_id) {
+            return counter.clone();
+        }
+        traffic
+            .write()
+            .await
+            .entry(device_id.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Spawn the task that closes idle connections
+    async fn start_idle_monitor(&self, idle_timeout: Duration) {
+        info!(
+            "Idle disconnect enabled: closing connections after {}s without traffic",
+            idle_timeout.as_secs()
+        );
+
+        let connections = self.connections.clone();
+        let idle_tracker = self.idle_tracker.clone();
+        let check_interval = (idle_timeout / 4).min(IDLE_CHECK_INTERVAL);
+
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(check_interval);
+            loop {
+                interval.tick().await;
+                Self::close_idle_connections(&connections, &idle_tracker, idle_timeout).await;
+            }
+        });
+
+        *self.idle_task.write().await = Some(task);
+    }
+
+    /// Close every connection idle for at least `idle_timeout`
+    ///
+    /// Returns the IDs of the devices that were disconnected.
+    async fn close_idle_connections(
+        connections: &Arc<RwLock<HashMap<String, ActiveConnection>>>,
+        idle_tracker: &Arc<RwLock<IdleTracker>>,
+        idle_timeout: Duration,
+    ) -> Vec<String> {
+        let idle = idle_tracker.read().await.idle_devices(idle_timeout);
+        if idle.is_empty() {
+            return idle;
+        }
+
+        let mut conns = connections.write().await;
+        let mut closed = Vec::new();
+        for device_id in idle {
+            if let Some(active_conn) = conns.remove(&device_id) {
+                info!(
+                    "Closing idle connection to {} (no traffic for {}s)",
+                    device_id,
+                    idle_timeout.as_secs()
+                );
+                let _ = active_conn.command_tx.send(ConnectionCommand::CloseIdle);
+                closed.push(device_id);
+            } else {
+                // Stale entry for a device that is no longer connected
+                idle_tracker.write().await.remove(&device_id);
+            }
+        }
+        closed
+    }
+
     /// Stop the connection manager
     pub async fn stop(&self) {
         info!("Stopping connection manager");
@@ -447,6 +1350,10 @@ impl ConnectionManager {
         }
         drop(server_task);
 
+        if let Some(task) = self.idle_task.write().await.take() {
+            task.abort();
+        }
+
         // Disconnect all devices
         let device_ids: Vec<String> = {
             let connections = self.connections.read().await;
@@ -463,25 +1370,229 @@ impl ConnectionManager {
         info!("Connection manager stopped");
     }
 
+    /// Store the capabilities advertised in an identity packet for a known device
+    ///
+    /// Uses `parse_capabilities` directly since a post-TLS identity may not
+    /// contain all fields required by `from_identity_packet`. An identity
+    /// without capabilities leaves the device unchanged. Returns whether the
+    /// stored capabilities changed.
+    fn apply_capabilities(dm: &mut DeviceManager, device_id: &str, packet: &Packet) -> bool {
+        use crate::discovery::parse_capabilities;
+
+        let Some(device) = dm.get_device_mut(device_id) else {
+            return false;
+        };
+        let incoming = parse_capabilities(packet, "incomingCapabilities");
+        let outgoing = parse_capabilities(packet, "outgoingCapabilities");
+        if incoming.is_empty() && outgoing.is_empty() {
+            return false;
+        }
+
+        let same = |old: &[String], new: &[String]| {
+            let old: HashSet<&String> = old.iter().collect();
+            old == new.iter().collect::<HashSet<_>>()
+        };
+        let changed = !same(&device.info.incoming_capabilities, &incoming)
+            || !same(&device.info.outgoing_capabilities, &outgoing);
+        device.info.incoming_capabilities = incoming;
+        device.info.outgoing_capabilities = outgoing;
+        device.info.payload_streams = crate::payload::advertised_payload_streams(packet);
+        debug!(
+            "Updated capabilities for device {} ({} in, {} out)",
+            device_id,
+            device.info.incoming_capabilities.len(),
+            device.info.outgoing_capabilities.len()
+        );
+        changed
+    }
+
+    /// Handle an identity packet re-sent on an established connection
+    ///
+    /// Peers re-advertise their identity when their capabilities change, e.g.
+    /// after a plugin is enabled on the phone. The device is updated in place
+    /// and [`ConnectionEvent::CapabilitiesChanged`] is emitted if anything
+    /// changed.
+    async fn handle_capability_update(
+        device_manager: &Arc<RwLock<DeviceManager>>,
+        event_tx: &mpsc::UnboundedSender<ConnectionEvent>,
+        device_id: &str,
+        packet: &Packet,
+    ) {
+        let mut dm = device_manager.write().await;
+        if !Self::apply_capabilities(&mut dm, device_id, packet) {
+            return;
+        }
+        let Some(device) = dm.get_device(device_id) else {
+            return;
+        };
+
+        info!("Device {} re-advertised its capabilities", device_id);
+        let _ = event_tx.send(ConnectionEvent::CapabilitiesChanged {
+            device_id: device_id.to_string(),
+            incoming_capabilities: device.info.incoming_capabilities.clone(),
+            outgoing_capabilities: device.info.outgoing_capabilities.clone(),
+        });
+    }
+
+    /// Compare the certificate a device presented with the one pinned for it
+    ///
+    /// Returns the event to emit if the connection must be refused.
+    fn check_pinned_certificate(
+        dm: &mut DeviceManager,
+        device_id: &str,
+        connection: &TlsConnection,
+    ) -> Option<ConnectionEvent> {
+        let fingerprint = connection
+            .peer_certificate()
+            .map(|certificate| CertificateInfo::calculate_fingerprint(&certificate));
+        Self::check_pinned_fingerprint(
+            dm,
+            device_id,
+            connection.remote_addr(),
+            fingerprint.as_deref(),
+        )
+    }
+
+    /// Compare a presented fingerprint, if any, with the one pinned for a device
+    ///
+    /// A device with a pin that presents no certificate is refused: there is
+    /// nothing to match the pin against.
+    fn check_pinned_fingerprint(
+        dm: &mut DeviceManager,
+        device_id: &str,
+        remote_addr: SocketAddr,
+        fingerprint: Option<&str>,
+    ) -> Option<ConnectionEvent> {
+        let Some(fingerprint) = fingerprint else {
+            let expected = dm
+                .get_device(device_id)
+                .filter(|device| device.is_paired())
+                .and_then(|device| device.certificate_fingerprint.clone())?;
+            warn!(
+                "Device {} at {} presented no certificate but {} is pinned, refusing",
+                device_id, remote_addr, expected
+            );
+            return Some(ConnectionEvent::CertificateMismatch {
+                device_id: device_id.to_string(),
+                remote_addr,
+                expected_fingerprint: expected,
+                presented_fingerprint: String::new(),
+            });
+        };
+        match dm.check_certificate(device_id, fingerprint) {
+            CertificateCheck::Trusted | CertificateCheck::Unpinned => None,
+            CertificateCheck::Pinned => {
+                info!("Pinned new certificate {} for {}", fingerprint, device_id);
+                if let Err(e) = dm.save_registry() {
+                    warn!("Failed to save device registry: {}", e);
+                }
+                None
+            }
+            CertificateCheck::Mismatch { expected } => {
+                warn!(
+                    "Device {} at {} presented certificate {} but {} is pinned, refusing",
+                    device_id, remote_addr, fingerprint, expected
+                );
+                Some(ConnectionEvent::CertificateMismatch {
+                    device_id: device_id.to_string(),
+                    remote_addr,
+                    expected_fingerprint: expected,
+                    presented_fingerprint: fingerprint.to_string(),
+                })
+            }
+        }
+    }
+
+    /// Queue a keepalive every interval until torn down
+    ///
+    /// A new interval from `watch` restarts the timer, so the next ping goes
+    /// out one new interval after the switch.
+    async fn run_heartbeat(
+        keepalive_tx: mpsc::UnboundedSender<ConnectionCommand>,
+        interval: Duration,
+        mut watch: Option<watch::Receiver<Duration>>,
+        mut signal: TeardownSignal,
+    ) {
+        let period = watch
+            .as_mut()
+            .map_or(interval, |rx| *rx.borrow_and_update());
+        let mut timer = tokio::time::interval(period);
+        loop {
+            tokio::select! {
+                _ = timer.tick() => {
+                    if keepalive_tx.send(ConnectionCommand::Keepalive).is_err() {
+                        break;
+                    }
+                }
+                period = interval_changed(&mut watch) => {
+                    debug!("Keepalive interval is now {:?}", period);
+                    timer = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+                }
+                _ = signal.cancelled() => break,
+            }
+        }
+    }
+
+    /// The state a connection task shares with this manager
+    fn handler_context(&self) -> HandlerContext {
+        HandlerContext {
+            device_info: self.device_info.clone(),
+            event_tx: self.event_tx.clone(),
+            connections: self.connections.clone(),
+            device_manager: self.device_manager.clone(),
+            last_connection_time: self.last_connection_time.clone(),
+            idle_tracker: self.idle_tracker.clone(),
+            traffic: self.traffic.clone(),
+            link_states: self.link_states.clone(),
+            backoff: self.backoff.clone(),
+            dependents: self.dependents.clone(),
+            keep_alive_interval: self.config.keep_alive_interval,
+            keep_alive_watch: self.keep_alive_watch.clone(),
+            keepalive_timeout: self.config.keepalive_timeout,
+            compression: self.config.compression,
+            packet_tap: self.packet_tap.clone(),
+            pending_requests: self.pending_requests.clone(),
+        }
+    }
+
     /// Spawn a task to handle a connection (send/receive)
     ///
     /// If `remote_identity` is Some, the identity exchange has already been completed
     /// (e.g., by TLS server's accept() method for protocol v8). Otherwise, perform
     /// the identity exchange here.
-    #[allow(clippy::too_many_arguments)]
     fn spawn_connection_handler(
+        handler: HandlerContext,
         mut connection: TlsConnection,
         remote_addr: SocketAddr,
-        device_info: Arc<crate::DeviceInfo>,
-        event_tx: mpsc::UnboundedSender<ConnectionEvent>,
-        connections: Arc<RwLock<HashMap<String, ActiveConnection>>>,
-        device_manager: Arc<RwLock<DeviceManager>>,
         remote_identity: Option<crate::Packet>,
-        last_connection_time: Arc<RwLock<HashMap<String, Instant>>>,
+        outgoing_device_id: Option<String>,
     ) {
+        let HandlerContext {
+            device_info,
+            event_tx,
+            connections,
+            device_manager,
+            last_connection_time,
+            idle_tracker,
+            traffic,
+            link_states,
+            backoff,
+            dependents,
+            keep_alive_interval,
+            keep_alive_watch,
+            keepalive_timeout,
+            compression,
+            packet_tap,
+            pending_requests,
+        } = handler;
         let (command_tx, mut command_rx) = mpsc::unbounded_channel();
+        let (task_tx, task_rx) = oneshot::channel::<AbortHandle>();
 
-        let _task = tokio::spawn(async move {
+        let task = tokio::spawn(async move {
+            // Handed over right after spawning, for ActiveConnection
+            let Ok(task) = task_rx.await else {
+                return;
+            };
             let device_id: Option<String>;
 
             // If remote_identity is already provided, skip the identity exchange
@@ -494,6 +1605,9 @@ impl ConnectionManager {
                 let core_identity = our_identity.to_core_packet();
                 if let Err(e) = connection.send_packet(&core_identity).await {
                     error!("Failed to send identity over TLS to {}: {}", remote_addr, e);
+                    if let Some(id) = &outgoing_device_id {
+                        link_states.write().await.connect_failed(id);
+                    }
                     return;
                 }
                 debug!("Sent encrypted identity packet to {}", remote_addr);
@@ -506,6 +1620,9 @@ impl ConnectionManager {
                             "Failed to receive identity packet from {}: {}",
                             remote_addr, e
                         );
+                        if let Some(id) = &outgoing_device_id {
+                            link_states.write().await.connect_failed(id);
+                        }
                         return;
                     }
                 }
@@ -518,9 +1635,30 @@ impl ConnectionManager {
 
                 info!("Connection identified as device {}", id);
 
+                // Another device answered at that address, so the attempt for
+                // the one we asked for is over
+                if let Some(requested) = outgoing_device_id.as_deref().filter(|&r| r != id) {
+                    warn!(
+                        "Connected to {} at {} while expecting {}",
+                        id, remote_addr, requested
+                    );
+                    link_states.write().await.connect_failed(requested);
+                }
+
                 // Update device manager - register device if not exists before marking connected
                 let mut dm = device_manager.write().await;
 
+                // Refuse a paired device presenting another certificate
+                if let Some(mismatch) = Self::check_pinned_certificate(&mut dm, id, &connection) {
+                    drop(dm);
+                    let _ = event_tx.send(mismatch);
+                    if let Some(id) = &outgoing_device_id {
+                        link_states.write().await.connect_failed(id);
+                    }
+                    let _ = connection.close().await;
+                    return;
+                }
+
                 // Register new device or update capabilities for existing one
                 if dm.get_device(id).is_none() {
                     // Device doesn't exist — try full parse to create it
@@ -534,23 +1672,9 @@ impl ConnectionManager {
                             warn!("Failed to parse device info from identity: {}", e);
                         }
                     }
-                } else if let Some(device) = dm.get_device_mut(id) {
+                } else {
                     // Device exists — update capabilities from the identity packet
-                    // Use parse_capabilities directly since post-TLS identity
-                    // may not contain all fields required by from_identity_packet
-                    use crate::discovery::parse_capabilities;
-                    let incoming = parse_capabilities(&packet, "incomingCapabilities");
-                    let outgoing = parse_capabilities(&packet, "outgoingCapabilities");
-                    if !incoming.is_empty() || !outgoing.is_empty() {
-                        device.info.incoming_capabilities = incoming;
-                        device.info.outgoing_capabilities = outgoing;
-                        debug!(
-                            "Updated capabilities for device {} ({} in, {} out)",
-                            id,
-                            device.info.incoming_capabilities.len(),
-                            device.info.outgoing_capabilities.len()
-                        );
-                    }
+                    Self::apply_capabilities(&mut dm, id, &packet);
                 }
 
                 if let Err(e) =
@@ -558,6 +1682,7 @@ impl ConnectionManager {
                 {
                     warn!("Failed to mark device {} as connected: {}", id, e);
                 }
+                dm.record_activity(id, Instant::now());
                 drop(dm);
 
                 // Rate limiting: Check if device is connecting too frequently
@@ -615,19 +1740,25 @@ impl ConnectionManager {
                     id.to_string(),
                     ActiveConnection {
                         command_tx: command_tx.clone(),
-                        task: tokio::task::spawn(async {}), // Placeholder task
+                        task,
                         device_id: id.to_string(),
                         remote_addr,
                     },
                 );
                 drop(conns);
 
+                idle_tracker.write().await.touch(id);
+                link_states.write().await.record_connected(id);
+                backoff.write().await.record_success(id);
+
                 // Emit connected event
                 let _ = event_tx.send(ConnectionEvent::Connected {
                     device_id: id.to_string(),
                     remote_addr,
                 });
 
+                packet_tap.record(TapDirection::Inbound, id, &packet);
+
                 // Emit packet received event
                 let _ = event_tx.send(ConnectionEvent::PacketReceived {
                     device_id: id.to_string(),
@@ -639,6 +1770,9 @@ impl ConnectionManager {
                     "Identity packet from {} did not contain deviceId",
                     remote_addr
                 );
+                if let Some(id) = &outgoing_device_id {
+                    link_states.write().await.connect_failed(id);
+                }
                 return;
             }
 
@@ -646,11 +1780,39 @@ impl ConnectionManager {
 
             // Keepalive pings to maintain connection stability
             // Uses "keepalive" flag so Android handles these silently without notifications
-            let mut keepalive_timer = Some(tokio::time::interval(KEEP_ALIVE_INTERVAL));
+            let mut heartbeat = DependentTasks::new();
+            let keepalive_tx = command_tx.clone();
+            heartbeat.spawn(DependentTask::Heartbeat, "keepalive", move |signal| {
+                Self::run_heartbeat(keepalive_tx, keep_alive_interval, keep_alive_watch, signal)
+            });
 
             // Track if this is a socket replacement (reconnect) to preserve plugins
             let mut is_reconnect = false;
 
+            // Reason reported in the Disconnected event
+            let mut close_reason = "Connection closed";
+
+            // Whether the link failed rather than being closed on purpose
+            let mut lost = false;
+
+            // Byte counters, including the identity packet received during setup
+            let counter = Self::counter_for(&traffic, &device_id).await;
+            counter.record_control_received(packet_wire_size(&packet));
+            let mut last_reported = TrafficStats::default();
+
+            // Dead-peer detection, see super::keepalive
+            let mut liveness = PeerLiveness::new(keepalive_timeout);
+
+            // Body compression both sides support, if enabled
+            let compression = compression.negotiate(&CompressionMode::advertised_by(&packet));
+            if compression != CompressionMode::Off {
+                debug!(
+                    "Compressing packet bodies to {} with {}",
+                    device_id,
+                    compression.as_str()
+                );
+            }
+
             // Main connection loop
             loop {
                 tokio::select! {
@@ -658,15 +1820,23 @@ impl ConnectionManager {
                     Some(cmd) = command_rx.recv() => {
                         match cmd {
                             ConnectionCommand::SendPacket(packet) => {
+                                if !is_keepalive(&packet) {
+                                    idle_tracker.write().await.touch(&device_id);
+                                    device_manager.write().await.record_activity(&device_id, Instant::now());
+                                }
                                 // Convert applet Packet to core Packet for TLS
                                 debug!("Connection task sending packet '{}' to {}", packet.packet_type, device_id);
-                                let core_packet = packet.to_core_packet();
+                                let wire = packet.compressed(compression);
+                                let core_packet = wire.to_core_packet();
                                 match connection.send_packet(&core_packet).await {
                                     Ok(_) => {
+                                        counter.record_control_sent(packet_wire_size(&wire));
+                                        packet_tap.record(TapDirection::Outbound, &device_id, &packet);
                                         debug!("Packet '{}' successfully written to socket for {}", packet.packet_type, device_id);
                                     }
                                     Err(e) => {
                                         error!("Failed to send packet '{}' to {}: {}", packet.packet_type, device_id, e);
+                                        lost = true;
                                         break;
                                     }
                                 }
@@ -680,6 +1850,35 @@ impl ConnectionManager {
                                 is_reconnect = true;
                                 break;
                             }
+                            ConnectionCommand::CloseIdle => {
+                                info!("Closing idle connection to {}", device_id);
+                                close_reason = "Idle timeout";
+                                break;
+                            }
+                            ConnectionCommand::Keepalive => {
+                                // Send keepalive ping with silent flag to prevent Android notifications
+                                debug!("Sending keepalive ping to device {}", device_id);
+                                let ping_packet = keepalive_ping();
+                                let core_ping = ping_packet.to_core_packet();
+                                if let Err(e) = connection.send_packet(&core_ping).await {
+                                    error!("Failed to send keepalive ping to {}: {}", device_id, e);
+                                    lost = true;
+                                    break;
+                                }
+                                counter.record_control_sent(packet_wire_size(&ping_packet));
+                                packet_tap.record(TapDirection::Outbound, &device_id, &ping_packet);
+
+                                // Report byte counters at most once per keepalive interval
+                                let stats = counter.snapshot();
+                                if stats != last_reported {
+                                    last_reported = stats;
+                                    let _ = event_tx.send(ConnectionEvent::TrafficUpdated {
+                                        device_id: device_id.clone(),
+                                        stats,
+                                        session_ended: false,
+                                    });
+                                }
+                            }
                         }
                     }
 
@@ -688,8 +1887,49 @@ impl ConnectionManager {
                         match result {
                             Ok(core_packet) => {
                                 // Convert core Packet to applet Packet
-                                let packet = crate::Packet::from_core_packet(core_packet);
+                                let wire = crate::Packet::from_core_packet(core_packet);
+                                let wire_size = packet_wire_size(&wire);
+                                let packet = match wire.decompressed() {
+                                    Ok(packet) => packet,
+                                    Err(e) => {
+                                        warn!("Dropping packet from {}: {}", device_id, e);
+                                        continue;
+                                    }
+                                };
                                 debug!("Received packet '{}' from {}", packet.packet_type, device_id);
+                                liveness.received();
+                                if !is_keepalive(&packet) {
+                                    idle_tracker.write().await.touch(&device_id);
+                                    device_manager.write().await.record_activity(&device_id, Instant::now());
+                                }
+                                counter.record_control_received(wire_size);
+                                packet_tap.record(TapDirection::Inbound, &device_id, &packet);
+                                if pending_requests.resolve(&device_id, &packet) {
+                                    continue;
+                                }
+                                if packet.is_type("cconnect.identity") {
+                                    Self::handle_capability_update(&device_manager, &event_tx, &device_id, &packet).await;
+                                } else if packet.is_type(IDENTITY_REQUEST) {
+                                    // Peer wants to re-sync our capabilities
+                                    let identity = device_info.to_identity_packet();
+                                    if let Err(e) = connection.send_packet(&identity.to_core_packet()).await {
+                                        error!("Failed to send identity to {}: {}", device_id, e);
+                                        lost = true;
+                                        break;
+                                    }
+                                    counter.record_control_sent(packet_wire_size(&identity));
+                                    packet_tap.record(TapDirection::Outbound, &device_id, &identity);
+                                } else if wants_reply(&packet) {
+                                    // Let the peer know we are still here
+                                    let reply = keepalive_reply();
+                                    if let Err(e) = connection.send_packet(&reply.to_core_packet()).await {
+                                        error!("Failed to answer keepalive from {}: {}", device_id, e);
+                                        lost = true;
+                                        break;
+                                    }
+                                    counter.record_control_sent(packet_wire_size(&reply));
+                                    packet_tap.record(TapDirection::Outbound, &device_id, &reply);
+                                }
                                 let _ = event_tx.send(ConnectionEvent::PacketReceived {
                                     device_id: device_id.clone(),
                                     packet,
@@ -698,30 +1938,23 @@ impl ConnectionManager {
                             }
                             Err(e) => {
                                 warn!("Error receiving packet from {}: {}", device_id, e);
+                                lost = true;
                                 break;
                             }
                         }
                     }
 
-                    // Keepalive timer - only for paired devices
-                    _ = async {
-                        if let Some(ref mut timer) = keepalive_timer {
-                            timer.tick().await;
-                        } else {
-                            // For unpaired devices, never send keepalive
-                            std::future::pending::<()>().await;
-                        }
-                    } => {
-                        // Send keepalive ping with silent flag to prevent Android notifications
-                        debug!("Sending keepalive ping to device {}", device_id);
-                        let ping_packet = crate::Packet::new("cconnect.ping", serde_json::json!({
-                            "keepalive": true
-                        }));
-                        let core_ping = ping_packet.to_core_packet();
-                        if let Err(e) = connection.send_packet(&core_ping).await {
-                            error!("Failed to send keepalive ping to {}: {}", device_id, e);
-                            break;
-                        }
+                    // Peer stopped answering
+                    _ = tokio::time::sleep_until(liveness.deadline()) => {
+                        let silence = liveness.silence();
+                        warn!("No traffic from {} for {:?}, closing connection", device_id, silence);
+                        let _ = event_tx.send(ConnectionEvent::Timeout {
+                            device_id: device_id.clone(),
+                            silence,
+                        });
+                        close_reason = "Keepalive timeout";
+                        lost = true;
+                        break;
                     }
                 }
             }
@@ -753,6 +1986,25 @@ impl ConnectionManager {
             };
             drop(conns);
 
+            // Stop dependent tasks while the transport is still open: the
+            // heartbeat always, the device's transfers and plugin dispatch only
+            // when the device is really going away
+            let mut cancelled = heartbeat.teardown(TEARDOWN_GRACE).await;
+            if should_mark_disconnected && !is_reconnect {
+                let device_tasks = dependents.write().await.remove(&device_id);
+                if let Some(mut tasks) = device_tasks {
+                    cancelled.extend(tasks.teardown(TEARDOWN_GRACE).await);
+                }
+            }
+            for task in cancelled {
+                let _ = event_tx.send(ConnectionEvent::TaskCancelled {
+                    device_id: device_id.clone(),
+                    task: task.kind,
+                    name: task.name,
+                    outcome: task.outcome,
+                });
+            }
+
             // Update device manager only if this was the active connection
             // and NOT a socket replacement (reconnect)
             if should_mark_disconnected && !is_reconnect {
@@ -760,10 +2012,22 @@ impl ConnectionManager {
                 let _ = dm.mark_disconnected(&device_id);
                 drop(dm);
 
+                idle_tracker.write().await.remove(&device_id);
+                link_states.write().await.record_closed(&device_id, lost);
+
+                // Report session totals, then start the next session from zero
+                if let Some(counter) = traffic.write().await.remove(&device_id) {
+                    let _ = event_tx.send(ConnectionEvent::TrafficUpdated {
+                        device_id: device_id.clone(),
+                        stats: counter.snapshot(),
+                        session_ended: true,
+                    });
+                }
+
                 // Emit disconnected event
                 let _ = event_tx.send(ConnectionEvent::Disconnected {
                     device_id: device_id.clone(),
-                    reason: Some("Connection closed".to_string()),
+                    reason: Some(close_reason.to_string()),
                     reconnect: false,
                 });
             } else if is_reconnect {
@@ -776,15 +2040,923 @@ impl ConnectionManager {
                 });
             }
 
-            // Close connection
+            // Close connection, now that nothing depends on it
             let _ = connection.close().await;
 
             info!("Connection handler for {} stopped", device_id);
         });
 
-        // Note: We can't update the task handle in ActiveConnection here
-        // because we just moved it into the spawn. This is a limitation
-        // of the current design. We could use Arc<Mutex<JoinHandle>> but
-        // it's not necessary since we can abort via the command channel.
+        let _ = task_tx.send(task.abort_handle());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::correlation::{reply_to, REPLY_TO_FIELD};
+
+    const IDLE_TIMEOUT: Duration = Duration::from_millis(30);
+
+    fn insert_connection(
+        connections: &mut HashMap<String, ActiveConnection>,
+        device_id: &str,
+        port: u16,
+    ) -> mpsc::UnboundedReceiver<ConnectionCommand> {
+        let (command_tx, command_rx) = mpsc::unbounded_channel();
+        connections.insert(
+            device_id.to_string(),
+            ActiveConnection {
+                command_tx,
+                task: tokio::spawn(async {}).abort_handle(),
+                device_id: device_id.to_string(),
+                remote_addr: SocketAddr::from(([127, 0, 0, 1], port)),
+            },
+        );
+        command_rx
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_follows_interval_changes() {
+        let (keepalive_tx, mut keepalive_rx) = mpsc::unbounded_channel();
+        let (interval_tx, interval_rx) = watch::channel(Duration::from_secs(3600));
+        let mut tasks = DependentTasks::new();
+        let signal = tasks.signal(DependentTask::Heartbeat);
+        let heartbeat = tokio::spawn(ConnectionManager::run_heartbeat(
+            keepalive_tx,
+            KEEP_ALIVE_INTERVAL,
+            Some(interval_rx),
+            signal,
+        ));
+
+        // First ping goes out straight away, the next only after an hour
+        assert!(matches!(
+            keepalive_rx.recv().await,
+            Some(ConnectionCommand::Keepalive)
+        ));
+
+        // A switched cadence keeps pinging at the new interval
+        interval_tx.send_replace(Duration::from_millis(20));
+        for _ in 0..3 {
+            let ping = tokio::time::timeout(Duration::from_secs(1), keepalive_rx.recv()).await;
+            assert!(matches!(ping, Ok(Some(ConnectionCommand::Keepalive))));
+        }
+
+        // Switching back to a slow cadence stops the quick pings
+        interval_tx.send_replace(Duration::from_secs(3600));
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        while keepalive_rx.try_recv().is_ok() {}
+        let ping = tokio::time::timeout(Duration::from_millis(100), keepalive_rx.recv()).await;
+        assert!(ping.is_err());
+
+        tasks.teardown(TEARDOWN_GRACE).await;
+        heartbeat.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_idle_connection_closed_after_timeout() {
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        let idle_tracker = Arc::new(RwLock::new(IdleTracker::new(
+            &ConnectionConfig::default().idle_exempt_plugins,
+        )));
+
+        let mut command_rx = insert_connection(&mut *connections.write().await, "phone", 1716);
+        idle_tracker.write().await.touch("phone");
+
+        // Not idle yet
+        let closed =
+            ConnectionManager::close_idle_connections(&connections, &idle_tracker, IDLE_TIMEOUT)
+                .await;
+        assert!(closed.is_empty());
+        assert!(connections.read().await.contains_key("phone"));
+
+        tokio::time::sleep(IDLE_TIMEOUT * 2).await;
+
+        let closed =
+            ConnectionManager::close_idle_connections(&connections, &idle_tracker, IDLE_TIMEOUT)
+                .await;
+        assert_eq!(closed, vec!["phone".to_string()]);
+        assert!(!connections.read().await.contains_key("phone"));
+        assert!(matches!(
+            command_rx.try_recv(),
+            Ok(ConnectionCommand::CloseIdle)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_traffic_counter_shared_per_device() {
+        let traffic = Arc::new(RwLock::new(HashMap::new()));
+
+        let connection_side = ConnectionManager::counter_for(&traffic, "phone").await;
+        let payload_side = ConnectionManager::counter_for(&traffic, "phone").await;
+        let other = ConnectionManager::counter_for(&traffic, "tablet").await;
+
+        let packet = Packet::new(
+            "cconnect.battery",
+            serde_json::json!({ "currentCharge": 80 }),
+        );
+        connection_side.record_control_sent(packet_wire_size(&packet));
+        payload_side.record_payload_received(1_000_000);
+
+        let stats = traffic.read().await["phone"].snapshot();
+        assert_eq!(stats.control_sent, packet.to_bytes().unwrap().len() as u64);
+        assert_eq!(stats.payload_received, 1_000_000);
+        assert_eq!(other.snapshot(), TrafficStats::default());
+
+        connection_side.reset();
+        assert_eq!(payload_side.snapshot(), TrafficStats::default());
+    }
+
+    /// Manager that knows one paired device at an address nobody listens on
+    async fn manager_with_unreachable_device(
+        device_id: &str,
+    ) -> (ConnectionManager, tempfile::TempDir) {
+        let closed_port = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut device_manager = DeviceManager::new(dir.path().join("registry.json")).unwrap();
+        let mut info = DeviceInfo::new("Phone", crate::DeviceType::Phone, closed_port);
+        info.device_id = device_id.to_string();
+        let mut device = Device::from_discovery(info);
+        device.host = Some("127.0.0.1".to_string());
+        device.port = Some(closed_port);
+        device_manager.add_device(device);
+
+        let manager = ConnectionManager::new(
+            CertificateInfo::generate("desktop").unwrap(),
+            DeviceInfo::new("Desktop", crate::DeviceType::Desktop, 1816),
+            Arc::new(RwLock::new(device_manager)),
+            ConnectionConfig::default(),
+        )
+        .unwrap();
+        (manager, dir)
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_now_skips_backoff_and_failure_preserves_it() {
+        let (manager, _dir) = manager_with_unreachable_device("phone").await;
+        for _ in 0..3 {
+            manager.claim_reconnect_attempt("phone").await.unwrap();
+        }
+        assert!(manager.claim_reconnect_attempt("phone").await.is_none());
+        let before = manager.reconnect_backoff().await;
+
+        // Attempted right away instead of waiting out the 8s backoff
+        let started = Instant::now();
+        let result = manager.reconnect_now("phone").await;
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        let after = manager.reconnect_backoff().await;
+        assert_eq!(after.failures("phone"), 3);
+        assert_eq!(
+            after.next_attempt_at("phone"),
+            before.next_attempt_at("phone")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_now_success_resets_backoff() {
+        let is_connected = |e: &ConnectionEvent| matches!(e, ConnectionEvent::Connected { .. });
+        let (_phone, _phone_events, phone_addr) =
+            link_local_manager("phone", false, Vec::new()).await;
+        let (desktop, mut desktop_events, _) =
+            link_local_manager("desktop", false, Vec::new()).await;
+        add_paired_device(&desktop, "phone", phone_addr).await;
+        desktop
+            .device_manager
+            .write()
+            .await
+            .get_device_mut("phone")
+            .unwrap()
+            .port = Some(phone_addr.port());
+
+        for _ in 0..3 {
+            desktop.claim_reconnect_attempt("phone").await.unwrap();
+        }
+        assert!(desktop.claim_reconnect_attempt("phone").await.is_none());
+
+        // The phone is listening again, so the immediate attempt connects
+        desktop.reconnect_now("phone").await.unwrap();
+        next_event(&mut desktop_events, is_connected).await;
+        assert!(desktop.has_connection("phone").await);
+        assert_eq!(desktop.reconnect_backoff().await.failures("phone"), 0);
+        assert_eq!(desktop.claim_reconnect_attempt("phone").await, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_connect_to_connected_device_resets_backoff() {
+        let (manager, _dir) = manager_with_unreachable_device("phone").await;
+        for _ in 0..3 {
+            manager.claim_reconnect_attempt("phone").await.unwrap();
+        }
+
+        // The device reconnected on its own while we were backing off
+        let _command_rx = insert_connection(&mut *manager.connections.write().await, "phone", 1716);
+        let addr = manager.last_known_addr("phone").await.unwrap();
+        manager.connect("phone", addr).await.unwrap();
+
+        let backoff = manager.reconnect_backoff().await;
+        assert_eq!(backoff.failures("phone"), 0);
+        assert_eq!(backoff.delay("phone"), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_connect_rejected_while_attempt_in_flight() {
+        let (manager, _dir) = manager_with_unreachable_device("phone").await;
+        let addr: SocketAddr = "127.0.0.1:1".parse().unwrap();
+
+        // Another attempt is already running
+        manager.link_states.write().await.begin_connect("phone").unwrap();
+        let result = manager.connect("phone", addr).await;
+        assert!(matches!(result, Err(ProtocolError::InvalidState(_))));
+        assert_eq!(manager.link_state("phone").await, LinkState::Connecting);
+
+        // A failed attempt after a lost link leaves it lost
+        manager.link_states.write().await.connect_failed("phone");
+        manager.link_states.write().await.record_connected("phone");
+        manager.link_states.write().await.record_closed("phone", true);
+        assert!(manager.connect("phone", addr).await.is_err());
+        assert_eq!(manager.link_state("phone").await, LinkState::Lost);
+    }
+
+    #[tokio::test]
+    async fn test_missing_certificate_refused_when_pinned() {
+        let (manager, _dir) = manager_with_unreachable_device("phone").await;
+        let addr: SocketAddr = "127.0.0.1:1716".parse().unwrap();
+        let mut dm = manager.device_manager.write().await;
+
+        // Nothing to enforce before pairing
+        assert!(
+            ConnectionManager::check_pinned_fingerprint(&mut dm, "phone", addr, None).is_none()
+        );
+
+        dm.mark_paired("phone", "AA:BB".to_string()).unwrap();
+        let Some(ConnectionEvent::CertificateMismatch {
+            expected_fingerprint,
+            presented_fingerprint,
+            ..
+        }) = ConnectionManager::check_pinned_fingerprint(&mut dm, "phone", addr, None)
+        else {
+            panic!("connection without a certificate was not refused");
+        };
+        assert_eq!(expected_fingerprint, "AA:BB");
+        assert!(presented_fingerprint.is_empty());
+        assert!(
+            ConnectionManager::check_pinned_fingerprint(&mut dm, "phone", addr, Some("AA:BB"))
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_payload_streams_capped_by_device() {
+        let (manager, _dir) = manager_with_unreachable_device("phone").await;
+        assert_eq!(manager.payload_streams("phone", 4).await, 1);
+
+        manager
+            .device_manager
+            .write()
+            .await
+            .get_device_mut("phone")
+            .unwrap()
+            .info
+            .payload_streams = 4;
+        assert_eq!(manager.payload_streams("phone", 1).await, 1);
+        assert_eq!(manager.payload_streams("phone", 2).await, 2);
+        assert_eq!(manager.payload_streams("phone", 8).await, 4);
+        assert_eq!(manager.payload_streams("tablet", 8).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_now_unknown_device() {
+        let (manager, _dir) = manager_with_unreachable_device("phone").await;
+        let result = manager.reconnect_now("tablet").await;
+        assert!(matches!(result, Err(ProtocolError::DeviceNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_capability_update_changes_device_and_keeps_connection() {
+        let (manager, _dir) = manager_with_unreachable_device("phone").await;
+        let mut command_rx =
+            insert_connection(&mut *manager.connections.write().await, "phone", 1716);
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+
+        let identity = DeviceInfo::with_id("phone", "Phone", crate::DeviceType::Phone, 1716)
+            .with_incoming_capability("cconnect.ping")
+            .with_incoming_capability("cconnect.share.request")
+            .with_outgoing_capability("cconnect.battery")
+            .to_identity_packet();
+        ConnectionManager::handle_capability_update(
+            &manager.device_manager,
+            &event_tx,
+            "phone",
+            &identity,
+        )
+        .await;
+
+        match event_rx.try_recv() {
+            Ok(ConnectionEvent::CapabilitiesChanged {
+                device_id,
+                incoming_capabilities,
+                outgoing_capabilities,
+            }) => {
+                assert_eq!(device_id, "phone");
+                assert_eq!(
+                    incoming_capabilities,
+                    vec!["cconnect.ping", "cconnect.share.request"]
+                );
+                assert_eq!(outgoing_capabilities, vec!["cconnect.battery"]);
+            }
+            other => panic!("expected CapabilitiesChanged, got {:?}", other),
+        }
+        {
+            let dm = manager.device_manager.read().await;
+            let device = dm.get_device("phone").unwrap();
+            assert!(device
+                .info
+                .incoming_capabilities
+                .contains(&"cconnect.share.request".to_string()));
+        }
+
+        // The connection is left alone
+        assert!(manager.connections.read().await.contains_key("phone"));
+        assert!(command_rx.try_recv().is_err());
+
+        // Same set again, in another order: nothing to report
+        let reordered = DeviceInfo::with_id("phone", "Phone", crate::DeviceType::Phone, 1716)
+            .with_incoming_capability("cconnect.share.request")
+            .with_incoming_capability("cconnect.ping")
+            .with_outgoing_capability("cconnect.battery")
+            .to_identity_packet();
+        ConnectionManager::handle_capability_update(
+            &manager.device_manager,
+            &event_tx,
+            "phone",
+            &reordered,
+        )
+        .await;
+        assert!(event_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_capabilities_tells_connected_devices() {
+        let (mut manager, _dir) = manager_with_unreachable_device("phone").await;
+        let mut command_rx =
+            insert_connection(&mut *manager.connections.write().await, "phone", 1716);
+
+        let told = manager
+            .update_capabilities(
+                vec!["cconnect.ping".to_string()],
+                vec!["cconnect.battery".to_string()],
+            )
+            .await;
+        assert_eq!(told, 1);
+        assert_eq!(
+            manager.device_info.incoming_capabilities,
+            vec!["cconnect.ping".to_string()]
+        );
+        match command_rx.try_recv() {
+            Ok(ConnectionCommand::SendPacket(packet)) => {
+                assert!(packet.is_type("cconnect.identity"));
+                assert_eq!(
+                    packet.get_body_field::<Vec<String>>("incomingCapabilities"),
+                    Some(vec!["cconnect.ping".to_string()])
+                );
+            }
+            _ => panic!("expected the updated identity to be queued"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_capabilities_requests_identity() {
+        let (manager, _dir) = manager_with_unreachable_device("phone").await;
+        let mut command_rx =
+            insert_connection(&mut *manager.connections.write().await, "phone", 1716);
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+
+        manager.refresh_capabilities("phone").await.unwrap();
+        match command_rx.try_recv() {
+            Ok(ConnectionCommand::SendPacket(packet)) => {
+                assert!(packet.is_type(IDENTITY_REQUEST));
+            }
+            _ => panic!("expected an identity request to be queued"),
+        }
+        assert!(matches!(
+            manager.refresh_capabilities("tablet").await,
+            Err(ProtocolError::DeviceNotFound(_))
+        ));
+
+        // The answer carries a plugin the phone did not advertise before
+        let answer = DeviceInfo::with_id("phone", "Phone", crate::DeviceType::Phone, 1716)
+            .with_incoming_capability("cconnect.ping")
+            .with_incoming_capability("cconnect.findmyphone.request")
+            .to_identity_packet();
+        ConnectionManager::handle_capability_update(
+            &manager.device_manager,
+            &event_tx,
+            "phone",
+            &answer,
+        )
+        .await;
+
+        assert!(matches!(
+            event_rx.try_recv(),
+            Ok(ConnectionEvent::CapabilitiesChanged { .. })
+        ));
+        let dm = manager.device_manager.read().await;
+        assert!(dm
+            .get_device("phone")
+            .unwrap()
+            .info
+            .incoming_capabilities
+            .contains(&"cconnect.findmyphone.request".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_send_request_resolves_with_correlated_reply() {
+        let (manager, _dir) = manager_with_unreachable_device("phone").await;
+        let mut command_rx =
+            insert_connection(&mut *manager.connections.write().await, "phone", 1716);
+
+        let device = async {
+            let request = match command_rx.recv().await {
+                Some(ConnectionCommand::SendPacket(packet)) => packet,
+                _ => panic!("expected the request to be queued"),
+            };
+            // An answer to some other request arrives first
+            let unrelated = Packet::new(
+                "cconnect.systeminfo",
+                serde_json::json!({REPLY_TO_FIELD: 9999, "hostname": "other"}),
+            );
+            assert!(!manager.pending_requests.resolve("phone", &unrelated));
+            let reply = reply_to(
+                &request,
+                "cconnect.systeminfo",
+                serde_json::json!({"hostname": "pixel"}),
+            );
+            assert!(manager.pending_requests.resolve("phone", &reply));
+        };
+        let request = Packet::new("cconnect.systeminfo.request", serde_json::json!({}));
+        let (reply, ()) = tokio::join!(manager.send_request("phone", request), device);
+
+        assert_eq!(reply.unwrap().body["hostname"], "pixel");
+        assert!(manager.pending_requests.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send_request_times_out_without_reply() {
+        let (manager, _dir) = manager_with_unreachable_device("phone").await;
+        let _command_rx = insert_connection(&mut *manager.connections.write().await, "phone", 1716);
+
+        let request = Packet::new("cconnect.systeminfo.request", serde_json::json!({}));
+        let result = manager
+            .send_request_with_timeout("phone", request, Duration::from_millis(50))
+            .await;
+
+        assert!(matches!(result, Err(ProtocolError::Timeout(_))));
+        assert!(manager.pending_requests.is_empty());
+
+        let request = Packet::new("cconnect.systeminfo.request", serde_json::json!({}));
+        assert!(matches!(
+            manager.send_request("tablet", request).await,
+            Err(ProtocolError::DeviceNotFound(_))
+        ));
+        assert!(manager.pending_requests.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prewarm_connects_to_listed_devices() {
+        let (mut manager, _dir) = manager_with_unreachable_device("phone").await;
+        manager
+            .device_manager
+            .write()
+            .await
+            .mark_paired("phone", "fingerprint".to_string())
+            .unwrap();
+        let devices = vec!["phone".to_string(), "unknown".to_string()];
+
+        // The phone is unreachable, so the attempt fails and is backed off
+        assert_eq!(manager.prewarm(&devices).await, vec!["phone"]);
+        let backoff = manager.reconnect_backoff().await;
+        assert_eq!(backoff.failures("phone"), 1);
+        assert!(!manager.has_connection("phone").await);
+
+        // Still backing off: a second hint does not hammer the device
+        assert!(manager.prewarm(&devices).await.is_empty());
+
+        manager.reset_reconnect_backoff("phone").await;
+        let (_power_tx, power_rx) = watch::channel(PowerSource::Battery);
+        manager.follow_power_source(power_rx);
+        assert!(manager.prewarm(&devices).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prewarm_skips_connected_devices() {
+        let (manager, _dir) = manager_with_unreachable_device("phone").await;
+        manager
+            .device_manager
+            .write()
+            .await
+            .mark_paired("phone", "fingerprint".to_string())
+            .unwrap();
+        let _command_rx = insert_connection(&mut *manager.connections.write().await, "phone", 1716);
+
+        assert!(manager.prewarm(&["phone".to_string()]).await.is_empty());
+        assert_eq!(manager.reconnect_backoff().await.failures("phone"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_exempt_plugin_keeps_connection_alive() {
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        let idle_tracker = Arc::new(RwLock::new(IdleTracker::new(&["clipboard".to_string()])));
+
+        let mut synced_rx = insert_connection(&mut *connections.write().await, "synced", 1716);
+        let mut plain_rx = insert_connection(&mut *connections.write().await, "plain", 1717);
+        {
+            let mut tracker = idle_tracker.write().await;
+            tracker.touch("synced");
+            tracker.touch("plain");
+            tracker.set_plugin_active("synced", "clipboard", true);
+        }
+
+        tokio::time::sleep(IDLE_TIMEOUT * 2).await;
+
+        let closed =
+            ConnectionManager::close_idle_connections(&connections, &idle_tracker, IDLE_TIMEOUT)
+                .await;
+        assert_eq!(closed, vec!["plain".to_string()]);
+        assert!(connections.read().await.contains_key("synced"));
+        assert!(synced_rx.try_recv().is_err());
+        assert!(matches!(
+            plain_rx.try_recv(),
+            Ok(ConnectionCommand::CloseIdle)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_not_exempt_by_default() {
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        let idle_tracker = Arc::new(RwLock::new(IdleTracker::new(
+            &ConnectionConfig::default().idle_exempt_plugins,
+        )));
+
+        let _command_rx = insert_connection(&mut *connections.write().await, "phone", 1716);
+        {
+            let mut tracker = idle_tracker.write().await;
+            tracker.touch("phone");
+            tracker.set_plugin_active("phone", "clipboard", true);
+        }
+
+        tokio::time::sleep(IDLE_TIMEOUT * 2).await;
+
+        let closed =
+            ConnectionManager::close_idle_connections(&connections, &idle_tracker, IDLE_TIMEOUT)
+                .await;
+        assert_eq!(closed, vec!["phone".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_stops_dependents_before_closing() {
+        use crate::connection::teardown::CancelOutcome;
+        use crate::test_utils::harness::{connect, TestStack};
+
+        let mut desktop = TestStack::start("teardown_desktop", "Desktop")
+            .await
+            .unwrap();
+        let mut phone = TestStack::start("teardown_phone", "Phone").await.unwrap();
+        connect(&mut desktop, &mut phone).await.unwrap();
+
+        let manager = &desktop.connection_manager;
+        assert!(manager
+            .spawn_dependent("unknown", DependentTask::Transfer, "x", |_| async {})
+            .await
+            .is_err());
+
+        // A transfer sending chunks until told to stop, and a plugin waiting on packets
+        manager
+            .spawn_dependent(
+                "teardown_phone",
+                DependentTask::Transfer,
+                "notes.txt",
+                |signal| async move {
+                    while !signal.is_cancelled() {
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                    }
+                },
+            )
+            .await
+            .unwrap();
+        manager
+            .spawn_dependent(
+                "teardown_phone",
+                DependentTask::PluginDispatch,
+                "ping",
+                |mut signal| async move { signal.cancelled().await },
+            )
+            .await
+            .unwrap();
+
+        manager.disconnect("teardown_phone").await.unwrap();
+        let events = desktop
+            .events_until(|event| matches!(event, ConnectionEvent::Disconnected { .. }))
+            .await
+            .unwrap();
+
+        let cancelled: Vec<(DependentTask, CancelOutcome)> = events
+            .iter()
+            .filter_map(|event| match event {
+                ConnectionEvent::TaskCancelled { task, outcome, .. } => Some((*task, *outcome)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            cancelled,
+            vec![
+                (DependentTask::Heartbeat, CancelOutcome::Stopped),
+                (DependentTask::Transfer, CancelOutcome::Stopped),
+                (DependentTask::PluginDispatch, CancelOutcome::Stopped),
+            ]
+        );
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, ConnectionEvent::ConnectionError { .. })));
+        assert!(desktop
+            .connection_manager
+            .dependents
+            .read()
+            .await
+            .is_empty());
+    }
+
+    /// Started manager on loopback, checking link-local mode against `subnets`
+    async fn link_local_manager(
+        device_id: &str,
+        link_local_only: bool,
+        subnets: Vec<LocalSubnet>,
+    ) -> (
+        ConnectionManager,
+        mpsc::UnboundedReceiver<ConnectionEvent>,
+        SocketAddr,
+    ) {
+        let device_manager = DeviceManager::with_storage(Arc::new(crate::MemoryStorage::new()));
+        let mut manager = ConnectionManager::new(
+            CertificateInfo::generate(device_id).unwrap(),
+            DeviceInfo::with_id(device_id, device_id, crate::DeviceType::Desktop, 0),
+            Arc::new(RwLock::new(device_manager.unwrap())),
+            ConnectionConfig {
+                listen_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+                link_local_only,
+                ..ConnectionConfig::default()
+            },
+        )
+        .unwrap();
+        manager.set_local_subnets(subnets);
+        let events = manager.subscribe().await;
+        let port = manager.start().await.unwrap();
+        (manager, events, SocketAddr::from(([127, 0, 0, 1], port)))
+    }
+
+    async fn next_event(
+        events: &mut mpsc::UnboundedReceiver<ConnectionEvent>,
+        mut matches: impl FnMut(&ConnectionEvent) -> bool,
+    ) -> ConnectionEvent {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let event = events.recv().await.expect("event channel closed");
+                if matches(&event) {
+                    return event;
+                }
+            }
+        })
+        .await
+        .expect("no matching connection event")
+    }
+
+    fn loopback_subnet() -> LocalSubnet {
+        LocalSubnet::new(std::net::Ipv4Addr::LOCALHOST.into(), 8)
+    }
+
+    fn remote_subnet() -> LocalSubnet {
+        LocalSubnet::new(std::net::Ipv4Addr::new(10, 0, 0, 1).into(), 8)
+    }
+
+    /// Pair `device_id`, last seen at `addr`
+    async fn add_paired_device(manager: &ConnectionManager, device_id: &str, addr: SocketAddr) {
+        let mut dm = manager.device_manager.write().await;
+        let mut device = Device::from_discovery(DeviceInfo::with_id(
+            device_id,
+            device_id,
+            crate::DeviceType::Phone,
+            0,
+        ));
+        device.host = Some(addr.ip().to_string());
+        dm.add_device(device);
+        dm.mark_paired(device_id, "fingerprint".to_string())
+            .unwrap();
+        // Nothing pinned yet: the first connection pins the peer's certificate
+        dm.reset_trust(device_id).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_request_connects_to_advertised_address() {
+        let is_connected = |e: &ConnectionEvent| matches!(e, ConnectionEvent::Connected { .. });
+        let (_phone, mut phone_events, phone_addr) =
+            link_local_manager("phone", false, Vec::new()).await;
+        let (desktop, mut desktop_events, _) =
+            link_local_manager("desktop", false, Vec::new()).await;
+        add_paired_device(&desktop, "phone", phone_addr).await;
+
+        let request = ConnectionManager::connect_request(phone_addr);
+        assert!(request.is_type(CONNECT_REQUEST));
+        desktop
+            .handle_connect_request("phone", &request)
+            .await
+            .unwrap();
+
+        next_event(&mut desktop_events, is_connected).await;
+        next_event(&mut phone_events, is_connected).await;
+        assert!(desktop.has_connection("phone").await);
+    }
+
+    #[tokio::test]
+    async fn test_pin_mismatch_leaves_backoff_untouched() {
+        let is_mismatch =
+            |e: &ConnectionEvent| matches!(e, ConnectionEvent::CertificateMismatch { .. });
+        let (_phone, _phone_events, phone_addr) =
+            link_local_manager("phone", false, Vec::new()).await;
+        let (desktop, mut desktop_events, _) =
+            link_local_manager("desktop", false, Vec::new()).await;
+        add_paired_device(&desktop, "phone", phone_addr).await;
+        desktop
+            .device_manager
+            .write()
+            .await
+            .mark_paired("phone", "not-the-phone".to_string())
+            .unwrap();
+        for _ in 0..3 {
+            desktop.claim_reconnect_attempt("phone").await.unwrap();
+        }
+        let before = desktop.reconnect_backoff().await;
+
+        // The handshake completes, but the phone is refused by its pin
+        desktop.connect("phone", phone_addr).await.unwrap();
+        next_event(&mut desktop_events, is_mismatch).await;
+        assert!(!desktop.has_connection("phone").await);
+
+        let after = desktop.reconnect_backoff().await;
+        assert_eq!(after.failures("phone"), 3);
+        assert_eq!(
+            after.next_attempt_at("phone"),
+            before.next_attempt_at("phone")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_to_other_device_ends_requested_attempt() {
+        let is_connected = |e: &ConnectionEvent| matches!(e, ConnectionEvent::Connected { .. });
+        let (_phone, _phone_events, phone_addr) =
+            link_local_manager("phone", false, Vec::new()).await;
+        let (desktop, mut desktop_events, _) =
+            link_local_manager("desktop", false, Vec::new()).await;
+
+        // The tablet's last address now belongs to the phone
+        desktop.connect("tablet", phone_addr).await.unwrap();
+        let event = next_event(&mut desktop_events, is_connected).await;
+        assert!(matches!(
+            event,
+            ConnectionEvent::Connected { device_id, .. } if device_id == "phone"
+        ));
+
+        assert_eq!(desktop.link_state("tablet").await, LinkState::Disconnected);
+        assert_eq!(desktop.link_state("phone").await, LinkState::Connected);
+        assert!(!desktop.has_connection("tablet").await);
+    }
+
+    #[tokio::test]
+    async fn test_connect_request_outside_policy_is_refused() {
+        let is_rejected =
+            |e: &ConnectionEvent| matches!(e, ConnectionEvent::ConnectionRejected { .. });
+        let (_phone, _, phone_addr) = link_local_manager("phone", false, Vec::new()).await;
+        let (desktop, mut desktop_events, _) =
+            link_local_manager("desktop", true, vec![remote_subnet()]).await;
+        add_paired_device(&desktop, "phone", phone_addr).await;
+
+        // Paired, but the address is off the allowed subnets
+        let result = desktop
+            .handle_connect_request("phone", &ConnectionManager::connect_request(phone_addr))
+            .await;
+        assert!(matches!(result, Err(ProtocolError::PermissionDenied(_))));
+        match next_event(&mut desktop_events, is_rejected).await {
+            ConnectionEvent::ConnectionRejected {
+                device_id,
+                remote_addr,
+                ..
+            } => {
+                assert_eq!(device_id.as_deref(), Some("phone"));
+                assert_eq!(remote_addr, phone_addr);
+            }
+            other => panic!("expected ConnectionRejected, got {:?}", other),
+        }
+
+        // Unpaired devices, non-unicast addresses and loopback or link-local
+        // addresses the device was not seen at are refused outright
+        let local = SocketAddr::from(([10, 0, 0, 7], 1716));
+        let unspecified = SocketAddr::from(([0, 0, 0, 0], 1716));
+        let loopback = SocketAddr::from(([127, 0, 0, 2], 22));
+        let link_local: SocketAddr = "[fe80::1]:1716".parse().unwrap();
+        for (device_id, addr) in [
+            ("stranger", local),
+            ("phone", unspecified),
+            ("phone", loopback),
+            ("phone", link_local),
+        ] {
+            let result = desktop
+                .handle_connect_request(device_id, &ConnectionManager::connect_request(addr))
+                .await;
+            assert!(matches!(result, Err(ProtocolError::PermissionDenied(_))));
+        }
+        let malformed = Packet::new(CONNECT_REQUEST, serde_json::json!({ "host": "phone" }));
+        assert!(matches!(
+            desktop.handle_connect_request("phone", &malformed).await,
+            Err(ProtocolError::InvalidPacket(_))
+        ));
+        assert!(!desktop.has_connection("phone").await);
+    }
+
+    #[tokio::test]
+    async fn test_link_local_only_refuses_off_subnet_peers() {
+        let is_rejected =
+            |e: &ConnectionEvent| matches!(e, ConnectionEvent::ConnectionRejected { .. });
+
+        // Outgoing: refused before any socket is opened
+        let (_responder, _, responder_addr) =
+            link_local_manager("responder", false, vec![loopback_subnet()]).await;
+        let (initiator, mut initiator_events, _) =
+            link_local_manager("initiator", true, vec![remote_subnet()]).await;
+        let result = initiator.connect("responder", responder_addr).await;
+        assert!(matches!(result, Err(ProtocolError::PermissionDenied(_))));
+        match next_event(&mut initiator_events, is_rejected).await {
+            ConnectionEvent::ConnectionRejected {
+                device_id,
+                remote_addr,
+                ..
+            } => {
+                assert_eq!(device_id.as_deref(), Some("responder"));
+                assert_eq!(remote_addr, responder_addr);
+            }
+            other => panic!("expected ConnectionRejected, got {:?}", other),
+        }
+
+        // Incoming: the accepted connection is closed without a handler
+        let (_guarded, mut guarded_events, guarded_addr) =
+            link_local_manager("guarded", true, vec![remote_subnet()]).await;
+        let (open, _, _) = link_local_manager("open", false, Vec::new()).await;
+        let _ = open.connect("guarded", guarded_addr).await;
+        let event = next_event(&mut guarded_events, |e| {
+            is_rejected(e) || matches!(e, ConnectionEvent::Connected { .. })
+        })
+        .await;
+        assert!(
+            matches!(
+                event,
+                ConnectionEvent::ConnectionRejected {
+                    device_id: None,
+                    ..
+                }
+            ),
+            "{:?}",
+            event
+        );
+    }
+
+    #[tokio::test]
+    async fn test_link_local_only_allows_local_peers() {
+        let is_connected = |e: &ConnectionEvent| matches!(e, ConnectionEvent::Connected { .. });
+
+        // Same subnet under link-local only mode
+        let (_responder, mut responder_events, responder_addr) =
+            link_local_manager("responder", true, vec![loopback_subnet()]).await;
+        let (initiator, mut initiator_events, _) =
+            link_local_manager("initiator", true, vec![loopback_subnet()]).await;
+        initiator
+            .connect("responder", responder_addr)
+            .await
+            .unwrap();
+        next_event(&mut initiator_events, is_connected).await;
+        next_event(&mut responder_events, is_connected).await;
+
+        // With the mode off, subnets do not matter
+        let (_responder, mut responder_events, responder_addr) =
+            link_local_manager("far-responder", false, vec![remote_subnet()]).await;
+        let (initiator, mut initiator_events, _) =
+            link_local_manager("far-initiator", false, vec![remote_subnet()]).await;
+        initiator
+            .connect("far-responder", responder_addr)
+            .await
+            .unwrap();
+        next_event(&mut initiator_events, is_connected).await;
+        next_event(&mut responder_events, is_connected).await;
     }
 }
@@ -3,8 +3,25 @@
 //! This module provides TLS connection management for secure communication
 //! between paired devices.
 
+pub mod backoff;
+pub mod correlation;
 pub mod events;
+mod flap;
+mod idle;
+mod keepalive;
 pub mod manager;
+pub mod packet_tap;
+pub mod state;
+pub mod subnet;
+pub mod teardown;
+pub mod traffic;
 
+pub use backoff::ReconnectBackoff;
+pub use correlation::{reply_to, PendingRequests, REPLY_TO_FIELD, REQUEST_ID_FIELD};
 pub use events::ConnectionEvent;
-pub use manager::{ConnectionConfig, ConnectionManager};
+pub use manager::{ConnectionConfig, ConnectionManager, CONNECT_REQUEST, IDENTITY_REQUEST};
+pub use packet_tap::{PacketTap, PacketTapConfig, TapDirection};
+pub use state::{ConnectionStateMachine, LinkState};
+pub use subnet::{LocalSubnet, SubnetGuard};
+pub use teardown::{CancelOutcome, DependentTask, TeardownSignal};
+pub use traffic::{TrafficCounter, TrafficStats};
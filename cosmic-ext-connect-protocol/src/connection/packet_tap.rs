@@ -0,0 +1,532 @@
+//! Raw Packet Tap
+//!
+//! Logs every packet a [`ConnectionManager`](super::ConnectionManager) sends or
+//! receives to a file, for diagnosing interoperability problems with other
+//! clients. Each line is a JSON object with the time, direction, device and
+//! packet type, plus the packet body.
+//!
+//! The tap is off by default and only writes anything when the crate is built
+//! with the `packet_tap` feature *and* [`PacketTapConfig::enabled`] is set.
+//! Bodies of packet types listed in [`PacketTapConfig::redacted_types`]
+//! (clipboard, contacts and SMS by default) are replaced with a placeholder,
+//! and certificate data is stripped from every body. The file is rotated once
+//! it grows past [`PacketTapConfig::max_file_size`].
+//!
+//! The log goes to `$XDG_STATE_HOME/cosmic-ext-connect/packets.log` by
+//! default and is only readable by the user; a symlink in its place is
+//! refused. Lines are handed to a writer thread, so recording never blocks
+//! the connection; if the writer falls behind, lines are dropped.
+
+use crate::Packet;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Placeholder written instead of redacted content
+pub const REDACTED: &str = "[redacted]";
+
+/// Packet types whose bodies are redacted by default (prefixes)
+pub const DEFAULT_REDACTED_TYPES: &[&str] =
+    &["cconnect.clipboard", "cconnect.contacts", "cconnect.sms"];
+
+/// Packet tap configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PacketTapConfig {
+    /// Log packets (requires the `packet_tap` feature)
+    pub enabled: bool,
+    /// Log file; rotated files get a `.1`, `.2`, ... suffix
+    pub path: PathBuf,
+    /// Size in bytes after which the file is rotated
+    pub max_file_size: u64,
+    /// Number of rotated files kept besides the current one
+    pub max_files: usize,
+    /// Packet types whose bodies are not logged; a type also covers its
+    /// subtypes (`cconnect.sms` covers `cconnect.sms.messages`)
+    pub redacted_types: Vec<String>,
+    /// Strip certificate data from bodies
+    pub redact_certificates: bool,
+}
+
+/// Default log file, under the user's state directory
+fn default_path() -> PathBuf {
+    dirs::state_dir()
+        .or_else(dirs::runtime_dir)
+        .or_else(dirs::data_local_dir)
+        .unwrap_or_default()
+        .join("cosmic-ext-connect")
+        .join("packets.log")
+}
+
+impl Default for PacketTapConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_path(),
+            max_file_size: 5 * 1024 * 1024,
+            max_files: 3,
+            redacted_types: DEFAULT_REDACTED_TYPES
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+            redact_certificates: true,
+        }
+    }
+}
+
+impl PacketTapConfig {
+    /// Whether the body of `packet_type` is redacted
+    pub fn is_redacted_type(&self, packet_type: &str) -> bool {
+        self.redacted_types.iter().any(|redacted| {
+            packet_type == redacted
+                || packet_type
+                    .strip_prefix(redacted.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+
+    /// Body of `packet` as it is written to the log
+    pub fn redact(&self, packet: &Packet) -> Value {
+        if self.is_redacted_type(&packet.packet_type) {
+            return Value::String(REDACTED.to_string());
+        }
+        let mut body = packet.body.clone();
+        if self.redact_certificates {
+            strip_certificates(&mut body);
+        }
+        body
+    }
+}
+
+/// Replace certificate fields and PEM blocks anywhere in `value`
+fn strip_certificates(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                if key.to_ascii_lowercase().contains("certificate") {
+                    *field = Value::String(REDACTED.to_string());
+                } else {
+                    strip_certificates(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_certificates),
+        Value::String(text) if text.contains("-----BEGIN") => {
+            *text = REDACTED.to_string();
+        }
+        _ => {}
+    }
+}
+
+/// Which way a packet went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapDirection {
+    /// Received from the device
+    Inbound,
+    /// Sent to the device
+    Outbound,
+}
+
+impl TapDirection {
+    #[cfg_attr(not(feature = "packet_tap"), allow(dead_code))]
+    fn as_str(self) -> &'static str {
+        match self {
+            TapDirection::Inbound => "in",
+            TapDirection::Outbound => "out",
+        }
+    }
+}
+
+#[cfg(feature = "packet_tap")]
+mod writer {
+    use super::PacketTapConfig;
+    use std::fs::{DirBuilder, File, OpenOptions};
+    use std::io::Write;
+    use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+    use std::path::PathBuf;
+    use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+
+    /// Lines waiting for the writer before new ones are dropped
+    const QUEUE_LEN: usize = 1024;
+
+    /// Work for the writer thread
+    enum TapMessage {
+        Line(String),
+        #[cfg(test)]
+        Flush(mpsc::Sender<()>),
+    }
+
+    /// Queue feeding a [`TapWriter`] on its own thread
+    pub(super) struct TapQueue {
+        sender: SyncSender<TapMessage>,
+    }
+
+    impl TapQueue {
+        /// Start the writer thread
+        pub(super) fn spawn(config: PacketTapConfig) -> std::io::Result<Self> {
+            let (sender, receiver) = mpsc::sync_channel(QUEUE_LEN);
+            let writer = TapWriter::new(config);
+            std::thread::Builder::new()
+                .name("packet-tap".to_string())
+                .spawn(move || writer.run(receiver))?;
+            Ok(Self { sender })
+        }
+
+        /// Queue a line without waiting for the writer
+        pub(super) fn push(&self, line: String) {
+            match self.sender.try_send(TapMessage::Line(line)) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    tracing::debug!("Packet tap writer is behind, dropping a line");
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    tracing::debug!("Packet tap writer has stopped");
+                }
+            }
+        }
+
+        /// Wait until every queued line is written
+        #[cfg(test)]
+        pub(super) fn flush(&self) {
+            let (done, wait) = mpsc::channel();
+            if self.sender.send(TapMessage::Flush(done)).is_ok() {
+                let _ = wait.recv();
+            }
+        }
+    }
+
+    /// Appends lines to the log file, rotating it when it grows too large
+    struct TapWriter {
+        config: PacketTapConfig,
+        file: Option<File>,
+        written: u64,
+    }
+
+    impl TapWriter {
+        fn new(config: PacketTapConfig) -> Self {
+            Self {
+                config,
+                file: None,
+                written: 0,
+            }
+        }
+
+        /// Write queued lines until the queue is dropped
+        fn run(mut self, receiver: Receiver<TapMessage>) {
+            for message in receiver {
+                match message {
+                    TapMessage::Line(line) => {
+                        if let Err(e) = self.write_line(&line) {
+                            tracing::warn!("Failed to write packet tap log: {}", e);
+                        }
+                    }
+                    #[cfg(test)]
+                    TapMessage::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        }
+
+        fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+            let len = line.len() as u64 + 1;
+            if self.file.is_none() {
+                self.open()?;
+            }
+            if self.written > 0 && self.written + len > self.config.max_file_size {
+                self.rotate()?;
+                self.open()?;
+            }
+            let file = self.file.as_mut().expect("tap file opened above");
+            writeln!(file, "{}", line)?;
+            self.written += len;
+            Ok(())
+        }
+
+        fn open(&mut self) -> std::io::Result<()> {
+            if let Some(parent) = self.config.path.parent() {
+                DirBuilder::new()
+                    .recursive(true)
+                    .mode(0o700)
+                    .create(parent)?;
+            }
+            // Packets carry private data; never write through a planted symlink
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .mode(0o600)
+                .custom_flags(nix::libc::O_NOFOLLOW)
+                .open(&self.config.path)?;
+            self.written = file.metadata()?.len();
+            self.file = Some(file);
+            Ok(())
+        }
+
+        fn rotated(&self, index: usize) -> PathBuf {
+            let mut name = self.config.path.clone().into_os_string();
+            name.push(format!(".{}", index));
+            PathBuf::from(name)
+        }
+
+        fn rotate(&mut self) -> std::io::Result<()> {
+            self.file = None;
+            if self.config.max_files == 0 {
+                std::fs::remove_file(&self.config.path)?;
+            } else {
+                // Shift path.N-1 -> path.N, dropping the oldest
+                for index in (1..self.config.max_files).rev() {
+                    let from = self.rotated(index);
+                    if from.exists() {
+                        std::fs::rename(&from, self.rotated(index + 1))?;
+                    }
+                }
+                std::fs::rename(&self.config.path, self.rotated(1))?;
+            }
+            self.written = 0;
+            Ok(())
+        }
+    }
+}
+
+/// Writes sent and received packets to the tap log, if enabled
+pub struct PacketTap {
+    #[cfg(feature = "packet_tap")]
+    writer: Option<(writer::TapQueue, PacketTapConfig)>,
+}
+
+impl PacketTap {
+    /// Create a tap from its configuration
+    pub fn new(config: &PacketTapConfig) -> Self {
+        #[cfg(feature = "packet_tap")]
+        {
+            let writer = config
+                .enabled
+                .then(|| writer::TapQueue::spawn(config.clone()));
+            Self {
+                writer: match writer {
+                    Some(Ok(queue)) => Some((queue, config.clone())),
+                    Some(Err(e)) => {
+                        tracing::warn!("Failed to start packet tap writer: {}", e);
+                        None
+                    }
+                    None => None,
+                },
+            }
+        }
+        #[cfg(not(feature = "packet_tap"))]
+        {
+            if config.enabled {
+                tracing::warn!("Packet tap enabled, but built without the packet_tap feature");
+            }
+            Self {}
+        }
+    }
+
+    /// A tap that logs nothing
+    pub fn disabled() -> Self {
+        Self::new(&PacketTapConfig::default())
+    }
+
+    /// Whether packets are being logged
+    pub fn is_enabled(&self) -> bool {
+        #[cfg(feature = "packet_tap")]
+        {
+            self.writer.is_some()
+        }
+        #[cfg(not(feature = "packet_tap"))]
+        {
+            false
+        }
+    }
+
+    /// Log a packet sent to or received from `device_id`
+    ///
+    /// Write failures are logged and otherwise ignored; the tap never affects
+    /// the connection.
+    pub fn record(&self, direction: TapDirection, device_id: &str, packet: &Packet) {
+        #[cfg(feature = "packet_tap")]
+        if let Some((queue, config)) = &self.writer {
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or_default();
+            let line = serde_json::json!({
+                "timestamp": timestamp,
+                "direction": direction.as_str(),
+                "device_id": device_id,
+                "type": packet.packet_type,
+                "body": config.redact(packet),
+            })
+            .to_string();
+            queue.push(line);
+        }
+        #[cfg(not(feature = "packet_tap"))]
+        let _ = (direction, device_id, packet);
+    }
+
+    /// Wait until every recorded packet is written
+    #[cfg(all(test, feature = "packet_tap"))]
+    fn flush(&self) {
+        if let Some((queue, _)) = &self.writer {
+            queue.flush();
+        }
+    }
+}
+
+impl std::fmt::Debug for PacketTap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PacketTap")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sensitive_bodies_redacted() {
+        let config = PacketTapConfig::default();
+
+        let sms = Packet::new("cconnect.sms.messages", json!({"messages": ["hi"]}));
+        assert_eq!(config.redact(&sms), json!(REDACTED));
+        let clipboard = Packet::new("cconnect.clipboard", json!({"content": "secret"}));
+        assert_eq!(config.redact(&clipboard), json!(REDACTED));
+        // Prefix match stops at a type boundary
+        assert!(!config.is_redacted_type("cconnect.smsx"));
+
+        let identity = Packet::new(
+            "cconnect.identity",
+            json!({
+                "deviceName": "Phone",
+                "certificate": "MIIB...",
+                "extra": ["-----BEGIN CERTIFICATE-----\nabc"],
+            }),
+        );
+        let body = config.redact(&identity);
+        assert_eq!(body["deviceName"], "Phone");
+        assert_eq!(body["certificate"], REDACTED);
+        assert_eq!(body["extra"][0], REDACTED);
+    }
+
+    #[test]
+    fn test_default_path_not_in_temp_dir() {
+        let config = PacketTapConfig::default();
+        assert!(!config.path.starts_with(std::env::temp_dir()));
+        assert!(config.path.ends_with("cosmic-ext-connect/packets.log"));
+    }
+
+    #[test]
+    fn test_disabled_tap_is_noop() {
+        let path =
+            std::env::temp_dir().join(format!("cconnect-tap-disabled-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let tap = PacketTap::new(&PacketTapConfig {
+            path: path.clone(),
+            ..Default::default()
+        });
+
+        tap.record(
+            TapDirection::Inbound,
+            "phone",
+            &Packet::new("cconnect.ping", json!({})),
+        );
+        assert!(!tap.is_enabled());
+        assert!(!path.exists());
+    }
+
+    #[cfg(feature = "packet_tap")]
+    #[test]
+    fn test_enabled_tap_records_and_rotates() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("packets.log");
+        let tap = PacketTap::new(&PacketTapConfig {
+            enabled: true,
+            path: path.clone(),
+            max_file_size: 400,
+            max_files: 1,
+            ..Default::default()
+        });
+        assert!(tap.is_enabled());
+
+        tap.record(
+            TapDirection::Outbound,
+            "phone",
+            &Packet::new("cconnect.ping", json!({})),
+        );
+        tap.record(
+            TapDirection::Inbound,
+            "phone",
+            &Packet::new("cconnect.clipboard", json!({"content": "secret"})),
+        );
+        tap.flush();
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<Value> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines[0]["type"], "cconnect.ping");
+        assert_eq!(lines[0]["direction"], "out");
+        assert_eq!(lines[1]["type"], "cconnect.clipboard");
+        assert_eq!(lines[1]["body"], REDACTED);
+        assert!(!log.contains("secret"));
+
+        // Past the size limit the file is rotated
+        for _ in 0..5 {
+            tap.record(
+                TapDirection::Inbound,
+                "phone",
+                &Packet::new("cconnect.ping", json!({})),
+            );
+        }
+        tap.flush();
+        assert!(dir.path().join("packets.log.1").exists());
+        assert!(!dir.path().join("packets.log.2").exists());
+        assert!(std::fs::metadata(&path).unwrap().len() <= 400);
+    }
+
+    #[cfg(feature = "packet_tap")]
+    #[test]
+    fn test_log_private_and_symlink_refused() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("state").join("packets.log");
+        let tap = PacketTap::new(&PacketTapConfig {
+            enabled: true,
+            path: path.clone(),
+            ..Default::default()
+        });
+        tap.record(
+            TapDirection::Outbound,
+            "phone",
+            &Packet::new("cconnect.ping", json!({})),
+        );
+        tap.flush();
+        let mode =
+            |path: &std::path::Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(path.parent().unwrap()), 0o700);
+
+        // A symlink planted at the log path is not followed
+        let target = dir.path().join("target");
+        std::fs::write(&target, "").unwrap();
+        let linked = dir.path().join("linked.log");
+        std::os::unix::fs::symlink(&target, &linked).unwrap();
+        let tap = PacketTap::new(&PacketTapConfig {
+            enabled: true,
+            path: linked,
+            ..Default::default()
+        });
+        tap.record(
+            TapDirection::Outbound,
+            "phone",
+            &Packet::new("cconnect.ping", json!({})),
+        );
+        tap.flush();
+        assert!(std::fs::read_to_string(&target).unwrap().is_empty());
+    }
+}
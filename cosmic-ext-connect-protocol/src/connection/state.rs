@@ -0,0 +1,286 @@
+//! Connection State Machine
+//!
+//! Tracks each device's link through explicit, validated transitions:
+//!
+//! ```text
+//! Disconnected -> Connecting -> Connected -> Lost -> Reconnecting -> Connected
+//! ```
+//!
+//! A failed attempt falls back to where it started (`Connecting` to
+//! `Disconnected`, `Reconnecting` to `Lost`), a clean close goes straight to
+//! `Disconnected`, and giving up on a lost device moves it to `Disconnected`.
+//! Anything else, such as starting a second attempt while one is in flight,
+//! is rejected and leaves the state untouched. Every applied transition is
+//! reported as [`ConnectionEvent::StateChanged`].
+
+use super::events::ConnectionEvent;
+use crate::{ConnectionState, ProtocolError, Result};
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// Link state of a single device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum LinkState {
+    /// No connection and none wanted
+    #[default]
+    Disconnected,
+    /// First connection attempt in progress
+    Connecting,
+    /// Connected and exchanging packets
+    Connected,
+    /// Connection dropped unexpectedly
+    Lost,
+    /// Attempting to restore a lost connection
+    Reconnecting,
+}
+
+impl LinkState {
+    /// Whether moving from this state to `next` is allowed
+    pub fn can_transition_to(self, next: LinkState) -> bool {
+        use LinkState::*;
+        matches!(
+            (self, next),
+            (Disconnected, Connecting)
+                | (Connecting, Connected | Disconnected)
+                | (Connected, Lost | Disconnected)
+                | (Lost, Reconnecting | Disconnected)
+                | (Reconnecting, Connected | Lost | Disconnected)
+        )
+    }
+
+    /// Whether an attempt to connect is in flight
+    pub fn is_attempting(self) -> bool {
+        matches!(self, LinkState::Connecting | LinkState::Reconnecting)
+    }
+
+    /// The coarser state stored on [`crate::Device`]
+    pub fn connection_state(self) -> ConnectionState {
+        match self {
+            LinkState::Disconnected | LinkState::Lost => ConnectionState::Disconnected,
+            LinkState::Connecting | LinkState::Reconnecting => ConnectionState::Connecting,
+            LinkState::Connected => ConnectionState::Connected,
+        }
+    }
+}
+
+/// Per-device connection state machine
+#[derive(Debug, Default)]
+pub struct ConnectionStateMachine {
+    /// Devices not in `Disconnected`
+    states: HashMap<String, LinkState>,
+    /// Where transition events are sent
+    event_tx: Option<mpsc::UnboundedSender<ConnectionEvent>>,
+}
+
+impl ConnectionStateMachine {
+    /// Create a state machine that does not emit events
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a state machine that reports transitions on `event_tx`
+    pub fn with_events(event_tx: mpsc::UnboundedSender<ConnectionEvent>) -> Self {
+        Self {
+            states: HashMap::new(),
+            event_tx: Some(event_tx),
+        }
+    }
+
+    /// Current state of a device
+    pub fn state(&self, device_id: &str) -> LinkState {
+        self.states.get(device_id).copied().unwrap_or_default()
+    }
+
+    /// Move a device to `next`, returning the state it left
+    ///
+    /// Illegal transitions return [`ProtocolError::InvalidState`] and change
+    /// nothing.
+    pub fn transition(&mut self, device_id: &str, next: LinkState) -> Result<LinkState> {
+        let from = self.state(device_id);
+        if !from.can_transition_to(next) {
+            return Err(ProtocolError::InvalidState(format!(
+                "Illegal connection transition for {}: {:?} -> {:?}",
+                device_id, from, next
+            )));
+        }
+
+        if next == LinkState::Disconnected {
+            self.states.remove(device_id);
+        } else {
+            self.states.insert(device_id.to_string(), next);
+        }
+        debug!("Device {} connection: {:?} -> {:?}", device_id, from, next);
+
+        if let Some(event_tx) = &self.event_tx {
+            let _ = event_tx.send(ConnectionEvent::StateChanged {
+                device_id: device_id.to_string(),
+                from,
+                to: next,
+            });
+        }
+        Ok(from)
+    }
+
+    /// Start an outgoing attempt, returning the new state
+    ///
+    /// Fails while the device is connected or another attempt is running.
+    pub fn begin_connect(&mut self, device_id: &str) -> Result<LinkState> {
+        let next = match self.state(device_id) {
+            LinkState::Lost => LinkState::Reconnecting,
+            _ => LinkState::Connecting,
+        };
+        self.transition(device_id, next)?;
+        Ok(next)
+    }
+
+    /// Record that an attempt failed before the link came up
+    pub fn connect_failed(&mut self, device_id: &str) {
+        let next = match self.state(device_id) {
+            LinkState::Connecting => LinkState::Disconnected,
+            LinkState::Reconnecting => LinkState::Lost,
+            _ => return,
+        };
+        let _ = self.transition(device_id, next);
+    }
+
+    /// Record an established link
+    ///
+    /// Incoming connections arrive without an attempt of ours, so this passes
+    /// through the attempt state first. Already connected (socket
+    /// replacement) is a no-op.
+    pub fn record_connected(&mut self, device_id: &str) {
+        let from = self.state(device_id);
+        if from == LinkState::Connected {
+            return;
+        }
+        if !from.is_attempting() {
+            if let Err(e) = self.begin_connect(device_id) {
+                warn!("{}", e);
+                return;
+            }
+        }
+        if let Err(e) = self.transition(device_id, LinkState::Connected) {
+            warn!("{}", e);
+        }
+    }
+
+    /// Record a closed link, `lost` when it was not closed on purpose
+    pub fn record_closed(&mut self, device_id: &str, lost: bool) {
+        let next = match (self.state(device_id), lost) {
+            (LinkState::Disconnected, _) | (LinkState::Lost, true) => return,
+            (LinkState::Connected | LinkState::Reconnecting, true) => LinkState::Lost,
+            (LinkState::Connecting, true) => LinkState::Disconnected,
+            (_, false) => LinkState::Disconnected,
+        };
+        let _ = self.transition(device_id, next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(rx: &mut mpsc::UnboundedReceiver<ConnectionEvent>) -> Vec<(LinkState, LinkState)> {
+        let mut transitions = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let ConnectionEvent::StateChanged { from, to, .. } = event {
+                transitions.push((from, to));
+            }
+        }
+        transitions
+    }
+
+    #[test]
+    fn test_valid_sequence_emits_each_transition() {
+        use LinkState::*;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut machine = ConnectionStateMachine::with_events(tx);
+
+        for next in [
+            Connecting,
+            Connected,
+            Lost,
+            Reconnecting,
+            Lost,
+            Reconnecting,
+            Connected,
+        ] {
+            machine.transition("phone", next).unwrap();
+        }
+        machine.transition("phone", Disconnected).unwrap();
+
+        assert_eq!(
+            drain(&mut rx),
+            vec![
+                (Disconnected, Connecting),
+                (Connecting, Connected),
+                (Connected, Lost),
+                (Lost, Reconnecting),
+                (Reconnecting, Lost),
+                (Lost, Reconnecting),
+                (Reconnecting, Connected),
+                (Connected, Disconnected),
+            ]
+        );
+        assert_eq!(machine.state("phone"), Disconnected);
+    }
+
+    #[test]
+    fn test_illegal_transitions_are_rejected() {
+        use LinkState::*;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut machine = ConnectionStateMachine::with_events(tx);
+
+        assert!(machine.transition("phone", Connected).is_err());
+        assert!(machine.transition("phone", Reconnecting).is_err());
+
+        machine.begin_connect("phone").unwrap();
+        // Connecting twice
+        assert!(matches!(
+            machine.begin_connect("phone"),
+            Err(ProtocolError::InvalidState(_))
+        ));
+        machine.transition("phone", Connected).unwrap();
+        // Connected -> Connecting without going through Lost
+        assert!(machine.transition("phone", Connecting).is_err());
+        assert!(machine.begin_connect("phone").is_err());
+        assert_eq!(machine.state("phone"), Connected);
+
+        // Only the two applied transitions were reported
+        assert_eq!(
+            drain(&mut rx),
+            vec![(Disconnected, Connecting), (Connecting, Connected)]
+        );
+    }
+
+    #[test]
+    fn test_helpers_follow_the_graph() {
+        use LinkState::*;
+        let mut machine = ConnectionStateMachine::new();
+
+        // Failed first attempt returns to Disconnected
+        assert_eq!(machine.begin_connect("phone").unwrap(), Connecting);
+        machine.connect_failed("phone");
+        assert_eq!(machine.state("phone"), Disconnected);
+
+        // Incoming connection passes through Connecting
+        machine.record_connected("phone");
+        assert_eq!(machine.state("phone"), Connected);
+        machine.record_connected("phone");
+        assert_eq!(machine.state("phone"), Connected);
+
+        // Dropped link is lost; a failed retry stays lost
+        machine.record_closed("phone", true);
+        assert_eq!(machine.state("phone"), Lost);
+        assert_eq!(machine.begin_connect("phone").unwrap(), Reconnecting);
+        machine.connect_failed("phone");
+        assert_eq!(machine.state("phone"), Lost);
+
+        // Clean close from anywhere ends in Disconnected
+        machine.record_closed("phone", false);
+        assert_eq!(machine.state("phone"), Disconnected);
+        assert_eq!(Lost.connection_state(), ConnectionState::Disconnected);
+        assert_eq!(Reconnecting.connection_state(), ConnectionState::Connecting);
+    }
+}
@@ -0,0 +1,176 @@
+//! Link-Local Only Connections
+//!
+//! With [`ConnectionConfig::link_local_only`](super::ConnectionConfig::link_local_only)
+//! set, the connection manager only talks to peers on a subnet one of this
+//! machine's interfaces is directly attached to. Connections to or from
+//! anything reached through a router or a VPN tunnel with a different subnet
+//! are refused, both when connecting out and when accepting, and reported as
+//! [`ConnectionEvent::ConnectionRejected`](super::ConnectionEvent::ConnectionRejected).
+//!
+//! The local subnets are read from the interfaces at check time, so a new
+//! DHCP lease or a Wi-Fi network change is picked up without a restart.
+
+use crate::{ProtocolError, Result};
+use std::net::{IpAddr, SocketAddrV4, SocketAddrV6};
+use tracing::warn;
+
+/// A subnet attached to a local interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalSubnet {
+    /// Any address within the subnet
+    pub addr: IpAddr,
+    /// Network prefix length in bits
+    pub prefix_len: u8,
+}
+
+impl LocalSubnet {
+    /// Subnet of `addr` with a `prefix_len` bit network part
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Self {
+        Self { addr, prefix_len }
+    }
+
+    /// Whether `ip` lies within this subnet
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, unmap(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = prefix_mask_v4(self.prefix_len);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = prefix_mask_v6(self.prefix_len);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_mask_v4(prefix_len: u8) -> u32 {
+    match prefix_len {
+        0 => 0,
+        len => u32::MAX << (32 - u32::from(len.min(32))),
+    }
+}
+
+fn prefix_mask_v6(prefix_len: u8) -> u128 {
+    match prefix_len {
+        0 => 0,
+        len => u128::MAX << (128 - u32::from(len.min(128))),
+    }
+}
+
+/// Treat IPv4-mapped IPv6 addresses from dual-stack sockets as IPv4
+fn unmap(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        v4 => v4,
+    }
+}
+
+/// Subnets of all local interfaces, loopback included
+///
+/// # Errors
+///
+/// Returns [`ProtocolError::NetworkError`] if the interfaces cannot be read.
+pub fn local_subnets() -> Result<Vec<LocalSubnet>> {
+    let addrs = nix::ifaddrs::getifaddrs()
+        .map_err(|e| ProtocolError::NetworkError(format!("Failed to list interfaces: {}", e)))?;
+
+    let mut subnets = Vec::new();
+    for ifaddr in addrs {
+        let (Some(address), Some(netmask)) = (ifaddr.address, ifaddr.netmask) else {
+            continue;
+        };
+        if let (Some(addr), Some(mask)) = (address.as_sockaddr_in(), netmask.as_sockaddr_in()) {
+            let mask = u32::from(*SocketAddrV4::from(*mask).ip());
+            subnets.push(LocalSubnet::new(
+                IpAddr::V4(*SocketAddrV4::from(*addr).ip()),
+                mask.count_ones() as u8,
+            ));
+        } else if let (Some(addr), Some(mask)) =
+            (address.as_sockaddr_in6(), netmask.as_sockaddr_in6())
+        {
+            subnets.push(LocalSubnet::new(
+                IpAddr::V6(*SocketAddrV6::from(*addr).ip()),
+                u128::from(*SocketAddrV6::from(*mask).ip()).count_ones() as u8,
+            ));
+        }
+    }
+    Ok(subnets)
+}
+
+/// Decides which peers are close enough to talk to
+#[derive(Debug, Clone, Default)]
+pub struct SubnetGuard {
+    /// Subnets to use instead of the interfaces'
+    fixed: Option<Vec<LocalSubnet>>,
+}
+
+impl SubnetGuard {
+    /// Check peers against the subnets of the local interfaces
+    pub fn system() -> Self {
+        Self::default()
+    }
+
+    /// Check peers against `subnets` only
+    pub fn fixed(subnets: Vec<LocalSubnet>) -> Self {
+        Self {
+            fixed: Some(subnets),
+        }
+    }
+
+    /// Subnets peers must be on
+    ///
+    /// If the interfaces cannot be read no subnet is local, so every peer is
+    /// refused rather than let through unchecked.
+    pub fn subnets(&self) -> Vec<LocalSubnet> {
+        match &self.fixed {
+            Some(subnets) => subnets.clone(),
+            None => local_subnets().unwrap_or_else(|e| {
+                warn!("Refusing all peers in link-local mode: {}", e);
+                Vec::new()
+            }),
+        }
+    }
+
+    /// Whether `ip` is on a local subnet
+    pub fn permits(&self, ip: IpAddr) -> bool {
+        self.subnets().iter().any(|subnet| subnet.contains(ip))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn test_subnet_contains() {
+        let lan = LocalSubnet::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)), 24);
+        assert!(lan.contains(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 77))));
+        assert!(!lan.contains(IpAddr::V4(Ipv4Addr::new(192, 168, 2, 77))));
+        assert!(!lan.contains(IpAddr::V4(Ipv4Addr::new(10, 8, 0, 1))));
+
+        // Dual-stack sockets report IPv4 peers as mapped IPv6 addresses
+        let mapped = Ipv4Addr::new(192, 168, 1, 77).to_ipv6_mapped();
+        assert!(lan.contains(IpAddr::V6(mapped)));
+
+        let link_local = LocalSubnet::new("fe80::1".parse().unwrap(), 64);
+        assert!(link_local.contains("fe80::abcd".parse().unwrap()));
+        assert!(!link_local.contains(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+
+        let everything = LocalSubnet::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+        assert!(everything.contains(IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8))));
+    }
+
+    #[test]
+    fn test_fixed_guard() {
+        let guard = SubnetGuard::fixed(vec![LocalSubnet::new(
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            8,
+        )]);
+        assert!(guard.permits(IpAddr::V4(Ipv4Addr::new(10, 20, 30, 40))));
+        assert!(!guard.permits(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        assert!(!SubnetGuard::fixed(Vec::new()).permits(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+    }
+}
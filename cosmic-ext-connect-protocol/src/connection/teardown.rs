@@ -0,0 +1,246 @@
+//! Connection Teardown
+//!
+//! Tasks that use a connection (the keepalive heartbeat, payload transfers,
+//! plugin packet dispatch) must stop before the transport they depend on is
+//! closed, otherwise they race the close and fail with errors about a socket
+//! that no longer exists. [`DependentTasks`] tracks such tasks and stops them
+//! in a fixed order when the connection goes away.
+//!
+//! Each task receives a [`TeardownSignal`] and is expected to return once it
+//! fires. Tasks that do not finish within the grace period are aborted and
+//! detached rather than awaited forever.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn};
+
+/// Kind of task that depends on a connection
+///
+/// Teardown stops kinds in declaration order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DependentTask {
+    /// Keepalive pings on the connection
+    Heartbeat,
+    /// A payload transfer with the device
+    Transfer,
+    /// Delivery of received packets to a plugin
+    PluginDispatch,
+}
+
+impl DependentTask {
+    /// All kinds, in teardown order
+    pub const TEARDOWN_ORDER: [DependentTask; 3] = [
+        DependentTask::Heartbeat,
+        DependentTask::Transfer,
+        DependentTask::PluginDispatch,
+    ];
+}
+
+/// How a dependent task ended during teardown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelOutcome {
+    /// The task returned after being signalled
+    Stopped,
+    /// The task ignored the signal past the grace period and was aborted
+    Aborted,
+    /// The task panicked
+    Panicked,
+}
+
+/// Signal telling a dependent task that its connection is closing
+#[derive(Debug, Clone)]
+pub struct TeardownSignal(watch::Receiver<bool>);
+
+impl TeardownSignal {
+    /// Whether teardown has started
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Wait until teardown starts
+    pub async fn cancelled(&mut self) {
+        // An error means the tracker was dropped, which also ends the task's use
+        let _ = self.0.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+/// A task that was stopped during teardown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CancelledTask {
+    /// Kind of task
+    pub kind: DependentTask,
+    /// Name given when the task was spawned
+    pub name: String,
+    /// How it ended
+    pub outcome: CancelOutcome,
+}
+
+struct Tracked {
+    kind: DependentTask,
+    name: String,
+    handle: JoinHandle<()>,
+}
+
+/// Tasks tied to one connection, stopped in order on teardown
+#[derive(Default)]
+pub struct DependentTasks {
+    signals: HashMap<DependentTask, watch::Sender<bool>>,
+    tasks: Vec<Tracked>,
+}
+
+impl DependentTasks {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Signal for tasks of `kind`
+    pub fn signal(&mut self, kind: DependentTask) -> TeardownSignal {
+        let sender = self
+            .signals
+            .entry(kind)
+            .or_insert_with(|| watch::channel(false).0);
+        TeardownSignal(sender.subscribe())
+    }
+
+    /// Spawn a task that is stopped when the connection is torn down
+    pub fn spawn<F, Fut>(&mut self, kind: DependentTask, name: impl Into<String>, task: F)
+    where
+        F: FnOnce(TeardownSignal) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.retain(|tracked| !tracked.handle.is_finished());
+        let signal = self.signal(kind);
+        self.tasks.push(Tracked {
+            kind,
+            name: name.into(),
+            handle: tokio::spawn(task(signal)),
+        });
+    }
+
+    /// Number of tasks still running
+    pub fn len(&self) -> usize {
+        self.tasks
+            .iter()
+            .filter(|tracked| !tracked.handle.is_finished())
+            .count()
+    }
+
+    /// Whether no tasks are running
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Stop every task, one kind at a time in [`DependentTask::TEARDOWN_ORDER`]
+    ///
+    /// Each task gets `grace` to return after being signalled before it is
+    /// aborted. Tasks that had already finished are not reported.
+    pub async fn teardown(&mut self, grace: Duration) -> Vec<CancelledTask> {
+        let mut cancelled = Vec::new();
+        let mut tasks = std::mem::take(&mut self.tasks);
+        tasks.retain(|tracked| !tracked.handle.is_finished());
+
+        for kind in DependentTask::TEARDOWN_ORDER {
+            if let Some(sender) = self.signals.get(&kind) {
+                sender.send_replace(true);
+            }
+
+            let (current, rest): (Vec<_>, Vec<_>) =
+                tasks.into_iter().partition(|tracked| tracked.kind == kind);
+            tasks = rest;
+
+            for mut tracked in current {
+                let outcome = match tokio::time::timeout(grace, &mut tracked.handle).await {
+                    Ok(Ok(())) => CancelOutcome::Stopped,
+                    Ok(Err(e)) if e.is_panic() => {
+                        error!("{:?} task '{}' panicked: {}", kind, tracked.name, e);
+                        CancelOutcome::Panicked
+                    }
+                    Ok(Err(_)) => CancelOutcome::Aborted,
+                    Err(_) => {
+                        warn!(
+                            "{:?} task '{}' did not stop within {:?}, aborting",
+                            kind, tracked.name, grace
+                        );
+                        tracked.handle.abort();
+                        CancelOutcome::Aborted
+                    }
+                };
+                debug!("{:?} task '{}' ended: {:?}", kind, tracked.name, outcome);
+                cancelled.push(CancelledTask {
+                    kind,
+                    name: tracked.name,
+                    outcome,
+                });
+            }
+        }
+
+        cancelled
+    }
+}
+
+impl Drop for DependentTasks {
+    fn drop(&mut self) {
+        // Never leave tasks running against a connection that is gone
+        for tracked in &self.tasks {
+            tracked.handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    const GRACE: Duration = Duration::from_millis(200);
+
+    #[tokio::test]
+    async fn test_teardown_stops_kinds_in_order() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = DependentTasks::new();
+
+        // Spawned out of order on purpose
+        for kind in [
+            DependentTask::PluginDispatch,
+            DependentTask::Transfer,
+            DependentTask::Heartbeat,
+        ] {
+            let order = order.clone();
+            tasks.spawn(kind, format!("{:?}", kind), move |mut signal| async move {
+                signal.cancelled().await;
+                order.lock().unwrap().push(kind);
+            });
+        }
+        assert_eq!(tasks.len(), 3);
+
+        let cancelled = tasks.teardown(GRACE).await;
+        assert_eq!(
+            *order.lock().unwrap(),
+            DependentTask::TEARDOWN_ORDER.to_vec()
+        );
+        assert!(cancelled
+            .iter()
+            .all(|task| task.outcome == CancelOutcome::Stopped));
+        assert!(tasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_teardown_aborts_tasks_that_ignore_signal() {
+        let mut tasks = DependentTasks::new();
+        tasks.spawn(DependentTask::Transfer, "stuck", |_signal| {
+            std::future::pending::<()>()
+        });
+        tasks.spawn(DependentTask::Transfer, "panics", |mut signal| async move {
+            signal.cancelled().await;
+            panic!("transfer failed");
+        });
+
+        let cancelled = tasks.teardown(GRACE).await;
+        assert_eq!(cancelled[0].outcome, CancelOutcome::Aborted);
+        assert_eq!(cancelled[1].outcome, CancelOutcome::Panicked);
+    }
+}
@@ -0,0 +1,165 @@
+//! Per-Connection Traffic Counters
+//!
+//! Cumulative byte counts for each connected device, split into control
+//! traffic (protocol packets over the TLS link) and payload traffic (file
+//! transfers). Counters are updated from the connection and payload read/write
+//! loops and can be shared with other tasks through a cloned [`TrafficCounter`].
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Snapshot of the bytes exchanged with a device
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrafficStats {
+    /// Protocol packet bytes sent
+    pub control_sent: u64,
+    /// Protocol packet bytes received
+    pub control_received: u64,
+    /// Payload bytes sent
+    pub payload_sent: u64,
+    /// Payload bytes received
+    pub payload_received: u64,
+}
+
+impl TrafficStats {
+    /// Total bytes sent (control + payload)
+    pub fn total_sent(&self) -> u64 {
+        self.control_sent.saturating_add(self.payload_sent)
+    }
+
+    /// Total bytes received (control + payload)
+    pub fn total_received(&self) -> u64 {
+        self.control_received.saturating_add(self.payload_received)
+    }
+
+    /// Total bytes in both directions
+    pub fn total(&self) -> u64 {
+        self.total_sent().saturating_add(self.total_received())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    control_sent: AtomicU64,
+    control_received: AtomicU64,
+    payload_sent: AtomicU64,
+    payload_received: AtomicU64,
+}
+
+/// Shared, lock-free byte counter for one device
+///
+/// Cloning yields a handle to the same counters.
+#[derive(Debug, Clone, Default)]
+pub struct TrafficCounter {
+    inner: Arc<Counters>,
+}
+
+impl TrafficCounter {
+    /// Create a zeroed counter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record protocol packet bytes sent
+    pub fn record_control_sent(&self, bytes: u64) {
+        self.inner.control_sent.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Record protocol packet bytes received
+    pub fn record_control_received(&self, bytes: u64) {
+        self.inner
+            .control_received
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Record payload bytes sent
+    pub fn record_payload_sent(&self, bytes: u64) {
+        self.inner.payload_sent.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Record payload bytes received
+    pub fn record_payload_received(&self, bytes: u64) {
+        self.inner
+            .payload_received
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Current totals
+    pub fn snapshot(&self) -> TrafficStats {
+        TrafficStats {
+            control_sent: self.inner.control_sent.load(Ordering::Relaxed),
+            control_received: self.inner.control_received.load(Ordering::Relaxed),
+            payload_sent: self.inner.payload_sent.load(Ordering::Relaxed),
+            payload_received: self.inner.payload_received.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Zero all counters, returning the totals before the reset
+    pub fn reset(&self) -> TrafficStats {
+        TrafficStats {
+            control_sent: self.inner.control_sent.swap(0, Ordering::Relaxed),
+            control_received: self.inner.control_received.swap(0, Ordering::Relaxed),
+            payload_sent: self.inner.payload_sent.swap(0, Ordering::Relaxed),
+            payload_received: self.inner.payload_received.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+/// Serialized size of a packet on the wire
+pub(crate) fn packet_wire_size(packet: &crate::Packet) -> u64 {
+    packet.to_bytes().map(|b| b.len() as u64).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters_accumulate() {
+        let counter = TrafficCounter::new();
+        counter.record_control_sent(100);
+        counter.record_control_sent(50);
+        counter.record_control_received(20);
+        counter.record_payload_sent(4096);
+        counter.record_payload_received(1024);
+
+        let stats = counter.snapshot();
+        assert_eq!(stats.control_sent, 150);
+        assert_eq!(stats.control_received, 20);
+        assert_eq!(stats.payload_sent, 4096);
+        assert_eq!(stats.payload_received, 1024);
+        assert_eq!(stats.total_sent(), 4246);
+        assert_eq!(stats.total_received(), 1044);
+        assert_eq!(stats.total(), 5290);
+    }
+
+    #[test]
+    fn test_clones_share_counters() {
+        let counter = TrafficCounter::new();
+        let handle = counter.clone();
+        handle.record_payload_received(512);
+
+        assert_eq!(counter.snapshot().payload_received, 512);
+    }
+
+    #[test]
+    fn test_reset_returns_previous_totals() {
+        let counter = TrafficCounter::new();
+        counter.record_control_sent(10);
+        counter.record_payload_received(20);
+
+        let before = counter.reset();
+        assert_eq!(before.control_sent, 10);
+        assert_eq!(before.payload_received, 20);
+        assert_eq!(counter.snapshot(), TrafficStats::default());
+    }
+
+    #[test]
+    fn test_packet_wire_size_matches_serialization() {
+        let packet = crate::Packet::new("cconnect.ping", serde_json::json!({}));
+        let expected = packet.to_bytes().unwrap().len() as u64;
+
+        assert_eq!(packet_wire_size(&packet), expected);
+    }
+}
@@ -17,15 +17,73 @@
 //!
 //! ## Persistence
 //!
-//! Device information is persisted to disk to remember paired devices
-//! across application restarts.
+//! Device information is persisted to remember paired devices across
+//! application restarts. [`DeviceManager::new`] keeps the registry in a JSON
+//! file; [`DeviceManager::with_storage`] uses any [`Storage`] backend instead.
+//!
+//! ## Activity
+//!
+//! Beyond connected/disconnected, [`Device::activity_level`] tells whether a
+//! connected device is in use right now. The connection manager records every
+//! packet sent or received (keepalives excluded); a device is
+//! [`ActivityLevel::Active`] within [`ACTIVE_WITHIN`] of the last one,
+//! [`ActivityLevel::Idle`] up to [`IDLE_WITHIN`], and [`ActivityLevel::Stale`]
+//! after that or while disconnected.
+//!
+//! ## Capabilities
+//!
+//! A device advertises the packet types it accepts and sends. Only the overlap
+//! with what this desktop handles is usable: [`Device::negotiated_capabilities`]
+//! intersects both sides into a [`CapabilitySet`]. Because re-advertised
+//! capabilities update the device in place, the set is always computed fresh.
+//!
+//! ## Certificate Pinning
+//!
+//! The certificate fingerprint captured when a device pairs is pinned. The
+//! connection manager checks every later connection from a paired device
+//! with [`DeviceManager::check_certificate`] and refuses one presenting a
+//! different certificate instead of trusting it, which would let anyone
+//! knowing the device ID impersonate it. After a known reinstall,
+//! [`Device::reset_trust`] drops the pin so the next connection pins the new
+//! certificate. Unpaired devices are not checked; they have to pair first.
+//!
+//! ## Forgetting Devices
+//!
+//! Unpairing only revokes trust; the device can pair again and find its old
+//! settings. [`DeviceManager::forget_device`] instead wipes everything known
+//! about a device: each registered [`DeviceStateStore`] (pinned certificates,
+//! per-device configuration, plugin data, transfer history) is purged before
+//! the device is removed from the registry.
+//!
+//! ## Identity Migration
+//!
+//! A reinstalled app comes back with a new certificate and therefore a new
+//! device ID. When such a device pairs, [`DeviceManager::offer_migration`]
+//! looks for a paired device of the same name and type (and the same
+//! [`DeviceInfo::persistent_id`] when both sides have one) and records an
+//! offer for the user to confirm. [`DeviceManager::accept_migration`] moves
+//! the old identity's state to the new ID in every [`DeviceStateStore`] and
+//! drops the old device.
+//!
+//! ## Waiting for a Device
+//!
+//! [`DeviceManager::wait_for_connection`] lets automations act when a device
+//! comes online without polling. It follows the connected transitions the
+//! connection manager reports through [`DeviceManager::mark_connected`], and
+//! the returned future does not borrow the manager, so no lock is held while
+//! waiting.
 
+use crate::storage::{FileStorage, Storage, StorageNamespace};
 use crate::{DeviceInfo, PairingStatus, ProtocolError, Result, TransportAddress};
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
-use std::path::PathBuf;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
 use tracing::{debug, info, warn};
 
 /// Device connection state
@@ -57,6 +115,35 @@ impl ConnectionState {
     }
 }
 
+/// A connected device counts as active this long after its last packet
+pub const ACTIVE_WITHIN: Duration = Duration::from_secs(30);
+
+/// A connected device counts as idle, not stale, this long after its last packet
+pub const IDLE_WITHIN: Duration = Duration::from_secs(5 * 60);
+
+/// How recently a device exchanged packets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityLevel {
+    /// Packets within [`ACTIVE_WITHIN`]
+    Active,
+    /// Packets within [`IDLE_WITHIN`]
+    Idle,
+    /// No recent packets, or not connected
+    Stale,
+}
+
+impl ActivityLevel {
+    /// Level name as used over D-Bus
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityLevel::Active => "active",
+            ActivityLevel::Idle => "idle",
+            ActivityLevel::Stale => "stale",
+        }
+    }
+}
+
 /// Complete device state
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Device {
@@ -91,6 +178,10 @@ pub struct Device {
     /// Certificate data (DER-encoded, for TLS validation)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub certificate_data: Option<Vec<u8>>,
+
+    /// When the last packet was exchanged in this session (keepalives excluded)
+    #[serde(skip)]
+    pub last_activity: Option<Instant>,
 }
 
 impl Device {
@@ -107,6 +198,7 @@ impl Device {
             port: None,
             certificate_fingerprint: None,
             certificate_data: None,
+            last_activity: None,
         }
     }
 
@@ -127,6 +219,7 @@ impl Device {
             port: None,
             certificate_fingerprint: None,
             certificate_data: None,
+            last_activity: None,
         }
     }
 
@@ -181,6 +274,7 @@ impl Device {
         self.connection_state = ConnectionState::Disconnected;
         self.host = None;
         self.port = None;
+        self.last_activity = None;
         self.update_last_seen();
         info!("Device {} ({}) disconnected", self.id(), self.name());
     }
@@ -227,6 +321,40 @@ impl Device {
         self.update_last_seen();
     }
 
+    /// Check a presented certificate fingerprint against the pinned one
+    ///
+    /// A paired device without a pin (see [`reset_trust`](Self::reset_trust))
+    /// pins `fingerprint`.
+    pub fn check_certificate(&mut self, fingerprint: &str) -> CertificateCheck {
+        if !self.is_paired() {
+            return CertificateCheck::Unpinned;
+        }
+        match &self.certificate_fingerprint {
+            Some(pinned) if pinned == fingerprint => CertificateCheck::Trusted,
+            Some(pinned) => CertificateCheck::Mismatch {
+                expected: pinned.clone(),
+            },
+            None => {
+                self.set_certificate_fingerprint(fingerprint.to_string());
+                CertificateCheck::Pinned
+            }
+        }
+    }
+
+    /// Forget the pinned certificate so the next connection pins a new one
+    ///
+    /// Meant for a device known to have been reinstalled; the device stays
+    /// paired.
+    pub fn reset_trust(&mut self) {
+        warn!(
+            "Dropping pinned certificate of {} ({})",
+            self.id(),
+            self.name()
+        );
+        self.certificate_fingerprint = None;
+        self.certificate_data = None;
+    }
+
     /// Check if device has a specific incoming capability
     pub fn has_incoming_capability(&self, capability: &str) -> bool {
         self.info
@@ -241,6 +369,43 @@ impl Device {
             .contains(&capability.to_string())
     }
 
+    /// Packet types usable with this device
+    ///
+    /// `local_incoming`/`local_outgoing` are this desktop's capabilities, e.g.
+    /// from [`PluginManager`](crate::PluginManager).
+    pub fn negotiated_capabilities(
+        &self,
+        local_incoming: &[String],
+        local_outgoing: &[String],
+    ) -> CapabilitySet {
+        CapabilitySet::negotiate(
+            local_incoming,
+            local_outgoing,
+            &self.info.incoming_capabilities,
+            &self.info.outgoing_capabilities,
+        )
+    }
+
+    /// Record a packet exchanged at `now`
+    pub fn record_activity(&mut self, now: Instant) {
+        self.last_activity = Some(now);
+    }
+
+    /// How recently the device exchanged packets, as of `now`
+    pub fn activity_level(&self, now: Instant) -> ActivityLevel {
+        let Some(last_activity) = self.last_activity.filter(|_| self.is_connected()) else {
+            return ActivityLevel::Stale;
+        };
+        let elapsed = now.saturating_duration_since(last_activity);
+        if elapsed <= ACTIVE_WITHIN {
+            ActivityLevel::Active
+        } else if elapsed <= IDLE_WITHIN {
+            ActivityLevel::Idle
+        } else {
+            ActivityLevel::Stale
+        }
+    }
+
     /// Get time since last seen in seconds
     pub fn seconds_since_last_seen(&self) -> u64 {
         current_timestamp().saturating_sub(self.last_seen)
@@ -252,15 +417,312 @@ impl Device {
     }
 }
 
+/// A store holding per-device state that must be wiped when a device is forgotten
+#[async_trait]
+pub trait DeviceStateStore: Send + Sync {
+    /// Short human-readable name of the store (e.g. "certificates")
+    fn name(&self) -> &str;
+
+    /// Delete everything the store holds for a device
+    ///
+    /// Must succeed when the store holds nothing for the device.
+    async fn purge_device(&self, device_id: &str) -> Result<()>;
+
+    /// Move everything the store holds for device `from` to device `to`
+    ///
+    /// Called when a reinstalled device takes over a previous identity; state
+    /// already held for `to` is replaced. Stores whose state belongs to the
+    /// old identity itself (such as its certificate) keep the default, which
+    /// leaves the state to be purged with the old device.
+    async fn migrate_device(&self, _from: &str, _to: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Packet types both sides of a connection agreed on
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilitySet {
+    /// Types the device accepts and this desktop sends
+    sendable: BTreeSet<String>,
+    /// Types the device sends and this desktop handles
+    receivable: BTreeSet<String>,
+}
+
+impl CapabilitySet {
+    /// Intersect local and remote capabilities
+    pub fn negotiate(
+        local_incoming: &[String],
+        local_outgoing: &[String],
+        remote_incoming: &[String],
+        remote_outgoing: &[String],
+    ) -> Self {
+        let intersect = |ours: &[String], theirs: &[String]| -> BTreeSet<String> {
+            ours.iter()
+                .filter(|capability| theirs.contains(capability))
+                .cloned()
+                .collect()
+        };
+        Self {
+            sendable: intersect(local_outgoing, remote_incoming),
+            receivable: intersect(local_incoming, remote_outgoing),
+        }
+    }
+
+    /// Rebuild a set from already negotiated lists
+    pub fn from_parts(
+        sendable: impl IntoIterator<Item = String>,
+        receivable: impl IntoIterator<Item = String>,
+    ) -> Self {
+        Self {
+            sendable: sendable.into_iter().collect(),
+            receivable: receivable.into_iter().collect(),
+        }
+    }
+
+    /// Whether packets of `packet_type` can be sent to the device
+    pub fn can_send(&self, packet_type: &str) -> bool {
+        self.sendable.contains(packet_type)
+    }
+
+    /// Whether packets of `packet_type` from the device are handled
+    pub fn can_receive(&self, packet_type: &str) -> bool {
+        self.receivable.contains(packet_type)
+    }
+
+    /// Types that can be sent, sorted
+    pub fn sendable(&self) -> impl Iterator<Item = &str> {
+        self.sendable.iter().map(String::as_str)
+    }
+
+    /// Types that are received, sorted
+    pub fn receivable(&self) -> impl Iterator<Item = &str> {
+        self.receivable.iter().map(String::as_str)
+    }
+
+    /// Whether nothing was agreed on
+    pub fn is_empty(&self) -> bool {
+        self.sendable.is_empty() && self.receivable.is_empty()
+    }
+}
+
+/// Layout of a [`DeviceFileStore`]
+#[derive(Debug, Clone)]
+enum DeviceFileLayout {
+    /// One directory per device: `<root>/<device_id>/`
+    Directory,
+    /// One file per device: `<root>/<device_id>.<extension>`
+    File(String),
+}
+
+/// Per-device state kept on disk under a common root
+#[derive(Debug, Clone)]
+pub struct DeviceFileStore {
+    name: String,
+    root: PathBuf,
+    layout: DeviceFileLayout,
+}
+
+impl DeviceFileStore {
+    /// A store keeping one directory per device under `root`
+    pub fn directory(name: impl Into<String>, root: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            root: root.into(),
+            layout: DeviceFileLayout::Directory,
+        }
+    }
+
+    /// A store keeping one `<device_id>.<extension>` file per device under `root`
+    pub fn file(
+        name: impl Into<String>,
+        root: impl Into<PathBuf>,
+        extension: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            root: root.into(),
+            layout: DeviceFileLayout::File(extension.into()),
+        }
+    }
+
+    /// Path of the state for a device
+    pub fn path_for(&self, device_id: &str) -> Result<PathBuf> {
+        // Device IDs come from the network; never let one escape the root
+        if device_id.is_empty()
+            || device_id.contains(['/', '\\'])
+            || device_id == "."
+            || device_id == ".."
+        {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Invalid device ID: {:?}",
+                device_id
+            )));
+        }
+
+        Ok(match &self.layout {
+            DeviceFileLayout::Directory => self.root.join(device_id),
+            DeviceFileLayout::File(extension) => {
+                self.root.join(format!("{}.{}", device_id, extension))
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl DeviceStateStore for DeviceFileStore {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn purge_device(&self, device_id: &str) -> Result<()> {
+        let path = self.path_for(device_id)?;
+        let result = match self.layout {
+            DeviceFileLayout::Directory => tokio::fs::remove_dir_all(&path).await,
+            DeviceFileLayout::File(_) => tokio::fs::remove_file(&path).await,
+        };
+
+        match result {
+            Ok(()) => {
+                debug!(
+                    "Removed {} for device {} at {:?}",
+                    self.name, device_id, path
+                );
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(ProtocolError::from_io_error(
+                e,
+                &format!("removing {} at {:?}", self.name, path),
+            )),
+        }
+    }
+
+    async fn migrate_device(&self, from: &str, to: &str) -> Result<()> {
+        let from_path = self.path_for(from)?;
+        let to_path = self.path_for(to)?;
+        if !tokio::fs::try_exists(&from_path).await.unwrap_or(false) {
+            return Ok(());
+        }
+
+        // Whatever the new identity created since pairing gives way
+        self.purge_device(to).await?;
+        tokio::fs::rename(&from_path, &to_path).await.map_err(|e| {
+            ProtocolError::from_io_error(
+                e,
+                &format!("moving {} from {:?} to {:?}", self.name, from_path, to_path),
+            )
+        })?;
+        debug!("Moved {} from device {} to {}", self.name, from, to);
+        Ok(())
+    }
+}
+
+/// Completion event for [`DeviceManager::forget_device`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceForgotten {
+    /// ID of the forgotten device
+    pub device_id: String,
+    /// Name the device had
+    pub device_name: String,
+    /// Stores that were purged
+    pub purged_stores: Vec<String>,
+}
+
+/// A newly paired device that looks like a reinstall of a known one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationOffer {
+    /// ID of the newly paired device
+    pub device_id: String,
+    /// ID the device had before
+    pub previous_device_id: String,
+    /// Name both identities share
+    pub device_name: String,
+}
+
+/// Separator between a device ID and the suffix given to a conflicting copy
+pub const DEVICE_ID_CONFLICT_SEPARATOR: char = '~';
+
+/// Two devices presenting the same ID with different certificates
+///
+/// Typically a phone restored from another phone's backup. The newcomer is
+/// kept under `alias_id` so neither device's state overwrites the other's.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceIdConflict {
+    /// ID both devices advertise
+    pub device_id: String,
+    /// ID the newcomer is known by
+    pub alias_id: String,
+    /// Name shown for the newcomer
+    pub device_name: String,
+    /// Certificate fingerprint already known for `device_id`
+    pub known_fingerprint: String,
+    /// Certificate fingerprint the newcomer presented
+    pub new_fingerprint: String,
+}
+
+/// Outcome of checking a presented certificate against the pinned one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertificateCheck {
+    /// The certificate matches the pinned fingerprint
+    Trusted,
+    /// Nothing was pinned for the paired device, so this certificate now is
+    Pinned,
+    /// The device is not paired; nothing is enforced
+    Unpinned,
+    /// The certificate differs from the pinned fingerprint
+    Mismatch {
+        /// Fingerprint pinned for the device
+        expected: String,
+    },
+}
+
+/// Completion event for [`DeviceManager::accept_migration`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceMigrated {
+    /// ID the state now belongs to
+    pub device_id: String,
+    /// ID the state was moved from, no longer known
+    pub previous_device_id: String,
+    /// Name of the device
+    pub device_name: String,
+    /// Stores whose state was moved
+    pub migrated_stores: Vec<String>,
+}
+
+/// Whether `id` is a conflict alias of `device_id`
+fn is_conflict_alias(id: &str, device_id: &str) -> bool {
+    id.strip_prefix(device_id)
+        .and_then(|rest| rest.strip_prefix(DEVICE_ID_CONFLICT_SEPARATOR))
+        .is_some_and(|suffix| !suffix.is_empty() && suffix.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Storage key of the device registry in [`StorageNamespace::Devices`]
+pub const DEVICE_REGISTRY_KEY: &str = "registry";
+
 /// Device manager for tracking multiple devices
 pub struct DeviceManager {
     /// Map of device ID to device
     devices: HashMap<String, Device>,
 
-    /// Path to store device registry
-    registry_path: PathBuf,
+    /// Backend holding the device registry
+    storage: Arc<dyn Storage>,
+
+    /// Key of the registry in the storage backend
+    registry_key: String,
+
+    /// Stores purged when a device is forgotten
+    state_stores: Vec<Arc<dyn DeviceStateStore>>,
+
+    /// Identity migrations awaiting the user's answer, by new device ID
+    migration_offers: HashMap<String, MigrationOffer>,
+
+    /// IDs of devices as they transition to connected
+    connected_tx: broadcast::Sender<String>,
 }
 
+/// Connected transitions buffered for slow waiters
+const CONNECTED_EVENT_CAPACITY: usize = 64;
+
 impl DeviceManager {
     /// Create a new device manager
     ///
@@ -269,20 +731,50 @@ impl DeviceManager {
     /// * `registry_path` - Path to store device registry JSON
     pub fn new(registry_path: impl Into<PathBuf>) -> Result<Self> {
         let registry_path = registry_path.into();
+        let parent = registry_path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
 
         // Ensure parent directory exists
-        if let Some(parent) = registry_path.parent() {
-            fs::create_dir_all(parent).map_err(|e| {
-                ProtocolError::from_io_error(
-                    e,
-                    &format!("creating registry directory {:?}", parent),
-                )
+        fs::create_dir_all(parent).map_err(|e| {
+            ProtocolError::from_io_error(e, &format!("creating registry directory {:?}", parent))
+        })?;
+
+        let key = registry_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| {
+                ProtocolError::Configuration(format!(
+                    "Invalid device registry path: {:?}",
+                    registry_path
+                ))
             })?;
-        }
+        let extension = registry_path
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or("");
+        let storage =
+            FileStorage::new(parent).with_location(StorageNamespace::Devices, parent, extension);
+
+        Self::open(Arc::new(storage), key.to_string())
+    }
 
+    /// Create a device manager persisting its registry in a storage backend
+    ///
+    /// The registry is stored under [`DEVICE_REGISTRY_KEY`].
+    pub fn with_storage(storage: Arc<dyn Storage>) -> Result<Self> {
+        Self::open(storage, DEVICE_REGISTRY_KEY.to_string())
+    }
+
+    fn open(storage: Arc<dyn Storage>, registry_key: String) -> Result<Self> {
         let mut manager = Self {
             devices: HashMap::new(),
-            registry_path,
+            storage,
+            registry_key,
+            state_stores: Vec::new(),
+            migration_offers: HashMap::new(),
+            connected_tx: broadcast::channel(CONNECTED_EVENT_CAPACITY).0,
         };
 
         // Load existing registry
@@ -303,6 +795,24 @@ impl DeviceManager {
         self.devices.get(device_id)
     }
 
+    /// Look up a device by ID
+    ///
+    /// Same as [`get_device`](Self::get_device): a single map lookup that
+    /// neither scans the device list nor allocates.
+    pub fn get(&self, device_id: &str) -> Option<&Device> {
+        self.devices.get(device_id)
+    }
+
+    /// Whether `device_id` is a known, paired device
+    pub fn is_paired(&self, device_id: &str) -> bool {
+        self.devices.get(device_id).is_some_and(Device::is_paired)
+    }
+
+    /// Whether `device_id` is a known, trusted device
+    pub fn is_trusted(&self, device_id: &str) -> bool {
+        self.devices.get(device_id).is_some_and(|d| d.is_trusted)
+    }
+
     /// Get a mutable reference to a device by ID
     pub fn get_device_mut(&mut self, device_id: &str) -> Option<&mut Device> {
         self.devices.get_mut(device_id)
@@ -314,6 +824,254 @@ impl DeviceManager {
         self.devices.remove(device_id)
     }
 
+    /// Register a store to purge when a device is forgotten
+    pub fn register_state_store(&mut self, store: Arc<dyn DeviceStateStore>) {
+        debug!("Registered device state store: {}", store.name());
+        self.state_stores.push(store);
+    }
+
+    /// Forget a device, wiping all state associated with it
+    ///
+    /// Unlike unpairing, nothing about the device survives: every registered
+    /// [`DeviceStateStore`] is purged, the device is removed and the registry is
+    /// saved. All stores are attempted; if any fails the device is kept so the
+    /// call can be retried.
+    pub async fn forget_device(&mut self, device_id: &str) -> Result<DeviceForgotten> {
+        let device_name = self
+            .get_device(device_id)
+            .ok_or_else(|| ProtocolError::DeviceNotFound(device_id.to_string()))?
+            .name()
+            .to_string();
+
+        info!("Forgetting device {} ({})", device_name, device_id);
+
+        let mut purged_stores = Vec::with_capacity(self.state_stores.len());
+        let mut failed = Vec::new();
+        for store in &self.state_stores {
+            match store.purge_device(device_id).await {
+                Ok(()) => purged_stores.push(store.name().to_string()),
+                Err(e) => {
+                    warn!(
+                        "Failed to purge {} for device {}: {}",
+                        store.name(),
+                        device_id,
+                        e
+                    );
+                    failed.push(store.name().to_string());
+                }
+            }
+        }
+
+        if !failed.is_empty() {
+            return Err(ProtocolError::InvalidState(format!(
+                "Could not forget device {}: failed to purge {}",
+                device_id,
+                failed.join(", ")
+            )));
+        }
+
+        self.remove_device(device_id);
+        self.save_registry()?;
+
+        Ok(DeviceForgotten {
+            device_id: device_id.to_string(),
+            device_name,
+            purged_stores,
+        })
+    }
+
+    /// Check the certificate a device presented under `device_id`
+    ///
+    /// If the known device with that ID has a different certificate, the
+    /// newcomer is added as a separate device under an alias ID
+    /// (`<device_id>~2`, `~3`, ...) and the conflict is returned. The known
+    /// device is left untouched. A newcomer that already has an alias is not
+    /// reported again; use [`resolve_device_id`](Self::resolve_device_id) to
+    /// find it.
+    pub fn detect_id_conflict(
+        &mut self,
+        device_id: &str,
+        fingerprint: &str,
+    ) -> Option<DeviceIdConflict> {
+        let known = self.devices.get(device_id)?;
+        let known_fingerprint = known.certificate_fingerprint.clone()?;
+        if known_fingerprint == fingerprint || self.conflict_alias(device_id, fingerprint).is_some()
+        {
+            return None;
+        }
+
+        let (alias_id, suffix) = (2..)
+            .map(|n| {
+                (
+                    format!("{}{}{}", device_id, DEVICE_ID_CONFLICT_SEPARATOR, n),
+                    n,
+                )
+            })
+            .find(|(alias_id, _)| !self.devices.contains_key(alias_id))?;
+        let mut info = known.info.clone();
+        info.device_id = alias_id.clone();
+        info.device_name = format!("{} ({})", known.name(), suffix);
+        let mut alias = Device::from_discovery(info);
+        alias.host = known.host.clone();
+        alias.port = known.port;
+        alias.set_certificate_fingerprint(fingerprint.to_string());
+
+        let conflict = DeviceIdConflict {
+            device_id: device_id.to_string(),
+            alias_id: alias_id.clone(),
+            device_name: alias.name().to_string(),
+            known_fingerprint,
+            new_fingerprint: fingerprint.to_string(),
+        };
+        warn!(
+            "Device ID {} presented with a second certificate, keeping it apart as {}",
+            device_id, alias_id
+        );
+        self.devices.insert(alias_id, alias);
+        Some(conflict)
+    }
+
+    /// ID of the device presenting `fingerprint` under `device_id`
+    ///
+    /// The alias of a conflicting copy if one was recorded by
+    /// [`detect_id_conflict`](Self::detect_id_conflict), `device_id` otherwise.
+    pub fn resolve_device_id(&self, device_id: &str, fingerprint: &str) -> String {
+        self.conflict_alias(device_id, fingerprint)
+            .unwrap_or(device_id)
+            .to_string()
+    }
+
+    /// Devices kept apart from `device_id` because of a certificate conflict
+    pub fn id_conflicts<'a>(&'a self, device_id: &'a str) -> impl Iterator<Item = &'a Device> {
+        self.devices
+            .iter()
+            .filter(move |(id, _)| is_conflict_alias(id, device_id))
+            .map(|(_, device)| device)
+    }
+
+    fn conflict_alias(&self, device_id: &str, fingerprint: &str) -> Option<&str> {
+        self.id_conflicts(device_id)
+            .find(|device| device.certificate_fingerprint.as_deref() == Some(fingerprint))
+            .map(Device::id)
+    }
+
+    /// Find the paired device a newly seen identity most likely replaces
+    ///
+    /// Candidates are other paired devices with the same name and type. When
+    /// both identities carry a persistent ID those must match too, and such a
+    /// match is preferred over one on name alone; ties go to the device seen
+    /// most recently.
+    pub fn find_previous_identity(&self, device_id: &str) -> Option<&Device> {
+        let info = &self.get_device(device_id)?.info;
+        self.devices
+            .values()
+            .filter(|device| {
+                device.id() != device_id
+                    && device.is_paired()
+                    && device.info.device_type == info.device_type
+                    && device.name() == info.device_name
+            })
+            .filter_map(
+                |device| match (&device.info.persistent_id, &info.persistent_id) {
+                    (Some(theirs), Some(ours)) if theirs != ours => None,
+                    (Some(_), Some(_)) => Some((true, device)),
+                    _ => Some((false, device)),
+                },
+            )
+            .max_by_key(|(persistent_match, device)| (*persistent_match, device.last_seen))
+            .map(|(_, device)| device)
+    }
+
+    /// Offer to move a previous identity's state to a newly paired device
+    ///
+    /// Returns the offer if [`find_previous_identity`](Self::find_previous_identity)
+    /// found a match; it stays pending until accepted or declined.
+    pub fn offer_migration(&mut self, device_id: &str) -> Option<MigrationOffer> {
+        let previous = self.find_previous_identity(device_id)?;
+        let offer = MigrationOffer {
+            device_id: device_id.to_string(),
+            previous_device_id: previous.id().to_string(),
+            device_name: previous.name().to_string(),
+        };
+        info!(
+            "Device {} ({}) may be a reinstall of {}",
+            offer.device_name, device_id, offer.previous_device_id
+        );
+        self.migration_offers
+            .insert(device_id.to_string(), offer.clone());
+        Some(offer)
+    }
+
+    /// Pending migration offer for a device
+    pub fn migration_offer(&self, device_id: &str) -> Option<&MigrationOffer> {
+        self.migration_offers.get(device_id)
+    }
+
+    /// Keep both identities separate
+    pub fn decline_migration(&mut self, device_id: &str) -> Option<MigrationOffer> {
+        self.migration_offers.remove(device_id)
+    }
+
+    /// Move the previous identity's state to the new device
+    ///
+    /// Every registered [`DeviceStateStore`] migrates its state, then the old
+    /// device is removed and the registry saved. If a store fails the offer
+    /// is kept so the call can be retried.
+    pub async fn accept_migration(&mut self, device_id: &str) -> Result<DeviceMigrated> {
+        let offer = self.migration_offers.remove(device_id).ok_or_else(|| {
+            ProtocolError::InvalidState(format!(
+                "No identity migration offered for device {}",
+                device_id
+            ))
+        })?;
+        let previous_device_id = offer.previous_device_id.clone();
+        if !self.has_device(&previous_device_id) {
+            return Err(ProtocolError::DeviceNotFound(previous_device_id));
+        }
+
+        info!(
+            "Migrating device {} from {} to {}",
+            offer.device_name, previous_device_id, device_id
+        );
+
+        let mut migrated_stores = Vec::with_capacity(self.state_stores.len());
+        let mut failed = Vec::new();
+        for store in &self.state_stores {
+            match store.migrate_device(&previous_device_id, device_id).await {
+                Ok(()) => migrated_stores.push(store.name().to_string()),
+                Err(e) => {
+                    warn!(
+                        "Failed to migrate {} from {} to {}: {}",
+                        store.name(),
+                        previous_device_id,
+                        device_id,
+                        e
+                    );
+                    failed.push(store.name().to_string());
+                }
+            }
+        }
+
+        if !failed.is_empty() {
+            self.migration_offers.insert(device_id.to_string(), offer);
+            return Err(ProtocolError::InvalidState(format!(
+                "Could not migrate device {}: failed to migrate {}",
+                device_id,
+                failed.join(", ")
+            )));
+        }
+
+        self.remove_device(&previous_device_id);
+        self.save_registry()?;
+
+        Ok(DeviceMigrated {
+            device_id: device_id.to_string(),
+            previous_device_id,
+            device_name: offer.device_name,
+            migrated_stores,
+        })
+    }
+
     /// Check if a device exists
     pub fn has_device(&self, device_id: &str) -> bool {
         self.devices.contains_key(device_id)
@@ -365,7 +1123,10 @@ impl DeviceManager {
 
         // Extract connection info based on transport
         let (host, port) = match &address {
-            TransportAddress::Tcp(addr) => (Some(addr.ip().to_string()), Some(info.tcp_port)),
+            TransportAddress::Tcp(addr) => (
+                Some(crate::transport::tcp::host_string(addr)),
+                Some(info.tcp_port),
+            ),
             TransportAddress::Bluetooth { address, .. } => (Some(address.clone()), None),
         };
 
@@ -442,6 +1203,15 @@ impl DeviceManager {
         Ok(())
     }
 
+    /// Record a packet exchanged with a device at `now`
+    ///
+    /// Unknown devices are ignored.
+    pub fn record_activity(&mut self, device_id: &str, now: Instant) {
+        if let Some(device) = self.devices.get_mut(device_id) {
+            device.record_activity(now);
+        }
+    }
+
     /// Mark device as connected
     pub fn mark_connected(&mut self, device_id: &str, host: String, port: u16) -> Result<()> {
         let device = self
@@ -449,10 +1219,70 @@ impl DeviceManager {
             .get_mut(device_id)
             .ok_or_else(|| ProtocolError::DeviceNotFound(device_id.to_string()))?;
 
+        let was_connected = device.is_connected();
         device.mark_connected(host, port);
+        if !was_connected {
+            let _ = self.connected_tx.send(device_id.to_string());
+        }
         Ok(())
     }
 
+    /// Wait for a device to come online
+    ///
+    /// Resolves right away if the device is already connected, otherwise on
+    /// its next transition to connected. The future owns everything it needs,
+    /// so a caller holding the manager behind a lock can release it first:
+    ///
+    /// ```rust,ignore
+    /// let online = device_manager.read().await.wait_for_connection(&id, timeout);
+    /// online.await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::Timeout`] if the device does not connect
+    /// within `timeout`.
+    pub fn wait_for_connection(
+        &self,
+        device_id: &str,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<()>> + Send + 'static {
+        let connected = self
+            .devices
+            .get(device_id)
+            .is_some_and(Device::is_connected);
+        let mut rx = self.connected_tx.subscribe();
+        let device_id = device_id.to_string();
+
+        async move {
+            if connected {
+                return Ok(());
+            }
+            let wait = async {
+                loop {
+                    match rx.recv().await {
+                        Ok(id) if id == device_id => return Ok(()),
+                        Ok(_) => continue,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            debug!("Missed {} connection events while waiting", skipped);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => {
+                            return Err(ProtocolError::InvalidState(
+                                "device manager dropped while waiting for a connection".to_string(),
+                            ));
+                        }
+                    }
+                }
+            };
+            tokio::time::timeout(timeout, wait).await.map_err(|_| {
+                ProtocolError::Timeout(format!(
+                    "device {} did not connect within {:?}",
+                    device_id, timeout
+                ))
+            })?
+        }
+    }
+
     /// Mark device as disconnected
     pub fn mark_disconnected(&mut self, device_id: &str) -> Result<()> {
         let device = self
@@ -486,33 +1316,51 @@ impl DeviceManager {
         Ok(())
     }
 
-    /// Save device registry to disk
+    /// Check the certificate a device presented against the pinned one
+    ///
+    /// Unknown devices are [`CertificateCheck::Unpinned`]. Persist the
+    /// registry after [`CertificateCheck::Pinned`].
+    pub fn check_certificate(&mut self, device_id: &str, fingerprint: &str) -> CertificateCheck {
+        self.devices
+            .get_mut(device_id)
+            .map_or(CertificateCheck::Unpinned, |device| {
+                device.check_certificate(fingerprint)
+            })
+    }
+
+    /// Forget a device's pinned certificate, see [`Device::reset_trust`]
+    pub fn reset_trust(&mut self, device_id: &str) -> Result<()> {
+        let device = self
+            .devices
+            .get_mut(device_id)
+            .ok_or_else(|| ProtocolError::DeviceNotFound(device_id.to_string()))?;
+
+        device.reset_trust();
+        Ok(())
+    }
+
+    /// Save device registry to storage
     pub fn save_registry(&self) -> Result<()> {
         let json = serde_json::to_string_pretty(&self.devices)?;
-        fs::write(&self.registry_path, &json).map_err(|e| {
-            ProtocolError::from_io_error(
-                e,
-                &format!("writing device registry to {:?}", self.registry_path),
-            )
-        })?;
-        debug!("Saved device registry to {:?}", self.registry_path);
+        self.storage.set(
+            StorageNamespace::Devices,
+            &self.registry_key,
+            json.as_bytes(),
+        )?;
+        debug!("Saved device registry ({})", self.registry_key);
         Ok(())
     }
 
-    /// Load device registry from disk
+    /// Load device registry from storage
     pub fn load_registry(&mut self) -> Result<()> {
-        if !self.registry_path.exists() {
-            debug!("No existing registry file at {:?}", self.registry_path);
+        let Some(json) = self
+            .storage
+            .get(StorageNamespace::Devices, &self.registry_key)?
+        else {
+            debug!("No existing device registry ({})", self.registry_key);
             return Ok(());
-        }
-
-        let json = fs::read_to_string(&self.registry_path).map_err(|e| {
-            ProtocolError::from_io_error(
-                e,
-                &format!("reading device registry from {:?}", self.registry_path),
-            )
-        })?;
-        self.devices = serde_json::from_str(&json)?;
+        };
+        self.devices = serde_json::from_slice(&json)?;
 
         // Reset all connection states to disconnected since no connections are active on startup
         for device in self.devices.values_mut() {
@@ -566,6 +1414,41 @@ mod tests {
         assert!(!ConnectionState::Disconnected.is_reachable());
     }
 
+    fn capabilities(types: &[&str]) -> Vec<String> {
+        types.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_negotiated_capabilities_intersect_both_sides() {
+        let mut info = create_test_device_info();
+        info.incoming_capabilities = capabilities(&["cconnect.ping", "cconnect.lock.request"]);
+        info.outgoing_capabilities = capabilities(&["cconnect.battery", "cconnect.sms.messages"]);
+        let mut device = Device::from_discovery(info);
+
+        let local_incoming = capabilities(&["cconnect.ping", "cconnect.battery"]);
+        let local_outgoing = capabilities(&["cconnect.ping", "cconnect.findmyphone.request"]);
+        let caps = device.negotiated_capabilities(&local_incoming, &local_outgoing);
+        assert_eq!(caps.sendable().collect::<Vec<_>>(), ["cconnect.ping"]);
+        assert_eq!(caps.receivable().collect::<Vec<_>>(), ["cconnect.battery"]);
+        assert!(!caps.can_send("cconnect.lock.request"));
+        assert!(!caps.can_send("cconnect.findmyphone.request"));
+
+        // The device re-advertises after enabling Find My Phone
+        device
+            .info
+            .incoming_capabilities
+            .push("cconnect.findmyphone.request".to_string());
+        let caps = device.negotiated_capabilities(&local_incoming, &local_outgoing);
+        assert!(caps.can_send("cconnect.findmyphone.request"));
+        assert_eq!(
+            caps,
+            CapabilitySet::from_parts(
+                capabilities(&["cconnect.findmyphone.request", "cconnect.ping"]),
+                capabilities(&["cconnect.battery"]),
+            )
+        );
+    }
+
     #[test]
     fn test_device_creation() {
         let info = create_test_device_info();
@@ -630,6 +1513,56 @@ mod tests {
         assert!(!device.has_incoming_capability("cconnect.notification"));
     }
 
+    #[test]
+    fn test_packet_activity_marks_device_active() {
+        let mut device = Device::from_discovery(create_test_device_info());
+        let start = Instant::now();
+
+        // Never active while disconnected
+        device.record_activity(start);
+        assert_eq!(device.activity_level(start), ActivityLevel::Stale);
+
+        device.mark_connected("192.168.1.100".to_string(), 1716);
+        assert_eq!(device.activity_level(start), ActivityLevel::Stale);
+        device.record_activity(start);
+        assert_eq!(device.activity_level(start), ActivityLevel::Active);
+
+        // A fresh packet brings an idle device back to active
+        let later = start + IDLE_WITHIN;
+        assert_eq!(device.activity_level(later), ActivityLevel::Idle);
+        device.record_activity(later);
+        assert_eq!(device.activity_level(later), ActivityLevel::Active);
+
+        device.mark_disconnected();
+        assert_eq!(device.activity_level(later), ActivityLevel::Stale);
+    }
+
+    #[test]
+    fn test_activity_ages_to_idle_then_stale() {
+        let mut device = Device::from_discovery(create_test_device_info());
+        device.mark_connected("192.168.1.100".to_string(), 1716);
+        let start = Instant::now();
+        device.record_activity(start);
+
+        let second = Duration::from_secs(1);
+        assert_eq!(
+            device.activity_level(start + ACTIVE_WITHIN),
+            ActivityLevel::Active
+        );
+        assert_eq!(
+            device.activity_level(start + ACTIVE_WITHIN + second),
+            ActivityLevel::Idle
+        );
+        assert_eq!(
+            device.activity_level(start + IDLE_WITHIN),
+            ActivityLevel::Idle
+        );
+        assert_eq!(
+            device.activity_level(start + IDLE_WITHIN + second),
+            ActivityLevel::Stale
+        );
+    }
+
     #[test]
     fn test_device_manager_creation() {
         let temp_dir = TempDir::new().unwrap();
@@ -639,6 +1572,62 @@ mod tests {
         assert_eq!(manager.device_count(), 0);
     }
 
+    #[tokio::test]
+    async fn test_wait_for_connection() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = Arc::new(tokio::sync::RwLock::new(
+            DeviceManager::new(temp_dir.path().join("registry.json")).unwrap(),
+        ));
+        let info = create_test_device_info();
+        let device_id = info.device_id.clone();
+        manager
+            .write()
+            .await
+            .add_device(Device::from_discovery(info));
+
+        let online = manager
+            .read()
+            .await
+            .wait_for_connection(&device_id, Duration::from_secs(5));
+        let waiter = tokio::spawn(online);
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let connected_at = Instant::now();
+        manager
+            .write()
+            .await
+            .mark_connected(&device_id, "192.168.1.100".to_string(), 1716)
+            .unwrap();
+
+        waiter.await.unwrap().unwrap();
+        assert!(connected_at.elapsed() < Duration::from_secs(1));
+
+        // Already connected devices resolve without waiting
+        let again = manager
+            .read()
+            .await
+            .wait_for_connection(&device_id, Duration::ZERO);
+        again.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_connection_times_out() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = DeviceManager::new(temp_dir.path().join("registry.json")).unwrap();
+        let info = create_test_device_info();
+        let device_id = info.device_id.clone();
+        manager.add_device(Device::from_discovery(info));
+
+        let online = manager.wait_for_connection(&device_id, Duration::from_millis(50));
+        let other = DeviceInfo::new("Other", DeviceType::Phone, 1716);
+        manager.add_device(Device::from_discovery(other.clone()));
+        manager
+            .mark_connected(&other.device_id, "192.168.1.101".to_string(), 1716)
+            .unwrap();
+
+        assert!(matches!(online.await, Err(ProtocolError::Timeout(_))));
+    }
+
     #[test]
     fn test_device_manager_add_remove() {
         let temp_dir = TempDir::new().unwrap();
@@ -658,6 +1647,208 @@ mod tests {
         assert_eq!(manager.device_count(), 0);
     }
 
+    /// Store that records purged devices, optionally failing
+    struct RecordingStore {
+        purged: std::sync::Mutex<Vec<String>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl DeviceStateStore for RecordingStore {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        async fn purge_device(&self, device_id: &str) -> Result<()> {
+            if self.fail {
+                return Err(ProtocolError::InvalidState("store offline".to_string()));
+            }
+            self.purged.lock().unwrap().push(device_id.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_forget_device_clears_all_stores() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.json");
+        let mut manager = DeviceManager::new(&registry_path).unwrap();
+
+        let info = create_test_device_info();
+        let device_id = info.device_id.clone();
+        let mut device = Device::from_discovery(info);
+        device.mark_paired("AA:BB".to_string());
+        manager.add_device(device);
+        manager.save_registry().unwrap();
+
+        // Certificate file, per-device config directory and an unrelated device
+        let cert_dir = temp_dir.path().join("certs");
+        let data_dir = temp_dir.path().join("devices");
+        fs::create_dir_all(&cert_dir).unwrap();
+        fs::create_dir_all(data_dir.join(&device_id).join("filesync")).unwrap();
+        fs::create_dir_all(data_dir.join("other-device")).unwrap();
+        fs::write(cert_dir.join(format!("{}.pem", device_id)), "cert").unwrap();
+        fs::write(
+            data_dir
+                .join(&device_id)
+                .join("filesync")
+                .join("config.json"),
+            "{}",
+        )
+        .unwrap();
+
+        let recording = Arc::new(RecordingStore {
+            purged: std::sync::Mutex::new(Vec::new()),
+            fail: false,
+        });
+        manager.register_state_store(Arc::new(DeviceFileStore::file(
+            "certificates",
+            &cert_dir,
+            "pem",
+        )));
+        manager.register_state_store(Arc::new(DeviceFileStore::directory(
+            "device data",
+            &data_dir,
+        )));
+        manager.register_state_store(recording.clone());
+
+        let forgotten = manager.forget_device(&device_id).await.unwrap();
+
+        assert_eq!(forgotten.device_id, device_id);
+        assert_eq!(
+            forgotten.purged_stores,
+            vec!["certificates", "device data", "recording"]
+        );
+        assert!(!manager.has_device(&device_id));
+        assert!(!cert_dir.join(format!("{}.pem", device_id)).exists());
+        assert!(!data_dir.join(&device_id).exists());
+        assert!(data_dir.join("other-device").exists());
+        assert_eq!(*recording.purged.lock().unwrap(), vec![device_id.clone()]);
+
+        // Gone from the persisted registry too
+        let reloaded = DeviceManager::new(&registry_path).unwrap();
+        assert!(!reloaded.has_device(&device_id));
+
+        // Forgetting again reports the device as unknown
+        assert!(matches!(
+            manager.forget_device(&device_id).await,
+            Err(ProtocolError::DeviceNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_forget_device_keeps_device_when_purge_fails() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = DeviceManager::new(temp_dir.path().join("registry.json")).unwrap();
+
+        let info = create_test_device_info();
+        let device_id = info.device_id.clone();
+        manager.add_device(Device::from_discovery(info));
+        manager.register_state_store(Arc::new(RecordingStore {
+            purged: std::sync::Mutex::new(Vec::new()),
+            fail: true,
+        }));
+
+        assert!(manager.forget_device(&device_id).await.is_err());
+        assert!(manager.has_device(&device_id));
+    }
+
+    /// A paired phone and its reinstall, which paired under a new ID
+    fn reinstalled_phone(manager: &mut DeviceManager) -> (String, String) {
+        let mut old = Device::from_discovery(
+            DeviceInfo::new("Pixel", DeviceType::Phone, 1716).with_persistent_id("hw-1"),
+        );
+        old.mark_paired("AA:BB".to_string());
+        let mut new = Device::from_discovery(
+            DeviceInfo::new("Pixel", DeviceType::Phone, 1716).with_persistent_id("hw-1"),
+        );
+        new.mark_paired("CC:DD".to_string());
+        let ids = (old.id().to_string(), new.id().to_string());
+        manager.add_device(old);
+        manager.add_device(new);
+        ids
+    }
+
+    #[test]
+    fn test_repair_offers_identity_migration() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = DeviceManager::new(temp_dir.path().join("registry.json")).unwrap();
+        let (old_id, new_id) = reinstalled_phone(&mut manager);
+
+        // Same name but a different persistent ID is another device
+        let mut other = Device::from_discovery(
+            DeviceInfo::new("Pixel", DeviceType::Phone, 1716).with_persistent_id("hw-2"),
+        );
+        other.mark_paired("EE:FF".to_string());
+        manager.add_device(other);
+
+        let offer = manager.offer_migration(&new_id).unwrap();
+        assert_eq!(offer.previous_device_id, old_id);
+        assert_eq!(offer.device_name, "Pixel");
+        assert_eq!(manager.migration_offer(&new_id), Some(&offer));
+
+        assert_eq!(manager.decline_migration(&new_id), Some(offer));
+        assert!(manager.migration_offer(&new_id).is_none());
+
+        // A device with an unknown name has nothing to migrate from
+        let laptop = Device::from_discovery(create_test_device_info());
+        let laptop_id = laptop.id().to_string();
+        manager.add_device(laptop);
+        assert!(manager.offer_migration(&laptop_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_accept_migration_moves_state_to_new_id() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry_path = temp_dir.path().join("registry.json");
+        let mut manager = DeviceManager::new(&registry_path).unwrap();
+        let (old_id, new_id) = reinstalled_phone(&mut manager);
+
+        let data_dir = temp_dir.path().join("devices");
+        fs::create_dir_all(data_dir.join(&old_id).join("filesync")).unwrap();
+        fs::write(
+            data_dir.join(&old_id).join("filesync").join("config.json"),
+            "{\"folders\":[]}",
+        )
+        .unwrap();
+        manager.register_state_store(Arc::new(DeviceFileStore::directory(
+            "device data",
+            &data_dir,
+        )));
+
+        // Accepting needs an offer first
+        assert!(manager.accept_migration(&new_id).await.is_err());
+
+        manager.offer_migration(&new_id).unwrap();
+        let migrated = manager.accept_migration(&new_id).await.unwrap();
+
+        assert_eq!(migrated.previous_device_id, old_id);
+        assert_eq!(migrated.migrated_stores, vec!["device data"]);
+        assert!(!data_dir.join(&old_id).exists());
+        assert_eq!(
+            fs::read_to_string(data_dir.join(&new_id).join("filesync").join("config.json"))
+                .unwrap(),
+            "{\"folders\":[]}"
+        );
+        assert!(!manager.has_device(&old_id));
+        assert!(manager.has_device(&new_id));
+
+        let reloaded = DeviceManager::new(&registry_path).unwrap();
+        assert!(!reloaded.has_device(&old_id));
+    }
+
+    #[test]
+    fn test_device_file_store_rejects_path_escape() {
+        let store = DeviceFileStore::directory("device data", "/tmp/devices");
+        assert!(store.path_for("../etc").is_err());
+        assert!(store.path_for("..").is_err());
+        assert!(store.path_for("").is_err());
+        assert_eq!(
+            store.path_for("abc_123").unwrap(),
+            PathBuf::from("/tmp/devices/abc_123")
+        );
+    }
+
     #[test]
     fn test_device_manager_persistence() {
         let temp_dir = TempDir::new().unwrap();
@@ -704,6 +1895,125 @@ mod tests {
         assert_eq!(manager.paired_count(), 1);
     }
 
+    #[test]
+    fn test_pinned_certificate_is_enforced() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = DeviceManager::new(temp_dir.path().join("registry.json")).unwrap();
+        let device = Device::from_discovery(create_test_device_info());
+        let device_id = device.id().to_string();
+        manager.add_device(device);
+
+        // Nothing is enforced before pairing
+        assert_eq!(
+            manager.check_certificate(&device_id, "AA:AA"),
+            CertificateCheck::Unpinned
+        );
+
+        manager
+            .mark_paired(&device_id, "AA:AA".to_string())
+            .unwrap();
+        assert_eq!(
+            manager.check_certificate(&device_id, "AA:AA"),
+            CertificateCheck::Trusted
+        );
+        assert_eq!(
+            manager.check_certificate(&device_id, "BB:BB"),
+            CertificateCheck::Mismatch {
+                expected: "AA:AA".to_string()
+            }
+        );
+
+        // After a reinstall the next certificate is pinned instead
+        manager.reset_trust(&device_id).unwrap();
+        assert!(manager.is_paired(&device_id));
+        assert_eq!(
+            manager.check_certificate(&device_id, "BB:BB"),
+            CertificateCheck::Pinned
+        );
+        assert_eq!(
+            manager.check_certificate(&device_id, "AA:AA"),
+            CertificateCheck::Mismatch {
+                expected: "BB:BB".to_string()
+            }
+        );
+        assert_eq!(
+            manager.check_certificate("unknown", "AA:AA"),
+            CertificateCheck::Unpinned
+        );
+    }
+
+    #[test]
+    fn test_second_certificate_for_id_is_a_conflict() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = DeviceManager::new(temp_dir.path().join("registry.json")).unwrap();
+        let mut original = Device::from_discovery(create_test_device_info());
+        let device_id = original.id().to_string();
+        original.mark_paired("AA:AA".to_string());
+        manager.add_device(original);
+
+        // The known certificate is no conflict
+        assert!(manager.detect_id_conflict(&device_id, "AA:AA").is_none());
+
+        let conflict = manager.detect_id_conflict(&device_id, "BB:BB").unwrap();
+        assert_eq!(conflict.alias_id, format!("{}~2", device_id));
+        assert_eq!(conflict.known_fingerprint, "AA:AA");
+        assert_eq!(conflict.new_fingerprint, "BB:BB");
+
+        // The original keeps its certificate and trust
+        let original = manager.get(&device_id).unwrap();
+        assert_eq!(original.certificate_fingerprint.as_deref(), Some("AA:AA"));
+        assert!(original.is_paired());
+        let alias = manager.get(&conflict.alias_id).unwrap();
+        assert_eq!(alias.certificate_fingerprint.as_deref(), Some("BB:BB"));
+        assert!(!alias.is_paired());
+        assert_ne!(alias.name(), original.name());
+
+        // The same copy is reported once and resolves to its alias
+        assert!(manager.detect_id_conflict(&device_id, "BB:BB").is_none());
+        assert_eq!(
+            manager.resolve_device_id(&device_id, "BB:BB"),
+            conflict.alias_id
+        );
+        assert_eq!(manager.resolve_device_id(&device_id, "AA:AA"), device_id);
+
+        // A third certificate gets its own alias
+        let third = manager.detect_id_conflict(&device_id, "CC:CC").unwrap();
+        assert_eq!(third.alias_id, format!("{}~3", device_id));
+        assert_eq!(manager.id_conflicts(&device_id).count(), 2);
+    }
+
+    #[test]
+    fn test_pairing_lookups_by_id() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = DeviceManager::new(temp_dir.path().join("registry.json")).unwrap();
+
+        for i in 0..1000 {
+            let mut info = DeviceInfo::new(&format!("Device {}", i), DeviceType::Phone, 1716);
+            info.device_id = format!("device_{}", i);
+            let mut device = Device::from_discovery(info);
+            if i % 2 == 0 {
+                device.update_pairing_status(PairingStatus::Paired);
+            }
+            manager.add_device(device);
+        }
+
+        assert!(manager.is_paired("device_998"));
+        assert!(manager.is_trusted("device_998"));
+        assert!(!manager.is_paired("device_999"));
+        assert!(!manager.is_trusted("device_999"));
+        assert!(!manager.is_paired("unknown"));
+        assert!(manager.get("unknown").is_none());
+
+        // The lookup hands out the stored device, not a copy
+        let stored = manager.get_device("device_500").unwrap();
+        assert!(std::ptr::eq(manager.get("device_500").unwrap(), stored));
+
+        // Trust follows the device, not the pairing status alone
+        manager.get_device_mut("device_500").unwrap().is_trusted = false;
+        assert!(manager.is_paired("device_500"));
+        assert!(!manager.is_trusted("device_500"));
+    }
+
     #[test]
     fn test_device_seen_recently() {
         let info = create_test_device_info();
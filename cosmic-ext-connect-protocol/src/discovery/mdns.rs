@@ -0,0 +1,232 @@
+//! mDNS/DNS-SD Discovery
+//!
+//! Some networks, such as guest Wi-Fi and many enterprise setups, drop UDP
+//! broadcasts but still pass multicast DNS. With
+//! [`DiscoveryConfig::enable_mdns`](super::DiscoveryConfig::enable_mdns) set,
+//! [`DiscoveryService`] also registers this device as a [`MDNS_SERVICE_TYPE`]
+//! service and browses for peers doing the same.
+//!
+//! ## Service Record
+//!
+//! The instance name is the device ID and the service port is the device's
+//! TCP port. The TXT record carries the device ID, name, type and protocol
+//! version, enough to build a [`DeviceInfo`] without a handshake.
+//! Capabilities are left out; they arrive with the identity packet once a
+//! connection is made.
+//!
+//! A device found both by broadcast and by mDNS is reported once. Both paths
+//! record sightings in the same presence map keyed by device ID; mDNS only
+//! emits `DeviceDiscovered` for devices not seen yet and otherwise just keeps
+//! them from timing out while their service is registered.
+
+use super::events::DiscoveryEvent;
+use super::service::{DiscoveryService, SeenDevice};
+use crate::{DeviceInfo, DeviceType, ProtocolError, Result, PROTOCOL_VERSION};
+use mdns_sd::{ResolvedService, ServiceDaemon, ServiceEvent, ServiceInfo};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, RwLock};
+use tracing::{debug, info, warn};
+
+/// DNS-SD service type CConnect devices register under
+pub const MDNS_SERVICE_TYPE: &str = "_cconnect._udp.local.";
+
+const TXT_DEVICE_ID: &str = "id";
+const TXT_DEVICE_NAME: &str = "name";
+const TXT_DEVICE_TYPE: &str = "type";
+const TXT_PROTOCOL_VERSION: &str = "protocol";
+
+/// Build the TXT record advertising `info`
+pub fn txt_record(info: &DeviceInfo) -> HashMap<String, String> {
+    HashMap::from([
+        (TXT_DEVICE_ID.to_string(), info.device_id.clone()),
+        (TXT_DEVICE_NAME.to_string(), info.device_name.clone()),
+        (
+            TXT_DEVICE_TYPE.to_string(),
+            info.device_type.as_str().to_string(),
+        ),
+        (
+            TXT_PROTOCOL_VERSION.to_string(),
+            info.protocol_version.to_string(),
+        ),
+    ])
+}
+
+/// Build a [`DeviceInfo`] from a peer's TXT record
+///
+/// # Errors
+///
+/// Returns [`ProtocolError::InvalidPacket`] if the ID, name or type is
+/// missing or the type is unknown.
+pub fn device_info_from_txt(txt: &HashMap<String, String>, tcp_port: u16) -> Result<DeviceInfo> {
+    let field = |key: &str| {
+        txt.get(key)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| ProtocolError::InvalidPacket(format!("mDNS TXT record lacks {key}")))
+    };
+    let device_type = field(TXT_DEVICE_TYPE)?;
+    let device_type = DeviceType::from_name(device_type).ok_or_else(|| {
+        ProtocolError::InvalidPacket(format!("Unknown device type: {}", device_type))
+    })?;
+
+    let mut info = DeviceInfo::with_id(
+        field(TXT_DEVICE_ID)?.as_str(),
+        field(TXT_DEVICE_NAME)?.as_str(),
+        device_type,
+        tcp_port,
+    );
+    info.protocol_version = txt
+        .get(TXT_PROTOCOL_VERSION)
+        .and_then(|version| version.parse().ok())
+        .unwrap_or(PROTOCOL_VERSION);
+    Ok(info)
+}
+
+/// Device and TCP address behind a resolved service
+fn resolved_device(service: &ResolvedService) -> Result<(DeviceInfo, SocketAddr)> {
+    let txt: HashMap<String, String> = service
+        .txt_properties
+        .iter()
+        .map(|property| (property.key().to_string(), property.val_str().to_string()))
+        .collect();
+    let info = device_info_from_txt(&txt, service.port)?;
+
+    // Prefer IPv4, which the broadcast path reports too
+    let ip = service
+        .addresses
+        .iter()
+        .map(|addr| addr.to_ip_addr())
+        .min_by_key(|ip| ip.is_ipv6())
+        .ok_or_else(|| {
+            ProtocolError::NetworkError(format!("{} resolved without an address", service.fullname))
+        })?;
+    Ok((info, SocketAddr::new(ip, service.port)))
+}
+
+fn mdns_error(e: mdns_sd::Error) -> ProtocolError {
+    ProtocolError::NetworkError(format!("mDNS: {}", e))
+}
+
+/// Registered mDNS service and running browser
+pub(super) struct MdnsDiscovery {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+impl MdnsDiscovery {
+    /// Register `device_info` and report peers through `event_tx`
+    ///
+    /// Peers whose service is still registered are marked as seen every
+    /// `refresh`, since mDNS only announces changes.
+    pub(super) fn start(
+        device_info: &DeviceInfo,
+        refresh: Duration,
+        event_tx: mpsc::UnboundedSender<DiscoveryEvent>,
+        last_seen: Arc<RwLock<HashMap<String, SeenDevice>>>,
+    ) -> Result<Self> {
+        let daemon = ServiceDaemon::new().map_err(mdns_error)?;
+        let service = ServiceInfo::new(
+            MDNS_SERVICE_TYPE,
+            &device_info.device_id,
+            &format!("{}.local.", device_info.device_id),
+            "",
+            device_info.tcp_port,
+            txt_record(device_info),
+        )
+        .map_err(mdns_error)?
+        .enable_addr_auto();
+        let fullname = service.get_fullname().to_string();
+        daemon.register(service).map_err(mdns_error)?;
+        let browser = daemon.browse(MDNS_SERVICE_TYPE).map_err(mdns_error)?;
+        info!("Registered mDNS service {}", fullname);
+
+        let own_device_id = device_info.device_id.clone();
+        tokio::spawn(async move {
+            // fullname -> peer, for services currently registered
+            let mut peers: HashMap<String, (DeviceInfo, SocketAddr)> = HashMap::new();
+            let mut refresh = tokio::time::interval(refresh);
+            loop {
+                tokio::select! {
+                    event = browser.recv_async() => match event {
+                        Ok(ServiceEvent::ServiceResolved(service)) => {
+                            match resolved_device(&service) {
+                                Ok((info, _)) if info.device_id == own_device_id => {}
+                                Ok((info, addr)) => {
+                                    DiscoveryService::record_device(
+                                        info.clone(),
+                                        addr,
+                                        &event_tx,
+                                        &last_seen,
+                                        false,
+                                    )
+                                    .await;
+                                    peers.insert(service.fullname.clone(), (info, addr));
+                                }
+                                Err(e) => {
+                                    debug!("Ignoring mDNS service {}: {}", service.fullname, e)
+                                }
+                            }
+                        }
+                        Ok(ServiceEvent::ServiceRemoved(_, fullname)) => {
+                            peers.remove(&fullname);
+                        }
+                        Ok(_) => {}
+                        Err(_) => break,
+                    },
+                    _ = refresh.tick() => {
+                        for (info, addr) in peers.values() {
+                            DiscoveryService::record_device(
+                                info.clone(),
+                                *addr,
+                                &event_tx,
+                                &last_seen,
+                                false,
+                            )
+                            .await;
+                        }
+                    }
+                }
+            }
+            debug!("mDNS browser stopped");
+        });
+
+        Ok(Self { daemon, fullname })
+    }
+
+    /// Withdraw the service and stop browsing
+    pub(super) fn stop(&self) {
+        if let Err(e) = self.daemon.unregister(&self.fullname) {
+            warn!("Failed to unregister mDNS service: {}", e);
+        }
+        let _ = self.daemon.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_txt_record_round_trip() {
+        let info = DeviceInfo::with_id("abc_123", "Workstation", DeviceType::Laptop, 1816);
+        let parsed = device_info_from_txt(&txt_record(&info), 1816).unwrap();
+        assert_eq!(parsed.device_id, "abc_123");
+        assert_eq!(parsed.device_name, "Workstation");
+        assert_eq!(parsed.device_type, DeviceType::Laptop);
+        assert_eq!(parsed.protocol_version, PROTOCOL_VERSION);
+        assert_eq!(parsed.tcp_port, 1816);
+    }
+
+    #[test]
+    fn test_incomplete_txt_record_rejected() {
+        let info = DeviceInfo::with_id("abc_123", "Phone", DeviceType::Phone, 1816);
+        let mut txt = txt_record(&info);
+        txt.insert(TXT_DEVICE_TYPE.to_string(), "toaster".to_string());
+        assert!(device_info_from_txt(&txt, 1816).is_err());
+
+        txt.remove(TXT_DEVICE_TYPE);
+        assert!(device_info_from_txt(&txt, 1816).is_err());
+    }
+}
@@ -1,6 +1,9 @@
 //! CConnect Device Discovery
 //!
-//! This module implements UDP broadcast-based device discovery for CConnect.
+//! This module implements UDP broadcast-based device discovery for CConnect,
+//! optionally alongside mDNS/DNS-SD (see [`mdns`]). Identity packets also go
+//! out over IPv6 multicast to `ff02::1` on every interface, for IPv6-only
+//! network segments.
 //!
 //! ## Discovery Protocol
 //!
@@ -52,10 +55,15 @@
 
 pub mod bluetooth;
 pub mod events;
+pub mod mdns;
 pub mod service;
 pub mod unified;
 
-use crate::{Packet, ProtocolError, Result, PROTOCOL_VERSION};
+use crate::payload::{advertised_payload_streams, MAX_PAYLOAD_STREAMS, PAYLOAD_STREAMS_FIELD};
+use crate::{
+    CompressionMode, Packet, PacketEncoding, ProtocolError, Result, COMPRESSION_FIELD,
+    PACKET_ENCODINGS_FIELD, PROTOCOL_VERSION,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::net::{IpAddr, SocketAddr, UdpSocket};
@@ -72,10 +80,11 @@ pub use bluetooth::{
     DEFAULT_BT_SCAN_INTERVAL,
 };
 pub use events::DiscoveryEvent;
+pub use mdns::MDNS_SERVICE_TYPE;
 pub use service::{
     default_additional_broadcast_addrs, DiscoveryConfig, DiscoveryService, BROADCAST_ADDR,
-    DEFAULT_BROADCAST_INTERVAL, DEFAULT_DEVICE_TIMEOUT, DISCOVERY_PORT, PORT_RANGE_END,
-    PORT_RANGE_START,
+    DEFAULT_BROADCAST_INTERVAL, DEFAULT_DEVICE_TIMEOUT, DISCOVERY_PORT, IPV6_MULTICAST_ADDR,
+    PORT_RANGE_END, PORT_RANGE_START,
 };
 pub use unified::{UnifiedDiscoveryConfig, UnifiedDiscoveryService};
 
@@ -101,6 +110,18 @@ impl DeviceType {
             DeviceType::Tv => "tv",
         }
     }
+
+    /// Parse a device type from its [`as_str`](Self::as_str) name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "desktop" => Some(DeviceType::Desktop),
+            "laptop" => Some(DeviceType::Laptop),
+            "phone" => Some(DeviceType::Phone),
+            "tablet" => Some(DeviceType::Tablet),
+            "tv" => Some(DeviceType::Tv),
+            _ => None,
+        }
+    }
 }
 
 /// Device identity information
@@ -129,6 +150,33 @@ pub struct DeviceInfo {
 
     /// TCP port for connections
     pub tcp_port: u16,
+
+    /// Identifier that survives an app reinstall, if the device sends one
+    ///
+    /// Unlike `device_id`, which changes with the certificate, this lets a
+    /// reinstalled device be recognised as a known one (see
+    /// [`DeviceManager::offer_migration`](crate::DeviceManager::offer_migration)).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persistent_id: Option<String>,
+
+    /// TCP addresses the device was seen at, best route first
+    ///
+    /// Filled in by discovery, not sent in the identity packet. A dual-stack
+    /// device can have IPv4 and IPv6 entries; see
+    /// [`order_by_route`](crate::transport::tcp::order_by_route).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<SocketAddr>,
+
+    /// Parallel payload streams the device accepts (1 = single stream)
+    ///
+    /// Taken from its identity packet, see
+    /// [`advertised_payload_streams`](crate::payload::advertised_payload_streams).
+    #[serde(default = "default_payload_streams")]
+    pub payload_streams: usize,
+}
+
+fn default_payload_streams() -> usize {
+    1
 }
 
 impl DeviceInfo {
@@ -164,6 +212,9 @@ impl DeviceInfo {
             incoming_capabilities: Vec::new(),
             outgoing_capabilities: Vec::new(),
             tcp_port,
+            persistent_id: None,
+            addresses: Vec::new(),
+            payload_streams: 1,
         }
     }
 
@@ -189,6 +240,9 @@ impl DeviceInfo {
             incoming_capabilities: Vec::new(),
             outgoing_capabilities: Vec::new(),
             tcp_port,
+            persistent_id: None,
+            addresses: Vec::new(),
+            payload_streams: 1,
         }
     }
 
@@ -216,12 +270,19 @@ impl DeviceInfo {
         self
     }
 
+    /// Set the identifier that survives an app reinstall
+    pub fn with_persistent_id(mut self, persistent_id: impl Into<String>) -> Self {
+        self.persistent_id = Some(persistent_id.into());
+        self
+    }
+
     /// Convert DeviceInfo to an identity packet
     ///
     /// Field order matches official CConnect implementation:
-    /// deviceId, deviceName, protocolVersion, deviceType, tcpPort, capabilities
+    /// deviceId, deviceName, protocolVersion, deviceType, tcpPort, capabilities.
+    /// `persistentId` is only sent when set.
     pub fn to_identity_packet(&self) -> Packet {
-        Packet::new(
+        let packet = Packet::new(
             "cconnect.identity",
             json!({
                 "deviceId": self.device_id,
@@ -231,8 +292,15 @@ impl DeviceInfo {
                 "tcpPort": self.tcp_port,
                 "incomingCapabilities": self.incoming_capabilities,
                 "outgoingCapabilities": self.outgoing_capabilities,
+                PACKET_ENCODINGS_FIELD: PacketEncoding::supported(),
+                PAYLOAD_STREAMS_FIELD: MAX_PAYLOAD_STREAMS,
+                COMPRESSION_FIELD: CompressionMode::supported(),
             }),
-        )
+        );
+        match &self.persistent_id {
+            Some(persistent_id) => packet.with_body_field("persistentId", persistent_id.as_str()),
+            None => packet,
+        }
     }
 
     /// Parse DeviceInfo from an identity packet
@@ -255,19 +323,9 @@ impl DeviceInfo {
             .get_body_field::<String>("deviceType")
             .ok_or_else(|| ProtocolError::InvalidPacket("Missing deviceType".to_string()))?;
 
-        let device_type = match device_type_str.as_str() {
-            "desktop" => DeviceType::Desktop,
-            "laptop" => DeviceType::Laptop,
-            "phone" => DeviceType::Phone,
-            "tablet" => DeviceType::Tablet,
-            "tv" => DeviceType::Tv,
-            _ => {
-                return Err(ProtocolError::InvalidPacket(format!(
-                    "Unknown device type: {}",
-                    device_type_str
-                )))
-            }
-        };
+        let device_type = DeviceType::from_name(&device_type_str).ok_or_else(|| {
+            ProtocolError::InvalidPacket(format!("Unknown device type: {}", device_type_str))
+        })?;
 
         let protocol_version = packet
             .get_body_field::<u32>("protocolVersion")
@@ -280,6 +338,10 @@ impl DeviceInfo {
         let incoming_capabilities = parse_capabilities(&packet, "incomingCapabilities");
         let outgoing_capabilities = parse_capabilities(&packet, "outgoingCapabilities");
 
+        let persistent_id = packet
+            .get_body_field::<String>("persistentId")
+            .filter(|id| !id.is_empty());
+
         Ok(Self {
             device_id,
             device_name,
@@ -288,6 +350,9 @@ impl DeviceInfo {
             incoming_capabilities,
             outgoing_capabilities,
             tcp_port,
+            persistent_id,
+            addresses: Vec::new(),
+            payload_streams: advertised_payload_streams(packet),
         })
     }
 }
@@ -485,6 +550,17 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_persistent_id_round_trip() {
+        let info = DeviceInfo::new("Pixel", DeviceType::Phone, 1816);
+        assert!(info.to_identity_packet().body.get("persistentId").is_none());
+
+        let info = info.with_persistent_id("hw-1234");
+        let parsed = DeviceInfo::from_identity_packet(&info.to_identity_packet()).unwrap();
+        assert_eq!(parsed.persistent_id.as_deref(), Some("hw-1234"));
+        assert_eq!(parsed.payload_streams, MAX_PAYLOAD_STREAMS);
+    }
+
     #[test]
     fn test_parse_capabilities_native_array() {
         let packet = Packet::new(
@@ -545,5 +621,6 @@ mod tests {
         let info = DeviceInfo::from_identity_packet(&packet).unwrap();
         assert!(info.incoming_capabilities.is_empty());
         assert!(info.outgoing_capabilities.is_empty());
+        assert_eq!(info.payload_streams, 1);
     }
 }
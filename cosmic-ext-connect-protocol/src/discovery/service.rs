@@ -1,10 +1,15 @@
 use super::events::DiscoveryEvent;
+use super::mdns::MdnsDiscovery;
+use crate::power_profile::interval_changed;
+use crate::transport::tcp::order_by_route;
 use crate::{DeviceInfo, Packet, ProtocolError, Result};
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
 use std::collections::HashMap;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6, UdpSocket};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, watch, RwLock};
 use tokio::time::interval;
 use tracing::{debug, error, info, warn};
 
@@ -14,6 +19,11 @@ pub const PORT_RANGE_END: u16 = 1864;
 pub const BROADCAST_ADDR: Ipv4Addr = Ipv4Addr::new(255, 255, 255, 255);
 pub const DEFAULT_BROADCAST_INTERVAL: Duration = Duration::from_secs(5);
 pub const DEFAULT_DEVICE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Link-local all-nodes group identity packets are multicast to over IPv6
+pub const IPV6_MULTICAST_ADDR: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+
+/// Most addresses remembered per device
+const MAX_DEVICE_ADDRESSES: usize = 4;
 
 /// Additional broadcast addresses for cross-network discovery
 /// Includes Waydroid subnet (192.168.240.255) by default
@@ -23,13 +33,20 @@ pub fn default_additional_broadcast_addrs() -> Vec<Ipv4Addr> {
     ]
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DiscoveryConfig {
+    #[serde(with = "crate::config::duration_secs")]
     pub broadcast_interval: Duration,
+    #[serde(with = "crate::config::duration_secs")]
     pub device_timeout: Duration,
     pub enable_timeout_check: bool,
     /// Additional broadcast addresses for cross-network discovery (e.g., Waydroid, VMs)
     pub additional_broadcast_addrs: Vec<Ipv4Addr>,
+    /// Also register and browse for peers over mDNS/DNS-SD
+    pub enable_mdns: bool,
+    /// Also announce and listen over IPv6 multicast ([`IPV6_MULTICAST_ADDR`])
+    pub enable_ipv6: bool,
 }
 
 impl Default for DiscoveryConfig {
@@ -39,35 +56,85 @@ impl Default for DiscoveryConfig {
             device_timeout: DEFAULT_DEVICE_TIMEOUT,
             enable_timeout_check: true,
             additional_broadcast_addrs: default_additional_broadcast_addrs(),
+            enable_mdns: false,
+            enable_ipv6: true,
         }
     }
 }
 
+/// Presence of one discovered device
+#[derive(Debug, Clone, Default)]
+pub(super) struct SeenDevice {
+    /// When the device was last seen (Unix seconds)
+    last_seen: u64,
+    /// TCP addresses it was seen at, best route first
+    addresses: Vec<SocketAddr>,
+}
+
+impl SeenDevice {
+    /// Record a sighting at `addr`, keeping the most recent addresses
+    fn saw(&mut self, addr: SocketAddr, now: u64) {
+        self.last_seen = now;
+        self.addresses.retain(|known| *known != addr);
+        self.addresses.insert(0, addr);
+        self.addresses.truncate(MAX_DEVICE_ADDRESSES);
+        order_by_route(&mut self.addresses);
+    }
+}
+
 pub struct DiscoveryService {
     device_info: DeviceInfo,
     socket: Arc<UdpSocket>,
+    /// IPv6 multicast socket, unless disabled or IPv6 is unavailable
+    socket_v6: Option<Arc<UdpSocket>>,
     event_tx: mpsc::UnboundedSender<DiscoveryEvent>,
     event_rx: Arc<RwLock<mpsc::UnboundedReceiver<DiscoveryEvent>>>,
     config: DiscoveryConfig,
     shutdown_tx: Option<tokio::sync::oneshot::Sender<()>>,
-    last_seen: Arc<RwLock<HashMap<String, u64>>>,
+    last_seen: Arc<RwLock<HashMap<String, SeenDevice>>>,
+    /// Broadcast interval updates, e.g. from the power profile
+    broadcast_interval_watch: Option<watch::Receiver<Duration>>,
+    /// mDNS registration, while running with `enable_mdns`
+    mdns: Option<MdnsDiscovery>,
 }
 
 impl DiscoveryService {
     pub fn new(device_info: DeviceInfo, config: DiscoveryConfig) -> Result<Self> {
         let socket = Self::bind_socket()?;
+        let socket_v6 = if config.enable_ipv6 {
+            match Self::bind_socket_v6() {
+                Ok(socket) => Some(Arc::new(socket)),
+                Err(e) => {
+                    warn!("IPv6 discovery unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
         let (event_tx, event_rx) = mpsc::unbounded_channel();
         Ok(Self {
             device_info,
             socket: Arc::new(socket),
+            socket_v6,
             event_tx,
             event_rx: Arc::new(RwLock::new(event_rx)),
             config,
             shutdown_tx: None,
             last_seen: Arc::new(RwLock::new(HashMap::new())),
+            broadcast_interval_watch: None,
+            mdns: None,
         })
     }
 
+    /// Take the broadcast interval from `interval` instead of the config
+    ///
+    /// Must be called before [`start`](Self::start). The broadcaster switches
+    /// to each new interval as it is published.
+    pub fn follow_broadcast_interval(&mut self, interval: watch::Receiver<Duration>) {
+        self.broadcast_interval_watch = Some(interval);
+    }
+
     pub fn with_defaults(device_info: DeviceInfo) -> Result<Self> {
         Self::new(device_info, DiscoveryConfig::default())
     }
@@ -104,10 +171,28 @@ impl DiscoveryService {
         }
     }
 
+    /// Bind the IPv6 discovery port and join the all-nodes group
+    ///
+    /// The socket is IPv6-only so it can share the port number with the
+    /// IPv4 socket.
+    fn bind_socket_v6() -> Result<UdpSocket> {
+        let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_only_v6(true)?;
+        socket.set_reuse_address(true)?;
+        socket.bind(&SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), DISCOVERY_PORT).into())?;
+        socket.join_multicast_v6(&IPV6_MULTICAST_ADDR, 0)?;
+        socket.set_nonblocking(true)?;
+        info!("Bound to UDP port {} for IPv6 multicast", DISCOVERY_PORT);
+        Ok(socket.into())
+    }
+
     pub async fn stop(&mut self) -> Result<()> {
         if let Some(shutdown_tx) = self.shutdown_tx.take() {
             let _ = shutdown_tx.send(());
         }
+        if let Some(mdns) = self.mdns.take() {
+            mdns.stop();
+        }
         Ok(())
     }
 
@@ -115,10 +200,25 @@ impl DiscoveryService {
         let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
         self.shutdown_tx = Some(shutdown_tx);
         self.spawn_broadcaster(shutdown_rx);
-        self.spawn_listener();
+        self.spawn_listener(self.socket.clone());
+        if let Some(socket_v6) = &self.socket_v6 {
+            self.spawn_listener(socket_v6.clone());
+        }
         if self.config.enable_timeout_check {
             self.spawn_timeout_checker();
         }
+        if self.config.enable_mdns && self.mdns.is_none() {
+            // Broadcast discovery still works without it
+            match MdnsDiscovery::start(
+                &self.device_info,
+                self.config.broadcast_interval,
+                self.event_tx.clone(),
+                self.last_seen.clone(),
+            ) {
+                Ok(mdns) => self.mdns = Some(mdns),
+                Err(e) => warn!("mDNS discovery unavailable: {}", e),
+            }
+        }
         Ok(())
     }
 
@@ -132,8 +232,12 @@ impl DiscoveryService {
 
     fn spawn_broadcaster(&self, mut shutdown_rx: tokio::sync::oneshot::Receiver<()>) {
         let socket = self.socket.clone();
+        let socket_v6 = self.socket_v6.clone();
         let device_info = self.device_info.clone();
-        let interval_duration = self.config.broadcast_interval;
+        let mut interval_watch = self.broadcast_interval_watch.clone();
+        let interval_duration = interval_watch
+            .as_mut()
+            .map_or(self.config.broadcast_interval, |rx| *rx.borrow_and_update());
         let additional_addrs = self.config.additional_broadcast_addrs.clone();
         tokio::spawn(async move {
             let mut interval = interval(interval_duration);
@@ -179,6 +283,15 @@ impl DiscoveryService {
                                 success_count += 1;
                             }
                         }
+                        if let Some(socket_v6) = &socket_v6 {
+                            for index in ipv6_multicast_interfaces() {
+                                let group =
+                                    SocketAddrV6::new(IPV6_MULTICAST_ADDR, DISCOVERY_PORT, 0, index);
+                                if let Err(e) = socket_v6.send_to(&bytes, group) {
+                                    debug!("Failed to multicast on interface {}: {}", index, e);
+                                }
+                            }
+                        }
                         debug!(
                             "Broadcasted identity packet ({} bytes) to {}/{} addresses for device: {}",
                             bytes.len(),
@@ -187,6 +300,11 @@ impl DiscoveryService {
                             device_info.device_name
                         );
                     }
+                    period = interval_changed(&mut interval_watch) => {
+                        debug!("Broadcasting every {:?}", period);
+                        let start = tokio::time::Instant::now() + period;
+                        interval = tokio::time::interval_at(start, period);
+                    }
                     _ = &mut shutdown_rx => {
                         debug!("Broadcaster shutting down");
                         break;
@@ -196,8 +314,7 @@ impl DiscoveryService {
         });
     }
 
-    fn spawn_listener(&self) {
-        let socket = self.socket.clone();
+    fn spawn_listener(&self, socket: Arc<UdpSocket>) {
         let event_tx = self.event_tx.clone();
         let own_device_id = self.device_info.device_id.clone();
         let own_device_info = self.device_info.clone();
@@ -240,7 +357,7 @@ impl DiscoveryService {
         _own_device_info: &DeviceInfo,
         _socket: &UdpSocket,
         event_tx: &mpsc::UnboundedSender<DiscoveryEvent>,
-        last_seen: &Arc<RwLock<HashMap<String, u64>>>,
+        last_seen: &Arc<RwLock<HashMap<String, SeenDevice>>>,
     ) -> Result<()> {
         let packet = Packet::from_bytes(data)?;
         if !packet.is_type("cconnect.identity") {
@@ -250,16 +367,44 @@ impl DiscoveryService {
         if device_info.device_id == own_device_id {
             return Ok(());
         }
+        let mut tcp_addr = src_addr;
+        tcp_addr.set_port(device_info.tcp_port);
+        Self::record_device(device_info, tcp_addr, event_tx, last_seen, true).await;
+        Ok(())
+    }
+
+    /// Note a sighting of `device_info` and emit the matching event
+    ///
+    /// Shared by the broadcast and mDNS paths, so a device seen by both is
+    /// only reported as discovered once. mDNS passes `report_update: false`:
+    /// its TXT record has no capabilities, so an update from it would hide
+    /// the ones the identity broadcast carried.
+    ///
+    /// The event carries every address the device was recently seen at in
+    /// [`DeviceInfo::addresses`], and the best route as its address.
+    pub(super) async fn record_device(
+        mut device_info: DeviceInfo,
+        tcp_addr: SocketAddr,
+        event_tx: &mpsc::UnboundedSender<DiscoveryEvent>,
+        last_seen: &Arc<RwLock<HashMap<String, SeenDevice>>>,
+        report_update: bool,
+    ) {
         let current_time = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
         let mut last_seen_map = last_seen.write().await;
         let is_new = !last_seen_map.contains_key(&device_info.device_id);
-        last_seen_map.insert(device_info.device_id.clone(), current_time);
+        let seen = last_seen_map
+            .entry(device_info.device_id.clone())
+            .or_default();
+        seen.saw(tcp_addr, current_time);
+        device_info.addresses = seen.addresses.clone();
         drop(last_seen_map);
-        let mut tcp_addr = src_addr;
-        tcp_addr.set_port(device_info.tcp_port);
+        if !is_new && !report_update {
+            return;
+        }
+        let tcp_addr = device_info.addresses.first().copied().unwrap_or(tcp_addr);
         let event = if is_new {
             info!(
                 "Discovered new device: {} ({}) at {}",
@@ -272,7 +417,6 @@ impl DiscoveryService {
             DiscoveryEvent::tcp_updated(device_info, tcp_addr)
         };
         let _ = event_tx.send(event);
-        Ok(())
     }
 
     fn spawn_timeout_checker(&self) {
@@ -289,8 +433,8 @@ impl DiscoveryService {
                     .as_secs();
                 let mut last_seen_map = last_seen.write().await;
                 let mut timed_out = Vec::new();
-                for (id, &last_time) in last_seen_map.iter() {
-                    if current_time - last_time > timeout_duration.as_secs() {
+                for (id, seen) in last_seen_map.iter() {
+                    if current_time - seen.last_seen > timeout_duration.as_secs() {
                         timed_out.push(id.clone());
                     }
                 }
@@ -305,4 +449,86 @@ impl DiscoveryService {
     pub fn local_port(&self) -> Result<u16> {
         Ok(self.socket.local_addr()?.port())
     }
+
+    /// Send this device's identity packet to a single address
+    ///
+    /// Reaches listeners that broadcasts do not, e.g. another instance bound
+    /// to a fallback port on the same host.
+    pub fn announce_to(&self, addr: SocketAddr) -> Result<()> {
+        let bytes = self.device_info.to_identity_packet().to_bytes()?;
+        let socket = match (addr, &self.socket_v6) {
+            (SocketAddr::V6(_), Some(socket_v6)) => socket_v6,
+            _ => &self.socket,
+        };
+        socket.send_to(&bytes, addr)?;
+        debug!("Sent identity packet to {}", addr);
+        Ok(())
+    }
+}
+
+/// Indexes of the interfaces IPv6 multicast can go out on
+///
+/// Up, multicast-capable and non-loopback interfaces with an IPv6 address.
+fn ipv6_multicast_interfaces() -> Vec<u32> {
+    use nix::net::if_::InterfaceFlags;
+
+    let Ok(addrs) = nix::ifaddrs::getifaddrs() else {
+        return Vec::new();
+    };
+    let mut indexes = Vec::new();
+    for ifaddr in addrs {
+        let usable = ifaddr
+            .flags
+            .contains(InterfaceFlags::IFF_UP | InterfaceFlags::IFF_MULTICAST)
+            && !ifaddr.flags.contains(InterfaceFlags::IFF_LOOPBACK);
+        let has_ipv6 = ifaddr
+            .address
+            .as_ref()
+            .is_some_and(|address| address.as_sockaddr_in6().is_some());
+        if !usable || !has_ipv6 {
+            continue;
+        }
+        if let Ok(index) = nix::net::if_::if_nametoindex(ifaddr.interface_name.as_str()) {
+            if !indexes.contains(&index) {
+                indexes.push(index);
+            }
+        }
+    }
+    indexes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DeviceType;
+
+    #[tokio::test]
+    async fn test_dual_stack_device_prefers_local_route() {
+        let (event_tx, mut events) = mpsc::unbounded_channel();
+        let last_seen = Arc::new(RwLock::new(HashMap::new()));
+        let info = DeviceInfo::with_id("phone", "Phone", DeviceType::Phone, 1816);
+
+        let global: SocketAddr = "[2001:db8::5]:1816".parse().unwrap();
+        let lan: SocketAddr = "192.168.1.5:1816".parse().unwrap();
+        for addr in [global, lan, global] {
+            DiscoveryService::record_device(info.clone(), addr, &event_tx, &last_seen, true).await;
+        }
+
+        let DiscoveryEvent::DeviceDiscovered { info, .. } = events.recv().await.unwrap() else {
+            panic!("expected DeviceDiscovered");
+        };
+        assert_eq!(info.addresses, vec![global]);
+        for _ in 0..2 {
+            let DiscoveryEvent::DeviceUpdated {
+                info,
+                transport_address,
+                ..
+            } = events.recv().await.unwrap()
+            else {
+                panic!("expected DeviceUpdated");
+            };
+            assert_eq!(info.addresses, vec![lan, global]);
+            assert_eq!(transport_address.to_string(), "tcp://192.168.1.5:1816");
+        }
+    }
 }
@@ -397,12 +397,42 @@ pub enum ProtocolError {
     #[error("Operation cancelled: {0}")]
     Cancelled(String),
 
+    /// Rejected by the peer
+    ///
+    /// This error occurs when the remote device declines a request, such as a
+    /// staged file transfer, or does not answer it in time.
+    #[error("Rejected by peer: {0}")]
+    PeerRejected(String),
+
+    /// Received file does not match the sender's checksum
+    ///
+    /// This error occurs when every byte of a file arrived but its SHA-256
+    /// differs from the one the sender announced (expected, actual), usually
+    /// after corruption on a flaky link. The file is discarded.
+    #[error("Checksum mismatch: expected {0}, got {1}")]
+    ChecksumMismatch(String, String),
+
+    /// Encrypted payload failed to decrypt
+    ///
+    /// This error occurs when an end-to-end encrypted chunk was modified,
+    /// reordered or sealed with another key, see [`crate::payload_crypto`].
+    /// The partial file is discarded.
+    #[error("Payload decryption failed: {0}")]
+    PayloadDecryption(String),
+
     /// Packet size exceeded
     ///
     /// This error occurs when a packet exceeds maximum allowed size (DoS prevention).
     #[error("Packet size exceeded: {0} bytes (max: {1})")]
     PacketSizeExceeded(usize, usize),
 
+    /// Packet body does not match its registered schema
+    ///
+    /// This error occurs when a field of a packet's body is missing or has the
+    /// wrong JSON type (field, what is wrong), see [`crate::packet::PacketType`].
+    #[error("Schema violation in field '{0}': {1}")]
+    SchemaViolation(String, String),
+
     /// Invalid state
     ///
     /// This error occurs when an operation is attempted in an invalid state.
@@ -590,12 +620,25 @@ impl ProtocolError {
             ProtocolError::InvalidPacket(msg) => {
                 format!("Invalid data received: {}.", msg)
             }
+            ProtocolError::SchemaViolation(field, msg) => {
+                format!("Invalid data received in field '{}': {}.", field, msg)
+            }
+            ProtocolError::ChecksumMismatch(_, _) => {
+                "The file arrived corrupted and was discarded. Try sending it again.".to_string()
+            }
+            ProtocolError::PayloadDecryption(_) => {
+                "The file could not be decrypted and was discarded. You may need to re-pair."
+                    .to_string()
+            }
             ProtocolError::Plugin(msg) => {
                 format!("Plugin error: {}.", msg)
             }
             ProtocolError::Cancelled(msg) => {
                 format!("Operation cancelled: {}.", msg)
             }
+            ProtocolError::PeerRejected(msg) => {
+                format!("The other device declined: {}.", msg)
+            }
             ProtocolError::Io(e) => {
                 format!("I/O error: {}.", e)
             }
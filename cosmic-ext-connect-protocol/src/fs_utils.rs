@@ -6,7 +6,7 @@
 use crate::{ProtocolError, Result};
 use std::path::{Path, PathBuf};
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 use tracing::{debug, info, warn};
 
 /// Check if sufficient disk space is available
@@ -198,6 +198,34 @@ pub async fn create_file_safe(path: impl AsRef<Path>) -> Result<fs::File> {
     Ok(file)
 }
 
+/// Reopen a partially received file to continue writing at `offset`
+///
+/// Anything past `offset` is truncated, and the returned file is positioned
+/// at `offset`.
+///
+/// # Errors
+///
+/// Returns `Io` if the file cannot be opened, truncated or seeked.
+pub async fn open_partial_file(path: impl AsRef<Path>, offset: u64) -> Result<fs::File> {
+    let path = path.as_ref();
+    let context = format!("reopening partial file {}", path.display());
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .await
+        .map_err(|e| ProtocolError::from_io_error(e, &context))?;
+    file.set_len(offset)
+        .await
+        .map_err(|e| ProtocolError::from_io_error(e, &context))?;
+    file.seek(std::io::SeekFrom::Start(offset))
+        .await
+        .map_err(|e| ProtocolError::from_io_error(e, &context))?;
+
+    debug!("Reopened {} at offset {}", path.display(), offset);
+    Ok(file)
+}
+
 /// Safe file write with disk full detection
 ///
 /// Writes data to a file, converting disk full errors to `ResourceExhausted`.
@@ -244,6 +272,59 @@ pub async fn cleanup_partial_file(path: impl AsRef<Path>) {
     }
 }
 
+/// Name a file is received under until it is complete: `.name.part`
+///
+/// The staged file lives in `dir` when given, otherwise next to `path`.
+pub fn staging_path(path: impl AsRef<Path>, dir: Option<&Path>) -> PathBuf {
+    let path = path.as_ref();
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let staged = format!(".{}.part", name);
+    match dir {
+        Some(dir) => dir.join(staged),
+        None => path.with_file_name(staged),
+    }
+}
+
+/// Move a completely received file from its staging path to its final name
+///
+/// The rename is atomic, so other applications never see a partial file
+/// under the final name. If the staging path is on another filesystem, the
+/// file is first copied to a staging name next to `dest` and renamed from
+/// there, then the original is removed.
+pub async fn commit_staged_file(staged: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
+    let (staged, dest) = (staged.as_ref(), dest.as_ref());
+    let context = format!("moving {} to {}", staged.display(), dest.display());
+
+    match fs::rename(staged, dest).await {
+        Ok(()) => {}
+        Err(e) if e.raw_os_error() == Some(nix::libc::EXDEV) => {
+            debug!(
+                "{} is on another filesystem than {}, copying",
+                staged.display(),
+                dest.display()
+            );
+            let local = staging_path(dest, None);
+            let copied = async {
+                fs::copy(staged, &local).await?;
+                fs::rename(&local, dest).await
+            }
+            .await;
+            if let Err(e) = copied {
+                cleanup_partial_file(&local).await;
+                return Err(ProtocolError::from_io_error(e, &context));
+            }
+            cleanup_partial_file(staged).await;
+        }
+        Err(e) => return Err(ProtocolError::from_io_error(e, &context)),
+    }
+
+    debug!("Committed {} to {}", staged.display(), dest.display());
+    Ok(())
+}
+
 /// Get a safe download path, handling filename conflicts
 ///
 /// If the file already exists, appends " (1)", " (2)", etc. to the filename.
@@ -313,12 +394,122 @@ pub async fn get_unique_download_path(base_dir: impl AsRef<Path>, filename: &str
     base_dir.join(new_filename)
 }
 
+/// Directory that received files can never be written outside of
+///
+/// Every path is resolved against the canonical base directory with symlinks
+/// followed, so neither an absolute path, a `..` component nor a symlink
+/// planted inside the base (at the file itself or at any parent) can send a
+/// write elsewhere. Anything that would land outside is refused with
+/// [`ProtocolError::PermissionDenied`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferSandbox {
+    base: PathBuf,
+}
+
+impl TransferSandbox {
+    /// Create a sandbox rooted at `base`, creating the directory if needed
+    pub fn new(base: impl AsRef<Path>) -> Result<Self> {
+        let base = base.as_ref();
+        std::fs::create_dir_all(base).map_err(|e| {
+            ProtocolError::from_io_error(e, &format!("creating {}", base.display()))
+        })?;
+        let base = base.canonicalize().map_err(|e| {
+            ProtocolError::from_io_error(e, &format!("resolving {}", base.display()))
+        })?;
+        Ok(Self { base })
+    }
+
+    /// Canonical base directory
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+
+    /// Resolve where a write to `path` would really land
+    ///
+    /// Relative paths are taken relative to the base. The existing part of
+    /// the path is canonicalized; the part that does not exist yet may not
+    /// contain `..`, since it cannot be resolved safely.
+    pub fn resolve(&self, path: impl AsRef<Path>) -> Result<PathBuf> {
+        let path = path.as_ref();
+        let joined = self.base.join(path);
+
+        // Split into the deepest existing ancestor and the components below it
+        let mut existing = joined.as_path();
+        let mut missing = Vec::new();
+        while std::fs::symlink_metadata(existing).is_err() {
+            match (existing.parent(), existing.file_name()) {
+                (Some(parent), Some(name)) => {
+                    missing.push(name.to_os_string());
+                    existing = parent;
+                }
+                _ => return Err(self.escape_error(path)),
+            }
+        }
+        if joined
+            .strip_prefix(existing)
+            .map(|rest| {
+                rest.components()
+                    .any(|c| matches!(c, std::path::Component::ParentDir))
+            })
+            .unwrap_or(true)
+        {
+            return Err(self.escape_error(path));
+        }
+
+        // Follows symlinks; a dangling one cannot be resolved and is refused
+        let mut resolved = existing
+            .canonicalize()
+            .map_err(|_| self.escape_error(path))?;
+        for name in missing.into_iter().rev() {
+            resolved.push(name);
+        }
+
+        if resolved.starts_with(&self.base) && resolved != self.base {
+            Ok(resolved)
+        } else {
+            Err(self.escape_error(path))
+        }
+    }
+
+    fn escape_error(&self, path: &Path) -> ProtocolError {
+        warn!(
+            "Refusing to write {} outside transfer directory {}",
+            path.display(),
+            self.base.display()
+        );
+        ProtocolError::PermissionDenied(format!(
+            "{} is outside the transfer directory {}",
+            path.display(),
+            self.base.display()
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::io::Write;
     use tempfile::TempDir;
 
+    #[tokio::test]
+    async fn test_staged_file_committed_to_final_name() {
+        let temp = TempDir::new().unwrap();
+        let dest = temp.path().join("report.pdf");
+        let staged = staging_path(&dest, None);
+        assert_eq!(staged, temp.path().join(".report.pdf.part"));
+
+        let staging_dir = temp.path().join("staging");
+        assert_eq!(
+            staging_path(&dest, Some(&staging_dir)),
+            staging_dir.join(".report.pdf.part")
+        );
+
+        std::fs::write(&staged, b"complete").unwrap();
+        commit_staged_file(&staged, &dest).await.unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), b"complete");
+        assert!(!staged.exists());
+    }
+
     #[tokio::test]
     async fn test_ensure_parent_dir_creates_nested() {
         let temp = TempDir::new().unwrap();
@@ -444,4 +635,60 @@ mod tests {
         let err = result.unwrap_err();
         assert!(err.to_string().contains("Insufficient disk space"));
     }
+
+    #[test]
+    fn test_sandbox_allows_nested_path() {
+        let temp = TempDir::new().unwrap();
+        let sandbox = TransferSandbox::new(temp.path().join("inbox")).unwrap();
+
+        let resolved = sandbox.resolve("photos/2026/img.jpg").unwrap();
+        assert_eq!(resolved, sandbox.base().join("photos/2026/img.jpg"));
+
+        let absolute = sandbox.base().join("notes.txt");
+        assert_eq!(sandbox.resolve(&absolute).unwrap(), absolute);
+    }
+
+    #[test]
+    fn test_sandbox_refuses_absolute_and_parent_paths() {
+        let temp = TempDir::new().unwrap();
+        let sandbox = TransferSandbox::new(temp.path().join("inbox")).unwrap();
+
+        for path in ["/etc/passwd", "../outside.txt", "a/../../outside.txt", "."] {
+            assert!(
+                matches!(
+                    sandbox.resolve(path),
+                    Err(ProtocolError::PermissionDenied(_))
+                ),
+                "{} was allowed",
+                path
+            );
+        }
+        // `..` that stays inside the base is fine once it exists
+        std::fs::create_dir(sandbox.base().join("a")).unwrap();
+        assert!(sandbox.resolve("a/../inside.txt").is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sandbox_refuses_symlink_out_of_base() {
+        let temp = TempDir::new().unwrap();
+        let outside = temp.path().join("outside");
+        std::fs::create_dir(&outside).unwrap();
+        let sandbox = TransferSandbox::new(temp.path().join("inbox")).unwrap();
+
+        // A symlinked directory and a (dangling) symlinked file
+        std::os::unix::fs::symlink(&outside, sandbox.base().join("link")).unwrap();
+        std::os::unix::fs::symlink(outside.join("new.txt"), sandbox.base().join("file.txt"))
+            .unwrap();
+
+        assert!(matches!(
+            sandbox.resolve("link/secret.txt"),
+            Err(ProtocolError::PermissionDenied(_))
+        ));
+        assert!(matches!(
+            sandbox.resolve("file.txt"),
+            Err(ProtocolError::PermissionDenied(_))
+        ));
+        assert!(!outside.join("new.txt").exists());
+    }
 }
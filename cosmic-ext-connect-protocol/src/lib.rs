@@ -4,7 +4,12 @@
 //! enabling device synchronization and communication between computers and mobile devices.
 
 pub mod auth;
+pub mod bandwidth;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod bluetooth_connection_manager;
+pub mod chunk_sizing;
+pub mod config;
 pub mod connection;
 pub mod device;
 pub mod discovery;
@@ -12,10 +17,15 @@ pub mod fs_utils;
 pub mod packet;
 pub mod pairing;
 pub mod payload;
+pub mod payload_crypto;
 pub mod plugins;
+pub mod power_profile;
+pub mod reassembly;
 pub mod recovery;
 pub mod recovery_coordinator;
 pub mod resource_manager;
+pub mod storage;
+pub mod transfer_integrity;
 pub mod transport;
 pub mod transport_manager;
 
@@ -29,31 +39,61 @@ pub use cosmic_ext_connect_core::crypto::{
 pub use cosmic_ext_connect_core::{Packet as CorePacket, ProtocolError as CoreProtocolError};
 
 // Re-export local types
+pub use bandwidth::BandwidthLimit;
 pub use bluetooth_connection_manager::BluetoothConnectionManager;
-pub use connection::{ConnectionConfig, ConnectionEvent, ConnectionManager};
-pub use device::{ConnectionState, Device, DeviceManager};
+pub use chunk_sizing::{ChunkProbe, ChunkProbeConfig, ChunkSizePolicy};
+pub use config::CConnectConfig;
+pub use connection::{
+    CancelOutcome, ConnectionConfig, ConnectionEvent, ConnectionManager, ConnectionStateMachine,
+    DependentTask, LinkState, PacketTapConfig, ReconnectBackoff, TeardownSignal, TrafficCounter,
+    TrafficStats,
+};
+pub use device::{
+    ActivityLevel, CapabilitySet, CertificateCheck, ConnectionState, Device, DeviceFileStore,
+    DeviceForgotten, DeviceIdConflict, DeviceManager, DeviceMigrated, DeviceStateStore,
+    MigrationOffer,
+};
 pub use discovery::{
     DeviceInfo, DeviceType, Discovery, DiscoveryConfig, DiscoveryEvent, DiscoveryService,
     DISCOVERY_PORT,
 };
 pub use error::{ProtocolError, Result};
-pub use packet::{current_timestamp, Packet};
+pub use packet::{
+    current_timestamp, CompressionMode, FieldType, Packet, PacketEncoding, PacketType,
+    PacketTypeRegistry, COMPRESSION_FIELD, PACKET_ENCODINGS_FIELD,
+};
 pub use pairing::{
-    PairingConfig, PairingEvent, PairingHandler, PairingPacket, PairingService, PairingStatus,
-    PAIRING_TIMEOUT,
+    BatchPairFilter, BatchPairResult, PairOutcome, PairingConfig, PairingEvent, PairingHandler,
+    PairingInvite, PairingPacket, PairingService, PairingStatus, RejectReason, PAIRING_TIMEOUT,
 };
 pub use payload::{
-    FileTransferInfo, PayloadClient, PayloadServer, TlsPayloadClient, TlsPayloadServer,
+    FanOutSummary, FileSizeLimits, FileTransferInfo, PayloadClient, PayloadReceiver, PayloadServer,
+    PendingTransferPrompts, ProgressThrottle, ReceiveTrust, ReceiveTrustLevels, StreamLayout,
+    TlsPayloadClient, TlsPayloadServer, TransferGate, TransferPrompt,
+};
+pub use payload_crypto::{
+    PayloadCipher, PayloadEncryption, PayloadEncryptionConfig, PayloadKey, TransferPath,
 };
-pub use plugins::{Plugin, PluginManager};
-pub use recovery::{ReconnectionStrategy, RecoveryManager, TransferState};
+pub use plugins::{
+    Plugin, PluginManager, PluginManifest, PluginManifestEntry, PluginMetrics, PluginStatus,
+};
+pub use power_profile::{
+    Cadence, PowerAwareCadence, PowerProfileConfig, PowerSource, PowerStateProvider,
+    UPowerStateProvider,
+};
+pub use reassembly::{ChunkAssembler, PayloadChunk};
+pub use recovery::{ReconnectionStrategy, RecoveryManager, TransferState, TransferStatus};
 pub use recovery_coordinator::RecoveryCoordinator;
-pub use resource_manager::{MemoryStats, ResourceConfig, ResourceManager, TransferInfo};
+pub use resource_manager::{
+    MemoryStats, ResourceConfig, ResourceManager, TransferInfo, TransferSlot, TransferStats,
+};
+pub use storage::{FileStorage, MemoryStorage, Storage, StorageNamespace};
 pub use transport::{
-    BluetoothConnection, BluetoothTransportFactory, LatencyCategory, TcpConnection,
-    TcpTransportFactory, Transport, TransportAddress, TransportCapabilities, TransportFactory,
-    TransportPreference, TransportType, CCONNECT_SERVICE_UUID, RFCOMM_READ_CHAR_UUID,
-    RFCOMM_WRITE_CHAR_UUID,
+    BluetoothConnection, BluetoothTransportFactory, LatencyCategory, PreferenceSelector,
+    SelectionContext, SelectionInput, TcpConnection, TcpSocketOptions, TcpTransportFactory,
+    Transport, TransportAddress, TransportCandidate, TransportCapabilities, TransportFactory,
+    TransportPreference, TransportSelector, TransportType, CCONNECT_SERVICE_UUID,
+    RFCOMM_READ_CHAR_UUID, RFCOMM_WRITE_CHAR_UUID,
 };
 pub use transport_manager::{TransportManager, TransportManagerConfig, TransportManagerEvent};
 
@@ -2,12 +2,439 @@
 //!
 //! This module implements the core packet structure for the CConnect protocol.
 //! Packets are JSON-formatted messages with a newline terminator.
+//!
+//! ## MessagePack
+//!
+//! On constrained links a packet can instead be encoded as MessagePack (see
+//! [`PacketEncoding`]), which is smaller and quicker to parse. Fields keep
+//! their names, so both encodings decode to the same packet. MessagePack is
+//! only sent to peers that list it under [`PACKET_ENCODINGS_FIELD`] in their
+//! identity and only over transports that allow it; standard clients keep
+//! getting JSON. [`Packet::decode`] only accepts MessagePack once it has been negotiated.
+//!
+//! ## Body Compression
+//!
+//! Text-heavy bodies (clipboard, notifications, contact batches) can be sent
+//! gzip or zstd compressed (see [`CompressionMode`]). The body is replaced by
+//! [`BODY_COMPRESSION_FIELD`], naming the algorithm, and
+//! [`COMPRESSED_BODY_FIELD`], holding the compressed original as base64; the
+//! packet type, ID and payload fields stay readable. A device lists the
+//! algorithms it can inflate under [`COMPRESSION_FIELD`] in its identity, and
+//! bodies are only compressed for peers that list the chosen one. Bodies
+//! under [`MIN_COMPRESSED_BODY_SIZE`], or that would not shrink, are sent as
+//! they are.
+//!
+//! ## Body Schemas
+//!
+//! Plugins build packet bodies by hand, so a misspelt field or a peer sending
+//! a string where a number belongs would otherwise only show up as a plugin
+//! quietly ignoring the packet. A [`PacketType`] declares the fields a packet
+//! type's body must or may carry and their JSON types; plugins register them
+//! in a [`PacketTypeRegistry`] and [`Packet::validate`] checks a packet
+//! against it, failing with [`ProtocolError::SchemaViolation`] naming the
+//! offending field. Fields a schema does not mention are allowed, so newer
+//! peers can add fields without tripping older ones. `kdeconnect.*` packets
+//! are checked against the schema of the matching `cconnect.*` type.
 
 use crate::{ProtocolError, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::io::{Read, Write};
+
+/// Identity body field listing the packet encodings a device can decode
+pub const PACKET_ENCODINGS_FIELD: &str = "packetEncodings";
+
+/// Identity body field listing the body compressions a device can inflate
+pub const COMPRESSION_FIELD: &str = "compression";
+
+/// Body field naming the algorithm of a compressed body
+pub const BODY_COMPRESSION_FIELD: &str = "bodyCompression";
+
+/// Body field holding a compressed body, base64 encoded
+pub const COMPRESSED_BODY_FIELD: &str = "compressedBody";
+
+/// Bodies smaller than this (as JSON) are never compressed
+pub const MIN_COMPRESSED_BODY_SIZE: usize = 1024;
+
+/// Largest body a compressed packet may inflate to
+const MAX_INFLATED_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// How a packet is serialized on the wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PacketEncoding {
+    /// Newline-terminated JSON, understood by every client
+    #[default]
+    Json,
+    /// MessagePack with named fields
+    MessagePack,
+}
+
+impl PacketEncoding {
+    /// Name used in identity packets
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::MessagePack => "msgpack",
+        }
+    }
+
+    /// Parse a name used in identity packets
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(Self::Json),
+            "msgpack" => Some(Self::MessagePack),
+            _ => None,
+        }
+    }
+
+    /// Encodings this implementation can decode
+    pub fn supported() -> Vec<&'static str> {
+        vec![Self::Json.as_str(), Self::MessagePack.as_str()]
+    }
+
+    /// Encodings a peer advertised in its identity
+    ///
+    /// JSON is always included; peers that do not advertise anything only
+    /// speak JSON.
+    pub fn advertised_by(identity: &Packet) -> Vec<Self> {
+        let mut encodings = vec![Self::Json];
+        let names = identity
+            .get_body_field::<Vec<String>>(PACKET_ENCODINGS_FIELD)
+            .unwrap_or_default();
+        for encoding in names.iter().filter_map(|name| Self::from_name(name)) {
+            if !encodings.contains(&encoding) {
+                encodings.push(encoding);
+            }
+        }
+        encodings
+    }
+}
+
+/// Compression applied to packet bodies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionMode {
+    /// Send bodies as they are
+    #[default]
+    Off,
+    /// gzip (DEFLATE)
+    Gzip,
+    /// Zstandard
+    Zstd,
+}
+
+impl CompressionMode {
+    /// Name used in identity packets and compressed bodies
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// Parse a compression algorithm name
+    ///
+    /// `off` is not an algorithm and yields None.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "gzip" => Some(Self::Gzip),
+            "zstd" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Algorithms this implementation can inflate
+    pub fn supported() -> Vec<&'static str> {
+        vec![Self::Zstd.as_str(), Self::Gzip.as_str()]
+    }
+
+    /// Algorithms a peer advertised in its identity
+    ///
+    /// Empty for peers that do not advertise any.
+    pub fn advertised_by(identity: &Packet) -> Vec<Self> {
+        let mut modes = Vec::new();
+        let names = identity
+            .get_body_field::<Vec<String>>(COMPRESSION_FIELD)
+            .unwrap_or_default();
+        for mode in names.iter().filter_map(|name| Self::from_name(name)) {
+            if !modes.contains(&mode) {
+                modes.push(mode);
+            }
+        }
+        modes
+    }
+
+    /// Compression to send with to a peer that inflates `peer_modes`
+    ///
+    /// The configured algorithm if the peer has it, otherwise another one
+    /// both sides support; off when compression is off or nothing matches.
+    pub fn negotiate(self, peer_modes: &[Self]) -> Self {
+        if self == Self::Off || peer_modes.contains(&self) {
+            return self;
+        }
+        [Self::Zstd, Self::Gzip]
+            .into_iter()
+            .find(|mode| peer_modes.contains(mode))
+            .unwrap_or(Self::Off)
+    }
+
+    fn deflate(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Off => Ok(data.to_vec()),
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Self::Zstd => zstd::encode_all(data, 0),
+        }
+    }
+
+    fn inflate(self, data: &[u8]) -> Result<Vec<u8>> {
+        let reader: Box<dyn Read + '_> = match self {
+            Self::Off => Box::new(data),
+            Self::Gzip => Box::new(flate2::read::GzDecoder::new(data)),
+            Self::Zstd => Box::new(zstd::stream::read::Decoder::new(data)?),
+        };
+        let mut body = Vec::new();
+        reader
+            .take(MAX_INFLATED_BODY_SIZE as u64 + 1)
+            .read_to_end(&mut body)?;
+        if body.len() > MAX_INFLATED_BODY_SIZE {
+            return Err(ProtocolError::PacketSizeExceeded(
+                body.len(),
+                MAX_INFLATED_BODY_SIZE,
+            ));
+        }
+        Ok(body)
+    }
+}
+
+/// JSON type a body field must have
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    String,
+    /// A number without a fractional part
+    Integer,
+    /// Any number
+    Number,
+    Bool,
+    Array,
+    Object,
+    /// Any value, only its presence is checked
+    Any,
+}
+
+impl FieldType {
+    /// Name used in schema violations
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Integer => "integer",
+            Self::Number => "number",
+            Self::Bool => "bool",
+            Self::Array => "array",
+            Self::Object => "object",
+            Self::Any => "any value",
+        }
+    }
+
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            Self::String => value.is_string(),
+            Self::Integer => value.is_i64() || value.is_u64(),
+            Self::Number => value.is_number(),
+            Self::Bool => value.is_boolean(),
+            Self::Array => value.is_array(),
+            Self::Object => value.is_object(),
+            Self::Any => true,
+        }
+    }
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FieldSchema {
+    name: String,
+    field_type: FieldType,
+    required: bool,
+}
+
+/// Expected body of one packet type
+///
+/// # Example
+///
+/// ```rust
+/// use cosmic_ext_connect_protocol::packet::{FieldType, PacketType};
+///
+/// let battery = PacketType::new("cconnect.battery")
+///     .required("currentCharge", FieldType::Integer)
+///     .required("isCharging", FieldType::Bool)
+///     .optional("thresholdEvent", FieldType::Integer);
+/// assert_eq!(battery.name(), "cconnect.battery");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacketType {
+    name: String,
+    fields: Vec<FieldSchema>,
+}
+
+impl PacketType {
+    /// Schema for `name` with no fields declared yet
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Packet type this schema describes
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Declare a field every packet of this type must carry
+    pub fn required(self, field: impl Into<String>, field_type: FieldType) -> Self {
+        self.field(field.into(), field_type, true)
+    }
+
+    /// Declare a field that may be missing or null
+    pub fn optional(self, field: impl Into<String>, field_type: FieldType) -> Self {
+        self.field(field.into(), field_type, false)
+    }
+
+    fn field(mut self, name: String, field_type: FieldType, required: bool) -> Self {
+        self.fields.retain(|f| f.name != name);
+        self.fields.push(FieldSchema {
+            name,
+            field_type,
+            required,
+        });
+        self
+    }
+
+    /// Check `packet`'s body against this schema
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::SchemaViolation`] for the first field that is
+    /// missing, or present with the wrong type.
+    pub fn check(&self, packet: &Packet) -> Result<()> {
+        let Some(body) = packet.body.as_object() else {
+            return Err(ProtocolError::SchemaViolation(
+                "body".to_string(),
+                format!(
+                    "{} body must be an object, got {}",
+                    packet.packet_type,
+                    json_type_name(&packet.body)
+                ),
+            ));
+        };
+        for field in &self.fields {
+            match body.get(&field.name) {
+                None | Some(Value::Null) if !field.required => {}
+                None => {
+                    return Err(ProtocolError::SchemaViolation(
+                        field.name.clone(),
+                        format!("missing from {}", packet.packet_type),
+                    ))
+                }
+                Some(value) if !field.field_type.matches(value) => {
+                    return Err(ProtocolError::SchemaViolation(
+                        field.name.clone(),
+                        format!(
+                            "{} expects {}, got {}",
+                            packet.packet_type,
+                            field.field_type.as_str(),
+                            json_type_name(value)
+                        ),
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Body schemas by packet type
+#[derive(Debug, Clone, Default)]
+pub struct PacketTypeRegistry {
+    types: HashMap<String, PacketType>,
+}
+
+impl PacketTypeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a schema
+    ///
+    /// Registering the same schema twice is a no-op.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::Plugin`] if a different schema is already
+    /// registered for the packet type.
+    pub fn register(&mut self, packet_type: PacketType) -> Result<()> {
+        match self.types.get(packet_type.name()) {
+            Some(existing) if *existing == packet_type => Ok(()),
+            Some(_) => Err(ProtocolError::Plugin(format!(
+                "Packet type '{}' already has a different schema",
+                packet_type.name()
+            ))),
+            None => {
+                self.types.insert(packet_type.name.clone(), packet_type);
+                Ok(())
+            }
+        }
+    }
+
+    /// Remove the schema for `packet_type`
+    pub fn unregister(&mut self, packet_type: &str) -> Option<PacketType> {
+        self.types.remove(packet_type)
+    }
+
+    /// Schema for `packet_type`, looking up `kdeconnect.*` types under
+    /// their `cconnect.*` name when not registered themselves
+    pub fn get(&self, packet_type: &str) -> Option<&PacketType> {
+        self.types.get(packet_type).or_else(|| {
+            packet_type
+                .strip_prefix("kdeconnect.")
+                .and_then(|rest| self.types.get(&format!("cconnect.{}", rest)))
+        })
+    }
+
+    /// Whether a schema covers `packet_type`
+    pub fn contains(&self, packet_type: &str) -> bool {
+        self.get(packet_type).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+}
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -98,6 +525,117 @@ impl Packet {
         })
     }
 
+    /// Serialize in `encoding`
+    ///
+    /// JSON output is identical to [`to_bytes`](Self::to_bytes).
+    pub fn encode(&self, encoding: PacketEncoding) -> Result<Vec<u8>> {
+        match encoding {
+            PacketEncoding::Json => self.to_bytes(),
+            // Named fields: positional arrays break with skipped optional fields
+            PacketEncoding::MessagePack => rmp_serde::to_vec_named(self).map_err(|e| {
+                ProtocolError::InvalidPacket(format!("Failed to encode packet: {}", e))
+            }),
+        }
+    }
+
+    /// Deserialize a packet received on a link using `encoding`
+    ///
+    /// JSON is always accepted. MessagePack is only accepted once it has been
+    /// negotiated, i.e. `encoding` is [`PacketEncoding::MessagePack`]; until
+    /// then a MessagePack packet is rejected rather than parsed. A JSON
+    /// packet starts with `{` (after optional whitespace), a MessagePack one
+    /// with a map marker, so the encoding is detected.
+    pub fn decode(data: &[u8], encoding: PacketEncoding) -> Result<Self> {
+        let first = data
+            .iter()
+            .find(|&&b| b != 0 && !b.is_ascii_whitespace())
+            .copied();
+        match first {
+            Some(0x80..=0x8f | 0xde | 0xdf) if encoding == PacketEncoding::MessagePack => {
+                rmp_serde::from_slice(data).map_err(|e| {
+                    ProtocolError::InvalidPacket(format!("Failed to decode packet: {}", e))
+                })
+            }
+            Some(0x80..=0x8f | 0xde | 0xdf) => Err(ProtocolError::InvalidPacket(
+                "MessagePack packet received before it was negotiated".to_string(),
+            )),
+            _ => Self::from_bytes(data),
+        }
+    }
+
+    /// Copy of this packet with its body compressed by `mode`
+    ///
+    /// The packet is returned unchanged if `mode` is off, the body is under
+    /// [`MIN_COMPRESSED_BODY_SIZE`] or compressing would not make it smaller.
+    pub fn compressed(&self, mode: CompressionMode) -> Packet {
+        if mode == CompressionMode::Off {
+            return self.clone();
+        }
+        let Ok(body) = serde_json::to_vec(&self.body) else {
+            return self.clone();
+        };
+        if body.len() < MIN_COMPRESSED_BODY_SIZE {
+            return self.clone();
+        }
+        let compressed = match mode.deflate(&body) {
+            Ok(compressed) => BASE64.encode(compressed),
+            Err(_) => return self.clone(),
+        };
+        if compressed.len() >= body.len() {
+            return self.clone();
+        }
+
+        let mut packet = self.clone();
+        packet.body = serde_json::json!({
+            BODY_COMPRESSION_FIELD: mode.as_str(),
+            COMPRESSED_BODY_FIELD: compressed,
+        });
+        packet
+    }
+
+    /// Whether the body was compressed by [`compressed`](Self::compressed)
+    pub fn is_compressed(&self) -> bool {
+        self.body.get(BODY_COMPRESSION_FIELD).is_some()
+    }
+
+    /// This packet with a compressed body inflated again
+    ///
+    /// Packets without a compressed body are returned as they are.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidPacket`] for an unknown algorithm or a
+    /// corrupt body, and [`ProtocolError::PacketSizeExceeded`] if the body
+    /// inflates beyond 16 MiB.
+    pub fn decompressed(mut self) -> Result<Packet> {
+        let Some(algorithm) = self.get_body_field::<String>(BODY_COMPRESSION_FIELD) else {
+            return Ok(self);
+        };
+        let mode = CompressionMode::from_name(&algorithm).ok_or_else(|| {
+            ProtocolError::InvalidPacket(format!("Unknown body compression: {}", algorithm))
+        })?;
+        let compressed = self
+            .body
+            .get(COMPRESSED_BODY_FIELD)
+            .and_then(|v| v.as_str())
+            .and_then(|data| BASE64.decode(data).ok())
+            .ok_or_else(|| {
+                ProtocolError::InvalidPacket(format!(
+                    "Compressed '{}' packet lacks a valid body",
+                    self.packet_type
+                ))
+            })?;
+        let body = mode.inflate(&compressed).map_err(|e| match e {
+            ProtocolError::Io(e) => ProtocolError::InvalidPacket(format!(
+                "Failed to inflate '{}' packet: {}",
+                self.packet_type, e
+            )),
+            e => e,
+        })?;
+        self.body = serde_json::from_slice(&body)?;
+        Ok(self)
+    }
+
     pub fn with_payload_size(mut self, size: i64) -> Self {
         self.payload_size = Some(size);
         self
@@ -135,6 +673,22 @@ impl Packet {
         false
     }
 
+    /// Check this packet against its type's schema in `registry`
+    ///
+    /// Packet types without a registered schema always pass; use
+    /// [`PacketTypeRegistry::contains`] to tell them apart.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::SchemaViolation`] naming the first field
+    /// that is missing or has the wrong type.
+    pub fn validate(&self, registry: &PacketTypeRegistry) -> Result<()> {
+        match registry.get(&self.packet_type) {
+            Some(schema) => schema.check(self),
+            None => Ok(()),
+        }
+    }
+
     pub fn get_body_field<T>(&self, key: &str) -> Option<T>
     where
         T: serde::de::DeserializeOwned,
@@ -173,3 +727,249 @@ where
 pub fn current_timestamp() -> i64 {
     Utc::now().timestamp_millis()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample_packets() -> Vec<Packet> {
+        let mut info = HashMap::new();
+        info.insert("port".to_string(), json!(1739));
+        vec![
+            Packet::with_id(1, "cconnect.ping", json!({})),
+            Packet::with_id(
+                -42,
+                "cconnect.battery",
+                json!({"currentCharge": 87, "isCharging": true, "thresholdEvent": 0}),
+            ),
+            Packet::with_id(
+                1_700_000_000_000,
+                "cconnect.share.request",
+                json!({
+                    "filename": "photo \u{1f4f7}.jpg",
+                    "ratio": 0.25,
+                    "offset": -3,
+                    "size": u64::MAX,
+                    "tags": ["a", null, false],
+                    "nested": {"deep": {"list": [1, 2.5, "x"]}},
+                }),
+            )
+            .with_payload_size(123_456)
+            .with_payload_transfer_info(info),
+        ]
+    }
+
+    #[test]
+    fn test_messagepack_round_trip() {
+        for packet in sample_packets() {
+            let bytes = packet.encode(PacketEncoding::MessagePack).unwrap();
+            assert_eq!(
+                Packet::decode(&bytes, PacketEncoding::MessagePack).unwrap(),
+                packet
+            );
+
+            // Both encodings decode to the same packet
+            let json = packet.encode(PacketEncoding::Json).unwrap();
+            assert_eq!(
+                Packet::decode(&json, PacketEncoding::MessagePack).unwrap(),
+                Packet::decode(&bytes, PacketEncoding::MessagePack).unwrap()
+            );
+            assert!(bytes.len() < json.len());
+        }
+    }
+
+    #[test]
+    fn test_messagepack_rejected_before_negotiation() {
+        let packet = Packet::with_id(1, "cconnect.ping", json!({}));
+        let bytes = packet.encode(PacketEncoding::MessagePack).unwrap();
+        assert!(matches!(
+            Packet::decode(&bytes, PacketEncoding::Json),
+            Err(ProtocolError::InvalidPacket(_))
+        ));
+
+        let json = packet.encode(PacketEncoding::Json).unwrap();
+        assert_eq!(Packet::decode(&json, PacketEncoding::Json).unwrap(), packet);
+    }
+
+    fn clipboard(len: usize) -> Packet {
+        Packet::with_id(
+            7,
+            "cconnect.clipboard",
+            json!({"content": "lorem ipsum dolor sit amet ".repeat(len / 27 + 1)}),
+        )
+        .with_payload_size(0)
+    }
+
+    #[test]
+    fn test_compressed_body_round_trip() {
+        let packet = clipboard(8 * 1024);
+        for mode in [CompressionMode::Gzip, CompressionMode::Zstd] {
+            let compressed = packet.compressed(mode);
+            assert!(compressed.is_compressed());
+            assert_eq!(
+                compressed.get_body_field::<String>(BODY_COMPRESSION_FIELD),
+                Some(mode.as_str().to_string())
+            );
+            assert_eq!(compressed.packet_type, packet.packet_type);
+            assert_eq!(compressed.payload_size, packet.payload_size);
+            assert!(compressed.to_bytes().unwrap().len() < packet.to_bytes().unwrap().len());
+
+            // Survives the wire in either encoding
+            let wire = compressed.encode(PacketEncoding::MessagePack).unwrap();
+            let received = Packet::decode(&wire, PacketEncoding::MessagePack).unwrap();
+            assert_eq!(received.decompressed().unwrap(), packet);
+        }
+    }
+
+    #[test]
+    fn test_small_or_incompressible_bodies_sent_as_is() {
+        let small = clipboard(100);
+        assert_eq!(small.compressed(CompressionMode::Zstd), small);
+
+        let noise: String = (0..4096u32)
+            .map(|i| char::from(b'!' + (i.wrapping_mul(2_654_435_761) >> 25) as u8 % 90))
+            .collect();
+        let random = Packet::with_id(1, "cconnect.clipboard", json!({ "content": noise }));
+        assert!(!random.compressed(CompressionMode::Gzip).is_compressed());
+
+        let packet = clipboard(8 * 1024);
+        assert_eq!(packet.compressed(CompressionMode::Off), packet);
+        assert_eq!(packet.clone().decompressed().unwrap(), packet);
+    }
+
+    #[test]
+    fn test_corrupt_compressed_body_rejected() {
+        let mut packet = clipboard(8 * 1024).compressed(CompressionMode::Zstd);
+        packet.body[COMPRESSED_BODY_FIELD] = json!(BASE64.encode(b"not zstd at all"));
+        assert!(packet.clone().decompressed().is_err());
+
+        packet.body[BODY_COMPRESSION_FIELD] = json!("brotli");
+        assert!(packet.decompressed().is_err());
+    }
+
+    #[test]
+    fn test_compression_negotiation() {
+        let standard = Packet::new("cconnect.identity", json!({"deviceId": "phone"}));
+        assert!(CompressionMode::advertised_by(&standard).is_empty());
+        assert_eq!(
+            CompressionMode::Zstd.negotiate(&CompressionMode::advertised_by(&standard)),
+            CompressionMode::Off
+        );
+
+        let gzip_only = Packet::new(
+            "cconnect.identity",
+            json!({COMPRESSION_FIELD: ["brotli", "gzip"]}),
+        );
+        let peer = CompressionMode::advertised_by(&gzip_only);
+        assert_eq!(peer, vec![CompressionMode::Gzip]);
+        assert_eq!(
+            CompressionMode::Zstd.negotiate(&peer),
+            CompressionMode::Gzip
+        );
+        assert_eq!(
+            CompressionMode::Gzip.negotiate(&peer),
+            CompressionMode::Gzip
+        );
+        assert_eq!(CompressionMode::Off.negotiate(&peer), CompressionMode::Off);
+    }
+
+    fn battery_schema() -> PacketTypeRegistry {
+        let mut registry = PacketTypeRegistry::new();
+        registry
+            .register(
+                PacketType::new("cconnect.battery")
+                    .required("currentCharge", FieldType::Integer)
+                    .required("isCharging", FieldType::Bool)
+                    .optional("thresholdEvent", FieldType::Integer),
+            )
+            .unwrap();
+        registry
+    }
+
+    fn violated_field(result: Result<()>) -> String {
+        match result {
+            Err(ProtocolError::SchemaViolation(field, _)) => field,
+            other => panic!("expected a schema violation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_schema_accepts_matching_bodies() {
+        let registry = battery_schema();
+        let full = json!({"currentCharge": 80, "isCharging": true, "thresholdEvent": 0});
+        assert!(Packet::new("cconnect.battery", full)
+            .validate(&registry)
+            .is_ok());
+
+        // Optional fields may be missing or null, unknown fields are allowed
+        let minimal =
+            json!({"currentCharge": 80, "isCharging": false, "thresholdEvent": null, "extra": "x"});
+        assert!(Packet::new("kdeconnect.battery", minimal)
+            .validate(&registry)
+            .is_ok());
+
+        // Types without a schema are not checked
+        assert!(!registry.contains("cconnect.ping"));
+        assert!(Packet::new("cconnect.ping", json!([]))
+            .validate(&registry)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_schema_violation_names_field() {
+        let registry = battery_schema();
+        let missing = Packet::new("cconnect.battery", json!({"currentCharge": 80}));
+        assert_eq!(violated_field(missing.validate(&registry)), "isCharging");
+
+        let typo = Packet::new(
+            "kdeconnect.battery",
+            json!({"currentcharge": 80, "isCharging": true}),
+        );
+        assert_eq!(violated_field(typo.validate(&registry)), "currentCharge");
+
+        let wrong_type = Packet::new(
+            "cconnect.battery",
+            json!({"currentCharge": 80.5, "isCharging": true}),
+        );
+        assert_eq!(
+            violated_field(wrong_type.validate(&registry)),
+            "currentCharge"
+        );
+
+        let not_object = Packet::new("cconnect.battery", json!("full"));
+        assert_eq!(violated_field(not_object.validate(&registry)), "body");
+    }
+
+    #[test]
+    fn test_conflicting_schema_rejected() {
+        let mut registry = battery_schema();
+        let same = PacketType::new("cconnect.battery")
+            .required("currentCharge", FieldType::Integer)
+            .required("isCharging", FieldType::Bool)
+            .optional("thresholdEvent", FieldType::Integer);
+        assert!(registry.register(same).is_ok());
+        assert!(registry
+            .register(PacketType::new("cconnect.battery").required("charge", FieldType::Number))
+            .is_err());
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn test_advertised_encodings() {
+        let standard = Packet::new("cconnect.identity", json!({"deviceId": "phone"}));
+        assert_eq!(
+            PacketEncoding::advertised_by(&standard),
+            vec![PacketEncoding::Json]
+        );
+
+        let capable = Packet::new(
+            "cconnect.identity",
+            json!({PACKET_ENCODINGS_FIELD: ["msgpack", "cbor", "json"]}),
+        );
+        assert_eq!(
+            PacketEncoding::advertised_by(&capable),
+            vec![PacketEncoding::Json, PacketEncoding::MessagePack]
+        );
+    }
+}
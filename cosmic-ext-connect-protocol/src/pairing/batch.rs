@@ -0,0 +1,294 @@
+//! Batch Pairing
+//!
+//! Setting up a new environment one device at a time is tedious. Batch
+//! pairing sends a pairing request to every discovered device matching a
+//! [`BatchPairFilter`] at once and reports how each one answered.
+//!
+//! Only devices on a trusted network, a subnet one of this machine's
+//! interfaces is directly attached to (see [`SubnetGuard`]), are asked;
+//! anything reached through a router is skipped. Every request still goes
+//! through the normal confirmation on the other device, unless that device
+//! has allowlisted us and accepts on its own.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let results = batch_pair(
+//!     &pairing_service,
+//!     candidates,
+//!     &BatchPairFilter::default().with_device_type(DeviceType::Phone),
+//!     &SubnetGuard::system(),
+//! )
+//! .await;
+//! for result in results {
+//!     println!("{}: {}", result.device_name, result.outcome);
+//! }
+//! ```
+
+use crate::connection::SubnetGuard;
+use crate::{DeviceInfo, DeviceType};
+use async_trait::async_trait;
+use std::fmt;
+use std::net::SocketAddr;
+use tracing::info;
+
+/// Which discovered devices a batch pairing asks
+#[derive(Debug, Clone, Default)]
+pub struct BatchPairFilter {
+    /// Only devices whose name contains this, ignoring case
+    pub name_contains: Option<String>,
+    /// Only devices of these types (empty for any type)
+    pub device_types: Vec<DeviceType>,
+}
+
+impl BatchPairFilter {
+    /// Only ask devices whose name contains `name`
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name_contains = Some(name.into());
+        self
+    }
+
+    /// Also ask devices of `device_type`
+    pub fn with_device_type(mut self, device_type: DeviceType) -> Self {
+        self.device_types.push(device_type);
+        self
+    }
+
+    /// Whether `device` should be asked
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        let name_matches = self.name_contains.as_ref().map_or(true, |name| {
+            device
+                .device_name
+                .to_lowercase()
+                .contains(&name.to_lowercase())
+        });
+        let type_matches =
+            self.device_types.is_empty() || self.device_types.contains(&device.device_type);
+        name_matches && type_matches
+    }
+}
+
+/// How one device answered a pairing request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PairOutcome {
+    /// The device accepted and is now paired
+    Paired,
+    /// The device (or its user) declined
+    Declined {
+        /// Reason given, if any
+        reason: Option<String>,
+    },
+    /// No answer before the pairing timeout
+    TimedOut,
+    /// The device was not asked
+    Skipped {
+        /// Why it was left out
+        reason: String,
+    },
+    /// The request could not be sent
+    Failed {
+        /// What went wrong
+        message: String,
+    },
+}
+
+impl PairOutcome {
+    /// Short name of the outcome
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Paired => "paired",
+            Self::Declined { .. } => "declined",
+            Self::TimedOut => "timed_out",
+            Self::Skipped { .. } => "skipped",
+            Self::Failed { .. } => "failed",
+        }
+    }
+}
+
+impl fmt::Display for PairOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Declined {
+                reason: Some(detail),
+            }
+            | Self::Skipped { reason: detail }
+            | Self::Failed { message: detail } => write!(f, "{}: {}", self.as_str(), detail),
+            _ => f.write_str(self.as_str()),
+        }
+    }
+}
+
+/// A discovered device a batch pairing may ask
+#[derive(Debug, Clone)]
+pub struct BatchPairCandidate {
+    /// Identity the device announced
+    pub device_info: DeviceInfo,
+    /// Address it was discovered at
+    pub remote_addr: SocketAddr,
+}
+
+/// Outcome of a batch pairing for one device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchPairResult {
+    /// ID of the device
+    pub device_id: String,
+    /// Name of the device
+    pub device_name: String,
+    /// How it answered
+    pub outcome: PairOutcome,
+}
+
+/// Something that can pair with a device and wait for its answer
+#[async_trait]
+pub trait PairingTarget: Send + Sync {
+    /// Request pairing with a device and wait until it answers or times out
+    async fn pair(&self, device_info: DeviceInfo, remote_addr: SocketAddr) -> PairOutcome;
+}
+
+/// Pair with every candidate matching `filter` on a trusted network
+///
+/// Requests are sent concurrently. Candidates filtered out are not reported;
+/// those outside the subnets of `guard` are reported as
+/// [`PairOutcome::Skipped`]. Results keep the order of `candidates`.
+pub async fn batch_pair(
+    target: &dyn PairingTarget,
+    candidates: Vec<BatchPairCandidate>,
+    filter: &BatchPairFilter,
+    guard: &SubnetGuard,
+) -> Vec<BatchPairResult> {
+    let requests = candidates
+        .into_iter()
+        .filter(|candidate| filter.matches(&candidate.device_info))
+        .map(|candidate| async move {
+            let BatchPairCandidate {
+                device_info,
+                remote_addr,
+            } = candidate;
+            let device_id = device_info.device_id.clone();
+            let device_name = device_info.device_name.clone();
+            let outcome = if guard.permits(remote_addr.ip()) {
+                target.pair(device_info, remote_addr).await
+            } else {
+                PairOutcome::Skipped {
+                    reason: format!("{} is not on a trusted network", remote_addr.ip()),
+                }
+            };
+            BatchPairResult {
+                device_id,
+                device_name,
+                outcome,
+            }
+        });
+
+    let results = futures::future::join_all(requests).await;
+    let paired = results
+        .iter()
+        .filter(|result| result.outcome == PairOutcome::Paired)
+        .count();
+    info!(
+        "Batch pairing paired {} of {} devices",
+        paired,
+        results.len()
+    );
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::LocalSubnet;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Mutex;
+
+    /// Pairs with everything except devices named in `declining`
+    struct MockTarget {
+        declining: Vec<&'static str>,
+        asked: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl PairingTarget for MockTarget {
+        async fn pair(&self, device_info: DeviceInfo, _remote_addr: SocketAddr) -> PairOutcome {
+            self.asked
+                .lock()
+                .unwrap()
+                .push(device_info.device_name.clone());
+            if self.declining.contains(&device_info.device_name.as_str()) {
+                PairOutcome::Declined {
+                    reason: Some("User declined".to_string()),
+                }
+            } else {
+                PairOutcome::Paired
+            }
+        }
+    }
+
+    fn candidate(name: &str, device_type: DeviceType, addr: &str) -> BatchPairCandidate {
+        BatchPairCandidate {
+            device_info: DeviceInfo::new(name, device_type, 1716),
+            remote_addr: addr.parse().unwrap(),
+        }
+    }
+
+    fn lan() -> SubnetGuard {
+        SubnetGuard::fixed(vec![LocalSubnet::new(
+            IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)),
+            24,
+        )])
+    }
+
+    #[tokio::test]
+    async fn test_batch_pair_asks_each_device() {
+        let target = MockTarget {
+            declining: vec!["Tablet"],
+            asked: Mutex::new(Vec::new()),
+        };
+        let candidates = vec![
+            candidate("Phone", DeviceType::Phone, "192.168.1.10:1716"),
+            candidate("Tablet", DeviceType::Tablet, "192.168.1.11:1716"),
+            candidate("Laptop", DeviceType::Laptop, "192.168.1.12:1716"),
+        ];
+
+        let results = batch_pair(&target, candidates, &BatchPairFilter::default(), &lan()).await;
+
+        let mut asked = target.asked.lock().unwrap().clone();
+        asked.sort();
+        assert_eq!(asked, vec!["Laptop", "Phone", "Tablet"]);
+
+        let outcomes: Vec<_> = results
+            .iter()
+            .map(|r| (r.device_name.as_str(), r.outcome.as_str()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("Phone", "paired"),
+                ("Tablet", "declined"),
+                ("Laptop", "paired")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_pair_filters_and_skips_untrusted() {
+        let target = MockTarget {
+            declining: Vec::new(),
+            asked: Mutex::new(Vec::new()),
+        };
+        let candidates = vec![
+            candidate("Pixel", DeviceType::Phone, "192.168.1.10:1716"),
+            candidate("Remote Pixel", DeviceType::Phone, "10.8.0.5:1716"),
+            candidate("Pixel Tablet", DeviceType::Tablet, "192.168.1.11:1716"),
+        ];
+        let filter = BatchPairFilter::default()
+            .with_name("pixel")
+            .with_device_type(DeviceType::Phone);
+
+        let results = batch_pair(&target, candidates, &filter, &lan()).await;
+
+        assert_eq!(*target.asked.lock().unwrap(), vec!["Pixel"]);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].outcome, PairOutcome::Paired);
+        assert!(matches!(results[1].outcome, PairOutcome::Skipped { .. }));
+    }
+}
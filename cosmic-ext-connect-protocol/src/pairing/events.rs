@@ -2,7 +2,7 @@
 //!
 //! This module defines events emitted during the pairing process.
 
-use crate::PairingStatus;
+use crate::{PairingStatus, RejectReason};
 
 /// Events emitted by the pairing service
 #[derive(Debug, Clone)]
@@ -25,6 +25,27 @@ pub enum PairingEvent {
         their_fingerprint: String,
     },
 
+    /// A PIN was generated for our pairing request
+    ///
+    /// The UI should show it; the user enters it on the other device.
+    PinGenerated {
+        /// ID of the device we sent the request to
+        device_id: String,
+        /// Code to display
+        pin: String,
+    },
+
+    /// A received pairing request must be confirmed by entering a PIN
+    ///
+    /// Sent after [`RequestReceived`](Self::RequestReceived); the UI should
+    /// ask for the code shown on the other device instead of a plain accept.
+    PinRequired {
+        /// ID of the device requesting pairing
+        device_id: String,
+        /// Name of the device requesting pairing
+        device_name: String,
+    },
+
     /// Pairing was accepted (by us or by peer)
     PairingAccepted {
         /// ID of the paired device
@@ -35,12 +56,12 @@ pub enum PairingEvent {
         certificate_fingerprint: String,
     },
 
-    /// Pairing was rejected (by us or by peer)
+    /// Pairing was rejected (by us or by peer) or failed
     PairingRejected {
         /// ID of the device
         device_id: String,
-        /// Reason for rejection (optional)
-        reason: Option<String>,
+        /// Why pairing did not complete, for the UI to explain
+        reason: RejectReason,
     },
 
     /// Pairing status changed
@@ -63,6 +84,15 @@ pub enum PairingEvent {
         device_id: String,
     },
 
+    /// A pending pairing request expired without an answer
+    ///
+    /// The UI should dismiss any accept/reject prompt for this device; a late
+    /// acceptance will be refused.
+    RequestExpired {
+        /// ID of the device
+        device_id: String,
+    },
+
     /// An error occurred during pairing
     Error {
         /// ID of the device (if applicable)
@@ -88,16 +118,24 @@ impl PairingEvent {
         matches!(self, PairingEvent::PairingRejected { .. })
     }
 
+    /// Check if this is a request expired event
+    pub fn is_request_expired(&self) -> bool {
+        matches!(self, PairingEvent::RequestExpired { .. })
+    }
+
     /// Get device ID if this event is device-related
     pub fn device_id(&self) -> Option<&str> {
         match self {
             PairingEvent::RequestSent { device_id, .. } => Some(device_id),
             PairingEvent::RequestReceived { device_id, .. } => Some(device_id),
+            PairingEvent::PinGenerated { device_id, .. } => Some(device_id),
+            PairingEvent::PinRequired { device_id, .. } => Some(device_id),
             PairingEvent::PairingAccepted { device_id, .. } => Some(device_id),
             PairingEvent::PairingRejected { device_id, .. } => Some(device_id),
             PairingEvent::StatusChanged { device_id, .. } => Some(device_id),
             PairingEvent::DeviceUnpaired { device_id } => Some(device_id),
             PairingEvent::PairingTimeout { device_id } => Some(device_id),
+            PairingEvent::RequestExpired { device_id } => Some(device_id),
             PairingEvent::Error { device_id, .. } => device_id.as_deref(),
         }
     }
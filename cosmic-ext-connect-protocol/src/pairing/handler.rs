@@ -27,16 +27,30 @@
 //! - Certificates stored and verified on subsequent connections
 //! - Pairing timeout: 30 seconds
 //!
+//! Clients that pair with a short code can use the PIN exchange in
+//! [`pin`](super::pin) instead of fingerprint comparison.
+//!
+//! Paired device certificates are kept in a [`Storage`] backend; by default
+//! the `<device_id>.pem` files next to this device's own certificate.
+//!
+//! Request and accept packets also carry an ephemeral key for the payload key
+//! agreement (see [`payload_crypto`](crate::payload_crypto)). The resulting
+//! key is stored next to the certificate when both sides sent one.
+//!
 //! ## References
 //! - [Valent Protocol Reference](https://valent.andyholmes.ca/documentation/protocol.html)
 //! - [CConnect TLS Implementation](https://invent.kde.org/network/cconnect-kde)
 
+use super::pin::{respond, PinChallenge, PinConfirmation};
+use crate::payload_crypto::{PayloadKey, PayloadKeyExchange, PAYLOAD_KEY_FIELD};
+use crate::storage::{FileStorage, Storage, StorageNamespace};
 use crate::{Packet, ProtocolError, Result};
 use cosmic_ext_connect_core::crypto::CertificateInfo;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, info, warn};
 
@@ -55,6 +69,39 @@ pub enum PairingStatus {
     RequestedByPeer,
     /// Successfully paired
     Paired,
+    /// The last pairing attempt failed
+    Rejected {
+        /// Why pairing failed
+        reason: RejectReason,
+    },
+}
+
+/// Why a pairing attempt failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectReason {
+    /// Nobody answered the request in time
+    TimedOut,
+    /// The user declined, here or on the other device
+    UserDeclined,
+    /// The other device speaks a protocol version we cannot pair with
+    VersionMismatch,
+    /// The other device's certificate is not the one expected for it
+    FingerprintConflict,
+    /// The PIN entered on the other device did not match
+    PinMismatch,
+}
+
+impl std::fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RejectReason::TimedOut => "request timed out",
+            RejectReason::UserDeclined => "declined",
+            RejectReason::VersionMismatch => "incompatible protocol version",
+            RejectReason::FingerprintConflict => "certificate does not match",
+            RejectReason::PinMismatch => "wrong PIN",
+        })
+    }
 }
 
 /// Pairing request/response packet
@@ -97,6 +144,27 @@ impl PairingPacket {
         )
     }
 
+    /// Create a pairing request packet asking the peer to confirm a PIN
+    pub fn request_with_pin(share: &str) -> Packet {
+        let mut packet = Self::request();
+        packet.body["pinShare"] = json!(share);
+        packet
+    }
+
+    /// Create a pairing accept response packet answering a PIN request
+    pub fn accept_with_pin(share: &str, proof: &str) -> Packet {
+        let mut packet = Self::accept_with_pin_proof(proof);
+        packet.body["pinShare"] = json!(share);
+        packet
+    }
+
+    /// Create a pairing accept packet carrying a PIN key confirmation
+    pub fn accept_with_pin_proof(proof: &str) -> Packet {
+        let mut packet = Self::accept();
+        packet.body["pinProof"] = json!(proof);
+        packet
+    }
+
     /// Create a pairing reject response packet
     pub fn reject() -> Packet {
         Packet::new("cconnect.pair", json!({ "pair": false }))
@@ -123,6 +191,23 @@ impl PairingPacket {
     }
 }
 
+/// Start a payload key agreement by adding our half to `packet`
+///
+/// Without randomness the packet goes out without one and no payload key is
+/// agreed.
+fn offer_payload_key(packet: &mut Packet) -> Option<PayloadKeyExchange> {
+    match PayloadKeyExchange::new() {
+        Ok(exchange) => {
+            packet.body[PAYLOAD_KEY_FIELD] = json!(exchange.public_key());
+            Some(exchange)
+        }
+        Err(e) => {
+            warn!("Pairing without a payload key: {}", e);
+            None
+        }
+    }
+}
+
 /// Pairing handler for managing device pairing
 pub struct PairingHandler {
     /// This device's certificate
@@ -131,11 +216,29 @@ pub struct PairingHandler {
     /// Pairing status
     status: PairingStatus,
 
+    /// Device the pending request (ours or the peer's) is with
+    request_device: Option<String>,
+
     /// Paired device certificates (device_id -> certificate)
     paired_devices: std::collections::HashMap<String, Vec<u8>>,
 
-    /// Certificate storage directory
-    cert_dir: PathBuf,
+    /// Backend holding paired device certificates
+    storage: Arc<dyn Storage>,
+
+    /// PIN we issued for our outstanding request
+    pin_challenge: Option<PinChallenge>,
+
+    /// PIN share of a received request that must be confirmed with a PIN
+    peer_pin_share: Option<String>,
+
+    /// Confirmation still expected for a PIN request we answered
+    pin_confirmation: Option<PinConfirmation>,
+
+    /// Our half of the payload key agreement for the outstanding request
+    key_exchange: Option<PayloadKeyExchange>,
+
+    /// Payload key agreement half sent with a received request
+    peer_payload_key: Option<String>,
 }
 
 impl PairingHandler {
@@ -166,12 +269,33 @@ impl PairingHandler {
             cert
         };
 
-        Ok(Self {
+        let storage = FileStorage::new(&cert_dir).with_location(
+            StorageNamespace::Certificates,
+            &cert_dir,
+            "pem",
+        );
+        Ok(Self::with_storage(certificate, Arc::new(storage)))
+    }
+
+    /// Create a pairing handler keeping paired device certificates in `storage`
+    ///
+    /// # Arguments
+    ///
+    /// * `certificate` - This device's certificate
+    /// * `storage` - Backend for paired device certificates
+    pub fn with_storage(certificate: CertificateInfo, storage: Arc<dyn Storage>) -> Self {
+        Self {
             certificate,
             status: PairingStatus::Unpaired,
+            request_device: None,
             paired_devices: std::collections::HashMap::new(),
-            cert_dir,
-        })
+            storage,
+            pin_challenge: None,
+            peer_pin_share: None,
+            pin_confirmation: None,
+            key_exchange: None,
+            peer_payload_key: None,
+        }
     }
 
     /// Get this device's certificate fingerprint
@@ -189,11 +313,36 @@ impl PairingHandler {
         self.status
     }
 
-    /// Send pairing request
-    pub fn request_pairing(&mut self) -> Packet {
+    /// Send pairing request to `device_id`
+    pub fn request_pairing(&mut self, device_id: &str) -> Packet {
         self.status = PairingStatus::Requested;
+        self.request_device = Some(device_id.to_string());
+        self.pin_challenge = None;
         info!("Sending pairing request");
-        PairingPacket::request()
+        let mut packet = PairingPacket::request();
+        self.key_exchange = offer_payload_key(&mut packet);
+        packet
+    }
+
+    /// Send a pairing request confirmed by PIN to `device_id`
+    ///
+    /// Returns the request packet and the PIN to show the user.
+    pub fn request_pairing_with_pin(&mut self, device_id: &str) -> Result<(Packet, String)> {
+        let challenge = PinChallenge::generate()?;
+        let mut packet = PairingPacket::request_with_pin(challenge.share());
+        self.key_exchange = offer_payload_key(&mut packet);
+        let pin = challenge.pin().to_string();
+
+        self.status = PairingStatus::Requested;
+        self.request_device = Some(device_id.to_string());
+        self.pin_challenge = Some(challenge);
+        info!("Sending pairing request with PIN confirmation");
+        Ok((packet, pin))
+    }
+
+    /// Whether the pending request from the peer must be confirmed with a PIN
+    pub fn peer_requires_pin(&self) -> bool {
+        self.status == PairingStatus::RequestedByPeer && self.peer_pin_share.is_some()
     }
 
     /// Handle incoming pairing packet
@@ -220,24 +369,80 @@ impl PairingHandler {
         if pairing.pair {
             // Pairing request or accept
             match self.status {
-                PairingStatus::Unpaired => {
+                PairingStatus::Unpaired | PairingStatus::Rejected { .. } => {
                     // Received pairing request
                     self.status = PairingStatus::RequestedByPeer;
+                    self.request_device = Some(device_id.to_string());
+                    self.peer_pin_share = packet.get_body_field::<String>("pinShare");
+                    self.peer_payload_key = packet.get_body_field::<String>(PAYLOAD_KEY_FIELD);
                     info!("Received pairing request from device {}", device_id);
                     // Don't auto-accept, wait for user confirmation
                     Ok((false, None))
                 }
                 PairingStatus::Requested => {
+                    // A PIN request is only accepted with a valid proof, and
+                    // our confirmation goes back with the accept
+                    let mut confirmation = PairingPacket::accept();
+                    if let Some(challenge) = self.pin_challenge.take() {
+                        let share = packet.get_body_field::<String>("pinShare");
+                        let proof = packet.get_body_field::<String>("pinProof");
+                        let their_fingerprint = CertificateInfo::calculate_fingerprint(device_cert);
+                        let our_proof = share
+                            .zip(proof)
+                            .filter(|_| !challenge.is_expired(PAIRING_TIMEOUT))
+                            .and_then(|(share, proof)| {
+                                let ours = self.fingerprint();
+                                challenge.confirm(ours, &their_fingerprint, &share, &proof)
+                            });
+                        let Some(our_proof) = our_proof else {
+                            self.status = PairingStatus::Unpaired;
+                            self.key_exchange = None;
+                            warn!("Pairing PIN from device {} did not match", device_id);
+                            return Err(ProtocolError::CertificateValidation(format!(
+                                "Pairing PIN confirmation from device {} failed",
+                                device_id
+                            )));
+                        };
+                        confirmation = PairingPacket::accept_with_pin_proof(&our_proof);
+                    }
+
                     // Received pairing accept - send confirmation response
                     self.store_device_certificate(device_id, device_cert)?;
+                    let exchange = self.key_exchange.take();
+                    let peer_key = packet.get_body_field::<String>(PAYLOAD_KEY_FIELD);
+                    self.store_payload_key(device_id, device_cert, exchange, peer_key);
                     self.status = PairingStatus::Paired;
                     info!(
                         "Pairing accepted by device {} - sending confirmation",
                         device_id
                     );
-                    Ok((true, Some(PairingPacket::accept())))
+                    Ok((true, Some(confirmation)))
                 }
                 PairingStatus::RequestedByPeer => {
+                    if let Some(expected) = self.pin_confirmation.take() {
+                        // The initiator's confirmation of a PIN request we answered
+                        let confirmed = packet
+                            .get_body_field::<String>("pinProof")
+                            .is_some_and(|proof| expected.verify(&proof));
+                        if !confirmed {
+                            self.status = PairingStatus::Unpaired;
+                            self.clear_request();
+                            warn!("Pairing PIN from device {} did not match", device_id);
+                            return Err(ProtocolError::CertificateValidation(format!(
+                                "Pairing PIN confirmation from device {} failed",
+                                device_id
+                            )));
+                        }
+
+                        self.store_device_certificate(device_id, device_cert)?;
+                        let exchange = self.key_exchange.take();
+                        let peer_key = self.peer_payload_key.take();
+                        self.store_payload_key(device_id, device_cert, exchange, peer_key);
+                        self.status = PairingStatus::Paired;
+                        info!("Pairing with device {} confirmed by PIN", device_id);
+                        return Ok((false, None));
+                    }
+
                     // Already have a pending request from this device
                     warn!("Received duplicate pairing request from {}", device_id);
                     Ok((false, None))
@@ -256,10 +461,14 @@ impl PairingHandler {
             if self.status == PairingStatus::Paired {
                 self.remove_device_certificate(device_id)?;
                 info!("Unpaired from device {}", device_id);
+                self.status = PairingStatus::Unpaired;
             } else {
                 info!("Pairing rejected by device {}", device_id);
+                self.status = PairingStatus::Rejected {
+                    reason: RejectReason::UserDeclined,
+                };
             }
-            self.status = PairingStatus::Unpaired;
+            self.clear_request();
             Ok((false, None))
         }
     }
@@ -271,21 +480,154 @@ impl PairingHandler {
                 "No pairing request pending".to_string(),
             ));
         }
+        if self.peer_pin_share.is_some() || self.pin_confirmation.is_some() {
+            return Err(ProtocolError::InvalidState(format!(
+                "Pairing request from device {} must be confirmed with a PIN",
+                device_id
+            )));
+        }
 
         self.store_device_certificate(device_id, device_cert)?;
+        let mut packet = PairingPacket::accept();
+        self.answer_payload_key(device_id, device_cert, &mut packet);
         self.status = PairingStatus::Paired;
         info!("Accepted pairing with device {}", device_id);
 
-        Ok(PairingPacket::accept())
+        Ok(packet)
+    }
+
+    /// Accept a PIN request with the PIN the user entered
+    ///
+    /// The peer checks the PIN and answers with its own confirmation (or a
+    /// reject). Pairing only completes once that confirmation arrives, so the
+    /// status stays [`PairingStatus::RequestedByPeer`] until then.
+    pub fn accept_pairing_with_pin(
+        &mut self,
+        device_id: &str,
+        device_cert: &[u8],
+        pin: &str,
+    ) -> Result<Packet> {
+        if self.status != PairingStatus::RequestedByPeer {
+            return Err(ProtocolError::InvalidPacket(
+                "No pairing request pending".to_string(),
+            ));
+        }
+        let share = self.peer_pin_share.take().ok_or_else(|| {
+            ProtocolError::InvalidState(format!(
+                "Pairing request from device {} did not ask for a PIN",
+                device_id
+            ))
+        })?;
+
+        let their_fingerprint = CertificateInfo::calculate_fingerprint(device_cert);
+        let response = respond(pin, &share, &their_fingerprint, self.fingerprint())?;
+
+        let mut packet = PairingPacket::accept_with_pin(&response.share, &response.proof);
+        // The key is only stored once the initiator has confirmed the PIN
+        if self.peer_payload_key.is_some() {
+            self.key_exchange = offer_payload_key(&mut packet);
+        }
+        self.pin_confirmation = Some(response.confirmation);
+        info!(
+            "Answered PIN pairing request from device {}, waiting for confirmation",
+            device_id
+        );
+
+        Ok(packet)
     }
 
     /// Reject pairing request (user declined)
     pub fn reject_pairing(&mut self) -> Packet {
         self.status = PairingStatus::Unpaired;
+        self.clear_request();
         info!("Rejected pairing request");
         PairingPacket::reject()
     }
 
+    /// Drop the pending request (ours or the peer's) with `device_id` that
+    /// went unanswered
+    ///
+    /// A request pending with another device is left alone. Returns `true` if
+    /// a request with `device_id` was pending.
+    pub fn expire_request(&mut self, device_id: &str) -> bool {
+        match self.status {
+            PairingStatus::Requested | PairingStatus::RequestedByPeer
+                if self.request_device.as_deref() == Some(device_id) =>
+            {
+                self.status = PairingStatus::Unpaired;
+                self.clear_request();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Forget the state of a request that ended without pairing
+    fn clear_request(&mut self) {
+        self.request_device = None;
+        self.pin_challenge = None;
+        self.peer_pin_share = None;
+        self.pin_confirmation = None;
+        self.key_exchange = None;
+        self.peer_payload_key = None;
+    }
+
+    /// Add our half of the payload key agreement to an accept and finish it
+    fn answer_payload_key(&mut self, device_id: &str, device_cert: &[u8], packet: &mut Packet) {
+        let peer_key = self.peer_payload_key.take();
+        if peer_key.is_none() {
+            debug!("Device {} does not support payload encryption", device_id);
+            return;
+        }
+        let exchange = offer_payload_key(packet);
+        self.store_payload_key(device_id, device_cert, exchange, peer_key);
+    }
+
+    /// Derive and store the payload key once both halves are known
+    ///
+    /// Pairing succeeds without a payload key; transfers that require one are
+    /// refused later instead.
+    fn store_payload_key(
+        &mut self,
+        device_id: &str,
+        device_cert: &[u8],
+        exchange: Option<PayloadKeyExchange>,
+        peer_key: Option<String>,
+    ) {
+        let (Some(exchange), Some(peer_key)) = (exchange, peer_key) else {
+            debug!("No payload key agreed with device {}", device_id);
+            return;
+        };
+        let their_fingerprint = CertificateInfo::calculate_fingerprint(device_cert);
+        let stored = exchange
+            .complete(&peer_key, self.fingerprint(), &their_fingerprint)
+            .and_then(|key| {
+                self.storage.set(
+                    StorageNamespace::PayloadKeys,
+                    device_id,
+                    key.to_base64().as_bytes(),
+                )
+            });
+        match stored {
+            Ok(()) => debug!("Stored payload key for device {}", device_id),
+            Err(e) => warn!("No payload key for device {}: {}", device_id, e),
+        }
+    }
+
+    /// Payload encryption key agreed with a paired device, if any
+    pub fn payload_key(&self, device_id: &str) -> Result<Option<PayloadKey>> {
+        let Some(encoded) = self.storage.get(StorageNamespace::PayloadKeys, device_id)? else {
+            return Ok(None);
+        };
+        let encoded = String::from_utf8(encoded).map_err(|_| {
+            ProtocolError::InvalidPacket(format!(
+                "Stored payload key for {} is not text",
+                device_id
+            ))
+        })?;
+        PayloadKey::from_base64(&encoded).map(Some)
+    }
+
     /// Unpair from a device
     pub fn unpair(&mut self, device_id: &str) -> Result<Packet> {
         self.remove_device_certificate(device_id)?;
@@ -301,26 +643,26 @@ impl PairingHandler {
 
     /// Store device certificate
     fn store_device_certificate(&mut self, device_id: &str, cert_der: &[u8]) -> Result<()> {
-        let cert_path = self.cert_dir.join(format!("{}.pem", device_id));
         let cert_pem = pem::encode(&pem::Pem::new("CERTIFICATE", cert_der.to_vec()));
-        fs::write(&cert_path, cert_pem)?;
+        self.storage.set(
+            StorageNamespace::Certificates,
+            device_id,
+            cert_pem.as_bytes(),
+        )?;
 
         self.paired_devices
             .insert(device_id.to_string(), cert_der.to_vec());
-        debug!(
-            "Stored certificate for device {} at {:?}",
-            device_id, cert_path
-        );
+        debug!("Stored certificate for device {}", device_id);
 
         Ok(())
     }
 
     /// Remove device certificate
     fn remove_device_certificate(&mut self, device_id: &str) -> Result<()> {
-        let cert_path = self.cert_dir.join(format!("{}.pem", device_id));
-        if cert_path.exists() {
-            fs::remove_file(&cert_path)?;
-        }
+        self.storage
+            .delete(StorageNamespace::Certificates, device_id)?;
+        self.storage
+            .delete(StorageNamespace::PayloadKeys, device_id)?;
 
         self.paired_devices.remove(device_id);
         debug!("Removed certificate for device {}", device_id);
@@ -330,48 +672,41 @@ impl PairingHandler {
 
     /// Load all paired device certificates
     pub fn load_paired_devices(&mut self) -> Result<()> {
-        for entry in fs::read_dir(&self.cert_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-
-            if path.extension().and_then(|s| s.to_str()) == Some("pem") {
-                let filename = path.file_stem().and_then(|s| s.to_str());
-                if let Some(device_id) = filename {
-                    // Skip our own certificate
-                    if device_id == "device_cert" || device_id == "device_key" {
-                        continue;
-                    }
+        for device_id in self.storage.keys(StorageNamespace::Certificates)? {
+            // Skip our own certificate
+            if device_id == "device_cert" || device_id == "device_key" {
+                continue;
+            }
 
-                    // Load certificate (PEM format) and extract DER
-                    // Paired device certificates are stored as cert only, no private key needed
-                    let cert_data = match fs::read(&path) {
-                        Ok(data) => data,
-                        Err(e) => {
-                            warn!("Failed to read certificate file for {}: {}", device_id, e);
-                            continue;
-                        }
-                    };
+            // Load certificate (PEM format) and extract DER
+            // Paired device certificates are stored as cert only, no private key needed
+            let cert_data = match self.storage.get(StorageNamespace::Certificates, &device_id) {
+                Ok(Some(data)) => data,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("Failed to read certificate for {}: {}", device_id, e);
+                    continue;
+                }
+            };
 
-                    let cert_pem = match pem::parse(&cert_data) {
-                        Ok(pem) => pem,
-                        Err(e) => {
-                            warn!("Failed to parse certificate PEM for {}: {}", device_id, e);
-                            continue;
-                        }
-                    };
-
-                    if cert_pem.tag() == "CERTIFICATE" {
-                        self.paired_devices
-                            .insert(device_id.to_string(), cert_pem.contents().to_vec());
-                        debug!("Loaded paired device certificate: {}", device_id);
-                    } else {
-                        warn!(
-                            "Invalid certificate tag for {}: expected CERTIFICATE, got {}",
-                            device_id,
-                            cert_pem.tag()
-                        );
-                    }
+            let cert_pem = match pem::parse(&cert_data) {
+                Ok(pem) => pem,
+                Err(e) => {
+                    warn!("Failed to parse certificate PEM for {}: {}", device_id, e);
+                    continue;
                 }
+            };
+
+            if cert_pem.tag() == "CERTIFICATE" {
+                debug!("Loaded paired device certificate: {}", device_id);
+                self.paired_devices
+                    .insert(device_id, cert_pem.contents().to_vec());
+            } else {
+                warn!(
+                    "Invalid certificate tag for {}: expected CERTIFICATE, got {}",
+                    device_id,
+                    cert_pem.tag()
+                );
             }
         }
 
@@ -459,11 +794,210 @@ mod tests {
         let mut handler = PairingHandler::new("test_device", temp_dir.path()).unwrap();
 
         // Send pairing request
-        let request = handler.request_pairing();
+        let request = handler.request_pairing("peer");
         assert_eq!(handler.status(), PairingStatus::Requested);
         assert!(request.is_type("cconnect.pair"));
     }
 
+    #[test]
+    fn test_expire_request() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut handler = PairingHandler::new("test_device", temp_dir.path()).unwrap();
+
+        assert!(!handler.expire_request("peer"));
+
+        handler.request_pairing("peer");
+        // A request with another device is not affected
+        assert!(!handler.expire_request("other"));
+        assert_eq!(handler.status(), PairingStatus::Requested);
+
+        assert!(handler.expire_request("peer"));
+        assert_eq!(handler.status(), PairingStatus::Unpaired);
+    }
+
+    #[test]
+    fn test_peer_reject_records_reason() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut handler = PairingHandler::new("test_device", temp_dir.path()).unwrap();
+
+        handler.request_pairing("peer");
+        handler
+            .handle_pairing_packet(&PairingPacket::reject(), "peer", b"peer-certificate")
+            .unwrap();
+        assert_eq!(
+            handler.status(),
+            PairingStatus::Rejected {
+                reason: RejectReason::UserDeclined
+            }
+        );
+
+        // A later request from the peer is handled as usual
+        handler
+            .handle_pairing_packet(&PairingPacket::request(), "peer", b"peer-certificate")
+            .unwrap();
+        assert_eq!(handler.status(), PairingStatus::RequestedByPeer);
+    }
+
+    fn pin_handlers() -> (TempDir, TempDir, PairingHandler, PairingHandler) {
+        let dir_a = TempDir::new().unwrap();
+        let dir_b = TempDir::new().unwrap();
+        let initiator = PairingHandler::new("initiator", dir_a.path()).unwrap();
+        let responder = PairingHandler::new("responder", dir_b.path()).unwrap();
+        (dir_a, dir_b, initiator, responder)
+    }
+
+    #[test]
+    fn test_matching_pin_completes_pairing() {
+        let (_a, _b, mut initiator, mut responder) = pin_handlers();
+        let initiator_cert = initiator.certificate().certificate.clone();
+        let responder_cert = responder.certificate().certificate.clone();
+
+        let (request, pin) = initiator.request_pairing_with_pin("responder").unwrap();
+        assert!(request.get_body_field::<String>("pinShare").is_some());
+        responder
+            .handle_pairing_packet(&request, "initiator", &initiator_cert)
+            .unwrap();
+        assert!(responder.peer_requires_pin());
+
+        // A plain accept is not enough for a PIN request
+        assert!(responder
+            .accept_pairing("initiator", &initiator_cert)
+            .is_err());
+
+        let accept = responder
+            .accept_pairing_with_pin("initiator", &initiator_cert, &pin)
+            .unwrap();
+        // Not paired until the initiator has confirmed the PIN too
+        assert!(!responder.is_paired("initiator"));
+        assert!(responder
+            .accept_pairing("initiator", &initiator_cert)
+            .is_err());
+
+        let (respond, confirmation) = initiator
+            .handle_pairing_packet(&accept, "responder", &responder_cert)
+            .unwrap();
+        assert!(respond);
+        assert_eq!(initiator.status(), PairingStatus::Paired);
+        assert!(initiator.is_paired("responder"));
+
+        responder
+            .handle_pairing_packet(&confirmation.unwrap(), "initiator", &initiator_cert)
+            .unwrap();
+        assert_eq!(responder.status(), PairingStatus::Paired);
+        assert!(responder.is_paired("initiator"));
+        assert_eq!(
+            initiator.payload_key("responder").unwrap(),
+            responder.payload_key("initiator").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_pin_confirmation_required_from_initiator() {
+        let (_a, _b, mut initiator, mut responder) = pin_handlers();
+        let initiator_cert = initiator.certificate().certificate.clone();
+
+        let (request, pin) = initiator.request_pairing_with_pin("responder").unwrap();
+        responder
+            .handle_pairing_packet(&request, "initiator", &initiator_cert)
+            .unwrap();
+        responder
+            .accept_pairing_with_pin("initiator", &initiator_cert, &pin)
+            .unwrap();
+
+        // A plain accept without a valid proof does not complete pairing
+        let result =
+            responder.handle_pairing_packet(&PairingPacket::accept(), "initiator", &initiator_cert);
+        assert!(matches!(
+            result,
+            Err(ProtocolError::CertificateValidation(_))
+        ));
+        assert_eq!(responder.status(), PairingStatus::Unpaired);
+        assert!(!responder.is_paired("initiator"));
+    }
+
+    #[test]
+    fn test_pairing_agrees_payload_key() {
+        let (_a, _b, mut initiator, mut responder) = pin_handlers();
+        let initiator_cert = initiator.certificate().certificate.clone();
+        let responder_cert = responder.certificate().certificate.clone();
+
+        let request = initiator.request_pairing("responder");
+        assert!(request
+            .get_body_field::<String>(PAYLOAD_KEY_FIELD)
+            .is_some());
+        responder
+            .handle_pairing_packet(&request, "initiator", &initiator_cert)
+            .unwrap();
+        let accept = responder
+            .accept_pairing("initiator", &initiator_cert)
+            .unwrap();
+        initiator
+            .handle_pairing_packet(&accept, "responder", &responder_cert)
+            .unwrap();
+
+        let initiator_key = initiator.payload_key("responder").unwrap();
+        assert!(initiator_key.is_some());
+        assert_eq!(initiator_key, responder.payload_key("initiator").unwrap());
+
+        initiator.unpair("responder").unwrap();
+        assert_eq!(initiator.payload_key("responder").unwrap(), None);
+    }
+
+    #[test]
+    fn test_peer_without_payload_key_pairs() {
+        let (_a, _b, initiator, mut responder) = pin_handlers();
+        let initiator_cert = initiator.certificate().certificate.clone();
+
+        // An older client's request carries no key
+        responder
+            .handle_pairing_packet(&PairingPacket::request(), "initiator", &initiator_cert)
+            .unwrap();
+        let accept = responder
+            .accept_pairing("initiator", &initiator_cert)
+            .unwrap();
+
+        assert!(accept.get_body_field::<String>(PAYLOAD_KEY_FIELD).is_none());
+        assert!(responder.is_paired("initiator"));
+        assert_eq!(responder.payload_key("initiator").unwrap(), None);
+    }
+
+    #[test]
+    fn test_wrong_pin_aborts_pairing() {
+        let (_a, _b, mut initiator, mut responder) = pin_handlers();
+        let initiator_cert = initiator.certificate().certificate.clone();
+        let responder_cert = responder.certificate().certificate.clone();
+
+        let (request, pin) = initiator.request_pairing_with_pin("responder").unwrap();
+        responder
+            .handle_pairing_packet(&request, "initiator", &initiator_cert)
+            .unwrap();
+        let wrong_pin = if pin == "123456" { "654321" } else { "123456" };
+        let accept = responder
+            .accept_pairing_with_pin("initiator", &initiator_cert, wrong_pin)
+            .unwrap();
+
+        let result = initiator.handle_pairing_packet(&accept, "responder", &responder_cert);
+        assert!(matches!(
+            result,
+            Err(ProtocolError::CertificateValidation(_))
+        ));
+        assert_eq!(initiator.status(), PairingStatus::Unpaired);
+        assert!(!initiator.is_paired("responder"));
+
+        // The PIN was single-use: replaying an answer does not pair
+        initiator
+            .handle_pairing_packet(&accept, "responder", &responder_cert)
+            .unwrap();
+        assert!(!initiator.is_paired("responder"));
+
+        // The responder never stored the certificate; the reject ends its request
+        assert!(!responder.is_paired("initiator"));
+        responder
+            .handle_pairing_packet(&PairingPacket::reject(), "initiator", &initiator_cert)
+            .unwrap();
+        assert!(!responder.is_paired("initiator"));
+    }
+
     #[test]
     fn test_certificate_fingerprint() {
         let cert1 = CertificateInfo::generate("device1").unwrap();
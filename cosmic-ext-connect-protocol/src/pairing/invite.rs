@@ -0,0 +1,320 @@
+//! Pairing Invites
+//!
+//! Comparing fingerprints or typing a verification key is error-prone on a
+//! phone. Instead, a device can show a pairing invite as a QR code: a short
+//! URI naming the device, where to reach it, and the SHA-256 digest of its
+//! certificate. The device that scans it already knows which certificate to
+//! expect, so [`PairingService::pair_with_invite`] can pair without asking
+//! the user to confirm anything.
+//!
+//! ## Format
+//!
+//! `cconnect://pair/<payload>`, where the payload is unpadded URL-safe base64
+//! of:
+//!
+//! | Field | Size |
+//! |-------|------|
+//! | Format version ([`INVITE_VERSION`]) | 1 |
+//! | Certificate SHA-256 digest | 32 |
+//! | Device ID, name and type | 1-byte length + UTF-8 each |
+//! | Address count | 1 |
+//! | Addresses | 1-byte family (4 or 6) + IP + 2-byte port each |
+//!
+//! Every field is length-checked, so a truncated or padded scan is rejected
+//! rather than yielding a half-read invite.
+//!
+//! [`PairingService::pair_with_invite`]: super::PairingService::pair_with_invite
+
+use crate::{DeviceInfo, DeviceType, ProtocolError, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use cosmic_ext_connect_core::crypto::CertificateInfo;
+use sha2::{Digest, Sha256};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Prefix of every pairing URI
+pub const PAIRING_URI_PREFIX: &str = "cconnect://pair/";
+
+/// Current invite payload format
+pub const INVITE_VERSION: u8 = 1;
+
+/// Most addresses put in an invite, to keep the QR code scannable
+pub const MAX_INVITE_ADDRESSES: usize = 4;
+
+const DIGEST_LEN: usize = 32;
+const FAMILY_V4: u8 = 4;
+const FAMILY_V6: u8 = 6;
+
+/// A device's pairing invite, read from a scanned [`pairing_uri`]
+#[derive(Debug, Clone)]
+pub struct PairingInvite {
+    /// The inviting device, with the addresses it can be reached at
+    pub device_info: DeviceInfo,
+    /// SHA-256 digest of the inviting device's certificate
+    certificate_digest: [u8; DIGEST_LEN],
+}
+
+impl PairingInvite {
+    /// Whether `certificate` (DER) is the one the invite was issued for
+    pub fn matches_certificate(&self, certificate: &[u8]) -> bool {
+        certificate_digest(certificate) == self.certificate_digest
+    }
+
+    /// Address to contact first
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.device_info.addresses.first().copied()
+    }
+}
+
+fn certificate_digest(certificate: &[u8]) -> [u8; DIGEST_LEN] {
+    Sha256::digest(certificate).into()
+}
+
+/// Build a pairing URI for `info`, suitable for rendering as a QR code
+///
+/// The invite lists up to [`MAX_INVITE_ADDRESSES`] of `info.addresses`,
+/// which should hold this device's own addresses, best route first.
+pub fn pairing_uri(info: &DeviceInfo, certificate: &CertificateInfo) -> String {
+    let mut payload = vec![INVITE_VERSION];
+    payload.extend_from_slice(&certificate_digest(&certificate.certificate));
+    for field in [
+        info.device_id.as_str(),
+        info.device_name.as_str(),
+        info.device_type.as_str(),
+    ] {
+        put_str(&mut payload, field);
+    }
+
+    let addresses = &info.addresses[..info.addresses.len().min(MAX_INVITE_ADDRESSES)];
+    payload.push(addresses.len() as u8);
+    for address in addresses {
+        match address.ip() {
+            IpAddr::V4(ip) => {
+                payload.push(FAMILY_V4);
+                payload.extend_from_slice(&ip.octets());
+            }
+            IpAddr::V6(ip) => {
+                payload.push(FAMILY_V6);
+                payload.extend_from_slice(&ip.octets());
+            }
+        }
+        payload.extend_from_slice(&address.port().to_be_bytes());
+    }
+
+    format!("{}{}", PAIRING_URI_PREFIX, URL_SAFE_NO_PAD.encode(payload))
+}
+
+/// This machine's addresses to put in an invite, best route first
+///
+/// Loopback and link-local addresses are left out: the scanning device
+/// cannot reach the former, and an invite has no room for an IPv6 scope.
+///
+/// # Errors
+///
+/// Returns [`ProtocolError::NetworkError`] if the interfaces cannot be read.
+pub fn invite_addresses(port: u16) -> Result<Vec<SocketAddr>> {
+    let mut addresses: Vec<SocketAddr> = crate::connection::subnet::local_subnets()?
+        .into_iter()
+        .map(|subnet| subnet.addr)
+        .filter(|ip| reachable_by_scanner(*ip))
+        .map(|ip| SocketAddr::new(ip, port))
+        .collect();
+    crate::transport::tcp::order_by_route(&mut addresses);
+    addresses.dedup();
+    Ok(addresses)
+}
+
+fn reachable_by_scanner(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => !(ip.is_loopback() || ip.is_link_local() || ip.is_unspecified()),
+        IpAddr::V6(ip) => {
+            !(ip.is_loopback() || ip.is_unspecified() || ip.segments()[0] & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
+/// Read a pairing URI scanned from another device
+///
+/// # Errors
+///
+/// Returns [`ProtocolError::InvalidPacket`] if `uri` is not a pairing URI,
+/// is truncated or has trailing data, uses an unknown format version or
+/// device type, or lists no address.
+pub fn parse_pairing_uri(uri: &str) -> Result<PairingInvite> {
+    let payload = uri
+        .trim()
+        .strip_prefix(PAIRING_URI_PREFIX)
+        .ok_or_else(|| invalid("not a pairing URI"))?;
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(|e| invalid(&format!("bad encoding: {}", e)))?;
+    let mut reader = Reader(&payload);
+
+    let version = reader.take(1)?[0];
+    if version != INVITE_VERSION {
+        return Err(invalid(&format!("unsupported version {}", version)));
+    }
+    let certificate_digest = reader.take(DIGEST_LEN)?.try_into().expect("digest length");
+    let device_id = reader.take_str()?;
+    let device_name = reader.take_str()?;
+    let device_type = reader.take_str()?;
+    let device_type = DeviceType::from_name(&device_type)
+        .ok_or_else(|| invalid(&format!("unknown device type {}", device_type)))?;
+
+    let count = reader.take(1)?[0];
+    let mut addresses = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let ip = match reader.take(1)?[0] {
+            FAMILY_V4 => {
+                let octets: [u8; 4] = reader.take(4)?.try_into().expect("IPv4 length");
+                IpAddr::V4(Ipv4Addr::from(octets))
+            }
+            FAMILY_V6 => {
+                let octets: [u8; 16] = reader.take(16)?.try_into().expect("IPv6 length");
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            family => return Err(invalid(&format!("unknown address family {}", family))),
+        };
+        let port = u16::from_be_bytes(reader.take(2)?.try_into().expect("port length"));
+        addresses.push(SocketAddr::new(ip, port));
+    }
+    if !reader.0.is_empty() {
+        return Err(invalid("trailing data"));
+    }
+    if device_id.is_empty() {
+        return Err(invalid("no device ID"));
+    }
+    let tcp_port = addresses
+        .first()
+        .map(|address| address.port())
+        .ok_or_else(|| invalid("no address"))?;
+
+    let mut device_info = DeviceInfo::with_id(device_id, device_name, device_type, tcp_port);
+    device_info.addresses = addresses;
+    Ok(PairingInvite {
+        device_info,
+        certificate_digest,
+    })
+}
+
+fn invalid(reason: &str) -> ProtocolError {
+    ProtocolError::InvalidPacket(format!("Invalid pairing URI: {}", reason))
+}
+
+/// Append `value` with a one-byte length, cut at a character boundary
+fn put_str(payload: &mut Vec<u8>, value: &str) {
+    let mut end = value.len().min(u8::MAX as usize);
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    payload.push(end as u8);
+    payload.extend_from_slice(&value.as_bytes()[..end]);
+}
+
+/// Cursor over an invite payload
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(invalid("truncated"));
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn take_str(&mut self) -> Result<String> {
+        let len = self.take(1)?[0] as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| invalid("bad text field"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn laptop() -> (DeviceInfo, CertificateInfo) {
+        let mut info = DeviceInfo::with_id("abc_123", "Workstation", DeviceType::Laptop, 1816);
+        info.addresses = vec![
+            "192.168.1.20:1816".parse().unwrap(),
+            "[fe80::1]:1816".parse().unwrap(),
+        ];
+        (info, CertificateInfo::generate("abc_123").unwrap())
+    }
+
+    #[test]
+    fn test_pairing_uri_round_trip() {
+        let (info, certificate) = laptop();
+        let uri = pairing_uri(&info, &certificate);
+        assert!(uri.starts_with(PAIRING_URI_PREFIX));
+        assert!(uri[PAIRING_URI_PREFIX.len()..]
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'));
+
+        let invite = parse_pairing_uri(&uri).unwrap();
+        assert_eq!(invite.device_info.device_id, "abc_123");
+        assert_eq!(invite.device_info.device_name, "Workstation");
+        assert_eq!(invite.device_info.device_type, DeviceType::Laptop);
+        assert_eq!(invite.device_info.addresses, info.addresses);
+        assert_eq!(invite.address(), Some(info.addresses[0]));
+        assert!(invite.matches_certificate(&certificate.certificate));
+        assert!(!invite.matches_certificate(b"another certificate"));
+    }
+
+    #[test]
+    fn test_invite_leaves_out_unreachable_addresses() {
+        for ip in ["127.0.0.1", "169.254.3.4", "::1", "fe80::1", "0.0.0.0"] {
+            assert!(!reachable_by_scanner(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["192.168.1.20", "10.0.0.5", "fd00::20", "2001:db8::1"] {
+            assert!(reachable_by_scanner(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_malformed_pairing_uri_rejected() {
+        let (info, certificate) = laptop();
+        let uri = pairing_uri(&info, &certificate);
+        let payload = &uri[PAIRING_URI_PREFIX.len()..];
+
+        assert!(parse_pairing_uri("").is_err());
+        assert!(parse_pairing_uri(&format!("https://example.com/{}", payload)).is_err());
+        assert!(parse_pairing_uri(&format!("{}!!not base64", PAIRING_URI_PREFIX)).is_err());
+
+        let mut bytes = URL_SAFE_NO_PAD.decode(payload).unwrap();
+        bytes[0] = INVITE_VERSION + 1;
+        let future = format!("{}{}", PAIRING_URI_PREFIX, URL_SAFE_NO_PAD.encode(&bytes));
+        assert!(parse_pairing_uri(&future).is_err());
+
+        let mut no_address = info.clone();
+        no_address.addresses.clear();
+        assert!(parse_pairing_uri(&pairing_uri(&no_address, &certificate)).is_err());
+    }
+
+    #[test]
+    fn test_truncated_pairing_uri_rejected() {
+        let (info, certificate) = laptop();
+        let uri = pairing_uri(&info, &certificate);
+        let bytes = URL_SAFE_NO_PAD
+            .decode(&uri[PAIRING_URI_PREFIX.len()..])
+            .unwrap();
+
+        for len in 0..bytes.len() {
+            let truncated = format!(
+                "{}{}",
+                PAIRING_URI_PREFIX,
+                URL_SAFE_NO_PAD.encode(&bytes[..len])
+            );
+            assert!(
+                parse_pairing_uri(&truncated).is_err(),
+                "accepted {len} bytes"
+            );
+        }
+
+        let mut padded = bytes.clone();
+        padded.push(0);
+        let padded = format!("{}{}", PAIRING_URI_PREFIX, URL_SAFE_NO_PAD.encode(&padded));
+        assert!(parse_pairing_uri(&padded).is_err());
+    }
+}
@@ -12,6 +12,9 @@
 //! 6. **Certificate Exchange**: Devices exchange and store certificates
 //! 7. **TLS Connection**: Future connections use TLS with stored certificates
 //!
+//! Step 4 can be skipped by scanning a QR code instead: the code holds a
+//! pairing invite naming the certificate to expect (see [`invite`]).
+//!
 //! ## Usage
 //!
 //! ```no_run
@@ -28,13 +31,21 @@
 //! }
 //! ```
 
+pub mod batch;
 pub mod events;
 pub mod handler;
+pub mod invite;
+pub mod pin;
 pub mod service;
 
 // Re-export main types
+pub use batch::{
+    batch_pair, BatchPairCandidate, BatchPairFilter, BatchPairResult, PairOutcome, PairingTarget,
+};
 pub use events::PairingEvent;
-pub use handler::{PairingHandler, PairingPacket, PairingStatus, PAIRING_TIMEOUT};
+pub use handler::{PairingHandler, PairingPacket, PairingStatus, RejectReason, PAIRING_TIMEOUT};
+pub use invite::{invite_addresses, pairing_uri, parse_pairing_uri, PairingInvite};
+pub use pin::{PinChallenge, PIN_LENGTH};
 pub use service::{PairingConfig, PairingService};
 
 // CertificateInfo now comes from cosmic-ext-connect-core (re-exported in lib.rs)
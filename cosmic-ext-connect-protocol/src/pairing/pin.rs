@@ -0,0 +1,324 @@
+//! Pairing PIN
+//!
+//! Optional PIN confirmation for clients that pair with a short code instead
+//! of comparing certificate fingerprints.
+//!
+//! ## Flow
+//!
+//! The PIN is turned into a shared key with SPAKE2 over ristretto255, so
+//! neither side ever sends anything derived from the PIN alone.
+//!
+//! 1. The initiator generates a [`PinChallenge`], shows its PIN to the user
+//!    and sends `cconnect.pair` with `pair: true` and `pinShare`, its SPAKE2
+//!    message.
+//! 2. The user types the PIN on the responder, which answers with
+//!    [`respond`]: `pair: true`, its own `pinShare` and `pinProof`, a key
+//!    confirmation over both shares and both certificate fingerprints.
+//! 3. The initiator checks the proof with [`PinChallenge::confirm`] against
+//!    the certificate it actually sees and, if it matches, confirms with its
+//!    own `pinProof`.
+//! 4. The responder checks that proof with [`PinConfirmation::verify`] and
+//!    only then stores the initiator's certificate.
+//!
+//! A wrong PIN or a substituted certificate fails either check, and pairing
+//! is aborted with `pair: false`. An attacker who takes part in the exchange
+//! gets exactly one guess at the PIN per request; the messages it sees do not
+//! let it test other PINs offline. A challenge is single-use: it is consumed
+//! by the first answer, right or wrong, and expires with
+//! [`PAIRING_TIMEOUT`](super::PAIRING_TIMEOUT). A one-in-a-million guess is
+//! still a guess, so fingerprint comparison remains the default.
+
+use crate::{ProtocolError, Result};
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::IsIdentity;
+use ring::digest::{Context, SHA256, SHA512};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::time::{Duration, Instant};
+
+/// Number of digits in a pairing PIN
+pub const PIN_LENGTH: usize = 6;
+
+/// Domain separator for the initiator's blinding point
+const SEED_M: &[u8] = b"cconnect-pin-spake2 M";
+
+/// Domain separator for the responder's blinding point
+const SEED_N: &[u8] = b"cconnect-pin-spake2 N";
+
+/// A PIN issued by the pairing initiator
+#[derive(Debug, Clone)]
+pub struct PinChallenge {
+    /// Code shown to the user
+    pin: String,
+    /// Our SPAKE2 secret
+    secret: Scalar,
+    /// Hex SPAKE2 message sent to the peer
+    share: String,
+    /// When the PIN was generated
+    issued_at: Instant,
+}
+
+impl PinChallenge {
+    /// Generate a random PIN and our SPAKE2 message for it
+    pub fn generate() -> Result<Self> {
+        let rng = SystemRandom::new();
+        let pin = random_pin(&rng)?;
+        let secret = random_scalar(&rng)?;
+        let share = blinded_share(&secret, &password_scalar(&pin), SEED_M);
+        Ok(Self {
+            pin,
+            secret,
+            share,
+            issued_at: Instant::now(),
+        })
+    }
+
+    /// The code to display
+    pub fn pin(&self) -> &str {
+        &self.pin
+    }
+
+    /// The SPAKE2 message to send in the pairing request
+    pub fn share(&self) -> &str {
+        &self.share
+    }
+
+    /// Whether the PIN is older than `timeout`
+    pub fn is_expired(&self, timeout: Duration) -> bool {
+        self.issued_at.elapsed() > timeout
+    }
+
+    /// Check the responder's answer
+    ///
+    /// `responder_fingerprint` must be the fingerprint of the certificate the
+    /// peer presented on this connection. Returns the proof to send back if
+    /// the responder used the same PIN.
+    pub fn confirm(
+        &self,
+        initiator_fingerprint: &str,
+        responder_fingerprint: &str,
+        peer_share: &str,
+        proof: &str,
+    ) -> Option<String> {
+        let w = password_scalar(&self.pin);
+        let shared = shared_point(&self.secret, &w, peer_share, SEED_N)?;
+        let key = confirmation_key(
+            &self.share,
+            peer_share,
+            &shared,
+            &w,
+            initiator_fingerprint,
+            responder_fingerprint,
+        );
+        let tag = hex::decode(proof).ok()?;
+        hmac::verify(&key, b"responder", &tag).ok()?;
+        Some(hex::encode(hmac::sign(&key, b"initiator").as_ref()))
+    }
+}
+
+/// The responder's answer to a PIN request
+#[derive(Debug)]
+pub struct PinResponse {
+    /// Hex SPAKE2 message to send back
+    pub share: String,
+    /// Key confirmation to send back
+    pub proof: String,
+    /// Check for the initiator's confirmation
+    pub confirmation: PinConfirmation,
+}
+
+/// Expected key confirmation from the initiator
+#[derive(Debug)]
+pub struct PinConfirmation {
+    key: hmac::Key,
+}
+
+impl PinConfirmation {
+    /// Whether `proof` shows the initiator derived the same key
+    pub fn verify(&self, proof: &str) -> bool {
+        hex::decode(proof).is_ok_and(|tag| hmac::verify(&self.key, b"initiator", &tag).is_ok())
+    }
+}
+
+/// Answer a PIN request with the PIN the user entered
+///
+/// Fails if `peer_share` is not a valid SPAKE2 message.
+pub fn respond(
+    pin: &str,
+    peer_share: &str,
+    initiator_fingerprint: &str,
+    responder_fingerprint: &str,
+) -> Result<PinResponse> {
+    let rng = SystemRandom::new();
+    let secret = random_scalar(&rng)?;
+    let w = password_scalar(pin);
+    let share = blinded_share(&secret, &w, SEED_N);
+    let shared = shared_point(&secret, &w, peer_share, SEED_M)
+        .ok_or_else(|| ProtocolError::InvalidPacket("Invalid pairing PIN share".to_string()))?;
+    let key = confirmation_key(
+        peer_share,
+        &share,
+        &shared,
+        &w,
+        initiator_fingerprint,
+        responder_fingerprint,
+    );
+    Ok(PinResponse {
+        proof: hex::encode(hmac::sign(&key, b"responder").as_ref()),
+        share,
+        confirmation: PinConfirmation { key },
+    })
+}
+
+/// Uniformly random PIN, without modulo bias
+fn random_pin(rng: &SystemRandom) -> Result<String> {
+    let range = 10u32.pow(PIN_LENGTH as u32);
+    // Largest multiple of `range` that fits; draws above it are retried
+    let limit = u32::MAX - u32::MAX % range;
+    loop {
+        let mut code = [0u8; 4];
+        rng.fill(&mut code).map_err(|_| {
+            ProtocolError::ResourceExhausted("Failed to generate pairing PIN".to_string())
+        })?;
+        let value = u32::from_be_bytes(code);
+        if value < limit {
+            return Ok(format!("{:0width$}", value % range, width = PIN_LENGTH));
+        }
+    }
+}
+
+fn random_scalar(rng: &SystemRandom) -> Result<Scalar> {
+    let mut bytes = [0u8; 64];
+    rng.fill(&mut bytes).map_err(|_| {
+        ProtocolError::ResourceExhausted("Failed to generate pairing secret".to_string())
+    })?;
+    Ok(Scalar::from_bytes_mod_order_wide(&bytes))
+}
+
+fn wide_hash(parts: &[&[u8]]) -> [u8; 64] {
+    let mut context = Context::new(&SHA512);
+    for part in parts {
+        context.update(part);
+    }
+    let mut bytes = [0u8; 64];
+    bytes.copy_from_slice(context.finish().as_ref());
+    bytes
+}
+
+fn password_scalar(pin: &str) -> Scalar {
+    Scalar::from_bytes_mod_order_wide(&wide_hash(&[b"cconnect-pin|", pin.trim().as_bytes()]))
+}
+
+/// Fixed point with no known discrete log
+fn blinding_point(seed: &[u8]) -> RistrettoPoint {
+    RistrettoPoint::from_uniform_bytes(&wide_hash(&[seed]))
+}
+
+fn blinded_share(secret: &Scalar, w: &Scalar, seed: &[u8]) -> String {
+    let point = RistrettoPoint::mul_base(secret) + w * blinding_point(seed);
+    hex::encode(point.compress().as_bytes())
+}
+
+/// Our secret times the peer's share with its blinding removed
+fn shared_point(
+    secret: &Scalar,
+    w: &Scalar,
+    peer_share: &str,
+    peer_seed: &[u8],
+) -> Option<RistrettoPoint> {
+    let bytes = hex::decode(peer_share).ok()?;
+    let peer = CompressedRistretto::from_slice(&bytes).ok()?.decompress()?;
+    let shared = secret * (peer - w * blinding_point(peer_seed));
+    (!shared.is_identity()).then_some(shared)
+}
+
+/// HMAC key over the whole transcript, bound to both certificates
+fn confirmation_key(
+    initiator_share: &str,
+    responder_share: &str,
+    shared: &RistrettoPoint,
+    w: &Scalar,
+    initiator_fingerprint: &str,
+    responder_fingerprint: &str,
+) -> hmac::Key {
+    let shared = shared.compress();
+    let mut context = Context::new(&SHA256);
+    for part in [
+        b"cconnect-pin-spake2".as_slice(),
+        initiator_share.as_bytes(),
+        responder_share.as_bytes(),
+        shared.as_bytes(),
+        w.as_bytes(),
+        initiator_fingerprint.as_bytes(),
+        responder_fingerprint.as_bytes(),
+    ] {
+        context.update(&(part.len() as u32).to_be_bytes());
+        context.update(part);
+    }
+    hmac::Key::new(hmac::HMAC_SHA256, context.finish().as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pin_format() {
+        let challenge = PinChallenge::generate().unwrap();
+        assert_eq!(challenge.pin().len(), PIN_LENGTH);
+        assert!(challenge.pin().chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(challenge.share().len(), 64);
+        assert!(!challenge.is_expired(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_proof_binds_pin_and_certificates() {
+        let challenge = PinChallenge::generate().unwrap();
+        let response = respond(challenge.pin(), challenge.share(), "AA", "BB").unwrap();
+
+        let confirmation = challenge
+            .confirm("AA", "BB", &response.share, &response.proof)
+            .unwrap();
+        assert!(response.confirmation.verify(&confirmation));
+
+        // Wrong certificate on either side
+        assert!(challenge
+            .confirm("AA", "CC", &response.share, &response.proof)
+            .is_none());
+        assert!(challenge
+            .confirm("CC", "BB", &response.share, &response.proof)
+            .is_none());
+        assert!(challenge
+            .confirm("AA", "BB", &response.share, "not hex")
+            .is_none());
+    }
+
+    #[test]
+    fn test_wrong_pin_fails_both_checks() {
+        let challenge = PinChallenge::generate().unwrap();
+        let wrong_pin = if challenge.pin() == "000000" {
+            "000001"
+        } else {
+            "000000"
+        };
+        let response = respond(wrong_pin, challenge.share(), "AA", "BB").unwrap();
+        assert!(challenge
+            .confirm("AA", "BB", &response.share, &response.proof)
+            .is_none());
+
+        // An initiator without the PIN cannot confirm either
+        let impostor = PinChallenge::generate().unwrap();
+        let response = respond(challenge.pin(), impostor.share(), "AA", "BB").unwrap();
+        let forged = impostor
+            .confirm("AA", "BB", &response.share, &response.proof)
+            .unwrap_or_default();
+        assert!(!response.confirmation.verify(&forged));
+    }
+
+    #[test]
+    fn test_invalid_share_rejected() {
+        assert!(respond("123456", "zz", "AA", "BB").is_err());
+        assert!(respond("123456", &"00".repeat(16), "AA", "BB").is_err());
+    }
+}
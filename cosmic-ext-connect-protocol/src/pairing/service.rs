@@ -2,26 +2,44 @@
 //!
 //! Manages pairing for multiple devices simultaneously.
 
+use super::batch::{PairOutcome, PairingTarget};
 use super::events::PairingEvent;
-use super::handler::{PairingHandler, PairingStatus};
-use crate::{DeviceInfo, Packet, Result};
+use super::handler::{PairingHandler, PairingPacket, PairingStatus, RejectReason};
+use super::invite::PairingInvite;
+use crate::{DeviceInfo, Packet, ProtocolError, Result};
+use async_trait::async_trait;
 use cosmic_ext_connect_core::crypto::CertificateInfo;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, oneshot, RwLock};
 use tracing::{debug, error, info, warn};
 
 /// Pairing timeout duration (30 seconds)
 const PAIRING_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// Maximum interval between checks for expired pairing requests
+const TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long an expired request is remembered to refuse late answers
+const EXPIRED_REQUEST_RETENTION: Duration = Duration::from_secs(600);
+
+/// Most expired requests remembered at once
+const MAX_EXPIRED_REQUESTS: usize = 64;
+
+/// Oldest protocol version we pair with
+const MIN_PROTOCOL_VERSION: u32 = 7;
+
 /// Pairing request state
 #[derive(Debug)]
 struct PairingRequest {
     /// When the request was initiated
     started_at: Instant,
+    /// How long the request may stay unanswered
+    timeout: Duration,
     /// Remote device information
     device_info: DeviceInfo,
     /// Remote address
@@ -31,12 +49,20 @@ struct PairingRequest {
 }
 
 /// Pairing service configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PairingConfig {
     /// Certificate storage directory
     pub cert_dir: PathBuf,
     /// Pairing timeout duration
+    #[serde(with = "crate::config::duration_secs")]
     pub timeout: Duration,
+    /// Timeout for new requests instead of `timeout`
+    ///
+    /// Meant for pairing over a slow link such as Bluetooth. Requests already
+    /// pending keep the timeout they started with.
+    #[serde(with = "crate::config::optional_duration_secs")]
+    pub timeout_override: Option<Duration>,
 }
 
 impl Default for PairingConfig {
@@ -44,6 +70,7 @@ impl Default for PairingConfig {
         Self {
             cert_dir: PathBuf::from(".config/kdeconnect/certs"),
             timeout: PAIRING_TIMEOUT,
+            timeout_override: None,
         }
     }
 }
@@ -59,6 +86,15 @@ pub struct PairingService {
     /// Active pairing requests (device_id -> request state)
     active_requests: Arc<RwLock<HashMap<String, PairingRequest>>>,
 
+    /// Devices whose last pairing request expired unanswered (device_id -> when)
+    expired_requests: Arc<RwLock<HashMap<String, Instant>>>,
+
+    /// Callers waiting for a device to answer our request (device_id -> waiter)
+    outcome_waiters: Arc<RwLock<HashMap<String, oneshot::Sender<PairOutcome>>>>,
+
+    /// Scanned invites whose pairing is under way (device_id -> invite)
+    invites: Arc<RwLock<HashMap<String, PairingInvite>>>,
+
     /// Event channel sender
     event_tx: mpsc::UnboundedSender<PairingEvent>,
 
@@ -79,19 +115,37 @@ impl PairingService {
 
         // Create pairing handler
         let handler = PairingHandler::new(device_id.clone(), &config.cert_dir)?;
+        Ok(Self::from_handler(handler, config))
+    }
+
+    /// Create a pairing service keeping paired device certificates in `storage`
+    ///
+    /// `config.cert_dir` is not used; `certificate` is this device's identity.
+    pub fn with_storage(
+        certificate: CertificateInfo,
+        config: PairingConfig,
+        storage: Arc<dyn crate::storage::Storage>,
+    ) -> Self {
+        Self::from_handler(PairingHandler::with_storage(certificate, storage), config)
+    }
+
+    fn from_handler(handler: PairingHandler, config: PairingConfig) -> Self {
         let certificate = handler.certificate().clone();
 
         let (event_tx, event_rx) = mpsc::unbounded_channel();
 
-        Ok(Self {
+        Self {
             certificate: Arc::new(certificate),
             handler: Arc::new(RwLock::new(handler)),
             active_requests: Arc::new(RwLock::new(HashMap::new())),
+            expired_requests: Arc::new(RwLock::new(HashMap::new())),
+            outcome_waiters: Arc::new(RwLock::new(HashMap::new())),
+            invites: Arc::new(RwLock::new(HashMap::new())),
             event_tx,
             event_rx: Arc::new(RwLock::new(event_rx)),
             config,
             connection_manager: None,
-        })
+        }
     }
 
     /// Set the connection manager (called after initialization to avoid circular dependencies)
@@ -130,6 +184,18 @@ impl PairingService {
         &self.certificate.fingerprint
     }
 
+    /// How long a request made now may stay unanswered
+    pub fn request_timeout(&self) -> Duration {
+        self.config.timeout_override.unwrap_or(self.config.timeout)
+    }
+
+    /// Change the timeout for new requests (None = the configured timeout)
+    ///
+    /// See [`PairingConfig::timeout_override`].
+    pub fn set_timeout_override(&mut self, timeout: Option<Duration>) {
+        self.config.timeout_override = timeout;
+    }
+
     /// Request pairing with a device
     ///
     /// Sends pairing request packet and starts timeout tracking.
@@ -138,6 +204,61 @@ impl PairingService {
         device_info: DeviceInfo,
         remote_addr: SocketAddr,
     ) -> Result<()> {
+        self.send_pairing_request(device_info, remote_addr, false)
+            .await
+            .map(|_| ())
+    }
+
+    /// Request pairing with a device, confirmed by PIN
+    ///
+    /// Returns the PIN to show the user, who enters it on the other device
+    /// (also sent as [`PairingEvent::PinGenerated`]). The PIN is only valid
+    /// for this request; see [`pin`](super::pin) for the exchange.
+    pub async fn request_pairing_with_pin(
+        &self,
+        device_info: DeviceInfo,
+        remote_addr: SocketAddr,
+    ) -> Result<String> {
+        let device_id = device_info.device_id.clone();
+        self.send_pairing_request(device_info, remote_addr, true)
+            .await?
+            .ok_or_else(|| {
+                ProtocolError::InvalidState(format!("Device {} is already paired", device_id))
+            })
+    }
+
+    /// Pair with the device behind a scanned pairing invite
+    ///
+    /// The invite names the certificate to expect, so there is nothing for
+    /// the user to verify: the device's answer completes pairing if it
+    /// presents that certificate and is rejected otherwise. A request the
+    /// device sends us instead is accepted the same way. The invite is
+    /// dropped when the request ends, including when it times out. See
+    /// [`invite`](super::invite).
+    pub async fn pair_with_invite(&self, invite: PairingInvite) -> Result<()> {
+        let remote_addr = invite.address().ok_or_else(|| {
+            ProtocolError::InvalidPacket("Pairing invite lists no address".to_string())
+        })?;
+        let device_info = invite.device_info.clone();
+        let device_id = device_info.device_id.clone();
+
+        self.invites.write().await.insert(device_id.clone(), invite);
+        let result = self.request_pairing(device_info, remote_addr).await;
+        // Nothing will time out an invite without a request, e.g. for a
+        // device that was already paired
+        if !self.active_requests.read().await.contains_key(&device_id) {
+            self.invites.write().await.remove(&device_id);
+        }
+        result
+    }
+
+    /// Send a pairing request, returning the PIN if one was generated
+    async fn send_pairing_request(
+        &self,
+        device_info: DeviceInfo,
+        remote_addr: SocketAddr,
+        with_pin: bool,
+    ) -> Result<Option<String>> {
         let device_id = device_info.device_id.clone();
 
         info!(
@@ -149,13 +270,18 @@ impl PairingService {
         let handler = self.handler.read().await;
         if handler.is_paired(&device_id) {
             warn!("Device {} is already paired", device_id);
-            return Ok(());
+            return Ok(None);
         }
         drop(handler);
 
         // Create pairing request packet
         let mut handler = self.handler.write().await;
-        let packet = handler.request_pairing();
+        let (packet, pin) = if with_pin {
+            let (packet, pin) = handler.request_pairing_with_pin(&device_id)?;
+            (packet, Some(pin))
+        } else {
+            (handler.request_pairing(&device_id), None)
+        };
         drop(handler);
 
         // For Protocol v8 unpaired devices: Ensure we have an active connection
@@ -197,11 +323,13 @@ impl PairingService {
         match self.send_pairing_packet(&packet, &device_id).await {
             Ok(_) => {
                 // Track active request
+                self.expired_requests.write().await.remove(&device_id);
                 let mut requests = self.active_requests.write().await;
                 requests.insert(
                     device_id.clone(),
                     PairingRequest {
                         started_at: Instant::now(),
+                        timeout: self.request_timeout(),
                         device_info: device_info.clone(),
                         remote_addr,
                         device_cert: Vec::new(), // Will be received in response
@@ -211,14 +339,20 @@ impl PairingService {
 
                 // Emit event
                 let _ = self.event_tx.send(PairingEvent::RequestSent {
-                    device_id,
+                    device_id: device_id.clone(),
                     our_fingerprint: self.fingerprint().to_string(),
                 });
+                if let Some(pin) = &pin {
+                    let _ = self.event_tx.send(PairingEvent::PinGenerated {
+                        device_id,
+                        pin: pin.clone(),
+                    });
+                }
 
                 // Start timeout checker
                 self.spawn_timeout_checker();
 
-                Ok(())
+                Ok(pin)
             }
             Err(e) => {
                 error!("Failed to send pairing request: {}", e);
@@ -246,11 +380,67 @@ impl PairingService {
             device_id, remote_addr
         );
 
+        // A device we hold an invite for must present the invited certificate
+        let invited = self
+            .invites
+            .read()
+            .await
+            .get(device_id)
+            .map(|invite| invite.matches_certificate(device_cert));
+        let wants_pairing = packet.get_body_field::<bool>("pair") == Some(true);
+        let refusal = if invited == Some(false) {
+            warn!(
+                "Device {} presented a certificate other than the invited one",
+                device_id
+            );
+            Some(RejectReason::FingerprintConflict)
+        } else if wants_pairing && device_info.protocol_version < MIN_PROTOCOL_VERSION {
+            warn!(
+                "Device {} uses protocol version {}, refusing to pair",
+                device_id, device_info.protocol_version
+            );
+            Some(RejectReason::VersionMismatch)
+        } else {
+            None
+        };
+
         let mut handler = self.handler.write().await;
-        let (should_respond, response_packet) =
-            handler.handle_pairing_packet(packet, device_id, device_cert)?;
+        let handled = match refusal {
+            Some(reason) => {
+                handler.expire_request(device_id);
+                Err(reason)
+            }
+            None => match handler.handle_pairing_packet(packet, device_id, device_cert) {
+                Err(ProtocolError::CertificateValidation(_)) => Err(RejectReason::PinMismatch),
+                result => Ok(result?),
+            },
+        };
+        let (should_respond, response_packet) = match handled {
+            Ok(handled) => handled,
+            Err(reason) => {
+                // Abort, and tell the peer, which may already have stored
+                // our certificate when it accepted
+                drop(handler);
+                self.active_requests.write().await.remove(device_id);
+                self.invites.write().await.remove(device_id);
+                resolve_outcome(
+                    &self.outcome_waiters,
+                    device_id,
+                    PairOutcome::Declined {
+                        reason: Some(reason.to_string()),
+                    },
+                )
+                .await;
+                let _ = self.event_tx.send(PairingEvent::PairingRejected {
+                    device_id: device_id.clone(),
+                    reason,
+                });
+                return Ok(Some(PairingPacket::reject()));
+            }
+        };
 
         let status = handler.status();
+        let pin_required = handler.peer_requires_pin();
         drop(handler);
 
         // Handle state changes
@@ -262,11 +452,13 @@ impl PairingService {
                 );
 
                 // Store the pairing request with certificate for later acceptance
+                self.expired_requests.write().await.remove(device_id);
                 let mut requests = self.active_requests.write().await;
                 requests.insert(
                     device_id.clone(),
                     PairingRequest {
                         started_at: Instant::now(),
+                        timeout: self.request_timeout(),
                         device_info: device_info.clone(),
                         remote_addr,
                         device_cert: device_cert.to_vec(),
@@ -274,6 +466,24 @@ impl PairingService {
                 );
                 drop(requests);
 
+                // The invite already vouches for the certificate
+                if invited == Some(true) && !pin_required {
+                    info!(
+                        "Accepting pairing request from invited device {}",
+                        device_id
+                    );
+                    match self.accept_pairing(device_id).await {
+                        Ok(()) => {
+                            self.invites.write().await.remove(device_id);
+                            return Ok(None);
+                        }
+                        Err(e) => warn!(
+                            "Failed to accept invited device {}, asking the user: {}",
+                            device_id, e
+                        ),
+                    }
+                }
+
                 let fingerprint = CertificateInfo::calculate_fingerprint(device_cert);
 
                 let _ = self.event_tx.send(PairingEvent::RequestReceived {
@@ -281,6 +491,12 @@ impl PairingService {
                     device_name: device_info.device_name.clone(),
                     their_fingerprint: fingerprint,
                 });
+                if pin_required {
+                    let _ = self.event_tx.send(PairingEvent::PinRequired {
+                        device_id: device_id.clone(),
+                        device_name: device_info.device_name.clone(),
+                    });
+                }
 
                 // Start timeout checker
                 self.spawn_timeout_checker();
@@ -292,26 +508,45 @@ impl PairingService {
                 let mut requests = self.active_requests.write().await;
                 requests.remove(device_id);
                 drop(requests);
+                self.invites.write().await.remove(device_id);
 
                 let fingerprint = CertificateInfo::calculate_fingerprint(device_cert);
 
+                resolve_outcome(&self.outcome_waiters, device_id, PairOutcome::Paired).await;
                 let _ = self.event_tx.send(PairingEvent::PairingAccepted {
                     device_id: device_id.clone(),
                     device_name: device_info.device_name.clone(),
                     certificate_fingerprint: fingerprint,
                 });
             }
-            PairingStatus::Unpaired => {
-                debug!("Pairing rejected or unpaired for device {}", device_id);
+            PairingStatus::Rejected { reason } => {
+                debug!("Pairing rejected by device {}: {}", device_id, reason);
 
                 // Remove from active requests
                 let mut requests = self.active_requests.write().await;
                 requests.remove(device_id);
                 drop(requests);
+                self.invites.write().await.remove(device_id);
 
+                resolve_outcome(
+                    &self.outcome_waiters,
+                    device_id,
+                    PairOutcome::Declined {
+                        reason: Some(reason.to_string()),
+                    },
+                )
+                .await;
                 let _ = self.event_tx.send(PairingEvent::PairingRejected {
                     device_id: device_id.clone(),
-                    reason: None,
+                    reason,
+                });
+            }
+            PairingStatus::Unpaired => {
+                debug!("Device {} unpaired us", device_id);
+
+                self.active_requests.write().await.remove(device_id);
+                let _ = self.event_tx.send(PairingEvent::DeviceUnpaired {
+                    device_id: device_id.clone(),
                 });
             }
             _ => {}
@@ -327,8 +562,39 @@ impl PairingService {
 
     /// Accept a pairing request (user confirmed)
     pub async fn accept_pairing(&self, device_id: &str) -> Result<()> {
+        self.accept_request(device_id, None).await
+    }
+
+    /// Accept a pairing request with the PIN shown on the other device
+    ///
+    /// Only for requests announced with [`PairingEvent::PinRequired`]. The
+    /// other device checks the PIN and aborts pairing if it does not match;
+    /// otherwise it confirms, and [`PairingEvent::PairingAccepted`] is sent
+    /// once that confirmation arrives.
+    pub async fn accept_pairing_with_pin(&self, device_id: &str, pin: &str) -> Result<()> {
+        self.accept_request(device_id, Some(pin)).await
+    }
+
+    async fn accept_request(&self, device_id: &str, pin: Option<&str>) -> Result<()> {
         info!("Accepting pairing with device {}", device_id);
 
+        let expired = self
+            .expired_requests
+            .read()
+            .await
+            .get(device_id)
+            .is_some_and(|at| at.elapsed() < EXPIRED_REQUEST_RETENTION);
+        if expired {
+            warn!(
+                "Refusing to accept expired pairing request from {}",
+                device_id
+            );
+            return Err(crate::ProtocolError::Timeout(format!(
+                "Pairing request from device {} has expired",
+                device_id
+            )));
+        }
+
         // Get the stored pairing request with certificate and address
         debug!(
             "Step 1: Retrieving stored pairing request data for {}",
@@ -359,7 +625,10 @@ impl PairingService {
         debug!("Step 3: Creating pairing acceptance response packet");
         let response = {
             let mut handler = self.handler.write().await;
-            let resp = handler.accept_pairing(device_id, &device_cert)?;
+            let resp = match pin {
+                Some(pin) => handler.accept_pairing_with_pin(device_id, &device_cert, pin)?,
+                None => handler.accept_pairing(device_id, &device_cert)?,
+            };
             debug!("Response packet created: type={}", resp.packet_type);
             resp
         };
@@ -427,6 +696,16 @@ impl PairingService {
             }
         }
 
+        if pin.is_some() {
+            // Paired once the other device confirms the PIN; the request
+            // stays active so it still times out if it never does
+            info!(
+                "Waiting for device {} to confirm the pairing PIN",
+                device_id
+            );
+            return Ok(());
+        }
+
         debug!("Step 9: Removing device from active pairing requests");
         // Remove from active requests now that pairing is accepted
         self.active_requests.write().await.remove(device_id);
@@ -472,7 +751,7 @@ impl PairingService {
 
         let _ = self.event_tx.send(PairingEvent::PairingRejected {
             device_id: device_id.to_string(),
-            reason: Some("User declined".to_string()),
+            reason: RejectReason::UserDeclined,
         });
 
         Ok(())
@@ -501,12 +780,36 @@ impl PairingService {
         Ok(())
     }
 
+    /// Revoke trust in a device that is being forgotten
+    ///
+    /// Removes the pinned certificate and notifies the device like
+    /// [`unpair`](Self::unpair), but emits no event: the caller reports a single
+    /// completion once all of the device's state is gone.
+    pub async fn revoke(&self, device_id: &str) -> Result<()> {
+        info!("Revoking pairing with device {}", device_id);
+
+        let packet = self.handler.write().await.unpair(device_id)?;
+        if let Err(e) = self.send_pairing_packet(&packet, device_id).await {
+            debug!("Could not notify {} of revoked pairing: {}", device_id, e);
+        }
+
+        Ok(())
+    }
+
     /// Check if a device is paired
     pub async fn is_paired(&self, device_id: &str) -> bool {
         let handler = self.handler.read().await;
         handler.is_paired(device_id)
     }
 
+    /// Payload encryption key agreed with a paired device, if any
+    pub async fn payload_key(
+        &self,
+        device_id: &str,
+    ) -> Result<Option<crate::payload_crypto::PayloadKey>> {
+        self.handler.read().await.payload_key(device_id)
+    }
+
     /// Send a pairing packet to a device over the TLS connection (Protocol v8)
     async fn send_pairing_packet(&self, packet: &Packet, device_id: &str) -> Result<()> {
         debug!(
@@ -530,11 +833,15 @@ impl PairingService {
     /// Spawn timeout checker task
     fn spawn_timeout_checker(&self) {
         let active_requests = self.active_requests.clone();
+        let expired_requests = self.expired_requests.clone();
+        let outcome_waiters = self.outcome_waiters.clone();
+        let invites = self.invites.clone();
+        let handler = self.handler.clone();
         let event_tx = self.event_tx.clone();
-        let timeout = self.config.timeout;
+        let check_interval = self.request_timeout().min(TIMEOUT_CHECK_INTERVAL);
 
         tokio::spawn(async move {
-            tokio::time::sleep(Duration::from_secs(5)).await;
+            tokio::time::sleep(check_interval).await;
 
             loop {
                 let mut requests = active_requests.write().await;
@@ -542,7 +849,7 @@ impl PairingService {
                 let mut timed_out = Vec::new();
 
                 for (device_id, request) in requests.iter() {
-                    if now.duration_since(request.started_at) > timeout {
+                    if now.duration_since(request.started_at) > request.timeout {
                         timed_out.push(device_id.clone());
                     }
                 }
@@ -550,10 +857,21 @@ impl PairingService {
                 for device_id in timed_out {
                     info!("Pairing request timed out for device {}", device_id);
                     requests.remove(&device_id);
+                    handler.write().await.expire_request(&device_id);
+                    record_expiry(&mut *expired_requests.write().await, &device_id);
+                    invites.write().await.remove(&device_id);
+                    resolve_outcome(&outcome_waiters, &device_id, PairOutcome::TimedOut).await;
 
                     let _ = event_tx.send(PairingEvent::PairingTimeout {
                         device_id: device_id.clone(),
                     });
+                    let _ = event_tx.send(PairingEvent::RequestExpired {
+                        device_id: device_id.clone(),
+                    });
+                    let _ = event_tx.send(PairingEvent::PairingRejected {
+                        device_id,
+                        reason: RejectReason::TimedOut,
+                    });
                 }
 
                 drop(requests);
@@ -562,12 +880,77 @@ impl PairingService {
                     break;
                 }
 
-                tokio::time::sleep(Duration::from_secs(5)).await;
+                tokio::time::sleep(check_interval).await;
             }
         });
     }
 }
 
+#[async_trait]
+impl PairingTarget for PairingService {
+    async fn pair(&self, device_info: DeviceInfo, remote_addr: SocketAddr) -> PairOutcome {
+        let device_id = device_info.device_id.clone();
+        if self.is_paired(&device_id).await {
+            return PairOutcome::Skipped {
+                reason: "already paired".to_string(),
+            };
+        }
+
+        // The timeout checker answers expired requests; the margin covers its
+        // check interval
+        let wait = self.request_timeout() + TIMEOUT_CHECK_INTERVAL;
+        let (tx, rx) = oneshot::channel();
+        self.outcome_waiters
+            .write()
+            .await
+            .insert(device_id.clone(), tx);
+        if let Err(e) = self.request_pairing(device_info, remote_addr).await {
+            self.outcome_waiters.write().await.remove(&device_id);
+            return PairOutcome::Failed {
+                message: e.to_string(),
+            };
+        }
+
+        match tokio::time::timeout(wait, rx).await {
+            Ok(Ok(outcome)) => outcome,
+            Ok(Err(_)) | Err(_) => {
+                self.outcome_waiters.write().await.remove(&device_id);
+                PairOutcome::TimedOut
+            }
+        }
+    }
+}
+
+/// Hand `outcome` to whoever waits for `device_id` to answer
+async fn resolve_outcome(
+    waiters: &RwLock<HashMap<String, oneshot::Sender<PairOutcome>>>,
+    device_id: &str,
+    outcome: PairOutcome,
+) {
+    if let Some(waiter) = waiters.write().await.remove(device_id) {
+        let _ = waiter.send(outcome);
+    }
+}
+
+/// Remember that the request with `device_id` expired
+///
+/// Entries past [`EXPIRED_REQUEST_RETENTION`] are pruned, and the oldest is
+/// dropped once [`MAX_EXPIRED_REQUESTS`] are held. A late answer to a
+/// forgotten request still fails, as there is no active request for it.
+fn record_expiry(expired: &mut HashMap<String, Instant>, device_id: &str) {
+    expired.retain(|_, at| at.elapsed() < EXPIRED_REQUEST_RETENTION);
+    if expired.len() >= MAX_EXPIRED_REQUESTS && !expired.contains_key(device_id) {
+        if let Some(oldest) = expired
+            .iter()
+            .min_by_key(|(_, at)| **at)
+            .map(|(id, _)| id.clone())
+        {
+            expired.remove(&oldest);
+        }
+    }
+    expired.insert(device_id.to_string(), Instant::now());
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -579,6 +962,7 @@ mod tests {
         let config = PairingConfig {
             cert_dir: temp_dir.path().to_path_buf(),
             timeout: Duration::from_secs(30),
+            timeout_override: None,
         };
 
         let service = PairingService::new("test_device", config).unwrap();
@@ -591,6 +975,7 @@ mod tests {
         let config = PairingConfig {
             cert_dir: temp_dir.path().to_path_buf(),
             timeout: Duration::from_secs(30),
+            timeout_override: None,
         };
 
         let service = PairingService::new("test_device", config).unwrap();
@@ -599,4 +984,311 @@ mod tests {
         // Events channel should be ready
         assert!(!service.event_tx.is_closed());
     }
+
+    async fn receive_incoming_request(service: &PairingService) -> DeviceInfo {
+        let device_info = DeviceInfo::new("Phone", crate::DeviceType::Phone, 1716);
+        service
+            .handle_pairing_packet(
+                &PairingPacket::request(),
+                &device_info,
+                b"peer-certificate",
+                "127.0.0.1:1716".parse().unwrap(),
+            )
+            .await
+            .unwrap();
+        device_info
+    }
+
+    #[tokio::test]
+    async fn test_wrong_pin_rejects_and_answers_with_reject() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = PairingConfig {
+            cert_dir: temp_dir.path().to_path_buf(),
+            timeout: Duration::from_secs(30),
+            timeout_override: None,
+        };
+
+        let service = PairingService::new("test_device", config).unwrap();
+        let mut events = service.subscribe().await;
+        let device_info = DeviceInfo::new("Phone", crate::DeviceType::Phone, 1716);
+        let (_, pin) = service
+            .handler
+            .write()
+            .await
+            .request_pairing_with_pin(&device_info.device_id)
+            .unwrap();
+
+        let wrong_pin = if pin == "000000" { "111111" } else { "000000" };
+        let impostor = crate::pairing::pin::PinChallenge::generate().unwrap();
+        let answer = crate::pairing::pin::respond(
+            wrong_pin,
+            impostor.share(),
+            service.fingerprint(),
+            &CertificateInfo::calculate_fingerprint(b"peer-certificate"),
+        )
+        .unwrap();
+        let response = service
+            .handle_pairing_packet(
+                &PairingPacket::accept_with_pin(&answer.share, &answer.proof),
+                &device_info,
+                b"peer-certificate",
+                "127.0.0.1:1716".parse().unwrap(),
+            )
+            .await
+            .unwrap()
+            .expect("reject sent to peer");
+
+        assert_eq!(response.get_body_field::<bool>("pair"), Some(false));
+        assert!(!service.is_paired(&device_info.device_id).await);
+        match events.recv().await {
+            Some(PairingEvent::PairingRejected { reason, .. }) => {
+                assert_eq!(reason, RejectReason::PinMismatch)
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_request_expires() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = PairingConfig {
+            cert_dir: temp_dir.path().to_path_buf(),
+            timeout: Duration::from_millis(100),
+            timeout_override: None,
+        };
+
+        let service = PairingService::new("test_device", config).unwrap();
+        let mut events = service.subscribe().await;
+        let device_info = receive_incoming_request(&service).await;
+
+        let expired = tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                match events.recv().await {
+                    Some(PairingEvent::RequestExpired { device_id }) => break device_id,
+                    Some(_) => continue,
+                    None => panic!("event channel closed"),
+                }
+            }
+        })
+        .await
+        .expect("no expiry event");
+
+        assert_eq!(expired, device_info.device_id);
+        assert!(service.active_requests.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_timeout_override_applies_to_new_requests() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = PairingConfig {
+            cert_dir: temp_dir.path().to_path_buf(),
+            timeout: Duration::from_secs(30),
+            timeout_override: None,
+        };
+
+        let mut service = PairingService::new("test_device", config).unwrap();
+        service.set_timeout_override(Some(Duration::from_millis(100)));
+        assert_eq!(service.request_timeout(), Duration::from_millis(100));
+        let mut events = service.subscribe().await;
+        let device_info = receive_incoming_request(&service).await;
+
+        let (device_id, reason) = tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                match events.recv().await {
+                    Some(PairingEvent::PairingRejected { device_id, reason }) => {
+                        break (device_id, reason)
+                    }
+                    Some(_) => continue,
+                    None => panic!("event channel closed"),
+                }
+            }
+        })
+        .await
+        .expect("no rejection event");
+
+        assert_eq!(device_id, device_info.device_id);
+        assert_eq!(reason, RejectReason::TimedOut);
+
+        service.set_timeout_override(None);
+        assert_eq!(service.request_timeout(), Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn test_old_protocol_version_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = PairingConfig {
+            cert_dir: temp_dir.path().to_path_buf(),
+            timeout: Duration::from_secs(30),
+            timeout_override: None,
+        };
+
+        let service = PairingService::new("test_device", config).unwrap();
+        let mut events = service.subscribe().await;
+        let mut device_info = DeviceInfo::new("Phone", crate::DeviceType::Phone, 1716);
+        device_info.protocol_version = MIN_PROTOCOL_VERSION - 1;
+        let response = service
+            .handle_pairing_packet(
+                &PairingPacket::request(),
+                &device_info,
+                b"peer-certificate",
+                "127.0.0.1:1716".parse().unwrap(),
+            )
+            .await
+            .unwrap()
+            .expect("reject sent to peer");
+
+        assert_eq!(response.get_body_field::<bool>("pair"), Some(false));
+        assert!(service.active_requests.read().await.is_empty());
+        match events.recv().await {
+            Some(PairingEvent::PairingRejected { reason, .. }) => {
+                assert_eq!(reason, RejectReason::VersionMismatch)
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pair_without_connection_fails() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = PairingConfig {
+            cert_dir: temp_dir.path().to_path_buf(),
+            timeout: Duration::from_secs(30),
+            timeout_override: None,
+        };
+
+        let service = PairingService::new("test_device", config).unwrap();
+        let device_info = DeviceInfo::new("Phone", crate::DeviceType::Phone, 1716);
+        let outcome = service
+            .pair(device_info, "127.0.0.1:1716".parse().unwrap())
+            .await;
+
+        assert!(matches!(outcome, PairOutcome::Failed { .. }));
+        assert!(service.outcome_waiters.read().await.is_empty());
+    }
+
+    fn invite_from(certificate: &CertificateInfo) -> (PairingInvite, DeviceInfo) {
+        let mut device_info =
+            DeviceInfo::with_id("phone_1", "Phone", crate::DeviceType::Phone, 1716);
+        device_info.addresses = vec!["127.0.0.1:1716".parse().unwrap()];
+        let uri = crate::pairing::pairing_uri(&device_info, certificate);
+        (
+            crate::pairing::parse_pairing_uri(&uri).unwrap(),
+            device_info,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_invite_pins_peer_certificate() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = PairingConfig {
+            cert_dir: temp_dir.path().to_path_buf(),
+            timeout: Duration::from_secs(30),
+            timeout_override: None,
+        };
+        let service = PairingService::new("test_device", config).unwrap();
+        let certificate = CertificateInfo::generate("phone_1").unwrap();
+        let (invite, device_info) = invite_from(&certificate);
+        let addr = invite.address().unwrap();
+
+        // A different certificate is refused
+        service
+            .invites
+            .write()
+            .await
+            .insert(device_info.device_id.clone(), invite.clone());
+        service
+            .handler
+            .write()
+            .await
+            .request_pairing(&device_info.device_id);
+        let response = service
+            .handle_pairing_packet(
+                &PairingPacket::accept(),
+                &device_info,
+                b"peer-certificate",
+                addr,
+            )
+            .await
+            .unwrap()
+            .expect("reject sent to peer");
+        assert_eq!(response.get_body_field::<bool>("pair"), Some(false));
+        assert!(!service.is_paired(&device_info.device_id).await);
+        assert!(service.invites.read().await.is_empty());
+
+        // The invited one pairs
+        service
+            .invites
+            .write()
+            .await
+            .insert(device_info.device_id.clone(), invite);
+        service
+            .handler
+            .write()
+            .await
+            .request_pairing(&device_info.device_id);
+        service
+            .handle_pairing_packet(
+                &PairingPacket::accept(),
+                &device_info,
+                &certificate.certificate,
+                addr,
+            )
+            .await
+            .unwrap();
+        assert!(service.is_paired(&device_info.device_id).await);
+        assert!(service.invites.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_invite_dropped_without_request() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = PairingConfig {
+            cert_dir: temp_dir.path().to_path_buf(),
+            timeout: Duration::from_secs(30),
+            timeout_override: None,
+        };
+        let service = PairingService::new("test_device", config).unwrap();
+        let certificate = CertificateInfo::generate("phone_1").unwrap();
+        let (invite, _) = invite_from(&certificate);
+
+        // No connection manager, so the request is never sent
+        assert!(service.pair_with_invite(invite).await.is_err());
+        assert!(service.invites.read().await.is_empty());
+    }
+
+    #[test]
+    fn test_expired_requests_bounded() {
+        let mut expired = HashMap::new();
+        for i in 0..MAX_EXPIRED_REQUESTS + 10 {
+            record_expiry(&mut expired, &format!("device_{}", i));
+        }
+        assert_eq!(expired.len(), MAX_EXPIRED_REQUESTS);
+        assert!(expired.contains_key(&format!("device_{}", MAX_EXPIRED_REQUESTS + 9)));
+
+        // Stale entries are pruned on the next expiry
+        if let Some(stale) = Instant::now().checked_sub(EXPIRED_REQUEST_RETENTION) {
+            expired.values_mut().for_each(|at| *at = stale);
+            record_expiry(&mut expired, "phone");
+            assert_eq!(expired.len(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_accept_after_expiry_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = PairingConfig {
+            cert_dir: temp_dir.path().to_path_buf(),
+            timeout: Duration::from_millis(100),
+            timeout_override: None,
+        };
+
+        let service = PairingService::new("test_device", config).unwrap();
+        let device_info = receive_incoming_request(&service).await;
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+
+        let result = service.accept_pairing(&device_info.device_id).await;
+        assert!(matches!(result, Err(crate::ProtocolError::Timeout(_))));
+        assert!(!service.is_paired(&device_info.device_id).await);
+    }
 }
@@ -59,14 +59,191 @@
 //! let client = TlsPayloadClient::new(remote_addr, port, &tls_config).await?;
 //! client.receive_file("/path/to/save/file.pdf", size).await?;
 //! ```
-
-use crate::fs_utils::{cleanup_partial_file, create_file_safe, write_file_safe};
-use crate::{ProtocolError, Result, TlsConfig};
+//!
+//! ### Confirmed Transfers
+//!
+//! For large files the sender can stage the transfer and wait for the receiver
+//! to decide before any bytes move. The sender marks the share packet with
+//! `"confirmRequired": true` (see [`CONFIRM_REQUIRED_FIELD`]) and enables
+//! [`PayloadServer::require_confirmation`]. After connecting, the receiver
+//! answers with [`PayloadClient::accept`] or [`PayloadClient::decline`]; a
+//! single confirmation byte is written before the file data. A decline, a
+//! closed connection or no answer within the timeout all fail the send with
+//! [`ProtocolError::PeerRejected`].
+//!
+//! ```rust,ignore
+//! // Sender
+//! let server = PayloadServer::new().await?.require_confirmation(Duration::from_secs(60));
+//! server.send_file("/path/to/video.mkv").await?;
+//!
+//! // Receiver, after asking the user
+//! let client = PayloadClient::new(remote_addr, port).await?;
+//! if user_accepted {
+//!     client.accept("/path/to/save/video.mkv", size).await?;
+//! } else {
+//!     client.decline().await?;
+//! }
+//! ```
+//!
+//! ### Incoming Size Limits
+//!
+//! Receivers can cap how large an incoming file may be with
+//! [`FileSizeLimits`]: a global default plus per-device overrides, where `None`
+//! means unlimited. Pass the device's limit to
+//! [`PayloadClient::with_size_limit`]; an oversized transfer is refused before
+//! the destination file is created. Staged transfers are declined, so the
+//! sender completes with [`ProtocolError::PeerRejected`].
+//!
+//! ### Receive Trust
+//!
+//! Each device has a [`ReceiveTrust`] level for incoming files, kept in
+//! [`ReceiveTrustLevels`] the same way as size limits. A [`TransferGate`]
+//! applies it: `AutoAccept` receives right away, `Reject` declines without
+//! asking, and `Prompt` hands a [`TransferPrompt`] to whoever listens on the
+//! gate's channel and declines if nobody answers within the prompt timeout.
+//! Senders that don't stage, such as Android, start streaming on connect, so
+//! for their files the gate is asked before connecting to the payload port.
+//! A staged file is held for the user unless its device has a level of its
+//! own: the sender asked for a decision, so a global `AutoAccept` default
+//! does not skip the prompt.
+//!
+//! ```rust,ignore
+//! let (gate, mut prompts) = TransferGate::new(levels, Duration::from_secs(30));
+//! tokio::spawn(async move {
+//!     while let Some(prompt) = prompts.recv().await {
+//!         // ... ask the user about prompt.filename ...
+//!         prompt.accept();
+//!     }
+//! });
+//! let received = client.accept_if_trusted(&gate, device_id, &save_path, size).await?;
+//! ```
+//!
+//! When prompts are answered from elsewhere (notification actions, applet
+//! buttons), [`PendingTransferPrompts`] holds them under an ID until the user
+//! decides. A prompt whose notification was dismissed simply stays
+//! unanswered, so the gate's timeout declines it.
+//!
+//! ### Transfer Directory Sandbox
+//!
+//! A receiver can confine writes to one directory with
+//! [`PayloadClient::with_sandbox`]. The save path is resolved against the
+//! canonical [`TransferSandbox`] base with symlinks followed, and a path that
+//! would land outside it is refused before the file is created.
+//!
+//! ### Reassembly
+//!
+//! Received bytes are written by offset through a
+//! [`ChunkAssembler`](crate::reassembly::ChunkAssembler), which fails the
+//! transfer if any part of the file is missing once the stream ends.
+//!
+//! ### Staged Receiving
+//!
+//! A file is received under a hidden `.name.part` staging name (see
+//! [`staging_path`]) and only renamed to its final name once every byte
+//! arrived and, if [`PayloadClient::with_checksums`] was given, the content
+//! verified. Other applications watching the directory therefore never see a
+//! partial file. A failed transfer removes the staging file and leaves no
+//! file under the final name. [`PayloadClient::with_staging_dir`] keeps staging
+//! files elsewhere; if that is another filesystem the file is copied over
+//! before the final rename.
+//!
+//! ### Resuming Interrupted Transfers
+//!
+//! A sender that can continue a transfer part-way marks the share packet with
+//! `"resumable": true` (see [`RESUMABLE_FIELD`]) and enables
+//! [`PayloadServer::allow_resume`]. After connecting (and confirming, for
+//! staged transfers), the receiver writes the byte offset it already has as
+//! a big-endian `u64`; the sender seeks there and sends only the rest.
+//!
+//! On the receiving side [`resume_offset`] reports how much of a leftover
+//! staging file can be kept, and [`PayloadClient::resume_from`] continues from
+//! it. A resumable transfer that fails on a network error or timeout keeps its
+//! staging file for the next attempt. The offset can also come from a
+//! [`RecoveryManager`](crate::recovery::RecoveryManager) checkpoint, so a
+//! transfer continues where it stopped even after the daemon restarted.
+//!
+//! ```rust,ignore
+//! // Sender
+//! let server = PayloadServer::new().await?.allow_resume();
+//! server.send_file("/path/to/video.mkv").await?;
+//!
+//! // Receiver
+//! let offset = resume_offset(&save_path, None, size).await;
+//! let client = PayloadClient::new(remote_addr, port).await?.resume_from(offset);
+//! client.receive_file(&save_path, size).await?;
+//! ```
+//!
+//! ### Whole-File Checksums
+//!
+//! A sender can compute the file's SHA-256 up front with
+//! [`FileTransferInfo::with_checksum`]; the share packet then carries it as
+//! `"sha256"` (see [`CHECKSUM_FIELD`]). A receiver given the digest with
+//! [`PayloadClient::with_file_checksum`] hashes the bytes as they arrive and,
+//! once the file is complete, fails with [`ProtocolError::ChecksumMismatch`]
+//! instead of keeping a corrupted file. Receivers that prefer throughput can
+//! simply not pass the digest on.
+//!
+//! ### Bandwidth Limits
+//!
+//! [`PayloadServer::with_bandwidth_limit`] keeps a `send_file` under a byte
+//! rate using a shared [`BandwidthLimit`]. The limit can be changed while the
+//! transfer runs; [`ResourceManager`](crate::ResourceManager) hands each
+//! active transfer its share of a global budget this way.
+//!
+//! ### Parallel Streams
+//!
+//! One TCP stream rarely fills a long-latency link. A sender built with
+//! [`PayloadServer::with_parallel_streams`] splits the file into contiguous
+//! ranges and advertises the [`StreamLayout`] in the share packet
+//! (`"payloadStreams"` and `"payloadStreamSize"`). The receiver pre-allocates
+//! the file, opens one connection per stream and names the stream it wants
+//! on each; the ranges are written by offset as they arrive. Devices
+//! advertise how many streams they accept in their identity packet, and
+//! [`TransportCapabilities::negotiate_payload_streams`](crate::TransportCapabilities::negotiate_payload_streams)
+//! falls back to a single stream when either side can't do more.
+//!
+//! ### Sending to Several Devices
+//!
+//! [`PayloadServer::send_file_multi`] (and its TLS twin) sends one file to
+//! several devices in one go. The file is read once and every chunk is
+//! written to all receivers concurrently; each server keeps its own progress
+//! callback. A device that fails or cancels is reported in the
+//! [`FanOutSummary`] without stopping the others.
+//!
+//! ### End-to-End Encryption
+//!
+//! When a transfer is relayed, the TLS session ends at the relay. Both sides
+//! can then seal every chunk with a [`PayloadCipher`] keyed from pairing
+//! (see [`payload_crypto`](crate::payload_crypto)), so the relay only ever
+//! sees ciphertext. The sender describes the cipher in the packet and calls
+//! [`PayloadServer::with_encryption`]; the receiver builds the same cipher
+//! with [`PayloadCipher::from_packet`] and passes it to
+//! [`PayloadClient::with_encryption`]. A tampered chunk fails the transfer.
+
+use crate::bandwidth::BandwidthLimit;
+use crate::chunk_sizing::ChunkProbe;
+use crate::fs_utils::{
+    cleanup_partial_file, commit_staged_file, create_file_safe, open_partial_file, staging_path,
+    write_file_safe, TransferSandbox,
+};
+use crate::payload_crypto::{read_sealed, write_sealed, PayloadCipher};
+use crate::reassembly::ChunkAssembler;
+use crate::transfer_integrity::ChunkChecksums;
+use crate::{Packet, ProtocolError, Result, TlsConfig, TrafficCounter};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::SeekFrom;
 use std::net::{SocketAddr, ToSocketAddrs};
-use std::path::Path;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
 use tokio::time::{timeout, Duration};
 use tokio_rustls::{TlsAcceptor, TlsConnector};
 use tracing::{debug, error, info, warn};
@@ -80,6 +257,38 @@ const TRANSFER_TIMEOUT: Duration = Duration::from_secs(60);
 /// Buffer size for file streaming (64KB)
 const BUFFER_SIZE: usize = 65536;
 
+/// Default time a sender waits for the receiver to accept a staged transfer
+pub const DEFAULT_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Share packet body field marking a transfer that needs confirmation
+pub const CONFIRM_REQUIRED_FIELD: &str = "confirmRequired";
+
+/// Share packet body field marking a sender that can resume a transfer
+pub const RESUMABLE_FIELD: &str = "resumable";
+
+/// Share packet body field carrying the file's SHA-256 (lowercase hex)
+pub const CHECKSUM_FIELD: &str = "sha256";
+
+/// Share packet body field with the number of parallel payload streams
+///
+/// Also sent in identity packets, with the most streams the device accepts.
+pub const PAYLOAD_STREAMS_FIELD: &str = "payloadStreams";
+
+/// Share packet body field with the bytes each parallel stream carries
+pub const PAYLOAD_STREAM_SIZE_FIELD: &str = "payloadStreamSize";
+
+/// Most parallel streams one transfer opens
+pub const MAX_PAYLOAD_STREAMS: usize = 8;
+
+/// Smallest range worth a stream of its own (256 KB)
+const MIN_STREAM_SIZE: u64 = 256 * 1024;
+
+/// Confirmation byte: receiver accepts the transfer
+const CONFIRM_ACCEPT: u8 = 0x01;
+
+/// Confirmation byte: receiver declines the transfer
+const CONFIRM_DECLINE: u8 = 0x00;
+
 /// Port range for payload servers (CConnect standard)
 const PORT_RANGE_START: u16 = 1739;
 const PORT_RANGE_END: u16 = 1764;
@@ -103,6 +312,15 @@ pub struct FileTransferInfo {
 
     /// Last modified time (UNIX milliseconds)
     pub last_modified: Option<i64>,
+
+    /// Key/value metadata sent along with the file
+    pub metadata: crate::plugins::share_metadata::FileMetadata,
+
+    /// User the file is sent as, on a machine serving several users
+    pub user: Option<String>,
+
+    /// SHA-256 of the file contents (lowercase hex), if computed
+    pub checksum: Option<String>,
 }
 
 impl FileTransferInfo {
@@ -146,8 +364,44 @@ impl FileTransferInfo {
             path: path.to_string_lossy().to_string(),
             creation_time,
             last_modified,
+            metadata: Default::default(),
+            user: None,
+            checksum: None,
         })
     }
+
+    /// Attach key/value metadata, such as a caption or tags
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::ResourceExhausted`] if the metadata exceeds
+    /// [`MAX_METADATA_BYTES`](crate::plugins::share_metadata::MAX_METADATA_BYTES).
+    pub fn with_metadata(
+        mut self,
+        metadata: crate::plugins::share_metadata::FileMetadata,
+    ) -> Result<Self> {
+        crate::plugins::share_metadata::check_metadata(&metadata)?;
+        self.metadata = metadata;
+        Ok(self)
+    }
+
+    /// Send the file as `user`, so the receiver can route it to them
+    pub fn with_user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// Compute the file's SHA-256 so the receiver can verify what arrives
+    ///
+    /// Reads the whole file once, before anything is sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the file cannot be read.
+    pub async fn with_checksum(mut self) -> Result<Self> {
+        self.checksum = Some(file_sha256(&self.path).await?);
+        Ok(self)
+    }
 }
 
 /// Converts FileTransferInfo to Share plugin's FileShareInfo
@@ -159,7 +413,69 @@ impl From<FileTransferInfo> for crate::plugins::share::FileShareInfo {
             creation_time: info.creation_time,
             last_modified: info.last_modified,
             open: false,
+            metadata: info.metadata,
+            user: info.user,
+            checksum: info.checksum,
+        }
+    }
+}
+
+/// Feed everything `reader` yields into `hasher`
+async fn hash_reader<R: AsyncRead + Unpin>(mut reader: R, hasher: &mut Sha256) -> Result<()> {
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    loop {
+        let bytes_read = reader.read(&mut buffer).await.map_err(ProtocolError::Io)?;
+        if bytes_read == 0 {
+            return Ok(());
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+}
+
+/// SHA-256 of a file's contents as lowercase hex
+///
+/// # Errors
+///
+/// Returns error if the file cannot be read.
+pub async fn file_sha256(path: impl AsRef<Path>) -> Result<String> {
+    let file = File::open(path).await.map_err(ProtocolError::Io)?;
+    let mut hasher = Sha256::new();
+    hash_reader(file, &mut hasher).await?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Running SHA-256 of a file being received, checked against the sender's
+struct FileDigest {
+    expected: String,
+    hasher: Sha256,
+}
+
+impl FileDigest {
+    /// Start hashing, covering the first `offset` bytes already in `staged`
+    async fn start(expected: &str, staged: &Path, offset: u64) -> Result<Self> {
+        let mut hasher = Sha256::new();
+        if offset > 0 {
+            let file = File::open(staged).await.map_err(ProtocolError::Io)?;
+            hash_reader(file.take(offset), &mut hasher).await?;
+        }
+        Ok(Self {
+            expected: expected.to_ascii_lowercase(),
+            hasher,
+        })
+    }
+
+    /// Hash the next received bytes
+    fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+    }
+
+    /// Compare the digest of everything received with the expected one
+    fn verify(self) -> Result<()> {
+        let actual = hex::encode(self.hasher.finalize());
+        if actual != self.expected {
+            return Err(ProtocolError::ChecksumMismatch(self.expected, actual));
         }
+        Ok(())
     }
 }
 
@@ -169,716 +485,1190 @@ impl From<FileTransferInfo> for crate::plugins::share::FileShareInfo {
 /// Return `false` to cancel the transfer.
 pub type ProgressCallback = Box<dyn Fn(u64, u64) -> bool + Send + Sync>;
 
-/// TCP server for sending file payloads
+/// Default maximum number of progress events per second
+pub const DEFAULT_PROGRESS_RATE: u32 = 10;
+
+/// Coalesces high-frequency progress updates
 ///
-/// Listens on an available port and accepts a single connection
-/// to transfer file data.
-pub struct PayloadServer {
-    listener: TcpListener,
-    port: u16,
-    progress_callback: Option<ProgressCallback>,
+/// Payload loops report progress after every buffer, which on fast links means
+/// thousands of callbacks per second. A throttle lets an update through at most
+/// `max_per_second` times per second, or earlier once at least `min_byte_delta`
+/// bytes have accumulated since the last one. The first update and the final
+/// (complete) update are always emitted.
+///
+/// ```rust,ignore
+/// let callback = ProgressThrottle::new(10).wrap(Box::new(|done, total| {
+///     emit_progress_signal(done, total);
+///     true
+/// }));
+/// let server = server.with_progress(callback);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProgressThrottle {
+    min_interval: Duration,
+    min_byte_delta: Option<u64>,
+    last_emit: Option<(std::time::Instant, u64)>,
+    completed: bool,
 }
 
-impl PayloadServer {
-    /// Create a new payload server on an available port
-    ///
-    /// Binds to 0.0.0.0 in the CConnect port range (1739-1764).
-    ///
-    /// # Errors
+impl ProgressThrottle {
+    /// Create a throttle emitting at most `max_per_second` updates per second
     ///
-    /// Returns error if no ports are available in the range.
-    pub async fn new() -> Result<Self> {
-        // Try to bind to a port in the CConnect range
-        for port in PORT_RANGE_START..=PORT_RANGE_END {
-            let addr = format!("0.0.0.0:{}", port);
-            if let Ok(listener) = TcpListener::bind(&addr).await {
-                info!("Payload server listening on port {}", port);
-                return Ok(Self {
-                    listener,
-                    port,
-                    progress_callback: None,
-                });
-            }
+    /// A rate of 0 disables time-based emission, leaving only the first, final and
+    /// byte-delta updates.
+    pub fn new(max_per_second: u32) -> Self {
+        let min_interval = if max_per_second == 0 {
+            Duration::MAX
+        } else {
+            Duration::from_secs(1) / max_per_second
+        };
+        Self {
+            min_interval,
+            min_byte_delta: None,
+            last_emit: None,
+            completed: false,
         }
+    }
 
-        Err(ProtocolError::Io(std::io::Error::new(
-            std::io::ErrorKind::AddrInUse,
-            format!(
-                "Failed to bind payload server - all ports in range {}-{} are in use",
-                PORT_RANGE_START, PORT_RANGE_END
-            ),
-        )))
+    /// Also emit whenever at least `bytes` have been transferred since the last update
+    pub fn with_min_byte_delta(mut self, bytes: u64) -> Self {
+        self.min_byte_delta = Some(bytes);
+        self
     }
 
-    /// Create a new payload server using blocking I/O
-    ///
-    /// This variant uses std::net::TcpListener internally and converts to tokio,
-    /// making it safe to call from contexts without an active tokio reactor
-    /// (e.g., zbus DBus handlers).
-    ///
-    /// Binds to 0.0.0.0 in the CConnect port range (1739-1764).
-    ///
-    /// # Errors
-    ///
-    /// Returns error if no ports are available in the range.
-    pub fn new_blocking() -> Result<Self> {
-        // Try to bind to a port in the CConnect range using std::net
-        for port in PORT_RANGE_START..=PORT_RANGE_END {
-            let addr = format!("0.0.0.0:{}", port);
-            if let Ok(std_listener) = std::net::TcpListener::bind(&addr) {
-                // Set non-blocking for tokio compatibility
-                std_listener
-                    .set_nonblocking(true)
-                    .map_err(ProtocolError::Io)?;
-                // Convert to tokio TcpListener
-                let listener = TcpListener::from_std(std_listener).map_err(ProtocolError::Io)?;
-                info!("Payload server listening on port {} (blocking init)", port);
-                return Ok(Self {
-                    listener,
-                    port,
-                    progress_callback: None,
-                });
-            }
+    /// Decide whether an update for `transferred` of `total` bytes should be emitted
+    pub fn should_emit(&mut self, transferred: u64, total: u64) -> bool {
+        if self.completed {
+            return false;
         }
 
-        Err(ProtocolError::Io(std::io::Error::new(
-            std::io::ErrorKind::AddrInUse,
-            format!(
-                "Failed to bind payload server - all ports in range {}-{} are in use",
-                PORT_RANGE_START, PORT_RANGE_END
-            ),
-        )))
+        let now = std::time::Instant::now();
+        let emit = if transferred >= total {
+            self.completed = true;
+            true
+        } else {
+            match self.last_emit {
+                None => true,
+                Some((at, bytes)) => {
+                    now.duration_since(at) >= self.min_interval
+                        || self
+                            .min_byte_delta
+                            .is_some_and(|delta| transferred.saturating_sub(bytes) >= delta)
+                }
+            }
+        };
+
+        if emit {
+            self.last_emit = Some((now, transferred));
+        }
+        emit
     }
 
-    /// Set a progress callback for transfer updates
-    ///
-    /// The callback receives (bytes_transferred, total_bytes) and returns
-    /// `true` to continue or `false` to cancel the transfer.
-    ///
-    /// # Example
+    /// Wrap a progress callback so only throttled updates reach it
     ///
-    /// ```rust,ignore
-    /// let server = PayloadServer::new().await?;
-    /// server.with_progress(Box::new(|transferred, total| {
-    ///     println!("Progress: {}/{} bytes", transferred, total);
-    ///     true // continue transfer
-    /// }));
-    /// ```
-    pub fn with_progress(mut self, callback: ProgressCallback) -> Self {
-        self.progress_callback = Some(callback);
-        self
+    /// Suppressed updates continue the transfer; cancellation is picked up on the
+    /// next emitted update.
+    pub fn wrap(self, callback: ProgressCallback) -> ProgressCallback {
+        let throttle = std::sync::Mutex::new(self);
+        Box::new(move |transferred, total| {
+            let emit = throttle
+                .lock()
+                .map(|mut t| t.should_emit(transferred, total))
+                .unwrap_or(true);
+            if emit {
+                callback(transferred, total)
+            } else {
+                true
+            }
+        })
     }
+}
 
-    /// Get the port this server is listening on
-    pub fn port(&self) -> u16 {
-        self.port
+impl Default for ProgressThrottle {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROGRESS_RATE)
     }
+}
 
-    /// Get the socket address this server is bound to
-    pub fn local_addr(&self) -> Result<SocketAddr> {
-        self.listener.local_addr().map_err(ProtocolError::Io)
+/// Wait for the receiver's accept/decline byte
+///
+/// Anything other than an explicit accept within `confirm_timeout` counts as
+/// a decline.
+async fn await_confirmation<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    confirm_timeout: Duration,
+) -> Result<()> {
+    debug!(
+        "Waiting up to {}s for receiver to confirm transfer",
+        confirm_timeout.as_secs()
+    );
+
+    let mut answer = [0u8; 1];
+    match timeout(confirm_timeout, stream.read(&mut answer)).await {
+        Ok(Ok(1)) if answer[0] == CONFIRM_ACCEPT => {
+            info!("Receiver accepted transfer");
+            Ok(())
+        }
+        Ok(Ok(1)) => Err(ProtocolError::PeerRejected(
+            "receiver declined the transfer".to_string(),
+        )),
+        Ok(Ok(_)) => Err(ProtocolError::PeerRejected(
+            "receiver closed the connection without accepting".to_string(),
+        )),
+        Ok(Err(e)) => Err(ProtocolError::from_io_error(
+            e,
+            "waiting for transfer confirmation",
+        )),
+        Err(_) => Err(ProtocolError::PeerRejected(format!(
+            "no answer from receiver within {}s",
+            confirm_timeout.as_secs()
+        ))),
     }
+}
 
-    /// Accept a connection and send a file
-    ///
-    /// Waits for exactly one connection, then streams the file.
-    /// The connection is closed after the file is fully sent.
-    ///
-    /// # Parameters
-    ///
-    /// - `file_path`: Path to the file to send
-    ///
-    /// # Errors
-    ///
-    /// Returns error if:
-    /// - Connection times out
-    /// - File cannot be opened
-    /// - Transfer fails or is interrupted
-    /// - Transfer is cancelled via progress callback
-    ///
-    /// # Example
-    ///
-    /// ```rust,ignore
-    /// let server = PayloadServer::new().await?;
-    /// server.send_file("/path/to/document.pdf").await?;
-    /// ```
-    pub async fn send_file(self, file_path: impl AsRef<Path>) -> Result<()> {
-        let file_path = file_path.as_ref();
-        info!("Waiting for connection to send file: {:?}", file_path);
-
-        // Get file size for progress tracking
-        let file_size = tokio::fs::metadata(file_path)
-            .await
-            .map_err(ProtocolError::Io)?
-            .len();
+/// Write one chunk of payload data, sealed if `cipher` is set
+async fn write_payload<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    cipher: Option<&mut PayloadCipher>,
+    data: &[u8],
+) -> Result<()> {
+    match cipher {
+        Some(cipher) => write_sealed(stream, cipher, data).await,
+        None => stream.write_all(data).await.map_err(ProtocolError::Io),
+    }
+}
 
-        // Accept connection with timeout
-        let (mut stream, remote_addr) = timeout(CONNECTION_TIMEOUT, self.listener.accept())
+/// Read the next piece of payload data into `buffer`
+///
+/// Without a cipher this reads at most `remaining` raw bytes; with one it
+/// reads and opens a whole sealed chunk, growing `buffer` if needed. Returns
+/// the number of bytes placed in `buffer`, 0 at end of stream.
+async fn read_payload<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    cipher: Option<&mut PayloadCipher>,
+    buffer: &mut Vec<u8>,
+    remaining: u64,
+) -> Result<usize> {
+    let Some(cipher) = cipher else {
+        let to_read = std::cmp::min(remaining, buffer.len() as u64) as usize;
+        return stream
+            .read(&mut buffer[..to_read])
             .await
-            .map_err(|_| {
-                ProtocolError::Io(std::io::Error::new(
-                    std::io::ErrorKind::TimedOut,
-                    "Connection timeout",
-                ))
-            })?
-            .map_err(ProtocolError::Io)?;
+            .map_err(ProtocolError::Io);
+    };
+
+    let Some(chunk) = read_sealed(stream, cipher).await? else {
+        return Ok(0);
+    };
+    if chunk.len() as u64 > remaining {
+        return Err(ProtocolError::InvalidPacket(format!(
+            "Sealed chunk of {} bytes overruns the {} bytes left",
+            chunk.len(),
+            remaining
+        )));
+    }
+    if buffer.len() < chunk.len() {
+        buffer.resize(chunk.len(), 0);
+    }
+    buffer[..chunk.len()].copy_from_slice(&chunk);
+    Ok(chunk.len())
+}
 
-        info!("Accepted connection from {} for file transfer", remote_addr);
+/// Write an in-memory payload in chunks and flush it
+async fn send_buffer<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    mut cipher: Option<PayloadCipher>,
+    traffic_counter: Option<&TrafficCounter>,
+    data: &[u8],
+) -> Result<()> {
+    for chunk in data.chunks(BUFFER_SIZE) {
+        timeout(
+            TRANSFER_TIMEOUT,
+            write_payload(stream, cipher.as_mut(), chunk),
+        )
+        .await
+        .map_err(|_| ProtocolError::Timeout("Stream write timeout".to_string()))??;
+        if let Some(counter) = traffic_counter {
+            counter.record_payload_sent(chunk.len() as u64);
+        }
+    }
+    stream.flush().await.map_err(ProtocolError::Io)
+}
 
-        // Open file
-        let mut file = File::open(file_path).await.map_err(ProtocolError::Io)?;
-
-        // Stream file data
-        let mut buffer = vec![0u8; BUFFER_SIZE];
-        let mut total_bytes = 0u64;
-
-        loop {
-            // Read from file
-            let bytes_read = timeout(TRANSFER_TIMEOUT, file.read(&mut buffer))
-                .await
-                .map_err(|_| {
-                    ProtocolError::Io(std::io::Error::new(
-                        std::io::ErrorKind::TimedOut,
-                        "File read timeout",
-                    ))
-                })?
-                .map_err(ProtocolError::Io)?;
-
-            if bytes_read == 0 {
-                break; // EOF
-            }
-
-            // Write to stream
-            timeout(TRANSFER_TIMEOUT, stream.write_all(&buffer[..bytes_read]))
-                .await
-                .map_err(|_| {
-                    ProtocolError::Io(std::io::Error::new(
-                        std::io::ErrorKind::TimedOut,
-                        "Stream write timeout",
-                    ))
-                })?
-                .map_err(ProtocolError::Io)?;
-
-            total_bytes += bytes_read as u64;
-
-            debug!(
-                "Transferred {} bytes ({}/{} total)",
-                bytes_read, total_bytes, file_size
-            );
-
-            // Call progress callback if set
-            if let Some(ref callback) = self.progress_callback {
-                if !callback(total_bytes, file_size) {
-                    info!("Transfer cancelled by progress callback");
-                    return Err(ProtocolError::Io(std::io::Error::new(
-                        std::io::ErrorKind::Interrupted,
-                        "Transfer cancelled",
-                    )));
-                }
-            }
+/// Read exactly `expected_size` payload bytes into memory
+async fn receive_buffer<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    mut cipher: Option<PayloadCipher>,
+    traffic_counter: Option<&TrafficCounter>,
+    expected_size: u64,
+) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    while (data.len() as u64) < expected_size {
+        let remaining = expected_size - data.len() as u64;
+        let bytes_read = timeout(
+            TRANSFER_TIMEOUT,
+            read_payload(stream, cipher.as_mut(), &mut buffer, remaining),
+        )
+        .await
+        .map_err(|_| ProtocolError::Timeout("Stream read timeout".to_string()))??;
+
+        if bytes_read == 0 {
+            return Err(ProtocolError::Io(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!(
+                    "Connection closed prematurely: received {} bytes, expected {}",
+                    data.len(),
+                    expected_size
+                ),
+            )));
         }
+        data.extend_from_slice(&buffer[..bytes_read]);
+        if let Some(counter) = traffic_counter {
+            counter.record_payload_received(bytes_read as u64);
+        }
+    }
+    Ok(data)
+}
 
-        // Flush stream
-        stream.flush().await.map_err(ProtocolError::Io)?;
-
-        info!(
-            "File transfer complete: {} bytes sent to {}",
-            total_bytes, remote_addr
-        );
+/// Outcome of sending one file to several devices
+#[derive(Debug, Default)]
+pub struct FanOutSummary {
+    /// Size of the file in bytes
+    pub file_size: u64,
+    /// Devices that received the whole file
+    pub delivered: Vec<String>,
+    /// Devices whose transfer failed, with the reason
+    pub failed: Vec<(String, ProtocolError)>,
+}
 
-        Ok(())
+impl FanOutSummary {
+    /// Whether every device received the file
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
     }
 }
 
-/// TCP client for receiving file payloads
-///
-/// Connects to a remote payload server and downloads file data.
-pub struct PayloadClient {
-    stream: TcpStream,
+/// One receiver of a fan-out transfer
+struct FanOutLeg<S> {
+    device_id: String,
+    stream: S,
+    cipher: Option<PayloadCipher>,
+    traffic_counter: Option<TrafficCounter>,
     progress_callback: Option<ProgressCallback>,
 }
 
-impl PayloadClient {
-    /// Connect to a remote payload server
-    ///
-    /// # Parameters
-    ///
-    /// - `host`: Remote host IP address or hostname
-    /// - `port`: Remote port number
-    ///
-    /// # Errors
-    ///
-    /// Returns error if connection fails or times out.
-    pub async fn new(host: &str, port: u16) -> Result<Self> {
-        use std::net::IpAddr;
-        use std::str::FromStr;
+impl<S: AsyncWrite + Unpin> FanOutLeg<S> {
+    /// Write one chunk and report progress
+    async fn send(&mut self, data: &[u8], sent: u64, file_size: u64) -> Result<()> {
+        timeout(
+            TRANSFER_TIMEOUT,
+            write_payload(&mut self.stream, self.cipher.as_mut(), data),
+        )
+        .await
+        .map_err(|_| {
+            ProtocolError::Io(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "Stream write timeout",
+            ))
+        })??;
 
-        // Try to parse as IP address first, otherwise do DNS resolution
-        let addr = if let Ok(ip) = IpAddr::from_str(host) {
-            SocketAddr::new(ip, port)
-        } else {
-            // Fall back to DNS resolution with "host:port" format
-            let addr_str = format!("{}:{}", host, port);
-            let addrs: Vec<SocketAddr> = addr_str
-                .to_socket_addrs()
-                .map_err(ProtocolError::Io)?
-                .collect();
-            if addrs.is_empty() {
+        if let Some(ref counter) = self.traffic_counter {
+            counter.record_payload_sent(data.len() as u64);
+        }
+        if let Some(ref callback) = self.progress_callback {
+            if !callback(sent, file_size) {
+                info!(
+                    "Transfer to {} cancelled by progress callback",
+                    self.device_id
+                );
                 return Err(ProtocolError::Io(std::io::Error::new(
-                    std::io::ErrorKind::InvalidInput,
-                    "No addresses resolved for host",
+                    std::io::ErrorKind::Interrupted,
+                    "Transfer cancelled",
                 )));
             }
-            addrs[0]
-        };
-
-        info!("Connecting to payload server at {}", addr);
+        }
+        Ok(())
+    }
+}
 
-        let stream = timeout(CONNECTION_TIMEOUT, TcpStream::connect(&addr))
+/// Read `file_path` once and stream each chunk to every leg concurrently
+///
+/// A leg that fails is dropped and reported in the summary; the others carry
+/// on. Only failing to read the file itself is an error.
+async fn fan_out<S: AsyncWrite + Unpin>(
+    file_path: &Path,
+    mut legs: Vec<FanOutLeg<S>>,
+    mut summary: FanOutSummary,
+) -> Result<FanOutSummary> {
+    let mut file = File::open(file_path).await.map_err(ProtocolError::Io)?;
+    summary.file_size = file.metadata().await.map_err(ProtocolError::Io)?.len();
+
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut total_bytes = 0u64;
+    while !legs.is_empty() {
+        let bytes_read = timeout(TRANSFER_TIMEOUT, file.read(&mut buffer))
             .await
             .map_err(|_| {
                 ProtocolError::Io(std::io::Error::new(
                     std::io::ErrorKind::TimedOut,
-                    "Connection timeout",
+                    "File read timeout",
                 ))
             })?
             .map_err(ProtocolError::Io)?;
+        if bytes_read == 0 {
+            break;
+        }
+        total_bytes += bytes_read as u64;
 
-        info!("Connected to payload server at {}", addr);
+        let chunk = &buffer[..bytes_read];
+        let file_size = summary.file_size;
+        let results = futures::future::join_all(
+            legs.iter_mut()
+                .map(|leg| leg.send(chunk, total_bytes, file_size)),
+        )
+        .await;
 
-        Ok(Self {
-            stream,
-            progress_callback: None,
-        })
+        let mut remaining = Vec::with_capacity(legs.len());
+        for (leg, result) in legs.into_iter().zip(results) {
+            match result {
+                Ok(()) => remaining.push(leg),
+                Err(e) => {
+                    warn!("Fan-out transfer to {} failed: {}", leg.device_id, e);
+                    summary.failed.push((leg.device_id, e));
+                }
+            }
+        }
+        legs = remaining;
     }
 
-    /// Set a progress callback for transfer updates
-    ///
-    /// The callback receives (bytes_transferred, total_bytes) and returns
-    /// `true` to continue or `false` to cancel the transfer.
-    ///
-    /// # Example
-    ///
-    /// ```rust,ignore
-    /// let client = PayloadClient::new("192.168.1.100", 1739).await?;
-    /// client.with_progress(Box::new(|transferred, total| {
-    ///     println!("Progress: {}/{} bytes", transferred, total);
-    ///     true // continue transfer
-    /// }));
-    /// ```
-    pub fn with_progress(mut self, callback: ProgressCallback) -> Self {
-        self.progress_callback = Some(callback);
+    for mut leg in legs {
+        match leg.stream.flush().await {
+            Ok(()) => summary.delivered.push(leg.device_id),
+            Err(e) => summary.failed.push((leg.device_id, ProtocolError::Io(e))),
+        }
+    }
+
+    info!(
+        "Fan-out transfer of {:?} complete: {} delivered, {} failed",
+        file_path,
+        summary.delivered.len(),
+        summary.failed.len()
+    );
+    Ok(summary)
+}
+
+/// Maximum incoming file sizes, globally and per device
+///
+/// A limit of `None` means unlimited. Per-device entries take precedence over
+/// the default, including an explicit `None` that lifts the default for a
+/// trusted device.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileSizeLimits {
+    default: Option<u64>,
+    devices: HashMap<String, Option<u64>>,
+}
+
+impl FileSizeLimits {
+    /// Create limits with a global default and no per-device overrides
+    pub fn new(default: Option<u64>) -> Self {
+        Self {
+            default,
+            devices: HashMap::new(),
+        }
+    }
+
+    /// Override the limit for one device
+    pub fn with_device_limit(mut self, device_id: impl Into<String>, limit: Option<u64>) -> Self {
+        self.set_device_limit(device_id, limit);
         self
     }
 
-    /// Receive a file from the connected server
-    ///
-    /// Downloads the specified number of bytes and saves to a file.
-    ///
-    /// # Parameters
-    ///
-    /// - `save_path`: Path where the file should be saved
-    /// - `expected_size`: Expected file size in bytes
+    /// Override the limit for one device
+    pub fn set_device_limit(&mut self, device_id: impl Into<String>, limit: Option<u64>) {
+        self.devices.insert(device_id.into(), limit);
+    }
+
+    /// Remove a device override so the default applies again
+    pub fn clear_device_limit(&mut self, device_id: &str) {
+        self.devices.remove(device_id);
+    }
+
+    /// Global default limit
+    pub fn default_limit(&self) -> Option<u64> {
+        self.default
+    }
+
+    /// Effective limit for a device
+    pub fn limit_for(&self, device_id: &str) -> Option<u64> {
+        self.devices.get(device_id).copied().unwrap_or(self.default)
+    }
+
+    /// Check an incoming file against a device's limit
     ///
     /// # Errors
     ///
-    /// Returns error if:
-    /// - File cannot be created
-    /// - Transfer fails or times out
-    /// - Size mismatch (received != expected)
-    /// - Transfer is cancelled via progress callback
-    ///
-    /// # Example
-    ///
-    /// ```rust,ignore
-    /// let client = PayloadClient::new("192.168.1.100", 1739).await?;
-    /// client.receive_file("/tmp/received_file.pdf", 1048576).await?;
-    /// ```
-    pub async fn receive_file(
-        mut self,
-        save_path: impl AsRef<Path>,
-        expected_size: u64,
-    ) -> Result<()> {
-        let save_path = save_path.as_ref();
-        info!(
-            "Receiving file to {:?} ({} bytes expected)",
-            save_path, expected_size
-        );
-
-        // Create file with safe error handling
-        let mut file = match create_file_safe(save_path).await {
-            Ok(f) => f,
-            Err(e) => {
-                warn!("Failed to create file {:?}: {}", save_path, e);
-                return Err(e);
-            }
-        };
+    /// Returns [`ProtocolError::ResourceExhausted`] if `size` exceeds the limit.
+    pub fn check(&self, device_id: &str, size: u64) -> Result<()> {
+        check_size_limit(self.limit_for(device_id), size)
+    }
+}
 
-        // Read and write data
-        let mut buffer = vec![0u8; BUFFER_SIZE];
-        let mut total_bytes = 0u64;
+/// How staged transfers from a device are handled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveTrust {
+    /// Receive without asking
+    AutoAccept,
+    /// Ask the user; no answer in time declines
+    #[default]
+    Prompt,
+    /// Decline without asking
+    Reject,
+}
 
-        let result = async {
-            while total_bytes < expected_size {
-                let remaining = expected_size - total_bytes;
-                let to_read = std::cmp::min(remaining, BUFFER_SIZE as u64) as usize;
+/// Receive trust levels, globally and per device
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReceiveTrustLevels {
+    default: ReceiveTrust,
+    devices: HashMap<String, ReceiveTrust>,
+}
 
-                // Read from stream
-                let bytes_read =
-                    timeout(TRANSFER_TIMEOUT, self.stream.read(&mut buffer[..to_read]))
-                        .await
-                        .map_err(|_| {
-                            ProtocolError::Timeout(
-                                "Stream read timeout during file transfer".to_string(),
-                            )
-                        })?
-                        .map_err(ProtocolError::Io)?;
+impl ReceiveTrustLevels {
+    /// Create levels with a global default and no per-device overrides
+    pub fn new(default: ReceiveTrust) -> Self {
+        Self {
+            default,
+            devices: HashMap::new(),
+        }
+    }
 
-                if bytes_read == 0 {
-                    return Err(ProtocolError::Io(std::io::Error::new(
-                        std::io::ErrorKind::UnexpectedEof,
-                        format!(
-                            "Connection closed prematurely: received {} bytes, expected {}",
-                            total_bytes, expected_size
-                        ),
-                    )));
-                }
+    /// Override the level for one device
+    pub fn with_device_trust(mut self, device_id: impl Into<String>, trust: ReceiveTrust) -> Self {
+        self.set_device_trust(device_id, trust);
+        self
+    }
 
-                // Write to file with safe error handling
-                write_file_safe(&mut file, &buffer[..bytes_read]).await?;
+    /// Override the level for one device
+    pub fn set_device_trust(&mut self, device_id: impl Into<String>, trust: ReceiveTrust) {
+        self.devices.insert(device_id.into(), trust);
+    }
 
-                total_bytes += bytes_read as u64;
+    /// Remove a device override so the default applies again
+    pub fn clear_device_trust(&mut self, device_id: &str) {
+        self.devices.remove(device_id);
+    }
 
-                debug!(
-                    "Received {} bytes ({}/{} total)",
-                    bytes_read, total_bytes, expected_size
-                );
+    /// Global default level
+    pub fn default_trust(&self) -> ReceiveTrust {
+        self.default
+    }
 
-                // Call progress callback if set
-                if let Some(ref callback) = self.progress_callback {
-                    if !callback(total_bytes, expected_size) {
-                        info!("Transfer cancelled by progress callback");
-                        return Err(ProtocolError::Io(std::io::Error::new(
-                            std::io::ErrorKind::Interrupted,
-                            "Transfer cancelled",
-                        )));
-                    }
-                }
-            }
+    /// Effective level for a device
+    pub fn trust_for(&self, device_id: &str) -> ReceiveTrust {
+        self.devices.get(device_id).copied().unwrap_or(self.default)
+    }
 
-            // Flush file
-            file.flush().await.map_err(ProtocolError::Io)?;
+    /// Level set for the device itself, if any
+    pub fn device_trust(&self, device_id: &str) -> Option<ReceiveTrust> {
+        self.devices.get(device_id).copied()
+    }
+}
 
-            info!(
-                "File transfer complete: {} bytes received to {:?}",
-                total_bytes, save_path
-            );
+/// An incoming transfer waiting for the user's decision
+///
+/// Dropping the prompt without answering declines the transfer.
+#[derive(Debug)]
+pub struct TransferPrompt {
+    /// Sending device
+    pub device_id: String,
+    /// Name the file would be saved under
+    pub filename: String,
+    /// File size in bytes
+    pub size: u64,
+    answer: oneshot::Sender<bool>,
+}
 
-            Ok(())
-        }
-        .await;
+impl TransferPrompt {
+    /// Receive the file
+    pub fn accept(self) {
+        let _ = self.answer.send(true);
+    }
 
-        // Clean up partial file on error
-        if result.is_err() {
-            warn!("Transfer failed, cleaning up partial file: {:?}", save_path);
-            cleanup_partial_file(save_path).await;
-        }
+    /// Refuse the file
+    pub fn decline(self) {
+        let _ = self.answer.send(false);
+    }
 
-        result
+    /// Whether the transfer stopped waiting, e.g. because the prompt timed out
+    pub fn is_expired(&self) -> bool {
+        self.answer.is_closed()
     }
 }
 
-/// TLS-enabled TCP client for receiving file payloads
+/// Transfer prompts waiting for the user, by prompt ID
 ///
-/// Connects to a remote payload server with TLS encryption.
-/// Uses KDE Connect's inverted TLS roles: TCP initiator acts as TLS SERVER.
-///
-/// ## Security
-///
-/// - Uses TLS 1.2+ with mutual certificate authentication
-/// - Trust-On-First-Use (TOFU) model - certificates are verified at application layer
-/// - Same certificate used for main connection and payload transfers
-///
-/// ## Example
-///
-/// ```rust,ignore
-/// use cosmic_ext_connect_core::payload::TlsPayloadClient;
-///
-/// // Get TLS config (same as main connection)
-/// let tls_config = TlsConfig::new(&certificate)?;
-///
-/// // Connect to payload server with TLS
-/// let client = TlsPayloadClient::new("192.168.1.100", 1739, &tls_config).await?;
-/// client.receive_file("/tmp/received_file.pdf", 1048576).await?;
-/// ```
-pub struct TlsPayloadClient {
-    stream: tokio_rustls::server::TlsStream<TcpStream>,
-    progress_callback: Option<ProgressCallback>,
+/// Expired prompts are dropped as they are found.
+#[derive(Debug, Clone, Default)]
+pub struct PendingTransferPrompts {
+    pending: std::sync::Arc<std::sync::Mutex<HashMap<String, TransferPrompt>>>,
 }
 
-impl TlsPayloadClient {
-    /// Connect to a remote payload server with TLS
-    ///
-    /// Establishes a TLS connection using KDE Connect's inverted roles:
-    /// - TCP connection initiated by us
-    /// - TLS handshake performed as SERVER (inverted role!)
-    ///
-    /// # Parameters
-    ///
-    /// - `host`: Remote host IP address or hostname
-    /// - `port`: Remote port number
-    /// - `tls_config`: TLS configuration with our certificate
-    ///
-    /// # Errors
-    ///
-    /// Returns error if connection fails, times out, or TLS handshake fails.
-    pub async fn new(host: &str, port: u16, tls_config: &TlsConfig) -> Result<Self> {
-        use std::net::IpAddr;
-        use std::str::FromStr;
-
-        // Try to parse as IP address first, otherwise do DNS resolution
-        let addr = if let Ok(ip) = IpAddr::from_str(host) {
-            SocketAddr::new(ip, port)
-        } else {
-            // Fall back to DNS resolution with "host:port" format
-            let addr_str = format!("{}:{}", host, port);
-            let addrs: Vec<SocketAddr> = addr_str
-                .to_socket_addrs()
-                .map_err(ProtocolError::Io)?
-                .collect();
-            if addrs.is_empty() {
-                return Err(ProtocolError::Io(std::io::Error::new(
-                    std::io::ErrorKind::InvalidInput,
-                    "No addresses resolved for host",
-                )));
-            }
-            addrs[0]
-        };
-
-        info!("Connecting to payload server at {} with TLS", addr);
+impl PendingTransferPrompts {
+    /// Create an empty set of prompts
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        // Connect TCP first
-        let tcp_stream = timeout(CONNECTION_TIMEOUT, TcpStream::connect(&addr))
-            .await
-            .map_err(|_| {
-                ProtocolError::Io(std::io::Error::new(
-                    std::io::ErrorKind::TimedOut,
-                    "Connection timeout",
-                ))
-            })?
-            .map_err(ProtocolError::Io)?;
+    /// Hold a prompt until it is answered, returning its ID
+    pub fn insert(&self, prompt: TransferPrompt) -> String {
+        let prompt_id = uuid::Uuid::new_v4().to_string();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.retain(|_, prompt| !prompt.is_expired());
+            pending.insert(prompt_id.clone(), prompt);
+        }
+        prompt_id
+    }
 
-        debug!("TCP connection established to payload server at {}", addr);
+    /// Receive the file of a prompt
+    ///
+    /// Returns `false` if the prompt is unknown, already answered or expired.
+    pub fn accept(&self, prompt_id: &str) -> bool {
+        self.take(prompt_id).map(TransferPrompt::accept).is_some()
+    }
 
-        // KDE Connect quirk: TCP initiator acts as TLS SERVER
-        // Create TLS acceptor with SERVER config (inverted role!)
-        let acceptor = TlsAcceptor::from(tls_config.server_config());
+    /// Refuse the file of a prompt
+    ///
+    /// Returns `false` if the prompt is unknown, already answered or expired.
+    pub fn decline(&self, prompt_id: &str) -> bool {
+        self.take(prompt_id).map(TransferPrompt::decline).is_some()
+    }
 
-        // Perform TLS handshake as SERVER
-        let tls_stream: tokio_rustls::server::TlsStream<TcpStream> =
-            timeout(CONNECTION_TIMEOUT, acceptor.accept(tcp_stream))
-                .await
-                .map_err(|_| {
-                    ProtocolError::Io(std::io::Error::new(
-                        std::io::ErrorKind::TimedOut,
-                        "TLS handshake timeout",
-                    ))
-                })?
-                .map_err(|e| {
-                    error!("TLS handshake failed for payload transfer: {}", e);
-                    ProtocolError::Io(std::io::Error::new(
-                        std::io::ErrorKind::ConnectionRefused,
-                        format!("TLS handshake failed: {}", e),
-                    ))
-                })?;
+    /// Whether a prompt still waits for an answer
+    pub fn contains(&self, prompt_id: &str) -> bool {
+        self.pending
+            .lock()
+            .map(|pending| pending.get(prompt_id).is_some_and(|p| !p.is_expired()))
+            .unwrap_or(false)
+    }
 
-        info!(
-            "TLS connection established to payload server at {} (as TLS SERVER)",
-            addr
-        );
+    /// Number of prompts waiting for an answer
+    pub fn len(&self) -> usize {
+        self.pending
+            .lock()
+            .map(|pending| pending.values().filter(|p| !p.is_expired()).count())
+            .unwrap_or(0)
+    }
 
-        Ok(Self {
-            stream: tls_stream,
-            progress_callback: None,
-        })
+    /// Whether no prompt waits for an answer
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
-    /// Set a progress callback for transfer updates
-    ///
-    /// The callback receives (bytes_transferred, total_bytes) and returns
-    /// `true` to continue or `false` to cancel the transfer.
-    pub fn with_progress(mut self, callback: ProgressCallback) -> Self {
-        self.progress_callback = Some(callback);
-        self
+    fn take(&self, prompt_id: &str) -> Option<TransferPrompt> {
+        self.pending
+            .lock()
+            .ok()?
+            .remove(prompt_id)
+            .filter(|prompt| !prompt.is_expired())
     }
+}
 
-    /// Receive a file from the connected server over TLS
-    ///
-    /// Downloads the specified number of bytes and saves to a file.
-    ///
-    /// # Parameters
-    ///
-    /// - `save_path`: Path where the file should be saved
-    /// - `expected_size`: Expected file size in bytes
-    ///
-    /// # Errors
-    ///
-    /// Returns error if:
-    /// - File cannot be created
-    /// - Transfer fails or times out
-    /// - Size mismatch (received != expected)
-    /// - Transfer is cancelled via progress callback
-    pub async fn receive_file(
-        mut self,
-        save_path: impl AsRef<Path>,
-        expected_size: u64,
-    ) -> Result<()> {
-        let save_path = save_path.as_ref();
-        info!(
-            "Receiving file to {:?} ({} bytes expected) over TLS",
-            save_path, expected_size
-        );
+/// Decides whether staged transfers are received, per device trust level
+#[derive(Debug, Clone)]
+pub struct TransferGate {
+    levels: ReceiveTrustLevels,
+    prompts: mpsc::Sender<TransferPrompt>,
+    prompt_timeout: Duration,
+}
 
-        // Create file with safe error handling
-        let mut file = match create_file_safe(save_path).await {
-            Ok(f) => f,
-            Err(e) => {
-                warn!("Failed to create file {:?}: {}", save_path, e);
-                return Err(e);
-            }
+impl TransferGate {
+    /// Create a gate and the channel its prompts arrive on
+    ///
+    /// A prompt not answered within `prompt_timeout` declines the transfer,
+    /// as does one sent after the receiver was dropped.
+    pub fn new(
+        levels: ReceiveTrustLevels,
+        prompt_timeout: Duration,
+    ) -> (Self, mpsc::Receiver<TransferPrompt>) {
+        let (prompts, receiver) = mpsc::channel(16);
+        let gate = Self {
+            levels,
+            prompts,
+            prompt_timeout,
         };
+        (gate, receiver)
+    }
 
-        // Read and write data
-        let mut buffer = vec![0u8; BUFFER_SIZE];
-        let mut total_bytes = 0u64;
-
-        let result = async {
-            while total_bytes < expected_size {
-                let remaining = expected_size - total_bytes;
-                let to_read = std::cmp::min(remaining, BUFFER_SIZE as u64) as usize;
-
-                // Read from TLS stream
-                let bytes_read: usize =
-                    timeout(TRANSFER_TIMEOUT, self.stream.read(&mut buffer[..to_read]))
-                        .await
-                        .map_err(|_| {
-                            ProtocolError::Timeout(
-                                "TLS stream read timeout during file transfer".to_string(),
-                            )
-                        })?
-                        .map_err(ProtocolError::Io)?;
+    /// Trust levels the gate applies
+    pub fn levels(&self) -> &ReceiveTrustLevels {
+        &self.levels
+    }
 
-                if bytes_read == 0 {
-                    return Err(ProtocolError::Io(std::io::Error::new(
-                        std::io::ErrorKind::UnexpectedEof,
-                        format!(
-                            "TLS connection closed prematurely: received {} bytes, expected {}",
-                            total_bytes, expected_size
-                        ),
-                    )));
-                }
+    /// Replace the trust levels
+    pub fn set_levels(&mut self, levels: ReceiveTrustLevels) {
+        self.levels = levels;
+    }
 
-                // Write to file with safe error handling
-                write_file_safe(&mut file, &buffer[..bytes_read]).await?;
+    /// Whether a file from `device_id` should be received
+    pub async fn decide(&self, device_id: &str, filename: &str, size: u64) -> bool {
+        self.apply(self.levels.trust_for(device_id), device_id, filename, size)
+            .await
+    }
 
-                total_bytes += bytes_read as u64;
+    /// Whether a staged file from `device_id` should be received
+    ///
+    /// The sender staged the file to have the user decide, so only a device
+    /// trusted on its own skips the prompt; a global `AutoAccept` default
+    /// still asks.
+    pub async fn decide_staged(&self, device_id: &str, filename: &str, size: u64) -> bool {
+        let trust = match self.levels.device_trust(device_id) {
+            Some(trust) => trust,
+            None if self.levels.default_trust() == ReceiveTrust::Reject => ReceiveTrust::Reject,
+            None => ReceiveTrust::Prompt,
+        };
+        self.apply(trust, device_id, filename, size).await
+    }
 
-                debug!(
-                    "Received {} bytes over TLS ({}/{} total)",
-                    bytes_read, total_bytes, expected_size
+    async fn apply(&self, trust: ReceiveTrust, device_id: &str, filename: &str, size: u64) -> bool {
+        match trust {
+            ReceiveTrust::AutoAccept => true,
+            ReceiveTrust::Reject => {
+                info!(
+                    "Rejecting '{}' from untrusted device {}",
+                    filename, device_id
                 );
+                false
+            }
+            ReceiveTrust::Prompt => self.prompt(device_id, filename, size).await,
+        }
+    }
 
-                // Call progress callback if set
-                if let Some(ref callback) = self.progress_callback {
-                    if !callback(total_bytes, expected_size) {
-                        info!("Transfer cancelled by progress callback");
-                        return Err(ProtocolError::Io(std::io::Error::new(
-                            std::io::ErrorKind::Interrupted,
-                            "Transfer cancelled",
-                        )));
-                    }
-                }
+    async fn prompt(&self, device_id: &str, filename: &str, size: u64) -> bool {
+        let (answer, receiver) = oneshot::channel();
+        let prompt = TransferPrompt {
+            device_id: device_id.to_string(),
+            filename: filename.to_string(),
+            size,
+            answer,
+        };
+        if self.prompts.send(prompt).await.is_err() {
+            warn!(
+                "Nobody to ask about '{}' from {}, declining",
+                filename, device_id
+            );
+            return false;
+        }
+        match timeout(self.prompt_timeout, receiver).await {
+            Ok(Ok(accepted)) => accepted,
+            Ok(Err(_)) => false,
+            Err(_) => {
+                info!(
+                    "No answer about '{}' from {} within {}s, declining",
+                    filename,
+                    device_id,
+                    self.prompt_timeout.as_secs()
+                );
+                false
             }
+        }
+    }
+}
 
-            // Flush file
-            file.flush().await.map_err(ProtocolError::Io)?;
+/// Name shown when asking about a file saved to `save_path`
+fn prompt_filename(save_path: &Path) -> String {
+    save_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
 
-            info!(
-                "TLS file transfer complete: {} bytes received to {:?}",
-                total_bytes, save_path
-            );
+/// Refuse a transfer larger than `limit`
+fn check_size_limit(limit: Option<u64>, size: u64) -> Result<()> {
+    match limit {
+        Some(max) if size > max => Err(ProtocolError::ResourceExhausted(format!(
+            "incoming file of {} bytes exceeds the {} byte limit",
+            size, max
+        ))),
+        _ => Ok(()),
+    }
+}
 
-            Ok(())
-        }
-        .await;
+/// Where a received file is written, confined to `sandbox` if there is one
+fn resolve_save_path(sandbox: Option<&TransferSandbox>, save_path: &Path) -> Result<PathBuf> {
+    match sandbox {
+        Some(sandbox) => sandbox.resolve(save_path),
+        None => Ok(save_path.to_path_buf()),
+    }
+}
 
-        // Clean up partial file on error
-        if result.is_err() {
-            warn!(
-                "TLS transfer failed, cleaning up partial file: {:?}",
-                save_path
-            );
-            cleanup_partial_file(save_path).await;
+/// Verify a completely received staged file and move it to `save_path`
+async fn finalize_staged(
+    staged: &Path,
+    save_path: &Path,
+    checksums: Option<&ChunkChecksums>,
+) -> Result<()> {
+    if let Some(checksums) = checksums {
+        let failing = checksums.failing_chunks(staged).await?;
+        if !failing.is_empty() {
+            return Err(ProtocolError::Transport(format!(
+                "{} of {} chunk(s) failed checksum verification",
+                failing.len(),
+                checksums.chunk_count()
+            )));
         }
+    }
+    commit_staged_file(staged, save_path).await
+}
 
-        result
+/// Send the accept/decline byte for a staged transfer
+async fn send_confirmation<S: AsyncWrite + Unpin>(stream: &mut S, accept: bool) -> Result<()> {
+    let answer = if accept {
+        CONFIRM_ACCEPT
+    } else {
+        CONFIRM_DECLINE
+    };
+    stream
+        .write_all(&[answer])
+        .await
+        .map_err(ProtocolError::Io)?;
+    stream.flush().await.map_err(ProtocolError::Io)?;
+    if !accept {
+        let _ = stream.shutdown().await;
+        info!("Declined incoming transfer");
     }
+    Ok(())
 }
 
-/// TLS-enabled TCP server for sending file payloads
-///
-/// Listens on an available port and accepts a single connection with TLS encryption.
-/// Uses KDE Connect's inverted TLS roles: TCP acceptor acts as TLS CLIENT.
-///
-/// ## Security
+/// Tell the sender which byte a resumed transfer continues from
+async fn send_resume_offset<S: AsyncWrite + Unpin>(stream: &mut S, offset: u64) -> Result<()> {
+    stream
+        .write_all(&offset.to_be_bytes())
+        .await
+        .map_err(ProtocolError::Io)?;
+    stream.flush().await.map_err(ProtocolError::Io)
+}
+
+/// Read which byte of a `file_size` byte file the receiver wants to continue from
+async fn read_resume_offset<S: AsyncRead + Unpin>(stream: &mut S, file_size: u64) -> Result<u64> {
+    let mut offset = [0u8; 8];
+    timeout(TRANSFER_TIMEOUT, stream.read_exact(&mut offset))
+        .await
+        .map_err(|_| ProtocolError::Timeout("No resume offset from receiver".to_string()))?
+        .map_err(|e| ProtocolError::from_io_error(e, "reading resume offset"))?;
+
+    let offset = u64::from_be_bytes(offset);
+    if offset > file_size {
+        return Err(ProtocolError::InvalidPacket(format!(
+            "Resume offset {} is past the end of the {} byte file",
+            offset, file_size
+        )));
+    }
+    Ok(offset)
+}
+
+/// Position `file` where the receiver of a resumable transfer continues
 ///
-/// - Uses TLS 1.2+ with mutual certificate authentication
-/// - Trust-On-First-Use (TOFU) model - certificates are verified at application layer
-/// - Same certificate used for main connection and payload transfers
+/// Returns that offset; always 0 unless `resumable`.
+async fn seek_to_resume_offset<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    file: &mut File,
+    file_size: u64,
+    resumable: bool,
+) -> Result<u64> {
+    if !resumable {
+        return Ok(0);
+    }
+    let offset = read_resume_offset(stream, file_size).await?;
+    if offset > 0 {
+        info!("Resuming transfer at byte {} of {}", offset, file_size);
+        file.seek(SeekFrom::Start(offset))
+            .await
+            .map_err(ProtocolError::Io)?;
+    }
+    Ok(offset)
+}
+
+/// Open the staging file for a transfer, keeping up to `resume` bytes of it
 ///
-/// ## Example
+/// Returns the file positioned where the transfer continues, and that offset.
+async fn open_staged(
+    staged: &Path,
+    resume: Option<u64>,
+    expected_size: u64,
+) -> Result<(File, u64)> {
+    let existing = match resume {
+        Some(_) => tokio::fs::metadata(staged).await.map_or(0, |m| m.len()),
+        None => 0,
+    };
+    let offset = resume.unwrap_or(0).min(existing).min(expected_size);
+    if offset == 0 {
+        return Ok((create_file_safe(staged).await?, 0));
+    }
+
+    info!(
+        "Resuming {:?} at byte {} of {}",
+        staged, offset, expected_size
+    );
+    Ok((open_partial_file(staged, offset).await?, offset))
+}
+
+/// Open a TCP connection to a payload server
+async fn connect_payload(addr: SocketAddr) -> Result<TcpStream> {
+    timeout(CONNECTION_TIMEOUT, TcpStream::connect(&addr))
+        .await
+        .map_err(|_| {
+            ProtocolError::Io(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "Connection timeout",
+            ))
+        })?
+        .map_err(ProtocolError::Io)
+}
+
+/// Open a connection to a payload server and complete the TLS handshake
 ///
-/// ```rust,ignore
-/// use cosmic_ext_connect_protocol::payload::TlsPayloadServer;
+/// The handshake runs as TLS SERVER (KDE Connect's inverted roles).
+async fn connect_tls_payload(
+    addr: SocketAddr,
+    acceptor: &TlsAcceptor,
+) -> Result<tokio_rustls::server::TlsStream<TcpStream>> {
+    let tcp_stream = connect_payload(addr).await?;
+    debug!("TCP connection established to payload server at {}", addr);
+
+    timeout(CONNECTION_TIMEOUT, acceptor.accept(tcp_stream))
+        .await
+        .map_err(|_| {
+            ProtocolError::Io(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "TLS handshake timeout",
+            ))
+        })?
+        .map_err(|e| {
+            error!("TLS handshake failed for payload transfer: {}", e);
+            ProtocolError::Io(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                format!("TLS handshake failed: {}", e),
+            ))
+        })
+}
+
+/// Parallel payload streams a device accepts, from its identity packet
 ///
-/// // Create TLS payload server
-/// let server = TlsPayloadServer::new(&tls_config).await?;
-/// let port = server.port();
+/// Devices that don't advertise any take a single stream.
+pub fn advertised_payload_streams(identity: &Packet) -> usize {
+    identity
+        .get_body_field::<usize>(PAYLOAD_STREAMS_FIELD)
+        .unwrap_or(1)
+        .clamp(1, MAX_PAYLOAD_STREAMS)
+}
+
+/// How a file is split across parallel payload streams
 ///
-/// // Send share packet with port info
-/// // ... then accept connection and transfer file ...
-/// server.send_file("/path/to/file.pdf").await?;
-/// ```
-pub struct TlsPayloadServer {
-    listener: TcpListener,
-    port: u16,
-    tls_config: std::sync::Arc<TlsConfig>,
-    progress_callback: Option<ProgressCallback>,
+/// Stream `i` carries the contiguous range starting at `i * stream_size`,
+/// up to the next stream's start or the end of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamLayout {
+    /// Number of streams
+    pub streams: usize,
+    /// Bytes each stream carries (the last one may carry fewer)
+    pub stream_size: u64,
 }
 
-impl TlsPayloadServer {
-    /// Create a new TLS payload server on an available port
-    ///
-    /// Binds to 0.0.0.0 in the CConnect port range (1739-1764).
+impl StreamLayout {
+    /// Split `file_size` bytes into up to `streams` ranges
     ///
-    /// # Parameters
-    ///
-    /// - `tls_config`: TLS configuration with our certificate
-    ///
-    /// # Errors
+    /// Small files get fewer streams, so each carries at least 256 KB.
+    pub fn new(file_size: u64, streams: usize) -> Self {
+        let streams = streams
+            .clamp(1, MAX_PAYLOAD_STREAMS)
+            .min((file_size / MIN_STREAM_SIZE).max(1) as usize);
+        Self {
+            streams,
+            stream_size: file_size.div_ceil(streams as u64).max(1),
+        }
+    }
+
+    /// Byte range stream `index` carries in a file of `file_size` bytes
+    pub fn range(&self, index: usize, file_size: u64) -> Range<u64> {
+        let start = (index as u64)
+            .saturating_mul(self.stream_size)
+            .min(file_size);
+        start..start.saturating_add(self.stream_size).min(file_size)
+    }
+
+    /// Read the layout a sender advertised in a share packet
     ///
-    /// Returns error if no ports are available in the range.
-    pub async fn new(tls_config: std::sync::Arc<TlsConfig>) -> Result<Self> {
-        // Try to bind to a port in the CConnect range
-        for port in PORT_RANGE_START..=PORT_RANGE_END {
-            let addr = format!("0.0.0.0:{}", port);
+    /// Returns `None` for single-stream transfers and invalid layouts.
+    pub fn from_packet(packet: &Packet) -> Option<Self> {
+        let streams = packet.get_body_field::<usize>(PAYLOAD_STREAMS_FIELD)?;
+        let stream_size = packet.get_body_field::<u64>(PAYLOAD_STREAM_SIZE_FIELD)?;
+        ((2..=MAX_PAYLOAD_STREAMS).contains(&streams) && stream_size > 0).then_some(Self {
+            streams,
+            stream_size,
+        })
+    }
+
+    /// Advertise the layout in a share packet
+    pub fn add_to_packet(&self, packet: Packet) -> Packet {
+        packet
+            .with_body_field(PAYLOAD_STREAMS_FIELD, self.streams)
+            .with_body_field(PAYLOAD_STREAM_SIZE_FIELD, self.stream_size)
+    }
+
+    /// Check that the streams together cover `file_size` bytes
+    fn check_covers(&self, file_size: u64) -> Result<()> {
+        let covered = (self.streams as u64).saturating_mul(self.stream_size);
+        if covered < file_size {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "{} streams of {} bytes cannot carry a {} byte file",
+                self.streams, self.stream_size, file_size
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Report `bytes` more sent or received across all streams of a transfer
+fn record_stream_progress(
+    done: &AtomicU64,
+    bytes: usize,
+    total: u64,
+    progress: Option<&ProgressCallback>,
+) -> Result<()> {
+    let transferred = done.fetch_add(bytes as u64, Ordering::Relaxed) + bytes as u64;
+    if let Some(callback) = progress {
+        if !callback(transferred, total) {
+            info!("Transfer cancelled by progress callback");
+            return Err(ProtocolError::Io(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "Transfer cancelled",
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Send the range of `file_path` the receiver asks for on `stream`
+///
+/// The receiver opens each stream by naming its index as a big-endian `u32`.
+#[allow(clippy::too_many_arguments)]
+async fn send_stream_range<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    file_path: &Path,
+    file_size: u64,
+    layout: StreamLayout,
+    bandwidth: Option<&BandwidthLimit>,
+    traffic_counter: Option<&TrafficCounter>,
+    sent: &AtomicU64,
+    progress: Option<&ProgressCallback>,
+) -> Result<()> {
+    let index = timeout(CONNECTION_TIMEOUT, stream.read_u32())
+        .await
+        .map_err(|_| ProtocolError::Timeout("No stream index from receiver".to_string()))?
+        .map_err(ProtocolError::Io)? as usize;
+    if index >= layout.streams {
+        return Err(ProtocolError::InvalidPacket(format!(
+            "Stream index {} out of range ({} streams)",
+            index, layout.streams
+        )));
+    }
+    let range = layout.range(index, file_size);
+    debug!("Sending bytes {:?} on stream {}", range, index);
+
+    let mut file = File::open(file_path).await.map_err(ProtocolError::Io)?;
+    file.seek(SeekFrom::Start(range.start))
+        .await
+        .map_err(ProtocolError::Io)?;
+    let mut remaining = range.end - range.start;
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    while remaining > 0 {
+        let want = remaining.min(BUFFER_SIZE as u64) as usize;
+        let bytes_read = file
+            .read(&mut buffer[..want])
+            .await
+            .map_err(ProtocolError::Io)?;
+        if bytes_read == 0 {
+            return Err(ProtocolError::Io(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "File shrank during transfer",
+            )));
+        }
+        if let Some(limit) = bandwidth {
+            limit.acquire(bytes_read).await;
+        }
+        timeout(TRANSFER_TIMEOUT, stream.write_all(&buffer[..bytes_read]))
+            .await
+            .map_err(|_| ProtocolError::Timeout("Stream write timeout".to_string()))?
+            .map_err(ProtocolError::Io)?;
+
+        remaining -= bytes_read as u64;
+        if let Some(counter) = traffic_counter {
+            counter.record_payload_sent(bytes_read as u64);
+        }
+        record_stream_progress(sent, bytes_read, file_size, progress)?;
+    }
+    stream.flush().await.map_err(ProtocolError::Io)
+}
+
+/// Receive stream `index` of a multi-stream transfer into its range of `staged`
+#[allow(clippy::too_many_arguments)]
+async fn receive_stream_range<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    index: usize,
+    staged: &Path,
+    range: Range<u64>,
+    expected_size: u64,
+    traffic_counter: Option<&TrafficCounter>,
+    received: &AtomicU64,
+    progress: Option<&ProgressCallback>,
+) -> Result<()> {
+    stream
+        .write_u32(index as u32)
+        .await
+        .map_err(ProtocolError::Io)?;
+    stream.flush().await.map_err(ProtocolError::Io)?;
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(staged)
+        .await
+        .map_err(ProtocolError::Io)?;
+    file.seek(SeekFrom::Start(range.start))
+        .await
+        .map_err(ProtocolError::Io)?;
+    let mut remaining = range.end - range.start;
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    while remaining > 0 {
+        let want = remaining.min(BUFFER_SIZE as u64) as usize;
+        let bytes_read = timeout(TRANSFER_TIMEOUT, stream.read(&mut buffer[..want]))
+            .await
+            .map_err(|_| {
+                ProtocolError::Timeout("Stream read timeout during file transfer".to_string())
+            })?
+            .map_err(ProtocolError::Io)?;
+        if bytes_read == 0 {
+            return Err(ProtocolError::Io(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!(
+                    "Stream {} closed with {} bytes of its range missing",
+                    index, remaining
+                ),
+            )));
+        }
+        write_file_safe(&mut file, &buffer[..bytes_read]).await?;
+
+        remaining -= bytes_read as u64;
+        if let Some(counter) = traffic_counter {
+            counter.record_payload_received(bytes_read as u64);
+        }
+        record_stream_progress(received, bytes_read, expected_size, progress)?;
+    }
+    file.flush().await.map_err(ProtocolError::Io)
+}
+
+/// Receive every stream of a multi-stream transfer into the pre-allocated `staged` file
+async fn receive_streams<S: AsyncRead + AsyncWrite + Unpin>(
+    mut legs: Vec<S>,
+    staged: &Path,
+    expected_size: u64,
+    layout: StreamLayout,
+    traffic_counter: Option<&TrafficCounter>,
+    progress: Option<&ProgressCallback>,
+) -> Result<()> {
+    let received = AtomicU64::new(0);
+    futures::future::try_join_all(legs.iter_mut().enumerate().map(|(index, stream)| {
+        receive_stream_range(
+            stream,
+            index,
+            staged,
+            layout.range(index, expected_size),
+            expected_size,
+            traffic_counter,
+            &received,
+            progress,
+        )
+    }))
+    .await?;
+    Ok(())
+}
+
+/// Create `staged` at its full size so every stream can write its range
+async fn preallocate_staged(staged: &Path, expected_size: u64) -> Result<()> {
+    let (file, _) = open_staged(staged, None, expected_size).await?;
+    file.set_len(expected_size).await.map_err(ProtocolError::Io)
+}
+
+/// Whether a resumable transfer that failed with `error` should keep its
+/// staging file for the next attempt
+///
+/// Connection trouble keeps it; a cancel, a refused file or bad content does not.
+fn keep_for_resume(resume: Option<u64>, error: &ProtocolError) -> bool {
+    let cancelled =
+        matches!(error, ProtocolError::Io(e) if e.kind() == std::io::ErrorKind::Interrupted);
+    resume.is_some() && error.is_recoverable() && !cancelled
+}
+
+/// Byte offset an interrupted transfer to `save_path` can continue from
+///
+/// This is the size of the staging file left by an earlier attempt (see
+/// [`staging_path`]), or 0 if there is none or it is larger than
+/// `expected_size`, meaning it belongs to a different file. Pass the result
+/// to [`PayloadClient::resume_from`].
+pub async fn resume_offset(
+    save_path: impl AsRef<Path>,
+    staging_dir: Option<&Path>,
+    expected_size: u64,
+) -> u64 {
+    let staged = staging_path(save_path, staging_dir);
+    match tokio::fs::metadata(&staged).await {
+        Ok(metadata) if metadata.is_file() && metadata.len() <= expected_size => metadata.len(),
+        _ => 0,
+    }
+}
+
+/// TCP server for sending file payloads
+///
+/// Listens on an available port and accepts a single connection
+/// to transfer file data.
+pub struct PayloadServer {
+    listener: TcpListener,
+    port: u16,
+    progress_callback: Option<ProgressCallback>,
+    traffic_counter: Option<TrafficCounter>,
+    confirmation_timeout: Option<Duration>,
+    chunk_probe: ChunkProbe,
+    encryption: Option<PayloadCipher>,
+    resumable: bool,
+    bandwidth: Option<BandwidthLimit>,
+    streams: usize,
+}
+
+impl PayloadServer {
+    /// Create a new payload server on an available port
+    ///
+    /// Binds to 0.0.0.0 in the CConnect port range (1739-1764).
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no ports are available in the range.
+    pub async fn new() -> Result<Self> {
+        // Try to bind to a port in the CConnect range
+        for port in PORT_RANGE_START..=PORT_RANGE_END {
+            let addr = format!("0.0.0.0:{}", port);
             if let Ok(listener) = TcpListener::bind(&addr).await {
-                info!("TLS Payload server listening on port {}", port);
+                info!("Payload server listening on port {}", port);
                 return Ok(Self {
                     listener,
                     port,
-                    tls_config,
                     progress_callback: None,
+                    traffic_counter: None,
+                    confirmation_timeout: None,
+                    chunk_probe: ChunkProbe::default(),
+                    encryption: None,
+                    resumable: false,
+                    bandwidth: None,
+                    streams: 1,
                 });
             }
         }
@@ -886,234 +1676,2505 @@ impl TlsPayloadServer {
         Err(ProtocolError::Io(std::io::Error::new(
             std::io::ErrorKind::AddrInUse,
             format!(
-                "Failed to bind TLS payload server - all ports in range {}-{} are in use",
+                "Failed to bind payload server - all ports in range {}-{} are in use",
+                PORT_RANGE_START, PORT_RANGE_END
+            ),
+        )))
+    }
+
+    /// Create a new payload server using blocking I/O
+    ///
+    /// This variant uses std::net::TcpListener internally and converts to tokio,
+    /// making it safe to call from contexts without an active tokio reactor
+    /// (e.g., zbus DBus handlers).
+    ///
+    /// Binds to 0.0.0.0 in the CConnect port range (1739-1764).
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no ports are available in the range.
+    pub fn new_blocking() -> Result<Self> {
+        // Try to bind to a port in the CConnect range using std::net
+        for port in PORT_RANGE_START..=PORT_RANGE_END {
+            let addr = format!("0.0.0.0:{}", port);
+            if let Ok(std_listener) = std::net::TcpListener::bind(&addr) {
+                // Set non-blocking for tokio compatibility
+                std_listener
+                    .set_nonblocking(true)
+                    .map_err(ProtocolError::Io)?;
+                // Convert to tokio TcpListener
+                let listener = TcpListener::from_std(std_listener).map_err(ProtocolError::Io)?;
+                info!("Payload server listening on port {} (blocking init)", port);
+                return Ok(Self {
+                    listener,
+                    port,
+                    progress_callback: None,
+                    traffic_counter: None,
+                    confirmation_timeout: None,
+                    chunk_probe: ChunkProbe::default(),
+                    encryption: None,
+                    resumable: false,
+                    bandwidth: None,
+                    streams: 1,
+                });
+            }
+        }
+
+        Err(ProtocolError::Io(std::io::Error::new(
+            std::io::ErrorKind::AddrInUse,
+            format!(
+                "Failed to bind payload server - all ports in range {}-{} are in use",
                 PORT_RANGE_START, PORT_RANGE_END
             ),
         )))
     }
 
-    /// Get the port the server is listening on
-    pub fn port(&self) -> u16 {
-        self.port
+    /// Set a progress callback for transfer updates
+    ///
+    /// The callback receives (bytes_transferred, total_bytes) and returns
+    /// `true` to continue or `false` to cancel the transfer.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let server = PayloadServer::new().await?;
+    /// server.with_progress(Box::new(|transferred, total| {
+    ///     println!("Progress: {}/{} bytes", transferred, total);
+    ///     true // continue transfer
+    /// }));
+    /// ```
+    pub fn with_progress(mut self, callback: ProgressCallback) -> Self {
+        self.progress_callback = Some(callback);
+        self
+    }
+
+    /// Count payload bytes in a device's traffic totals
+    ///
+    /// See [`ConnectionManager::traffic_counter`](crate::ConnectionManager::traffic_counter).
+    pub fn with_traffic_counter(mut self, counter: TrafficCounter) -> Self {
+        self.traffic_counter = Some(counter);
+        self
+    }
+
+    /// Wait for the receiver to accept before sending any data
+    ///
+    /// The receiver must answer with `accept` or `decline` within `timeout`;
+    /// otherwise the transfer fails with [`ProtocolError::PeerRejected`].
+    pub fn require_confirmation(mut self, timeout: Duration) -> Self {
+        self.confirmation_timeout = Some(timeout);
+        self
+    }
+
+    /// Choose the size of each write with `probe`
+    ///
+    /// Without a probe every write is 64 KB. See
+    /// [`ChunkSizePolicy`](crate::chunk_sizing::ChunkSizePolicy) for picking
+    /// one per transport.
+    pub fn with_chunk_probe(mut self, probe: ChunkProbe) -> Self {
+        self.chunk_probe = probe;
+        self
+    }
+
+    /// Seal every chunk with `cipher` before it is written
+    ///
+    /// The receiver needs the matching cipher; see
+    /// [`payload_crypto`](crate::payload_crypto).
+    pub fn with_encryption(mut self, cipher: PayloadCipher) -> Self {
+        self.encryption = Some(cipher);
+        self
+    }
+
+    /// Let the receiver continue an interrupted transfer part-way
+    ///
+    /// Mark the share packet with [`RESUMABLE_FIELD`]; the receiver then names
+    /// the offset it already has and only the rest of the file is sent. Only
+    /// `send_file` honours this.
+    pub fn allow_resume(mut self) -> Self {
+        self.resumable = true;
+        self
+    }
+
+    /// Keep `send_file` under the byte rate of `limit`
+    ///
+    /// The limit is shared, so changing it through a clone takes effect on
+    /// the next write. See [`bandwidth`](crate::bandwidth).
+    pub fn with_bandwidth_limit(mut self, limit: BandwidthLimit) -> Self {
+        self.bandwidth = Some(limit);
+        self
+    }
+
+    /// Let `send_file` split the file over up to `streams` connections
+    ///
+    /// Advertise [`stream_layout`](Self::stream_layout) in the share packet;
+    /// the receiver then opens one connection per stream. Negotiate `streams`
+    /// with [`TransportCapabilities::negotiate_payload_streams`](crate::TransportCapabilities::negotiate_payload_streams)
+    /// first. Transfers that need confirmation, resumption or encryption
+    /// stay on a single stream.
+    pub fn with_parallel_streams(mut self, streams: usize) -> Self {
+        self.streams = streams;
+        self
+    }
+
+    /// How `send_file` splits a file of `file_size` bytes, if at all
+    pub fn stream_layout(&self, file_size: u64) -> Option<StreamLayout> {
+        let single =
+            self.confirmation_timeout.is_some() || self.resumable || self.encryption.is_some();
+        Some(StreamLayout::new(file_size, self.streams))
+            .filter(|layout| !single && layout.streams > 1)
+    }
+
+    /// Get the port this server is listening on
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Get the socket address this server is bound to
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr().map_err(ProtocolError::Io)
+    }
+
+    /// Accept the receiver's connection and wait for its confirmation
+    async fn accept_receiver(&self) -> Result<(TcpStream, SocketAddr)> {
+        let (mut stream, remote_addr) = timeout(CONNECTION_TIMEOUT, self.listener.accept())
+            .await
+            .map_err(|_| {
+                ProtocolError::Io(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "Connection timeout",
+                ))
+            })?
+            .map_err(ProtocolError::Io)?;
+
+        info!("Accepted connection from {} for file transfer", remote_addr);
+
+        if let Some(confirm_timeout) = self.confirmation_timeout {
+            await_confirmation(&mut stream, confirm_timeout).await?;
+        }
+        Ok((stream, remote_addr))
+    }
+
+    /// Accept a connection and send a file
+    ///
+    /// Waits for exactly one connection, then streams the file.
+    /// The connection is closed after the file is fully sent.
+    ///
+    /// # Parameters
+    ///
+    /// - `file_path`: Path to the file to send
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Connection times out
+    /// - File cannot be opened
+    /// - Transfer fails or is interrupted
+    /// - Transfer is cancelled via progress callback
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let server = PayloadServer::new().await?;
+    /// server.send_file("/path/to/document.pdf").await?;
+    /// ```
+    pub async fn send_file(self, file_path: impl AsRef<Path>) -> Result<()> {
+        let file_path = file_path.as_ref();
+        info!("Waiting for connection to send file: {:?}", file_path);
+
+        // Get file size for progress tracking
+        let file_size = tokio::fs::metadata(file_path)
+            .await
+            .map_err(ProtocolError::Io)?
+            .len();
+        if let Some(layout) = self.stream_layout(file_size) {
+            return self.send_file_streams(file_path, file_size, layout).await;
+        }
+
+        let (mut stream, remote_addr) = self.accept_receiver().await?;
+
+        // Open file
+        let mut file = File::open(file_path).await.map_err(ProtocolError::Io)?;
+        let mut total_bytes =
+            seek_to_resume_offset(&mut stream, &mut file, file_size, self.resumable).await?;
+
+        // Stream file data
+        let mut chunks = self.chunk_probe;
+        let mut cipher = self.encryption;
+        let mut buffer = vec![0u8; chunks.max_chunk_size()];
+
+        loop {
+            // Read from file
+            let chunk = &mut buffer[..chunks.chunk_size()];
+            let bytes_read = timeout(TRANSFER_TIMEOUT, file.read(chunk))
+                .await
+                .map_err(|_| {
+                    ProtocolError::Io(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        "File read timeout",
+                    ))
+                })?
+                .map_err(ProtocolError::Io)?;
+
+            if bytes_read == 0 {
+                break; // EOF
+            }
+
+            if let Some(ref limit) = self.bandwidth {
+                limit.acquire(bytes_read).await;
+            }
+
+            // Write to stream
+            let started = std::time::Instant::now();
+            timeout(
+                TRANSFER_TIMEOUT,
+                write_payload(&mut stream, cipher.as_mut(), &buffer[..bytes_read]),
+            )
+            .await
+            .map_err(|_| {
+                ProtocolError::Io(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "Stream write timeout",
+                ))
+            })??;
+            chunks.record(bytes_read, started.elapsed());
+
+            total_bytes += bytes_read as u64;
+            if let Some(ref counter) = self.traffic_counter {
+                counter.record_payload_sent(bytes_read as u64);
+            }
+
+            debug!(
+                "Transferred {} bytes ({}/{} total)",
+                bytes_read, total_bytes, file_size
+            );
+
+            // Call progress callback if set
+            if let Some(ref callback) = self.progress_callback {
+                if !callback(total_bytes, file_size) {
+                    info!("Transfer cancelled by progress callback");
+                    return Err(ProtocolError::Io(std::io::Error::new(
+                        std::io::ErrorKind::Interrupted,
+                        "Transfer cancelled",
+                    )));
+                }
+            }
+        }
+
+        // Flush stream
+        stream.flush().await.map_err(ProtocolError::Io)?;
+
+        info!(
+            "File transfer complete: {} bytes sent to {}",
+            total_bytes, remote_addr
+        );
+
+        Ok(())
+    }
+
+    /// Accept one connection per stream and send each its range concurrently
+    async fn send_file_streams(
+        self,
+        file_path: &Path,
+        file_size: u64,
+        layout: StreamLayout,
+    ) -> Result<()> {
+        let mut legs = Vec::with_capacity(layout.streams);
+        for _ in 0..layout.streams {
+            legs.push(self.accept_receiver().await?.0);
+        }
+
+        let sent = AtomicU64::new(0);
+        futures::future::try_join_all(legs.iter_mut().map(|stream| {
+            send_stream_range(
+                stream,
+                file_path,
+                file_size,
+                layout,
+                self.bandwidth.as_ref(),
+                self.traffic_counter.as_ref(),
+                &sent,
+                self.progress_callback.as_ref(),
+            )
+        }))
+        .await?;
+
+        info!(
+            "File transfer complete: {} bytes sent over {} streams",
+            file_size, layout.streams
+        );
+        Ok(())
+    }
+
+    /// Accept a connection and send `data` from memory
+    ///
+    /// For small payloads that never touch the disk, such as clipboard
+    /// content. Progress callbacks and chunk probing are not used.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the connection times out or the transfer fails.
+    pub async fn send_bytes(self, data: &[u8]) -> Result<()> {
+        let (mut stream, remote_addr) = self.accept_receiver().await?;
+        send_buffer(
+            &mut stream,
+            self.encryption,
+            self.traffic_counter.as_ref(),
+            data,
+        )
+        .await?;
+        info!(
+            "Payload transfer complete: {} bytes sent to {}",
+            data.len(),
+            remote_addr
+        );
+        Ok(())
+    }
+
+    /// Send one file to several devices at once
+    ///
+    /// `servers` pairs each device ID with the server it will connect to.
+    /// All receivers are accepted concurrently, then the file is read once and
+    /// every chunk is written to all of them. Each server's progress callback,
+    /// traffic counter and encryption apply to its own device; chunk probing
+    /// and bandwidth limits do not, as all devices share one fixed chunk size.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error only if the file cannot be read. Devices that fail to
+    /// connect or drop out are listed in [`FanOutSummary::failed`].
+    pub async fn send_file_multi(
+        file_path: impl AsRef<Path>,
+        servers: Vec<(String, PayloadServer)>,
+    ) -> Result<FanOutSummary> {
+        let accepted =
+            futures::future::join_all(servers.into_iter().map(|(device_id, server)| async move {
+                let accepted = server.accept_receiver().await;
+                (device_id, server, accepted)
+            }))
+            .await;
+
+        let mut legs = Vec::with_capacity(accepted.len());
+        let mut summary = FanOutSummary::default();
+        for (device_id, server, accepted) in accepted {
+            match accepted {
+                Ok((stream, _)) => legs.push(FanOutLeg {
+                    device_id,
+                    stream,
+                    cipher: server.encryption,
+                    traffic_counter: server.traffic_counter,
+                    progress_callback: server.progress_callback,
+                }),
+                Err(e) => summary.failed.push((device_id, e)),
+            }
+        }
+        fan_out(file_path.as_ref(), legs, summary).await
+    }
+}
+
+/// Opens another connection to the payload server, for multi-stream transfers
+type LegConnector<S> =
+    Box<dyn Fn(SocketAddr) -> Pin<Box<dyn Future<Output = Result<S>> + Send>> + Send + Sync>;
+
+/// Client for receiving file payloads over a connected stream
+///
+/// Everything but connecting is shared between the transports; use
+/// [`PayloadClient`] for plain TCP and [`TlsPayloadClient`] for TLS.
+pub struct PayloadReceiver<S> {
+    stream: S,
+    addr: SocketAddr,
+    connect_leg: LegConnector<S>,
+    streams: Option<StreamLayout>,
+    progress_callback: Option<ProgressCallback>,
+    traffic_counter: Option<TrafficCounter>,
+    size_limit: Option<u64>,
+    sandbox: Option<TransferSandbox>,
+    checksums: Option<ChunkChecksums>,
+    staging_dir: Option<PathBuf>,
+    encryption: Option<PayloadCipher>,
+    resume: Option<u64>,
+    file_checksum: Option<String>,
+    keep_staged: bool,
+}
+
+/// TCP client for receiving file payloads
+///
+/// Connects to a remote payload server and downloads file data.
+pub type PayloadClient = PayloadReceiver<TcpStream>;
+
+/// TLS-enabled TCP client for receiving file payloads
+///
+/// Connects to a remote payload server with TLS encryption.
+/// Uses KDE Connect's inverted TLS roles: TCP initiator acts as TLS SERVER.
+///
+/// ## Security
+///
+/// - Uses TLS 1.2+ with mutual certificate authentication
+/// - Trust-On-First-Use (TOFU) model - certificates are verified at application layer
+/// - Same certificate used for main connection and payload transfers
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// use cosmic_ext_connect_core::payload::TlsPayloadClient;
+///
+/// // Get TLS config (same as main connection)
+/// let tls_config = TlsConfig::new(&certificate)?;
+///
+/// // Connect to payload server with TLS
+/// let client = TlsPayloadClient::new("192.168.1.100", 1739, &tls_config).await?;
+/// client.receive_file("/tmp/received_file.pdf", 1048576).await?;
+/// ```
+pub type TlsPayloadClient = PayloadReceiver<tokio_rustls::server::TlsStream<TcpStream>>;
+
+/// Resolve the address of a remote payload server
+fn resolve_payload_addr(host: &str, port: u16) -> Result<SocketAddr> {
+    // Try to parse as IP address first, otherwise do DNS resolution
+    if let Some(addr) = crate::transport::tcp::parse_host_addr(host, port) {
+        return Ok(addr);
+    }
+    // Fall back to DNS resolution with "host:port" format
+    let addr_str = format!("{}:{}", host, port);
+    let addrs: Vec<SocketAddr> = addr_str
+        .to_socket_addrs()
+        .map_err(ProtocolError::Io)?
+        .collect();
+    addrs.first().copied().ok_or_else(|| {
+        ProtocolError::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "No addresses resolved for host",
+        ))
+    })
+}
+
+impl PayloadClient {
+    /// Connect to a remote payload server
+    ///
+    /// # Parameters
+    ///
+    /// - `host`: Remote host IP address or hostname
+    /// - `port`: Remote port number
+    ///
+    /// # Errors
+    ///
+    /// Returns error if connection fails or times out.
+    pub async fn new(host: &str, port: u16) -> Result<Self> {
+        let addr = resolve_payload_addr(host, port)?;
+
+        info!("Connecting to payload server at {}", addr);
+
+        let stream = connect_payload(addr).await?;
+
+        info!("Connected to payload server at {}", addr);
+
+        Ok(PayloadReceiver::connected(
+            stream,
+            addr,
+            Box::new(|addr| Box::pin(connect_payload(addr))),
+        ))
+    }
+}
+
+impl TlsPayloadClient {
+    /// Connect to a remote payload server with TLS
+    ///
+    /// Establishes a TLS connection using KDE Connect's inverted roles:
+    /// - TCP connection initiated by us
+    /// - TLS handshake performed as SERVER (inverted role!)
+    ///
+    /// # Parameters
+    ///
+    /// - `host`: Remote host IP address or hostname
+    /// - `port`: Remote port number
+    /// - `tls_config`: TLS configuration with our certificate
+    ///
+    /// # Errors
+    ///
+    /// Returns error if connection fails, times out, or TLS handshake fails.
+    pub async fn new(host: &str, port: u16, tls_config: &TlsConfig) -> Result<Self> {
+        let addr = resolve_payload_addr(host, port)?;
+
+        info!("Connecting to payload server at {} with TLS", addr);
+
+        // KDE Connect quirk: TCP initiator acts as TLS SERVER
+        // Create TLS acceptor with SERVER config (inverted role!)
+        let acceptor = TlsAcceptor::from(tls_config.server_config());
+        let tls_stream = connect_tls_payload(addr, &acceptor).await?;
+
+        info!(
+            "TLS connection established to payload server at {} (as TLS SERVER)",
+            addr
+        );
+
+        // Further streams of a multi-stream transfer are TLS-wrapped as well
+        Ok(PayloadReceiver::connected(
+            tls_stream,
+            addr,
+            Box::new(move |addr| {
+                let acceptor = acceptor.clone();
+                Box::pin(async move { connect_tls_payload(addr, &acceptor).await })
+            }),
+        ))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> PayloadReceiver<S> {
+    /// Wrap a stream connected to the payload server at `addr`
+    fn connected(stream: S, addr: SocketAddr, connect_leg: LegConnector<S>) -> Self {
+        Self {
+            stream,
+            addr,
+            connect_leg,
+            streams: None,
+            progress_callback: None,
+            traffic_counter: None,
+            size_limit: None,
+            sandbox: None,
+            checksums: None,
+            staging_dir: None,
+            encryption: None,
+            resume: None,
+            file_checksum: None,
+            keep_staged: false,
+        }
+    }
+
+    /// Set a progress callback for transfer updates
+    ///
+    /// The callback receives (bytes_transferred, total_bytes) and returns
+    /// `true` to continue or `false` to cancel the transfer.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let client = PayloadClient::new("192.168.1.100", 1739).await?;
+    /// client.with_progress(Box::new(|transferred, total| {
+    ///     println!("Progress: {}/{} bytes", transferred, total);
+    ///     true // continue transfer
+    /// }));
+    /// ```
+    pub fn with_progress(mut self, callback: ProgressCallback) -> Self {
+        self.progress_callback = Some(callback);
+        self
+    }
+
+    /// Count payload bytes in a device's traffic totals
+    ///
+    /// See [`ConnectionManager::traffic_counter`](crate::ConnectionManager::traffic_counter).
+    pub fn with_traffic_counter(mut self, counter: TrafficCounter) -> Self {
+        self.traffic_counter = Some(counter);
+        self
+    }
+
+    /// Refuse files larger than `limit` bytes (`None` = unlimited)
+    ///
+    /// See [`FileSizeLimits::limit_for`].
+    pub fn with_size_limit(mut self, limit: Option<u64>) -> Self {
+        self.size_limit = limit;
+        self
+    }
+
+    /// Only write the file inside `sandbox`
+    ///
+    /// The save path is resolved against the sandbox before anything is
+    /// created; a path that escapes it fails with
+    /// [`ProtocolError::PermissionDenied`].
+    pub fn with_sandbox(mut self, sandbox: TransferSandbox) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Verify the received file against `checksums` before it is moved to
+    /// its final name
+    pub fn with_checksums(mut self, checksums: ChunkChecksums) -> Self {
+        self.checksums = Some(checksums);
+        self
+    }
+
+    /// Keep the file in `dir` while it is being received
+    ///
+    /// By default it is staged next to the save path.
+    pub fn with_staging_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.staging_dir = Some(dir.into());
+        self
+    }
+
+    /// Leave the received file under its staging name
+    ///
+    /// For callers that check or repair the file before it gets its final
+    /// name: it stays at [`staging_path`] of the save path until they move it
+    /// with [`commit_staged_file`].
+    pub fn keep_staged(mut self) -> Self {
+        self.keep_staged = true;
+        self
+    }
+
+    /// Open the sealed chunks of an end-to-end encrypted payload
+    ///
+    /// See [`PayloadCipher::from_packet`].
+    pub fn with_encryption(mut self, cipher: PayloadCipher) -> Self {
+        self.encryption = Some(cipher);
+        self
+    }
+
+    /// Continue an interrupted transfer from byte `offset`
+    ///
+    /// Only for senders that marked the share packet with
+    /// [`RESUMABLE_FIELD`]. Up to `offset` bytes of the staging file left by
+    /// the earlier attempt are kept and the sender is asked for the rest; an
+    /// offset of 0 starts afresh. If this attempt is interrupted as well, the
+    /// staging file stays for the next one. See [`resume_offset`].
+    pub fn resume_from(mut self, offset: u64) -> Self {
+        self.resume = Some(offset);
+        self
+    }
+
+    /// Check the whole file against the SHA-256 the sender announced
+    ///
+    /// `sha256` is the hex digest from the share packet (see
+    /// [`CHECKSUM_FIELD`]). Bytes are hashed as they arrive; a file whose
+    /// digest differs is discarded with [`ProtocolError::ChecksumMismatch`].
+    pub fn with_file_checksum(mut self, sha256: impl Into<String>) -> Self {
+        self.file_checksum = Some(sha256.into());
+        self
+    }
+
+    /// Receive over the parallel streams the sender advertised
+    ///
+    /// `layout` comes from the share packet (see [`StreamLayout::from_packet`]).
+    /// One more connection is opened per extra stream and each writes its
+    /// range of the pre-allocated file. Ignored when resuming or decrypting.
+    pub fn with_parallel_streams(mut self, layout: StreamLayout) -> Self {
+        self.streams = Some(layout);
+        self
+    }
+
+    /// Accept a staged transfer and receive the file
+    ///
+    /// Use when the sender required confirmation (the share packet carries
+    /// [`CONFIRM_REQUIRED_FIELD`]). Otherwise behaves like `receive_file`.
+    /// A file over the size limit or a save path outside the sandbox is
+    /// declined instead.
+    pub async fn accept(mut self, save_path: impl AsRef<Path>, expected_size: u64) -> Result<()> {
+        let checked = check_size_limit(self.size_limit, expected_size)
+            .and_then(|()| resolve_save_path(self.sandbox.as_ref(), save_path.as_ref()));
+        if let Err(e) = checked {
+            warn!("Declining staged transfer: {}", e);
+            send_confirmation(&mut self.stream, false).await?;
+            return Err(e);
+        }
+        send_confirmation(&mut self.stream, true).await?;
+        self.receive_file(save_path, expected_size).await
+    }
+
+    /// Decline a staged transfer without receiving any data
+    pub async fn decline(mut self) -> Result<()> {
+        send_confirmation(&mut self.stream, false).await
+    }
+
+    /// Accept or decline a staged transfer as `gate` decides for `device_id`
+    ///
+    /// Waits for the user unless the device is trusted or rejected on its
+    /// own (see [`TransferGate::decide_staged`]). Returns whether the file
+    /// was received.
+    pub async fn accept_if_trusted(
+        self,
+        gate: &TransferGate,
+        device_id: &str,
+        save_path: impl AsRef<Path>,
+        expected_size: u64,
+    ) -> Result<bool> {
+        let filename = prompt_filename(save_path.as_ref());
+        if gate
+            .decide_staged(device_id, &filename, expected_size)
+            .await
+        {
+            self.accept(save_path, expected_size).await?;
+            Ok(true)
+        } else {
+            self.decline().await?;
+            Ok(false)
+        }
+    }
+
+    /// Receive `expected_size` bytes into memory
+    ///
+    /// For small payloads that never touch the disk, such as clipboard
+    /// content. The size limit applies; the sandbox and staging do not.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the payload exceeds the size limit, the transfer
+    /// fails or times out, or fewer bytes than expected arrive.
+    pub async fn receive_bytes(mut self, expected_size: u64) -> Result<Vec<u8>> {
+        if let Err(e) = check_size_limit(self.size_limit, expected_size) {
+            warn!("Refusing in-memory transfer: {}", e);
+            let _ = self.stream.shutdown().await;
+            return Err(e);
+        }
+        receive_buffer(
+            &mut self.stream,
+            self.encryption.take(),
+            self.traffic_counter.as_ref(),
+            expected_size,
+        )
+        .await
+    }
+
+    /// Receive a file from the connected server
+    ///
+    /// Downloads the specified number of bytes and saves to a file.
+    ///
+    /// # Parameters
+    ///
+    /// - `save_path`: Path where the file should be saved
+    /// - `expected_size`: Expected file size in bytes
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - File cannot be created
+    /// - Transfer fails or times out
+    /// - Size mismatch (received != expected)
+    /// - Transfer is cancelled via progress callback
+    /// - File exceeds the size limit (nothing is written)
+    /// - Save path is outside the sandbox (nothing is written)
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let client = PayloadClient::new("192.168.1.100", 1739).await?;
+    /// client.receive_file("/tmp/received_file.pdf", 1048576).await?;
+    /// ```
+    pub async fn receive_file(
+        mut self,
+        save_path: impl AsRef<Path>,
+        expected_size: u64,
+    ) -> Result<()> {
+        let save_path = save_path.as_ref();
+        info!(
+            "Receiving file to {:?} ({} bytes expected)",
+            save_path, expected_size
+        );
+
+        if let Err(e) = check_size_limit(self.size_limit, expected_size) {
+            warn!("Refusing transfer to {:?}: {}", save_path, e);
+            let _ = self.stream.shutdown().await;
+            return Err(e);
+        }
+
+        let save_path = match resolve_save_path(self.sandbox.as_ref(), save_path) {
+            Ok(path) => path,
+            Err(e) => {
+                let _ = self.stream.shutdown().await;
+                return Err(e);
+            }
+        };
+        let save_path = save_path.as_path();
+        let staged = staging_path(save_path, self.staging_dir.as_deref());
+        if let Some(layout) = self
+            .streams
+            .filter(|_| self.resume.is_none() && self.encryption.is_none())
+        {
+            return self
+                .receive_file_streams(save_path, &staged, expected_size, layout)
+                .await;
+        }
+
+        // Receive under the staging name; the final name appears only when complete
+        let (file, mut total_bytes) = match open_staged(&staged, self.resume, expected_size).await {
+            Ok(opened) => opened,
+            Err(e) => {
+                warn!("Failed to create file {:?}: {}", staged, e);
+                return Err(e);
+            }
+        };
+        let mut assembler = ChunkAssembler::resume(file, expected_size, total_bytes);
+
+        // Read and write data
+        let mut buffer = vec![0u8; BUFFER_SIZE];
+
+        let result = async {
+            let mut digest = match &self.file_checksum {
+                Some(expected) => Some(FileDigest::start(expected, &staged, total_bytes).await?),
+                None => None,
+            };
+            if self.resume.is_some() {
+                send_resume_offset(&mut self.stream, total_bytes).await?;
+            }
+
+            while total_bytes < expected_size {
+                let remaining = expected_size - total_bytes;
+
+                // Read from stream
+                let bytes_read = timeout(
+                    TRANSFER_TIMEOUT,
+                    read_payload(
+                        &mut self.stream,
+                        self.encryption.as_mut(),
+                        &mut buffer,
+                        remaining,
+                    ),
+                )
+                .await
+                .map_err(|_| {
+                    ProtocolError::Timeout("Stream read timeout during file transfer".to_string())
+                })??;
+
+                if bytes_read == 0 {
+                    return Err(ProtocolError::Io(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        format!(
+                            "Connection closed prematurely: received {} bytes, expected {}",
+                            total_bytes, expected_size
+                        ),
+                    )));
+                }
+
+                // Write at the stream offset; reassembly checks contiguity
+                assembler
+                    .write_chunk(total_bytes, &buffer[..bytes_read])
+                    .await?;
+                if let Some(digest) = digest.as_mut() {
+                    digest.update(&buffer[..bytes_read]);
+                }
+
+                total_bytes += bytes_read as u64;
+                if let Some(ref counter) = self.traffic_counter {
+                    counter.record_payload_received(bytes_read as u64);
+                }
+
+                debug!(
+                    "Received {} bytes ({}/{} total)",
+                    bytes_read, total_bytes, expected_size
+                );
+
+                // Call progress callback if set
+                if let Some(ref callback) = self.progress_callback {
+                    if !callback(total_bytes, expected_size) {
+                        info!("Transfer cancelled by progress callback");
+                        return Err(ProtocolError::Io(std::io::Error::new(
+                            std::io::ErrorKind::Interrupted,
+                            "Transfer cancelled",
+                        )));
+                    }
+                }
+            }
+
+            // Verify every byte arrived, then flush and move into place
+            drop(assembler.finish().await?);
+            if let Some(digest) = digest {
+                digest.verify()?;
+            }
+            if !self.keep_staged {
+                finalize_staged(&staged, save_path, self.checksums.as_ref()).await?;
+            }
+
+            info!(
+                "File transfer complete: {} bytes received to {:?}",
+                total_bytes, save_path
+            );
+
+            Ok(())
+        }
+        .await;
+
+        // Clean up partial file on error, unless the next attempt can resume from it
+        match &result {
+            Err(e) if keep_for_resume(self.resume, e) => {
+                warn!(
+                    "Transfer interrupted, keeping {:?} to resume at byte {}",
+                    staged, total_bytes
+                );
+            }
+            Err(_) => {
+                warn!("Transfer failed, cleaning up partial file: {:?}", staged);
+                cleanup_partial_file(&staged).await;
+            }
+            Ok(()) => {}
+        }
+
+        result
+    }
+
+    /// Receive the ranges of a multi-stream transfer in parallel
+    ///
+    /// The connection made when the client was created carries the first
+    /// stream; one more is opened for each of the others.
+    async fn receive_file_streams(
+        self,
+        save_path: &Path,
+        staged: &Path,
+        expected_size: u64,
+        layout: StreamLayout,
+    ) -> Result<()> {
+        let Self {
+            stream,
+            addr,
+            connect_leg,
+            progress_callback,
+            traffic_counter,
+            checksums,
+            file_checksum,
+            keep_staged,
+            ..
+        } = self;
+
+        let result = async {
+            layout.check_covers(expected_size)?;
+            preallocate_staged(staged, expected_size).await?;
+            let mut legs = vec![stream];
+            for _ in 1..layout.streams {
+                legs.push(connect_leg(addr).await?);
+            }
+            receive_streams(
+                legs,
+                staged,
+                expected_size,
+                layout,
+                traffic_counter.as_ref(),
+                progress_callback.as_ref(),
+            )
+            .await?;
+
+            if let Some(expected) = &file_checksum {
+                FileDigest::start(expected, staged, expected_size)
+                    .await?
+                    .verify()?;
+            }
+            if !keep_staged {
+                finalize_staged(staged, save_path, checksums.as_ref()).await?;
+            }
+
+            info!(
+                "File transfer complete: {} bytes received over {} streams to {:?}",
+                expected_size, layout.streams, save_path
+            );
+            Ok(())
+        }
+        .await;
+
+        if result.is_err() {
+            warn!("Transfer failed, cleaning up partial file: {:?}", staged);
+            cleanup_partial_file(staged).await;
+        }
+        result
+    }
+}
+
+/// TLS-enabled TCP server for sending file payloads
+///
+/// Listens on an available port and accepts a single connection with TLS encryption.
+/// Uses KDE Connect's inverted TLS roles: TCP acceptor acts as TLS CLIENT.
+///
+/// ## Security
+///
+/// - Uses TLS 1.2+ with mutual certificate authentication
+/// - Trust-On-First-Use (TOFU) model - certificates are verified at application layer
+/// - Same certificate used for main connection and payload transfers
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// use cosmic_ext_connect_protocol::payload::TlsPayloadServer;
+///
+/// // Create TLS payload server
+/// let server = TlsPayloadServer::new(&tls_config).await?;
+/// let port = server.port();
+///
+/// // Send share packet with port info
+/// // ... then accept connection and transfer file ...
+/// server.send_file("/path/to/file.pdf").await?;
+/// ```
+pub struct TlsPayloadServer {
+    listener: TcpListener,
+    port: u16,
+    tls_config: std::sync::Arc<TlsConfig>,
+    progress_callback: Option<ProgressCallback>,
+    traffic_counter: Option<TrafficCounter>,
+    confirmation_timeout: Option<Duration>,
+    chunk_probe: ChunkProbe,
+    encryption: Option<PayloadCipher>,
+    resumable: bool,
+    bandwidth: Option<BandwidthLimit>,
+    streams: usize,
+}
+
+impl TlsPayloadServer {
+    /// Create a new TLS payload server on an available port
+    ///
+    /// Binds to 0.0.0.0 in the CConnect port range (1739-1764).
+    ///
+    /// # Parameters
+    ///
+    /// - `tls_config`: TLS configuration with our certificate
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no ports are available in the range.
+    pub async fn new(tls_config: std::sync::Arc<TlsConfig>) -> Result<Self> {
+        // Try to bind to a port in the CConnect range
+        for port in PORT_RANGE_START..=PORT_RANGE_END {
+            let addr = format!("0.0.0.0:{}", port);
+            if let Ok(listener) = TcpListener::bind(&addr).await {
+                info!("TLS Payload server listening on port {}", port);
+                return Ok(Self {
+                    listener,
+                    port,
+                    tls_config,
+                    progress_callback: None,
+                    traffic_counter: None,
+                    confirmation_timeout: None,
+                    chunk_probe: ChunkProbe::default(),
+                    encryption: None,
+                    resumable: false,
+                    bandwidth: None,
+                    streams: 1,
+                });
+            }
+        }
+
+        Err(ProtocolError::Io(std::io::Error::new(
+            std::io::ErrorKind::AddrInUse,
+            format!(
+                "Failed to bind TLS payload server - all ports in range {}-{} are in use",
+                PORT_RANGE_START, PORT_RANGE_END
+            ),
+        )))
+    }
+
+    /// Get the port the server is listening on
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Set a progress callback for transfer updates
+    ///
+    /// The callback receives (bytes_transferred, total_bytes) and returns
+    /// `true` to continue or `false` to cancel the transfer.
+    pub fn with_progress(mut self, callback: ProgressCallback) -> Self {
+        self.progress_callback = Some(callback);
+        self
+    }
+
+    /// Count payload bytes in a device's traffic totals
+    ///
+    /// See [`ConnectionManager::traffic_counter`](crate::ConnectionManager::traffic_counter).
+    pub fn with_traffic_counter(mut self, counter: TrafficCounter) -> Self {
+        self.traffic_counter = Some(counter);
+        self
+    }
+
+    /// Wait for the receiver to accept before sending any data
+    ///
+    /// The receiver must answer with `accept` or `decline` within `timeout`;
+    /// otherwise the transfer fails with [`ProtocolError::PeerRejected`].
+    pub fn require_confirmation(mut self, timeout: Duration) -> Self {
+        self.confirmation_timeout = Some(timeout);
+        self
+    }
+
+    /// Choose the size of each write with `probe`
+    ///
+    /// Without a probe every write is 64 KB. See
+    /// [`ChunkSizePolicy`](crate::chunk_sizing::ChunkSizePolicy) for picking
+    /// one per transport.
+    pub fn with_chunk_probe(mut self, probe: ChunkProbe) -> Self {
+        self.chunk_probe = probe;
+        self
+    }
+
+    /// Seal every chunk with `cipher` before it is written
+    ///
+    /// The receiver needs the matching cipher; see
+    /// [`payload_crypto`](crate::payload_crypto).
+    pub fn with_encryption(mut self, cipher: PayloadCipher) -> Self {
+        self.encryption = Some(cipher);
+        self
+    }
+
+    /// Let the receiver continue an interrupted transfer part-way
+    ///
+    /// Mark the share packet with [`RESUMABLE_FIELD`]; the receiver then names
+    /// the offset it already has and only the rest of the file is sent. Only
+    /// `send_file` honours this.
+    pub fn allow_resume(mut self) -> Self {
+        self.resumable = true;
+        self
+    }
+
+    /// Keep `send_file` under the byte rate of `limit`
+    ///
+    /// The limit is shared, so changing it through a clone takes effect on
+    /// the next write. See [`bandwidth`](crate::bandwidth).
+    pub fn with_bandwidth_limit(mut self, limit: BandwidthLimit) -> Self {
+        self.bandwidth = Some(limit);
+        self
+    }
+
+    /// Let `send_file` split the file over up to `streams` connections
+    ///
+    /// Advertise [`stream_layout`](Self::stream_layout) in the share packet;
+    /// the receiver then opens one connection per stream. Negotiate `streams`
+    /// with [`TransportCapabilities::negotiate_payload_streams`](crate::TransportCapabilities::negotiate_payload_streams)
+    /// first. Transfers that need confirmation, resumption or encryption
+    /// stay on a single stream.
+    pub fn with_parallel_streams(mut self, streams: usize) -> Self {
+        self.streams = streams;
+        self
+    }
+
+    /// How `send_file` splits a file of `file_size` bytes, if at all
+    pub fn stream_layout(&self, file_size: u64) -> Option<StreamLayout> {
+        let single =
+            self.confirmation_timeout.is_some() || self.resumable || self.encryption.is_some();
+        Some(StreamLayout::new(file_size, self.streams))
+            .filter(|layout| !single && layout.streams > 1)
+    }
+
+    /// Accept the receiver's connection, set up TLS and wait for its confirmation
+    async fn accept_receiver(
+        &self,
+    ) -> Result<(tokio_rustls::client::TlsStream<TcpStream>, SocketAddr)> {
+        // Accept TCP connection
+        let (tcp_stream, peer_addr) = timeout(CONNECTION_TIMEOUT, self.listener.accept())
+            .await
+            .map_err(|_| {
+                ProtocolError::Io(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "No client connected within timeout",
+                ))
+            })?
+            .map_err(ProtocolError::Io)?;
+
+        info!(
+            "Accepted TCP connection from {} for TLS file transfer",
+            peer_addr
+        );
+
+        // KDE Connect quirk: TCP acceptor acts as TLS CLIENT
+        // Create TLS connector with CLIENT config (inverted role!)
+        let connector = TlsConnector::from(self.tls_config.client_config());
+
+        // Use a dummy server name since we're using TOFU
+        let server_name = rustls::pki_types::ServerName::try_from("kdeconnect").map_err(|e| {
+            ProtocolError::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Invalid server name: {}", e),
+            ))
+        })?;
+
+        // Perform TLS handshake as CLIENT (inverted role!)
+        let mut tls_stream = timeout(
+            CONNECTION_TIMEOUT,
+            connector.connect(server_name, tcp_stream),
+        )
+        .await
+        .map_err(|_| {
+            ProtocolError::Io(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "TLS handshake timeout",
+            ))
+        })?
+        .map_err(|e| {
+            error!("TLS handshake failed for payload transfer: {}", e);
+            ProtocolError::Io(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                format!("TLS handshake failed: {}", e),
+            ))
+        })?;
+
+        info!(
+            "TLS connection established with {} for file transfer (as TLS CLIENT)",
+            peer_addr
+        );
+
+        if let Some(confirm_timeout) = self.confirmation_timeout {
+            await_confirmation(&mut tls_stream, confirm_timeout).await?;
+        }
+        Ok((tls_stream, peer_addr))
+    }
+
+    /// Accept connection and send file over TLS
+    ///
+    /// Waits for a single connection, performs TLS handshake as CLIENT (inverted role),
+    /// and streams file data over the encrypted connection.
+    ///
+    /// # Parameters
+    ///
+    /// - `file_path`: Path to the file to send
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Connection times out
+    /// - TLS handshake fails
+    /// - File cannot be read
+    /// - Transfer fails
+    /// - Transfer is cancelled via progress callback
+    pub async fn send_file(self, file_path: impl AsRef<Path>) -> Result<()> {
+        let file_path = file_path.as_ref();
+        info!("Waiting for TLS connection to send file: {:?}", file_path);
+
+        let file_size = tokio::fs::metadata(file_path)
+            .await
+            .map_err(ProtocolError::Io)?
+            .len();
+        if let Some(layout) = self.stream_layout(file_size) {
+            return self.send_file_streams(file_path, file_size, layout).await;
+        }
+
+        let (mut tls_stream, peer_addr) = self.accept_receiver().await?;
+
+        // Open file
+        let mut file = File::open(file_path).await.map_err(ProtocolError::Io)?;
+        let mut total_bytes =
+            seek_to_resume_offset(&mut tls_stream, &mut file, file_size, self.resumable).await?;
+
+        // Stream file data over TLS
+        let mut chunks = self.chunk_probe;
+        let mut cipher = self.encryption;
+        let mut buffer = vec![0u8; chunks.max_chunk_size()];
+
+        loop {
+            let chunk = &mut buffer[..chunks.chunk_size()];
+            let bytes_read = timeout(TRANSFER_TIMEOUT, file.read(chunk))
+                .await
+                .map_err(|_| {
+                    ProtocolError::Io(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        "Read timeout",
+                    ))
+                })?
+                .map_err(ProtocolError::Io)?;
+
+            if bytes_read == 0 {
+                break;
+            }
+
+            if let Some(ref limit) = self.bandwidth {
+                limit.acquire(bytes_read).await;
+            }
+
+            // Write to TLS stream
+            let started = std::time::Instant::now();
+            timeout(
+                TRANSFER_TIMEOUT,
+                write_payload(&mut tls_stream, cipher.as_mut(), &buffer[..bytes_read]),
+            )
+            .await
+            .map_err(|_| {
+                ProtocolError::Io(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "Write timeout",
+                ))
+            })??;
+            chunks.record(bytes_read, started.elapsed());
+
+            total_bytes += bytes_read as u64;
+            if let Some(ref counter) = self.traffic_counter {
+                counter.record_payload_sent(bytes_read as u64);
+            }
+
+            debug!(
+                "Sent {} bytes over TLS ({}/{} total)",
+                bytes_read, total_bytes, file_size
+            );
+
+            // Call progress callback if set
+            if let Some(ref callback) = self.progress_callback {
+                if !callback(total_bytes, file_size) {
+                    info!("Transfer cancelled by progress callback");
+                    return Err(ProtocolError::Io(std::io::Error::new(
+                        std::io::ErrorKind::Interrupted,
+                        "Transfer cancelled",
+                    )));
+                }
+            }
+        }
+
+        // Flush TLS stream
+        tls_stream.flush().await.map_err(ProtocolError::Io)?;
+
+        info!(
+            "TLS file transfer complete: {} bytes sent to {}",
+            total_bytes, peer_addr
+        );
+
+        Ok(())
+    }
+
+    /// Accept one connection per stream and send each its range concurrently
+    async fn send_file_streams(
+        self,
+        file_path: &Path,
+        file_size: u64,
+        layout: StreamLayout,
+    ) -> Result<()> {
+        let mut legs = Vec::with_capacity(layout.streams);
+        for _ in 0..layout.streams {
+            legs.push(self.accept_receiver().await?.0);
+        }
+
+        let sent = AtomicU64::new(0);
+        futures::future::try_join_all(legs.iter_mut().map(|stream| {
+            send_stream_range(
+                stream,
+                file_path,
+                file_size,
+                layout,
+                self.bandwidth.as_ref(),
+                self.traffic_counter.as_ref(),
+                &sent,
+                self.progress_callback.as_ref(),
+            )
+        }))
+        .await?;
+
+        info!(
+            "File transfer complete: {} bytes sent over {} streams",
+            file_size, layout.streams
+        );
+        Ok(())
+    }
+
+    /// Accept a connection and send `data` from memory
+    ///
+    /// For small payloads that never touch the disk, such as clipboard
+    /// content. Progress callbacks and chunk probing are not used.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the connection times out or the transfer fails.
+    pub async fn send_bytes(self, data: &[u8]) -> Result<()> {
+        let (mut stream, remote_addr) = self.accept_receiver().await?;
+        send_buffer(
+            &mut stream,
+            self.encryption,
+            self.traffic_counter.as_ref(),
+            data,
+        )
+        .await?;
+        info!(
+            "Payload transfer complete: {} bytes sent to {}",
+            data.len(),
+            remote_addr
+        );
+        Ok(())
+    }
+
+    /// Send one file to several devices at once over TLS
+    ///
+    /// See [`PayloadServer::send_file_multi`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error only if the file cannot be read. Devices that fail to
+    /// connect or drop out are listed in [`FanOutSummary::failed`].
+    pub async fn send_file_multi(
+        file_path: impl AsRef<Path>,
+        servers: Vec<(String, TlsPayloadServer)>,
+    ) -> Result<FanOutSummary> {
+        let accepted =
+            futures::future::join_all(servers.into_iter().map(|(device_id, server)| async move {
+                let accepted = server.accept_receiver().await;
+                (device_id, server, accepted)
+            }))
+            .await;
+
+        let mut legs = Vec::with_capacity(accepted.len());
+        let mut summary = FanOutSummary::default();
+        for (device_id, server, accepted) in accepted {
+            match accepted {
+                Ok((stream, _)) => legs.push(FanOutLeg {
+                    device_id,
+                    stream,
+                    cipher: server.encryption,
+                    traffic_counter: server.traffic_counter,
+                    progress_callback: server.progress_callback,
+                }),
+                Err(e) => summary.failed.push((device_id, e)),
+            }
+        }
+        fan_out(file_path.as_ref(), legs, summary).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn collect_events(
+        throttle: ProgressThrottle,
+    ) -> (ProgressCallback, std::sync::Arc<std::sync::Mutex<Vec<u64>>>) {
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let callback = throttle.wrap(Box::new(move |transferred, _total| {
+            sink.lock().unwrap().push(transferred);
+            true
+        }));
+        (callback, events)
+    }
+
+    #[test]
+    fn test_progress_throttle_rate_limits_rapid_updates() {
+        let (callback, events) = collect_events(ProgressThrottle::new(10));
+
+        let total = 10_000u64;
+        for transferred in (1..=total).step_by(10) {
+            assert!(callback(transferred, total));
+        }
+        assert!(callback(total, total));
+
+        let events = events.lock().unwrap();
+        // A tight loop finishes well within one 100ms window: first + final only
+        assert!(events.len() <= 3, "too many events: {}", events.len());
+        assert_eq!(events.first(), Some(&1));
+        assert_eq!(events.last(), Some(&total));
+    }
+
+    #[test]
+    fn test_progress_throttle_always_emits_completion_once() {
+        let (callback, events) = collect_events(ProgressThrottle::new(1));
+
+        callback(10, 100);
+        callback(50, 100);
+        callback(100, 100);
+        callback(100, 100);
+
+        assert_eq!(*events.lock().unwrap(), vec![10, 100]);
+    }
+
+    #[test]
+    fn test_progress_throttle_emits_after_interval() {
+        let (callback, events) = collect_events(ProgressThrottle::new(20));
+
+        for transferred in 1..=3 {
+            callback(transferred, 100);
+            std::thread::sleep(Duration::from_millis(60));
+        }
+
+        assert_eq!(*events.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_progress_throttle_byte_delta() {
+        let (callback, events) = collect_events(ProgressThrottle::new(0).with_min_byte_delta(1000));
+
+        for transferred in (100..=5000).step_by(100) {
+            callback(transferred, 10_000);
+        }
+
+        assert_eq!(*events.lock().unwrap(), vec![100, 1100, 2100, 3100, 4100]);
+    }
+
+    #[test]
+    fn test_progress_throttle_propagates_cancel() {
+        let callback = ProgressThrottle::new(10).wrap(Box::new(|_, _| false));
+        assert!(!callback(1, 100));
+        // Suppressed updates keep the transfer going until the next emission
+        assert!(callback(2, 100));
+        assert!(!callback(100, 100));
+    }
+
+    #[tokio::test]
+    async fn test_file_transfer_info_from_path() {
+        // Create temporary file
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(b"test content").unwrap();
+        temp_file.flush().unwrap();
+
+        let info = FileTransferInfo::from_path(temp_file.path()).await.unwrap();
+
+        assert_eq!(info.size, 12);
+        assert!(!info.filename.is_empty());
+        assert!(info.last_modified.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_payload_server_creation() {
+        let server = PayloadServer::new().await.unwrap();
+        let port = server.port();
+
+        assert!(port >= PORT_RANGE_START);
+        assert!(port <= PORT_RANGE_END);
+    }
+
+    #[tokio::test]
+    async fn test_file_transfer_round_trip() {
+        // Create temporary source file with test data
+        let mut source_file = NamedTempFile::new().unwrap();
+        let test_data = b"Hello, this is a test file for payload transfer!";
+        source_file.write_all(test_data).unwrap();
+        source_file.flush().unwrap();
+        let source_path = source_file.path().to_owned();
+
+        // Create temporary destination file
+        let dest_file = NamedTempFile::new().unwrap();
+        let dest_path = dest_file.path().to_owned();
+
+        // Start server
+        let server = PayloadServer::new().await.unwrap();
+        let port = server.port();
+
+        // Spawn server task
+        let source_path_clone = source_path.clone();
+        let server_task = tokio::spawn(async move { server.send_file(source_path_clone).await });
+
+        // Give server time to start listening
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Connect client and receive file
+        let client = PayloadClient::new("127.0.0.1", port).await.unwrap();
+        client
+            .receive_file(&dest_path, test_data.len() as u64)
+            .await
+            .unwrap();
+
+        // Wait for server to complete
+        server_task.await.unwrap().unwrap();
+
+        // Verify file contents
+        let received_data = tokio::fs::read(&dest_path).await.unwrap();
+        assert_eq!(&received_data[..], test_data);
+    }
+
+    #[tokio::test]
+    async fn test_transfer_updates_traffic_counters() {
+        let mut source_file = NamedTempFile::new().unwrap();
+        let test_data = vec![0xA5u8; BUFFER_SIZE * 2 + 123];
+        source_file.write_all(&test_data).unwrap();
+        source_file.flush().unwrap();
+        let source_path = source_file.path().to_owned();
+        let dest_file = NamedTempFile::new().unwrap();
+        let dest_path = dest_file.path().to_owned();
+
+        let sender_counter = TrafficCounter::new();
+        let receiver_counter = TrafficCounter::new();
+
+        let server = PayloadServer::new()
+            .await
+            .unwrap()
+            .with_traffic_counter(sender_counter.clone());
+        let port = server.port();
+        let server_task = tokio::spawn(async move { server.send_file(source_path).await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .with_traffic_counter(receiver_counter.clone())
+            .receive_file(&dest_path, test_data.len() as u64)
+            .await
+            .unwrap();
+        server_task.await.unwrap().unwrap();
+
+        let sent = sender_counter.snapshot();
+        let received = receiver_counter.snapshot();
+        assert_eq!(sent.payload_sent, test_data.len() as u64);
+        assert_eq!(received.payload_received, test_data.len() as u64);
+        assert_eq!(sent.control_sent + sent.payload_received, 0);
+
+        // Resetting starts a fresh tally
+        assert_eq!(
+            receiver_counter.reset().payload_received,
+            test_data.len() as u64
+        );
+        assert_eq!(receiver_counter.snapshot().total(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_probed_chunks_deliver_whole_file() {
+        let mut source_file = NamedTempFile::new().unwrap();
+        let test_data: Vec<u8> = (0..BUFFER_SIZE * 5 + 7).map(|i| i as u8).collect();
+        source_file.write_all(&test_data).unwrap();
+        source_file.flush().unwrap();
+        let source_path = source_file.path().to_owned();
+        let dest_file = NamedTempFile::new().unwrap();
+        let dest_path = dest_file.path().to_owned();
+
+        let probe = ChunkProbe::new(crate::chunk_sizing::ChunkProbeConfig {
+            min: 4096,
+            max: BUFFER_SIZE * 2,
+            initial: 4096,
+            ..Default::default()
+        })
+        .unwrap();
+        let server = PayloadServer::new().await.unwrap().with_chunk_probe(probe);
+        let port = server.port();
+        let server_task = tokio::spawn(async move { server.send_file(source_path).await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .receive_file(&dest_path, test_data.len() as u64)
+            .await
+            .unwrap();
+        server_task.await.unwrap().unwrap();
+
+        assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), test_data);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_transfer_round_trip() {
+        let mut source_file = NamedTempFile::new().unwrap();
+        let test_data: Vec<u8> = (0..BUFFER_SIZE * 2 + 99).map(|i| i as u8).collect();
+        source_file.write_all(&test_data).unwrap();
+        source_file.flush().unwrap();
+        let source_path = source_file.path().to_owned();
+        let dest_file = NamedTempFile::new().unwrap();
+        let dest_path = dest_file.path().to_owned();
+
+        let key = crate::PayloadKey::from_base64(&format!("{}=", "A".repeat(43))).unwrap();
+        let server = PayloadServer::new()
+            .await
+            .unwrap()
+            .with_encryption(PayloadCipher::new(&key, [7; 16]).unwrap());
+        let port = server.port();
+        let server_task = tokio::spawn(async move { server.send_file(source_path).await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .with_encryption(PayloadCipher::new(&key, [7; 16]).unwrap())
+            .receive_file(&dest_path, test_data.len() as u64)
+            .await
+            .unwrap();
+        server_task.await.unwrap().unwrap();
+
+        assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), test_data);
+    }
+
+    async fn fan_out_to(
+        data: &[u8],
+        devices: &[&str],
+        cancel: Option<&str>,
+    ) -> (FanOutSummary, Vec<(String, Result<Vec<u8>>)>) {
+        let mut source_file = NamedTempFile::new().unwrap();
+        source_file.write_all(data).unwrap();
+        source_file.flush().unwrap();
+
+        let mut servers = Vec::new();
+        let mut receivers = Vec::new();
+        for &device in devices {
+            let keep_going = Some(device) != cancel;
+            let server = PayloadServer::new()
+                .await
+                .unwrap()
+                .with_progress(Box::new(move |_, _| keep_going));
+            let port = server.port();
+            servers.push((device.to_string(), server));
+
+            let len = data.len() as u64;
+            receivers.push((
+                device.to_string(),
+                tokio::spawn(async move {
+                    let dest = NamedTempFile::new().unwrap();
+                    PayloadClient::new("127.0.0.1", port)
+                        .await?
+                        .receive_file(dest.path(), len)
+                        .await?;
+                    Ok(tokio::fs::read(dest.path()).await.unwrap())
+                }),
+            ));
+        }
+
+        let summary = PayloadServer::send_file_multi(source_file.path(), servers)
+            .await
+            .unwrap();
+        let mut received = Vec::new();
+        for (device, task) in receivers {
+            received.push((device, task.await.unwrap()));
+        }
+        (summary, received)
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_delivers_to_every_receiver() {
+        let data: Vec<u8> = (0..BUFFER_SIZE * 3 + 11).map(|i| (i % 251) as u8).collect();
+
+        let (summary, received) = fan_out_to(&data, &["phone", "tablet", "laptop"], None).await;
+
+        assert!(summary.is_complete());
+        assert_eq!(summary.file_size, data.len() as u64);
+        let mut delivered = summary.delivered.clone();
+        delivered.sort();
+        assert_eq!(delivered, vec!["laptop", "phone", "tablet"]);
+        for (device, result) in received {
+            assert_eq!(result.unwrap(), data, "{} got a different file", device);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_reports_failures_per_device() {
+        let data = vec![0x3Cu8; BUFFER_SIZE * 3];
+
+        let (summary, received) =
+            fan_out_to(&data, &["phone", "tablet", "laptop"], Some("tablet")).await;
+
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].0, "tablet");
+        assert!(matches!(summary.failed[0].1, ProtocolError::Io(_)));
+        assert_eq!(summary.delivered.len(), 2);
+        for (device, result) in received {
+            match device.as_str() {
+                "tablet" => assert!(result.is_err()),
+                _ => assert_eq!(result.unwrap(), data),
+            }
+        }
+    }
+
+    async fn send_in_background(
+        data: &[u8],
+    ) -> (NamedTempFile, tokio::task::JoinHandle<Result<()>>, u16) {
+        let mut source_file = NamedTempFile::new().unwrap();
+        source_file.write_all(data).unwrap();
+        source_file.flush().unwrap();
+        let source_path = source_file.path().to_owned();
+
+        let server = PayloadServer::new().await.unwrap();
+        let port = server.port();
+        let task = tokio::spawn(async move { server.send_file(source_path).await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        (source_file, task, port)
+    }
+
+    #[tokio::test]
+    async fn test_final_name_appears_only_when_complete() {
+        let data = vec![0x5Au8; BUFFER_SIZE * 3];
+        let (_source, task, port) = send_in_background(&data).await;
+        let dir = tempfile::tempdir().unwrap();
+        let dest_path = dir.path().join("photo.jpg");
+        let staged = dir.path().join(".photo.jpg.part");
+
+        let seen_early = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let (flag, dest, part) = (seen_early.clone(), dest_path.clone(), staged.clone());
+        PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .with_checksums(ChunkChecksums::from_bytes(&data, 4096))
+            .with_progress(Box::new(move |_, _| {
+                if dest.exists() || !part.exists() {
+                    flag.store(true, std::sync::atomic::Ordering::SeqCst);
+                }
+                true
+            }))
+            .receive_file(&dest_path, data.len() as u64)
+            .await
+            .unwrap();
+        task.await.unwrap().unwrap();
+
+        assert!(!seen_early.load(std::sync::atomic::Ordering::SeqCst));
+        assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), data);
+        assert!(!staged.exists());
+    }
+
+    #[tokio::test]
+    async fn test_failed_transfer_leaves_no_final_file() {
+        let data = vec![0x11u8; BUFFER_SIZE * 2];
+        let dir = tempfile::tempdir().unwrap();
+        let dest_path = dir.path().join("video.mkv");
+
+        // Cancelled mid-way
+        let (_source, task, port) = send_in_background(&data).await;
+        let result = PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .with_progress(Box::new(|_, _| false))
+            .receive_file(&dest_path, data.len() as u64)
+            .await;
+        assert!(result.is_err());
+        let _ = task.await;
+        assert!(!dest_path.exists());
+
+        // Complete, but the content does not match the checksums
+        let (_source, task, port) = send_in_background(&data).await;
+        let result = PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .with_checksums(ChunkChecksums::from_bytes(&vec![0x22u8; data.len()], 4096))
+            .receive_file(&dest_path, data.len() as u64)
+            .await;
+        assert!(matches!(result, Err(ProtocolError::Transport(_))));
+        task.await.unwrap().unwrap();
+        assert!(!dest_path.exists());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_transfer_resumes_from_partial_file() {
+        let data: Vec<u8> = (0..BUFFER_SIZE * 3).map(|i| (i % 251) as u8).collect();
+        let cut = BUFFER_SIZE + 100;
+        let dir = tempfile::tempdir().unwrap();
+        let dest_path = dir.path().join("backup.tar");
+
+        // The first sender drops the connection part-way
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let partial = data[..cut].to_vec();
+        let dropping_sender = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let offset = read_resume_offset(&mut stream, u64::MAX).await.unwrap();
+            assert_eq!(offset, 0);
+            stream.write_all(&partial).await.unwrap();
+        });
+        let result = PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .resume_from(0)
+            .receive_file(&dest_path, data.len() as u64)
+            .await;
+        assert!(result.is_err());
+        dropping_sender.await.unwrap();
+        assert!(!dest_path.exists());
+        let offset = resume_offset(&dest_path, None, data.len() as u64).await;
+        assert_eq!(offset, cut as u64);
+
+        // The second attempt only moves the rest
+        let mut source_file = NamedTempFile::new().unwrap();
+        source_file.write_all(&data).unwrap();
+        let source_path = source_file.path().to_owned();
+        let sender_counter = TrafficCounter::new();
+        let server = PayloadServer::new()
+            .await
+            .unwrap()
+            .allow_resume()
+            .with_traffic_counter(sender_counter.clone());
+        let port = server.port();
+        let task = tokio::spawn(async move { server.send_file(source_path).await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let progress = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = progress.clone();
+        PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .resume_from(offset)
+            .with_progress(Box::new(move |transferred, _| {
+                seen.lock().unwrap().push(transferred);
+                true
+            }))
+            .receive_file(&dest_path, data.len() as u64)
+            .await
+            .unwrap();
+        task.await.unwrap().unwrap();
+
+        assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), data);
+        assert!(!staging_path(&dest_path, None).exists());
+        assert_eq!(
+            sender_counter.snapshot().payload_sent,
+            (data.len() - cut) as u64
+        );
+        assert!(progress.lock().unwrap()[0] > cut as u64);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_resumable_transfer_discards_partial_file() {
+        let data = vec![0x33u8; BUFFER_SIZE * 2];
+        let dir = tempfile::tempdir().unwrap();
+        let dest_path = dir.path().join("video.mkv");
+        let mut source_file = NamedTempFile::new().unwrap();
+        source_file.write_all(&data).unwrap();
+        let source_path = source_file.path().to_owned();
+
+        let server = PayloadServer::new().await.unwrap().allow_resume();
+        let port = server.port();
+        let task = tokio::spawn(async move { server.send_file(source_path).await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let result = PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .resume_from(0)
+            .with_progress(Box::new(|_, _| false))
+            .receive_file(&dest_path, data.len() as u64)
+            .await;
+        assert!(result.is_err());
+        let _ = task.await;
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_resume_offset_past_end_rejected() {
+        let (mut receiver, mut sender) = tokio::io::duplex(64);
+        send_resume_offset(&mut receiver, 101).await.unwrap();
+        let result = read_resume_offset(&mut sender, 100).await;
+        assert!(matches!(result, Err(ProtocolError::InvalidPacket(_))));
+    }
+
+    #[tokio::test]
+    async fn test_file_checksum_verified() {
+        let data: Vec<u8> = (0..BUFFER_SIZE * 2 + 17).map(|i| (i % 251) as u8).collect();
+        let mut source_file = NamedTempFile::new().unwrap();
+        source_file.write_all(&data).unwrap();
+        let info = FileTransferInfo::from_path(source_file.path())
+            .await
+            .unwrap()
+            .with_checksum()
+            .await
+            .unwrap();
+        let checksum = info.checksum.clone().unwrap();
+        assert_eq!(checksum, hex::encode(Sha256::digest(&data)));
+
+        let server = PayloadServer::new().await.unwrap();
+        let port = server.port();
+        let task = tokio::spawn(async move { server.send_file(info.path).await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest_path = dir.path().join("photo.jpg");
+        PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .with_file_checksum(checksum.to_uppercase())
+            .receive_file(&dest_path, data.len() as u64)
+            .await
+            .unwrap();
+        task.await.unwrap().unwrap();
+        assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_file_checksum_mismatch_discards_file() {
+        let data = vec![0x5au8; BUFFER_SIZE + 10];
+        let mut source_file = NamedTempFile::new().unwrap();
+        source_file.write_all(&data).unwrap();
+        let source_path = source_file.path().to_owned();
+
+        let server = PayloadServer::new().await.unwrap().allow_resume();
+        let port = server.port();
+        let task = tokio::spawn(async move { server.send_file(source_path).await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest_path = dir.path().join("photo.jpg");
+        let wrong = hex::encode(Sha256::digest(b"something else"));
+        let result = PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .resume_from(0)
+            .with_file_checksum(wrong.clone())
+            .receive_file(&dest_path, data.len() as u64)
+            .await;
+        let _ = task.await;
+
+        match result {
+            Err(ProtocolError::ChecksumMismatch(expected, actual)) => {
+                assert_eq!(expected, wrong);
+                assert_eq!(actual, hex::encode(Sha256::digest(&data)));
+            }
+            other => panic!("expected checksum mismatch, got {other:?}"),
+        }
+        assert!(!dest_path.exists());
+        assert!(!staging_path(&dest_path, None).exists());
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_limit_paces_send() {
+        let data = vec![0x42u8; 100_000];
+        let mut source_file = NamedTempFile::new().unwrap();
+        source_file.write_all(&data).unwrap();
+        let source_path = source_file.path().to_owned();
+
+        let server = PayloadServer::new()
+            .await
+            .unwrap()
+            .with_chunk_probe(ChunkProbe::fixed(10_000))
+            .with_bandwidth_limit(BandwidthLimit::new(Some(200_000)));
+        let port = server.port();
+        let task = tokio::spawn(async move {
+            let started = std::time::Instant::now();
+            server.send_file(source_path).await.unwrap();
+            started.elapsed()
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest_path = dir.path().join("upload.bin");
+        PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .receive_file(&dest_path, data.len() as u64)
+            .await
+            .unwrap();
+
+        // 100 KB at 200 KB/s, less one burst, takes at least 0.4s
+        let elapsed = task.await.unwrap();
+        assert!(elapsed >= Duration::from_millis(400), "{elapsed:?}");
+        assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), data);
+    }
+
+    #[test]
+    fn test_stream_layout_splits_file() {
+        let layout = StreamLayout::new(1_500_000, 4);
+        assert_eq!(layout.streams, 4);
+        let ranges: Vec<_> = (0..4).map(|i| layout.range(i, 1_500_000)).collect();
+        assert_eq!(ranges[0].start, 0);
+        assert_eq!(ranges[3].end, 1_500_000);
+        assert!(ranges.windows(2).all(|pair| pair[0].end == pair[1].start));
+
+        // Small files don't get a stream per few bytes
+        assert_eq!(StreamLayout::new(300_000, 4).streams, 1);
+        assert_eq!(StreamLayout::new(600_000, 4).streams, 2);
+
+        let packet = layout.add_to_packet(Packet::new("cconnect.share.request", json!({})));
+        assert_eq!(StreamLayout::from_packet(&packet), Some(layout));
+        let single = StreamLayout::new(1000, 1)
+            .add_to_packet(Packet::new("cconnect.share.request", json!({})));
+        assert_eq!(StreamLayout::from_packet(&single), None);
+        assert!(StreamLayout {
+            streams: 2,
+            stream_size: 10,
+        }
+        .check_covers(21)
+        .is_err());
+    }
+
+    #[test]
+    fn test_advertised_payload_streams() {
+        let standard = Packet::new("cconnect.identity", json!({}));
+        assert_eq!(advertised_payload_streams(&standard), 1);
+        let capable = Packet::new("cconnect.identity", json!({ PAYLOAD_STREAMS_FIELD: 4 }));
+        assert_eq!(advertised_payload_streams(&capable), 4);
+    }
+
+    #[tokio::test]
+    async fn test_parallel_streams_reassemble_file() {
+        let data: Vec<u8> = (0..1_500_000u32).map(|i| (i % 251) as u8).collect();
+        let mut source_file = NamedTempFile::new().unwrap();
+        source_file.write_all(&data).unwrap();
+        let source_path = source_file.path().to_owned();
+
+        let counter = TrafficCounter::new();
+        let server = PayloadServer::new()
+            .await
+            .unwrap()
+            .with_traffic_counter(counter.clone())
+            .with_parallel_streams(4);
+        let layout = server.stream_layout(data.len() as u64).unwrap();
+        assert_eq!(layout.streams, 4);
+        let port = server.port();
+        let task = tokio::spawn(async move { server.send_file(source_path).await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest_path = dir.path().join("video.mp4");
+        let progress = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = progress.clone();
+        PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .with_parallel_streams(layout)
+            .with_file_checksum(hex::encode(Sha256::digest(&data)))
+            .with_progress(Box::new(move |transferred, _| {
+                seen.lock().unwrap().push(transferred);
+                true
+            }))
+            .receive_file(&dest_path, data.len() as u64)
+            .await
+            .unwrap();
+        task.await.unwrap().unwrap();
+
+        assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), data);
+        assert!(!staging_path(&dest_path, None).exists());
+        assert_eq!(counter.snapshot().payload_sent, data.len() as u64);
+        assert_eq!(progress.lock().unwrap().last(), Some(&(data.len() as u64)));
     }
 
-    /// Set a progress callback for transfer updates
-    ///
-    /// The callback receives (bytes_transferred, total_bytes) and returns
-    /// `true` to continue or `false` to cancel the transfer.
-    pub fn with_progress(mut self, callback: ProgressCallback) -> Self {
-        self.progress_callback = Some(callback);
-        self
+    #[tokio::test]
+    async fn test_parallel_streams_fall_back_for_resumable_transfers() {
+        let server = PayloadServer::new()
+            .await
+            .unwrap()
+            .with_parallel_streams(4)
+            .allow_resume();
+        assert_eq!(server.stream_layout(10_000_000), None);
     }
 
-    /// Accept connection and send file over TLS
-    ///
-    /// Waits for a single connection, performs TLS handshake as CLIENT (inverted role),
-    /// and streams file data over the encrypted connection.
-    ///
-    /// # Parameters
-    ///
-    /// - `file_path`: Path to the file to send
-    ///
-    /// # Errors
-    ///
-    /// Returns error if:
-    /// - Connection times out
-    /// - TLS handshake fails
-    /// - File cannot be read
-    /// - Transfer fails
-    /// - Transfer is cancelled via progress callback
-    pub async fn send_file(self, file_path: impl AsRef<Path>) -> Result<()> {
-        let file_path = file_path.as_ref();
-        info!("Waiting for TLS connection to send file: {:?}", file_path);
+    async fn staged_transfer(
+        data: &[u8],
+        confirm_timeout: Duration,
+    ) -> (
+        NamedTempFile,
+        TrafficCounter,
+        tokio::task::JoinHandle<Result<()>>,
+        u16,
+    ) {
+        let mut source_file = NamedTempFile::new().unwrap();
+        source_file.write_all(data).unwrap();
+        source_file.flush().unwrap();
+        let source_path = source_file.path().to_owned();
 
-        // Accept TCP connection
-        let (tcp_stream, peer_addr) = timeout(CONNECTION_TIMEOUT, self.listener.accept())
+        let counter = TrafficCounter::new();
+        let server = PayloadServer::new()
             .await
-            .map_err(|_| {
-                ProtocolError::Io(std::io::Error::new(
-                    std::io::ErrorKind::TimedOut,
-                    "No client connected within timeout",
-                ))
-            })?
-            .map_err(ProtocolError::Io)?;
+            .unwrap()
+            .with_traffic_counter(counter.clone())
+            .require_confirmation(confirm_timeout);
+        let port = server.port();
+        let task = tokio::spawn(async move { server.send_file(source_path).await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
 
-        info!(
-            "Accepted TCP connection from {} for TLS file transfer",
-            peer_addr
-        );
+        (source_file, counter, task, port)
+    }
 
-        // KDE Connect quirk: TCP acceptor acts as TLS CLIENT
-        // Create TLS connector with CLIENT config (inverted role!)
-        let connector = TlsConnector::from(self.tls_config.client_config());
+    #[tokio::test]
+    async fn test_staged_transfer_accepted() {
+        let data = b"large file contents";
+        let (_source, counter, task, port) =
+            staged_transfer(data, DEFAULT_CONFIRMATION_TIMEOUT).await;
+        let dest_dir = tempfile::tempdir().unwrap();
+        let dest_path = dest_dir.path().join("received.bin");
 
-        // Use a dummy server name since we're using TOFU
-        let server_name = rustls::pki_types::ServerName::try_from("kdeconnect").map_err(|e| {
-            ProtocolError::Io(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                format!("Invalid server name: {}", e),
-            ))
-        })?;
+        let client = PayloadClient::new("127.0.0.1", port).await.unwrap();
+        client.accept(&dest_path, data.len() as u64).await.unwrap();
+        task.await.unwrap().unwrap();
 
-        // Perform TLS handshake as CLIENT (inverted role!)
-        let mut tls_stream = timeout(
-            CONNECTION_TIMEOUT,
-            connector.connect(server_name, tcp_stream),
-        )
-        .await
-        .map_err(|_| {
-            ProtocolError::Io(std::io::Error::new(
-                std::io::ErrorKind::TimedOut,
-                "TLS handshake timeout",
-            ))
-        })?
-        .map_err(|e| {
-            error!("TLS handshake failed for payload transfer: {}", e);
-            ProtocolError::Io(std::io::Error::new(
-                std::io::ErrorKind::ConnectionRefused,
-                format!("TLS handshake failed: {}", e),
-            ))
-        })?;
+        assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), data);
+        assert_eq!(counter.snapshot().payload_sent, data.len() as u64);
+    }
 
-        info!(
-            "TLS connection established with {} for file transfer (as TLS CLIENT)",
-            peer_addr
-        );
+    #[tokio::test]
+    async fn test_staged_transfer_declined() {
+        let (_source, counter, task, port) =
+            staged_transfer(b"unwanted", DEFAULT_CONFIRMATION_TIMEOUT).await;
 
-        // Open file and get size
-        let mut file = File::open(file_path).await.map_err(ProtocolError::Io)?;
-        let file_size = file.metadata().await.map_err(ProtocolError::Io)?.len();
+        let client = PayloadClient::new("127.0.0.1", port).await.unwrap();
+        client.decline().await.unwrap();
 
-        // Stream file data over TLS
-        let mut buffer = vec![0u8; BUFFER_SIZE];
-        let mut total_bytes: u64 = 0;
+        let result = task.await.unwrap();
+        assert!(matches!(result, Err(ProtocolError::PeerRejected(_))));
+        assert_eq!(counter.snapshot().payload_sent, 0);
+    }
 
-        loop {
-            let bytes_read = timeout(TRANSFER_TIMEOUT, file.read(&mut buffer))
-                .await
-                .map_err(|_| {
-                    ProtocolError::Io(std::io::Error::new(
-                        std::io::ErrorKind::TimedOut,
-                        "Read timeout",
-                    ))
-                })?
-                .map_err(ProtocolError::Io)?;
+    #[tokio::test]
+    async fn test_staged_transfer_times_out_as_decline() {
+        let (_source, counter, task, port) =
+            staged_transfer(b"nobody answered", Duration::from_millis(200)).await;
 
-            if bytes_read == 0 {
-                break;
-            }
+        // Connect but never answer
+        let _client = PayloadClient::new("127.0.0.1", port).await.unwrap();
 
-            // Write to TLS stream
-            timeout(
-                TRANSFER_TIMEOUT,
-                tls_stream.write_all(&buffer[..bytes_read]),
-            )
-            .await
-            .map_err(|_| {
-                ProtocolError::Io(std::io::Error::new(
-                    std::io::ErrorKind::TimedOut,
-                    "Write timeout",
-                ))
-            })?
-            .map_err(ProtocolError::Io)?;
+        let result = task.await.unwrap();
+        assert!(matches!(result, Err(ProtocolError::PeerRejected(_))));
+        assert_eq!(counter.snapshot().payload_sent, 0);
+    }
 
-            total_bytes += bytes_read as u64;
+    fn trust_gate(prompt_timeout: Duration) -> (TransferGate, mpsc::Receiver<TransferPrompt>) {
+        let levels = ReceiveTrustLevels::new(ReceiveTrust::Prompt)
+            .with_device_trust("laptop", ReceiveTrust::AutoAccept)
+            .with_device_trust("stranger", ReceiveTrust::Reject);
+        TransferGate::new(levels, prompt_timeout)
+    }
 
-            debug!(
-                "Sent {} bytes over TLS ({}/{} total)",
-                bytes_read, total_bytes, file_size
-            );
+    #[tokio::test]
+    async fn test_trusted_device_auto_accepted() {
+        let data = b"from a trusted device";
+        let (_source, _counter, task, port) =
+            staged_transfer(data, DEFAULT_CONFIRMATION_TIMEOUT).await;
+        let (gate, mut prompts) = trust_gate(Duration::from_secs(5));
+        let dest_dir = tempfile::tempdir().unwrap();
+        let dest_path = dest_dir.path().join("trusted.bin");
 
-            // Call progress callback if set
-            if let Some(ref callback) = self.progress_callback {
-                if !callback(total_bytes, file_size) {
-                    info!("Transfer cancelled by progress callback");
-                    return Err(ProtocolError::Io(std::io::Error::new(
-                        std::io::ErrorKind::Interrupted,
-                        "Transfer cancelled",
-                    )));
-                }
-            }
-        }
+        let client = PayloadClient::new("127.0.0.1", port).await.unwrap();
+        let received = client
+            .accept_if_trusted(&gate, "laptop", &dest_path, data.len() as u64)
+            .await
+            .unwrap();
+        task.await.unwrap().unwrap();
 
-        // Flush TLS stream
-        tls_stream.flush().await.map_err(ProtocolError::Io)?;
+        assert!(received);
+        assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), data);
+        assert!(prompts.try_recv().is_err());
+    }
 
-        info!(
-            "TLS file transfer complete: {} bytes sent to {}",
-            total_bytes, peer_addr
+    #[tokio::test]
+    async fn test_staged_transfer_held_despite_auto_accept_default() {
+        let data = b"staged for the user";
+        let (_source, _counter, task, port) =
+            staged_transfer(data, DEFAULT_CONFIRMATION_TIMEOUT).await;
+        let (gate, mut prompts) = TransferGate::new(
+            ReceiveTrustLevels::new(ReceiveTrust::AutoAccept),
+            Duration::from_secs(5),
         );
+        let dest_dir = tempfile::tempdir().unwrap();
+        let dest_path = dest_dir.path().join("staged.bin");
 
-        Ok(())
+        // Unstaged files are still taken without asking
+        assert!(gate.decide("phone", "other.bin", 1).await);
+        assert!(prompts.try_recv().is_err());
+
+        let answer = tokio::spawn(async move {
+            let prompt = prompts.recv().await.unwrap();
+            assert_eq!(prompt.filename, "staged.bin");
+            prompt.accept();
+        });
+
+        let client = PayloadClient::new("127.0.0.1", port).await.unwrap();
+        let received = client
+            .accept_if_trusted(&gate, "phone", &dest_path, data.len() as u64)
+            .await
+            .unwrap();
+        answer.await.unwrap();
+        task.await.unwrap().unwrap();
+
+        assert!(received);
+        assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), data);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
+    #[tokio::test]
+    async fn test_rejected_device_declined_without_prompt() {
+        let (_source, counter, task, port) =
+            staged_transfer(b"unwanted", DEFAULT_CONFIRMATION_TIMEOUT).await;
+        let (gate, mut prompts) = trust_gate(Duration::from_secs(5));
+        let dest_dir = tempfile::tempdir().unwrap();
+        let dest_path = dest_dir.path().join("unwanted.bin");
+
+        let client = PayloadClient::new("127.0.0.1", port).await.unwrap();
+        let received = client
+            .accept_if_trusted(&gate, "stranger", &dest_path, 8)
+            .await
+            .unwrap();
+
+        assert!(!received);
+        assert!(matches!(
+            task.await.unwrap(),
+            Err(ProtocolError::PeerRejected(_))
+        ));
+        assert_eq!(counter.snapshot().payload_sent, 0);
+        assert!(!dest_path.exists());
+        assert!(prompts.try_recv().is_err());
+    }
 
     #[tokio::test]
-    async fn test_file_transfer_info_from_path() {
-        // Create temporary file
-        let mut temp_file = NamedTempFile::new().unwrap();
-        temp_file.write_all(b"test content").unwrap();
-        temp_file.flush().unwrap();
+    async fn test_prompted_transfer_accepted_by_user() {
+        let data = b"asked first";
+        let (_source, _counter, task, port) =
+            staged_transfer(data, DEFAULT_CONFIRMATION_TIMEOUT).await;
+        let (gate, mut prompts) = trust_gate(Duration::from_secs(5));
+        let dest_dir = tempfile::tempdir().unwrap();
+        let dest_path = dest_dir.path().join("photo.jpg");
+
+        let answer = tokio::spawn(async move {
+            let prompt = prompts.recv().await.unwrap();
+            assert_eq!(prompt.device_id, "phone");
+            assert_eq!(prompt.filename, "photo.jpg");
+            assert_eq!(prompt.size, data.len() as u64);
+            prompt.accept();
+        });
 
-        let info = FileTransferInfo::from_path(temp_file.path()).await.unwrap();
+        let client = PayloadClient::new("127.0.0.1", port).await.unwrap();
+        let received = client
+            .accept_if_trusted(&gate, "phone", &dest_path, data.len() as u64)
+            .await
+            .unwrap();
+        answer.await.unwrap();
+        task.await.unwrap().unwrap();
 
-        assert_eq!(info.size, 12);
-        assert!(!info.filename.is_empty());
-        assert!(info.last_modified.is_some());
+        assert!(received);
+        assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), data);
     }
 
     #[tokio::test]
-    async fn test_payload_server_creation() {
-        let server = PayloadServer::new().await.unwrap();
-        let port = server.port();
+    async fn test_unanswered_prompt_declines() {
+        let (_source, counter, task, port) =
+            staged_transfer(b"nobody looked", DEFAULT_CONFIRMATION_TIMEOUT).await;
+        let (gate, mut prompts) = trust_gate(Duration::from_millis(100));
+        let dest_dir = tempfile::tempdir().unwrap();
+        let dest_path = dest_dir.path().join("ignored.bin");
 
-        assert!(port >= PORT_RANGE_START);
-        assert!(port <= PORT_RANGE_END);
+        let client = PayloadClient::new("127.0.0.1", port).await.unwrap();
+        let received = client
+            .accept_if_trusted(&gate, "phone", &dest_path, 13)
+            .await
+            .unwrap();
+
+        assert!(!received);
+        assert!(prompts.recv().await.is_some());
+        assert!(matches!(
+            task.await.unwrap(),
+            Err(ProtocolError::PeerRejected(_))
+        ));
+        assert_eq!(counter.snapshot().payload_sent, 0);
+        assert!(!dest_path.exists());
+    }
+
+    /// Answer prompts through a [`PendingTransferPrompts`], like the daemon does
+    fn register_prompts(
+        mut prompts: mpsc::Receiver<TransferPrompt>,
+    ) -> (PendingTransferPrompts, mpsc::UnboundedReceiver<String>) {
+        let pending = PendingTransferPrompts::new();
+        let (ids_tx, ids_rx) = mpsc::unbounded_channel();
+        let registry = pending.clone();
+        tokio::spawn(async move {
+            while let Some(prompt) = prompts.recv().await {
+                let _ = ids_tx.send(registry.insert(prompt));
+            }
+        });
+        (pending, ids_rx)
     }
 
     #[tokio::test]
-    async fn test_file_transfer_round_trip() {
-        // Create temporary source file with test data
-        let mut source_file = NamedTempFile::new().unwrap();
-        let test_data = b"Hello, this is a test file for payload transfer!";
-        source_file.write_all(test_data).unwrap();
-        source_file.flush().unwrap();
-        let source_path = source_file.path().to_owned();
+    async fn test_pending_prompt_accept_starts_transfer() {
+        let data = b"accepted from a notification";
+        let (_source, _counter, task, port) =
+            staged_transfer(data, DEFAULT_CONFIRMATION_TIMEOUT).await;
+        let (gate, prompts) = trust_gate(Duration::from_secs(5));
+        let (pending, mut ids) = register_prompts(prompts);
+        let dest_dir = tempfile::tempdir().unwrap();
+        let dest_path = dest_dir.path().join("photo.jpg");
+
+        let answer = tokio::spawn(async move {
+            let prompt_id = ids.recv().await.unwrap();
+            assert!(pending.contains(&prompt_id));
+            assert!(pending.accept(&prompt_id));
+            // Answered prompts are gone
+            assert!(!pending.decline(&prompt_id));
+        });
 
-        // Create temporary destination file
-        let dest_file = NamedTempFile::new().unwrap();
-        let dest_path = dest_file.path().to_owned();
+        let client = PayloadClient::new("127.0.0.1", port).await.unwrap();
+        let received = client
+            .accept_if_trusted(&gate, "phone", &dest_path, data.len() as u64)
+            .await
+            .unwrap();
+        answer.await.unwrap();
+        task.await.unwrap().unwrap();
 
-        // Start server
-        let server = PayloadServer::new().await.unwrap();
-        let port = server.port();
+        assert!(received);
+        assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), data);
+    }
 
-        // Spawn server task
-        let source_path_clone = source_path.clone();
-        let server_task = tokio::spawn(async move { server.send_file(source_path_clone).await });
+    #[tokio::test]
+    async fn test_pending_prompt_decline_refuses_transfer() {
+        let (_source, counter, task, port) =
+            staged_transfer(b"declined", DEFAULT_CONFIRMATION_TIMEOUT).await;
+        let (gate, prompts) = trust_gate(Duration::from_secs(5));
+        let (pending, mut ids) = register_prompts(prompts);
+        let dest_dir = tempfile::tempdir().unwrap();
+        let dest_path = dest_dir.path().join("declined.bin");
+
+        let answer = tokio::spawn(async move {
+            let prompt_id = ids.recv().await.unwrap();
+            assert!(pending.decline(&prompt_id));
+        });
 
-        // Give server time to start listening
-        tokio::time::sleep(Duration::from_millis(100)).await;
+        let client = PayloadClient::new("127.0.0.1", port).await.unwrap();
+        let received = client
+            .accept_if_trusted(&gate, "phone", &dest_path, 8)
+            .await
+            .unwrap();
+        answer.await.unwrap();
+
+        assert!(!received);
+        assert!(matches!(
+            task.await.unwrap(),
+            Err(ProtocolError::PeerRejected(_))
+        ));
+        assert_eq!(counter.snapshot().payload_sent, 0);
+        assert!(!dest_path.exists());
+    }
 
-        // Connect client and receive file
+    #[tokio::test]
+    async fn test_dismissed_prompt_times_out_to_decline() {
+        let (_source, counter, task, port) =
+            staged_transfer(b"dismissed", DEFAULT_CONFIRMATION_TIMEOUT).await;
+        let (gate, prompts) = trust_gate(Duration::from_millis(100));
+        let (pending, mut ids) = register_prompts(prompts);
+        let dest_dir = tempfile::tempdir().unwrap();
+        let dest_path = dest_dir.path().join("dismissed.bin");
+
+        // The notification is dismissed: nobody answers
         let client = PayloadClient::new("127.0.0.1", port).await.unwrap();
-        client
-            .receive_file(&dest_path, test_data.len() as u64)
+        let received = client
+            .accept_if_trusted(&gate, "phone", &dest_path, 9)
             .await
             .unwrap();
 
-        // Wait for server to complete
-        server_task.await.unwrap().unwrap();
+        assert!(!received);
+        assert!(matches!(
+            task.await.unwrap(),
+            Err(ProtocolError::PeerRejected(_))
+        ));
+        assert_eq!(counter.snapshot().payload_sent, 0);
+
+        // A late answer finds the prompt expired
+        let prompt_id = ids.recv().await.unwrap();
+        assert!(!pending.contains(&prompt_id));
+        assert!(!pending.accept(&prompt_id));
+        assert!(pending.is_empty());
+    }
 
-        // Verify file contents
-        let received_data = tokio::fs::read(&dest_path).await.unwrap();
-        assert_eq!(&received_data[..], test_data);
+    #[tokio::test]
+    async fn test_size_limit_accepts_file_at_limit() {
+        let data = b"exactly at the limit";
+        let (_source, _counter, task, port) =
+            staged_transfer(data, DEFAULT_CONFIRMATION_TIMEOUT).await;
+        let dest_dir = tempfile::tempdir().unwrap();
+        let dest_path = dest_dir.path().join("received.bin");
+
+        let limits = FileSizeLimits::new(Some(data.len() as u64));
+        let client = PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .with_size_limit(limits.limit_for("phone"));
+        client.accept(&dest_path, data.len() as u64).await.unwrap();
+        task.await.unwrap().unwrap();
+
+        assert_eq!(tokio::fs::read(&dest_path).await.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_size_limit_rejects_oversized_file_before_writing() {
+        let data = b"one byte too many";
+        let (_source, counter, task, port) =
+            staged_transfer(data, DEFAULT_CONFIRMATION_TIMEOUT).await;
+        let dest_dir = tempfile::tempdir().unwrap();
+        let dest_path = dest_dir.path().join("received.bin");
+
+        let client = PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .with_size_limit(Some(data.len() as u64 - 1));
+        let result = client.accept(&dest_path, data.len() as u64).await;
+        assert!(matches!(result, Err(ProtocolError::ResourceExhausted(_))));
+        assert!(!dest_path.exists());
+
+        // The sender sees the refusal as a declined transfer
+        let sent = task.await.unwrap();
+        assert!(matches!(sent, Err(ProtocolError::PeerRejected(_))));
+        assert_eq!(counter.snapshot().payload_sent, 0);
+
+        // Unstaged transfers are refused the same way
+        let server = PayloadServer::new().await.unwrap();
+        let client = PayloadClient::new("127.0.0.1", server.port())
+            .await
+            .unwrap()
+            .with_size_limit(Some(1));
+        let result = client.receive_file(&dest_path, 2).await;
+        assert!(matches!(result, Err(ProtocolError::ResourceExhausted(_))));
+        assert!(!dest_path.exists());
+    }
+
+    #[test]
+    fn test_device_size_limit_overrides_default() {
+        let limits = FileSizeLimits::new(Some(1024))
+            .with_device_limit("laptop", Some(4096))
+            .with_device_limit("trusted", None);
+
+        assert_eq!(limits.limit_for("phone"), Some(1024));
+        assert_eq!(limits.limit_for("laptop"), Some(4096));
+        assert_eq!(limits.limit_for("trusted"), None);
+
+        assert!(limits.check("phone", 2048).is_err());
+        assert!(limits.check("laptop", 2048).is_ok());
+        assert!(limits.check("trusted", u64::MAX).is_ok());
+
+        let mut limits = limits;
+        limits.clear_device_limit("laptop");
+        assert!(limits.check("laptop", 2048).is_err());
+        assert_eq!(FileSizeLimits::default().limit_for("phone"), None);
     }
 
     #[tokio::test]
@@ -1124,6 +4185,8 @@ mod tests {
             path: "/tmp/test.txt".to_string(),
             creation_time: Some(1640000000000),
             last_modified: Some(1640000000000),
+            metadata: Default::default(),
+            user: None,
         };
 
         let share_info: crate::plugins::share::FileShareInfo = transfer_info.into();
@@ -1163,4 +4226,49 @@ mod tests {
         let result = server_task.await.unwrap();
         assert!(result.is_err());
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sandbox_declines_symlink_escape() {
+        let data = b"should never leave the inbox";
+        let (_source, counter, task, port) =
+            staged_transfer(data, DEFAULT_CONFIRMATION_TIMEOUT).await;
+        let dir = tempfile::tempdir().unwrap();
+        let outside = dir.path().join("outside");
+        std::fs::create_dir(&outside).unwrap();
+        let sandbox = TransferSandbox::new(dir.path().join("inbox")).unwrap();
+        std::os::unix::fs::symlink(&outside, sandbox.base().join("escape")).unwrap();
+
+        let client = PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .with_sandbox(sandbox.clone());
+        let result = client
+            .accept(
+                sandbox.base().join("escape/received.bin"),
+                data.len() as u64,
+            )
+            .await;
+        assert!(matches!(result, Err(ProtocolError::PermissionDenied(_))));
+        assert!(!outside.join("received.bin").exists());
+        assert!(matches!(
+            task.await.unwrap(),
+            Err(ProtocolError::PeerRejected(_))
+        ));
+        assert_eq!(counter.snapshot().payload_sent, 0);
+
+        // A nested path inside the sandbox is received normally
+        let (_source, _counter, task, port) =
+            staged_transfer(data, DEFAULT_CONFIRMATION_TIMEOUT).await;
+        PayloadClient::new("127.0.0.1", port)
+            .await
+            .unwrap()
+            .with_sandbox(sandbox.clone())
+            .accept("albums/2026/received.bin", data.len() as u64)
+            .await
+            .unwrap();
+        task.await.unwrap().unwrap();
+        let received = sandbox.base().join("albums/2026/received.bin");
+        assert_eq!(tokio::fs::read(received).await.unwrap(), data);
+    }
 }
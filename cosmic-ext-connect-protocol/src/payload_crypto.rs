@@ -0,0 +1,508 @@
+//! End-to-End Payload Encryption
+//!
+//! Payload transfers are protected by TLS, but only up to whoever terminates
+//! the TLS connection. When a transfer passes through a relay, the relay sees
+//! the file in plaintext. Payload encryption seals every chunk with a key only
+//! the two paired devices know, before it reaches the transport, so a relay
+//! only ever handles ciphertext.
+//!
+//! ## Keys
+//!
+//! During pairing both devices add an ephemeral X25519 public key to their
+//! `cconnect.pair` packet (see [`PAYLOAD_KEY_FIELD`]). Once pairing completes,
+//! each side derives the same [`PayloadKey`] from the shared secret and both
+//! certificate fingerprints and stores it with the paired certificate. Peers
+//! that pair without the field (older clients) have no payload key.
+//!
+//! ## Transfers
+//!
+//! Each transfer derives its own [`PayloadCipher`] from the payload key and a
+//! random salt the sender advertises in the share packet (see
+//! [`PAYLOAD_ENCRYPTION_FIELD`]). Chunks are sealed with AES-256-GCM and
+//! framed on the wire as a 4-byte big-endian length followed by the sealed
+//! chunk. Chunks carry a counter in their nonce, so dropped, replayed,
+//! reordered or modified chunks fail to open.
+//!
+//! ## Policy
+//!
+//! [`PayloadEncryption`] decides when to encrypt. The default encrypts every
+//! transfer to a peer that agreed a payload key at pairing, and relies on TLS
+//! alone for peers that didn't (older clients). The stricter policies refuse
+//! a transfer to a peer without a payload key rather than send it in
+//! plaintext.
+//!
+//! ```rust,ignore
+//! if config.encryption.policy.should_encrypt(TransferPath::Relayed, key.is_some())? {
+//!     let cipher = PayloadCipher::generate(&key.unwrap())?;
+//!     share_packet.body[PAYLOAD_ENCRYPTION_FIELD] = cipher.describe();
+//!     server = server.with_encryption(cipher);
+//! }
+//! ```
+
+use crate::{Packet, ProtocolError, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::agreement::{self, EphemeralPrivateKey, UnparsedPublicKey, X25519};
+use ring::hkdf::{Salt, HKDF_SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Pairing packet field carrying the ephemeral public key (base64)
+pub const PAYLOAD_KEY_FIELD: &str = "payloadKey";
+
+/// Share packet field describing an encrypted payload
+pub const PAYLOAD_ENCRYPTION_FIELD: &str = "payloadEncryption";
+
+/// Cipher suite advertised in [`PAYLOAD_ENCRYPTION_FIELD`]
+pub const CIPHER_SUITE: &str = "aes-256-gcm";
+
+/// Largest sealed chunk accepted from the wire (16 MB)
+pub const MAX_SEALED_CHUNK: usize = 16 * 1024 * 1024;
+
+/// Bytes a sealed chunk adds to its plaintext
+pub const TAG_LEN: usize = 16;
+
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const PAIRING_INFO: &[u8] = b"cconnect payload key";
+const TRANSFER_INFO: &[u8] = b"cconnect payload transfer";
+
+/// When payloads are encrypted end to end
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadEncryption {
+    /// Never; TLS only
+    Off,
+    /// Every transfer to a peer with a payload key; TLS only for others
+    #[default]
+    WhenSupported,
+    /// Only transfers passing through a relay
+    RelayOnly,
+    /// Every transfer
+    Always,
+}
+
+/// How a transfer reaches the peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferPath {
+    /// Straight to the peer, e.g. on the LAN
+    Direct,
+    /// Through a relay that terminates TLS
+    Relayed,
+}
+
+impl PayloadEncryption {
+    /// Whether a transfer over `path` is encrypted
+    ///
+    /// `peer_has_key` tells whether a payload key was established with the
+    /// peer at pairing.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::PermissionDenied`] if the policy requires
+    /// encryption but the peer has no payload key.
+    pub fn should_encrypt(self, path: TransferPath, peer_has_key: bool) -> Result<bool> {
+        let required = match self {
+            Self::Off => false,
+            Self::WhenSupported => return Ok(peer_has_key),
+            Self::RelayOnly => path == TransferPath::Relayed,
+            Self::Always => true,
+        };
+        if required && !peer_has_key {
+            return Err(ProtocolError::PermissionDenied(format!(
+                "payload encryption is required for {:?} transfers but the peer has no payload key",
+                path
+            )));
+        }
+        Ok(required)
+    }
+}
+
+/// Payload encryption settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PayloadEncryptionConfig {
+    /// When to encrypt payloads end to end
+    pub policy: PayloadEncryption,
+}
+
+/// Key shared with one paired device for payload encryption
+#[derive(Clone, PartialEq, Eq)]
+pub struct PayloadKey([u8; KEY_LEN]);
+
+impl PayloadKey {
+    /// Encode for storage
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(self.0)
+    }
+
+    /// Decode a stored key
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidPacket`] if `encoded` is not a key.
+    pub fn from_base64(encoded: &str) -> Result<Self> {
+        let bytes = BASE64
+            .decode(encoded.trim())
+            .map_err(|e| ProtocolError::InvalidPacket(format!("Invalid payload key: {}", e)))?;
+        let key = <[u8; KEY_LEN]>::try_from(bytes.as_slice()).map_err(|_| {
+            ProtocolError::InvalidPacket(format!("Payload key must be {} bytes", KEY_LEN))
+        })?;
+        Ok(Self(key))
+    }
+}
+
+impl fmt::Debug for PayloadKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PayloadKey(..)")
+    }
+}
+
+/// Our half of the payload key agreement during pairing
+pub struct PayloadKeyExchange {
+    private_key: EphemeralPrivateKey,
+    public_key: String,
+}
+
+impl PayloadKeyExchange {
+    /// Generate a fresh ephemeral key pair
+    pub fn new() -> Result<Self> {
+        let rng = SystemRandom::new();
+        let private_key = EphemeralPrivateKey::generate(&X25519, &rng)
+            .map_err(|_| ProtocolError::InvalidState("failed to generate payload key".into()))?;
+        let public_key = private_key
+            .compute_public_key()
+            .map_err(|_| ProtocolError::InvalidState("failed to derive payload key".into()))?;
+        Ok(Self {
+            public_key: BASE64.encode(public_key.as_ref()),
+            private_key,
+        })
+    }
+
+    /// Public key to send to the peer (base64)
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    /// Derive the payload key from the peer's public key
+    ///
+    /// Both fingerprints bind the key to the certificates that were paired;
+    /// their order does not matter.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidPacket`] if the peer's key is invalid.
+    pub fn complete(
+        self,
+        peer_public_key: &str,
+        our_fingerprint: &str,
+        their_fingerprint: &str,
+    ) -> Result<PayloadKey> {
+        let peer_public_key = BASE64.decode(peer_public_key.trim()).map_err(|e| {
+            ProtocolError::InvalidPacket(format!("Invalid peer payload key: {}", e))
+        })?;
+        let mut fingerprints = [our_fingerprint, their_fingerprint];
+        fingerprints.sort_unstable();
+        let salt = fingerprints.concat();
+
+        agreement::agree_ephemeral(
+            self.private_key,
+            &UnparsedPublicKey::new(&X25519, &peer_public_key),
+            |shared_secret| derive_key(salt.as_bytes(), shared_secret, PAIRING_INFO),
+        )
+        .map_err(|_| ProtocolError::InvalidPacket("Peer payload key was rejected".to_string()))?
+        .map(PayloadKey)
+    }
+}
+
+/// HKDF-SHA256 of `secret` into a 32-byte key
+fn derive_key(salt: &[u8], secret: &[u8], info: &[u8]) -> Result<[u8; KEY_LEN]> {
+    let mut key = [0u8; KEY_LEN];
+    Salt::new(HKDF_SHA256, salt)
+        .extract(secret)
+        .expand(&[info], HKDF_SHA256)
+        .and_then(|okm| okm.fill(&mut key))
+        .map_err(|_| ProtocolError::InvalidState("payload key derivation failed".to_string()))?;
+    Ok(key)
+}
+
+/// Seals or opens the chunks of one transfer
+pub struct PayloadCipher {
+    key: LessSafeKey,
+    salt: [u8; SALT_LEN],
+    counter: u64,
+}
+
+impl PayloadCipher {
+    /// Cipher for a transfer identified by `salt`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidState`] if the key cannot be derived.
+    pub fn new(key: &PayloadKey, salt: [u8; SALT_LEN]) -> Result<Self> {
+        let transfer_key = derive_key(&salt, &key.0, TRANSFER_INFO)?;
+        let key = UnboundKey::new(&AES_256_GCM, &transfer_key)
+            .map_err(|_| ProtocolError::InvalidState("invalid payload cipher key".to_string()))?;
+        Ok(Self {
+            key: LessSafeKey::new(key),
+            salt,
+            counter: 0,
+        })
+    }
+
+    /// Cipher for a new outgoing transfer with a random salt
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidState`] if no randomness is available.
+    pub fn generate(key: &PayloadKey) -> Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| ProtocolError::InvalidState("no randomness for payload salt".into()))?;
+        Self::new(key, salt)
+    }
+
+    /// Cipher for an incoming transfer described by `packet`
+    ///
+    /// Returns `None` if the payload is not encrypted.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidPacket`] for an unknown cipher suite or
+    /// malformed description, and [`ProtocolError::PermissionDenied`] if the
+    /// payload is encrypted but there is no key for the sender.
+    pub fn from_packet(packet: &Packet, key: Option<&PayloadKey>) -> Result<Option<Self>> {
+        let Some(description) = packet.body.get(PAYLOAD_ENCRYPTION_FIELD) else {
+            return Ok(None);
+        };
+        let suite = description.get("cipher").and_then(|v| v.as_str());
+        if suite != Some(CIPHER_SUITE) {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Unsupported payload cipher {:?}",
+                suite
+            )));
+        }
+        let salt = description
+            .get("salt")
+            .and_then(|v| v.as_str())
+            .and_then(|salt| BASE64.decode(salt).ok())
+            .and_then(|salt| <[u8; SALT_LEN]>::try_from(salt.as_slice()).ok())
+            .ok_or_else(|| {
+                ProtocolError::InvalidPacket("Invalid payload encryption salt".to_string())
+            })?;
+        let key = key.ok_or_else(|| {
+            ProtocolError::PermissionDenied(
+                "payload is encrypted but no payload key is shared with the sender".to_string(),
+            )
+        })?;
+        Self::new(key, salt).map(Some)
+    }
+
+    /// Value for [`PAYLOAD_ENCRYPTION_FIELD`] in the share packet
+    pub fn describe(&self) -> serde_json::Value {
+        json!({
+            "cipher": CIPHER_SUITE,
+            "salt": BASE64.encode(self.salt),
+        })
+    }
+
+    fn next_nonce(&mut self) -> Result<Nonce> {
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce[NONCE_LEN - 8..].copy_from_slice(&self.counter.to_be_bytes());
+        self.counter = self.counter.checked_add(1).ok_or_else(|| {
+            ProtocolError::ResourceExhausted("payload chunk counter exhausted".to_string())
+        })?;
+        Ok(Nonce::assume_unique_for_key(nonce))
+    }
+
+    /// Seal the next chunk
+    pub fn seal(&mut self, chunk: &[u8]) -> Result<Vec<u8>> {
+        let nonce = self.next_nonce()?;
+        let mut sealed = Vec::with_capacity(chunk.len() + TAG_LEN);
+        sealed.extend_from_slice(chunk);
+        self.key
+            .seal_in_place_append_tag(nonce, Aad::empty(), &mut sealed)
+            .map_err(|_| ProtocolError::InvalidState("failed to seal payload chunk".into()))?;
+        Ok(sealed)
+    }
+
+    /// Open the next chunk
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::PayloadDecryption`] if the chunk was
+    /// modified, reordered or sealed with another key.
+    pub fn open(&mut self, mut sealed: Vec<u8>) -> Result<Vec<u8>> {
+        let chunk = self.counter;
+        let nonce = self.next_nonce()?;
+        let len = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut sealed)
+            .map_err(|_| {
+                ProtocolError::PayloadDecryption(format!(
+                    "payload chunk {} failed authentication",
+                    chunk
+                ))
+            })?
+            .len();
+        sealed.truncate(len);
+        Ok(sealed)
+    }
+}
+
+/// Seal `chunk` and write it as one frame
+pub async fn write_sealed<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    cipher: &mut PayloadCipher,
+    chunk: &[u8],
+) -> Result<usize> {
+    let sealed = cipher.seal(chunk)?;
+    writer.write_u32(sealed.len() as u32).await?;
+    writer.write_all(&sealed).await?;
+    Ok(4 + sealed.len())
+}
+
+/// Read one frame and open it
+///
+/// Returns `None` at a clean end of stream between frames.
+pub async fn read_sealed<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    cipher: &mut PayloadCipher,
+) -> Result<Option<Vec<u8>>> {
+    let len = match reader.read_u32().await {
+        Ok(len) => len as usize,
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    if !(TAG_LEN..=MAX_SEALED_CHUNK).contains(&len) {
+        return Err(ProtocolError::InvalidPacket(format!(
+            "sealed payload chunk of {} bytes is out of range",
+            len
+        )));
+    }
+    let mut sealed = vec![0u8; len];
+    reader.read_exact(&mut sealed).await?;
+    cipher.open(sealed).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run a pairing key agreement and return both sides' keys
+    fn paired_keys() -> (PayloadKey, PayloadKey) {
+        let desktop = PayloadKeyExchange::new().unwrap();
+        let phone = PayloadKeyExchange::new().unwrap();
+        let desktop_public = desktop.public_key().to_string();
+        let phone_public = phone.public_key().to_string();
+        (
+            desktop.complete(&phone_public, "AA:AA", "BB:BB").unwrap(),
+            phone.complete(&desktop_public, "BB:BB", "AA:AA").unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_payload_encrypted_on_the_wire() {
+        let (sender_key, receiver_key) = paired_keys();
+        assert_eq!(sender_key, receiver_key);
+
+        let plaintext = b"quarterly report, do not forward".repeat(64);
+        let mut sender = PayloadCipher::generate(&sender_key).unwrap();
+        let share = Packet::new(
+            "cconnect.share.request",
+            json!({"filename": "report.pdf", PAYLOAD_ENCRYPTION_FIELD: sender.describe()}),
+        );
+
+        let mut wire = Vec::new();
+        for chunk in plaintext.chunks(500) {
+            write_sealed(&mut wire, &mut sender, chunk).await.unwrap();
+        }
+        assert!(!wire
+            .windows(b"quarterly".len())
+            .any(|window| window == b"quarterly"));
+
+        let mut receiver = PayloadCipher::from_packet(&share, Some(&receiver_key))
+            .unwrap()
+            .unwrap();
+        let mut reader = wire.as_slice();
+        let mut received = Vec::new();
+        while let Some(chunk) = read_sealed(&mut reader, &mut receiver).await.unwrap() {
+            received.extend(chunk);
+        }
+        assert_eq!(received, plaintext);
+    }
+
+    #[tokio::test]
+    async fn test_tampering_detected() {
+        let (key, _) = paired_keys();
+        let mut sender = PayloadCipher::generate(&key).unwrap();
+        let salt = sender.salt;
+
+        let mut wire = Vec::new();
+        write_sealed(&mut wire, &mut sender, b"first")
+            .await
+            .unwrap();
+        write_sealed(&mut wire, &mut sender, b"second")
+            .await
+            .unwrap();
+
+        // A flipped bit fails authentication
+        let mut tampered = wire.clone();
+        tampered[6] ^= 0x01;
+        let mut receiver = PayloadCipher::new(&key, salt).unwrap();
+        assert!(matches!(
+            read_sealed(&mut tampered.as_slice(), &mut receiver).await,
+            Err(ProtocolError::PayloadDecryption(_))
+        ));
+
+        // So does a chunk replayed out of order
+        let first_frame = 4 + b"first".len() + TAG_LEN;
+        let mut receiver = PayloadCipher::new(&key, salt).unwrap();
+        assert!(read_sealed(&mut &wire[first_frame..], &mut receiver)
+            .await
+            .is_err());
+
+        // And a key from another pairing
+        let (other_key, _) = paired_keys();
+        let mut stranger = PayloadCipher::new(&other_key, salt).unwrap();
+        assert!(read_sealed(&mut wire.as_slice(), &mut stranger)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_policy() {
+        use PayloadEncryption::*;
+        use TransferPath::*;
+
+        assert!(!RelayOnly.should_encrypt(Direct, true).unwrap());
+        assert!(!RelayOnly.should_encrypt(Direct, false).unwrap());
+        assert!(RelayOnly.should_encrypt(Relayed, true).unwrap());
+        assert!(matches!(
+            RelayOnly.should_encrypt(Relayed, false),
+            Err(ProtocolError::PermissionDenied(_))
+        ));
+        assert!(Always.should_encrypt(Direct, true).unwrap());
+        assert!(!Off.should_encrypt(Relayed, false).unwrap());
+
+        // The default applies to the direct transfers that exist today
+        assert_eq!(PayloadEncryption::default(), WhenSupported);
+        assert!(WhenSupported.should_encrypt(Direct, true).unwrap());
+        assert!(!WhenSupported.should_encrypt(Direct, false).unwrap());
+        assert!(!WhenSupported.should_encrypt(Relayed, false).unwrap());
+    }
+
+    #[test]
+    fn test_unencrypted_packet_has_no_cipher() {
+        let plain = Packet::new("cconnect.share.request", json!({"filename": "a.txt"}));
+        assert!(PayloadCipher::from_packet(&plain, None).unwrap().is_none());
+
+        let key = PayloadKey([7; KEY_LEN]);
+        assert_eq!(PayloadKey::from_base64(&key.to_base64()).unwrap(), key);
+    }
+}
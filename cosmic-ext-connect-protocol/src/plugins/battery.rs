@@ -92,7 +92,7 @@
 //!
 //! - [Valent Protocol - Battery](https://valent.andyholmes.ca/documentation/protocol.html)
 
-use crate::{Device, Packet, Result};
+use crate::{Device, FieldType, Packet, PacketType, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -484,6 +484,16 @@ impl PluginFactory for BatteryPluginFactory {
         ]
     }
 
+    fn packet_types(&self) -> Vec<PacketType> {
+        vec![
+            PacketType::new("cconnect.battery")
+                .required("currentCharge", FieldType::Integer)
+                .required("isCharging", FieldType::Bool)
+                .optional("thresholdEvent", FieldType::Integer),
+            PacketType::new("cconnect.battery.request").optional("request", FieldType::Bool),
+        ]
+    }
+
     fn create(&self) -> Box<dyn Plugin> {
         Box::new(BatteryPlugin::new())
     }
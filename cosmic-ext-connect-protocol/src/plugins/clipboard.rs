@@ -41,6 +41,30 @@
 //! }
 //! ```
 //!
+//! ## Large and Binary Content
+//!
+//! Only text up to a configurable length (64 KiB by default, see
+//! [`ClipboardPlugin::with_max_inline_length`]) travels inside the packet.
+//! Longer text and any non-text content are sent as a payload instead, with
+//! the MIME type in the body and no `content` field:
+//!
+//! ```json
+//! {
+//!     "id": 1234567890,
+//!     "type": "cconnect.clipboard",
+//!     "body": {
+//!         "mimeType": "image/png"
+//!     },
+//!     "payloadSize": 524288,
+//!     "payloadTransferInfo": { "port": 1739 }
+//! }
+//! ```
+//!
+//! Payload bytes are kept exactly as sent, so binary content survives the
+//! round trip unchanged. Received text is applied as a normal update; other
+//! content is written to the system clipboard under its MIME type and kept
+//! in [`ClipboardPlugin::get_binary_content`].
+//!
 //! ## Sync Loop Prevention
 //!
 //! To prevent devices from endlessly updating each other's clipboards:
@@ -103,6 +127,7 @@ use crate::{Device, Packet, Result};
 use async_trait::async_trait;
 use chrono::Utc;
 use serde_json::json;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::RwLock;
@@ -111,6 +136,78 @@ use tracing::{debug, info, warn};
 use super::clipboard_backend::ClipboardBackend;
 use super::{Plugin, PluginFactory};
 
+/// Longest text sent inside a clipboard packet by default (64 KiB)
+pub const DEFAULT_MAX_INLINE_LENGTH: usize = 64 * 1024;
+
+/// Largest clipboard payload that will be downloaded (64 MiB)
+pub const MAX_PAYLOAD_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Clipboard packet field naming the MIME type of payload content
+pub const MIME_TYPE_FIELD: &str = "mimeType";
+
+/// MIME type assumed for payload content without one
+const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+/// Clipboard content of any type, as raw bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardContent {
+    /// MIME type, e.g. `text/plain` or `image/png`
+    pub mime_type: String,
+
+    /// Content bytes, unchanged
+    pub data: Vec<u8>,
+}
+
+impl ClipboardContent {
+    /// Content of type `mime_type`
+    pub fn new(mime_type: impl Into<String>, data: Vec<u8>) -> Self {
+        Self {
+            mime_type: mime_type.into(),
+            data,
+        }
+    }
+
+    /// Plain text content
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::new("text/plain", text.into().into_bytes())
+    }
+
+    /// The content as text, if it is a text type holding valid UTF-8
+    pub fn as_text(&self) -> Option<&str> {
+        if !self.mime_type.starts_with("text/") {
+            return None;
+        }
+        std::str::from_utf8(&self.data).ok()
+    }
+
+    /// Size of the content in bytes
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Whether the content is empty
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+/// How clipboard content goes to a device
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClipboardTransfer {
+    /// The content fits in the packet
+    Inline(Packet),
+
+    /// The content follows as a payload
+    ///
+    /// The packet has no port yet; see [`ClipboardPlugin::serve_payload`].
+    Payload {
+        /// Packet announcing the payload
+        packet: Packet,
+        /// Content to serve
+        content: ClipboardContent,
+    },
+}
+
 /// Clipboard state with content and timestamp
 ///
 /// Tracks the current clipboard content and when it was last modified.
@@ -241,6 +338,7 @@ impl Default for ClipboardState {
 /// - Timestamp-based sync loop prevention
 /// - Device connection sync
 /// - UTF-8 text content support
+/// - Oversized and binary content sent as payloads
 /// - Thread-safe state management
 /// - System clipboard integration (Wayland/X11)
 ///
@@ -268,6 +366,18 @@ pub struct ClipboardPlugin {
 
     /// Packet sender for proactive updates
     packet_sender: Option<Sender<(String, Packet)>>,
+
+    /// Longest text sent inside a packet, in bytes
+    max_inline_length: usize,
+
+    /// Last non-text content sent or received
+    binary: Arc<RwLock<Option<ClipboardContent>>>,
+
+    /// TLS configuration for payload transfers (`None` = plain TCP)
+    tls_config: Option<Arc<crate::TlsConfig>>,
+
+    /// Counts received payload bytes in the device's traffic totals
+    traffic_counter: Option<crate::TrafficCounter>,
 }
 
 impl ClipboardPlugin {
@@ -289,6 +399,109 @@ impl ClipboardPlugin {
             state: Arc::new(RwLock::new(ClipboardState::empty())),
             backend: ClipboardBackend::new(),
             packet_sender: None,
+            max_inline_length: DEFAULT_MAX_INLINE_LENGTH,
+            binary: Arc::new(RwLock::new(None)),
+            tls_config: None,
+            traffic_counter: None,
+        }
+    }
+
+    /// Send text longer than `length` bytes as a payload
+    pub fn with_max_inline_length(mut self, length: usize) -> Self {
+        self.max_inline_length = length;
+        self
+    }
+
+    /// Send text longer than `length` bytes as a payload
+    pub fn set_max_inline_length(&mut self, length: usize) {
+        self.max_inline_length = length;
+    }
+
+    /// Longest text sent inside a packet, in bytes
+    pub fn max_inline_length(&self) -> usize {
+        self.max_inline_length
+    }
+
+    /// Set TLS configuration for payload transfers
+    ///
+    /// Without one, payloads are sent and received over plain TCP.
+    pub fn set_tls_config(&mut self, config: Arc<crate::TlsConfig>) {
+        self.tls_config = Some(config);
+    }
+
+    /// Count received payload bytes in the device's traffic totals
+    ///
+    /// See [`ConnectionManager::traffic_counter`](crate::ConnectionManager::traffic_counter).
+    pub fn set_traffic_counter(&mut self, counter: crate::TrafficCounter) {
+        self.traffic_counter = Some(counter);
+    }
+
+    /// Decide how `content` is sent and record it as the local clipboard
+    ///
+    /// Text within [`max_inline_length`](Self::max_inline_length) becomes a
+    /// regular update packet. Anything longer or not text becomes a
+    /// [`ClipboardTransfer::Payload`].
+    pub async fn prepare_content(&self, content: ClipboardContent) -> ClipboardTransfer {
+        if let Some(text) = content.as_text() {
+            if text.len() <= self.max_inline_length {
+                let packet = self.create_clipboard_packet(text.to_string()).await;
+                return ClipboardTransfer::Inline(packet);
+            }
+        }
+
+        self.record_content(content.clone()).await;
+        let packet = Packet::new(
+            "cconnect.clipboard",
+            json!({ MIME_TYPE_FIELD: content.mime_type }),
+        )
+        .with_payload_size(content.len() as i64);
+        ClipboardTransfer::Payload { packet, content }
+    }
+
+    /// Serve `content` to the device and add the server's port to `packet`
+    ///
+    /// The server waits in the background for the device to connect. It uses
+    /// TLS if a configuration was set with [`set_tls_config`](Self::set_tls_config).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no payload server can be started.
+    pub async fn serve_payload(&self, packet: Packet, content: ClipboardContent) -> Result<Packet> {
+        let port = match &self.tls_config {
+            Some(config) => {
+                let server = crate::TlsPayloadServer::new(Arc::clone(config)).await?;
+                let port = server.port();
+                tokio::spawn(async move {
+                    if let Err(e) = server.send_bytes(&content.data).await {
+                        warn!("Failed to send clipboard payload: {}", e);
+                    }
+                });
+                port
+            }
+            None => {
+                let server = crate::PayloadServer::new().await?;
+                let port = server.port();
+                tokio::spawn(async move {
+                    if let Err(e) = server.send_bytes(&content.data).await {
+                        warn!("Failed to send clipboard payload: {}", e);
+                    }
+                });
+                port
+            }
+        };
+        Ok(packet.with_payload_transfer_info(HashMap::from([("port".to_string(), json!(port))])))
+    }
+
+    /// Last non-text content sent or received, if any
+    pub async fn get_binary_content(&self) -> Option<ClipboardContent> {
+        self.binary.read().await.clone()
+    }
+
+    /// Record content as the current clipboard
+    async fn record_content(&self, content: ClipboardContent) {
+        match content.as_text() {
+            Some(text) => self.set_content(text.to_string()).await,
+            None => *self.binary.write().await = Some(content),
         }
     }
 
@@ -488,6 +701,99 @@ impl ClipboardPlugin {
         );
     }
 
+    /// Handle incoming clipboard content sent as a payload
+    ///
+    /// Downloads the payload into memory and applies it like a standard
+    /// update, keeping the bytes and MIME type as sent.
+    async fn handle_clipboard_payload(&mut self, packet: &Packet, device: &Device) {
+        let mime_type = packet
+            .body
+            .get(MIME_TYPE_FIELD)
+            .and_then(|v| v.as_str())
+            .unwrap_or(DEFAULT_MIME_TYPE);
+        let size = packet.payload_size.unwrap_or(0).max(0) as u64;
+        let port = packet
+            .payload_transfer_info
+            .as_ref()
+            .and_then(|info| info.get("port"))
+            .and_then(|v| v.as_u64())
+            .and_then(|p| u16::try_from(p).ok());
+        let (Some(host), Some(port)) = (device.host.as_deref(), port) else {
+            warn!(
+                "Cannot download clipboard payload from {} ({}): no address",
+                device.name(),
+                device.id()
+            );
+            return;
+        };
+        if size == 0 {
+            debug!("Ignoring empty clipboard payload from {}", device.id());
+            return;
+        }
+
+        let data = match self.download_payload(host, port, size).await {
+            Ok(data) => data,
+            Err(e) => {
+                warn!(
+                    "Failed to download clipboard payload from {} ({}): {}",
+                    device.name(),
+                    device.id(),
+                    e
+                );
+                return;
+            }
+        };
+
+        info!(
+            "Received clipboard payload from {} ({}): {} bytes of {}",
+            device.name(),
+            device.id(),
+            data.len(),
+            mime_type
+        );
+        let content = ClipboardContent::new(mime_type, data);
+        if !self
+            .backend
+            .write_bytes(&content.mime_type, &content.data)
+            .await
+        {
+            warn!(
+                "Failed to write clipboard content from {} ({}) to system clipboard",
+                device.name(),
+                device.id()
+            );
+        }
+        self.record_content(content).await;
+    }
+
+    /// Download `size` payload bytes from `host:port` into memory
+    async fn download_payload(&self, host: &str, port: u16, size: u64) -> Result<Vec<u8>> {
+        match &self.tls_config {
+            Some(config) => {
+                let client = crate::TlsPayloadClient::new(host, port, config).await?;
+                let client = match &self.traffic_counter {
+                    Some(counter) => client.with_traffic_counter(counter.clone()),
+                    None => client,
+                };
+                client
+                    .with_size_limit(Some(MAX_PAYLOAD_SIZE))
+                    .receive_bytes(size)
+                    .await
+            }
+            None => {
+                let client = crate::PayloadClient::new(host, port).await?;
+                let client = match &self.traffic_counter {
+                    Some(counter) => client.with_traffic_counter(counter.clone()),
+                    None => client,
+                };
+                client
+                    .with_size_limit(Some(MAX_PAYLOAD_SIZE))
+                    .receive_bytes(size)
+                    .await
+            }
+        }
+    }
+
     /// Handle incoming clipboard connect packet
     ///
     /// Processes clipboard sync on device connection.
@@ -593,8 +899,19 @@ impl ClipboardPlugin {
             return false;
         }
 
-        // Create and send packet
-        let packet = self.create_clipboard_packet(content).await;
+        // Create packet, serving oversized content as a payload
+        let packet = match self.prepare_content(ClipboardContent::text(content)).await {
+            ClipboardTransfer::Inline(packet) => packet,
+            ClipboardTransfer::Payload { packet, content } => {
+                match self.serve_payload(packet, content).await {
+                    Ok(packet) => packet,
+                    Err(e) => {
+                        warn!("Failed to serve clipboard payload: {}", e);
+                        return false;
+                    }
+                }
+            }
+        };
         if let Err(e) = packet_sender.send((device_id, packet)).await {
             warn!("Failed to send clipboard packet: {}", e);
             return false;
@@ -690,7 +1007,9 @@ impl Plugin for ClipboardPlugin {
             return Ok(());
         }
 
-        if packet.is_type("cconnect.clipboard") || packet.is_type("kdeconnect.clipboard") {
+        if packet.is_type("cconnect.clipboard") && packet.payload_size.is_some() {
+            self.handle_clipboard_payload(packet, device).await;
+        } else if packet.is_type("cconnect.clipboard") || packet.is_type("kdeconnect.clipboard") {
             self.handle_clipboard_update(packet, device).await;
         } else if packet.is_type("cconnect.clipboard.connect")
             || packet.is_type("kdeconnect.clipboard.connect")
@@ -1093,4 +1412,75 @@ mod tests {
         assert_eq!(state.content, "Current");
         assert_eq!(state.timestamp, 2000);
     }
+
+    /// Started plugin receiving from a device on localhost
+    async fn started_receiver() -> (ClipboardPlugin, Device) {
+        let mut plugin = ClipboardPlugin::new();
+        let mut device = create_test_device();
+        device.host = Some("127.0.0.1".to_string());
+        plugin
+            .init(&device, tokio::sync::mpsc::channel(100).0)
+            .await
+            .unwrap();
+        plugin.start().await.unwrap();
+        (plugin, device)
+    }
+
+    #[tokio::test]
+    async fn test_short_text_is_sent_inline() {
+        let plugin = ClipboardPlugin::new().with_max_inline_length(16);
+
+        let transfer = plugin
+            .prepare_content(ClipboardContent::text("short text"))
+            .await;
+
+        let ClipboardTransfer::Inline(packet) = transfer else {
+            panic!("expected inline transfer, got {:?}", transfer);
+        };
+        assert_eq!(packet.body["content"], "short text");
+        assert!(packet.payload_size.is_none());
+        assert_eq!(plugin.get_content().await, "short text");
+    }
+
+    #[tokio::test]
+    async fn test_oversized_text_is_sent_as_payload() {
+        let sender = ClipboardPlugin::new().with_max_inline_length(16);
+        let text = "a clipboard text longer than the limit";
+
+        let transfer = sender.prepare_content(ClipboardContent::text(text)).await;
+        let ClipboardTransfer::Payload { packet, content } = transfer else {
+            panic!("expected payload transfer, got {:?}", transfer);
+        };
+        assert!(packet.body.get("content").is_none());
+        assert_eq!(packet.body[MIME_TYPE_FIELD], "text/plain");
+        assert_eq!(packet.payload_size, Some(text.len() as i64));
+
+        let packet = sender.serve_payload(packet, content).await.unwrap();
+        let (mut receiver, mut device) = started_receiver().await;
+        receiver.handle_packet(&packet, &mut device).await.unwrap();
+
+        assert_eq!(receiver.get_content().await, text);
+        assert!(receiver.get_binary_content().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_binary_content_round_trips_exactly() {
+        let sender = ClipboardPlugin::new();
+        // Every byte value, including ones that are never valid UTF-8
+        let data: Vec<u8> = (0..=255u8).cycle().take(100_000).collect();
+        let original = ClipboardContent::new("image/png", data);
+
+        let transfer = sender.prepare_content(original.clone()).await;
+        let ClipboardTransfer::Payload { packet, content } = transfer else {
+            panic!("expected payload transfer, got {:?}", transfer);
+        };
+        assert_eq!(packet.body[MIME_TYPE_FIELD], "image/png");
+        assert_eq!(sender.get_binary_content().await, Some(original.clone()));
+
+        let packet = sender.serve_payload(packet, content).await.unwrap();
+        let (mut receiver, mut device) = started_receiver().await;
+        receiver.handle_packet(&packet, &mut device).await.unwrap();
+
+        assert_eq!(receiver.get_binary_content().await, Some(original));
+    }
 }
@@ -105,15 +105,23 @@ impl ClipboardBackend {
     ///
     /// Returns `true` if successful, `false` otherwise.
     pub async fn write(&self, content: &str) -> bool {
+        self.write_bytes("text/plain", content.as_bytes()).await
+    }
+
+    /// Write content of any MIME type to system clipboard
+    ///
+    /// The bytes are passed through unchanged, so binary content such as
+    /// images survives intact. Returns `true` if successful, `false` otherwise.
+    pub async fn write_bytes(&self, mime_type: &str, data: &[u8]) -> bool {
         match self.session_type {
-            SessionType::Wayland => self.write_wayland(content).await,
-            SessionType::X11 => self.write_x11(content).await,
+            SessionType::Wayland => self.write_wayland(mime_type, data).await,
+            SessionType::X11 => self.write_x11(mime_type, data).await,
             SessionType::Unknown => {
                 // Try Wayland first, fall back to X11
-                if self.write_wayland(content).await {
+                if self.write_wayland(mime_type, data).await {
                     return true;
                 }
-                self.write_x11(content).await
+                self.write_x11(mime_type, data).await
             }
         }
     }
@@ -176,10 +184,10 @@ impl ClipboardBackend {
     }
 
     /// Write clipboard using wl-copy (Wayland)
-    async fn write_wayland(&self, content: &str) -> bool {
+    async fn write_wayland(&self, mime_type: &str, data: &[u8]) -> bool {
         let mut child = match Command::new("wl-copy")
             .arg("--type")
-            .arg("text/plain")
+            .arg(mime_type)
             .stdin(Stdio::piped())
             .stdout(Stdio::null())
             .stderr(Stdio::null())
@@ -193,7 +201,7 @@ impl ClipboardBackend {
         };
 
         if let Some(stdin) = child.stdin.as_mut() {
-            if stdin.write_all(content.as_bytes()).await.is_err() {
+            if stdin.write_all(data).await.is_err() {
                 warn!("Failed to write to wl-copy stdin");
                 return false;
             }
@@ -201,7 +209,7 @@ impl ClipboardBackend {
 
         match child.wait().await {
             Ok(status) if status.success() => {
-                debug!("Wrote {} chars to Wayland clipboard", content.len());
+                debug!("Wrote {} bytes to Wayland clipboard", data.len());
                 true
             }
             Ok(status) => {
@@ -216,10 +224,12 @@ impl ClipboardBackend {
     }
 
     /// Write clipboard using xclip (X11)
-    async fn write_x11(&self, content: &str) -> bool {
+    async fn write_x11(&self, mime_type: &str, data: &[u8]) -> bool {
         let mut child = match Command::new("xclip")
             .arg("-selection")
             .arg("clipboard")
+            .arg("-t")
+            .arg(mime_type)
             .stdin(Stdio::piped())
             .stdout(Stdio::null())
             .stderr(Stdio::null())
@@ -233,7 +243,7 @@ impl ClipboardBackend {
         };
 
         if let Some(stdin) = child.stdin.as_mut() {
-            if stdin.write_all(content.as_bytes()).await.is_err() {
+            if stdin.write_all(data).await.is_err() {
                 warn!("Failed to write to xclip stdin");
                 return false;
             }
@@ -241,7 +251,7 @@ impl ClipboardBackend {
 
         match child.wait().await {
             Ok(status) if status.success() => {
-                debug!("Wrote {} chars to X11 clipboard", content.len());
+                debug!("Wrote {} bytes to X11 clipboard", data.len());
                 true
             }
             Ok(status) => {
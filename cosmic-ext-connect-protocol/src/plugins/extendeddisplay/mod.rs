@@ -23,6 +23,25 @@
 //! 6. Touch events arrive via `touch` action packets
 //! 7. Either side sends `stop` to end
 //!
+//! ### Capture Source
+//!
+//! A `request` may carry `source`: `"monitor"` (the default) offers whole
+//! outputs in the portal dialog, `"window"` single application windows.
+//! Without it, [`ExtendedDisplayConfig::capture_source`] applies.
+//!
+//! ### Encoding
+//!
+//! The encoder starts from [`EncoderConfig::preset`] for the configured
+//! network and latency target; bitrate, framerate, keyframe interval and
+//! profile set in [`ExtendedDisplayConfig`] override the preset.
+//!
+//! ### Touch Input
+//!
+//! Touch events are injected by a per-session input task. With
+//! [`ExtendedDisplayConfig::jitter_buffer_ms`] set, events carrying a
+//! `sequence` number are held for that long, reordered and released at the
+//! spacing of their `timestamp`s to smooth out network variance.
+//!
 //! ### Capabilities
 //!
 //! - Incoming: `cconnect.extendeddisplay`, `cconnect.extendeddisplay.request`
@@ -35,13 +54,18 @@ use serde::{Deserialize, Serialize};
 use std::any::Any;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 
 use cosmic_ext_display_stream::{
-    capture::ScreenCapture, EncoderConfig, InputHandler, StreamConfig, StreamingServer,
-    TouchAction, TouchEvent, VideoEncoder, VideoTransform,
+    capture::ScreenCapture, CaptureEvent, DisconnectReason, EncoderConfig, EncoderOverrides,
+    H264Profile, InputHandler, JitterBufferConfig, LatencyTarget, StreamConfig, StreamEvent,
+    StreamingServer, TouchAction, TouchEvent, TransportType, VideoEncoder, DEFAULT_MAX_QUEUE_DEPTH,
 };
 
+pub use cosmic_ext_display_stream::CaptureSource;
+
 /// Plugin name constant
 const PLUGIN_NAME: &str = "extendeddisplay";
 
@@ -54,11 +78,8 @@ const PACKET_TYPE_REQUEST: &str = "cconnect.extendeddisplay.request";
 /// Default WebSocket signaling port
 const DEFAULT_SIGNALING_PORT: u16 = 18080;
 
-/// Default bitrate in bits per second (10 Mbps)
-const DEFAULT_BITRATE_BPS: u32 = 10_000_000;
-
-/// Default framerate
-const DEFAULT_FRAMERATE: u32 = 60;
+/// Longest accepted input jitter buffer delay
+const MAX_JITTER_BUFFER_MS: u64 = 200;
 
 /// Internal packet type emitted when session starts (for D-Bus signal routing)
 const INTERNAL_SESSION_STARTED: &str = "cconnect.internal.extendeddisplay.started";
@@ -73,33 +94,56 @@ pub struct ExtendedDisplayConfig {
     #[serde(default = "default_signaling_port")]
     pub signaling_port: u16,
 
-    /// Target bitrate in bits per second
-    #[serde(default = "default_bitrate_bps")]
-    pub bitrate_bps: u32,
+    /// Network the tablet is reached over, selecting the encoder preset
+    #[serde(default)]
+    pub network: TransportType,
 
-    /// Target framerate
-    #[serde(default = "default_framerate")]
-    pub framerate: u32,
-}
+    /// Latency target, selecting the encoder preset
+    #[serde(default)]
+    pub latency: LatencyTarget,
 
-fn default_signaling_port() -> u16 {
-    DEFAULT_SIGNALING_PORT
-}
+    /// Target bitrate in bits per second (None for the preset's)
+    #[serde(default)]
+    pub bitrate_bps: Option<u32>,
+
+    /// Target framerate (None for the preset's)
+    #[serde(default)]
+    pub framerate: Option<u32>,
+
+    /// Keyframe interval in frames (None for the preset's)
+    #[serde(default)]
+    pub keyframe_interval: Option<u32>,
+
+    /// H.264 profile (None for the preset's)
+    #[serde(default)]
+    pub profile: Option<H264Profile>,
+
+    /// Touch input jitter buffer delay in milliseconds (None injects
+    /// touch events as they arrive)
+    #[serde(default)]
+    pub jitter_buffer_ms: Option<u64>,
 
-fn default_bitrate_bps() -> u32 {
-    DEFAULT_BITRATE_BPS
+    /// Whether the portal offers whole outputs or single windows
+    #[serde(default)]
+    pub capture_source: CaptureSource,
 }
 
-fn default_framerate() -> u32 {
-    DEFAULT_FRAMERATE
+fn default_signaling_port() -> u16 {
+    DEFAULT_SIGNALING_PORT
 }
 
 impl Default for ExtendedDisplayConfig {
     fn default() -> Self {
         Self {
             signaling_port: DEFAULT_SIGNALING_PORT,
-            bitrate_bps: DEFAULT_BITRATE_BPS,
-            framerate: DEFAULT_FRAMERATE,
+            network: TransportType::default(),
+            latency: LatencyTarget::default(),
+            bitrate_bps: None,
+            framerate: None,
+            keyframe_interval: None,
+            profile: None,
+            jitter_buffer_ms: None,
+            capture_source: CaptureSource::default(),
         }
     }
 }
@@ -112,20 +156,58 @@ impl ExtendedDisplayConfig {
                 "Signaling port must be non-zero".to_string(),
             ));
         }
-        if self.bitrate_bps < 500_000 || self.bitrate_bps > 100_000_000 {
-            return Err(ProtocolError::InvalidPacket(format!(
-                "Bitrate {} bps out of range (500 Kbps - 100 Mbps)",
-                self.bitrate_bps
-            )));
+        if let Some(bitrate) = self.bitrate_bps {
+            if !(500_000..=100_000_000).contains(&bitrate) {
+                return Err(ProtocolError::InvalidPacket(format!(
+                    "Bitrate {} bps out of range (500 Kbps - 100 Mbps)",
+                    bitrate
+                )));
+            }
         }
-        if self.framerate < 1 || self.framerate > 120 {
-            return Err(ProtocolError::InvalidPacket(format!(
-                "Framerate {} out of range (1-120)",
-                self.framerate
-            )));
+        if let Some(framerate) = self.framerate {
+            if !(1..=120).contains(&framerate) {
+                return Err(ProtocolError::InvalidPacket(format!(
+                    "Framerate {} out of range (1-120)",
+                    framerate
+                )));
+            }
+        }
+        if self.keyframe_interval == Some(0) {
+            return Err(ProtocolError::InvalidPacket(
+                "Keyframe interval must be non-zero".to_string(),
+            ));
+        }
+        if let Some(delay) = self.jitter_buffer_ms {
+            if delay > MAX_JITTER_BUFFER_MS {
+                return Err(ProtocolError::InvalidPacket(format!(
+                    "Jitter buffer delay {} ms out of range (0-{} ms)",
+                    delay, MAX_JITTER_BUFFER_MS
+                )));
+            }
         }
         Ok(())
     }
+
+    /// Input jitter buffer settings, if enabled
+    pub fn jitter_buffer(&self) -> Option<JitterBufferConfig> {
+        self.jitter_buffer_ms
+            .map(|ms| JitterBufferConfig::default().with_delay(Duration::from_millis(ms)))
+    }
+
+    /// Encoder settings for a `width`x`height` session
+    ///
+    /// Starts from the preset for [`network`](Self::network) and
+    /// [`latency`](Self::latency), then applies the values set explicitly.
+    pub fn encoder_config(&self, width: u32, height: u32) -> EncoderConfig {
+        EncoderConfig::preset(self.network, self.latency)
+            .with_resolution(width, height)
+            .with_overrides(&EncoderOverrides {
+                bitrate: self.bitrate_bps,
+                framerate: self.framerate,
+                keyframe_interval: self.keyframe_interval,
+                profile: self.profile,
+            })
+    }
 }
 
 /// Extended Display Plugin
@@ -152,8 +234,11 @@ pub struct ExtendedDisplayPlugin {
     /// Video encoder (GStreamer pipeline) - moved into capture task on start
     encoder: Option<VideoEncoder>,
 
-    /// Touch input handler (libei/enigo)
-    input_handler: Option<InputHandler>,
+    /// Touch events for the input task (None when injection is unavailable)
+    input_events: Option<mpsc::UnboundedSender<TouchEvent>>,
+
+    /// Task injecting touch input through the session's `InputHandler`
+    input_task: Option<tokio::task::JoinHandle<()>>,
 
     /// Handle to the capture task
     capture_task: Option<tokio::task::JoinHandle<()>>,
@@ -161,6 +246,9 @@ pub struct ExtendedDisplayPlugin {
     /// Configuration for the current/next session
     config: ExtendedDisplayConfig,
 
+    /// Streaming server settings; port and framerate come from `config`
+    stream_config: StreamConfig,
+
     /// Shared flag to signal stop to background tasks
     stop_flag: Arc<AtomicBool>,
 
@@ -181,15 +269,39 @@ impl ExtendedDisplayPlugin {
             session_active: false,
             streaming_server: None,
             encoder: None,
-            input_handler: None,
+            input_events: None,
+            input_task: None,
             capture_task: None,
             config: ExtendedDisplayConfig::default(),
+            stream_config: StreamConfig::default(),
             stop_flag: Arc::new(AtomicBool::new(false)),
             display_resolution: (1920, 1080), // Default resolution
             last_touch_time: None,
         }
     }
 
+    /// Create a plugin instance with the given session configuration
+    pub fn with_config(config: ExtendedDisplayConfig) -> Self {
+        Self {
+            config,
+            ..Self::new()
+        }
+    }
+
+    /// Create a plugin instance streaming with the given server settings
+    ///
+    /// The signaling port is taken from `stream_config`.
+    pub fn with_stream_config(stream_config: StreamConfig) -> Self {
+        Self {
+            config: ExtendedDisplayConfig {
+                signaling_port: stream_config.signaling_port,
+                ..ExtendedDisplayConfig::default()
+            },
+            stream_config,
+            ..Self::new()
+        }
+    }
+
     /// Check if a streaming session is active
     pub fn is_session_active(&self) -> bool {
         self.session_active
@@ -204,6 +316,19 @@ impl ExtendedDisplayPlugin {
         device_id: &str,
         capabilities: &str,
         requested_resolution: Option<(u32, u32)>,
+    ) -> Result<()> {
+        let source = self.config.capture_source;
+        self.start_session_with_source(device_id, capabilities, requested_resolution, source)
+            .await
+    }
+
+    /// Start an extended display session capturing `source`
+    pub async fn start_session_with_source(
+        &mut self,
+        device_id: &str,
+        capabilities: &str,
+        requested_resolution: Option<(u32, u32)>,
+        source: CaptureSource,
     ) -> Result<()> {
         if self.session_active {
             warn!("Extended display session already active, stopping first");
@@ -211,24 +336,24 @@ impl ExtendedDisplayPlugin {
         }
 
         info!(
-            "Starting extended display session for device {} (capabilities: {})",
-            device_id, capabilities
+            "Starting extended display session for device {} (capabilities: {}, source: {:?})",
+            device_id, capabilities, source
         );
 
         self.stop_flag.store(false, Ordering::SeqCst);
 
         // Use requested resolution or default to 1920x1080
         let (display_width, display_height) = requested_resolution.unwrap_or((1920, 1080));
-        let encoder_config = EncoderConfig {
-            width: display_width,
-            height: display_height,
-            framerate: self.config.framerate,
-            bitrate: self.config.bitrate_bps,
-            encoder_type: None,
-            low_latency: true,
-            keyframe_interval: 60,
-            transform: VideoTransform::None,
-        };
+        let encoder_config = self.config.encoder_config(display_width, display_height);
+        debug!(
+            "Encoder preset for {:?}/{:?}: {} bps, {} fps, GOP {}",
+            self.config.network,
+            self.config.latency,
+            encoder_config.bitrate,
+            encoder_config.framerate,
+            encoder_config.keyframe_interval
+        );
+        let framerate = encoder_config.framerate;
 
         // Create encoder (but don't store it yet until all operations succeed)
         let encoder = VideoEncoder::new(encoder_config).map_err(|e| {
@@ -241,8 +366,8 @@ impl ExtendedDisplayPlugin {
         // Create streaming server (encoder not stored yet, so failure is clean)
         let stream_config = StreamConfig {
             signaling_port: self.config.signaling_port,
-            max_clients: 1,
-            ..StreamConfig::default()
+            framerate,
+            ..self.stream_config.clone()
         };
 
         let mut server = StreamingServer::new(stream_config).map_err(|e| {
@@ -257,8 +382,13 @@ impl ExtendedDisplayPlugin {
 
         // Initialize input handler for touch events
         // Use (0,0) offset and encoder resolution as display size
-        let input_handler = InputHandler::new((0, 0), (display_width, display_height));
-        if !input_handler.is_input_available() {
+        let mut input_handler = InputHandler::new((0, 0), (display_width, display_height));
+        if let Some(jitter) = self.config.jitter_buffer() {
+            debug!("Touch input jitter buffer: {:?}", jitter.delay);
+            input_handler = input_handler.with_jitter_buffer(jitter);
+        }
+        let input_available = input_handler.is_input_available();
+        if !input_available {
             warn!("Touch input injection unavailable — touch events will be ignored");
         }
 
@@ -275,36 +405,91 @@ impl ExtendedDisplayPlugin {
         let server_arc = Arc::new(server);
         let server_for_task = server_arc.clone();
         let stop_flag = self.stop_flag.clone();
+        let mut stream_events = server_arc.subscribe_events();
+        let packet_sender = self.packet_sender.clone();
+        let task_device_id = device_id.to_string();
 
         // Spawn background capture task
         let capture_task = tokio::spawn(async move {
             // Create screen capture via portal
             let mut capture = match ScreenCapture::new_any_output("portal").await {
-                Ok(c) => c,
+                Ok(c) => c.with_source(source),
                 Err(e) => {
                     error!("Failed to create screen capture: {}", e);
                     return;
                 }
             };
+            let mut capture_events = capture.subscribe_events();
 
-            // Start capture stream
+            // Start capture stream, dropping stale frames rather than letting
+            // latency build up behind a slow encoder
             let mut frame_stream = match capture.start_capture().await {
-                Ok(fs) => fs,
+                Ok(fs) => fs.with_max_queue_depth(Some(DEFAULT_MAX_QUEUE_DEPTH)),
                 Err(e) => {
                     error!("Failed to start capture: {}", e);
                     return;
                 }
             };
 
+            if let Some(title) = capture
+                .get_output_info()
+                .and_then(|info| info.window_title.as_deref())
+            {
+                info!("Capturing window '{}'", title);
+            }
             info!("Screen capture started, beginning frame encode loop");
 
             // Move encoder into the task
             let mut encoder = encoder;
+            let mut reported_drops = 0;
 
             // Main capture loop
             while !stop_flag.load(Ordering::SeqCst) {
+                match stream_events.try_recv() {
+                    // A new viewer can only start decoding at a keyframe
+                    Ok(StreamEvent::KeyframeRequested { client_id }) => {
+                        debug!("Forcing keyframe for new client {}", client_id);
+                        if let Err(e) = encoder.force_keyframe() {
+                            warn!("Failed to force keyframe: {}", e);
+                        }
+                    }
+                    // Stop capture/encode once the last client is lost to a heartbeat timeout
+                    Ok(StreamEvent::ClientDisconnected {
+                        client_id,
+                        reason: DisconnectReason::HeartbeatTimeout,
+                        stats,
+                        remaining_clients: 0,
+                    }) => {
+                        warn!(
+                            "Client {} lost (no heartbeat), stopping capture after {} frames",
+                            client_id, stats.frames_sent
+                        );
+                        if let Some(sender) = &packet_sender {
+                            let packet = Packet::new(
+                                INTERNAL_SESSION_STOPPED,
+                                serde_json::json!({
+                                    "reason": "heartbeat_timeout",
+                                    "framesSent": stats.frames_sent,
+                                    "packetsSent": stats.packets_sent,
+                                    "packetsLost": stats.packets_lost,
+                                    "durationSecs": stats.duration_secs,
+                                }),
+                            );
+                            let _ = sender.send((task_device_id.clone(), packet)).await;
+                        }
+                        break;
+                    }
+                    _ => {}
+                }
+
                 match frame_stream.next_frame().await {
                     Some(frame) => {
+                        let dropped = frame_stream.dropped_frames();
+                        if dropped > reported_drops {
+                            server_for_task.record_dropped_frames(dropped - reported_drops);
+                            reported_drops = dropped;
+                        }
+
                         // Encode frame
                         match encoder.encode_video_frame(&frame) {
                             Ok(Some(encoded_frame)) => {
@@ -325,6 +510,24 @@ impl ExtendedDisplayPlugin {
                     }
                     None => {
                         info!("Capture stream ended");
+                        if let Ok(CaptureEvent::SourceClosed { window_title, .. }) =
+                            capture_events.try_recv()
+                        {
+                            warn!(
+                                "Captured source closed ({:?}), ending session",
+                                window_title
+                            );
+                            if let Some(sender) = &packet_sender {
+                                let packet = Packet::new(
+                                    INTERNAL_SESSION_STOPPED,
+                                    serde_json::json!({
+                                        "reason": "source_closed",
+                                        "windowTitle": window_title,
+                                    }),
+                                );
+                                let _ = sender.send((task_device_id.clone(), packet)).await;
+                            }
+                        }
                         break;
                     }
                 }
@@ -339,7 +542,11 @@ impl ExtendedDisplayPlugin {
 
         // Store state (including resolution for touch coordinate validation)
         self.streaming_server = Some(server_arc);
-        self.input_handler = Some(input_handler);
+        if input_available {
+            let (input_tx, input_rx) = mpsc::unbounded_channel();
+            self.input_events = Some(input_tx);
+            self.input_task = Some(tokio::spawn(run_input_task(input_handler, input_rx)));
+        }
         self.capture_task = Some(capture_task);
         self.display_resolution = (display_width, display_height);
         self.session_active = true;
@@ -418,7 +625,7 @@ impl ExtendedDisplayPlugin {
 
         // Drop encoder and input handler (encoder was moved into capture task)
         self.encoder = None;
-        self.input_handler = None;
+        self.stop_input_task().await;
         self.session_active = false;
 
         // Send stop to Android
@@ -437,15 +644,23 @@ impl ExtendedDisplayPlugin {
         Ok(())
     }
 
+    /// Stop the input task once it has injected the events still buffered
+    async fn stop_input_task(&mut self) {
+        // Closing the channel ends the task
+        self.input_events = None;
+        if let Some(handle) = self.input_task.take() {
+            if let Err(e) = handle.await {
+                warn!("Input task panicked: {}", e);
+            }
+        }
+    }
+
     /// Handle a touch event from the Android device
     fn handle_touch(&mut self, body: &serde_json::Value, display_bounds: (u32, u32)) {
-        let handler = match &mut self.input_handler {
-            Some(h) if h.is_input_available() => h,
-            _ => {
-                debug!("Touch event ignored — input handler unavailable");
-                return;
-            }
-        };
+        if self.input_events.is_none() {
+            debug!("Touch event ignored — input handler unavailable");
+            return;
+        }
 
         let raw_x = body["x"].as_f64().unwrap_or(0.0);
         let raw_y = body["y"].as_f64().unwrap_or(0.0);
@@ -497,10 +712,15 @@ impl ExtendedDisplayPlugin {
             touch_id,
             pressure: body["pressure"].as_f64(),
             timestamp: body["timestamp"].as_u64(),
+            sequence: body["sequence"].as_u64(),
         };
 
-        if let Err(e) = handler.handle_touch_event(&event) {
-            debug!("Touch injection error: {}", e);
+        let sent = self
+            .input_events
+            .as_ref()
+            .is_some_and(|events| events.send(event).is_ok());
+        if !sent {
+            debug!("Touch event ignored — input task stopped");
         }
     }
 
@@ -616,7 +836,7 @@ impl Plugin for ExtendedDisplayPlugin {
                 // Otherwise drop happens implicitly
             }
             self.encoder = None;
-            self.input_handler = None;
+            self.stop_input_task().await;
             self.session_active = false;
             debug!(
                 "Cleaned up extended display session for {}",
@@ -676,7 +896,28 @@ impl Plugin for ExtendedDisplayPlugin {
                     None
                 };
 
-                self.start_session(&device_id, capabilities, requested_resolution).await?;
+                // The device may pick a single window instead of an output
+                let source = match packet.body.get("source") {
+                    Some(value) => match serde_json::from_value::<CaptureSource>(value.clone()) {
+                        Ok(source) => source,
+                        Err(_) => {
+                            warn!(
+                                "Device {} requested unknown capture source {}",
+                                device_id, value
+                            );
+                            self.config.capture_source
+                        }
+                    },
+                    None => self.config.capture_source,
+                };
+
+                self.start_session_with_source(
+                    &device_id,
+                    capabilities,
+                    requested_resolution,
+                    source,
+                )
+                .await?;
             }
             "touch" => {
                 self.handle_touch(&packet.body, self.display_resolution);
@@ -697,12 +938,24 @@ impl Plugin for ExtendedDisplayPlugin {
 }
 
 /// Factory for creating `ExtendedDisplayPlugin` instances
-pub struct ExtendedDisplayPluginFactory;
+pub struct ExtendedDisplayPluginFactory {
+    /// Streaming server settings (None = plugin defaults)
+    stream_config: Option<StreamConfig>,
+}
 
 impl ExtendedDisplayPluginFactory {
     /// Create a new factory
     pub fn new() -> Self {
-        Self
+        Self {
+            stream_config: None,
+        }
+    }
+
+    /// Create a factory whose plugins stream with `stream_config`
+    pub fn with_stream_config(stream_config: StreamConfig) -> Self {
+        Self {
+            stream_config: Some(stream_config),
+        }
     }
 }
 
@@ -732,7 +985,46 @@ impl PluginFactory for ExtendedDisplayPluginFactory {
     }
 
     fn create(&self) -> Box<dyn Plugin> {
-        Box::new(ExtendedDisplayPlugin::new())
+        match &self.stream_config {
+            Some(stream_config) => Box::new(ExtendedDisplayPlugin::with_stream_config(
+                stream_config.clone(),
+            )),
+            None => Box::new(ExtendedDisplayPlugin::new()),
+        }
+    }
+}
+
+/// Inject touch events, releasing jitter-buffered ones when they are due
+///
+/// Runs until `events` is closed, then injects whatever is still buffered so
+/// a final `up` is not lost.
+async fn run_input_task(
+    mut handler: InputHandler,
+    mut events: mpsc::UnboundedReceiver<TouchEvent>,
+) {
+    loop {
+        let event = match handler.next_jitter_release() {
+            Some(release_at) => tokio::select! {
+                event = events.recv() => event,
+                _ = tokio::time::sleep_until(release_at.into()) => {
+                    if let Err(e) = handler.poll_jitter_buffer() {
+                        debug!("Touch injection error: {}", e);
+                    }
+                    continue;
+                }
+            },
+            None => events.recv().await,
+        };
+        let Some(event) = event else {
+            break;
+        };
+        if let Err(e) = handler.handle_touch_event(&event) {
+            debug!("Touch injection error: {}", e);
+        }
+    }
+
+    if let Err(e) = handler.set_jitter_buffer(None) {
+        debug!("Touch injection error: {}", e);
     }
 }
 
@@ -756,9 +1048,35 @@ mod tests {
     fn test_config_default() {
         let config = ExtendedDisplayConfig::default();
         assert_eq!(config.signaling_port, DEFAULT_SIGNALING_PORT);
-        assert_eq!(config.bitrate_bps, DEFAULT_BITRATE_BPS);
-        assert_eq!(config.framerate, DEFAULT_FRAMERATE);
+        assert_eq!(config.network, TransportType::Wifi);
+        assert_eq!(config.latency, LatencyTarget::Interactive);
+        assert_eq!(config.bitrate_bps, None);
+        assert_eq!(config.framerate, None);
         assert!(config.validate().is_ok());
+
+        // The preset decides when nothing is overridden
+        let encoder = config.encoder_config(2560, 1600);
+        let preset = EncoderConfig::preset(TransportType::Wifi, LatencyTarget::Interactive);
+        assert_eq!((encoder.width, encoder.height), (2560, 1600));
+        assert_eq!(encoder.bitrate, preset.bitrate);
+        assert_eq!(encoder.keyframe_interval, preset.keyframe_interval);
+        assert_eq!(encoder.profile, preset.profile);
+    }
+
+    #[test]
+    fn test_configured_values_override_preset() {
+        let config = ExtendedDisplayConfig {
+            network: TransportType::Cellular,
+            bitrate_bps: Some(4_000_000),
+            profile: Some(H264Profile::Main),
+            ..ExtendedDisplayConfig::default()
+        };
+        let encoder = config.encoder_config(1920, 1080);
+        let preset = EncoderConfig::preset(TransportType::Cellular, LatencyTarget::Interactive);
+        assert_eq!(encoder.bitrate, 4_000_000);
+        assert_eq!(encoder.profile, Some(H264Profile::Main));
+        assert_eq!(encoder.framerate, preset.framerate);
+        assert_eq!(encoder.keyframe_interval, preset.keyframe_interval);
     }
 
     #[test]
@@ -771,42 +1089,70 @@ mod tests {
     #[test]
     fn test_config_validation_bitrate_low() {
         let mut config = ExtendedDisplayConfig::default();
-        config.bitrate_bps = 100;
+        config.bitrate_bps = Some(100);
         assert!(config.validate().is_err());
     }
 
     #[test]
     fn test_config_validation_bitrate_high() {
         let mut config = ExtendedDisplayConfig::default();
-        config.bitrate_bps = 200_000_000;
+        config.bitrate_bps = Some(200_000_000);
         assert!(config.validate().is_err());
     }
 
     #[test]
     fn test_config_validation_framerate() {
         let mut config = ExtendedDisplayConfig::default();
-        config.framerate = 0;
+        config.framerate = Some(0);
         assert!(config.validate().is_err());
 
-        config.framerate = 121;
+        config.framerate = Some(121);
         assert!(config.validate().is_err());
 
-        config.framerate = 60;
+        config.framerate = Some(60);
         assert!(config.validate().is_ok());
     }
 
+    #[test]
+    fn test_config_jitter_buffer() {
+        let mut config = ExtendedDisplayConfig::default();
+        assert!(config.jitter_buffer().is_none());
+
+        config.jitter_buffer_ms = Some(30);
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.jitter_buffer().map(|j| j.delay),
+            Some(Duration::from_millis(30))
+        );
+
+        config.jitter_buffer_ms = Some(MAX_JITTER_BUFFER_MS + 1);
+        assert!(config.validate().is_err());
+    }
+
     #[test]
     fn test_config_serialization() {
         let config = ExtendedDisplayConfig {
             signaling_port: 9999,
-            bitrate_bps: 5_000_000,
-            framerate: 30,
+            network: TransportType::Cellular,
+            bitrate_bps: Some(5_000_000),
+            framerate: Some(30),
+            capture_source: CaptureSource::Window,
+            ..ExtendedDisplayConfig::default()
         };
         let json = serde_json::to_string(&config).unwrap();
         let deserialized: ExtendedDisplayConfig = serde_json::from_str(&json).unwrap();
         assert_eq!(deserialized.signaling_port, 9999);
-        assert_eq!(deserialized.bitrate_bps, 5_000_000);
-        assert_eq!(deserialized.framerate, 30);
+        assert_eq!(deserialized.network, TransportType::Cellular);
+        assert_eq!(deserialized.bitrate_bps, Some(5_000_000));
+        assert_eq!(deserialized.framerate, Some(30));
+        assert_eq!(deserialized.capture_source, CaptureSource::Window);
+
+        // Settings written before presets existed still load
+        let legacy: ExtendedDisplayConfig =
+            serde_json::from_str(r#"{"signaling_port": 18080, "bitrate_bps": 8000000}"#).unwrap();
+        assert_eq!(legacy.bitrate_bps, Some(8_000_000));
+        assert_eq!(legacy.latency, LatencyTarget::Interactive);
+        assert_eq!(legacy.capture_source, CaptureSource::Monitor);
     }
 
     #[tokio::test]
@@ -840,6 +1186,21 @@ mod tests {
         assert!(!plugin.enabled);
     }
 
+    #[test]
+    fn test_factory_applies_stream_config() {
+        let factory = ExtendedDisplayPluginFactory::with_stream_config(StreamConfig {
+            signaling_port: 19000,
+            ..StreamConfig::default()
+        });
+        let plugin = factory.create();
+        let plugin = plugin
+            .as_any()
+            .downcast_ref::<ExtendedDisplayPlugin>()
+            .unwrap();
+        assert_eq!(plugin.config.signaling_port, 19000);
+        assert_eq!(plugin.stream_config.signaling_port, 19000);
+    }
+
     #[test]
     fn test_factory_creation() {
         let factory = ExtendedDisplayPluginFactory::new();
@@ -1,7 +1,7 @@
 //! Lock Plugin
 //!
-//! Enables remote locking and unlocking of the desktop screen.
-//! Provides secure screen lock control for COSMIC Desktop.
+//! Enables remote locking of the desktop screen, and locking the phone from the
+//! desktop. Provides secure screen lock control for COSMIC Desktop.
 //!
 //! ## Protocol
 //!
@@ -20,11 +20,14 @@
 //!     "id": 1234567890,
 //!     "type": "cconnect.lock.request",
 //!     "body": {
-//!         "setLocked": true
+//!         "setLocked": true,
+//!         "screenOff": true
 //!     }
 //! }
 //! ```
 //!
+//! `screenOff` (optional) also powers off the displays after locking.
+//!
 //! ## Lock State
 //!
 //! Report current lock state:
@@ -55,9 +58,11 @@
 //!
 //! ## Security Considerations
 //!
-//! - Locking is always allowed (security enhancement)
-//! - Unlocking may require device authentication
-//! - Lock state changes are broadcast to all paired devices
+//! - Remote locking is gated by a per-device permission (allowed by default)
+//! - Remote screen-off is a separate per-device permission (denied by default)
+//! - Remote unlock requests are always refused; the current state is reported back
+//! - Every remote lock action emits a `cconnect.internal.lock.remote_action` packet
+//!   so the daemon can surface it
 //! - Uses COSMIC Desktop session manager for lock/unlock
 //!
 //! ## Example
@@ -86,6 +91,63 @@ use tracing::{debug, info, warn};
 use super::logind_backend::LogindBackend;
 use super::{Plugin, PluginFactory};
 
+/// Internal packet type reporting remote lock actions to the daemon
+pub const INTERNAL_REMOTE_ACTION: &str = "cconnect.internal.lock.remote_action";
+
+/// Desktop session controls used for remote lock requests
+#[async_trait]
+pub trait DesktopLockControl: Send + Sync {
+    /// Prepare the backend (e.g. connect to DBus)
+    async fn connect(&mut self) -> std::result::Result<(), String> {
+        Ok(())
+    }
+
+    /// Lock the desktop session
+    async fn lock(&mut self) -> std::result::Result<(), String>;
+
+    /// Power off the displays (DPMS off)
+    async fn screen_off(&mut self) -> std::result::Result<(), String>;
+
+    /// Query whether the session is locked
+    async fn is_locked(&mut self) -> std::result::Result<bool, String>;
+}
+
+#[async_trait]
+impl DesktopLockControl for LogindBackend {
+    async fn connect(&mut self) -> std::result::Result<(), String> {
+        LogindBackend::connect(self).await
+    }
+
+    async fn lock(&mut self) -> std::result::Result<(), String> {
+        LogindBackend::lock(self).await
+    }
+
+    /// Uses `wlopm` on Wayland and falls back to `xset` on X11
+    async fn screen_off(&mut self) -> std::result::Result<(), String> {
+        let attempts: [(&str, &[&str]); 2] = [
+            ("wlopm", &["--off", "*"]),
+            ("xset", &["dpms", "force", "off"]),
+        ];
+
+        for (program, args) in attempts {
+            match tokio::process::Command::new(program)
+                .args(args)
+                .status()
+                .await
+            {
+                Ok(status) if status.success() => return Ok(()),
+                Ok(status) => debug!("{} exited with {}", program, status),
+                Err(e) => debug!("{} unavailable: {}", program, e),
+            }
+        }
+        Err("No display power control available (install wlopm)".to_string())
+    }
+
+    async fn is_locked(&mut self) -> std::result::Result<bool, String> {
+        LogindBackend::is_locked(self).await
+    }
+}
+
 /// Lock plugin for remote desktop lock/unlock
 pub struct LockPlugin {
     /// Device ID this plugin is attached to
@@ -97,8 +159,14 @@ pub struct LockPlugin {
     /// Current lock state (thread-safe cached)
     lock_state: Arc<RwLock<bool>>,
 
-    /// Logind DBus backend for screen lock control
-    logind_backend: LogindBackend,
+    /// Session controls (logind DBus by default)
+    lock_control: Box<dyn DesktopLockControl>,
+
+    /// Whether this device may lock the desktop
+    remote_lock_allowed: bool,
+
+    /// Whether this device may turn the displays off
+    screen_off_allowed: bool,
 
     /// Packet sender for response packets
     packet_sender: Option<tokio::sync::mpsc::Sender<(String, Packet)>>,
@@ -107,15 +175,32 @@ pub struct LockPlugin {
 impl LockPlugin {
     /// Create a new Lock plugin
     pub fn new() -> Self {
+        Self::with_lock_control(Box::new(LogindBackend::new()))
+    }
+
+    /// Create a Lock plugin using custom session controls
+    pub fn with_lock_control(lock_control: Box<dyn DesktopLockControl>) -> Self {
         Self {
             device_id: None,
             enabled: false,
             lock_state: Arc::new(RwLock::new(false)),
-            logind_backend: LogindBackend::new(),
+            lock_control,
+            remote_lock_allowed: true,
+            screen_off_allowed: false,
             packet_sender: None,
         }
     }
 
+    /// Allow or deny this device locking the desktop
+    pub fn set_remote_lock_allowed(&mut self, allowed: bool) {
+        self.remote_lock_allowed = allowed;
+    }
+
+    /// Allow or deny this device turning the displays off when locking
+    pub fn set_screen_off_allowed(&mut self, allowed: bool) {
+        self.screen_off_allowed = allowed;
+    }
+
     /// Check if the desktop is currently locked
     ///
     /// Returns the cached lock state. This is updated when lock state
@@ -245,7 +330,13 @@ impl LockPlugin {
     async fn handle_lock_request(&mut self, packet: &Packet, device: &mut Device) -> Result<()> {
         // Check if this is a lock/unlock request
         if let Some(set_locked) = packet.body.get("setLocked").and_then(|v| v.as_bool()) {
-            self.handle_set_locked(set_locked, device).await?;
+            let screen_off = packet
+                .body
+                .get("screenOff")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            self.handle_set_locked(set_locked, screen_off, device)
+                .await?;
             return Ok(());
         }
 
@@ -264,7 +355,14 @@ impl LockPlugin {
     }
 
     /// Handle a set locked request
-    async fn handle_set_locked(&mut self, set_locked: bool, device: &Device) -> Result<()> {
+    ///
+    /// Only locking is honoured; remote unlock is refused for security.
+    async fn handle_set_locked(
+        &mut self,
+        set_locked: bool,
+        screen_off: bool,
+        device: &Device,
+    ) -> Result<()> {
         let action = if set_locked { "lock" } else { "unlock" };
         info!(
             "Received {} request from {} ({})",
@@ -273,25 +371,49 @@ impl LockPlugin {
             device.id()
         );
 
-        let result = if set_locked {
-            self.lock_desktop().await
-        } else {
-            self.unlock_desktop().await
-        };
+        if !set_locked {
+            warn!(
+                "Refusing remote unlock request from {} ({})",
+                device.name(),
+                device.id()
+            );
+            self.emit_remote_action(json!({ "action": "unlock_refused" }))
+                .await;
+            self.send_lock_state(self.is_locked()).await;
+            return Ok(());
+        }
+
+        if !self.remote_lock_allowed {
+            warn!(
+                "Remote lock not permitted for {} ({})",
+                device.name(),
+                device.id()
+            );
+            self.emit_remote_action(json!({ "action": "lock_denied" }))
+                .await;
+            self.send_lock_state(self.is_locked()).await;
+            return Ok(());
+        }
 
-        match result {
+        match self.lock_desktop().await {
             Ok(()) => {
-                self.set_lock_state(set_locked);
+                self.set_lock_state(true);
 
-                // Send state update back to device
-                let state_packet = self.create_lock_state(set_locked);
-                if let (Some(device_id), Some(sender)) = (&self.device_id, &self.packet_sender) {
-                    if let Err(e) = sender.send((device_id.clone(), state_packet)).await {
-                        warn!("Failed to send lock state packet: {}", e);
+                let screen_off = screen_off && self.screen_off_allowed;
+                if screen_off {
+                    if let Err(e) = self.lock_control.screen_off().await {
+                        warn!("Failed to turn displays off: {}", e);
                     }
-                } else {
-                    warn!("Cannot send lock state - plugin not properly initialized");
                 }
+
+                self.emit_remote_action(json!({
+                    "action": "locked",
+                    "screenOff": screen_off,
+                }))
+                .await;
+
+                // Send state update back to device
+                self.send_lock_state(true).await;
             }
             Err(e) => {
                 warn!("Failed to {} desktop: {}", action, e);
@@ -301,6 +423,28 @@ impl LockPlugin {
         Ok(())
     }
 
+    /// Send the lock state to the remote device
+    async fn send_lock_state(&self, locked: bool) {
+        let state_packet = self.create_lock_state(locked);
+        if let (Some(device_id), Some(sender)) = (&self.device_id, &self.packet_sender) {
+            if let Err(e) = sender.send((device_id.clone(), state_packet)).await {
+                warn!("Failed to send lock state packet: {}", e);
+            }
+        } else {
+            warn!("Cannot send lock state - plugin not properly initialized");
+        }
+    }
+
+    /// Report a remote lock action to the daemon
+    async fn emit_remote_action(&self, body: serde_json::Value) {
+        if let (Some(device_id), Some(sender)) = (&self.device_id, &self.packet_sender) {
+            let packet = Packet::new(INTERNAL_REMOTE_ACTION, body);
+            if let Err(e) = sender.send((device_id.clone(), packet)).await {
+                warn!("Failed to emit lock action event: {}", e);
+            }
+        }
+    }
+
     /// Handle a lock state query request
     async fn handle_lock_state_query(&mut self, device: &Device) -> Result<()> {
         info!(
@@ -347,23 +491,16 @@ impl LockPlugin {
 
     /// Lock the desktop using logind DBus
     async fn lock_desktop(&mut self) -> Result<()> {
-        self.logind_backend.lock().await.map_err(|e| {
+        self.lock_control.lock().await.map_err(|e| {
             crate::ProtocolError::invalid_state(format!("Failed to lock desktop: {}", e))
         })
     }
 
-    /// Unlock the desktop using logind DBus
-    async fn unlock_desktop(&mut self) -> Result<()> {
-        self.logind_backend.unlock().await.map_err(|e| {
-            crate::ProtocolError::invalid_state(format!("Failed to unlock desktop: {}", e))
-        })
-    }
-
     /// Query current lock state from logind DBus
     async fn query_lock_state(&mut self) -> Result<bool> {
         debug!("Querying lock state via logind DBus");
 
-        let is_locked = self.logind_backend.is_locked().await.unwrap_or(false);
+        let is_locked = self.lock_control.is_locked().await.unwrap_or(false);
 
         debug!("Current lock state: {}", is_locked);
         Ok(is_locked)
@@ -422,7 +559,7 @@ impl Plugin for LockPlugin {
         self.enabled = true;
 
         // Connect to logind DBus
-        if let Err(e) = self.logind_backend.connect().await {
+        if let Err(e) = self.lock_control.connect().await {
             warn!("Failed to connect to logind DBus: {}", e);
             // Continue anyway - will try to connect on first use
         }
@@ -666,4 +803,130 @@ mod tests {
 
         assert!(!plugin.is_locked());
     }
+
+    /// Records lock actions instead of talking to logind
+    #[derive(Default)]
+    struct MockLockControl {
+        locks: Arc<std::sync::atomic::AtomicUsize>,
+        screen_offs: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl DesktopLockControl for MockLockControl {
+        async fn lock(&mut self) -> std::result::Result<(), String> {
+            self.locks.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn screen_off(&mut self) -> std::result::Result<(), String> {
+            self.screen_offs
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn is_locked(&mut self) -> std::result::Result<bool, String> {
+            Ok(self.locks.load(std::sync::atomic::Ordering::SeqCst) > 0)
+        }
+    }
+
+    async fn create_mock_plugin() -> (
+        LockPlugin,
+        Arc<std::sync::atomic::AtomicUsize>,
+        Arc<std::sync::atomic::AtomicUsize>,
+        tokio::sync::mpsc::Receiver<(String, Packet)>,
+    ) {
+        let control = MockLockControl::default();
+        let locks = control.locks.clone();
+        let screen_offs = control.screen_offs.clone();
+
+        let mut plugin = LockPlugin::with_lock_control(Box::new(control));
+        let (tx, rx) = tokio::sync::mpsc::channel(100);
+        plugin.init(&create_test_device(), tx).await.unwrap();
+        plugin.start().await.unwrap();
+        (plugin, locks, screen_offs, rx)
+    }
+
+    fn drain(rx: &mut tokio::sync::mpsc::Receiver<(String, Packet)>) -> Vec<Packet> {
+        let mut packets = Vec::new();
+        while let Ok((_, packet)) = rx.try_recv() {
+            packets.push(packet);
+        }
+        packets
+    }
+
+    #[tokio::test]
+    async fn test_remote_lock_request_locks_desktop() {
+        let (mut plugin, locks, screen_offs, mut rx) = create_mock_plugin().await;
+        plugin.set_screen_off_allowed(true);
+        let mut device = create_test_device();
+
+        let packet = Packet::new(
+            "cconnect.lock.request",
+            json!({ "setLocked": true, "screenOff": true }),
+        );
+        plugin.handle_packet(&packet, &mut device).await.unwrap();
+
+        assert_eq!(locks.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(screen_offs.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(plugin.is_locked());
+
+        let packets = drain(&mut rx);
+        let event = packets
+            .iter()
+            .find(|p| p.is_type(INTERNAL_REMOTE_ACTION))
+            .unwrap();
+        assert_eq!(event.body["action"], json!("locked"));
+        assert_eq!(event.body["screenOff"], json!(true));
+        assert!(packets
+            .iter()
+            .any(|p| p.is_type("cconnect.lock") && p.body["isLocked"] == json!(true)));
+    }
+
+    #[tokio::test]
+    async fn test_remote_unlock_request_refused() {
+        let (mut plugin, locks, _, mut rx) = create_mock_plugin().await;
+        let mut device = create_test_device();
+
+        let lock = Packet::new("cconnect.lock.request", json!({ "setLocked": true }));
+        plugin.handle_packet(&lock, &mut device).await.unwrap();
+        drain(&mut rx);
+
+        let unlock = Packet::new("cconnect.lock.request", json!({ "setLocked": false }));
+        plugin.handle_packet(&unlock, &mut device).await.unwrap();
+
+        assert!(plugin.is_locked());
+        assert_eq!(locks.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let packets = drain(&mut rx);
+        assert!(packets
+            .iter()
+            .any(|p| p.is_type(INTERNAL_REMOTE_ACTION)
+                && p.body["action"] == json!("unlock_refused")));
+        assert!(packets
+            .iter()
+            .any(|p| p.is_type("cconnect.lock") && p.body["isLocked"] == json!(true)));
+    }
+
+    #[tokio::test]
+    async fn test_remote_lock_permission() {
+        let (mut plugin, locks, screen_offs, mut rx) = create_mock_plugin().await;
+        let mut device = create_test_device();
+
+        // Screen-off is not granted by default
+        let packet = Packet::new(
+            "cconnect.lock.request",
+            json!({ "setLocked": true, "screenOff": true }),
+        );
+        plugin.handle_packet(&packet, &mut device).await.unwrap();
+        assert_eq!(locks.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(screen_offs.load(std::sync::atomic::Ordering::SeqCst), 0);
+        drain(&mut rx);
+
+        plugin.set_remote_lock_allowed(false);
+        plugin.handle_packet(&packet, &mut device).await.unwrap();
+        assert_eq!(locks.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(drain(&mut rx)
+            .iter()
+            .any(|p| p.body["action"] == json!("lock_denied")));
+    }
 }
@@ -0,0 +1,151 @@
+//! Declarative Plugin Manifest
+//!
+//! Describes the set of plugins a daemon should register as plain data instead of a
+//! chain of hand-written `register_factory` calls. Each entry carries the plugin id,
+//! the capabilities it handles and the factory that creates per-device instances.
+//!
+//! The manifest is filtered at registration time (typically by the user's config), so
+//! the list of available plugins and the list of active plugins share one source of
+//! truth.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let manifest = PluginManifest::new()
+//!     .with_entry(PluginManifestEntry::from_factory(Arc::new(PingPluginFactory)))
+//!     .with_entry(PluginManifestEntry::from_factory(Arc::new(BatteryPluginFactory)));
+//!
+//! let registered = manager.register_manifest(&manifest, |entry| config.is_enabled(&entry.id))?;
+//! ```
+
+use super::{Plugin, PluginFactory};
+use std::sync::Arc;
+
+/// A single plugin declaration in a [`PluginManifest`]
+#[derive(Clone)]
+pub struct PluginManifestEntry {
+    /// Unique plugin identifier (e.g. "ping", "battery")
+    pub id: String,
+
+    /// Packet types routed to this plugin
+    pub incoming_capabilities: Vec<String>,
+
+    /// Packet types this plugin may send
+    pub outgoing_capabilities: Vec<String>,
+
+    /// Factory creating per-device plugin instances
+    pub factory: Arc<dyn PluginFactory>,
+}
+
+impl PluginManifestEntry {
+    /// Create an entry with explicit id and capabilities
+    pub fn new(
+        id: impl Into<String>,
+        incoming_capabilities: Vec<String>,
+        outgoing_capabilities: Vec<String>,
+        factory: Arc<dyn PluginFactory>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            incoming_capabilities,
+            outgoing_capabilities,
+            factory,
+        }
+    }
+
+    /// Create an entry that takes its id and capabilities from the factory
+    pub fn from_factory(factory: Arc<dyn PluginFactory>) -> Self {
+        Self {
+            id: factory.name().to_string(),
+            incoming_capabilities: factory.incoming_capabilities(),
+            outgoing_capabilities: factory.outgoing_capabilities(),
+            factory,
+        }
+    }
+}
+
+impl std::fmt::Debug for PluginManifestEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PluginManifestEntry")
+            .field("id", &self.id)
+            .field("incoming_capabilities", &self.incoming_capabilities)
+            .field("outgoing_capabilities", &self.outgoing_capabilities)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Ordered list of plugin declarations
+#[derive(Debug, Clone, Default)]
+pub struct PluginManifest {
+    entries: Vec<PluginManifestEntry>,
+}
+
+impl PluginManifest {
+    /// Create an empty manifest
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an entry (builder style)
+    pub fn with_entry(mut self, entry: PluginManifestEntry) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
+    /// Add an entry
+    pub fn push(&mut self, entry: PluginManifestEntry) {
+        self.entries.push(entry);
+    }
+
+    /// All declared entries in declaration order
+    pub fn entries(&self) -> &[PluginManifestEntry] {
+        &self.entries
+    }
+
+    /// Declared plugin ids in declaration order
+    pub fn ids(&self) -> Vec<&str> {
+        self.entries.iter().map(|e| e.id.as_str()).collect()
+    }
+
+    /// Number of declared entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the manifest declares no plugins
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Adapter exposing a manifest entry through the [`PluginFactory`] trait
+///
+/// The manifest's id and capabilities take precedence over whatever the wrapped
+/// factory reports, so routing always follows the declaration.
+pub(crate) struct ManifestPluginFactory {
+    entry: PluginManifestEntry,
+}
+
+impl ManifestPluginFactory {
+    pub(crate) fn new(entry: PluginManifestEntry) -> Self {
+        Self { entry }
+    }
+}
+
+impl PluginFactory for ManifestPluginFactory {
+    fn name(&self) -> &str {
+        &self.entry.id
+    }
+
+    fn incoming_capabilities(&self) -> Vec<String> {
+        self.entry.incoming_capabilities.clone()
+    }
+
+    fn outgoing_capabilities(&self) -> Vec<String> {
+        self.entry.outgoing_capabilities.clone()
+    }
+
+    fn create(&self) -> Box<dyn Plugin> {
+        self.entry.factory.create()
+    }
+}
@@ -0,0 +1,60 @@
+//! Per-Plugin Metrics
+//!
+//! Counters kept by [`PluginManager`](super::PluginManager) around every
+//! `handle_packet` call, summed over all devices. They make it easy to spot a
+//! plugin that fails on every packet or takes too long to handle them.
+
+use serde::Serialize;
+use std::time::Duration;
+
+/// Packet handling counters for one plugin
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PluginMetrics {
+    /// Packets dispatched to the plugin
+    pub packets: u64,
+    /// Dispatches where the handler returned an error (recoverable or not)
+    pub errors: u64,
+    /// Time spent in the handler across all packets
+    pub total_handle_time: Duration,
+    /// Longest single handler call
+    pub max_handle_time: Duration,
+}
+
+impl PluginMetrics {
+    /// Record one handler call
+    pub(crate) fn record(&mut self, elapsed: Duration, failed: bool) {
+        self.packets += 1;
+        if failed {
+            self.errors += 1;
+        }
+        self.total_handle_time += elapsed;
+        self.max_handle_time = self.max_handle_time.max(elapsed);
+    }
+
+    /// Average time spent handling one packet
+    pub fn average_handle_time(&self) -> Duration {
+        if self.packets == 0 {
+            return Duration::ZERO;
+        }
+        let nanos = self.total_handle_time.as_nanos() / u128::from(self.packets);
+        Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_average_handle_time() {
+        let mut metrics = PluginMetrics::default();
+        assert_eq!(metrics.average_handle_time(), Duration::ZERO);
+
+        metrics.record(Duration::from_millis(10), false);
+        metrics.record(Duration::from_millis(30), true);
+        assert_eq!(metrics.packets, 2);
+        assert_eq!(metrics.errors, 1);
+        assert_eq!(metrics.average_handle_time(), Duration::from_millis(20));
+        assert_eq!(metrics.max_handle_time, Duration::from_millis(30));
+    }
+}
@@ -25,6 +25,12 @@
 //! - `cconnect.mpris` - Media player state
 //! - `cconnect.mpris.request` - Media player commands
 //!
+//! A factory can declare the body of each type it receives through
+//! [`PluginFactory::packet_types`]. The manager then rejects packets that
+//! violate the schema before they reach the plugin, and logs packet types no
+//! plugin handles the first time each arrives (see
+//! [`PluginManager::unknown_packet_types`]).
+//!
 //! ### Plugin Categories
 //!
 //! - **Device Status**: Battery, Connectivity Report, Lock
@@ -116,6 +122,8 @@ pub mod findmyphone;
 pub mod lock;
 pub mod logind_backend;
 pub mod r#macro;
+pub mod manifest;
+pub mod metrics;
 pub mod mkshare;
 pub mod mousekeyboardshare;
 pub mod mpris;
@@ -129,9 +137,19 @@ pub mod presenter;
 pub mod remotedesktop;
 pub mod remoteinput;
 pub mod runcommand;
+pub mod runcommand_schedule;
+pub mod safe_mode;
 pub mod screenshare;
 pub mod screenshot;
+pub mod sftp_browser;
 pub mod share;
+pub mod share_batch;
+pub mod share_fetch;
+pub mod share_hooks;
+pub mod share_metadata;
+pub mod share_retransmit;
+pub mod share_users;
+pub mod switches;
 pub mod systemd_inhibitor;
 pub mod systemmonitor;
 pub mod systemvolume;
@@ -142,11 +160,17 @@ pub mod wol;
 #[cfg(feature = "extendeddisplay")]
 pub mod extendeddisplay;
 
-use crate::{Device, Packet, ProtocolError, Result};
+pub use manifest::{PluginManifest, PluginManifestEntry};
+pub use metrics::PluginMetrics;
+pub use switches::PluginStatus;
+
+use crate::{Device, Packet, PacketType, PacketTypeRegistry, ProtocolError, Result};
 use async_trait::async_trait;
 use std::any::Any;
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use switches::PluginSwitches;
 use tokio::sync::mpsc::Sender;
 use tracing::{debug, error, info, warn};
 
@@ -180,6 +204,15 @@ pub trait PluginFactory: Send + Sync {
     /// Get outgoing capabilities for this plugin type
     fn outgoing_capabilities(&self) -> Vec<String>;
 
+    /// Body schemas of the packet types this plugin receives
+    ///
+    /// Registered with the [`PluginManager`] along with the factory; incoming
+    /// packets of these types are checked with [`Packet::validate`] before
+    /// they reach the plugin. Default declares none.
+    fn packet_types(&self) -> Vec<PacketType> {
+        Vec::new()
+    }
+
     /// Create a new plugin instance
     fn create(&self) -> Box<dyn Plugin>;
 }
@@ -356,6 +389,21 @@ pub struct PluginManager {
 
     /// Mapping from incoming capability to plugin name
     capability_map: HashMap<String, String>,
+
+    /// Packet handling counters by plugin name
+    metrics: HashMap<String, PluginMetrics>,
+
+    /// Body schemas incoming packets are validated against
+    packet_types: PacketTypeRegistry,
+
+    /// Packet types received that no plugin handles
+    unknown_packet_types: HashSet<String>,
+
+    /// Plugins switched off at runtime, see [`switches`]
+    switches: RwLock<PluginSwitches>,
+
+    /// Whether command execution and input plugins are refused
+    safe_mode: bool,
 }
 
 impl PluginManager {
@@ -365,9 +413,34 @@ impl PluginManager {
             factories: HashMap::new(),
             device_plugins: HashMap::new(),
             capability_map: HashMap::new(),
+            metrics: HashMap::new(),
+            packet_types: PacketTypeRegistry::new(),
+            unknown_packet_types: HashSet::new(),
+            switches: RwLock::new(PluginSwitches::default()),
+            safe_mode: false,
         }
     }
 
+    /// Turn safe mode on or off
+    ///
+    /// Turning it on unregisters any blocked plugin factory already
+    /// registered; see [`safe_mode`] for what is blocked. Call before
+    /// initializing device plugins.
+    pub fn set_safe_mode(&mut self, enabled: bool) {
+        self.safe_mode = enabled;
+        if enabled {
+            info!("Safe mode enabled: command execution and input plugins are disabled");
+            for name in safe_mode::BLOCKED_PLUGINS {
+                self.unregister_factory(name);
+            }
+        }
+    }
+
+    /// Whether safe mode is on
+    pub fn safe_mode(&self) -> bool {
+        self.safe_mode
+    }
+
     /// Register a plugin factory
     ///
     /// Adds the plugin factory to the registry and builds capability mappings.
@@ -378,9 +451,17 @@ impl PluginManager {
     /// Returns error if:
     /// - A plugin factory with the same name is already registered
     /// - A capability is already handled by another plugin
+    /// - A packet type schema conflicts with one already registered
+    ///
+    /// In safe mode, factories of blocked plugins are skipped without error.
     pub fn register_factory(&mut self, factory: Arc<dyn PluginFactory>) -> Result<()> {
         let name = factory.name().to_string();
 
+        if self.safe_mode && safe_mode::is_blocked_plugin(&name) {
+            info!("Safe mode: not registering plugin factory {}", name);
+            return Ok(());
+        }
+
         // Check for duplicate plugin name
         if self.factories.contains_key(&name) {
             return Err(ProtocolError::Plugin(format!(
@@ -389,6 +470,12 @@ impl PluginManager {
             )));
         }
 
+        // Check body schemas before touching any mapping
+        let mut packet_types = self.packet_types.clone();
+        for packet_type in factory.packet_types() {
+            packet_types.register(packet_type)?;
+        }
+
         // Build capability mappings
         for capability in factory.incoming_capabilities() {
             if let Some(existing) = self.capability_map.get(&capability) {
@@ -399,12 +486,83 @@ impl PluginManager {
             }
             self.capability_map.insert(capability, name.clone());
         }
+        self.packet_types = packet_types;
 
         info!("Registered plugin factory: {}", name);
         self.factories.insert(name, factory);
         Ok(())
     }
 
+    /// Register every plugin declared in a manifest that passes `filter`
+    ///
+    /// Entries are validated as a whole before anything is registered, so a bad
+    /// manifest leaves the manager untouched. Entries rejected by `filter` (e.g.
+    /// disabled in config) are skipped without validation.
+    ///
+    /// Returns the ids of the registered plugins in manifest order.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Two selected entries share an id, or an id is already registered
+    /// - A capability is declared twice or already handled by another plugin
+    pub fn register_manifest<F>(
+        &mut self,
+        manifest: &PluginManifest,
+        filter: F,
+    ) -> Result<Vec<String>>
+    where
+        F: Fn(&PluginManifestEntry) -> bool,
+    {
+        let safe_mode = self.safe_mode;
+        let selected: Vec<&PluginManifestEntry> = manifest
+            .entries()
+            .iter()
+            .filter(|e| filter(e) && !(safe_mode && safe_mode::is_blocked_plugin(&e.id)))
+            .collect();
+
+        let mut ids = HashSet::new();
+        let mut capabilities = HashMap::new();
+        for entry in &selected {
+            if self.factories.contains_key(&entry.id) || !ids.insert(entry.id.as_str()) {
+                return Err(ProtocolError::Plugin(format!(
+                    "Plugin factory '{}' is already registered",
+                    entry.id
+                )));
+            }
+
+            for capability in &entry.incoming_capabilities {
+                let existing = self
+                    .capability_map
+                    .get(capability)
+                    .map(String::as_str)
+                    .or_else(|| capabilities.get(capability.as_str()).copied());
+                if let Some(existing) = existing {
+                    return Err(ProtocolError::Plugin(format!(
+                        "Capability '{}' already handled by plugin '{}'",
+                        capability, existing
+                    )));
+                }
+                capabilities.insert(capability.as_str(), entry.id.as_str());
+            }
+        }
+
+        let mut registered = Vec::with_capacity(selected.len());
+        for entry in selected {
+            self.register_factory(Arc::new(manifest::ManifestPluginFactory::new(
+                entry.clone(),
+            )))?;
+            registered.push(entry.id.clone());
+        }
+
+        info!(
+            "Registered {} of {} manifest plugins",
+            registered.len(),
+            manifest.len()
+        );
+        Ok(registered)
+    }
+
     /// Register a new plugin (legacy API for backward compatibility)
     ///
     /// This method exists for backward compatibility but is deprecated.
@@ -547,7 +705,8 @@ impl PluginManager {
 
     /// Unregister a plugin factory by name
     ///
-    /// Removes the plugin factory and clears its capability mappings.
+    /// Removes the plugin factory and clears its capability mappings and
+    /// packet type schemas.
     /// Device plugin instances should be cleaned up before unregistering.
     pub fn unregister_factory(&mut self, name: &str) -> Option<Arc<dyn PluginFactory>> {
         // Remove capability mappings
@@ -556,12 +715,135 @@ impl PluginManager {
 
         // Remove factory
         let factory = self.factories.remove(name);
-        if factory.is_some() {
+        if let Some(factory) = &factory {
+            for packet_type in factory.packet_types() {
+                self.packet_types.unregister(packet_type.name());
+            }
             info!("Unregistered plugin factory: {}", name);
         }
         factory
     }
 
+    /// Enable or disable a plugin for every device
+    ///
+    /// A disabled plugin keeps its device instances but receives no packets,
+    /// and its packet types are left out of the advertised capabilities; see
+    /// [`switches`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no plugin factory with that name is registered
+    pub fn set_enabled(&self, plugin_id: &str, enabled: bool) -> Result<()> {
+        self.check_registered(plugin_id)?;
+        if self.write_switches().set(plugin_id, enabled) {
+            info!(
+                "Plugin {} {}",
+                plugin_id,
+                if enabled { "enabled" } else { "disabled" }
+            );
+        }
+        Ok(())
+    }
+
+    /// Enable or disable a plugin for one device
+    ///
+    /// Only packet dispatch is affected; the advertised capabilities are
+    /// shared by all devices.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no plugin factory with that name is registered
+    pub fn set_device_enabled(
+        &self,
+        device_id: &str,
+        plugin_id: &str,
+        enabled: bool,
+    ) -> Result<()> {
+        self.check_registered(plugin_id)?;
+        self.write_switches()
+            .set_for_device(device_id, plugin_id, enabled);
+        Ok(())
+    }
+
+    /// Whether a plugin is enabled for every device
+    pub fn is_enabled(&self, plugin_id: &str) -> bool {
+        self.read_switches().is_enabled(plugin_id)
+    }
+
+    /// Whether a plugin receives packets from a device
+    pub fn is_enabled_for(&self, device_id: &str, plugin_id: &str) -> bool {
+        self.read_switches().is_enabled_for(device_id, plugin_id)
+    }
+
+    /// Registered plugins with their enabled state, sorted by name
+    pub fn list(&self) -> Vec<PluginStatus> {
+        let switches = self.read_switches();
+        let mut statuses: Vec<PluginStatus> = self
+            .factories
+            .keys()
+            .map(|name| switches.status(name))
+            .collect();
+        statuses.sort_by(|a, b| a.id.cmp(&b.id));
+        statuses
+    }
+
+    fn check_registered(&self, plugin_id: &str) -> Result<()> {
+        if self.factories.contains_key(plugin_id) {
+            Ok(())
+        } else {
+            Err(ProtocolError::Plugin(format!(
+                "Plugin '{}' is not registered",
+                plugin_id
+            )))
+        }
+    }
+
+    fn read_switches(&self) -> std::sync::RwLockReadGuard<'_, PluginSwitches> {
+        // The switches are plain sets, so a poisoned lock is still usable
+        self.switches.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write_switches(&self) -> std::sync::RwLockWriteGuard<'_, PluginSwitches> {
+        self.switches.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Register a body schema outside of a plugin factory
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a different schema is already registered for the
+    /// packet type.
+    pub fn register_packet_type(&mut self, packet_type: PacketType) -> Result<()> {
+        self.packet_types.register(packet_type)
+    }
+
+    /// Body schemas incoming packets are validated against
+    pub fn packet_types(&self) -> &PacketTypeRegistry {
+        &self.packet_types
+    }
+
+    /// Packet types received so far that no plugin handles, sorted
+    pub fn unknown_packet_types(&self) -> Vec<String> {
+        let mut types: Vec<String> = self.unknown_packet_types.iter().cloned().collect();
+        types.sort();
+        types
+    }
+
+    /// Log a packet type no plugin handles, loudly the first time
+    fn note_unknown_packet_type(&mut self, device_id: &str, packet_type: &str) {
+        if self.unknown_packet_types.insert(packet_type.to_string()) {
+            warn!(
+                "Received unknown packet type {} from {}; no plugin handles it",
+                packet_type, device_id
+            );
+        } else {
+            debug!(
+                "Ignoring unknown packet type {} from {}",
+                packet_type, device_id
+            );
+        }
+    }
+
     /// Get a reference to a plugin by name (deprecated)
     ///
     /// Use `get_device_plugin(device_id, plugin_name)` instead for per-device instances.
@@ -576,16 +858,30 @@ impl PluginManager {
     }
 
     /// Get all incoming capabilities from registered factories
+    ///
+    /// Packet types of disabled plugins, and in safe mode of blocked
+    /// plugins, are never included.
     pub fn get_all_incoming_capabilities(&self) -> Vec<String> {
-        self.capability_map.keys().cloned().collect()
+        let switches = self.read_switches();
+        self.capability_map
+            .iter()
+            .filter(|(c, plugin)| !self.is_blocked(c) && switches.is_enabled(plugin))
+            .map(|(c, _)| c.clone())
+            .collect()
     }
 
     /// Get all outgoing capabilities from registered factories
+    ///
+    /// Packet types of disabled plugins, and in safe mode of blocked
+    /// plugins, are never included.
     pub fn get_all_outgoing_capabilities(&self) -> Vec<String> {
+        let switches = self.read_switches();
         let mut capabilities: Vec<String> = self
             .factories
-            .values()
-            .flat_map(|f| f.outgoing_capabilities())
+            .iter()
+            .filter(|(name, _)| switches.is_enabled(name))
+            .flat_map(|(_, f)| f.outgoing_capabilities())
+            .filter(|c| !self.is_blocked(c))
             .collect();
         capabilities.sort();
         capabilities.dedup();
@@ -650,11 +946,14 @@ impl PluginManager {
     /// Handle an incoming packet by routing to appropriate device-specific plugin
     ///
     /// Looks up the plugin that handles the packet's type for the given device
-    /// and delegates packet processing to that plugin instance.
+    /// and delegates packet processing to that plugin instance. Packets for a
+    /// plugin disabled globally or for the device are dropped without error.
     ///
     /// # Errors
     ///
     /// Returns error if:
+    /// - The packet belongs to a plugin blocked by safe mode
+    /// - The packet body violates its type's registered schema
     /// - No plugin handles the packet type
     /// - Device has no initialized plugins
     /// - Plugin packet handling fails critically
@@ -664,6 +963,18 @@ impl PluginManager {
         packet: &Packet,
         device: &mut Device,
     ) -> Result<()> {
+        if self.is_blocked(&packet.packet_type) {
+            return Err(ProtocolError::UnsupportedFeature(format!(
+                "{} is disabled in safe mode",
+                packet.packet_type
+            )));
+        }
+
+        if let Err(e) = packet.validate(&self.packet_types) {
+            warn!("Rejecting packet from {}: {}", device_id, e);
+            return Err(e);
+        }
+
         let mut packet_type = packet.packet_type.clone();
 
         // Find plugin name for this packet type
@@ -680,18 +991,28 @@ impl PluginManager {
                 packet_type = aliased;
                 name.clone()
             } else {
+                self.note_unknown_packet_type(device_id, &packet.packet_type);
                 return Err(ProtocolError::Plugin(format!(
                     "No plugin handles packet type: {} (nor aliased {})",
                     packet.packet_type, aliased
                 )));
             }
         } else {
+            self.note_unknown_packet_type(device_id, &packet_type);
             return Err(ProtocolError::Plugin(format!(
                 "No plugin handles packet type: {}",
                 packet_type
             )));
         };
 
+        if !self.is_enabled_for(device_id, &plugin_name) {
+            debug!(
+                "Plugin {} is disabled for {}, dropping packet {}",
+                plugin_name, device_id, packet.packet_type
+            );
+            return Ok(());
+        }
+
         // Get device plugins
         let device_plugins = self.device_plugins.get_mut(device_id).ok_or_else(|| {
             ProtocolError::Plugin(format!("No plugins initialized for device {}", device_id))
@@ -711,7 +1032,14 @@ impl PluginManager {
         );
 
         // Handle packet with error isolation
-        match plugin.handle_packet(packet, device).await {
+        let started = Instant::now();
+        let result = plugin.handle_packet(packet, device).await;
+        self.metrics
+            .entry(plugin_name.clone())
+            .or_default()
+            .record(started.elapsed(), result.is_err());
+
+        match result {
             Ok(()) => Ok(()),
             Err(e) => {
                 // Check if error is recoverable
@@ -747,6 +1075,21 @@ impl PluginManager {
         }
     }
 
+    /// Snapshot of packet handling counters for every plugin that has seen a packet
+    pub fn plugin_metrics(&self) -> HashMap<String, PluginMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Packet handling counters for one plugin
+    pub fn plugin_metrics_for(&self, plugin_name: &str) -> Option<PluginMetrics> {
+        self.metrics.get(plugin_name).copied()
+    }
+
+    /// Clear all plugin counters
+    pub fn reset_plugin_metrics(&mut self) {
+        self.metrics.clear();
+    }
+
     /// Check if a packet type is supported
     pub fn supports_packet_type(&self, packet_type: &str) -> bool {
         self.capability_map.contains_key(packet_type)
@@ -832,6 +1175,11 @@ impl PluginManager {
     pub fn plugin_count(&self) -> usize {
         self.factories.len()
     }
+
+    /// Whether safe mode blocks `packet_type`
+    fn is_blocked(&self, packet_type: &str) -> bool {
+        self.safe_mode && safe_mode::is_blocked_packet_type(packet_type)
+    }
 }
 
 impl Default for PluginManager {
@@ -853,6 +1201,7 @@ mod tests {
         initialized: bool,
         started: bool,
         packets_handled: usize,
+        fail: bool,
     }
 
     impl MockPlugin {
@@ -864,6 +1213,7 @@ mod tests {
                 initialized: false,
                 started: false,
                 packets_handled: 0,
+                fail: false,
             }
         }
     }
@@ -911,6 +1261,9 @@ mod tests {
 
         async fn handle_packet(&mut self, _packet: &Packet, _device: &mut Device) -> Result<()> {
             self.packets_handled += 1;
+            if self.fail {
+                return Err(ProtocolError::InvalidPacket("mock failure".to_string()));
+            }
             Ok(())
         }
     }
@@ -933,6 +1286,8 @@ mod tests {
         name: String,
         incoming: Vec<String>,
         outgoing: Vec<String>,
+        packet_types: Vec<PacketType>,
+        fail: bool,
     }
 
     impl MockPluginFactory {
@@ -941,8 +1296,22 @@ mod tests {
                 name: name.to_string(),
                 incoming: incoming.iter().map(|s| s.to_string()).collect(),
                 outgoing: outgoing.iter().map(|s| s.to_string()).collect(),
+                packet_types: Vec::new(),
+                fail: false,
             }
         }
+
+        /// Declare a body schema for the factory's plugin
+        fn with_packet_type(mut self, packet_type: PacketType) -> Self {
+            self.packet_types.push(packet_type);
+            self
+        }
+
+        /// Plugins created by this factory fail every packet
+        fn failing(mut self) -> Self {
+            self.fail = true;
+            self
+        }
     }
 
     impl PluginFactory for MockPluginFactory {
@@ -958,10 +1327,16 @@ mod tests {
             self.outgoing.clone()
         }
 
+        fn packet_types(&self) -> Vec<PacketType> {
+            self.packet_types.clone()
+        }
+
         fn create(&self) -> Box<dyn Plugin> {
             let incoming: Vec<&str> = self.incoming.iter().map(|s| s.as_str()).collect();
             let outgoing: Vec<&str> = self.outgoing.iter().map(|s| s.as_str()).collect();
-            Box::new(MockPlugin::new(&self.name, incoming, outgoing))
+            let mut plugin = MockPlugin::new(&self.name, incoming, outgoing);
+            plugin.fail = self.fail;
+            Box::new(plugin)
         }
     }
 
@@ -1095,6 +1470,63 @@ mod tests {
             .is_ok());
     }
 
+    #[tokio::test]
+    async fn test_plugin_metrics_count_packets_and_errors() {
+        let mut manager = PluginManager::new();
+        manager
+            .register_factory(Arc::new(MockPluginFactory::new(
+                "good",
+                vec!["cconnect.good"],
+                vec![],
+            )))
+            .unwrap();
+        manager
+            .register_factory(Arc::new(
+                MockPluginFactory::new("bad", vec!["cconnect.bad"], vec![]).failing(),
+            ))
+            .unwrap();
+
+        let mut device = create_test_device();
+        let device_id = device.id().to_string();
+        let (tx, _rx) = tokio::sync::mpsc::channel(100);
+        manager
+            .init_device_plugins(&device_id, &device, tx)
+            .await
+            .unwrap();
+        assert!(manager.plugin_metrics().is_empty());
+
+        let good = Packet::new("cconnect.good", serde_json::json!({}));
+        let bad = Packet::new("cconnect.bad", serde_json::json!({}));
+        for _ in 0..3 {
+            manager
+                .handle_packet(&device_id, &good, &mut device)
+                .await
+                .unwrap();
+        }
+        let _ = manager.handle_packet(&device_id, &bad, &mut device).await;
+        let _ = manager.handle_packet(&device_id, &bad, &mut device).await;
+
+        let good_metrics = manager.plugin_metrics_for("good").unwrap();
+        assert_eq!(good_metrics.packets, 3);
+        assert_eq!(good_metrics.errors, 0);
+
+        let bad_metrics = manager.plugin_metrics_for("bad").unwrap();
+        assert_eq!(bad_metrics.packets, 2);
+        assert_eq!(bad_metrics.errors, 2);
+        assert!(bad_metrics.max_handle_time >= bad_metrics.average_handle_time());
+
+        // Packets nobody handles are not attributed to any plugin
+        let unknown = Packet::new("cconnect.unknown", serde_json::json!({}));
+        assert!(manager
+            .handle_packet(&device_id, &unknown, &mut device)
+            .await
+            .is_err());
+        assert_eq!(manager.plugin_metrics().len(), 2);
+
+        manager.reset_plugin_metrics();
+        assert!(manager.plugin_metrics_for("good").is_none());
+    }
+
     #[tokio::test]
     async fn test_multiple_devices_independent_state() {
         let mut manager = PluginManager::new();
@@ -1176,4 +1608,392 @@ mod tests {
             .to_string()
             .contains("No plugin handles"));
     }
+
+    #[tokio::test]
+    async fn test_packets_validated_against_plugin_schema() {
+        let mut manager = PluginManager::new();
+        let factory = MockPluginFactory::new("test_plugin", vec!["cconnect.test"], vec![])
+            .with_packet_type(
+                PacketType::new("cconnect.test").required("value", crate::FieldType::Integer),
+            );
+        manager.register_factory(Arc::new(factory)).unwrap();
+        assert!(manager.packet_types().contains("cconnect.test"));
+
+        let mut device = create_test_device();
+        let device_id = device.id().to_string();
+        let (tx, _rx) = tokio::sync::mpsc::channel(100);
+        manager
+            .init_device_plugins(&device_id, &device, tx)
+            .await
+            .unwrap();
+
+        let valid = Packet::new("cconnect.test", serde_json::json!({ "value": 3 }));
+        assert!(manager
+            .handle_packet(&device_id, &valid, &mut device)
+            .await
+            .is_ok());
+
+        let typo = Packet::new("cconnect.test", serde_json::json!({ "valeu": 3 }));
+        let result = manager.handle_packet(&device_id, &typo, &mut device).await;
+        assert!(matches!(
+            result,
+            Err(ProtocolError::SchemaViolation(ref field, _)) if field == "value"
+        ));
+        let plugin = manager
+            .get_device_plugin(&device_id, "test_plugin")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<MockPlugin>()
+            .unwrap();
+        assert_eq!(plugin.packets_handled, 1);
+
+        manager.unregister_factory("test_plugin");
+        assert!(manager.packet_types().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_packet_types_recorded() {
+        let mut manager = PluginManager::new();
+        manager
+            .register_factory(Arc::new(MockPluginFactory::new(
+                "test_plugin",
+                vec!["cconnect.test"],
+                vec![],
+            )))
+            .unwrap();
+
+        let mut device = create_test_device();
+        let device_id = device.id().to_string();
+        for packet_type in ["cconnect.future", "kdeconnect.future", "cconnect.future"] {
+            let packet = Packet::new(packet_type, serde_json::json!({}));
+            assert!(manager
+                .handle_packet(&device_id, &packet, &mut device)
+                .await
+                .is_err());
+        }
+        assert_eq!(
+            manager.unknown_packet_types(),
+            vec!["cconnect.future", "kdeconnect.future"]
+        );
+    }
+
+    #[test]
+    fn test_conflicting_packet_schema_rejected() {
+        let mut manager = PluginManager::new();
+        let first = MockPluginFactory::new("first", vec!["cconnect.first"], vec![])
+            .with_packet_type(
+                PacketType::new("cconnect.shared").required("a", crate::FieldType::String),
+            );
+        let second = MockPluginFactory::new("second", vec!["cconnect.second"], vec![])
+            .with_packet_type(
+                PacketType::new("cconnect.shared").required("a", crate::FieldType::Bool),
+            );
+        manager.register_factory(Arc::new(first)).unwrap();
+        assert!(manager.register_factory(Arc::new(second)).is_err());
+        assert!(!manager.supports_packet_type("cconnect.second"));
+    }
+
+    #[tokio::test]
+    async fn test_disabled_plugin_skipped_and_not_advertised() {
+        let mut manager = PluginManager::new();
+        manager
+            .register_factory(Arc::new(MockPluginFactory::new(
+                "clipboard",
+                vec!["cconnect.clipboard"],
+                vec!["cconnect.clipboard"],
+            )))
+            .unwrap();
+        manager
+            .register_factory(Arc::new(MockPluginFactory::new(
+                "ping",
+                vec!["cconnect.ping"],
+                vec!["cconnect.ping"],
+            )))
+            .unwrap();
+
+        let mut device = create_test_device();
+        let device_id = device.id().to_string();
+        let (tx, _rx) = tokio::sync::mpsc::channel(100);
+        manager
+            .init_device_plugins(&device_id, &device, tx)
+            .await
+            .unwrap();
+
+        assert!(manager.set_enabled("nonexistent", false).is_err());
+        manager.set_enabled("clipboard", false).unwrap();
+        assert!(!manager.is_enabled("clipboard"));
+        assert_eq!(
+            manager.get_all_incoming_capabilities(),
+            vec!["cconnect.ping".to_string()]
+        );
+        assert_eq!(
+            manager.get_all_outgoing_capabilities(),
+            vec!["cconnect.ping".to_string()]
+        );
+
+        let packet = Packet::new("cconnect.clipboard", serde_json::json!({}));
+        assert!(manager
+            .handle_packet(&device_id, &packet, &mut device)
+            .await
+            .is_ok());
+        let handled = |manager: &PluginManager| {
+            manager
+                .get_device_plugin(&device_id, "clipboard")
+                .unwrap()
+                .as_any()
+                .downcast_ref::<MockPlugin>()
+                .unwrap()
+                .packets_handled
+        };
+        assert_eq!(handled(&manager), 0);
+
+        manager.set_enabled("clipboard", true).unwrap();
+        manager
+            .handle_packet(&device_id, &packet, &mut device)
+            .await
+            .unwrap();
+        assert_eq!(handled(&manager), 1);
+        assert_eq!(manager.get_all_incoming_capabilities().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_plugin_disabled_for_one_device() {
+        let mut manager = PluginManager::new();
+        manager
+            .register_factory(Arc::new(MockPluginFactory::new(
+                "clipboard",
+                vec!["cconnect.clipboard"],
+                vec![],
+            )))
+            .unwrap();
+
+        let mut device = create_test_device();
+        let device_id = device.id().to_string();
+        let (tx, _rx) = tokio::sync::mpsc::channel(100);
+        manager
+            .init_device_plugins(&device_id, &device, tx)
+            .await
+            .unwrap();
+
+        manager
+            .set_device_enabled(&device_id, "clipboard", false)
+            .unwrap();
+        assert!(manager.is_enabled("clipboard"));
+        assert!(!manager.is_enabled_for(&device_id, "clipboard"));
+        assert!(manager.is_enabled_for("other_device", "clipboard"));
+        // Capabilities are shared by all devices
+        assert_eq!(manager.get_all_incoming_capabilities().len(), 1);
+
+        let packet = Packet::new("cconnect.clipboard", serde_json::json!({}));
+        manager
+            .handle_packet(&device_id, &packet, &mut device)
+            .await
+            .unwrap();
+        let plugin = manager
+            .get_device_plugin(&device_id, "clipboard")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<MockPlugin>()
+            .unwrap();
+        assert_eq!(plugin.packets_handled, 0);
+
+        assert_eq!(
+            manager.list(),
+            vec![PluginStatus {
+                id: "clipboard".to_string(),
+                enabled: true,
+                disabled_devices: vec![device_id.clone()],
+            }]
+        );
+        manager
+            .set_device_enabled(&device_id, "clipboard", true)
+            .unwrap();
+        assert!(manager.list()[0].disabled_devices.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_safe_mode_blocks_execution_and_input_plugins() {
+        let mut manager = PluginManager::new();
+        manager
+            .register_factory(Arc::new(MockPluginFactory::new(
+                "runcommand",
+                vec!["cconnect.runcommand.request"],
+                vec!["cconnect.runcommand"],
+            )))
+            .unwrap();
+        manager.set_safe_mode(true);
+        assert!(manager.safe_mode());
+
+        // Registered after safe mode is on, without failing startup
+        for (name, incoming, outgoing) in [
+            (
+                "remoteinput",
+                "cconnect.mousepad.request",
+                "cconnect.mousepad.echo",
+            ),
+            ("lock", "cconnect.lock.request", "cconnect.lock"),
+            ("share", "cconnect.share.request", "cconnect.share.request"),
+            ("clipboard", "cconnect.clipboard", "cconnect.clipboard"),
+        ] {
+            manager
+                .register_factory(Arc::new(MockPluginFactory::new(
+                    name,
+                    vec![incoming],
+                    vec![outgoing],
+                )))
+                .unwrap();
+        }
+
+        let mut plugins = manager.list_plugins();
+        plugins.sort();
+        assert_eq!(plugins, vec!["clipboard", "share"]);
+
+        let mut incoming = manager.get_all_incoming_capabilities();
+        incoming.sort();
+        assert_eq!(
+            incoming,
+            vec!["cconnect.clipboard", "cconnect.share.request"]
+        );
+        assert_eq!(
+            manager.get_all_outgoing_capabilities(),
+            vec!["cconnect.clipboard", "cconnect.share.request"]
+        );
+
+        let mut device = create_test_device();
+        let device_id = device.id().to_string();
+        let (tx, _rx) = tokio::sync::mpsc::channel(100);
+        manager
+            .init_device_plugins(&device_id, &device, tx)
+            .await
+            .unwrap();
+
+        for packet_type in [
+            "cconnect.runcommand.request",
+            "kdeconnect.mousepad.request",
+            "cconnect.lock.request",
+        ] {
+            let packet = Packet::new(packet_type, serde_json::json!({}));
+            let result = manager
+                .handle_packet(&device_id, &packet, &mut device)
+                .await;
+            assert!(
+                matches!(result, Err(ProtocolError::UnsupportedFeature(_))),
+                "{} was not rejected",
+                packet_type
+            );
+        }
+
+        for packet_type in ["cconnect.share.request", "cconnect.clipboard"] {
+            let packet = Packet::new(packet_type, serde_json::json!({}));
+            assert!(manager
+                .handle_packet(&device_id, &packet, &mut device)
+                .await
+                .is_ok());
+        }
+    }
+
+    fn mock_entry(id: &str, incoming: Vec<&str>) -> PluginManifestEntry {
+        PluginManifestEntry::from_factory(Arc::new(MockPluginFactory::new(id, incoming, vec![])))
+    }
+
+    #[test]
+    fn test_register_manifest_maps_capabilities() {
+        let mut manager = PluginManager::new();
+        let manifest = PluginManifest::new()
+            .with_entry(mock_entry(
+                "alpha",
+                vec!["cconnect.alpha", "cconnect.alpha.request"],
+            ))
+            .with_entry(PluginManifestEntry::new(
+                "beta",
+                vec!["cconnect.beta".to_string()],
+                vec!["cconnect.beta.reply".to_string()],
+                Arc::new(MockPluginFactory::new("ignored", vec![], vec![])),
+            ));
+
+        let registered = manager.register_manifest(&manifest, |_| true).unwrap();
+
+        assert_eq!(registered, vec!["alpha", "beta"]);
+        assert_eq!(
+            manager.get_plugin_for_packet("cconnect.alpha"),
+            Some("alpha")
+        );
+        assert_eq!(
+            manager.get_plugin_for_packet("cconnect.alpha.request"),
+            Some("alpha")
+        );
+        // Manifest declaration wins over the wrapped factory's own name/capabilities
+        assert_eq!(manager.get_plugin_for_packet("cconnect.beta"), Some("beta"));
+        assert!(manager
+            .get_all_outgoing_capabilities()
+            .contains(&"cconnect.beta.reply".to_string()));
+    }
+
+    #[test]
+    fn test_register_manifest_applies_filter() {
+        let mut manager = PluginManager::new();
+        let manifest = PluginManifest::new()
+            .with_entry(mock_entry("alpha", vec!["cconnect.alpha"]))
+            .with_entry(mock_entry("beta", vec!["cconnect.beta"]));
+
+        let registered = manager
+            .register_manifest(&manifest, |entry| entry.id != "beta")
+            .unwrap();
+
+        assert_eq!(registered, vec!["alpha"]);
+        assert!(!manager.supports_packet_type("cconnect.beta"));
+    }
+
+    #[test]
+    fn test_register_manifest_rejects_duplicate_ids() {
+        let mut manager = PluginManager::new();
+        let manifest = PluginManifest::new()
+            .with_entry(mock_entry("alpha", vec!["cconnect.alpha"]))
+            .with_entry(mock_entry("alpha", vec!["cconnect.other"]));
+
+        let result = manager.register_manifest(&manifest, |_| true);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("already registered"));
+        // Validation happens up front, nothing is partially registered
+        assert_eq!(manager.factory_count(), 0);
+    }
+
+    #[test]
+    fn test_register_manifest_rejects_existing_id() {
+        let mut manager = PluginManager::new();
+        manager
+            .register_factory(Arc::new(MockPluginFactory::new(
+                "alpha",
+                vec!["cconnect.alpha"],
+                vec![],
+            )))
+            .unwrap();
+
+        let manifest = PluginManifest::new().with_entry(mock_entry("alpha", vec!["cconnect.x"]));
+        assert!(manager.register_manifest(&manifest, |_| true).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_manifest_plugins_handle_packets() {
+        let mut manager = PluginManager::new();
+        let manifest =
+            PluginManifest::new().with_entry(mock_entry("alpha", vec!["cconnect.alpha"]));
+        manager.register_manifest(&manifest, |_| true).unwrap();
+
+        let mut device = create_test_device();
+        let device_id = device.id().to_string();
+        let (tx, _rx) = tokio::sync::mpsc::channel(100);
+        manager
+            .init_device_plugins(&device_id, &device, tx)
+            .await
+            .unwrap();
+
+        let packet = Packet::new("cconnect.alpha", serde_json::json!({}));
+        assert!(manager
+            .handle_packet(&device_id, &packet, &mut device)
+            .await
+            .is_ok());
+    }
 }
@@ -22,7 +22,8 @@
 //!         "port": 1739,
 //!         "user": "kdeconnect",
 //!         "password": "generated_password",
-//!         "path": "/storage/emulated/0"
+//!         "path": "/storage/emulated/0",
+//!         "hostKey": "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAA..."
 //!     }
 //! }
 //! ```
@@ -34,6 +35,9 @@
 //!
 //! `sshfs -p <port> <user>@<ip>:/ <mountpoint> -o password_stdin`
 //!
+//! `hostKey` is optional. When present the server is pinned to it; otherwise
+//! the first key seen for the address is pinned (trust on first use).
+//!
 //! ## Public API
 //!
 //! ```rust,ignore
@@ -51,6 +55,10 @@
 //! if plugin.has_shares().await {
 //!     println!("SFTP shares available");
 //! }
+//!
+//! // Browse or fetch single files without mounting (see `sftp_browser`)
+//! let browser = plugin.browser(Arc::new(SftpCommandBackend::new()));
+//! let entries = browser.list_remote_dir("device-id", "Download").await?;
 //! ```
 //!
 //! ## References
@@ -66,6 +74,7 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
+use super::sftp_browser::{RemoteFileBrowser, SftpBackend};
 use super::{Plugin, PluginFactory};
 
 /// Packet type for SFTP connection info
@@ -85,6 +94,10 @@ pub struct SftpInfo {
     pub password: String,
     /// Path to mount (optional)
     pub path: Option<String>,
+    /// Server host key as an OpenSSH public key line, e.g. `ssh-ed25519 AAAA...`
+    /// (optional; used to pin the server when connecting)
+    #[serde(default, rename = "hostKey", skip_serializing_if = "Option::is_none")]
+    pub host_key: Option<String>,
     /// Timestamp when this info was received
     #[serde(skip)]
     pub received_at: Option<std::time::Instant>,
@@ -217,6 +230,14 @@ impl NetworkSharePlugin {
     pub async fn clear_shares(&self) {
         self.shares.write().await.clear();
     }
+
+    /// Create a remote file browser over this plugin's shares
+    ///
+    /// The browser sees shares as they are received, so it can be created once and
+    /// kept for the plugin's lifetime.
+    pub fn browser(&self, backend: Arc<dyn SftpBackend>) -> RemoteFileBrowser {
+        RemoteFileBrowser::new(self.shares.clone(), backend)
+    }
 }
 
 impl Default for NetworkSharePlugin {
@@ -372,6 +393,7 @@ mod tests {
             user: "test".to_string(),
             password: "pass".to_string(),
             path: None,
+            host_key: None,
             received_at: None,
         };
         assert_eq!(info.effective_port(), 22);
@@ -385,6 +407,7 @@ mod tests {
             user: "test".to_string(),
             password: "pass".to_string(),
             path: None,
+            host_key: None,
             received_at: None,
         };
         assert_eq!(info.effective_port(), 1739);
@@ -398,6 +421,7 @@ mod tests {
             user: "test".to_string(),
             password: "pass".to_string(),
             path: None,
+            host_key: None,
             received_at: None,
         };
         assert_eq!(info.effective_path(), "/");
@@ -411,6 +435,7 @@ mod tests {
             user: "test".to_string(),
             password: "pass".to_string(),
             path: Some("/storage/emulated/0".to_string()),
+            host_key: None,
             received_at: None,
         };
         assert_eq!(info.effective_path(), "/storage/emulated/0");
@@ -424,6 +449,7 @@ mod tests {
             user: "kdeconnect".to_string(),
             password: "secret".to_string(),
             path: Some("/sdcard".to_string()),
+            host_key: None,
             received_at: None,
         };
         assert_eq!(
@@ -440,6 +466,7 @@ mod tests {
             user: "kdeconnect".to_string(),
             password: "secret".to_string(),
             path: Some("/sdcard".to_string()),
+            host_key: None,
             received_at: None,
         };
         let cmd = info.sshfs_command("/mnt/phone");
@@ -457,6 +484,7 @@ mod tests {
             user: "test".to_string(),
             password: "pass".to_string(),
             path: None,
+            host_key: None,
             received_at: None,
         };
         assert!(!info.is_fresh());
@@ -153,7 +153,36 @@
 //! - **Dismissal Sync**: Dismiss notification on one device, gone on all
 //! - **Action Buttons**: Trigger notification actions (future)
 //! - **Inline Replies**: Reply to messages directly (future)
-//! - **Icon Transfer**: Download notification icons (future)
+//! - **Icon Transfer**: Download notification icons sent as payloads
+//!
+//! ## Icon Payloads
+//!
+//! Android attaches the app icon as a payload (`payloadSize`,
+//! `payloadTransferInfo`, with `payloadHash` identifying the icon). When a
+//! [`NotificationIconCache`] is configured, the plugin downloads the icon
+//! (bounded by [`NotificationIconCache::max_size`]), stores it under its hash and
+//! reports the local path in the [`NotificationPosted`] event. Missing, oversized
+//! or failed icons fall back to [`DEFAULT_NOTIFICATION_ICON`].
+//!
+//! ## Rate Limiting
+//!
+//! Chatty apps can flood the desktop. Received notifications pass through a
+//! [`NotificationRateLimit`]: rapid updates to one notification ID are
+//! coalesced into a single trailing update carrying the latest content, and
+//! each app may post at most a burst of notifications per window, with the
+//! excess dropped and logged. Calls, alarms and critical-urgency notifications
+//! bypass the limiter.
+//!
+//! ## Notification Sinks
+//!
+//! Besides the [`NotificationPosted`] events, received notifications can be
+//! handed to a [`NotificationSink`] that displays them: it is asked to show a
+//! notification the first time its ID is posted, to update it on later posts
+//! and to dismiss it when the device cancels it. Calls reach the sink one at a
+//! time, in the order the plugin made them. [`FreedesktopNotificationSink`]
+//! displays them through the `org.freedesktop.Notifications` D-Bus service;
+//! other frontends, or tests, plug in their own sink with
+//! [`NotificationPlugin::set_sink`].
 //!
 //! ## Use Cases
 //!
@@ -186,12 +215,15 @@
 //!
 //! - [Valent Protocol - Notification](https://valent.andyholmes.ca/documentation/protocol.html)
 
-use crate::{Device, Packet, Result};
+use crate::{Device, Packet, ProtocolError, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
 use tracing::{debug, info, warn};
 
 use super::{Plugin, PluginFactory};
@@ -772,6 +804,542 @@ impl Notification {
     }
 }
 
+/// Icon name used when a notification has no usable icon
+pub const DEFAULT_NOTIFICATION_ICON: &str = "phone-symbolic";
+
+/// Default largest icon payload downloaded (512 KiB)
+pub const DEFAULT_MAX_ICON_SIZE: u64 = 512 * 1024;
+
+/// Capacity of the notification event channel
+const EVENT_CHANNEL_CAPACITY: usize = 32;
+
+/// Icon to display for a received notification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationIcon {
+    /// App icon downloaded from the device
+    Cached(PathBuf),
+    /// No icon was sent, or it could not be fetched
+    Default,
+}
+
+impl NotificationIcon {
+    /// Icon name or path to pass to the desktop notification server
+    pub fn as_icon_name(&self) -> String {
+        match self {
+            Self::Cached(path) => path.to_string_lossy().into_owned(),
+            Self::Default => DEFAULT_NOTIFICATION_ICON.to_string(),
+        }
+    }
+}
+
+/// Emitted when a notification is received, once its icon is resolved
+#[derive(Debug, Clone)]
+pub struct NotificationPosted {
+    /// Device the notification came from
+    pub device_id: String,
+    /// The notification
+    pub notification: Notification,
+    /// Icon to display
+    pub icon: NotificationIcon,
+}
+
+/// Displays notifications received from devices
+///
+/// [`show`](Self::show) is called the first time a notification ID from a
+/// device is posted, [`update`](Self::update) for later posts with the same
+/// ID, and [`dismiss`](Self::dismiss) once the device cancels it. Errors are
+/// logged by the plugin and otherwise ignored.
+#[async_trait]
+pub trait NotificationSink: Send + Sync + std::fmt::Debug {
+    /// Display a new notification
+    async fn show(&self, posted: &NotificationPosted) -> Result<()>;
+
+    /// Replace the content of a notification that is already displayed
+    async fn update(&self, posted: &NotificationPosted) -> Result<()>;
+
+    /// Remove a displayed notification
+    async fn dismiss(&self, device_id: &str, notification_id: &str) -> Result<()>;
+}
+
+/// Notification sink backed by the `org.freedesktop.Notifications` service
+///
+/// Connects to the session bus on first use and remembers the ID the
+/// notification server assigned to each notification, so updates replace the
+/// displayed notification and cancels close it.
+#[derive(Debug, Default)]
+pub struct FreedesktopNotificationSink {
+    connection: tokio::sync::OnceCell<zbus::Connection>,
+    /// Server notification IDs by (device ID, notification ID)
+    server_ids: Mutex<HashMap<(String, String), u32>>,
+}
+
+impl FreedesktopNotificationSink {
+    /// Create a sink; the session bus is connected on first use
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn proxy(&self) -> Result<zbus::Proxy<'static>> {
+        let connection = self
+            .connection
+            .get_or_try_init(zbus::Connection::session)
+            .await
+            .map_err(|e| ProtocolError::Plugin(format!("Session bus unavailable: {}", e)))?;
+        zbus::Proxy::new(
+            connection,
+            "org.freedesktop.Notifications",
+            "/org/freedesktop/Notifications",
+            "org.freedesktop.Notifications",
+        )
+        .await
+        .map_err(|e| ProtocolError::Plugin(format!("Notification service unavailable: {}", e)))
+    }
+
+    fn key(device_id: &str, notification_id: &str) -> (String, String) {
+        (device_id.to_string(), notification_id.to_string())
+    }
+
+    async fn notify(&self, posted: &NotificationPosted) -> Result<()> {
+        let key = Self::key(&posted.device_id, &posted.notification.id);
+        let replaces_id = self
+            .server_ids
+            .lock()
+            .ok()
+            .and_then(|ids| ids.get(&key).copied())
+            .unwrap_or(0);
+
+        let notification = &posted.notification;
+        let actions: Vec<&str> = Vec::new();
+        let mut hints: HashMap<&str, zbus::zvariant::Value<'_>> = HashMap::new();
+        hints.insert(
+            "urgency",
+            zbus::zvariant::Value::U8(notification.get_urgency().to_byte()),
+        );
+        if let Some(category) = &notification.category {
+            hints.insert("category", zbus::zvariant::Value::from(category.as_str()));
+        }
+
+        let server_id: u32 = self
+            .proxy()
+            .await?
+            .call_method(
+                "Notify",
+                &(
+                    notification.app_name.as_str(),
+                    replaces_id,
+                    posted.icon.as_icon_name(),
+                    notification.title.as_str(),
+                    notification.text.as_str(),
+                    actions,
+                    hints,
+                    -1i32,
+                ),
+            )
+            .await
+            .map_err(|e| ProtocolError::Plugin(format!("Failed to show notification: {}", e)))?
+            .body()
+            .deserialize()
+            .map_err(|e| ProtocolError::Plugin(format!("Invalid notification ID: {}", e)))?;
+
+        if let Ok(mut ids) = self.server_ids.lock() {
+            ids.insert(key, server_id);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl NotificationSink for FreedesktopNotificationSink {
+    async fn show(&self, posted: &NotificationPosted) -> Result<()> {
+        self.notify(posted).await
+    }
+
+    async fn update(&self, posted: &NotificationPosted) -> Result<()> {
+        self.notify(posted).await
+    }
+
+    async fn dismiss(&self, device_id: &str, notification_id: &str) -> Result<()> {
+        let server_id = self
+            .server_ids
+            .lock()
+            .ok()
+            .and_then(|mut ids| ids.remove(&Self::key(device_id, notification_id)));
+        let Some(server_id) = server_id else {
+            return Ok(());
+        };
+        self.proxy()
+            .await?
+            .call_method("CloseNotification", &(server_id,))
+            .await
+            .map_err(|e| ProtocolError::Plugin(format!("Failed to close notification: {}", e)))?;
+        Ok(())
+    }
+}
+
+/// Call made on a [`NotificationSink`]
+#[derive(Debug)]
+enum SinkCall {
+    Post(NotificationPosted),
+    Dismiss {
+        device_id: String,
+        notification_id: String,
+    },
+}
+
+/// Feeds a [`NotificationSink`] from a single task so calls arrive in order
+#[derive(Clone)]
+struct SinkDispatcher {
+    sink: Arc<dyn NotificationSink>,
+    queue: Arc<OnceLock<mpsc::UnboundedSender<SinkCall>>>,
+}
+
+impl SinkDispatcher {
+    fn new(sink: Arc<dyn NotificationSink>) -> Self {
+        Self {
+            sink,
+            queue: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// Queue a call, starting the dispatch task on first use
+    fn send(&self, call: SinkCall) {
+        let queue = self.queue.get_or_init(|| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(Self::run(self.sink.clone(), rx));
+            tx
+        });
+        let _ = queue.send(call);
+    }
+
+    async fn run(sink: Arc<dyn NotificationSink>, mut calls: mpsc::UnboundedReceiver<SinkCall>) {
+        let mut shown: HashSet<(String, String)> = HashSet::new();
+        while let Some(call) = calls.recv().await {
+            let result = match call {
+                SinkCall::Post(posted) => {
+                    let key = (posted.device_id.clone(), posted.notification.id.clone());
+                    if shown.insert(key) {
+                        sink.show(&posted).await
+                    } else {
+                        sink.update(&posted).await
+                    }
+                }
+                SinkCall::Dismiss {
+                    device_id,
+                    notification_id,
+                } => {
+                    if !shown.remove(&(device_id.clone(), notification_id.clone())) {
+                        continue;
+                    }
+                    sink.dismiss(&device_id, &notification_id).await
+                }
+            };
+            if let Err(e) = result {
+                warn!("Notification sink failed: {}", e);
+            }
+        }
+    }
+}
+
+/// On-disk cache of notification icons downloaded from devices
+///
+/// Icons are stored by their `payloadHash`, so an app's icon is only
+/// transferred once.
+#[derive(Debug, Clone)]
+pub struct NotificationIconCache {
+    dir: PathBuf,
+    max_size: u64,
+}
+
+impl NotificationIconCache {
+    /// Create a cache storing icons in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_size: DEFAULT_MAX_ICON_SIZE,
+        }
+    }
+
+    /// Set the largest icon payload that will be downloaded
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Cache directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Largest icon payload that will be downloaded
+    pub fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    /// Path of a cached icon, if present
+    pub fn cached_path(&self, hash: &str) -> Option<PathBuf> {
+        self.path_for(hash).ok().filter(|path| path.is_file())
+    }
+
+    /// Download an icon payload into the cache, returning its path
+    ///
+    /// Returns the existing file without downloading if the icon is cached.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidPacket`] for an invalid hash or a size
+    /// outside `1..=max_size`, or the transfer error if the download fails.
+    pub async fn fetch(
+        &self,
+        hash: &str,
+        host: &str,
+        port: u16,
+        size: u64,
+        tls_config: Option<&crate::TlsConfig>,
+    ) -> Result<PathBuf> {
+        let path = self.path_for(hash)?;
+        if path.is_file() {
+            return Ok(path);
+        }
+        if size == 0 || size > self.max_size {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Icon payload of {} bytes exceeds limit of {} bytes",
+                size, self.max_size
+            )));
+        }
+
+        // Received under a staging name, so a partial icon is never cached
+        match tls_config {
+            Some(config) => {
+                crate::TlsPayloadClient::new(host, port, config)
+                    .await?
+                    .receive_file(&path, size)
+                    .await?
+            }
+            None => {
+                crate::PayloadClient::new(host, port)
+                    .await?
+                    .receive_file(&path, size)
+                    .await?
+            }
+        }
+        debug!("Cached notification icon {} at {:?}", hash, path);
+        Ok(path)
+    }
+
+    /// Resolve the icon for a notification packet, falling back to the default
+    async fn resolve(
+        &self,
+        packet: &Packet,
+        notification: &Notification,
+        host: Option<&str>,
+        tls_config: Option<&crate::TlsConfig>,
+    ) -> NotificationIcon {
+        let Some(hash) = notification.payload_hash.as_deref() else {
+            return NotificationIcon::Default;
+        };
+        if let Some(path) = self.cached_path(hash) {
+            return NotificationIcon::Cached(path);
+        }
+
+        let size = packet.payload_size.unwrap_or(0).max(0) as u64;
+        let port = packet
+            .payload_transfer_info
+            .as_ref()
+            .and_then(|info| info.get("port"))
+            .and_then(|v| v.as_u64())
+            .and_then(|p| u16::try_from(p).ok());
+        let (Some(host), Some(port)) = (host, port) else {
+            return NotificationIcon::Default;
+        };
+
+        match self.fetch(hash, host, port, size, tls_config).await {
+            Ok(path) => NotificationIcon::Cached(path),
+            Err(e) => {
+                warn!(
+                    "Using default icon for notification {}: {}",
+                    notification.id, e
+                );
+                NotificationIcon::Default
+            }
+        }
+    }
+
+    fn path_for(&self, hash: &str) -> Result<PathBuf> {
+        if hash.is_empty() || hash.len() > 128 || !hash.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Invalid notification icon hash: {:?}",
+                hash
+            )));
+        }
+        Ok(self.dir.join(format!("{}.png", hash)))
+    }
+}
+
+/// Limits on how fast received notifications are posted to the desktop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotificationRateLimit {
+    /// Updates to one notification ID within this window are merged
+    pub coalesce_window: Duration,
+    /// Notifications one app may post per `app_window`
+    pub app_burst: usize,
+    /// Window over which `app_burst` is counted
+    pub app_window: Duration,
+    /// Categories that are never limited
+    pub bypass_categories: Vec<String>,
+}
+
+impl Default for NotificationRateLimit {
+    fn default() -> Self {
+        Self {
+            coalesce_window: Duration::from_secs(1),
+            app_burst: 5,
+            app_window: Duration::from_secs(10),
+            bypass_categories: vec!["call".to_string(), "alarm".to_string()],
+        }
+    }
+}
+
+impl NotificationRateLimit {
+    /// Whether a notification bypasses the limiter
+    pub fn bypasses(&self, notification: &Notification) -> bool {
+        notification.get_urgency() == NotificationUrgency::Critical
+            || notification
+                .category
+                .as_deref()
+                .is_some_and(|c| self.bypass_categories.iter().any(|b| b == c))
+    }
+}
+
+/// What to do with a received notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Admission {
+    /// Post it now
+    Post,
+    /// Hold it and post the latest version at the given time
+    Coalesce(Instant),
+    /// Drop it
+    Throttle,
+}
+
+/// Tracks recent posts for coalescing and per-app rate limiting
+#[derive(Debug)]
+struct NotificationLimiter {
+    config: NotificationRateLimit,
+    /// When each notification ID was last posted
+    last_posted: HashMap<String, Instant>,
+    /// Recent post times per app
+    app_posts: HashMap<String, VecDeque<Instant>>,
+}
+
+impl NotificationLimiter {
+    fn new(config: NotificationRateLimit) -> Self {
+        Self {
+            config,
+            last_posted: HashMap::new(),
+            app_posts: HashMap::new(),
+        }
+    }
+
+    fn admit(&mut self, notification: &Notification, now: Instant) -> Admission {
+        if self.config.bypasses(notification) {
+            return Admission::Post;
+        }
+
+        let window = self.config.coalesce_window;
+        self.last_posted
+            .retain(|_, posted| now.duration_since(*posted) < window);
+        if let Some(posted) = self.last_posted.get(&notification.id) {
+            return Admission::Coalesce(*posted + window);
+        }
+
+        let app_window = self.config.app_window;
+        let posts = self
+            .app_posts
+            .entry(notification.app_name.clone())
+            .or_default();
+        while posts
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= app_window)
+        {
+            posts.pop_front();
+        }
+        if posts.len() >= self.config.app_burst {
+            return Admission::Throttle;
+        }
+        posts.push_back(now);
+
+        self.last_posted.insert(notification.id.clone(), now);
+        Admission::Post
+    }
+
+    /// Record the trailing post of a coalesced notification
+    fn mark_posted(&mut self, id: &str, now: Instant) {
+        self.last_posted.insert(id.to_string(), now);
+    }
+
+    fn forget(&mut self, id: &str) {
+        self.last_posted.remove(id);
+    }
+}
+
+/// Latest version of a coalesced notification, waiting to be posted
+struct PendingUpdate {
+    packet: Packet,
+    host: Option<String>,
+    notification: Notification,
+}
+
+/// Posts notification events, resolving icons first
+#[derive(Clone)]
+struct NotificationPoster {
+    events: broadcast::Sender<NotificationPosted>,
+    sink: Option<SinkDispatcher>,
+    icon_cache: Option<Arc<NotificationIconCache>>,
+    tls_config: Option<Arc<crate::TlsConfig>>,
+}
+
+impl NotificationPoster {
+    fn post(
+        &self,
+        packet: Packet,
+        device_id: String,
+        host: Option<String>,
+        notification: Notification,
+    ) {
+        let posted = NotificationPosted {
+            device_id,
+            notification,
+            icon: NotificationIcon::Default,
+        };
+
+        let Some(cache) = self.icon_cache.clone() else {
+            self.publish(posted);
+            return;
+        };
+
+        let poster = self.clone();
+        let tls_config = self.tls_config.clone();
+        tokio::spawn(async move {
+            let icon = cache
+                .resolve(
+                    &packet,
+                    &posted.notification,
+                    host.as_deref(),
+                    tls_config.as_deref(),
+                )
+                .await;
+            poster.publish(NotificationPosted { icon, ..posted });
+        });
+    }
+
+    fn publish(&self, posted: NotificationPosted) {
+        if let Some(sink) = &self.sink {
+            sink.send(SinkCall::Post(posted.clone()));
+        }
+        let _ = self.events.send(posted);
+    }
+}
+
 /// Notification sync plugin
 ///
 /// Handles notification mirroring between devices.
@@ -797,13 +1365,47 @@ impl Notification {
 /// // Initially no notifications
 /// assert_eq!(plugin.notification_count(), 0);
 /// ```
-#[derive(Debug)]
 pub struct NotificationPlugin {
     /// Device ID this plugin is attached to
     device_id: Option<String>,
 
     /// Active notifications by ID
     notifications: Arc<RwLock<HashMap<String, Notification>>>,
+
+    /// Where icon payloads are stored (`None` = icons are not downloaded)
+    icon_cache: Option<Arc<NotificationIconCache>>,
+
+    /// TLS configuration for icon payload downloads
+    tls_config: Option<Arc<crate::TlsConfig>>,
+
+    /// Received notification events
+    events: broadcast::Sender<NotificationPosted>,
+
+    /// Where received notifications are displayed (`None` = events only)
+    sink: Option<SinkDispatcher>,
+
+    /// Coalescing and per-app rate limiting (`None` = post everything)
+    limiter: Option<Arc<Mutex<NotificationLimiter>>>,
+
+    /// Coalesced updates waiting for their window to end, by notification ID
+    pending_updates: Arc<Mutex<HashMap<String, PendingUpdate>>>,
+}
+
+// Manual Debug impl to skip tls_config (TlsConfig doesn't implement Debug)
+impl std::fmt::Debug for NotificationPlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NotificationPlugin")
+            .field("device_id", &self.device_id)
+            .field("notifications", &self.notifications)
+            .field("icon_cache", &self.icon_cache)
+            .field("limiter", &self.limiter)
+            .field("sink", &self.sink.as_ref().map(|d| &d.sink))
+            .field(
+                "tls_config",
+                &self.tls_config.as_ref().map(|_| "<TlsConfig>"),
+            )
+            .finish()
+    }
 }
 
 impl NotificationPlugin {
@@ -821,6 +1423,126 @@ impl NotificationPlugin {
         Self {
             device_id: None,
             notifications: Arc::new(RwLock::new(HashMap::new())),
+            icon_cache: None,
+            tls_config: None,
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            sink: None,
+            limiter: Some(Arc::new(Mutex::new(NotificationLimiter::new(
+                NotificationRateLimit::default(),
+            )))),
+            pending_updates: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Configure rate limiting of posted notifications (`None` disables it)
+    pub fn set_rate_limit(&mut self, rate_limit: Option<NotificationRateLimit>) {
+        self.limiter =
+            rate_limit.map(|config| Arc::new(Mutex::new(NotificationLimiter::new(config))));
+    }
+
+    /// Download icon payloads into `cache`
+    pub fn set_icon_cache(&mut self, cache: NotificationIconCache) {
+        self.icon_cache = Some(Arc::new(cache));
+    }
+
+    /// Set the TLS configuration used for icon payload downloads
+    ///
+    /// Without it icons are fetched over plain TCP.
+    pub fn set_tls_config(&mut self, config: Arc<crate::TlsConfig>) {
+        self.tls_config = Some(config);
+    }
+
+    /// Display received notifications through `sink`
+    ///
+    /// Use [`FreedesktopNotificationSink`] for the desktop notification
+    /// server. Without a sink notifications are only reported through
+    /// [`subscribe`](Self::subscribe).
+    pub fn set_sink(&mut self, sink: Arc<dyn NotificationSink>) {
+        self.sink = Some(SinkDispatcher::new(sink));
+    }
+
+    /// Subscribe to received notifications
+    ///
+    /// Events are sent once the notification's icon has been resolved.
+    pub fn subscribe(&self) -> broadcast::Receiver<NotificationPosted> {
+        self.events.subscribe()
+    }
+
+    fn poster(&self) -> NotificationPoster {
+        NotificationPoster {
+            events: self.events.clone(),
+            sink: self.sink.clone(),
+            icon_cache: self.icon_cache.clone(),
+            tls_config: self.tls_config.clone(),
+        }
+    }
+
+    /// Post a received notification, subject to the rate limit
+    fn emit_posted(&self, packet: &Packet, device: &Device, notification: Notification) {
+        let poster = self.poster();
+        let device_id = device.id().to_string();
+        let Some(limiter) = self.limiter.clone() else {
+            poster.post(packet.clone(), device_id, device.host.clone(), notification);
+            return;
+        };
+
+        let admission = match limiter.lock() {
+            Ok(mut limiter) => limiter.admit(&notification, Instant::now()),
+            Err(_) => Admission::Post,
+        };
+
+        match admission {
+            Admission::Post => {
+                poster.post(packet.clone(), device_id, device.host.clone(), notification)
+            }
+            Admission::Throttle => {
+                warn!(
+                    "Dropping notification {} from {}: {} exceeded its burst limit",
+                    notification.id,
+                    device.name(),
+                    notification.app_name
+                );
+            }
+            Admission::Coalesce(flush_at) => {
+                let id = notification.id.clone();
+                let update = PendingUpdate {
+                    packet: packet.clone(),
+                    host: device.host.clone(),
+                    notification,
+                };
+                let Ok(mut pending) = self.pending_updates.lock() else {
+                    return;
+                };
+                if pending.insert(id.clone(), update).is_some() {
+                    debug!("Merged rapid update to notification {}", id);
+                    return;
+                }
+                drop(pending);
+
+                // First held update for this ID: post the latest one when the window ends
+                let pending_updates = self.pending_updates.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep_until(flush_at.into()).await;
+                    let update = pending_updates.lock().ok().and_then(|mut p| p.remove(&id));
+                    let Some(update) = update else {
+                        return;
+                    };
+                    if let Ok(mut limiter) = limiter.lock() {
+                        limiter.mark_posted(&id, Instant::now());
+                    }
+                    poster.post(update.packet, device_id, update.host, update.notification);
+                });
+            }
+        }
+    }
+
+    /// Drop rate limiter state for a cancelled notification
+    fn forget_posted(&self, id: &str) {
+        if let Ok(mut pending) = self.pending_updates.lock() {
+            pending.remove(id);
+        }
+        if let Some(Ok(mut limiter)) = self.limiter.as_ref().map(|l| l.lock()) {
+            limiter.forget(id);
         }
     }
 
@@ -1150,6 +1872,13 @@ impl NotificationPlugin {
         if let Some(is_cancel) = packet.body.get("isCancel").and_then(|v| v.as_bool()) {
             if is_cancel {
                 if let Some(id) = packet.body.get("id").and_then(|v| v.as_str()) {
+                    self.forget_posted(id);
+                    if let Some(sink) = &self.sink {
+                        sink.send(SinkCall::Dismiss {
+                            device_id: device.id().to_string(),
+                            notification_id: id.to_string(),
+                        });
+                    }
                     if let Ok(mut notifications) = self.notifications.write() {
                         notifications.remove(id);
                         info!(
@@ -1174,6 +1903,7 @@ impl NotificationPlugin {
                 if let Ok(mut notifications) = self.notifications.write() {
                     notifications.insert(id.clone(), notification.clone());
                 }
+                self.emit_posted(packet, device, notification.clone());
 
                 // Log notification
                 if silent {
@@ -1411,6 +2141,265 @@ mod tests {
         Device::from_discovery(info)
     }
 
+    async fn next_posted(
+        events: &mut broadcast::Receiver<NotificationPosted>,
+    ) -> NotificationPosted {
+        tokio::time::timeout(std::time::Duration::from_secs(5), events.recv())
+            .await
+            .expect("notification event")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_icon_payload_is_cached() {
+        use std::io::Write;
+
+        let cache_dir = tempfile::tempdir().unwrap();
+        let mut plugin = NotificationPlugin::new();
+        plugin.set_icon_cache(NotificationIconCache::new(cache_dir.path()));
+        let mut events = plugin.subscribe();
+
+        let icon_data = b"\x89PNG\r\n\x1a\nnot really an icon";
+        let mut icon_file = tempfile::NamedTempFile::new().unwrap();
+        icon_file.write_all(icon_data).unwrap();
+        let server = crate::PayloadServer::new().await.unwrap();
+        let port = server.port();
+        let icon_path = icon_file.path().to_owned();
+        tokio::spawn(async move { server.send_file(icon_path).await });
+
+        let mut notif = Notification::new("icon-1", "Messages", "Title", "Text", true);
+        notif.payload_hash = Some("5d41402abc4b2a76b9719d911017c592".to_string());
+        let packet = plugin
+            .create_notification_packet(&notif)
+            .with_payload_size(icon_data.len() as i64)
+            .with_payload_transfer_info(HashMap::from([("port".to_string(), json!(port))]));
+
+        let mut device = create_test_device();
+        device.host = Some("127.0.0.1".to_string());
+        plugin.handle_packet(&packet, &mut device).await.unwrap();
+
+        let posted = next_posted(&mut events).await;
+        assert_eq!(posted.notification.id, "icon-1");
+        let NotificationIcon::Cached(path) = posted.icon else {
+            panic!("expected cached icon, got {:?}", posted.icon);
+        };
+        assert!(path.starts_with(cache_dir.path()));
+        assert_eq!(std::fs::read(&path).unwrap(), icon_data);
+
+        // The same icon is served from the cache without a transfer
+        let cache = NotificationIconCache::new(cache_dir.path());
+        assert_eq!(
+            cache.cached_path("5d41402abc4b2a76b9719d911017c592"),
+            Some(path)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_notification_without_icon_uses_default() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let mut plugin = NotificationPlugin::new();
+        plugin.set_icon_cache(NotificationIconCache::new(cache_dir.path()));
+        let mut events = plugin.subscribe();
+
+        let notif = Notification::new("plain-1", "Messages", "Title", "Text", true);
+        let packet = plugin.create_notification_packet(&notif);
+        plugin
+            .handle_packet(&packet, &mut create_test_device())
+            .await
+            .unwrap();
+
+        let posted = next_posted(&mut events).await;
+        assert_eq!(posted.icon, NotificationIcon::Default);
+        assert_eq!(posted.icon.as_icon_name(), DEFAULT_NOTIFICATION_ICON);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_icon_uses_default() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let mut plugin = NotificationPlugin::new();
+        plugin.set_icon_cache(NotificationIconCache::new(cache_dir.path()).with_max_size(16));
+        let mut events = plugin.subscribe();
+
+        let mut notif = Notification::new("big-1", "Photos", "Title", "Text", true);
+        notif.payload_hash = Some("abc123".to_string());
+        let packet = plugin
+            .create_notification_packet(&notif)
+            .with_payload_size(1024)
+            .with_payload_transfer_info(HashMap::from([("port".to_string(), json!(1739))]));
+        let mut device = create_test_device();
+        device.host = Some("127.0.0.1".to_string());
+        plugin.handle_packet(&packet, &mut device).await.unwrap();
+
+        let posted = next_posted(&mut events).await;
+        assert_eq!(posted.icon, NotificationIcon::Default);
+        assert!(std::fs::read_dir(cache_dir.path())
+            .unwrap()
+            .next()
+            .is_none());
+    }
+
+    fn drain_posted(events: &mut broadcast::Receiver<NotificationPosted>) -> Vec<Notification> {
+        std::iter::from_fn(|| events.try_recv().ok())
+            .map(|posted| posted.notification)
+            .collect()
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum SinkEvent {
+        Show(String, String, String),
+        Update(String, String, String),
+        Dismiss(String, String),
+    }
+
+    #[derive(Debug, Default)]
+    struct RecordingSink {
+        events: Mutex<Vec<SinkEvent>>,
+    }
+
+    impl RecordingSink {
+        async fn wait_for(&self, count: usize) -> Vec<SinkEvent> {
+            for _ in 0..100 {
+                let events = self.events.lock().unwrap().clone();
+                if events.len() >= count {
+                    return events;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            panic!("sink saw {:?}", self.events.lock().unwrap());
+        }
+    }
+
+    #[async_trait]
+    impl NotificationSink for RecordingSink {
+        async fn show(&self, posted: &NotificationPosted) -> Result<()> {
+            self.events.lock().unwrap().push(SinkEvent::Show(
+                posted.device_id.clone(),
+                posted.notification.id.clone(),
+                posted.notification.title.clone(),
+            ));
+            Ok(())
+        }
+
+        async fn update(&self, posted: &NotificationPosted) -> Result<()> {
+            self.events.lock().unwrap().push(SinkEvent::Update(
+                posted.device_id.clone(),
+                posted.notification.id.clone(),
+                posted.notification.title.clone(),
+            ));
+            Ok(())
+        }
+
+        async fn dismiss(&self, device_id: &str, notification_id: &str) -> Result<()> {
+            self.events.lock().unwrap().push(SinkEvent::Dismiss(
+                device_id.to_string(),
+                notification_id.to_string(),
+            ));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sink_shows_updates_and_dismisses() {
+        let sink = Arc::new(RecordingSink::default());
+        let mut plugin = NotificationPlugin::new();
+        plugin.set_rate_limit(None);
+        plugin.set_sink(sink.clone());
+        let mut device = create_test_device();
+        let device_id = device.id().to_string();
+
+        for title in ["Alice", "Alice (2)"] {
+            let notif = Notification::new("msg-1", "Messages", title, "Hi", true);
+            let packet = plugin.create_notification_packet(&notif);
+            plugin.handle_packet(&packet, &mut device).await.unwrap();
+        }
+        let cancel = plugin.create_cancel_packet("msg-1");
+        plugin.handle_packet(&cancel, &mut device).await.unwrap();
+        // Cancelling something never shown does not reach the sink
+        let cancel = plugin.create_cancel_packet("unknown");
+        plugin.handle_packet(&cancel, &mut device).await.unwrap();
+
+        let events = sink.wait_for(3).await;
+        assert_eq!(
+            events,
+            vec![
+                SinkEvent::Show(device_id.clone(), "msg-1".into(), "Alice".into()),
+                SinkEvent::Update(device_id.clone(), "msg-1".into(), "Alice (2)".into()),
+                SinkEvent::Dismiss(device_id, "msg-1".into()),
+            ]
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(sink.events.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_rapid_updates_coalesce() {
+        let mut plugin = NotificationPlugin::new();
+        plugin.set_rate_limit(Some(NotificationRateLimit {
+            coalesce_window: Duration::from_millis(100),
+            ..Default::default()
+        }));
+        let mut events = plugin.subscribe();
+        let mut device = create_test_device();
+
+        for i in 0..5 {
+            let notif = Notification::new("chat-1", "Signal", "Group", format!("msg {}", i), true);
+            let packet = plugin.create_notification_packet(&notif);
+            plugin.handle_packet(&packet, &mut device).await.unwrap();
+        }
+
+        let posted = drain_posted(&mut events);
+        assert_eq!(posted.len(), 1);
+        assert_eq!(posted[0].text, "msg 0");
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let posted = drain_posted(&mut events);
+        assert_eq!(posted.len(), 1, "updates should merge into one");
+        assert_eq!(posted[0].text, "msg 4");
+        assert_eq!(plugin.get_notification("chat-1").unwrap().text, "msg 4");
+    }
+
+    #[tokio::test]
+    async fn test_burst_throttled_but_calls_pass() {
+        let mut plugin = NotificationPlugin::new();
+        plugin.set_rate_limit(Some(NotificationRateLimit {
+            app_burst: 3,
+            ..Default::default()
+        }));
+        let mut events = plugin.subscribe();
+        let mut device = create_test_device();
+
+        for i in 0..6 {
+            let notif = Notification::new(format!("wa-{}", i), "WhatsApp", "Group", "hi", true);
+            let packet = plugin.create_notification_packet(&notif);
+            plugin.handle_packet(&packet, &mut device).await.unwrap();
+        }
+
+        let mut call = Notification::new("wa-call", "WhatsApp", "Incoming call", "Alice", false);
+        call.category = Some("call".to_string());
+        let packet = plugin.create_notification_packet(&call);
+        plugin.handle_packet(&packet, &mut device).await.unwrap();
+
+        let ids: Vec<String> = drain_posted(&mut events)
+            .into_iter()
+            .map(|n| n.id)
+            .collect();
+        assert_eq!(ids, ["wa-0", "wa-1", "wa-2", "wa-call"]);
+
+        // Other apps have their own budget
+        let notif = Notification::new("mail-1", "Mail", "Inbox", "hello", true);
+        let packet = plugin.create_notification_packet(&notif);
+        plugin.handle_packet(&packet, &mut device).await.unwrap();
+        assert_eq!(drain_posted(&mut events).len(), 1);
+    }
+
+    #[test]
+    fn test_icon_cache_rejects_unsafe_hash() {
+        let cache = NotificationIconCache::new("/tmp/icons");
+        assert!(cache.path_for("../../etc/passwd").is_err());
+        assert!(cache.path_for("").is_err());
+        assert!(cache.path_for("abc123").is_ok());
+    }
+
     #[test]
     fn test_notification_new() {
         let notif = Notification::new("123", "Messages", "Title", "Text", true);
@@ -63,7 +63,7 @@
 //!
 //! - [Valent Protocol - Ping](https://valent.andyholmes.ca/documentation/protocol.html)
 
-use crate::{Device, Packet, Result};
+use crate::{Device, FieldType, Packet, PacketType, Result};
 use async_trait::async_trait;
 use serde_json::json;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -316,6 +316,12 @@ impl PluginFactory for PingPluginFactory {
         vec!["cconnect.ping".to_string()]
     }
 
+    fn packet_types(&self) -> Vec<PacketType> {
+        vec![PacketType::new("cconnect.ping")
+            .optional("message", FieldType::String)
+            .optional("keepalive", FieldType::Bool)]
+    }
+
     fn create(&self) -> Box<dyn Plugin> {
         Box::new(PingPlugin::new())
     }
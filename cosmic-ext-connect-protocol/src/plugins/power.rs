@@ -637,6 +637,9 @@ mod tests {
                 incoming_capabilities: vec!["cconnect.power".to_string()],
                 outgoing_capabilities: vec!["cconnect.power".to_string()],
                 tcp_port: 1814,
+                persistent_id: None,
+                addresses: Vec::new(),
+                payload_streams: 1,
             },
             crate::ConnectionState::Disconnected,
             crate::PairingStatus::Paired,
@@ -0,0 +1,484 @@
+//! Scheduled Remote Commands
+//!
+//! Lets the desktop trigger a device's run commands on a schedule, e.g. run
+//! the phone's "Mute" command every day at 22:00. Each entry belongs to one
+//! device and names a command from that device's command list.
+//!
+//! ## Schedules
+//!
+//! Schedules are written as short strings, in local wall-clock time:
+//! - `once 2026-10-16 22:00` - a single run
+//! - `daily 22:00` - every day at that time
+//! - `every 30m` - repeatedly, at least a minute apart (`m`, `h` or `d`)
+//!
+//! ## Offline Devices
+//!
+//! A command that comes due while its device is disconnected stays queued and
+//! fires once the device is back, however many runs were missed; a recurring
+//! command then continues from its next regular time.
+//!
+//! ## Time
+//!
+//! [`CommandScheduler`] never reads the clock; callers pass `now`, so the
+//! daemon uses the local time and tests use fixed times.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let schedule: Schedule = "daily 22:00".parse()?;
+//! scheduler.schedule("phone", "mute", schedule, now)?;
+//!
+//! for (device_id, command_id) in scheduler.due(now, |id| connected.contains(id)) {
+//!     send(&device_id, execute_request_packet(&command_id)).await?;
+//!     scheduler.fired(&device_id, &command_id, now);
+//! }
+//! ```
+
+use crate::{Packet, ProtocolError, Result};
+use chrono::{Duration as ChronoDuration, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::debug;
+
+/// Shortest interval for `every` schedules
+pub const MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+const DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+const TIME_FORMAT: &str = "%H:%M";
+
+/// When a scheduled command runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Schedule {
+    /// A single run at the given time
+    Once(NaiveDateTime),
+    /// Every day at the given time
+    Daily(NaiveTime),
+    /// Repeatedly with the given interval, starting one interval from now
+    Every(Duration),
+}
+
+impl Schedule {
+    /// First run for a schedule registered at `now`
+    fn first_run(&self, now: NaiveDateTime) -> Result<NaiveDateTime> {
+        match *self {
+            Schedule::Once(at) if at <= now => Err(ProtocolError::Configuration(format!(
+                "Scheduled time {} is in the past",
+                at.format(DATE_TIME_FORMAT)
+            ))),
+            Schedule::Once(at) => Ok(at),
+            Schedule::Daily(at) => Ok(next_daily(at, now)),
+            Schedule::Every(interval) => Ok(now + chrono_duration(interval)),
+        }
+    }
+
+    /// Run following one that was due at `previous` and fired at `now`
+    ///
+    /// Runs missed in between are skipped. `None` for one-shot schedules.
+    fn next_run(&self, previous: NaiveDateTime, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        match *self {
+            Schedule::Once(_) => None,
+            Schedule::Daily(at) => Some(next_daily(at, now)),
+            Schedule::Every(interval) => {
+                let step = chrono_duration(interval);
+                let mut next = previous + step;
+                if next <= now {
+                    // Stay aligned with the original times
+                    let missed = (now - next).num_seconds() / step.num_seconds() + 1;
+                    next += step * missed as i32;
+                }
+                Some(next)
+            }
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = ProtocolError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            ProtocolError::Configuration(format!(
+                "Invalid schedule '{}': expected 'once YYYY-MM-DD HH:MM', 'daily HH:MM' or 'every <n>m|h|d'",
+                s
+            ))
+        };
+        let (kind, rest) = s.trim().split_once(' ').ok_or_else(invalid)?;
+        let rest = rest.trim();
+        match kind {
+            "once" => NaiveDateTime::parse_from_str(rest, DATE_TIME_FORMAT)
+                .map(Schedule::Once)
+                .map_err(|_| invalid()),
+            "daily" => NaiveTime::parse_from_str(rest, TIME_FORMAT)
+                .map(Schedule::Daily)
+                .map_err(|_| invalid()),
+            "every" => {
+                let split = rest.len().checked_sub(1).ok_or_else(invalid)?;
+                let (count, unit) = rest.split_at(split);
+                let count: u64 = count.parse().map_err(|_| invalid())?;
+                let unit_secs = match unit {
+                    "m" => 60,
+                    "h" => 60 * 60,
+                    "d" => 24 * 60 * 60,
+                    _ => return Err(invalid()),
+                };
+                let interval = Duration::from_secs(count.saturating_mul(unit_secs));
+                if interval < MIN_INTERVAL {
+                    return Err(ProtocolError::Configuration(format!(
+                        "Schedule interval must be at least {} seconds",
+                        MIN_INTERVAL.as_secs()
+                    )));
+                }
+                Ok(Schedule::Every(interval))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Once(at) => write!(f, "once {}", at.format(DATE_TIME_FORMAT)),
+            Schedule::Daily(at) => write!(f, "daily {}", at.format(TIME_FORMAT)),
+            Schedule::Every(interval) => {
+                let minutes = interval.as_secs() / 60;
+                if minutes % (24 * 60) == 0 {
+                    write!(f, "every {}d", minutes / (24 * 60))
+                } else if minutes % 60 == 0 {
+                    write!(f, "every {}h", minutes / 60)
+                } else {
+                    write!(f, "every {}m", minutes)
+                }
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for Schedule {
+    type Error = ProtocolError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Schedule> for String {
+    fn from(schedule: Schedule) -> Self {
+        schedule.to_string()
+    }
+}
+
+/// A command registered to run on a schedule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledCommand {
+    /// Key of the command on the device
+    pub command_id: String,
+    /// When it runs
+    pub schedule: Schedule,
+    /// Next time it is due
+    #[serde(with = "local_time")]
+    pub next_run: NaiveDateTime,
+}
+
+/// Scheduled commands of every device
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CommandScheduler {
+    /// Device ID -> command ID -> entry
+    devices: HashMap<String, HashMap<String, ScheduledCommand>>,
+}
+
+impl CommandScheduler {
+    /// Create an empty scheduler
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schedule a device's command, replacing any schedule it already had
+    ///
+    /// Returns the first time it will run.
+    pub fn schedule(
+        &mut self,
+        device_id: &str,
+        command_id: &str,
+        schedule: Schedule,
+        now: NaiveDateTime,
+    ) -> Result<NaiveDateTime> {
+        let next_run = schedule.first_run(now)?;
+        debug!(
+            "Scheduled command {} on {} ({}), next run {}",
+            command_id, device_id, schedule, next_run
+        );
+        self.devices
+            .entry(device_id.to_string())
+            .or_default()
+            .insert(
+                command_id.to_string(),
+                ScheduledCommand {
+                    command_id: command_id.to_string(),
+                    schedule,
+                    next_run,
+                },
+            );
+        Ok(next_run)
+    }
+
+    /// Remove a command's schedule, returning whether it had one
+    pub fn cancel(&mut self, device_id: &str, command_id: &str) -> bool {
+        let Some(commands) = self.devices.get_mut(device_id) else {
+            return false;
+        };
+        let removed = commands.remove(command_id).is_some();
+        if commands.is_empty() {
+            self.devices.remove(device_id);
+        }
+        removed
+    }
+
+    /// Remove every schedule of a device
+    pub fn remove_device(&mut self, device_id: &str) {
+        self.devices.remove(device_id);
+    }
+
+    /// A device's scheduled commands, soonest first
+    pub fn commands_for(&self, device_id: &str) -> Vec<&ScheduledCommand> {
+        let mut commands: Vec<_> = self
+            .devices
+            .get(device_id)
+            .map(|commands| commands.values().collect())
+            .unwrap_or_default();
+        commands.sort_by(|a, b| {
+            a.next_run
+                .cmp(&b.next_run)
+                .then_with(|| a.command_id.cmp(&b.command_id))
+        });
+        commands
+    }
+
+    /// Commands due at `now` on connected devices, as (device ID, command ID)
+    ///
+    /// Entries stay due until [`fired`](Self::fired) is called, so a command
+    /// whose device is offline or whose packet failed to send is retried.
+    pub fn due(
+        &self,
+        now: NaiveDateTime,
+        is_connected: impl Fn(&str) -> bool,
+    ) -> Vec<(String, String)> {
+        let mut due: Vec<_> = self
+            .devices
+            .iter()
+            .filter(|(device_id, _)| is_connected(device_id))
+            .flat_map(|(device_id, commands)| {
+                commands
+                    .values()
+                    .filter(|command| command.next_run <= now)
+                    .map(move |command| (command.next_run, device_id, &command.command_id))
+            })
+            .collect();
+        due.sort();
+        due.into_iter()
+            .map(|(_, device_id, command_id)| (device_id.clone(), command_id.clone()))
+            .collect()
+    }
+
+    /// Record that a due command was sent at `now`
+    ///
+    /// Recurring commands move to their next run; one-shots are removed.
+    pub fn fired(&mut self, device_id: &str, command_id: &str, now: NaiveDateTime) {
+        let Some(command) = self
+            .devices
+            .get_mut(device_id)
+            .and_then(|commands| commands.get_mut(command_id))
+        else {
+            return;
+        };
+        match command.schedule.next_run(command.next_run, now) {
+            Some(next_run) => {
+                debug!(
+                    "Command {} on {} fired, next run {}",
+                    command_id, device_id, next_run
+                );
+                command.next_run = next_run;
+            }
+            None => {
+                debug!("One-shot command {} on {} fired", command_id, device_id);
+                self.cancel(device_id, command_id);
+            }
+        }
+    }
+
+    /// Whether nothing is scheduled
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+}
+
+/// Packet asking a device to run one of its commands
+pub fn execute_request_packet(command_id: &str) -> Packet {
+    Packet::new("cconnect.runcommand.request", json!({ "key": command_id }))
+}
+
+fn next_daily(at: NaiveTime, now: NaiveDateTime) -> NaiveDateTime {
+    let today = now.date().and_time(at);
+    if today > now {
+        today
+    } else {
+        today + ChronoDuration::days(1)
+    }
+}
+
+fn chrono_duration(duration: Duration) -> ChronoDuration {
+    ChronoDuration::seconds(duration.as_secs() as i64)
+}
+
+mod local_time {
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    const FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+    pub fn serialize<S: Serializer>(
+        time: &NaiveDateTime,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(&time.format(FORMAT))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<NaiveDateTime, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        NaiveDateTime::parse_from_str(&s, FORMAT).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test clock: 2026-10-16 at the given time
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        day(16, hour, minute)
+    }
+
+    fn day(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2026, 10, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn online(_: &str) -> bool {
+        true
+    }
+
+    #[test]
+    fn test_parse_and_display_schedules() {
+        for text in [
+            "once 2026-10-16 22:00",
+            "daily 22:00",
+            "every 30m",
+            "every 2h",
+        ] {
+            let schedule: Schedule = text.parse().unwrap();
+            assert_eq!(schedule.to_string(), text);
+        }
+        assert_eq!(
+            "every 90m".parse::<Schedule>().unwrap(),
+            Schedule::Every(Duration::from_secs(90 * 60))
+        );
+        for bad in [
+            "daily",
+            "daily 25:00",
+            "every 0m",
+            "every 10s",
+            "weekly 22:00",
+        ] {
+            assert!(bad.parse::<Schedule>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_daily_command_fires_at_time_and_recurs() {
+        let mut scheduler = CommandScheduler::new();
+        let first = scheduler
+            .schedule("phone", "mute", "daily 22:00".parse().unwrap(), at(9, 0))
+            .unwrap();
+        assert_eq!(first, at(22, 0));
+
+        assert!(scheduler.due(at(21, 59), online).is_empty());
+        let due = scheduler.due(at(22, 0), online);
+        assert_eq!(due, vec![("phone".to_string(), "mute".to_string())]);
+
+        scheduler.fired("phone", "mute", at(22, 0));
+        assert!(scheduler.due(at(23, 0), online).is_empty());
+        assert_eq!(scheduler.commands_for("phone")[0].next_run, day(17, 22, 0));
+        assert_eq!(scheduler.due(day(17, 22, 0), online).len(), 1);
+    }
+
+    #[test]
+    fn test_one_shot_does_not_refire() {
+        let mut scheduler = CommandScheduler::new();
+        scheduler
+            .schedule(
+                "phone",
+                "alarm",
+                "once 2026-10-16 07:30".parse().unwrap(),
+                at(6, 0),
+            )
+            .unwrap();
+
+        assert_eq!(scheduler.due(at(7, 30), online).len(), 1);
+        scheduler.fired("phone", "alarm", at(7, 30));
+        assert!(scheduler.due(at(7, 31), online).is_empty());
+        assert!(scheduler.due(day(20, 7, 30), online).is_empty());
+        assert!(scheduler.is_empty());
+
+        // A one-shot in the past is refused
+        assert!(scheduler
+            .schedule(
+                "phone",
+                "alarm",
+                "once 2026-10-16 07:30".parse().unwrap(),
+                at(8, 0)
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_offline_device_keeps_command_queued() {
+        let mut scheduler = CommandScheduler::new();
+        scheduler
+            .schedule("phone", "sync", "every 1h".parse().unwrap(), at(8, 0))
+            .unwrap();
+
+        // Due at 09:00 but the phone is away until 11:20
+        assert!(scheduler.due(at(11, 20), |_| false).is_empty());
+        assert_eq!(scheduler.due(at(11, 20), online).len(), 1);
+        scheduler.fired("phone", "sync", at(11, 20));
+
+        // Missed runs are not replayed; the hourly rhythm continues
+        assert!(scheduler.due(at(11, 59), online).is_empty());
+        assert_eq!(scheduler.commands_for("phone")[0].next_run, at(12, 0));
+    }
+
+    #[test]
+    fn test_scheduler_round_trips_through_json() {
+        let mut scheduler = CommandScheduler::new();
+        scheduler
+            .schedule("phone", "mute", "daily 22:00".parse().unwrap(), at(9, 0))
+            .unwrap();
+        scheduler
+            .schedule("tablet", "sync", "every 15m".parse().unwrap(), at(9, 0))
+            .unwrap();
+
+        let json = serde_json::to_string(&scheduler).unwrap();
+        let restored: CommandScheduler = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, scheduler);
+    }
+}
@@ -0,0 +1,96 @@
+//! Safe Mode
+//!
+//! Locked-down deployments want file, clipboard and notification sync without
+//! letting a paired device run commands or drive the keyboard and mouse. In
+//! safe mode the [`PluginManager`](super::PluginManager) refuses to register
+//! the plugins listed in [`BLOCKED_PLUGINS`], leaves their packet types out of
+//! the advertised capabilities and rejects incoming packets for them, no
+//! matter what the global or per-device plugin settings say. Share completion
+//! hooks, which open received files or run commands on them, are not
+//! installed either.
+//!
+//! Safe mode is switched on by the daemon configuration or by setting
+//! [`SAFE_MODE_ENV`] to `1`, `true` or `yes`.
+
+/// Environment variable that forces safe mode on
+pub const SAFE_MODE_ENV: &str = "COSMIC_CONNECT_SAFE_MODE";
+
+/// Plugins that execute commands or inject input, refused in safe mode
+pub const BLOCKED_PLUGINS: &[&str] = &[
+    "runcommand",
+    "macro",
+    "remoteinput",
+    "mousekeyboardshare",
+    "remotedesktop",
+    "presenter",
+    "lock",
+    "power",
+];
+
+/// Packet type namespaces of the blocked plugins
+const BLOCKED_PACKET_NAMESPACES: &[&str] = &[
+    "cconnect.runcommand",
+    "cconnect.macro",
+    "cconnect.mousepad",
+    "cconnect.mkshare",
+    "cconnect.remotedesktop",
+    "cconnect.presenter",
+    "cconnect.lock",
+    "cconnect.power",
+];
+
+/// Whether the environment asks for safe mode
+pub fn requested_by_env() -> bool {
+    std::env::var(SAFE_MODE_ENV).is_ok_and(|value| {
+        matches!(
+            value.trim().to_ascii_lowercase().as_str(),
+            "1" | "true" | "yes"
+        )
+    })
+}
+
+/// Whether safe mode refuses the plugin called `plugin_name`
+pub fn is_blocked_plugin(plugin_name: &str) -> bool {
+    BLOCKED_PLUGINS.contains(&plugin_name)
+}
+
+/// Whether `packet_type` belongs to a plugin safe mode refuses
+///
+/// Legacy `kdeconnect.*` types are matched like their `cconnect.*` names.
+pub fn is_blocked_packet_type(packet_type: &str) -> bool {
+    let normalized = match packet_type.strip_prefix("kdeconnect.") {
+        Some(rest) => format!("cconnect.{}", rest),
+        None => packet_type.to_string(),
+    };
+    BLOCKED_PACKET_NAMESPACES.iter().any(|namespace| {
+        normalized
+            .strip_prefix(namespace)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocked_packet_types() {
+        for packet_type in [
+            "cconnect.runcommand.request",
+            "cconnect.mousepad.request",
+            "kdeconnect.mousepad.keyboardstate",
+            "cconnect.lock",
+            "cconnect.power.inhibit",
+        ] {
+            assert!(is_blocked_packet_type(packet_type), "{}", packet_type);
+        }
+        for packet_type in [
+            "cconnect.share.request",
+            "cconnect.clipboard",
+            "cconnect.notification",
+            "cconnect.lockscreen",
+        ] {
+            assert!(!is_blocked_packet_type(packet_type), "{}", packet_type);
+        }
+    }
+}
@@ -1,6 +1,14 @@
 //! Video Decoder for Screen Share
 //!
 //! Uses GStreamer to decode H.264 video streams from Android devices.
+//!
+//! ## Hardware and Software Decode
+//!
+//! A hardware decoder (VA-API, NVDEC, V4L2) is used when GStreamer has one.
+//! Otherwise, or if the hardware pipeline fails to start, the decoder falls
+//! back to software decode (libav or OpenH264) and logs a warning; decoded
+//! frames are RGBA in system memory either way, so the viewer renders them
+//! the same.
 
 #[cfg(feature = "screenshare")]
 use crate::Result;
@@ -11,7 +19,66 @@ use gstreamer::prelude::*;
 #[cfg(feature = "screenshare")]
 use gstreamer_app as gst_app;
 #[cfg(feature = "screenshare")]
-use tracing::{debug, error};
+use tracing::{debug, error, info, warn};
+
+/// Hardware H.264 decoder elements, most preferred first
+pub const HARDWARE_H264_DECODERS: &[&str] = &[
+    "vah264dec",
+    "vaapih264dec",
+    "nvh264dec",
+    "v4l2slh264dec",
+    "v4l2h264dec",
+];
+
+/// Software H.264 decoder elements, most preferred first
+pub const SOFTWARE_H264_DECODERS: &[&str] = &["avdec_h264", "openh264dec"];
+
+/// Where decoding happens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecoderKind {
+    Hardware,
+    Software,
+}
+
+/// The decoder element picked for a stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecoderChoice {
+    /// GStreamer element name
+    pub element: &'static str,
+    pub kind: DecoderKind,
+}
+
+/// Which decoders may be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecoderPreference {
+    /// Hardware if available, software otherwise
+    #[default]
+    Auto,
+    /// Never use hardware decode
+    SoftwareOnly,
+}
+
+/// Pick the decoder to use given which elements are installed
+///
+/// Returns `None` if no H.264 decoder is available at all.
+pub fn select_decoder(
+    preference: DecoderPreference,
+    is_available: impl Fn(&str) -> bool,
+) -> Option<DecoderChoice> {
+    let hardware = match preference {
+        DecoderPreference::Auto => HARDWARE_H264_DECODERS,
+        DecoderPreference::SoftwareOnly => &[],
+    };
+    let pick = |elements: &[&'static str], kind| {
+        elements
+            .iter()
+            .copied()
+            .find(|element| is_available(element))
+            .map(|element| DecoderChoice { element, kind })
+    };
+    pick(hardware, DecoderKind::Hardware)
+        .or_else(|| pick(SOFTWARE_H264_DECODERS, DecoderKind::Software))
+}
 
 /// Video decoder
 #[cfg(feature = "screenshare")]
@@ -19,22 +86,59 @@ pub struct VideoDecoder {
     pipeline: gst::Pipeline,
     appsrc: gst_app::AppSrc,
     appsink: gst_app::AppSink,
+    choice: DecoderChoice,
 }
 
 #[cfg(feature = "screenshare")]
 impl VideoDecoder {
-    /// Create a new video decoder
+    /// Create a new video decoder, preferring hardware decode
     pub fn new() -> Result<Self> {
+        Self::with_preference(DecoderPreference::Auto)
+    }
+
+    /// Create a new video decoder limited to `preference`
+    pub fn with_preference(preference: DecoderPreference) -> Result<Self> {
         gst::init()
             .map_err(|e| crate::ProtocolError::Plugin(format!("GStreamer init failed: {}", e)))?;
 
-        // Create pipeline: appsrc -> h264parse -> avdec_h264 -> videoconvert -> appsink
+        let choice = Self::choose(preference)?;
+        if choice.kind == DecoderKind::Software && preference == DecoderPreference::Auto {
+            warn!(
+                "No hardware H.264 decoder available, falling back to software decode ({})",
+                choice.element
+            );
+        }
+
+        match Self::build(choice) {
+            Ok(decoder) => Ok(decoder),
+            Err(e) if choice.kind == DecoderKind::Hardware => {
+                warn!(
+                    "Hardware decoder {} unusable ({}), falling back to software decode",
+                    choice.element, e
+                );
+                Self::build(Self::choose(DecoderPreference::SoftwareOnly)?)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn choose(preference: DecoderPreference) -> Result<DecoderChoice> {
+        select_decoder(preference, |name| gst::ElementFactory::find(name).is_some()).ok_or_else(
+            || crate::ProtocolError::Plugin("No H.264 decoder available in GStreamer".to_string()),
+        )
+    }
+
+    fn build(choice: DecoderChoice) -> Result<Self> {
+        // Create pipeline: appsrc -> h264parse -> decoder -> videoconvert -> appsink
         // Using raw H.264 stream
-        let pipeline_str = "appsrc name=src format=time is-live=true do-timestamp=true ! h264parse ! avdec_h264 ! videoconvert ! video/x-raw,format=RGBA ! appsink name=sink drop=true max-buffers=1";
+        let pipeline_str = format!(
+            "appsrc name=src format=time is-live=true do-timestamp=true ! h264parse ! {} ! videoconvert ! video/x-raw,format=RGBA ! appsink name=sink drop=true max-buffers=1",
+            choice.element
+        );
 
         debug!("Creating GStreamer pipeline: {}", pipeline_str);
 
-        let pipeline = gst::parse::launch(pipeline_str)
+        let pipeline = gst::parse::launch(&pipeline_str)
             .map_err(|e| crate::ProtocolError::Plugin(format!("Failed to parse pipeline: {}", e)))?
             .downcast::<gst::Pipeline>()
             .map_err(|_| crate::ProtocolError::Plugin("Failed to downcast pipeline".to_string()))?;
@@ -58,15 +162,42 @@ impl VideoDecoder {
             pipeline,
             appsrc,
             appsink,
+            choice,
         })
     }
 
+    /// The decoder element in use
+    pub fn backend(&self) -> DecoderChoice {
+        self.choice
+    }
+
     /// Start the decoder
-    pub fn start(&self) -> Result<()> {
-        self.pipeline.set_state(gst::State::Playing).map_err(|e| {
-            crate::ProtocolError::Plugin(format!("Failed to start pipeline: {}", e))
-        })?;
-        debug!("Video decoder started");
+    ///
+    /// A hardware pipeline that fails to start is replaced by a software one.
+    pub fn start(&mut self) -> Result<()> {
+        if let Err(e) = self.pipeline.set_state(gst::State::Playing) {
+            if self.choice.kind != DecoderKind::Hardware {
+                return Err(crate::ProtocolError::Plugin(format!(
+                    "Failed to start pipeline: {}",
+                    e
+                )));
+            }
+            warn!(
+                "Hardware decoder {} failed to start ({}), falling back to software decode",
+                self.choice.element, e
+            );
+            let _ = self.pipeline.set_state(gst::State::Null);
+            *self = Self::build(Self::choose(DecoderPreference::SoftwareOnly)?)?;
+            return self.start();
+        }
+        info!(
+            "Video decoder started ({} decode via {})",
+            match self.choice.kind {
+                DecoderKind::Hardware => "hardware",
+                DecoderKind::Software => "software",
+            },
+            self.choice.element
+        );
         Ok(())
     }
 
@@ -144,7 +275,11 @@ impl VideoDecoder {
         Ok(Self)
     }
 
-    pub fn start(&self) -> crate::Result<()> {
+    pub fn with_preference(_preference: DecoderPreference) -> crate::Result<Self> {
+        Ok(Self)
+    }
+
+    pub fn start(&mut self) -> crate::Result<()> {
         Ok(())
     }
 
@@ -160,3 +295,85 @@ impl VideoDecoder {
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hardware_preferred_when_available() {
+        let choice = select_decoder(DecoderPreference::Auto, |_| true).unwrap();
+        assert_eq!(choice.kind, DecoderKind::Hardware);
+        assert_eq!(choice.element, HARDWARE_H264_DECODERS[0]);
+    }
+
+    #[test]
+    fn test_falls_back_to_software_without_hardware() {
+        // Only software decoders installed
+        let software_only = |name: &str| SOFTWARE_H264_DECODERS.contains(&name);
+        let choice = select_decoder(DecoderPreference::Auto, software_only).unwrap();
+        assert_eq!(
+            choice,
+            DecoderChoice {
+                element: "avdec_h264",
+                kind: DecoderKind::Software,
+            }
+        );
+
+        // libav missing as well: OpenH264
+        let choice = select_decoder(DecoderPreference::Auto, |name| name == "openh264dec");
+        assert_eq!(choice.unwrap().element, "openh264dec");
+
+        // Hardware present but disabled
+        let choice = select_decoder(DecoderPreference::SoftwareOnly, |_| true).unwrap();
+        assert_eq!(choice.kind, DecoderKind::Software);
+
+        assert!(select_decoder(DecoderPreference::Auto, |_| false).is_none());
+    }
+
+    /// Encode a few test frames with x264 and decode them in software
+    #[cfg(feature = "screenshare")]
+    #[test]
+    #[ignore = "Requires GStreamer with x264enc and a software H.264 decoder"]
+    fn test_software_decode_produces_frames() {
+        gst::init().unwrap();
+        let encoder = gst::parse::launch(
+            "videotestsrc num-buffers=30 ! video/x-raw,width=64,height=48,framerate=30/1 ! x264enc tune=zerolatency key-int-max=1 ! video/x-h264,stream-format=byte-stream,alignment=au ! appsink name=sink sync=false",
+        )
+        .unwrap()
+        .downcast::<gst::Pipeline>()
+        .unwrap();
+        let encoded_sink = encoder
+            .by_name("sink")
+            .unwrap()
+            .downcast::<gst_app::AppSink>()
+            .unwrap();
+        encoder.set_state(gst::State::Playing).unwrap();
+
+        let mut decoder = VideoDecoder::with_preference(DecoderPreference::SoftwareOnly).unwrap();
+        assert_eq!(decoder.backend().kind, DecoderKind::Software);
+        decoder.start().unwrap();
+
+        let mut decoded = None;
+        while let Some(sample) = encoded_sink.try_pull_sample(gst::ClockTime::from_seconds(2)) {
+            let map = sample.buffer().unwrap().map_readable().unwrap();
+            decoder.push_frame(&map).unwrap();
+            if let Some(frame) = decoder.pull_frame().unwrap() {
+                decoded = Some(frame);
+                break;
+            }
+        }
+        for _ in 0..100 {
+            if decoded.is_some() {
+                break;
+            }
+            decoded = decoder.pull_frame().unwrap();
+        }
+        encoder.set_state(gst::State::Null).unwrap();
+        decoder.stop().unwrap();
+
+        let (data, width, height) = decoded.expect("no frame decoded");
+        assert_eq!((width, height), (64, 48));
+        assert_eq!(data.len(), (64 * 48 * 4) as usize);
+    }
+}
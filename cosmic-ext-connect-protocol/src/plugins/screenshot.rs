@@ -199,7 +199,8 @@ impl ScreenshotPlugin {
     /// Create a screenshot response packet
     ///
     /// Creates a `cconnect.screenshot.data` packet with payload transfer info.
-    fn create_screenshot_response(
+    /// Also used to send a desktop screenshot the device did not ask for.
+    pub fn create_screenshot_response(
         filename: &str,
         width: u32,
         height: u32,
@@ -0,0 +1,766 @@
+//! Remote File Browser (SFTP)
+//!
+//! Lightweight listing and single-file download over the SFTP share advertised by
+//! the Network Share plugin, without mounting the whole remote filesystem.
+//!
+//! ## Architecture
+//!
+//! - [`SftpBackend`] performs the remote operations (list, stat, open). The default
+//!   [`SftpCommandBackend`] drives the OpenSSH `sftp` client; tests use a mock.
+//! - [`RemoteFileBrowser`] resolves the device's [`SftpInfo`], calls the backend and
+//!   writes fetched files with the same safe file helpers used for payload
+//!   transfers (disk space check, unique download path, partial file cleanup).
+//!
+//! ## Errors
+//!
+//! - Missing remote paths are reported as [`ProtocolError::Io`] with
+//!   [`std::io::ErrorKind::NotFound`] (see [`is_not_found`])
+//! - Access problems are reported as [`ProtocolError::PermissionDenied`]
+//! - A device without an advertised share yields [`ProtocolError::InvalidState`]
+//!
+//! ## Usage
+//!
+//! ```rust,ignore
+//! let browser = network_share_plugin.browser(Arc::new(SftpCommandBackend::new()));
+//!
+//! for entry in browser.list_remote_dir("device-id", "DCIM/Camera").await? {
+//!     println!("{} ({} bytes)", entry.name, entry.size);
+//! }
+//!
+//! let saved = browser
+//!     .fetch_remote_file("device-id", "DCIM/Camera/IMG_0001.jpg", downloads_dir)
+//!     .await?;
+//! ```
+
+use super::networkshare::SftpInfo;
+use crate::fs_utils::{
+    check_disk_space, cleanup_partial_file, create_file_safe, get_unique_download_path,
+    write_file_safe,
+};
+use crate::{ProtocolError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// Read buffer size for remote file downloads
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Environment variable carrying the share password to the askpass helper
+const ASKPASS_PASSWORD_ENV: &str = "CCONNECT_SFTP_PASSWORD";
+
+/// Kind of a remote filesystem entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteEntryKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+/// A single entry in a remote directory listing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteEntry {
+    /// File name without directory
+    pub name: String,
+    /// Full remote path
+    pub path: String,
+    /// Entry kind
+    pub kind: RemoteEntryKind,
+    /// Size in bytes (0 for directories)
+    pub size: u64,
+}
+
+impl RemoteEntry {
+    /// Check whether this entry is a directory
+    pub fn is_dir(&self) -> bool {
+        self.kind == RemoteEntryKind::Directory
+    }
+}
+
+/// Readable stream for a remote file
+pub type RemoteReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Remote filesystem operations over an SFTP share
+#[async_trait]
+pub trait SftpBackend: Send + Sync {
+    /// List the entries of a remote directory
+    async fn list_dir(&self, share: &SftpInfo, path: &str) -> Result<Vec<RemoteEntry>>;
+
+    /// Look up a single remote entry
+    async fn stat(&self, share: &SftpInfo, path: &str) -> Result<RemoteEntry>;
+
+    /// Open a remote file for reading
+    async fn open_file(&self, share: &SftpInfo, path: &str) -> Result<RemoteReader>;
+}
+
+/// Build the error returned for a missing remote path
+pub fn remote_not_found(path: &str) -> ProtocolError {
+    ProtocolError::Io(std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        format!("Remote path not found: {}", path),
+    ))
+}
+
+/// Check whether an error means the remote path does not exist
+pub fn is_not_found(error: &ProtocolError) -> bool {
+    matches!(error, ProtocolError::Io(e) if e.kind() == std::io::ErrorKind::NotFound)
+}
+
+/// Join a path onto a remote directory
+fn join_remote(dir: &str, name: &str) -> String {
+    if name.is_empty() {
+        dir.to_string()
+    } else if name.starts_with('/') {
+        name.to_string()
+    } else if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Split a remote path into parent directory and file name
+fn split_remote(path: &str) -> (&str, &str) {
+    let trimmed = path.trim_end_matches('/');
+    match trimmed.rfind('/') {
+        Some(0) => ("/", &trimmed[1..]),
+        Some(idx) => (&trimmed[..idx], &trimmed[idx + 1..]),
+        None => (".", trimmed),
+    }
+}
+
+/// Browses and downloads files from devices' SFTP shares
+pub struct RemoteFileBrowser {
+    /// SFTP connection info keyed by device ID (shared with the plugin)
+    shares: Arc<RwLock<HashMap<String, SftpInfo>>>,
+    /// Backend performing remote operations
+    backend: Arc<dyn SftpBackend>,
+}
+
+impl RemoteFileBrowser {
+    /// Create a browser over a set of shares
+    pub fn new(
+        shares: Arc<RwLock<HashMap<String, SftpInfo>>>,
+        backend: Arc<dyn SftpBackend>,
+    ) -> Self {
+        Self { shares, backend }
+    }
+
+    /// Get the share for a device
+    async fn share(&self, device_id: &str) -> Result<SftpInfo> {
+        self.shares
+            .read()
+            .await
+            .get(device_id)
+            .cloned()
+            .ok_or_else(|| {
+                ProtocolError::InvalidState(format!(
+                    "No SFTP share available for device {}",
+                    device_id
+                ))
+            })
+    }
+
+    /// List a remote directory
+    ///
+    /// Relative paths are resolved against the share's root path. Directories are
+    /// listed first, then files, each sorted by name.
+    pub async fn list_remote_dir(&self, device_id: &str, path: &str) -> Result<Vec<RemoteEntry>> {
+        let share = self.share(device_id).await?;
+        let remote_path = join_remote(share.effective_path(), path);
+
+        debug!("Listing {} on device {}", remote_path, device_id);
+
+        let mut entries = self.backend.list_dir(&share, &remote_path).await?;
+        entries.sort_by(|a, b| {
+            b.is_dir()
+                .cmp(&a.is_dir())
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Ok(entries)
+    }
+
+    /// Download a single remote file into `download_dir`
+    ///
+    /// Returns the local path the file was saved to. A numbered suffix is added if
+    /// a file with the same name already exists.
+    pub async fn fetch_remote_file(
+        &self,
+        device_id: &str,
+        path: &str,
+        download_dir: impl AsRef<Path>,
+    ) -> Result<PathBuf> {
+        let share = self.share(device_id).await?;
+        let remote_path = join_remote(share.effective_path(), path);
+
+        let entry = self.backend.stat(&share, &remote_path).await?;
+        if entry.is_dir() {
+            return Err(ProtocolError::InvalidState(format!(
+                "Remote path {} is a directory",
+                remote_path
+            )));
+        }
+
+        let download_dir = download_dir.as_ref();
+        check_disk_space(download_dir, entry.size).await?;
+        let save_path = get_unique_download_path(download_dir, &entry.name).await;
+
+        info!(
+            "Fetching {} ({} bytes) from device {} to {:?}",
+            remote_path, entry.size, device_id, save_path
+        );
+
+        let mut reader = self.backend.open_file(&share, &remote_path).await?;
+        let mut file = create_file_safe(&save_path).await?;
+
+        let result = async {
+            let mut buffer = vec![0u8; BUFFER_SIZE];
+            let mut total_bytes = 0u64;
+            loop {
+                let bytes_read = reader.read(&mut buffer).await?;
+                if bytes_read == 0 {
+                    break;
+                }
+                write_file_safe(&mut file, &buffer[..bytes_read]).await?;
+                total_bytes += bytes_read as u64;
+            }
+            file.flush().await?;
+
+            if total_bytes != entry.size {
+                return Err(ProtocolError::Transport(format!(
+                    "Size mismatch for {}: received {} bytes, expected {}",
+                    remote_path, total_bytes, entry.size
+                )));
+            }
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            warn!("Fetching {} failed: {}", remote_path, e);
+            cleanup_partial_file(&save_path).await;
+            return Err(e);
+        }
+
+        Ok(save_path)
+    }
+}
+
+/// [`SftpBackend`] driving the OpenSSH `sftp` command-line client
+///
+/// The share password is supplied through `SSH_ASKPASS`, so no terminal is
+/// required. Requires OpenSSH 8.4+ for `SSH_ASKPASS_REQUIRE`.
+///
+/// The askpass helper lives in a private directory (mode 0700, random name)
+/// under `$XDG_RUNTIME_DIR`, removed when the backend is dropped. Host keys
+/// are always checked: a key advertised in the share is pinned in a private
+/// known_hosts file, otherwise the first key seen is pinned in
+/// [`known_hosts_path`] and must match from then on. Downloads are staged in
+/// the same private directory.
+pub struct SftpCommandBackend {
+    /// Private directory holding the askpass helper, created on first use
+    workdir: tokio::sync::OnceCell<tempfile::TempDir>,
+}
+
+/// Known hosts file for shares that do not advertise a host key
+pub fn known_hosts_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("cosmic-ext-connect").join("sftp_known_hosts"))
+}
+
+/// Write a new file readable only by us, failing if it already exists
+async fn write_private_file(path: &Path, contents: &[u8], mode: u32) -> Result<()> {
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(mode);
+    #[cfg(not(unix))]
+    let _ = mode;
+    let mut file = options.open(path).await?;
+    file.write_all(contents).await?;
+    file.flush().await?;
+    Ok(())
+}
+
+/// Host name as OpenSSH writes it in known_hosts
+fn known_hosts_name(share: &SftpInfo) -> String {
+    match share.effective_port() {
+        22 => share.ip.clone(),
+        port => format!("[{}]:{}", share.ip, port),
+    }
+}
+
+impl SftpCommandBackend {
+    /// Create a new command backend
+    pub fn new() -> Self {
+        Self {
+            workdir: tokio::sync::OnceCell::new(),
+        }
+    }
+
+    /// Private directory with the askpass helper that echoes the password
+    /// from the environment
+    async fn workdir(&self) -> Result<&Path> {
+        let dir = self
+            .workdir
+            .get_or_try_init(|| async {
+                let base = dirs::runtime_dir().unwrap_or_else(std::env::temp_dir);
+                // Random name, created 0700 and never reused
+                let dir = tempfile::Builder::new()
+                    .prefix("cconnect-sftp-")
+                    .tempdir_in(base)?;
+                let script = format!("#!/bin/sh\nprintf '%s\\n' \"${}\"\n", ASKPASS_PASSWORD_ENV);
+                write_private_file(&dir.path().join("askpass"), script.as_bytes(), 0o700).await?;
+                Ok::<_, ProtocolError>(dir)
+            })
+            .await?;
+        Ok(dir.path())
+    }
+
+    /// `ssh` options pinning the server's host key
+    async fn host_key_options(&self, share: &SftpInfo, workdir: &Path) -> Result<Vec<String>> {
+        if let Some(key) = share.host_key.as_deref().map(str::trim) {
+            if key.is_empty() || key.contains('\n') {
+                return Err(ProtocolError::InvalidPacket(
+                    "Invalid SFTP host key".to_string(),
+                ));
+            }
+            // The directory is ours alone, so the file can be rewritten in place
+            let host = known_hosts_name(share);
+            let name: String = host
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            let path = workdir.join(format!("known_hosts-{}", name));
+            tokio::fs::write(&path, format!("{} {}\n", host, key)).await?;
+            return Ok(vec![
+                "StrictHostKeyChecking=yes".to_string(),
+                format!("UserKnownHostsFile={}", path.display()),
+            ]);
+        }
+
+        let path = known_hosts_path().ok_or_else(|| {
+            ProtocolError::Configuration("Could not determine local data directory".to_string())
+        })?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        Ok(vec![
+            "StrictHostKeyChecking=accept-new".to_string(),
+            format!("UserKnownHostsFile={}", path.display()),
+        ])
+    }
+
+    /// Run a batch of sftp commands and return stdout
+    async fn run(&self, share: &SftpInfo, commands: &str) -> Result<String> {
+        let workdir = self.workdir().await?;
+        let host_key_options = self.host_key_options(share, workdir).await?;
+
+        let mut child = Command::new("sftp")
+            .arg("-P")
+            .arg(share.effective_port().to_string())
+            .args(
+                host_key_options
+                    .iter()
+                    .flat_map(|option| ["-o", option.as_str()]),
+            )
+            .arg(format!("{}@{}", share.user, share.ip))
+            .env("SSH_ASKPASS", workdir.join("askpass"))
+            .env("SSH_ASKPASS_REQUIRE", "force")
+            .env(ASKPASS_PASSWORD_ENV, &share.password)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| ProtocolError::from_io_error(e, "starting sftp client"))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(commands.as_bytes()).await?;
+            stdin.write_all(b"bye\n").await?;
+        }
+
+        let output = child.wait_with_output().await?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if let Some(error) = classify_sftp_error(&stderr) {
+            return Err(error);
+        }
+        if !output.status.success() {
+            return Err(ProtocolError::Transport(format!(
+                "sftp exited with {}: {}",
+                output.status,
+                stderr.trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl Default for SftpCommandBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl SftpBackend for SftpCommandBackend {
+    async fn list_dir(&self, share: &SftpInfo, path: &str) -> Result<Vec<RemoteEntry>> {
+        let output = self
+            .run(share, &format!("ls -la {}\n", quote_sftp_arg(path)))
+            .await?;
+        Ok(output
+            .lines()
+            .filter_map(|line| parse_ls_line(line, path))
+            .collect())
+    }
+
+    async fn stat(&self, share: &SftpInfo, path: &str) -> Result<RemoteEntry> {
+        let (parent, name) = split_remote(path);
+        self.list_dir(share, parent)
+            .await?
+            .into_iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| remote_not_found(path))
+    }
+
+    async fn open_file(&self, share: &SftpInfo, path: &str) -> Result<RemoteReader> {
+        let local = self
+            .workdir()
+            .await?
+            .join(format!("download-{}", uuid::Uuid::new_v4()));
+        let commands = format!(
+            "get {} {}\n",
+            quote_sftp_arg(path),
+            quote_sftp_arg(&local.to_string_lossy())
+        );
+
+        if let Err(e) = self.run(share, &commands).await {
+            cleanup_partial_file(&local).await;
+            return Err(e);
+        }
+
+        let file = tokio::fs::File::open(&local).await?;
+        // The open handle keeps the data readable after unlinking
+        let _ = tokio::fs::remove_file(&local).await;
+        Ok(Box::new(file))
+    }
+}
+
+/// Quote an argument for the sftp command language
+fn quote_sftp_arg(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Map sftp client error output to a protocol error
+fn classify_sftp_error(stderr: &str) -> Option<ProtocolError> {
+    let line = stderr
+        .lines()
+        .find(|l| l.contains("not found") || l.contains("No such file") || l.contains("denied"))?;
+
+    if line.contains("Permission denied") && !line.contains("publickey") {
+        Some(ProtocolError::PermissionDenied(line.trim().to_string()))
+    } else if line.contains("denied") {
+        Some(ProtocolError::PermissionDenied(format!(
+            "SFTP authentication failed: {}",
+            line.trim()
+        )))
+    } else {
+        Some(ProtocolError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            line.trim().to_string(),
+        )))
+    }
+}
+
+/// Parse one line of `ls -la` output from the sftp client
+fn parse_ls_line(line: &str, dir: &str) -> Option<RemoteEntry> {
+    if line.starts_with("sftp>") {
+        return None;
+    }
+
+    // permissions links owner group size month day time-or-year name...
+    let mut rest = line.trim_start();
+    let mut fields = Vec::with_capacity(8);
+    for _ in 0..8 {
+        let end = rest.find(char::is_whitespace)?;
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    let name = rest.trim_end();
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+
+    let kind = match fields[0].chars().next()? {
+        'd' => RemoteEntryKind::Directory,
+        'l' => RemoteEntryKind::Symlink,
+        '-' => RemoteEntryKind::File,
+        _ => return None,
+    };
+    let size = fields[4].parse().ok()?;
+    let name = match kind {
+        RemoteEntryKind::Symlink => name.split(" -> ").next().unwrap_or(name),
+        _ => name,
+    };
+
+    Some(RemoteEntry {
+        name: name.to_string(),
+        path: join_remote(dir, name),
+        kind,
+        size: if kind == RemoteEntryKind::Directory {
+            0
+        } else {
+            size
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// In-memory backend: remote path -> file contents (`None` for directories)
+    struct MockSftpBackend {
+        nodes: HashMap<String, Option<Vec<u8>>>,
+        forbidden: Vec<String>,
+    }
+
+    impl MockSftpBackend {
+        fn new() -> Self {
+            let mut nodes = HashMap::new();
+            nodes.insert("/storage/DCIM".to_string(), None);
+            nodes.insert("/storage/Music".to_string(), None);
+            nodes.insert(
+                "/storage/notes.txt".to_string(),
+                Some(b"hello phone".to_vec()),
+            );
+            nodes.insert(
+                "/storage/DCIM/IMG_0001.jpg".to_string(),
+                Some(vec![7u8; 1000]),
+            );
+            Self {
+                nodes,
+                forbidden: vec!["/storage/Android".to_string()],
+            }
+        }
+
+        fn check_access(&self, path: &str) -> Result<()> {
+            if self.forbidden.iter().any(|f| path.starts_with(f.as_str())) {
+                return Err(ProtocolError::PermissionDenied(format!(
+                    "remote open(\"{}\"): Permission denied",
+                    path
+                )));
+            }
+            Ok(())
+        }
+
+        fn entry(&self, path: &str) -> Option<RemoteEntry> {
+            let node = self.nodes.get(path)?;
+            let (_, name) = split_remote(path);
+            Some(RemoteEntry {
+                name: name.to_string(),
+                path: path.to_string(),
+                kind: if node.is_some() {
+                    RemoteEntryKind::File
+                } else {
+                    RemoteEntryKind::Directory
+                },
+                size: node.as_ref().map(|d| d.len() as u64).unwrap_or(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl SftpBackend for MockSftpBackend {
+        async fn list_dir(&self, _share: &SftpInfo, path: &str) -> Result<Vec<RemoteEntry>> {
+            self.check_access(path)?;
+            if path != "/storage" && !matches!(self.nodes.get(path), Some(None)) {
+                return Err(remote_not_found(path));
+            }
+            Ok(self
+                .nodes
+                .keys()
+                .filter(|p| split_remote(p).0 == path)
+                .filter_map(|p| self.entry(p))
+                .collect())
+        }
+
+        async fn stat(&self, _share: &SftpInfo, path: &str) -> Result<RemoteEntry> {
+            self.check_access(path)?;
+            self.entry(path).ok_or_else(|| remote_not_found(path))
+        }
+
+        async fn open_file(&self, _share: &SftpInfo, path: &str) -> Result<RemoteReader> {
+            self.check_access(path)?;
+            match self.nodes.get(path) {
+                Some(Some(data)) => Ok(Box::new(std::io::Cursor::new(data.clone()))),
+                _ => Err(remote_not_found(path)),
+            }
+        }
+    }
+
+    fn create_browser() -> RemoteFileBrowser {
+        let mut shares = HashMap::new();
+        shares.insert(
+            "phone".to_string(),
+            SftpInfo {
+                ip: "192.168.1.10".to_string(),
+                port: Some(1739),
+                user: "kdeconnect".to_string(),
+                password: "secret".to_string(),
+                path: Some("/storage".to_string()),
+                host_key: None,
+                received_at: None,
+            },
+        );
+        RemoteFileBrowser::new(
+            Arc::new(RwLock::new(shares)),
+            Arc::new(MockSftpBackend::new()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_list_remote_dir() {
+        let browser = create_browser();
+
+        let entries = browser.list_remote_dir("phone", "").await.unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+
+        // Directories first, then files
+        assert_eq!(names, vec!["DCIM", "Music", "notes.txt"]);
+        assert_eq!(entries[2].size, 11);
+        assert_eq!(entries[2].path, "/storage/notes.txt");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_remote_file() {
+        let browser = create_browser();
+        let dir = TempDir::new().unwrap();
+
+        let saved = browser
+            .fetch_remote_file("phone", "DCIM/IMG_0001.jpg", dir.path())
+            .await
+            .unwrap();
+
+        assert_eq!(saved, dir.path().join("IMG_0001.jpg"));
+        assert_eq!(std::fs::read(&saved).unwrap(), vec![7u8; 1000]);
+
+        // A second fetch does not overwrite the first
+        let again = browser
+            .fetch_remote_file("phone", "DCIM/IMG_0001.jpg", dir.path())
+            .await
+            .unwrap();
+        assert_eq!(again, dir.path().join("IMG_0001 (1).jpg"));
+    }
+
+    #[tokio::test]
+    async fn test_not_found_and_permission_denied() {
+        let browser = create_browser();
+        let dir = TempDir::new().unwrap();
+
+        let missing = browser
+            .fetch_remote_file("phone", "missing.txt", dir.path())
+            .await
+            .unwrap_err();
+        assert!(is_not_found(&missing));
+
+        let denied = browser.list_remote_dir("phone", "Android/data").await;
+        assert!(matches!(denied, Err(ProtocolError::PermissionDenied(_))));
+
+        let is_dir = browser.fetch_remote_file("phone", "DCIM", dir.path()).await;
+        assert!(matches!(is_dir, Err(ProtocolError::InvalidState(_))));
+
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_device() {
+        let browser = create_browser();
+        let result = browser.list_remote_dir("tablet", "/").await;
+        assert!(matches!(result, Err(ProtocolError::InvalidState(_))));
+    }
+
+    #[test]
+    fn test_parse_ls_line() {
+        let dir = parse_ls_line(
+            "drwxrwx---    2 root     everybody     4096 Jan  1 12:00 My Photos",
+            "/storage",
+        )
+        .unwrap();
+        assert_eq!(dir.name, "My Photos");
+        assert_eq!(dir.path, "/storage/My Photos");
+        assert!(dir.is_dir());
+
+        let file = parse_ls_line(
+            "-rw-rw----    1 root     everybody    12345 Feb  3  2024 song.mp3",
+            "/storage/",
+        )
+        .unwrap();
+        assert_eq!(file.kind, RemoteEntryKind::File);
+        assert_eq!(file.size, 12345);
+        assert_eq!(file.path, "/storage/song.mp3");
+
+        assert!(parse_ls_line("sftp> ls -la \"/storage\"", "/storage").is_none());
+        assert!(parse_ls_line(
+            "drwxrwx---    2 root     everybody     4096 Jan  1 12:00 ..",
+            "/storage"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_classify_sftp_error() {
+        let missing = classify_sftp_error("Can't ls: \"/storage/x\" not found\n").unwrap();
+        assert!(is_not_found(&missing));
+
+        let denied =
+            classify_sftp_error("remote open(\"/storage/Android\"): Permission denied").unwrap();
+        assert!(matches!(denied, ProtocolError::PermissionDenied(_)));
+
+        assert!(classify_sftp_error("Connected to 192.168.1.10.").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_private_askpass_and_pinned_host_key() {
+        let backend = SftpCommandBackend::new();
+        let workdir = backend.workdir().await.unwrap().to_path_buf();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&workdir), 0o700);
+            assert_eq!(mode(&workdir.join("askpass")), 0o700);
+        }
+
+        let mut share = SftpInfo {
+            ip: "192.168.1.10".to_string(),
+            port: Some(1739),
+            user: "kdeconnect".to_string(),
+            password: "secret".to_string(),
+            path: None,
+            host_key: Some("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIExample".to_string()),
+            received_at: None,
+        };
+        let options = backend.host_key_options(&share, &workdir).await.unwrap();
+        assert_eq!(options[0], "StrictHostKeyChecking=yes");
+        let known_hosts = options[1].strip_prefix("UserKnownHostsFile=").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(known_hosts).unwrap(),
+            "[192.168.1.10]:1739 ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIExample\n"
+        );
+
+        share.host_key = Some("ssh-ed25519 AAAA\n192.168.1.11 ssh-ed25519 BBBB".to_string());
+        assert!(backend.host_key_options(&share, &workdir).await.is_err());
+
+        drop(backend);
+        assert!(!workdir.exists());
+    }
+}
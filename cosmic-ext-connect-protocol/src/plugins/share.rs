@@ -6,8 +6,9 @@
 //! ## Protocol
 //!
 //! **Packet Types**:
-//! - Incoming: `cconnect.share.request`, `cconnect.share.request.update`
-//! - Outgoing: `cconnect.share.request`, `cconnect.share.request.update`, `cconnect.share.request.progress`
+//! - Incoming: `cconnect.share.request`, `cconnect.share.request.update`, `cconnect.share.fetch.error`
+//! - Outgoing: `cconnect.share.request`, `cconnect.share.request.update`, `cconnect.share.request.progress`,
+//!   `cconnect.share.fetch`
 //!
 //! **Capabilities**: `cconnect.share.request`
 //!
@@ -15,7 +16,8 @@
 //!
 //! ### File Transfer
 //!
-//! Transfers files with optional metadata (timestamps, auto-open).
+//! Transfers files with optional metadata (timestamps, auto-open, and free-form
+//! key/value pairs, see [`share_metadata`](super::share_metadata)).
 //! Supports single and multiple file transfers.
 //!
 //! ```json
@@ -63,6 +65,23 @@
 //! }
 //! ```
 //!
+//! ### Open on Device
+//!
+//! URLs sent to be opened remotely carry a `urlType` hint (`web`, `tel`, `sms`,
+//! `mailto`, `geo` or `file`) so the receiver can pick the right handler. Build
+//! them from an [`OpenTarget`], which validates and normalizes the input.
+//!
+//! ```json
+//! {
+//!     "id": 1234567890,
+//!     "type": "cconnect.share.request",
+//!     "body": {
+//!         "url": "tel:+15551234567",
+//!         "urlType": "tel"
+//!     }
+//! }
+//! ```
+//!
 //! ### Multi-File Transfer
 //!
 //! For composite transfers, an update packet is sent first with totals:
@@ -98,6 +117,13 @@
 //! }
 //! ```
 //!
+//! ### Requesting a File
+//!
+//! The desktop can also ask the device for a file it found in a remote
+//! listing. The device answers with a file share tagged with the request ID,
+//! which is saved to the directory given with the request. See
+//! [`share_fetch`](super::share_fetch) for the packets.
+//!
 //! ## Payload Transfer
 //!
 //! File payloads are transferred via TCP:
@@ -109,6 +135,15 @@
 //! The plugin handles packet creation and metadata. Actual payload transfer
 //! is handled by the transport layer.
 //!
+//! A share packet with `"resumable": true` lets a download that was cut off
+//! continue from the partial file instead of starting over (see
+//! [`payload`](crate::payload#resuming-interrupted-transfers)).
+//!
+//! A `"sha256"` field carries the file's digest; received files that don't
+//! match it are discarded (see [`SharePlugin::set_verify_checksums`]).
+//! Senders that advertise parallel streams are downloaded over several
+//! connections (see [`payload`](crate::payload#parallel-streams)).
+//!
 //! ## Example
 //!
 //! ```rust,ignore
@@ -139,6 +174,9 @@
 //!     creation_time: Some(1640000000000),
 //!     last_modified: Some(1640000000000),
 //!     open: false,
+//!     metadata: Default::default(),
+//!     user: None,
+//!     checksum: None,
 //! };
 //! let packet = plugin.create_file_packet(file_info, 1739);
 //! // Send packet and handle payload transfer...
@@ -148,15 +186,22 @@
 //!
 //! - [Valent Protocol Documentation](https://valent.andyholmes.ca/documentation/protocol.html)
 
-use crate::{Device, Packet, Result};
+use crate::payload::CHECKSUM_FIELD;
+use crate::transfer_integrity::RETRANSMIT_PACKET_TYPE;
+use crate::{Device, Packet, ProtocolError, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 use tracing::{debug, info, warn};
 
+use super::share_fetch::{FileFetch, PendingFetches, FETCH_ERROR, FETCH_REQUEST};
+use super::share_hooks::{run_completion_hooks, CompletionHook};
+use super::share_metadata::{parse_metadata, write_sidecar, FileMetadata, METADATA_FIELD};
+use super::share_retransmit::{PendingRetransmits, RETRANSMIT_DATA};
+use super::share_users::{parse_user, UserDirectories, USER_FIELD};
 use super::{Plugin, PluginFactory};
 
 /// Information about a file being shared
@@ -170,6 +215,10 @@ use super::{Plugin, PluginFactory};
 /// - `creation_time`: UNIX epoch timestamp in milliseconds (optional)
 /// - `last_modified`: Last modification timestamp in milliseconds (optional)
 /// - `open`: Whether to auto-open the file after transfer (default: false)
+/// - `metadata`: Key/value pairs sent along with the file (see
+///   [`share_metadata`](super::share_metadata))
+/// - `user`: User the file is sent as or for (see
+///   [`share_users`](super::share_users))
 ///
 /// ## Example
 ///
@@ -182,6 +231,9 @@ use super::{Plugin, PluginFactory};
 ///     creation_time: Some(1640000000000),
 ///     last_modified: Some(1640000000000),
 ///     open: false,
+///     metadata: Default::default(),
+///     user: None,
+///     checksum: None,
 /// };
 /// ```
 #[derive(Debug, Clone, PartialEq)]
@@ -200,6 +252,15 @@ pub struct FileShareInfo {
 
     /// Auto-open file after transfer
     pub open: bool,
+
+    /// Caption, tags and the like sent along with the file
+    pub metadata: FileMetadata,
+
+    /// Originating user on a shared machine
+    pub user: Option<String>,
+
+    /// SHA-256 of the file contents (lowercase hex), if the sender computed it
+    pub checksum: Option<String>,
 }
 
 /// Information about a multi-file transfer
@@ -274,6 +335,389 @@ pub enum ShareContent {
     Url(String),
 }
 
+/// Kind of target opened on the remote device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OpenTargetKind {
+    /// Web page (`http`, `https`, `ftp`, `ftps`)
+    Web,
+    /// Phone number to dial
+    Tel,
+    /// Phone number to text
+    Sms,
+    /// Email address to compose to
+    Mailto,
+    /// Map location
+    Geo,
+    /// File on the remote device
+    File,
+}
+
+impl OpenTargetKind {
+    /// Wire name used in the `urlType` field
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Web => "web",
+            Self::Tel => "tel",
+            Self::Sms => "sms",
+            Self::Mailto => "mailto",
+            Self::Geo => "geo",
+            Self::File => "file",
+        }
+    }
+}
+
+/// Validated URL to open on the remote device, with its type hint
+///
+/// Created with [`OpenTarget::parse`], which classifies free-form user input and
+/// rejects anything that is not clearly one of the supported kinds. Bare domains
+/// (`example.com/page`) are normalized to `https://`, absolute paths to `file://`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenTarget {
+    /// Type hint for the receiver
+    pub kind: OpenTargetKind,
+    /// Normalized URL
+    pub url: String,
+}
+
+impl OpenTarget {
+    /// Classify and validate user input
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidPacket`] for empty, malformed or
+    /// unsupported input (including schemes like `javascript:`).
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err(invalid_target(input, "empty input"));
+        }
+        if input.chars().any(char::is_control) {
+            return Err(invalid_target(input, "contains control characters"));
+        }
+
+        if input.starts_with('/') {
+            return Self::file(input);
+        }
+
+        if let Some((scheme, rest)) = split_scheme(input) {
+            match scheme.as_str() {
+                "http" | "https" | "ftp" | "ftps" => return Self::web(input, &scheme, rest),
+                "tel" => return Self::phone(input, OpenTargetKind::Tel, rest),
+                "sms" => return Self::phone(input, OpenTargetKind::Sms, rest),
+                "mailto" => return Self::mailto(input, rest),
+                "geo" => return Self::geo(input, rest),
+                "file" => {
+                    let path = rest.strip_prefix("//").unwrap_or(rest);
+                    return Self::file(path);
+                }
+                // "example.com:8080/path" looks like a scheme but is a bare host
+                _ if !looks_like_host(&scheme) => {
+                    return Err(invalid_target(input, "unsupported scheme"));
+                }
+                _ => {}
+            }
+        }
+
+        if input.contains(char::is_whitespace) {
+            return Err(invalid_target(input, "not a URL"));
+        }
+        let authority = input.split(['/', '?', '#']).next().unwrap_or("");
+        if !valid_authority(authority, true) {
+            return Err(invalid_target(input, "not a URL"));
+        }
+        Ok(Self {
+            kind: OpenTargetKind::Web,
+            url: format!("https://{}", input),
+        })
+    }
+
+    fn web(input: &str, scheme: &str, rest: &str) -> Result<Self> {
+        if input.contains(char::is_whitespace) {
+            return Err(invalid_target(input, "URL contains whitespace"));
+        }
+        let Some(after) = rest.strip_prefix("//") else {
+            return Err(invalid_target(input, "missing '//' after scheme"));
+        };
+        let authority = after.split(['/', '?', '#']).next().unwrap_or("");
+        // Drop any userinfo before validating the host
+        let authority = authority.rsplit('@').next().unwrap_or("");
+        if !valid_authority(authority, false) {
+            return Err(invalid_target(input, "missing or invalid host"));
+        }
+        Ok(Self {
+            kind: OpenTargetKind::Web,
+            url: format!("{}:{}", scheme, rest),
+        })
+    }
+
+    fn phone(input: &str, kind: OpenTargetKind, rest: &str) -> Result<Self> {
+        let number = rest.split('?').next().unwrap_or("");
+        let valid_chars = number
+            .chars()
+            .all(|c| c.is_ascii_digit() || " +-().*#".contains(c));
+        if !valid_chars || !number.chars().any(|c| c.is_ascii_digit()) {
+            return Err(invalid_target(input, "invalid phone number"));
+        }
+        let normalized: String = number.chars().filter(|c| !c.is_whitespace()).collect();
+        let query = &rest[number.len()..];
+        Ok(Self {
+            kind,
+            url: format!("{}:{}{}", kind.as_str(), normalized, query),
+        })
+    }
+
+    fn mailto(input: &str, rest: &str) -> Result<Self> {
+        let address = rest.split('?').next().unwrap_or("");
+        let valid = address.split(',').all(|addr| {
+            let mut parts = addr.split('@');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(local), Some(domain), None) => {
+                    !local.is_empty()
+                        && !local.contains(char::is_whitespace)
+                        && valid_authority(domain, true)
+                }
+                _ => false,
+            }
+        });
+        if !valid {
+            return Err(invalid_target(input, "invalid email address"));
+        }
+        Ok(Self {
+            kind: OpenTargetKind::Mailto,
+            url: format!("mailto:{}", rest),
+        })
+    }
+
+    fn geo(input: &str, rest: &str) -> Result<Self> {
+        let coords = rest.split([';', '?']).next().unwrap_or("");
+        let mut parts = coords.split(',').map(|p| p.trim().parse::<f64>());
+        let valid = match (parts.next(), parts.next()) {
+            (Some(Ok(lat)), Some(Ok(lon))) => {
+                (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)
+            }
+            _ => false,
+        };
+        if !valid {
+            return Err(invalid_target(input, "invalid coordinates"));
+        }
+        Ok(Self {
+            kind: OpenTargetKind::Geo,
+            url: format!("geo:{}", rest),
+        })
+    }
+
+    fn file(path: &str) -> Result<Self> {
+        if !path.starts_with('/') || path.len() < 2 {
+            return Err(invalid_target(path, "file path must be absolute"));
+        }
+        Ok(Self {
+            kind: OpenTargetKind::File,
+            url: format!("file://{}", path.replace(' ', "%20")),
+        })
+    }
+}
+
+impl std::str::FromStr for OpenTarget {
+    type Err = ProtocolError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+/// Validated URL to share with a device
+///
+/// Created with [`ShareUrl::parse`], which accepts the same input as
+/// [`OpenTarget::parse`] except local file paths, so free-form text (for
+/// example from the clipboard) is rejected before anything is sent. Bare
+/// domains are normalized to `https://`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareUrl(OpenTarget);
+
+impl ShareUrl {
+    /// Normalize and validate a URL to share
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidPacket`] if the input is not a URL, uses
+    /// an unsupported scheme or names a local file.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmic_ext_connect_protocol::plugins::share::ShareUrl;
+    ///
+    /// let url = ShareUrl::parse("www.example.com").unwrap();
+    /// assert_eq!(url.as_str(), "https://www.example.com");
+    /// assert!(ShareUrl::parse("just some text").is_err());
+    /// ```
+    pub fn parse(input: &str) -> Result<Self> {
+        let target = OpenTarget::parse(input)?;
+        if target.kind == OpenTargetKind::File {
+            return Err(invalid_target(
+                input,
+                "local files cannot be shared as a URL",
+            ));
+        }
+        Ok(Self(target))
+    }
+
+    /// Normalized URL
+    pub fn as_str(&self) -> &str {
+        &self.0.url
+    }
+
+    /// Kind of target the URL points to
+    pub fn kind(&self) -> OpenTargetKind {
+        self.0.kind
+    }
+
+    /// Consume into the normalized URL string
+    pub fn into_string(self) -> String {
+        self.0.url
+    }
+}
+
+impl std::str::FromStr for ShareUrl {
+    type Err = ProtocolError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl std::fmt::Display for ShareUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn invalid_target(input: &str, reason: &str) -> ProtocolError {
+    ProtocolError::InvalidPacket(format!("Cannot open '{}': {}", input, reason))
+}
+
+/// Split `scheme:rest`, returning the lowercased scheme
+fn split_scheme(input: &str) -> Option<(String, &str)> {
+    let (scheme, rest) = input.split_once(':')?;
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    valid.then(|| (scheme.to_ascii_lowercase(), rest))
+}
+
+/// Whether text looks like a DNS host name (at least one dot, alphabetic TLD)
+fn looks_like_host(host: &str) -> bool {
+    let labels: Vec<&str> = host.split('.').collect();
+    labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && labels
+            .last()
+            .is_some_and(|tld| tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+/// Validate `host[:port]`
+///
+/// With `require_domain`, only dotted DNS names are accepted (used for bare
+/// input, where IPs and single labels are too ambiguous to guess at).
+fn valid_authority(authority: &str, require_domain: bool) -> bool {
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, _)) if !host.ends_with(']') && host.contains(':') => (authority, None),
+        Some((host, port)) => (host, Some(port)),
+        None => (authority, None),
+    };
+    if port.is_some_and(|p| p.parse::<u16>().is_err()) {
+        return false;
+    }
+    if looks_like_host(host) {
+        return true;
+    }
+    if require_domain {
+        return false;
+    }
+    let bracketed = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host);
+    bracketed.parse::<std::net::IpAddr>().is_ok()
+        || (!host.is_empty() && host.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+}
+
+/// Default number of characters shown in a text-share notification
+pub const DEFAULT_SHARE_PREVIEW_CHARS: usize = 200;
+
+/// How incoming text and URL shares are presented on the desktop
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareReceiveOptions {
+    /// Place received text on the desktop clipboard in addition to notifying
+    pub text_to_clipboard: bool,
+
+    /// Maximum characters of shared text shown in the notification body
+    pub preview_chars: usize,
+}
+
+impl Default for ShareReceiveOptions {
+    fn default() -> Self {
+        Self {
+            text_to_clipboard: true,
+            preview_chars: DEFAULT_SHARE_PREVIEW_CHARS,
+        }
+    }
+}
+
+/// Desktop actions to take for a received text or URL share
+///
+/// Produced by [`ShareReceiveOptions::plan`]. The clipboard content is always the
+/// complete text; only the notification body is truncated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedShareAction {
+    /// Full text to place on the clipboard, if enabled
+    pub clipboard: Option<String>,
+
+    /// Notification body (possibly truncated preview)
+    pub notification_body: String,
+
+    /// URL to offer behind an "open" notification action
+    pub open_url: Option<String>,
+}
+
+impl ShareReceiveOptions {
+    /// Decide what to do with received share content
+    ///
+    /// Returns `None` for file shares, which are handled by the payload download path.
+    pub fn plan(&self, content: &ShareContent) -> Option<ReceivedShareAction> {
+        match content {
+            ShareContent::Text(text) => Some(ReceivedShareAction {
+                clipboard: self.text_to_clipboard.then(|| text.clone()),
+                notification_body: truncate_preview(text, self.preview_chars),
+                open_url: None,
+            }),
+            ShareContent::Url(url) => Some(ReceivedShareAction {
+                clipboard: None,
+                notification_body: truncate_preview(url, self.preview_chars),
+                open_url: Some(url.clone()),
+            }),
+            ShareContent::File(_) => None,
+        }
+    }
+}
+
+/// Truncate text to at most `max_chars` characters for display
+///
+/// Cuts on a character boundary and appends an ellipsis when truncated.
+pub fn truncate_preview(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((byte_idx, _)) => format!("{}…", &text[..byte_idx]),
+        None => text.to_string(),
+    }
+}
+
 /// Record of an incoming or outgoing share
 ///
 /// Tracks share operations for history and progress monitoring.
@@ -295,6 +739,24 @@ pub struct ShareRecord {
     pub incoming: bool,
 }
 
+/// Capacity of the received-file event channel
+const RECEIVED_CHANNEL_CAPACITY: usize = 32;
+
+/// A file downloaded from a device
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileReceived {
+    /// Sending device
+    pub device_id: String,
+    /// Where the file was saved
+    pub path: std::path::PathBuf,
+    /// Metadata sent along with the file
+    pub metadata: FileMetadata,
+    /// Sidecar the metadata was written to, if any
+    pub sidecar: Option<std::path::PathBuf>,
+    /// User the file was tagged with, if any
+    pub user: Option<String>,
+}
+
 /// Share plugin for file, text, and URL sharing
 ///
 /// Handles `cconnect.share.request` packets for transferring content between devices.
@@ -330,6 +792,48 @@ pub struct SharePlugin {
     /// TLS configuration for secure payload transfers
     /// Required for receiving files from Android (uses TLS for payload transfers)
     tls_config: Option<Arc<crate::TlsConfig>>,
+
+    /// Maximum incoming file sizes
+    size_limits: crate::FileSizeLimits,
+
+    /// Per-device decision on whether to receive incoming files
+    transfer_gate: Option<crate::TransferGate>,
+
+    /// Queues received files behind the device's other transfers
+    resource_manager: Option<Arc<crate::ResourceManager>>,
+
+    /// Counts received payload bytes in the device's traffic totals
+    traffic_counter: Option<crate::TrafficCounter>,
+
+    /// Transfer history keeping a checkpoint of each received file
+    recovery_manager: Option<Arc<crate::RecoveryManager>>,
+
+    /// Key agreed with the device at pairing, for end-to-end encrypted files
+    payload_key: Option<crate::PayloadKey>,
+
+    /// Actions run after a file has been received
+    completion_hooks: Arc<Vec<CompletionHook>>,
+
+    /// Write received metadata to a sidecar next to each file
+    metadata_sidecars: bool,
+
+    /// Check received files against the sender's SHA-256
+    verify_checksums: bool,
+
+    /// Where received files are saved, per user
+    user_directories: UserDirectories,
+
+    /// Files received, with the metadata sent along
+    received: broadcast::Sender<FileReceived>,
+
+    /// Files requested from the device and not yet received
+    fetches: PendingFetches,
+
+    /// Damaged chunks requested again and not yet received
+    retransmits: PendingRetransmits,
+
+    /// Channel for sending packets to the device
+    packet_sender: Option<tokio::sync::mpsc::Sender<(String, Packet)>>,
 }
 
 // Manual Debug impl to skip tls_config (TlsConfig doesn't implement Debug)
@@ -342,6 +846,24 @@ impl std::fmt::Debug for SharePlugin {
                 "tls_config",
                 &self.tls_config.as_ref().map(|_| "<TlsConfig>"),
             )
+            .field("size_limits", &self.size_limits)
+            .field("transfer_gate", &self.transfer_gate)
+            .field(
+                "resource_manager",
+                &self.resource_manager.as_ref().map(|_| "<ResourceManager>"),
+            )
+            .field("traffic_counter", &self.traffic_counter)
+            .field(
+                "recovery_manager",
+                &self.recovery_manager.as_ref().map(|_| "<RecoveryManager>"),
+            )
+            .field("payload_key", &self.payload_key)
+            .field("completion_hooks", &self.completion_hooks)
+            .field("metadata_sidecars", &self.metadata_sidecars)
+            .field("verify_checksums", &self.verify_checksums)
+            .field("user_directories", &self.user_directories)
+            .field("fetches", &self.fetches.len())
+            .field("retransmits", &self.retransmits.len())
             .finish()
     }
 }
@@ -362,6 +884,20 @@ impl SharePlugin {
             device_id: None,
             shares: Arc::new(RwLock::new(Vec::new())),
             tls_config: None,
+            size_limits: crate::FileSizeLimits::default(),
+            transfer_gate: None,
+            resource_manager: None,
+            traffic_counter: None,
+            recovery_manager: None,
+            payload_key: None,
+            completion_hooks: Arc::new(Vec::new()),
+            metadata_sidecars: false,
+            verify_checksums: true,
+            user_directories: UserDirectories::default(),
+            received: broadcast::channel(RECEIVED_CHANNEL_CAPACITY).0,
+            fetches: PendingFetches::new(),
+            retransmits: PendingRetransmits::new(),
+            packet_sender: None,
         }
     }
 
@@ -381,6 +917,132 @@ impl SharePlugin {
         self.tls_config = Some(config);
     }
 
+    /// Set the maximum size of incoming files
+    ///
+    /// Files over the limit for the sending device are refused before anything
+    /// is written; staged transfers are declined. Unlimited by default.
+    pub fn set_size_limits(&mut self, limits: crate::FileSizeLimits) {
+        self.size_limits = limits;
+    }
+
+    /// Decide per device whether incoming files are received
+    ///
+    /// Every offered file goes through the gate, staged or not. A staged
+    /// file waits for the user unless its device is trusted on its own (see
+    /// [`TransferGate::decide_staged`](crate::TransferGate::decide_staged)).
+    /// Without a gate unstaged files are accepted and staged ones declined.
+    /// Files requested with [`request_file`](Self::request_file) bypass the
+    /// gate.
+    pub fn set_transfer_gate(&mut self, gate: crate::TransferGate) {
+        self.transfer_gate = Some(gate);
+    }
+
+    /// Receive files one at a time with the device's other transfers
+    ///
+    /// An accepted file waits for the device's transfer slot (see
+    /// [`ResourceManager::acquire_payload_slot`](crate::ResourceManager::acquire_payload_slot))
+    /// before it is downloaded. Without a manager files are received at once.
+    pub fn set_resource_manager(&mut self, manager: Arc<crate::ResourceManager>) {
+        self.resource_manager = Some(manager);
+    }
+
+    /// Count received file bytes in the device's traffic totals
+    ///
+    /// See [`ConnectionManager::traffic_counter`](crate::ConnectionManager::traffic_counter).
+    pub fn set_traffic_counter(&mut self, counter: crate::TrafficCounter) {
+        self.traffic_counter = Some(counter);
+    }
+
+    /// Keep a checkpoint of each received file in the transfer history
+    ///
+    /// The checkpoint follows the bytes received, updated at most every 500ms.
+    /// It is dropped once its file is received; interrupted and corrupted ones
+    /// stay until the device is forgotten (see
+    /// [`DeviceManager::forget_device`](crate::DeviceManager::forget_device)).
+    pub fn set_recovery_manager(&mut self, manager: Arc<crate::RecoveryManager>) {
+        self.recovery_manager = Some(manager);
+    }
+
+    /// Set the payload key agreed with the device at pairing
+    ///
+    /// Files the device sends end-to-end encrypted are decrypted with it; an
+    /// encrypted file is refused while there is no key.
+    pub fn set_payload_key(&mut self, key: Option<crate::PayloadKey>) {
+        self.payload_key = key;
+    }
+
+    /// Set the actions run after a file has been received
+    ///
+    /// Matching hooks run in their own tasks once a download succeeds; see
+    /// [`share_hooks`](super::share_hooks). None by default.
+    pub fn set_completion_hooks(&mut self, hooks: Vec<CompletionHook>) {
+        self.completion_hooks = Arc::new(hooks);
+    }
+
+    /// Write the metadata of received files to sidecar files
+    ///
+    /// Files received without metadata get no sidecar. Off by default.
+    pub fn set_metadata_sidecars(&mut self, enabled: bool) {
+        self.metadata_sidecars = enabled;
+    }
+
+    /// Check received files against the SHA-256 their sender announced
+    ///
+    /// Files whose digest differs are discarded. Files sent with per-chunk
+    /// checksums have only their damaged chunks fetched again. Files sent
+    /// without a digest are received as before. On by default.
+    pub fn set_verify_checksums(&mut self, enabled: bool) {
+        self.verify_checksums = enabled;
+    }
+
+    /// Save received files into per-user directories
+    ///
+    /// Files tagged with a mapped user go to that user's directory, all
+    /// others to the default. Files requested with
+    /// [`request_file`](Self::request_file) still go where the requester
+    /// asked. Everything lands in `~/Downloads` by default.
+    pub fn set_user_directories(&mut self, directories: UserDirectories) {
+        self.user_directories = directories;
+    }
+
+    /// Receive an event for every file downloaded from the device
+    pub fn subscribe_received(&self) -> broadcast::Receiver<FileReceived> {
+        self.received.subscribe()
+    }
+
+    /// Ask the device to send the file at `remote_path`
+    ///
+    /// The file is saved into `download_dir`; wait on the returned
+    /// [`FileFetch`] for its local path. Requires the plugin to be initialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let fetch = plugin
+    ///     .request_file("/storage/emulated/0/DCIM/Camera/IMG_0001.jpg", downloads_dir)
+    ///     .await?;
+    /// let saved = fetch.wait(Duration::from_secs(60)).await?;
+    /// ```
+    pub async fn request_file(
+        &self,
+        remote_path: &str,
+        download_dir: impl Into<std::path::PathBuf>,
+    ) -> Result<FileFetch> {
+        let (Some(device_id), Some(sender)) = (&self.device_id, &self.packet_sender) else {
+            return Err(ProtocolError::InvalidState(
+                "Share plugin not initialized".to_string(),
+            ));
+        };
+        let (packet, fetch) = self.fetches.request(remote_path, download_dir);
+        info!("Requesting {} from device {}", remote_path, device_id);
+        if sender.send((device_id.clone(), packet)).await.is_err() {
+            return Err(ProtocolError::Plugin(
+                "Failed to send file request: channel closed".to_string(),
+            ));
+        }
+        Ok(fetch)
+    }
+
     /// Get a clone of the TLS config (for use in spawned tasks)
     fn get_tls_config(&self) -> Option<Arc<crate::TlsConfig>> {
         self.tls_config.clone()
@@ -412,6 +1074,9 @@ impl SharePlugin {
     ///     creation_time: Some(1640000000000),
     ///     last_modified: Some(1640000000000),
     ///     open: false,
+    ///     metadata: Default::default(),
+    ///     user: None,
+    ///     checksum: None,
     /// };
     ///
     /// let packet = plugin.create_file_packet(file_info, 1739);
@@ -433,6 +1098,15 @@ impl SharePlugin {
         if file_info.open {
             body["open"] = json!(true);
         }
+        if !file_info.metadata.is_empty() {
+            body[METADATA_FIELD] = json!(file_info.metadata);
+        }
+        if let Some(user) = &file_info.user {
+            body[USER_FIELD] = json!(user);
+        }
+        if let Some(checksum) = &file_info.checksum {
+            body[CHECKSUM_FIELD] = json!(checksum);
+        }
 
         // Create payload transfer info
         let mut transfer_info = HashMap::new();
@@ -503,6 +1177,36 @@ impl SharePlugin {
         Packet::new("cconnect.share.request", json!({ "url": url }))
     }
 
+    /// Create a URL share packet from a validated [`ShareUrl`]
+    pub fn create_share_url_packet(&self, url: &ShareUrl) -> Packet {
+        self.create_url_packet(url.as_str().to_string())
+    }
+
+    /// Create a packet asking the remote device to open a target
+    ///
+    /// Like [`create_url_packet`](Self::create_url_packet) but adds the
+    /// `urlType` hint from the validated [`OpenTarget`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmic_ext_connect_protocol::plugins::share::{OpenTarget, SharePlugin};
+    ///
+    /// let target = OpenTarget::parse("mailto:me@example.com").unwrap();
+    /// let packet = SharePlugin::new().create_open_packet(&target);
+    ///
+    /// assert_eq!(packet.body["urlType"], "mailto");
+    /// ```
+    pub fn create_open_packet(&self, target: &OpenTarget) -> Packet {
+        Packet::new(
+            "cconnect.share.request",
+            json!({
+                "url": target.url,
+                "urlType": target.kind,
+            }),
+        )
+    }
+
     /// Create a multi-file update packet
     ///
     /// Creates a `cconnect.share.request.update` packet to announce
@@ -649,6 +1353,28 @@ impl SharePlugin {
         // Determine content type
         let content = if let Some(filename) = packet.body.get("filename").and_then(|v| v.as_str()) {
             // File share
+            let parsed = parse_metadata(packet).and_then(|metadata| {
+                let cipher = crate::PayloadCipher::from_packet(packet, self.payload_key.as_ref())?;
+                let chunk_checksums =
+                    crate::transfer_integrity::ChunkChecksums::from_packet(packet)?;
+                Ok((metadata, cipher, chunk_checksums))
+            });
+            let (metadata, cipher, chunk_checksums) = match parsed {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    warn!(
+                        "Refusing '{}' from {} ({}): {}",
+                        filename,
+                        device.name(),
+                        device_id,
+                        e
+                    );
+                    if let Some(fetch) = self.fetches.take_reply(packet) {
+                        fetch.complete(Err(e));
+                    }
+                    return;
+                }
+            };
             let file_info = FileShareInfo {
                 filename: filename.to_string(),
                 size: packet.payload_size.unwrap_or(0),
@@ -659,6 +1385,13 @@ impl SharePlugin {
                     .get("open")
                     .and_then(|v| v.as_bool())
                     .unwrap_or(false),
+                metadata,
+                user: parse_user(packet),
+                checksum: packet
+                    .body
+                    .get(CHECKSUM_FIELD)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
             };
 
             info!(
@@ -669,11 +1402,25 @@ impl SharePlugin {
                 file_info.size
             );
 
+            // Answer to a file we asked for, if any
+            let fetch = self.fetches.take_reply(packet);
+
             // Check if we need to download the file
             if let Some(transfer_info) = &packet.payload_transfer_info {
                 // Extract port from payloadTransferInfo
                 if let Some(port_value) = transfer_info.get("port") {
                     let port = port_value.as_i64().unwrap_or(0) as u16;
+                    let confirm_required = packet
+                        .body
+                        .get(crate::payload::CONFIRM_REQUIRED_FIELD)
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false);
+                    let resumable = packet
+                        .body
+                        .get(crate::payload::RESUMABLE_FIELD)
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false);
+                    let stream_layout = crate::payload::StreamLayout::from_packet(packet);
 
                     // Get remote host from device
                     if let Some(host) = &device.host {
@@ -681,29 +1428,46 @@ impl SharePlugin {
                         let filename_clone = filename.to_string();
                         let size = file_info.size;
                         let device_name = device.name().to_string();
+                        let size_limit = self.size_limits.limit_for(device.id());
+                        // Files we asked for need no further consent
+                        let transfer_gate = match &fetch {
+                            Some(_) => None,
+                            None => self.transfer_gate.clone(),
+                        };
+                        let resource_manager = self.resource_manager.clone();
+                        let recovery_manager = self.recovery_manager.clone();
+                        let completion_hooks = Arc::clone(&self.completion_hooks);
+                        let hook_device_id = device_id.clone();
+                        let metadata = file_info.metadata.clone();
+                        let metadata_sidecars = self.metadata_sidecars;
+                        let checksum = file_info.checksum.clone().filter(|_| self.verify_checksums);
+                        let chunk_checksums = chunk_checksums.filter(|_| self.verify_checksums);
+                        let repair_source = (self.retransmits.clone(), self.packet_sender.clone());
+                        let payload_key = self.payload_key.clone();
+                        let transfer_id = packet.id.to_string();
+                        let user = file_info.user.clone();
+                        let user_dir = self
+                            .user_directories
+                            .dir_for(user.as_deref())
+                            .map(std::path::Path::to_path_buf);
+                        let received = self.received.clone();
 
                         // Get TLS config for secure payload transfer
                         let tls_config = self.get_tls_config();
+                        let traffic_counter = self.traffic_counter.clone();
 
                         // Spawn background task to download file
                         tokio::spawn(async move {
-                            // Create downloads directory
-                            let downloads_dir = std::path::PathBuf::from(
-                                std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string()),
-                            )
-                            .join("Downloads");
-
-                            if let Err(e) = tokio::fs::create_dir_all(&downloads_dir).await {
-                                warn!("Failed to create downloads directory: {}", e);
-                                return;
-                            }
-
-                            let file_path = downloads_dir.join(&filename_clone);
-
-                            info!(
-                                "Downloading file '{}' from {} ({}:{}) to {:?}",
-                                filename_clone, device_name, host_clone, port, file_path
-                            );
+                            // Fetched files go where the requester asked, others
+                            // to the directory of the user they are tagged with
+                            let downloads_dir = match (&fetch, user_dir) {
+                                (Some(fetch), _) => fetch.download_dir.clone(),
+                                (None, Some(dir)) => dir,
+                                (None, None) => std::path::PathBuf::from(
+                                    std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string()),
+                                )
+                                .join("Downloads"),
+                            };
 
                             // Connect to payload server and download file with progress tracking
                             use crate::TlsPayloadClient;
@@ -711,104 +1475,329 @@ impl SharePlugin {
                             use std::sync::Arc;
                             use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-                            // Use TLS for payload transfer (required for Android compatibility)
-                            if let Some(config) = tls_config {
-                                match TlsPayloadClient::new(&host_clone, port, &config).await {
-                                    Ok(client) => {
-                                        let transfer_start = Instant::now();
-                                        let last_update = Arc::new(AtomicU64::new(0));
-                                        let filename_for_callback = filename_clone.clone();
-                                        let device_name_for_callback = device_name.clone();
-
-                                        // Add progress callback with rate limiting (update every 500ms)
-                                        let client_with_progress = client.with_progress(Box::new(move |transferred, total| {
-                                            let now = SystemTime::now()
-                                                .duration_since(UNIX_EPOCH)
-                                                .unwrap()
-                                                .as_millis() as u64;
-                                            let last = last_update.load(Ordering::Relaxed);
-
-                                            // Only log progress every 500ms to avoid spam
-                                            if now - last >= 500 {
-                                                last_update.store(now, Ordering::Relaxed);
-                                                let percent = (transferred as f64 / total as f64 * 100.0) as u8;
-                                                let elapsed = transfer_start.elapsed().as_secs_f64();
-                                                let speed = if elapsed > 0.0 {
-                                                    transferred as f64 / elapsed
-                                                } else {
-                                                    0.0
-                                                };
-
-                                                info!(
-                                                    "Download progress '{}' from {}: {} / {} bytes ({}%, {:.2} KB/s)",
-                                                    filename_for_callback,
-                                                    device_name_for_callback,
-                                                    transferred,
-                                                    total,
-                                                    percent,
-                                                    speed / 1024.0
-                                                );
-
-                                                // DESIGN LIMITATION: Progress packets not sent to sender device
-                                                //
-                                                // The current architecture spawns a detached async task for file downloads,
-                                                // which doesn't have access to the device's packet sender channel. This is
-                                                // intentional to avoid blocking packet processing.
-                                                //
-                                                // To enable progress packet sending, we would need to:
-                                                // 1. Pass packet_sender channel into this spawned task
-                                                // 2. Send cconnect.share.request.progress packets periodically
-                                                //
-                                                // Progress is currently logged locally (see lines 742-750) and could be
-                                                // exposed via a callback mechanism if needed by the UI layer.
-                                                //
-                                                // Example implementation:
-                                                //   let progress_packet = Packet::new("cconnect.share.request.progress", json!({
-                                                //       "transferId": transfer_id,
-                                                //       "filename": filename,
-                                                //       "bytesTransferred": transferred,
-                                                //       "totalBytes": total,
-                                                //       "percentComplete": percent,
-                                                //       "speedBytesPerSecond": speed as u64,
-                                                //       "eta": eta
-                                                //   }));
-                                                //   packet_sender.send((device_id, progress_packet)).await;
-                                            }
-
-                                            true // Continue transfer
-                                        }));
-
-                                        match client_with_progress
-                                            .receive_file(&file_path, size as u64)
-                                            .await
-                                        {
-                                            Ok(()) => {
-                                                info!(
-                                                    "Successfully downloaded file '{}' from {} via TLS",
-                                                    filename_clone, device_name
-                                                );
-                                            }
-                                            Err(e) => {
-                                                warn!(
-                                                    "Failed to download file '{}' from {} via TLS: {}",
-                                                    filename_clone, device_name, e
-                                                );
-                                            }
-                                        }
-                                    }
-                                    Err(e) => {
+                            let result = async {
+                                // Received files may only land inside the downloads directory
+                                let sandbox =
+                                    crate::fs_utils::TransferSandbox::new(&downloads_dir)?;
+
+                                // A fetched file never replaces an existing one
+                                let file_path = if fetch.is_some() {
+                                    crate::fs_utils::get_unique_download_path(
+                                        &downloads_dir,
+                                        &filename_clone,
+                                    )
+                                    .await
+                                } else {
+                                    downloads_dir.join(&filename_clone)
+                                };
+
+                                info!(
+                                    "Downloading file '{}' from {} ({}:{}) to {:?}",
+                                    filename_clone, device_name, host_clone, port, file_path
+                                );
+
+                                if let Some(recovery) = &recovery_manager {
+                                    let checkpoint = crate::TransferState::new(
+                                        transfer_id.clone(),
+                                        hook_device_id.clone(),
+                                        filename_clone.clone(),
+                                        file_path.clone(),
+                                        size as u64,
+                                    );
+                                    if let Err(e) = recovery.register_transfer(checkpoint).await {
                                         warn!(
-                                            "Failed to connect to TLS payload server {}:{}: {}",
-                                            host_clone, port, e
+                                            "Failed to checkpoint '{}': {}",
+                                            filename_clone, e
                                         );
                                     }
                                 }
-                            } else {
-                                warn!(
-                                    "Cannot download file '{}' from {}: TLS config not set. \
-                                     Call set_tls_config() on SharePlugin before receiving files.",
-                                    filename_clone, device_name
-                                );
+
+                                // Files with chunk checksums stay under their
+                                // staging name and are repaired there before they
+                                // get their final name
+                                let repair = match chunk_checksums {
+                                    Some(checksums) => {
+                                        let dest = sandbox.resolve(&file_path)?;
+                                        let staged = crate::fs_utils::staging_path(&dest, None);
+                                        Some((checksums, staged, dest))
+                                    }
+                                    None => None,
+                                };
+
+                                // Senders that don't stage start streaming as soon as
+                                // we connect, so ask the gate first; a declined offer
+                                // is never connected to
+                                if let (false, Some(gate)) = (confirm_required, &transfer_gate) {
+                                    if !gate
+                                        .decide(&hook_device_id, &filename_clone, size as u64)
+                                        .await
+                                    {
+                                        return Err(ProtocolError::PermissionDenied(format!(
+                                            "Declined '{}' from {}",
+                                            filename_clone, device_name
+                                        )));
+                                    }
+                                }
+
+                                // Wait for the device's other transfers; the slot
+                                // is held until this one ends
+                                let _slot = match &resource_manager {
+                                    Some(manager) => {
+                                        Some(manager.acquire_payload_slot(&hook_device_id).await)
+                                    }
+                                    None => None,
+                                };
+
+                                // Use TLS for payload transfer (required for Android compatibility)
+                                let config = tls_config.ok_or_else(|| {
+                                    ProtocolError::InvalidState(
+                                        "TLS config not set. Call set_tls_config() on SharePlugin \
+                                         before receiving files."
+                                            .to_string(),
+                                    )
+                                })?;
+                                let client =
+                                    TlsPayloadClient::new(&host_clone, port, &config).await?;
+                                let repair_config = Arc::clone(&config);
+
+                                // Pick up whatever an interrupted earlier download left behind
+                                let client = match (resumable, sandbox.resolve(&file_path)) {
+                                    (true, Ok(resolved)) => {
+                                        let offset = crate::payload::resume_offset(
+                                            &resolved,
+                                            None,
+                                            size as u64,
+                                        )
+                                        .await;
+                                        client.resume_from(offset)
+                                    }
+                                    _ => client,
+                                };
+                                // Files with chunk checksums are verified after
+                                // the download, when damage can still be repaired
+                                let client = match (checksum, &repair) {
+                                    (_, Some(_)) => client.keep_staged(),
+                                    (Some(sha256), None) => client.with_file_checksum(sha256),
+                                    (None, None) => client,
+                                };
+                                let client = match stream_layout {
+                                    Some(layout) => client.with_parallel_streams(layout),
+                                    None => client,
+                                };
+                                let client = match cipher {
+                                    Some(cipher) => client.with_encryption(cipher),
+                                    None => client,
+                                };
+                                let client = match traffic_counter {
+                                    Some(counter) => client.with_traffic_counter(counter),
+                                    None => client,
+                                };
+                                let transfer_start = Instant::now();
+                                let last_update = Arc::new(AtomicU64::new(0));
+                                let filename_for_callback = filename_clone.clone();
+                                let device_name_for_callback = device_name.clone();
+                                let checkpoint = recovery_manager
+                                    .clone()
+                                    .map(|recovery| (recovery, transfer_id.clone()));
+
+                                // Add progress callback with rate limiting (update every 500ms)
+                                let client_with_progress = client.with_size_limit(size_limit).with_sandbox(sandbox).with_progress(Box::new(move |transferred, total| {
+                                    let now = SystemTime::now()
+                                        .duration_since(UNIX_EPOCH)
+                                        .unwrap()
+                                        .as_millis() as u64;
+                                    let last = last_update.load(Ordering::Relaxed);
+
+                                    // Only log progress every 500ms to avoid spam
+                                    if now - last >= 500 {
+                                        last_update.store(now, Ordering::Relaxed);
+                                        let percent = (transferred as f64 / total as f64 * 100.0) as u8;
+                                        let elapsed = transfer_start.elapsed().as_secs_f64();
+                                        let speed = if elapsed > 0.0 {
+                                            transferred as f64 / elapsed
+                                        } else {
+                                            0.0
+                                        };
+
+                                        info!(
+                                            "Download progress '{}' from {}: {} / {} bytes ({}%, {:.2} KB/s)",
+                                            filename_for_callback,
+                                            device_name_for_callback,
+                                            transferred,
+                                            total,
+                                            percent,
+                                            speed / 1024.0
+                                        );
+
+                                        // Keep the checkpoint at the bytes received so far
+                                        if let Some((recovery, transfer_id)) = &checkpoint {
+                                            let recovery = Arc::clone(recovery);
+                                            let transfer_id = transfer_id.clone();
+                                            tokio::spawn(async move {
+                                                if let Err(e) = recovery
+                                                    .update_transfer_progress(&transfer_id, transferred)
+                                                    .await
+                                                {
+                                                    warn!(
+                                                        "Failed to checkpoint transfer {}: {}",
+                                                        transfer_id, e
+                                                    );
+                                                }
+                                            });
+                                        }
+
+                                        // DESIGN LIMITATION: Progress packets not sent to sender device
+                                        //
+                                        // The current architecture spawns a detached async task for file downloads,
+                                        // which doesn't have access to the device's packet sender channel. This is
+                                        // intentional to avoid blocking packet processing.
+                                        //
+                                        // To enable progress packet sending, we would need to:
+                                        // 1. Pass packet_sender channel into this spawned task
+                                        // 2. Send cconnect.share.request.progress packets periodically
+                                        //
+                                        // Progress is currently logged locally (see lines 742-750) and could be
+                                        // exposed via a callback mechanism if needed by the UI layer.
+                                        //
+                                        // Example implementation:
+                                        //   let progress_packet = Packet::new("cconnect.share.request.progress", json!({
+                                        //       "transferId": transfer_id,
+                                        //       "filename": filename,
+                                        //       "bytesTransferred": transferred,
+                                        //       "totalBytes": total,
+                                        //       "percentComplete": percent,
+                                        //       "speedBytesPerSecond": speed as u64,
+                                        //       "eta": eta
+                                        //   }));
+                                        //   packet_sender.send((device_id, progress_packet)).await;
+                                    }
+
+                                    true // Continue transfer
+                                }));
+
+                                // Staged transfers wait for the user's accept, which
+                                // the gate may withhold; it is asked about the
+                                // offered name, not the hidden one received into
+                                if let (true, Some(gate)) = (confirm_required, &transfer_gate) {
+                                    if !gate
+                                        .decide_staged(
+                                            &hook_device_id,
+                                            &filename_clone,
+                                            size as u64,
+                                        )
+                                        .await
+                                    {
+                                        client_with_progress.decline().await?;
+                                        return Err(ProtocolError::PermissionDenied(format!(
+                                            "Declined '{}' from {}",
+                                            filename_clone, device_name
+                                        )));
+                                    }
+                                    client_with_progress
+                                        .accept(&file_path, size as u64)
+                                        .await?;
+                                } else if confirm_required && fetch.is_some() {
+                                    // We asked for the file, so it needs no answer
+                                    client_with_progress
+                                        .accept(&file_path, size as u64)
+                                        .await?;
+                                } else if confirm_required {
+                                    // Nobody can answer without a gate
+                                    client_with_progress.decline().await?;
+                                    return Err(ProtocolError::PermissionDenied(format!(
+                                        "Declined staged '{}' from {}: nobody to ask",
+                                        filename_clone, device_name
+                                    )));
+                                } else {
+                                    client_with_progress
+                                        .receive_file(&file_path, size as u64)
+                                        .await?;
+                                }
+
+                                if let Some((checksums, received_at, dest)) = repair {
+                                    let (retransmits, packet_sender) = repair_source;
+                                    let mut source = retransmits.source(
+                                        &hook_device_id,
+                                        &transfer_id,
+                                        &host_clone,
+                                        repair_config,
+                                        payload_key,
+                                        packet_sender,
+                                    );
+                                    let repaired = async {
+                                        crate::transfer_integrity::ChunkRepairer::default()
+                                            .repair(&received_at, &checksums, &mut source)
+                                            .await?;
+                                        crate::fs_utils::commit_staged_file(&received_at, &dest)
+                                            .await
+                                    }
+                                    .await;
+                                    if let Err(e) = repaired {
+                                        crate::fs_utils::cleanup_partial_file(&received_at).await;
+                                        return Err(e);
+                                    }
+                                }
+                                Ok::<_, ProtocolError>(file_path)
+                            }
+                            .await;
+
+                            if let Some(recovery) = &recovery_manager {
+                                let recorded = match &result {
+                                    Ok(_) => recovery.complete_transfer(&transfer_id).await,
+                                    Err(ProtocolError::ChecksumMismatch(_, _)) => {
+                                        recovery.mark_corrupted(&transfer_id).await
+                                    }
+                                    Err(_) => Ok(()),
+                                };
+                                if let Err(e) = recorded {
+                                    warn!(
+                                        "Failed to update checkpoint of '{}': {}",
+                                        filename_clone, e
+                                    );
+                                }
+                            }
+
+                            match &result {
+                                Ok(file_path) => {
+                                    info!(
+                                        "Successfully downloaded file '{}' from {} via TLS",
+                                        filename_clone, device_name
+                                    );
+                                    let sidecar = if metadata_sidecars && !metadata.is_empty() {
+                                        write_sidecar(file_path, &metadata)
+                                            .await
+                                            .map_err(|e| {
+                                                warn!(
+                                                    "Failed to write metadata for '{}': {}",
+                                                    filename_clone, e
+                                                )
+                                            })
+                                            .ok()
+                                    } else {
+                                        None
+                                    };
+                                    run_completion_hooks(
+                                        &completion_hooks,
+                                        &hook_device_id,
+                                        file_path,
+                                    );
+                                    let _ = received.send(FileReceived {
+                                        device_id: hook_device_id.clone(),
+                                        path: file_path.clone(),
+                                        metadata,
+                                        sidecar,
+                                        user,
+                                    });
+                                }
+                                Err(e) => {
+                                    warn!(
+                                        "Failed to download file '{}' from {}: {}",
+                                        filename_clone, device_name, e
+                                    );
+                                }
+                            }
+                            if let Some(fetch) = fetch {
+                                fetch.complete(result);
                             }
                         });
                     } else {
@@ -914,6 +1903,8 @@ impl Plugin for SharePlugin {
             "cconnect.share.request.update".to_string(),
             "kdeconnect.share.request".to_string(),
             "kdeconnect.share.request.update".to_string(),
+            FETCH_ERROR.to_string(),
+            RETRANSMIT_DATA.to_string(),
         ]
     }
 
@@ -921,15 +1912,18 @@ impl Plugin for SharePlugin {
         vec![
             "cconnect.share.request".to_string(),
             "cconnect.share.request.update".to_string(),
+            FETCH_REQUEST.to_string(),
+            RETRANSMIT_PACKET_TYPE.to_string(),
         ]
     }
 
     async fn init(
         &mut self,
         device: &Device,
-        _packet_sender: tokio::sync::mpsc::Sender<(String, Packet)>,
+        packet_sender: tokio::sync::mpsc::Sender<(String, Packet)>,
     ) -> Result<()> {
         self.device_id = Some(device.id().to_string());
+        self.packet_sender = Some(packet_sender);
         info!("Share plugin initialized for device {}", device.name());
         Ok(())
     }
@@ -952,6 +1946,18 @@ impl Plugin for SharePlugin {
             || packet.is_type("kdeconnect.share.request.update")
         {
             self.handle_multifile_update(packet, device);
+        } else if packet.is_type(FETCH_ERROR) {
+            if !self.fetches.handle_error(packet) {
+                debug!(
+                    "Ignoring error for unknown file request from {}",
+                    device.name()
+                );
+            }
+        } else if packet.is_type(RETRANSMIT_DATA) && !self.retransmits.handle_data(packet) {
+            debug!(
+                "Ignoring retransmitted data nobody asked for from {}",
+                device.name()
+            );
         }
         Ok(())
     }
@@ -972,6 +1978,8 @@ impl PluginFactory for SharePluginFactory {
             "cconnect.share.request.update".to_string(),
             "kdeconnect.share.request".to_string(),
             "kdeconnect.share.request.update".to_string(),
+            FETCH_ERROR.to_string(),
+            RETRANSMIT_DATA.to_string(),
         ]
     }
 
@@ -979,6 +1987,8 @@ impl PluginFactory for SharePluginFactory {
         vec![
             "cconnect.share.request".to_string(),
             "cconnect.share.request.update".to_string(),
+            FETCH_REQUEST.to_string(),
+            RETRANSMIT_PACKET_TYPE.to_string(),
         ]
     }
 
@@ -1010,14 +2020,14 @@ mod tests {
         let plugin = SharePlugin::new();
 
         let incoming = plugin.incoming_capabilities();
-        assert_eq!(incoming.len(), 4);
+        assert_eq!(incoming.len(), 6);
         assert!(incoming.contains(&"cconnect.share.request".to_string()));
         assert!(incoming.contains(&"cconnect.share.request.update".to_string()));
         assert!(incoming.contains(&"kdeconnect.share.request".to_string()));
         assert!(incoming.contains(&"kdeconnect.share.request.update".to_string()));
 
         let outgoing = plugin.outgoing_capabilities();
-        assert_eq!(outgoing.len(), 2);
+        assert_eq!(outgoing.len(), 4);
         assert!(outgoing.contains(&"cconnect.share.request".to_string()));
         assert!(outgoing.contains(&"cconnect.share.request.update".to_string()));
     }
@@ -1050,6 +2060,9 @@ mod tests {
             creation_time: Some(1640000000000),
             last_modified: Some(1640000000000),
             open: false,
+            metadata: Default::default(),
+            user: None,
+            checksum: None,
         };
 
         let packet = plugin.create_file_packet(file_info, 1739);
@@ -1066,6 +2079,29 @@ mod tests {
             transfer_info.get("port").and_then(|v| v.as_i64()),
             Some(1739)
         );
+        assert!(packet.body.get(CHECKSUM_FIELD).is_none());
+    }
+
+    #[test]
+    fn test_create_file_packet_with_checksum() {
+        let plugin = SharePlugin::new();
+        let file_info = FileShareInfo {
+            filename: "test.txt".to_string(),
+            size: 1024,
+            creation_time: None,
+            last_modified: None,
+            open: false,
+            metadata: Default::default(),
+            user: None,
+            checksum: Some("ab".repeat(32)),
+        };
+
+        let packet = plugin.create_file_packet(file_info, 1739);
+
+        assert_eq!(
+            packet.body.get(CHECKSUM_FIELD).and_then(|v| v.as_str()),
+            Some("ab".repeat(32).as_str())
+        );
     }
 
     #[test]
@@ -1094,6 +2130,182 @@ mod tests {
         assert!(packet.payload_size.is_none());
     }
 
+    #[test]
+    fn test_share_url_normalization() {
+        assert_eq!(
+            ShareUrl::parse("www.example.com").unwrap().as_str(),
+            "https://www.example.com"
+        );
+        assert_eq!(
+            ShareUrl::parse(" example.org/page?q=1 ").unwrap().as_str(),
+            "https://example.org/page?q=1"
+        );
+        assert_eq!(
+            ShareUrl::parse("HTTP://example.com").unwrap().to_string(),
+            "http://example.com"
+        );
+    }
+
+    #[test]
+    fn test_share_url_accepts_valid_schemes() {
+        for (input, kind) in [
+            ("https://rust-lang.org", OpenTargetKind::Web),
+            ("http://192.168.1.10:8080/", OpenTargetKind::Web),
+            ("ftp://files.example.com/a.zip", OpenTargetKind::Web),
+            ("mailto:me@example.com", OpenTargetKind::Mailto),
+            ("tel:+15551234567", OpenTargetKind::Tel),
+            ("geo:51.5074,-0.1278", OpenTargetKind::Geo),
+        ] {
+            let url: ShareUrl = input.parse().unwrap();
+            assert_eq!(url.kind(), kind, "{}", input);
+            assert_eq!(url.into_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_share_url_rejects_plain_text() {
+        for input in [
+            "",
+            "hello world",
+            "remember to buy milk",
+            "localhost",
+            "javascript:alert(1)",
+            "/home/user/notes.txt",
+            "file:///etc/passwd",
+        ] {
+            let err = ShareUrl::parse(input).unwrap_err();
+            assert!(
+                matches!(err, ProtocolError::InvalidPacket(_)),
+                "{}: {:?}",
+                input,
+                err
+            );
+        }
+    }
+
+    fn parse_target(input: &str) -> (OpenTargetKind, String) {
+        let target = OpenTarget::parse(input).unwrap();
+        (target.kind, target.url)
+    }
+
+    #[test]
+    fn test_open_target_classification() {
+        assert_eq!(
+            parse_target("tel:+1 555 123-4567"),
+            (OpenTargetKind::Tel, "tel:+1555123-4567".to_string())
+        );
+        assert_eq!(
+            parse_target("sms:+15551234567?body=hi"),
+            (OpenTargetKind::Sms, "sms:+15551234567?body=hi".to_string())
+        );
+        assert_eq!(
+            parse_target("mailto:me@example.com?subject=Hello"),
+            (
+                OpenTargetKind::Mailto,
+                "mailto:me@example.com?subject=Hello".to_string()
+            )
+        );
+        assert_eq!(
+            parse_target("HTTPS://example.com/path?q=1"),
+            (
+                OpenTargetKind::Web,
+                "https://example.com/path?q=1".to_string()
+            )
+        );
+        assert_eq!(
+            parse_target("http://192.168.1.10:8080/"),
+            (OpenTargetKind::Web, "http://192.168.1.10:8080/".to_string())
+        );
+        assert_eq!(
+            parse_target("geo:51.5074,-0.1278?q=London"),
+            (
+                OpenTargetKind::Geo,
+                "geo:51.5074,-0.1278?q=London".to_string()
+            )
+        );
+        assert_eq!(
+            parse_target("file:///sdcard/Download/a.pdf"),
+            (
+                OpenTargetKind::File,
+                "file:///sdcard/Download/a.pdf".to_string()
+            )
+        );
+        assert_eq!(
+            parse_target("/sdcard/My File.pdf"),
+            (
+                OpenTargetKind::File,
+                "file:///sdcard/My%20File.pdf".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_open_target_bare_domain_normalized() {
+        assert_eq!(
+            parse_target("  example.com "),
+            (OpenTargetKind::Web, "https://example.com".to_string())
+        );
+        assert_eq!(
+            parse_target("docs.rs/tokio?search=x"),
+            (
+                OpenTargetKind::Web,
+                "https://docs.rs/tokio?search=x".to_string()
+            )
+        );
+        assert_eq!(
+            parse_target("example.com:8080/status"),
+            (
+                OpenTargetKind::Web,
+                "https://example.com:8080/status".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_open_target_rejects_garbage() {
+        for input in [
+            "",
+            "   ",
+            "hello world",
+            "not a url",
+            "localhost",
+            "javascript:alert(1)",
+            "data:text/html,<script>alert(1)</script>",
+            "about:blank",
+            "https://",
+            "https:example.com",
+            "http://exa mple.com",
+            "tel:call-me",
+            "mailto:nobody",
+            "mailto:a@b@example.com",
+            "geo:200,10",
+            "geo:north",
+            "file://relative/path",
+            "example.com:99999",
+            "line\nbreak.com",
+        ] {
+            assert!(
+                matches!(
+                    OpenTarget::parse(input),
+                    Err(ProtocolError::InvalidPacket(_))
+                ),
+                "expected rejection for {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_create_open_packet() {
+        let plugin = SharePlugin::new();
+        let target: OpenTarget = "geo:0,0?q=Oslo".parse().unwrap();
+        let packet = plugin.create_open_packet(&target);
+
+        assert_eq!(packet.packet_type, "cconnect.share.request");
+        assert_eq!(packet.body["url"], "geo:0,0?q=Oslo");
+        assert_eq!(packet.body["urlType"], "geo");
+    }
+
     #[test]
     fn test_create_multifile_update_packet() {
         let plugin = SharePlugin::new();
@@ -1337,4 +2549,556 @@ mod tests {
         // Should not create a share record
         assert_eq!(plugin.share_count(), 0);
     }
+
+    #[test]
+    fn test_received_text_goes_to_clipboard_when_enabled() {
+        let options = ShareReceiveOptions::default();
+        let action = options
+            .plan(&ShareContent::Text("copied text".to_string()))
+            .unwrap();
+
+        assert_eq!(action.clipboard.as_deref(), Some("copied text"));
+        assert_eq!(action.notification_body, "copied text");
+        assert!(action.open_url.is_none());
+    }
+
+    #[test]
+    fn test_received_text_skips_clipboard_when_disabled() {
+        let options = ShareReceiveOptions {
+            text_to_clipboard: false,
+            ..Default::default()
+        };
+        let action = options
+            .plan(&ShareContent::Text("notify only".to_string()))
+            .unwrap();
+
+        assert!(action.clipboard.is_none());
+        assert_eq!(action.notification_body, "notify only");
+    }
+
+    #[test]
+    fn test_long_text_truncated_for_notification_only() {
+        let options = ShareReceiveOptions {
+            text_to_clipboard: true,
+            preview_chars: 10,
+        };
+        let text = "ä".repeat(50);
+        let action = options.plan(&ShareContent::Text(text.clone())).unwrap();
+
+        assert_eq!(action.clipboard, Some(text));
+        assert_eq!(action.notification_body, format!("{}…", "ä".repeat(10)));
+    }
+
+    #[test]
+    fn test_received_url_offers_open_action() {
+        let options = ShareReceiveOptions::default();
+        let action = options
+            .plan(&ShareContent::Url("https://example.com".to_string()))
+            .unwrap();
+
+        assert_eq!(action.open_url.as_deref(), Some("https://example.com"));
+        assert!(action.clipboard.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_listed_file() {
+        use crate::plugins::share_fetch::FETCH_REQUEST_ID_FIELD;
+
+        let certificate = crate::CertificateInfo::generate("share-fetch-test").unwrap();
+        let tls_config = Arc::new(crate::TlsConfig::new(&certificate).unwrap());
+        let remote = tempfile::TempDir::new().unwrap();
+        let photo = remote.path().join("IMG_0001.jpg");
+        std::fs::write(&photo, vec![7u8; 4096]).unwrap();
+        let downloads = tempfile::TempDir::new().unwrap();
+
+        let (tx, mut requests) = tokio::sync::mpsc::channel(10);
+        let counter = crate::TrafficCounter::new();
+        let mut plugin = SharePlugin::new();
+        plugin.set_tls_config(tls_config.clone());
+        plugin.set_traffic_counter(counter.clone());
+        let mut device = create_test_device();
+        device.host = Some("127.0.0.1".to_string());
+        plugin.init(&device, tx).await.unwrap();
+
+        let fetch = plugin
+            .request_file("/DCIM/Camera/IMG_0001.jpg", downloads.path())
+            .await
+            .unwrap();
+        let (_, request) = requests.recv().await.unwrap();
+        assert_eq!(request.packet_type, FETCH_REQUEST);
+        assert_eq!(request.body["path"], "/DCIM/Camera/IMG_0001.jpg");
+
+        // The mock device answers with a file share tagged with the request ID
+        let server = crate::TlsPayloadServer::new(tls_config).await.unwrap();
+        let file_info = crate::payload::FileTransferInfo::from_path(&photo)
+            .await
+            .unwrap();
+        let mut answer = plugin.create_file_packet(file_info.into(), server.port());
+        answer.body[FETCH_REQUEST_ID_FIELD] = request.body["requestId"].clone();
+        tokio::spawn(server.send_file(photo));
+        plugin.handle_packet(&answer, &mut device).await.unwrap();
+
+        let saved = fetch
+            .wait(std::time::Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(saved, downloads.path().join("IMG_0001.jpg"));
+        assert_eq!(std::fs::read(&saved).unwrap(), vec![7u8; 4096]);
+        assert_eq!(counter.snapshot().payload_received, 4096);
+    }
+
+    #[tokio::test]
+    async fn test_received_file_waits_for_transfer_slot() {
+        use crate::plugins::share_fetch::FETCH_REQUEST_ID_FIELD;
+
+        let certificate = crate::CertificateInfo::generate("share-slot-test").unwrap();
+        let tls_config = Arc::new(crate::TlsConfig::new(&certificate).unwrap());
+        let remote = tempfile::TempDir::new().unwrap();
+        let photo = remote.path().join("IMG_0003.jpg");
+        std::fs::write(&photo, vec![5u8; 4096]).unwrap();
+        let downloads = tempfile::TempDir::new().unwrap();
+
+        let manager = Arc::new(crate::ResourceManager::new(crate::ResourceConfig::default()));
+        let (tx, mut requests) = tokio::sync::mpsc::channel(10);
+        let mut plugin = SharePlugin::new();
+        plugin.set_tls_config(tls_config.clone());
+        plugin.set_resource_manager(manager.clone());
+        let mut device = create_test_device();
+        device.host = Some("127.0.0.1".to_string());
+        plugin.init(&device, tx).await.unwrap();
+
+        // Another transfer with the device is running
+        let slot = manager.acquire_payload_slot(device.id()).await;
+
+        let fetch = plugin
+            .request_file("/DCIM/Camera/IMG_0003.jpg", downloads.path())
+            .await
+            .unwrap();
+        let (_, request) = requests.recv().await.unwrap();
+        let server = crate::TlsPayloadServer::new(tls_config).await.unwrap();
+        let file_info = crate::payload::FileTransferInfo::from_path(&photo)
+            .await
+            .unwrap();
+        let mut answer = plugin.create_file_packet(file_info.into(), server.port());
+        answer.body[FETCH_REQUEST_ID_FIELD] = request.body["requestId"].clone();
+        tokio::spawn(server.send_file(photo));
+        plugin.handle_packet(&answer, &mut device).await.unwrap();
+
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        assert!(!downloads.path().join("IMG_0003.jpg").exists());
+
+        drop(slot);
+        let saved = fetch
+            .wait(std::time::Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&saved).unwrap(), vec![5u8; 4096]);
+    }
+
+    #[tokio::test]
+    async fn test_received_file_checkpointed_until_complete() {
+        use crate::plugins::share_fetch::FETCH_REQUEST_ID_FIELD;
+
+        let certificate = crate::CertificateInfo::generate("share-checkpoint-test").unwrap();
+        let tls_config = Arc::new(crate::TlsConfig::new(&certificate).unwrap());
+        let remote = tempfile::TempDir::new().unwrap();
+        let photo = remote.path().join("IMG_0004.jpg");
+        std::fs::write(&photo, vec![9u8; 4096]).unwrap();
+        let downloads = tempfile::TempDir::new().unwrap();
+        let state_dir = tempfile::TempDir::new().unwrap();
+
+        let manager = Arc::new(crate::ResourceManager::new(crate::ResourceConfig::default()));
+        let recovery = Arc::new(crate::RecoveryManager::new(state_dir.path()));
+        let (tx, mut requests) = tokio::sync::mpsc::channel(10);
+        let mut plugin = SharePlugin::new();
+        plugin.set_tls_config(tls_config.clone());
+        plugin.set_resource_manager(manager.clone());
+        plugin.set_recovery_manager(recovery.clone());
+        let mut device = create_test_device();
+        device.host = Some("127.0.0.1".to_string());
+        plugin.init(&device, tx).await.unwrap();
+
+        // Hold the device's transfer slot so the download waits
+        let slot = manager.acquire_payload_slot(device.id()).await;
+
+        let fetch = plugin
+            .request_file("/DCIM/Camera/IMG_0004.jpg", downloads.path())
+            .await
+            .unwrap();
+        let (_, request) = requests.recv().await.unwrap();
+        let server = crate::TlsPayloadServer::new(tls_config).await.unwrap();
+        let file_info = crate::payload::FileTransferInfo::from_path(&photo)
+            .await
+            .unwrap();
+        let mut answer = plugin.create_file_packet(file_info.into(), server.port());
+        answer.body[FETCH_REQUEST_ID_FIELD] = request.body["requestId"].clone();
+        tokio::spawn(server.send_file(photo));
+        plugin.handle_packet(&answer, &mut device).await.unwrap();
+
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        let checkpoints = recovery.get_device_transfers(device.id()).await;
+        assert_eq!(checkpoints.len(), 1);
+        assert_eq!(checkpoints[0].filename, "IMG_0004.jpg");
+        assert_eq!(checkpoints[0].total_size, 4096);
+
+        drop(slot);
+        fetch
+            .wait(std::time::Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert!(recovery.get_device_transfers(device.id()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_metadata_round_trips_with_file() {
+        use crate::plugins::share_fetch::FETCH_REQUEST_ID_FIELD;
+        use crate::plugins::share_metadata::{sidecar_path, FileMetadata};
+
+        let certificate = crate::CertificateInfo::generate("share-metadata-test").unwrap();
+        let tls_config = Arc::new(crate::TlsConfig::new(&certificate).unwrap());
+        let remote = tempfile::TempDir::new().unwrap();
+        let photo = remote.path().join("IMG_0002.jpg");
+        std::fs::write(&photo, vec![3u8; 2048]).unwrap();
+        let downloads = tempfile::TempDir::new().unwrap();
+
+        let (tx, mut requests) = tokio::sync::mpsc::channel(10);
+        let mut plugin = SharePlugin::new();
+        plugin.set_tls_config(tls_config.clone());
+        plugin.set_metadata_sidecars(true);
+        let mut received = plugin.subscribe_received();
+        let mut device = create_test_device();
+        device.host = Some("127.0.0.1".to_string());
+        plugin.init(&device, tx).await.unwrap();
+
+        let fetch = plugin
+            .request_file("/DCIM/Camera/IMG_0002.jpg", downloads.path())
+            .await
+            .unwrap();
+        let (_, request) = requests.recv().await.unwrap();
+
+        let metadata = FileMetadata::from([
+            ("caption".to_string(), "Sunset at the pier".to_string()),
+            ("origin".to_string(), "org.gallery".to_string()),
+        ]);
+        let server = crate::TlsPayloadServer::new(tls_config).await.unwrap();
+        let file_info = crate::payload::FileTransferInfo::from_path(&photo)
+            .await
+            .unwrap()
+            .with_metadata(metadata.clone())
+            .unwrap();
+        let mut answer = plugin.create_file_packet(file_info.into(), server.port());
+        answer.body[FETCH_REQUEST_ID_FIELD] = request.body["requestId"].clone();
+        tokio::spawn(server.send_file(photo));
+        plugin.handle_packet(&answer, &mut device).await.unwrap();
+
+        let saved = fetch
+            .wait(std::time::Duration::from_secs(10))
+            .await
+            .unwrap();
+        let event = received.recv().await.unwrap();
+        assert_eq!(event.path, saved);
+        assert_eq!(event.metadata, metadata);
+        assert_eq!(event.sidecar, Some(sidecar_path(&saved)));
+
+        let sidecar: FileMetadata =
+            serde_json::from_slice(&std::fs::read(sidecar_path(&saved)).unwrap()).unwrap();
+        assert_eq!(sidecar, metadata);
+    }
+
+    #[tokio::test]
+    async fn test_damaged_chunk_fetched_again() {
+        use crate::plugins::share_fetch::FETCH_REQUEST_ID_FIELD;
+        use crate::plugins::share_retransmit::create_retransmit_data;
+        use crate::transfer_integrity::{ChunkChecksums, ChunkRange, CHUNK_CHECKSUMS_FIELD};
+
+        let certificate = crate::CertificateInfo::generate("share-repair-test").unwrap();
+        let tls_config = Arc::new(crate::TlsConfig::new(&certificate).unwrap());
+        let data: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+        let remote = tempfile::TempDir::new().unwrap();
+        let damaged = remote.path().join("notes.bin");
+        let mut corrupted = data.clone();
+        corrupted[1500] ^= 0xFF;
+        std::fs::write(&damaged, &corrupted).unwrap();
+        let downloads = tempfile::TempDir::new().unwrap();
+
+        let (tx, mut requests) = tokio::sync::mpsc::channel(10);
+        let mut plugin = SharePlugin::new();
+        plugin.set_tls_config(tls_config.clone());
+        let mut device = create_test_device();
+        device.host = Some("127.0.0.1".to_string());
+        plugin.init(&device, tx).await.unwrap();
+
+        let fetch = plugin
+            .request_file("/sdcard/notes.bin", downloads.path())
+            .await
+            .unwrap();
+        let (_, request) = requests.recv().await.unwrap();
+
+        // The file arrives with one bad byte in chunk 1
+        let server = crate::TlsPayloadServer::new(tls_config.clone())
+            .await
+            .unwrap();
+        let file_info = crate::payload::FileTransferInfo::from_path(&damaged)
+            .await
+            .unwrap();
+        let mut answer = plugin.create_file_packet(file_info.into(), server.port());
+        answer.body[FETCH_REQUEST_ID_FIELD] = request.body["requestId"].clone();
+        answer.body[CHUNK_CHECKSUMS_FIELD] = json!(ChunkChecksums::from_bytes(&data, 1024));
+        tokio::spawn(server.send_file(damaged));
+        plugin.handle_packet(&answer, &mut device).await.unwrap();
+
+        // Only that chunk is asked for again
+        let (_, retransmit) = requests.recv().await.unwrap();
+        assert_eq!(retransmit.packet_type, RETRANSMIT_PACKET_TYPE);
+        assert_eq!(retransmit.body["transferId"], answer.id.to_string());
+        let range: ChunkRange =
+            serde_json::from_value(retransmit.body["ranges"][0].clone()).unwrap();
+        assert_eq!((range.offset, range.length), (1024, 1024));
+
+        let server = crate::TlsPayloadServer::new(tls_config).await.unwrap();
+        let reply = create_retransmit_data(&answer.id.to_string(), &range, server.port());
+        let chunk = data[1024..2048].to_vec();
+        tokio::spawn(async move { server.send_bytes(&chunk).await });
+        plugin.handle_packet(&reply, &mut device).await.unwrap();
+
+        let saved = fetch
+            .wait(std::time::Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&saved).unwrap(), data);
+        assert!(!crate::fs_utils::staging_path(&saved, None).exists());
+    }
+
+    #[tokio::test]
+    async fn test_tagged_file_routes_to_user_directory() {
+        let certificate = crate::CertificateInfo::generate("share-user-test").unwrap();
+        let tls_config = Arc::new(crate::TlsConfig::new(&certificate).unwrap());
+        let remote = tempfile::TempDir::new().unwrap();
+        let shared = tempfile::TempDir::new().unwrap();
+        let alice = tempfile::TempDir::new().unwrap();
+
+        let (tx, _requests) = tokio::sync::mpsc::channel(10);
+        let mut plugin = SharePlugin::new();
+        plugin.set_tls_config(tls_config.clone());
+        plugin.set_user_directories(
+            UserDirectories::new(Some(shared.path().to_path_buf()))
+                .with_user("alice", alice.path()),
+        );
+        let mut received = plugin.subscribe_received();
+        let mut device = create_test_device();
+        device.host = Some("127.0.0.1".to_string());
+        plugin.init(&device, tx).await.unwrap();
+
+        for (name, user) in [("report.pdf", Some("alice")), ("notes.txt", None)] {
+            let source = remote.path().join(name);
+            std::fs::write(&source, name).unwrap();
+            let mut file_info = crate::payload::FileTransferInfo::from_path(&source)
+                .await
+                .unwrap();
+            if let Some(user) = user {
+                file_info = file_info.with_user(user);
+            }
+            let server = crate::TlsPayloadServer::new(tls_config.clone())
+                .await
+                .unwrap();
+            let packet = plugin.create_file_packet(file_info.into(), server.port());
+            assert_eq!(packet.body.get(USER_FIELD).and_then(|v| v.as_str()), user);
+            tokio::spawn(server.send_file(source));
+            plugin.handle_packet(&packet, &mut device).await.unwrap();
+        }
+
+        let first = received.recv().await.unwrap();
+        let second = received.recv().await.unwrap();
+        let (tagged, untagged) = if first.user.is_some() {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        assert_eq!(tagged.user.as_deref(), Some("alice"));
+        assert_eq!(tagged.path, alice.path().join("report.pdf"));
+        assert_eq!(untagged.user, None);
+        assert_eq!(untagged.path, shared.path().join("notes.txt"));
+        assert_eq!(std::fs::read(&tagged.path).unwrap(), b"report.pdf");
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_file_opened_with_pairing_key() {
+        use crate::payload_crypto::{PayloadKeyExchange, PAYLOAD_ENCRYPTION_FIELD};
+
+        let certificate = crate::CertificateInfo::generate("share-crypto-test").unwrap();
+        let tls_config = Arc::new(crate::TlsConfig::new(&certificate).unwrap());
+        let remote = tempfile::TempDir::new().unwrap();
+        let downloads = tempfile::TempDir::new().unwrap();
+        let source = remote.path().join("payslip.pdf");
+        std::fs::write(&source, vec![9u8; 4096]).unwrap();
+
+        let desktop = PayloadKeyExchange::new().unwrap();
+        let phone = PayloadKeyExchange::new().unwrap();
+        let phone_public = phone.public_key().to_string();
+        let receiver_key = phone
+            .complete(desktop.public_key(), "BB:BB", "AA:AA")
+            .unwrap();
+        let sender_key = desktop.complete(&phone_public, "AA:AA", "BB:BB").unwrap();
+
+        let (tx, _requests) = tokio::sync::mpsc::channel(10);
+        let mut plugin = SharePlugin::new();
+        plugin.set_tls_config(tls_config.clone());
+        plugin.set_payload_key(Some(receiver_key));
+        plugin.set_user_directories(UserDirectories::new(Some(downloads.path().to_path_buf())));
+        let mut received = plugin.subscribe_received();
+        let mut device = create_test_device();
+        device.host = Some("127.0.0.1".to_string());
+        plugin.init(&device, tx).await.unwrap();
+
+        let cipher = crate::PayloadCipher::generate(&sender_key).unwrap();
+        let description = cipher.describe();
+        let server = crate::TlsPayloadServer::new(tls_config)
+            .await
+            .unwrap()
+            .with_encryption(cipher);
+        let file_info = crate::payload::FileTransferInfo::from_path(&source)
+            .await
+            .unwrap();
+        let mut packet = plugin.create_file_packet(file_info.into(), server.port());
+        packet.body[PAYLOAD_ENCRYPTION_FIELD] = description;
+        tokio::spawn(server.send_file(source));
+        plugin.handle_packet(&packet, &mut device).await.unwrap();
+
+        let event = received.recv().await.unwrap();
+        assert_eq!(std::fs::read(&event.path).unwrap(), vec![9u8; 4096]);
+    }
+
+    #[tokio::test]
+    async fn test_prompt_holds_unstaged_share() {
+        use std::time::Duration;
+
+        let certificate = crate::CertificateInfo::generate("share-prompt-test").unwrap();
+        let tls_config = Arc::new(crate::TlsConfig::new(&certificate).unwrap());
+        let remote = tempfile::TempDir::new().unwrap();
+        let downloads = tempfile::TempDir::new().unwrap();
+        let source = remote.path().join("holiday.jpg");
+        std::fs::write(&source, vec![5u8; 1024]).unwrap();
+
+        let (gate, mut prompts) = crate::TransferGate::new(
+            crate::ReceiveTrustLevels::new(crate::ReceiveTrust::Prompt),
+            Duration::from_secs(10),
+        );
+        let (tx, _requests) = tokio::sync::mpsc::channel(10);
+        let mut plugin = SharePlugin::new();
+        plugin.set_tls_config(tls_config.clone());
+        plugin.set_transfer_gate(gate);
+        plugin.set_user_directories(UserDirectories::new(Some(downloads.path().to_path_buf())));
+        let mut received = plugin.subscribe_received();
+        let mut device = create_test_device();
+        device.host = Some("127.0.0.1".to_string());
+        plugin.init(&device, tx).await.unwrap();
+
+        // A plain share.request, as Android sends it: no staging, and the
+        // sender serves the file right away
+        let server = crate::TlsPayloadServer::new(tls_config).await.unwrap();
+        let file_info = crate::payload::FileTransferInfo::from_path(&source)
+            .await
+            .unwrap();
+        let packet = plugin.create_file_packet(file_info.into(), server.port());
+        assert!(packet
+            .body
+            .get(crate::payload::CONFIRM_REQUIRED_FIELD)
+            .is_none());
+        tokio::spawn(server.send_file(source));
+        plugin.handle_packet(&packet, &mut device).await.unwrap();
+
+        let prompt = tokio::time::timeout(Duration::from_secs(10), prompts.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(prompt.filename, "holiday.jpg");
+        assert_eq!(prompt.size, 1024);
+
+        // Nothing is downloaded until the user answers
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!downloads.path().join("holiday.jpg").exists());
+        assert!(received.try_recv().is_err());
+
+        prompt.accept();
+        let event = received.recv().await.unwrap();
+        assert_eq!(event.path, downloads.path().join("holiday.jpg"));
+        assert_eq!(std::fs::read(&event.path).unwrap(), vec![5u8; 1024]);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_metadata_refuses_file() {
+        use crate::plugins::share_fetch::FETCH_REQUEST_ID_FIELD;
+        use crate::plugins::share_metadata::{FileMetadata, MAX_METADATA_BYTES};
+
+        let remote = tempfile::TempDir::new().unwrap();
+        let photo = remote.path().join("IMG_0003.jpg");
+        std::fs::write(&photo, vec![5u8; 64]).unwrap();
+        let downloads = tempfile::TempDir::new().unwrap();
+        let oversized =
+            FileMetadata::from([("caption".to_string(), "x".repeat(MAX_METADATA_BYTES + 1))]);
+
+        // The sender refuses to attach it
+        let file_info = crate::payload::FileTransferInfo::from_path(&photo)
+            .await
+            .unwrap();
+        assert!(matches!(
+            file_info.clone().with_metadata(oversized.clone()),
+            Err(ProtocolError::ResourceExhausted(_))
+        ));
+
+        // A receiver refuses it from a sender that attached it anyway
+        let (tx, mut requests) = tokio::sync::mpsc::channel(10);
+        let mut plugin = SharePlugin::new();
+        let mut device = create_test_device();
+        device.host = Some("127.0.0.1".to_string());
+        plugin.init(&device, tx).await.unwrap();
+
+        let fetch = plugin
+            .request_file("/DCIM/Camera/IMG_0003.jpg", downloads.path())
+            .await
+            .unwrap();
+        let (_, request) = requests.recv().await.unwrap();
+        let mut share_info: FileShareInfo = file_info.into();
+        share_info.metadata = oversized;
+        let mut answer = plugin.create_file_packet(share_info, 1739);
+        answer.body[FETCH_REQUEST_ID_FIELD] = request.body["requestId"].clone();
+        plugin.handle_packet(&answer, &mut device).await.unwrap();
+
+        let error = fetch
+            .wait(std::time::Duration::from_secs(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ProtocolError::ResourceExhausted(_)));
+        assert!(error.to_string().contains("metadata"));
+        assert_eq!(std::fs::read_dir(downloads.path()).unwrap().count(), 0);
+        assert_eq!(plugin.share_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_missing_file_errors() {
+        use crate::plugins::share_fetch::{create_fetch_error, FetchErrorReason};
+
+        let downloads = tempfile::TempDir::new().unwrap();
+        let (tx, mut requests) = tokio::sync::mpsc::channel(10);
+        let mut plugin = SharePlugin::new();
+        let mut device = create_test_device();
+        plugin.init(&device, tx).await.unwrap();
+
+        let fetch = plugin
+            .request_file("/DCIM/Camera/missing.jpg", downloads.path())
+            .await
+            .unwrap();
+        let (_, request) = requests.recv().await.unwrap();
+        let request_id = request.body["requestId"].as_str().unwrap();
+
+        let refusal = create_fetch_error(request_id, FetchErrorReason::NotFound, "No such file");
+        plugin.handle_packet(&refusal, &mut device).await.unwrap();
+
+        let error = fetch
+            .wait(std::time::Duration::from_secs(1))
+            .await
+            .unwrap_err();
+        assert!(crate::plugins::sftp_browser::is_not_found(&error));
+        assert_eq!(std::fs::read_dir(downloads.path()).unwrap().count(), 0);
+    }
 }
@@ -0,0 +1,699 @@
+//! Multi-File Share Batches
+//!
+//! Sends several files to a device as one batch. A file that cannot be sent
+//! (missing, unreadable, failed transfer) is recorded and the batch moves on
+//! to the next one. Once every file has been tried, [`BatchEvent::Completed`]
+//! carries the result of each file so the UI can report "7 of 8 sent,
+//! 1 failed" instead of a single pass/fail.
+//!
+//! How a file reaches the device is up to the [`BatchFileSender`]; the daemon
+//! uses a TLS payload server per file.
+//!
+//! ## Progress
+//!
+//! Each file is bracketed by [`BatchEvent::FileStarted`] and
+//! [`BatchEvent::FileFinished`], and [`BatchEvent::Progress`] reports bytes
+//! sent across the whole batch, so the UI can show "transferring
+//! photo_12.jpg (4/20)" next to an overall bar. [`send_directory`] sends a
+//! directory's files this way instead of packing them into one archive.
+//!
+//! Walking a large directory takes a while before the first byte is sent.
+//! Meanwhile [`BatchEvent::Preparing`] reports how many files have been
+//! found so far, so the UI can show an indeterminate "preparing" state
+//! instead of looking hung. Setting the cancel flag passed to
+//! [`send_directory_cancellable`] during that phase stops the walk and
+//! nothing is sent.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let report = send_batch("batch-1", &paths, &sender, Some(&event_tx)).await;
+//! println!("{}", report.summary()); // "2 of 3 sent, 1 failed"
+//! ```
+
+use super::share::MultiFileInfo;
+use crate::payload::FileTransferInfo;
+use crate::{ProtocolError, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// Delivers the files of a batch
+#[async_trait]
+pub trait BatchFileSender: Send + Sync {
+    /// Announce the files about to be sent
+    ///
+    /// Only the files that could be read are counted. A failed announcement
+    /// is logged and does not stop the batch.
+    async fn announce(&self, _info: MultiFileInfo) -> Result<()> {
+        Ok(())
+    }
+
+    /// Send one file, returning the number of bytes sent
+    async fn send_file(&self, file: &FileTransferInfo) -> Result<u64>;
+
+    /// Send one file, reporting the bytes of it sent so far through `progress`
+    ///
+    /// The default uses [`send_file`](Self::send_file) and reports once the
+    /// file is done; senders that see individual chunks should override it.
+    async fn send_file_with_progress(
+        &self,
+        file: &FileTransferInfo,
+        progress: FileProgress,
+    ) -> Result<u64> {
+        let bytes = self.send_file(file).await?;
+        progress(bytes);
+        Ok(bytes)
+    }
+}
+
+/// Receives the bytes of the current file sent so far
+pub type FileProgress = Arc<dyn Fn(u64) + Send + Sync>;
+
+/// Files found between two [`BatchEvent::Preparing`] reports
+pub const PREPARE_REPORT_INTERVAL: usize = 100;
+
+/// What happened to one file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FileOutcome {
+    /// The file was sent
+    Sent {
+        /// Bytes transferred
+        bytes: u64,
+    },
+    /// The file could not be sent
+    Failed {
+        /// Why it failed
+        error: String,
+    },
+}
+
+/// Result for one file of a batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileResult {
+    /// Path of the file
+    pub path: PathBuf,
+    /// Outcome
+    #[serde(flatten)]
+    pub outcome: FileOutcome,
+}
+
+impl FileResult {
+    /// Whether the file was sent
+    pub fn is_sent(&self) -> bool {
+        matches!(self.outcome, FileOutcome::Sent { .. })
+    }
+}
+
+/// Per-file breakdown of a finished batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BatchReport {
+    /// ID of the batch
+    pub batch_id: String,
+    /// One entry per requested file, in request order
+    pub results: Vec<FileResult>,
+}
+
+impl BatchReport {
+    /// Number of files in the batch
+    pub fn total(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Number of files sent
+    pub fn sent(&self) -> usize {
+        self.results.iter().filter(|r| r.is_sent()).count()
+    }
+
+    /// Number of files that failed
+    pub fn failed(&self) -> usize {
+        self.total() - self.sent()
+    }
+
+    /// Files that failed
+    pub fn failures(&self) -> impl Iterator<Item = &FileResult> {
+        self.results.iter().filter(|r| !r.is_sent())
+    }
+
+    /// Short human-readable summary, e.g. "7 of 8 sent, 1 failed"
+    pub fn summary(&self) -> String {
+        match self.failed() {
+            0 => format!("{} of {} sent", self.sent(), self.total()),
+            failed => format!(
+                "{} of {} sent, {} failed",
+                self.sent(),
+                self.total(),
+                failed
+            ),
+        }
+    }
+}
+
+/// Progress of a batch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchEvent {
+    /// A directory is being searched for files; nothing is sent yet
+    Preparing {
+        /// ID of the batch
+        batch_id: String,
+        /// Files found so far
+        files_found: usize,
+    },
+    /// A file is about to be sent
+    FileStarted {
+        /// ID of the batch
+        batch_id: String,
+        /// Position of the file in the batch
+        index: usize,
+        /// Number of files in the batch
+        total_files: usize,
+        /// Path of the file
+        path: PathBuf,
+    },
+    /// Bytes sent so far across the batch
+    Progress {
+        /// ID of the batch
+        batch_id: String,
+        /// Bytes of finished files plus those of the current file sent so far
+        bytes_sent: u64,
+        /// Combined size of every readable file
+        total_bytes: u64,
+    },
+    /// A file was sent or failed
+    FileFinished {
+        /// ID of the batch
+        batch_id: String,
+        /// Position of the file in the batch
+        index: usize,
+        /// Its result
+        result: FileResult,
+    },
+    /// Every file has been tried
+    Completed(BatchReport),
+}
+
+/// Send `paths` one after another, continuing past failures
+///
+/// Events are sent on `events` if given; the final report is also returned.
+pub async fn send_batch(
+    batch_id: &str,
+    paths: &[PathBuf],
+    sender: &dyn BatchFileSender,
+    events: Option<&mpsc::UnboundedSender<BatchEvent>>,
+) -> BatchReport {
+    let emit = |event: BatchEvent| {
+        if let Some(events) = events {
+            let _ = events.send(event);
+        }
+    };
+
+    // Read metadata up front so the announcement only counts sendable files
+    let mut files = Vec::with_capacity(paths.len());
+    for path in paths {
+        files.push(FileTransferInfo::from_path(path).await);
+    }
+    let readable: Vec<&FileTransferInfo> = files.iter().filter_map(|f| f.as_ref().ok()).collect();
+    let total_bytes: u64 = readable.iter().map(|f| f.size).sum();
+    if !readable.is_empty() {
+        let info = MultiFileInfo {
+            number_of_files: readable.len() as i32,
+            total_payload_size: readable.iter().map(|f| f.size as i64).sum(),
+        };
+        if let Err(e) = sender.announce(info).await {
+            warn!("Failed to announce batch {}: {}", batch_id, e);
+        }
+    }
+
+    let mut results = Vec::with_capacity(paths.len());
+    let mut bytes_done = 0;
+    for (index, (path, file)) in paths.iter().zip(&files).enumerate() {
+        emit(BatchEvent::FileStarted {
+            batch_id: batch_id.to_string(),
+            index,
+            total_files: paths.len(),
+            path: path.clone(),
+        });
+        let outcome = match file {
+            Ok(file) => match sender
+                .send_file_with_progress(
+                    file,
+                    batch_progress(batch_id, events, bytes_done, total_bytes),
+                )
+                .await
+            {
+                Ok(bytes) => {
+                    bytes_done += bytes;
+                    FileOutcome::Sent { bytes }
+                }
+                Err(e) => FileOutcome::Failed {
+                    error: e.to_string(),
+                },
+            },
+            Err(e) => FileOutcome::Failed {
+                error: e.to_string(),
+            },
+        };
+        match &outcome {
+            FileOutcome::Sent { bytes } => {
+                debug!(
+                    "Batch {}: sent {} ({} bytes)",
+                    batch_id,
+                    path.display(),
+                    bytes
+                )
+            }
+            FileOutcome::Failed { error } => {
+                warn!(
+                    "Batch {}: failed to send {}: {}",
+                    batch_id,
+                    path.display(),
+                    error
+                )
+            }
+        }
+
+        let result = FileResult {
+            path: path.clone(),
+            outcome,
+        };
+        emit(BatchEvent::FileFinished {
+            batch_id: batch_id.to_string(),
+            index,
+            result: result.clone(),
+        });
+        results.push(result);
+    }
+
+    let report = BatchReport {
+        batch_id: batch_id.to_string(),
+        results,
+    };
+    info!("Batch {} finished: {}", batch_id, report.summary());
+    emit(BatchEvent::Completed(report.clone()));
+    report
+}
+
+/// Progress reporter for a file starting after `bytes_before` bytes
+fn batch_progress(
+    batch_id: &str,
+    events: Option<&mpsc::UnboundedSender<BatchEvent>>,
+    bytes_before: u64,
+    total_bytes: u64,
+) -> FileProgress {
+    let batch_id = batch_id.to_string();
+    let events = events.cloned();
+    Arc::new(move |bytes| {
+        if let Some(events) = &events {
+            let _ = events.send(BatchEvent::Progress {
+                batch_id: batch_id.clone(),
+                bytes_sent: bytes_before + bytes,
+                total_bytes,
+            });
+        }
+    })
+}
+
+/// Regular files below `dir`, in path order
+///
+/// Subdirectories are searched too; symbolic links are skipped so a link
+/// cannot pull files from outside the directory into the transfer.
+pub async fn directory_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    walk_directory(dir, &AtomicBool::new(false), |_| {}).await
+}
+
+/// Regular files below `dir` like [`directory_files`], reporting the search
+///
+/// Emits [`BatchEvent::Preparing`] when the search starts and every
+/// [`PREPARE_REPORT_INTERVAL`] files found.
+///
+/// # Errors
+///
+/// Returns [`ProtocolError::Cancelled`] if `cancel` is set before the search
+/// finishes, or an error if a directory cannot be listed.
+pub async fn prepare_directory(
+    batch_id: &str,
+    dir: &Path,
+    events: Option<&mpsc::UnboundedSender<BatchEvent>>,
+    cancel: &AtomicBool,
+) -> Result<Vec<PathBuf>> {
+    let report = |files_found: usize| {
+        if let Some(events) = events {
+            let _ = events.send(BatchEvent::Preparing {
+                batch_id: batch_id.to_string(),
+                files_found,
+            });
+        }
+    };
+
+    report(0);
+    walk_directory(dir, cancel, |files_found| {
+        if files_found % PREPARE_REPORT_INTERVAL == 0 {
+            report(files_found);
+        }
+    })
+    .await
+}
+
+/// Search `dir`, calling `found` with the running count of files found
+async fn walk_directory(
+    dir: &Path,
+    cancel: &AtomicBool,
+    mut found: impl FnMut(usize),
+) -> Result<Vec<PathBuf>> {
+    let cancelled =
+        || ProtocolError::Cancelled(format!("Preparing {} was cancelled", dir.display()));
+
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(cancelled());
+        }
+        let mut entries = tokio::fs::read_dir(&dir)
+            .await
+            .map_err(|e| ProtocolError::from_io_error(e, &format!("listing {:?}", dir)))?;
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| ProtocolError::from_io_error(e, &format!("listing {:?}", dir)))?
+        {
+            let file_type = entry
+                .file_type()
+                .await
+                .map_err(|e| ProtocolError::from_io_error(e, &format!("reading {:?}", dir)))?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                files.push(entry.path());
+                found(files.len());
+            }
+            if cancel.load(Ordering::SeqCst) {
+                return Err(cancelled());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Send every file below `dir` as one batch
+///
+/// Fails only if the directory cannot be listed; files that cannot be sent
+/// are reported like in [`send_batch`].
+pub async fn send_directory(
+    batch_id: &str,
+    dir: &Path,
+    sender: &dyn BatchFileSender,
+    events: Option<&mpsc::UnboundedSender<BatchEvent>>,
+) -> Result<BatchReport> {
+    send_directory_cancellable(batch_id, dir, sender, events, &AtomicBool::new(false)).await
+}
+
+/// Send every file below `dir` as one batch, reporting the search first
+///
+/// Like [`send_directory`], with [`BatchEvent::Preparing`] events while the
+/// directory is searched. Setting `cancel` before the search finishes
+/// aborts the batch before anything is announced or sent.
+///
+/// # Errors
+///
+/// Returns [`ProtocolError::Cancelled`] if cancelled while preparing, or an
+/// error if the directory cannot be listed.
+pub async fn send_directory_cancellable(
+    batch_id: &str,
+    dir: &Path,
+    sender: &dyn BatchFileSender,
+    events: Option<&mpsc::UnboundedSender<BatchEvent>>,
+    cancel: &AtomicBool,
+) -> Result<BatchReport> {
+    let paths = prepare_directory(batch_id, dir, events, cancel).await?;
+    if cancel.load(Ordering::SeqCst) {
+        info!("Batch {} cancelled while preparing", batch_id);
+        return Err(ProtocolError::Cancelled(format!(
+            "Batch {} was cancelled",
+            batch_id
+        )));
+    }
+    debug!(
+        "Batch {}: sending {} files from {}",
+        batch_id,
+        paths.len(),
+        dir.display()
+    );
+    Ok(send_batch(batch_id, &paths, sender, events).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Reads each file fully, like a payload server would
+    #[derive(Default)]
+    struct ReadingSender {
+        announced: Mutex<Option<MultiFileInfo>>,
+        sent: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl BatchFileSender for ReadingSender {
+        async fn announce(&self, info: MultiFileInfo) -> Result<()> {
+            *self.announced.lock().unwrap() = Some(info);
+            Ok(())
+        }
+
+        async fn send_file(&self, file: &FileTransferInfo) -> Result<u64> {
+            let data = tokio::fs::read(&file.path)
+                .await
+                .map_err(|e| ProtocolError::from_io_error(e, "reading shared file"))?;
+            self.sent.lock().unwrap().push(file.filename.clone());
+            Ok(data.len() as u64)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_continues_past_unreadable_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut paths = Vec::new();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, name).unwrap();
+            paths.push(path);
+        }
+        // Vanishes before it is read
+        paths.insert(1, dir.path().join("missing.txt"));
+
+        let sender = ReadingSender::default();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let report = send_batch("batch", &paths, &sender, Some(&tx)).await;
+
+        assert_eq!(report.total(), 4);
+        assert_eq!(report.sent(), 3);
+        assert_eq!(report.summary(), "3 of 4 sent, 1 failed");
+        let failures: Vec<_> = report.failures().collect();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].path, paths[1]);
+        assert_eq!(
+            *sender.sent.lock().unwrap(),
+            vec!["a.txt", "b.txt", "c.txt"]
+        );
+        assert_eq!(
+            sender
+                .announced
+                .lock()
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .number_of_files,
+            3
+        );
+
+        let mut finished = 0;
+        let mut completed = None;
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                BatchEvent::FileFinished { .. } => finished += 1,
+                BatchEvent::Completed(report) => completed = Some(report),
+                BatchEvent::Preparing { .. }
+                | BatchEvent::FileStarted { .. }
+                | BatchEvent::Progress { .. } => {}
+            }
+        }
+        assert_eq!(finished, 4);
+        assert_eq!(completed, Some(report));
+    }
+
+    #[tokio::test]
+    async fn test_batch_where_every_file_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = vec![dir.path().join("x"), dir.path().join("y")];
+
+        let sender = ReadingSender::default();
+        let report = send_batch("batch", &paths, &sender, None).await;
+
+        assert_eq!(report.sent(), 0);
+        assert_eq!(report.failed(), 2);
+        assert_eq!(report.summary(), "0 of 2 sent, 2 failed");
+        assert!(report
+            .results
+            .iter()
+            .all(|r| matches!(&r.outcome, FileOutcome::Failed { error } if !error.is_empty())));
+        // Nothing readable, so nothing announced
+        assert!(sender.announced.lock().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_directory_reports_each_file_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+        std::fs::write(dir.path().join("a.jpg"), vec![0u8; 10]).unwrap();
+        std::fs::write(dir.path().join("b.jpg"), vec![0u8; 20]).unwrap();
+        std::fs::write(dir.path().join("nested").join("c.jpg"), vec![0u8; 30]).unwrap();
+
+        let sender = ReadingSender::default();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let report = send_directory("dir", dir.path(), &sender, Some(&tx))
+            .await
+            .unwrap();
+        assert_eq!(report.summary(), "3 of 3 sent");
+
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(match event {
+                BatchEvent::Preparing { files_found, .. } => {
+                    format!("preparing {}", files_found)
+                }
+                BatchEvent::FileStarted {
+                    index,
+                    total_files,
+                    path,
+                    ..
+                } => format!(
+                    "start {}/{} {}",
+                    index + 1,
+                    total_files,
+                    path.file_name().unwrap().to_string_lossy()
+                ),
+                BatchEvent::Progress {
+                    bytes_sent,
+                    total_bytes,
+                    ..
+                } => format!("progress {}/{}", bytes_sent, total_bytes),
+                BatchEvent::FileFinished { index, result, .. } => {
+                    assert!(result.is_sent());
+                    format!("done {}", index + 1)
+                }
+                BatchEvent::Completed(_) => "completed".to_string(),
+            });
+        }
+        assert_eq!(
+            events,
+            vec![
+                "preparing 0",
+                "start 1/3 a.jpg",
+                "progress 10/60",
+                "done 1",
+                "start 2/3 b.jpg",
+                "progress 30/60",
+                "done 2",
+                "start 3/3 c.jpg",
+                "progress 60/60",
+                "done 3",
+                "completed",
+            ]
+        );
+    }
+
+    fn large_directory(files: usize) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..files {
+            std::fs::write(dir.path().join(format!("{:04}.txt", i)), b"x").unwrap();
+        }
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_large_directory_prepares_before_streaming() {
+        let dir = large_directory(250);
+        let sender = ReadingSender::default();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let report = send_directory_cancellable(
+            "dir",
+            dir.path(),
+            &sender,
+            Some(&tx),
+            &AtomicBool::new(false),
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.summary(), "250 of 250 sent");
+
+        let mut preparing = Vec::new();
+        let mut first_other = None;
+        let mut last_progress = None;
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                BatchEvent::Preparing { files_found, .. } => {
+                    // Nothing is streamed while preparing
+                    assert!(first_other.is_none());
+                    preparing.push(files_found);
+                }
+                BatchEvent::Progress {
+                    bytes_sent,
+                    total_bytes,
+                    ..
+                } => last_progress = Some((bytes_sent, total_bytes)),
+                other => {
+                    first_other.get_or_insert(other);
+                }
+            }
+        }
+        assert_eq!(preparing, vec![0, 100, 200]);
+        assert!(matches!(
+            first_other,
+            Some(BatchEvent::FileStarted { index: 0, .. })
+        ));
+        assert_eq!(last_progress, Some((250, 250)));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_while_preparing_sends_nothing() {
+        let dir = large_directory(1000);
+        let sender = ReadingSender::default();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        // Cancel as soon as the search is under way, like a user would
+        let canceller = tokio::spawn({
+            let cancel = cancel.clone();
+            async move {
+                let mut events = Vec::new();
+                while let Some(event) = rx.recv().await {
+                    if matches!(event, BatchEvent::Preparing { files_found, .. } if files_found > 0)
+                    {
+                        cancel.store(true, Ordering::SeqCst);
+                    }
+                    events.push(event);
+                }
+                events
+            }
+        });
+
+        let result =
+            send_directory_cancellable("dir", dir.path(), &sender, Some(&tx), &cancel).await;
+        drop(tx);
+        let events = canceller.await.unwrap();
+
+        assert!(matches!(result, Err(ProtocolError::Cancelled(_))));
+        assert!(sender.announced.lock().unwrap().is_none());
+        assert!(sender.sent.lock().unwrap().is_empty());
+        assert!(events
+            .iter()
+            .all(|event| matches!(event, BatchEvent::Preparing { .. })));
+    }
+}
@@ -0,0 +1,307 @@
+//! Requesting Files From a Device
+//!
+//! Shares are normally pushed by the device. A fetch works the other way
+//! round: the desktop picks a file, typically from a remote listing (see
+//! [`sftp_browser`](super::sftp_browser)), and asks the device to send it.
+//! The file then arrives through the usual payload path.
+//!
+//! ## Protocol
+//!
+//! The desktop sends a fetch request naming the remote path:
+//!
+//! ```json
+//! {
+//!     "type": "cconnect.share.fetch",
+//!     "body": {
+//!         "requestId": "6f1c...",
+//!         "path": "/storage/emulated/0/DCIM/Camera/IMG_0001.jpg"
+//!     }
+//! }
+//! ```
+//!
+//! The device answers with a regular file share (`cconnect.share.request` with
+//! a payload) whose body carries the same ID in `fetchRequestId`, or with an
+//! error:
+//!
+//! ```json
+//! {
+//!     "type": "cconnect.share.fetch.error",
+//!     "body": {
+//!         "requestId": "6f1c...",
+//!         "reason": "notFound",
+//!         "message": "No such file"
+//!     }
+//! }
+//! ```
+//!
+//! A fetched file is saved into the directory given with the request, next to
+//! any existing file of the same name rather than over it.
+
+use super::sftp_browser::remote_not_found;
+use crate::{Packet, ProtocolError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tracing::debug;
+
+/// Packet asking the device to send a file
+pub const FETCH_REQUEST: &str = "cconnect.share.fetch";
+
+/// Packet reporting that a requested file cannot be sent
+pub const FETCH_ERROR: &str = "cconnect.share.fetch.error";
+
+/// Share request field linking a file to the fetch it answers
+pub const FETCH_REQUEST_ID_FIELD: &str = "fetchRequestId";
+
+/// Why the device could not send a requested file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FetchErrorReason {
+    /// The path does not exist
+    NotFound,
+    /// The device may not read the path
+    PermissionDenied,
+    /// The path is not a regular file
+    NotAFile,
+    /// Anything else
+    #[serde(other)]
+    Other,
+}
+
+/// Build a fetch request for `path`
+pub fn create_fetch_request(request_id: &str, path: &str) -> Packet {
+    Packet::new(
+        FETCH_REQUEST,
+        json!({
+            "requestId": request_id,
+            "path": path,
+        }),
+    )
+}
+
+/// Build the error answer to a fetch request
+pub fn create_fetch_error(request_id: &str, reason: FetchErrorReason, message: &str) -> Packet {
+    Packet::new(
+        FETCH_ERROR,
+        json!({
+            "requestId": request_id,
+            "reason": reason,
+            "message": message,
+        }),
+    )
+}
+
+/// ID of the fetch a share request answers, if any
+pub fn fetch_request_id(packet: &Packet) -> Option<&str> {
+    packet.body.get(FETCH_REQUEST_ID_FIELD)?.as_str()
+}
+
+/// A fetch answered by a file, ready to be downloaded
+#[derive(Debug)]
+pub struct FetchReply {
+    /// Remote path that was requested
+    pub path: String,
+    /// Directory the file is saved into
+    pub download_dir: PathBuf,
+    reply: oneshot::Sender<Result<PathBuf>>,
+}
+
+impl FetchReply {
+    /// Report the outcome of the download to the requester
+    pub fn complete(self, result: Result<PathBuf>) {
+        let _ = self.reply.send(result);
+    }
+}
+
+#[derive(Debug)]
+struct Pending {
+    path: String,
+    download_dir: PathBuf,
+    reply: oneshot::Sender<Result<PathBuf>>,
+}
+
+/// Fetch requests waiting for the device's answer
+#[derive(Debug, Clone, Default)]
+pub struct PendingFetches {
+    pending: Arc<Mutex<HashMap<String, Pending>>>,
+}
+
+impl PendingFetches {
+    /// Create an empty set of fetches
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a fetch of `path` into `download_dir`
+    ///
+    /// Returns the packet to send to the device and a handle that resolves
+    /// once the file has been received or the device refused.
+    pub fn request(&self, path: &str, download_dir: impl Into<PathBuf>) -> (Packet, FileFetch) {
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let (reply, receiver) = oneshot::channel();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(
+                request_id.clone(),
+                Pending {
+                    path: path.to_string(),
+                    download_dir: download_dir.into(),
+                    reply,
+                },
+            );
+        }
+        let fetch = FileFetch {
+            request_id: request_id.clone(),
+            path: path.to_string(),
+            receiver,
+            fetches: self.clone(),
+        };
+        (create_fetch_request(&request_id, path), fetch)
+    }
+
+    /// Claim the fetch answered by a received share request
+    ///
+    /// `None` if the packet is an ordinary share or the fetch was abandoned.
+    pub fn take_reply(&self, packet: &Packet) -> Option<FetchReply> {
+        let pending = self.remove(fetch_request_id(packet)?)?;
+        Some(FetchReply {
+            path: pending.path,
+            download_dir: pending.download_dir,
+            reply: pending.reply,
+        })
+    }
+
+    /// Fail the fetch named in a received fetch error packet
+    ///
+    /// Returns whether a pending fetch was failed.
+    pub fn handle_error(&self, packet: &Packet) -> bool {
+        let Some(request_id) = packet.body.get("requestId").and_then(|v| v.as_str()) else {
+            return false;
+        };
+        let Some(pending) = self.remove(request_id) else {
+            return false;
+        };
+        let reason = packet
+            .get_body_field::<FetchErrorReason>("reason")
+            .unwrap_or(FetchErrorReason::Other);
+        let message = packet
+            .get_body_field::<String>("message")
+            .unwrap_or_default();
+        let error = match reason {
+            FetchErrorReason::NotFound => remote_not_found(&pending.path),
+            FetchErrorReason::PermissionDenied => ProtocolError::PermissionDenied(format!(
+                "Device refused access to {}: {}",
+                pending.path, message
+            )),
+            FetchErrorReason::NotAFile => {
+                ProtocolError::InvalidState(format!("Remote path {} is not a file", pending.path))
+            }
+            FetchErrorReason::Other => ProtocolError::PeerRejected(format!(
+                "Device could not send {}: {}",
+                pending.path, message
+            )),
+        };
+        let _ = pending.reply.send(Err(error));
+        true
+    }
+
+    /// Number of fetches waiting for an answer
+    pub fn len(&self) -> usize {
+        self.pending.lock().map(|p| p.len()).unwrap_or(0)
+    }
+
+    /// Whether no fetch is waiting for an answer
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn remove(&self, request_id: &str) -> Option<Pending> {
+        self.pending.lock().ok()?.remove(request_id)
+    }
+}
+
+/// Handle to a file requested from a device
+#[derive(Debug)]
+pub struct FileFetch {
+    request_id: String,
+    path: String,
+    receiver: oneshot::Receiver<Result<PathBuf>>,
+    fetches: PendingFetches,
+}
+
+impl FileFetch {
+    /// ID carried by the fetch request
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    /// Remote path that was requested
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Wait for the file, returning where it was saved
+    ///
+    /// A device that neither sends the file nor refuses within `timeout`
+    /// yields [`ProtocolError::Timeout`]; a late answer is then treated as an
+    /// ordinary share.
+    pub async fn wait(self, timeout: Duration) -> Result<PathBuf> {
+        match tokio::time::timeout(timeout, self.receiver).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(ProtocolError::Transport(format!(
+                "Request for {} ended without a file",
+                self.path
+            ))),
+            Err(_) => {
+                debug!("Abandoning fetch {} of {}", self.request_id, self.path);
+                self.fetches.remove(&self.request_id);
+                Err(ProtocolError::Timeout(format!(
+                    "Device did not send {} within {:?}",
+                    self.path, timeout
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::sftp_browser::is_not_found;
+
+    #[tokio::test]
+    async fn test_error_answer_fails_fetch() {
+        let fetches = PendingFetches::new();
+        let (packet, fetch) = fetches.request("/sdcard/missing.jpg", "/tmp");
+        assert_eq!(packet.packet_type, FETCH_REQUEST);
+        assert_eq!(packet.body["path"], "/sdcard/missing.jpg");
+
+        let error = create_fetch_error(fetch.request_id(), FetchErrorReason::NotFound, "gone");
+        assert!(fetches.handle_error(&error));
+        assert!(!fetches.handle_error(&error));
+
+        let result = fetch.wait(Duration::from_secs(1)).await;
+        assert!(is_not_found(&result.unwrap_err()));
+        assert!(fetches.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_fetch_times_out() {
+        let fetches = PendingFetches::new();
+        let (_, fetch) = fetches.request("/sdcard/a.jpg", "/tmp");
+        let request_id = fetch.request_id().to_string();
+
+        let result = fetch.wait(Duration::from_millis(20)).await;
+        assert!(matches!(result, Err(ProtocolError::Timeout(_))));
+
+        // A late file is no longer claimed as the fetch's answer
+        let late = Packet::new(
+            "cconnect.share.request",
+            json!({"filename": "a.jpg", FETCH_REQUEST_ID_FIELD: request_id}),
+        );
+        assert!(fetches.take_reply(&late).is_none());
+    }
+}
@@ -0,0 +1,326 @@
+//! Download-Complete Hooks
+//!
+//! Actions run after the Share plugin has finished receiving a file: open it
+//! with the desktop's default application, or run a user command with the
+//! file path as its last argument.
+//!
+//! Each hook is gated by file extension and sending device, so a command is
+//! only ever run for the files it was configured for. An `open` hook needs an
+//! explicit extension list; without one it matches nothing, so a peer cannot
+//! get arbitrary file types opened. Executable files and `.desktop` launchers
+//! never trigger a hook. Commands are started directly (never through a
+//! shell) with the path as a separate argument, and run in their own task so
+//! a slow or hanging hook cannot hold up transfers.
+//!
+//! ## Example
+//!
+//! ```toml
+//! [[plugins.share_completion_hooks]]
+//! action = "open"
+//! extensions = ["pdf", "png"]
+//!
+//! [[plugins.share_completion_hooks]]
+//! action = "run"
+//! program = "/home/me/bin/import-photo"
+//! extensions = ["jpg"]
+//! devices = ["a1b2c3d4"]
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Program used to open files with the default application
+const OPEN_PROGRAM: &str = "xdg-open";
+
+/// Extensions of launchers that run a program when opened
+const LAUNCHER_EXTENSIONS: &[&str] = &["desktop"];
+
+/// What to do with a received file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum CompletionAction {
+    /// Open the file with the default application
+    Open,
+    /// Run `program` with `args` followed by the file path
+    Run {
+        /// Executable to run
+        program: String,
+        /// Arguments placed before the file path
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+/// A completion action and the files it applies to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompletionHook {
+    /// Action to take
+    #[serde(flatten)]
+    pub action: CompletionAction,
+
+    /// File extensions this hook applies to, without the dot (empty = any
+    /// for `run`, none for `open`)
+    #[serde(default)]
+    pub extensions: Vec<String>,
+
+    /// Devices this hook applies to (empty = any)
+    #[serde(default)]
+    pub devices: Vec<String>,
+}
+
+impl CompletionHook {
+    /// Create a hook that applies to files from every device
+    ///
+    /// A `run` hook applies to every file until restricted with
+    /// [`with_extensions`](Self::with_extensions); an `open` hook applies to
+    /// none until given one.
+    pub fn new(action: CompletionAction) -> Self {
+        Self {
+            action,
+            extensions: Vec::new(),
+            devices: Vec::new(),
+        }
+    }
+
+    /// Only apply to files with one of these extensions
+    pub fn with_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extensions = extensions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Only apply to files from these devices
+    pub fn with_devices<I, S>(mut self, devices: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.devices = devices.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Whether this hook applies to `path` received from `device_id`
+    ///
+    /// Extensions are compared case-insensitively; files without an
+    /// extension only match `run` hooks without an extension filter. Launchers
+    /// never match.
+    pub fn matches(&self, device_id: &str, path: &Path) -> bool {
+        if !self.devices.is_empty() && !self.devices.iter().any(|d| d == device_id) {
+            return false;
+        }
+        if is_launcher(path) {
+            return false;
+        }
+        if self.extensions.is_empty() {
+            return !matches!(self.action, CompletionAction::Open);
+        }
+        let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+            return false;
+        };
+        self.extensions.iter().any(|allowed| {
+            allowed
+                .trim_start_matches('.')
+                .eq_ignore_ascii_case(extension)
+        })
+    }
+
+    /// Program and arguments to run for `path`
+    pub fn command(&self, path: &Path) -> tokio::process::Command {
+        let mut command = match &self.action {
+            CompletionAction::Open => tokio::process::Command::new(OPEN_PROGRAM),
+            CompletionAction::Run { program, args } => {
+                let mut command = tokio::process::Command::new(program);
+                command.args(args);
+                command
+            }
+        };
+        command
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        command
+    }
+}
+
+/// Whether `path` names a `.desktop` launcher
+fn is_launcher(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|extension| {
+            LAUNCHER_EXTENSIONS
+                .iter()
+                .any(|launcher| launcher.eq_ignore_ascii_case(extension))
+        })
+}
+
+/// Whether the received file is marked executable
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::symlink_metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// Start every hook matching a received file
+///
+/// Each matching hook runs in its own task; the returned handles resolve to
+/// the command's exit status and can be ignored. Nothing runs for an
+/// executable file.
+pub fn run_completion_hooks(
+    hooks: &[CompletionHook],
+    device_id: &str,
+    path: &Path,
+) -> Vec<JoinHandle<std::io::Result<ExitStatus>>> {
+    if !hooks.is_empty() && is_executable(path) {
+        warn!(
+            "Not running completion hooks for executable file {:?}",
+            path
+        );
+        return Vec::new();
+    }
+    hooks
+        .iter()
+        .filter(|hook| hook.matches(device_id, path))
+        .map(|hook| {
+            let mut command = hook.command(path);
+            let action = hook.action.clone();
+            let path = path.to_path_buf();
+            tokio::spawn(async move {
+                info!("Running completion hook {:?} for {:?}", action, path);
+                let status = command.status().await;
+                match &status {
+                    Ok(status) if !status.success() => {
+                        warn!(
+                            "Completion hook {:?} for {:?} exited with {}",
+                            action, path, status
+                        );
+                    }
+                    Err(e) => warn!("Failed to run completion hook {:?}: {}", action, e),
+                    Ok(_) => {}
+                }
+                status
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hook that writes the path it was given to `out`
+    fn recording_hook(out: &Path) -> CompletionHook {
+        CompletionHook::new(CompletionAction::Run {
+            program: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                format!("printf '%s' \"$1\" > '{}'", out.display()),
+                "hook".to_string(),
+            ],
+        })
+    }
+
+    #[tokio::test]
+    async fn test_completion_runs_hook_with_file_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("hook.out");
+        let received = dir.path().join("holiday photo.JPG");
+        let hooks = vec![recording_hook(&out).with_extensions(["jpg"])];
+
+        let handles = run_completion_hooks(&hooks, "phone", &received);
+        assert_eq!(handles.len(), 1);
+        for handle in handles {
+            assert!(handle.await.unwrap().unwrap().success());
+        }
+
+        let recorded = tokio::fs::read_to_string(&out).await.unwrap();
+        assert_eq!(recorded, received.to_string_lossy());
+    }
+
+    #[tokio::test]
+    async fn test_completion_respects_type_and_device_filters() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("hook.out");
+        let hooks = vec![recording_hook(&out)
+            .with_extensions(["pdf"])
+            .with_devices(["phone"])];
+
+        assert!(run_completion_hooks(&hooks, "phone", &dir.path().join("a.zip")).is_empty());
+        assert!(run_completion_hooks(&hooks, "phone", &dir.path().join("README")).is_empty());
+        assert!(run_completion_hooks(&hooks, "tablet", &dir.path().join("a.pdf")).is_empty());
+        assert!(!out.exists());
+
+        assert!(hooks[0].matches("phone", Path::new("/tmp/a.PDF")));
+        assert!(CompletionHook::new(CompletionAction::Run {
+            program: "true".to_string(),
+            args: Vec::new(),
+        })
+        .matches("any", Path::new("README")));
+    }
+
+    #[test]
+    fn test_open_requires_extension_allowlist() {
+        let open = CompletionHook::new(CompletionAction::Open);
+        assert!(!open.matches("any", Path::new("a.pdf")));
+        assert!(!open.matches("any", Path::new("README")));
+
+        let open = open.with_extensions(["pdf"]);
+        assert!(open.matches("any", Path::new("a.pdf")));
+        assert!(!open.matches("any", Path::new("a.sh")));
+    }
+
+    #[tokio::test]
+    async fn test_completion_skips_launchers_and_executables() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("hook.out");
+        let hooks = vec![
+            recording_hook(&out),
+            CompletionHook::new(CompletionAction::Open).with_extensions(["desktop", "pdf"]),
+        ];
+
+        let launcher = dir.path().join("evil.Desktop");
+        assert!(!hooks.iter().any(|hook| hook.matches("phone", &launcher)));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let script = dir.path().join("report.pdf");
+            std::fs::write(&script, "#!/bin/sh\n").unwrap();
+            std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+            assert!(run_completion_hooks(&hooks, "phone", &script).is_empty());
+        }
+        assert!(!out.exists());
+    }
+
+    #[test]
+    fn test_hook_config_format() {
+        let hook: CompletionHook = serde_json::from_value(serde_json::json!({
+            "action": "run",
+            "program": "notify",
+            "extensions": ["png"],
+        }))
+        .unwrap();
+        assert_eq!(
+            hook.action,
+            CompletionAction::Run {
+                program: "notify".to_string(),
+                args: Vec::new(),
+            }
+        );
+        assert_eq!(hook.extensions, vec!["png"]);
+        assert!(hook.devices.is_empty());
+    }
+}
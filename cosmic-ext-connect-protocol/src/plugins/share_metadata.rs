@@ -0,0 +1,157 @@
+//! Metadata Attached to Shared Files
+//!
+//! A file share can carry free-form key/value metadata next to the file:
+//! a caption, tags, the app it was shared from. The sender fills it in, the
+//! receiver reports it with the received file and can write it to a sidecar
+//! file next to the download.
+//!
+//! ## Protocol
+//!
+//! The metadata travels as a string map in the share request body:
+//!
+//! ```json
+//! {
+//!     "type": "cconnect.share.request",
+//!     "body": {
+//!         "filename": "IMG_0001.jpg",
+//!         "metadata": {
+//!             "caption": "Sunset at the pier",
+//!             "origin": "org.gallery"
+//!         }
+//!     },
+//!     "payloadSize": 1048576
+//! }
+//! ```
+//!
+//! Keys and values together may not exceed [`MAX_METADATA_BYTES`]. Senders
+//! check this when attaching metadata; receivers refuse shares that exceed it
+//! rather than truncating.
+//!
+//! ## Sidecar Files
+//!
+//! With sidecars enabled, the metadata of `IMG_0001.jpg` is written as a JSON
+//! object to `IMG_0001.jpg.meta.json` in the same directory.
+
+use crate::{Packet, ProtocolError, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Key/value metadata of a shared file
+pub type FileMetadata = BTreeMap<String, String>;
+
+/// Share request field carrying the metadata
+pub const METADATA_FIELD: &str = "metadata";
+
+/// Largest metadata accepted, counting the bytes of all keys and values
+pub const MAX_METADATA_BYTES: usize = 4096;
+
+/// Suffix appended to a file's name for its sidecar
+pub const SIDECAR_SUFFIX: &str = ".meta.json";
+
+/// Bytes of all keys and values
+pub fn metadata_size(metadata: &FileMetadata) -> usize {
+    metadata
+        .iter()
+        .map(|(key, value)| key.len() + value.len())
+        .sum()
+}
+
+/// Check metadata against [`MAX_METADATA_BYTES`]
+///
+/// # Errors
+///
+/// Returns [`ProtocolError::ResourceExhausted`] if the metadata is too large.
+pub fn check_metadata(metadata: &FileMetadata) -> Result<()> {
+    let size = metadata_size(metadata);
+    if size > MAX_METADATA_BYTES {
+        return Err(ProtocolError::ResourceExhausted(format!(
+            "file metadata of {} bytes exceeds the {} byte limit",
+            size, MAX_METADATA_BYTES
+        )));
+    }
+    Ok(())
+}
+
+/// Metadata carried by a share request
+///
+/// A request without metadata yields an empty map.
+///
+/// # Errors
+///
+/// Returns [`ProtocolError::InvalidPacket`] if the field is not a map of
+/// strings and [`ProtocolError::ResourceExhausted`] if it is too large.
+pub fn parse_metadata(packet: &Packet) -> Result<FileMetadata> {
+    let Some(value) = packet.body.get(METADATA_FIELD) else {
+        return Ok(FileMetadata::new());
+    };
+    let metadata: FileMetadata = serde_json::from_value(value.clone()).map_err(|e| {
+        ProtocolError::InvalidPacket(format!("Share metadata is not a string map: {}", e))
+    })?;
+    check_metadata(&metadata)?;
+    Ok(metadata)
+}
+
+/// Where the sidecar of `file` is written
+pub fn sidecar_path(file: &Path) -> PathBuf {
+    let mut name = file.file_name().unwrap_or_default().to_os_string();
+    name.push(SIDECAR_SUFFIX);
+    file.with_file_name(name)
+}
+
+/// Write `metadata` next to `file`, returning the sidecar's path
+pub async fn write_sidecar(file: &Path, metadata: &FileMetadata) -> Result<PathBuf> {
+    let path = sidecar_path(file);
+    let json = serde_json::to_vec_pretty(metadata)?;
+    tokio::fs::write(&path, json)
+        .await
+        .map_err(|e| ProtocolError::from_io_error(e, "writing share metadata sidecar"))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_oversized_metadata_rejected() {
+        let mut metadata = FileMetadata::new();
+        metadata.insert("caption".to_string(), "x".repeat(MAX_METADATA_BYTES));
+
+        let error = check_metadata(&metadata).unwrap_err();
+        assert!(matches!(error, ProtocolError::ResourceExhausted(_)));
+        assert!(error.to_string().contains("metadata"));
+
+        let packet = Packet::new(
+            "cconnect.share.request",
+            json!({"filename": "a.jpg", METADATA_FIELD: metadata}),
+        );
+        assert!(matches!(
+            parse_metadata(&packet),
+            Err(ProtocolError::ResourceExhausted(_))
+        ));
+    }
+
+    #[test]
+    fn test_metadata_must_be_string_map() {
+        let packet = Packet::new(
+            "cconnect.share.request",
+            json!({"filename": "a.jpg", METADATA_FIELD: {"tags": ["a", "b"]}}),
+        );
+        assert!(matches!(
+            parse_metadata(&packet),
+            Err(ProtocolError::InvalidPacket(_))
+        ));
+
+        let plain = Packet::new("cconnect.share.request", json!({"filename": "a.jpg"}));
+        assert!(parse_metadata(&plain).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sidecar_path() {
+        assert_eq!(
+            sidecar_path(Path::new("/home/me/Downloads/IMG_0001.jpg")),
+            PathBuf::from("/home/me/Downloads/IMG_0001.jpg.meta.json")
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! Repairing Received Files
+//!
+//! A sender may announce per-chunk SHA-256 checksums with a file (see
+//! [`transfer_integrity`](crate::transfer_integrity)). The file is then
+//! received under a hidden staging name, verified chunk by chunk, and only
+//! the chunks that fail are fetched again before it gets its final name.
+//!
+//! ## Protocol
+//!
+//! The receiver asks for each failing range with a retransmit request whose
+//! `transferId` is the ID of the share request packet:
+//!
+//! ```json
+//! {
+//!     "type": "cconnect.share.request.retransmit",
+//!     "body": {
+//!         "transferId": "1700000000000",
+//!         "ranges": [{ "firstChunk": 3, "offset": 786432, "length": 262144 }]
+//!     }
+//! }
+//! ```
+//!
+//! The sender answers each range with its bytes as payload, encrypted like
+//! the original file if that was encrypted:
+//!
+//! ```json
+//! {
+//!     "type": "cconnect.share.request.retransmit.data",
+//!     "body": { "transferId": "1700000000000", "offset": 786432, "length": 262144 },
+//!     "payloadSize": 262144,
+//!     "payloadTransferInfo": { "port": 1739 }
+//! }
+//! ```
+//!
+//! A sender that does not answer within [`RETRANSMIT_TIMEOUT`] fails the
+//! repair, and the file is discarded.
+
+use crate::transfer_integrity::{create_retransmit_packet, ChunkRange, ChunkSource};
+use crate::{Packet, PayloadCipher, PayloadKey, ProtocolError, Result, TlsConfig};
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tracing::debug;
+
+/// Packet carrying one retransmitted range
+pub const RETRANSMIT_DATA: &str = "cconnect.share.request.retransmit.data";
+
+/// How long to wait for the sender to answer a retransmit request
+pub const RETRANSMIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Build the answer to a retransmit request, served on `port`
+pub fn create_retransmit_data(transfer_id: &str, range: &ChunkRange, port: u16) -> Packet {
+    let mut transfer_info = HashMap::new();
+    transfer_info.insert("port".to_string(), json!(port));
+
+    Packet::new(
+        RETRANSMIT_DATA,
+        json!({
+            "transferId": transfer_id,
+            "offset": range.offset,
+            "length": range.length,
+        }),
+    )
+    .with_payload_size(range.length as i64)
+    .with_payload_transfer_info(transfer_info)
+}
+
+fn pending_key(transfer_id: &str, offset: u64) -> String {
+    format!("{}:{}", transfer_id, offset)
+}
+
+/// Retransmit requests waiting for the sender's answer
+#[derive(Debug, Clone, Default)]
+pub struct PendingRetransmits {
+    pending: Arc<Mutex<HashMap<String, oneshot::Sender<Packet>>>>,
+}
+
+impl PendingRetransmits {
+    /// Create an empty set of requests
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Source that repairs the file sent as `transfer_id` by `device_id`
+    ///
+    /// Ranges are requested through `packet_sender` and downloaded from
+    /// `host` over TLS. Without a sender every fetch fails.
+    pub fn source(
+        &self,
+        device_id: &str,
+        transfer_id: &str,
+        host: &str,
+        tls_config: Arc<TlsConfig>,
+        payload_key: Option<PayloadKey>,
+        packet_sender: Option<mpsc::Sender<(String, Packet)>>,
+    ) -> RetransmitSource {
+        RetransmitSource {
+            device_id: device_id.to_string(),
+            transfer_id: transfer_id.to_string(),
+            host: host.to_string(),
+            tls_config,
+            payload_key,
+            packet_sender,
+            pending: self.clone(),
+        }
+    }
+
+    /// Hand a received retransmit answer to the source waiting for it
+    ///
+    /// Returns whether a request was waiting.
+    pub fn handle_data(&self, packet: &Packet) -> bool {
+        let (Some(transfer_id), Some(offset)) = (
+            packet.body.get("transferId").and_then(|v| v.as_str()),
+            packet.body.get("offset").and_then(|v| v.as_u64()),
+        ) else {
+            return false;
+        };
+        match self.remove(&pending_key(transfer_id, offset)) {
+            Some(reply) => reply.send(packet.clone()).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Number of requests waiting for an answer
+    pub fn len(&self) -> usize {
+        self.pending.lock().map(|p| p.len()).unwrap_or(0)
+    }
+
+    /// Whether no request is waiting for an answer
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn expect(&self, key: String) -> oneshot::Receiver<Packet> {
+        let (reply, receiver) = oneshot::channel();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(key, reply);
+        }
+        receiver
+    }
+
+    fn remove(&self, key: &str) -> Option<oneshot::Sender<Packet>> {
+        self.pending.lock().ok()?.remove(key)
+    }
+}
+
+/// Fetches failing ranges of one received file from its sender
+pub struct RetransmitSource {
+    device_id: String,
+    transfer_id: String,
+    host: String,
+    tls_config: Arc<TlsConfig>,
+    payload_key: Option<PayloadKey>,
+    packet_sender: Option<mpsc::Sender<(String, Packet)>>,
+    pending: PendingRetransmits,
+}
+
+impl RetransmitSource {
+    /// Wait for the sender's answer to the request for `range`
+    async fn request(&self, range: &ChunkRange) -> Result<Packet> {
+        let Some(sender) = &self.packet_sender else {
+            return Err(ProtocolError::InvalidState(
+                "Share plugin not initialized".to_string(),
+            ));
+        };
+        let key = pending_key(&self.transfer_id, range.offset);
+        let receiver = self.pending.expect(key.clone());
+        let request = create_retransmit_packet(&self.transfer_id, std::slice::from_ref(range));
+        if sender
+            .send((self.device_id.clone(), request))
+            .await
+            .is_err()
+        {
+            self.pending.remove(&key);
+            return Err(ProtocolError::Plugin(
+                "Failed to request retransmission: channel closed".to_string(),
+            ));
+        }
+
+        match tokio::time::timeout(RETRANSMIT_TIMEOUT, receiver).await {
+            Ok(Ok(reply)) => Ok(reply),
+            Ok(Err(_)) => Err(ProtocolError::Transport(format!(
+                "Retransmission of {} ended without data",
+                key
+            ))),
+            Err(_) => {
+                debug!("Abandoning retransmission of {}", key);
+                self.pending.remove(&key);
+                Err(ProtocolError::Timeout(format!(
+                    "Device did not retransmit {} within {:?}",
+                    key, RETRANSMIT_TIMEOUT
+                )))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ChunkSource for RetransmitSource {
+    async fn fetch_range(&mut self, range: &ChunkRange) -> Result<Vec<u8>> {
+        let reply = self.request(range).await?;
+        let port = reply
+            .payload_transfer_info
+            .as_ref()
+            .and_then(|info| info.get("port"))
+            .and_then(|v| v.as_u64())
+            .and_then(|port| u16::try_from(port).ok())
+            .ok_or_else(|| {
+                ProtocolError::InvalidPacket("Retransmitted range has no payload port".to_string())
+            })?;
+        let cipher = PayloadCipher::from_packet(&reply, self.payload_key.as_ref())?;
+
+        let client = crate::TlsPayloadClient::new(&self.host, port, &self.tls_config).await?;
+        let client = match cipher {
+            Some(cipher) => client.with_encryption(cipher),
+            None => client,
+        };
+        client.receive_bytes(range.length).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfer_integrity::RETRANSMIT_PACKET_TYPE;
+
+    #[tokio::test]
+    async fn test_answer_routed_to_waiting_request() {
+        let pending = PendingRetransmits::new();
+        let range = ChunkRange {
+            first_chunk: 3,
+            offset: 3072,
+            length: 1024,
+        };
+        let receiver = pending.expect(pending_key("42", range.offset));
+
+        // An answer for another range is not claimed
+        let other = ChunkRange { offset: 0, ..range };
+        assert!(!pending.handle_data(&create_retransmit_data("42", &other, 1739)));
+
+        let answer = create_retransmit_data("42", &range, 1739);
+        assert!(pending.handle_data(&answer));
+        assert!(pending.is_empty());
+        assert_eq!(receiver.await.unwrap().payload_size, Some(1024));
+    }
+
+    #[tokio::test]
+    async fn test_request_sent_to_device() {
+        let pending = PendingRetransmits::new();
+        let (sender, mut requests) = mpsc::channel(1);
+        let certificate = crate::CertificateInfo::generate("share-retransmit-test").unwrap();
+        let tls_config = Arc::new(TlsConfig::new(&certificate).unwrap());
+        let mut source = pending.source("phone", "42", "127.0.0.1", tls_config, None, Some(sender));
+        let range = ChunkRange {
+            first_chunk: 1,
+            offset: 1024,
+            length: 1024,
+        };
+
+        let fetch = tokio::spawn(async move { source.fetch_range(&range).await });
+        let (device_id, request) = requests.recv().await.unwrap();
+        assert_eq!(device_id, "phone");
+        assert_eq!(request.packet_type, RETRANSMIT_PACKET_TYPE);
+        assert_eq!(request.body["transferId"], "42");
+        assert_eq!(request.body["ranges"][0]["offset"], 1024);
+
+        // An answer without a port fails the fetch instead of hanging
+        let mut answer = create_retransmit_data("42", &range, 1739);
+        answer.payload_transfer_info = None;
+        assert!(pending.handle_data(&answer));
+        assert!(matches!(
+            fetch.await.unwrap(),
+            Err(ProtocolError::InvalidPacket(_))
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+//! Per-User Routing of Shared Files
+//!
+//! On a shared machine one daemon may serve several users. A file share can
+//! name the user it is meant for (or sent by, for a "send as" identity), and
+//! the receiver saves it into that user's directory instead of a single
+//! downloads folder. The user is reported with the received file.
+//!
+//! ## Protocol
+//!
+//! The user travels as a plain string in the share request body:
+//!
+//! ```json
+//! {
+//!     "type": "cconnect.share.request",
+//!     "body": {
+//!         "filename": "report.pdf",
+//!         "user": "alice"
+//!     },
+//!     "payloadSize": 52311
+//! }
+//! ```
+//!
+//! The name is only used as a key into the receiver's [`UserDirectories`];
+//! it never becomes part of a path. Unknown or missing users fall back to the
+//! default directory.
+
+use crate::Packet;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Share request field naming the originating user
+pub const USER_FIELD: &str = "user";
+
+/// User named by a share request, if any
+pub fn parse_user(packet: &Packet) -> Option<String> {
+    packet
+        .body
+        .get(USER_FIELD)?
+        .as_str()
+        .filter(|user| !user.is_empty())
+        .map(str::to_string)
+}
+
+/// Where received files are saved, per user
+#[derive(Debug, Clone, Default)]
+pub struct UserDirectories {
+    default: Option<PathBuf>,
+    users: HashMap<String, PathBuf>,
+}
+
+impl UserDirectories {
+    /// Save untagged files into `default`
+    ///
+    /// `None` keeps the standard downloads folder.
+    pub fn new(default: Option<PathBuf>) -> Self {
+        Self {
+            default,
+            users: HashMap::new(),
+        }
+    }
+
+    /// Save files tagged with `user` into `dir`
+    pub fn with_user(mut self, user: impl Into<String>, dir: impl Into<PathBuf>) -> Self {
+        self.users.insert(user.into(), dir.into());
+        self
+    }
+
+    /// Directory for untagged files, if configured
+    pub fn default_dir(&self) -> Option<&Path> {
+        self.default.as_deref()
+    }
+
+    /// Directory for files tagged with `user`
+    ///
+    /// Falls back to [`default_dir`](Self::default_dir) for untagged files
+    /// and users without a mapping.
+    pub fn dir_for(&self, user: Option<&str>) -> Option<&Path> {
+        let Some(user) = user else {
+            return self.default_dir();
+        };
+        match self.users.get(user) {
+            Some(dir) => Some(dir),
+            None => {
+                debug!("No directory for user {}, using the default", user);
+                self.default_dir()
+            }
+        }
+    }
+
+    /// Number of users with their own directory
+    pub fn len(&self) -> usize {
+        self.users.len()
+    }
+
+    /// Whether no user has their own directory
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_unknown_user_uses_default() {
+        let dirs = UserDirectories::new(Some(PathBuf::from("/srv/shared")))
+            .with_user("alice", "/home/alice/Downloads");
+
+        assert_eq!(
+            dirs.dir_for(Some("alice")),
+            Some(Path::new("/home/alice/Downloads"))
+        );
+        assert_eq!(
+            dirs.dir_for(Some("mallory")),
+            Some(Path::new("/srv/shared"))
+        );
+        assert_eq!(dirs.dir_for(None), Some(Path::new("/srv/shared")));
+        assert_eq!(UserDirectories::default().dir_for(Some("alice")), None);
+    }
+
+    #[test]
+    fn test_parse_user() {
+        let tagged = Packet::new(
+            "cconnect.share.request",
+            json!({"filename": "a.pdf", USER_FIELD: "alice"}),
+        );
+        assert_eq!(parse_user(&tagged).as_deref(), Some("alice"));
+
+        for body in [
+            json!({"filename": "a.pdf"}),
+            json!({"filename": "a.pdf", USER_FIELD: ""}),
+            json!({"filename": "a.pdf", USER_FIELD: 42}),
+        ] {
+            assert_eq!(
+                parse_user(&Packet::new("cconnect.share.request", body)),
+                None
+            );
+        }
+    }
+}
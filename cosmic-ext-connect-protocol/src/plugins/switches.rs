@@ -0,0 +1,92 @@
+//! Runtime Plugin Switches
+//!
+//! Plugins can be turned off without unregistering their factory or
+//! restarting the daemon, e.g. to stop clipboard sync on a shared machine.
+//! [`PluginManager::set_enabled`](super::PluginManager::set_enabled) turns a
+//! plugin off for every device: its packets are no longer dispatched and its
+//! packet types are left out of the advertised capabilities, so the identity
+//! sent on the next handshake tells peers to stop sending them.
+//! [`PluginManager::set_device_enabled`](super::PluginManager::set_device_enabled)
+//! turns a plugin off for one device only. The identity is shared by all
+//! peers, so that only stops dispatch.
+//!
+//! The switches live in memory; the daemon persists them with the rest of
+//! the plugin settings and applies them again at startup.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// A registered plugin and whether it is enabled
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PluginStatus {
+    /// Plugin name, as returned by its factory
+    pub id: String,
+    /// Whether the plugin is enabled for all devices
+    pub enabled: bool,
+    /// Devices the plugin is disabled for, sorted
+    pub disabled_devices: Vec<String>,
+}
+
+/// Plugins switched off globally or per device
+#[derive(Debug, Default)]
+pub(crate) struct PluginSwitches {
+    disabled: HashSet<String>,
+    /// device_id -> plugins disabled for it
+    device_disabled: HashMap<String, HashSet<String>>,
+}
+
+impl PluginSwitches {
+    /// Switch `plugin` on or off globally; returns whether anything changed
+    pub(crate) fn set(&mut self, plugin: &str, enabled: bool) -> bool {
+        if enabled {
+            self.disabled.remove(plugin)
+        } else {
+            self.disabled.insert(plugin.to_string())
+        }
+    }
+
+    /// Switch `plugin` on or off for one device
+    pub(crate) fn set_for_device(&mut self, device_id: &str, plugin: &str, enabled: bool) {
+        if enabled {
+            if let Some(plugins) = self.device_disabled.get_mut(device_id) {
+                plugins.remove(plugin);
+                if plugins.is_empty() {
+                    self.device_disabled.remove(device_id);
+                }
+            }
+        } else {
+            self.device_disabled
+                .entry(device_id.to_string())
+                .or_default()
+                .insert(plugin.to_string());
+        }
+    }
+
+    pub(crate) fn is_enabled(&self, plugin: &str) -> bool {
+        !self.disabled.contains(plugin)
+    }
+
+    /// Whether `plugin` is enabled both globally and for `device_id`
+    pub(crate) fn is_enabled_for(&self, device_id: &str, plugin: &str) -> bool {
+        self.is_enabled(plugin)
+            && !self
+                .device_disabled
+                .get(device_id)
+                .is_some_and(|plugins| plugins.contains(plugin))
+    }
+
+    pub(crate) fn status(&self, plugin: &str) -> PluginStatus {
+        let mut disabled_devices: Vec<String> = self
+            .device_disabled
+            .iter()
+            .filter(|(_, plugins)| plugins.contains(plugin))
+            .map(|(device_id, _)| device_id.clone())
+            .collect();
+        disabled_devices.sort();
+        PluginStatus {
+            id: plugin.to_string(),
+            enabled: self.is_enabled(plugin),
+            disabled_devices,
+        }
+    }
+}
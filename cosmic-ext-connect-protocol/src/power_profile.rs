@@ -0,0 +1,338 @@
+//! Power-Aware Cadence
+//!
+//! Discovery broadcasts and connection keepalives wake the radio every few
+//! seconds. On AC that is harmless; on battery it adds up. With the power
+//! profile enabled, [`PowerAwareCadence`] polls a [`PowerStateProvider`] and
+//! switches both intervals to a slower battery profile while the system runs
+//! on battery, restoring the configured cadence once it is plugged in again.
+//!
+//! The [`DiscoveryService`](crate::DiscoveryService) and
+//! [`ConnectionManager`](crate::ConnectionManager) follow the intervals through
+//! watch channels, so a switch takes effect on the next tick without
+//! restarting either service or dropping connections.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let cadence = Arc::new(PowerAwareCadence::new(
+//!     Arc::new(UPowerStateProvider::new()),
+//!     Cadence::from_configs(&config.discovery, &config.connection),
+//!     config.power.clone(),
+//! ));
+//! discovery.follow_broadcast_interval(cadence.watch_broadcast_interval());
+//! connections.follow_keep_alive(cadence.watch_keep_alive_interval());
+//! cadence.spawn();
+//! ```
+
+use crate::connection::ConnectionConfig;
+use crate::discovery::DiscoveryConfig;
+use crate::plugins::upower_backend::UPowerBackend;
+use crate::{ProtocolError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Default discovery broadcast interval on battery
+pub const DEFAULT_BATTERY_BROADCAST_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Default keepalive interval on battery
+pub const DEFAULT_BATTERY_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default interval between power source checks
+pub const DEFAULT_POWER_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Where the system draws its power from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerSource {
+    /// Mains power, or no battery at all
+    Ac,
+    /// Running on battery
+    Battery,
+}
+
+/// Source of the current power state
+#[async_trait]
+pub trait PowerStateProvider: Send + Sync {
+    /// Current power source
+    async fn power_source(&self) -> Result<PowerSource>;
+}
+
+/// Power state read from UPower on the system bus
+pub struct UPowerStateProvider {
+    backend: tokio::sync::Mutex<UPowerBackend>,
+}
+
+impl UPowerStateProvider {
+    /// Create a provider connecting to UPower on first use
+    pub fn new() -> Self {
+        Self {
+            backend: tokio::sync::Mutex::new(UPowerBackend::new()),
+        }
+    }
+}
+
+impl Default for UPowerStateProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl PowerStateProvider for UPowerStateProvider {
+    async fn power_source(&self) -> Result<PowerSource> {
+        let status = self
+            .backend
+            .lock()
+            .await
+            .get_power_status()
+            .await
+            .map_err(|e| ProtocolError::Plugin(format!("Failed to read power state: {}", e)))?;
+        Ok(if status.on_battery {
+            PowerSource::Battery
+        } else {
+            PowerSource::Ac
+        })
+    }
+}
+
+/// Discovery and keepalive intervals in effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cadence {
+    /// Time between discovery broadcasts
+    pub broadcast_interval: Duration,
+    /// Time between keepalive pings on each connection
+    pub keep_alive_interval: Duration,
+}
+
+impl Cadence {
+    /// Cadence configured for discovery and connections
+    pub fn from_configs(discovery: &DiscoveryConfig, connection: &ConnectionConfig) -> Self {
+        Self {
+            broadcast_interval: discovery.broadcast_interval,
+            keep_alive_interval: connection.keep_alive_interval,
+        }
+    }
+}
+
+/// Power profile settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerProfileConfig {
+    /// Slow down discovery and keepalives on battery
+    pub enabled: bool,
+    /// Discovery broadcast interval on battery
+    #[serde(with = "crate::config::duration_secs")]
+    pub battery_broadcast_interval: Duration,
+    /// Keepalive interval on battery
+    #[serde(with = "crate::config::duration_secs")]
+    pub battery_keep_alive_interval: Duration,
+    /// How often the power source is checked
+    #[serde(with = "crate::config::duration_secs")]
+    pub poll_interval: Duration,
+}
+
+impl Default for PowerProfileConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            battery_broadcast_interval: DEFAULT_BATTERY_BROADCAST_INTERVAL,
+            battery_keep_alive_interval: DEFAULT_BATTERY_KEEP_ALIVE_INTERVAL,
+            poll_interval: DEFAULT_POWER_POLL_INTERVAL,
+        }
+    }
+}
+
+impl PowerProfileConfig {
+    /// Cadence used on battery
+    pub fn battery_cadence(&self) -> Cadence {
+        Cadence {
+            broadcast_interval: self.battery_broadcast_interval,
+            keep_alive_interval: self.battery_keep_alive_interval,
+        }
+    }
+}
+
+/// Switches discovery and keepalive intervals with the power source
+pub struct PowerAwareCadence {
+    provider: Arc<dyn PowerStateProvider>,
+    ac: Cadence,
+    battery: Cadence,
+    poll_interval: Duration,
+    source: Mutex<PowerSource>,
+    broadcast_tx: watch::Sender<Duration>,
+    keep_alive_tx: watch::Sender<Duration>,
+    source_tx: watch::Sender<PowerSource>,
+}
+
+impl PowerAwareCadence {
+    /// Create a controller starting at the AC cadence
+    pub fn new(
+        provider: Arc<dyn PowerStateProvider>,
+        ac: Cadence,
+        config: PowerProfileConfig,
+    ) -> Self {
+        Self {
+            provider,
+            ac,
+            battery: config.battery_cadence(),
+            poll_interval: config.poll_interval,
+            source: Mutex::new(PowerSource::Ac),
+            broadcast_tx: watch::channel(ac.broadcast_interval).0,
+            keep_alive_tx: watch::channel(ac.keep_alive_interval).0,
+            source_tx: watch::channel(PowerSource::Ac).0,
+        }
+    }
+
+    /// Power source seen at the last refresh
+    pub fn source(&self) -> PowerSource {
+        self.source.lock().map(|s| *s).unwrap_or(PowerSource::Ac)
+    }
+
+    /// Cadence currently in effect
+    pub fn active(&self) -> Cadence {
+        match self.source() {
+            PowerSource::Ac => self.ac,
+            PowerSource::Battery => self.battery,
+        }
+    }
+
+    /// Follow the discovery broadcast interval
+    pub fn watch_broadcast_interval(&self) -> watch::Receiver<Duration> {
+        self.broadcast_tx.subscribe()
+    }
+
+    /// Follow the keepalive interval
+    pub fn watch_keep_alive_interval(&self) -> watch::Receiver<Duration> {
+        self.keep_alive_tx.subscribe()
+    }
+
+    /// Follow the power source, e.g. to skip optional work on battery
+    pub fn watch_power_source(&self) -> watch::Receiver<PowerSource> {
+        self.source_tx.subscribe()
+    }
+
+    /// Check the power source and switch cadence if it changed
+    ///
+    /// Returns the new cadence after a switch, `None` if the source is
+    /// unchanged.
+    pub async fn refresh(&self) -> Result<Option<Cadence>> {
+        let source = self.provider.power_source().await?;
+        {
+            let mut current = self
+                .source
+                .lock()
+                .map_err(|_| ProtocolError::InvalidState("power source lock poisoned".into()))?;
+            if *current == source {
+                return Ok(None);
+            }
+            *current = source;
+        }
+        let cadence = self.active();
+        info!(
+            "Power source is now {:?}: broadcasting every {:?}, keepalive every {:?}",
+            source, cadence.broadcast_interval, cadence.keep_alive_interval
+        );
+        self.broadcast_tx.send_replace(cadence.broadcast_interval);
+        self.keep_alive_tx.send_replace(cadence.keep_alive_interval);
+        self.source_tx.send_replace(source);
+        Ok(Some(cadence))
+    }
+
+    /// Poll the power source in the background
+    pub fn spawn(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut timer = tokio::time::interval(self.poll_interval);
+            loop {
+                timer.tick().await;
+                if let Err(e) = self.refresh().await {
+                    warn!("Keeping {:?} cadence: {}", self.source(), e);
+                }
+            }
+        })
+    }
+}
+
+/// Wait for the next interval published on `watch`
+///
+/// Never resolves without a watch or once its sender is gone, so it can sit
+/// in a `select!` next to the ticking timer.
+pub(crate) async fn interval_changed(watch: &mut Option<watch::Receiver<Duration>>) -> Duration {
+    if let Some(rx) = watch.as_mut() {
+        if rx.changed().await.is_ok() {
+            return *rx.borrow_and_update();
+        }
+        debug!("Interval source dropped, keeping the last interval");
+        *watch = None;
+    }
+    std::future::pending().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockPower(Mutex<PowerSource>);
+
+    impl MockPower {
+        fn set(&self, source: PowerSource) {
+            *self.0.lock().unwrap() = source;
+        }
+    }
+
+    #[async_trait]
+    impl PowerStateProvider for MockPower {
+        async fn power_source(&self) -> Result<PowerSource> {
+            Ok(*self.0.lock().unwrap())
+        }
+    }
+
+    fn ac_cadence() -> Cadence {
+        Cadence {
+            broadcast_interval: Duration::from_secs(5),
+            keep_alive_interval: Duration::from_secs(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cadence_follows_power_source() {
+        let power = Arc::new(MockPower(Mutex::new(PowerSource::Ac)));
+        let cadence =
+            PowerAwareCadence::new(power.clone(), ac_cadence(), PowerProfileConfig::default());
+        let broadcast = cadence.watch_broadcast_interval();
+        let keep_alive = cadence.watch_keep_alive_interval();
+
+        assert_eq!(cadence.refresh().await.unwrap(), None);
+        assert_eq!(*keep_alive.borrow(), Duration::from_secs(10));
+
+        power.set(PowerSource::Battery);
+        let battery = cadence.refresh().await.unwrap().unwrap();
+        assert_eq!(battery, PowerProfileConfig::default().battery_cadence());
+        assert_eq!(*broadcast.borrow(), DEFAULT_BATTERY_BROADCAST_INTERVAL);
+        assert_eq!(*keep_alive.borrow(), DEFAULT_BATTERY_KEEP_ALIVE_INTERVAL);
+        assert_eq!(cadence.refresh().await.unwrap(), None);
+
+        power.set(PowerSource::Ac);
+        assert_eq!(cadence.refresh().await.unwrap(), Some(ac_cadence()));
+        assert_eq!(*broadcast.borrow(), Duration::from_secs(5));
+        assert_eq!(cadence.source(), PowerSource::Ac);
+    }
+
+    #[tokio::test]
+    async fn test_interval_changed_outlives_sender() {
+        let (tx, rx) = watch::channel(Duration::from_secs(10));
+        let mut watch = Some(rx);
+
+        tx.send_replace(Duration::from_secs(30));
+        assert_eq!(interval_changed(&mut watch).await, Duration::from_secs(30));
+
+        drop(tx);
+        let waited =
+            tokio::time::timeout(Duration::from_millis(20), interval_changed(&mut watch)).await;
+        assert!(waited.is_err());
+        assert!(watch.is_none());
+    }
+}
@@ -0,0 +1,287 @@
+//! Payload Chunk Reassembly
+//!
+//! Writes payload chunks into the destination file by offset, so chunks that
+//! arrive out of order still land in the right place, and verifies on
+//! completion that every byte of the file was received.
+//!
+//! Over TCP the payload clients feed the stream through a [`ChunkAssembler`]
+//! with offsets taken from the stream position, which makes reassembly a
+//! cheap contiguity assertion. Transports that may reorder or drop chunks
+//! supply each chunk's own offset, and a missing chunk is reported by
+//! [`ChunkAssembler::finish`] instead of leaving a silently corrupted file.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let file = create_file_safe(&path).await?;
+//! let mut assembler = ChunkAssembler::new(file, size);
+//! for chunk in chunks {
+//!     assembler.write_chunk(chunk.offset, &chunk.data).await?;
+//! }
+//! assembler.finish().await?;
+//! ```
+
+use crate::fs_utils::write_file_safe;
+use crate::{ProtocolError, Result};
+use std::collections::BTreeMap;
+use std::io::SeekFrom;
+use std::ops::Range;
+use tokio::fs::File;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+/// A piece of payload data and where it belongs in the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadChunk {
+    /// Byte offset of the first byte of `data`
+    pub offset: u64,
+    /// Chunk contents
+    pub data: Vec<u8>,
+}
+
+impl PayloadChunk {
+    /// Create a chunk
+    pub fn new(offset: u64, data: Vec<u8>) -> Self {
+        Self { offset, data }
+    }
+
+    /// Offset one past the last byte of the chunk
+    pub fn end(&self) -> u64 {
+        self.offset + self.data.len() as u64
+    }
+}
+
+/// Writes chunks into a file by offset and tracks which bytes have arrived
+pub struct ChunkAssembler {
+    file: File,
+    expected_size: u64,
+    /// Received byte ranges, start -> end (exclusive), merged and disjoint
+    received: BTreeMap<u64, u64>,
+    /// Current file cursor, to skip seeks for in-order chunks
+    cursor: u64,
+}
+
+impl ChunkAssembler {
+    /// Reassemble `expected_size` bytes into `file`
+    ///
+    /// `file` should be empty and positioned at its start.
+    pub fn new(file: File, expected_size: u64) -> Self {
+        Self {
+            file,
+            expected_size,
+            received: BTreeMap::new(),
+            cursor: 0,
+        }
+    }
+
+    /// Continue reassembly into `file`, which already holds the first `offset` bytes
+    ///
+    /// `file` should be positioned at `offset`.
+    pub fn resume(file: File, expected_size: u64, offset: u64) -> Self {
+        let offset = offset.min(expected_size);
+        let mut assembler = Self::new(file, expected_size);
+        if offset > 0 {
+            assembler.received.insert(0, offset);
+        }
+        assembler.cursor = offset;
+        assembler
+    }
+
+    /// Total size the file will have once complete
+    pub fn expected_size(&self) -> u64 {
+        self.expected_size
+    }
+
+    /// Write a chunk at `offset`
+    ///
+    /// Chunks may arrive in any order, and a repeated chunk simply overwrites
+    /// the same bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidPacket`] if the chunk extends past the
+    /// expected size, or an I/O error if writing fails.
+    pub async fn write_chunk(&mut self, offset: u64, data: &[u8]) -> Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let end = offset
+            .checked_add(data.len() as u64)
+            .filter(|end| *end <= self.expected_size)
+            .ok_or_else(|| {
+                ProtocolError::InvalidPacket(format!(
+                    "Payload chunk at offset {} ({} bytes) exceeds file size {}",
+                    offset,
+                    data.len(),
+                    self.expected_size
+                ))
+            })?;
+
+        if offset != self.cursor {
+            self.file
+                .seek(SeekFrom::Start(offset))
+                .await
+                .map_err(|e| ProtocolError::from_io_error(e, "seeking payload chunk"))?;
+        }
+        write_file_safe(&mut self.file, data).await?;
+        self.cursor = end;
+        self.insert_range(offset, end);
+        Ok(())
+    }
+
+    /// Write a [`PayloadChunk`]
+    pub async fn write(&mut self, chunk: &PayloadChunk) -> Result<()> {
+        self.write_chunk(chunk.offset, &chunk.data).await
+    }
+
+    /// Number of distinct bytes received so far
+    pub fn received_bytes(&self) -> u64 {
+        self.received.iter().map(|(start, end)| end - start).sum()
+    }
+
+    /// Whether every byte of the file has been received
+    pub fn is_complete(&self) -> bool {
+        self.received_bytes() == self.expected_size
+    }
+
+    /// Byte ranges not yet received, in file order
+    pub fn missing_ranges(&self) -> Vec<Range<u64>> {
+        let mut missing = Vec::new();
+        let mut next = 0;
+        for (&start, &end) in &self.received {
+            if start > next {
+                missing.push(next..start);
+            }
+            next = end;
+        }
+        if next < self.expected_size {
+            missing.push(next..self.expected_size);
+        }
+        missing
+    }
+
+    /// Verify contiguity and flush the file
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidPacket`] naming the first gap if any
+    /// bytes are missing.
+    pub async fn finish(mut self) -> Result<File> {
+        let missing = self.missing_ranges();
+        if let Some(first) = missing.first() {
+            let missing_bytes: u64 = missing.iter().map(|range| range.end - range.start).sum();
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Payload incomplete: {} bytes missing in {} gap(s), first at {}..{}",
+                missing_bytes,
+                missing.len(),
+                first.start,
+                first.end
+            )));
+        }
+        self.file.flush().await.map_err(ProtocolError::Io)?;
+        Ok(self.file)
+    }
+
+    /// Record `start..end` as received, merging with touching ranges
+    fn insert_range(&mut self, mut start: u64, mut end: u64) {
+        if let Some((&prev_start, &prev_end)) = self.received.range(..=start).next_back() {
+            if prev_end >= start {
+                start = prev_start;
+                end = end.max(prev_end);
+            }
+        }
+        let overlapping: Vec<u64> = self
+            .received
+            .range(start..=end)
+            .map(|(&range_start, _)| range_start)
+            .collect();
+        for range_start in overlapping {
+            if let Some(range_end) = self.received.remove(&range_start) {
+                end = end.max(range_end);
+            }
+        }
+        self.received.insert(start, end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(data: &[u8], size: usize) -> Vec<PayloadChunk> {
+        data.chunks(size)
+            .enumerate()
+            .map(|(i, chunk)| PayloadChunk::new((i * size) as u64, chunk.to_vec()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_out_of_order_chunks_reassemble_by_offset() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.bin");
+        let data: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+
+        let mut pieces = chunks(&data, 64);
+        pieces.reverse();
+        pieces.swap(3, 9);
+        // A duplicate is harmless
+        let duplicate = pieces[5].clone();
+        pieces.push(duplicate);
+
+        let mut assembler = ChunkAssembler::new(File::create(&path).await.unwrap(), 1000);
+        for chunk in &pieces {
+            assembler.write(chunk).await.unwrap();
+        }
+        assert!(assembler.is_complete());
+        assembler.finish().await.unwrap();
+
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_missing_chunk_detected_at_completion() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.bin");
+        let data = vec![7u8; 500];
+
+        let mut assembler = ChunkAssembler::new(File::create(&path).await.unwrap(), 500);
+        for chunk in chunks(&data, 100).iter().filter(|c| c.offset != 200) {
+            assembler.write(chunk).await.unwrap();
+        }
+
+        assert!(!assembler.is_complete());
+        assert_eq!(assembler.received_bytes(), 400);
+        assert_eq!(assembler.missing_ranges(), vec![200..300]);
+        let result = assembler.finish().await;
+        assert!(matches!(result, Err(ProtocolError::InvalidPacket(_))));
+    }
+
+    #[tokio::test]
+    async fn test_chunk_past_end_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = File::create(dir.path().join("file.bin")).await.unwrap();
+        let mut assembler = ChunkAssembler::new(file, 10);
+
+        assert!(assembler.write_chunk(8, &[0; 4]).await.is_err());
+        assert!(assembler.write_chunk(u64::MAX, &[0]).await.is_err());
+        assert_eq!(assembler.received_bytes(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_resumed_file_only_needs_the_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.bin");
+        let data: Vec<u8> = (0..300u32).map(|i| (i % 251) as u8).collect();
+        // An earlier attempt wrote 140 bytes but only checkpointed 100
+        tokio::fs::write(&path, &data[..140]).await.unwrap();
+
+        let file = crate::fs_utils::open_partial_file(&path, 100)
+            .await
+            .unwrap();
+        let mut assembler = ChunkAssembler::resume(file, 300, 100);
+        assert_eq!(assembler.missing_ranges(), vec![100..300]);
+        assembler.write_chunk(100, &data[100..]).await.unwrap();
+        assembler.finish().await.unwrap();
+
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), data);
+    }
+}
@@ -5,8 +5,19 @@
 //! - Packet retry with limits
 //! - Transfer state tracking for resumption
 //! - State persistence for daemon crash recovery
+//!
+//! ## Encrypted Checkpoints
+//!
+//! Checkpoints contain destination paths, which can be sensitive on shared
+//! machines. [`RecoveryManager::with_encryption_secret`] derives a
+//! ChaCha20-Poly1305 key (HKDF-SHA256) from a device secret, such as the stored
+//! private key, and seals the checkpoint file with it. A checkpoint that fails to
+//! decrypt is discarded with a warning instead of failing initialization.
 
 use crate::{Packet, ProtocolError, Result};
+use ring::aead::{self, LessSafeKey, Nonce, UnboundKey, NONCE_LEN};
+use ring::hkdf;
+use ring::rand::{SecureRandom, SystemRandom};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -32,6 +43,18 @@ const MAX_PACKET_RETRIES: u32 = 3;
 #[allow(dead_code)]
 const PACKET_RETRY_DELAY: Duration = Duration::from_millis(500);
 
+/// Plaintext checkpoint file name
+const STATE_FILE_NAME: &str = "recovery_state.json";
+
+/// Encrypted checkpoint file name
+const ENCRYPTED_STATE_FILE_NAME: &str = "recovery_state.enc";
+
+/// Header identifying an encrypted checkpoint (format version 1)
+const ENCRYPTED_STATE_MAGIC: &[u8] = b"CCRS1";
+
+/// HKDF salt for checkpoint key derivation
+const CHECKPOINT_KEY_SALT: &[u8] = b"cconnect-recovery-checkpoint";
+
 /// Reconnection strategy with exponential backoff
 #[derive(Debug, Clone)]
 pub struct ReconnectionStrategy {
@@ -97,6 +120,19 @@ impl ReconnectionStrategy {
     }
 }
 
+/// Where a tracked file transfer stands
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    /// Still receiving, or interrupted and resumable
+    #[default]
+    InProgress,
+    /// Finished, but the file did not match the sender's checksum
+    ///
+    /// The received bytes were discarded; the transfer has to be sent again.
+    Corrupted,
+}
+
 /// State of an in-progress file transfer
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransferState {
@@ -116,6 +152,9 @@ pub struct TransferState {
     pub started_at: u64,
     /// Last update timestamp
     pub last_updated: u64,
+    /// Whether the transfer is still going or ended corrupted
+    #[serde(default)]
+    pub status: TransferStatus,
 }
 
 impl TransferState {
@@ -141,6 +180,7 @@ impl TransferState {
             bytes_received: 0,
             started_at: now,
             last_updated: now,
+            status: TransferStatus::InProgress,
         }
     }
 
@@ -190,21 +230,98 @@ pub struct RecoveryManager {
     retry_queue: Arc<RwLock<Vec<PacketRetryEntry>>>,
     /// Path to state persistence file
     state_file_path: PathBuf,
+    /// Path to the plaintext state file (read once for migration when encrypting)
+    legacy_state_file_path: Option<PathBuf>,
+    /// Key sealing the state file, if encryption is enabled
+    checkpoint_key: Option<LessSafeKey>,
 }
 
 impl RecoveryManager {
     /// Create a new recovery manager
     pub fn new(state_dir: impl AsRef<Path>) -> Self {
-        let state_file_path = state_dir.as_ref().join("recovery_state.json");
+        let state_file_path = state_dir.as_ref().join(STATE_FILE_NAME);
 
         Self {
             reconnection_strategies: Arc::new(RwLock::new(HashMap::new())),
             transfer_states: Arc::new(RwLock::new(HashMap::new())),
             retry_queue: Arc::new(RwLock::new(Vec::new())),
             state_file_path,
+            legacy_state_file_path: None,
+            checkpoint_key: None,
         }
     }
 
+    /// Encrypt the checkpoint file with a key derived from `secret`
+    ///
+    /// The same secret must be supplied on the next start to restore checkpoints.
+    /// An existing plaintext checkpoint is migrated and then removed.
+    pub fn with_encryption_secret(mut self, secret: &[u8]) -> Self {
+        let salt = hkdf::Salt::new(hkdf::HKDF_SHA256, CHECKPOINT_KEY_SALT);
+        let okm = salt
+            .extract(secret)
+            .expand(
+                &[ENCRYPTED_STATE_FILE_NAME.as_bytes()],
+                &aead::CHACHA20_POLY1305,
+            )
+            .expect("HKDF output length matches the AEAD key length");
+        let key = UnboundKey::from(okm);
+
+        let state_dir = self
+            .state_file_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        self.legacy_state_file_path = Some(std::mem::replace(
+            &mut self.state_file_path,
+            state_dir.join(ENCRYPTED_STATE_FILE_NAME),
+        ));
+        self.checkpoint_key = Some(LessSafeKey::new(key));
+        self
+    }
+
+    /// Check whether checkpoints are encrypted at rest
+    pub fn is_encrypted(&self) -> bool {
+        self.checkpoint_key.is_some()
+    }
+
+    /// Seal serialized checkpoint data (magic || nonce || ciphertext+tag)
+    fn seal_checkpoint(key: &LessSafeKey, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce_bytes).map_err(|_| {
+            ProtocolError::Io(std::io::Error::other("Failed to generate checkpoint nonce"))
+        })?;
+
+        let mut in_out = plaintext.to_vec();
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce_bytes),
+            aead::Aad::from(ENCRYPTED_STATE_MAGIC),
+            &mut in_out,
+        )
+        .map_err(|_| ProtocolError::Io(std::io::Error::other("Failed to encrypt checkpoint")))?;
+
+        let mut sealed = Vec::with_capacity(ENCRYPTED_STATE_MAGIC.len() + NONCE_LEN + in_out.len());
+        sealed.extend_from_slice(ENCRYPTED_STATE_MAGIC);
+        sealed.extend_from_slice(&nonce_bytes);
+        sealed.extend_from_slice(&in_out);
+        Ok(sealed)
+    }
+
+    /// Open sealed checkpoint data, returning `None` if it cannot be authenticated
+    fn open_checkpoint(key: &LessSafeKey, sealed: &[u8]) -> Option<Vec<u8>> {
+        let body = sealed.strip_prefix(ENCRYPTED_STATE_MAGIC)?;
+        if body.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce_bytes, ciphertext) = body.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce_bytes).ok()?;
+
+        let mut in_out = ciphertext.to_vec();
+        let plaintext = key
+            .open_in_place(nonce, aead::Aad::from(ENCRYPTED_STATE_MAGIC), &mut in_out)
+            .ok()?;
+        Some(plaintext.to_vec())
+    }
+
     /// Initialize recovery manager and restore state
     pub async fn init(&self) -> Result<()> {
         debug!("Initializing recovery manager");
@@ -315,6 +432,26 @@ impl RecoveryManager {
         Ok(())
     }
 
+    /// Record that a transfer ended with a checksum mismatch
+    ///
+    /// The checkpoint is kept with [`TransferStatus::Corrupted`] so the UI can
+    /// offer to retry it, but it no longer counts for
+    /// [`resume_offset`](Self::resume_offset).
+    pub async fn mark_corrupted(&self, transfer_id: &str) -> Result<()> {
+        let mut states = self.transfer_states.write().await;
+        if let Some(state) = states.get_mut(transfer_id) {
+            state.status = TransferStatus::Corrupted;
+            state.update_progress(0);
+            warn!("Transfer {} arrived corrupted", transfer_id);
+        }
+        drop(states);
+
+        // Persist state to disk
+        self.persist_transfer_states().await?;
+
+        Ok(())
+    }
+
     /// Get transfer state by ID
     pub async fn get_transfer_state(&self, transfer_id: &str) -> Option<TransferState> {
         let states = self.transfer_states.read().await;
@@ -331,6 +468,32 @@ impl RecoveryManager {
             .collect()
     }
 
+    /// Byte offset an interrupted transfer of `file_path` from `device_id` can resume at
+    ///
+    /// Checkpoints outlive reconnects and daemon restarts, so a file offered
+    /// again continues from the bytes already received. Only an unfinished
+    /// checkpoint for the same path and size counts; pass the offset to
+    /// [`PayloadClient::resume_from`](crate::payload::PayloadClient::resume_from).
+    pub async fn resume_offset(
+        &self,
+        device_id: &str,
+        file_path: &Path,
+        total_size: u64,
+    ) -> Option<u64> {
+        let states = self.transfer_states.read().await;
+        states
+            .values()
+            .filter(|s| {
+                s.device_id == device_id
+                    && s.file_path == file_path
+                    && s.total_size == total_size
+                    && s.status == TransferStatus::InProgress
+                    && !s.is_complete()
+            })
+            .map(|s| s.bytes_received)
+            .max()
+    }
+
     /// Queue a packet for retry
     pub async fn queue_packet_retry(&self, device_id: String, packet: Packet) {
         let entry = PacketRetryEntry {
@@ -385,6 +548,28 @@ impl RecoveryManager {
         debug!("Cleared retry queue for device {}", device_id);
     }
 
+    /// Drop every transfer checkpoint, queued retry and reconnection strategy for a device
+    pub async fn purge_device(&self, device_id: &str) -> Result<()> {
+        self.reconnection_strategies.write().await.remove(device_id);
+        self.clear_device_retry_queue(device_id).await;
+
+        let mut states = self.transfer_states.write().await;
+        let before = states.len();
+        states.retain(|_, state| state.device_id != device_id);
+        let removed = before - states.len();
+        drop(states);
+
+        if removed > 0 {
+            info!(
+                "Purged {} transfer states for device {}",
+                removed, device_id
+            );
+            self.persist_transfer_states().await?;
+        }
+
+        Ok(())
+    }
+
     /// Persist transfer states to disk
     async fn persist_transfer_states(&self) -> Result<()> {
         let states = self.transfer_states.read().await;
@@ -392,6 +577,10 @@ impl RecoveryManager {
 
         let json = serde_json::to_string_pretty(&states_vec)
             .map_err(|e| ProtocolError::Io(std::io::Error::other(e)))?;
+        let contents = match &self.checkpoint_key {
+            Some(key) => Self::seal_checkpoint(key, json.as_bytes())?,
+            None => json.into_bytes(),
+        };
 
         // Ensure parent directory exists
         if let Some(parent) = self.state_file_path.parent() {
@@ -403,15 +592,36 @@ impl RecoveryManager {
             })?;
         }
 
-        fs::write(&self.state_file_path, json).await.map_err(|e| {
-            ProtocolError::from_io_error(
-                e,
-                &format!(
-                    "writing recovery state file {}",
-                    self.state_file_path.display()
-                ),
-            )
-        })?;
+        fs::write(&self.state_file_path, contents)
+            .await
+            .map_err(|e| {
+                ProtocolError::from_io_error(
+                    e,
+                    &format!(
+                        "writing recovery state file {}",
+                        self.state_file_path.display()
+                    ),
+                )
+            })?;
+
+        if self.checkpoint_key.is_some() {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let _ = fs::set_permissions(
+                    &self.state_file_path,
+                    std::fs::Permissions::from_mode(0o600),
+                )
+                .await;
+            }
+
+            // Don't leave a readable plaintext copy behind
+            if let Some(legacy) = &self.legacy_state_file_path {
+                if legacy.exists() {
+                    let _ = fs::remove_file(legacy).await;
+                }
+            }
+        }
 
         debug!("Persisted {} transfer states to disk", states_vec.len());
         Ok(())
@@ -419,25 +629,40 @@ impl RecoveryManager {
 
     /// Restore transfer states from disk
     async fn restore_transfer_states(&self) -> Result<()> {
-        if !self.state_file_path.exists() {
+        let (path, key) = if self.state_file_path.exists() {
+            (&self.state_file_path, self.checkpoint_key.as_ref())
+        } else if let Some(legacy) = self.legacy_state_file_path.as_ref().filter(|p| p.exists()) {
+            info!("Migrating plaintext recovery state to encrypted storage");
+            (legacy, None)
+        } else {
             debug!("No recovery state file found, starting fresh");
             return Ok(());
-        }
+        };
 
-        let json = fs::read_to_string(&self.state_file_path)
-            .await
-            .map_err(|e| {
-                ProtocolError::from_io_error(
-                    e,
-                    &format!(
-                        "reading recovery state file {}",
-                        self.state_file_path.display()
-                    ),
-                )
-            })?;
+        let contents = fs::read(path).await.map_err(|e| {
+            ProtocolError::from_io_error(
+                e,
+                &format!("reading recovery state file {}", path.display()),
+            )
+        })?;
 
-        let states_vec: Vec<TransferState> =
-            serde_json::from_str(&json).map_err(|e| ProtocolError::Io(std::io::Error::other(e)))?;
+        let json = match key {
+            Some(key) => match Self::open_checkpoint(key, &contents) {
+                Some(plaintext) => plaintext,
+                None => {
+                    warn!(
+                        "Discarding recovery state {}: cannot be decrypted with this device's key",
+                        path.display()
+                    );
+                    let _ = fs::remove_file(path).await;
+                    return Ok(());
+                }
+            },
+            None => contents,
+        };
+
+        let states_vec: Vec<TransferState> = serde_json::from_slice(&json)
+            .map_err(|e| ProtocolError::Io(std::io::Error::other(e)))?;
 
         let mut states = self.transfer_states.write().await;
         for state in states_vec {
@@ -476,6 +701,17 @@ impl RecoveryManager {
     }
 }
 
+#[async_trait::async_trait]
+impl crate::DeviceStateStore for RecoveryManager {
+    fn name(&self) -> &str {
+        "transfer history"
+    }
+
+    async fn purge_device(&self, device_id: &str) -> Result<()> {
+        RecoveryManager::purge_device(self, device_id).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -583,6 +819,64 @@ mod tests {
         assert!(manager.get_transfer_state("transfer-1").await.is_none());
     }
 
+    #[tokio::test]
+    async fn test_reset_after_exhausted_reconnects_starts_fresh() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path());
+
+        while manager.should_reconnect("device-1").await.is_some() {}
+        assert!(manager.should_reconnect("device-1").await.is_none());
+
+        // A successful connection allows reconnects again, from the base delay
+        manager.reset_reconnection_strategy("device-1").await;
+        assert_eq!(
+            manager.should_reconnect("device-1").await,
+            Some(INITIAL_RECONNECT_DELAY)
+        );
+        assert_eq!(
+            manager.should_reconnect("device-1").await,
+            Some(INITIAL_RECONNECT_DELAY * 2)
+        );
+        let strategy = manager.get_reconnection_strategy("device-1").await;
+        assert_eq!(strategy.attempt, 2);
+    }
+
+    #[tokio::test]
+    async fn test_purge_device_removes_only_its_state() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path());
+        manager.init().await.unwrap();
+
+        for (transfer_id, device_id) in [("t1", "device-1"), ("t2", "device-2")] {
+            let state = TransferState::new(
+                transfer_id.to_string(),
+                device_id.to_string(),
+                "test.txt".to_string(),
+                PathBuf::from("/tmp/test.txt"),
+                1000,
+            );
+            manager.register_transfer(state).await.unwrap();
+        }
+        manager
+            .queue_packet_retry(
+                "device-1".to_string(),
+                Packet::new("cconnect.ping", serde_json::json!({})),
+            )
+            .await;
+
+        manager.purge_device("device-1").await.unwrap();
+
+        assert!(manager.get_device_transfers("device-1").await.is_empty());
+        assert_eq!(manager.get_device_transfers("device-2").await.len(), 1);
+        assert!(manager.process_retry_queue().await.is_empty());
+
+        // Purged state stays gone after a restart
+        let restored = RecoveryManager::new(temp_dir.path());
+        restored.init().await.unwrap();
+        assert!(restored.get_device_transfers("device-1").await.is_empty());
+        assert_eq!(restored.get_device_transfers("device-2").await.len(), 1);
+    }
+
     #[tokio::test]
     async fn test_recovery_manager_packet_retry() {
         let temp_dir = TempDir::new().unwrap();
@@ -631,4 +925,151 @@ mod tests {
             assert_eq!(restored.unwrap().filename, "test.txt");
         }
     }
+
+    #[tokio::test]
+    async fn test_resume_offset_survives_restart() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = PathBuf::from("/tmp/backup.tar");
+        let total = 2 * 1024 * 1024 * 1024;
+
+        {
+            let manager = RecoveryManager::new(temp_dir.path());
+            let state = TransferState::new(
+                "transfer-1".to_string(),
+                "device-1".to_string(),
+                "backup.tar".to_string(),
+                path.clone(),
+                total,
+            );
+            manager.register_transfer(state).await.unwrap();
+            manager
+                .update_transfer_progress("transfer-1", 1_500_000_000)
+                .await
+                .unwrap();
+        }
+
+        let manager = RecoveryManager::new(temp_dir.path());
+        manager.init().await.unwrap();
+        assert_eq!(
+            manager.resume_offset("device-1", &path, total).await,
+            Some(1_500_000_000)
+        );
+        // A different device or a changed file starts over
+        assert_eq!(manager.resume_offset("device-2", &path, total).await, None);
+        assert_eq!(manager.resume_offset("device-1", &path, 1000).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_transfer_is_terminal() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = PathBuf::from("/tmp/photo.jpg");
+
+        {
+            let manager = RecoveryManager::new(temp_dir.path());
+            let state = TransferState::new(
+                "transfer-1".to_string(),
+                "device-1".to_string(),
+                "photo.jpg".to_string(),
+                path.clone(),
+                1000,
+            );
+            manager.register_transfer(state).await.unwrap();
+            manager
+                .update_transfer_progress("transfer-1", 600)
+                .await
+                .unwrap();
+            manager.mark_corrupted("transfer-1").await.unwrap();
+        }
+
+        let manager = RecoveryManager::new(temp_dir.path());
+        manager.init().await.unwrap();
+        let state = manager.get_transfer_state("transfer-1").await.unwrap();
+        assert_eq!(state.status, TransferStatus::Corrupted);
+        assert_eq!(manager.resume_offset("device-1", &path, 1000).await, None);
+    }
+
+    fn sample_transfer() -> TransferState {
+        TransferState::new(
+            "transfer-1".to_string(),
+            "device-1".to_string(),
+            "secret-plans.pdf".to_string(),
+            PathBuf::from("/home/alice/Private/secret-plans.pdf"),
+            1000,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_checkpoint_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+
+        {
+            let manager =
+                RecoveryManager::new(temp_dir.path()).with_encryption_secret(b"device-key");
+            assert!(manager.is_encrypted());
+            manager.register_transfer(sample_transfer()).await.unwrap();
+        }
+
+        // Destination path must not be readable on disk
+        let on_disk = std::fs::read(temp_dir.path().join(ENCRYPTED_STATE_FILE_NAME)).unwrap();
+        assert!(on_disk.starts_with(ENCRYPTED_STATE_MAGIC));
+        assert!(!String::from_utf8_lossy(&on_disk).contains("secret-plans"));
+        assert!(!temp_dir.path().join(STATE_FILE_NAME).exists());
+
+        let manager = RecoveryManager::new(temp_dir.path()).with_encryption_secret(b"device-key");
+        manager.init().await.unwrap();
+
+        let restored = manager.get_transfer_state("transfer-1").await.unwrap();
+        assert_eq!(
+            restored.file_path,
+            PathBuf::from("/home/alice/Private/secret-plans.pdf")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_undecryptable_checkpoint_is_discarded() {
+        let temp_dir = TempDir::new().unwrap();
+
+        {
+            let manager =
+                RecoveryManager::new(temp_dir.path()).with_encryption_secret(b"device-key");
+            manager.register_transfer(sample_transfer()).await.unwrap();
+        }
+
+        // Wrong key
+        let manager = RecoveryManager::new(temp_dir.path()).with_encryption_secret(b"other-key");
+        manager.init().await.unwrap();
+        assert!(manager.get_transfer_state("transfer-1").await.is_none());
+        assert!(!temp_dir.path().join(ENCRYPTED_STATE_FILE_NAME).exists());
+
+        // Corrupted file
+        std::fs::write(
+            temp_dir.path().join(ENCRYPTED_STATE_FILE_NAME),
+            b"CCRS1 definitely not ciphertext",
+        )
+        .unwrap();
+        let manager = RecoveryManager::new(temp_dir.path()).with_encryption_secret(b"device-key");
+        manager.init().await.unwrap();
+        assert!(manager.get_transfer_state("transfer-1").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_checkpoint_migrated_to_encrypted() {
+        let temp_dir = TempDir::new().unwrap();
+
+        {
+            let manager = RecoveryManager::new(temp_dir.path());
+            manager.register_transfer(sample_transfer()).await.unwrap();
+        }
+
+        let manager = RecoveryManager::new(temp_dir.path()).with_encryption_secret(b"device-key");
+        manager.init().await.unwrap();
+        assert!(manager.get_transfer_state("transfer-1").await.is_some());
+
+        manager
+            .update_transfer_progress("transfer-1", 10)
+            .await
+            .unwrap();
+        assert!(!temp_dir.path().join(STATE_FILE_NAME).exists());
+        assert!(temp_dir.path().join(ENCRYPTED_STATE_FILE_NAME).exists());
+    }
 }
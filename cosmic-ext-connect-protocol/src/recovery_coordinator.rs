@@ -5,7 +5,6 @@
 //! listening for connection failures and triggering appropriate recovery actions.
 
 use crate::{ConnectionEvent, ConnectionManager, DeviceManager, RecoveryManager, Result};
-use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::time::sleep;
@@ -119,8 +118,9 @@ impl RecoveryCoordinator {
                                     );
 
                                     // Parse socket address
-                                    let addr_str = format!("{}:{}", host, port);
-                                    if let Ok(addr) = addr_str.parse::<SocketAddr>() {
+                                    if let Some(addr) =
+                                        crate::transport::tcp::parse_host_addr(&host, port)
+                                    {
                                         // Attempt reconnection
                                         match connection_manager_clone
                                             .connect(&device_id_clone, addr)
@@ -243,6 +243,9 @@ mod tests {
             incoming_capabilities: vec![],
             outgoing_capabilities: vec![],
             tcp_port: 1814,
+            persistent_id: None,
+            addresses: Vec::new(),
+            payload_streams: 1,
         };
 
         // Create managers
@@ -2,13 +2,47 @@
 //!
 //! Provides resource management to prevent exhaustion and ensure system stability.
 //! Manages connection limits, memory pressure, concurrent transfers, and quotas.
-
+//!
+//! ## Transfer Ordering
+//!
+//! Transfers to one device share its connection, so running two at once only
+//! interleaves them and slows both. [`ResourceManager::acquire_transfer_slot`]
+//! therefore runs a device's transfers one at a time, in the order they asked.
+//! Devices listed in [`ResourceConfig::parallel_transfer_devices`] (or enabled
+//! with [`ResourceManager::set_parallel_transfers`]) run them concurrently, but
+//! only over a transport whose [`TransportCapabilities::multiplexed`] is set.
+//! File payloads always travel over their own TCP connection, so senders and
+//! receivers of files use [`ResourceManager::acquire_payload_slot`]. Call
+//! [`ResourceManager::forget_device`] when a device disconnects or is
+//! unpaired to drop its queue.
+//!
+//! ## Upload Bandwidth
+//!
+//! [`ResourceConfig::max_bytes_per_sec`] caps the bytes sent per second by all
+//! active transfers together. Every registered [`TransferInfo`] carries a
+//! [`BandwidthLimit`]; the manager gives each an equal share of the budget and
+//! rebalances whenever a transfer starts or ends, or the budget is changed
+//! with [`ResourceManager::set_max_bytes_per_sec`]. Pass the transfer's limit
+//! to [`PayloadServer::with_bandwidth_limit`](crate::PayloadServer::with_bandwidth_limit)
+//! so its writes follow the changes live.
+//!
+//! ## Transfer Throughput
+//!
+//! Each [`TransferInfo`] keeps an exponentially weighted moving average of
+//! its rate, updated on every [`ResourceManager::update_transfer_progress`].
+//! Instantaneous rates jump around with chunk timing and scheduler noise;
+//! the average settles within a few [`RATE_SMOOTHING`] windows. Read it with
+//! [`ResourceManager::transfer_stats`] rather than diffing byte counts in the
+//! UI.
+
+use crate::bandwidth::BandwidthLimit;
+use crate::transport::TransportCapabilities;
 use crate::{ProtocolError, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
 use tracing::{debug, info, warn};
 
 /// Maximum number of concurrent connections to a single device
@@ -38,6 +72,7 @@ const MAX_PACKET_QUEUE_SIZE: usize = 100;
 
 /// Resource management configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ResourceConfig {
     /// Maximum connections per device
     pub max_connections_per_device: usize,
@@ -55,6 +90,10 @@ pub struct ResourceConfig {
     pub memory_pressure_threshold: u64,
     /// Maximum packet queue size per device
     pub max_packet_queue_size: usize,
+    /// Devices whose transfers may run in parallel over a multiplexed transport
+    pub parallel_transfer_devices: HashSet<String>,
+    /// Upload budget shared by all active transfers (None = unlimited)
+    pub max_bytes_per_sec: Option<u64>,
 }
 
 impl Default for ResourceConfig {
@@ -68,10 +107,64 @@ impl Default for ResourceConfig {
             max_total_transfer_size: MAX_TOTAL_TRANSFER_SIZE,
             memory_pressure_threshold: MEMORY_PRESSURE_THRESHOLD,
             max_packet_queue_size: MAX_PACKET_QUEUE_SIZE,
+            parallel_transfer_devices: HashSet::new(),
+            max_bytes_per_sec: None,
         }
     }
 }
 
+/// Time constant of the smoothed transfer rate
+///
+/// A sample this old has about a third of the weight of a fresh one.
+pub const RATE_SMOOTHING: Duration = Duration::from_secs(2);
+
+/// Rate samples taken from successive progress updates
+#[derive(Debug, Clone, Default)]
+struct RateTracker {
+    /// When and at how many bytes the last sample was taken
+    last: Option<(Instant, u64)>,
+    /// Rate between the last two samples (bytes/sec)
+    current: f64,
+    /// Moving average of the rate (bytes/sec), None before the first interval
+    smoothed: Option<f64>,
+}
+
+impl RateTracker {
+    fn record(&mut self, bytes: u64, now: Instant) {
+        let Some((then, previous)) = self.last else {
+            self.last = Some((now, bytes));
+            return;
+        };
+        let elapsed = now.duration_since(then).as_secs_f64();
+        if elapsed <= 0.0 {
+            // Fold same-instant updates into the next interval
+            return;
+        }
+        self.current = bytes.saturating_sub(previous) as f64 / elapsed;
+        let weight = 1.0 - (-elapsed / RATE_SMOOTHING.as_secs_f64()).exp();
+        self.smoothed = Some(match self.smoothed {
+            Some(smoothed) => smoothed + weight * (self.current - smoothed),
+            None => self.current,
+        });
+        self.last = Some((now, bytes));
+    }
+}
+
+/// Snapshot of a transfer's progress and rate
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferStats {
+    /// Rate over the last progress interval (bytes/sec)
+    pub current_bps: f64,
+    /// Moving average of the rate (bytes/sec)
+    pub smoothed_bps: f64,
+    /// Bytes transferred so far
+    pub bytes_transferred: u64,
+    /// File size in bytes
+    pub size: u64,
+    /// Estimated time left at the smoothed rate
+    pub eta: Option<Duration>,
+}
+
 /// Active connection tracking
 #[derive(Debug, Clone)]
 struct ConnectionInfo {
@@ -97,6 +190,10 @@ pub struct TransferInfo {
     pub started_at: u64,
     /// Bytes transferred so far
     pub bytes_transferred: u64,
+    /// This transfer's share of the upload budget
+    pub bandwidth: BandwidthLimit,
+    /// Rate samples behind [`Self::throughput_bps`]
+    rate: RateTracker,
 }
 
 impl TransferInfo {
@@ -113,12 +210,52 @@ impl TransferInfo {
             size,
             started_at: now,
             bytes_transferred: 0,
+            bandwidth: BandwidthLimit::unlimited(),
+            rate: RateTracker::default(),
         }
     }
 
     /// Update transfer progress
     pub fn update_progress(&mut self, bytes: u64) {
+        self.record_progress(bytes, Instant::now());
+    }
+
+    fn record_progress(&mut self, bytes: u64, now: Instant) {
         self.bytes_transferred = bytes;
+        self.rate.record(bytes, now);
+    }
+
+    /// Smoothed transfer rate in bytes per second
+    ///
+    /// Zero until two progress updates have been seen.
+    pub fn throughput_bps(&self) -> f64 {
+        self.rate.smoothed.unwrap_or(0.0)
+    }
+
+    /// Estimated time left at the smoothed rate
+    ///
+    /// `None` while the rate is still unknown or has dropped to zero.
+    pub fn eta(&self) -> Option<Duration> {
+        let remaining = self.size.saturating_sub(self.bytes_transferred);
+        if remaining == 0 {
+            return Some(Duration::ZERO);
+        }
+        let rate = self.throughput_bps();
+        if rate < 1.0 {
+            return None;
+        }
+        Duration::try_from_secs_f64(remaining as f64 / rate).ok()
+    }
+
+    /// Current progress and rate
+    pub fn stats(&self) -> TransferStats {
+        TransferStats {
+            current_bps: self.rate.current,
+            smoothed_bps: self.throughput_bps(),
+            bytes_transferred: self.bytes_transferred,
+            size: self.size,
+            eta: self.eta(),
+        }
     }
 
     /// Check if transfer is complete
@@ -135,6 +272,15 @@ impl TransferInfo {
     }
 }
 
+/// Permission for one transfer to a device to run
+///
+/// Held for the duration of the transfer; dropping it lets the device's next
+/// queued transfer start.
+#[derive(Debug)]
+pub struct TransferSlot {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
 /// Memory usage statistics
 #[derive(Debug, Clone, Default)]
 pub struct MemoryStats {
@@ -170,17 +316,28 @@ pub struct ResourceManager {
     queue_sizes: Arc<RwLock<HashMap<String, usize>>>,
     /// Memory usage statistics
     memory_stats: Arc<RwLock<MemoryStats>>,
+    /// Per-device transfer queues (device_id -> single permit)
+    transfer_queues: Arc<RwLock<HashMap<String, Arc<Semaphore>>>>,
+    /// Devices allowed to run transfers in parallel
+    parallel_devices: Arc<RwLock<HashSet<String>>>,
+    /// Upload budget split across active transfers
+    max_bytes_per_sec: Arc<RwLock<Option<u64>>>,
 }
 
 impl ResourceManager {
     /// Create a new resource manager
     pub fn new(config: ResourceConfig) -> Self {
+        let parallel_devices = config.parallel_transfer_devices.clone();
+        let max_bytes_per_sec = config.max_bytes_per_sec;
         Self {
             config,
             connections: Arc::new(RwLock::new(HashMap::new())),
             transfers: Arc::new(RwLock::new(HashMap::new())),
             queue_sizes: Arc::new(RwLock::new(HashMap::new())),
             memory_stats: Arc::new(RwLock::new(MemoryStats::default())),
+            transfer_queues: Arc::new(RwLock::new(HashMap::new())),
+            parallel_devices: Arc::new(RwLock::new(parallel_devices)),
+            max_bytes_per_sec: Arc::new(RwLock::new(max_bytes_per_sec)),
         }
     }
 
@@ -332,7 +489,24 @@ impl ResourceManager {
     pub async fn register_transfer(&self, transfer_info: TransferInfo) -> Result<()> {
         self.can_start_transfer(&transfer_info.device_id, transfer_info.size)
             .await?;
+        self.insert_transfer(transfer_info).await;
+        Ok(())
+    }
 
+    /// Register a file being sent, for its share of the upload budget
+    ///
+    /// Unlike [`register_transfer`](Self::register_transfer) no size or count
+    /// limits are checked: outgoing files are streamed from disk and already
+    /// wait for [`acquire_payload_slot`](Self::acquire_payload_slot). Returns
+    /// the transfer's limit; call [`unregister_transfer`](Self::unregister_transfer)
+    /// once the upload ends.
+    pub async fn register_upload(&self, transfer_info: TransferInfo) -> BandwidthLimit {
+        let bandwidth = transfer_info.bandwidth.clone();
+        self.insert_transfer(transfer_info).await;
+        bandwidth
+    }
+
+    async fn insert_transfer(&self, transfer_info: TransferInfo) {
         let transfer_id = transfer_info.transfer_id.clone();
         let device_id = transfer_info.device_id.clone();
         let size = transfer_info.size;
@@ -340,6 +514,7 @@ impl ResourceManager {
         let mut transfers = self.transfers.write().await;
         transfers.insert(transfer_id.clone(), transfer_info);
         drop(transfers);
+        self.rebalance_bandwidth().await;
 
         // Update memory stats
         let mut stats = self.memory_stats.write().await;
@@ -357,8 +532,6 @@ impl ResourceManager {
 
         // Check for memory pressure
         self.check_memory_pressure().await;
-
-        Ok(())
     }
 
     /// Unregister a file transfer
@@ -368,6 +541,7 @@ impl ResourceManager {
             let size = info.size;
             let device_id = info.device_id.clone();
             drop(transfers);
+            self.rebalance_bandwidth().await;
 
             // Update memory stats
             let mut stats = self.memory_stats.write().await;
@@ -392,6 +566,12 @@ impl ResourceManager {
         }
     }
 
+    /// Progress and rate of one active transfer
+    pub async fn transfer_stats(&self, transfer_id: &str) -> Option<TransferStats> {
+        let transfers = self.transfers.read().await;
+        transfers.get(transfer_id).map(TransferInfo::stats)
+    }
+
     /// Get transfer count
     pub async fn get_transfer_count(&self) -> usize {
         self.transfers.read().await.len()
@@ -411,6 +591,106 @@ impl ResourceManager {
         self.transfers.read().await.values().cloned().collect()
     }
 
+    /// Change the upload budget shared by all active transfers
+    ///
+    /// Running transfers pick up their new share on their next write.
+    /// `None` removes the limit.
+    pub async fn set_max_bytes_per_sec(&self, max_bytes_per_sec: Option<u64>) {
+        *self.max_bytes_per_sec.write().await = max_bytes_per_sec;
+        self.rebalance_bandwidth().await;
+        info!(
+            "Upload bandwidth limit set to {:?} bytes/s",
+            max_bytes_per_sec
+        );
+    }
+
+    /// Current upload budget shared by all active transfers
+    pub async fn max_bytes_per_sec(&self) -> Option<u64> {
+        *self.max_bytes_per_sec.read().await
+    }
+
+    /// Give every active transfer an equal share of the upload budget
+    async fn rebalance_bandwidth(&self) {
+        let budget = *self.max_bytes_per_sec.read().await;
+        let transfers = self.transfers.read().await;
+        let share = budget.map(|total| (total / transfers.len().max(1) as u64).max(1));
+        for info in transfers.values() {
+            info.bandwidth.set_max_bytes_per_sec(share);
+        }
+    }
+
+    /// Allow or forbid parallel transfers to a device
+    ///
+    /// Only takes effect over multiplexed transports; see the module
+    /// documentation.
+    pub async fn set_parallel_transfers(&self, device_id: &str, enabled: bool) {
+        let mut devices = self.parallel_devices.write().await;
+        if enabled {
+            devices.insert(device_id.to_string());
+        } else {
+            devices.remove(device_id);
+        }
+    }
+
+    /// Whether a device is allowed to run transfers in parallel
+    pub async fn allows_parallel_transfers(&self, device_id: &str) -> bool {
+        self.parallel_devices.read().await.contains(device_id)
+    }
+
+    /// Wait for this device's turn to transfer over `transport`
+    ///
+    /// Transfers are serialized per device in FIFO order unless the device
+    /// allows parallel transfers and `transport` is multiplexed, in which case
+    /// the slot is granted immediately. Limits are still enforced separately
+    /// by [`register_transfer`](Self::register_transfer).
+    pub async fn acquire_transfer_slot(
+        &self,
+        device_id: &str,
+        transport: &TransportCapabilities,
+    ) -> TransferSlot {
+        if transport.multiplexed && self.allows_parallel_transfers(device_id).await {
+            return TransferSlot { _permit: None };
+        }
+
+        let queue = self
+            .transfer_queues
+            .write()
+            .await
+            .entry(device_id.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(1)))
+            .clone();
+
+        // Tokio's semaphore hands out permits in request order, which makes the
+        // queue FIFO; it is never closed, so acquiring cannot fail
+        let permit = queue.acquire_owned().await.ok();
+        debug!("Transfer slot granted for device {}", device_id);
+        TransferSlot { _permit: permit }
+    }
+
+    /// Wait for this device's turn to send or receive a file payload
+    ///
+    /// Payloads get a TCP connection of their own whatever transport carries
+    /// the packets, and TCP is not multiplexed.
+    pub async fn acquire_payload_slot(&self, device_id: &str) -> TransferSlot {
+        self.acquire_transfer_slot(device_id, &crate::transport::tcp::TCP_CAPABILITIES)
+            .await
+    }
+
+    /// Drop a device's transfer queue
+    ///
+    /// For devices that disconnected or were unpaired. A queue that is still
+    /// held or waited on is kept, so the transfers in it stay ordered.
+    pub async fn forget_device(&self, device_id: &str) {
+        let mut queues = self.transfer_queues.write().await;
+        // Every slot holder and waiter owns a reference to the queue
+        if queues
+            .get(device_id)
+            .is_some_and(|queue| Arc::strong_count(queue) == 1)
+        {
+            queues.remove(device_id);
+        }
+    }
+
     /// Check if packet queue can accept more packets
     pub async fn can_queue_packet(&self, device_id: &str) -> Result<()> {
         let queue_sizes = self.queue_sizes.read().await;
@@ -538,6 +818,8 @@ impl ResourceManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
 
     #[tokio::test]
     async fn test_connection_limits() {
@@ -661,4 +943,208 @@ mod tests {
         assert_eq!(transfers[0].bytes_transferred, 500);
         assert_eq!(transfers[0].progress_percentage(), 50.0);
     }
+
+    #[test]
+    fn test_throughput_smooths_rate_spikes() {
+        let mut transfer = TransferInfo::new("t1".to_string(), "device-1".to_string(), 20_000_000);
+        let start = Instant::now();
+        assert_eq!(transfer.throughput_bps(), 0.0);
+        assert_eq!(transfer.eta(), None);
+
+        // Steady 1 MB/s for ten seconds
+        for second in 0..=10 {
+            transfer.record_progress(second * 1_000_000, start + Duration::from_secs(second));
+        }
+        assert!((transfer.throughput_bps() - 1_000_000.0).abs() < 1.0);
+
+        // One fast burst moves the instantaneous rate far more than the average
+        transfer.record_progress(
+            14_000_000,
+            start + Duration::from_secs(10) + Duration::from_millis(500),
+        );
+        let stats = transfer.stats();
+        assert_eq!(stats.current_bps, 8_000_000.0);
+        assert!(stats.smoothed_bps > 1_000_000.0 && stats.smoothed_bps < 3_000_000.0);
+
+        let eta = stats.eta.unwrap();
+        let expected = 6_000_000.0 / stats.smoothed_bps;
+        assert!((eta.as_secs_f64() - expected).abs() < 0.01);
+
+        transfer.record_progress(20_000_000, start + Duration::from_secs(12));
+        assert_eq!(transfer.eta(), Some(Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn test_transfer_stats() {
+        let manager = ResourceManager::new(ResourceConfig::default());
+        assert_eq!(manager.transfer_stats("t1").await, None);
+
+        let transfer = TransferInfo::new("t1".to_string(), "device-1".to_string(), 1000);
+        manager.register_transfer(transfer).await.unwrap();
+        manager.update_transfer_progress("t1", 100).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        manager.update_transfer_progress("t1", 600).await;
+
+        let stats = manager.transfer_stats("t1").await.unwrap();
+        assert_eq!(stats.bytes_transferred, 600);
+        assert_eq!(stats.size, 1000);
+        assert!(stats.smoothed_bps > 0.0);
+        assert_eq!(stats.smoothed_bps, stats.current_bps);
+        assert!(stats.eta.is_some());
+    }
+
+    const SINGLE_STREAM: TransportCapabilities = TransportCapabilities {
+        max_packet_size: 1024,
+        reliable: true,
+        connection_oriented: true,
+        latency: crate::transport::LatencyCategory::Low,
+        multiplexed: false,
+        msgpack: false,
+        payload_streams: 1,
+    };
+
+    const MULTIPLEXED: TransportCapabilities = TransportCapabilities {
+        multiplexed: true,
+        ..SINGLE_STREAM
+    };
+
+    /// Run two transfers to one device and return how many overlapped
+    async fn peak_concurrency(
+        manager: &Arc<ResourceManager>,
+        transport: TransportCapabilities,
+    ) -> usize {
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let tasks: Vec<_> = (0..2)
+            .map(|_| {
+                let (manager, active, peak) = (manager.clone(), active.clone(), peak.clone());
+                tokio::spawn(async move {
+                    let _slot = manager.acquire_transfer_slot("phone", &transport).await;
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    active.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        peak.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn test_transfers_to_one_device_run_sequentially_in_order() {
+        let manager = Arc::new(ResourceManager::new(ResourceConfig::default()));
+        assert_eq!(peak_concurrency(&manager, SINGLE_STREAM).await, 1);
+
+        // Queued transfers start in the order they asked
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let first = manager.acquire_transfer_slot("phone", &SINGLE_STREAM).await;
+        let mut waiting = Vec::new();
+        for name in ["second", "third"] {
+            let (manager, order) = (manager.clone(), order.clone());
+            waiting.push(tokio::spawn(async move {
+                let _slot = manager.acquire_transfer_slot("phone", &SINGLE_STREAM).await;
+                order.lock().unwrap().push(name);
+            }));
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(order.lock().unwrap().is_empty());
+
+        // Other devices are not held up
+        drop(
+            manager
+                .acquire_transfer_slot("laptop", &SINGLE_STREAM)
+                .await,
+        );
+
+        drop(first);
+        for task in waiting {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec!["second", "third"]);
+    }
+
+    #[tokio::test]
+    async fn test_parallel_transfers_need_multiplexed_transport() {
+        let config = ResourceConfig {
+            parallel_transfer_devices: ["phone".to_string()].into_iter().collect(),
+            ..Default::default()
+        };
+        let manager = Arc::new(ResourceManager::new(config));
+
+        assert_eq!(peak_concurrency(&manager, MULTIPLEXED).await, 2);
+        assert_eq!(peak_concurrency(&manager, SINGLE_STREAM).await, 1);
+
+        manager.set_parallel_transfers("phone", false).await;
+        assert_eq!(peak_concurrency(&manager, MULTIPLEXED).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_transfer_queue_forgotten_once_idle() {
+        let manager = ResourceManager::new(ResourceConfig::default());
+
+        let slot = manager.acquire_payload_slot("phone").await;
+        manager.forget_device("phone").await;
+        assert!(manager.transfer_queues.read().await.contains_key("phone"));
+
+        drop(slot);
+        manager.forget_device("phone").await;
+        assert!(manager.transfer_queues.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_budget_split_across_transfers() {
+        let config = ResourceConfig {
+            max_bytes_per_sec: Some(1_000_000),
+            ..Default::default()
+        };
+        let manager = ResourceManager::new(config);
+
+        let first = TransferInfo::new("t1".to_string(), "device-1".to_string(), 1000);
+        let first_limit = first.bandwidth.clone();
+        manager.register_transfer(first).await.unwrap();
+        assert_eq!(first_limit.max_bytes_per_sec(), Some(1_000_000));
+
+        let second = TransferInfo::new("t2".to_string(), "device-2".to_string(), 1000);
+        let second_limit = second.bandwidth.clone();
+        manager.register_transfer(second).await.unwrap();
+        assert_eq!(first_limit.max_bytes_per_sec(), Some(500_000));
+        assert_eq!(second_limit.max_bytes_per_sec(), Some(500_000));
+
+        // Changing the budget reaches transfers already in flight
+        manager.set_max_bytes_per_sec(Some(300_000)).await;
+        assert_eq!(first_limit.max_bytes_per_sec(), Some(150_000));
+
+        // A finished transfer hands its share back
+        manager.unregister_transfer("t2").await;
+        assert_eq!(first_limit.max_bytes_per_sec(), Some(300_000));
+
+        manager.set_max_bytes_per_sec(None).await;
+        assert_eq!(first_limit.max_bytes_per_sec(), None);
+    }
+
+    #[tokio::test]
+    async fn test_upload_shares_budget_beyond_transfer_size_limit() {
+        let config = ResourceConfig {
+            max_bytes_per_sec: Some(1_000_000),
+            max_transfer_size: 1000,
+            ..Default::default()
+        };
+        let manager = ResourceManager::new(config);
+
+        let download = TransferInfo::new("t1".to_string(), "device-1".to_string(), 1000);
+        let download_limit = download.bandwidth.clone();
+        manager.register_transfer(download).await.unwrap();
+
+        // Too large to receive, but a file we send is still throttled
+        let upload = TransferInfo::new("t2".to_string(), "device-1".to_string(), 5000);
+        let upload_limit = manager.register_upload(upload).await;
+        assert_eq!(upload_limit.max_bytes_per_sec(), Some(500_000));
+        assert_eq!(download_limit.max_bytes_per_sec(), Some(500_000));
+
+        manager.unregister_transfer("t2").await;
+        assert_eq!(download_limit.max_bytes_per_sec(), Some(1_000_000));
+    }
 }
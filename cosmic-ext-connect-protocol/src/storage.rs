@@ -0,0 +1,424 @@
+//! Persistence Backends
+//!
+//! Device registry, paired-device certificates and configuration are stored
+//! through the [`Storage`] trait so the protocol stack can be embedded without
+//! touching the user's files (tests, alternative frontends).
+//!
+//! ## Backends
+//!
+//! - [`FileStorage`]: one file per key under a directory per namespace. This
+//!   is the default and keeps the on-disk layout used by earlier versions.
+//! - [`MemoryStorage`]: a process-local map, for tests.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use cosmic_ext_connect_protocol::storage::{MemoryStorage, Storage, StorageNamespace};
+//!
+//! let storage = MemoryStorage::new();
+//! storage.set(StorageNamespace::Config, "theme", b"dark").unwrap();
+//! assert_eq!(
+//!     storage.get(StorageNamespace::Config, "theme").unwrap(),
+//!     Some(b"dark".to_vec())
+//! );
+//! ```
+
+use crate::{ProtocolError, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::debug;
+
+/// Kind of data kept in a [`Storage`] backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum StorageNamespace {
+    /// Device registry
+    Devices,
+    /// Paired device certificates (PEM), keyed by device ID
+    Certificates,
+    /// Configuration documents
+    Config,
+    /// Payload encryption keys of paired devices (base64), keyed by device ID
+    PayloadKeys,
+}
+
+impl StorageNamespace {
+    /// All namespaces
+    pub const ALL: [StorageNamespace; 4] = [
+        Self::Devices,
+        Self::Certificates,
+        Self::Config,
+        Self::PayloadKeys,
+    ];
+
+    /// Stable name, used as the directory name by [`FileStorage`]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Devices => "devices",
+            Self::Certificates => "certificates",
+            Self::Config => "config",
+            Self::PayloadKeys => "payload-keys",
+        }
+    }
+
+    /// File extension used by [`FileStorage`]
+    fn default_extension(&self) -> &'static str {
+        match self {
+            Self::Devices | Self::Config => "json",
+            Self::Certificates => "pem",
+            Self::PayloadKeys => "key",
+        }
+    }
+}
+
+/// Key/value persistence backend
+///
+/// Keys are opaque strings but must be valid file names (no path separators,
+/// not `.` or `..`) so that every backend can store them.
+pub trait Storage: Send + Sync {
+    /// Read a value, `None` if it does not exist
+    fn get(&self, namespace: StorageNamespace, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Create or replace a value
+    fn set(&self, namespace: StorageNamespace, key: &str, value: &[u8]) -> Result<()>;
+
+    /// Delete a value, returning whether it existed
+    fn delete(&self, namespace: StorageNamespace, key: &str) -> Result<bool>;
+
+    /// Keys present in a namespace
+    fn keys(&self, namespace: StorageNamespace) -> Result<Vec<String>>;
+}
+
+/// Reject keys that cannot be used as a file name
+fn validate_key(key: &str) -> Result<()> {
+    if key.is_empty() || key.contains(['/', '\\', '\0']) || key == "." || key == ".." {
+        return Err(ProtocolError::InvalidPacket(format!(
+            "Invalid storage key: {:?}",
+            key
+        )));
+    }
+    Ok(())
+}
+
+/// Where one namespace lives on disk
+#[derive(Debug, Clone)]
+struct FileLocation {
+    dir: PathBuf,
+    extension: String,
+}
+
+impl FileLocation {
+    fn path_for(&self, key: &str) -> Result<PathBuf> {
+        validate_key(key)?;
+        Ok(if self.extension.is_empty() {
+            self.dir.join(key)
+        } else {
+            self.dir.join(format!("{}.{}", key, self.extension))
+        })
+    }
+
+    fn key_of(&self, path: &Path) -> Option<String> {
+        let name = path.file_name()?.to_str()?;
+        if self.extension.is_empty() {
+            return Some(name.to_string());
+        }
+        name.strip_suffix(&format!(".{}", self.extension))
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+    }
+}
+
+/// Filesystem backend storing each key as `<dir>/<key>.<extension>`
+///
+/// By default each namespace is a subdirectory of the root named after
+/// [`StorageNamespace::as_str`]; [`FileStorage::with_location`] points a
+/// namespace elsewhere (for example the existing certificate directory).
+/// Directories are created on first write.
+#[derive(Debug, Clone)]
+pub struct FileStorage {
+    locations: HashMap<StorageNamespace, FileLocation>,
+}
+
+impl FileStorage {
+    /// Store every namespace under `root`
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let locations = StorageNamespace::ALL
+            .into_iter()
+            .map(|namespace| {
+                let location = FileLocation {
+                    dir: root.join(namespace.as_str()),
+                    extension: namespace.default_extension().to_string(),
+                };
+                (namespace, location)
+            })
+            .collect();
+        Self { locations }
+    }
+
+    /// Store a namespace in `dir`, with files named `<key>.<extension>`
+    ///
+    /// An empty extension stores keys as plain file names.
+    pub fn with_location(
+        mut self,
+        namespace: StorageNamespace,
+        dir: impl Into<PathBuf>,
+        extension: impl Into<String>,
+    ) -> Self {
+        self.locations.insert(
+            namespace,
+            FileLocation {
+                dir: dir.into(),
+                extension: extension.into(),
+            },
+        );
+        self
+    }
+
+    /// File holding a key
+    pub fn path_for(&self, namespace: StorageNamespace, key: &str) -> Result<PathBuf> {
+        self.location(namespace).path_for(key)
+    }
+
+    fn location(&self, namespace: StorageNamespace) -> &FileLocation {
+        // Every namespace is inserted by `new`
+        &self.locations[&namespace]
+    }
+}
+
+impl Storage for FileStorage {
+    fn get(&self, namespace: StorageNamespace, key: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.path_for(namespace, key)?;
+        match fs::read(&path) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(ProtocolError::from_io_error(
+                e,
+                &format!("reading {:?}", path),
+            )),
+        }
+    }
+
+    fn set(&self, namespace: StorageNamespace, key: &str, value: &[u8]) -> Result<()> {
+        let path = self.path_for(namespace, key)?;
+        let dir = &self.location(namespace).dir;
+        fs::create_dir_all(dir).map_err(|e| {
+            ProtocolError::from_io_error(e, &format!("creating storage directory {:?}", dir))
+        })?;
+        fs::write(&path, value)
+            .map_err(|e| ProtocolError::from_io_error(e, &format!("writing {:?}", path)))?;
+        debug!("Stored {}/{} at {:?}", namespace.as_str(), key, path);
+        Ok(())
+    }
+
+    fn delete(&self, namespace: StorageNamespace, key: &str) -> Result<bool> {
+        let path = self.path_for(namespace, key)?;
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(ProtocolError::from_io_error(
+                e,
+                &format!("removing {:?}", path),
+            )),
+        }
+    }
+
+    fn keys(&self, namespace: StorageNamespace) -> Result<Vec<String>> {
+        let location = self.location(namespace);
+        let entries = match fs::read_dir(&location.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(ProtocolError::from_io_error(
+                    e,
+                    &format!("listing {:?}", location.dir),
+                ))
+            }
+        };
+
+        let mut keys = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(ProtocolError::Io)?.path();
+            if path.is_file() {
+                keys.extend(location.key_of(&path));
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+/// In-memory backend, for tests and embedders without persistence
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    entries: Mutex<BTreeMap<(StorageNamespace, String), Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, BTreeMap<(StorageNamespace, String), Vec<u8>>> {
+        // A panic while holding the lock cannot leave the map half-updated
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn get(&self, namespace: StorageNamespace, key: &str) -> Result<Option<Vec<u8>>> {
+        validate_key(key)?;
+        Ok(self.entries().get(&(namespace, key.to_string())).cloned())
+    }
+
+    fn set(&self, namespace: StorageNamespace, key: &str, value: &[u8]) -> Result<()> {
+        validate_key(key)?;
+        self.entries()
+            .insert((namespace, key.to_string()), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, namespace: StorageNamespace, key: &str) -> Result<bool> {
+        validate_key(key)?;
+        Ok(self
+            .entries()
+            .remove(&(namespace, key.to_string()))
+            .is_some())
+    }
+
+    fn keys(&self, namespace: StorageNamespace) -> Result<Vec<String>> {
+        Ok(self
+            .entries()
+            .keys()
+            .filter(|(ns, _)| *ns == namespace)
+            .map(|(_, key)| key.clone())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Device, DeviceInfo, DeviceManager, DeviceType, PairingHandler, PairingPacket};
+    use cosmic_ext_connect_core::crypto::CertificateInfo;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    fn exercise(storage: &dyn Storage) {
+        assert_eq!(storage.get(StorageNamespace::Config, "a").unwrap(), None);
+
+        storage.set(StorageNamespace::Config, "a", b"1").unwrap();
+        storage.set(StorageNamespace::Config, "b", b"2").unwrap();
+        storage
+            .set(StorageNamespace::Devices, "a", b"other")
+            .unwrap();
+        storage.set(StorageNamespace::Config, "a", b"3").unwrap();
+
+        assert_eq!(
+            storage.get(StorageNamespace::Config, "a").unwrap(),
+            Some(b"3".to_vec())
+        );
+        assert_eq!(storage.keys(StorageNamespace::Config).unwrap(), ["a", "b"]);
+
+        assert!(storage.delete(StorageNamespace::Config, "a").unwrap());
+        assert!(!storage.delete(StorageNamespace::Config, "a").unwrap());
+        assert_eq!(storage.keys(StorageNamespace::Config).unwrap(), ["b"]);
+        assert_eq!(
+            storage.get(StorageNamespace::Devices, "a").unwrap(),
+            Some(b"other".to_vec())
+        );
+
+        assert!(storage.set(StorageNamespace::Config, "../x", b"").is_err());
+        assert!(storage.get(StorageNamespace::Config, "..").is_err());
+    }
+
+    #[test]
+    fn test_backends_behave_alike() {
+        let temp_dir = TempDir::new().unwrap();
+        exercise(&FileStorage::new(temp_dir.path()));
+        exercise(&MemoryStorage::new());
+    }
+
+    #[test]
+    fn test_memory_backend_device_lifecycle() {
+        let storage: Arc<dyn Storage> = Arc::new(MemoryStorage::new());
+
+        let mut manager = DeviceManager::with_storage(storage.clone()).unwrap();
+        let info = DeviceInfo::new("Phone", DeviceType::Phone, 1716);
+        let device_id = info.device_id.clone();
+        manager.add_device(Device::from_discovery(info));
+        manager
+            .mark_paired(&device_id, "AA:BB".to_string())
+            .unwrap();
+        manager.save_registry().unwrap();
+
+        // Pairing stores the peer certificate in the same backend
+        let identity = CertificateInfo::generate("this_device").unwrap();
+        let peer = CertificateInfo::generate(&device_id).unwrap();
+        let mut handler = PairingHandler::with_storage(identity.clone(), storage.clone());
+        handler
+            .handle_pairing_packet(&PairingPacket::request(), &device_id, &peer.certificate)
+            .unwrap();
+        handler
+            .accept_pairing(&device_id, &peer.certificate)
+            .unwrap();
+
+        // A fresh stack over the same backend sees the paired device
+        let reloaded = DeviceManager::with_storage(storage.clone()).unwrap();
+        assert!(reloaded.get_device(&device_id).unwrap().is_paired());
+        let mut handler = PairingHandler::with_storage(identity.clone(), storage.clone());
+        handler.load_paired_devices().unwrap();
+        assert!(handler.is_paired(&device_id));
+
+        // Unpairing and removal clear the backend
+        handler.unpair(&device_id).unwrap();
+        let mut manager = reloaded;
+        manager.remove_device(&device_id);
+        manager.save_registry().unwrap();
+
+        assert!(storage
+            .keys(StorageNamespace::Certificates)
+            .unwrap()
+            .is_empty());
+        let manager = DeviceManager::with_storage(storage).unwrap();
+        assert!(manager.get_device(&device_id).is_none());
+    }
+
+    #[test]
+    fn test_file_backend_persists_across_reload() {
+        let temp_dir = TempDir::new().unwrap();
+        let info = DeviceInfo::new("Laptop", DeviceType::Laptop, 1716);
+        let device_id = info.device_id.clone();
+
+        {
+            let storage = Arc::new(FileStorage::new(temp_dir.path()));
+            let mut manager = DeviceManager::with_storage(storage.clone()).unwrap();
+            manager.add_device(Device::from_discovery(info));
+            manager.save_registry().unwrap();
+            storage
+                .set(
+                    StorageNamespace::Config,
+                    &device_id,
+                    br#"{"nickname":"work"}"#,
+                )
+                .unwrap();
+        }
+
+        let storage = Arc::new(FileStorage::new(temp_dir.path()));
+        let manager = DeviceManager::with_storage(storage.clone()).unwrap();
+        assert_eq!(manager.get_device(&device_id).unwrap().name(), "Laptop");
+        assert_eq!(
+            storage.get(StorageNamespace::Config, &device_id).unwrap(),
+            Some(br#"{"nickname":"work"}"#.to_vec())
+        );
+        assert!(temp_dir
+            .path()
+            .join("devices")
+            .join("registry.json")
+            .is_file());
+    }
+}
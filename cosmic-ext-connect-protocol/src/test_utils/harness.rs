@@ -0,0 +1,396 @@
+//! Two-Stack Test Harness
+//!
+//! Runs two complete protocol stacks in one process so tests can exercise
+//! discovery, TLS connections, pairing and payload transfers end to end
+//! without a phone. Each [`TestStack`] has its own certificate, in-memory
+//! storage, [`DeviceManager`], [`ConnectionManager`] (listening on loopback),
+//! [`DiscoveryService`] and [`PairingHandler`].
+//!
+//! Discovery broadcasts are not reliable inside test sandboxes, so stacks
+//! find each other with a unicast identity packet to the peer's discovery
+//! port ([`DiscoveryService::announce_to`]); the rest of the discovery path
+//! is the real one.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let mut desktop = TestStack::start("desktop", "Desktop").await?;
+//! let mut phone = TestStack::start("phone", "Phone").await?;
+//! connect(&mut desktop, &mut phone).await?;
+//! pair(&mut desktop, &mut phone).await?;
+//! let received = transfer_file(&mut desktop, &mut phone, &path, dir.path()).await?;
+//! ```
+
+use crate::plugins::share::SharePlugin;
+use crate::{
+    CertificateInfo, ConnectionConfig, ConnectionEvent, ConnectionManager, DeviceInfo,
+    DeviceManager, DeviceType, DiscoveryConfig, DiscoveryEvent, DiscoveryService, FileTransferInfo,
+    MemoryStorage, Packet, PairingHandler, ProtocolError, Result, TlsPayloadClient,
+    TlsPayloadServer, TransportAddress,
+};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, RwLock};
+
+/// How long to wait for the peer before failing
+pub const STEP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One protocol stack bound to loopback
+pub struct TestStack {
+    pub device_id: String,
+    pub certificate: CertificateInfo,
+    pub device_manager: Arc<RwLock<DeviceManager>>,
+    pub connection_manager: ConnectionManager,
+    pub discovery: DiscoveryService,
+    pub pairing: PairingHandler,
+    /// Port of the TLS server
+    pub tcp_port: u16,
+    connection_events: mpsc::UnboundedReceiver<ConnectionEvent>,
+    discovery_events: mpsc::UnboundedReceiver<DiscoveryEvent>,
+}
+
+impl TestStack {
+    /// Create and start a stack with fresh identity and empty storage
+    pub async fn start(device_id: &str, device_name: &str) -> Result<Self> {
+        let certificate = CertificateInfo::generate(device_id)?;
+        let device_manager = Arc::new(RwLock::new(DeviceManager::with_storage(Arc::new(
+            MemoryStorage::new(),
+        ))?));
+        let pairing =
+            PairingHandler::with_storage(certificate.clone(), Arc::new(MemoryStorage::new()));
+
+        let config = ConnectionConfig {
+            listen_addr: (Ipv4Addr::LOCALHOST, 0).into(),
+            ..ConnectionConfig::default()
+        };
+        let mut connection_manager = ConnectionManager::new(
+            certificate.clone(),
+            DeviceInfo::with_id(device_id, device_name, DeviceType::Desktop, 0),
+            device_manager.clone(),
+            config,
+        )?;
+        let connection_events = connection_manager.subscribe().await;
+        let tcp_port = connection_manager.start().await?;
+
+        // Identity packets advertise the port the TLS server actually got
+        let device_info =
+            DeviceInfo::with_id(device_id, device_name, DeviceType::Desktop, tcp_port)
+                .with_incoming_capability("cconnect.share.request")
+                .with_outgoing_capability("cconnect.share.request");
+        connection_manager.update_device_info(device_info.clone());
+
+        let mut discovery = DiscoveryService::new(
+            device_info,
+            DiscoveryConfig {
+                broadcast_interval: Duration::from_secs(3600),
+                device_timeout: Duration::from_secs(3600),
+                enable_timeout_check: false,
+                additional_broadcast_addrs: Vec::new(),
+                enable_mdns: false,
+                enable_ipv6: false,
+            },
+        )?;
+        let discovery_events = discovery.subscribe().await;
+        discovery.start().await?;
+
+        Ok(Self {
+            device_id: device_id.to_string(),
+            certificate,
+            device_manager,
+            connection_manager,
+            discovery,
+            pairing,
+            tcp_port,
+            connection_events,
+            discovery_events,
+        })
+    }
+
+    /// DER certificate of this stack
+    pub fn certificate_der(&self) -> &[u8] {
+        &self.certificate.certificate
+    }
+
+    /// Have `other` announce itself to this stack and wait until it is
+    /// discovered, returning its TLS address
+    pub async fn discover(&mut self, other: &TestStack) -> Result<SocketAddr> {
+        let port = self.discovery.local_port()?;
+        other
+            .discovery
+            .announce_to((Ipv4Addr::LOCALHOST, port).into())?;
+
+        // A broadcast may arrive first from a non-loopback source; only the
+        // loopback announcement is reachable
+        let other_id = other.device_id.clone();
+        let (info, addr) = within(STEP_TIMEOUT, "discovery", async {
+            loop {
+                let (info, address) = match self.discovery_events.recv().await {
+                    Some(DiscoveryEvent::DeviceDiscovered {
+                        info,
+                        transport_address,
+                        ..
+                    })
+                    | Some(DiscoveryEvent::DeviceUpdated {
+                        info,
+                        transport_address,
+                        ..
+                    }) => (info, transport_address),
+                    Some(_) => continue,
+                    None => return Err(closed("discovery")),
+                };
+                match address {
+                    TransportAddress::Tcp(addr)
+                        if info.device_id == other_id && addr.ip().is_loopback() =>
+                    {
+                        return Ok((info, addr))
+                    }
+                    _ => continue,
+                }
+            }
+        })
+        .await?;
+
+        self.device_manager
+            .write()
+            .await
+            .update_from_discovery(info, TransportAddress::Tcp(addr));
+        Ok(addr)
+    }
+
+    /// Wait until the connection to `device_id` is up
+    pub async fn wait_connected(&mut self, device_id: &str) -> Result<()> {
+        within(STEP_TIMEOUT, "connection", async {
+            loop {
+                match self.connection_events.recv().await {
+                    Some(ConnectionEvent::Connected { device_id: id, .. }) if id == device_id => {
+                        return Ok(())
+                    }
+                    Some(_) => continue,
+                    None => return Err(closed("connection")),
+                }
+            }
+        })
+        .await
+    }
+
+    /// Wait for the next packet of `packet_type` from `device_id`
+    ///
+    /// Other packets are dropped.
+    pub async fn next_packet(&mut self, device_id: &str, packet_type: &str) -> Result<Packet> {
+        within(STEP_TIMEOUT, packet_type, async {
+            loop {
+                match self.connection_events.recv().await {
+                    Some(ConnectionEvent::PacketReceived {
+                        device_id: id,
+                        packet,
+                        ..
+                    }) if id == device_id && packet.is_type(packet_type) => return Ok(packet),
+                    Some(_) => continue,
+                    None => return Err(closed("connection")),
+                }
+            }
+        })
+        .await
+    }
+
+    /// Collect connection events up to and including the first that matches
+    pub async fn events_until(
+        &mut self,
+        mut matches: impl FnMut(&ConnectionEvent) -> bool,
+    ) -> Result<Vec<ConnectionEvent>> {
+        within(STEP_TIMEOUT, "connection event", async {
+            let mut events = Vec::new();
+            loop {
+                match self.connection_events.recv().await {
+                    Some(event) => {
+                        let done = matches(&event);
+                        events.push(event);
+                        if done {
+                            return Ok(events);
+                        }
+                    }
+                    None => return Err(closed("connection")),
+                }
+            }
+        })
+        .await
+    }
+
+    /// Send a packet to a connected device
+    pub async fn send(&self, device_id: &str, packet: &Packet) -> Result<()> {
+        self.connection_manager.send_packet(device_id, packet).await
+    }
+}
+
+/// Discover and connect `initiator` to `responder`
+pub async fn connect(initiator: &mut TestStack, responder: &mut TestStack) -> Result<()> {
+    let addr = initiator.discover(responder).await?;
+    initiator
+        .connection_manager
+        .connect(&responder.device_id, addr)
+        .await?;
+    initiator.wait_connected(&responder.device_id).await?;
+    responder.wait_connected(&initiator.device_id).await
+}
+
+/// Pair two connected stacks, `initiator` asking and `responder` accepting
+///
+/// Both device managers record the peer as paired afterwards.
+pub async fn pair(initiator: &mut TestStack, responder: &mut TestStack) -> Result<()> {
+    let initiator_id = initiator.device_id.clone();
+    let responder_id = responder.device_id.clone();
+    let initiator_cert = initiator.certificate_der().to_vec();
+    let responder_cert = responder.certificate_der().to_vec();
+
+    let request = initiator.pairing.request_pairing(&responder_id);
+    initiator.send(&responder_id, &request).await?;
+
+    let request = responder
+        .next_packet(&initiator_id, "cconnect.pair")
+        .await?;
+    responder
+        .pairing
+        .handle_pairing_packet(&request, &initiator_id, &initiator_cert)?;
+    let accept = responder
+        .pairing
+        .accept_pairing(&initiator_id, &initiator_cert)?;
+    responder.send(&initiator_id, &accept).await?;
+
+    let accept = initiator
+        .next_packet(&responder_id, "cconnect.pair")
+        .await?;
+    let (_, confirmation) =
+        initiator
+            .pairing
+            .handle_pairing_packet(&accept, &responder_id, &responder_cert)?;
+    if let Some(confirmation) = confirmation {
+        initiator.send(&responder_id, &confirmation).await?;
+        let confirmation = responder
+            .next_packet(&initiator_id, "cconnect.pair")
+            .await?;
+        responder
+            .pairing
+            .handle_pairing_packet(&confirmation, &initiator_id, &initiator_cert)?;
+    }
+
+    initiator.device_manager.write().await.mark_paired(
+        &responder_id,
+        CertificateInfo::calculate_fingerprint(&responder_cert),
+    )?;
+    responder.device_manager.write().await.mark_paired(
+        &initiator_id,
+        CertificateInfo::calculate_fingerprint(&initiator_cert),
+    )?;
+    Ok(())
+}
+
+/// Share `path` from `sender` to `receiver` like the share plugin does
+///
+/// The file is saved under `save_dir` with its original name; the saved path
+/// is returned.
+pub async fn transfer_file(
+    sender: &mut TestStack,
+    receiver: &mut TestStack,
+    path: &Path,
+    save_dir: &Path,
+) -> Result<PathBuf> {
+    let file_info = FileTransferInfo::from_path(path).await?;
+    let server = TlsPayloadServer::new(sender.connection_manager.tls_config()).await?;
+    let packet = SharePlugin::new().create_file_packet(file_info.into(), server.port());
+    sender.send(&receiver.device_id, &packet).await?;
+
+    let source = path.to_path_buf();
+    let upload = tokio::spawn(async move { server.send_file(source).await });
+
+    let offer = receiver
+        .next_packet(&sender.device_id, "cconnect.share.request")
+        .await?;
+    let port = offer
+        .payload_transfer_info
+        .as_ref()
+        .and_then(|info| info.get("port"))
+        .and_then(|port| port.as_u64())
+        .ok_or_else(|| ProtocolError::InvalidPacket("Share offer has no port".to_string()))?;
+    let size = offer.payload_size.unwrap_or_default().max(0) as u64;
+    let filename = offer
+        .get_body_field::<String>("filename")
+        .ok_or_else(|| ProtocolError::InvalidPacket("Share offer has no filename".to_string()))?;
+
+    let save_path = save_dir.join(filename);
+    TlsPayloadClient::new(
+        "127.0.0.1",
+        port as u16,
+        &receiver.connection_manager.tls_config(),
+    )
+    .await?
+    .receive_file(&save_path, size)
+    .await?;
+
+    upload
+        .await
+        .map_err(|e| ProtocolError::Transport(format!("Upload task failed: {}", e)))??;
+    Ok(save_path)
+}
+
+async fn within<T>(
+    timeout: Duration,
+    step: &str,
+    future: impl std::future::Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::time::timeout(timeout, future)
+        .await
+        .map_err(|_| ProtocolError::Timeout(format!("Timed out waiting for {}", step)))?
+}
+
+fn closed(channel: &str) -> ProtocolError {
+    ProtocolError::InvalidState(format!("{} event channel closed", channel))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    fn sha256(path: &Path) -> Vec<u8> {
+        Sha256::digest(std::fs::read(path).unwrap()).to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_pair_and_transfer_file_between_stacks() {
+        let mut desktop = TestStack::start("harness_desktop", "Desktop")
+            .await
+            .unwrap();
+        let mut phone = TestStack::start("harness_phone", "Phone").await.unwrap();
+
+        connect(&mut desktop, &mut phone).await.unwrap();
+        pair(&mut desktop, &mut phone).await.unwrap();
+        assert!(desktop.pairing.is_paired("harness_phone"));
+        assert!(phone.pairing.is_paired("harness_desktop"));
+        assert!(desktop
+            .device_manager
+            .read()
+            .await
+            .is_paired("harness_phone"));
+        assert!(phone
+            .device_manager
+            .read()
+            .await
+            .is_paired("harness_desktop"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("notes.txt");
+        let contents: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&source, &contents).unwrap();
+        let inbox = dir.path().join("inbox");
+        std::fs::create_dir(&inbox).unwrap();
+
+        let received = transfer_file(&mut desktop, &mut phone, &source, &inbox)
+            .await
+            .unwrap();
+
+        assert_eq!(received, inbox.join("notes.txt"));
+        assert_eq!(sha256(&received), sha256(&source));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod harness;
+
 use crate::{Device, DeviceInfo, DeviceType};
 
 pub fn create_test_device() -> Device {
@@ -0,0 +1,579 @@
+//! Chunk-Level Transfer Integrity
+//!
+//! Verifies received files against per-chunk SHA-256 checksums and repairs only the
+//! chunks that fail, instead of restarting the whole transfer. This matters most on
+//! slow or lossy links (e.g. Bluetooth) where a single bad block would otherwise
+//! throw away an almost complete file.
+//!
+//! ## Repair Flow
+//!
+//! 1. Sender advertises [`ChunkChecksums`] alongside the file
+//! 2. Receiver downloads the file as usual
+//! 3. [`ChunkRepairer::repair`] verifies every chunk
+//! 4. Contiguous failing chunks are coalesced into [`ChunkRange`]s and re-requested
+//!    from a [`ChunkSource`]
+//! 5. Steps 3-4 repeat until every chunk verifies or the attempt cap is hit
+//!
+//! Checksums and retransmitted data come from the peer, so neither is
+//! trusted: [`ChunkChecksums::validate`] rejects a zero or oversized chunk
+//! size and a checksum list that does not cover the file, and a
+//! retransmitted range of the wrong length fails the repair without being
+//! written.
+
+use crate::{Packet, ProtocolError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use tokio::fs::OpenOptions;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, SeekFrom};
+use tracing::{debug, info, warn};
+
+/// Default chunk size used for checksums (256 KB)
+pub const DEFAULT_CHUNK_SIZE: u64 = 256 * 1024;
+
+/// Largest chunk size accepted; chunks are read into memory whole (4 MB)
+pub const MAX_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Share request field carrying the file's [`ChunkChecksums`]
+pub const CHUNK_CHECKSUMS_FIELD: &str = "chunkChecksums";
+
+/// Default maximum number of retransmission rounds before giving up
+pub const DEFAULT_MAX_REPAIR_ATTEMPTS: u32 = 3;
+
+/// Packet type used to request retransmission of chunk ranges
+pub const RETRANSMIT_PACKET_TYPE: &str = "cconnect.share.request.retransmit";
+
+/// A contiguous byte range covering one or more chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkRange {
+    /// Index of the first chunk in the range
+    #[serde(rename = "firstChunk")]
+    pub first_chunk: usize,
+
+    /// Byte offset of the range within the file
+    pub offset: u64,
+
+    /// Length of the range in bytes
+    pub length: u64,
+}
+
+/// Per-chunk SHA-256 checksums for a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkChecksums {
+    /// Size of each chunk in bytes (the last chunk may be shorter)
+    #[serde(rename = "chunkSize")]
+    pub chunk_size: u64,
+
+    /// Total file size in bytes
+    #[serde(rename = "totalSize")]
+    pub total_size: u64,
+
+    /// Hex-encoded SHA-256 checksum of each chunk, in order
+    pub checksums: Vec<String>,
+}
+
+impl ChunkChecksums {
+    /// Compute checksums over an in-memory buffer
+    ///
+    /// `chunk_size` is clamped to `1..=`[`MAX_CHUNK_SIZE`].
+    pub fn from_bytes(data: &[u8], chunk_size: u64) -> Self {
+        let chunk_size = chunk_size.clamp(1, MAX_CHUNK_SIZE);
+        let checksums = data
+            .chunks(chunk_size as usize)
+            .map(|chunk| hex::encode(Sha256::digest(chunk)))
+            .collect();
+
+        Self {
+            chunk_size,
+            total_size: data.len() as u64,
+            checksums,
+        }
+    }
+
+    /// Compute checksums over a file on disk
+    ///
+    /// `chunk_size` is clamped to `1..=`[`MAX_CHUNK_SIZE`].
+    pub async fn from_file(path: impl AsRef<Path>, chunk_size: u64) -> Result<Self> {
+        let chunk_size = chunk_size.clamp(1, MAX_CHUNK_SIZE);
+        let mut file = tokio::fs::File::open(path.as_ref()).await?;
+        let total_size = file.metadata().await?.len();
+
+        let mut checksums = Vec::new();
+        let mut buffer = vec![0u8; chunk_size as usize];
+        let mut remaining = total_size;
+        while remaining > 0 {
+            let len = remaining.min(chunk_size) as usize;
+            file.read_exact(&mut buffer[..len]).await?;
+            checksums.push(hex::encode(Sha256::digest(&buffer[..len])));
+            remaining -= len as u64;
+        }
+
+        Ok(Self {
+            chunk_size,
+            total_size,
+            checksums,
+        })
+    }
+
+    /// Checksums announced in a share request, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidPacket`] if the field is malformed,
+    /// fails [`validate`](Self::validate) or describes a different size than
+    /// the packet's payload.
+    pub fn from_packet(packet: &Packet) -> Result<Option<Self>> {
+        let Some(value) = packet.body.get(CHUNK_CHECKSUMS_FIELD) else {
+            return Ok(None);
+        };
+        let checksums: Self = serde_json::from_value(value.clone())
+            .map_err(|e| ProtocolError::InvalidPacket(format!("Invalid chunk checksums: {}", e)))?;
+        checksums.validate()?;
+        if let Some(size) = packet.payload_size {
+            if size as u64 != checksums.total_size {
+                return Err(ProtocolError::InvalidPacket(format!(
+                    "Chunk checksums cover {} bytes but the payload has {}",
+                    checksums.total_size, size
+                )));
+            }
+        }
+        Ok(Some(checksums))
+    }
+
+    /// Check checksums received from a peer before using them
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidPacket`] if the chunk size is 0 or over
+    /// [`MAX_CHUNK_SIZE`], or there is not exactly one SHA-256 per chunk.
+    pub fn validate(&self) -> Result<()> {
+        if self.chunk_size == 0 || self.chunk_size > MAX_CHUNK_SIZE {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "Chunk size {} outside 1..={}",
+                self.chunk_size, MAX_CHUNK_SIZE
+            )));
+        }
+        let expected = self.total_size.div_ceil(self.chunk_size);
+        if self.checksums.len() as u64 != expected {
+            return Err(ProtocolError::InvalidPacket(format!(
+                "{} chunk checksum(s) for {} chunk(s)",
+                self.checksums.len(),
+                expected
+            )));
+        }
+        if !self
+            .checksums
+            .iter()
+            .all(|sum| sum.len() == 64 && sum.bytes().all(|b| b.is_ascii_hexdigit()))
+        {
+            return Err(ProtocolError::InvalidPacket(
+                "Chunk checksum is not a hex SHA-256".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Number of chunks described
+    pub fn chunk_count(&self) -> usize {
+        self.checksums.len()
+    }
+
+    /// Byte offset and length of a single chunk
+    pub fn chunk_bounds(&self, index: usize) -> (u64, u64) {
+        let offset = index as u64 * self.chunk_size;
+        let length = self.chunk_size.min(self.total_size.saturating_sub(offset));
+        (offset, length)
+    }
+
+    /// Verify a file and return the indices of chunks whose checksum does not match
+    ///
+    /// A file that is shorter than expected reports the missing chunks as failing.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the checksums fail [`validate`](Self::validate) or the
+    /// file cannot be read.
+    pub async fn failing_chunks(&self, path: impl AsRef<Path>) -> Result<Vec<usize>> {
+        self.validate()?;
+        let mut file = tokio::fs::File::open(path.as_ref()).await?;
+        let actual_size = file.metadata().await?.len();
+
+        let mut failing = Vec::new();
+        let mut buffer = vec![0u8; self.chunk_size as usize];
+        for (index, expected) in self.checksums.iter().enumerate() {
+            let (offset, length) = self.chunk_bounds(index);
+            if offset + length > actual_size {
+                failing.push(index);
+                continue;
+            }
+
+            file.seek(SeekFrom::Start(offset)).await?;
+            let buf = &mut buffer[..length as usize];
+            file.read_exact(buf).await?;
+            if !hex::encode(Sha256::digest(&*buf)).eq_ignore_ascii_case(expected) {
+                failing.push(index);
+            }
+        }
+
+        Ok(failing)
+    }
+
+    /// Coalesce chunk indices into contiguous byte ranges
+    ///
+    /// `indices` must be sorted ascending, as returned by [`failing_chunks`](Self::failing_chunks).
+    pub fn ranges_for(&self, indices: &[usize]) -> Vec<ChunkRange> {
+        let mut ranges: Vec<ChunkRange> = Vec::new();
+        for &index in indices {
+            let (offset, length) = self.chunk_bounds(index);
+            match ranges.last_mut() {
+                Some(last) if last.offset + last.length == offset => last.length += length,
+                _ => ranges.push(ChunkRange {
+                    first_chunk: index,
+                    offset,
+                    length,
+                }),
+            }
+        }
+        ranges
+    }
+}
+
+/// Source of retransmitted data for chunk repair
+///
+/// Implementations send a retransmission request to the peer (see
+/// [`create_retransmit_packet`]) and return the bytes for the requested range.
+#[async_trait]
+pub trait ChunkSource: Send {
+    /// Fetch `range.length` bytes starting at `range.offset`
+    async fn fetch_range(&mut self, range: &ChunkRange) -> Result<Vec<u8>>;
+}
+
+/// Outcome of a successful chunk repair
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RepairReport {
+    /// Retransmission rounds performed (0 if the file verified immediately)
+    pub attempts: u32,
+
+    /// Total chunks re-fetched across all rounds
+    pub chunks_refetched: usize,
+}
+
+/// Repairs a received file by re-fetching only the chunks that fail verification
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkRepairer {
+    max_attempts: u32,
+}
+
+impl Default for ChunkRepairer {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_REPAIR_ATTEMPTS)
+    }
+}
+
+impl ChunkRepairer {
+    /// Create a repairer allowing at most `max_attempts` retransmission rounds
+    pub fn new(max_attempts: u32) -> Self {
+        Self { max_attempts }
+    }
+
+    /// Verify `path` against `checksums`, re-fetching failing ranges until it verifies
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the checksums are invalid, the file cannot be read or
+    /// written, the source fails or returns a range of the wrong length, or
+    /// chunks still fail verification after `max_attempts` retransmission rounds.
+    pub async fn repair(
+        &self,
+        path: impl AsRef<Path>,
+        checksums: &ChunkChecksums,
+        source: &mut dyn ChunkSource,
+    ) -> Result<RepairReport> {
+        let path = path.as_ref();
+        let mut report = RepairReport::default();
+
+        loop {
+            let failing = checksums.failing_chunks(path).await?;
+            if failing.is_empty() {
+                if report.attempts > 0 {
+                    info!(
+                        "Repaired {:?} after {} round(s), {} chunk(s) re-fetched",
+                        path, report.attempts, report.chunks_refetched
+                    );
+                }
+                return Ok(report);
+            }
+
+            if report.attempts >= self.max_attempts {
+                warn!(
+                    "Giving up on {:?}: {} chunk(s) still corrupt after {} attempt(s)",
+                    path,
+                    failing.len(),
+                    report.attempts
+                );
+                return Err(ProtocolError::Transport(format!(
+                    "{} chunk(s) failed verification after {} retransmission attempt(s)",
+                    failing.len(),
+                    report.attempts
+                )));
+            }
+
+            report.attempts += 1;
+            report.chunks_refetched += failing.len();
+
+            let ranges = checksums.ranges_for(&failing);
+            debug!(
+                "Retransmission round {} for {:?}: {} chunk(s) in {} range(s)",
+                report.attempts,
+                path,
+                failing.len(),
+                ranges.len()
+            );
+
+            let mut file = OpenOptions::new().write(true).open(path).await?;
+            for range in &ranges {
+                let data = source.fetch_range(range).await?;
+                if data.len() as u64 != range.length {
+                    warn!(
+                        "Retransmitted range at offset {} has {} bytes, expected {}",
+                        range.offset,
+                        data.len(),
+                        range.length
+                    );
+                    return Err(ProtocolError::InvalidPacket(format!(
+                        "Retransmitted range at offset {} has {} bytes, expected {}",
+                        range.offset,
+                        data.len(),
+                        range.length
+                    )));
+                }
+                file.seek(SeekFrom::Start(range.offset)).await?;
+                file.write_all(&data).await?;
+            }
+            file.flush().await?;
+        }
+    }
+}
+
+/// Create a packet requesting retransmission of chunk ranges
+pub fn create_retransmit_packet(transfer_id: &str, ranges: &[ChunkRange]) -> Packet {
+    Packet::new(
+        RETRANSMIT_PACKET_TYPE,
+        json!({
+            "transferId": transfer_id,
+            "ranges": ranges,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Serves ranges from a known-good buffer, optionally corrupting every reply
+    struct BufferSource {
+        data: Vec<u8>,
+        corrupt: bool,
+        requests: Vec<ChunkRange>,
+    }
+
+    #[async_trait]
+    impl ChunkSource for BufferSource {
+        async fn fetch_range(&mut self, range: &ChunkRange) -> Result<Vec<u8>> {
+            self.requests.push(*range);
+            let start = range.offset as usize;
+            let mut bytes = self.data[start..start + range.length as usize].to_vec();
+            if self.corrupt {
+                bytes[0] ^= 0xFF;
+            }
+            Ok(bytes)
+        }
+    }
+
+    fn sample_data() -> Vec<u8> {
+        (0..10_000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_checksums_from_bytes() {
+        let data = sample_data();
+        let checksums = ChunkChecksums::from_bytes(&data, 1024);
+        assert_eq!(checksums.chunk_count(), 10);
+        assert_eq!(checksums.chunk_bounds(9), (9216, 784));
+    }
+
+    #[test]
+    fn test_ranges_coalesce_adjacent_chunks() {
+        let checksums = ChunkChecksums::from_bytes(&sample_data(), 1024);
+        let ranges = checksums.ranges_for(&[1, 2, 5]);
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges[0].offset, 1024);
+        assert_eq!(ranges[0].length, 2048);
+        assert_eq!(ranges[1].first_chunk, 5);
+    }
+
+    #[tokio::test]
+    async fn test_single_corrupted_chunk_is_refetched() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("received.bin");
+        let data = sample_data();
+        let checksums = ChunkChecksums::from_bytes(&data, 1024);
+
+        let mut corrupted = data.clone();
+        corrupted[3 * 1024 + 17] ^= 0x55;
+        tokio::fs::write(&path, &corrupted).await.unwrap();
+
+        let mut source = BufferSource {
+            data: data.clone(),
+            corrupt: false,
+            requests: Vec::new(),
+        };
+        let report = ChunkRepairer::default()
+            .repair(&path, &checksums, &mut source)
+            .await
+            .unwrap();
+
+        assert_eq!(report.attempts, 1);
+        assert_eq!(report.chunks_refetched, 1);
+        assert_eq!(source.requests.len(), 1);
+        assert_eq!(source.requests[0].first_chunk, 3);
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_failures_abort() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("received.bin");
+        let data = sample_data();
+        let checksums = ChunkChecksums::from_bytes(&data, 1024);
+
+        let mut corrupted = data.clone();
+        corrupted[0] ^= 0x01;
+        tokio::fs::write(&path, &corrupted).await.unwrap();
+
+        let mut source = BufferSource {
+            data,
+            corrupt: true,
+            requests: Vec::new(),
+        };
+        let result = ChunkRepairer::new(2)
+            .repair(&path, &checksums, &mut source)
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(source.requests.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_intact_file_needs_no_repair() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("received.bin");
+        let data = sample_data();
+        tokio::fs::write(&path, &data).await.unwrap();
+
+        let checksums = ChunkChecksums::from_file(&path, 1024).await.unwrap();
+        assert_eq!(checksums, ChunkChecksums::from_bytes(&data, 1024));
+
+        let mut source = BufferSource {
+            data,
+            corrupt: false,
+            requests: Vec::new(),
+        };
+        let report = ChunkRepairer::default()
+            .repair(&path, &checksums, &mut source)
+            .await
+            .unwrap();
+        assert_eq!(report, RepairReport::default());
+    }
+
+    #[test]
+    fn test_peer_chunk_size_validated() {
+        let data = sample_data();
+        let mut checksums = ChunkChecksums::from_bytes(&data, 1024);
+        assert!(checksums.validate().is_ok());
+
+        checksums.chunk_size = 0;
+        assert!(checksums.validate().is_err());
+        checksums.chunk_size = MAX_CHUNK_SIZE + 1;
+        assert!(checksums.validate().is_err());
+
+        // One checksum too few for the file
+        let mut short = ChunkChecksums::from_bytes(&data, 1024);
+        short.checksums.pop();
+        assert!(short.validate().is_err());
+
+        assert_eq!(
+            ChunkChecksums::from_bytes(&data, u64::MAX).chunk_size,
+            MAX_CHUNK_SIZE
+        );
+    }
+
+    #[test]
+    fn test_checksums_from_packet() {
+        let data = sample_data();
+        let checksums = ChunkChecksums::from_bytes(&data, 1024);
+        let packet = Packet::new(
+            "cconnect.share.request",
+            json!({ "filename": "a.bin", CHUNK_CHECKSUMS_FIELD: checksums }),
+        )
+        .with_payload_size(data.len() as i64);
+        assert_eq!(
+            ChunkChecksums::from_packet(&packet).unwrap(),
+            Some(checksums.clone())
+        );
+
+        let other_size = packet.clone().with_payload_size(1);
+        assert!(ChunkChecksums::from_packet(&other_size).is_err());
+
+        let mut hostile = checksums;
+        hostile.chunk_size = u64::MAX;
+        let packet = Packet::new(
+            "cconnect.share.request",
+            json!({ "filename": "a.bin", CHUNK_CHECKSUMS_FIELD: hostile }),
+        );
+        assert!(ChunkChecksums::from_packet(&packet).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_short_retransmit_rejected() {
+        struct ShortSource;
+
+        #[async_trait]
+        impl ChunkSource for ShortSource {
+            async fn fetch_range(&mut self, range: &ChunkRange) -> Result<Vec<u8>> {
+                Ok(vec![0u8; range.length as usize - 1])
+            }
+        }
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("received.bin");
+        let data = sample_data();
+        let checksums = ChunkChecksums::from_bytes(&data, 1024);
+
+        let mut corrupted = data.clone();
+        corrupted[5000] ^= 0x01;
+        tokio::fs::write(&path, &corrupted).await.unwrap();
+
+        let result = ChunkRepairer::default()
+            .repair(&path, &checksums, &mut ShortSource)
+            .await;
+        assert!(matches!(result, Err(ProtocolError::InvalidPacket(_))));
+        // Nothing was written over the received data
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), corrupted);
+    }
+
+    #[test]
+    fn test_retransmit_packet() {
+        let range = ChunkRange {
+            first_chunk: 2,
+            offset: 2048,
+            length: 1024,
+        };
+        let packet = create_retransmit_packet("transfer-1", &[range]);
+        assert_eq!(packet.packet_type, RETRANSMIT_PACKET_TYPE);
+        assert_eq!(packet.body["ranges"][0]["firstChunk"], 2);
+    }
+}
@@ -20,7 +20,7 @@ use crate::transport::{
     LatencyCategory, Transport, TransportAddress, TransportCapabilities, TransportFactory,
     TransportType,
 };
-use crate::{Packet, ProtocolError, Result};
+use crate::{Packet, PacketEncoding, ProtocolError, Result};
 use async_trait::async_trait;
 use bluer::rfcomm::{Listener, Profile, ProfileHandle, SocketAddr, Stream};
 use bluer::{Address, Session};
@@ -72,6 +72,9 @@ pub struct BluetoothConnection {
 
     /// Connection state
     connected: Arc<Mutex<bool>>,
+
+    /// Encoding of sent packets (received ones are detected)
+    encoding: PacketEncoding,
 }
 
 impl BluetoothConnection {
@@ -116,6 +119,7 @@ impl BluetoothConnection {
             remote_address: bt_addr,
             remote_address_str: address,
             connected: Arc::new(Mutex::new(true)),
+            encoding: PacketEncoding::Json,
         })
     }
 
@@ -134,7 +138,28 @@ impl BluetoothConnection {
             remote_address,
             remote_address_str,
             connected: Arc::new(Mutex::new(true)),
+            encoding: PacketEncoding::Json,
+        }
+    }
+
+    /// Encoding packets are sent in
+    pub fn encoding(&self) -> PacketEncoding {
+        self.encoding
+    }
+
+    /// Send packets in `encoding` from now on
+    ///
+    /// Only switch once the peer has advertised it can decode `encoding`;
+    /// see [`TransportCapabilities::negotiate_encoding`].
+    pub fn set_encoding(&mut self, encoding: PacketEncoding) {
+        if encoding != self.encoding {
+            debug!(
+                "Sending {} packets to {}",
+                encoding.as_str(),
+                self.remote_address_str
+            );
         }
+        self.encoding = encoding;
     }
 
     /// Close the connection
@@ -165,20 +190,29 @@ impl std::fmt::Debug for BluetoothConnection {
     }
 }
 
+/// Capabilities of every Bluetooth connection
+pub(crate) const BLUETOOTH_CAPABILITIES: TransportCapabilities = TransportCapabilities {
+    // Bluetooth has smaller MTU than TCP
+    max_packet_size: MAX_BT_PACKET_SIZE,
+    // RFCOMM is reliable (retransmission built-in)
+    reliable: true,
+    // RFCOMM is connection-oriented
+    connection_oriented: true,
+    // Bluetooth typically has medium latency
+    latency: LatencyCategory::Medium,
+    // One RFCOMM channel per connection
+    multiplexed: false,
+    // Length-prefixed frames carry either encoding; every byte counts here
+    msgpack: true,
+    // Payloads share the one RFCOMM link
+    payload_streams: 1,
+};
+
 // Implement Transport trait for BluetoothConnection
 #[async_trait]
 impl Transport for BluetoothConnection {
     fn capabilities(&self) -> TransportCapabilities {
-        TransportCapabilities {
-            // Bluetooth has smaller MTU than TCP
-            max_packet_size: MAX_BT_PACKET_SIZE,
-            // RFCOMM is reliable (retransmission built-in)
-            reliable: true,
-            // RFCOMM is connection-oriented
-            connection_oriented: true,
-            // Bluetooth typically has medium latency
-            latency: LatencyCategory::Medium,
-        }
+        BLUETOOTH_CAPABILITIES
     }
 
     fn remote_address(&self) -> TransportAddress {
@@ -189,7 +223,7 @@ impl Transport for BluetoothConnection {
     }
 
     async fn send_packet(&mut self, packet: &Packet) -> Result<()> {
-        let bytes = packet.to_bytes()?;
+        let bytes = packet.encode(self.encoding)?;
 
         if bytes.len() > MAX_BT_PACKET_SIZE {
             return Err(ProtocolError::InvalidPacket(format!(
@@ -265,7 +299,8 @@ impl Transport for BluetoothConnection {
             })?
             .map_err(ProtocolError::Io)?;
 
-        let packet = Packet::from_bytes(&packet_data)?;
+        // Inflate compressed bodies so callers always see plain packets
+        let packet = Packet::decode(&packet_data, self.encoding)?.decompressed()?;
         debug!(
             "Received packet type '{}' from {}",
             packet.packet_type, self.remote_address_str
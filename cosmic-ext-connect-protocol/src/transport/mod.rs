@@ -7,8 +7,10 @@
 //! through a common trait interface.
 
 pub mod bluetooth;
+pub mod selector;
 pub mod tcp;
 mod r#trait;
+pub mod upgrade;
 
 // TLS modules removed - now using cosmic-ext-connect-core
 // Old OpenSSL-based TLS moved to cosmic-ext-connect-core with rustls
@@ -23,7 +25,11 @@ pub use r#trait::{
     LatencyCategory, Transport, TransportAddress, TransportCapabilities, TransportFactory,
     TransportPreference, TransportType,
 };
-pub use tcp::{TcpConnection, TcpTransportFactory};
+pub use selector::{
+    PreferenceSelector, SelectionContext, SelectionInput, TransportCandidate, TransportSelector,
+};
+pub use tcp::{TcpConnection, TcpSocketOptions, TcpTransportFactory};
+pub use upgrade::{SessionChange, TransportUpgrades};
 
 // TLS types now re-exported from cosmic-ext-connect-core in lib.rs
 // pub use cosmic_ext_connect_core::crypto::{TlsConnection, TlsServer, TlsConfig};
@@ -0,0 +1,241 @@
+//! Transport Selection Strategy
+//!
+//! Decides the order in which [`TransportManager`](crate::TransportManager)
+//! tries transports when connecting to a device. The default,
+//! [`PreferenceSelector`], follows the configured [`TransportPreference`] and
+//! the address type. Integrators can plug in their own [`TransportSelector`],
+//! for example to prefer Bluetooth while the network is metered.
+//!
+//! A selector sees every enabled transport with its capabilities and last
+//! measured latency, plus a [`SelectionContext`] describing the device's
+//! situation. It returns transports in the order they should be tried; the
+//! manager only goes past the first when auto-fallback is enabled.
+
+use super::r#trait::{TransportAddress, TransportCapabilities, TransportPreference, TransportType};
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// Conditions a selector may take into account
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SelectionContext {
+    /// The network connection is metered (e.g. cellular)
+    pub metered: bool,
+    /// Running on battery power
+    pub on_battery: bool,
+}
+
+/// A transport that can be used for the connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransportCandidate {
+    /// Which transport
+    pub transport_type: TransportType,
+    /// What it can do
+    pub capabilities: TransportCapabilities,
+    /// Last measured round-trip latency, if any
+    pub latency: Option<Duration>,
+}
+
+/// Everything a selector decides on
+#[derive(Debug, Clone, Copy)]
+pub struct SelectionInput<'a> {
+    /// Address being connected to
+    pub address: &'a TransportAddress,
+    /// Configured preference
+    pub preference: TransportPreference,
+    /// Whether a failed transport may be followed by another
+    pub auto_fallback: bool,
+    /// Enabled transports
+    pub candidates: &'a [TransportCandidate],
+    /// Current conditions
+    pub context: SelectionContext,
+}
+
+impl SelectionInput<'_> {
+    /// Candidate entry for a transport, if it is enabled
+    pub fn candidate(&self, transport_type: TransportType) -> Option<&TransportCandidate> {
+        self.candidates
+            .iter()
+            .find(|candidate| candidate.transport_type == transport_type)
+    }
+}
+
+/// Strategy ordering transports for a connection attempt
+pub trait TransportSelector: Send + Sync + Debug {
+    /// Transports to try, most preferred first
+    ///
+    /// An empty list means no transport should be attempted.
+    fn select(&self, input: &SelectionInput<'_>) -> Vec<TransportType>;
+}
+
+/// The built-in strategy: configured preference, then address type
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PreferenceSelector;
+
+impl PreferenceSelector {
+    /// Transport to try first
+    fn primary(preference: TransportPreference, address: &TransportAddress) -> TransportType {
+        match (preference, address) {
+            // Explicit "Only" preferences
+            (TransportPreference::Only(t), _) => t,
+
+            // For TCP addresses, prefer TCP unless explicitly configured otherwise
+            (_, TransportAddress::Tcp(_)) => match preference {
+                TransportPreference::PreferBluetooth => TransportType::Bluetooth,
+                TransportPreference::BluetoothFirst => TransportType::Bluetooth,
+                _ => TransportType::Tcp,
+            },
+
+            // For Bluetooth addresses, prefer Bluetooth unless explicitly configured otherwise
+            (_, TransportAddress::Bluetooth { .. }) => match preference {
+                TransportPreference::PreferTcp => TransportType::Tcp,
+                TransportPreference::TcpFirst => TransportType::Tcp,
+                _ => TransportType::Bluetooth,
+            },
+        }
+    }
+
+    /// Fallback transport, if the preference allows one for this address
+    fn secondary(
+        preference: TransportPreference,
+        address: &TransportAddress,
+    ) -> Option<TransportType> {
+        match (preference, address) {
+            // "Only" preferences have no fallback
+            (TransportPreference::Only(_), _) => None,
+
+            // TcpFirst/BluetoothFirst always have a fallback
+            (TransportPreference::TcpFirst, _) => Some(TransportType::Bluetooth),
+            (TransportPreference::BluetoothFirst, _) => Some(TransportType::Tcp),
+
+            // Prefer* might have fallback based on address
+            (TransportPreference::PreferTcp, TransportAddress::Tcp(_)) => {
+                Some(TransportType::Bluetooth)
+            }
+            (TransportPreference::PreferBluetooth, TransportAddress::Bluetooth { .. }) => {
+                Some(TransportType::Tcp)
+            }
+
+            _ => None,
+        }
+    }
+}
+
+impl TransportSelector for PreferenceSelector {
+    fn select(&self, input: &SelectionInput<'_>) -> Vec<TransportType> {
+        let mut order = vec![Self::primary(input.preference, input.address)];
+        if input.auto_fallback {
+            order.extend(Self::secondary(input.preference, input.address));
+        }
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::LatencyCategory;
+
+    fn candidate(transport_type: TransportType) -> TransportCandidate {
+        TransportCandidate {
+            transport_type,
+            capabilities: TransportCapabilities {
+                max_packet_size: 512,
+                reliable: true,
+                connection_oriented: true,
+                latency: LatencyCategory::Medium,
+                multiplexed: false,
+                msgpack: true,
+                payload_streams: 1,
+            },
+            latency: None,
+        }
+    }
+
+    fn input<'a>(
+        address: &'a TransportAddress,
+        preference: TransportPreference,
+        candidates: &'a [TransportCandidate],
+    ) -> SelectionInput<'a> {
+        SelectionInput {
+            address,
+            preference,
+            auto_fallback: true,
+            candidates,
+            context: SelectionContext::default(),
+        }
+    }
+
+    #[test]
+    fn test_default_selector_matches_preference_table() {
+        use TransportPreference::*;
+        use TransportType::{Bluetooth as Bt, Tcp};
+
+        let tcp = TransportAddress::Tcp("192.168.1.2:1716".parse().unwrap());
+        let bt = TransportAddress::Bluetooth {
+            address: "00:11:22:33:44:55".to_string(),
+            service_uuid: None,
+        };
+        let candidates = [candidate(Tcp), candidate(Bt)];
+
+        let cases = [
+            (&tcp, PreferTcp, vec![Tcp, Bt]),
+            (&tcp, PreferBluetooth, vec![Bt]),
+            (&tcp, TcpFirst, vec![Tcp, Bt]),
+            (&tcp, BluetoothFirst, vec![Bt, Tcp]),
+            (&tcp, Only(Bt), vec![Bt]),
+            (&bt, PreferTcp, vec![Tcp]),
+            (&bt, PreferBluetooth, vec![Bt, Tcp]),
+            (&bt, TcpFirst, vec![Tcp, Bt]),
+            (&bt, Only(Tcp), vec![Tcp]),
+        ];
+        for (address, preference, expected) in cases {
+            let order = PreferenceSelector.select(&input(address, preference, &candidates));
+            assert_eq!(order, expected, "{} with {:?}", address, preference);
+        }
+
+        // Without auto-fallback only the primary is tried
+        let mut no_fallback = input(&tcp, TcpFirst, &candidates);
+        no_fallback.auto_fallback = false;
+        assert_eq!(PreferenceSelector.select(&no_fallback), vec![Tcp]);
+    }
+
+    /// Prefers Bluetooth whenever the network is metered
+    #[derive(Debug)]
+    struct MeteredSelector;
+
+    impl TransportSelector for MeteredSelector {
+        fn select(&self, input: &SelectionInput<'_>) -> Vec<TransportType> {
+            if input.context.metered && input.candidate(TransportType::Bluetooth).is_some() {
+                vec![TransportType::Bluetooth, TransportType::Tcp]
+            } else {
+                PreferenceSelector.select(input)
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_selector_overrides_ordering() {
+        let tcp = TransportAddress::Tcp("192.168.1.2:1716".parse().unwrap());
+        let candidates = [
+            candidate(TransportType::Tcp),
+            candidate(TransportType::Bluetooth),
+        ];
+        let mut metered = input(&tcp, TransportPreference::PreferTcp, &candidates);
+
+        assert_eq!(
+            MeteredSelector.select(&metered),
+            vec![TransportType::Tcp, TransportType::Bluetooth]
+        );
+
+        metered.context.metered = true;
+        assert_eq!(
+            MeteredSelector.select(&metered),
+            vec![TransportType::Bluetooth, TransportType::Tcp]
+        );
+
+        // Bluetooth not enabled: the custom rule does not apply
+        let tcp_only = [candidate(TransportType::Tcp)];
+        metered.candidates = &tcp_only;
+        assert_eq!(MeteredSelector.select(&metered)[0], TransportType::Tcp);
+    }
+}
@@ -1,6 +1,22 @@
 //! Basic TCP Transport for Pairing
 //!
 //! Simple TCP connection for exchanging pairing packets before TLS is established.
+//!
+//! ## Socket Options
+//!
+//! Every connection made through [`TcpConnection::connect_with_options`] or the
+//! [`TcpTransportFactory`] has its [`TcpSocketOptions`] applied before the first
+//! packet is sent. By default Nagle's algorithm is disabled, since packets are
+//! small and latency sensitive, and TCP keepalive is enabled so a peer that
+//! vanished without closing the socket is noticed by the kernel.
+//!
+//! ## IPv6
+//!
+//! Connections work over IPv4 and IPv6 alike. Link-local IPv6 addresses
+//! (`fe80::/10`) are only meaningful on one interface, so they must carry a
+//! scope ID; [`parse_host_addr`] accepts it as an interface index or name
+//! (`fe80::1%2`, `fe80::1%wlan0`). When a device is reachable at several
+//! addresses, [`order_by_route`] puts the lowest [`route_latency`] first.
 
 use crate::transport::{
     LatencyCategory, Transport, TransportAddress, TransportCapabilities, TransportFactory,
@@ -8,7 +24,9 @@ use crate::transport::{
 };
 use crate::{Packet, ProtocolError, Result};
 use async_trait::async_trait;
-use std::net::SocketAddr;
+use serde::{Deserialize, Serialize};
+use socket2::{SockRef, TcpKeepalive};
+use std::net::{IpAddr, SocketAddr, SocketAddrV6};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio::time::{timeout, Duration};
@@ -20,6 +38,172 @@ const TCP_TIMEOUT: Duration = Duration::from_secs(10);
 /// Maximum packet size (1MB)
 const MAX_PACKET_SIZE: usize = 1024 * 1024;
 
+/// Socket options applied to TCP connections
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TcpSocketOptions {
+    /// Send small packets immediately (TCP_NODELAY)
+    pub nodelay: bool,
+    /// Probe idle connections to detect dead peers (SO_KEEPALIVE)
+    pub keepalive: bool,
+    /// Idle time before the first keepalive probe
+    #[serde(with = "crate::config::duration_secs")]
+    pub keepalive_idle: Duration,
+    /// Time between unanswered keepalive probes
+    #[serde(with = "crate::config::duration_secs")]
+    pub keepalive_interval: Duration,
+    /// Unanswered probes before the connection is dropped
+    pub keepalive_probes: u32,
+    /// Send buffer size in bytes (None = system default)
+    pub send_buffer_size: Option<usize>,
+    /// Receive buffer size in bytes (None = system default)
+    pub recv_buffer_size: Option<usize>,
+}
+
+impl Default for TcpSocketOptions {
+    fn default() -> Self {
+        Self {
+            nodelay: true,
+            keepalive: true,
+            keepalive_idle: Duration::from_secs(60),
+            keepalive_interval: Duration::from_secs(10),
+            keepalive_probes: 5,
+            send_buffer_size: None,
+            recv_buffer_size: None,
+        }
+    }
+}
+
+impl TcpSocketOptions {
+    /// Apply the options to a connected stream
+    ///
+    /// The kernel may round buffer sizes up (Linux doubles them), so reading
+    /// them back can return more than was requested.
+    pub fn apply(&self, stream: &TcpStream) -> Result<()> {
+        let socket = SockRef::from(stream);
+        socket
+            .set_nodelay(self.nodelay)
+            .map_err(|e| ProtocolError::from_io_error(e, "setting TCP_NODELAY"))?;
+
+        if self.keepalive {
+            let keepalive = TcpKeepalive::new().with_time(self.keepalive_idle);
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            let keepalive = keepalive
+                .with_interval(self.keepalive_interval)
+                .with_retries(self.keepalive_probes);
+            socket
+                .set_tcp_keepalive(&keepalive)
+                .map_err(|e| ProtocolError::from_io_error(e, "enabling TCP keepalive"))?;
+        } else {
+            socket
+                .set_keepalive(false)
+                .map_err(|e| ProtocolError::from_io_error(e, "disabling TCP keepalive"))?;
+        }
+
+        if let Some(size) = self.send_buffer_size {
+            socket
+                .set_send_buffer_size(size)
+                .map_err(|e| ProtocolError::from_io_error(e, "setting send buffer size"))?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket
+                .set_recv_buffer_size(size)
+                .map_err(|e| ProtocolError::from_io_error(e, "setting receive buffer size"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a host and port into a socket address
+///
+/// Accepts IPv4 and IPv6 literals, the latter with or without brackets and
+/// with an optional scope ID. Returns `None` for anything else, e.g. host
+/// names that need DNS resolution.
+pub fn parse_host_addr(host: &str, port: u16) -> Option<SocketAddr> {
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    let (ip, scope) = match host.split_once('%') {
+        Some((ip, scope)) => (ip, Some(scope)),
+        None => (host, None),
+    };
+    match (ip.parse::<IpAddr>().ok()?, scope) {
+        (IpAddr::V4(ip), None) => Some(SocketAddr::new(IpAddr::V4(ip), port)),
+        (IpAddr::V4(_), Some(_)) => None,
+        (IpAddr::V6(ip), scope) => {
+            let scope_id = match scope {
+                Some(scope) => parse_scope_id(scope)?,
+                None => 0,
+            };
+            Some(SocketAddr::V6(SocketAddrV6::new(ip, port, 0, scope_id)))
+        }
+    }
+}
+
+/// Parse `ip:port` or `[ip%scope]:port` into a socket address
+pub fn parse_socket_addr(addr: &str) -> Option<SocketAddr> {
+    let (host, port) = addr.rsplit_once(':')?;
+    // Without brackets the port of an IPv6 literal is ambiguous
+    if host.contains(':') && !host.starts_with('[') {
+        return None;
+    }
+    parse_host_addr(host, port.parse().ok()?)
+}
+
+/// Interface index of a scope given as a number or an interface name
+fn parse_scope_id(scope: &str) -> Option<u32> {
+    scope
+        .parse()
+        .ok()
+        .or_else(|| nix::net::if_::if_nametoindex(scope).ok())
+}
+
+/// Host part of `addr` as stored for a device, keeping the IPv6 scope
+///
+/// The result round-trips through [`parse_host_addr`].
+pub fn host_string(addr: &SocketAddr) -> String {
+    match addr {
+        SocketAddr::V6(v6) if v6.scope_id() != 0 => format!("{}%{}", v6.ip(), v6.scope_id()),
+        _ => addr.ip().to_string(),
+    }
+}
+
+/// Whether `addr` is an IPv6 link-local address (`fe80::/10`)
+fn is_link_local_v6(addr: &SocketAddrV6) -> bool {
+    addr.ip().segments()[0] & 0xffc0 == 0xfe80
+}
+
+/// Expected latency of the route to `addr`
+///
+/// Loopback, link-local and private (RFC 1918, unique local) addresses are on
+/// the local network; anything else may be routed further away.
+pub fn route_latency(addr: &SocketAddr) -> LatencyCategory {
+    let local = match addr {
+        SocketAddr::V4(v4) => {
+            let ip = v4.ip();
+            ip.is_loopback() || ip.is_private() || ip.is_link_local()
+        }
+        SocketAddr::V6(v6) => {
+            v6.ip().is_loopback()
+                || is_link_local_v6(v6)
+                || v6.ip().segments()[0] & 0xfe00 == 0xfc00
+        }
+    };
+    if local {
+        LatencyCategory::Low
+    } else {
+        LatencyCategory::Medium
+    }
+}
+
+/// Sort a device's addresses by [`route_latency`], IPv4 first on a tie
+///
+/// The sort is stable, so otherwise equal addresses keep their order.
+pub fn order_by_route(addresses: &mut [SocketAddr]) {
+    addresses.sort_by_key(|addr| (route_latency(addr), addr.is_ipv6()));
+}
+
 /// Simple TCP connection for pairing
 #[derive(Debug)]
 pub struct TcpConnection {
@@ -28,13 +212,30 @@ pub struct TcpConnection {
 }
 
 impl TcpConnection {
-    /// Connect to a remote device
+    /// Connect to a remote device with the default socket options
     ///
     /// # Arguments
     ///
     /// * `addr` - Remote socket address (IP:port)
     pub async fn connect(addr: SocketAddr) -> Result<Self> {
+        Self::connect_with_options(addr, &TcpSocketOptions::default()).await
+    }
+
+    /// Connect to a remote device, applying `options` to the socket
+    pub async fn connect_with_options(
+        addr: SocketAddr,
+        options: &TcpSocketOptions,
+    ) -> Result<Self> {
         debug!("Connecting to {}", addr);
+        if let SocketAddr::V6(v6) = addr {
+            if is_link_local_v6(&v6) && v6.scope_id() == 0 {
+                return Err(ProtocolError::NetworkUnreachable(format!(
+                    "link-local address {} needs a scope ID, e.g. {}%eth0",
+                    addr,
+                    v6.ip()
+                )));
+            }
+        }
 
         let stream = timeout(TCP_TIMEOUT, TcpStream::connect(addr))
             .await
@@ -44,6 +245,7 @@ impl TcpConnection {
                     "Connection timeout",
                 ))
             })??;
+        options.apply(&stream)?;
 
         debug!("Connected to {}", addr);
 
@@ -130,6 +332,11 @@ impl TcpConnection {
         Ok(packet)
     }
 
+    /// Underlying stream, for inspecting socket state
+    pub fn stream(&self) -> &TcpStream {
+        &self.stream
+    }
+
     /// Get remote address
     pub fn remote_addr(&self) -> SocketAddr {
         self.remote_addr
@@ -143,20 +350,29 @@ impl TcpConnection {
     }
 }
 
+/// Capabilities of every TCP connection
+pub(crate) const TCP_CAPABILITIES: TransportCapabilities = TransportCapabilities {
+    // TCP can handle large packets
+    max_packet_size: MAX_PACKET_SIZE,
+    // TCP is reliable
+    reliable: true,
+    // TCP is connection-oriented
+    connection_oriented: true,
+    // TCP typically has low latency on local network
+    latency: LatencyCategory::Low,
+    // A single byte stream: concurrent transfers interleave
+    multiplexed: false,
+    // Newline framing shared with standard clients
+    msgpack: false,
+    // Payload transfers can open several sockets to fill long-latency links
+    payload_streams: crate::payload::MAX_PAYLOAD_STREAMS,
+};
+
 // Implement Transport trait for TcpConnection
 #[async_trait]
 impl Transport for TcpConnection {
     fn capabilities(&self) -> TransportCapabilities {
-        TransportCapabilities {
-            // TCP can handle large packets
-            max_packet_size: MAX_PACKET_SIZE,
-            // TCP is reliable
-            reliable: true,
-            // TCP is connection-oriented
-            connection_oriented: true,
-            // TCP typically has low latency on local network
-            latency: LatencyCategory::Low,
-        }
+        TCP_CAPABILITIES
     }
 
     fn remote_address(&self) -> TransportAddress {
@@ -253,12 +469,22 @@ impl Transport for TcpConnection {
 
 /// Factory for creating TCP connections
 #[derive(Debug, Clone)]
-pub struct TcpTransportFactory;
+pub struct TcpTransportFactory {
+    socket_options: TcpSocketOptions,
+}
 
 impl TcpTransportFactory {
     /// Create a new TCP transport factory
     pub fn new() -> Self {
-        Self
+        Self {
+            socket_options: TcpSocketOptions::default(),
+        }
+    }
+
+    /// Apply `options` to every connection this factory creates
+    pub fn with_socket_options(mut self, options: TcpSocketOptions) -> Self {
+        self.socket_options = options;
+        self
     }
 }
 
@@ -273,7 +499,8 @@ impl TransportFactory for TcpTransportFactory {
     async fn connect(&self, address: TransportAddress) -> Result<Box<dyn Transport>> {
         match address {
             TransportAddress::Tcp(addr) => {
-                let connection = TcpConnection::connect(addr).await?;
+                let connection =
+                    TcpConnection::connect_with_options(addr, &self.socket_options).await?;
                 Ok(Box::new(connection))
             }
             _ => Err(ProtocolError::InvalidPacket(
@@ -332,4 +559,128 @@ mod tests {
         let result = TcpConnection::connect("127.0.0.1:1".parse().unwrap()).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_default_socket_options_are_applied() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = TcpConnection::connect(addr).await.unwrap();
+        let socket = SockRef::from(client.stream());
+        assert!(socket.nodelay().unwrap());
+        assert!(socket.keepalive().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_configured_socket_options_are_honored() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let options = TcpSocketOptions {
+            nodelay: false,
+            keepalive_idle: Duration::from_secs(30),
+            keepalive_interval: Duration::from_secs(7),
+            keepalive_probes: 3,
+            send_buffer_size: Some(64 * 1024),
+            recv_buffer_size: Some(128 * 1024),
+            ..Default::default()
+        };
+
+        let client = TcpConnection::connect_with_options(addr, &options)
+            .await
+            .unwrap();
+        let socket = SockRef::from(client.stream());
+        assert!(!socket.nodelay().unwrap());
+        assert!(socket.keepalive().unwrap());
+        assert!(socket.send_buffer_size().unwrap() >= 64 * 1024);
+        assert!(socket.recv_buffer_size().unwrap() >= 128 * 1024);
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            assert_eq!(socket.keepalive_time().unwrap(), Duration::from_secs(30));
+            assert_eq!(socket.keepalive_interval().unwrap(), Duration::from_secs(7));
+            assert_eq!(socket.keepalive_retries().unwrap(), 3);
+        }
+
+        let plain = TcpConnection::connect_with_options(
+            addr,
+            &TcpSocketOptions {
+                keepalive: false,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(!SockRef::from(plain.stream()).keepalive().unwrap());
+    }
+
+    #[test]
+    fn test_parse_ipv6_addresses() {
+        assert_eq!(
+            parse_socket_addr("[::1]:1816"),
+            Some("[::1]:1816".parse().unwrap())
+        );
+        assert_eq!(
+            parse_socket_addr("192.168.1.5:1816"),
+            Some("192.168.1.5:1816".parse().unwrap())
+        );
+        assert_eq!(parse_socket_addr("::1:1816"), None);
+        assert_eq!(parse_host_addr("phone.local", 1816), None);
+        assert_eq!(parse_host_addr("192.168.1.5%2", 1816), None);
+
+        let scoped = parse_host_addr("fe80::1%3", 1816).unwrap();
+        let SocketAddr::V6(v6) = scoped else {
+            panic!("expected IPv6");
+        };
+        assert_eq!(v6.scope_id(), 3);
+        assert_eq!(host_string(&scoped), "fe80::1%3");
+        assert_eq!(parse_host_addr(&host_string(&scoped), 1816), Some(scoped));
+        assert_eq!(parse_socket_addr("[fe80::1%3]:1816"), Some(scoped));
+
+        // Interface names resolve to their index
+        let named = parse_host_addr("fe80::1%lo", 1816);
+        assert!(matches!(named, Some(SocketAddr::V6(v6)) if v6.scope_id() != 0));
+    }
+
+    #[test]
+    fn test_addresses_ordered_by_route_latency() {
+        let mut addresses: Vec<SocketAddr> = vec![
+            "[2001:db8::5]:1816".parse().unwrap(),
+            "[fe80::5%2]:1816".parse().unwrap(),
+            "203.0.113.5:1816".parse().unwrap(),
+            "192.168.1.5:1816".parse().unwrap(),
+        ];
+        order_by_route(&mut addresses);
+        assert_eq!(
+            addresses,
+            vec![
+                "192.168.1.5:1816".parse::<SocketAddr>().unwrap(),
+                "[fe80::5%2]:1816".parse().unwrap(),
+                "203.0.113.5:1816".parse().unwrap(),
+                "[2001:db8::5]:1816".parse().unwrap(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_over_ipv6_loopback() {
+        let Ok(listener) = TcpListener::bind("[::1]:0").await else {
+            // No IPv6 on this host
+            return;
+        };
+        let port = listener.local_addr().unwrap().port();
+        let address: TransportAddress = format!("tcp://[::1]:{}", port).parse().unwrap();
+        let accept = tokio::spawn(async move { listener.accept().await.unwrap() });
+
+        let connection = TcpTransportFactory::new().connect(address).await.unwrap();
+        assert!(connection
+            .remote_address()
+            .to_string()
+            .starts_with("tcp://[::1]:"));
+        accept.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_link_local_without_scope_rejected() {
+        let result = TcpConnection::connect("[fe80::1]:1816".parse().unwrap()).await;
+        assert!(matches!(result, Err(ProtocolError::NetworkUnreachable(_))));
+    }
 }
@@ -3,7 +3,7 @@
 //! Defines a common interface for different transport types (TCP, Bluetooth, etc.)
 //! that can be used to send and receive CConnect packets.
 
-use crate::{Packet, Result};
+use crate::{Packet, PacketEncoding, ProtocolError, Result};
 use async_trait::async_trait;
 use std::fmt::Debug;
 
@@ -21,10 +21,44 @@ pub struct TransportCapabilities {
 
     /// Typical latency category
     pub latency: LatencyCategory,
+
+    /// Whether independent streams can share the connection without one
+    /// holding up the other (e.g. QUIC or a stream multiplexer)
+    pub multiplexed: bool,
+
+    /// Whether packets may be sent as MessagePack to peers that decode it
+    pub msgpack: bool,
+
+    /// Most parallel connections one payload transfer may open (1 = single stream)
+    pub payload_streams: usize,
 }
 
-/// Latency categories for transports
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl TransportCapabilities {
+    /// Encoding to send packets in to a peer that decodes `peer_encodings`
+    ///
+    /// MessagePack is only used if both this transport and the peer allow
+    /// it; everything else gets JSON.
+    pub fn negotiate_encoding(&self, peer_encodings: &[PacketEncoding]) -> PacketEncoding {
+        if self.msgpack && peer_encodings.contains(&PacketEncoding::MessagePack) {
+            PacketEncoding::MessagePack
+        } else {
+            PacketEncoding::Json
+        }
+    }
+
+    /// Number of parallel payload streams to use with a peer
+    ///
+    /// `requested` is what the sender asked for and `peer_streams` what the
+    /// peer advertised (see
+    /// [`advertised_payload_streams`](crate::payload::advertised_payload_streams)).
+    /// Falls back to a single stream unless both sides support more.
+    pub fn negotiate_payload_streams(&self, requested: usize, peer_streams: usize) -> usize {
+        requested.min(self.payload_streams).min(peer_streams).max(1)
+    }
+}
+
+/// Latency categories for transports, ordered from lowest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LatencyCategory {
     /// Low latency (< 10ms typical)
     Low,
@@ -70,6 +104,29 @@ impl std::fmt::Display for TransportAddress {
     }
 }
 
+/// Parses `tcp://ip:port`, `bluetooth://MAC` or a bare `ip:port`
+///
+/// IPv6 addresses go in brackets and may carry a scope ID, as in
+/// `tcp://[fe80::1%wlan0]:1816`.
+impl std::str::FromStr for TransportAddress {
+    type Err = ProtocolError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(address) = s.strip_prefix("bluetooth://") {
+            return Ok(TransportAddress::Bluetooth {
+                address: address.to_string(),
+                service_uuid: None,
+            });
+        }
+        let addr = s.strip_prefix("tcp://").unwrap_or(s);
+        super::tcp::parse_socket_addr(addr)
+            .map(TransportAddress::Tcp)
+            .ok_or_else(|| {
+                ProtocolError::Configuration(format!("Invalid transport address: {}", s))
+            })
+    }
+}
+
 /// Common transport interface for CConnect
 #[async_trait]
 pub trait Transport: Send + Sync + Debug {
@@ -181,6 +238,76 @@ mod tests {
         assert_eq!(bt_addr.to_string(), "bluetooth://00:11:22:33:44:55");
     }
 
+    #[test]
+    fn test_transport_address_parse_round_trip() {
+        for text in [
+            "tcp://192.168.1.100:1716",
+            "tcp://[::1]:1816",
+            "tcp://[fe80::1%2]:1816",
+            "bluetooth://00:11:22:33:44:55",
+        ] {
+            let address: TransportAddress = text.parse().unwrap();
+            assert_eq!(address.to_string(), text);
+        }
+        assert!("tcp://fe80::1".parse::<TransportAddress>().is_err());
+    }
+
+    #[test]
+    fn test_encoding_negotiation() {
+        let constrained = TransportCapabilities {
+            max_packet_size: 512,
+            reliable: true,
+            connection_oriented: true,
+            latency: LatencyCategory::Medium,
+            multiplexed: false,
+            msgpack: true,
+            payload_streams: 1,
+        };
+        let both = [PacketEncoding::Json, PacketEncoding::MessagePack];
+
+        assert_eq!(
+            constrained.negotiate_encoding(&both),
+            PacketEncoding::MessagePack
+        );
+        // A standard client only speaks JSON
+        assert_eq!(
+            constrained.negotiate_encoding(&[PacketEncoding::Json]),
+            PacketEncoding::Json
+        );
+        let json_only_link = TransportCapabilities {
+            msgpack: false,
+            ..constrained
+        };
+        assert_eq!(
+            json_only_link.negotiate_encoding(&both),
+            PacketEncoding::Json
+        );
+    }
+
+    #[test]
+    fn test_payload_stream_negotiation() {
+        let tcp = TransportCapabilities {
+            max_packet_size: 1024,
+            reliable: true,
+            connection_oriented: true,
+            latency: LatencyCategory::Low,
+            multiplexed: false,
+            msgpack: false,
+            payload_streams: 8,
+        };
+        assert_eq!(tcp.negotiate_payload_streams(4, 8), 4);
+        // A peer that does not advertise streams gets one
+        assert_eq!(tcp.negotiate_payload_streams(4, 1), 1);
+        assert_eq!(tcp.negotiate_payload_streams(16, 16), 8);
+        assert_eq!(tcp.negotiate_payload_streams(0, 8), 1);
+
+        let rfcomm = TransportCapabilities {
+            payload_streams: 1,
+            ..tcp
+        };
+        assert_eq!(rfcomm.negotiate_payload_streams(4, 8), 1);
+    }
+
     #[test]
     fn test_transport_type_display() {
         assert_eq!(TransportType::Tcp.to_string(), "TCP");
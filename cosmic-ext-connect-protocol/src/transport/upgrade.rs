@@ -0,0 +1,471 @@
+//! Bluetooth to WiFi Upgrade
+//!
+//! Bluetooth needs no shared network, which makes it a good channel for first
+//! contact, but it is slow for everything that follows. Pairing packets sent
+//! over Bluetooth therefore carry the sender's WiFi addresses in a
+//! `wifiAddresses` field. Once the pairing completes, a device that is only
+//! reachable over Bluetooth but announced a WiFi address is connected over TCP
+//! as well, and traffic moves there. The Bluetooth connection stays open as a
+//! fallback for when the TCP connection drops.
+//!
+//! [`TransportUpgrades`] tracks, per device, which transports are connected
+//! and which WiFi addresses were learned, and decides when to upgrade.
+//!
+//! ## Session Switching
+//!
+//! A device connected over several transports has one active transport that
+//! carries its traffic. Transports are ranked by the configured
+//! [`TransportPreference`] (see [`transport_ranking`]). When a better-ranked
+//! transport connects, [`TransportUpgrades::pending_switch`] reports the move
+//! and [`TransportUpgrades::switch_to`] makes it. When the active transport
+//! closes, the session fails over to the best one still connected, so only
+//! the last transport closing ends the device's session.
+
+use super::r#trait::{TransportPreference, TransportType};
+use crate::Packet;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+
+/// Pairing packet field listing the sender's WiFi addresses (`"ip:port"`)
+pub const WIFI_ADDRESSES_FIELD: &str = "wifiAddresses";
+
+/// Add our WiFi addresses to an outgoing pairing packet
+pub fn attach_wifi_addresses(packet: &mut Packet, addresses: &[SocketAddr]) {
+    if addresses.is_empty() {
+        return;
+    }
+    let addresses: Vec<String> = addresses.iter().map(|addr| addr.to_string()).collect();
+    packet.body[WIFI_ADDRESSES_FIELD] = json!(addresses);
+}
+
+/// WiFi addresses announced in a packet, skipping malformed entries
+pub fn wifi_addresses(packet: &Packet) -> Vec<SocketAddr> {
+    packet
+        .body
+        .get(WIFI_ADDRESSES_FIELD)
+        .and_then(|value| value.as_array())
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| entry.as_str()?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Transports in the order [`TransportPreference`] ranks them, best first
+///
+/// A transport left out (with [`TransportPreference::Only`]) still carries
+/// traffic when nothing else is connected, but is never switched to.
+pub fn transport_ranking(preference: TransportPreference) -> Vec<TransportType> {
+    match preference {
+        TransportPreference::PreferTcp | TransportPreference::TcpFirst => {
+            vec![TransportType::Tcp, TransportType::Bluetooth]
+        }
+        TransportPreference::PreferBluetooth | TransportPreference::BluetoothFirst => {
+            vec![TransportType::Bluetooth, TransportType::Tcp]
+        }
+        TransportPreference::Only(transport) => vec![transport],
+    }
+}
+
+/// What a transport closing means for the device's session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionChange {
+    /// No transport is left; the device is gone
+    Ended,
+    /// The active transport closed and traffic moved to another one
+    FailedOver(TransportType),
+    /// A transport not carrying traffic closed
+    Unaffected,
+}
+
+#[derive(Debug, Default)]
+struct DeviceTransports {
+    connected: HashSet<TransportType>,
+    wifi_addresses: Vec<SocketAddr>,
+    /// Transport carrying the device's traffic
+    active: Option<TransportType>,
+}
+
+/// Per-device transport state used to decide on WiFi upgrades
+#[derive(Debug)]
+pub struct TransportUpgrades {
+    devices: HashMap<String, DeviceTransports>,
+    /// Transports in order of preference, best first
+    ranking: Vec<TransportType>,
+}
+
+impl Default for TransportUpgrades {
+    fn default() -> Self {
+        Self {
+            devices: HashMap::new(),
+            ranking: transport_ranking(TransportPreference::default()),
+        }
+    }
+}
+
+impl TransportUpgrades {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rank transports by `preference` instead of preferring TCP
+    pub fn with_preference(mut self, preference: TransportPreference) -> Self {
+        self.ranking = transport_ranking(preference);
+        self
+    }
+
+    /// Record that a device connected over `transport`
+    ///
+    /// Returns whether this starts the device's session, i.e. no other
+    /// transport was connected. The first transport becomes the active one.
+    pub fn connected(&mut self, device_id: &str, transport: TransportType) -> bool {
+        let device = self.device(device_id);
+        let first = device.connected.is_empty();
+        device.connected.insert(transport);
+        if first {
+            device.active = Some(transport);
+        }
+        first
+    }
+
+    /// Record that a device's `transport` connection closed
+    pub fn disconnected(&mut self, device_id: &str, transport: TransportType) -> SessionChange {
+        let ranking = &self.ranking;
+        let Some(device) = self.devices.get_mut(device_id) else {
+            return SessionChange::Ended;
+        };
+        device.connected.remove(&transport);
+        if device.connected.is_empty() {
+            device.active = None;
+            return SessionChange::Ended;
+        }
+        if device.active != Some(transport) {
+            return SessionChange::Unaffected;
+        }
+        device.active = Self::best(ranking, &device.connected);
+        device
+            .active
+            .map_or(SessionChange::Ended, SessionChange::FailedOver)
+    }
+
+    /// Devices connected over at least one transport
+    pub fn connected_devices(&self) -> Vec<String> {
+        self.devices
+            .iter()
+            .filter(|(_, device)| device.active.is_some())
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// Transport currently carrying a device's traffic
+    pub fn active_transport(&self, device_id: &str) -> Option<TransportType> {
+        self.devices.get(device_id)?.active
+    }
+
+    /// Switch the device should make, as `(from, to)`, if a better-ranked
+    /// transport than the active one is connected
+    pub fn pending_switch(&self, device_id: &str) -> Option<(TransportType, TransportType)> {
+        let from = self.active_transport(device_id)?;
+        let to = self.preferred_transport(device_id)?;
+        (from != to).then_some((from, to))
+    }
+
+    /// Move a device's traffic to `transport`, returning the previous one
+    ///
+    /// Does nothing and returns None unless `transport` is connected.
+    pub fn switch_to(
+        &mut self,
+        device_id: &str,
+        transport: TransportType,
+    ) -> Option<TransportType> {
+        let device = self.devices.get_mut(device_id)?;
+        if !device.connected.contains(&transport) {
+            return None;
+        }
+        device.active.replace(transport)
+    }
+
+    /// Learn WiFi addresses from a received pairing packet
+    ///
+    /// Only pairing requests and acceptances are considered. Returns whether
+    /// any address was learned.
+    pub fn observe_packet(&mut self, device_id: &str, packet: &Packet) -> bool {
+        if !packet.is_type("cconnect.pair") || packet.get_body_field::<bool>("pair") != Some(true) {
+            return false;
+        }
+        let addresses = wifi_addresses(packet);
+        if addresses.is_empty() {
+            return false;
+        }
+        self.learn_wifi_addresses(device_id, addresses);
+        true
+    }
+
+    /// Remember WiFi addresses for a device, replacing earlier ones
+    pub fn learn_wifi_addresses(&mut self, device_id: &str, addresses: Vec<SocketAddr>) {
+        self.device(device_id).wifi_addresses = addresses;
+    }
+
+    /// WiFi addresses known for a device
+    pub fn wifi_addresses(&self, device_id: &str) -> &[SocketAddr] {
+        self.devices
+            .get(device_id)
+            .map(|device| device.wifi_addresses.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Addresses to try for upgrading a device to TCP
+    ///
+    /// Empty unless the device is connected over Bluetooth only, has
+    /// announced a WiFi address and TCP ranks above Bluetooth.
+    pub fn upgrade_candidates(&self, device_id: &str) -> Vec<SocketAddr> {
+        if !self.ranks_above(TransportType::Tcp, TransportType::Bluetooth) {
+            return Vec::new();
+        }
+        match self.devices.get(device_id) {
+            Some(device)
+                if device.connected.contains(&TransportType::Bluetooth)
+                    && !device.connected.contains(&TransportType::Tcp) =>
+            {
+                device.wifi_addresses.clone()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Transport traffic to a device should use, if it is connected at all
+    ///
+    /// The best-ranked connected transport; by default TCP wins whenever it
+    /// is connected and Bluetooth is the fallback.
+    pub fn preferred_transport(&self, device_id: &str) -> Option<TransportType> {
+        let device = self.devices.get(device_id)?;
+        Self::best(&self.ranking, &device.connected)
+    }
+
+    /// Best-ranked transport in `connected`, or any if none is ranked
+    fn best(
+        ranking: &[TransportType],
+        connected: &HashSet<TransportType>,
+    ) -> Option<TransportType> {
+        ranking
+            .iter()
+            .chain(&[TransportType::Tcp, TransportType::Bluetooth])
+            .copied()
+            .find(|transport| connected.contains(transport))
+    }
+
+    /// Whether `better` is ranked and ranks above `worse`
+    fn ranks_above(&self, better: TransportType, worse: TransportType) -> bool {
+        let rank = |transport| self.ranking.iter().position(|t| *t == transport);
+        match (rank(better), rank(worse)) {
+            (Some(better), Some(worse)) => better < worse,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+
+    fn device(&mut self, device_id: &str) -> &mut DeviceTransports {
+        self.devices.entry(device_id.to_string()).or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pairing::PairingPacket;
+    use crate::transport::{LatencyCategory, Transport, TransportAddress, TransportCapabilities};
+    use crate::{ProtocolError, Result};
+    use tokio::sync::mpsc;
+
+    /// One end of an in-memory link posing as Bluetooth
+    #[derive(Debug)]
+    struct MockBluetooth {
+        tx: mpsc::UnboundedSender<Packet>,
+        rx: mpsc::UnboundedReceiver<Packet>,
+    }
+
+    fn bluetooth_pair() -> (MockBluetooth, MockBluetooth) {
+        let (a_tx, a_rx) = mpsc::unbounded_channel();
+        let (b_tx, b_rx) = mpsc::unbounded_channel();
+        (
+            MockBluetooth { tx: a_tx, rx: b_rx },
+            MockBluetooth { tx: b_tx, rx: a_rx },
+        )
+    }
+
+    #[async_trait::async_trait]
+    impl Transport for MockBluetooth {
+        fn capabilities(&self) -> TransportCapabilities {
+            TransportCapabilities {
+                max_packet_size: 512,
+                reliable: true,
+                connection_oriented: true,
+                latency: LatencyCategory::Medium,
+                multiplexed: false,
+                msgpack: true,
+                payload_streams: 1,
+            }
+        }
+
+        fn remote_address(&self) -> TransportAddress {
+            TransportAddress::Bluetooth {
+                address: "00:11:22:33:44:55".to_string(),
+                service_uuid: None,
+            }
+        }
+
+        async fn send_packet(&mut self, packet: &Packet) -> Result<()> {
+            self.tx
+                .send(packet.clone())
+                .map_err(|_| ProtocolError::Transport("peer closed".to_string()))
+        }
+
+        async fn receive_packet(&mut self) -> Result<Packet> {
+            self.rx
+                .recv()
+                .await
+                .ok_or_else(|| ProtocolError::Transport("peer closed".to_string()))
+        }
+
+        async fn close(self: Box<Self>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_wifi_addresses_round_trip() {
+        let addr: SocketAddr = "192.168.1.20:1814".parse().unwrap();
+        let mut packet = PairingPacket::request();
+        attach_wifi_addresses(&mut packet, &[addr]);
+        packet.body[WIFI_ADDRESSES_FIELD]
+            .as_array_mut()
+            .unwrap()
+            .push(json!("not an address"));
+
+        assert_eq!(wifi_addresses(&packet), vec![addr]);
+        assert!(wifi_addresses(&PairingPacket::request()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pairing_over_bluetooth_upgrades_to_tcp() {
+        let desktop_addr: SocketAddr = "192.168.1.10:1814".parse().unwrap();
+        let phone_addr: SocketAddr = "192.168.1.20:1814".parse().unwrap();
+        let (mut desktop, mut phone) = bluetooth_pair();
+        let mut desktop_view = TransportUpgrades::new();
+        let mut phone_view = TransportUpgrades::new();
+        desktop_view.connected("phone", TransportType::Bluetooth);
+        phone_view.connected("desktop", TransportType::Bluetooth);
+
+        // The whole pairing exchange happens over Bluetooth
+        let mut request = PairingPacket::request();
+        attach_wifi_addresses(&mut request, &[desktop_addr]);
+        desktop.send_packet(&request).await.unwrap();
+        let received = phone.receive_packet().await.unwrap();
+        assert!(phone_view.observe_packet("desktop", &received));
+
+        let mut accept = PairingPacket::accept();
+        attach_wifi_addresses(&mut accept, &[phone_addr]);
+        phone.send_packet(&accept).await.unwrap();
+        let received = desktop.receive_packet().await.unwrap();
+        assert!(desktop_view.observe_packet("phone", &received));
+
+        assert_eq!(
+            desktop_view.preferred_transport("phone"),
+            Some(TransportType::Bluetooth)
+        );
+        assert_eq!(desktop_view.upgrade_candidates("phone"), vec![phone_addr]);
+        assert_eq!(phone_view.upgrade_candidates("desktop"), vec![desktop_addr]);
+
+        // Upgrade done: TCP carries traffic, Bluetooth remains as fallback
+        desktop_view.connected("phone", TransportType::Tcp);
+        assert_eq!(
+            desktop_view.preferred_transport("phone"),
+            Some(TransportType::Tcp)
+        );
+        assert!(desktop_view.upgrade_candidates("phone").is_empty());
+
+        desktop_view.disconnected("phone", TransportType::Tcp);
+        assert_eq!(
+            desktop_view.preferred_transport("phone"),
+            Some(TransportType::Bluetooth)
+        );
+        assert_eq!(desktop_view.upgrade_candidates("phone"), vec![phone_addr]);
+    }
+
+    #[test]
+    fn test_session_fails_over_and_switches_back() {
+        let mut upgrades = TransportUpgrades::new();
+        assert!(upgrades.connected("phone", TransportType::Tcp));
+        assert!(!upgrades.connected("phone", TransportType::Bluetooth));
+        assert_eq!(upgrades.pending_switch("phone"), None);
+
+        // WiFi lost: Bluetooth carries on
+        assert_eq!(
+            upgrades.disconnected("phone", TransportType::Tcp),
+            SessionChange::FailedOver(TransportType::Bluetooth)
+        );
+        assert_eq!(
+            upgrades.active_transport("phone"),
+            Some(TransportType::Bluetooth)
+        );
+
+        // WiFi back: TCP takes over once switched to
+        assert!(!upgrades.connected("phone", TransportType::Tcp));
+        assert_eq!(
+            upgrades.pending_switch("phone"),
+            Some((TransportType::Bluetooth, TransportType::Tcp))
+        );
+        assert_eq!(
+            upgrades.switch_to("phone", TransportType::Tcp),
+            Some(TransportType::Bluetooth)
+        );
+        assert_eq!(upgrades.pending_switch("phone"), None);
+
+        assert_eq!(
+            upgrades.disconnected("phone", TransportType::Bluetooth),
+            SessionChange::Unaffected
+        );
+        assert_eq!(
+            upgrades.disconnected("phone", TransportType::Tcp),
+            SessionChange::Ended
+        );
+        assert!(upgrades.connected_devices().is_empty());
+    }
+
+    #[test]
+    fn test_ranking_follows_preference() {
+        let mut upgrades =
+            TransportUpgrades::new().with_preference(TransportPreference::PreferBluetooth);
+        upgrades.connected("phone", TransportType::Bluetooth);
+        upgrades.learn_wifi_addresses("phone", vec!["192.168.1.20:1814".parse().unwrap()]);
+        assert!(upgrades.upgrade_candidates("phone").is_empty());
+
+        upgrades.connected("phone", TransportType::Tcp);
+        assert_eq!(upgrades.pending_switch("phone"), None);
+
+        let mut upgrades = TransportUpgrades::new()
+            .with_preference(TransportPreference::Only(TransportType::Bluetooth));
+        upgrades.connected("phone", TransportType::Tcp);
+        upgrades.connected("phone", TransportType::Bluetooth);
+        assert_eq!(
+            upgrades.pending_switch("phone"),
+            Some((TransportType::Tcp, TransportType::Bluetooth))
+        );
+    }
+
+    #[test]
+    fn test_no_upgrade_without_wifi_address() {
+        let mut upgrades = TransportUpgrades::new();
+        upgrades.connected("phone", TransportType::Bluetooth);
+        assert!(!upgrades.observe_packet("phone", &PairingPacket::request()));
+
+        let mut reject = PairingPacket::reject();
+        attach_wifi_addresses(&mut reject, &["192.168.1.20:1814".parse().unwrap()]);
+        assert!(!upgrades.observe_packet("phone", &reject));
+        assert!(upgrades.upgrade_candidates("phone").is_empty());
+    }
+}
@@ -21,18 +21,67 @@
 //! - Transport availability
 //! - Connection address type
 //! - Auto-fallback settings
+//!
+//! The ordering comes from a [`TransportSelector`]; [`PreferenceSelector`] is
+//! used unless another one is set with [`TransportManager::with_selector`].
+//!
+//! ## Bluetooth to WiFi Upgrade
+//!
+//! Pairing packets sent over Bluetooth carry the addresses set with
+//! [`TransportManager::set_local_wifi_addresses`], and the peer's addresses are
+//! picked up from the pairing packets it sends back. After pairing,
+//! [`TransportManager::upgrade_to_wifi`] connects the device over TCP as well.
+//! Packets always go over TCP when it is connected, so the Bluetooth link is
+//! left open only as a fallback. See [`crate::transport::upgrade`].
+//!
+//! ## Transport Failover
+//!
+//! A device connected over both transports has a single session; its
+//! traffic goes over the active transport (see [`crate::transport::upgrade`]
+//! for how that is tracked). When the active transport drops, traffic moves
+//! to the other one and [`TransportManagerEvent::TransportSwitched`] is
+//! emitted instead of a disconnect. When a transport that ranks higher under
+//! the configured [`TransportPreference`] connects, traffic moves back to it
+//! the same way. Every [`TransportManagerConfig::migration_interval`] the
+//! manager also tries to reach such a transport for devices that are not on
+//! it, which for TCP means connecting to the WiFi addresses the device
+//! announced.
+//!
+//! The previous transport stays open as a fallback unless
+//! [`TransportManagerConfig::keep_fallback`] is off. Then it is closed once
+//! the packets already queued on it are sent. Payload transfers run on their
+//! own sockets and are not affected by the switch, and a TCP link is not
+//! closed while a transfer is still running on it.
+//!
+//! ## Multiple Addresses
+//!
+//! A device can be reachable at several addresses, e.g. over both IPv4 and
+//! IPv6. [`TransportManager::connect_addresses`] tries them in order of
+//! expected route latency until one connects.
 
 use crate::{
     bluetooth_connection_manager::BluetoothConnectionManager,
     connection::{ConnectionEvent, ConnectionManager},
-    transport::{TransportAddress, TransportPreference, TransportType},
+    transport::{
+        bluetooth::BLUETOOTH_CAPABILITIES,
+        tcp::{order_by_route, TCP_CAPABILITIES},
+        upgrade::{attach_wifi_addresses, SessionChange},
+        PreferenceSelector, SelectionContext, SelectionInput, TransportAddress, TransportCandidate,
+        TransportPreference, TransportSelector, TransportType, TransportUpgrades,
+    },
     Packet, Result,
 };
+use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{mpsc, RwLock};
+use tokio::task::JoinHandle;
 use tracing::{debug, info, warn};
 
+/// How long a transport being replaced may take to send what is queued on it
+const FLUSH_GRACE: Duration = Duration::from_secs(2);
+
 /// Transport manager configuration
 ///
 /// This configuration controls which transports are enabled and how they
@@ -59,6 +108,14 @@ pub struct TransportManagerConfig {
 
     /// Bluetooth device filtering (empty = no filter, accepts all)
     pub bluetooth_device_filter: Vec<String>,
+
+    /// How often connected devices are checked for a better transport to
+    /// move to (None = only when a transport connects)
+    pub migration_interval: Option<Duration>,
+
+    /// Keep the previous transport open as a fallback after a device moved
+    /// to a better one
+    pub keep_fallback: bool,
 }
 
 impl Default for TransportManagerConfig {
@@ -71,6 +128,8 @@ impl Default for TransportManagerConfig {
             bluetooth_timeout: Duration::from_secs(15),
             auto_fallback: true,
             bluetooth_device_filter: Vec::new(),
+            migration_interval: Some(Duration::from_secs(15)),
+            keep_fallback: true,
         }
     }
 }
@@ -104,6 +163,13 @@ pub enum TransportManagerEvent {
         transport_type: TransportType,
     },
 
+    /// A device's traffic moved to another transport; the session goes on
+    TransportSwitched {
+        device_id: String,
+        from: TransportType,
+        to: TransportType,
+    },
+
     /// An error occurred
     Error {
         transport_type: TransportType,
@@ -111,6 +177,170 @@ pub enum TransportManagerEvent {
     },
 }
 
+/// Handles needed to move a device's session between transports
+///
+/// Shared by the event forwarders and the migration task.
+#[derive(Clone)]
+struct SessionMigration {
+    tcp_manager: Arc<RwLock<ConnectionManager>>,
+    bluetooth_manager: Option<Arc<RwLock<BluetoothConnectionManager>>>,
+    upgrades: Arc<RwLock<TransportUpgrades>>,
+    event_tx: mpsc::UnboundedSender<TransportManagerEvent>,
+    enable_tcp: bool,
+    keep_fallback: bool,
+}
+
+impl SessionMigration {
+    /// Event to report for a device connecting over `transport_type`
+    ///
+    /// Only the first transport starts the session. A later one is switched
+    /// to in the background if it ranks higher, and reports nothing itself.
+    async fn opened(
+        &self,
+        device_id: String,
+        transport_type: TransportType,
+    ) -> Option<TransportManagerEvent> {
+        if self
+            .upgrades
+            .write()
+            .await
+            .connected(&device_id, transport_type)
+        {
+            return Some(TransportManagerEvent::Connected {
+                device_id,
+                transport_type,
+            });
+        }
+        debug!(
+            "{} is now also connected via {:?}",
+            device_id, transport_type
+        );
+        let migration = self.clone();
+        tokio::spawn(async move {
+            migration.migrate(&device_id).await;
+        });
+        None
+    }
+
+    /// Event to report for a device's `transport_type` connection closing
+    ///
+    /// A disconnect is only reported once no transport is left.
+    async fn closed(
+        &self,
+        device_id: String,
+        transport_type: TransportType,
+        reason: Option<String>,
+    ) -> Option<TransportManagerEvent> {
+        let change = self
+            .upgrades
+            .write()
+            .await
+            .disconnected(&device_id, transport_type);
+        match change {
+            SessionChange::Ended => Some(TransportManagerEvent::Disconnected {
+                device_id,
+                transport_type,
+                reason,
+            }),
+            SessionChange::FailedOver(to) => {
+                info!(
+                    "{:?} link to {} closed, continuing over {:?}",
+                    transport_type, device_id, to
+                );
+                Some(TransportManagerEvent::TransportSwitched {
+                    device_id,
+                    from: transport_type,
+                    to,
+                })
+            }
+            SessionChange::Unaffected => None,
+        }
+    }
+
+    /// Move a device to its best connected transport if it is not on it
+    ///
+    /// Returns the switch made as `(from, to)`.
+    async fn migrate(&self, device_id: &str) -> Option<(TransportType, TransportType)> {
+        let (from, to) = self.upgrades.read().await.pending_switch(device_id)?;
+
+        // Transfers are tied to the TCP connection and would end with it
+        if from == TransportType::Tcp
+            && !self.keep_fallback
+            && self
+                .tcp_manager
+                .read()
+                .await
+                .has_active_transfers(device_id)
+                .await
+        {
+            debug!("Not moving {} off TCP while a transfer runs", device_id);
+            return None;
+        }
+
+        self.upgrades.write().await.switch_to(device_id, to)?;
+        info!("Moved {} from {:?} to {:?}", device_id, from, to);
+        let _ = self
+            .event_tx
+            .send(TransportManagerEvent::TransportSwitched {
+                device_id: device_id.to_string(),
+                from,
+                to,
+            });
+
+        if !self.keep_fallback {
+            self.close(device_id, from).await;
+        }
+        Some((from, to))
+    }
+
+    /// Close a transport that no longer carries traffic, after what is
+    /// queued on it has been sent
+    async fn close(&self, device_id: &str, transport_type: TransportType) {
+        let result = match transport_type {
+            // The close command is queued behind any pending packets
+            TransportType::Tcp => self.tcp_manager.read().await.disconnect(device_id).await,
+            TransportType::Bluetooth => match &self.bluetooth_manager {
+                Some(bt_mgr) => {
+                    bt_mgr
+                        .read()
+                        .await
+                        .flush_and_disconnect(device_id, FLUSH_GRACE)
+                        .await
+                }
+                None => Ok(()),
+            },
+        };
+        if let Err(e) = result {
+            warn!(
+                "Failed to close {:?} link to {} after switching: {}",
+                transport_type, device_id, e
+            );
+        }
+    }
+
+    /// Try to connect a device over a better-ranked transport it lacks
+    ///
+    /// Only TCP can be reached this way, at the WiFi addresses the device
+    /// announced; a Bluetooth link has to come up on its own. The switch
+    /// happens once the new connection reports in.
+    async fn reach_preferred(&self, device_id: &str) {
+        if !self.enable_tcp {
+            return;
+        }
+        let candidates = self.upgrades.read().await.upgrade_candidates(device_id);
+        for addr in candidates {
+            let tcp_mgr = self.tcp_manager.read().await;
+            match tcp_mgr.connect(device_id, addr).await {
+                Ok(()) => {
+                    info!("Reached {} over TCP at {}", device_id, addr);
+                    return;
+                }
+                Err(e) => debug!("{} is not reachable at {}: {}", device_id, addr, e),
+            }
+        }
+    }
+}
+
 /// Transport manager for coordinating multiple transport types
 ///
 /// The TransportManager provides a unified interface for managing connections
@@ -134,6 +364,27 @@ pub struct TransportManager {
 
     /// Event channel receiver
     event_rx: Arc<RwLock<mpsc::UnboundedReceiver<TransportManagerEvent>>>,
+
+    /// Strategy ordering transports for new connections
+    selector: Arc<dyn TransportSelector>,
+
+    /// Conditions passed to the selector
+    selection_context: RwLock<SelectionContext>,
+
+    /// Last measured latency per transport
+    latencies: RwLock<HashMap<TransportType, Duration>>,
+
+    /// Connected transports and learned WiFi addresses per device
+    upgrades: Arc<RwLock<TransportUpgrades>>,
+
+    /// Our WiFi addresses, announced in pairing packets sent over Bluetooth
+    local_wifi_addresses: RwLock<Vec<SocketAddr>>,
+
+    /// Moves sessions between transports
+    migration: SessionMigration,
+
+    /// Periodic migration check task handle
+    migration_task: RwLock<Option<JoinHandle<()>>>,
 }
 
 impl TransportManager {
@@ -168,15 +419,40 @@ impl TransportManager {
             None
         };
 
+        let upgrades = Arc::new(RwLock::new(
+            TransportUpgrades::new().with_preference(config.preference),
+        ));
+        let migration = SessionMigration {
+            tcp_manager: tcp_manager.clone(),
+            bluetooth_manager: bluetooth_manager.clone(),
+            upgrades: upgrades.clone(),
+            event_tx: event_tx.clone(),
+            enable_tcp: config.enable_tcp,
+            keep_fallback: config.keep_fallback,
+        };
+
         Ok(Self {
             tcp_manager,
             bluetooth_manager,
             config,
             event_tx,
             event_rx: Arc::new(RwLock::new(event_rx)),
+            selector: Arc::new(PreferenceSelector),
+            selection_context: RwLock::new(SelectionContext::default()),
+            latencies: RwLock::new(HashMap::new()),
+            upgrades,
+            local_wifi_addresses: RwLock::new(Vec::new()),
+            migration,
+            migration_task: RwLock::new(None),
         })
     }
 
+    /// Use a custom strategy to order transports
+    pub fn with_selector(mut self, selector: Arc<dyn TransportSelector>) -> Self {
+        self.selector = selector;
+        self
+    }
+
     /// Start the transport manager
     ///
     /// This starts all enabled transport managers and begins listening for connections.
@@ -215,14 +491,36 @@ impl TransportManager {
             }
         }
 
+        if let Some(interval) = self.config.migration_interval {
+            self.start_migration_checks(interval).await;
+        }
+
         info!("Transport manager started successfully");
         Ok(())
     }
 
+    /// Periodically try to move connected devices to a better transport
+    async fn start_migration_checks(&self, interval: Duration) {
+        let migration = self.migration.clone();
+        let task = tokio::spawn(async move {
+            let mut timer = tokio::time::interval(interval);
+            loop {
+                timer.tick().await;
+                let devices = migration.upgrades.read().await.connected_devices();
+                for device_id in devices {
+                    migration.reach_preferred(&device_id).await;
+                    migration.migrate(&device_id).await;
+                }
+            }
+        });
+        *self.migration_task.write().await = Some(task);
+    }
+
     /// Forward TCP connection events to transport manager events
     async fn forward_tcp_events(&self) {
         let tcp_mgr = self.tcp_manager.clone();
         let event_tx = self.event_tx.clone();
+        let migration = self.migration.clone();
 
         tokio::spawn(async move {
             let mgr = tcp_mgr.read().await;
@@ -234,19 +532,23 @@ impl TransportManager {
                     ConnectionEvent::Connected {
                         device_id,
                         remote_addr: _,
-                    } => TransportManagerEvent::Connected {
-                        device_id,
-                        transport_type: TransportType::Tcp,
+                    } => match migration.opened(device_id, TransportType::Tcp).await {
+                        Some(event) => event,
+                        None => continue,
+                    },
+                    // Socket replaced: the new socket has already reported in
+                    ConnectionEvent::Disconnected {
+                        reconnect: true, ..
+                    } => continue,
+                    ConnectionEvent::Disconnected {
+                        device_id, reason, ..
+                    } => match migration
+                        .closed(device_id, TransportType::Tcp, reason)
+                        .await
+                    {
+                        Some(event) => event,
+                        None => continue,
                     },
-                    ConnectionEvent::Disconnected { device_id, reason, reconnect: _ } => {
-                        // Note: reconnect field is handled at the daemon level for plugin cleanup
-                        // Transport manager just forwards the disconnection event
-                        TransportManagerEvent::Disconnected {
-                            device_id,
-                            transport_type: TransportType::Tcp,
-                            reason,
-                        }
-                    }
                     ConnectionEvent::PacketReceived {
                         device_id,
                         packet,
@@ -256,12 +558,37 @@ impl TransportManager {
                         packet,
                         transport_type: TransportType::Tcp,
                     },
+                    ConnectionEvent::ConnectionRejected {
+                        remote_addr,
+                        reason,
+                        ..
+                    } => TransportManagerEvent::Error {
+                        transport_type: TransportType::Tcp,
+                        message: format!("Refused {}: {}", remote_addr, reason),
+                    },
+                    ConnectionEvent::CertificateMismatch {
+                        device_id,
+                        remote_addr,
+                        ..
+                    } => TransportManagerEvent::Error {
+                        transport_type: TransportType::Tcp,
+                        message: format!(
+                            "Refused {} at {}: certificate does not match the pinned one",
+                            device_id, remote_addr
+                        ),
+                    },
                     ConnectionEvent::ConnectionError { device_id, message } => {
                         TransportManagerEvent::Error {
                             transport_type: TransportType::Tcp,
                             message: format!("Device {:?}: {}", device_id, message),
                         }
                     }
+                    ConnectionEvent::TrafficUpdated { .. } => continue,
+                    ConnectionEvent::StateChanged { .. } => continue,
+                    ConnectionEvent::LinkFlapped { .. } => continue,
+                    ConnectionEvent::Timeout { .. } => continue,
+                    ConnectionEvent::CapabilitiesChanged { .. } => continue,
+                    ConnectionEvent::TaskCancelled { .. } => continue,
                     ConnectionEvent::ManagerStarted { .. } => continue,
                     ConnectionEvent::ManagerStopped => continue,
                 };
@@ -277,6 +604,8 @@ impl TransportManager {
     async fn forward_bluetooth_events(&self) {
         let bt_mgr = self.bluetooth_manager.as_ref().unwrap().clone();
         let event_tx = self.event_tx.clone();
+        let upgrades = self.upgrades.clone();
+        let migration = self.migration.clone();
 
         tokio::spawn(async move {
             let mgr = bt_mgr.read().await;
@@ -284,6 +613,34 @@ impl TransportManager {
             drop(mgr);
 
             while let Some(event) = bt_events.recv().await {
+                let event = match event {
+                    TransportManagerEvent::Connected { device_id, .. } => {
+                        match migration.opened(device_id, TransportType::Bluetooth).await {
+                            Some(event) => event,
+                            None => continue,
+                        }
+                    }
+                    TransportManagerEvent::Disconnected {
+                        device_id, reason, ..
+                    } => match migration
+                        .closed(device_id, TransportType::Bluetooth, reason)
+                        .await
+                    {
+                        Some(event) => event,
+                        None => continue,
+                    },
+                    TransportManagerEvent::PacketReceived {
+                        ref device_id,
+                        ref packet,
+                        ..
+                    } => {
+                        if upgrades.write().await.observe_packet(device_id, packet) {
+                            debug!("Learned WiFi addresses of {} over Bluetooth", device_id);
+                        }
+                        event
+                    }
+                    event => event,
+                };
                 if event_tx.send(event).is_err() {
                     break;
                 }
@@ -293,102 +650,175 @@ impl TransportManager {
 
     /// Connect to a device using the configured transport preference
     ///
-    /// This method selects the appropriate transport based on:
-    /// - The address type (TCP vs Bluetooth)
-    /// - The configured preference
-    /// - Transport availability
-    /// - Auto-fallback settings
+    /// Transports are tried in the order given by the
+    /// [`TransportSelector`]; later ones are only tried when auto-fallback is
+    /// enabled.
     pub async fn connect(&self, device_id: &str, address: TransportAddress) -> Result<()> {
         debug!(
             "Connecting to device {} using preference {:?}",
             device_id, self.config.preference
         );
 
-        // Determine which transport to try first based on address and preference
-        let primary_transport = self.select_primary_transport(&address);
-        let secondary_transport = self.select_secondary_transport(&address);
+        let mut order = self.transport_order(&address).await;
+        if !self.config.auto_fallback {
+            order.truncate(1);
+        }
 
-        // Try primary transport
-        match self
-            .connect_with_transport(device_id, &address, primary_transport)
-            .await
-        {
-            Ok(()) => {
-                info!("Connected to {} via {:?}", device_id, primary_transport);
-                Ok(())
-            }
-            Err(e) => {
-                warn!(
-                    "Failed to connect to {} via {:?}: {}",
-                    device_id, primary_transport, e
+        let mut last_error = None;
+        for transport_type in order {
+            if let Some(e) = &last_error {
+                info!(
+                    "Attempting fallback to {:?} for device {} after: {}",
+                    transport_type, device_id, e
                 );
+            }
 
-                // Try fallback if enabled and available
-                if self.config.auto_fallback {
-                    if let Some(fallback) = secondary_transport {
-                        info!(
-                            "Attempting fallback to {:?} for device {}",
-                            fallback, device_id
-                        );
-
-                        return self
-                            .connect_with_transport(device_id, &address, fallback)
-                            .await;
-                    }
+            match self
+                .connect_with_transport(device_id, &address, transport_type)
+                .await
+            {
+                Ok(()) => {
+                    info!("Connected to {} via {:?}", device_id, transport_type);
+                    return Ok(());
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to connect to {} via {:?}: {}",
+                        device_id, transport_type, e
+                    );
+                    last_error = Some(e);
                 }
-
-                // No fallback available or disabled
-                Err(e)
             }
         }
-    }
 
-    /// Select the primary transport to try based on preference and address
-    fn select_primary_transport(&self, address: &TransportAddress) -> TransportType {
-        match (&self.config.preference, address) {
-            // Explicit "Only" preferences
-            (TransportPreference::Only(t), _) => *t,
-
-            // For TCP addresses, prefer TCP unless explicitly configured otherwise
-            (_, TransportAddress::Tcp(_)) => match self.config.preference {
-                TransportPreference::PreferBluetooth => TransportType::Bluetooth,
-                TransportPreference::BluetoothFirst => TransportType::Bluetooth,
-                _ => TransportType::Tcp,
-            },
+        Err(last_error.unwrap_or_else(|| {
+            crate::ProtocolError::Transport(format!(
+                "No transport selected for device {}",
+                device_id
+            ))
+        }))
+    }
 
-            // For Bluetooth addresses, prefer Bluetooth unless explicitly configured otherwise
-            (_, TransportAddress::Bluetooth { .. }) => match self.config.preference {
-                TransportPreference::PreferTcp => TransportType::Tcp,
-                TransportPreference::TcpFirst => TransportType::Tcp,
-                _ => TransportType::Bluetooth,
-            },
+    /// Transports to try for `address`, most preferred first
+    ///
+    /// Asks the configured [`TransportSelector`] with the enabled transports,
+    /// their last measured latencies and the current [`SelectionContext`].
+    pub async fn transport_order(&self, address: &TransportAddress) -> Vec<TransportType> {
+        let latencies = self.latencies.read().await;
+        let mut candidates = Vec::new();
+        if self.config.enable_tcp {
+            candidates.push(TransportCandidate {
+                transport_type: TransportType::Tcp,
+                capabilities: TCP_CAPABILITIES,
+                latency: latencies.get(&TransportType::Tcp).copied(),
+            });
         }
+        if self.bluetooth_manager.is_some() {
+            candidates.push(TransportCandidate {
+                transport_type: TransportType::Bluetooth,
+                capabilities: BLUETOOTH_CAPABILITIES,
+                latency: latencies.get(&TransportType::Bluetooth).copied(),
+            });
+        }
+        drop(latencies);
+
+        let input = SelectionInput {
+            address,
+            preference: self.config.preference,
+            auto_fallback: self.config.auto_fallback,
+            candidates: &candidates,
+            context: *self.selection_context.read().await,
+        };
+        self.selector.select(&input)
     }
 
-    /// Select the secondary (fallback) transport if auto-fallback is enabled
-    fn select_secondary_transport(&self, address: &TransportAddress) -> Option<TransportType> {
-        if !self.config.auto_fallback {
-            return None;
-        }
+    /// Update the conditions passed to the transport selector
+    pub async fn set_selection_context(&self, context: SelectionContext) {
+        *self.selection_context.write().await = context;
+    }
+
+    /// Record a measured round-trip latency for a transport
+    pub async fn record_latency(&self, transport_type: TransportType, latency: Duration) {
+        self.latencies.write().await.insert(transport_type, latency);
+    }
 
-        match (&self.config.preference, address) {
-            // "Only" preferences have no fallback
-            (TransportPreference::Only(_), _) => None,
+    /// Set the WiFi addresses announced to devices pairing over Bluetooth
+    pub async fn set_local_wifi_addresses(&self, addresses: Vec<SocketAddr>) {
+        *self.local_wifi_addresses.write().await = addresses;
+    }
 
-            // TcpFirst/BluetoothFirst always have a fallback
-            (TransportPreference::TcpFirst, _) => Some(TransportType::Bluetooth),
-            (TransportPreference::BluetoothFirst, _) => Some(TransportType::Tcp),
+    /// Transport packets to a device currently go over, if it is connected
+    pub async fn preferred_transport(&self, device_id: &str) -> Option<TransportType> {
+        self.upgrades.read().await.active_transport(device_id)
+    }
 
-            // Prefer* might have fallback based on address
-            (TransportPreference::PreferTcp, TransportAddress::Tcp(_)) => {
-                Some(TransportType::Bluetooth)
-            }
-            (TransportPreference::PreferBluetooth, TransportAddress::Bluetooth { .. }) => {
-                Some(TransportType::Tcp)
+    /// Move a device to its best connected transport now, without waiting
+    /// for the next migration check
+    ///
+    /// Returns the switch made as `(from, to)`, or None if the device is
+    /// already on its best transport.
+    pub async fn migrate(&self, device_id: &str) -> Option<(TransportType, TransportType)> {
+        self.migration.migrate(device_id).await
+    }
+
+    /// Connect a Bluetooth-only device over TCP using its announced WiFi addresses
+    ///
+    /// Returns `Ok(false)` when there is nothing to upgrade: the device is not
+    /// connected over Bluetooth, is already on TCP, announced no address, or
+    /// the preference ranks Bluetooth higher. Traffic moves to TCP once the
+    /// connection reports in.
+    pub async fn upgrade_to_wifi(&self, device_id: &str) -> Result<bool> {
+        if !self.config.enable_tcp {
+            return Ok(false);
+        }
+        let candidates = self.upgrades.read().await.upgrade_candidates(device_id);
+
+        let mut last_error = None;
+        for addr in candidates {
+            let tcp_mgr = self.tcp_manager.read().await;
+            match tcp_mgr.connect(device_id, addr).await {
+                Ok(()) => {
+                    info!("Upgraded {} from Bluetooth to TCP at {}", device_id, addr);
+                    return Ok(true);
+                }
+                Err(e) => {
+                    debug!("WiFi upgrade of {} via {} failed: {}", device_id, addr, e);
+                    last_error = Some(e);
+                }
             }
+        }
 
-            _ => None,
+        match last_error {
+            Some(e) => Err(e),
+            None => Ok(false),
+        }
+    }
+
+    /// Connect to a device over TCP, trying each of its addresses in turn
+    ///
+    /// Addresses are tried best route first (see
+    /// [`order_by_route`](crate::transport::tcp::order_by_route)), so a
+    /// dual-stack device is reached over its lowest-latency route when that
+    /// works and over the others when it does not. Discovery collects the
+    /// addresses in [`DeviceInfo::addresses`](crate::DeviceInfo::addresses).
+    pub async fn connect_addresses(&self, device_id: &str, addresses: &[SocketAddr]) -> Result<()> {
+        let mut addresses = addresses.to_vec();
+        order_by_route(&mut addresses);
+
+        let mut last_error = None;
+        for addr in addresses {
+            match self.connect(device_id, TransportAddress::Tcp(addr)).await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    debug!("Connecting to {} via {} failed: {}", device_id, addr, e);
+                    last_error = Some(e);
+                }
+            }
         }
+
+        Err(last_error.unwrap_or_else(|| {
+            crate::ProtocolError::Transport(format!("No address known for device {}", device_id))
+        }))
     }
 
     /// Connect using a specific transport type
@@ -454,21 +884,14 @@ impl TransportManager {
     /// This automatically routes the packet to the appropriate transport
     /// based on the active connection for the device.
     pub async fn send_packet(&self, device_id: &str, packet: &Packet) -> Result<()> {
-        // Try TCP first (most devices use TCP)
-        if self.config.enable_tcp {
-            let tcp_mgr = self.tcp_manager.read().await;
-            if tcp_mgr.has_connection(device_id).await {
-                return tcp_mgr.send_packet(device_id, packet).await;
-            }
-        }
-
-        // Try Bluetooth if available
-        if self.config.enable_bluetooth {
-            if let Some(bt_mgr) = &self.bluetooth_manager {
-                let bt = bt_mgr.read().await;
-                if bt.has_connection(device_id).await {
-                    return bt.send_packet(device_id, packet).await;
-                }
+        // The active transport first, then whatever else is connected
+        let order = match self.upgrades.read().await.active_transport(device_id) {
+            Some(TransportType::Bluetooth) => [TransportType::Bluetooth, TransportType::Tcp],
+            _ => [TransportType::Tcp, TransportType::Bluetooth],
+        };
+        for transport_type in order {
+            if let Some(result) = self.send_via(transport_type, device_id, packet).await {
+                return result;
             }
         }
 
@@ -478,6 +901,43 @@ impl TransportManager {
         )))
     }
 
+    /// Send a packet over one transport, or None if the device is not
+    /// connected on it
+    async fn send_via(
+        &self,
+        transport_type: TransportType,
+        device_id: &str,
+        packet: &Packet,
+    ) -> Option<Result<()>> {
+        match transport_type {
+            TransportType::Tcp => {
+                if !self.config.enable_tcp {
+                    return None;
+                }
+                let tcp_mgr = self.tcp_manager.read().await;
+                if !tcp_mgr.has_connection(device_id).await {
+                    return None;
+                }
+                Some(tcp_mgr.send_packet(device_id, packet).await)
+            }
+            TransportType::Bluetooth => {
+                if !self.config.enable_bluetooth {
+                    return None;
+                }
+                let bt = self.bluetooth_manager.as_ref()?.read().await;
+                if !bt.has_connection(device_id).await {
+                    return None;
+                }
+                if packet.is_type("cconnect.pair") {
+                    let mut packet = packet.clone();
+                    attach_wifi_addresses(&mut packet, &self.local_wifi_addresses.read().await);
+                    return Some(bt.send_packet(device_id, &packet).await);
+                }
+                Some(bt.send_packet(device_id, packet).await)
+            }
+        }
+    }
+
     /// Disconnect from a device
     ///
     /// This disconnects from the device on all active transports.
@@ -559,6 +1019,10 @@ impl TransportManager {
     pub async fn stop(&self) {
         info!("Stopping transport manager...");
 
+        if let Some(task) = self.migration_task.write().await.take() {
+            task.abort();
+        }
+
         // Stop TCP manager
         if self.config.enable_tcp {
             let tcp_mgr = self.tcp_manager.read().await;
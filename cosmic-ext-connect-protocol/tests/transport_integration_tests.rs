@@ -51,6 +51,9 @@ impl Transport for MockTransport {
             } else {
                 LatencyCategory::Medium
             },
+            multiplexed: false,
+            msgpack: self.transport_type == TransportType::Bluetooth,
+            payload_streams: 1,
         }
     }
 
@@ -7,6 +7,19 @@
 //! 2. Requesting permission to capture a specific display output
 //! 3. Connecting to the `PipeWire` stream for video frames
 //! 4. Filtering for HDMI dummy displays only
+//!
+//! A session captures a whole output by default. With
+//! [`CaptureSource::Window`] the portal offers single application windows
+//! instead; if the window closes mid-stream the session ends and a
+//! [`CaptureEvent::SourceClosed`] is emitted.
+//!
+//! ## Teardown
+//!
+//! A session releases its `PipeWire` stream and portal session when dropped,
+//! even without [`ScreenCapture::stop_capture`] (an early return or a panic
+//! unwinding through the owner). Other per-session resources, such as the
+//! encoder feeding on the frames, can be tied to the session with
+//! [`ScreenCapture::defer_cleanup`] so they go with it.
 
 use crate::error::{DisplayStreamError, Result};
 use crate::output::OutputInfo;
@@ -14,7 +27,9 @@ use crate::pipewire::PipeWireStream;
 
 use ashpd::desktop::screencast::{CursorMode, Screencast, SourceType};
 use ashpd::desktop::PersistMode;
-use tokio::sync::mpsc;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
 use tracing::{debug, info, warn};
 
 /// Screen capture session state
@@ -32,6 +47,213 @@ pub enum SessionState {
     Stopped,
 }
 
+/// Kind of source the portal offers the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureSource {
+    /// A whole display output
+    #[default]
+    Monitor,
+    /// A single application window
+    Window,
+}
+
+/// Source selection sent to the screencast portal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceRequest {
+    /// Kind of source to offer
+    pub source: CaptureSource,
+    /// Include the cursor in the stream
+    pub embed_cursor: bool,
+    /// Allow selecting more than one source
+    pub multiple: bool,
+}
+
+/// Stream handed back by the portal once the user picked a source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortalStream {
+    /// `PipeWire` node carrying the frames
+    pub node_id: u32,
+    /// Source size in pixels, if reported
+    pub size: Option<(i32, i32)>,
+    /// Title of the selected window, if reported
+    pub window_title: Option<String>,
+    /// Portal session handle
+    pub session_handle: String,
+}
+
+/// Screencast portal that lets the user pick a source and opens its stream
+#[async_trait::async_trait]
+pub trait ScreenCastPortal: Send + Sync {
+    /// Ask the user for a source matching `request` and start streaming it
+    async fn open(&self, request: &SourceRequest) -> Result<PortalStream>;
+}
+
+/// How long to wait for `PipeWire` to name a selected window
+const WINDOW_TITLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The xdg-desktop-portal `ScreenCast` interface
+///
+/// The portal response does not name the selected window, so
+/// [`PortalStream::window_title`] is taken from the `PipeWire` node the
+/// portal created for it (see [`crate::pipewire::node_title`]).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct XdgScreenCastPortal;
+
+#[async_trait::async_trait]
+impl ScreenCastPortal for XdgScreenCastPortal {
+    async fn open(&self, request: &SourceRequest) -> Result<PortalStream> {
+        // Create the screencast portal proxy
+        let screencast = Screencast::new()
+            .await
+            .map_err(|e| DisplayStreamError::Portal(format!("Failed to create screencast: {e}")))?;
+
+        // Create a session
+        let session = screencast
+            .create_session()
+            .await
+            .map_err(|e| DisplayStreamError::Portal(format!("Failed to create session: {e}")))?;
+
+        debug!("Portal session created");
+
+        let source_type = match request.source {
+            CaptureSource::Monitor => SourceType::Monitor,
+            CaptureSource::Window => SourceType::Window,
+        };
+        let cursor_mode = if request.embed_cursor {
+            CursorMode::Embedded
+        } else {
+            CursorMode::Hidden
+        };
+
+        screencast
+            .select_sources(
+                &session,
+                cursor_mode,
+                source_type.into(),
+                request.multiple,
+                None,               // No restore token
+                PersistMode::DoNot, // Don't persist
+            )
+            .await
+            .map_err(|e| DisplayStreamError::Portal(format!("Failed to select sources: {e}")))?;
+
+        debug!("Sources selected, starting portal session");
+
+        // Start the session - this shows the permission dialog
+        let streams = screencast
+            .start(&session, None)
+            .await
+            .map_err(|e| {
+                DisplayStreamError::CaptureSessionFailed(format!("Failed to start session: {e}"))
+            })?
+            .response()
+            .map_err(|e| {
+                DisplayStreamError::CaptureSessionFailed(format!("Portal response error: {e}"))
+            })?;
+
+        // Get the first stream's PipeWire node ID
+        let Some(stream_info) = streams.streams().first() else {
+            return Err(DisplayStreamError::CaptureSessionFailed(
+                "No streams returned from portal".to_string(),
+            ));
+        };
+
+        let node_id = stream_info.pipe_wire_node_id();
+        let window_title = match request.source {
+            CaptureSource::Window => {
+                let lookup =
+                    tokio::task::spawn_blocking(move || crate::pipewire::node_title(node_id));
+                match tokio::time::timeout(WINDOW_TITLE_TIMEOUT, lookup).await {
+                    Ok(Ok(title)) => title,
+                    _ => {
+                        warn!("Could not find the title of captured window {node_id}");
+                        None
+                    }
+                }
+            }
+            CaptureSource::Monitor => None,
+        };
+
+        Ok(PortalStream {
+            node_id,
+            size: stream_info.size(),
+            window_title,
+            session_handle: format!("{session:?}"),
+        })
+    }
+}
+
+/// Events emitted by a capture session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureEvent {
+    /// The captured source went away (e.g. its window was closed). The
+    /// session has ended and the frame stream yields no more frames.
+    SourceClosed {
+        /// Kind of source that was being captured
+        source: CaptureSource,
+        /// Title of the closed window, if known
+        window_title: Option<String>,
+    },
+}
+
+/// Cleanup actions for the resources of a capture session
+///
+/// Actions run once, newest first, on [`SessionResources::release`] or when
+/// the guard is dropped.
+#[derive(Default)]
+pub struct SessionResources {
+    cleanups: Vec<(&'static str, Box<dyn FnOnce() + Send>)>,
+}
+
+impl SessionResources {
+    /// Create a guard holding nothing
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `cleanup` when the resources are released
+    pub fn defer(&mut self, name: &'static str, cleanup: impl FnOnce() + Send + 'static) {
+        self.cleanups.push((name, Box::new(cleanup)));
+    }
+
+    /// Number of resources still held
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.cleanups.len()
+    }
+
+    /// Whether no resources are held
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.cleanups.is_empty()
+    }
+
+    /// Release every resource now
+    pub fn release(&mut self) {
+        while let Some((name, cleanup)) = self.cleanups.pop() {
+            debug!("Releasing capture resource: {}", name);
+            cleanup();
+        }
+    }
+}
+
+impl std::fmt::Debug for SessionResources {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<_> = self.cleanups.iter().map(|(name, _)| *name).collect();
+        f.debug_struct("SessionResources")
+            .field("cleanups", &names)
+            .finish()
+    }
+}
+
+impl Drop for SessionResources {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
 /// Screen capture session using xdg-desktop-portal
 ///
 /// This struct manages the lifecycle of a screen capture session,
@@ -53,8 +275,14 @@ pub struct ScreenCapture {
     /// Output information (cached after discovery)
     output_info: Option<OutputInfo>,
 
-    /// Frame sender for async frame delivery
-    frame_sender: Option<mpsc::Sender<VideoFrame>>,
+    /// Kind of source requested from the portal
+    source: CaptureSource,
+
+    /// Session event broadcaster
+    event_tx: broadcast::Sender<CaptureEvent>,
+
+    /// Resources released with the session
+    resources: SessionResources,
 }
 
 impl ScreenCapture {
@@ -98,7 +326,9 @@ impl ScreenCapture {
             session_handle: None,
             pipewire_stream: None,
             output_info: Some(output_info),
-            frame_sender: None,
+            source: CaptureSource::Monitor,
+            event_tx: broadcast::channel(8).0,
+            resources: SessionResources::new(),
         })
     }
 
@@ -125,10 +355,35 @@ impl ScreenCapture {
             session_handle: None,
             pipewire_stream: None,
             output_info: Some(output_info),
-            frame_sender: None,
+            source: CaptureSource::Monitor,
+            event_tx: broadcast::channel(8).0,
+            resources: SessionResources::new(),
         })
     }
 
+    /// Capture a single window or a whole output
+    ///
+    /// Only takes effect before [`ScreenCapture::start_capture`].
+    #[must_use]
+    pub fn with_source(mut self, source: CaptureSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Kind of source this session captures
+    #[must_use]
+    pub fn source(&self) -> CaptureSource {
+        self.source
+    }
+
+    /// Subscribe to session events
+    ///
+    /// Subscribe before starting the capture to be sure of seeing every event.
+    #[must_use]
+    pub fn subscribe_events(&self) -> broadcast::Receiver<CaptureEvent> {
+        self.event_tx.subscribe()
+    }
+
     /// Discover and validate the target output
     ///
     /// This queries the compositor for available outputs and verifies
@@ -269,97 +524,109 @@ impl ScreenCapture {
     /// - The portal session fails to start
     /// - `PipeWire` connection fails
     pub async fn start_capture(&mut self) -> Result<FrameStream> {
-        if self.state != SessionState::Idle {
-            return Err(DisplayStreamError::StreamAlreadyStarted);
-        }
+        self.start_capture_with(&XdgScreenCastPortal).await
+    }
 
-        info!("Starting screen capture for output: {}", self.target_output);
-        self.state = SessionState::RequestingPermission;
+    /// Start the screen capture session through the given portal
+    ///
+    /// See [`ScreenCapture::start_capture`].
+    pub async fn start_capture_with(
+        &mut self,
+        portal: &dyn ScreenCastPortal,
+    ) -> Result<FrameStream> {
+        let pipewire_node_id = self.open_portal_stream(portal).await?;
+
+        // Create frame channel. Only the PipeWire thread holds the sender, so
+        // the frame stream ends when that thread does.
+        let (tx, rx) = mpsc::channel(32);
 
-        // Create the screencast portal proxy
-        let screencast = Screencast::new().await.map_err(|e| {
-            DisplayStreamError::Portal(format!("Failed to create screencast: {e}"))
-        })?;
+        // End the session with an event if the source disappears
+        let event_tx = self.event_tx.clone();
+        let closed_event = CaptureEvent::SourceClosed {
+            source: self.source,
+            window_title: self
+                .output_info
+                .as_ref()
+                .and_then(|info| info.window_title.clone()),
+        };
+        let on_source_closed = move || {
+            info!("Captured source closed, ending session");
+            let _ = event_tx.send(closed_event);
+        };
 
-        // Create a session
-        let session = screencast
-            .create_session()
+        // Connect to PipeWire stream
+        let pipewire_stream = PipeWireStream::connect(pipewire_node_id, tx, on_source_closed)
             .await
-            .map_err(|e| DisplayStreamError::Portal(format!("Failed to create session: {e}")))?;
+            .map_err(|e| DisplayStreamError::PipeWire(e.to_string()))?;
 
-        debug!("Portal session created");
+        self.pipewire_stream = Some(pipewire_stream);
+        self.state = SessionState::Capturing;
 
-        // Select sources - request monitor capture
-        screencast
-            .select_sources(
-                &session,
-                CursorMode::Embedded,       // Include cursor in the stream
-                SourceType::Monitor.into(), // Capture monitors only
-                false,                      // Don't allow multiple sources
-                None,                       // No restore token
-                PersistMode::DoNot,         // Don't persist
-            )
-            .await
-            .map_err(|e| DisplayStreamError::Portal(format!("Failed to select sources: {e}")))?;
+        info!("Screen capture started successfully");
 
-        debug!("Sources selected, starting portal session");
-        self.state = SessionState::Connecting;
+        // Return the frame stream
+        Ok(FrameStream::new(rx))
+    }
 
-        // Start the session - this shows the permission dialog
-        let streams = screencast
-            .start(&session, None)
-            .await
-            .map_err(|e| {
-                DisplayStreamError::CaptureSessionFailed(format!("Failed to start session: {e}"))
-            })?
-            .response()
-            .map_err(|e| {
-                DisplayStreamError::CaptureSessionFailed(format!("Portal response error: {e}"))
-            })?;
+    /// Release `cleanup` together with the session
+    ///
+    /// It runs when the capture is stopped or, failing that, when the session
+    /// is dropped.
+    pub fn defer_cleanup(&mut self, name: &'static str, cleanup: impl FnOnce() + Send + 'static) {
+        self.resources.defer(name, cleanup);
+    }
 
-        // Get streams from response
-        if streams.streams().is_empty() {
-            return Err(DisplayStreamError::CaptureSessionFailed(
-                "No streams returned from portal".to_string(),
-            ));
+    /// Request a source from the portal and record what was selected
+    ///
+    /// Returns the `PipeWire` node ID of the selected source.
+    async fn open_portal_stream(&mut self, portal: &dyn ScreenCastPortal) -> Result<u32> {
+        if self.state != SessionState::Idle {
+            return Err(DisplayStreamError::StreamAlreadyStarted);
         }
 
-        // Get the first stream's PipeWire node ID
-        let stream_info = &streams.streams()[0];
-        let pipewire_node_id = stream_info.pipe_wire_node_id();
-        let stream_size = stream_info.size();
+        info!(
+            "Starting {:?} capture for output: {}",
+            self.source, self.target_output
+        );
+        self.state = SessionState::RequestingPermission;
+
+        let request = SourceRequest {
+            source: self.source,
+            embed_cursor: true,
+            multiple: false,
+        };
+        let stream = match portal.open(&request).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                self.state = SessionState::Idle;
+                return Err(e);
+            }
+        };
+        self.state = SessionState::Connecting;
+
+        info!(
+            "Portal session started - PipeWire node: {}, size: {:?}, window: {:?}",
+            stream.node_id, stream.size, stream.window_title
+        );
 
         // Validate PipeWire node ID
-        if pipewire_node_id == 0 {
+        if stream.node_id == 0 {
+            self.state = SessionState::Idle;
             return Err(DisplayStreamError::PipeWire(
                 "Portal returned invalid PipeWire node ID (0)".to_string(),
             ));
         }
 
-        info!(
-            "Portal session started - PipeWire node: {}, size: {:?}",
-            pipewire_node_id, stream_size
-        );
-
-        // Store session handle
-        self.session_handle = Some(format!("{session:?}"));
-
-        // Create frame channel
-        let (tx, rx) = mpsc::channel(32);
-        self.frame_sender = Some(tx.clone());
-
-        // Connect to PipeWire stream
-        let pipewire_stream = PipeWireStream::connect(pipewire_node_id, tx)
-            .await
-            .map_err(|e| DisplayStreamError::PipeWire(e.to_string()))?;
-
-        self.pipewire_stream = Some(pipewire_stream);
-        self.state = SessionState::Capturing;
-
-        info!("Screen capture started successfully");
+        if let Some(info) = self.output_info.as_mut() {
+            if let Some((width, height)) = stream.size {
+                info.width = u32::try_from(width).unwrap_or(info.width);
+                info.height = u32::try_from(height).unwrap_or(info.height);
+            }
+            info.window_title = stream.window_title;
+        }
+        self.session_handle = Some(stream.session_handle);
 
-        // Return the frame stream
-        Ok(FrameStream::new(rx))
+        Ok(stream.node_id)
     }
 
     /// Stop the screen capture session
@@ -372,23 +639,28 @@ impl ScreenCapture {
         }
 
         info!("Stopping screen capture for output: {}", self.target_output);
+        self.teardown()?;
+
+        info!("Screen capture stopped");
+        Ok(())
+    }
+
+    /// Release everything the session holds
+    fn teardown(&mut self) -> Result<()> {
+        self.resources.release();
 
         // Disconnect PipeWire stream
-        if let Some(mut stream) = self.pipewire_stream.take() {
-            stream
+        let disconnected = match self.pipewire_stream.take() {
+            Some(mut stream) => stream
                 .disconnect()
-                .map_err(|e| DisplayStreamError::PipeWire(e.to_string()))?;
-        }
-
-        // Close frame sender
-        self.frame_sender = None;
+                .map_err(|e| DisplayStreamError::PipeWire(e.to_string())),
+            None => Ok(()),
+        };
 
         // Close portal session
         self.session_handle = None;
         self.state = SessionState::Stopped;
-
-        info!("Screen capture stopped");
-        Ok(())
+        disconnected
     }
 
     /// Get the current output information
@@ -410,21 +682,98 @@ impl ScreenCapture {
     }
 }
 
+impl Drop for ScreenCapture {
+    fn drop(&mut self) {
+        if matches!(self.state, SessionState::Idle | SessionState::Stopped) {
+            return;
+        }
+        warn!(
+            "Capture of {} dropped while {:?}, releasing its resources",
+            self.target_output, self.state
+        );
+        if let Err(e) = self.teardown() {
+            warn!("Error releasing dropped capture session: {}", e);
+        }
+    }
+}
+
+/// Default number of frames allowed to wait for the encoder
+///
+/// Two frames absorb a single slow encode without adding more than one frame
+/// interval of latency.
+pub const DEFAULT_MAX_QUEUE_DEPTH: usize = 2;
+
 /// Stream of video frames from the capture session
+///
+/// With a maximum queue depth set, frames that back up behind a slow encoder
+/// are dropped oldest-first so the encoder always works on recent content
+/// instead of falling further behind.
 pub struct FrameStream {
     receiver: mpsc::Receiver<VideoFrame>,
+    max_queue_depth: Option<usize>,
+    dropped_frames: u64,
 }
 
 impl FrameStream {
     /// Create a new frame stream from a receiver
     #[must_use] 
     pub fn new(receiver: mpsc::Receiver<VideoFrame>) -> Self {
-        Self { receiver }
+        Self {
+            receiver,
+            max_queue_depth: None,
+            dropped_frames: 0,
+        }
+    }
+
+    /// Drop the oldest pending frames when more than `depth` are waiting
+    ///
+    /// `None` (the default) delivers every frame. A depth of 1 always yields
+    /// the newest frame.
+    #[must_use]
+    pub fn with_max_queue_depth(mut self, depth: Option<usize>) -> Self {
+        self.max_queue_depth = depth.map(|d| d.max(1));
+        self
+    }
+
+    /// Frames currently waiting to be consumed
+    #[must_use]
+    pub fn queue_depth(&self) -> usize {
+        self.receiver.len()
+    }
+
+    /// Total frames dropped because the queue was too deep
+    #[must_use]
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames
     }
 
     /// Receive the next frame (async)
     pub async fn next_frame(&mut self) -> Option<VideoFrame> {
-        self.receiver.recv().await
+        let frame = self.receiver.recv().await?;
+        Some(self.skip_stale(frame))
+    }
+
+    /// Replace `frame` with newer ones while the queue exceeds its limit
+    fn skip_stale(&mut self, mut frame: VideoFrame) -> VideoFrame {
+        let Some(max_depth) = self.max_queue_depth else {
+            return frame;
+        };
+
+        // `frame` itself counts towards the depth
+        while self.receiver.len() >= max_depth {
+            match self.receiver.try_recv() {
+                Ok(newer) => {
+                    debug!(
+                        "Encoder behind, dropping frame {} for {}",
+                        frame.sequence, newer.sequence
+                    );
+                    frame = newer;
+                    self.dropped_frames += 1;
+                }
+                Err(_) => break,
+            }
+        }
+        frame
     }
 }
 
@@ -435,7 +784,9 @@ impl futures::Stream for FrameStream {
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
-        std::pin::Pin::new(&mut self.receiver).poll_recv(cx)
+        std::pin::Pin::new(&mut self.receiver)
+            .poll_recv(cx)
+            .map(|frame| frame.map(|frame| self.skip_stale(frame)))
     }
 }
 
@@ -594,6 +945,125 @@ pub enum BufferType {
     },
 }
 
+/// Pixel layout of a video frame
+///
+/// Names follow `GStreamer`'s video formats. Captured frames are usually
+/// `BGRx`; hardware encoders prefer NV12 and x264 prefers I420.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PixelFormat {
+    /// 32-bit BGR with an unused padding byte
+    #[default]
+    Bgrx,
+    /// 32-bit BGR with alpha
+    Bgra,
+    /// 32-bit RGB with an unused padding byte
+    Rgbx,
+    /// 32-bit RGB with alpha
+    Rgba,
+    /// Packed 24-bit RGB
+    Rgb,
+    /// Y plane followed by an interleaved half-resolution UV plane
+    Nv12,
+    /// Y, U and V planes, chroma at half resolution
+    I420,
+}
+
+impl PixelFormat {
+    /// `GStreamer` name of this format
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Bgrx => "BGRx",
+            Self::Bgra => "BGRA",
+            Self::Rgbx => "RGBx",
+            Self::Rgba => "RGBA",
+            Self::Rgb => "RGB",
+            Self::Nv12 => "NV12",
+            Self::I420 => "I420",
+        }
+    }
+
+    /// Parse a `GStreamer` format name
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Self::Bgrx,
+            Self::Bgra,
+            Self::Rgbx,
+            Self::Rgba,
+            Self::Rgb,
+            Self::Nv12,
+            Self::I420,
+        ]
+        .into_iter()
+        .find(|format| format.name() == name)
+    }
+
+    /// Format of a DRM fourcc code, as carried by DMA-BUF frames
+    #[must_use]
+    pub fn from_drm_fourcc(fourcc: u32) -> Option<Self> {
+        match &fourcc.to_le_bytes() {
+            b"XR24" => Some(Self::Bgrx),
+            b"AR24" => Some(Self::Bgra),
+            b"XB24" => Some(Self::Rgbx),
+            b"AB24" => Some(Self::Rgba),
+            b"BG24" => Some(Self::Rgb),
+            b"NV12" => Some(Self::Nv12),
+            b"YU12" => Some(Self::I420),
+            _ => None,
+        }
+    }
+
+    /// Matching `GStreamer` video format
+    #[must_use]
+    pub fn to_gst(self) -> gstreamer_video::VideoFormat {
+        use gstreamer_video::VideoFormat;
+        match self {
+            Self::Bgrx => VideoFormat::Bgrx,
+            Self::Bgra => VideoFormat::Bgra,
+            Self::Rgbx => VideoFormat::Rgbx,
+            Self::Rgba => VideoFormat::Rgba,
+            Self::Rgb => VideoFormat::Rgb,
+            Self::Nv12 => VideoFormat::Nv12,
+            Self::I420 => VideoFormat::I420,
+        }
+    }
+
+    /// Whether this is a planar YUV format
+    #[must_use]
+    pub fn is_yuv(self) -> bool {
+        matches!(self, Self::Nv12 | Self::I420)
+    }
+
+    /// Bytes per pixel of the first plane (luma for YUV formats)
+    #[must_use]
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Bgrx | Self::Bgra | Self::Rgbx | Self::Rgba => 4,
+            Self::Rgb => 3,
+            Self::Nv12 | Self::I420 => 1,
+        }
+    }
+
+    /// Size in bytes of a tightly packed `width` x `height` frame
+    #[must_use]
+    pub fn frame_size(self, width: u32, height: u32) -> usize {
+        let (width, height) = (width as usize, height as usize);
+        let luma = width * height * self.bytes_per_pixel();
+        if self.is_yuv() {
+            luma + 2 * ((width + 1) / 2) * ((height + 1) / 2)
+        } else {
+            luma
+        }
+    }
+}
+
+impl std::fmt::Display for PixelFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 /// A single video frame from the capture stream
 #[derive(Debug, Clone)]
 pub struct VideoFrame {
@@ -606,8 +1076,8 @@ pub struct VideoFrame {
     /// Frame height in pixels
     pub height: u32,
 
-    /// Frame format (e.g., "`BGRx`", "`RGBx`")
-    pub format: String,
+    /// Pixel format of the frame data
+    pub format: PixelFormat,
 
     /// Frame timestamp in microseconds
     pub timestamp: i64,
@@ -638,7 +1108,7 @@ impl VideoFrame {
         data: Vec<u8>,
         width: u32,
         height: u32,
-        format: String,
+        format: PixelFormat,
         timestamp: i64,
         sequence: u64,
     ) -> Self {
@@ -662,7 +1132,7 @@ impl VideoFrame {
     /// * `data` - Frame data (may be empty for DMA-BUF)
     /// * `width` - Frame width in pixels
     /// * `height` - Frame height in pixels
-    /// * `format` - Frame format (e.g., "`BGRx`")
+    /// * `format` - Pixel format of the buffer
     /// * `timestamp` - Frame timestamp in microseconds
     /// * `sequence` - Frame sequence number
     /// * `fd` - DMA-BUF file descriptor
@@ -676,7 +1146,7 @@ impl VideoFrame {
         data: Vec<u8>,
         width: u32,
         height: u32,
-        format: String,
+        format: PixelFormat,
         timestamp: i64,
         sequence: u64,
         fd: i32,
@@ -710,15 +1180,10 @@ impl VideoFrame {
         self.data.len()
     }
 
-    /// Get bytes per pixel based on format
+    /// Get bytes per pixel based on format (of the luma plane for YUV)
     #[must_use]
-    #[allow(clippy::match_same_arms)] // Explicit formats and default both return 4
     pub fn bytes_per_pixel(&self) -> usize {
-        match self.format.as_str() {
-            "BGRx" | "RGBx" | "BGRA" | "RGBA" => 4,
-            "BGR" | "RGB" => 3,
-            _ => 4, // Default to 4 bytes
-        }
+        self.format.bytes_per_pixel()
     }
 
     /// Check if this frame uses DMA-BUF
@@ -812,13 +1277,14 @@ mod tests {
             vec![0u8; 1920 * 1080 * 4],
             1920,
             1080,
-            "BGRx".to_string(),
+            PixelFormat::Bgrx,
             12345,
             1,
         );
 
         assert_eq!(frame.width, 1920);
         assert_eq!(frame.height, 1080);
+        assert_eq!(frame.format, PixelFormat::Bgrx);
         assert_eq!(frame.bytes_per_pixel(), 4);
         assert_eq!(frame.size(), 1920 * 1080 * 4);
         assert!(!frame.is_dmabuf());
@@ -832,7 +1298,7 @@ mod tests {
             vec![],
             1920,
             1080,
-            "BGRx".to_string(),
+            PixelFormat::Bgrx,
             12345,
             1,
             42,    // fd
@@ -860,6 +1326,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_pixel_format_names() {
+        for name in ["BGRx", "BGRA", "RGBx", "RGBA", "RGB", "NV12", "I420"] {
+            let format = PixelFormat::from_name(name).unwrap();
+            assert_eq!(format.name(), name);
+        }
+        assert_eq!(PixelFormat::from_name("DMA-BUF"), None);
+        assert_eq!(
+            PixelFormat::from_drm_fourcc(0x3432_5258),
+            Some(PixelFormat::Bgrx)
+        );
+        assert_eq!(
+            PixelFormat::from_drm_fourcc(u32::from_le_bytes(*b"NV12")),
+            Some(PixelFormat::Nv12)
+        );
+
+        assert_eq!(PixelFormat::Bgrx.frame_size(1920, 1080), 1920 * 1080 * 4);
+        assert_eq!(PixelFormat::Nv12.frame_size(1920, 1080), 1920 * 1080 * 3 / 2);
+        // Odd sizes round the chroma planes up
+        assert_eq!(PixelFormat::I420.frame_size(3, 3), 9 + 2 * 4);
+    }
+
     #[test]
     fn test_buffer_type_default() {
         assert_eq!(BufferType::default(), BufferType::Shm);
@@ -906,7 +1394,7 @@ mod tests {
             vec![0u8; 100],
             10,
             10,
-            "BGRx".to_string(),
+            PixelFormat::Bgrx,
             0,
             0,
         );
@@ -921,7 +1409,7 @@ mod tests {
             vec![0u8; 1920 * 1080 * 4],
             1920,
             1080,
-            "BGRx".to_string(),
+            PixelFormat::Bgrx,
             0,
             0,
         )
@@ -941,7 +1429,7 @@ mod tests {
             vec![0u8; 100],
             10,
             10,
-            "BGRx".to_string(),
+            PixelFormat::Bgrx,
             0,
             0,
         )
@@ -1023,13 +1511,59 @@ mod tests {
         assert_eq!(VideoTransform::default(), VideoTransform::None);
     }
 
+    #[tokio::test]
+    async fn test_frame_stream_drops_oldest_when_encoder_lags() {
+        let (tx, rx) = mpsc::channel(32);
+        let mut stream = FrameStream::new(rx).with_max_queue_depth(Some(2));
+        let frame = |sequence| VideoFrame::new(vec![0; 4], 1, 1, PixelFormat::Bgrx, 0, sequence);
+
+        // Capture produces three frames for every one the slow encoder takes
+        let mut sequence = 0;
+        let mut encoded = Vec::new();
+        for _ in 0..10 {
+            for _ in 0..3 {
+                tx.try_send(frame(sequence)).unwrap();
+                sequence += 1;
+            }
+            let next = stream.next_frame().await.unwrap();
+            encoded.push(next.sequence);
+            assert!(
+                stream.queue_depth() < 2,
+                "queue grew to {}",
+                stream.queue_depth()
+            );
+        }
+
+        assert!(encoded.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(*encoded.last().unwrap(), sequence - 2);
+        assert_eq!(
+            stream.dropped_frames() + encoded.len() as u64 + stream.queue_depth() as u64,
+            sequence
+        );
+        assert!(stream.dropped_frames() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_frame_stream_without_limit_keeps_every_frame() {
+        let (tx, rx) = mpsc::channel(32);
+        let mut stream = FrameStream::new(rx);
+        for sequence in 0..5 {
+            let frame = VideoFrame::new(vec![], 1, 1, PixelFormat::Bgrx, 0, sequence);
+            tx.try_send(frame).unwrap();
+        }
+
+        assert_eq!(stream.next_frame().await.unwrap().sequence, 0);
+        assert_eq!(stream.queue_depth(), 4);
+        assert_eq!(stream.dropped_frames(), 0);
+    }
+
     #[test]
     fn test_video_frame_carries_transform() {
         let mut frame = VideoFrame::new(
             vec![0u8; 100],
             10,
             10,
-            "BGRx".to_string(),
+            PixelFormat::Bgrx,
             0,
             0,
         );
@@ -1041,4 +1575,104 @@ mod tests {
         assert_eq!(frame.transform, VideoTransform::Rotate90);
         assert!(frame.transform.needs_dimension_swap());
     }
+
+    /// Portal that records the request and hands back a fixed window stream
+    struct MockPortal {
+        requests: std::sync::Mutex<Vec<SourceRequest>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ScreenCastPortal for MockPortal {
+        async fn open(&self, request: &SourceRequest) -> Result<PortalStream> {
+            self.requests.lock().unwrap().push(*request);
+            Ok(PortalStream {
+                node_id: 42,
+                size: Some((1280, 720)),
+                window_title: Some("Firefox".to_string()),
+                session_handle: "mock-session".to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_capture_source_names() {
+        assert_eq!(
+            serde_json::to_value(CaptureSource::Window).unwrap(),
+            serde_json::json!("window")
+        );
+        assert_eq!(
+            serde_json::from_value::<CaptureSource>(serde_json::json!("monitor")).unwrap(),
+            CaptureSource::Monitor
+        );
+    }
+
+    #[tokio::test]
+    async fn test_window_source_request_surfaces_title() {
+        let portal = MockPortal {
+            requests: std::sync::Mutex::new(Vec::new()),
+        };
+        let mut capture = ScreenCapture::new_any_output("portal")
+            .await
+            .unwrap()
+            .with_source(CaptureSource::Window);
+
+        let node_id = capture.open_portal_stream(&portal).await.unwrap();
+        assert_eq!(node_id, 42);
+        assert_eq!(capture.state(), SessionState::Connecting);
+
+        let requests = portal.requests.lock().unwrap();
+        assert_eq!(
+            *requests,
+            vec![SourceRequest {
+                source: CaptureSource::Window,
+                embed_cursor: true,
+                multiple: false,
+            }]
+        );
+
+        let info = capture.get_output_info().unwrap();
+        assert_eq!(info.window_title.as_deref(), Some("Firefox"));
+        assert_eq!((info.width, info.height), (1280, 720));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_session_releases_resources() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let portal = MockPortal {
+            requests: std::sync::Mutex::new(Vec::new()),
+        };
+        let open = Arc::new(AtomicUsize::new(0));
+        {
+            let mut capture = ScreenCapture::new_any_output("portal").await.unwrap();
+            capture.open_portal_stream(&portal).await.unwrap();
+            for name in ["encoder", "pipewire"] {
+                open.fetch_add(1, Ordering::SeqCst);
+                let open = Arc::clone(&open);
+                capture.defer_cleanup(name, move || {
+                    open.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+            assert_eq!(open.load(Ordering::SeqCst), 2);
+            // Dropped without stop_capture
+        }
+        assert_eq!(open.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_session_resources_release_once_newest_first() {
+        let order = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut resources = SessionResources::new();
+        for name in ["portal", "pipewire", "encoder"] {
+            let order = std::sync::Arc::clone(&order);
+            resources.defer(name, move || order.lock().unwrap().push(name));
+        }
+        assert_eq!(resources.len(), 3);
+
+        resources.release();
+        assert!(resources.is_empty());
+        drop(resources);
+        assert_eq!(*order.lock().unwrap(), ["encoder", "pipewire", "portal"]);
+    }
 }
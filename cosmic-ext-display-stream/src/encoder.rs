@@ -10,6 +10,24 @@
 //! - **NVENC**: NVIDIA hardware encoding (nvh264enc)
 //! - **Software**: x264 software encoding (x264enc)
 //!
+//! ## Pixel Formats
+//!
+//! Each encoder has a preferred input format ([`EncoderType::preferred_format`]),
+//! which [`EncoderConfig::pixel_format`] can override. Frames are converted
+//! to it only when their format differs, on the GPU where the encoder has a
+//! converter available, and not at all when the formats already match. See
+//! [`FormatConversion`]. A frame arriving in a different format than the
+//! pipeline was built for renegotiates the pipeline once.
+//!
+//! ## Presets
+//!
+//! [`EncoderConfig::preset`] picks bitrate, framerate, keyframe interval and
+//! H.264 profile for the network the stream travels over and how much delay
+//! is acceptable: a wired LAN gets a high bitrate and long GOP, cellular a
+//! low bitrate, Baseline profile and frequent keyframes so a lost packet
+//! heals quickly. Values the user set explicitly are applied on top with
+//! [`EncoderConfig::with_overrides`].
+//!
 //! ## Example
 //!
 //! ```no_run
@@ -30,12 +48,14 @@
 //! # }
 //! ```
 
-use crate::capture::{VideoFrame, VideoTransform};
+use crate::capture::{PixelFormat, VideoFrame, VideoTransform};
 use crate::error::{DisplayStreamError, Result};
+use crate::streaming::TransportMode;
 use gstreamer as gst;
 use gstreamer::prelude::*;
 use gstreamer_app as gst_app;
 use gstreamer_video as gst_video;
+use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, warn};
 
 /// Type of hardware encoder to use
@@ -78,6 +98,147 @@ impl EncoderType {
     pub fn supports_dmabuf(self) -> bool {
         matches!(self, Self::Vaapi)
     }
+
+    /// Pixel format this encoder consumes without converting internally
+    #[must_use]
+    pub fn preferred_format(self) -> PixelFormat {
+        match self {
+            Self::Vaapi | Self::Nvenc => PixelFormat::Nv12,
+            Self::Software => PixelFormat::I420,
+        }
+    }
+
+    /// `GStreamer` element converting formats on this encoder's GPU, if any
+    #[must_use]
+    pub fn gpu_converter(self) -> Option<&'static str> {
+        match self {
+            Self::Vaapi => Some("vaapipostproc"),
+            Self::Nvenc | Self::Software => None,
+        }
+    }
+}
+
+/// `GStreamer` element converting formats on the CPU
+pub const CPU_CONVERTER: &str = "videoconvert";
+
+/// How frames get from their capture format to the encoder's input format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatConversion {
+    /// Format of the frames fed to the encoder
+    pub source: PixelFormat,
+    /// Format the encoder consumes
+    pub target: PixelFormat,
+    /// Element converting `source` to `target`, `None` when they match
+    pub converter: Option<&'static str>,
+}
+
+impl FormatConversion {
+    /// Plan the conversion of `source` frames for `encoder_type`
+    ///
+    /// `requested` overrides the encoder's preferred format. The encoder's GPU
+    /// converter is used when `is_available` finds it, [`CPU_CONVERTER`]
+    /// otherwise.
+    pub fn negotiate(
+        source: PixelFormat,
+        requested: Option<PixelFormat>,
+        encoder_type: EncoderType,
+        is_available: impl Fn(&str) -> bool,
+    ) -> Self {
+        let target = requested.unwrap_or_else(|| encoder_type.preferred_format());
+        let converter = (source != target).then(|| {
+            encoder_type
+                .gpu_converter()
+                .filter(|name| is_available(name))
+                .unwrap_or(CPU_CONVERTER)
+        });
+        Self {
+            source,
+            target,
+            converter,
+        }
+    }
+
+    /// Whether frames reach the encoder unconverted
+    #[must_use]
+    pub fn is_passthrough(&self) -> bool {
+        self.converter.is_none()
+    }
+}
+
+/// Network a stream travels over, for choosing an encoder preset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportType {
+    /// Wired local network or USB
+    Lan,
+    /// Wireless local network
+    #[default]
+    Wifi,
+    /// Mobile data or a hotspot
+    Cellular,
+}
+
+impl From<TransportMode> for TransportType {
+    fn from(mode: TransportMode) -> Self {
+        match mode {
+            TransportMode::WiFi => Self::Wifi,
+            TransportMode::Usb => Self::Lan,
+        }
+    }
+}
+
+/// How much delay a stream may trade for picture quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LatencyTarget {
+    /// Touch and pointer input must feel immediate
+    #[default]
+    Interactive,
+    /// Some buffering is acceptable
+    Balanced,
+    /// Mostly static content; quality over delay
+    Quality,
+}
+
+/// H.264 profile the encoder is constrained to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum H264Profile {
+    /// No B-frames or CABAC; cheapest to decode and most loss tolerant
+    Baseline,
+    /// Broadly supported middle ground
+    Main,
+    /// Best compression
+    High,
+}
+
+impl H264Profile {
+    /// Profile name as used in H.264 caps
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Baseline => "constrained-baseline",
+            Self::Main => "main",
+            Self::High => "high",
+        }
+    }
+}
+
+/// Encoder settings the user chose explicitly, applied over a preset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct EncoderOverrides {
+    /// Target bitrate in bits per second
+    #[serde(default)]
+    pub bitrate: Option<u32>,
+    /// Framerate (frames per second)
+    #[serde(default)]
+    pub framerate: Option<u32>,
+    /// Keyframe interval (GOP size) in frames
+    #[serde(default)]
+    pub keyframe_interval: Option<u32>,
+    /// H.264 profile
+    #[serde(default)]
+    pub profile: Option<H264Profile>,
 }
 
 /// Encoder configuration options
@@ -99,6 +260,12 @@ pub struct EncoderConfig {
     pub keyframe_interval: u32,
     /// Display orientation transform to apply before encoding
     pub transform: VideoTransform,
+    /// Pixel format of the frames fed to the encoder
+    pub source_format: PixelFormat,
+    /// Input format for the encoder (None for its preferred format)
+    pub pixel_format: Option<PixelFormat>,
+    /// H.264 profile (None lets the encoder choose)
+    pub profile: Option<H264Profile>,
 }
 
 impl Default for EncoderConfig {
@@ -112,6 +279,9 @@ impl Default for EncoderConfig {
             low_latency: true,
             keyframe_interval: 30, // Keyframe every 30 frames (~0.5s at 60fps)
             transform: VideoTransform::None,
+            source_format: PixelFormat::Bgrx, // What screen capture delivers
+            pixel_format: None,
+            profile: None,
         }
     }
 }
@@ -123,6 +293,57 @@ impl EncoderConfig {
         Self::default()
     }
 
+    /// Parameters tuned for `transport` and `latency`
+    ///
+    /// Resolution, encoder type and formats keep their defaults.
+    #[must_use]
+    pub fn preset(transport: TransportType, latency: LatencyTarget) -> Self {
+        // Keyframe intervals are one GOP per 2s, 1s and 0.5s respectively
+        let (bitrate, framerate, keyframe_interval, profile) = match transport {
+            TransportType::Lan => (20_000_000, 60, 120, H264Profile::High),
+            TransportType::Wifi => (10_000_000, 60, 60, H264Profile::Main),
+            TransportType::Cellular => (2_500_000, 30, 15, H264Profile::Baseline),
+        };
+        let (bitrate, keyframe_interval, low_latency) = match latency {
+            LatencyTarget::Interactive => (bitrate, keyframe_interval, true),
+            LatencyTarget::Balanced => (bitrate, keyframe_interval * 2, true),
+            LatencyTarget::Quality => (bitrate / 2 * 3, keyframe_interval * 4, false),
+        };
+        Self {
+            bitrate,
+            framerate,
+            low_latency,
+            keyframe_interval,
+            profile: Some(profile),
+            ..Self::default()
+        }
+    }
+
+    /// Apply the settings the user chose explicitly
+    #[must_use]
+    pub fn with_overrides(mut self, overrides: &EncoderOverrides) -> Self {
+        if let Some(bitrate) = overrides.bitrate {
+            self.bitrate = bitrate;
+        }
+        if let Some(framerate) = overrides.framerate {
+            self.framerate = framerate;
+        }
+        if let Some(interval) = overrides.keyframe_interval {
+            self.keyframe_interval = interval;
+        }
+        if let Some(profile) = overrides.profile {
+            self.profile = Some(profile);
+        }
+        self
+    }
+
+    /// Constrain the encoder to an H.264 profile
+    #[must_use]
+    pub fn with_profile(mut self, profile: H264Profile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
     /// Set the video resolution
     #[must_use] 
     pub fn with_resolution(mut self, width: u32, height: u32) -> Self {
@@ -172,6 +393,20 @@ impl EncoderConfig {
         self.transform = transform;
         self
     }
+
+    /// Set the pixel format of the frames fed to the encoder
+    #[must_use]
+    pub fn with_source_format(mut self, format: PixelFormat) -> Self {
+        self.source_format = format;
+        self
+    }
+
+    /// Set the encoder's input format instead of its preferred one
+    #[must_use]
+    pub fn with_pixel_format(mut self, format: PixelFormat) -> Self {
+        self.pixel_format = Some(format);
+        self
+    }
 }
 
 /// Encoded video frame ready for transmission
@@ -199,6 +434,8 @@ pub struct VideoEncoder {
     config: EncoderConfig,
     /// Detected encoder type
     encoder_type: EncoderType,
+    /// Conversion from the source format to the encoder's input format
+    conversion: FormatConversion,
     /// Whether the encoder is running
     running: bool,
 }
@@ -239,7 +476,13 @@ impl VideoEncoder {
         );
 
         // Build the pipeline
-        let (pipeline, appsrc, appsink) = Self::build_pipeline(&config, encoder_type)?;
+        let conversion = FormatConversion::negotiate(
+            config.source_format,
+            config.pixel_format,
+            encoder_type,
+            is_encoder_available,
+        );
+        let (pipeline, appsrc, appsink) = Self::build_pipeline(&config, encoder_type, conversion)?;
 
         Ok(Self {
             pipeline,
@@ -247,6 +490,7 @@ impl VideoEncoder {
             appsink,
             config,
             encoder_type,
+            conversion,
             running: false,
         })
     }
@@ -255,6 +499,7 @@ impl VideoEncoder {
     fn build_pipeline(
         config: &EncoderConfig,
         encoder_type: EncoderType,
+        conversion: FormatConversion,
     ) -> Result<(gst::Pipeline, gst_app::AppSrc, gst_app::AppSink)> {
         let pipeline = gst::Pipeline::new();
 
@@ -268,7 +513,7 @@ impl VideoEncoder {
             .name("source")
             .caps(
                 &gst_video::VideoCapsBuilder::new()
-                    .format(gst_video::VideoFormat::Bgrx)
+                    .format(conversion.source.to_gst())
                     .width(width)
                     .height(height)
                     .framerate(gst::Fraction::new(framerate, 1))
@@ -288,12 +533,36 @@ impl VideoEncoder {
                 DisplayStreamError::Encoder(format!("Failed to create videoflip: {e}"))
             })?;
 
-        let videoconvert = gst::ElementFactory::make("videoconvert")
-            .name("convert")
-            .build()
-            .map_err(|e| {
-                DisplayStreamError::Encoder(format!("Failed to create videoconvert: {e}"))
-            })?;
+        // Format conversion, only when the encoder cannot take the frames as is
+        let mut converters = Vec::new();
+        if let Some(converter) = conversion.converter {
+            info!(
+                "Converting {} to {} with {}",
+                conversion.source, conversion.target, converter
+            );
+            let element = gst::ElementFactory::make(converter)
+                .name("convert")
+                .build()
+                .map_err(|e| {
+                    DisplayStreamError::Encoder(format!("Failed to create {converter}: {e}"))
+                })?;
+            let capsfilter = gst::ElementFactory::make("capsfilter")
+                .name("convert-caps")
+                .property(
+                    "caps",
+                    &gst_video::VideoCapsBuilder::new()
+                        .format(conversion.target.to_gst())
+                        .build(),
+                )
+                .build()
+                .map_err(|e| {
+                    DisplayStreamError::Encoder(format!("Failed to create capsfilter: {e}"))
+                })?;
+            converters.push(element);
+            converters.push(capsfilter);
+        } else {
+            debug!("Frames already in {}, no conversion", conversion.target);
+        }
 
         // Create encoder based on type
         let encoder = Self::create_encoder(encoder_type, config)?;
@@ -306,40 +575,29 @@ impl VideoEncoder {
                 DisplayStreamError::Encoder(format!("Failed to create h264parse: {e}"))
             })?;
 
+        // Constraining the profile here makes the encoder negotiate it
+        let mut sink_caps = gst::Caps::builder("video/x-h264")
+            .field("stream-format", "byte-stream")
+            .field("alignment", "au");
+        if let Some(profile) = config.profile {
+            sink_caps = sink_caps.field("profile", profile.as_str());
+        }
         let appsink = gst_app::AppSink::builder()
             .name("sink")
-            .caps(
-                &gst::Caps::builder("video/x-h264")
-                    .field("stream-format", "byte-stream")
-                    .field("alignment", "au")
-                    .build(),
-            )
+            .caps(&sink_caps.build())
             .build();
 
+        // Link: appsrc → videoflip → [convert → caps] → encoder → h264parse → appsink
+        let mut elements: Vec<&gst::Element> = vec![appsrc.upcast_ref(), &videoflip];
+        elements.extend(&converters);
+        elements.extend([&encoder, &h264parse, appsink.upcast_ref()]);
+
         // Add elements to pipeline
-        pipeline
-            .add_many([
-                appsrc.upcast_ref(),
-                &videoflip,
-                &videoconvert,
-                &encoder,
-                &h264parse,
-                appsink.upcast_ref(),
-            ])
-            .map_err(|e| {
-                DisplayStreamError::Encoder(format!("Failed to add elements to pipeline: {e}"))
-            })?;
+        pipeline.add_many(elements.iter().copied()).map_err(|e| {
+            DisplayStreamError::Encoder(format!("Failed to add elements to pipeline: {e}"))
+        })?;
 
-        // Link elements: appsrc → videoflip → videoconvert → encoder → h264parse → appsink
-        gst::Element::link_many([
-            appsrc.upcast_ref(),
-            &videoflip,
-            &videoconvert,
-            &encoder,
-            &h264parse,
-            appsink.upcast_ref(),
-        ])
-        .map_err(|e| {
+        gst::Element::link_many(elements.iter().copied()).map_err(|e| {
             DisplayStreamError::Encoder(format!("Failed to link pipeline elements: {e}"))
         })?;
 
@@ -441,7 +699,7 @@ impl VideoEncoder {
     ///
     /// # Arguments
     ///
-    /// * `frame` - Raw video frame data (`BGRx` format)
+    /// * `frame` - Raw video frame data in [`EncoderConfig::source_format`]
     /// * `timestamp` - Presentation timestamp in microseconds
     ///
     /// # Returns
@@ -486,6 +744,9 @@ impl VideoEncoder {
     ///
     /// Automatically dispatches to the appropriate encoding path based on buffer type.
     pub fn encode_video_frame(&mut self, frame: &VideoFrame) -> Result<Option<EncodedFrame>> {
+        if frame.format != self.conversion.source {
+            self.renegotiate(frame.format)?;
+        }
         if frame.is_dmabuf() {
             self.encode_dmabuf_frame(frame)
         } else {
@@ -513,17 +774,32 @@ impl VideoEncoder {
 
     /// Encode a shared memory video frame
     fn encode_shm_frame(&mut self, frame: &VideoFrame) -> Result<Option<EncodedFrame>> {
-        // Verify format compatibility
-        if frame.format != "BGRx" && frame.format != "BGRA" {
-            warn!(
-                "Frame format '{}' may not be compatible, expected BGRx",
-                frame.format
-            );
-        }
-
         self.encode_frame(&frame.data, frame.timestamp)
     }
 
+    /// Rebuild the pipeline for frames in `source` format
+    fn renegotiate(&mut self, source: PixelFormat) -> Result<()> {
+        info!(
+            "Frames switched from {} to {}, renegotiating encoder input",
+            self.conversion.source, source
+        );
+        self.stop()?;
+        let conversion = FormatConversion::negotiate(
+            source,
+            self.config.pixel_format,
+            self.encoder_type,
+            is_encoder_available,
+        );
+        let (pipeline, appsrc, appsink) =
+            Self::build_pipeline(&self.config, self.encoder_type, conversion)?;
+        self.pipeline = pipeline;
+        self.appsrc = appsrc;
+        self.appsink = appsink;
+        self.config.source_format = source;
+        self.conversion = conversion;
+        Ok(())
+    }
+
     /// Pull an encoded frame from the pipeline
     fn pull_encoded_frame(&self) -> Result<Option<EncodedFrame>> {
         // Try to pull a sample with a short timeout
@@ -591,6 +867,12 @@ impl VideoEncoder {
         self.encoder_type
     }
 
+    /// Get the negotiated pixel format conversion
+    #[must_use]
+    pub fn conversion(&self) -> FormatConversion {
+        self.conversion
+    }
+
     /// Check if the encoder is running
     #[must_use] 
     pub fn is_running(&self) -> bool {
@@ -714,6 +996,68 @@ mod tests {
         assert_eq!(config.keyframe_interval, 60);
     }
 
+    #[test]
+    fn test_presets_follow_transport() {
+        for latency in [
+            LatencyTarget::Interactive,
+            LatencyTarget::Balanced,
+            LatencyTarget::Quality,
+        ] {
+            let lan = EncoderConfig::preset(TransportType::Lan, latency);
+            let wifi = EncoderConfig::preset(TransportType::Wifi, latency);
+            let cellular = EncoderConfig::preset(TransportType::Cellular, latency);
+
+            assert!(cellular.bitrate < wifi.bitrate && wifi.bitrate < lan.bitrate);
+            assert!(cellular.keyframe_interval < wifi.keyframe_interval);
+            assert!(wifi.keyframe_interval < lan.keyframe_interval);
+            assert!((1_000_000..=5_000_000).contains(&cellular.bitrate));
+            assert!(lan.bitrate >= 15_000_000);
+            assert_eq!(cellular.profile, Some(H264Profile::Baseline));
+            assert_eq!(lan.profile, Some(H264Profile::High));
+        }
+
+        // Cellular keyframes at least every second
+        let cellular = EncoderConfig::preset(TransportType::Cellular, LatencyTarget::Interactive);
+        assert!(cellular.keyframe_interval <= cellular.framerate);
+        assert!(cellular.low_latency);
+    }
+
+    #[test]
+    fn test_presets_follow_latency_target() {
+        let interactive = EncoderConfig::preset(TransportType::Wifi, LatencyTarget::Interactive);
+        let quality = EncoderConfig::preset(TransportType::Wifi, LatencyTarget::Quality);
+
+        assert!(interactive.low_latency);
+        assert!(!quality.low_latency);
+        assert!(interactive.keyframe_interval < quality.keyframe_interval);
+        assert!(interactive.bitrate < quality.bitrate);
+        assert_eq!(TransportType::from(TransportMode::Usb), TransportType::Lan);
+    }
+
+    #[test]
+    fn test_user_overrides_take_precedence() {
+        let overrides = EncoderOverrides {
+            bitrate: Some(8_000_000),
+            keyframe_interval: Some(45),
+            ..EncoderOverrides::default()
+        };
+        let config = EncoderConfig::preset(TransportType::Cellular, LatencyTarget::Interactive)
+            .with_resolution(2560, 1600)
+            .with_overrides(&overrides);
+
+        assert_eq!(config.bitrate, 8_000_000);
+        assert_eq!(config.keyframe_interval, 45);
+        // Left to the preset
+        assert_eq!(config.framerate, 30);
+        assert_eq!(config.profile, Some(H264Profile::Baseline));
+        assert_eq!((config.width, config.height), (2560, 1600));
+
+        let untouched = EncoderConfig::preset(TransportType::Lan, LatencyTarget::Balanced)
+            .with_overrides(&EncoderOverrides::default());
+        assert_eq!(untouched.bitrate, 20_000_000);
+        assert_eq!(untouched.keyframe_interval, 240);
+    }
+
     #[test]
     fn test_encoder_type_display_name() {
         assert_eq!(EncoderType::Vaapi.display_name(), "VAAPI (Intel/AMD)");
@@ -766,6 +1110,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_matching_formats_skip_conversion() {
+        let conversion =
+            FormatConversion::negotiate(PixelFormat::Nv12, None, EncoderType::Vaapi, |_| true);
+        assert!(conversion.is_passthrough());
+        assert_eq!(conversion.target, PixelFormat::Nv12);
+
+        // A requested format the frames already have is not converted either
+        let conversion = FormatConversion::negotiate(
+            PixelFormat::Bgrx,
+            Some(PixelFormat::Bgrx),
+            EncoderType::Software,
+            |_| true,
+        );
+        assert!(conversion.is_passthrough());
+    }
+
+    #[test]
+    fn test_mismatched_format_converted_once() {
+        let conversion =
+            FormatConversion::negotiate(PixelFormat::Bgrx, None, EncoderType::Software, |_| true);
+        assert_eq!(conversion.target, EncoderType::Software.preferred_format());
+        assert_eq!(conversion.converter, Some(CPU_CONVERTER));
+
+        // VAAPI converts on the GPU when it can, on the CPU otherwise
+        let gpu =
+            FormatConversion::negotiate(PixelFormat::Bgrx, None, EncoderType::Vaapi, |_| true);
+        assert_eq!(gpu.target, PixelFormat::Nv12);
+        assert_eq!(gpu.converter, Some("vaapipostproc"));
+        let cpu =
+            FormatConversion::negotiate(PixelFormat::Bgrx, None, EncoderType::Vaapi, |_| false);
+        assert_eq!(cpu.converter, Some(CPU_CONVERTER));
+
+        let config = EncoderConfig::new().with_pixel_format(PixelFormat::Nv12);
+        let requested = FormatConversion::negotiate(
+            config.source_format,
+            config.pixel_format,
+            EncoderType::Software,
+            |_| true,
+        );
+        assert_eq!(requested.target, PixelFormat::Nv12);
+        assert!(!requested.is_passthrough());
+    }
+
     #[test]
     fn test_encoder_type_supports_dmabuf() {
         assert!(EncoderType::Vaapi.supports_dmabuf());
@@ -40,6 +40,16 @@
 //!   desktop_y = 0 + (0.5 * 1600) = 800
 //! ```
 //!
+//! ## Jitter Buffer
+//!
+//! Touch events crossing a busy network arrive with uneven spacing, which
+//! shows up as jerky pointer motion. An optional [`JitterBuffer`] (see
+//! [`InputHandler::with_jitter_buffer`]) holds events carrying a sequence number
+//! for a short playout delay, reorders them, drops duplicates and releases them
+//! at the spacing given by their sender timestamps. It is off by default for
+//! the lowest latency; while enabled, call [`InputHandler::poll_jitter_buffer`]
+//! at [`InputHandler::next_jitter_release`] to inject due events.
+//!
 //! ## Requirements
 //!
 //! - COSMIC Desktop or other compositor with libei/reis support
@@ -51,7 +61,9 @@ use crate::error::{DisplayStreamError, Result};
 use enigo::Settings;
 use enigo::{Button, Coordinate, Direction, Enigo, Mouse};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tracing::{debug, error, trace, warn};
 
 /// Touch action types
@@ -88,6 +100,9 @@ pub struct TouchEvent {
     /// Timestamp in milliseconds
     #[serde(default)]
     pub timestamp: Option<u64>,
+    /// Sender sequence number, used by the jitter buffer to reorder events
+    #[serde(default)]
+    pub sequence: Option<u64>,
 }
 
 impl TouchEvent {
@@ -118,6 +133,7 @@ impl TouchEvent {
             touch_id,
             pressure: None,
             timestamp: None,
+            sequence: None,
         }
     }
 
@@ -135,6 +151,13 @@ impl TouchEvent {
         self
     }
 
+    /// Create a new touch event with a sender sequence number
+    #[must_use]
+    pub fn with_sequence(mut self, sequence: u64) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+
     /// Validate that coordinates are in valid range
     ///
     /// # Errors
@@ -218,6 +241,241 @@ impl DisplayGeometry {
     }
 }
 
+/// Default playout delay of the input jitter buffer
+pub const DEFAULT_JITTER_DELAY: Duration = Duration::from_millis(20);
+
+/// Default number of events the jitter buffer holds before releasing early
+pub const DEFAULT_JITTER_CAPACITY: usize = 64;
+
+/// Backwards jump in sequence numbers taken as a sender restart
+///
+/// Reordering never moves an event this far back, so an event this far
+/// behind the last released one starts a new sequence instead of being
+/// dropped as stale.
+pub const SEQUENCE_RESET_GAP: u64 = 1024;
+
+/// Input jitter buffer configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JitterBufferConfig {
+    /// Latency added to absorb network variance
+    pub delay: Duration,
+    /// Events held before the oldest is released regardless of its schedule
+    pub capacity: usize,
+}
+
+impl Default for JitterBufferConfig {
+    fn default() -> Self {
+        Self {
+            delay: DEFAULT_JITTER_DELAY,
+            capacity: DEFAULT_JITTER_CAPACITY,
+        }
+    }
+}
+
+impl JitterBufferConfig {
+    /// Set the playout delay
+    #[must_use]
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Set the capacity (at least one event)
+    #[must_use]
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+}
+
+/// Reorders sequenced touch events and paces their release
+///
+/// Each event is scheduled for `delay` after the time its sender timestamp
+/// predicts, anchored on the first event; events without a timestamp are
+/// scheduled `delay` after arrival. Events are released strictly in sequence
+/// order once the lowest queued sequence is due, so a missing event holds up
+/// later ones for at most its own playout slot. A jump back of more than
+/// [`SEQUENCE_RESET_GAP`] restarts the sequence; events still queued from
+/// before the restart are released first.
+#[derive(Debug)]
+pub struct JitterBuffer {
+    config: JitterBufferConfig,
+    /// Queued events by sequence number, with their playout time
+    pending: BTreeMap<u64, (Instant, TouchEvent)>,
+    /// Events queued before a sequence restart, released ahead of `pending`
+    flushed: Vec<(Instant, TouchEvent)>,
+    /// Highest sequence number released so far
+    last_released: Option<u64>,
+    /// Local time and sender timestamp (ms) anchoring the playout schedule
+    anchor: Option<(Instant, u64)>,
+    /// Duplicate or stale events discarded
+    dropped: u64,
+    /// Events that arrived after a higher sequence number
+    reordered: u64,
+}
+
+impl JitterBuffer {
+    /// Create an empty buffer
+    #[must_use]
+    pub fn new(config: JitterBufferConfig) -> Self {
+        Self {
+            config,
+            pending: BTreeMap::new(),
+            flushed: Vec::new(),
+            last_released: None,
+            anchor: None,
+            dropped: 0,
+            reordered: 0,
+        }
+    }
+
+    /// Get the buffer configuration
+    #[must_use]
+    pub fn config(&self) -> JitterBufferConfig {
+        self.config
+    }
+
+    /// Queue an event received at `now`
+    ///
+    /// Returns `false` if the event was discarded as a duplicate or because a
+    /// later sequence number was already released.
+    pub fn push(&mut self, sequence: u64, event: TouchEvent, now: Instant) -> bool {
+        let restarted = self
+            .last_released
+            .into_iter()
+            .chain(self.pending.keys().next_back().copied())
+            .max()
+            .is_some_and(|highest| highest.saturating_sub(sequence) > SEQUENCE_RESET_GAP);
+        if restarted {
+            debug!(
+                "Input sequence restarted at #{}, flushing {} queued events",
+                sequence,
+                self.pending.len()
+            );
+            self.flushed
+                .extend(std::mem::take(&mut self.pending).into_values());
+            self.last_released = None;
+            self.anchor = None;
+        }
+
+        let stale = self.last_released.is_some_and(|last| sequence <= last);
+        if stale || self.pending.contains_key(&sequence) {
+            trace!("Dropping duplicate or stale input event #{}", sequence);
+            self.dropped += 1;
+            return false;
+        }
+
+        if self
+            .pending
+            .last_key_value()
+            .is_some_and(|(&highest, _)| sequence < highest)
+        {
+            self.reordered += 1;
+        }
+
+        let playout = self.playout_time(&event, now);
+        self.pending.insert(sequence, (playout, event));
+        true
+    }
+
+    /// Schedule an event, re-anchoring when the sender clock drifts away
+    fn playout_time(&mut self, event: &TouchEvent, now: Instant) -> Instant {
+        let delay = self.config.delay;
+        let Some(timestamp) = event.timestamp else {
+            return now + delay;
+        };
+
+        if let Some((base, base_timestamp)) = self.anchor {
+            if let Some(offset) = timestamp.checked_sub(base_timestamp) {
+                let target = base + Duration::from_millis(offset) + delay;
+                let too_late = target + delay < now;
+                let too_early = self.pending.is_empty() && target > now + delay * 2;
+                if !too_late && !too_early {
+                    return target;
+                }
+            }
+        }
+
+        self.anchor = Some((now, timestamp));
+        now + delay
+    }
+
+    /// Release the events due at `now`, in sequence order
+    pub fn pop_ready(&mut self, now: Instant) -> Vec<TouchEvent> {
+        let due = self
+            .flushed
+            .iter()
+            .take_while(|(at, _)| *at <= now || self.len() > self.config.capacity)
+            .count();
+        let mut ready: Vec<TouchEvent> = self.flushed.drain(..due).map(|(_, e)| e).collect();
+        if !self.flushed.is_empty() {
+            return ready;
+        }
+
+        while let Some(entry) = self.pending.first_entry() {
+            let over_capacity = self.pending.len() > self.config.capacity;
+            if entry.get().0 > now && !over_capacity {
+                break;
+            }
+            let (sequence, (_, event)) = entry.remove_entry();
+            self.last_released = Some(sequence);
+            ready.push(event);
+        }
+        ready
+    }
+
+    /// Release every queued event, in sequence order
+    pub fn drain(&mut self) -> Vec<TouchEvent> {
+        if let Some(&highest) = self.pending.keys().next_back() {
+            self.last_released = Some(highest);
+        }
+        std::mem::take(&mut self.flushed)
+            .into_iter()
+            .chain(std::mem::take(&mut self.pending).into_values())
+            .map(|(_, event)| event)
+            .collect()
+    }
+
+    /// When the next queued event is due
+    #[must_use]
+    pub fn next_release_at(&self) -> Option<Instant> {
+        match self.flushed.first() {
+            Some((at, _)) => Some(*at),
+            None => self.pending.first_key_value().map(|(_, (at, _))| *at),
+        }
+    }
+
+    /// Number of queued events
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.flushed.len() + self.pending.len()
+    }
+
+    /// Whether no events are queued
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.flushed.is_empty() && self.pending.is_empty()
+    }
+
+    /// Number of duplicate or stale events discarded
+    #[must_use]
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Number of events that arrived out of order
+    #[must_use]
+    pub fn reordered_count(&self) -> u64 {
+        self.reordered
+    }
+
+    /// Reset the discard and reorder counters
+    pub fn reset_counters(&mut self) {
+        self.dropped = 0;
+        self.reordered = 0;
+    }
+}
+
 /// Input handler for touch events
 ///
 /// Manages coordinate conversion and pointer event injection for remote
@@ -240,6 +498,9 @@ pub struct InputHandler {
     /// Whether initialization has been attempted (prevents repeated retries)
     init_attempted: bool,
 
+    /// Optional jitter buffer smoothing sequenced input (`None` = inject immediately)
+    jitter_buffer: Option<JitterBuffer>,
+
     /// Statistics
     events_processed: u64,
     events_injected: u64,
@@ -279,6 +540,7 @@ impl InputHandler {
             active_touches: std::collections::HashMap::new(),
             enigo: Mutex::new(None),
             init_attempted: false,
+            jitter_buffer: None,
             events_processed: 0,
             events_injected: 0,
             events_failed: 0,
@@ -341,6 +603,79 @@ impl InputHandler {
         }
     }
 
+    /// Enable a jitter buffer for sequenced input
+    #[must_use]
+    pub fn with_jitter_buffer(mut self, config: JitterBufferConfig) -> Self {
+        self.jitter_buffer = Some(JitterBuffer::new(config));
+        self
+    }
+
+    /// Enable, reconfigure or disable (`None`) the jitter buffer
+    ///
+    /// Events still queued in the previous buffer are injected immediately.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first injection error for the flushed events
+    pub fn set_jitter_buffer(&mut self, config: Option<JitterBufferConfig>) -> Result<()> {
+        let flushed = self
+            .jitter_buffer
+            .take()
+            .map(|mut buffer| buffer.drain())
+            .unwrap_or_default();
+        self.jitter_buffer = config.map(JitterBuffer::new);
+        self.inject_all(&flushed).map(|_| ())
+    }
+
+    /// Get the jitter buffer configuration, if enabled
+    #[must_use]
+    pub fn jitter_buffer_config(&self) -> Option<JitterBufferConfig> {
+        self.jitter_buffer.as_ref().map(JitterBuffer::config)
+    }
+
+    /// When the jitter buffer next has an event due, if any are queued
+    #[must_use]
+    pub fn next_jitter_release(&self) -> Option<Instant> {
+        self.jitter_buffer
+            .as_ref()
+            .and_then(JitterBuffer::next_release_at)
+    }
+
+    /// Inject the buffered events that are due
+    ///
+    /// Returns the number of events released. Does nothing when the jitter
+    /// buffer is disabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first injection error; remaining due events are still injected
+    pub fn poll_jitter_buffer(&mut self) -> Result<usize> {
+        self.release_jitter_buffer(Instant::now())
+    }
+
+    /// Inject the buffered events due at `now`
+    fn release_jitter_buffer(&mut self, now: Instant) -> Result<usize> {
+        let ready = match self.jitter_buffer.as_mut() {
+            Some(buffer) => buffer.pop_ready(now),
+            None => return Ok(0),
+        };
+        self.inject_all(&ready)
+    }
+
+    /// Inject events in order, continuing past failures
+    fn inject_all(&mut self, events: &[TouchEvent]) -> Result<usize> {
+        let mut first_error = None;
+        for event in events {
+            if let Err(e) = self.inject_event(event) {
+                first_error.get_or_insert(e);
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(events.len()),
+        }
+    }
+
     /// Check whether input injection is available on this system
     ///
     /// Returns `true` if enigo/libei initialized successfully, `false` if
@@ -461,6 +796,18 @@ impl InputHandler {
 
         self.events_processed += 1;
 
+        // Sequenced events go through the jitter buffer when it is enabled
+        if let (Some(buffer), Some(sequence)) = (self.jitter_buffer.as_mut(), event.sequence) {
+            let now = Instant::now();
+            buffer.push(sequence, event.clone(), now);
+            return self.release_jitter_buffer(now).map(|_| ());
+        }
+
+        self.inject_event(event)
+    }
+
+    /// Convert a validated event to desktop space and inject it
+    fn inject_event(&mut self, event: &TouchEvent) -> Result<()> {
         // Convert to desktop coordinates
         let desktop_coords = self.normalize_to_desktop(event.x, event.y);
 
@@ -698,11 +1045,15 @@ impl InputHandler {
     /// Get statistics about processed events
     #[must_use]
     pub fn statistics(&self) -> InputStatistics {
+        let buffer = self.jitter_buffer.as_ref();
         InputStatistics {
             events_processed: self.events_processed,
             events_injected: self.events_injected,
             events_failed: self.events_failed,
             active_touches: self.active_touches.len(),
+            events_buffered: buffer.map_or(0, JitterBuffer::len),
+            events_dropped: buffer.map_or(0, JitterBuffer::dropped_count),
+            events_reordered: buffer.map_or(0, JitterBuffer::reordered_count),
         }
     }
 
@@ -711,6 +1062,9 @@ impl InputHandler {
         self.events_processed = 0;
         self.events_injected = 0;
         self.events_failed = 0;
+        if let Some(buffer) = self.jitter_buffer.as_mut() {
+            buffer.reset_counters();
+        }
     }
 }
 
@@ -725,6 +1079,12 @@ pub struct InputStatistics {
     pub events_failed: u64,
     /// Number of currently active touch points
     pub active_touches: usize,
+    /// Events waiting in the jitter buffer
+    pub events_buffered: usize,
+    /// Duplicate or stale events discarded by the jitter buffer
+    pub events_dropped: u64,
+    /// Events the jitter buffer received out of order
+    pub events_reordered: u64,
 }
 
 #[cfg(test)]
@@ -918,4 +1278,186 @@ mod tests {
         assert_eq!(deserialized.pressure, event.pressure);
         assert_eq!(deserialized.timestamp, event.timestamp);
     }
+
+    fn sequenced(action: TouchAction, sequence: u64, timestamp: u64) -> TouchEvent {
+        TouchEvent::new(0.5, 0.5, action, 0)
+            .with_sequence(sequence)
+            .with_timestamp(timestamp)
+    }
+
+    fn sequences(events: &[TouchEvent]) -> Vec<u64> {
+        events.iter().filter_map(|e| e.sequence).collect()
+    }
+
+    #[test]
+    fn test_jitter_buffer_reorders_jittered_input() {
+        let delay = Duration::from_millis(20);
+        let mut buffer = JitterBuffer::new(JitterBufferConfig::default().with_delay(delay));
+        let t0 = Instant::now();
+
+        // Sent 10ms apart; #3 overtakes #2 on the network
+        buffer.push(1, sequenced(TouchAction::Down, 1, 0), t0);
+        buffer.push(
+            3,
+            sequenced(TouchAction::Move, 3, 20),
+            t0 + Duration::from_millis(12),
+        );
+        buffer.push(
+            2,
+            sequenced(TouchAction::Move, 2, 10),
+            t0 + Duration::from_millis(14),
+        );
+        buffer.push(
+            4,
+            sequenced(TouchAction::Up, 4, 30),
+            t0 + Duration::from_millis(31),
+        );
+
+        assert!(buffer.pop_ready(t0 + Duration::from_millis(19)).is_empty());
+        assert_eq!(sequences(&buffer.pop_ready(t0 + delay)), vec![1]);
+        assert_eq!(
+            sequences(&buffer.pop_ready(t0 + delay + Duration::from_millis(25))),
+            vec![2, 3]
+        );
+        assert_eq!(
+            sequences(&buffer.pop_ready(t0 + delay + Duration::from_millis(30))),
+            vec![4]
+        );
+        assert_eq!(buffer.reordered_count(), 1);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_jitter_buffer_paces_bursts() {
+        let delay = Duration::from_millis(20);
+        let mut buffer = JitterBuffer::new(JitterBufferConfig::default().with_delay(delay));
+        let t0 = Instant::now();
+
+        // Three events sent 8ms apart arrive together after a stall
+        for sequence in 1..=3 {
+            buffer.push(
+                sequence,
+                sequenced(TouchAction::Move, sequence, (sequence - 1) * 8),
+                t0,
+            );
+        }
+
+        assert_eq!(buffer.next_release_at(), Some(t0 + delay));
+        assert_eq!(sequences(&buffer.pop_ready(t0 + delay)), vec![1]);
+        assert_eq!(
+            buffer.next_release_at(),
+            Some(t0 + delay + Duration::from_millis(8))
+        );
+        assert_eq!(
+            sequences(&buffer.pop_ready(t0 + delay + Duration::from_millis(8))),
+            vec![2]
+        );
+        assert_eq!(
+            sequences(&buffer.pop_ready(t0 + delay + Duration::from_millis(16))),
+            vec![3]
+        );
+    }
+
+    #[test]
+    fn test_jitter_buffer_drops_duplicates_and_stale() {
+        let mut buffer =
+            JitterBuffer::new(JitterBufferConfig::default().with_delay(Duration::ZERO));
+        let t0 = Instant::now();
+
+        assert!(buffer.push(1, sequenced(TouchAction::Down, 1, 0), t0));
+        assert!(!buffer.push(1, sequenced(TouchAction::Down, 1, 0), t0));
+        assert!(buffer.push(3, sequenced(TouchAction::Move, 3, 16), t0));
+        assert_eq!(
+            sequences(&buffer.pop_ready(t0 + Duration::from_millis(16))),
+            vec![1, 3]
+        );
+
+        // #2 arrives after #3 was injected
+        assert!(!buffer.push(2, sequenced(TouchAction::Move, 2, 8), t0));
+        assert_eq!(buffer.dropped_count(), 2);
+    }
+
+    #[test]
+    fn test_jitter_buffer_sequence_restart() {
+        let delay = Duration::from_millis(20);
+        let mut buffer = JitterBuffer::new(JitterBufferConfig::default().with_delay(delay));
+        let t0 = Instant::now();
+
+        buffer.push(5000, sequenced(TouchAction::Down, 5000, 0), t0);
+        assert_eq!(sequences(&buffer.pop_ready(t0 + delay)), vec![5000]);
+        buffer.push(5001, sequenced(TouchAction::Up, 5001, 10), t0 + delay);
+
+        // The sender restarted: #1 is not stale, and the queued #5001 goes first
+        let t1 = t0 + delay + Duration::from_millis(5);
+        assert!(buffer.push(1, sequenced(TouchAction::Down, 1, 0), t1));
+        assert!(buffer.push(2, sequenced(TouchAction::Move, 2, 10), t1));
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(
+            sequences(&buffer.pop_ready(t0 + delay + Duration::from_millis(30))),
+            vec![5001]
+        );
+        assert_eq!(sequences(&buffer.pop_ready(t1 + delay)), vec![1]);
+        assert_eq!(
+            sequences(&buffer.pop_ready(t1 + delay + Duration::from_millis(10))),
+            vec![2]
+        );
+        assert_eq!(buffer.dropped_count(), 0);
+    }
+
+    #[test]
+    fn test_jitter_buffer_capacity_releases_early() {
+        let config = JitterBufferConfig::default()
+            .with_delay(Duration::from_secs(10))
+            .with_capacity(2);
+        let mut buffer = JitterBuffer::new(config);
+        let t0 = Instant::now();
+
+        for sequence in 1..=3 {
+            buffer.push(
+                sequence,
+                sequenced(TouchAction::Move, sequence, sequence),
+                t0,
+            );
+        }
+
+        assert_eq!(sequences(&buffer.pop_ready(t0)), vec![1]);
+        assert_eq!(sequences(&buffer.drain()), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_handler_with_jitter_buffer() {
+        let config = JitterBufferConfig::default().with_delay(Duration::from_millis(30));
+        let mut handler = InputHandler::new((0, 0), (1920, 1080)).with_jitter_buffer(config);
+
+        handler
+            .handle_touch_event(&sequenced(TouchAction::Down, 1, 0))
+            .unwrap();
+        handler
+            .handle_touch_event(&sequenced(TouchAction::Down, 1, 0))
+            .unwrap();
+        assert_eq!(handler.active_touch_count(), 0);
+        assert!(handler.next_jitter_release().is_some());
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(handler.poll_jitter_buffer().unwrap(), 1);
+        assert_eq!(handler.active_touch_count(), 1);
+
+        let stats = handler.statistics();
+        assert_eq!(stats.events_processed, 2);
+        assert_eq!(stats.events_injected, 1);
+        assert_eq!(stats.events_dropped, 1);
+        assert_eq!(stats.events_buffered, 0);
+
+        // Unsequenced events bypass the buffer; disabling flushes what is queued
+        handler
+            .handle_touch_event(&TouchEvent::new(0.5, 0.5, TouchAction::Down, 1))
+            .unwrap();
+        assert_eq!(handler.active_touch_count(), 2);
+        handler
+            .handle_touch_event(&sequenced(TouchAction::Up, 2, 10))
+            .unwrap();
+        handler.set_jitter_buffer(None).unwrap();
+        assert_eq!(handler.active_touch_count(), 1);
+        assert!(handler.jitter_buffer_config().is_none());
+    }
 }
@@ -99,19 +99,26 @@ pub mod pipewire;
 pub mod streaming;
 
 pub use capture::{
-    BufferType, DamageRect, FrameStream, ScreenCapture, SessionState, VideoFrame, VideoTransform,
+    BufferType, CaptureEvent, CaptureSource, DamageRect, FrameStream, PixelFormat, PortalStream,
+    ScreenCapture, ScreenCastPortal, SessionResources, SessionState, SourceRequest, VideoFrame,
+    VideoTransform, XdgScreenCastPortal, DEFAULT_MAX_QUEUE_DEPTH,
+};
+pub use encoder::{
+    EncodedFrame, EncoderConfig, EncoderOverrides, EncoderType, FormatConversion, H264Profile,
+    LatencyTarget, TransportType, VideoEncoder, CPU_CONVERTER,
 };
-pub use encoder::{EncodedFrame, EncoderConfig, EncoderType, VideoEncoder};
 pub use error::{DisplayStreamError, Result};
 pub use gbm_devices::{
     gbm_to_spa_format, spa_format_to_gbm, DmaBufInfo, GbmDevice, GbmDeviceManager,
 };
 pub use input::{
-    DesktopCoordinates, DisplayGeometry, InputHandler, InputStatistics, TouchAction, TouchEvent,
+    DesktopCoordinates, DisplayGeometry, InputHandler, InputStatistics, JitterBufferConfig,
+    TouchAction, TouchEvent,
 };
 pub use output::OutputInfo;
 pub use streaming::{
-    ConnectionStats, StreamConfig, StreamingServer, TransportMode, split_nal_units,
+    ConnectionStats, DisconnectReason, HeartbeatMonitor, StatsHistory, StatsSample, StreamConfig,
+    StreamEvent, StreamingServer, TransportMode, split_nal_units,
 };
 
 /// Library version
@@ -19,6 +19,10 @@ pub struct OutputInfo {
 
     /// Whether this is a virtual/dummy display
     pub is_virtual: bool,
+
+    /// Title of the captured window, for single-window capture
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_title: Option<String>,
 }
 
 impl OutputInfo {
@@ -31,9 +35,17 @@ impl OutputInfo {
             height,
             refresh_rate,
             is_virtual,
+            window_title: None,
         }
     }
 
+    /// Set the title of the captured window
+    #[must_use]
+    pub fn with_window_title(mut self, title: Option<String>) -> Self {
+        self.window_title = title;
+        self
+    }
+
     /// Check if this output is an HDMI dummy plug
     ///
     /// HDMI dummy plugs typically have names like "HDMI-2", "HDMI-A-2", etc.
@@ -49,9 +61,13 @@ impl OutputInfo {
     /// Format output description for display
     #[must_use] 
     pub fn description(&self) -> String {
+        let name = match &self.window_title {
+            Some(title) => format!("{}: {title}", self.name),
+            None => self.name.clone(),
+        };
         format!(
             "{} ({}x{} @ {}Hz{})",
-            self.name,
+            name,
             self.width,
             self.height,
             self.refresh_rate,
@@ -81,4 +97,13 @@ mod tests {
         let output = OutputInfo::new("HDMI-2".to_string(), 1920, 1080, 60, true);
         assert_eq!(output.description(), "HDMI-2 (1920x1080 @ 60Hz, virtual)");
     }
+
+    #[test]
+    fn test_window_title() {
+        let output = OutputInfo::new("portal".to_string(), 1280, 720, 60, false);
+        assert_eq!(output.window_title, None);
+
+        let window = output.with_window_title(Some("Terminal".to_string()));
+        assert_eq!(window.description(), "portal: Terminal (1280x720 @ 60Hz)");
+    }
 }
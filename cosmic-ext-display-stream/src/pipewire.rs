@@ -3,7 +3,7 @@
 //! This module provides integration with `PipeWire` to receive raw video frames
 //! from the screen capture session.
 
-use crate::capture::{BufferType, DamageRect, VideoFrame, VideoTransform};
+use crate::capture::{BufferType, DamageRect, PixelFormat, VideoFrame, VideoTransform};
 use crate::error::Result;
 use pipewire as pw;
 use pipewire::context::Context;
@@ -14,6 +14,8 @@ use pipewire::spa::param::ParamType;
 use pipewire::spa::sys as spa_sys;
 use pipewire::spa::utils::Direction;
 use pipewire::stream::{Stream, StreamFlags, StreamState};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -78,11 +80,18 @@ impl PipeWireStream {
     ///
     /// * `node_id` - `PipeWire` node ID from the portal session
     /// * `frame_sender` - Channel to send captured frames
+    /// * `on_source_closed` - Called once if the node goes away while
+    ///   streaming (e.g. the captured window was closed). The stream then
+    ///   stops and `frame_sender` is dropped.
     ///
     /// # Returns
     ///
     /// A connected `PipeWire` stream ready to receive frames
-    pub async fn connect(node_id: u32, frame_sender: mpsc::Sender<VideoFrame>) -> Result<Self> {
+    pub async fn connect(
+        node_id: u32,
+        frame_sender: mpsc::Sender<VideoFrame>,
+        on_source_closed: impl FnOnce() + Send + 'static,
+    ) -> Result<Self> {
         info!("Connecting to PipeWire node: {}", node_id);
 
         let connected = Arc::new(AtomicBool::new(false));
@@ -94,8 +103,13 @@ impl PipeWireStream {
 
         // Spawn PipeWire thread
         let thread_handle = std::thread::spawn(move || {
-            if let Err(e) = run_pipewire_loop(node_id, frame_sender, running_clone, connected_clone)
-            {
+            if let Err(e) = run_pipewire_loop(
+                node_id,
+                frame_sender,
+                running_clone,
+                connected_clone,
+                on_source_closed,
+            ) {
                 error!("PipeWire loop error: {}", e);
             }
         });
@@ -146,6 +160,97 @@ impl PipeWireStream {
     }
 }
 
+impl Drop for PipeWireStream {
+    fn drop(&mut self) {
+        // Dropped without disconnect: stop the loop but don't block on the
+        // thread, which exits within one loop iteration
+        if self.thread_handle.take().is_some() {
+            debug!(
+                "PipeWire stream for node {} dropped, stopping",
+                self.node_id
+            );
+            self.running.store(false, Ordering::SeqCst);
+            self.connected.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Title of the window a `PipeWire` node captures
+///
+/// Portal backends name the node they create for a window capture after the
+/// window, in `node.description` or `media.name`. Blocks until the `PipeWire`
+/// server has listed its nodes; returns `None` if the node is gone or
+/// unnamed.
+#[must_use]
+pub fn node_title(node_id: u32) -> Option<String> {
+    match lookup_node_title(node_id) {
+        Ok(title) => title,
+        Err(e) => {
+            debug!("Could not look up PipeWire node {}: {}", node_id, e);
+            None
+        }
+    }
+}
+
+fn lookup_node_title(node_id: u32) -> Result<Option<String>> {
+    pw::init();
+
+    let mainloop = MainLoop::new(None).map_err(|e| {
+        crate::error::DisplayStreamError::PipeWire(format!(
+            "Failed to create PipeWire main loop: {e}"
+        ))
+    })?;
+    let context = Context::new(&mainloop).map_err(|e| {
+        crate::error::DisplayStreamError::PipeWire(format!("Failed to create context: {e}"))
+    })?;
+    let core = context.connect(None).map_err(|e| {
+        crate::error::DisplayStreamError::PipeWire(format!("Failed to connect to PipeWire: {e}"))
+    })?;
+    let registry = core.get_registry().map_err(|e| {
+        crate::error::DisplayStreamError::PipeWire(format!("Failed to get registry: {e}"))
+    })?;
+
+    let title = Rc::new(RefCell::new(None));
+    let title_clone = title.clone();
+    let _registry_listener = registry
+        .add_listener_local()
+        .global(move |global| {
+            if global.id != node_id {
+                return;
+            }
+            let name = global.props.and_then(|props| {
+                props
+                    .get(*pw::keys::NODE_DESCRIPTION)
+                    .or_else(|| props.get(*pw::keys::MEDIA_NAME))
+            });
+            *title_clone.borrow_mut() = name.filter(|n| !n.is_empty()).map(str::to_string);
+        })
+        .register();
+
+    // Every existing global is announced before the server answers the sync
+    let done = Rc::new(Cell::new(false));
+    let done_clone = done.clone();
+    let mainloop_clone = mainloop.clone();
+    let pending = core.sync(0).map_err(|e| {
+        crate::error::DisplayStreamError::PipeWire(format!("Failed to sync with PipeWire: {e}"))
+    })?;
+    let _core_listener = core
+        .add_listener_local()
+        .done(move |id, seq| {
+            if id == pw::core::PW_ID_CORE && seq == pending {
+                done_clone.set(true);
+                mainloop_clone.quit();
+            }
+        })
+        .register();
+
+    while !done.get() {
+        mainloop.run();
+    }
+
+    Ok(title.take())
+}
+
 /// Run the `PipeWire` main loop (called from background thread)
 #[allow(clippy::needless_pass_by_value, clippy::too_many_lines)]
 fn run_pipewire_loop(
@@ -153,6 +258,7 @@ fn run_pipewire_loop(
     frame_sender: mpsc::Sender<VideoFrame>,
     running: Arc<AtomicBool>,
     connected: Arc<AtomicBool>,
+    on_source_closed: impl FnOnce() + Send + 'static,
 ) -> Result<()> {
     // Initialize PipeWire
     pw::init();
@@ -202,6 +308,8 @@ fn run_pipewire_loop(
 
     let connected_clone = connected.clone();
     let running_clone = running.clone();
+    let running_state = running.clone();
+    let mut on_source_closed = Some(on_source_closed);
 
     // Add stream listener
     let _listener = stream
@@ -210,6 +318,18 @@ fn run_pipewire_loop(
             debug!("Stream state changed: {:?} -> {:?}", old, new);
             if new == StreamState::Streaming {
                 connected_clone.store(true, Ordering::SeqCst);
+            } else if connected_clone.load(Ordering::SeqCst)
+                && running_state.load(Ordering::SeqCst)
+                && matches!(new, StreamState::Unconnected | StreamState::Error(_))
+            {
+                // The node was removed under us, which is what happens when a
+                // captured window closes. End the session instead of idling.
+                info!("PipeWire source went away, ending stream");
+                connected_clone.store(false, Ordering::SeqCst);
+                running_state.store(false, Ordering::SeqCst);
+                if let Some(callback) = on_source_closed.take() {
+                    callback();
+                }
             }
         })
         .param_changed(move |_stream, _user_data, id, param| {
@@ -300,7 +420,8 @@ fn run_pipewire_loop(
                         data: Vec::new(), // No CPU copy for DMA-BUF
                         width,
                         height,
-                        format: "DMA-BUF".to_string(),
+                        // XR24 until the negotiated fourcc is parsed
+                        format: PixelFormat::Bgrx,
                         timestamp: std::time::SystemTime::now()
                             .duration_since(std::time::UNIX_EPOCH)
                             .map(|d| i64::try_from(d.as_micros()).unwrap_or(i64::MAX))
@@ -357,7 +478,7 @@ fn run_pipewire_loop(
                         frame_data,
                         inferred_width,
                         inferred_height,
-                        "BGRx".to_string(), // Most common format from screen capture
+                        PixelFormat::Bgrx, // Most common format from screen capture
                         std::time::SystemTime::now()
                             .duration_since(std::time::UNIX_EPOCH)
                             .map(|d| i64::try_from(d.as_micros()).unwrap_or(i64::MAX))
@@ -26,6 +26,46 @@
 //! - Support for `WiFi` and USB (ADB port forwarding) connections
 //! - Connection statistics and monitoring
 //! - Adaptive bitrate hints
+//! - Data-channel heartbeats with automatic teardown of lost clients
+//! - Multi-viewer fan-out with a single designated input controller
+//! - Frame dropping under congestion that keeps the stream decodable
+//! - A rolling history of bitrate, FPS and RTT for bandwidth graphs
+//!
+//! ## Multiple Viewers
+//!
+//! Raise [`StreamConfig::max_clients`] to stream one desktop to several tablets.
+//! Each frame is encoded and packetized once and the same RTP packets are
+//! written to every peer's track. Statistics are tracked per peer;
+//! [`StreamingServer::get_stats`] reports the worst-performing link so bitrate
+//! adaptation never outruns the slowest viewer.
+//!
+//! A viewer joining a running stream cannot decode anything until the next
+//! keyframe, so every new peer emits [`StreamEvent::KeyframeRequested`]; the
+//! frame source should answer with
+//! [`VideoEncoder::force_keyframe`](crate::encoder::VideoEncoder::force_keyframe).
+//! The new peer's statistics start at zero, while those of peers already
+//! watching carry on.
+//!
+//! Touch input sent over a peer's data channel is only accepted from the
+//! controller peer. The first peer to join becomes the controller; another
+//! peer can be promoted with [`StreamingServer::set_controller`].
+//!
+//! ## Congestion
+//!
+//! Writing RTP packets waits on the peer's send path, so a link that cannot
+//! keep up shows as frames sitting in the queue. A frame that waited longer
+//! than [`StreamConfig::max_frame_latency`] before being written to a client
+//! is dropped for that client instead of adding to the delay, unless it is a
+//! keyframe. Later P-frames reference the dropped one, so the client then
+//! skips every P-frame until the next keyframe, which is always sent. Frames
+//! skipped this way are counted in [`ConnectionStats::frames_congested`].
+//!
+//! ## Stats History
+//!
+//! While running, the server samples the stream's outgoing bitrate and frame
+//! rate, plus the worst link's RTT, every [`StreamConfig::stats_sample_interval`]
+//! and keeps the last [`StreamConfig::stats_history_len`] samples. Use
+//! [`StreamingServer::stats_history`] to draw a sparkline of recent bandwidth.
 //!
 //! ## Example
 //!
@@ -56,18 +96,22 @@
 
 use crate::encoder::EncodedFrame;
 use crate::error::{DisplayStreamError, Result};
+use crate::input::TouchEvent;
 use futures_util::StreamExt;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{mpsc, Mutex, Notify, RwLock};
+use tokio::sync::{broadcast, mpsc, Mutex, Notify, RwLock};
 use tokio_tungstenite::{accept_async, tungstenite::Message};
 use tracing::{debug, error, info, warn};
 use webrtc::api::interceptor_registry::register_default_interceptors;
 use webrtc::api::media_engine::MediaEngine;
 use webrtc::api::APIBuilder;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+use webrtc::data_channel::RTCDataChannel;
 use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
 use webrtc::ice_transport::ice_server::RTCIceServer;
 use webrtc::interceptor::registry::Registry;
@@ -87,8 +131,27 @@ const NAL_START_CODE: [u8; 4] = [0x00, 0x00, 0x00, 0x01];
 /// Short NAL start code
 const NAL_SHORT_START_CODE: [u8; 3] = [0x00, 0x00, 0x01];
 
+/// Default time a frame may wait before it is dropped under congestion
+pub const DEFAULT_MAX_FRAME_LATENCY: Duration = Duration::from_millis(100);
+
+/// Default time without a client heartbeat before the client is considered lost
+pub const DEFAULT_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default number of stats samples kept for the bandwidth graph
+pub const DEFAULT_STATS_HISTORY_LEN: usize = 60;
+
+/// Default time between stats samples
+pub const DEFAULT_STATS_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Capacity of the stream event broadcast channel
+const EVENT_CHANNEL_CAPACITY: usize = 16;
+
+/// Capacity of the controller input broadcast channel
+const INPUT_CHANNEL_CAPACITY: usize = 64;
+
 /// Transport mode for streaming
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TransportMode {
     /// Stream over `WiFi` network
     #[default]
@@ -109,7 +172,8 @@ impl TransportMode {
 }
 
 /// Streaming server configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct StreamConfig {
     /// Port for WebSocket signaling server
     pub signaling_port: u16,
@@ -125,6 +189,69 @@ pub struct StreamConfig {
     pub enable_encryption: bool,
     /// Target framerate in frames per second
     pub framerate: u32,
+    /// Time without a data-channel heartbeat before a client is torn down
+    ///
+    /// `None` disables the keepalive watchdog. Serialized as whole seconds.
+    #[serde(with = "optional_duration_secs")]
+    pub heartbeat_timeout: Option<Duration>,
+    /// Longest a P-frame may wait before it is dropped instead of sent
+    ///
+    /// `None` sends every frame however late. Serialized as milliseconds.
+    #[serde(with = "optional_duration_millis")]
+    pub max_frame_latency: Option<Duration>,
+    /// Number of stats samples kept; older samples are evicted
+    pub stats_history_len: usize,
+    /// Time between stats samples
+    ///
+    /// `None` disables the stats history. Serialized as milliseconds.
+    #[serde(with = "optional_duration_millis")]
+    pub stats_sample_interval: Option<Duration>,
+}
+
+/// Serde helper storing an optional [`Duration`] as whole seconds
+mod optional_duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    #[allow(clippy::ref_option)]
+    pub fn serialize<S: Serializer>(
+        value: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(duration) => serializer.serialize_some(&duration.as_secs()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_secs))
+    }
+}
+
+/// Serde helper storing an optional [`Duration`] as whole milliseconds
+mod optional_duration_millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    #[allow(clippy::ref_option, clippy::cast_possible_truncation)]
+    pub fn serialize<S: Serializer>(
+        value: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(duration) => serializer.serialize_some(&(duration.as_millis() as u64)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_millis))
+    }
 }
 
 impl Default for StreamConfig {
@@ -137,9 +264,13 @@ impl Default for StreamConfig {
                 "stun:stun.l.google.com:19302".to_string(),
                 "stun:stun1.l.google.com:19302".to_string(),
             ],
-            max_clients: 1, // Single tablet for now
+            max_clients: 1, // Raise to stream to several tablets
             enable_encryption: true,
             framerate: 60,
+            heartbeat_timeout: Some(DEFAULT_HEARTBEAT_TIMEOUT),
+            max_frame_latency: Some(DEFAULT_MAX_FRAME_LATENCY),
+            stats_history_len: DEFAULT_STATS_HISTORY_LEN,
+            stats_sample_interval: Some(DEFAULT_STATS_SAMPLE_INTERVAL),
         }
     }
 }
@@ -193,6 +324,34 @@ impl StreamConfig {
         self
     }
 
+    /// Set the heartbeat timeout (`None` disables the keepalive watchdog)
+    #[must_use]
+    pub fn with_heartbeat_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.heartbeat_timeout = timeout;
+        self
+    }
+
+    /// Set how late a P-frame may be before it is dropped (`None` never drops)
+    #[must_use]
+    pub fn with_max_frame_latency(mut self, latency: Option<Duration>) -> Self {
+        self.max_frame_latency = latency;
+        self
+    }
+
+    /// Set how many stats samples are kept (at least one)
+    #[must_use]
+    pub fn with_stats_history_len(mut self, len: usize) -> Self {
+        self.stats_history_len = len.max(1);
+        self
+    }
+
+    /// Set the time between stats samples (`None` disables the history)
+    #[must_use]
+    pub fn with_stats_sample_interval(mut self, interval: Option<Duration>) -> Self {
+        self.stats_sample_interval = interval;
+        self
+    }
+
     /// Get the full signaling server address
     #[must_use]
     pub fn signaling_address(&self) -> String {
@@ -213,6 +372,13 @@ pub struct ConnectionStats {
     pub packets_lost: u64,
     /// Frames sent
     pub frames_sent: u64,
+    /// Frames dropped before encoding because the encoder fell behind
+    ///
+    /// Shared by all clients of the server; see
+    /// [`StreamingServer::record_dropped_frames`].
+    pub frames_dropped: u64,
+    /// Frames skipped for this client because its link was congested
+    pub frames_congested: u64,
     /// Connection duration in seconds
     pub duration_secs: u64,
     /// ICE connection state
@@ -221,6 +387,311 @@ pub struct ConnectionStats {
     pub connection_state: String,
 }
 
+impl ConnectionStats {
+    /// Fraction of sent packets reported lost (0.0-1.0)
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn loss_ratio(&self) -> f64 {
+        if self.packets_sent == 0 {
+            return 0.0;
+        }
+        (self.packets_lost as f64 / self.packets_sent as f64).min(1.0)
+    }
+
+    /// Whether this link performs worse than `other`
+    ///
+    /// Links are ranked by loss ratio, then by round-trip time.
+    #[must_use]
+    pub fn is_worse_than(&self, other: &Self) -> bool {
+        match self.loss_ratio().total_cmp(&other.loss_ratio()) {
+            std::cmp::Ordering::Greater => true,
+            std::cmp::Ordering::Less => false,
+            std::cmp::Ordering::Equal => self.rtt_ms > other.rtt_ms,
+        }
+    }
+}
+
+/// One point of the stats history
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatsSample {
+    /// When the sample was taken
+    pub at: Instant,
+    /// Outgoing stream bitrate since the previous sample, in bits per second
+    pub bitrate_bps: u64,
+    /// Frames sent per second since the previous sample
+    pub fps: f64,
+    /// Round-trip time of the worst link in milliseconds
+    pub rtt_ms: u32,
+}
+
+/// Bounded history of stats samples, oldest first
+#[derive(Debug, Clone)]
+pub struct StatsHistory {
+    samples: VecDeque<StatsSample>,
+    capacity: usize,
+}
+
+impl StatsHistory {
+    /// Create an empty history keeping at most `capacity` samples
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Append a sample, evicting the oldest once full
+    pub fn push(&mut self, sample: StatsSample) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Samples from oldest to newest
+    pub fn samples(&self) -> impl Iterator<Item = &StatsSample> {
+        self.samples.iter()
+    }
+
+    /// Most recent sample
+    #[must_use]
+    pub fn latest(&self) -> Option<&StatsSample> {
+        self.samples.back()
+    }
+
+    /// Number of samples held
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Whether no sample has been taken yet
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Most samples kept
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// Turns running stream counters into per-interval rates
+#[derive(Debug)]
+struct StatsSampler {
+    last_at: Instant,
+    last_frames: u64,
+    last_bytes: u64,
+}
+
+impl StatsSampler {
+    fn new(now: Instant) -> Self {
+        Self {
+            last_at: now,
+            last_frames: 0,
+            last_bytes: 0,
+        }
+    }
+
+    /// Sample the counters at `now`, rating what was sent since the last call
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn sample(&mut self, counters: &SharedCounters, rtt_ms: u32, now: Instant) -> StatsSample {
+        let frames = counters.frames_sent.load(Ordering::Relaxed);
+        let bytes = counters.bytes_sent.load(Ordering::Relaxed);
+        let elapsed = now.saturating_duration_since(self.last_at).as_secs_f64();
+
+        let (bitrate_bps, fps) = if elapsed > 0.0 {
+            let bits = bytes.saturating_sub(self.last_bytes) * 8;
+            let frames = frames.saturating_sub(self.last_frames);
+            ((bits as f64 / elapsed) as u64, frames as f64 / elapsed)
+        } else {
+            (0, 0.0)
+        };
+
+        self.last_at = now;
+        self.last_frames = frames;
+        self.last_bytes = bytes;
+        StatsSample {
+            at: now,
+            bitrate_bps,
+            fps,
+            rtt_ms,
+        }
+    }
+}
+
+/// Why a streaming client was removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The client closed the signaling connection
+    Closed,
+    /// No heartbeat was received within the configured timeout
+    HeartbeatTimeout,
+}
+
+/// Events emitted by the streaming server
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    /// A client joined and needs a keyframe to start decoding
+    KeyframeRequested {
+        /// The joined client's ID
+        client_id: String,
+    },
+    /// A client was removed, with its final connection statistics
+    ClientDisconnected {
+        /// The removed client's ID
+        client_id: String,
+        /// Why the client was removed
+        reason: DisconnectReason,
+        /// Statistics at the time of removal
+        stats: ConnectionStats,
+        /// Number of clients still connected
+        remaining_clients: usize,
+    },
+}
+
+/// Tracks the last heartbeat time of each client
+///
+/// Any message on a client's WebRTC data channel counts as a heartbeat.
+/// Only clients that have opened a data channel are tracked.
+#[derive(Debug, Clone, Default)]
+pub struct HeartbeatMonitor {
+    last_seen: Arc<std::sync::Mutex<HashMap<String, Instant>>>,
+}
+
+impl HeartbeatMonitor {
+    /// Create an empty monitor
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start tracking a client, treating registration as its first heartbeat
+    pub fn register(&self, client_id: &str) {
+        self.beat(client_id);
+    }
+
+    /// Record a heartbeat for a client
+    pub fn beat(&self, client_id: &str) {
+        if let Ok(mut last_seen) = self.last_seen.lock() {
+            last_seen.insert(client_id.to_string(), Instant::now());
+        }
+    }
+
+    /// Stop tracking a client
+    pub fn remove(&self, client_id: &str) {
+        if let Ok(mut last_seen) = self.last_seen.lock() {
+            last_seen.remove(client_id);
+        }
+    }
+
+    /// Remove and return all clients whose last heartbeat is older than `timeout`
+    #[must_use]
+    pub fn take_expired(&self, timeout: Duration) -> Vec<String> {
+        let Ok(mut last_seen) = self.last_seen.lock() else {
+            return Vec::new();
+        };
+        let expired: Vec<String> = last_seen
+            .iter()
+            .filter(|(_, seen)| seen.elapsed() > timeout)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &expired {
+            last_seen.remove(id);
+        }
+        expired
+    }
+}
+
+/// Spawn a task that reports clients whose heartbeat has lapsed
+///
+/// Checks four times per `timeout` and sends each expired client ID on the
+/// returned channel until `shutdown` is notified.
+#[must_use]
+pub fn spawn_heartbeat_watchdog(
+    monitor: HeartbeatMonitor,
+    timeout: Duration,
+    shutdown: Arc<Notify>,
+) -> (tokio::task::JoinHandle<()>, mpsc::Receiver<String>) {
+    let (expired_tx, expired_rx) = mpsc::channel(8);
+    let check_interval = (timeout / 4).max(Duration::from_millis(10));
+
+    let handle = tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(check_interval);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    for client_id in monitor.take_expired(timeout) {
+                        warn!("Client {} missed heartbeats for {:?}", client_id, timeout);
+                        if expired_tx.send(client_id).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                () = shutdown.notified() => break,
+            }
+        }
+        debug!("Heartbeat watchdog shut down");
+    });
+
+    (handle, expired_rx)
+}
+
+/// Destination for a peer's RTP packets
+#[async_trait::async_trait]
+trait RtpSink: std::fmt::Debug + Send + Sync {
+    /// Write one RTP packet to the peer
+    async fn write_packet(&self, packet: &webrtc::rtp::packet::Packet) -> Result<()>;
+}
+
+#[async_trait::async_trait]
+impl RtpSink for TrackLocalStaticRTP {
+    async fn write_packet(&self, packet: &webrtc::rtp::packet::Packet) -> Result<()> {
+        self.write_rtp(packet)
+            .await
+            .map(|_| ())
+            .map_err(|e| DisplayStreamError::Streaming(format!("Failed to write RTP packet: {e}")))
+    }
+}
+
+/// Decides which frames a congested client skips
+///
+/// See the module documentation on congestion.
+#[derive(Debug, Default)]
+struct CongestionGate {
+    /// A P-frame was skipped; nothing but a keyframe decodes until one arrives
+    awaiting_keyframe: bool,
+}
+
+impl CongestionGate {
+    /// Whether to send a frame that has waited `queued_for`
+    fn admit(&mut self, is_keyframe: bool, queued_for: Duration, budget: Option<Duration>) -> bool {
+        if is_keyframe {
+            self.awaiting_keyframe = false;
+            return true;
+        }
+        if !self.awaiting_keyframe && budget.is_some_and(|budget| queued_for > budget) {
+            self.awaiting_keyframe = true;
+        }
+        !self.awaiting_keyframe
+    }
+}
+
+/// An encoded frame waiting to be sent
+#[derive(Debug)]
+struct QueuedFrame {
+    frame: EncodedFrame,
+    queued_at: Instant,
+}
+
 /// Client connection information
 #[derive(Debug)]
 struct ClientConnection {
@@ -229,11 +700,35 @@ struct ClientConnection {
     /// WebRTC peer connection
     peer_connection: Arc<RTCPeerConnection>,
     /// Video track for sending frames
-    video_track: Arc<TrackLocalStaticRTP>,
+    video_track: Arc<dyn RtpSink>,
     /// Connection timestamp
     connected_at: std::time::Instant,
     /// Per-client connection stats (shared with RTCP reader task)
     stats: Arc<RwLock<ClientStats>>,
+    /// Packets and frames delivered to this client
+    counters: Arc<SharedCounters>,
+    /// Frame dropping state under congestion
+    congestion: std::sync::Mutex<CongestionGate>,
+}
+
+impl ClientConnection {
+    /// A client that just joined, with its statistics at zero
+    fn new(
+        id: String,
+        peer_connection: Arc<RTCPeerConnection>,
+        video_track: Arc<dyn RtpSink>,
+        stats: Arc<RwLock<ClientStats>>,
+    ) -> Self {
+        Self {
+            id,
+            peer_connection,
+            video_track,
+            connected_at: Instant::now(),
+            stats,
+            counters: Arc::new(SharedCounters::default()),
+            congestion: std::sync::Mutex::default(),
+        }
+    }
 }
 
 /// Per-client statistics populated from RTCP Receiver Reports
@@ -254,6 +749,8 @@ pub struct ClientStats {
 struct SharedCounters {
     packets_sent: AtomicU64,
     frames_sent: AtomicU64,
+    frames_congested: AtomicU64,
+    bytes_sent: AtomicU64,
 }
 
 /// Signaling message types
@@ -310,21 +807,37 @@ pub struct StreamingServer {
     /// Signaling server handle
     signaling_handle: Option<tokio::task::JoinHandle<()>>,
     /// Channel for sending frames to clients
-    frame_tx: mpsc::Sender<EncodedFrame>,
+    frame_tx: mpsc::Sender<QueuedFrame>,
     /// Frame receiver for the broadcast task
-    frame_rx: Arc<Mutex<mpsc::Receiver<EncodedFrame>>>,
+    frame_rx: Arc<Mutex<mpsc::Receiver<QueuedFrame>>>,
     /// Server running state
     running: Arc<AtomicBool>,
     /// Server ID
     server_id: String,
     /// Shutdown notification for graceful stop
     shutdown_notify: Arc<Notify>,
-    /// Shared packet/frame counters
-    counters: Arc<SharedCounters>,
+    /// Peer whose input is accepted
+    controller: Arc<RwLock<Option<String>>>,
+    /// Controller input broadcaster
+    input_tx: broadcast::Sender<TouchEvent>,
     /// SSRC for RTP packets (random per RFC 3550)
     ssrc: u32,
     /// RTP timestamp increment per frame (90000 Hz / framerate)
     rtp_timestamp_increment: u32,
+    /// Last heartbeat per client
+    heartbeats: HeartbeatMonitor,
+    /// Stream event broadcaster
+    event_tx: broadcast::Sender<StreamEvent>,
+    /// Heartbeat watchdog handle
+    watchdog_handle: Option<tokio::task::JoinHandle<()>>,
+    /// Frames the capture loop dropped instead of encoding
+    frames_dropped: AtomicU64,
+    /// Frames and bytes of the shared stream, counted once for all clients
+    stream_counters: Arc<SharedCounters>,
+    /// Recent bitrate, FPS and RTT samples
+    stats_history: Arc<std::sync::Mutex<StatsHistory>>,
+    /// Stats sampler handle
+    sampler_handle: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl StreamingServer {
@@ -365,6 +878,10 @@ impl StreamingServer {
         // Compute RTP timestamp increment: 90000 Hz / framerate
         let rtp_timestamp_increment = 90000 / config.framerate;
 
+        let (event_tx, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let (input_tx, _) = broadcast::channel(INPUT_CHANNEL_CAPACITY);
+        let stats_history = StatsHistory::new(config.stats_history_len);
+
         Ok(Self {
             config,
             api,
@@ -375,9 +892,17 @@ impl StreamingServer {
             running: Arc::new(AtomicBool::new(false)),
             server_id,
             shutdown_notify: Arc::new(Notify::new()),
-            counters: Arc::new(SharedCounters::default()),
+            controller: Arc::new(RwLock::new(None)),
+            input_tx,
             ssrc,
             rtp_timestamp_increment,
+            heartbeats: HeartbeatMonitor::new(),
+            event_tx,
+            watchdog_handle: None,
+            frames_dropped: AtomicU64::new(0),
+            stream_counters: Arc::new(SharedCounters::default()),
+            stats_history: Arc::new(std::sync::Mutex::new(stats_history)),
+            sampler_handle: None,
         })
     }
 
@@ -420,6 +945,12 @@ impl StreamingServer {
         // Start frame broadcast task
         self.start_frame_broadcaster();
 
+        // Start keepalive watchdog
+        self.start_heartbeat_watchdog();
+
+        // Start sampling stats for the bandwidth graph
+        self.start_stats_sampler();
+
         self.running.store(true, Ordering::SeqCst);
         info!(
             "Streaming server started on {}",
@@ -446,12 +977,21 @@ impl StreamingServer {
             debug!("Closing connection for client {}", id);
             let _ = client.peer_connection.close().await;
         }
+        *self.controller.write().await = None;
 
         // Stop signaling server
         if let Some(handle) = self.signaling_handle.take() {
             handle.abort();
         }
 
+        if let Some(handle) = self.watchdog_handle.take() {
+            handle.abort();
+        }
+
+        if let Some(handle) = self.sampler_handle.take() {
+            handle.abort();
+        }
+
         self.running.store(false, Ordering::SeqCst);
         info!("Streaming server stopped");
 
@@ -472,6 +1012,10 @@ impl StreamingServer {
         let server_id = self.server_id.clone();
         let shutdown_notify = self.shutdown_notify.clone();
         let max_clients = self.config.max_clients;
+        let heartbeats = self.heartbeats.clone();
+        let event_tx = self.event_tx.clone();
+        let controller = self.controller.clone();
+        let input_tx = self.input_tx.clone();
 
         let handle = tokio::spawn(async move {
             info!("Signaling server listening on {}", addr);
@@ -501,10 +1045,15 @@ impl StreamingServer {
                                 let api = api.clone();
                                 let config = config.clone();
                                 let server_id = server_id.clone();
+                                let heartbeats = heartbeats.clone();
+                                let event_tx = event_tx.clone();
+                                let controller = controller.clone();
+                                let input_tx = input_tx.clone();
 
                                 tokio::spawn(async move {
                                     if let Err(e) = Self::handle_signaling_connection(
                                         stream, peer_addr, clients, api, config, server_id,
+                                        heartbeats, event_tx, controller, input_tx,
                                     )
                                     .await
                                     {
@@ -529,7 +1078,7 @@ impl StreamingServer {
     }
 
     /// Handle a signaling connection
-    #[allow(clippy::too_many_lines)]
+    #[allow(clippy::too_many_lines, clippy::too_many_arguments)]
     async fn handle_signaling_connection(
         stream: TcpStream,
         peer_addr: SocketAddr,
@@ -537,6 +1086,10 @@ impl StreamingServer {
         api: Arc<webrtc::api::API>,
         config: StreamConfig,
         server_id: String,
+        heartbeats: HeartbeatMonitor,
+        event_tx: broadcast::Sender<StreamEvent>,
+        controller: Arc<RwLock<Option<String>>>,
+        input_tx: broadcast::Sender<TouchEvent>,
     ) -> Result<()> {
         let ws_stream = accept_async(stream).await.map_err(|e| {
             DisplayStreamError::Streaming(format!("WebSocket handshake failed: {e}"))
@@ -620,6 +1173,43 @@ impl StreamingServer {
             })
         }));
 
+        // Any message on a client-opened data channel counts as a heartbeat. Clients
+        // are only monitored once they open a channel, so clients without keepalive
+        // support are never torn down by the watchdog. Touch events on the channel
+        // are forwarded only when this client is the controller.
+        let heartbeats_dc = heartbeats.clone();
+        let client_id_dc = client_id.clone();
+        let controller_dc = controller.clone();
+        let input_tx_dc = input_tx.clone();
+        peer_connection.on_data_channel(Box::new(move |channel: Arc<RTCDataChannel>| {
+            let heartbeats = heartbeats_dc.clone();
+            let client_id = client_id_dc.clone();
+            let controller = controller_dc.clone();
+            let input_tx = input_tx_dc.clone();
+            Box::pin(async move {
+                debug!(
+                    "Client {} opened data channel '{}'",
+                    client_id,
+                    channel.label()
+                );
+                heartbeats.register(&client_id);
+                channel.on_message(Box::new(move |msg: DataChannelMessage| {
+                    heartbeats.beat(&client_id);
+                    let client_id = client_id.clone();
+                    let controller = controller.clone();
+                    let input_tx = input_tx.clone();
+                    Box::pin(async move {
+                        let controller = controller.read().await;
+                        if let Some(event) =
+                            accept_controller_input(controller.as_deref(), &client_id, &msg.data)
+                        {
+                            let _ = input_tx.send(event);
+                        }
+                    })
+                }));
+            })
+        }));
+
         // Set up connection state handler
         let client_id_state = client_id.clone();
         peer_connection.on_peer_connection_state_change(Box::new(move |state| {
@@ -646,16 +1236,22 @@ impl StreamingServer {
                 let _ = peer_connection.close().await;
                 return Ok(());
             }
-            clients_guard.insert(
-                client_id.clone(),
-                ClientConnection {
-                    id: client_id.clone(),
-                    peer_connection: peer_connection.clone(),
+            Self::join_client(
+                &mut clients_guard,
+                ClientConnection::new(
+                    client_id.clone(),
+                    peer_connection.clone(),
                     video_track,
-                    connected_at: std::time::Instant::now(),
-                    stats: client_stats,
-                },
+                    client_stats,
+                ),
+                &event_tx,
             );
+
+            let mut controller_guard = controller.write().await;
+            if controller_guard.is_none() {
+                info!("Client {} is now the input controller", client_id);
+                *controller_guard = Some(client_id.clone());
+            }
         }
 
         // Process signaling messages
@@ -742,17 +1338,158 @@ impl StreamingServer {
             }
         }
 
-        // Clean up client
-        {
+        // Clean up client (may already be gone if the heartbeat watchdog removed it)
+        heartbeats.remove(&client_id);
+        let removed = {
             let mut clients_guard = clients.write().await;
-            if let Some(client) = clients_guard.remove(&client_id) {
-                let _ = client.peer_connection.close().await;
-            }
+            clients_guard
+                .remove(&client_id)
+                .map(|client| (client, clients_guard.len()))
+        };
+        if let Some((client, remaining_clients)) = removed {
+            Self::release_controller(&controller, &client_id).await;
+            let stats = Self::build_stats(&client).await;
+            let _ = client.peer_connection.close().await;
+            let _ = event_tx.send(StreamEvent::ClientDisconnected {
+                client_id,
+                reason: DisconnectReason::Closed,
+                stats,
+                remaining_clients,
+            });
         }
 
         Ok(())
     }
 
+    /// Start the keepalive watchdog that tears down clients with lapsed heartbeats
+    fn start_heartbeat_watchdog(&mut self) {
+        let Some(timeout) = self.config.heartbeat_timeout else {
+            return;
+        };
+
+        let (watchdog, mut expired_rx) = spawn_heartbeat_watchdog(
+            self.heartbeats.clone(),
+            timeout,
+            self.shutdown_notify.clone(),
+        );
+        self.watchdog_handle = Some(watchdog);
+
+        let clients = self.clients.clone();
+        let controller = self.controller.clone();
+        let event_tx = self.event_tx.clone();
+
+        tokio::spawn(async move {
+            while let Some(client_id) = expired_rx.recv().await {
+                let removed = {
+                    let mut clients_guard = clients.write().await;
+                    clients_guard
+                        .remove(&client_id)
+                        .map(|client| (client, clients_guard.len()))
+                };
+                let Some((client, remaining_clients)) = removed else {
+                    continue;
+                };
+
+                info!(
+                    "Tearing down client {} after heartbeat timeout ({} remaining)",
+                    client_id, remaining_clients
+                );
+                Self::release_controller(&controller, &client_id).await;
+                let stats = Self::build_stats(&client).await;
+                let _ = client.peer_connection.close().await;
+                let _ = event_tx.send(StreamEvent::ClientDisconnected {
+                    client_id,
+                    reason: DisconnectReason::HeartbeatTimeout,
+                    stats,
+                    remaining_clients,
+                });
+            }
+        });
+    }
+
+    /// Start the task that records stats samples into the history
+    fn start_stats_sampler(&mut self) {
+        let Some(interval) = self.config.stats_sample_interval else {
+            return;
+        };
+
+        let clients = self.clients.clone();
+        let counters = self.stream_counters.clone();
+        let history = self.stats_history.clone();
+        let shutdown = self.shutdown_notify.clone();
+
+        self.sampler_handle = Some(tokio::spawn(async move {
+            let start = tokio::time::Instant::now();
+            let mut sampler = StatsSampler::new(start.into_std());
+            let mut ticker = tokio::time::interval_at(start + interval, interval);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    () = shutdown.notified() => break,
+                }
+                let rtt_ms = {
+                    let clients = clients.read().await;
+                    let mut worst: Option<ConnectionStats> = None;
+                    for client in clients.values() {
+                        let stats = Self::build_stats(client).await;
+                        worst = match worst {
+                            Some(worst) if !stats.is_worse_than(&worst) => Some(worst),
+                            _ => Some(stats),
+                        };
+                    }
+                    worst.map_or(0, |stats| stats.rtt_ms)
+                };
+                let sample = sampler.sample(&counters, rtt_ms, Instant::now());
+                if let Ok(mut history) = history.lock() {
+                    history.push(sample);
+                }
+            }
+            debug!("Stats sampler shut down");
+        }));
+    }
+
+    /// Add a new client and ask the frame source for a keyframe
+    ///
+    /// Clients already connected keep receiving frames and keep their stats.
+    fn join_client(
+        clients: &mut HashMap<String, ClientConnection>,
+        client: ClientConnection,
+        event_tx: &broadcast::Sender<StreamEvent>,
+    ) {
+        let client_id = client.id.clone();
+        clients.insert(client_id.clone(), client);
+        debug!("Requesting keyframe for new client {}", client_id);
+        // Nobody listening means nobody produces frames either
+        let _ = event_tx.send(StreamEvent::KeyframeRequested { client_id });
+    }
+
+    /// Subscribe to stream events (client joins and disconnects)
+    ///
+    /// Owners of the capture/encode pipeline should force a keyframe on
+    /// `KeyframeRequested` and stop the pipeline when a `ClientDisconnected`
+    /// event reports no remaining clients.
+    #[must_use]
+    pub fn subscribe_events(&self) -> broadcast::Receiver<StreamEvent> {
+        self.event_tx.subscribe()
+    }
+
+    /// Subscribe to touch input from the controller peer
+    ///
+    /// Input sent by any other peer is dropped before it reaches subscribers.
+    #[must_use]
+    pub fn subscribe_input(&self) -> broadcast::Receiver<TouchEvent> {
+        self.input_tx.subscribe()
+    }
+
+    /// Clear the controller if it was the given client
+    async fn release_controller(controller: &RwLock<Option<String>>, client_id: &str) {
+        let mut controller = controller.write().await;
+        if controller.as_deref() == Some(client_id) {
+            info!("Input controller {} left", client_id);
+            *controller = None;
+        }
+    }
+
     /// Send a signaling message over WebSocket
     async fn send_signaling_message<S>(sender: &mut S, msg: &SignalingMessage) -> Result<()>
     where
@@ -776,16 +1513,17 @@ impl StreamingServer {
         let clients = self.clients.clone();
         let frame_rx = self.frame_rx.clone();
         let shutdown = self.shutdown_notify.clone();
-        let counters = self.counters.clone();
         let ssrc = self.ssrc;
         let rtp_timestamp_increment = self.rtp_timestamp_increment;
+        let max_latency = self.config.max_frame_latency;
+        let stream_counters = self.stream_counters.clone();
 
         tokio::spawn(async move {
             let mut seq_num: u16 = 0;
             let mut timestamp: u32 = 0;
 
             loop {
-                let frame = tokio::select! {
+                let queued = tokio::select! {
                     f = async {
                         let mut rx = frame_rx.lock().await;
                         rx.recv().await
@@ -798,183 +1536,164 @@ impl StreamingServer {
                     () = shutdown.notified() => break,
                 };
 
+                // Packetize once and fan the same packets out to every peer
+                let packets = packetize_frame(
+                    &queued.frame,
+                    &mut seq_num,
+                    &mut timestamp,
+                    ssrc,
+                    rtp_timestamp_increment,
+                );
                 let clients_guard = clients.read().await;
-                for client in clients_guard.values() {
-                    if let Err(e) = Self::send_rtp_frame(
-                        &client.video_track,
-                        &frame,
-                        &mut seq_num,
-                        &mut timestamp,
-                        ssrc,
-                        rtp_timestamp_increment,
-                        &counters,
-                    )
-                    .await
-                    {
-                        warn!("Failed to send frame to client {}: {}", client.id, e);
-                    }
+                if !clients_guard.is_empty() {
+                    record_stream_frame(&stream_counters, &packets);
                 }
-                counters.frames_sent.fetch_add(1, Ordering::Relaxed);
+                Self::broadcast_packets(&clients_guard, &queued, &packets, max_latency).await;
             }
             debug!("Frame broadcaster shut down");
         });
     }
 
-    /// Send an encoded frame as RTP packets with RFC 6184 FU-A fragmentation
-    async fn send_rtp_frame(
-        track: &TrackLocalStaticRTP,
-        frame: &EncodedFrame,
-        seq_num: &mut u16,
-        timestamp: &mut u32,
-        ssrc: u32,
-        rtp_timestamp_increment: u32,
-        counters: &SharedCounters,
-    ) -> Result<()> {
-        let nals = split_nal_units(&frame.data);
-        let nal_count = nals.len();
-
-        for (nal_idx, nal) in nals.iter().enumerate() {
-            // Validate NAL unit has at least a header byte
-            if nal.is_empty() {
-                warn!("Skipping empty NAL unit");
+    /// Write one frame's RTP packets to every client that is keeping up
+    async fn broadcast_packets(
+        clients: &HashMap<String, ClientConnection>,
+        queued: &QueuedFrame,
+        packets: &[webrtc::rtp::packet::Packet],
+        max_latency: Option<Duration>,
+    ) {
+        for client in clients.values() {
+            // Earlier clients' writes count towards this client's wait
+            let admitted = client.congestion.lock().is_ok_and(|mut gate| {
+                gate.admit(
+                    queued.frame.is_keyframe,
+                    queued.queued_at.elapsed(),
+                    max_latency,
+                )
+            });
+            if !admitted {
+                client
+                    .counters
+                    .frames_congested
+                    .fetch_add(1, Ordering::Relaxed);
                 continue;
             }
 
-            let is_last_nal = nal_idx == nal_count - 1;
-
-            if nal.len() <= MAX_RTP_PAYLOAD_SIZE {
-                // Single NAL unit packet — fits in one RTP packet
-                let rtp_packet = webrtc::rtp::packet::Packet {
-                    header: webrtc::rtp::header::Header {
-                        version: 2,
-                        padding: false,
-                        extension: false,
-                        marker: is_last_nal,
-                        payload_type: 96,
-                        sequence_number: *seq_num,
-                        timestamp: *timestamp,
-                        ssrc,
-                        ..Default::default()
-                    },
-                    payload: nal.clone().into(),
-                };
-
-                if let Err(e) = track.write_rtp(&rtp_packet).await {
-                    warn!("Failed to write RTP packet: {e}");
-                    // Continue sending remaining NALs rather than aborting entire frame
-                    continue;
-                }
-
-                *seq_num = seq_num.wrapping_add(1);
-                counters.packets_sent.fetch_add(1, Ordering::Relaxed);
-            } else {
-                // FU-A fragmentation for large NAL units (RFC 6184 §5.8)
-                // Validate NAL has header + payload for fragmentation
-                if nal.len() < 2 {
-                    warn!("NAL unit too short for fragmentation: {} bytes", nal.len());
-                    continue;
-                }
-
-                let nal_header = nal[0];
-                let nri = nal_header & 0x60; // NRI bits
-                let nal_type = nal_header & 0x1F;
-                let nal_payload = &nal[1..]; // Skip NAL header byte
-
-                let fragments = fragment_nal_payload(nal_payload, MAX_RTP_PAYLOAD_SIZE - 2);
-                let frag_count = fragments.len();
-
-                for (frag_idx, frag) in fragments.iter().enumerate() {
-                    let is_start = frag_idx == 0;
-                    let is_end = frag_idx == frag_count - 1;
-                    let marker = is_last_nal && is_end;
-
-                    // FU indicator: F=0 | NRI | Type=28 (FU-A)
-                    let fu_indicator = nri | 28;
-                    // FU header: S | E | R=0 | Type
-                    let fu_header = if is_start {
-                        0x80 | nal_type // S=1, E=0
-                    } else if is_end {
-                        0x40 | nal_type // S=0, E=1
-                    } else {
-                        nal_type // S=0, E=0
-                    };
-
-                    let mut payload = Vec::with_capacity(2 + frag.len());
-                    payload.push(fu_indicator);
-                    payload.push(fu_header);
-                    payload.extend_from_slice(frag);
-
-                    let rtp_packet = webrtc::rtp::packet::Packet {
-                        header: webrtc::rtp::header::Header {
-                            version: 2,
-                            padding: false,
-                            extension: false,
-                            marker,
-                            payload_type: 96,
-                            sequence_number: *seq_num,
-                            timestamp: *timestamp,
-                            ssrc,
-                            ..Default::default()
-                        },
-                        payload: payload.into(),
-                    };
-
-                    if let Err(e) = track.write_rtp(&rtp_packet).await {
-                        warn!("Failed to write RTP FU-A fragment: {e}");
-                        // Continue sending remaining fragments rather than aborting entire frame
-                        continue;
-                    }
-
-                    *seq_num = seq_num.wrapping_add(1);
-                    counters.packets_sent.fetch_add(1, Ordering::Relaxed);
+            let mut written = 0u64;
+            for packet in packets {
+                match client.video_track.write_packet(packet).await {
+                    Ok(()) => written += 1,
+                    // Continue sending remaining packets rather than aborting the frame
+                    Err(e) => warn!("Failed to send frame to client {}: {}", client.id, e),
                 }
             }
+            client
+                .counters
+                .packets_sent
+                .fetch_add(written, Ordering::Relaxed);
+            client.counters.frames_sent.fetch_add(1, Ordering::Relaxed);
         }
-
-        // Advance timestamp by configured increment
-        *timestamp = timestamp.wrapping_add(rtp_timestamp_increment);
-
-        Ok(())
     }
 
     /// Send an encoded frame to all connected clients
     pub async fn send_frame(&self, frame: EncodedFrame) -> Result<()> {
         self.frame_tx
-            .send(frame)
+            .send(QueuedFrame {
+                frame,
+                queued_at: Instant::now(),
+            })
             .await
             .map_err(|e| DisplayStreamError::Streaming(format!("Failed to queue frame: {e}")))?;
         Ok(())
     }
 
-    /// Get connection statistics for all clients
+    /// Count frames dropped upstream of the encoder
+    ///
+    /// Called by the capture loop so that dropped frames show up in
+    /// [`ConnectionStats::frames_dropped`].
+    pub fn record_dropped_frames(&self, count: u64) {
+        self.frames_dropped.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Get connection statistics for the worst-performing client
+    ///
+    /// With several viewers this is the link bitrate adaptation should follow,
+    /// so that every viewer can keep up with the shared stream.
     pub async fn get_stats(&self) -> Option<ConnectionStats> {
+        self.get_all_stats()
+            .await
+            .into_values()
+            .reduce(|worst, stats| {
+                if stats.is_worse_than(&worst) {
+                    stats
+                } else {
+                    worst
+                }
+            })
+    }
+
+    /// Recent stats samples, oldest first
+    ///
+    /// Holds at most [`StreamConfig::stats_history_len`] samples, one per
+    /// [`StreamConfig::stats_sample_interval`] while the server runs.
+    #[must_use]
+    pub fn stats_history(&self) -> Vec<StatsSample> {
+        self.stats_history
+            .lock()
+            .map(|history| history.samples().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Get connection statistics for one client
+    pub async fn get_peer_stats(&self, client_id: &str) -> Option<ConnectionStats> {
         let clients = self.clients.read().await;
+        match clients.get(client_id) {
+            Some(client) => {
+                let mut stats = Self::build_stats(client).await;
+                stats.frames_dropped = self.frames_dropped.load(Ordering::Relaxed);
+                Some(stats)
+            }
+            None => None,
+        }
+    }
 
-        // Return stats for the first connected client
-        if let Some(client) = clients.values().next() {
-            let duration = client.connected_at.elapsed();
-
-            // Get peer connection stats
-            let pc_state = client.peer_connection.connection_state();
-            let ice_state = client.peer_connection.ice_connection_state();
-
-            // Read RTCP-derived stats
-            let client_stats = client.stats.read().await;
-            let packets_sent = self.counters.packets_sent.load(Ordering::Relaxed);
-            let frames_sent = self.counters.frames_sent.load(Ordering::Relaxed);
-
-            Some(ConnectionStats {
-                rtt_ms: 0, // RTT requires RTCP SR/RR round-trip — future enhancement
-                bitrate_bps: 0,
-                packets_sent,
-                packets_lost: u64::from(client_stats.cumulative_lost),
-                frames_sent,
-                duration_secs: duration.as_secs(),
-                ice_state: format!("{ice_state:?}"),
-                connection_state: format!("{pc_state:?}"),
-            })
-        } else {
-            None
+    /// Get connection statistics for every client, keyed by client ID
+    pub async fn get_all_stats(&self) -> HashMap<String, ConnectionStats> {
+        let clients = self.clients.read().await;
+        let mut all = HashMap::with_capacity(clients.len());
+        let frames_dropped = self.frames_dropped.load(Ordering::Relaxed);
+        for (id, client) in &*clients {
+            let mut stats = Self::build_stats(client).await;
+            stats.frames_dropped = frames_dropped;
+            all.insert(id.clone(), stats);
+        }
+        all
+    }
+
+    /// Snapshot connection statistics for a client
+    async fn build_stats(client: &ClientConnection) -> ConnectionStats {
+        let duration = client.connected_at.elapsed();
+
+        // Get peer connection stats
+        let pc_state = client.peer_connection.connection_state();
+        let ice_state = client.peer_connection.ice_connection_state();
+
+        // Read RTCP-derived stats
+        let client_stats = client.stats.read().await;
+        let packets_sent = client.counters.packets_sent.load(Ordering::Relaxed);
+        let frames_sent = client.counters.frames_sent.load(Ordering::Relaxed);
+        let frames_congested = client.counters.frames_congested.load(Ordering::Relaxed);
+
+        ConnectionStats {
+            rtt_ms: 0, // RTT requires RTCP SR/RR round-trip — future enhancement
+            bitrate_bps: 0,
+            packets_sent,
+            packets_lost: u64::from(client_stats.cumulative_lost),
+            frames_sent,
+            frames_dropped: 0,
+            frames_congested,
+            duration_secs: duration.as_secs(),
+            ice_state: format!("{ice_state:?}"),
+            connection_state: format!("{pc_state:?}"),
         }
     }
 
@@ -983,6 +1702,33 @@ impl StreamingServer {
         self.clients.read().await.len()
     }
 
+    /// Get the number of clients that have received at least one frame
+    pub async fn viewer_count(&self) -> usize {
+        self.clients
+            .read()
+            .await
+            .values()
+            .filter(|client| client.counters.frames_sent.load(Ordering::Relaxed) > 0)
+            .count()
+    }
+
+    /// Get the ID of the client whose input is accepted
+    pub async fn controller(&self) -> Option<String> {
+        self.controller.read().await.clone()
+    }
+
+    /// Designate the client whose input is accepted
+    pub async fn set_controller(&self, client_id: &str) -> Result<()> {
+        if !self.clients.read().await.contains_key(client_id) {
+            return Err(DisplayStreamError::Streaming(format!(
+                "Unknown client: {client_id}"
+            )));
+        }
+        info!("Client {} is now the input controller", client_id);
+        *self.controller.write().await = Some(client_id.to_string());
+        Ok(())
+    }
+
     /// Check if the server is running
     pub async fn is_running(&self) -> bool {
         self.running.load(Ordering::SeqCst)
@@ -1068,9 +1814,121 @@ fn fragment_nal_payload(payload: &[u8], max_fragment_size: usize) -> Vec<&[u8]>
     payload.chunks(max_fragment_size).collect()
 }
 
+/// Packetize an encoded frame as RTP packets with RFC 6184 FU-A fragmentation
+///
+/// Advances `seq_num` per packet and `timestamp` by one frame interval.
+fn packetize_frame(
+    frame: &EncodedFrame,
+    seq_num: &mut u16,
+    timestamp: &mut u32,
+    ssrc: u32,
+    rtp_timestamp_increment: u32,
+) -> Vec<webrtc::rtp::packet::Packet> {
+    let nals = split_nal_units(&frame.data);
+    let nal_count = nals.len();
+    let mut packets = Vec::new();
+
+    let mut push = |marker: bool, payload: Vec<u8>, seq_num: &mut u16| {
+        packets.push(webrtc::rtp::packet::Packet {
+            header: webrtc::rtp::header::Header {
+                version: 2,
+                padding: false,
+                extension: false,
+                marker,
+                payload_type: 96,
+                sequence_number: *seq_num,
+                timestamp: *timestamp,
+                ssrc,
+                ..Default::default()
+            },
+            payload: payload.into(),
+        });
+        *seq_num = seq_num.wrapping_add(1);
+    };
+
+    for (nal_idx, nal) in nals.iter().enumerate() {
+        // Validate NAL unit has at least a header byte
+        if nal.is_empty() {
+            warn!("Skipping empty NAL unit");
+            continue;
+        }
+
+        let is_last_nal = nal_idx == nal_count - 1;
+
+        if nal.len() <= MAX_RTP_PAYLOAD_SIZE {
+            // Single NAL unit packet — fits in one RTP packet
+            push(is_last_nal, nal.clone(), seq_num);
+        } else {
+            // FU-A fragmentation for large NAL units (RFC 6184 §5.8)
+            let nal_header = nal[0];
+            let nri = nal_header & 0x60; // NRI bits
+            let nal_type = nal_header & 0x1F;
+            let nal_payload = &nal[1..]; // Skip NAL header byte
+
+            let fragments = fragment_nal_payload(nal_payload, MAX_RTP_PAYLOAD_SIZE - 2);
+            let frag_count = fragments.len();
+
+            for (frag_idx, frag) in fragments.iter().enumerate() {
+                let is_start = frag_idx == 0;
+                let is_end = frag_idx == frag_count - 1;
+
+                // FU indicator: F=0 | NRI | Type=28 (FU-A)
+                let fu_indicator = nri | 28;
+                // FU header: S | E | R=0 | Type
+                let fu_header = if is_start {
+                    0x80 | nal_type // S=1, E=0
+                } else if is_end {
+                    0x40 | nal_type // S=0, E=1
+                } else {
+                    nal_type // S=0, E=0
+                };
+
+                let mut payload = Vec::with_capacity(2 + frag.len());
+                payload.push(fu_indicator);
+                payload.push(fu_header);
+                payload.extend_from_slice(frag);
+
+                push(is_last_nal && is_end, payload, seq_num);
+            }
+        }
+    }
+
+    // Advance timestamp by configured increment
+    *timestamp = timestamp.wrapping_add(rtp_timestamp_increment);
+
+    packets
+}
+
+/// Count one frame of the shared stream
+fn record_stream_frame(counters: &SharedCounters, packets: &[webrtc::rtp::packet::Packet]) {
+    let bytes: usize = packets.iter().map(|packet| packet.payload.len()).sum();
+    counters.frames_sent.fetch_add(1, Ordering::Relaxed);
+    counters
+        .packets_sent
+        .fetch_add(packets.len() as u64, Ordering::Relaxed);
+    counters
+        .bytes_sent
+        .fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+/// Parse a data-channel message as touch input if it came from the controller
+fn accept_controller_input(
+    controller: Option<&str>,
+    client_id: &str,
+    data: &[u8],
+) -> Option<TouchEvent> {
+    let event: TouchEvent = serde_json::from_slice(data).ok()?;
+    if controller != Some(client_id) {
+        debug!("Ignoring input from non-controller client {}", client_id);
+        return None;
+    }
+    Some(event)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::input::TouchAction;
 
     #[test]
     fn test_stream_config_default() {
@@ -1081,6 +1939,13 @@ mod tests {
         assert_eq!(config.max_clients, 1);
         assert!(config.enable_encryption);
         assert_eq!(config.framerate, 60);
+        assert_eq!(config.heartbeat_timeout, Some(DEFAULT_HEARTBEAT_TIMEOUT));
+        assert_eq!(config.max_frame_latency, Some(DEFAULT_MAX_FRAME_LATENCY));
+        assert_eq!(config.stats_history_len, DEFAULT_STATS_HISTORY_LEN);
+        assert_eq!(
+            config.stats_sample_interval,
+            Some(DEFAULT_STATS_SAMPLE_INTERVAL)
+        );
     }
 
     #[test]
@@ -1256,4 +2121,412 @@ mod tests {
         assert_eq!(stats.highest_seq, 0);
         assert_eq!(stats.jitter, 0);
     }
+
+    /// Simulated capture/encode loop that stops when the watchdog reports a lost client
+    fn spawn_pipeline(
+        monitor: &HeartbeatMonitor,
+        timeout: Duration,
+    ) -> (Arc<AtomicBool>, Arc<Notify>) {
+        let running = Arc::new(AtomicBool::new(true));
+        let shutdown = Arc::new(Notify::new());
+        let (_watchdog, mut expired_rx) =
+            spawn_heartbeat_watchdog(monitor.clone(), timeout, shutdown.clone());
+
+        let running_task = running.clone();
+        tokio::spawn(async move {
+            if expired_rx.recv().await.is_some() {
+                running_task.store(false, Ordering::SeqCst);
+            }
+        });
+
+        (running, shutdown)
+    }
+
+    #[tokio::test]
+    async fn test_missed_heartbeats_stop_pipeline() {
+        let monitor = HeartbeatMonitor::new();
+        monitor.register("tablet");
+        let timeout = Duration::from_millis(100);
+        let (running, shutdown) = spawn_pipeline(&monitor, timeout);
+
+        tokio::time::sleep(timeout * 3).await;
+
+        assert!(!running.load(Ordering::SeqCst));
+        assert!(monitor.take_expired(Duration::ZERO).is_empty());
+        shutdown.notify_waiters();
+    }
+
+    #[tokio::test]
+    async fn test_live_client_keeps_pipeline_running() {
+        let monitor = HeartbeatMonitor::new();
+        monitor.register("tablet");
+        let timeout = Duration::from_millis(150);
+        let (running, shutdown) = spawn_pipeline(&monitor, timeout);
+
+        for _ in 0..10 {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            monitor.beat("tablet");
+        }
+
+        assert!(running.load(Ordering::SeqCst));
+        shutdown.notify_waiters();
+    }
+
+    #[test]
+    fn test_heartbeat_monitor_remove() {
+        let monitor = HeartbeatMonitor::new();
+        monitor.register("tablet");
+        monitor.remove("tablet");
+        assert!(monitor.take_expired(Duration::ZERO).is_empty());
+    }
+
+    #[test]
+    fn test_stream_config_heartbeat_builder() {
+        let config = StreamConfig::new().with_heartbeat_timeout(None);
+        assert!(config.heartbeat_timeout.is_none());
+    }
+
+    #[test]
+    fn test_stream_config_serde_round_trip() {
+        let config = StreamConfig::new().with_transport(TransportMode::Usb);
+        let json = serde_json::to_string(&config).unwrap();
+        let parsed: StreamConfig = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.transport, TransportMode::Usb);
+        assert_eq!(parsed.heartbeat_timeout, Some(DEFAULT_HEARTBEAT_TIMEOUT));
+        assert_eq!(parsed.max_frame_latency, Some(DEFAULT_MAX_FRAME_LATENCY));
+
+        let partial: StreamConfig =
+            serde_json::from_str(r#"{ "framerate": 30, "heartbeat_timeout": null }"#).unwrap();
+        assert_eq!(partial.framerate, 30);
+        assert_eq!(partial.signaling_port, 8080);
+        assert!(partial.heartbeat_timeout.is_none());
+    }
+
+    /// Sink that records every RTP payload written to it
+    #[derive(Debug, Default)]
+    struct RecordingSink {
+        payloads: std::sync::Mutex<Vec<Vec<u8>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl RtpSink for RecordingSink {
+        async fn write_packet(&self, packet: &webrtc::rtp::packet::Packet) -> Result<()> {
+            self.payloads.lock().unwrap().push(packet.payload.to_vec());
+            Ok(())
+        }
+    }
+
+    async fn add_peer(server: &StreamingServer, id: &str) -> Arc<RecordingSink> {
+        let sink = Arc::new(RecordingSink::default());
+        add_peer_with_sink(server, id, sink.clone()).await;
+        sink
+    }
+
+    async fn add_peer_with_sink(server: &StreamingServer, id: &str, sink: Arc<dyn RtpSink>) {
+        let peer_connection = Arc::new(
+            server
+                .api
+                .new_peer_connection(RTCConfiguration::default())
+                .await
+                .unwrap(),
+        );
+        StreamingServer::join_client(
+            &mut *server.clients.write().await,
+            ClientConnection::new(
+                id.to_string(),
+                peer_connection,
+                sink,
+                Arc::new(RwLock::new(ClientStats::default())),
+            ),
+            &server.event_tx,
+        );
+    }
+
+    fn test_frame(nal_len: usize) -> EncodedFrame {
+        let mut data = NAL_START_CODE.to_vec();
+        data.push(0x65);
+        data.resize(data.len() + nal_len, 0xAB);
+        EncodedFrame {
+            data,
+            pts: 0,
+            duration: 16_666,
+            is_keyframe: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_two_peers_receive_same_frames() {
+        let server = StreamingServer::new(StreamConfig::new().with_max_clients(2)).unwrap();
+        let first = add_peer(&server, "tablet-1").await;
+        let second = add_peer(&server, "tablet-2").await;
+        assert_eq!(server.viewer_count().await, 0);
+
+        server.start_frame_broadcaster();
+        server.send_frame(test_frame(3000)).await.unwrap();
+        server.send_frame(test_frame(100)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let first_payloads = first.payloads.lock().unwrap().clone();
+        let second_payloads = second.payloads.lock().unwrap().clone();
+        // 3 FU-A fragments for the large NAL, one packet for the small one
+        assert_eq!(first_payloads.len(), 4);
+        assert_eq!(first_payloads, second_payloads);
+
+        assert_eq!(server.client_count().await, 2);
+        assert_eq!(server.viewer_count().await, 2);
+        let all = server.get_all_stats().await;
+        assert_eq!(all.len(), 2);
+        for stats in all.values() {
+            assert_eq!(stats.frames_sent, 2);
+            assert_eq!(stats.packets_sent, 4);
+        }
+        server.shutdown_notify.notify_waiters();
+    }
+
+    #[tokio::test]
+    async fn test_new_viewer_gets_keyframe_and_fresh_stats() {
+        let server = StreamingServer::new(StreamConfig::new().with_max_clients(2)).unwrap();
+        let first = add_peer(&server, "tablet-1").await;
+        server.start_frame_broadcaster();
+        server.send_frame(test_frame(100)).await.unwrap();
+        server.send_frame(test_frame(100)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let mut events = server.subscribe_events();
+        let second = add_peer(&server, "tablet-2").await;
+        match events.try_recv() {
+            Ok(StreamEvent::KeyframeRequested { client_id }) => assert_eq!(client_id, "tablet-2"),
+            other => panic!("expected a keyframe request, got {other:?}"),
+        }
+        assert!(events.try_recv().is_err());
+
+        let joined = server.get_peer_stats("tablet-2").await.unwrap();
+        assert_eq!(joined.frames_sent, 0);
+        assert_eq!(joined.packets_sent, 0);
+
+        server.send_frame(test_frame(100)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(first.payloads.lock().unwrap().len(), 3);
+        assert_eq!(second.payloads.lock().unwrap().len(), 1);
+        let stats = server.get_all_stats().await;
+        assert_eq!(stats["tablet-1"].frames_sent, 3);
+        assert_eq!(stats["tablet-2"].frames_sent, 1);
+        server.shutdown_notify.notify_waiters();
+    }
+
+    #[test]
+    fn test_worst_link_ranking() {
+        let good = ConnectionStats {
+            packets_sent: 1000,
+            packets_lost: 5,
+            rtt_ms: 40,
+            ..Default::default()
+        };
+        let lossy = ConnectionStats {
+            packets_sent: 1000,
+            packets_lost: 50,
+            rtt_ms: 10,
+            ..Default::default()
+        };
+        let slow = ConnectionStats {
+            rtt_ms: 80,
+            ..good.clone()
+        };
+
+        assert!(lossy.is_worse_than(&good));
+        assert!(!good.is_worse_than(&lossy));
+        assert!(slow.is_worse_than(&good));
+        assert!((ConnectionStats::default().loss_ratio()).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn test_input_only_from_controller() {
+        let server = StreamingServer::new(StreamConfig::new().with_max_clients(2)).unwrap();
+        add_peer(&server, "teacher").await;
+        add_peer(&server, "student").await;
+        assert!(server.set_controller("nobody").await.is_err());
+        server.set_controller("teacher").await.unwrap();
+
+        let touch = serde_json::to_vec(&TouchEvent::new(0.5, 0.5, TouchAction::Down, 0)).unwrap();
+        let controller = server.controller().await;
+        assert!(accept_controller_input(controller.as_deref(), "teacher", &touch).is_some());
+        assert!(accept_controller_input(controller.as_deref(), "student", &touch).is_none());
+        assert!(accept_controller_input(controller.as_deref(), "teacher", b"ping").is_none());
+
+        StreamingServer::release_controller(&server.controller, "teacher").await;
+        assert!(server.controller().await.is_none());
+    }
+
+    /// Sink whose writes take `delay` per packet, like a saturated link
+    #[derive(Debug)]
+    struct CongestedSink {
+        delay: Duration,
+        start: Instant,
+        /// Frame index (first payload byte after the NAL header) and write time
+        delivered: std::sync::Mutex<Vec<(u8, Duration)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl RtpSink for CongestedSink {
+        async fn write_packet(&self, packet: &webrtc::rtp::packet::Packet) -> Result<()> {
+            tokio::time::sleep(self.delay).await;
+            self.delivered
+                .lock()
+                .unwrap()
+                .push((packet.payload[1], self.start.elapsed()));
+            Ok(())
+        }
+    }
+
+    fn numbered_frame(index: u8, is_keyframe: bool) -> EncodedFrame {
+        let mut data = NAL_START_CODE.to_vec();
+        data.push(if is_keyframe { 0x65 } else { 0x41 });
+        data.extend_from_slice(&[index; 32]);
+        EncodedFrame {
+            data,
+            pts: i64::from(index) * 16_666,
+            duration: 16_666,
+            is_keyframe,
+        }
+    }
+
+    #[test]
+    fn test_congestion_gate_waits_for_keyframe() {
+        let budget = Some(Duration::from_millis(50));
+        let fresh = Duration::from_millis(5);
+        let late = Duration::from_millis(80);
+        let mut gate = CongestionGate::default();
+
+        assert!(gate.admit(false, fresh, budget));
+        assert!(!gate.admit(false, late, budget));
+        // Fresh again, but the reference frame is gone
+        assert!(!gate.admit(false, fresh, budget));
+        // Keyframes go out however late, and restart the P-frames
+        assert!(gate.admit(true, late, budget));
+        assert!(gate.admit(false, fresh, budget));
+        // Without a budget nothing is dropped
+        assert!(gate.admit(false, late, None));
+    }
+
+    #[tokio::test]
+    async fn test_congested_link_drops_p_frames_keeps_keyframes() {
+        let server = StreamingServer::new(
+            StreamConfig::new().with_max_frame_latency(Some(Duration::from_millis(40))),
+        )
+        .unwrap();
+        let sink = Arc::new(CongestedSink {
+            delay: Duration::from_millis(20),
+            start: Instant::now(),
+            delivered: std::sync::Mutex::new(Vec::new()),
+        });
+        add_peer_with_sink(&server, "tablet", sink.clone()).await;
+
+        // 20 frames arrive at once: twice what the link could carry in 200ms
+        server.start_frame_broadcaster();
+        for index in 0..20u8 {
+            server
+                .send_frame(numbered_frame(index, index % 10 == 0))
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(400)).await;
+
+        let delivered = sink.delivered.lock().unwrap().clone();
+        let frames: Vec<u8> = delivered.iter().map(|(index, _)| *index).collect();
+        assert!(frames.contains(&0) && frames.contains(&10), "{frames:?}");
+        assert!(frames.len() < 20, "nothing was dropped: {frames:?}");
+        // After a gap the next frame sent is a keyframe, so decoding stays valid
+        for pair in frames.windows(2) {
+            assert!(pair[1] == pair[0] + 1 || pair[1] % 10 == 0, "{frames:?}");
+        }
+        // Sending all 20 would have taken 400ms
+        let last = delivered.last().unwrap().1;
+        assert!(last < Duration::from_millis(200), "last frame at {last:?}");
+
+        let stats = server.get_peer_stats("tablet").await.unwrap();
+        assert_eq!(stats.frames_sent, frames.len() as u64);
+        assert_eq!(stats.frames_congested, 20 - frames.len() as u64);
+        server.shutdown_notify.notify_waiters();
+    }
+
+    fn sample_at(at: Instant, bitrate_bps: u64) -> StatsSample {
+        StatsSample {
+            at,
+            bitrate_bps,
+            fps: 0.0,
+            rtt_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_stats_history_evicts_oldest() {
+        let start = Instant::now();
+        let mut history = StatsHistory::new(3);
+        for bitrate in 1..=5 {
+            history.push(sample_at(start, bitrate));
+        }
+
+        assert_eq!(history.len(), 3);
+        let bitrates: Vec<u64> = history.samples().map(|s| s.bitrate_bps).collect();
+        assert_eq!(bitrates, vec![3, 4, 5]);
+        assert_eq!(history.latest().map(|s| s.bitrate_bps), Some(5));
+        assert_eq!(StatsHistory::new(0).capacity(), 1);
+    }
+
+    #[test]
+    fn test_stats_sampler_rates_fed_counters() {
+        let start = Instant::now();
+        let counters = SharedCounters::default();
+        let mut sampler = StatsSampler::new(start);
+
+        // 30 frames of 12.5 KB in half a second: 60 fps at 6 Mbps
+        counters.frames_sent.store(30, Ordering::Relaxed);
+        counters.bytes_sent.store(375_000, Ordering::Relaxed);
+        let sample = sampler.sample(&counters, 25, start + Duration::from_millis(500));
+        assert_eq!(sample.bitrate_bps, 6_000_000);
+        assert!((sample.fps - 60.0).abs() < 1e-9);
+        assert_eq!(sample.rtt_ms, 25);
+
+        // Nothing sent since: the next sample drops to zero
+        let idle = sampler.sample(&counters, 25, start + Duration::from_secs(1));
+        assert_eq!(idle.bitrate_bps, 0);
+        assert!(idle.fps.abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn test_stats_history_follows_stream() {
+        let interval = Duration::from_millis(50);
+        let mut server = StreamingServer::new(
+            StreamConfig::new()
+                .with_stats_history_len(4)
+                .with_stats_sample_interval(Some(interval)),
+        )
+        .unwrap();
+        add_peer(&server, "tablet").await;
+        server.start_frame_broadcaster();
+        server.start_stats_sampler();
+
+        for _ in 0..5 {
+            server.send_frame(test_frame(3000)).await.unwrap();
+        }
+        tokio::time::sleep(interval * 3 + interval / 2).await;
+
+        // One sample per interval, none before the first one elapsed
+        let history = server.stats_history();
+        assert!((2..=4).contains(&history.len()), "{history:?}");
+        assert!(history[0].bitrate_bps > 0 && history[0].fps > 0.0);
+        assert!(history.iter().skip(1).all(|s| s.bitrate_bps == 0));
+        for pair in history.windows(2) {
+            assert!(pair[1].at - pair[0].at >= interval / 2);
+        }
+
+        // Past the bound the oldest samples make way
+        tokio::time::sleep(interval * 4).await;
+        let history = server.stats_history();
+        assert_eq!(history.len(), 4);
+        assert!(history.iter().all(|s| s.bitrate_bps == 0));
+        server.shutdown_notify.notify_waiters();
+    }
 }